@@ -157,6 +157,11 @@ pub enum Top {
     /// shell — including outside a kiri terminal — to debug why the CLI
     /// cannot find a window or to script an external-terminal handshake.
     Env,
+    /// Relay an MCP client's stdio to kiri's MCP server.
+    ///
+    /// Point an agent at it with `{"command": "kiri-cli", "args": ["mcp"]}`.
+    /// The server must be turned on in kiri's settings first.
+    Mcp,
 }
 
 #[derive(Subcommand, Debug)]
@@ -44,6 +44,13 @@ async fn run() -> Result<i32> {
         return Ok(0);
     }
 
+    // `mcp` talks to the app-wide MCP socket, not a window's.
+    if matches!(&args.command, Top::Mcp) {
+        let socket = mcp_socket().ok_or_else(|| anyhow!("no home directory"))?;
+        transport::relay_stdio(&socket).await?;
+        return Ok(0);
+    }
+
     // `signal wait --print-data` decodes the payload only on the success
     // path; capture it now so we can branch on it after the response
     // arrives.
@@ -60,6 +67,7 @@ async fn run() -> Result<i32> {
     // project-scoped resolution.
     let (req, socket) = match args.command {
         Top::Env => unreachable!("env is handled above"),
+        Top::Mcp => unreachable!("mcp is handled above"),
         Top::Window(w) => {
             let req = build_window_request(w);
             let socket = resolve_any_socket().await?;
@@ -93,6 +101,11 @@ async fn run() -> Result<i32> {
     Ok(if last_was_error { 1 } else { 0 })
 }
 
+/// `~/.kiri/mcp.sock`, served by kiri while its MCP server is on.
+fn mcp_socket() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("mcp.sock"))
+}
+
 /// Resolve the kiri socket to use, in priority order:
 ///
 /// 1. `$KIRI_SOCKET` — if set AND the socket file exists AND a connection
@@ -813,6 +826,14 @@ mod tests {
         assert!(matches!(cli.command, Top::Env));
     }
 
+    #[test]
+    fn mcp_top_parses_with_no_args() {
+        // Agents launch `kiri mcp` from their own config, outside any
+        // kiri terminal.
+        let cli = Cli::try_parse_from(["kiri", "mcp"]).unwrap();
+        assert!(matches!(cli.command, Top::Mcp));
+    }
+
     #[test]
     fn env_top_supports_pretty_flag() {
         // The global --pretty flag must apply to `env` the same way it
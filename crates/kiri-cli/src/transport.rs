@@ -58,3 +58,35 @@ pub async fn send(socket: &Path, req: &Request) -> Result<Vec<Response>> {
     }
     Ok(responses)
 }
+
+/// Copy stdin to the socket and the socket to stdout until either side
+/// closes. MCP messages are newline-delimited, so bytes pass through as-is.
+pub async fn relay_stdio(socket: &Path) -> Result<()> {
+    use interprocess::local_socket::traits::tokio::Stream as _;
+    let name = socket
+        .as_os_str()
+        .to_fs_name::<GenericFilePath>()
+        .context("invalid socket path")?;
+    let conn = interprocess::local_socket::tokio::Stream::connect(name)
+        .await
+        .with_context(|| {
+            format!(
+                "connect to kiri MCP server at {} (is it turned on in kiri's settings?)",
+                socket.display()
+            )
+        })?;
+    let (mut reader, mut writer) = conn.split();
+    let upstream = async {
+        tokio::io::copy(&mut tokio::io::stdin(), &mut writer).await?;
+        writer.shutdown().await
+    };
+    let downstream = async {
+        tokio::io::copy(&mut reader, &mut tokio::io::stdout()).await?;
+        tokio::io::stdout().flush().await
+    };
+    tokio::select! {
+        result = upstream => result.context("relay stdin to kiri")?,
+        result = downstream => result.context("relay kiri to stdout")?,
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! Criterion benchmarks for the commands on the hot path of every session:
+//! directory listing, git status and diffs, and content search.
+//!
+//! ```sh
+//! npm run bench:rust
+//! KIRI_BENCH_SCALE=0.1 npm run bench:rust   # smaller workspaces
+//! ```
+//!
+//! Workspaces are generated once per group; see support/mod.rs. The git
+//! commands are benchmarked without a watcher, so the git cache never
+//! serves them and every iteration does the full walk.
+
+mod support;
+
+use app_lib::commands::fs::{invalidate_directory_cache, read_directory};
+use app_lib::commands::git::{get_all_git_diffs, get_git_diff, get_git_status};
+use app_lib::commands::search::search_content;
+use criterion::{criterion_group, criterion_main, Criterion};
+use support::{path_string, scaled, LARGE_DIFF_FILE, NEEDLE};
+use tokio::runtime::Runtime;
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("runtime")
+}
+
+fn bench_read_directory(c: &mut Criterion) {
+    let rt = runtime();
+    let dir = support::wide_directory(scaled(10_000));
+    let path = path_string(dir.path());
+
+    let mut group = c.benchmark_group("read_directory");
+    group.bench_function("cold", |b| {
+        b.to_async(&rt).iter(|| {
+            invalidate_directory_cache(None);
+            read_directory(path.clone(), None)
+        })
+    });
+    group.bench_function("cold_fast", |b| {
+        b.to_async(&rt).iter(|| {
+            invalidate_directory_cache(None);
+            read_directory(path.clone(), Some(true))
+        })
+    });
+    group.bench_function("cached", |b| {
+        b.to_async(&rt).iter(|| read_directory(path.clone(), None))
+    });
+    group.finish();
+}
+
+fn bench_git_status(c: &mut Criterion) {
+    let rt = runtime();
+    let repo = support::monorepo(scaled(100_000));
+    let path = path_string(repo.path());
+
+    let mut group = c.benchmark_group("git_status");
+    group.sample_size(10);
+    group.bench_function("monorepo", |b| {
+        b.to_async(&rt).iter(|| get_git_status(path.clone()))
+    });
+    group.finish();
+}
+
+fn bench_git_diff(c: &mut Criterion) {
+    let rt = runtime();
+    let repo = support::large_diff_repo(scaled(10_000));
+    let path = path_string(repo.path());
+
+    let mut group = c.benchmark_group("git_diff");
+    group.bench_function("single_file", |b| {
+        b.to_async(&rt)
+            .iter(|| get_git_diff(path.clone(), LARGE_DIFF_FILE.to_string()))
+    });
+    group.bench_function("all_files", |b| {
+        b.to_async(&rt).iter(|| get_all_git_diffs(path.clone()))
+    });
+    group.finish();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let rt = runtime();
+    let repo = support::monorepo(scaled(100_000));
+    let path = path_string(repo.path());
+
+    let mut group = c.benchmark_group("search_content");
+    group.sample_size(10);
+    group.bench_function("monorepo", |b| {
+        b.to_async(&rt)
+            .iter(|| search_content(path.clone(), NEEDLE.to_string(), 10_000, Vec::new(), None))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_read_directory,
+    bench_git_status,
+    bench_git_diff,
+    bench_search
+);
+criterion_main!(benches);
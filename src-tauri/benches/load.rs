@@ -0,0 +1,188 @@
+//! Command-level load harness.
+//!
+//! Several simulated windows call the hot commands at the same time, as a
+//! session with a few projects open does on focus or after a checkout.
+//! Every call's latency is recorded under that contention, and the run
+//! fails when a command's p95 exceeds its budget. A regression therefore
+//! fails CI instead of reaching a release.
+//!
+//! The monorepo here is a fifth of the one in hot_paths.rs, so a run takes
+//! about a minute. Budgets are roughly 2.5x the p95 measured on a
+//! single-core Linux VM, which leaves headroom on CI runners.
+//!
+//! ```sh
+//! npm run perf:load
+//! KIRI_BENCH_SCALE=0.2 npm run perf:load   # smaller workspaces
+//! ```
+
+mod support;
+
+use app_lib::commands::error::KiriError;
+use app_lib::commands::fs::{invalidate_directory_cache, read_directory};
+use app_lib::commands::git::{get_all_git_diffs, get_git_diff, get_git_status};
+use app_lib::commands::search::search_content;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use support::{path_string, scaled, LARGE_DIFF_FILE, NEEDLE};
+use tempfile::TempDir;
+
+/// Concurrent callers, i.e. simulated windows.
+const CALLERS: usize = 8;
+
+/// Times each caller runs through every command.
+const ROUNDS: usize = 4;
+
+const MONOREPO_FILES: usize = 20_000;
+const WIDE_DIRECTORY_ENTRIES: usize = 10_000;
+const DIFF_LINES: usize = 10_000;
+
+/// p95 budget per command at full scale, under [`CALLERS`]-way contention.
+const BUDGETS: &[(&str, Duration)] = &[
+    ("read_directory", Duration::from_millis(600)),
+    ("get_git_status", Duration::from_secs(8)),
+    ("get_git_diff", Duration::from_millis(250)),
+    ("get_all_git_diffs", Duration::from_secs(15)),
+    ("search_content", Duration::from_secs(8)),
+];
+
+struct Workspaces {
+    wide: TempDir,
+    monorepo: TempDir,
+    large_diff: TempDir,
+}
+
+#[derive(Default)]
+struct Samples {
+    durations: Vec<Duration>,
+    errors: Vec<String>,
+}
+
+impl Samples {
+    fn percentile(&self, p: f64) -> Duration {
+        let mut sorted = self.durations.clone();
+        sorted.sort();
+        let rank = ((sorted.len() as f64 * p).ceil() as usize).saturating_sub(1);
+        sorted.get(rank).copied().unwrap_or_default()
+    }
+}
+
+async fn call(command: &str, ws: &Workspaces) -> Result<(), KiriError> {
+    match command {
+        "read_directory" => {
+            invalidate_directory_cache(None);
+            read_directory(path_string(ws.wide.path()), None)
+                .await
+                .map(drop)
+        }
+        "get_git_status" => get_git_status(path_string(ws.monorepo.path()))
+            .await
+            .map(drop),
+        "get_git_diff" => get_git_diff(
+            path_string(ws.large_diff.path()),
+            LARGE_DIFF_FILE.to_string(),
+        )
+        .await
+        .map(drop),
+        "get_all_git_diffs" => get_all_git_diffs(path_string(ws.monorepo.path()))
+            .await
+            .map(drop),
+        "search_content" => search_content(
+            path_string(ws.monorepo.path()),
+            NEEDLE.to_string(),
+            10_000,
+            Vec::new(),
+            None,
+        )
+        .await
+        .map(drop),
+        other => Err(format!("no load scenario for {}", other).into()),
+    }
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; there are no other options.
+    let started = Instant::now();
+    let ws = Arc::new(Workspaces {
+        wide: support::wide_directory(scaled(WIDE_DIRECTORY_ENTRIES)),
+        monorepo: support::monorepo(scaled(MONOREPO_FILES)),
+        large_diff: support::large_diff_repo(scaled(DIFF_LINES)),
+    });
+    println!("workspaces generated in {:?}", started.elapsed());
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("runtime");
+    let samples: Arc<Mutex<BTreeMap<&'static str, Samples>>> = Arc::default();
+
+    let started = Instant::now();
+    rt.block_on(async {
+        let mut callers = Vec::new();
+        for caller in 0..CALLERS {
+            let ws = Arc::clone(&ws);
+            let samples = Arc::clone(&samples);
+            callers.push(tokio::spawn(async move {
+                for round in 0..ROUNDS {
+                    // Stagger the order so callers contend on different
+                    // commands at any moment.
+                    for i in 0..BUDGETS.len() {
+                        let (command, _) = BUDGETS[(caller + round + i) % BUDGETS.len()];
+                        let start = Instant::now();
+                        let result = call(command, &ws).await;
+                        let elapsed = start.elapsed();
+                        let mut samples = samples.lock().unwrap();
+                        let entry = samples.entry(command).or_default();
+                        entry.durations.push(elapsed);
+                        if let Err(e) = result {
+                            entry.errors.push(e.message);
+                        }
+                    }
+                }
+            }));
+        }
+        for caller in callers {
+            caller.await.expect("caller task");
+        }
+    });
+    println!(
+        "{} callers x {} rounds finished in {:?}\n",
+        CALLERS,
+        ROUNDS,
+        started.elapsed()
+    );
+
+    let samples = samples.lock().unwrap();
+    let mut failed = false;
+    println!(
+        "{:<20} {:>6} {:>7} {:>10} {:>10} {:>10} {:>10}",
+        "command", "calls", "errors", "p50", "p95", "max", "budget"
+    );
+    for (command, budget) in BUDGETS {
+        let Some(s) = samples.get(command) else {
+            continue;
+        };
+        let p95 = s.percentile(0.95);
+        let over = p95 > *budget || !s.errors.is_empty();
+        failed |= over;
+        println!(
+            "{:<20} {:>6} {:>7} {:>10.1?} {:>10.1?} {:>10.1?} {:>10.1?}{}",
+            command,
+            s.durations.len(),
+            s.errors.len(),
+            s.percentile(0.5),
+            p95,
+            s.percentile(1.0),
+            budget,
+            if over { "  FAIL" } else { "" }
+        );
+        for error in s.errors.iter().take(3) {
+            println!("    error: {}", error);
+        }
+    }
+
+    if failed {
+        eprintln!("\nload harness: budget exceeded or commands failed");
+        std::process::exit(1);
+    }
+}
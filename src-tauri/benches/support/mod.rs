@@ -0,0 +1,130 @@
+//! Synthetic workspaces shared by the benches.
+//!
+//! Sizes follow the worst cases users report: a monorepo with around 100k
+//! tracked files, a single directory with thousands of entries, and a
+//! 10k-line diff. `KIRI_BENCH_SCALE` shrinks every workspace
+//! proportionally (e.g. `0.1` for a quick local or CI run).
+
+#![allow(dead_code)]
+
+use git2::{IndexAddOption, Repository, Signature};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Files per directory in [`monorepo`].
+const FILES_PER_DIR: usize = 100;
+
+/// Word that [`monorepo`] plants in one file in fifty, for content search.
+pub const NEEDLE: &str = "kiri_bench_needle";
+
+/// `count` scaled by `KIRI_BENCH_SCALE`, and at least 1.
+pub fn scaled(count: usize) -> usize {
+    let scale = std::env::var("KIRI_BENCH_SCALE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|s| *s > 0.0)
+        .unwrap_or(1.0);
+    ((count as f64 * scale) as usize).max(1)
+}
+
+pub fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn source_file(i: usize) -> String {
+    let mut body = format!("// file {}\npub fn f{}() -> usize {{\n", i, i);
+    for line in 0..20 {
+        body.push_str(&format!("    let v{} = {} * {};\n", line, i, line));
+    }
+    if i % 50 == 0 {
+        body.push_str(&format!("    // {}\n", NEEDLE));
+    }
+    body.push_str("    0\n}\n");
+    body
+}
+
+fn commit_all(root: &Path) -> Repository {
+    let repo = Repository::init(root).expect("init repo");
+    {
+        let mut index = repo.index().expect("index");
+        index
+            .add_all(["*"].iter(), IndexAddOption::DEFAULT, None)
+            .expect("add files");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = Signature::now("Bench", "bench@example.com").expect("signature");
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .expect("commit");
+    }
+    repo
+}
+
+/// One directory holding `entries` entries, a tenth of them directories.
+pub fn wide_directory(entries: usize) -> TempDir {
+    let dir = tempfile::tempdir().expect("tempdir");
+    for i in 0..entries {
+        if i % 10 == 0 {
+            fs::create_dir(dir.path().join(format!("dir_{:06}", i))).expect("create dir");
+        } else {
+            fs::write(dir.path().join(format!("file_{:06}.txt", i)), "x").expect("write");
+        }
+    }
+    dir
+}
+
+/// A committed repository of `files` source files, [`FILES_PER_DIR`] to a
+/// directory, with one file in a hundred modified and as many untracked
+/// files added.
+pub fn monorepo(files: usize) -> TempDir {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path();
+    for i in 0..files {
+        let sub = root
+            .join(format!("pkg_{:03}", i / (FILES_PER_DIR * 10)))
+            .join(format!("mod_{:03}", i / FILES_PER_DIR));
+        if i % FILES_PER_DIR == 0 {
+            fs::create_dir_all(&sub).expect("create dir");
+        }
+        fs::write(sub.join(format!("file_{:06}.rs", i)), source_file(i)).expect("write");
+    }
+    commit_all(root);
+
+    for i in (0..files).step_by(100) {
+        let sub = root
+            .join(format!("pkg_{:03}", i / (FILES_PER_DIR * 10)))
+            .join(format!("mod_{:03}", i / FILES_PER_DIR));
+        fs::write(sub.join(format!("file_{:06}.rs", i)), "// rewritten\n").expect("modify");
+        fs::write(sub.join(format!("untracked_{:06}.rs", i)), "// new\n").expect("untracked");
+    }
+    dir
+}
+
+/// Path of the file changed by [`large_diff_repo`], relative to its root.
+pub const LARGE_DIFF_FILE: &str = "big.rs";
+
+/// A repository whose only change rewrites every other line of a
+/// `lines`-line file.
+pub fn large_diff_repo(lines: usize) -> TempDir {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path();
+    let original: String = (0..lines)
+        .map(|i| format!("let line_{} = {};\n", i, i))
+        .collect();
+    fs::write(root.join(LARGE_DIFF_FILE), original).expect("write");
+    commit_all(root);
+
+    let changed: String = (0..lines)
+        .map(|i| {
+            if i % 2 == 0 {
+                format!("let line_{} = {} + 1;\n", i, i)
+            } else {
+                format!("let line_{} = {};\n", i, i)
+            }
+        })
+        .collect();
+    fs::write(root.join(LARGE_DIFF_FILE), changed).expect("rewrite");
+    dir
+}
@@ -0,0 +1,156 @@
+//! Accelerator strings (`CmdOrCtrl+Shift+P`) shared by the menu keymap
+//! and global shortcuts: parsing into one canonical spelling and
+//! platform-aware comparison.
+
+const MODIFIER_ORDER: [&str; 5] = ["CmdOrCtrl", "Super", "Ctrl", "Alt", "Shift"];
+
+/// Keys that are valid on their own besides letters, digits and F1–F24.
+const NAMED_KEYS: [&str; 22] = [
+    "Space",
+    "Enter",
+    "Tab",
+    "Escape",
+    "Backspace",
+    "Delete",
+    "Insert",
+    "Up",
+    "Down",
+    "Left",
+    "Right",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "Backquote",
+    "Minus",
+    "Equal",
+    "Comma",
+    "Period",
+    "Slash",
+    "Semicolon",
+];
+
+fn modifier_name(token: &str) -> Option<&'static str> {
+    match token.to_ascii_lowercase().as_str() {
+        "cmdorctrl" | "commandorcontrol" | "cmdorcontrol" | "commandorctrl" => Some("CmdOrCtrl"),
+        "cmd" | "command" | "super" | "meta" => Some("Super"),
+        "ctrl" | "control" => Some("Ctrl"),
+        "alt" | "option" => Some("Alt"),
+        "shift" => Some("Shift"),
+        _ => None,
+    }
+}
+
+fn key_name(token: &str) -> Option<String> {
+    if token.len() == 1 {
+        let c = token.chars().next()?;
+        return c
+            .is_ascii_alphanumeric()
+            .then(|| c.to_ascii_uppercase().to_string());
+    }
+    let upper = token.to_ascii_uppercase();
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&n).then(|| format!("F{}", n));
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|k| k.eq_ignore_ascii_case(token))
+        .map(|k| k.to_string())
+}
+
+/// Parse an accelerator such as `cmd+shift+p` into the canonical
+/// `Super+Shift+P` form: known modifier aliases, fixed modifier order and
+/// exactly one key. At least one modifier is required so a shortcut never
+/// swallows plain typing.
+pub fn normalize_accelerator(input: &str) -> Result<String, String> {
+    let tokens: Vec<&str> = input.split('+').map(str::trim).collect();
+    let (key, modifiers) = tokens
+        .split_last()
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("Invalid shortcut: {}", input))?;
+
+    let mut found = Vec::new();
+    for token in modifiers {
+        let name = modifier_name(token)
+            .ok_or_else(|| format!("Unknown modifier `{}` in {}", token, input))?;
+        if found.contains(&name) {
+            return Err(format!("Duplicate modifier `{}` in {}", name, input));
+        }
+        found.push(name);
+    }
+    if found.is_empty() {
+        return Err("A shortcut needs at least one modifier".to_string());
+    }
+    let key = key_name(key).ok_or_else(|| format!("Unknown key `{}` in {}", key, input))?;
+
+    let mut parts: Vec<String> = MODIFIER_ORDER
+        .iter()
+        .filter(|m| found.contains(m))
+        .map(|m| m.to_string())
+        .collect();
+    parts.push(key);
+    Ok(parts.join("+"))
+}
+
+/// Comparison key for a canonical accelerator: `CmdOrCtrl` is resolved for
+/// the current platform so `CmdOrCtrl+K` and `Super+K` compare equal on
+/// macOS.
+pub fn accelerator_key(canonical: &str) -> String {
+    let primary = if cfg!(target_os = "macos") {
+        "Super"
+    } else {
+        "Ctrl"
+    };
+    let mut tokens: Vec<&str> = canonical
+        .split('+')
+        .map(|t| if t == "CmdOrCtrl" { primary } else { t })
+        .collect();
+    let key = tokens.pop().unwrap_or_default();
+    tokens.sort_by_key(|t| MODIFIER_ORDER.iter().position(|m| m == t));
+    tokens.dedup();
+    tokens.push(key);
+    tokens.join("+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_accelerator() {
+        assert_eq!(
+            normalize_accelerator("shift+cmd+p").unwrap(),
+            "Super+Shift+P"
+        );
+        assert_eq!(
+            normalize_accelerator("CommandOrControl + Alt + f12").unwrap(),
+            "CmdOrCtrl+Alt+F12"
+        );
+        assert_eq!(
+            normalize_accelerator("ctrl+backquote").unwrap(),
+            "Ctrl+Backquote"
+        );
+    }
+
+    #[test]
+    fn test_normalize_rejects_invalid() {
+        assert!(normalize_accelerator("P").is_err());
+        assert!(normalize_accelerator("").is_err());
+        assert!(normalize_accelerator("Ctrl+").is_err());
+        assert!(normalize_accelerator("Ctrl+Ctrl+P").is_err());
+        assert!(normalize_accelerator("Hyper+P").is_err());
+        assert!(normalize_accelerator("Ctrl+F25").is_err());
+        assert!(normalize_accelerator("Ctrl+Shift").is_err());
+    }
+
+    #[test]
+    fn test_accelerator_key_resolves_cmd_or_ctrl() {
+        let native = if cfg!(target_os = "macos") {
+            "Super+Alt+K"
+        } else {
+            "Ctrl+Alt+K"
+        };
+        assert_eq!(accelerator_key("CmdOrCtrl+Alt+K"), accelerator_key(native));
+        assert_ne!(accelerator_key("CmdOrCtrl+K"), accelerator_key("Alt+K"));
+    }
+}
@@ -0,0 +1,471 @@
+//! Coding-agent CLI sessions (Claude Code, Codex) running in worktrees.
+//!
+//! Each session runs the agent's CLI on a PTY with a worktree as its cwd,
+//! so one task per worktree can be supervised from a single dashboard.
+//! [`AgentSessions`] tracks the sessions and whether each agent is working
+//! or waiting for the user: an agent that has gone quiet with an approval
+//! prompt or its input box on screen needs input. The agent's own
+//! transcript file is found once it writes one. The runner lives in
+//! agents_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::clock::now_ms;
+use super::fs_path::path_to_wire;
+use super::jobs::JobId;
+use super::tasks::RunHandle;
+use super::terminal::create_pty_size;
+
+/// Job kind of an agent session.
+pub const AGENT_JOB_KIND: &str = "agent";
+
+/// Finished sessions kept in the history.
+const MAX_FINISHED_SESSIONS: usize = 50;
+
+/// Output kept per session for redrawing it after a window reload.
+const OUTPUT_TAIL_BYTES: usize = 256 * 1024;
+
+/// Trailing output searched for an input prompt: roughly the last screen.
+const SCREEN_BYTES: usize = 4 * 1024;
+
+/// How long an agent must be quiet before it counts as waiting. Agents
+/// animate a spinner while they work, so silence means they stopped.
+pub const IDLE_AFTER: Duration = Duration::from_millis(1500);
+
+lazy_static::lazy_static! {
+    static ref ANSI: regex::Regex = regex::Regex::new(
+        r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]"
+    )
+    .expect("pattern compiles");
+}
+
+/// Payload of `agent-output`: a chunk of a session's output.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentOutput {
+    pub session_id: JobId,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentKind {
+    Claude,
+    Codex,
+}
+
+impl AgentKind {
+    pub const ALL: [AgentKind; 2] = [AgentKind::Claude, AgentKind::Codex];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AgentKind::Claude => "Claude Code",
+            AgentKind::Codex => "Codex",
+        }
+    }
+
+    pub fn program(self) -> &'static str {
+        match self {
+            AgentKind::Claude => "claude",
+            AgentKind::Codex => "codex",
+        }
+    }
+
+    /// Text on screen while the agent waits for the user: approval
+    /// prompts and the idle input box's hints.
+    fn input_markers(self) -> &'static [&'static str] {
+        match self {
+            AgentKind::Claude => &[
+                "Do you want to",
+                "❯ 1. Yes",
+                "? for shortcuts",
+                "Press Enter to continue",
+            ],
+            AgentKind::Codex => &[
+                "Allow command?",
+                "Would you like to",
+                "Yes, proceed",
+                "⏎ send",
+            ],
+        }
+    }
+
+    /// Argv starting the agent, with `prompt` as its first message. The
+    /// prompt follows `--` so one starting with `-` isn't read as a flag.
+    pub fn argv(self, prompt: Option<&str>) -> Vec<String> {
+        let mut argv = vec![self.program().to_string()];
+        if let Some(prompt) = prompt {
+            argv.extend(["--".to_string(), prompt.to_string()]);
+        }
+        argv
+    }
+}
+
+/// Whether `screen`, the agent's latest output, shows it waiting for input.
+pub fn shows_input_prompt(kind: AgentKind, screen: &str) -> bool {
+    let text = ANSI.replace_all(screen, "");
+    kind.input_markers()
+        .iter()
+        .any(|marker| text.contains(marker))
+}
+
+/// Claude Code keeps transcripts in `~/.claude/projects/<cwd>`, with every
+/// character of the cwd other than letters and digits replaced by `-`.
+fn claude_transcript_dir(home: &Path, cwd: &str) -> PathBuf {
+    let encoded: String = cwd
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    home.join(".claude").join("projects").join(encoded)
+}
+
+fn modified_since(path: &Path, since: SystemTime) -> Option<SystemTime> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    (modified >= since).then_some(modified)
+}
+
+/// `.jsonl` files under `dir`, `depth` directory levels deep at most.
+fn jsonl_files(dir: &Path, depth: usize, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                jsonl_files(&path, depth - 1, out);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "jsonl") {
+            out.push(path);
+        }
+    }
+}
+
+/// Whether a Codex rollout file was recorded in `cwd`; its first line is
+/// the session's metadata.
+fn codex_rollout_in(path: &Path, cwd: &str) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut first = String::new();
+    let _ = BufReader::new(file).read_line(&mut first);
+    let cwd = serde_json::to_string(cwd).unwrap_or_default();
+    first.contains(&format!("\"cwd\":{}", cwd))
+}
+
+/// The transcript an agent started in `cwd` at `since` is writing: the
+/// newest one modified since then.
+pub fn find_transcript(
+    kind: AgentKind,
+    home: &Path,
+    cwd: &str,
+    since: SystemTime,
+) -> Option<PathBuf> {
+    let mut files = Vec::new();
+    match kind {
+        AgentKind::Claude => jsonl_files(&claude_transcript_dir(home, cwd), 0, &mut files),
+        // Rollouts are filed by date: sessions/YYYY/MM/DD.
+        AgentKind::Codex => jsonl_files(&home.join(".codex").join("sessions"), 3, &mut files),
+    }
+    files
+        .into_iter()
+        .filter_map(|path| modified_since(&path, since).map(|modified| (modified, path)))
+        .filter(|(_, path)| kind != AgentKind::Codex || codex_rollout_in(path, cwd))
+        .max()
+        .map(|(_, path)| path)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum AgentStatus {
+    /// The agent is working.
+    Running,
+    /// The agent is waiting for the user to answer or give it a task.
+    Waiting,
+    Done {
+        code: u32,
+    },
+    Stopped,
+    /// The agent could not be started or waited on.
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct AgentSession {
+    /// Id of the session's job.
+    pub id: JobId,
+    pub kind: AgentKind,
+    pub worktree: String,
+    pub prompt: Option<String>,
+    pub status: AgentStatus,
+    /// The agent's transcript file, once it has written one.
+    pub transcript: Option<String>,
+    pub started_ms: u64,
+    pub finished_ms: Option<u64>,
+}
+
+impl AgentSession {
+    pub fn is_live(&self) -> bool {
+        matches!(self.status, AgentStatus::Running | AgentStatus::Waiting)
+    }
+}
+
+#[derive(Default)]
+pub struct AgentSessions {
+    sessions: BTreeMap<JobId, AgentSession>,
+    output: BTreeMap<JobId, String>,
+    last_output: BTreeMap<JobId, Instant>,
+    handles: BTreeMap<JobId, RunHandle>,
+}
+
+pub type AgentSessionsState = Arc<Mutex<AgentSessions>>;
+
+impl AgentSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(
+        &mut self,
+        id: JobId,
+        kind: AgentKind,
+        worktree: String,
+        prompt: Option<String>,
+    ) -> AgentSession {
+        let session = AgentSession {
+            id,
+            kind,
+            worktree,
+            prompt,
+            status: AgentStatus::Running,
+            transcript: None,
+            started_ms: now_ms(),
+            finished_ms: None,
+        };
+        self.sessions.insert(id, session.clone());
+        self.output.insert(id, String::new());
+        self.last_output.insert(id, Instant::now());
+        session
+    }
+
+    pub fn attach(&mut self, id: JobId, handle: RunHandle) {
+        self.handles.insert(id, handle);
+    }
+
+    /// Keep `data` in the session's output tail.
+    pub fn append_output(&mut self, id: JobId, data: &str, now: Instant) {
+        let Some(output) = self.output.get_mut(&id) else {
+            return;
+        };
+        output.push_str(data);
+        if output.len() > OUTPUT_TAIL_BYTES {
+            let mut cut = output.len() - OUTPUT_TAIL_BYTES;
+            while !output.is_char_boundary(cut) {
+                cut += 1;
+            }
+            output.drain(..cut);
+        }
+        self.last_output.insert(id, now);
+    }
+
+    fn set_status(&mut self, id: JobId, status: AgentStatus) -> Option<AgentSession> {
+        let session = self.sessions.get_mut(&id)?;
+        if session.status == status {
+            return None;
+        }
+        session.status = status;
+        Some(session.clone())
+    }
+
+    /// Re-check whether a live session is working or waiting. Returns the
+    /// session if its status changed.
+    pub fn refresh(&mut self, id: JobId, now: Instant) -> Option<AgentSession> {
+        let session = self.sessions.get(&id).filter(|s| s.is_live())?;
+        let output = self.output.get(&id)?;
+        let mut start = output.len().saturating_sub(SCREEN_BYTES);
+        while !output.is_char_boundary(start) {
+            start += 1;
+        }
+        let prompt = shows_input_prompt(session.kind, &output[start..]);
+        let quiet = self
+            .last_output
+            .get(&id)
+            .map_or(true, |at| now.saturating_duration_since(*at) >= IDLE_AFTER);
+        let status = match (prompt, quiet) {
+            (true, true) => AgentStatus::Waiting,
+            (false, _) => AgentStatus::Running,
+            // A prompt may be on its way out; wait for the screen to settle.
+            (true, false) => return None,
+        };
+        self.set_status(id, status)
+    }
+
+    pub fn set_transcript(&mut self, id: JobId, path: &Path) -> Option<AgentSession> {
+        let session = self.sessions.get_mut(&id)?;
+        session.transcript = Some(path_to_wire(path));
+        Some(session.clone())
+    }
+
+    pub fn finish(&mut self, id: JobId, status: AgentStatus) -> Option<AgentSession> {
+        self.handles.remove(&id);
+        self.last_output.remove(&id);
+        let session = self.sessions.get_mut(&id)?;
+        session.status = status;
+        session.finished_ms = Some(now_ms());
+        let session = session.clone();
+        self.prune();
+        Some(session)
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&AgentSession> {
+        self.sessions.get(&id)
+    }
+
+    pub fn output(&self, id: JobId) -> Option<&str> {
+        self.output.get(&id).map(String::as_str)
+    }
+
+    /// Sessions in `worktree`, or all of them, newest first.
+    pub fn list(&self, worktree: Option<&str>) -> Vec<AgentSession> {
+        self.sessions
+            .values()
+            .rev()
+            .filter(|s| worktree.map_or(true, |w| s.worktree == w))
+            .cloned()
+            .collect()
+    }
+
+    /// Kill a live session's agent. Returns `false` if it isn't running.
+    pub fn kill(&mut self, id: JobId) -> bool {
+        match self.handles.get_mut(&id) {
+            Some(handle) => {
+                let _ = handle.killer.kill();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Kill every live session's agent, for app exit. Returns how many
+    /// there were.
+    pub fn kill_all(&mut self) -> usize {
+        for handle in self.handles.values_mut() {
+            let _ = handle.killer.kill();
+        }
+        self.handles.len()
+    }
+
+    /// Send the user's input to the agent. Answering a prompt puts the
+    /// agent back to work, so the session counts as running again.
+    pub fn write(&mut self, id: JobId, data: &str) -> Result<Option<AgentSession>, String> {
+        let handle = self
+            .handles
+            .get_mut(&id)
+            .ok_or_else(|| format!("Agent session {} is not running", id))?;
+        handle
+            .writer
+            .write_all(data.as_bytes())
+            .and_then(|_| handle.writer.flush())
+            .map_err(|e| format!("Failed to write to agent: {}", e))?;
+        self.last_output.insert(id, Instant::now());
+        Ok(self.set_status(id, AgentStatus::Running))
+    }
+
+    pub fn resize(&self, id: JobId, cols: u16, rows: u16) -> Result<(), String> {
+        let handle = self
+            .handles
+            .get(&id)
+            .ok_or_else(|| format!("Agent session {} is not running", id))?;
+        handle
+            .master
+            .resize(create_pty_size(cols, rows))
+            .map_err(|e| format!("Failed to resize agent: {}", e))
+    }
+
+    /// Drop the oldest finished sessions beyond [`MAX_FINISHED_SESSIONS`].
+    fn prune(&mut self) {
+        let finished: Vec<JobId> = self
+            .sessions
+            .values()
+            .filter(|s| s.finished_ms.is_some())
+            .map(|s| s.id)
+            .collect();
+        let excess = finished.len().saturating_sub(MAX_FINISHED_SESSIONS);
+        for id in finished.into_iter().take(excess) {
+            self.sessions.remove(&id);
+            self.output.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_status_follows_prompts_and_silence() {
+        let mut sessions = AgentSessions::new();
+        let t0 = Instant::now();
+        sessions.start(1, AgentKind::Claude, "/repo".into(), None);
+        sessions.append_output(1, "\x1b[2K⠋ Thinking… (esc to interrupt)", t0);
+        assert!(sessions.refresh(1, t0 + IDLE_AFTER * 2).is_none());
+
+        let prompt = "\x1b[1mDo you want to make this edit?\x1b[0m\r\n\x1b[36m❯ 1. Yes\x1b[0m";
+        sessions.append_output(1, prompt, t0);
+        assert!(sessions.refresh(1, t0).is_none());
+        let waiting = sessions.refresh(1, t0 + IDLE_AFTER).unwrap();
+        assert_eq!(waiting.status, AgentStatus::Waiting);
+        assert!(sessions.refresh(1, t0 + IDLE_AFTER * 2).is_none());
+
+        sessions.append_output(1, &"⠙ Editing… ".repeat(SCREEN_BYTES), t0);
+        let running = sessions.refresh(1, t0 + IDLE_AFTER).unwrap();
+        assert_eq!(running.status, AgentStatus::Running);
+
+        let done = sessions.finish(1, AgentStatus::Done { code: 0 }).unwrap();
+        assert!(done.finished_ms.is_some());
+        assert!(sessions.refresh(1, t0 + IDLE_AFTER).is_none());
+        assert!(sessions.write(1, "y").is_err());
+        assert_eq!(sessions.list(Some("/repo")).len(), 1);
+        assert!(sessions.list(Some("/other")).is_empty());
+    }
+
+    #[test]
+    fn test_find_transcript() {
+        let home = TempDir::new().unwrap();
+        let since = SystemTime::now() - Duration::from_secs(60);
+        let cwd = "/work/kiri.feature";
+
+        let claude_dir = home.path().join(".claude/projects/-work-kiri-feature");
+        std::fs::create_dir_all(&claude_dir).unwrap();
+        std::fs::write(claude_dir.join("abc.jsonl"), "{}\n").unwrap();
+        std::fs::write(claude_dir.join("notes.txt"), "").unwrap();
+        assert_eq!(
+            find_transcript(AgentKind::Claude, home.path(), cwd, since),
+            Some(claude_dir.join("abc.jsonl"))
+        );
+        let later = SystemTime::now() + Duration::from_secs(60);
+        assert!(find_transcript(AgentKind::Claude, home.path(), cwd, later).is_none());
+
+        let day = home.path().join(".codex/sessions/2026/10/16");
+        std::fs::create_dir_all(&day).unwrap();
+        std::fs::write(
+            day.join("rollout-a.jsonl"),
+            "{\"type\":\"session_meta\",\"payload\":{\"cwd\":\"/elsewhere\"}}\n",
+        )
+        .unwrap();
+        assert!(find_transcript(AgentKind::Codex, home.path(), cwd, since).is_none());
+        let meta = format!("{{\"payload\":{{\"cwd\":\"{}\"}}}}\n", cwd);
+        std::fs::write(day.join("rollout-b.jsonl"), meta).unwrap();
+        assert_eq!(
+            find_transcript(AgentKind::Codex, home.path(), cwd, since),
+            Some(day.join("rollout-b.jsonl"))
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! Runner and Tauri commands for agent sessions; see agents.rs.
+//!
+//! A session is a job of kind `agent` and its id is the job id. Output
+//! streams as `agent-output` events, each change of a session is sent as
+//! `agent-session-updated` with its [`AgentSession`], and a session that
+//! starts waiting for the user also sends `agent-needs-input`.
+
+use serde::Serialize;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::agents::{
+    find_transcript, AgentKind, AgentOutput, AgentSession, AgentSessionsState, AgentStatus,
+    AGENT_JOB_KIND,
+};
+use super::error::{ErrorCode, KiriError};
+use super::event_throttle_commands::emit_throttled;
+use super::fs_path::normalize_input_path;
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::{self, spawn_job};
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::notifications::NotificationEvent;
+use super::notifications_commands::notify;
+use super::tasks::RunHandle;
+use super::tasks_commands::user_shell_command;
+use super::terminal::{open_pty_with_command, read_utf8_chunks, resolve_terminal_size};
+
+pub const AGENT_OUTPUT_EVENT: &str = "agent-output";
+pub const AGENT_SESSION_UPDATED_EVENT: &str = "agent-session-updated";
+pub const AGENT_NEEDS_INPUT_EVENT: &str = "agent-needs-input";
+
+/// How often a session checks whether its agent exited, went quiet or was
+/// cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often a session looks for its transcript until it finds it.
+const TRANSCRIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentCli {
+    pub kind: AgentKind,
+    pub label: &'static str,
+    pub installed: bool,
+    pub version: Option<String>,
+}
+
+fn sessions_state(app: &AppHandle) -> Result<AgentSessionsState, String> {
+    app.try_state::<AgentSessionsState>()
+        .map(|state| state.inner().clone())
+        .ok_or_else(|| "Agent sessions are not available".to_string())
+}
+
+fn publish(app: &AppHandle, session: &AgentSession) {
+    let _ = app.emit(AGENT_SESSION_UPDATED_EVENT, session);
+    if session.status == AgentStatus::Waiting {
+        let _ = app.emit(AGENT_NEEDS_INPUT_EVENT, session);
+        let event = NotificationEvent::AgentWaiting {
+            agent: session.kind.label().to_string(),
+            worktree: session.worktree.clone(),
+        };
+        notify(app, event);
+    }
+}
+
+/// Body of a session's job: start the agent on a PTY and supervise it
+/// until it exits or the job is cancelled.
+fn run_agent_job(
+    app: &AppHandle,
+    ctx: &JobContext,
+    kind: AgentKind,
+    worktree: String,
+    prompt: Option<String>,
+    (cols, rows): (u16, u16),
+) -> Result<(), String> {
+    let sessions = sessions_state(app)?;
+    let id = ctx.id();
+    let started_at = SystemTime::now();
+    let argv = kind.argv(prompt.as_deref());
+    let session = sessions
+        .lock_recover()
+        .start(id, kind, worktree.clone(), prompt);
+    publish(app, &session);
+
+    let command = user_shell_command(app, &argv, &worktree);
+    let started = open_pty_with_command(cols, rows, command).and_then(|pty| {
+        let reader = pty
+            .pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| format!("Failed to read agent output: {}", e))?;
+        let writer = pty
+            .pair
+            .master
+            .take_writer()
+            .map_err(|e| format!("Failed to open agent input: {}", e))?;
+        Ok((pty, reader, writer))
+    });
+    let (pty, reader, writer) = match started {
+        Ok(started) => started,
+        Err(error) => {
+            let status = AgentStatus::Failed {
+                error: error.clone(),
+            };
+            if let Some(session) = sessions.lock_recover().finish(id, status) {
+                publish(app, &session);
+            }
+            return Err(error);
+        }
+    };
+    let mut child = pty.child;
+    // Only the child holds the PTY's slave end now, so the reader sees EOF
+    // once it exits.
+    drop(pty.pair.slave);
+    sessions.lock_recover().attach(
+        id,
+        RunHandle {
+            master: pty.pair.master,
+            writer,
+            killer: child.clone_killer(),
+        },
+    );
+    {
+        let (app, sessions) = (app.clone(), sessions.clone());
+        thread::spawn(move || {
+            read_utf8_chunks(reader, |chunk| {
+                sessions
+                    .lock_recover()
+                    .append_output(id, chunk, Instant::now());
+                let payload = AgentOutput {
+                    session_id: id,
+                    data: chunk.to_string(),
+                };
+                emit_throttled(&app, AGENT_OUTPUT_EVENT, &id.to_string(), &payload);
+            })
+        });
+    }
+
+    let home = dirs::home_dir();
+    let mut transcript_found = false;
+    let mut transcript_due = Instant::now();
+    let mut killed = false;
+    let exit = loop {
+        if ctx.is_cancelled() && !killed {
+            let _ = child.kill();
+            killed = true;
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => {}
+            Err(e) => break Err(format!("Failed to wait for agent: {}", e)),
+        }
+        let now = Instant::now();
+        if let Some(session) = sessions.lock_recover().refresh(id, now) {
+            publish(app, &session);
+        }
+        if !transcript_found && now >= transcript_due {
+            transcript_due = now + TRANSCRIPT_POLL_INTERVAL;
+            let transcript = home
+                .as_deref()
+                .and_then(|home| find_transcript(kind, home, &worktree, started_at));
+            if let Some(path) = transcript {
+                transcript_found = true;
+                if let Some(session) = sessions.lock_recover().set_transcript(id, &path) {
+                    publish(app, &session);
+                }
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let (status, result) = match exit {
+        Ok(_) if killed => (AgentStatus::Stopped, Ok(())),
+        Ok(status) => (
+            AgentStatus::Done {
+                code: status.exit_code(),
+            },
+            Ok(()),
+        ),
+        Err(error) => (
+            AgentStatus::Failed {
+                error: error.clone(),
+            },
+            Err(error),
+        ),
+    };
+    if let Some(session) = sessions.lock_recover().finish(id, status) {
+        publish(app, &session);
+    }
+    result
+}
+
+/// The agent CLIs kiri can run, and whether each is installed.
+#[tauri::command]
+pub async fn list_agent_clis() -> Result<Vec<AgentCli>, KiriError> {
+    run_blocking("list_agent_clis", || {
+        Ok(AgentKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let version = Command::new(kind.program())
+                    .arg("--version")
+                    .stdin(Stdio::null())
+                    .output()
+                    .ok()
+                    .filter(|output| output.status.success())
+                    .map(|output| {
+                        let text = String::from_utf8_lossy(&output.stdout);
+                        text.lines().next().unwrap_or_default().trim().to_string()
+                    });
+                AgentCli {
+                    kind,
+                    label: kind.label(),
+                    installed: version.is_some(),
+                    version: version.filter(|v| !v.is_empty()),
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Start an agent in `worktree`, optionally with `prompt` as its first
+/// message. Returns the session id.
+#[tauri::command]
+pub fn start_agent_session(
+    app: AppHandle,
+    worktree: String,
+    kind: AgentKind,
+    prompt: Option<String>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+) -> Result<JobId, KiriError> {
+    let dir = normalize_input_path(&worktree);
+    if !dir.is_dir() {
+        return Err(KiriError::new(
+            ErrorCode::NotFound,
+            format!("{} is not a directory", worktree),
+        ));
+    }
+    let worktree = dir.to_string_lossy().to_string();
+    let prompt = prompt.filter(|p| !p.trim().is_empty());
+    let size = resolve_terminal_size(cols, rows);
+    let label = format!("{} {}", kind.label(), worktree);
+    let job_app = app.clone();
+    spawn_job(&app, AGENT_JOB_KIND, &label, move |ctx| {
+        run_agent_job(&job_app, ctx, kind, worktree, prompt, size)
+    })
+    .map_err(KiriError::from)
+}
+
+/// Stop a session, killing its agent.
+#[tauri::command]
+pub fn stop_agent_session(
+    app: AppHandle,
+    state: tauri::State<'_, AgentSessionsState>,
+    session_id: JobId,
+) -> Result<(), KiriError> {
+    jobs_commands::cancel(&app, session_id)?;
+    state.lock_recover().kill(session_id);
+    Ok(())
+}
+
+/// Sessions in `worktree`, or in all worktrees, newest first.
+#[tauri::command]
+pub fn list_agent_sessions(
+    state: tauri::State<'_, AgentSessionsState>,
+    worktree: Option<String>,
+) -> Vec<AgentSession> {
+    let worktree = worktree.map(|w| normalize_input_path(&w).to_string_lossy().to_string());
+    state.lock_recover().list(worktree.as_deref())
+}
+
+/// The last output of a session, for redrawing it after a window reload.
+#[tauri::command]
+pub fn get_agent_output(
+    state: tauri::State<'_, AgentSessionsState>,
+    session_id: JobId,
+) -> Result<String, KiriError> {
+    state
+        .lock_recover()
+        .output(session_id)
+        .map(str::to_string)
+        .ok_or_else(|| format!("No agent session {}", session_id))
+        .map_err(KiriError::from)
+}
+
+#[tauri::command]
+pub fn write_agent_input(
+    app: AppHandle,
+    state: tauri::State<'_, AgentSessionsState>,
+    session_id: JobId,
+    data: String,
+) -> Result<(), KiriError> {
+    let changed = state.lock_recover().write(session_id, &data)?;
+    if let Some(session) = changed {
+        publish(&app, &session);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resize_agent_session(
+    state: tauri::State<'_, AgentSessionsState>,
+    session_id: JobId,
+    cols: u16,
+    rows: u16,
+) -> Result<(), KiriError> {
+    state
+        .lock_recover()
+        .resize(session_id, cols, rows)
+        .map_err(KiriError::from)
+}
@@ -0,0 +1,383 @@
+//! Scheduled background fetch of the open repositories' remotes.
+//!
+//! Every repository open in a window (its main checkout or one of its
+//! worktrees) is fetched once per interval, ten minutes unless set
+//! otherwise globally or for that repository. Worktrees of one repository
+//! share their remotes, so it is fetched once for all of them. Fetches are
+//! skipped while the connection is metered, as far as the system says so:
+//! NetworkManager on Linux and the connection cost on Windows. macOS
+//! doesn't expose it to command-line tools, so there it never pauses.
+//!
+//! After a fetch each checkout's upstream is compared with before, and a
+//! moved upstream is reported with the new ahead / behind counts. Settings
+//! are saved in `~/.kiri/auto_fetch.json`; the scheduler thread lives in
+//! auto_fetch_commands.rs.
+
+use git2::{BranchType, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::file_io::write_file_contents_atomic;
+use super::fs_path::path_to_wire;
+use super::git_worktree::open_common_repository;
+
+pub const DEFAULT_INTERVAL_MINUTES: u32 = 10;
+
+/// Shortest interval accepted, so a typo can't hammer a remote.
+pub const MIN_INTERVAL_MINUTES: u32 = 1;
+
+pub fn auto_fetch_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("auto_fetch.json"))
+}
+
+/// Overrides for one repository; unset fields follow the global settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoFetchSettings {
+    pub enabled: Option<bool>,
+    pub interval_minutes: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoFetchSettings {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    /// Skip fetches while the connection is metered.
+    pub pause_on_metered: bool,
+    /// By main checkout path.
+    pub repos: BTreeMap<String, RepoFetchSettings>,
+}
+
+impl Default for AutoFetchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_minutes: DEFAULT_INTERVAL_MINUTES,
+            pause_on_metered: true,
+            repos: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AutoFetch {
+    file: Option<PathBuf>,
+    settings: AutoFetchSettings,
+    /// When each repository, by main checkout, was last fetched.
+    last_fetch: HashMap<PathBuf, Instant>,
+}
+
+pub type AutoFetchState = Arc<Mutex<AutoFetch>>;
+
+impl AutoFetch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let settings = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    log::warn!("failed to parse auto-fetch settings: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            settings,
+            last_fetch: HashMap::new(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_vec_pretty(&self.settings)
+            .map_err(|e| format!("Failed to serialize auto-fetch settings: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn settings(&self) -> &AutoFetchSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, mut settings: AutoFetchSettings) -> Result<(), String> {
+        let intervals = std::iter::once(Some(settings.interval_minutes))
+            .chain(settings.repos.values().map(|repo| repo.interval_minutes));
+        if intervals
+            .flatten()
+            .any(|minutes| minutes < MIN_INTERVAL_MINUTES)
+        {
+            return Err(format!(
+                "Fetch interval must be at least {} minute",
+                MIN_INTERVAL_MINUTES
+            ));
+        }
+        settings
+            .repos
+            .retain(|_, repo| *repo != RepoFetchSettings::default());
+        self.settings = settings;
+        self.save()
+    }
+
+    /// How often the repository at `main_checkout` is fetched; `None` if
+    /// it isn't.
+    pub fn interval_for(&self, main_checkout: &Path) -> Option<Duration> {
+        let repo = self
+            .settings
+            .repos
+            .get(&path_to_wire(main_checkout))
+            .cloned()
+            .unwrap_or_default();
+        if !repo.enabled.unwrap_or(self.settings.enabled) {
+            return None;
+        }
+        let minutes = repo
+            .interval_minutes
+            .unwrap_or(self.settings.interval_minutes);
+        Some(Duration::from_secs(u64::from(minutes) * 60))
+    }
+
+    /// The repositories among `main_checkouts` due for a fetch at `now`,
+    /// recorded as fetched. One seen for the first time is due at once.
+    pub fn take_due(&mut self, main_checkouts: &[PathBuf], now: Instant) -> Vec<PathBuf> {
+        let mut due = Vec::new();
+        for checkout in main_checkouts {
+            let Some(interval) = self.interval_for(checkout) else {
+                continue;
+            };
+            let last = self.last_fetch.get(checkout);
+            if last.map_or(true, |last| now.duration_since(*last) >= interval) {
+                self.last_fetch.insert(checkout.clone(), now);
+                due.push(checkout.clone());
+            }
+        }
+        due
+    }
+
+    /// How long ago each repository was last fetched, by main checkout.
+    pub fn fetched_ago(&self, now: Instant) -> BTreeMap<String, u64> {
+        self.last_fetch
+            .iter()
+            .map(|(checkout, at)| {
+                let secs = now.saturating_duration_since(*at).as_secs();
+                (path_to_wire(checkout), secs)
+            })
+            .collect()
+    }
+}
+
+/// Main checkout of the repository `path` belongs to, which its worktrees
+/// share.
+pub fn main_checkout(path: &Path) -> Option<PathBuf> {
+    let repo = open_common_repository(path).ok()?;
+    repo.workdir().map(|dir| dir.components().collect())
+}
+
+/// Where the current branch of the checkout at `path` tracks its remote.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpstreamStatus {
+    pub branch: String,
+    /// e.g. `origin/main`
+    pub upstream: String,
+    #[serde(skip)]
+    pub upstream_oid: Option<git2::Oid>,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// The upstream of the branch checked out at `path`, if it has one.
+pub fn upstream_status(path: &Path) -> Option<UpstreamStatus> {
+    let repo = Repository::open(path).ok()?;
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let branch_name = head.shorthand()?.to_string();
+    let upstream = repo
+        .find_branch(&branch_name, BranchType::Local)
+        .ok()?
+        .upstream()
+        .ok()?;
+    let upstream_name = upstream.name().ok().flatten()?.to_string();
+    let upstream_oid = upstream.get().target();
+    let (ahead, behind) = match (head.target(), upstream_oid) {
+        (Some(local), Some(remote)) => repo.graph_ahead_behind(local, remote).ok()?,
+        _ => (0, 0),
+    };
+    Some(UpstreamStatus {
+        branch: branch_name,
+        upstream: upstream_name,
+        upstream_oid,
+        ahead,
+        behind,
+    })
+}
+
+/// Remote the checkout at `path` fetches from: its branch's upstream
+/// remote, else `origin`.
+pub fn fetch_remote_name(path: &Path) -> String {
+    Repository::open(path)
+        .ok()
+        .and_then(|repo| {
+            let head = repo.head().ok()?;
+            let remote = repo.branch_upstream_remote(head.name()?).ok()?;
+            remote.as_str().map(String::from)
+        })
+        .unwrap_or_else(|| "origin".to_string())
+}
+
+/// `nmcli -g GENERAL.METERED device show`: `yes` or `yes (guessed)` for a
+/// device on a metered connection.
+pub fn parse_nmcli_metered(output: &str) -> bool {
+    output.lines().any(|line| line.trim().starts_with("yes"))
+}
+
+/// Windows' `NetworkCostType` of the internet connection: `Fixed` and
+/// `Variable` are metered, `Unrestricted` is not.
+pub fn parse_connection_cost(output: &str) -> bool {
+    matches!(output.trim(), "Fixed" | "Variable")
+}
+
+/// Whether the system reports the connection as metered.
+pub fn is_metered() -> bool {
+    let query = |program: &str, args: &[&str]| {
+        let output = Command::new(program).args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    if cfg!(target_os = "linux") {
+        query("nmcli", &["-g", "GENERAL.METERED", "device", "show"])
+            .is_some_and(|output| parse_nmcli_metered(&output))
+    } else if cfg!(windows) {
+        let script = "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType";
+        query("powershell", &["-NoProfile", "-Command", script])
+            .is_some_and(|output| parse_connection_cost(&output))
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_schedule_and_overrides() {
+        let dir = TempDir::new().unwrap();
+        let mut auto_fetch = AutoFetch::load(dir.path().join("auto_fetch.json"));
+        let (a, b, c) = (
+            PathBuf::from("/a"),
+            PathBuf::from("/b"),
+            PathBuf::from("/c"),
+        );
+        let mut settings = AutoFetchSettings::default();
+        settings.repos.insert(
+            "/b".to_string(),
+            RepoFetchSettings {
+                enabled: None,
+                interval_minutes: Some(1),
+            },
+        );
+        settings.repos.insert(
+            "/c".to_string(),
+            RepoFetchSettings {
+                enabled: Some(false),
+                interval_minutes: None,
+            },
+        );
+        settings
+            .repos
+            .insert("/d".to_string(), RepoFetchSettings::default());
+        auto_fetch.set_settings(settings).unwrap();
+        assert_eq!(auto_fetch.settings().repos.len(), 2);
+
+        let repos = vec![a.clone(), b.clone(), c.clone()];
+        let start = Instant::now();
+        assert_eq!(
+            auto_fetch.take_due(&repos, start),
+            vec![a.clone(), b.clone()]
+        );
+        assert!(auto_fetch
+            .take_due(&repos, start + Duration::from_secs(30))
+            .is_empty());
+        assert_eq!(
+            auto_fetch.take_due(&repos, start + Duration::from_secs(60)),
+            vec![b.clone()]
+        );
+        assert_eq!(
+            auto_fetch.take_due(&repos, start + Duration::from_secs(600)),
+            vec![a, b]
+        );
+
+        let reloaded = AutoFetch::load(dir.path().join("auto_fetch.json"));
+        assert_eq!(reloaded.interval_for(&c), None);
+        let too_short = AutoFetchSettings {
+            interval_minutes: 0,
+            ..Default::default()
+        };
+        assert!(auto_fetch.set_settings(too_short).is_err());
+
+        assert!(parse_nmcli_metered("unknown\nyes (guessed)\n"));
+        assert!(!parse_nmcli_metered("no\nunknown\n"));
+        assert!(parse_connection_cost("Variable\r\n"));
+        assert!(!parse_connection_cost("Unrestricted"));
+    }
+
+    #[test]
+    fn test_upstream_status() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let first = repo
+            .commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])
+            .unwrap();
+        let first_commit = repo.find_commit(first).unwrap();
+        let second = repo
+            .commit(None, &sig, &sig, "second", &tree, &[&first_commit])
+            .unwrap();
+
+        assert_eq!(upstream_status(dir.path()), None);
+        repo.remote("upstream", "https://example.com/repo.git")
+            .unwrap();
+        repo.reference("refs/remotes/upstream/main", first, true, "test")
+            .unwrap();
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        repo.find_branch(&branch, BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("upstream/main"))
+            .unwrap();
+        assert_eq!(fetch_remote_name(dir.path()), "upstream");
+
+        let before = upstream_status(dir.path()).unwrap();
+        assert_eq!((before.ahead, before.behind), (0, 0));
+        repo.reference("refs/remotes/upstream/main", second, true, "fetch")
+            .unwrap();
+        let after = upstream_status(dir.path()).unwrap();
+        assert_eq!(after.upstream, "upstream/main");
+        assert_ne!(before.upstream_oid, after.upstream_oid);
+        assert_eq!((after.ahead, after.behind), (0, 1));
+        assert_eq!(
+            main_checkout(dir.path()),
+            Some(dir.path().components().collect())
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Background fetch scheduler and its settings commands; scheduling and
+//! upstream lookups live in auto_fetch.rs.
+//!
+//! The scheduler wakes every [`TICK`], fetches the open repositories that
+//! are due and emits `upstream-moved` for each open checkout whose
+//! upstream changed, so ahead / behind badges refresh on their own.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::auto_fetch::{
+    auto_fetch_file_path, fetch_remote_name, is_metered, main_checkout, upstream_status, AutoFetch,
+    AutoFetchSettings, AutoFetchState, RepoFetchSettings, UpstreamStatus,
+};
+use super::error::KiriError;
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::git_history;
+use super::jobs::CancelToken;
+use super::lock_ext::{LockExt, RwLockExt};
+use super::window::WindowRegistryState;
+
+pub const UPSTREAM_MOVED_EVENT: &str = "upstream-moved";
+
+/// How often the scheduler checks for repositories due a fetch.
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamMoved {
+    /// The checkout whose upstream moved.
+    pub path: String,
+    #[serde(flatten)]
+    pub status: UpstreamStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoFetchStatus {
+    pub settings: AutoFetchSettings,
+    /// Whether the connection is metered right now.
+    pub metered: bool,
+    /// Seconds since each repository, by main checkout, was last fetched.
+    pub fetched_secs_ago: BTreeMap<String, u64>,
+}
+
+/// Read `~/.kiri/auto_fetch.json` into the managed state. Call from setup.
+pub fn load_auto_fetch_settings(app: &AppHandle) {
+    let Some(path) = auto_fetch_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<AutoFetchState>() {
+        *state.lock_recover() = AutoFetch::load(path);
+    }
+}
+
+/// Open checkouts grouped by the repository they belong to.
+fn open_repositories(app: &AppHandle) -> HashMap<PathBuf, Vec<PathBuf>> {
+    let roots = app
+        .try_state::<WindowRegistryState>()
+        .map(|registry| registry.read_recover().contexts().roots())
+        .unwrap_or_default();
+    let mut repos: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for root in roots {
+        if let Some(checkout) = main_checkout(&root) {
+            let checkouts = repos.entry(checkout).or_default();
+            if !checkouts.contains(&root) {
+                checkouts.push(root);
+            }
+        }
+    }
+    repos
+}
+
+/// Fetch the repositories that are due and report moved upstreams.
+fn fetch_due(app: &AppHandle) {
+    let Some(state) = app.try_state::<AutoFetchState>() else {
+        return;
+    };
+    let repos = open_repositories(app);
+    if repos.is_empty() {
+        return;
+    }
+    if state.lock_recover().settings().pause_on_metered && is_metered() {
+        return;
+    }
+    let keys: Vec<PathBuf> = repos.keys().cloned().collect();
+    let due = state.lock_recover().take_due(&keys, Instant::now());
+
+    for checkout in due {
+        let roots = &repos[&checkout];
+        let before: Vec<_> = roots.iter().map(|root| upstream_status(root)).collect();
+        // Each checkout may track a different remote; fetch each once.
+        let mut remotes: Vec<String> = roots.iter().map(|root| fetch_remote_name(root)).collect();
+        remotes.sort();
+        remotes.dedup();
+        for remote in remotes {
+            let path = checkout.to_string_lossy().to_string();
+            match git_history::fetch_remote(path, Some(remote.clone()), &CancelToken::default()) {
+                Ok(result) if !result.success => log::warn!(
+                    "auto-fetch of {} from {} failed: {}",
+                    checkout.display(),
+                    remote,
+                    result.message.trim()
+                ),
+                Ok(_) => {}
+                Err(e) => log::warn!("auto-fetch of {} failed: {}", checkout.display(), e),
+            }
+        }
+        for (root, before) in roots.iter().zip(before) {
+            let Some(after) = upstream_status(root) else {
+                continue;
+            };
+            if before.as_ref().map(|b| b.upstream_oid) == Some(after.upstream_oid) {
+                continue;
+            }
+            let moved = UpstreamMoved {
+                path: root.to_string_lossy().to_string(),
+                status: after,
+            };
+            let _ = app.emit(UPSTREAM_MOVED_EVENT, &moved);
+        }
+    }
+}
+
+/// Start the scheduler thread. Call once from setup.
+pub fn start_auto_fetch(app: &AppHandle) {
+    let app = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("kiri-auto-fetch".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(TICK);
+            fetch_due(&app);
+        });
+    if let Err(e) = spawned {
+        log::warn!("failed to start auto-fetch: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_auto_fetch_settings(state: tauri::State<'_, AutoFetchState>) -> AutoFetchStatus {
+    let metered = is_metered();
+    let auto_fetch = state.lock_recover();
+    AutoFetchStatus {
+        settings: auto_fetch.settings().clone(),
+        metered,
+        fetched_secs_ago: auto_fetch.fetched_ago(Instant::now()),
+    }
+}
+
+#[tauri::command]
+pub fn set_auto_fetch_settings(
+    state: tauri::State<'_, AutoFetchState>,
+    settings: AutoFetchSettings,
+) -> Result<(), KiriError> {
+    state
+        .lock_recover()
+        .set_settings(settings)
+        .map_err(KiriError::from)
+}
+
+/// Override auto-fetch for the repository containing `repo_path`; `None`
+/// fields follow the global settings.
+#[tauri::command]
+pub fn set_repo_auto_fetch(
+    state: tauri::State<'_, AutoFetchState>,
+    repo_path: String,
+    enabled: Option<bool>,
+    interval_minutes: Option<u32>,
+) -> Result<(), KiriError> {
+    let path = normalize_input_path(&repo_path);
+    let checkout =
+        main_checkout(&path).ok_or_else(|| format!("Not a git repository: {}", path.display()))?;
+    let mut auto_fetch = state.lock_recover();
+    let mut settings = auto_fetch.settings().clone();
+    settings.repos.insert(
+        path_to_wire(&checkout),
+        RepoFetchSettings {
+            enabled,
+            interval_minutes,
+        },
+    );
+    auto_fetch.set_settings(settings).map_err(KiriError::from)
+}
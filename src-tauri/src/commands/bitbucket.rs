@@ -0,0 +1,409 @@
+//! Bitbucket Cloud implementation of [`CodeHost`]. Commit build statuses
+//! are reported as checks, and reviewer participants as reviews.
+//!
+//! The saved token is either an access token, sent as a bearer token, or
+//! `username:app_password`, sent with basic auth.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::code_host::{
+    http_client, send_json, CheckRun, CodeHost, HostKind, NewPullRequest, PullRequestSummary,
+    RemoteRepo, Review,
+};
+use super::error::{ErrorCode, KiriError};
+
+const BITBUCKET_API: &str = "https://api.bitbucket.org/2.0";
+const ALL_STATES: [&str; 4] = ["OPEN", "MERGED", "DECLINED", "SUPERSEDED"];
+
+#[derive(Deserialize)]
+struct Page<T> {
+    values: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    display_name: String,
+    nickname: Option<String>,
+}
+
+impl ApiUser {
+    fn name(self) -> String {
+        self.nickname.unwrap_or(self.display_name)
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiName {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ApiCommit {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct ApiRepository {
+    full_name: String,
+    mainbranch: Option<ApiName>,
+}
+
+#[derive(Deserialize)]
+struct ApiEndpoint {
+    branch: ApiName,
+    commit: Option<ApiCommit>,
+    repository: Option<ApiRepository>,
+}
+
+#[derive(Deserialize)]
+struct ApiLink {
+    href: String,
+}
+
+#[derive(Deserialize)]
+struct ApiLinks {
+    html: ApiLink,
+}
+
+#[derive(Deserialize)]
+struct ApiParticipant {
+    user: ApiUser,
+    role: String,
+    /// `approved`, `changes_requested` or null
+    state: Option<String>,
+    participated_on: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiPullRequest {
+    id: u64,
+    title: String,
+    /// `OPEN`, `MERGED`, `DECLINED` or `SUPERSEDED`
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    author: Option<ApiUser>,
+    source: ApiEndpoint,
+    destination: ApiEndpoint,
+    links: ApiLinks,
+    updated_on: String,
+    /// Only in single pull request responses
+    #[serde(default)]
+    participants: Vec<ApiParticipant>,
+}
+
+impl ApiPullRequest {
+    fn into_summary(self) -> PullRequestSummary {
+        let state = match self.state.as_str() {
+            "OPEN" => "open",
+            "MERGED" => "merged",
+            _ => "closed",
+        };
+        // A deleted fork leaves the source repository empty.
+        let from_fork = match (&self.source.repository, &self.destination.repository) {
+            (Some(source), Some(destination)) => source.full_name != destination.full_name,
+            _ => true,
+        };
+        PullRequestSummary {
+            number: self.id,
+            title: self.title,
+            state: state.to_string(),
+            draft: self.draft,
+            author: self.author.map(ApiUser::name).unwrap_or_default(),
+            head_branch: self.source.branch.name,
+            // Abbreviated; the statuses endpoint accepts it as is.
+            head_sha: self.source.commit.map(|c| c.hash).unwrap_or_default(),
+            base_branch: self.destination.branch.name,
+            from_fork,
+            url: self.links.html.href,
+            updated_at: self.updated_on,
+            worktree_path: None,
+        }
+    }
+}
+
+fn reviews_from_participants(participants: Vec<ApiParticipant>) -> Vec<Review> {
+    participants
+        .into_iter()
+        .filter(|p| p.role == "REVIEWER")
+        .map(|p| Review {
+            author: p.user.name(),
+            state: match p.state.as_deref() {
+                Some("approved") => "APPROVED",
+                Some("changes_requested") => "CHANGES_REQUESTED",
+                _ => "PENDING",
+            }
+            .to_string(),
+            submitted_at: p.participated_on,
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct ApiStatus {
+    name: Option<String>,
+    key: String,
+    /// `INPROGRESS`, `SUCCESSFUL`, `FAILED` or `STOPPED`
+    state: String,
+    url: Option<String>,
+}
+
+fn check_from_status(status: ApiStatus) -> CheckRun {
+    let (state, conclusion) = match status.state.as_str() {
+        "SUCCESSFUL" => ("completed", Some("success")),
+        "FAILED" => ("completed", Some("failure")),
+        "STOPPED" => ("completed", Some("cancelled")),
+        _ => ("in_progress", None),
+    };
+    CheckRun {
+        name: status.name.unwrap_or(status.key),
+        status: state.to_string(),
+        conclusion: conclusion.map(str::to_string),
+        url: status.url,
+    }
+}
+
+fn state_query(state: &str) -> String {
+    let states: &[&str] = match state {
+        "open" => &["OPEN"],
+        "merged" => &["MERGED"],
+        "closed" => &["DECLINED", "SUPERSEDED"],
+        _ => &ALL_STATES,
+    };
+    states
+        .iter()
+        .map(|s| format!("state={}", s))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub struct BitbucketClient {
+    http: reqwest::Client,
+    token: Option<String>,
+}
+
+impl BitbucketClient {
+    pub fn new(token: Option<String>) -> Result<Self, String> {
+        Ok(Self {
+            http: http_client()?,
+            token,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", BITBUCKET_API, path));
+        match &self.token {
+            Some(token) => match token.split_once(':') {
+                Some((username, password)) => request.basic_auth(username, Some(password)),
+                None => request.bearer_auth(token),
+            },
+            None => request,
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, KiriError> {
+        send_json(
+            HostKind::Bitbucket,
+            self.request(reqwest::Method::GET, path),
+        )
+        .await
+    }
+
+    async fn pull_request(
+        &self,
+        repo: &RemoteRepo,
+        number: u64,
+    ) -> Result<ApiPullRequest, KiriError> {
+        self.get(&format!(
+            "/repositories/{}/pullrequests/{}",
+            repo.path(),
+            number
+        ))
+        .await
+    }
+}
+
+#[async_trait]
+impl CodeHost for BitbucketClient {
+    fn kind(&self) -> HostKind {
+        HostKind::Bitbucket
+    }
+
+    async fn default_branch(&self, repo: &RemoteRepo) -> Result<String, KiriError> {
+        let info: ApiRepository = self.get(&format!("/repositories/{}", repo.path())).await?;
+        info.mainbranch.map(|b| b.name).ok_or_else(|| {
+            KiriError::new(
+                ErrorCode::NotFound,
+                format!("{} has no main branch", info.full_name),
+            )
+        })
+    }
+
+    async fn list_pulls(
+        &self,
+        repo: &RemoteRepo,
+        state: &str,
+    ) -> Result<Vec<PullRequestSummary>, KiriError> {
+        let page: Page<ApiPullRequest> = self
+            .get(&format!(
+                "/repositories/{}/pullrequests?{}&sort=-updated_on&pagelen=50",
+                repo.path(),
+                state_query(state)
+            ))
+            .await?;
+        Ok(page
+            .values
+            .into_iter()
+            .map(ApiPullRequest::into_summary)
+            .collect())
+    }
+
+    async fn pull_for_branch(
+        &self,
+        repo: &RemoteRepo,
+        branch: &str,
+    ) -> Result<Option<PullRequestSummary>, KiriError> {
+        let query = format!(
+            "source.branch.name=\"{}\" AND source.repository.full_name=\"{}\"",
+            branch.replace('"', "\\\""),
+            repo.path()
+        );
+        let page: Page<ApiPullRequest> = self
+            .get(&format!(
+                "/repositories/{}/pullrequests?{}&q={}&sort=-updated_on&pagelen=1",
+                repo.path(),
+                state_query("all"),
+                urlencoding::encode(&query)
+            ))
+            .await?;
+        Ok(page
+            .values
+            .into_iter()
+            .next()
+            .map(ApiPullRequest::into_summary))
+    }
+
+    async fn get_pull(
+        &self,
+        repo: &RemoteRepo,
+        number: u64,
+    ) -> Result<PullRequestSummary, KiriError> {
+        Ok(self.pull_request(repo, number).await?.into_summary())
+    }
+
+    async fn checks(&self, repo: &RemoteRepo, sha: &str) -> Result<Vec<CheckRun>, KiriError> {
+        let page: Page<ApiStatus> = self
+            .get(&format!(
+                "/repositories/{}/commit/{}/statuses?pagelen=100",
+                repo.path(),
+                sha
+            ))
+            .await?;
+        Ok(page.values.into_iter().map(check_from_status).collect())
+    }
+
+    async fn reviews(&self, repo: &RemoteRepo, number: u64) -> Result<Vec<Review>, KiriError> {
+        let pull = self.pull_request(repo, number).await?;
+        Ok(reviews_from_participants(pull.participants))
+    }
+
+    async fn create_pull(
+        &self,
+        repo: &RemoteRepo,
+        new: &NewPullRequest,
+    ) -> Result<PullRequestSummary, KiriError> {
+        let request = self
+            .request(
+                reqwest::Method::POST,
+                &format!("/repositories/{}/pullrequests", repo.path()),
+            )
+            .json(&serde_json::json!({
+                "title": new.title,
+                "description": new.body,
+                "source": {"branch": {"name": new.head}},
+                "destination": {"branch": {"name": new.base}},
+                "draft": new.draft,
+            }));
+        let created: ApiPullRequest = send_json(HostKind::Bitbucket, request).await?;
+        Ok(created.into_summary())
+    }
+
+    /// Bitbucket doesn't publish pull request refs.
+    fn pull_head_ref(&self, _number: u64) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> serde_json::Value {
+        serde_json::json!({
+            "id": 3,
+            "title": "Add thing",
+            "state": "DECLINED",
+            "author": {"display_name": "Dev Eloper", "nickname": "dev"},
+            "source": {
+                "branch": {"name": "feature/x"},
+                "commit": {"hash": "abc123def456"},
+                "repository": {"full_name": "ws/repo"}
+            },
+            "destination": {
+                "branch": {"name": "main"},
+                "commit": {"hash": "0123456789ab"},
+                "repository": {"full_name": "ws/repo"}
+            },
+            "links": {"html": {"href": "https://bitbucket.org/ws/repo/pull-requests/3"}},
+            "updated_on": "2024-05-01T00:00:00+00:00",
+            "participants": [
+                {"user": {"display_name": "A"}, "role": "REVIEWER", "state": "approved",
+                 "participated_on": "2024-05-01T00:00:00+00:00"},
+                {"user": {"display_name": "B"}, "role": "REVIEWER", "state": null},
+                {"user": {"display_name": "C"}, "role": "PARTICIPANT", "state": null}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_pull_request_from_api_payload() {
+        let pull: ApiPullRequest = serde_json::from_value(payload()).unwrap();
+        let reviews = reviews_from_participants(pull.participants);
+        let pull: ApiPullRequest = serde_json::from_value(payload()).unwrap();
+        let summary = pull.into_summary();
+        assert_eq!(summary.number, 3);
+        assert_eq!(summary.state, "closed");
+        assert_eq!(summary.author, "dev");
+        assert_eq!(summary.head_sha, "abc123def456");
+        assert!(!summary.from_fork);
+
+        let states: Vec<&str> = reviews.iter().map(|r| r.state.as_str()).collect();
+        assert_eq!(states, vec!["APPROVED", "PENDING"]);
+    }
+
+    #[test]
+    fn test_build_statuses_map_to_checks() {
+        let check = |state: &str| {
+            check_from_status(ApiStatus {
+                name: None,
+                key: "build".to_string(),
+                state: state.to_string(),
+                url: None,
+            })
+        };
+        assert_eq!(check("SUCCESSFUL").conclusion.as_deref(), Some("success"));
+        assert_eq!(check("STOPPED").conclusion.as_deref(), Some("cancelled"));
+        assert_eq!(check("INPROGRESS").conclusion, None);
+        assert_eq!(check("FAILED").name, "build");
+    }
+
+    #[test]
+    fn test_state_query() {
+        assert_eq!(state_query("open"), "state=OPEN");
+        assert_eq!(state_query("closed"), "state=DECLINED&state=SUPERSEDED");
+        assert_eq!(state_query("all").matches("state=").count(), 4);
+    }
+}
@@ -0,0 +1,438 @@
+//! Bookmarked files and directories, per project.
+//!
+//! Bookmarks are saved in `~/.kiri/bookmarks.json` under the project's
+//! main checkout, by path relative to the checkout, so every worktree of
+//! a repository shows the same ones. They keep the order the user gave
+//! them and an optional label.
+//!
+//! When a bookmarked path is gone, [`find_moved`] looks for where it
+//! went: first in the renames git detects in the checkout (a `git mv`, or
+//! a move in the file tree that left a deleted and an untracked file),
+//! then for the only file or directory of the same name in the project.
+
+use git2::{Delta, Repository, StatusOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::file_io::write_file_contents_atomic;
+use super::fs_path::{os_str_to_wire, os_string_from_wire};
+use super::search::DEFAULT_EXCLUDED_DIRS;
+
+/// Longest label, in characters.
+pub const MAX_LABEL_CHARS: usize = 80;
+
+/// Entries [`find_by_name`] looks at before giving up.
+const MAX_NAME_SEARCH_ENTRIES: usize = 100_000;
+
+const MAX_NAME_SEARCH_DEPTH: usize = 32;
+
+pub fn bookmarks_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("bookmarks.json"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub id: String,
+    /// Relative to the checkout, `/`-separated, in wire form.
+    pub path: String,
+    pub label: Option<String>,
+    pub is_dir: bool,
+}
+
+/// `rel` as a bookmark path.
+pub fn relative_key(rel: &Path) -> String {
+    rel.components()
+        .map(|part| os_str_to_wire(part.as_os_str()))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Where bookmark path `key` is in the checkout at `checkout`.
+pub fn absolute_path(checkout: &Path, key: &str) -> PathBuf {
+    key.split('/').fold(checkout.to_path_buf(), |path, part| {
+        path.join(os_string_from_wire(part))
+    })
+}
+
+/// A label trimmed, `None` when empty; too long is an error.
+pub fn clean_label(label: Option<String>) -> Result<Option<String>, String> {
+    let Some(label) = label.map(|l| l.trim().to_string()) else {
+        return Ok(None);
+    };
+    if label.chars().count() > MAX_LABEL_CHARS {
+        return Err(format!(
+            "Bookmark labels can be at most {} characters",
+            MAX_LABEL_CHARS
+        ));
+    }
+    Ok(Some(label).filter(|l| !l.is_empty()))
+}
+
+/// Files git sees renamed in the checkout, staged or not, as
+/// `(old, new)` bookmark paths.
+pub fn git_renames(checkout: &Path) -> Vec<(String, String)> {
+    let Ok(repo) = Repository::open(checkout) else {
+        return Vec::new();
+    };
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true);
+    let Ok(statuses) = repo.statuses(Some(&mut opts)) else {
+        return Vec::new();
+    };
+    statuses
+        .iter()
+        .filter_map(|entry| {
+            let renamed = |delta: &git2::DiffDelta| delta.status() == Delta::Renamed;
+            let delta = entry
+                .head_to_index()
+                .filter(renamed)
+                .or_else(|| entry.index_to_workdir().filter(renamed))?;
+            Some((
+                relative_key(delta.old_file().path()?),
+                relative_key(delta.new_file().path()?),
+            ))
+        })
+        .collect()
+}
+
+/// Where `renames` moved bookmark path `key`: the file itself, or for a
+/// directory, any file that was in it.
+fn renamed_to(key: &str, is_dir: bool, renames: &[(String, String)]) -> Option<String> {
+    if !is_dir {
+        return renames
+            .iter()
+            .find(|(old, _)| old == key)
+            .map(|(_, new)| new.clone());
+    }
+    let prefix = format!("{}/", key);
+    renames.iter().find_map(|(old, new)| {
+        let suffix = old.strip_prefix(&prefix)?;
+        let dir = new.strip_suffix(suffix)?.strip_suffix('/')?;
+        Some(dir.to_string()).filter(|dir| !dir.is_empty())
+    })
+}
+
+/// The only file (or directory, with `is_dir`) named `name` in the
+/// checkout, skipping the directories search skips.
+fn find_by_name(checkout: &Path, name: &str, is_dir: bool) -> Option<PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![(checkout.to_path_buf(), 0)];
+    let mut visited = 0;
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            visited += 1;
+            if visited > MAX_NAME_SEARCH_ENTRIES || found.len() > 1 {
+                return None;
+            }
+            let entry_name = os_str_to_wire(&entry.file_name());
+            let entry_is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if entry_name == name && entry_is_dir == is_dir {
+                found.push(entry.path());
+            }
+            if entry_is_dir
+                && depth < MAX_NAME_SEARCH_DEPTH
+                && !DEFAULT_EXCLUDED_DIRS.contains(&entry_name.as_str())
+            {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    (found.len() == 1).then(|| found.remove(0))
+}
+
+/// The new bookmark path of a `bookmark` that is no longer in the
+/// checkout, if it can be told where it went.
+pub fn find_moved(
+    checkout: &Path,
+    bookmark: &Bookmark,
+    renames: &[(String, String)],
+) -> Option<String> {
+    let exists = |key: &str| absolute_path(checkout, key).exists();
+    if let Some(key) = renamed_to(&bookmark.path, bookmark.is_dir, renames) {
+        if exists(&key) {
+            return Some(key);
+        }
+    }
+    let name = bookmark.path.rsplit('/').next()?;
+    let found = find_by_name(checkout, name, bookmark.is_dir)?;
+    let rel = found.strip_prefix(checkout).ok()?;
+    Some(relative_key(rel))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BookmarksFile {
+    /// Bookmarks in order, by main checkout.
+    projects: BTreeMap<String, Vec<Bookmark>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    file: Option<PathBuf>,
+    projects: BTreeMap<String, Vec<Bookmark>>,
+}
+
+pub type BookmarksState = Arc<Mutex<Bookmarks>>;
+
+fn not_found(id: &str) -> String {
+    format!("Bookmark {} not found", id)
+}
+
+impl Bookmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let projects = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<BookmarksFile>(&contents) {
+                    Ok(file) => Some(file.projects),
+                    Err(e) => {
+                        log::warn!("failed to parse bookmarks: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            projects,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = BookmarksFile {
+            projects: self.projects.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)
+            .map_err(|e| format!("Failed to serialize bookmarks: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    /// Apply `change` to the bookmarks of `project` and save, or leave
+    /// them as they were when either fails.
+    fn update<T>(
+        &mut self,
+        project: &str,
+        change: impl FnOnce(&mut Vec<Bookmark>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let previous = self.projects.get(project).cloned();
+        let bookmarks = self.projects.entry(project.to_string()).or_default();
+        let result = change(bookmarks);
+        if bookmarks.is_empty() {
+            self.projects.remove(project);
+        }
+        let result = result.and_then(|result| self.save().map(|_| result));
+        if result.is_err() {
+            match previous {
+                Some(previous) => self.projects.insert(project.to_string(), previous),
+                None => self.projects.remove(project),
+            };
+        }
+        result
+    }
+
+    pub fn list(&self, project: &str) -> &[Bookmark] {
+        self.projects.get(project).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bookmark `path` at the end of the list, or return the bookmark it
+    /// already has.
+    pub fn add(
+        &mut self,
+        project: &str,
+        path: String,
+        is_dir: bool,
+        label: Option<String>,
+    ) -> Result<Bookmark, String> {
+        let label = clean_label(label)?;
+        self.update(project, |bookmarks| {
+            if let Some(existing) = bookmarks.iter().find(|b| b.path == path) {
+                return Ok(existing.clone());
+            }
+            let bookmark = Bookmark {
+                id: uuid::Uuid::new_v4().to_string(),
+                path,
+                label,
+                is_dir,
+            };
+            bookmarks.push(bookmark.clone());
+            Ok(bookmark)
+        })
+    }
+
+    pub fn set_label(
+        &mut self,
+        project: &str,
+        id: &str,
+        label: Option<String>,
+    ) -> Result<Bookmark, String> {
+        let label = clean_label(label)?;
+        self.update(project, |bookmarks| {
+            let bookmark = bookmarks
+                .iter_mut()
+                .find(|b| b.id == id)
+                .ok_or_else(|| not_found(id))?;
+            bookmark.label = label;
+            Ok(bookmark.clone())
+        })
+    }
+
+    pub fn remove(&mut self, project: &str, id: &str) -> Result<(), String> {
+        self.update(project, |bookmarks| {
+            let index = bookmarks
+                .iter()
+                .position(|b| b.id == id)
+                .ok_or_else(|| not_found(id))?;
+            bookmarks.remove(index);
+            Ok(())
+        })
+    }
+
+    /// Put the bookmarks `ids` names first, in that order; the others
+    /// follow in their current order.
+    pub fn reorder(&mut self, project: &str, ids: &[String]) -> Result<(), String> {
+        self.update(project, |bookmarks| {
+            if let Some(unknown) = ids
+                .iter()
+                .find(|id| !bookmarks.iter().any(|b| &b.id == *id))
+            {
+                return Err(not_found(unknown));
+            }
+            bookmarks.sort_by_key(|b| ids.iter().position(|id| *id == b.id).unwrap_or(ids.len()));
+            Ok(())
+        })
+    }
+
+    /// Point bookmarks at new paths, by id, e.g. from [`find_moved`].
+    pub fn relocate(&mut self, project: &str, moves: &[(String, String)]) -> Result<(), String> {
+        self.update(project, |bookmarks| {
+            for (id, path) in moves {
+                if let Some(bookmark) = bookmarks.iter_mut().find(|b| &b.id == id) {
+                    bookmark.path = path.clone();
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_crud_and_order_persist() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("bookmarks.json");
+        let mut store = Bookmarks::load(path.clone());
+        let web = store
+            .add(
+                "/src/kiri",
+                "packages/web".into(),
+                true,
+                Some(" Web ".into()),
+            )
+            .unwrap();
+        assert_eq!(web.label.as_deref(), Some("Web"));
+        let readme = store
+            .add("/src/kiri", "README.md".into(), false, None)
+            .unwrap();
+        let again = store
+            .add("/src/kiri", "README.md".into(), false, None)
+            .unwrap();
+        assert_eq!(again.id, readme.id);
+        assert!(store
+            .add("/src/kiri", "x".into(), false, Some("x".repeat(81)))
+            .is_err());
+
+        store
+            .reorder("/src/kiri", std::slice::from_ref(&readme.id))
+            .unwrap();
+        assert!(store.reorder("/src/kiri", &["nope".into()]).is_err());
+        store
+            .set_label("/src/kiri", &readme.id, Some("  ".into()))
+            .unwrap();
+
+        let mut store = Bookmarks::load(path);
+        let ids: Vec<_> = store.list("/src/kiri").iter().map(|b| &b.id).collect();
+        assert_eq!(ids, [&readme.id, &web.id]);
+        assert_eq!(store.list("/src/kiri")[0].label, None);
+        store.remove("/src/kiri", &readme.id).unwrap();
+        store.remove("/src/kiri", &web.id).unwrap();
+        assert!(store.list("/src/kiri").is_empty());
+        assert!(store.remove("/src/kiri", &web.id).is_err());
+        assert!(store.projects.is_empty());
+    }
+
+    #[test]
+    fn test_renamed_to_follows_files_and_directories() {
+        let renames = vec![
+            ("src/a.rs".to_string(), "src/b.rs".to_string()),
+            (
+                "packages/web/src/index.ts".to_string(),
+                "apps/web/src/index.ts".to_string(),
+            ),
+        ];
+        assert_eq!(
+            renamed_to("src/a.rs", false, &renames).as_deref(),
+            Some("src/b.rs")
+        );
+        assert_eq!(
+            renamed_to("packages/web", true, &renames).as_deref(),
+            Some("apps/web")
+        );
+        assert_eq!(renamed_to("packages/api", true, &renames), None);
+        assert_eq!(renamed_to("src/c.rs", false, &renames), None);
+    }
+
+    #[test]
+    fn test_find_moved_by_unique_name() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("apps/web/components")).unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg/components")).unwrap();
+        fs::write(root.join("apps/web/Button.tsx"), "").unwrap();
+        fs::write(root.join("apps/Card.tsx"), "").unwrap();
+        fs::write(root.join("apps/web/Card.tsx"), "").unwrap();
+
+        let bookmark = |path: &str, is_dir| Bookmark {
+            id: "1".into(),
+            path: path.into(),
+            label: None,
+            is_dir,
+        };
+        assert_eq!(
+            find_moved(root, &bookmark("web/components", true), &[]).as_deref(),
+            Some("apps/web/components")
+        );
+        assert_eq!(
+            find_moved(root, &bookmark("src/Button.tsx", false), &[]).as_deref(),
+            Some("apps/web/Button.tsx")
+        );
+        // Two candidates: no guess.
+        assert_eq!(
+            find_moved(root, &bookmark("src/Card.tsx", false), &[]),
+            None
+        );
+    }
+}
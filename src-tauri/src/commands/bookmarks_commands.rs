@@ -0,0 +1,197 @@
+//! Tauri commands for the bookmarks of bookmarks.rs.
+//!
+//! Commands take the checkout the window has open; bookmarks are shared
+//! by all checkouts of a repository, and each change is announced with a
+//! `bookmarks-changed` event carrying the main checkout's path. Listing
+//! repairs bookmarks whose path moved.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::auto_fetch::main_checkout;
+use super::bookmarks::{
+    absolute_path, bookmarks_file_path, find_moved, git_renames, relative_key, Bookmark, Bookmarks,
+    BookmarksState,
+};
+use super::error::{ErrorCode, KiriError};
+use super::fs_path::{display_path, input_path, resolve_within, strip_root};
+use super::latency::{run_blocking, run_blocking_coded};
+use super::lock_ext::LockExt;
+
+pub const BOOKMARKS_CHANGED_EVENT: &str = "bookmarks-changed";
+
+/// A bookmark as seen from one checkout.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkEntry {
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    pub absolute_path: String,
+    /// `false` when the path is gone and couldn't be found elsewhere.
+    pub exists: bool,
+}
+
+/// Read `~/.kiri/bookmarks.json` into the managed state. Call from setup.
+pub fn load_bookmarks(app: &AppHandle) {
+    let Some(path) = bookmarks_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<BookmarksState>() {
+        *state.lock_recover() = Bookmarks::load(path);
+    }
+}
+
+/// The key bookmarks of `checkout` are saved under: its main checkout.
+fn project_key(checkout: &Path) -> String {
+    display_path(&main_checkout(checkout).unwrap_or_else(|| checkout.to_path_buf()))
+}
+
+fn entry(checkout: &Path, bookmark: Bookmark) -> BookmarkEntry {
+    let path = absolute_path(checkout, &bookmark.path);
+    BookmarkEntry {
+        exists: path.exists(),
+        absolute_path: display_path(&path),
+        bookmark,
+    }
+}
+
+fn changed(app: &AppHandle, project: &str) {
+    let _ = app.emit(BOOKMARKS_CHANGED_EVENT, project);
+}
+
+/// Point bookmarks of `checkout` that no longer exist at where they
+/// moved, when that can be told. Returns whether any moved.
+fn repair(state: &BookmarksState, checkout: &Path, project: &str) -> Result<bool, String> {
+    let missing: Vec<Bookmark> = state
+        .lock_recover()
+        .list(project)
+        .iter()
+        .filter(|b| !absolute_path(checkout, &b.path).exists())
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(false);
+    }
+    let renames = git_renames(checkout);
+    let moves: Vec<(String, String)> = missing
+        .iter()
+        .filter_map(|b| Some((b.id.clone(), find_moved(checkout, b, &renames)?)))
+        .collect();
+    if moves.is_empty() {
+        return Ok(false);
+    }
+    for (id, path) in &moves {
+        log::info!("bookmark {} moved to {}", id, path);
+    }
+    state.lock_recover().relocate(project, &moves)?;
+    Ok(true)
+}
+
+/// Bookmarks of the project `project_path` belongs to, in order, with
+/// moved ones repaired first.
+#[tauri::command]
+pub async fn list_bookmarks(
+    app: AppHandle,
+    state: tauri::State<'_, BookmarksState>,
+    project_path: String,
+) -> Result<Vec<BookmarkEntry>, KiriError> {
+    let state = Arc::clone(&state);
+    run_blocking("list_bookmarks", move || {
+        let checkout = input_path(&project_path)?;
+        let project = project_key(&checkout);
+        if repair(&state, &checkout, &project)? {
+            changed(&app, &project);
+        }
+        let bookmarks = state.lock_recover().list(&project).to_vec();
+        Ok(bookmarks.into_iter().map(|b| entry(&checkout, b)).collect())
+    })
+    .await
+}
+
+/// Bookmark the file or directory at `path`, absolute or relative to
+/// `project_path`, at the end of the list. Bookmarking it again returns
+/// the existing bookmark.
+#[tauri::command]
+pub async fn add_bookmark(
+    app: AppHandle,
+    state: tauri::State<'_, BookmarksState>,
+    project_path: String,
+    path: String,
+    label: Option<String>,
+) -> Result<BookmarkEntry, KiriError> {
+    let state = Arc::clone(&state);
+    run_blocking_coded("add_bookmark", move || {
+        let checkout = input_path(&project_path)?;
+        let target = resolve_within(&path, &checkout)?;
+        if !target.exists() {
+            return Err(KiriError::new(
+                ErrorCode::NotFound,
+                format!("{} does not exist", display_path(&target)),
+            ));
+        }
+        let root = checkout
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", display_path(&checkout), e))?;
+        let rel = strip_root(&target, &root)
+            .filter(|rel| !rel.as_os_str().is_empty())
+            .ok_or("Choose a file or directory inside the project to bookmark")?;
+        let project = project_key(&checkout);
+        let bookmark =
+            state
+                .lock_recover()
+                .add(&project, relative_key(&rel), target.is_dir(), label)?;
+        changed(&app, &project);
+        Ok(entry(&checkout, bookmark))
+    })
+    .await
+}
+
+/// Set or clear (`None` or blank) the label of bookmark `id`.
+#[tauri::command]
+pub fn update_bookmark(
+    app: AppHandle,
+    state: tauri::State<'_, BookmarksState>,
+    project_path: String,
+    id: String,
+    label: Option<String>,
+) -> Result<BookmarkEntry, KiriError> {
+    let checkout = input_path(&project_path)?;
+    let project = project_key(&checkout);
+    let bookmark = state.lock_recover().set_label(&project, &id, label)?;
+    changed(&app, &project);
+    Ok(entry(&checkout, bookmark))
+}
+
+#[tauri::command]
+pub fn remove_bookmark(
+    app: AppHandle,
+    state: tauri::State<'_, BookmarksState>,
+    project_path: String,
+    id: String,
+) -> Result<(), KiriError> {
+    let project = project_key(&input_path(&project_path)?);
+    state.lock_recover().remove(&project, &id)?;
+    changed(&app, &project);
+    Ok(())
+}
+
+/// Move the bookmarks `ids` names to the top, in that order.
+#[tauri::command]
+pub fn reorder_bookmarks(
+    app: AppHandle,
+    state: tauri::State<'_, BookmarksState>,
+    project_path: String,
+    ids: Vec<String>,
+) -> Result<Vec<BookmarkEntry>, KiriError> {
+    let checkout = input_path(&project_path)?;
+    let project = project_key(&checkout);
+    let bookmarks = {
+        let mut store = state.lock_recover();
+        store.reorder(&project, &ids)?;
+        store.list(&project).to_vec()
+    };
+    changed(&app, &project);
+    Ok(bookmarks.into_iter().map(|b| entry(&checkout, b)).collect())
+}
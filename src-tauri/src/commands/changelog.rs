@@ -0,0 +1,448 @@
+//! Changelog from conventional commits (see conventional_commit.rs).
+//!
+//! Commits in a range, by default everything since the latest tag, are
+//! grouped by type into markdown. Only changes users notice are listed:
+//! features, fixes, performance work, reverts and anything marked
+//! breaking. Two presets shape the result:
+//!
+//! - `keep_a_changelog`: `## [1.2.0] - 2026-10-16` with Added, Changed and
+//!   Fixed sections, as in kiri's own CHANGELOG.md.
+//! - `conventional`: `## 1.2.0 (2026-10-16)` with Features, Bug Fixes and
+//!   the other conventional-changelog sections, each entry with its commit.
+//!
+//! Writing updates CHANGELOG.md in place: the generated notes replace the
+//! body of the unreleased section, or become a new section under it when a
+//! version is given.
+
+use git2::{DescribeFormatOptions, DescribeOptions, Repository};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::clock::now_ms;
+use super::conventional_commit::parse_header;
+use super::file_io::write_file_contents_atomic;
+use super::fs_path::path_to_wire;
+
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangelogPreset {
+    #[default]
+    KeepAChangelog,
+    Conventional,
+}
+
+/// One listed commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangelogEntry {
+    #[serde(rename = "type")]
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub description: String,
+    pub breaking: bool,
+    pub short_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Changelog {
+    /// The range read, e.g. `v0.6.1..HEAD`
+    pub range: String,
+    pub markdown: String,
+    pub entries: Vec<ChangelogEntry>,
+    /// Commits that aren't conventional or aren't listed (chore, docs, ..)
+    pub skipped: usize,
+    /// Where the changelog was written, if it was
+    pub written: Option<String>,
+}
+
+impl ChangelogEntry {
+    fn is_listed(&self) -> bool {
+        self.breaking
+            || matches!(
+                self.commit_type.as_str(),
+                "feat" | "fix" | "perf" | "revert"
+            )
+    }
+}
+
+/// Section titles of `preset`, in order, and the section an entry goes in.
+fn sections(preset: ChangelogPreset) -> &'static [&'static str] {
+    match preset {
+        ChangelogPreset::KeepAChangelog => &["Added", "Changed", "Fixed"],
+        ChangelogPreset::Conventional => &[
+            "⚠ BREAKING CHANGES",
+            "Features",
+            "Bug Fixes",
+            "Performance Improvements",
+            "Reverts",
+            "Other Changes",
+        ],
+    }
+}
+
+fn section_of(preset: ChangelogPreset, entry: &ChangelogEntry) -> &'static str {
+    let commit_type = entry.commit_type.as_str();
+    match preset {
+        ChangelogPreset::KeepAChangelog => match commit_type {
+            "feat" => "Added",
+            "fix" => "Fixed",
+            _ => "Changed",
+        },
+        ChangelogPreset::Conventional if entry.breaking => "⚠ BREAKING CHANGES",
+        ChangelogPreset::Conventional => match commit_type {
+            "feat" => "Features",
+            "fix" => "Bug Fixes",
+            "perf" => "Performance Improvements",
+            "revert" => "Reverts",
+            _ => "Other Changes",
+        },
+    }
+}
+
+fn entry_line(preset: ChangelogPreset, entry: &ChangelogEntry) -> String {
+    let scope = entry
+        .scope
+        .as_ref()
+        .map(|scope| format!("**{}:** ", scope))
+        .unwrap_or_default();
+    match preset {
+        ChangelogPreset::KeepAChangelog => {
+            let breaking = if entry.breaking { "**Breaking:** " } else { "" };
+            format!("- {}{}{}", breaking, scope, entry.description)
+        }
+        ChangelogPreset::Conventional => {
+            format!("* {}{} ({})", scope, entry.description, entry.short_id)
+        }
+    }
+}
+
+/// The unreleased section's heading in `preset`.
+fn unreleased_heading(preset: ChangelogPreset) -> &'static str {
+    match preset {
+        ChangelogPreset::KeepAChangelog => "## [Unreleased]",
+        ChangelogPreset::Conventional => "## Unreleased",
+    }
+}
+
+fn version_heading(preset: ChangelogPreset, version: &str, date: &str) -> String {
+    let version = version.trim_start_matches('v');
+    match preset {
+        ChangelogPreset::KeepAChangelog => format!("## [{}] - {}", version, date),
+        ChangelogPreset::Conventional => format!("## {} ({})", version, date),
+    }
+}
+
+/// The sections for `entries`, without a heading. Entries keep their
+/// order, newest first.
+pub fn render(preset: ChangelogPreset, entries: &[ChangelogEntry]) -> String {
+    let mut out = String::new();
+    for section in sections(preset) {
+        let lines: Vec<String> = entries
+            .iter()
+            .filter(|entry| section_of(preset, entry) == *section)
+            .map(|entry| entry_line(preset, entry))
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("### {}\n\n{}\n", section, lines.join("\n")));
+    }
+    out
+}
+
+/// `existing` (the current CHANGELOG.md, if any) with `notes` in place:
+/// as the body of the unreleased section, or, with `version`, as a new
+/// section under an emptied unreleased one.
+pub fn update_changelog(
+    existing: Option<&str>,
+    preset: ChangelogPreset,
+    notes: &str,
+    version: Option<(&str, &str)>,
+) -> String {
+    let unreleased = unreleased_heading(preset);
+    let section = match version {
+        Some((version, date)) => format!(
+            "{}\n\n{}\n\n{}",
+            unreleased,
+            version_heading(preset, version, date),
+            notes
+        ),
+        None => format!("{}\n\n{}", unreleased, notes),
+    };
+    let Some(existing) = existing else {
+        return format!("# Changelog\n\n{}", section);
+    };
+    let lines: Vec<&str> = existing.lines().collect();
+    let start = lines.iter().position(|line| line.trim_end() == unreleased);
+    let start = start.or_else(|| lines.iter().position(|line| line.starts_with("## ")));
+    let Some(start) = start else {
+        return format!("{}\n\n{}", existing.trim_end(), section);
+    };
+    let replaces_unreleased = lines[start].trim_end() == unreleased;
+    let end = if replaces_unreleased {
+        lines[start + 1..]
+            .iter()
+            .position(|line| line.starts_with("## "))
+            .map_or(lines.len(), |offset| start + 1 + offset)
+    } else {
+        start
+    };
+    let mut out = lines[..start].join("\n");
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&section);
+    if end < lines.len() {
+        out.push('\n');
+        out.push_str(&lines[end..].join("\n"));
+        out.push('\n');
+    }
+    out
+}
+
+/// `YYYY-MM-DD` of a Unix timestamp, in UTC.
+fn utc_date(secs: u64) -> String {
+    // Howard Hinnant's civil_from_days.
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The latest tag reachable from HEAD, if any.
+fn latest_tag(repo: &Repository) -> Option<String> {
+    let mut options = DescribeOptions::new();
+    options.describe_tags();
+    let describe = repo.describe(&options).ok()?;
+    let mut format = DescribeFormatOptions::new();
+    format.abbreviated_size(0);
+    describe.format(Some(&format)).ok()
+}
+
+/// `range` as a revwalk range: `a..b` as given, a single revision as
+/// everything after it up to HEAD, and nothing as everything since the
+/// latest tag (or all of HEAD when there is none).
+fn resolve_range(repo: &Repository, range: Option<&str>) -> String {
+    match range.map(str::trim).filter(|r| !r.is_empty()) {
+        Some(range) if range.contains("..") => range.to_string(),
+        Some(since) => format!("{}..HEAD", since),
+        None => match latest_tag(repo) {
+            Some(tag) => format!("{}..HEAD", tag),
+            None => "HEAD".to_string(),
+        },
+    }
+}
+
+/// Conventional commits in `range` of `repo`, newest first, and the number
+/// of commits left out.
+fn collect_entries(repo: &Repository, range: &str) -> Result<(Vec<ChangelogEntry>, usize), String> {
+    let mut revwalk = repo
+        .revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    if range.contains("..") {
+        revwalk.push_range(range)
+    } else {
+        revwalk.push_head()
+    }
+    .map_err(|e| format!("Invalid range {}: {}", range, e))?;
+    revwalk
+        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for oid in revwalk {
+        let oid = oid.map_err(|e| format!("Failed to walk history: {}", e))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("Failed to read commit {}: {}", oid, e))?;
+        let message = commit.message().unwrap_or_default();
+        let header = (commit.parent_count() <= 1)
+            .then(|| parse_header(message.lines().next().unwrap_or_default()))
+            .flatten();
+        let Some(header) = header.filter(|h| !h.description.trim().is_empty()) else {
+            skipped += 1;
+            continue;
+        };
+        let entry = ChangelogEntry {
+            breaking: header.breaking
+                || message.lines().any(|line| {
+                    line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+                }),
+            commit_type: header.commit_type.to_lowercase(),
+            scope: header.scope.filter(|s| !s.trim().is_empty()),
+            description: header.description.trim().to_string(),
+            short_id: oid.to_string()[..7].to_string(),
+        };
+        if entry.is_listed() {
+            entries.push(entry);
+        } else {
+            skipped += 1;
+        }
+    }
+    Ok((entries, skipped))
+}
+
+/// The changelog of `range` in `repo_path`. With `write` it is also put
+/// into the repository's CHANGELOG.md, under `version` if given.
+pub fn generate_changelog(
+    repo_path: &Path,
+    range: Option<&str>,
+    preset: ChangelogPreset,
+    version: Option<&str>,
+    write: bool,
+) -> Result<Changelog, String> {
+    let repo =
+        Repository::discover(repo_path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let range = resolve_range(&repo, range);
+    let (entries, skipped) = collect_entries(&repo, &range)?;
+    let notes = render(preset, &entries);
+    let today = utc_date(now_ms() / 1000);
+    let version = version.map(str::trim).filter(|v| !v.is_empty());
+    let markdown = match version {
+        Some(version) => format!("{}\n\n{}", version_heading(preset, version, &today), notes),
+        None => format!("{}\n\n{}", unreleased_heading(preset), notes),
+    };
+
+    let written = if write {
+        let root = repo
+            .workdir()
+            .ok_or("Failed to write changelog: the repository is bare")?;
+        let path = root.join(CHANGELOG_FILE);
+        let existing = match std::fs::read_to_string(&path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let updated = update_changelog(
+            existing.as_deref(),
+            preset,
+            &notes,
+            version.map(|v| (v, today.as_str())),
+        );
+        write_file_contents_atomic(&path, updated.as_bytes())?;
+        Some(path_to_wire(&path))
+    } else {
+        None
+    };
+
+    Ok(Changelog {
+        range,
+        markdown,
+        entries,
+        skipped,
+        written,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn commit(repo: &Repository, message: &str) {
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_generate_since_latest_tag() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, "feat: initial release");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.tag_lightweight("v0.1.0", head.as_object(), false)
+            .unwrap();
+        commit(&repo, "feat(git): stash support");
+        commit(&repo, "fix: crash on empty repo");
+        commit(&repo, "chore: bump deps");
+        commit(&repo, "Update readme");
+        commit(
+            &repo,
+            "refactor(config)!: move settings\n\nBREAKING CHANGE: config path moved",
+        );
+
+        let changelog = generate_changelog(
+            dir.path(),
+            None,
+            ChangelogPreset::KeepAChangelog,
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(changelog.range, "v0.1.0..HEAD");
+        assert_eq!(changelog.skipped, 2);
+        assert_eq!(
+            changelog.markdown,
+            "## [Unreleased]\n\n### Added\n\n- **git:** stash support\n\n### Changed\n\n- **Breaking:** **config:** move settings\n\n### Fixed\n\n- crash on empty repo\n"
+        );
+
+        let conventional = generate_changelog(
+            dir.path(),
+            Some("v0.1.0"),
+            ChangelogPreset::Conventional,
+            Some("v0.2.0"),
+            true,
+        )
+        .unwrap();
+        let heading = conventional.markdown.lines().next().unwrap();
+        assert!(heading.starts_with("## 0.2.0 ("));
+        assert!(conventional
+            .markdown
+            .contains("### ⚠ BREAKING CHANGES\n\n* **config:** move settings ("));
+        let written = std::fs::read_to_string(dir.path().join(CHANGELOG_FILE)).unwrap();
+        assert!(written.starts_with("# Changelog\n\n## Unreleased\n\n## 0.2.0 ("));
+    }
+
+    #[test]
+    fn test_update_changelog() {
+        let existing = "# Changelog\n\nIntro.\n\n## [Unreleased]\n\n- old note\n\n## [0.1.0] - 2026-01-01\n\n### Added\n\n- first\n";
+        let notes = "### Fixed\n\n- crash\n";
+        assert_eq!(
+            update_changelog(Some(existing), ChangelogPreset::KeepAChangelog, notes, None),
+            "# Changelog\n\nIntro.\n\n## [Unreleased]\n\n### Fixed\n\n- crash\n\n## [0.1.0] - 2026-01-01\n\n### Added\n\n- first\n"
+        );
+        assert_eq!(
+            update_changelog(
+                Some(existing),
+                ChangelogPreset::KeepAChangelog,
+                notes,
+                Some(("0.2.0", "2026-02-01"))
+            ),
+            "# Changelog\n\nIntro.\n\n## [Unreleased]\n\n## [0.2.0] - 2026-02-01\n\n### Fixed\n\n- crash\n\n## [0.1.0] - 2026-01-01\n\n### Added\n\n- first\n"
+        );
+        // Without an unreleased section the notes go above the newest one.
+        assert_eq!(
+            update_changelog(
+                Some("# Changelog\n\n## 0.1.0 (2026-01-01)\n\n* first\n"),
+                ChangelogPreset::Conventional,
+                "### Bug Fixes\n\n* crash (abc1234)\n",
+                None
+            ),
+            "# Changelog\n\n## Unreleased\n\n### Bug Fixes\n\n* crash (abc1234)\n\n## 0.1.0 (2026-01-01)\n\n* first\n"
+        );
+
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(1_792_108_800), "2026-10-16");
+    }
+}
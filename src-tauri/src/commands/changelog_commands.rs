@@ -0,0 +1,29 @@
+//! Tauri command for changelog generation; see changelog.rs.
+
+use super::changelog::{self, Changelog, ChangelogPreset};
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+
+/// Changelog of `range` (default: since the latest tag). With `write`,
+/// CHANGELOG.md is updated too: under `version` if given, else in the
+/// unreleased section.
+#[tauri::command]
+pub async fn generate_changelog(
+    repo_path: String,
+    range: Option<String>,
+    preset: Option<ChangelogPreset>,
+    version: Option<String>,
+    write: Option<bool>,
+) -> Result<Changelog, KiriError> {
+    run_blocking("generate_changelog", move || {
+        changelog::generate_changelog(
+            &normalize_input_path(&repo_path),
+            range.as_deref(),
+            preset.unwrap_or_default(),
+            version.as_deref(),
+            write.unwrap_or(false),
+        )
+    })
+    .await
+}
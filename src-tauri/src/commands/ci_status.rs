@@ -0,0 +1,161 @@
+//! Cache of CI status per branch, for build badges on worktree cards.
+//!
+//! Status is fetched from the code host (see code_host.rs) for the local
+//! head of a branch and cached per repository and branch, so every
+//! worktree of a repo shares it. An entry goes stale when the branch head
+//! moves, or after [`SETTLED_TTL`] (finished builds) or [`PENDING_TTL`]
+//! (builds still running). While a build is pending it is polled in the
+//! background, and a `ci-status-changed` event goes out whenever a fetch
+//! returns something different. Time is passed in; the fetching and the
+//! poller live in ci_status_commands.rs.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::code_host::{BranchStatus, CheckState, RemoteRepo};
+
+/// How long a finished (or check-less) status is reused.
+pub const SETTLED_TTL: Duration = Duration::from_secs(60);
+
+/// How long a pending status is reused, and the background poll interval.
+pub const PENDING_TTL: Duration = Duration::from_secs(15);
+
+/// Stop polling a build that is still pending after this long.
+pub const MAX_POLL: Duration = Duration::from_secs(2 * 60 * 60);
+
+pub fn ttl(state: CheckState) -> Duration {
+    match state {
+        CheckState::Pending => PENDING_TTL,
+        _ => SETTLED_TTL,
+    }
+}
+
+/// Cache key for a hosted repository, e.g. `github.com/o/r`.
+pub fn repo_key(remote: &RemoteRepo) -> String {
+    format!("{}/{}", remote.host, remote.path())
+}
+
+struct Entry {
+    status: BranchStatus,
+    fetched: Instant,
+}
+
+#[derive(Default)]
+pub struct CiStatusCache {
+    entries: HashMap<(String, String), Entry>,
+    polling: HashSet<(String, String)>,
+}
+
+fn key(repo: &str, branch: &str) -> (String, String) {
+    (repo.to_string(), branch.to_string())
+}
+
+impl CiStatusCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached status of `branch`, if it was fetched for `head_sha` and
+    /// hasn't expired.
+    pub fn fresh(
+        &self,
+        repo: &str,
+        branch: &str,
+        head_sha: &str,
+        now: Instant,
+    ) -> Option<BranchStatus> {
+        let entry = self.entries.get(&key(repo, branch))?;
+        let expired = now.saturating_duration_since(entry.fetched) >= ttl(entry.status.check_state);
+        (entry.status.head_sha == head_sha && !expired).then(|| entry.status.clone())
+    }
+
+    /// Cache a fetched status. Returns whether it differs from the one it
+    /// replaces, i.e. whether listeners should hear about it.
+    pub fn store(&mut self, repo: &str, status: BranchStatus, now: Instant) -> bool {
+        let key = key(repo, &status.branch);
+        let changed = self
+            .entries
+            .get(&key)
+            .map_or(true, |previous| previous.status != status);
+        self.entries.insert(
+            key,
+            Entry {
+                status,
+                fetched: now,
+            },
+        );
+        changed
+    }
+
+    /// Claim the background poll for `branch`. Returns false if one is
+    /// already running.
+    pub fn begin_poll(&mut self, repo: &str, branch: &str) -> bool {
+        self.polling.insert(key(repo, branch))
+    }
+
+    pub fn end_poll(&mut self, repo: &str, branch: &str) {
+        self.polling.remove(&key(repo, branch));
+    }
+}
+
+pub type CiStatusState = Arc<Mutex<CiStatusCache>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: Duration = Duration::from_secs(1);
+
+    fn status(branch: &str, sha: &str, check_state: CheckState) -> BranchStatus {
+        BranchStatus {
+            branch: branch.to_string(),
+            head_sha: sha.to_string(),
+            checks: Vec::new(),
+            check_state,
+            pull_request: None,
+        }
+    }
+
+    #[test]
+    fn test_fresh_expires_by_state() {
+        let mut cache = CiStatusCache::new();
+        let start = Instant::now();
+        cache.store("r", status("main", "a", CheckState::Success), start);
+        cache.store("r", status("dev", "b", CheckState::Pending), start);
+
+        let later = start + 30 * SEC;
+        assert!(cache.fresh("r", "main", "a", later).is_some());
+        assert!(cache.fresh("r", "dev", "b", later).is_none());
+        assert!(cache.fresh("r", "main", "a", start + SETTLED_TTL).is_none());
+    }
+
+    #[test]
+    fn test_fresh_requires_same_head_and_repo() {
+        let mut cache = CiStatusCache::new();
+        let now = Instant::now();
+        cache.store("r", status("main", "a", CheckState::Success), now);
+        assert!(cache.fresh("r", "main", "b", now).is_none());
+        assert!(cache.fresh("other", "main", "a", now).is_none());
+    }
+
+    #[test]
+    fn test_store_reports_changes_only() {
+        let mut cache = CiStatusCache::new();
+        let now = Instant::now();
+        assert!(cache.store("r", status("main", "a", CheckState::Pending), now));
+        assert!(!cache.store("r", status("main", "a", CheckState::Pending), now + SEC));
+        assert!(cache.store("r", status("main", "a", CheckState::Failure), now + SEC));
+        assert!(cache.store("r", status("main", "b", CheckState::Failure), now + SEC));
+    }
+
+    #[test]
+    fn test_one_poll_per_branch() {
+        let mut cache = CiStatusCache::new();
+        assert!(cache.begin_poll("r", "main"));
+        assert!(!cache.begin_poll("r", "main"));
+        assert!(cache.begin_poll("r", "dev"));
+        cache.end_poll("r", "main");
+        assert!(cache.begin_poll("r", "main"));
+    }
+}
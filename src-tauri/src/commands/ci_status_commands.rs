@@ -0,0 +1,125 @@
+//! Tauri command and background polling for CI status; the cache lives
+//! in ci_status.rs.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+use super::ci_status::{repo_key, CiStatusState, MAX_POLL, PENDING_TTL};
+use super::code_host::{self, BranchStatus, CheckState, RemoteRepo};
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::lock_ext::LockExt;
+use super::performance::CommandTimer;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CiStatusChangedEvent {
+    /// Repository key, e.g. `github.com/o/r`
+    pub repo: String,
+    pub branch: String,
+    pub status: BranchStatus,
+}
+
+/// Fetch the status of `branch` at `head_sha`, cache it, and emit
+/// `ci-status-changed` if it differs from the cached one.
+async fn refresh(
+    app: &AppHandle,
+    state: &CiStatusState,
+    remote: &RemoteRepo,
+    branch: &str,
+    head_sha: &str,
+) -> Result<BranchStatus, KiriError> {
+    let status = code_host::signed_in_host(remote)?
+        .branch_status(remote, branch, head_sha)
+        .await?;
+    let repo = repo_key(remote);
+    let changed = state
+        .lock_recover()
+        .store(&repo, status.clone(), Instant::now());
+    if changed {
+        let _ = app.emit(
+            "ci-status-changed",
+            CiStatusChangedEvent {
+                repo,
+                branch: branch.to_string(),
+                status: status.clone(),
+            },
+        );
+    }
+    Ok(status)
+}
+
+/// Re-fetch a pending build every [`PENDING_TTL`] until it settles, the
+/// branch can no longer be read, or [`MAX_POLL`] passes. Follows the
+/// branch if its head moves meanwhile.
+fn poll_pending(
+    app: AppHandle,
+    state: CiStatusState,
+    path: PathBuf,
+    remote: RemoteRepo,
+    branch: String,
+) {
+    let repo = repo_key(&remote);
+    if !state.lock_recover().begin_poll(&repo, &branch) {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        while started.elapsed() < MAX_POLL {
+            tokio::time::sleep(PENDING_TTL).await;
+            let head_sha = match code_host::branch_head(&path, &branch) {
+                Ok(sha) => sha,
+                Err(_) => break,
+            };
+            match refresh(&app, &state, &remote, &branch, &head_sha).await {
+                Ok(status) if status.check_state == CheckState::Pending => {}
+                Ok(_) => break,
+                Err(e) => {
+                    log::warn!("CI status poll for {} failed: {}", branch, e);
+                    break;
+                }
+            }
+        }
+        state.lock_recover().end_poll(&repo, &branch);
+    });
+}
+
+/// CI status of the local head of `branch` (default: the branch checked
+/// out at `repo_path`). Cached per repository and branch; `force` skips
+/// the cache. Pending builds keep being polled, and every change is
+/// announced with a `ci-status-changed` event.
+#[tauri::command]
+pub async fn get_ci_status(
+    app: AppHandle,
+    state: tauri::State<'_, CiStatusState>,
+    repo_path: String,
+    branch: Option<String>,
+    force: Option<bool>,
+) -> Result<BranchStatus, KiriError> {
+    let _timer = CommandTimer::start("get_ci_status");
+    let path = normalize_input_path(&repo_path);
+    let remote = code_host::origin_remote(&path)?;
+    let branch = match branch {
+        Some(branch) => branch,
+        None => code_host::current_branch(&path)?,
+    };
+    let head_sha = code_host::branch_head(&path, &branch)?;
+    if !force.unwrap_or(false) {
+        let repo = repo_key(&remote);
+        let cached = state
+            .lock_recover()
+            .fresh(&repo, &branch, &head_sha, Instant::now());
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+    }
+
+    let state: CiStatusState = Arc::clone(&state);
+    let status = refresh(&app, &state, &remote, &branch, &head_sha).await?;
+    if status.check_state == CheckState::Pending {
+        poll_pending(app, state, path, remote, branch);
+    }
+    Ok(status)
+}
@@ -3,14 +3,16 @@
 //! invoke it as `kiri`.
 //!
 //! Two consumers:
-//! 1. [`ensure_installed`] — run once at app startup (best-effort; logs
-//!    on failure but does not abort).
+//! 1. [`ensure_installed_once`] — started in the background at app
+//!    startup and awaited by the first terminal (best-effort; logs on
+//!    failure but does not abort).
 //! 2. [`kiri_bin_dir`] / [`socket_path_for`] — pure path helpers,
 //!    re-exported from [`crate::commands::cli_install_paths`] for
 //!    backwards compatibility and used by the PTY env injector in
 //!    [`crate::commands::terminal::build_shell_command`].
 
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use tauri::{AppHandle, Manager};
 
 // Re-export the pure path helpers so existing call sites
@@ -83,3 +85,16 @@ pub fn ensure_installed(app: &AppHandle) -> std::io::Result<Option<PathBuf>> {
     Ok(Some(dest))
 }
 
+/// Run [`ensure_installed`] at most once per process. Callers that arrive
+/// while the install is in progress wait for it, so a terminal never
+/// starts before `kiri` is on its PATH.
+pub fn ensure_installed_once(app: &AppHandle) {
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+    INSTALLED.get_or_init(|| {
+        super::startup::time_deferred("cli_install", || {
+            if let Err(e) = ensure_installed(app) {
+                log::warn!("failed to install kiri CLI: {e}");
+            }
+        })
+    });
+}
@@ -25,6 +25,8 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 
+use super::error::KiriError;
+
 pub struct CliServerHandle {
     pub socket_path: PathBuf,
     pub label: String,
@@ -268,7 +270,7 @@ pub fn cli_resolve_pending(
     label: String,
     request_id: String,
     payload: serde_json::Value,
-) -> Result<bool, String> {
+) -> Result<bool, KiriError> {
     let map = registry.handles.lock().map_err(|e| e.to_string())?;
     let handle = map
         .get(&label)
@@ -281,7 +283,7 @@ pub fn cli_update_pane_map(
     registry: tauri::State<'_, CliServerRegistryState>,
     label: String,
     panes: Vec<pane_map::PaneEntry>,
-) -> Result<(), String> {
+) -> Result<(), KiriError> {
     let map = registry.handles.lock().map_err(|e| e.to_string())?;
     let handle = map
         .get(&label)
@@ -109,7 +109,6 @@ pub async fn dispatch_line(ctx: &DispatchContext, line: &str) -> Vec<Response> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::Mutex as StdMutex;
     use std::time::Duration;
 
     fn make_ctx(terminals: TerminalState, bus: TerminalOutputBusState) -> DispatchContext {
@@ -127,9 +126,7 @@ mod tests {
 
     #[tokio::test]
     async fn ensure_subscribed_pushes_published_bytes_to_ring_buffer() {
-        let terminals: TerminalState = Arc::new(StdMutex::new(
-            crate::commands::terminal::TerminalManager::new(),
-        ));
+        let terminals: TerminalState = Arc::new(crate::commands::terminal::TerminalManager::new());
         let bus: TerminalOutputBusState = Arc::new(TerminalOutputBus::new());
         let ctx = make_ctx(terminals, bus.clone());
 
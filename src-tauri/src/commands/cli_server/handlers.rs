@@ -2,8 +2,10 @@
 
 use super::dispatch::DispatchContext;
 use super::run_logic::{extract_output, tail_lines, Sentinel};
-use super::signals::{now_ms, Signal, MAX_SIGNAL_WAIT_SECS};
-use crate::commands::lock_ext::LockExt;
+use super::signals::{Signal, MAX_SIGNAL_WAIT_SECS};
+use crate::commands::clock::now_ms;
+use crate::commands::fs_path::path_to_wire;
+use crate::commands::lock_ext::{LockExt, RwLockExt};
 use kiri_cli_proto::{ErrorCode, PaneRef, Request, Response, SignalTarget, SplitDirection};
 use tauri::Emitter;
 use tokio::sync::broadcast;
@@ -77,7 +79,7 @@ fn project_path_for_label(app: &tauri::AppHandle, label: &str) -> Option<String>
     use crate::commands::window::WindowRegistryState;
     use tauri::Manager;
     let registry = app.try_state::<WindowRegistryState>()?;
-    let guard = registry.lock().ok()?;
+    let guard = registry.read().ok()?;
     guard.get_path_for_label(label).cloned()
 }
 
@@ -121,18 +123,13 @@ fn process_info_for(
     state: &crate::commands::terminal::TerminalState,
     id: u32,
 ) -> (String, u64, bool) {
-    // Phase 1: hold the TerminalState mutex only for the cheap lookup.
-    // Sysinfo's full process scan is slow (50–200ms on macOS) and we
-    // must not block create_terminal/write_terminal/etc. for that long.
+    // Phase 1: the cheap lookup. Sysinfo's full process scan is slow
+    // (50–200ms on macOS), so it runs without holding the terminal.
     let shell_pid = {
-        let mut manager = match state.lock() {
-            Ok(g) => g,
-            Err(_) => return ("Terminal".into(), 0, false),
-        };
-        let Some(instance) = manager.instances.get_mut(&id) else {
+        let Some(instance) = state.get(id) else {
             return ("Terminal".into(), 0, false);
         };
-        if matches!(instance.child.try_wait(), Ok(Some(_))) {
+        if matches!(instance.is_running(), Ok(false)) {
             return ("Terminal".into(), 0, false);
         }
         match instance.shell_pid {
@@ -149,13 +146,11 @@ fn process_info_for(
 }
 
 fn cwd_for(state: &crate::commands::terminal::TerminalState, id: u32) -> Option<String> {
-    // Same locking discipline as process_info_for: extract pid under
-    // the lock, release, then call into get_process_cwd which does its
-    // own (slow) /proc lookup.
+    // Same discipline as process_info_for: extract the pid, then call
+    // into get_process_cwd which does its own (slow) /proc lookup.
     let pid = {
-        let mut manager = state.lock().ok()?;
-        let instance = manager.instances.get_mut(&id)?;
-        if matches!(instance.child.try_wait(), Ok(Some(_))) {
+        let instance = state.get(id)?;
+        if matches!(instance.is_running(), Ok(false)) {
             return None;
         }
         instance.shell_pid?
@@ -185,16 +180,10 @@ async fn send(ctx: &DispatchContext, p: PaneRef, data: String, submit: bool) ->
     //
     // The shell-pid lookup is slow (~50–200ms) so we do it before
     // taking the terminal write lock.
-    let shell_pid = {
-        let manager = match ctx.terminals.lock() {
-            Ok(g) => g,
-            Err(_) => return internal("terminal state poisoned"),
-        };
-        let Some(instance) = manager.instances.get(&pane.terminal_id) else {
-            return pane_not_found(p);
-        };
-        instance.shell_pid
+    let Some(instance) = ctx.terminals.get(pane.terminal_id) else {
+        return pane_not_found(p);
     };
+    let shell_pid = instance.shell_pid;
     let separate_submit = submit
         && shell_pid
             .map(is_ai_process_for_shell_pid)
@@ -205,21 +194,8 @@ async fn send(ctx: &DispatchContext, p: PaneRef, data: String, submit: bool) ->
         data.as_bytes()
     };
 
-    {
-        let mut manager = match ctx.terminals.lock() {
-            Ok(g) => g,
-            Err(_) => return internal("terminal state poisoned"),
-        };
-        let Some(instance) = manager.instances.get_mut(&pane.terminal_id) else {
-            return pane_not_found(p);
-        };
-        use std::io::Write;
-        if let Err(e) = instance.writer.write_all(body) {
-            return pty_error(format!("write failed: {e}"));
-        }
-        if let Err(e) = instance.writer.flush() {
-            return pty_error(format!("flush failed: {e}"));
-        }
+    if let Err(e) = instance.write(body) {
+        return pty_error(format!("write failed: {e}"));
     }
 
     let submitted = if separate_submit {
@@ -228,20 +204,12 @@ async fn send(ctx: &DispatchContext, p: PaneRef, data: String, submit: bool) ->
         // separate write_all call the bytes can still be read together
         // and treated as one paste.
         tokio::time::sleep(Duration::from_millis(80)).await;
-        let mut manager = match ctx.terminals.lock() {
-            Ok(g) => g,
-            Err(_) => return internal("terminal state poisoned"),
-        };
-        let Some(instance) = manager.instances.get_mut(&pane.terminal_id) else {
+        let Some(instance) = ctx.terminals.get(pane.terminal_id) else {
             return pane_not_found(p);
         };
-        use std::io::Write;
-        if let Err(e) = instance.writer.write_all(b"\r") {
+        if let Err(e) = instance.write(b"\r") {
             return pty_error(format!("submit write failed: {e}"));
         }
-        if let Err(e) = instance.writer.flush() {
-            return pty_error(format!("flush failed: {e}"));
-        }
         true
     } else {
         false
@@ -325,14 +293,10 @@ async fn run(
     // Busy-check: if the shell currently has child processes, refuse.
     {
         let pid_opt = {
-            let mut manager = match ctx.terminals.lock() {
-                Ok(g) => g,
-                Err(_) => return internal("terminal state poisoned"),
-            };
-            let Some(instance) = manager.instances.get_mut(&pane.terminal_id) else {
+            let Some(instance) = ctx.terminals.get(pane.terminal_id) else {
                 return pane_not_found(p);
             };
-            if matches!(instance.child.try_wait(), Ok(Some(_))) {
+            if matches!(instance.is_running(), Ok(false)) {
                 return pty_error("shell exited".into());
             }
             instance.shell_pid
@@ -350,6 +314,10 @@ async fn run(
         }
     }
 
+    // Where the command runs, for the command history.
+    let cwd = cwd_for(&ctx.terminals, pane.terminal_id).unwrap_or_default();
+    let started_at = std::time::Instant::now();
+
     let nonce = format!("{:08x}", rand_nonce());
     let sentinel = Sentinel::new(nonce);
     let payload = sentinel.payload(&cmd);
@@ -358,18 +326,12 @@ async fn run(
     let mut rx: broadcast::Receiver<Vec<u8>> = ctx.bus.subscribe(pane.terminal_id);
 
     {
-        use std::io::Write;
-        let mut manager = match ctx.terminals.lock() {
-            Ok(g) => g,
-            Err(_) => return internal("terminal state poisoned"),
-        };
-        let Some(instance) = manager.instances.get_mut(&pane.terminal_id) else {
+        let Some(instance) = ctx.terminals.get(pane.terminal_id) else {
             return pane_not_found(p);
         };
-        if let Err(e) = instance.writer.write_all(payload.as_bytes()) {
+        if let Err(e) = instance.write(payload.as_bytes()) {
             return pty_error(format!("write failed: {e}"));
         }
-        let _ = instance.writer.flush();
     }
 
     let collect = async {
@@ -407,6 +369,18 @@ async fn run(
             Err(_) => (None, String::new(), true, false),
         };
 
+    // A command still running when the wait timed out hasn't finished yet.
+    if let (Some(app), false) = (ctx.app.as_ref(), timed_out) {
+        crate::commands::command_history_commands::record_command(
+            app,
+            crate::commands::command_history::CommandOrigin::Terminal,
+            &cmd,
+            &cwd,
+            exit_code,
+            started_at.elapsed(),
+        );
+    }
+
     let cursor = ctx
         .buffers
         .get(pane.terminal_id)
@@ -840,13 +814,13 @@ async fn open_window(ctx: &DispatchContext, dir: String, force_new: bool) -> Res
             detail: None,
         };
     }
-    let project = canonical.to_string_lossy().to_string();
+    let project = path_to_wire(&canonical);
 
     // A window may already be open for this path. Reuse it unless the
     // caller forced a new one or the window has since been closed.
     let existing_label = {
         let registry = app.state::<WindowRegistryState>();
-        let guard = registry.lock_recover();
+        let guard = registry.read_recover();
         guard.get_label_for_path(&project).cloned()
     };
     let focus_label = match (force_new, existing_label) {
@@ -1044,11 +1018,11 @@ mod tests {
     use crate::commands::terminal::{
         TerminalManager, TerminalOutputBus, TerminalOutputBusState, TerminalState,
     };
-    use std::sync::{Arc, Mutex as StdMutex};
+    use std::sync::Arc;
     use std::time::Duration;
 
     fn make_ctx(pane_entries: Vec<PaneEntry>) -> (DispatchContext, TerminalOutputBusState) {
-        let terminals: TerminalState = Arc::new(StdMutex::new(TerminalManager::new()));
+        let terminals: TerminalState = Arc::new(TerminalManager::new());
         let bus: TerminalOutputBusState = Arc::new(TerminalOutputBus::new());
         let pane_map = Arc::new(PaneMap::new());
         pane_map.replace(pane_entries);
@@ -219,17 +219,6 @@ impl SignalRegistry {
     }
 }
 
-/// Wall-clock milliseconds since the Unix epoch, used for `sent_at_ms`.
-/// Saturates to `0` on clock failures (clock-before-1970) — readable
-/// timestamps are best-effort metadata, not load-bearing for correctness.
-pub fn now_ms() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .unwrap_or(0)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
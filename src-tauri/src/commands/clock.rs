@@ -0,0 +1,26 @@
+//! Wall-clock timestamps, as recorded on jobs, runs, reports and history
+//! entries.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Wall-clock milliseconds since the Unix epoch, as `Date.now()` counts
+/// them, so the frontend can use the timestamps as they are.
+/// Saturates to `0` on clock failures (clock-before-1970) — readable
+/// timestamps are best-effort metadata, not load-bearing for correctness.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_now_ms_is_wall_clock() {
+        // 2020-01-01T00:00:00Z.
+        assert!(now_ms() > 1_577_836_800_000);
+    }
+}
@@ -0,0 +1,159 @@
+//! Cloudflare tunnels through `cloudflared`; see tunnel.rs.
+//!
+//! Quick tunnels need no account and get a random `trycloudflare.com` URL.
+//! Named tunnels run a tunnel the user created with `cloudflared tunnel
+//! create`, optionally routed to a hostname.
+
+use serde::{Deserialize, Serialize};
+
+use super::tunnel::{valid_hostname, Capabilities, LogEvent, ProviderKind, TunnelProvider};
+
+lazy_static::lazy_static! {
+    static ref QUICK_TUNNEL_URL: regex::Regex =
+        regex::Regex::new(r"https://[a-z0-9-]+\.trycloudflare\.com").expect("pattern compiles");
+    static ref TUNNEL_NAME: regex::Regex =
+        regex::Regex::new(r"^[A-Za-z0-9][A-Za-z0-9_.-]{0,63}$").expect("pattern compiles");
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CloudflareTunnel {
+    /// A throwaway `trycloudflare.com` tunnel.
+    Quick,
+    /// A tunnel created with `cloudflared tunnel create <name>`.
+    Named {
+        name: String,
+        /// Hostname routed to the tunnel, shown as its public URL.
+        #[serde(default)]
+        hostname: Option<String>,
+    },
+}
+
+impl TunnelProvider for CloudflareTunnel {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Cloudflare
+    }
+
+    fn label(&self) -> &'static str {
+        "Cloudflare Tunnel"
+    }
+
+    fn program(&self) -> &'static str {
+        "cloudflared"
+    }
+
+    fn install_hint(&self) -> &'static str {
+        "brew install cloudflared"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            requires_account: false,
+            custom_domain: true,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let CloudflareTunnel::Named { name, hostname } = self {
+            if !TUNNEL_NAME.is_match(name) {
+                return Err(format!("Invalid tunnel name: {}", name));
+            }
+            if let Some(hostname) = hostname {
+                if !valid_hostname(hostname) {
+                    return Err(format!("Invalid hostname: {}", hostname));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn args(&self, port: u16) -> Vec<String> {
+        let origin = format!("http://localhost:{}", port);
+        let mut args: Vec<String> = vec!["tunnel".into(), "--no-autoupdate".into()];
+        match self {
+            CloudflareTunnel::Quick => args.extend(["--url".into(), origin]),
+            CloudflareTunnel::Named { name, .. } => {
+                args.extend(["run".into(), "--url".into(), origin, name.clone()])
+            }
+        }
+        args
+    }
+
+    fn log_event(&self, line: &str) -> Option<LogEvent> {
+        if line.contains(" ERR ") {
+            return Some(LogEvent::Error(line.trim().to_string()));
+        }
+        match self {
+            CloudflareTunnel::Quick => {
+                let url = QUICK_TUNNEL_URL.find(line)?.as_str().to_string();
+                Some(LogEvent::Ready { url: Some(url) })
+            }
+            CloudflareTunnel::Named { hostname, .. } => {
+                if !line.contains("Registered tunnel connection") {
+                    return None;
+                }
+                let url = hostname.as_ref().map(|h| format!("https://{}", h));
+                Some(LogEvent::Ready { url })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str, hostname: Option<&str>) -> CloudflareTunnel {
+        CloudflareTunnel::Named {
+            name: name.to_string(),
+            hostname: hostname.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_validate_and_args() {
+        let tunnel = named("dev-app", Some("dev.example.com"));
+        assert!(tunnel.validate().is_ok());
+        assert_eq!(
+            tunnel.args(5173),
+            vec![
+                "tunnel",
+                "--no-autoupdate",
+                "run",
+                "--url",
+                "http://localhost:5173",
+                "dev-app"
+            ]
+        );
+        assert!(named("--config=/tmp/x", None).validate().is_err());
+        assert!(named("app; rm -rf ~", None).validate().is_err());
+        assert!(named("app", Some("-bad.example.com")).validate().is_err());
+        assert!(named("app", Some("localhost")).validate().is_err());
+    }
+
+    #[test]
+    fn test_log_events() {
+        let quick = CloudflareTunnel::Quick;
+        let line = "2024-05-01T10:00:00Z INF |  https://quiet-river-1234.trycloudflare.com                      |";
+        assert_eq!(
+            quick.log_event(line),
+            Some(LogEvent::Ready {
+                url: Some("https://quiet-river-1234.trycloudflare.com".to_string())
+            })
+        );
+        assert_eq!(
+            quick.log_event("INF Requesting new quick Tunnel on trycloudflare.com..."),
+            None
+        );
+        let line = "2024-05-01T10:00:01Z INF Registered tunnel connection connIndex=0";
+        assert_eq!(
+            named("app", None).log_event(line),
+            Some(LogEvent::Ready { url: None })
+        );
+        let line = "2024-05-01T10:00:00Z ERR Cannot determine default origin certificate path";
+        assert_eq!(
+            quick.log_event(line),
+            Some(LogEvent::Error(line.to_string()))
+        );
+    }
+}
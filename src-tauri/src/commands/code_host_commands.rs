@@ -0,0 +1,252 @@
+//! Tauri commands for pull requests, checks and reviews on any code host;
+//! see code_host.rs.
+//!
+//! Every command takes the path of a checkout, not a repository name, and
+//! works with whichever host its `origin` remote points at. API calls are
+//! async; git work runs on the blocking pool.
+
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+
+use super::code_host::{
+    self, attach_worktrees, BranchStatus, HostKind, NewPullRequest, PullRequestStatus,
+    PullRequestSummary, RemoteRepo,
+};
+use super::error::{ErrorCode, KiriError};
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::git_history;
+use super::git_worktree::{create_worktree_for, list_worktrees_for, WorktreeInfo};
+use super::jobs::CancelToken;
+use super::latency::run_blocking;
+use super::performance::CommandTimer;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeHostInfo {
+    pub remote: RemoteRepo,
+    /// A token is saved for this host
+    pub signed_in: bool,
+}
+
+/// The host and repository behind the checkout's `origin` remote.
+#[tauri::command]
+pub fn get_code_host(repo_path: String) -> Result<CodeHostInfo, KiriError> {
+    let remote = code_host::origin_remote(&normalize_input_path(&repo_path))?;
+    Ok(CodeHostInfo {
+        signed_in: code_host::load_token(remote.kind).is_some(),
+        remote,
+    })
+}
+
+/// Save an access token for GitLab or Bitbucket (`username:app_password`
+/// also works there). GitHub signs in with the device flow or the browser
+/// instead (oauth_commands.rs).
+#[tauri::command]
+pub fn set_code_host_token(kind: HostKind, token: String) -> Result<(), KiriError> {
+    if token.trim().is_empty() {
+        return Err(KiriError::new(
+            ErrorCode::InvalidInput,
+            format!("{} token is empty", kind.label()),
+        ));
+    }
+    code_host::save_token(kind, &token).map_err(KiriError::from)
+}
+
+#[tauri::command]
+pub fn clear_code_host_token(kind: HostKind) -> Result<(), KiriError> {
+    code_host::clear_token(kind).map_err(KiriError::from)
+}
+
+/// Pull requests of the checkout's repository, newest first. `state` is
+/// `open` (default), `closed`, `merged` or `all`. Each is annotated with
+/// the local worktree that has its branch checked out.
+#[tauri::command]
+pub async fn list_pull_requests(
+    repo_path: String,
+    state: Option<String>,
+) -> Result<Vec<PullRequestSummary>, KiriError> {
+    let _timer = CommandTimer::start("list_pull_requests");
+    let path = normalize_input_path(&repo_path);
+    let repo = code_host::origin_remote(&path)?;
+    let mut pulls = code_host::signed_in_host(&repo)?
+        .list_pulls(&repo, state.as_deref().unwrap_or("open"))
+        .await?;
+    let worktrees = run_blocking("list_worktrees", move || list_worktrees_for(&path)).await?;
+    attach_worktrees(&mut pulls, &worktrees);
+    Ok(pulls)
+}
+
+/// Checks on the head commit plus reviews for one pull request.
+#[tauri::command]
+pub async fn get_pull_request_status(
+    repo_path: String,
+    number: u64,
+) -> Result<PullRequestStatus, KiriError> {
+    let _timer = CommandTimer::start("get_pull_request_status");
+    let path = normalize_input_path(&repo_path);
+    let repo = code_host::origin_remote(&path)?;
+    let mut status = code_host::signed_in_host(&repo)?
+        .pull_status(&repo, number)
+        .await?;
+    let worktrees = run_blocking("list_worktrees", move || list_worktrees_for(&path)).await?;
+    attach_worktrees(std::slice::from_mut(&mut status.pull_request), &worktrees);
+    Ok(status)
+}
+
+/// The pull request for the branch checked out at `repo_path`, if any.
+#[tauri::command]
+pub async fn get_branch_pull_request(
+    repo_path: String,
+) -> Result<Option<PullRequestSummary>, KiriError> {
+    let path = normalize_input_path(&repo_path);
+    let repo = code_host::origin_remote(&path)?;
+    let branch = code_host::current_branch(&path)?;
+    let pull = code_host::signed_in_host(&repo)?
+        .pull_for_branch(&repo, &branch)
+        .await?;
+    Ok(pull.map(|mut pull| {
+        pull.worktree_path = Some(path_to_wire(&path));
+        pull
+    }))
+}
+
+/// Checks on the local head of `branch` (default: the branch checked out
+/// at `repo_path`) and its pull request, if any.
+#[tauri::command]
+pub async fn get_branch_status(
+    repo_path: String,
+    branch: Option<String>,
+) -> Result<BranchStatus, KiriError> {
+    let _timer = CommandTimer::start("get_branch_status");
+    let path = normalize_input_path(&repo_path);
+    let repo = code_host::origin_remote(&path)?;
+    let branch = match branch {
+        Some(branch) => branch,
+        None => code_host::current_branch(&path)?,
+    };
+    let head_sha = code_host::branch_head(&path, &branch)?;
+    code_host::signed_in_host(&repo)?
+        .branch_status(&repo, &branch, &head_sha)
+        .await
+}
+
+/// Open a pull request for the branch checked out at `repo_path`. The
+/// branch is pushed to `origin` first. `base` defaults to the
+/// repository's default branch.
+#[tauri::command]
+pub async fn create_pull_request(
+    repo_path: String,
+    title: String,
+    body: Option<String>,
+    base: Option<String>,
+    draft: Option<bool>,
+) -> Result<PullRequestSummary, KiriError> {
+    let _timer = CommandTimer::start("create_pull_request");
+    let path = normalize_input_path(&repo_path);
+    let repo = code_host::origin_remote(&path)?;
+    let branch = code_host::current_branch(&path)?;
+    let host = code_host::signed_in_host(&repo)?;
+    let base = match base {
+        Some(base) => base,
+        None => host.default_branch(&repo).await?,
+    };
+    if base == branch {
+        return Err(KiriError::new(
+            ErrorCode::InvalidInput,
+            format!("Cannot open a pull request from {} into itself", branch),
+        ));
+    }
+
+    let push_path = path.to_string_lossy().to_string();
+    let push_branch = branch.clone();
+    let pushed = run_blocking("push_commits", move || {
+        git_history::push_commits(push_path, None, Some(push_branch), &CancelToken::default())
+    })
+    .await?;
+    if !pushed.success {
+        return Err(format!("Failed to push {}: {}", branch, pushed.message.trim()).into());
+    }
+
+    let new = NewPullRequest {
+        head: branch,
+        base,
+        title,
+        body: body.unwrap_or_default(),
+        draft: draft.unwrap_or(false),
+    };
+    let mut pull = host.create_pull(&repo, &new).await?;
+    pull.worktree_path = Some(path_to_wire(&path));
+    Ok(pull)
+}
+
+/// Check out a pull request in its own worktree, reusing an existing one.
+/// Branches from forks are fetched as `pr-<number>` where the host
+/// publishes pull request refs.
+#[tauri::command]
+pub async fn checkout_pull_request(
+    repo_path: String,
+    number: u64,
+) -> Result<WorktreeInfo, KiriError> {
+    let path = normalize_input_path(&repo_path);
+    let repo = code_host::origin_remote(&path)?;
+    let host = code_host::signed_in_host(&repo)?;
+    let pull = host.get_pull(&repo, number).await?;
+    let head_ref = if pull.from_fork {
+        Some(host.pull_head_ref(number).ok_or_else(|| {
+            format!(
+                "{} pull requests from forks can't be checked out",
+                repo.kind.label()
+            )
+        })?)
+    } else {
+        None
+    };
+    run_blocking("checkout_pull_request", move || {
+        let branch = match head_ref {
+            Some(head_ref) => {
+                let branch = code_host::fork_branch_name(number);
+                code_host::fetch_ref(&path, &head_ref, &branch)?;
+                branch
+            }
+            None => {
+                // Without the remote branch, git would create an unrelated one.
+                let fetched = git_history::fetch_remote(
+                    path.to_string_lossy().to_string(),
+                    None,
+                    &CancelToken::default(),
+                )?;
+                if !fetched.success {
+                    return Err(format!(
+                        "Failed to fetch origin: {}",
+                        fetched.message.trim()
+                    ));
+                }
+                pull.head_branch
+            }
+        };
+        create_worktree_for(&path, &branch, None)
+    })
+    .await
+}
+
+/// Open a pull request in the default browser. Only URLs on the host of
+/// the checkout's `origin` remote are accepted.
+#[tauri::command]
+pub fn open_pull_request_url(
+    app: AppHandle,
+    repo_path: String,
+    url: String,
+) -> Result<(), KiriError> {
+    let repo = code_host::origin_remote(&normalize_input_path(&repo_path))?;
+    let prefix = format!("https://{}/", repo.host);
+    if !url.starts_with(&prefix) {
+        return Err(KiriError::new(
+            ErrorCode::InvalidInput,
+            format!("Not a {} URL: {}", repo.kind.label(), url),
+        ));
+    }
+    app.opener()
+        .open_url(url, None::<&str>)
+        .map_err(|e| format!("Failed to open pull request: {}", e))
+        .map_err(KiriError::from)
+}
@@ -0,0 +1,438 @@
+//! CODEOWNERS parsing and ownership lookup.
+//!
+//! The file is looked for where GitHub and GitLab look: `.github/`,
+//! `.gitlab/`, the repository root and `docs/`, first found wins. Patterns
+//! follow the CODEOWNERS flavour of gitignore syntax:
+//!
+//! - a pattern without a `/` (other than a trailing one) matches at any
+//!   depth, one with a leading or inner `/` from the root;
+//! - a trailing `/` matches everything under that directory;
+//! - `*` and `?` stay within a path segment, `**` crosses them;
+//! - `docs/*` matches the files directly in `docs/` but not deeper ones.
+//!
+//! The last matching rule wins. GitLab `[Section]` headers split the file:
+//! each section picks its own last match, and a path's owners are those of
+//! every section. A section header may name default owners for rules that
+//! list none. `!` negations aren't part of the format and are skipped.
+
+use git2::{Repository, StatusOptions};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use super::fs_path::path_to_wire;
+
+/// Where CODEOWNERS is looked for, in order.
+pub const CODEOWNERS_LOCATIONS: &[&str] = &[
+    ".github/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// 1-based line in the file.
+    line: usize,
+    pattern: String,
+    regex: Regex,
+    owners: Vec<String>,
+    section: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Section {
+    name: Option<String>,
+    /// `^[Section]`: approval from its owners is optional.
+    optional: bool,
+    default_owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnerRule {
+    pub line: usize,
+    pub pattern: String,
+    /// GitLab section the rule is in, if any.
+    pub section: Option<String>,
+    pub optional: bool,
+    pub owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileOwners {
+    pub path: String,
+    /// Everyone who owns the file, in rule order. Empty if nobody does.
+    pub owners: Vec<String>,
+    /// The rule that decided it, one per section.
+    pub rules: Vec<OwnerRule>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Codeowners {
+    rules: Vec<Rule>,
+    sections: Vec<Section>,
+}
+
+/// Split a line into whitespace-separated words, keeping `\ ` escapes
+/// and stopping at an unescaped `#`.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+            }
+            '#' => break,
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// A `[Section]`, `^[Section]` or `[Section][2]` header and its default
+/// owners.
+fn parse_section(line: &str) -> Option<Section> {
+    let (optional, rest) = match line.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let rest = rest.strip_prefix('[')?;
+    let (name, rest) = rest.split_once(']')?;
+    // An approval count such as `[2]` may follow the name.
+    let rest = match rest.strip_prefix('[') {
+        Some(count) => count.split_once(']').map_or("", |(_, rest)| rest),
+        None => rest,
+    };
+    Some(Section {
+        name: Some(name.trim().to_string()),
+        optional,
+        default_owners: words(rest),
+    })
+}
+
+/// The regex a CODEOWNERS pattern matches repository-relative paths with.
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    if pattern == "*" {
+        return Regex::new(".*").ok();
+    }
+    let dir_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.contains('/');
+    let trimmed = trimmed.trim_start_matches('/');
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let mut body = String::new();
+    let mut rest = trimmed;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            body.push_str("(?:.*/)?");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("**") {
+            body.push_str(".*");
+            rest = after;
+        } else {
+            match c {
+                '*' => body.push_str("[^/]*"),
+                '?' => body.push_str("[^/]"),
+                c => body.push_str(&regex::escape(&c.to_string())),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    let last_segment = trimmed.rsplit('/').next().unwrap_or(trimmed);
+    let suffix = if dir_only {
+        "/.*"
+    } else if last_segment.contains(['*', '?']) {
+        ""
+    } else {
+        // A plain name may be a directory, whose contents it then owns.
+        "(?:/.*)?"
+    };
+    let prefix = if anchored { "^" } else { "^(?:.*/)?" };
+    Regex::new(&format!("{}{}{}$", prefix, body, suffix)).ok()
+}
+
+impl Codeowners {
+    pub fn parse(text: &str) -> Self {
+        let mut codeowners = Codeowners {
+            rules: Vec::new(),
+            sections: vec![Section::default()],
+        };
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') || line.starts_with("^[") {
+                if let Some(section) = parse_section(line) {
+                    codeowners.sections.push(section);
+                    continue;
+                }
+            }
+            let mut words = words(line).into_iter();
+            let Some(pattern) = words.next() else {
+                continue;
+            };
+            if pattern.starts_with('!') {
+                continue;
+            }
+            let Some(regex) = pattern_regex(&pattern) else {
+                continue;
+            };
+            codeowners.rules.push(Rule {
+                line: index + 1,
+                pattern,
+                regex,
+                owners: words.collect(),
+                section: codeowners.sections.len() - 1,
+            });
+        }
+        codeowners
+    }
+
+    /// The CODEOWNERS of the checkout at `root`, and where it was found.
+    pub fn find(root: &Path) -> Option<(PathBuf, Self)> {
+        CODEOWNERS_LOCATIONS.iter().find_map(|location| {
+            let path = root.join(location);
+            let text = std::fs::read_to_string(&path).ok()?;
+            Some((path, Self::parse(&text)))
+        })
+    }
+
+    /// Owners of `path`, relative to the repository root.
+    pub fn owners_of(&self, path: &str) -> FileOwners {
+        let path = path.replace('\\', "/");
+        let path = path.trim_start_matches('/');
+        let mut rules: Vec<OwnerRule> = Vec::new();
+        for (index, section) in self.sections.iter().enumerate() {
+            let Some(rule) = self
+                .rules
+                .iter()
+                .rev()
+                .find(|rule| rule.section == index && rule.regex.is_match(path))
+            else {
+                continue;
+            };
+            let owners = if rule.owners.is_empty() {
+                section.default_owners.clone()
+            } else {
+                rule.owners.clone()
+            };
+            rules.push(OwnerRule {
+                line: rule.line,
+                pattern: rule.pattern.clone(),
+                section: section.name.clone(),
+                optional: section.optional,
+                owners,
+            });
+        }
+        let mut owners: Vec<String> = Vec::new();
+        for owner in rules.iter().flat_map(|rule| &rule.owners) {
+            if !owners.contains(owner) {
+                owners.push(owner.clone());
+            }
+        }
+        FileOwners {
+            path: path.to_string(),
+            owners,
+            rules,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ownership {
+    /// The CODEOWNERS file used; `None` if the repository has none.
+    pub codeowners: Option<String>,
+    pub files: Vec<FileOwners>,
+    /// Everyone whose review the files need, excluding optional sections.
+    pub required: Vec<String>,
+}
+
+/// Paths with uncommitted changes, staged or not, relative to the root.
+fn changed_paths(repo: &Repository) -> Result<Vec<String>, String> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| format!("Failed to read changes: {}", e))?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(String::from))
+        .collect())
+}
+
+/// Owners of `paths` (absolute or relative to the root) in the checkout
+/// at `repo_path`, or of every changed file when `paths` is `None`.
+pub fn get_owners(repo_path: &Path, paths: Option<Vec<String>>) -> Result<Ownership, String> {
+    let repo =
+        Repository::discover(repo_path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let root = repo.workdir().unwrap_or(repo_path).to_path_buf();
+    let paths = match paths {
+        Some(paths) => paths
+            .into_iter()
+            .map(|path| {
+                Path::new(&path)
+                    .strip_prefix(&root)
+                    .map(path_to_wire)
+                    .unwrap_or(path)
+            })
+            .collect(),
+        None => changed_paths(&repo)?,
+    };
+    let Some((file, codeowners)) = Codeowners::find(&root) else {
+        return Ok(Ownership {
+            codeowners: None,
+            files: paths
+                .into_iter()
+                .map(|path| Codeowners::default().owners_of(&path))
+                .collect(),
+            required: Vec::new(),
+        });
+    };
+    let files: Vec<FileOwners> = paths.iter().map(|p| codeowners.owners_of(p)).collect();
+    let required: BTreeSet<&String> = files
+        .iter()
+        .flat_map(|file| &file.rules)
+        .filter(|rule| !rule.optional)
+        .flat_map(|rule| &rule.owners)
+        .collect();
+    Ok(Ownership {
+        codeowners: Some(path_to_wire(&file)),
+        required: required.into_iter().cloned().collect(),
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn owners(codeowners: &Codeowners, path: &str) -> Vec<String> {
+        codeowners.owners_of(path).owners
+    }
+
+    #[test]
+    fn test_patterns_and_precedence() {
+        let codeowners = Codeowners::parse(
+            r"# Default owners
+*                   @org/core
+*.rs                @rustaceans
+/src-tauri/         @org/backend
+docs/*              docs@example.com
+apps/               @apps-team
+/build/logs/        @ops
+**/fixtures/**      @qa
+my\ file.txt        @spaces # trailing comment
+!ignored            @nobody
+",
+        );
+        assert_eq!(owners(&codeowners, "README.md"), vec!["@org/core"]);
+        assert_eq!(
+            owners(&codeowners, "crates/cli/src/main.rs"),
+            vec!["@rustaceans"]
+        );
+        // Later rules win over earlier ones.
+        assert_eq!(
+            owners(&codeowners, "src-tauri/src/lib.rs"),
+            vec!["@org/backend"]
+        );
+        assert_eq!(
+            owners(&codeowners, "docs/guide.md"),
+            vec!["docs@example.com"]
+        );
+        assert_eq!(owners(&codeowners, "docs/api/index.md"), vec!["@org/core"]);
+        assert_eq!(owners(&codeowners, "web/apps/main.ts"), vec!["@apps-team"]);
+        assert_eq!(owners(&codeowners, "build/logs/a.log"), vec!["@ops"]);
+        assert_eq!(owners(&codeowners, "x/build/logs/a.log"), vec!["@org/core"]);
+        assert_eq!(owners(&codeowners, "src/fixtures/a/b.json"), vec!["@qa"]);
+        assert_eq!(owners(&codeowners, "dir/my file.txt"), vec!["@spaces"]);
+        assert_eq!(codeowners.owners_of("src-tauri/x").rules[0].line, 4);
+
+        let unowned = Codeowners::parse("/src/ @dev\n");
+        assert!(owners(&unowned, "README.md").is_empty());
+    }
+
+    #[test]
+    fn test_gitlab_sections_and_changed_files() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        std::fs::create_dir_all(dir.path().join(".gitlab")).unwrap();
+        std::fs::write(
+            dir.path().join(".gitlab/CODEOWNERS"),
+            "* @everyone\n\n[Docs] @docs-team\n*.md\nREADME.md @readme-owner\n\n^[Style][2] @linters\n*.md\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README.md"), "hi").unwrap();
+        std::fs::write(dir.path().join("guide.md"), "hi").unwrap();
+        drop(repo);
+
+        let ownership = get_owners(dir.path(), None).unwrap();
+        assert!(ownership
+            .codeowners
+            .as_deref()
+            .is_some_and(|f| f.ends_with("CODEOWNERS")));
+        let mut files = ownership.files.clone();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let summary: Vec<(&str, Vec<String>)> = files
+            .iter()
+            .map(|f| (f.path.as_str(), f.owners.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (".gitlab/CODEOWNERS", vec!["@everyone".to_string()]),
+                (
+                    "README.md",
+                    vec![
+                        "@everyone".to_string(),
+                        "@readme-owner".to_string(),
+                        "@linters".to_string()
+                    ]
+                ),
+                (
+                    "guide.md",
+                    vec![
+                        "@everyone".to_string(),
+                        "@docs-team".to_string(),
+                        "@linters".to_string()
+                    ]
+                ),
+            ]
+        );
+        assert_eq!(
+            ownership.required,
+            vec!["@docs-team", "@everyone", "@readme-owner"]
+        );
+        assert!(files[1].rules[2].optional);
+        assert_eq!(files[1].rules[2].section.as_deref(), Some("Style"));
+
+        let given = get_owners(
+            dir.path(),
+            Some(vec![dir
+                .path()
+                .join("README.md")
+                .to_string_lossy()
+                .to_string()]),
+        )
+        .unwrap();
+        assert_eq!(given.files[0].path, "README.md");
+    }
+}
@@ -0,0 +1,19 @@
+//! Tauri command for code ownership; see codeowners.rs.
+
+use super::codeowners::{self, Ownership};
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+
+/// Who owns `paths` according to CODEOWNERS, or, without `paths`, every
+/// file changed in the worktree at `repo_path`.
+#[tauri::command]
+pub async fn get_owners(
+    repo_path: String,
+    paths: Option<Vec<String>>,
+) -> Result<Ownership, KiriError> {
+    run_blocking("get_owners", move || {
+        codeowners::get_owners(&normalize_input_path(&repo_path), paths)
+    })
+    .await
+}
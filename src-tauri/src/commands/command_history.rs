@@ -0,0 +1,238 @@
+//! History of the commands kiri ran, shared by every worktree.
+//!
+//! Task runs (watch-mode reruns and worktree init commands included) and
+//! commands sent to a terminal with `kiri run` are recorded when they
+//! finish, with the directory they ran in and their exit code. Commands
+//! typed into a terminal by hand aren't: without shell integration their
+//! end and exit code can't be told apart from their output.
+//!
+//! The history is saved in `~/.kiri/command-history.json`, oldest first,
+//! and trimmed to [`MAX_HISTORY_ENTRIES`]. [`CommandHistory::search`]
+//! answers the command palette, newest first with repeats folded.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::file_io::write_file_contents_atomic;
+
+/// Entries kept; older ones are dropped first.
+pub const MAX_HISTORY_ENTRIES: usize = 2000;
+
+/// Matches [`CommandHistory::search`] returns without a limit.
+pub const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+pub fn command_history_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("command-history.json"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CommandOrigin {
+    /// A task run, see tasks_commands.rs.
+    Task,
+    /// Sent to a terminal pane with `kiri run`.
+    Terminal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub command: String,
+    /// Directory it ran in; empty when it couldn't be told.
+    pub cwd: String,
+    /// `None` when it was stopped or its exit wasn't seen.
+    pub exit_code: Option<i32>,
+    pub origin: CommandOrigin,
+    /// When it finished, from [`now_ms`](super::clock::now_ms).
+    pub finished_at: u64,
+    pub duration_ms: u64,
+}
+
+/// A command in search results: its latest run, and how often it ran in
+/// that directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryMatch {
+    #[serde(flatten)]
+    pub latest: HistoryEntry,
+    pub runs: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CommandHistoryFile {
+    entries: Vec<HistoryEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    file: Option<PathBuf>,
+    /// Oldest first.
+    entries: Vec<HistoryEntry>,
+}
+
+pub type CommandHistoryState = Arc<Mutex<CommandHistory>>;
+
+/// Whether every word of the query is in the command or its directory.
+fn matches(entry: &HistoryEntry, terms: &[String]) -> bool {
+    let command = entry.command.to_lowercase();
+    let cwd = entry.cwd.to_lowercase();
+    terms
+        .iter()
+        .all(|term| command.contains(term.as_str()) || cwd.contains(term.as_str()))
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<CommandHistoryFile>(&contents) {
+                    Ok(file) => Some(file.entries),
+                    Err(e) => {
+                        log::warn!("failed to parse command history: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        let mut history = Self {
+            file: Some(path),
+            entries,
+        };
+        history.trim();
+        history
+    }
+
+    fn trim(&mut self) {
+        let excess = self.entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = CommandHistoryFile {
+            entries: self.entries.clone(),
+        };
+        let json = serde_json::to_vec(&file)
+            .map_err(|e| format!("Failed to serialize command history: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    /// Add a finished command and save. Blank commands are ignored.
+    pub fn record(&mut self, entry: HistoryEntry) -> Result<(), String> {
+        if entry.command.trim().is_empty() {
+            return Ok(());
+        }
+        self.entries.push(entry);
+        self.trim();
+        self.save()
+    }
+
+    /// Commands matching every word of `query`, case-insensitively, in
+    /// the command or the directory; all of them for a blank query. Runs
+    /// of the same command in the same directory are one match.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<HistoryMatch> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut found: Vec<HistoryMatch> = Vec::new();
+        for entry in self.entries.iter().rev() {
+            if !matches(entry, &terms) {
+                continue;
+            }
+            let earlier = found
+                .iter_mut()
+                .find(|m| m.latest.command == entry.command && m.latest.cwd == entry.cwd);
+            match earlier {
+                Some(earlier) => earlier.runs += 1,
+                None => found.push(HistoryMatch {
+                    latest: entry.clone(),
+                    runs: 1,
+                }),
+            }
+        }
+        found.truncate(limit);
+        found
+    }
+
+    pub fn clear(&mut self) -> Result<(), String> {
+        let previous = std::mem::take(&mut self.entries);
+        if let Err(e) = self.save() {
+            self.entries = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(command: &str, cwd: &str, finished_at: u64) -> HistoryEntry {
+        HistoryEntry {
+            command: command.to_string(),
+            cwd: cwd.to_string(),
+            exit_code: Some(0),
+            origin: CommandOrigin::Terminal,
+            finished_at,
+            duration_ms: 10,
+        }
+    }
+
+    #[test]
+    fn test_search_folds_repeats_newest_first() {
+        let mut history = CommandHistory::new();
+        history
+            .record(entry("docker compose up -d db", "/src/app-feature", 1))
+            .unwrap();
+        history.record(entry("npm test", "/src/app", 2)).unwrap();
+        history
+            .record(entry("docker compose up -d db", "/src/app-feature", 3))
+            .unwrap();
+        history
+            .record(entry("docker compose logs -f", "/src/app", 4))
+            .unwrap();
+        history.record(entry("  ", "/src/app", 5)).unwrap();
+
+        let found = history.search("Docker  UP", 10);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].latest.finished_at, found[0].runs), (3, 2));
+
+        let in_feature = history.search("feature", 10);
+        assert_eq!(in_feature[0].latest.command, "docker compose up -d db");
+
+        let recent: Vec<u64> = history
+            .search("", 2)
+            .iter()
+            .map(|m| m.latest.finished_at)
+            .collect();
+        assert_eq!(recent, vec![4, 3]);
+    }
+
+    #[test]
+    fn test_saved_and_trimmed() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("command-history.json");
+        let mut history = CommandHistory::load(path.clone());
+        for n in 0..MAX_HISTORY_ENTRIES as u64 + 5 {
+            history.entries.push(entry("make", "/src", n));
+        }
+        history.record(entry("cargo build", "/src", 9999)).unwrap();
+
+        let reloaded = CommandHistory::load(path);
+        assert_eq!(reloaded.entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(reloaded.entries[0].finished_at, 6);
+        assert_eq!(reloaded.search("cargo", 10)[0].latest.finished_at, 9999);
+    }
+}
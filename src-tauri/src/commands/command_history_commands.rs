@@ -0,0 +1,80 @@
+//! Recording and Tauri commands for the command history of
+//! command_history.rs.
+//!
+//! Task runs and `kiri run` call [`record_command`] when a command
+//! finishes; the palette asks [`search_command_history`].
+
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::clock::now_ms;
+use super::command_history::{
+    command_history_file_path, CommandHistory, CommandHistoryState, CommandOrigin, HistoryEntry,
+    HistoryMatch, DEFAULT_SEARCH_LIMIT,
+};
+use super::error::KiriError;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+
+/// Read `~/.kiri/command-history.json` into the managed state. Call from
+/// setup.
+pub fn load_command_history(app: &AppHandle) {
+    let Some(path) = command_history_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<CommandHistoryState>() {
+        *state.lock_recover() = CommandHistory::load(path);
+    }
+}
+
+/// Add a command that just finished after running for `duration`. A
+/// failure to save is logged; the command itself went fine.
+pub fn record_command(
+    app: &AppHandle,
+    origin: CommandOrigin,
+    command: &str,
+    cwd: &str,
+    exit_code: Option<i32>,
+    duration: Duration,
+) {
+    let Some(state) = app.try_state::<CommandHistoryState>() else {
+        return;
+    };
+    let entry = HistoryEntry {
+        command: command.trim().to_string(),
+        cwd: cwd.to_string(),
+        exit_code,
+        origin,
+        finished_at: now_ms(),
+        duration_ms: duration.as_millis() as u64,
+    };
+    let saved = state.lock_recover().record(entry);
+    if let Err(e) = saved {
+        log::warn!("failed to save command history: {}", e);
+    }
+}
+
+/// Commands run in any worktree that match every word of `query`, in the
+/// command or its directory, newest first. A blank query lists the most
+/// recent ones.
+#[tauri::command]
+pub async fn search_command_history(
+    state: tauri::State<'_, CommandHistoryState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryMatch>, KiriError> {
+    let state = Arc::clone(&state);
+    run_blocking("search_command_history", move || {
+        let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        Ok(state.lock_recover().search(&query, limit))
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn clear_command_history(
+    state: tauri::State<'_, CommandHistoryState>,
+) -> Result<(), KiriError> {
+    Ok(state.lock_recover().clear()?)
+}
@@ -0,0 +1,198 @@
+//! Backend-issued confirmation for destructive and sensitive commands.
+//!
+//! Permanently deleting a file, force-removing a worktree and running a
+//! project's init commands can't be undone, so the webview alone saying
+//! "the user agreed" is not enough: a script injected into it could say
+//! the same. Instead the frontend calls `request_confirmation`, which asks
+//! the user in a native dialog the webview can't draw or click, and only
+//! then issues a token here. The privileged command passes the token back
+//! and calls [`require_confirmation`], which accepts it once, for the same
+//! action and target, within [`CONFIRMATION_TTL`].
+//!
+//! The dialog lives in confirmation_commands.rs.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::error::{ErrorCode, KiriError};
+use super::fs_path::nfc_path;
+use super::i18n::Message;
+use super::lock_ext::LockExt;
+
+/// How long an issued token stays valid.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PrivilegedAction {
+    /// Delete a file or directory without going through the trash.
+    DeletePermanently,
+    /// Remove a worktree that has local changes or untracked files.
+    ForceRemoveWorktree,
+    /// Run the init commands a project or worktree configures.
+    RunInitCommands,
+}
+
+/// Text of the native confirmation dialog, in the user's language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationPrompt {
+    pub title: String,
+    pub message: String,
+    /// Label of the confirming button.
+    pub confirm_label: String,
+    pub cancel_label: String,
+}
+
+impl PrivilegedAction {
+    pub fn prompt(self, target: &Path) -> ConfirmationPrompt {
+        let (title, message, button) = match self {
+            PrivilegedAction::DeletePermanently => (
+                "confirm.delete.title",
+                "confirm.delete.message",
+                "confirm.delete.button",
+            ),
+            PrivilegedAction::ForceRemoveWorktree => (
+                "confirm.remove_worktree.title",
+                "confirm.remove_worktree.message",
+                "confirm.remove_worktree.button",
+            ),
+            PrivilegedAction::RunInitCommands => (
+                "confirm.run_init.title",
+                "confirm.run_init.message",
+                "confirm.run_init.button",
+            ),
+        };
+        let text = |key| Message::new(key).with("target", target.display()).text();
+        ConfirmationPrompt {
+            title: text(title),
+            message: text(message),
+            confirm_label: text(button),
+            cancel_label: text("confirm.cancel"),
+        }
+    }
+}
+
+struct Grant {
+    action: PrivilegedAction,
+    target: PathBuf,
+    expires: Instant,
+}
+
+/// Tokens issued and not yet used.
+#[derive(Default)]
+pub struct Confirmations {
+    grants: HashMap<String, Grant>,
+}
+
+impl Confirmations {
+    /// Issue a token for `action` on `target`, valid until `now` plus
+    /// [`CONFIRMATION_TTL`].
+    pub fn issue(&mut self, action: PrivilegedAction, target: &Path, now: Instant) -> String {
+        self.grants.retain(|_, grant| grant.expires > now);
+        let token = uuid::Uuid::new_v4().to_string();
+        self.grants.insert(
+            token.clone(),
+            Grant {
+                action,
+                target: target.to_path_buf(),
+                expires: now + CONFIRMATION_TTL,
+            },
+        );
+        token
+    }
+
+    /// Use up `token`. Targets are compared in NFC. The token is spent
+    /// even when it doesn't match, so a guessed or replayed token gets one
+    /// try.
+    pub fn consume(
+        &mut self,
+        token: &str,
+        action: PrivilegedAction,
+        target: &Path,
+        now: Instant,
+    ) -> Result<(), Message> {
+        let grant = self
+            .grants
+            .remove(token)
+            .ok_or(Message::new("error.confirmation_used"))?;
+        if grant.expires <= now {
+            return Err(Message::new("error.confirmation_expired"));
+        }
+        if grant.action != action || nfc_path(&grant.target) != nfc_path(target) {
+            return Err(Message::new("error.confirmation_mismatch"));
+        }
+        Ok(())
+    }
+}
+
+/// The tokens issued by this process.
+pub fn confirmations() -> &'static Mutex<Confirmations> {
+    static CONFIRMATIONS: OnceLock<Mutex<Confirmations>> = OnceLock::new();
+    CONFIRMATIONS.get_or_init(|| Mutex::new(Confirmations::default()))
+}
+
+/// Error unless `token` confirms `action` on `target`. Call at the start
+/// of the privileged command, with `target` normalized the same way it was
+/// when the token was requested.
+pub fn require_confirmation(
+    token: Option<&str>,
+    action: PrivilegedAction,
+    target: &Path,
+) -> Result<(), KiriError> {
+    let denied = |message: Message| KiriError::localized(ErrorCode::PermissionDenied, message);
+    let token = token.ok_or_else(|| denied(Message::new("error.confirmation_required")))?;
+    confirmations()
+        .lock_recover()
+        .consume(token, action, target, Instant::now())
+        .map_err(denied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_accepted_once() {
+        let mut registry = Confirmations::default();
+        let now = Instant::now();
+        let target = Path::new("/repo/build");
+        let token = registry.issue(PrivilegedAction::DeletePermanently, target, now);
+
+        let action = PrivilegedAction::DeletePermanently;
+        assert_eq!(registry.consume(&token, action, target, now), Ok(()));
+        assert!(registry.consume(&token, action, target, now).is_err());
+    }
+
+    #[test]
+    fn test_token_is_bound_to_action_target_and_time() {
+        let mut registry = Confirmations::default();
+        let now = Instant::now();
+        let target = Path::new("/repo");
+        let delete = PrivilegedAction::DeletePermanently;
+
+        let token = registry.issue(delete, target, now);
+        let other_action = PrivilegedAction::RunInitCommands;
+        assert!(registry.consume(&token, other_action, target, now).is_err());
+        // A mismatch spends the token.
+        assert!(registry.consume(&token, delete, target, now).is_err());
+
+        let token = registry.issue(delete, target, now);
+        let other_target = Path::new("/repo/.git");
+        assert!(registry.consume(&token, delete, other_target, now).is_err());
+
+        let token = registry.issue(delete, target, now);
+        let later = now + CONFIRMATION_TTL;
+        assert!(registry.consume(&token, delete, target, later).is_err());
+    }
+
+    #[test]
+    fn test_missing_token_is_denied() {
+        let err = require_confirmation(None, PrivilegedAction::DeletePermanently, Path::new("/a"))
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::PermissionDenied);
+        assert_eq!(err.key, Some("error.confirmation_required"));
+    }
+}
@@ -0,0 +1,50 @@
+//! The native dialog that issues confirmation tokens; see confirmation.rs.
+
+use std::time::Instant;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use super::confirmation::{confirmations, ConfirmationPrompt, PrivilegedAction};
+use super::error::KiriError;
+use super::fs_path::input_path;
+use super::lock_ext::LockExt;
+
+/// Show `prompt` in a native dialog and wait for the answer; `true` if the
+/// user confirmed.
+pub fn confirm_in_dialog(app: &AppHandle, prompt: ConfirmationPrompt) -> bool {
+    app.dialog()
+        .message(prompt.message)
+        .title(prompt.title)
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            prompt.confirm_label,
+            prompt.cancel_label,
+        ))
+        .blocking_show()
+}
+
+/// Ask the user in a native dialog to confirm `action` on `target`.
+/// Returns a single-use token for the privileged command when they
+/// confirm, `None` when they cancel.
+#[tauri::command]
+pub async fn request_confirmation(
+    app: AppHandle,
+    action: PrivilegedAction,
+    target: String,
+) -> Result<Option<String>, KiriError> {
+    let target = input_path(&target)?;
+    // The dialog blocks until the user answers. Not run_blocking: the
+    // time the user takes to decide isn't command latency.
+    tokio::task::spawn_blocking(move || {
+        if !confirm_in_dialog(&app, action.prompt(&target)) {
+            log::info!("confirmation declined: {:?} {}", action, target.display());
+            return None;
+        }
+        let token = confirmations()
+            .lock_recover()
+            .issue(action, &target, Instant::now());
+        Some(token)
+    })
+    .await
+    .map_err(|e| format!("request_confirmation task panicked: {}", e).into())
+}
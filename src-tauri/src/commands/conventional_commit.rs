@@ -0,0 +1,662 @@
+//! Conventional Commits for the commit UI: linting a message and guessing
+//! its type and scope from the changed paths.
+//!
+//! The rules follow the Conventional Commits spec and the defaults of
+//! `@commitlint/config-conventional`. Lines starting with `#` are dropped
+//! first, as `git commit` does, and merge, revert and `fixup!` messages
+//! git writes itself are not linted.
+//!
+//! A suggested type comes from what kind of files changed (docs, tests,
+//! CI, build files); anything else is `feat` when it adds files and `fix`
+//! otherwise. The scope is the closest meaningful directory all paths
+//! share, preferring one the repository's history already uses as a
+//! scope. The Tauri commands live in conventional_commit_commands.rs.
+
+use git2::{Delta, DiffOptions, Repository, Sort, StatusOptions};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+pub const COMMIT_TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+pub const HEADER_MAX_LENGTH: usize = 100;
+pub const LINE_MAX_LENGTH: usize = 100;
+
+/// Commits read for scopes already in use.
+const HISTORY_DEPTH: usize = 200;
+
+/// Directory names too generic to be a scope.
+const GENERIC_DIRS: [&str; 12] = [
+    "src", "lib", "app", "apps", "packages", "crates", "internal", "pkg", "cmd", "source",
+    "sources", "commands",
+];
+
+/// Directories whose children are the packages of a monorepo.
+const PACKAGE_DIRS: [&str; 4] = ["packages", "crates", "apps", "libs"];
+
+lazy_static::lazy_static! {
+    static ref HEADER: regex::Regex =
+        regex::Regex::new(r"^(\w+)(?:\(([^()]*)\))?(!)?: ?(.*)$").expect("pattern compiles");
+    static ref GIT_GENERATED: regex::Regex =
+        regex::Regex::new(r#"^(Merge |Revert "|(fixup|squash|amend)! )"#).expect("pattern compiles");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintIssue {
+    /// commitlint's name for the rule, e.g. `type-enum`
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// 1-based line of the message, comments removed
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitHeader {
+    #[serde(rename = "type")]
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintResult {
+    /// No errors; warnings don't count
+    pub valid: bool,
+    pub header: Option<CommitHeader>,
+    pub issues: Vec<LintIssue>,
+}
+
+/// `message` as git would commit it: comment lines and trailing blank
+/// lines removed.
+fn strip_comments(message: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    while lines.first().is_some_and(|line| line.is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+pub fn parse_header(header: &str) -> Option<CommitHeader> {
+    let captures = HEADER.captures(header)?;
+    Some(CommitHeader {
+        commit_type: captures[1].to_string(),
+        scope: captures.get(2).map(|scope| scope.as_str().to_string()),
+        breaking: captures.get(3).is_some(),
+        description: captures[4].to_string(),
+    })
+}
+
+pub fn lint_message(message: &str) -> LintResult {
+    let lines = strip_comments(message);
+    let Some(first) = lines.first() else {
+        return LintResult {
+            valid: false,
+            header: None,
+            issues: vec![LintIssue {
+                rule: "header-empty",
+                severity: Severity::Error,
+                message: "The message is empty".to_string(),
+                line: 1,
+            }],
+        };
+    };
+    if GIT_GENERATED.is_match(first) {
+        return LintResult {
+            valid: true,
+            header: None,
+            issues: Vec::new(),
+        };
+    }
+
+    let mut issues = Vec::new();
+    let mut issue = |rule, severity, line, message: String| {
+        issues.push(LintIssue {
+            rule,
+            severity,
+            message,
+            line,
+        })
+    };
+
+    let header = parse_header(first);
+    match &header {
+        None => issue(
+            "header-format",
+            Severity::Error,
+            1,
+            "The header should look like `type(scope): description`".into(),
+        ),
+        Some(header) => {
+            let commit_type = header.commit_type.as_str();
+            if commit_type != commit_type.to_lowercase() {
+                issue(
+                    "type-case",
+                    Severity::Error,
+                    1,
+                    format!(
+                        "Write the type in lower case: {}",
+                        commit_type.to_lowercase()
+                    ),
+                );
+            } else if !COMMIT_TYPES.contains(&commit_type) {
+                issue(
+                    "type-enum",
+                    Severity::Error,
+                    1,
+                    format!(
+                        "Unknown type `{}`; use one of {}",
+                        commit_type,
+                        COMMIT_TYPES.join(", ")
+                    ),
+                );
+            }
+            match header.scope.as_deref() {
+                Some(scope) if scope.trim().is_empty() => issue(
+                    "scope-empty",
+                    Severity::Error,
+                    1,
+                    "Remove the empty parentheses or name a scope".into(),
+                ),
+                Some(scope) if scope != scope.to_lowercase() => issue(
+                    "scope-case",
+                    Severity::Warning,
+                    1,
+                    format!("Write the scope in lower case: {}", scope.to_lowercase()),
+                ),
+                _ => {}
+            }
+            let description = header.description.trim();
+            if description.is_empty() {
+                issue(
+                    "subject-empty",
+                    Severity::Error,
+                    1,
+                    "Describe the change after the colon".into(),
+                );
+            } else {
+                if !first.contains(": ") {
+                    issue(
+                        "header-format",
+                        Severity::Error,
+                        1,
+                        "Put a space after the colon".into(),
+                    );
+                }
+                if description.ends_with('.') {
+                    issue(
+                        "subject-full-stop",
+                        Severity::Warning,
+                        1,
+                        "Leave out the full stop at the end".into(),
+                    );
+                }
+                if description.chars().next().is_some_and(char::is_uppercase) {
+                    issue(
+                        "subject-case",
+                        Severity::Warning,
+                        1,
+                        "Start the description in lower case".into(),
+                    );
+                }
+            }
+        }
+    }
+    let header_length = first.chars().count();
+    if header_length > HEADER_MAX_LENGTH {
+        issue(
+            "header-max-length",
+            Severity::Error,
+            1,
+            format!(
+                "The header is {} characters long; keep it within {}",
+                header_length, HEADER_MAX_LENGTH
+            ),
+        );
+    }
+
+    if lines.get(1).is_some_and(|line| !line.is_empty()) {
+        issue(
+            "body-leading-blank",
+            Severity::Warning,
+            2,
+            "Leave a blank line between the header and the body".into(),
+        );
+    }
+    for (index, line) in lines.iter().enumerate().skip(1) {
+        let number = index + 1;
+        // URLs can't be wrapped.
+        if line.chars().count() > LINE_MAX_LENGTH && !line.contains("://") {
+            issue(
+                "body-max-line-length",
+                Severity::Warning,
+                number,
+                format!("Wrap lines at {} characters", LINE_MAX_LENGTH),
+            );
+        }
+        let token = line.split(':').next().unwrap_or_default();
+        let is_breaking = token.eq_ignore_ascii_case("breaking change")
+            || token.eq_ignore_ascii_case("breaking-change");
+        if is_breaking && token != "BREAKING CHANGE" && token != "BREAKING-CHANGE" {
+            issue(
+                "footer-breaking-change",
+                Severity::Error,
+                number,
+                "Write the footer token as `BREAKING CHANGE:`".into(),
+            );
+        }
+    }
+
+    LintResult {
+        valid: !issues.iter().any(|i| i.severity == Severity::Error),
+        header,
+        issues,
+    }
+}
+
+// --- Suggestions --------------------------------------------------------------
+
+/// A path in the commit and whether it is new.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedPath {
+    pub path: String,
+    pub added: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitSuggestion {
+    #[serde(rename = "type")]
+    pub commit_type: &'static str,
+    pub scope: Option<String>,
+    /// Paths the suggestion is based on
+    pub paths: Vec<String>,
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn is_docs(path: &str) -> bool {
+    let name = file_name(path).to_lowercase();
+    path.starts_with("docs/")
+        || path.contains("/docs/")
+        || [".md", ".mdx", ".rst", ".adoc"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+        || name.starts_with("license")
+}
+
+fn is_test(path: &str) -> bool {
+    let name = file_name(path);
+    path.split('/')
+        .any(|dir| matches!(dir, "tests" | "test" | "__tests__" | "spec" | "e2e"))
+        || [".test.", ".spec.", "_test.", "_spec."]
+            .iter()
+            .any(|marker| name.contains(marker))
+        || (name.starts_with("test_") && name.ends_with(".py"))
+}
+
+fn is_ci(path: &str) -> bool {
+    path.starts_with(".github/workflows/")
+        || path.starts_with(".circleci/")
+        || path.starts_with(".buildkite/")
+        || matches!(
+            file_name(path),
+            ".gitlab-ci.yml" | "azure-pipelines.yml" | "Jenkinsfile" | ".travis.yml"
+        )
+}
+
+fn is_build(path: &str) -> bool {
+    let name = file_name(path);
+    matches!(
+        name,
+        "Cargo.toml"
+            | "Cargo.lock"
+            | "build.rs"
+            | "package.json"
+            | "package-lock.json"
+            | "pnpm-lock.yaml"
+            | "yarn.lock"
+            | "bun.lockb"
+            | "go.mod"
+            | "go.sum"
+            | "pyproject.toml"
+            | "requirements.txt"
+            | "Makefile"
+            | "Dockerfile"
+            | "tsconfig.json"
+    ) || name.starts_with("vite.config.")
+        || name.starts_with("webpack.config.")
+}
+
+fn suggest_type(changes: &[ChangedPath]) -> &'static str {
+    let all = |test: fn(&str) -> bool| changes.iter().all(|c| test(&c.path));
+    if all(is_docs) {
+        "docs"
+    } else if all(is_test) {
+        "test"
+    } else if all(is_ci) {
+        "ci"
+    } else if all(is_build) {
+        "build"
+    } else if changes
+        .iter()
+        .any(|c| c.added && !is_test(&c.path) && !is_docs(&c.path))
+    {
+        "feat"
+    } else {
+        "fix"
+    }
+}
+
+/// A scope for paths that share the directories `common`.
+fn scope_from_dirs(common: &[&str]) -> Option<String> {
+    // The package of a monorepo names the scope, however deep the change.
+    if let Some(index) = common.iter().position(|dir| PACKAGE_DIRS.contains(dir)) {
+        if let Some(package) = common.get(index + 1) {
+            return Some(package.to_lowercase());
+        }
+    }
+    common
+        .iter()
+        .rev()
+        .find(|dir| !GENERIC_DIRS.contains(dir) && !dir.starts_with('.'))
+        .map(|dir| dir.to_lowercase())
+}
+
+fn suggest_scope(changes: &[ChangedPath], known_scopes: &HashSet<String>) -> Option<String> {
+    let dirs: Vec<Vec<&str>> = changes
+        .iter()
+        .map(|c| {
+            let mut parts: Vec<&str> = c.path.split('/').collect();
+            parts.pop();
+            parts
+        })
+        .collect();
+    // A scope the history uses that every path is under, or is named by
+    // every file, e.g. `git` for git.rs and git_history.rs.
+    let in_all = |scope: &str| {
+        changes.iter().all(|c| {
+            c.path.split('/').any(|part| {
+                let stem = part.split('.').next().unwrap_or(part).to_lowercase();
+                stem == scope || stem.starts_with(&format!("{}_", scope))
+            })
+        })
+    };
+    let mut known: Vec<&String> = known_scopes.iter().filter(|s| in_all(s)).collect();
+    known.sort_by_key(|scope| std::cmp::Reverse(scope.len()));
+    if let Some(scope) = known.first() {
+        return Some(scope.to_string());
+    }
+
+    let first = dirs.first()?;
+    let shared = (0..first.len())
+        .take_while(|&i| dirs.iter().all(|d| d.get(i) == first.get(i)))
+        .count();
+    if let [only] = changes {
+        let stem = file_name(&only.path).split('.').next().unwrap_or_default();
+        if !stem.is_empty() && !matches!(stem, "mod" | "index" | "lib" | "main") {
+            let mut common = first.clone();
+            common.push(stem);
+            return scope_from_dirs(&common);
+        }
+    }
+    scope_from_dirs(&first[..shared])
+}
+
+pub fn suggest(changes: &[ChangedPath], known_scopes: &HashSet<String>) -> CommitSuggestion {
+    CommitSuggestion {
+        commit_type: suggest_type(changes),
+        scope: (!changes.is_empty())
+            .then(|| suggest_scope(changes, known_scopes))
+            .flatten(),
+        paths: changes.iter().map(|c| c.path.clone()).collect(),
+    }
+}
+
+/// Staged paths, or every changed path when nothing is staged.
+fn changed_paths(repo: &Repository) -> Result<Vec<ChangedPath>, String> {
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let mut options = DiffOptions::new();
+    let diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), None, Some(&mut options))
+        .map_err(|e| format!("Failed to read staged changes: {}", e))?;
+    let staged: Vec<ChangedPath> = diff
+        .deltas()
+        .filter_map(|delta| {
+            let path = delta.new_file().path().or(delta.old_file().path())?;
+            Some(ChangedPath {
+                path: path.to_string_lossy().replace('\\', "/"),
+                added: delta.status() == Delta::Added,
+            })
+        })
+        .collect();
+    if !staged.is_empty() {
+        return Ok(staged);
+    }
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| format!("Failed to read changes: {}", e))?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| {
+            Some(ChangedPath {
+                path: entry.path()?.to_string(),
+                added: entry.status().is_wt_new() || entry.status().is_index_new(),
+            })
+        })
+        .collect())
+}
+
+/// Scopes of the last [`HISTORY_DEPTH`] conventional commits on HEAD.
+fn known_scopes(repo: &Repository) -> HashSet<String> {
+    let mut scopes = HashSet::new();
+    let Ok(mut revwalk) = repo.revwalk() else {
+        return scopes;
+    };
+    if revwalk.push_head().is_err() || revwalk.set_sorting(Sort::TIME).is_err() {
+        return scopes;
+    }
+    for oid in revwalk.take(HISTORY_DEPTH).flatten() {
+        let Ok(commit) = repo.find_commit(oid) else {
+            continue;
+        };
+        let summary = commit.summary().unwrap_or_default();
+        if let Some(scope) = parse_header(summary).and_then(|header| header.scope) {
+            let scope = scope.trim().to_lowercase();
+            if !scope.is_empty() {
+                scopes.insert(scope);
+            }
+        }
+    }
+    scopes
+}
+
+/// Suggest a type and scope for the commit being prepared in `repo_path`:
+/// `paths` if given, else the staged changes.
+pub fn suggest_for_repo(
+    repo_path: &Path,
+    paths: Option<Vec<String>>,
+) -> Result<CommitSuggestion, String> {
+    let repo =
+        Repository::discover(repo_path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let changes = match paths {
+        Some(paths) => {
+            let workdir = repo.workdir().unwrap_or(repo_path);
+            paths
+                .into_iter()
+                .map(|path| {
+                    let relative = Path::new(&path)
+                        .strip_prefix(workdir)
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or(path);
+                    let added = repo
+                        .status_file(Path::new(&relative))
+                        .is_ok_and(|s| s.is_wt_new() || s.is_index_new());
+                    ChangedPath {
+                        path: relative.replace('\\', "/"),
+                        added,
+                    }
+                })
+                .collect()
+        }
+        None => changed_paths(&repo)?,
+    };
+    Ok(suggest(&changes, &known_scopes(&repo)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(message: &str) -> Vec<&'static str> {
+        lint_message(message)
+            .issues
+            .iter()
+            .map(|i| i.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_message() {
+        let result = lint_message("feat(git)!: add stash support\n\nLonger body.\n\nBREAKING CHANGE: stash list moved\n# Please enter the commit message\n");
+        assert!(result.valid);
+        assert!(result.issues.is_empty());
+        assert_eq!(
+            result.header,
+            Some(CommitHeader {
+                commit_type: "feat".to_string(),
+                scope: Some("git".to_string()),
+                breaking: true,
+                description: "add stash support".to_string(),
+            })
+        );
+
+        assert_eq!(codes("add stash support"), vec!["header-format"]);
+        assert_eq!(codes("Feat: add stash"), vec!["type-case"]);
+        assert_eq!(codes("feature: add stash"), vec!["type-enum"]);
+        assert_eq!(codes("fix(): crash"), vec!["scope-empty"]);
+        assert_eq!(codes("fix:"), vec!["subject-empty"]);
+        assert_eq!(codes("fix:crash"), vec!["header-format"]);
+        assert_eq!(
+            codes("fix(UI): Crash on open.\nbody"),
+            vec![
+                "scope-case",
+                "subject-full-stop",
+                "subject-case",
+                "body-leading-blank"
+            ]
+        );
+        assert_eq!(
+            codes("fix: crash\n\nbreaking change: config moved"),
+            vec!["footer-breaking-change"]
+        );
+        assert_eq!(
+            codes(&format!("fix: {}", "x".repeat(100))),
+            vec!["header-max-length"]
+        );
+        assert_eq!(codes("# only a comment\n"), vec!["header-empty"]);
+        assert!(lint_message("Merge branch 'main' into feature").valid);
+        assert!(lint_message("fixup! feat: add stash").valid);
+
+        let warnings_only = lint_message("fix: Crash");
+        assert!(warnings_only.valid);
+        assert_eq!(warnings_only.issues[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let changed = |paths: &[(&str, bool)]| -> Vec<ChangedPath> {
+            paths
+                .iter()
+                .map(|(path, added)| ChangedPath {
+                    path: path.to_string(),
+                    added: *added,
+                })
+                .collect()
+        };
+        let none = HashSet::new();
+
+        let docs = suggest(
+            &changed(&[("README.md", false), ("docs/setup.md", true)]),
+            &none,
+        );
+        assert_eq!((docs.commit_type, docs.scope), ("docs", None));
+
+        let tests = suggest(
+            &changed(&[
+                ("src/lib/stores/tabs.test.ts", false),
+                ("src/lib/stores/ui.test.ts", true),
+            ]),
+            &none,
+        );
+        assert_eq!(
+            (tests.commit_type, tests.scope.as_deref()),
+            ("test", Some("stores"))
+        );
+
+        let ci = suggest(&changed(&[(".github/workflows/ci.yml", false)]), &none);
+        assert_eq!(ci.commit_type, "ci");
+        let build = suggest(
+            &changed(&[("Cargo.toml", false), ("Cargo.lock", false)]),
+            &none,
+        );
+        assert_eq!(build.commit_type, "build");
+
+        let package = suggest(
+            &changed(&[
+                ("crates/kiri-cli/src/main.rs", false),
+                ("crates/kiri-cli/src/transport.rs", true),
+            ]),
+            &none,
+        );
+        assert_eq!(
+            (package.commit_type, package.scope.as_deref()),
+            ("feat", Some("kiri-cli"))
+        );
+
+        let single = suggest(
+            &changed(&[("src-tauri/src/commands/docker.rs", false)]),
+            &none,
+        );
+        assert_eq!(
+            (single.commit_type, single.scope.as_deref()),
+            ("fix", Some("docker"))
+        );
+
+        let known: HashSet<String> = ["git".to_string(), "ui".to_string()].into();
+        let from_history = suggest(
+            &changed(&[
+                ("src-tauri/src/commands/git.rs", false),
+                ("src-tauri/src/commands/git_history.rs", false),
+            ]),
+            &known,
+        );
+        assert_eq!(from_history.scope.as_deref(), Some("git"));
+
+        let scattered = suggest(
+            &changed(&[("src/main.ts", false), ("scripts/release.sh", false)]),
+            &none,
+        );
+        assert_eq!(scattered.scope, None);
+    }
+}
@@ -0,0 +1,26 @@
+//! Tauri commands for the commit message assistant; see
+//! conventional_commit.rs.
+
+use super::conventional_commit::{self, CommitSuggestion, LintResult};
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+
+/// Lint a commit message against the Conventional Commits rules.
+#[tauri::command]
+pub fn lint_commit_message(message: String) -> LintResult {
+    conventional_commit::lint_message(&message)
+}
+
+/// Suggest a type and scope for the commit being prepared: from `paths`
+/// if given, else from the staged changes.
+#[tauri::command]
+pub async fn suggest_commit_metadata(
+    repo_path: String,
+    paths: Option<Vec<String>>,
+) -> Result<CommitSuggestion, KiriError> {
+    run_blocking("suggest_commit_metadata", move || {
+        conventional_commit::suggest_for_repo(&normalize_input_path(&repo_path), paths)
+    })
+    .await
+}
@@ -0,0 +1,235 @@
+//! Crash reports for panics, with their native backtraces.
+//!
+//! [`install_panic_hook`] runs first thing in `run()`. A panic on any
+//! thread (a PTY reader, a watcher callback, a command) writes a report to
+//! `~/.kiri/diagnostics/crashes` before the default hook prints it, so a
+//! crash that took the app down can still be looked at after the next
+//! launch. Paths in a report are redacted: the home directory becomes `~`
+//! and paths under it keep only their file name, except those of the Rust
+//! toolchain and crates, which the backtrace needs. The newest
+//! [`MAX_REPORTS`] are kept.
+//!
+//! Native crashes (signals) don't go through the panic hook and are not
+//! captured.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use super::clock::now_ms;
+use super::file_io::write_file_contents_atomic;
+
+/// Reports kept; older ones are deleted when a new one is written.
+pub const MAX_REPORTS: usize = 20;
+
+/// Longest backtrace kept, in bytes; the frames nearest the panic come
+/// first.
+const MAX_BACKTRACE: usize = 64 * 1024;
+
+pub fn crash_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("diagnostics").join("crashes"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    pub timestamp_ms: u64,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread: String,
+    pub message: String,
+    /// `file:line:column` of the panic.
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+/// Redact the paths in `text`, see the module docs.
+pub fn redact(text: &str, home: Option<&Path>) -> String {
+    static UNDER_HOME: OnceLock<Regex> = OnceLock::new();
+    let Some(home) = home.map(|h| h.to_string_lossy()).filter(|h| h.len() > 1) else {
+        return text.to_string();
+    };
+    let text = text.replace(home.trim_end_matches('/'), "~");
+    let under_home =
+        UNDER_HOME.get_or_init(|| Regex::new(r#"~/[^\s:'"(),<>]+"#).expect("valid regex"));
+    let text = under_home.replace_all(&text, |caps: &regex::Captures| {
+        let path = &caps[0];
+        if path.starts_with("~/.cargo/") || path.starts_with("~/.rustup/") {
+            return path.to_string();
+        }
+        match path.trim_end_matches('/').rsplit_once('/') {
+            Some(("~", _)) | None => path.to_string(),
+            Some((_, name)) => format!("~/…/{}", name),
+        }
+    });
+    // The user name shows up outside the home directory too, e.g. in
+    // temporary directories.
+    let user = Path::new(home.as_ref())
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .filter(|n| n.len() >= 3);
+    match user {
+        Some(user) => match Regex::new(&format!(r"\b{}\b", regex::escape(&user))) {
+            Ok(re) => re.replace_all(&text, "<user>").to_string(),
+            Err(_) => text.to_string(),
+        },
+        None => text.to_string(),
+    }
+}
+
+/// A report of a panic on `thread`, redacted.
+pub fn build_report(
+    thread: &str,
+    message: &str,
+    location: Option<String>,
+    backtrace: &str,
+    timestamp_ms: u64,
+    home: Option<&Path>,
+) -> CrashReport {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let mut backtrace = redact(backtrace, home);
+    if backtrace.len() > MAX_BACKTRACE {
+        let mut cut = MAX_BACKTRACE;
+        while !backtrace.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        backtrace.truncate(cut);
+    }
+    CrashReport {
+        id: format!(
+            "crash-{}-{}",
+            timestamp_ms,
+            SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        ),
+        timestamp_ms,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        thread: redact(thread, home),
+        message: redact(message, home),
+        location: location.map(|l| redact(&l, home)),
+        backtrace,
+    }
+}
+
+/// Write `report` into `dir` and delete the oldest beyond [`MAX_REPORTS`].
+pub fn write_report(dir: &Path, report: &CrashReport) -> Result<(), String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let json = serde_json::to_vec_pretty(report)
+        .map_err(|e| format!("Failed to serialize crash report: {}", e))?;
+    write_file_contents_atomic(&dir.join(format!("{}.json", report.id)), &json)?;
+    for old in list_reports(dir).iter().skip(MAX_REPORTS) {
+        let _ = std::fs::remove_file(dir.join(format!("{}.json", old.id)));
+    }
+    Ok(())
+}
+
+/// Reports in `dir`, newest first. Files that don't parse are skipped.
+pub fn list_reports(dir: &Path) -> Vec<CrashReport> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|contents| serde_json::from_str(&contents).ok())
+        .collect();
+    reports.sort_by(|a, b| b.timestamp_ms.cmp(&a.timestamp_ms).then(b.id.cmp(&a.id)));
+    reports
+}
+
+/// Delete every report in `dir`.
+pub fn clear_reports(dir: &Path) -> Result<(), String> {
+    for report in list_reports(dir) {
+        let path = dir.join(format!("{}.json", report.id));
+        std::fs::remove_file(&path)
+            .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Write a report for every panic, then run the hook that was installed
+/// before (which prints the panic).
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        let thread = std::thread::current();
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        let home = dirs::home_dir();
+        let report = build_report(
+            thread.name().unwrap_or("<unnamed>"),
+            &message,
+            location,
+            &backtrace,
+            now_ms(),
+            home.as_deref(),
+        );
+        if let Some(dir) = crash_dir() {
+            if let Err(e) = write_report(&dir, &report) {
+                eprintln!("failed to write crash report: {}", e);
+            }
+        }
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_redact_paths() {
+        let home = Path::new("/Users/jdoe");
+        let text = "failed to open /Users/jdoe/work/acme-secret/src/main.rs: denied\n\
+                    at /Users/jdoe/.cargo/registry/src/portable-pty-0.8.1/src/unix.rs:120\n\
+                    tmp /var/folders/jdoe/T/x and ~/notes";
+        let redacted = redact(text, Some(home));
+        assert!(redacted.contains("open ~/…/main.rs: denied"));
+        assert!(redacted.contains("~/.cargo/registry/src/portable-pty-0.8.1/src/unix.rs:120"));
+        assert!(redacted.contains("/var/folders/<user>/T/x"));
+        assert!(redacted.ends_with("~/notes"));
+        assert!(!redacted.contains("jdoe"));
+        assert!(!redacted.contains("acme-secret"));
+        assert_eq!(redact(text, None), text);
+    }
+
+    #[test]
+    fn test_reports_are_written_listed_and_pruned() {
+        let dir = TempDir::new().unwrap();
+        let home = Path::new("/home/jdoe");
+        for i in 0..MAX_REPORTS as u64 + 2 {
+            let report = build_report(
+                "kiri-pty-reader",
+                "index out of bounds",
+                Some("/home/jdoe/kiri/src-tauri/src/commands/terminal.rs:10:5".to_string()),
+                "0: std::backtrace\n",
+                1_000 + i,
+                Some(home),
+            );
+            write_report(dir.path(), &report).unwrap();
+        }
+        let reports = list_reports(dir.path());
+        assert_eq!(reports.len(), MAX_REPORTS);
+        assert_eq!(reports[0].timestamp_ms, 1_000 + MAX_REPORTS as u64 + 1);
+        assert_eq!(reports[0].location.as_deref(), Some("~/…/terminal.rs:10:5"));
+
+        clear_reports(dir.path()).unwrap();
+        assert!(list_reports(dir.path()).is_empty());
+    }
+}
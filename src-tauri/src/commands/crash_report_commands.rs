@@ -0,0 +1,26 @@
+//! Tauri commands for the crash reports of crash_report.rs.
+
+use super::crash_report::{clear_reports, crash_dir, list_reports, CrashReport};
+use super::error::KiriError;
+use super::latency::run_blocking;
+
+/// Crash reports written so far, newest first, already redacted.
+#[tauri::command]
+pub async fn get_crash_reports() -> Result<Vec<CrashReport>, KiriError> {
+    run_blocking("get_crash_reports", || {
+        Ok(crash_dir()
+            .map(|dir| list_reports(&dir))
+            .unwrap_or_default())
+    })
+    .await
+}
+
+/// Delete every crash report.
+#[tauri::command]
+pub async fn clear_crash_reports() -> Result<(), KiriError> {
+    run_blocking("clear_crash_reports", || match crash_dir() {
+        Some(dir) => clear_reports(&dir),
+        None => Ok(()),
+    })
+    .await
+}
@@ -0,0 +1,281 @@
+//! `kiri://` URL parsing.
+//!
+//! Supported forms:
+//!
+//! - `kiri://open?path=/abs/dir` — open (or focus) a window for a path
+//! - `kiri://worktree/new?repo=/abs/repo&branch=feature/x[&path=/abs/dir]`
+//!   — create a worktree for a branch and open it
+//! - `kiri://focus?path=/abs/dir` or `kiri://focus?window=<label>` —
+//!   bring an existing window to the front
+//!
+//! Links can come from any web page, so parsing is strict: paths must be
+//! absolute (after `~` expansion), unknown actions are rejected, and branch
+//! names go through [`validate_branch_name`] before they reach git. A
+//! `worktree/new` link only gets a worktree next to the main checkout (see
+//! [`resolve_worktree_request`]), and only after the user confirms it in a
+//! native dialog.
+
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+use super::confirmation::ConfirmationPrompt;
+use super::fs_path::{nfc_path, normalize_input_path};
+use super::git_worktree::{default_worktree_path, open_common_repository, validate_branch_name};
+use super::i18n::Message;
+
+pub const DEEP_LINK_SCHEME: &str = "kiri";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkAction {
+    Open {
+        path: PathBuf,
+    },
+    NewWorktree {
+        repo: PathBuf,
+        branch: String,
+        path: Option<PathBuf>,
+    },
+    FocusPath {
+        path: PathBuf,
+    },
+    FocusWindow {
+        label: String,
+    },
+}
+
+fn query_param(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+        .filter(|v| !v.is_empty())
+}
+
+fn absolute_path(url: &Url, key: &str) -> Result<PathBuf, String> {
+    let raw = query_param(url, key).ok_or_else(|| format!("Missing `{}` parameter", key))?;
+    let path = normalize_input_path(&raw);
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Err(format!("`{}` must be an absolute path", key))
+    }
+}
+
+/// Parse a `kiri://` URL into the action it requests.
+pub fn parse_deep_link(raw: &str) -> Result<DeepLinkAction, String> {
+    let url = Url::parse(raw).map_err(|e| format!("Invalid URL: {}", e))?;
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(format!("Unsupported scheme: {}", url.scheme()));
+    }
+
+    // `kiri://worktree/new` parses with host `worktree` and path `/new`.
+    let host = url.host_str().unwrap_or_default();
+    let route = format!("{}{}", host, url.path().trim_end_matches('/'));
+
+    match route.as_str() {
+        "open" => Ok(DeepLinkAction::Open {
+            path: absolute_path(&url, "path")?,
+        }),
+        "worktree/new" => {
+            let branch = query_param(&url, "branch")
+                .ok_or_else(|| "Missing `branch` parameter".to_string())?;
+            validate_branch_name(&branch)?;
+            let path = match query_param(&url, "path") {
+                Some(_) => Some(absolute_path(&url, "path")?),
+                None => None,
+            };
+            Ok(DeepLinkAction::NewWorktree {
+                repo: absolute_path(&url, "repo")?,
+                branch,
+                path,
+            })
+        }
+        "focus" => match query_param(&url, "window") {
+            Some(label) => Ok(DeepLinkAction::FocusWindow { label }),
+            None => Ok(DeepLinkAction::FocusPath {
+                path: absolute_path(&url, "path")?,
+            }),
+        },
+        other => Err(format!("Unknown kiri:// action: {}", other)),
+    }
+}
+
+/// The worktree a `worktree/new` link asks for, resolved against the
+/// repository before anything is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorktreeRequest {
+    pub main_checkout: PathBuf,
+    pub branch: String,
+    pub target: PathBuf,
+}
+
+impl WorktreeRequest {
+    /// Text of the dialog that asks before the worktree is created.
+    pub fn prompt(&self) -> ConfirmationPrompt {
+        let text = |key| {
+            Message::new(key)
+                .with("repo", self.main_checkout.display())
+                .with("branch", &self.branch)
+                .with("target", self.target.display())
+                .text()
+        };
+        ConfirmationPrompt {
+            title: text("confirm.link_worktree.title"),
+            message: text("confirm.link_worktree.message"),
+            confirm_label: text("confirm.link_worktree.button"),
+            cancel_label: text("confirm.cancel"),
+        }
+    }
+}
+
+/// Whether `target` is a directory right next to `main_checkout`, where
+/// [`default_worktree_path`] puts worktrees.
+fn is_worktree_location(main_checkout: &Path, target: &Path) -> bool {
+    let named = matches!(target.components().next_back(), Some(Component::Normal(_)));
+    let parent = target.parent().map(nfc_path);
+    named && parent.is_some() && parent == main_checkout.parent().map(nfc_path)
+}
+
+/// Resolve a `worktree/new` link for the repository at `repo`. Without a
+/// `path` the worktree goes where the app would put it; a `path` anywhere
+/// but next to the main checkout is rejected.
+pub fn resolve_worktree_request(
+    repo: &Path,
+    branch: &str,
+    path: Option<&Path>,
+) -> Result<WorktreeRequest, String> {
+    let repo = open_common_repository(repo)?;
+    let main_checkout = repo
+        .workdir()
+        .map(|p| p.components().as_path().to_path_buf())
+        .ok_or("Repository has no working directory")?;
+    let target = match path {
+        Some(path) if is_worktree_location(&main_checkout, path) => path.to_path_buf(),
+        Some(_) => return Err("`path` must be next to the repository's main checkout".into()),
+        None => default_worktree_path(&main_checkout, branch),
+    };
+    Ok(WorktreeRequest {
+        main_checkout,
+        branch: branch.to_string(),
+        target,
+    })
+}
+
+/// Whether a process argument is a deep link (Windows and Linux deliver
+/// them as a command-line argument).
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.strip_prefix(DEEP_LINK_SCHEME)
+        .is_some_and(|rest| rest.starts_with("://"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_open() {
+        assert_eq!(
+            parse_deep_link("kiri://open?path=%2Ftmp%2Fproject").unwrap(),
+            DeepLinkAction::Open {
+                path: PathBuf::from("/tmp/project")
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_open_rejects_relative_path() {
+        assert!(parse_deep_link("kiri://open?path=relative/dir").is_err());
+        assert!(parse_deep_link("kiri://open").is_err());
+    }
+
+    #[test]
+    fn test_parse_new_worktree() {
+        let action =
+            parse_deep_link("kiri://worktree/new?repo=/src/kiri&branch=feature/login").unwrap();
+        assert_eq!(
+            action,
+            DeepLinkAction::NewWorktree {
+                repo: PathBuf::from("/src/kiri"),
+                branch: "feature/login".to_string(),
+                path: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_new_worktree_with_path_and_trailing_slash() {
+        let action = parse_deep_link("kiri://worktree/new/?repo=/r&branch=b&path=/wt/b").unwrap();
+        assert!(matches!(
+            action,
+            DeepLinkAction::NewWorktree { path: Some(ref p), .. } if p == &PathBuf::from("/wt/b")
+        ));
+    }
+
+    #[test]
+    fn test_parse_new_worktree_rejects_option_like_branch() {
+        assert!(parse_deep_link("kiri://worktree/new?repo=/r&branch=--force").is_err());
+        assert!(parse_deep_link("kiri://worktree/new?repo=/r").is_err());
+    }
+
+    #[test]
+    fn test_parse_focus() {
+        assert_eq!(
+            parse_deep_link("kiri://focus?window=window-3").unwrap(),
+            DeepLinkAction::FocusWindow {
+                label: "window-3".to_string()
+            }
+        );
+        assert_eq!(
+            parse_deep_link("kiri://focus?path=/a").unwrap(),
+            DeepLinkAction::FocusPath {
+                path: PathBuf::from("/a")
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_other_schemes_and_actions() {
+        assert!(parse_deep_link("https://open?path=/a").is_err());
+        assert!(parse_deep_link("kiri://delete?path=/a").is_err());
+        assert!(parse_deep_link("not a url").is_err());
+    }
+
+    #[test]
+    fn test_worktree_location_is_next_to_main_checkout() {
+        let main = Path::new("/src/kiri");
+        assert!(is_worktree_location(main, Path::new("/src/kiri-login")));
+        assert!(!is_worktree_location(main, Path::new("/src/kiri/login")));
+        assert!(!is_worktree_location(main, Path::new("/tmp/login")));
+        assert!(!is_worktree_location(main, Path::new("/src/..")));
+        assert!(!is_worktree_location(main, Path::new("/")));
+    }
+
+    #[test]
+    fn test_resolve_worktree_request() {
+        let dir = TempDir::new().unwrap();
+        let main = dir.path().join("app");
+        git2::Repository::init(&main).unwrap();
+
+        let request = resolve_worktree_request(&main, "feature/x", None).unwrap();
+        assert_eq!(request.main_checkout, main);
+        assert_eq!(request.target, dir.path().join("app-feature-x"));
+        let prompt = request.prompt();
+        assert!(prompt.message.contains("feature/x"));
+        assert!(prompt
+            .message
+            .contains(&request.target.display().to_string()));
+
+        let next_to = dir.path().join("elsewhere");
+        let request = resolve_worktree_request(&main, "b", Some(&next_to)).unwrap();
+        assert_eq!(request.target, next_to);
+        assert!(resolve_worktree_request(&main, "b", Some(Path::new("/tmp/x"))).is_err());
+        assert!(resolve_worktree_request(&main, "b", Some(&main.join("inner"))).is_err());
+    }
+
+    #[test]
+    fn test_is_deep_link() {
+        assert!(is_deep_link("kiri://open?path=/a"));
+        assert!(!is_deep_link("/some/dir"));
+        assert!(!is_deep_link("kiri-notes"));
+    }
+}
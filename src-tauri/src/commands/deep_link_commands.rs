@@ -0,0 +1,106 @@
+//! Dispatch for `kiri://` deep links. Parsing lives in deep_link.rs.
+//!
+//! macOS delivers links through `RunEvent::Opened`; Windows and Linux pass
+//! them as a command-line argument, which reaches this module from the
+//! startup arguments (and, once another instance is running, through the
+//! single-instance forwarding path).
+//!
+//! A `worktree/new` link writes to disk, and any web page can open one, so
+//! the worktree is only created after the user confirms the repository,
+//! branch and destination in a native dialog.
+
+use super::confirmation_commands::confirm_in_dialog;
+use super::deep_link::{parse_deep_link, resolve_worktree_request, DeepLinkAction};
+use super::fs_path::display_path;
+use super::git_worktree::create_worktree_for;
+use super::lock_ext::RwLockExt;
+use super::window::{route_to_project, WindowRegistryState};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkErrorEvent {
+    pub url: String,
+    pub message: String,
+}
+
+fn report_error(app: &AppHandle, url: &str, message: String) {
+    log::warn!("deep link {} failed: {}", url, message);
+    let _ = app.emit(
+        "deep-link-error",
+        DeepLinkErrorEvent {
+            url: url.to_string(),
+            message,
+        },
+    );
+}
+
+fn open_path(app: &AppHandle, path: String) -> Result<(), String> {
+    let registry = app.state::<WindowRegistryState>();
+    route_to_project(app, &registry, &path).map(|_| ())
+}
+
+/// Handle one `kiri://` URL. Failures are logged and emitted as
+/// `deep-link-error` so the frontend can show them; they never panic.
+pub fn handle_deep_link(app: &AppHandle, url: &str) {
+    let action = match parse_deep_link(url) {
+        Ok(action) => action,
+        Err(e) => return report_error(app, url, e),
+    };
+
+    let result = match action {
+        DeepLinkAction::Open { path } => {
+            if path.is_dir() {
+                open_path(app, display_path(&path))
+            } else {
+                Err("Path is not a directory".to_string())
+            }
+        }
+        DeepLinkAction::FocusPath { path } => {
+            let registry = app.state::<WindowRegistryState>();
+            let label = registry.read_recover().owner_of(&display_path(&path));
+            focus_label(app, label.as_deref())
+        }
+        DeepLinkAction::FocusWindow { label } => focus_label(app, Some(&label)),
+        DeepLinkAction::NewWorktree { repo, branch, path } => {
+            // The dialog waits for the user and `git worktree add` can take
+            // a while on large repos; keep both off the thread that
+            // delivered the link.
+            let app = app.clone();
+            let url = url.to_string();
+            tauri::async_runtime::spawn_blocking(move || {
+                let result =
+                    resolve_worktree_request(&repo, &branch, path.as_deref()).and_then(|request| {
+                        if !confirm_in_dialog(&app, request.prompt()) {
+                            log::info!("deep link {} declined", url);
+                            return Ok(());
+                        }
+                        create_worktree_for(
+                            &request.main_checkout,
+                            &request.branch,
+                            Some(&request.target),
+                        )
+                        .and_then(|worktree| open_path(&app, worktree.path))
+                    });
+                if let Err(e) = result {
+                    report_error(&app, &url, e);
+                }
+            });
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        report_error(app, url, e);
+    }
+}
+
+fn focus_label(app: &AppHandle, label: Option<&str>) -> Result<(), String> {
+    let window = label
+        .and_then(|l| app.get_webview_window(l))
+        .ok_or_else(|| "No matching window".to_string())?;
+    let _ = window.unminimize();
+    window
+        .set_focus()
+        .map_err(|e| format!("Failed to focus window: {}", e))
+}
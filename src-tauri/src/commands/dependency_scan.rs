@@ -0,0 +1,1098 @@
+//! Dependency, license and vulnerability report for a project.
+//!
+//! Dependencies come from the lockfiles at the project root:
+//! `package-lock.json` (npm), `Cargo.lock`, `poetry.lock` or a pinned
+//! `requirements.txt` (pip) and `go.mod`. Each is marked direct when the
+//! project itself asks for it. Licenses are taken from the lockfile where
+//! it records them and otherwise from the installed copy: `node_modules`,
+//! the cargo registry, a `.venv`, or the Go module cache. Nothing is
+//! downloaded for them.
+//!
+//! Known vulnerabilities come from the OSV API (<https://osv.dev>): one
+//! batch query for the ids, then the details of each advisory found.
+//!
+//! A report is cached per project with the lockfiles' mtimes, so the UI can
+//! show the last one at once and tell whether it's out of date. The job
+//! that builds it lives in dependency_scan_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use super::clock::now_ms;
+use super::code_host::http_client;
+use super::fs_path::path_to_wire;
+
+pub const OSV_API: &str = "https://api.osv.dev/v1";
+
+/// The querybatch endpoint takes at most this many queries per request.
+pub const OSV_BATCH_SIZE: usize = 1000;
+
+/// Advisories fetched in detail per scan; the rest are listed by id.
+pub const MAX_VULNERABILITY_DETAILS: usize = 200;
+
+/// Key in [`DependencyReport::licenses`] for dependencies whose license
+/// wasn't found.
+pub const UNKNOWN_LICENSE: &str = "unknown";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Npm,
+    Cargo,
+    Pypi,
+    Go,
+}
+
+impl Ecosystem {
+    /// The ecosystem's name in OSV.
+    pub fn osv_name(self) -> &'static str {
+        match self {
+            Ecosystem::Npm => "npm",
+            Ecosystem::Cargo => "crates.io",
+            Ecosystem::Pypi => "PyPI",
+            Ecosystem::Go => "Go",
+        }
+    }
+
+    /// `version` as OSV expects it: Go versions without their `v`.
+    fn osv_version(self, version: &str) -> String {
+        match self {
+            Ecosystem::Go => version.trim_start_matches('v').to_string(),
+            _ => version.to_string(),
+        }
+    }
+}
+
+/// Lockfiles read, in order. A pinned requirements.txt is only read when
+/// there's no poetry.lock.
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "Cargo.lock",
+    "poetry.lock",
+    "requirements.txt",
+    "go.mod",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    pub name: String,
+    pub version: String,
+    pub ecosystem: Ecosystem,
+    /// Asked for by the project itself rather than by another dependency.
+    pub direct: bool,
+    /// SPDX expression or license name, if found.
+    pub license: Option<String>,
+    /// Lockfile it was read from, relative to the project.
+    pub lockfile: String,
+    /// Ids of the advisories that affect this version.
+    pub vulnerabilities: Vec<String>,
+}
+
+impl Dependency {
+    fn new(name: &str, version: &str, ecosystem: Ecosystem, lockfile: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            ecosystem,
+            direct: false,
+            license: None,
+            lockfile: lockfile.to_string(),
+            vulnerabilities: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Vulnerability {
+    pub id: String,
+    pub summary: Option<String>,
+    /// `low`, `moderate`, `high` or `critical`, where the advisory says.
+    pub severity: Option<String>,
+    /// CVSS vector, e.g. `CVSS:3.1/AV:N/AC:L/..`.
+    pub cvss: Option<String>,
+    /// Other ids for the same issue, e.g. its CVE.
+    pub aliases: Vec<String>,
+    /// Affected dependencies as `name@version`.
+    pub packages: Vec<String>,
+    /// First version that fixes it for the first affected package.
+    pub fixed: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyReport {
+    pub project: String,
+    pub scanned_ms: u64,
+    pub lockfiles: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// Number of dependencies per license.
+    pub licenses: BTreeMap<String, usize>,
+    /// Why the vulnerability lookup failed, if it did. The dependencies
+    /// and licenses are still listed.
+    pub osv_error: Option<String>,
+    /// A lockfile changed since the scan.
+    pub stale: bool,
+    #[serde(skip)]
+    fingerprint: Vec<(String, u64)>,
+}
+
+impl DependencyReport {
+    pub fn new(
+        root: &Path,
+        lockfiles: Vec<String>,
+        mut dependencies: Vec<Dependency>,
+        vulnerabilities: Vec<Vulnerability>,
+        osv_error: Option<String>,
+    ) -> Self {
+        let mut affected: HashMap<String, Vec<String>> = HashMap::new();
+        for vulnerability in &vulnerabilities {
+            for package in &vulnerability.packages {
+                affected
+                    .entry(package.clone())
+                    .or_default()
+                    .push(vulnerability.id.clone());
+            }
+        }
+        let mut licenses = BTreeMap::new();
+        for dependency in &mut dependencies {
+            let key = format!("{}@{}", dependency.name, dependency.version);
+            if let Some(ids) = affected.remove(&key) {
+                dependency.vulnerabilities = ids;
+            }
+            let license = dependency.license.as_deref().unwrap_or(UNKNOWN_LICENSE);
+            *licenses.entry(license.to_string()).or_insert(0) += 1;
+        }
+        Self {
+            project: path_to_wire(root),
+            scanned_ms: now_ms(),
+            lockfiles,
+            dependencies,
+            vulnerabilities,
+            licenses,
+            osv_error,
+            stale: false,
+            fingerprint: fingerprint(root),
+        }
+    }
+}
+
+/// The lockfiles to read in `root`.
+pub fn find_lockfiles(root: &Path) -> Vec<PathBuf> {
+    let has_poetry = root.join("poetry.lock").is_file();
+    LOCKFILES
+        .iter()
+        .filter(|name| !(has_poetry && **name == "requirements.txt"))
+        .map(|name| root.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Each lockfile with its mtime, to tell whether a report is stale.
+fn fingerprint(root: &Path) -> Vec<(String, u64)> {
+    find_lockfiles(root)
+        .into_iter()
+        .map(|path| {
+            let mtime = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            (path.to_string_lossy().to_string(), mtime)
+        })
+        .collect()
+}
+
+/// The value of a `key = "value"` TOML line.
+fn toml_string<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (k, v) = line.split_once('=')?;
+    (k.trim() == key).then(|| v.trim().trim_matches('"'))
+}
+
+/// Packages of a Cargo.lock. Workspace members (no `source`) are left out;
+/// what they depend on is direct.
+pub fn parse_cargo_lock(text: &str, lockfile: &str) -> Vec<Dependency> {
+    struct Package {
+        name: String,
+        version: String,
+        local: bool,
+        dependencies: Vec<String>,
+    }
+    let mut packages: Vec<Package> = Vec::new();
+    let mut in_dependencies = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line == "[[package]]" {
+            packages.push(Package {
+                name: String::new(),
+                version: String::new(),
+                local: true,
+                dependencies: Vec::new(),
+            });
+            in_dependencies = false;
+            continue;
+        }
+        let Some(package) = packages.last_mut() else {
+            continue;
+        };
+        if in_dependencies {
+            if line.starts_with(']') {
+                in_dependencies = false;
+            } else if let Some(name) = line.trim_matches([',', '"']).split(' ').next() {
+                package.dependencies.push(name.to_string());
+            }
+        } else if let Some(name) = toml_string(line, "name") {
+            package.name = name.to_string();
+        } else if let Some(version) = toml_string(line, "version") {
+            package.version = version.to_string();
+        } else if toml_string(line, "source").is_some() {
+            package.local = false;
+        } else if line.starts_with("dependencies") && line.ends_with('[') {
+            in_dependencies = true;
+        }
+    }
+
+    let direct: HashSet<&str> = packages
+        .iter()
+        .filter(|p| p.local)
+        .flat_map(|p| p.dependencies.iter().map(String::as_str))
+        .collect();
+    packages
+        .iter()
+        .filter(|p| !p.local && !p.name.is_empty())
+        .map(|p| Dependency {
+            direct: direct.contains(p.name.as_str()),
+            ..Dependency::new(&p.name, &p.version, Ecosystem::Cargo, lockfile)
+        })
+        .collect()
+}
+
+/// Names a package.json depends on directly.
+fn package_json_dependencies(package: &serde_json::Value) -> HashSet<String> {
+    [
+        "dependencies",
+        "devDependencies",
+        "optionalDependencies",
+        "peerDependencies",
+    ]
+    .iter()
+    .filter_map(|key| package.get(*key).and_then(|d| d.as_object()))
+    .flat_map(|deps| deps.keys().cloned())
+    .collect()
+}
+
+/// The license field of a package.json or package-lock entry: an SPDX
+/// string, or the older `{"type": ..}` and `licenses: [..]` forms.
+fn npm_license(package: &serde_json::Value) -> Option<String> {
+    match package.get("license") {
+        Some(serde_json::Value::String(license)) => return Some(license.clone()),
+        Some(license) => {
+            if let Some(kind) = license.get("type").and_then(|t| t.as_str()) {
+                return Some(kind.to_string());
+            }
+        }
+        None => {}
+    }
+    let types: Vec<&str> = package
+        .get("licenses")?
+        .as_array()?
+        .iter()
+        .filter_map(|l| l.get("type").and_then(|t| t.as_str()))
+        .collect();
+    (!types.is_empty()).then(|| types.join(" OR "))
+}
+
+/// Packages of a package-lock.json. Lockfile v2 and v3 list every
+/// installed path under `packages`; v1 nests them under `dependencies`.
+pub fn parse_package_lock(
+    text: &str,
+    package_json: Option<&str>,
+    lockfile: &str,
+) -> Result<Vec<Dependency>, String> {
+    let lock: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse {}: {}", lockfile, e))?;
+    let direct = match lock.get("packages").and_then(|p| p.get("")) {
+        Some(root) => package_json_dependencies(root),
+        None => package_json
+            .and_then(|text| serde_json::from_str(text).ok())
+            .map(|package: serde_json::Value| package_json_dependencies(&package))
+            .unwrap_or_default(),
+    };
+
+    let mut dependencies = Vec::new();
+    if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+        for (path, entry) in packages {
+            let Some((parent, name)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            let Some(version) = entry.get("version").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            if entry.get("link").and_then(|l| l.as_bool()) == Some(true) {
+                continue;
+            }
+            let name = entry.get("name").and_then(|n| n.as_str()).unwrap_or(name);
+            dependencies.push(Dependency {
+                direct: parent.is_empty() && direct.contains(name),
+                license: npm_license(entry),
+                ..Dependency::new(name, version, Ecosystem::Npm, lockfile)
+            });
+        }
+    } else if let Some(top) = lock.get("dependencies").and_then(|d| d.as_object()) {
+        let mut stack: Vec<(&String, &serde_json::Value, bool)> = top
+            .iter()
+            .map(|(name, entry)| (name, entry, true))
+            .collect();
+        while let Some((name, entry, top_level)) = stack.pop() {
+            if let Some(version) = entry.get("version").and_then(|v| v.as_str()) {
+                dependencies.push(Dependency {
+                    direct: top_level && direct.contains(name.as_str()),
+                    ..Dependency::new(name, version, Ecosystem::Npm, lockfile)
+                });
+            }
+            if let Some(nested) = entry.get("dependencies").and_then(|d| d.as_object()) {
+                stack.extend(nested.iter().map(|(name, entry)| (name, entry, false)));
+            }
+        }
+    }
+    Ok(dependencies)
+}
+
+/// PyPI names compare case-insensitively with `-`, `_` and `.` alike.
+fn normalize_python_name(name: &str) -> String {
+    name.trim().to_lowercase().replace(['_', '.'], "-")
+}
+
+/// The name at the start of a requirement such as `requests[socks]>=2`.
+fn requirement_name(requirement: &str) -> Option<String> {
+    let end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..end];
+    (!name.is_empty()).then(|| normalize_python_name(name))
+}
+
+/// Pinned (`name==version`) lines of a requirements.txt, all direct.
+/// Ranges can't be looked up and are left out.
+pub fn parse_requirements(text: &str, lockfile: &str) -> Vec<Dependency> {
+    text.lines()
+        .map(|line| line.split(" #").next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '-']))
+        .filter_map(|line| {
+            let (name, version) = line.split_once("==")?;
+            let version = version.split([';', ',', ' ']).next()?.trim();
+            let name = requirement_name(name)?;
+            Some(Dependency {
+                direct: true,
+                ..Dependency::new(&name, version, Ecosystem::Pypi, lockfile)
+            })
+        })
+        .collect()
+}
+
+/// Names a pyproject.toml depends on: `[project] dependencies` and the
+/// poetry dependency tables.
+fn pyproject_dependencies(text: &str) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut section = "";
+    let mut in_list = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if in_list {
+            if line.starts_with(']') {
+                in_list = false;
+            } else if let Some(name) = requirement_name(line.trim_matches([',', '"', '\''])) {
+                names.insert(name);
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            section = line;
+            continue;
+        }
+        if section == "[project]" && line.starts_with("dependencies") {
+            let list = line
+                .split_once('[')
+                .map(|(_, rest)| rest)
+                .unwrap_or_default();
+            for item in list.trim_end_matches(']').split(',') {
+                if let Some(name) = requirement_name(item.trim().trim_matches(['"', '\''])) {
+                    names.insert(name);
+                }
+            }
+            in_list = !line.ends_with(']');
+        } else if section.starts_with("[tool.poetry") && section.ends_with("dependencies]") {
+            if let Some((name, _)) = line.split_once('=') {
+                let name = normalize_python_name(name.trim_matches('"'));
+                if !name.is_empty() && !name.starts_with('#') && name != "python" {
+                    names.insert(name);
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Packages of a poetry.lock; direct ones are named in pyproject.toml.
+pub fn parse_poetry_lock(text: &str, pyproject: Option<&str>, lockfile: &str) -> Vec<Dependency> {
+    let direct = pyproject.map(pyproject_dependencies).unwrap_or_default();
+    let mut dependencies: Vec<Dependency> = Vec::new();
+    let mut in_package = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[[package]]";
+            if in_package {
+                dependencies.push(Dependency::new("", "", Ecosystem::Pypi, lockfile));
+            }
+            continue;
+        }
+        let Some(dependency) = dependencies.last_mut().filter(|_| in_package) else {
+            continue;
+        };
+        if let Some(name) = toml_string(line, "name") {
+            dependency.name = normalize_python_name(name);
+            dependency.direct = direct.contains(&dependency.name);
+        } else if let Some(version) = toml_string(line, "version") {
+            dependency.version = version.to_string();
+        }
+    }
+    dependencies.retain(|d| !d.name.is_empty() && !d.version.is_empty());
+    dependencies
+}
+
+/// Modules a go.mod requires; those marked `// indirect` aren't direct.
+pub fn parse_go_mod(text: &str, lockfile: &str) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        let line = line.trim();
+        let require = if in_block {
+            if line.starts_with(')') {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if let Some(rest) = line.strip_prefix("require") {
+            let rest = rest.trim();
+            if rest.starts_with('(') {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+        let (spec, comment) = require.split_once("//").unwrap_or((require, ""));
+        let mut parts = spec.split_whitespace();
+        if let (Some(module), Some(version)) = (parts.next(), parts.next()) {
+            dependencies.push(Dependency {
+                direct: comment.trim() != "indirect",
+                ..Dependency::new(module, version, Ecosystem::Go, lockfile)
+            });
+        }
+    }
+    dependencies
+}
+
+/// Read every lockfile in `root`. Dependencies listed twice (e.g. by a
+/// nested npm install) are kept once, direct if either says so.
+pub fn read_dependencies(root: &Path) -> Result<(Vec<String>, Vec<Dependency>), String> {
+    let read = |name: &str| std::fs::read_to_string(root.join(name)).ok();
+    let mut names = Vec::new();
+    let mut all = Vec::new();
+    for path in find_lockfiles(root) {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let found = match name.as_str() {
+            "package-lock.json" => {
+                parse_package_lock(&text, read("package.json").as_deref(), &name)?
+            }
+            "Cargo.lock" => parse_cargo_lock(&text, &name),
+            "poetry.lock" => parse_poetry_lock(&text, read("pyproject.toml").as_deref(), &name),
+            "requirements.txt" => parse_requirements(&text, &name),
+            _ => parse_go_mod(&text, &name),
+        };
+        all.extend(found);
+        names.push(name);
+    }
+
+    let mut unique: BTreeMap<(Ecosystem, String, String), Dependency> = BTreeMap::new();
+    for dependency in all {
+        let key = (
+            dependency.ecosystem,
+            dependency.name.clone(),
+            dependency.version.clone(),
+        );
+        match unique.get_mut(&key) {
+            Some(existing) => {
+                existing.direct |= dependency.direct;
+                existing.license = existing.license.take().or(dependency.license);
+            }
+            None => {
+                unique.insert(key, dependency);
+            }
+        }
+    }
+    Ok((names, unique.into_values().collect()))
+}
+
+/// The license a LICENSE / COPYING text is, from its wording.
+pub fn classify_license_text(text: &str) -> Option<&'static str> {
+    let has = |phrase: &str| text.contains(phrase);
+    let license = if has("GNU AFFERO GENERAL PUBLIC LICENSE") {
+        "AGPL-3.0"
+    } else if has("GNU LESSER GENERAL PUBLIC LICENSE") {
+        if has("Version 3") {
+            "LGPL-3.0"
+        } else {
+            "LGPL-2.1"
+        }
+    } else if has("GNU GENERAL PUBLIC LICENSE") {
+        if has("Version 3") {
+            "GPL-3.0"
+        } else {
+            "GPL-2.0"
+        }
+    } else if has("Mozilla Public License Version 2.0") || has("Mozilla Public License, v. 2.0") {
+        "MPL-2.0"
+    } else if has("Apache License") && has("Version 2.0") {
+        "Apache-2.0"
+    } else if has("Permission is hereby granted, free of charge") {
+        "MIT"
+    } else if has("Permission to use, copy, modify, and/or distribute this software") {
+        "ISC"
+    } else if has("Redistribution and use in source and binary forms") {
+        if has("Neither the name") || has("names of its contributors") {
+            "BSD-3-Clause"
+        } else {
+            "BSD-2-Clause"
+        }
+    } else if has("This is free and unencumbered software released into the public domain") {
+        "Unlicense"
+    } else {
+        return None;
+    };
+    Some(license)
+}
+
+/// Licenses of the LICENSE* / LICENCE* / COPYING* files in `dir`, e.g.
+/// `Apache-2.0 OR MIT` for a dual-licensed module.
+fn license_from_files(dir: &Path) -> Option<String> {
+    let mut found = BTreeSet::new();
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_uppercase();
+        if !["LICENSE", "LICENCE", "COPYING"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            continue;
+        }
+        let text = std::fs::read_to_string(entry.path()).unwrap_or_default();
+        if let Some(license) = classify_license_text(&text) {
+            found.insert(license);
+        }
+    }
+    (!found.is_empty()).then(|| found.into_iter().collect::<Vec<_>>().join(" OR "))
+}
+
+/// The `license` of a Cargo.toml's `[package]` section.
+fn cargo_toml_license(text: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(license) = toml_string(line, "license") {
+                return Some(license.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// The license in a dist-info METADATA: `License-Expression`, else a
+/// short `License`, else the last `License ::` classifier.
+fn python_metadata_license(text: &str) -> Option<String> {
+    let mut license = None;
+    let mut classifier = None;
+    for line in text.lines().take_while(|line| !line.is_empty()) {
+        if let Some(expression) = line.strip_prefix("License-Expression:") {
+            return Some(expression.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("License:") {
+            let value = value.trim();
+            if !value.is_empty() && value.len() <= 60 && value != "UNKNOWN" {
+                license = Some(value.to_string());
+            }
+        } else if let Some(value) = line.strip_prefix("Classifier: License ::") {
+            classifier = value.rsplit("::").next().map(|v| v.trim().to_string());
+        }
+    }
+    license.or(classifier)
+}
+
+/// `path` as the Go module cache spells it: upper case letters become `!`
+/// and the lower case letter.
+fn go_cache_escape(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c.is_ascii_uppercase() {
+            escaped.push('!');
+            escaped.push(c.to_ascii_lowercase());
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+fn go_module_cache() -> Option<PathBuf> {
+    if let Some(cache) = std::env::var_os("GOMODCACHE").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(cache));
+    }
+    let gopath = std::env::var_os("GOPATH")
+        .filter(|v| !v.is_empty())
+        .and_then(|v| std::env::split_paths(&v).next())
+        .or_else(|| dirs::home_dir().map(|home| home.join("go")))?;
+    Some(gopath.join("pkg").join("mod"))
+}
+
+/// Source directories of the cargo registry's indexes.
+fn cargo_registry_sources() -> Vec<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+    let Some(src) = cargo_home.map(|home| home.join("registry").join("src")) else {
+        return Vec::new();
+    };
+    std::fs::read_dir(src)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+/// Licenses of the packages installed in the project's virtualenv, by
+/// normalized name and version.
+fn python_installed_licenses(root: &Path) -> HashMap<(String, String), String> {
+    let mut licenses = HashMap::new();
+    for venv in [".venv", "venv"] {
+        let base = root.join(venv);
+        let patterns = [
+            base.join("lib/python*/site-packages/*.dist-info/METADATA"),
+            base.join("Lib/site-packages/*.dist-info/METADATA"),
+        ];
+        for pattern in patterns {
+            let Ok(paths) = glob::glob(&pattern.to_string_lossy()) else {
+                continue;
+            };
+            for path in paths.flatten() {
+                let text = std::fs::read_to_string(&path).unwrap_or_default();
+                let header = |key: &str| {
+                    text.lines()
+                        .take_while(|line| !line.is_empty())
+                        .find_map(|line| line.strip_prefix(key).map(|v| v.trim().to_string()))
+                };
+                if let (Some(name), Some(version), Some(license)) = (
+                    header("Name:"),
+                    header("Version:"),
+                    python_metadata_license(&text),
+                ) {
+                    licenses.insert((normalize_python_name(&name), version), license);
+                }
+            }
+        }
+    }
+    licenses
+}
+
+/// Fill in the licenses the lockfiles didn't record from installed copies.
+pub fn resolve_licenses(root: &Path, dependencies: &mut [Dependency]) {
+    let registry = cargo_registry_sources();
+    let go_cache = go_module_cache();
+    let mut python: Option<HashMap<(String, String), String>> = None;
+    for dependency in dependencies.iter_mut().filter(|d| d.license.is_none()) {
+        dependency.license = match dependency.ecosystem {
+            Ecosystem::Npm => std::fs::read_to_string(
+                root.join("node_modules")
+                    .join(&dependency.name)
+                    .join("package.json"),
+            )
+            .ok()
+            .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+            .filter(|package| {
+                package.get("version").and_then(|v| v.as_str()) == Some(dependency.version.as_str())
+            })
+            .and_then(|package| npm_license(&package)),
+            Ecosystem::Cargo => registry.iter().find_map(|index| {
+                let dir = index.join(format!("{}-{}", dependency.name, dependency.version));
+                std::fs::read_to_string(dir.join("Cargo.toml"))
+                    .ok()
+                    .and_then(|text| cargo_toml_license(&text))
+            }),
+            Ecosystem::Pypi => python
+                .get_or_insert_with(|| python_installed_licenses(root))
+                .get(&(dependency.name.clone(), dependency.version.clone()))
+                .cloned(),
+            Ecosystem::Go => go_cache.as_ref().and_then(|cache| {
+                let dir = format!(
+                    "{}@{}",
+                    go_cache_escape(&dependency.name),
+                    go_cache_escape(&dependency.version)
+                );
+                license_from_files(&cache.join(dir))
+            }),
+        };
+    }
+}
+
+/// Body of an OSV `querybatch` request for `dependencies`.
+pub fn osv_batch_query(dependencies: &[Dependency]) -> serde_json::Value {
+    let queries: Vec<serde_json::Value> = dependencies
+        .iter()
+        .map(|d| {
+            serde_json::json!({
+                "package": { "name": d.name, "ecosystem": d.ecosystem.osv_name() },
+                "version": d.ecosystem.osv_version(&d.version),
+            })
+        })
+        .collect();
+    serde_json::json!({ "queries": queries })
+}
+
+/// Advisory ids per query from a `querybatch` response, in query order.
+pub fn parse_osv_batch(body: &serde_json::Value, queries: usize) -> Vec<Vec<String>> {
+    let mut ids = vec![Vec::new(); queries];
+    let results = body.get("results").and_then(|r| r.as_array());
+    for (slot, result) in ids.iter_mut().zip(results.into_iter().flatten()) {
+        *slot = result
+            .get("vulns")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.get("id").and_then(|id| id.as_str()).map(String::from))
+            .collect();
+    }
+    ids
+}
+
+/// A [`Vulnerability`] from an OSV advisory, for the affected
+/// `packages` (`name@version`).
+pub fn parse_osv_vulnerability(
+    id: &str,
+    advisory: Option<&serde_json::Value>,
+    packages: Vec<String>,
+) -> Vulnerability {
+    let field = |key: &str| {
+        advisory
+            .and_then(|a| a.get(key))
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    };
+    let severity = advisory
+        .and_then(|a| a.pointer("/database_specific/severity"))
+        .and_then(|s| s.as_str())
+        .map(str::to_lowercase);
+    let cvss = advisory
+        .and_then(|a| a.get("severity"))
+        .and_then(|s| s.as_array())
+        .into_iter()
+        .flatten()
+        .find_map(|s| s.get("score").and_then(|score| score.as_str()))
+        .map(String::from);
+    let aliases = advisory
+        .and_then(|a| a.get("aliases"))
+        .and_then(|a| a.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|a| a.as_str().map(String::from))
+        .collect();
+    let first_package = packages
+        .first()
+        .and_then(|p| p.rsplit_once('@'))
+        .map(|(name, _)| name.to_string());
+    let fixed = advisory
+        .and_then(|a| a.get("affected"))
+        .and_then(|a| a.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|affected| {
+            affected.pointer("/package/name").and_then(|n| n.as_str()) == first_package.as_deref()
+        })
+        .flat_map(|affected| {
+            affected
+                .get("ranges")
+                .and_then(|r| r.as_array())
+                .into_iter()
+                .flatten()
+        })
+        .flat_map(|range| {
+            range
+                .get("events")
+                .and_then(|e| e.as_array())
+                .into_iter()
+                .flatten()
+        })
+        .find_map(|event| {
+            event
+                .get("fixed")
+                .and_then(|f| f.as_str())
+                .map(String::from)
+        });
+    Vulnerability {
+        id: id.to_string(),
+        summary: field("summary"),
+        severity,
+        cvss,
+        aliases,
+        packages,
+        fixed,
+    }
+}
+
+/// Advisory ids for each of `dependencies`, in order.
+pub async fn query_osv(dependencies: &[Dependency]) -> Result<Vec<Vec<String>>, String> {
+    let response = http_client()?
+        .post(format!("{}/querybatch", OSV_API))
+        .json(&osv_batch_query(dependencies))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to query OSV: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("OSV query failed: HTTP {}", response.status()));
+    }
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read the OSV response: {}", e))?;
+    Ok(parse_osv_batch(&body, dependencies.len()))
+}
+
+/// The full OSV advisory `id`.
+pub async fn fetch_osv_advisory(id: &str) -> Result<serde_json::Value, String> {
+    let response = http_client()?
+        .get(format!("{}/vulns/{}", OSV_API, urlencoding::encode(id)))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", id, e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch {}: HTTP {}",
+            id,
+            response.status()
+        ));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", id, e))
+}
+
+/// Last report per project root, shared by every window.
+#[derive(Default)]
+pub struct DependencyReports {
+    reports: HashMap<String, DependencyReport>,
+}
+
+pub type DependencyReportsState = Arc<Mutex<DependencyReports>>;
+
+impl DependencyReports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn store(&mut self, report: DependencyReport) {
+        self.reports.insert(report.project.clone(), report);
+    }
+
+    /// The last report for `root`, marked stale if a lockfile changed.
+    pub fn get(&self, root: &Path) -> Option<DependencyReport> {
+        let mut report = self.reports.get(&path_to_wire(root))?.clone();
+        report.stale = fingerprint(root) != report.fingerprint;
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_dependencies() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(
+            root.join("Cargo.lock"),
+            r#"version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "serde",
+]
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "serde_derive 1.0.200",
+]
+
+[[package]]
+name = "serde_derive"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("package-lock.json"),
+            r#"{"lockfileVersion": 3, "packages": {
+                "": {"name": "app", "devDependencies": {"vite": "^5"}},
+                "node_modules/vite": {"version": "5.2.0", "license": "MIT"},
+                "node_modules/esbuild": {"version": "0.20.2", "license": "MIT"},
+                "node_modules/vite/node_modules/esbuild": {"version": "0.19.0"}
+            }}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("requirements.txt"),
+            "# pinned\nDjango==4.2.1 ; python_version >= '3.8'\nrequests>=2\n-r dev.txt\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("go.mod"),
+            "module example.com/app\n\ngo 1.22\n\nrequire github.com/BurntSushi/toml v1.3.2\n\nrequire (\n\tgolang.org/x/text v0.14.0 // indirect\n)\n",
+        )
+        .unwrap();
+
+        let (lockfiles, dependencies) = read_dependencies(root).unwrap();
+        assert_eq!(
+            lockfiles,
+            vec![
+                "package-lock.json",
+                "Cargo.lock",
+                "requirements.txt",
+                "go.mod"
+            ]
+        );
+        let summary: Vec<(&str, &str, bool)> = dependencies
+            .iter()
+            .map(|d| (d.name.as_str(), d.version.as_str(), d.direct))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("esbuild", "0.19.0", false),
+                ("esbuild", "0.20.2", false),
+                ("vite", "5.2.0", true),
+                ("serde", "1.0.200", true),
+                ("serde_derive", "1.0.200", false),
+                ("django", "4.2.1", true),
+                ("github.com/BurntSushi/toml", "v1.3.2", true),
+                ("golang.org/x/text", "v0.14.0", false),
+            ]
+        );
+        assert_eq!(dependencies[2].license.as_deref(), Some("MIT"));
+        assert_eq!(
+            go_cache_escape("github.com/BurntSushi/toml"),
+            "github.com/!burnt!sushi/toml"
+        );
+
+        // poetry.lock takes over from requirements.txt.
+        std::fs::write(
+            root.join("poetry.lock"),
+            "[[package]]\nname = \"Flask\"\nversion = \"3.0.0\"\n\n[package.dependencies]\nclick = \">=8\"\n\n[[package]]\nname = \"click\"\nversion = \"8.1.7\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("pyproject.toml"),
+            "[tool.poetry.dependencies]\npython = \"^3.11\"\nflask = \"^3\"\n",
+        )
+        .unwrap();
+        let python: Vec<(String, bool)> = read_dependencies(root)
+            .unwrap()
+            .1
+            .into_iter()
+            .filter(|d| d.ecosystem == Ecosystem::Pypi)
+            .map(|d| (d.name, d.direct))
+            .collect();
+        assert_eq!(
+            python,
+            vec![("click".to_string(), false), ("flask".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn test_osv_results_in_report() {
+        let dir = TempDir::new().unwrap();
+        let dependencies = vec![
+            Dependency::new("lodash", "4.17.20", Ecosystem::Npm, "package-lock.json"),
+            Dependency::new("golang.org/x/net", "v0.7.0", Ecosystem::Go, "go.mod"),
+        ];
+        let query = osv_batch_query(&dependencies);
+        assert_eq!(query["queries"][1]["package"]["ecosystem"], "Go");
+        assert_eq!(query["queries"][1]["version"], "0.7.0");
+
+        let batch = serde_json::json!({"results": [
+            {"vulns": [{"id": "GHSA-35jh-r3h4-6jhm", "modified": "2024-01-01T00:00:00Z"}]},
+            {}
+        ]});
+        let ids = parse_osv_batch(&batch, 2);
+        assert_eq!(ids, vec![vec!["GHSA-35jh-r3h4-6jhm".to_string()], vec![]]);
+
+        let advisory = serde_json::json!({
+            "id": "GHSA-35jh-r3h4-6jhm",
+            "summary": "Command Injection in lodash",
+            "aliases": ["CVE-2021-23337"],
+            "severity": [{"type": "CVSS_V3", "score": "CVSS:3.1/AV:N/AC:L/PR:H/UI:N/S:U/C:H/I:H/A:H"}],
+            "affected": [{
+                "package": {"ecosystem": "npm", "name": "lodash"},
+                "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"fixed": "4.17.21"}]}]
+            }],
+            "database_specific": {"severity": "HIGH"}
+        });
+        let vulnerability = parse_osv_vulnerability(
+            "GHSA-35jh-r3h4-6jhm",
+            Some(&advisory),
+            vec!["lodash@4.17.20".to_string()],
+        );
+        assert_eq!(vulnerability.severity.as_deref(), Some("high"));
+        assert_eq!(vulnerability.fixed.as_deref(), Some("4.17.21"));
+        assert_eq!(vulnerability.aliases, vec!["CVE-2021-23337"]);
+
+        let report = DependencyReport::new(
+            dir.path(),
+            vec!["package-lock.json".to_string()],
+            dependencies,
+            vec![vulnerability],
+            None,
+        );
+        assert_eq!(
+            report.dependencies[0].vulnerabilities,
+            vec!["GHSA-35jh-r3h4-6jhm"]
+        );
+        assert_eq!(report.licenses.get(UNKNOWN_LICENSE), Some(&2));
+
+        let mut reports = DependencyReports::new();
+        reports.store(report);
+        assert!(!reports.get(dir.path()).unwrap().stale);
+        std::fs::write(dir.path().join("package-lock.json"), "{}").unwrap();
+        assert!(reports.get(dir.path()).unwrap().stale);
+
+        assert_eq!(
+            classify_license_text("Permission is hereby granted, free of charge, to any person"),
+            Some("MIT")
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Job and Tauri commands for the dependency report; lockfile parsing,
+//! license lookup and OSV queries live in dependency_scan.rs.
+//!
+//! [`scan_dependencies`] starts a job of kind `dependency-scan` and returns
+//! its id. When it ends the report is cached and sent as
+//! `dependency-scan-finished`; [`get_dependency_report`] returns the cached
+//! one later.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::dependency_scan::{
+    fetch_osv_advisory, parse_osv_vulnerability, query_osv, read_dependencies, resolve_licenses,
+    Dependency, DependencyReport, DependencyReportsState, Vulnerability, MAX_VULNERABILITY_DETAILS,
+    OSV_BATCH_SIZE,
+};
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::spawn_job;
+use super::lock_ext::LockExt;
+
+pub const DEPENDENCY_SCAN_FINISHED_EVENT: &str = "dependency-scan-finished";
+
+const DEPENDENCY_SCAN_JOB_KIND: &str = "dependency-scan";
+
+/// Percent of the job spent reading lockfiles and licenses; the rest is
+/// the OSV lookup.
+const LOCAL_PROGRESS: u8 = 20;
+
+/// Advisories for `dependencies` from OSV. `None` if cancelled.
+fn lookup_vulnerabilities(
+    ctx: &JobContext,
+    dependencies: &[Dependency],
+) -> Result<Option<Vec<Vulnerability>>, String> {
+    let mut affected: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let batches = dependencies.chunks(OSV_BATCH_SIZE);
+    let batch_count = batches.len().max(1);
+    for (index, batch) in batches.enumerate() {
+        if ctx.is_cancelled() {
+            return Ok(None);
+        }
+        let ids = tauri::async_runtime::block_on(query_osv(batch))?;
+        for (dependency, ids) in batch.iter().zip(ids) {
+            for id in ids {
+                affected
+                    .entry(id)
+                    .or_default()
+                    .push(format!("{}@{}", dependency.name, dependency.version));
+            }
+        }
+        let percent = LOCAL_PROGRESS as usize + (index + 1) * 30 / batch_count;
+        ctx.progress(
+            Some(percent as u8),
+            Some("Checking for vulnerabilities".to_string()),
+        );
+    }
+
+    let total = affected.len();
+    let mut vulnerabilities = Vec::with_capacity(total);
+    for (index, (id, packages)) in affected.into_iter().enumerate() {
+        if ctx.is_cancelled() {
+            return Ok(None);
+        }
+        // Past the cap, or if its details can't be fetched, an advisory is
+        // still listed by id.
+        let advisory = (index < MAX_VULNERABILITY_DETAILS)
+            .then(|| tauri::async_runtime::block_on(fetch_osv_advisory(&id)))
+            .and_then(|result| {
+                result
+                    .map_err(|e| log::warn!("dependency scan: {}", e))
+                    .ok()
+            });
+        vulnerabilities.push(parse_osv_vulnerability(&id, advisory.as_ref(), packages));
+        let percent = 50 + (index + 1) * 50 / total;
+        ctx.progress(Some(percent as u8), Some(format!("Reading {}", id)));
+    }
+    Ok(Some(vulnerabilities))
+}
+
+/// Build the report for `root`. `None` if cancelled.
+fn scan(ctx: &JobContext, root: &Path) -> Result<Option<DependencyReport>, String> {
+    ctx.progress(Some(0), Some("Reading lockfiles".to_string()));
+    let (lockfiles, mut dependencies) = read_dependencies(root)?;
+    if lockfiles.is_empty() {
+        return Err(format!("No lockfile found in {}", root.display()));
+    }
+    resolve_licenses(root, &mut dependencies);
+    ctx.progress(
+        Some(LOCAL_PROGRESS),
+        Some("Checking for vulnerabilities".to_string()),
+    );
+
+    // An unreachable OSV leaves the report without vulnerabilities rather
+    // than failing it; the dependencies and licenses are still useful.
+    let (vulnerabilities, osv_error) = match lookup_vulnerabilities(ctx, &dependencies) {
+        Ok(Some(vulnerabilities)) => (vulnerabilities, None),
+        Ok(None) => return Ok(None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    Ok(Some(DependencyReport::new(
+        root,
+        lockfiles,
+        dependencies,
+        vulnerabilities,
+        osv_error,
+    )))
+}
+
+/// Scan the lockfiles of the project at `path` for dependencies, their
+/// licenses and known vulnerabilities. Returns the job id.
+#[tauri::command]
+pub fn scan_dependencies(app: AppHandle, path: String) -> Result<JobId, KiriError> {
+    let root = normalize_input_path(&path);
+    let label = format!("Dependency scan of {}", root.display());
+    let job_app = app.clone();
+    spawn_job(&app, DEPENDENCY_SCAN_JOB_KIND, &label, move |ctx| {
+        let Some(report) = scan(ctx, &root)? else {
+            return Ok(());
+        };
+        let _ = job_app.emit(DEPENDENCY_SCAN_FINISHED_EVENT, &report);
+        if let Some(state) = job_app.try_state::<DependencyReportsState>() {
+            state.lock_recover().store(report);
+        }
+        Ok(())
+    })
+    .map_err(KiriError::from)
+}
+
+/// The last report for the project at `path`, if it was scanned.
+#[tauri::command]
+pub fn get_dependency_report(
+    state: tauri::State<'_, DependencyReportsState>,
+    path: String,
+) -> Option<DependencyReport> {
+    state.lock_recover().get(&normalize_input_path(&path))
+}
@@ -0,0 +1,736 @@
+//! Dev containers (`devcontainer.json`) for worktrees.
+//!
+//! The config is JSON with comments, read from `.devcontainer/` or the
+//! worktree root. Image and Dockerfile configs get one container per
+//! worktree, started with the worktree bind-mounted at its workspace
+//! folder. Compose configs bring up their service with `docker compose`.
+//! Containers carry the labels the devcontainer CLI uses, so a container
+//! started by VS Code for the same folder is found and reused.
+//!
+//! A linked worktree's `.git` file points into the main repository, so
+//! the repository's git dir is also mounted at its host path, and git
+//! works inside the container.
+//!
+//! Lifecycle commands and terminals run through `docker exec`, with
+//! `remoteEnv` exported by the shell so `${containerEnv:PATH}` resolves in
+//! the container. Only the `postCreateCommand` and `postStartCommand`
+//! hooks run. The config builds images and runs its own commands, so `up`
+//! loads it with [`DevContainer::load_trusted`]. The jobs and commands live
+//! in devcontainer_commands.rs.
+
+use portable_pty::CommandBuilder;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::docker::{project_name, PROJECT_LABEL, SERVICE_LABEL};
+use super::error::{user_path_error, user_path_io_error, KiriError};
+use super::git_worktree::open_common_repository;
+use super::trust::{require_trusted_in, TrustStoreState};
+
+/// Config locations, in the order they are looked up.
+pub const CONFIG_PATHS: [&str; 2] = [".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+pub const LOCAL_FOLDER_LABEL: &str = "devcontainer.local_folder";
+pub const CONFIG_FILE_LABEL: &str = "devcontainer.config_file";
+
+/// Keeps a container with `overrideCommand` alive until it is stopped;
+/// what the devcontainer CLI runs.
+const KEEP_ALIVE: &str =
+    "echo Container started\ntrap \"exit 0\" 15\nwhile sleep 1 & wait $!; do :; done";
+
+/// Shell started by terminals: bash if the image has it.
+const LOGIN_SHELL: &str = "command -v bash >/dev/null && exec bash -l || exec sh -l";
+
+/// Copy a string literal starting at the `"` just taken from `chars`.
+fn copy_string(chars: &mut impl Iterator<Item = char>, out: &mut String) {
+    out.push('"');
+    while let Some(c) = chars.next() {
+        out.push(c);
+        match c {
+            '\\' => out.extend(chars.next()),
+            '"' => break,
+            _ => {}
+        }
+    }
+}
+
+/// `text` with `//` and `/* */` comments and trailing commas removed, so
+/// it parses as JSON.
+pub fn strip_jsonc(text: &str) -> String {
+    let mut code = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => copy_string(&mut chars, &mut code),
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                code.push(' ');
+            }
+            _ => code.push(c),
+        }
+    }
+
+    let mut out = String::with_capacity(code.len());
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => copy_string(&mut chars, &mut out),
+            ',' => {
+                let mut whitespace = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_whitespace()) {
+                    whitespace.push(c);
+                }
+                if !matches!(chars.peek(), Some('}') | Some(']')) {
+                    out.push(',');
+                }
+                out.push_str(&whitespace);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The dev container config of `dir`, if it has one.
+pub fn find_config(dir: &Path) -> Option<PathBuf> {
+    CONFIG_PATHS
+        .iter()
+        .map(|path| dir.join(path))
+        .find(|path| path.is_file())
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildConfig {
+    pub dockerfile: Option<String>,
+    pub context: Option<String>,
+    #[serde(default)]
+    pub args: BTreeMap<String, String>,
+    pub target: Option<String>,
+}
+
+/// The parts of `devcontainer.json` kiri acts on.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevContainerConfig {
+    pub name: Option<String>,
+    pub image: Option<String>,
+    pub build: Option<BuildConfig>,
+    /// Pre-`build` spelling of `build.dockerfile`
+    pub docker_file: Option<String>,
+    /// Pre-`build` spelling of `build.context`
+    pub context: Option<String>,
+    /// One file or a list
+    pub docker_compose_file: Option<Value>,
+    pub service: Option<String>,
+    #[serde(default)]
+    pub run_services: Vec<String>,
+    pub workspace_folder: Option<String>,
+    pub workspace_mount: Option<String>,
+    #[serde(default)]
+    pub container_env: BTreeMap<String, String>,
+    /// A null value unsets the variable
+    #[serde(default)]
+    pub remote_env: BTreeMap<String, Option<String>>,
+    pub remote_user: Option<String>,
+    pub container_user: Option<String>,
+    /// `--mount` strings or `{type, source, target}` objects
+    #[serde(default)]
+    pub mounts: Vec<Value>,
+    #[serde(default)]
+    pub run_args: Vec<String>,
+    pub override_command: Option<bool>,
+    pub post_create_command: Option<Value>,
+    pub post_start_command: Option<Value>,
+}
+
+/// How the container comes to be.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Image(String),
+    Dockerfile {
+        dockerfile: PathBuf,
+        context: PathBuf,
+        args: BTreeMap<String, String>,
+        target: Option<String>,
+    },
+    Compose {
+        files: Vec<PathBuf>,
+        service: String,
+        run_services: Vec<String>,
+    },
+}
+
+/// Replace `${localWorkspaceFolder}`, `${localWorkspaceFolderBasename}`,
+/// `${containerWorkspaceFolder}`, `${devcontainerId}` and
+/// `${localEnv:NAME[:default]}` in `text`. Others, such as
+/// `${containerEnv:NAME}`, are kept.
+fn substitute(text: &str, vars: &BTreeMap<&str, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &rest[start + 2..start + len];
+        let value = match name.strip_prefix("localEnv:") {
+            Some(env) => {
+                let (env, default) = env.split_once(':').unwrap_or((env, ""));
+                Some(std::env::var(env).unwrap_or_else(|_| default.to_string()))
+            }
+            None => vars.get(name).cloned(),
+        };
+        match value {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn substitute_all(value: &mut Value, vars: &BTreeMap<&str, String>) {
+    match value {
+        Value::String(text) => *text = substitute(text, vars),
+        Value::Array(items) => items.iter_mut().for_each(|v| substitute_all(v, vars)),
+        Value::Object(map) => map.values_mut().for_each(|v| substitute_all(v, vars)),
+        _ => {}
+    }
+}
+
+/// Argument lists of a lifecycle command: a string runs in a shell, an
+/// array runs as is, and an object holds several of either.
+pub fn lifecycle_commands(command: &Value) -> Vec<Vec<String>> {
+    match command {
+        Value::String(script) if !script.trim().is_empty() => {
+            vec![vec![
+                "/bin/sh".to_string(),
+                "-c".to_string(),
+                script.clone(),
+            ]]
+        }
+        Value::Array(args) => {
+            let args: Vec<String> = args
+                .iter()
+                .filter_map(|a| a.as_str().map(str::to_string))
+                .collect();
+            if args.is_empty() {
+                Vec::new()
+            } else {
+                vec![args]
+            }
+        }
+        Value::Object(commands) => commands.values().flat_map(lifecycle_commands).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `value` in double quotes for `sh`, leaving `$` expansions live.
+fn sh_double_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn fnv1a_hex(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// A worktree's loaded dev container config.
+#[derive(Debug, Clone)]
+pub struct DevContainer {
+    /// The worktree
+    pub dir: PathBuf,
+    pub config_file: PathBuf,
+    pub config: DevContainerConfig,
+    /// Where the worktree is mounted in the container
+    pub workspace_folder: String,
+}
+
+impl DevContainer {
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let config_file = find_config(dir)
+            .ok_or_else(|| user_path_error("No devcontainer.json in the folder", dir))?;
+        let text = std::fs::read_to_string(&config_file)
+            .map_err(|e| user_path_io_error("Failed to read devcontainer.json", &config_file, e))?;
+        Self::parse(dir, config_file, &text)
+    }
+
+    /// [`DevContainer::load`], if the worktree is trusted to run its config.
+    pub fn load_trusted(trust: &TrustStoreState, dir: &Path) -> Result<Self, KiriError> {
+        require_trusted_in(trust, dir)?;
+        Ok(Self::load(dir)?)
+    }
+
+    pub fn parse(dir: &Path, config_file: PathBuf, text: &str) -> Result<Self, String> {
+        let mut value: Value = serde_json::from_str(&strip_jsonc(text))
+            .map_err(|e| user_path_io_error("Invalid devcontainer.json", &config_file, e))?;
+        let basename = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut vars = BTreeMap::from([
+            ("localWorkspaceFolder", dir.to_string_lossy().to_string()),
+            ("localWorkspaceFolderBasename", basename.clone()),
+            ("devcontainerId", fnv1a_hex(&dir.to_string_lossy())),
+        ]);
+        let workspace_folder = value
+            .get("workspaceFolder")
+            .and_then(Value::as_str)
+            .map(|folder| substitute(folder, &vars))
+            .unwrap_or_else(|| format!("/workspaces/{}", basename));
+        vars.insert("containerWorkspaceFolder", workspace_folder.clone());
+        substitute_all(&mut value, &vars);
+        let config: DevContainerConfig = serde_json::from_value(value)
+            .map_err(|e| user_path_io_error("Invalid devcontainer.json", &config_file, e))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            config_file,
+            config,
+            workspace_folder,
+        })
+    }
+
+    /// Paths in the config are relative to the config file.
+    fn config_relative(&self, path: &str) -> PathBuf {
+        self.config_file.parent().unwrap_or(&self.dir).join(path)
+    }
+
+    pub fn name(&self) -> String {
+        self.config
+            .name
+            .clone()
+            .unwrap_or_else(|| project_name(&self.dir))
+    }
+
+    pub fn source(&self) -> Result<Source, String> {
+        let config = &self.config;
+        if let Some(files) = &config.docker_compose_file {
+            let files: Vec<PathBuf> = match files {
+                Value::String(file) => vec![self.config_relative(file)],
+                Value::Array(files) => files
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|file| self.config_relative(file))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let service = config
+                .service
+                .clone()
+                .ok_or("devcontainer.json sets dockerComposeFile without service")?;
+            return Ok(Source::Compose {
+                files,
+                service,
+                run_services: config.run_services.clone(),
+            });
+        }
+        let build = config.build.clone().unwrap_or_default();
+        if let Some(dockerfile) = build.dockerfile.or_else(|| config.docker_file.clone()) {
+            let context = build
+                .context
+                .or_else(|| config.context.clone())
+                .unwrap_or_else(|| ".".to_string());
+            return Ok(Source::Dockerfile {
+                dockerfile: self.config_relative(&dockerfile),
+                context: self.config_relative(&context),
+                args: build.args,
+                target: build.target,
+            });
+        }
+        config
+            .image
+            .clone()
+            .map(Source::Image)
+            .ok_or_else(|| "devcontainer.json sets no image, build or dockerComposeFile".into())
+    }
+
+    /// Tag for images built from the Dockerfile.
+    pub fn image_tag(&self) -> String {
+        format!("kiri-dev-{}", project_name(&self.dir))
+    }
+
+    /// Compose project of a compose config, as the devcontainer CLI names it.
+    pub fn compose_project(&self) -> String {
+        format!("{}_devcontainer", project_name(&self.dir))
+    }
+
+    /// Label filters that find this worktree's container.
+    pub fn container_labels(&self) -> Vec<String> {
+        match self.source() {
+            Ok(Source::Compose { service, .. }) => vec![
+                format!("{}={}", PROJECT_LABEL, self.compose_project()),
+                format!("{}={}", SERVICE_LABEL, service),
+            ],
+            _ => vec![format!("{}={}", LOCAL_FOLDER_LABEL, self.dir.display())],
+        }
+    }
+
+    /// `docker run` arguments for an image or Dockerfile config. `git_dir`
+    /// is the repository's git dir when `dir` is a linked worktree.
+    pub fn run_args(&self, image: &str, git_dir: Option<&Path>) -> Vec<String> {
+        let config = &self.config;
+        let mut args = vec![
+            "run".to_string(),
+            "--detach".to_string(),
+            "--label".to_string(),
+            format!("{}={}", LOCAL_FOLDER_LABEL, self.dir.display()),
+            "--label".to_string(),
+            format!("{}={}", CONFIG_FILE_LABEL, self.config_file.display()),
+            "--mount".to_string(),
+            config.workspace_mount.clone().unwrap_or_else(|| {
+                format!(
+                    "type=bind,source={},target={}",
+                    self.dir.display(),
+                    self.workspace_folder
+                )
+            }),
+            "--workdir".to_string(),
+            self.workspace_folder.clone(),
+        ];
+        if let Some(git_dir) = git_dir {
+            args.push("--mount".to_string());
+            args.push(format!(
+                "type=bind,source={0},target={0}",
+                git_dir.display()
+            ));
+        }
+        for mount in &config.mounts {
+            let mount = match mount {
+                Value::String(mount) => mount.clone(),
+                Value::Object(fields) => fields
+                    .iter()
+                    .filter_map(|(key, value)| Some(format!("{}={}", key, value.as_str()?)))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                _ => continue,
+            };
+            args.push("--mount".to_string());
+            args.push(mount);
+        }
+        for (key, value) in &config.container_env {
+            args.push("--env".to_string());
+            args.push(format!("{}={}", key, value));
+        }
+        if let Some(user) = &config.container_user {
+            args.push("--user".to_string());
+            args.push(user.clone());
+        }
+        args.extend(config.run_args.iter().cloned());
+        if config.override_command.unwrap_or(true) {
+            args.extend(["--entrypoint".to_string(), "/bin/sh".to_string()]);
+            args.push(image.to_string());
+            args.extend(["-c".to_string(), KEEP_ALIVE.to_string(), "-".to_string()]);
+        } else {
+            args.push(image.to_string());
+        }
+        args
+    }
+
+    /// `export` statements for `remoteEnv`, with `${containerEnv:NAME}`
+    /// turned into the shell's `${NAME}`.
+    pub fn remote_env_script(&self) -> String {
+        self.config
+            .remote_env
+            .iter()
+            .map(|(key, value)| match value {
+                Some(value) => format!(
+                    "export {}={}; ",
+                    key,
+                    sh_double_quote(&value.replace("${containerEnv:", "${"))
+                ),
+                None => format!("unset {}; ", key),
+            })
+            .collect()
+    }
+
+    /// The user commands run as: `remoteUser`, else `containerUser`.
+    pub fn remote_user(&self) -> Option<&str> {
+        self.config
+            .remote_user
+            .as_deref()
+            .or(self.config.container_user.as_deref())
+    }
+
+    /// `docker exec` arguments running `command` in `container` as the
+    /// remote user, with `remoteEnv` set.
+    pub fn exec_args(&self, container: &str, tty: bool, command: &[String]) -> Vec<String> {
+        let mut args = vec!["exec".to_string()];
+        if tty {
+            args.push("-it".to_string());
+        }
+        if let Some(user) = self.remote_user() {
+            args.extend(["--user".to_string(), user.to_string()]);
+        }
+        args.extend(["--workdir".to_string(), self.workspace_folder.clone()]);
+        if tty {
+            args.extend([
+                "--env".to_string(),
+                "TERM=xterm-256color".to_string(),
+                "--env".to_string(),
+                "TERM_PROGRAM=kiri".to_string(),
+            ]);
+        }
+        args.push(container.to_string());
+        args.extend([
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            format!("{}exec \"$@\"", self.remote_env_script()),
+            "sh".to_string(),
+        ]);
+        args.extend(command.iter().cloned());
+        args
+    }
+
+    /// `docker exec` arguments for an interactive login shell.
+    pub fn shell_args(&self, container: &str) -> Vec<String> {
+        let shell = [
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            LOGIN_SHELL.to_string(),
+        ];
+        self.exec_args(container, true, &shell)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerRef {
+    pub id: String,
+    /// `running`, `exited`, ...
+    pub state: String,
+}
+
+/// The first container with all of `labels`, running or not.
+pub fn find_container(labels: &[String]) -> Result<Option<ContainerRef>, String> {
+    let mut command = Command::new("docker");
+    command.args(["ps", "--all", "--format", "{{.ID}}\t{{.State}}"]);
+    for label in labels {
+        command.arg("--filter").arg(format!("label={}", label));
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run docker: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker ps failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let (id, state) = line.split_once('\t')?;
+            Some(ContainerRef {
+                id: id.to_string(),
+                state: state.to_string(),
+            })
+        }))
+}
+
+/// Command for a terminal in the running dev container of `dir`.
+pub fn terminal_command(dir: &Path) -> Result<CommandBuilder, String> {
+    let devcontainer = DevContainer::load(dir)?;
+    let container = find_container(&devcontainer.container_labels())?
+        .filter(|container| container.state == "running")
+        .ok_or_else(|| user_path_error("The dev container is not running", dir))?;
+    let mut cmd = CommandBuilder::new("docker");
+    cmd.args(devcontainer.shell_args(&container.id));
+    cmd.cwd(dir);
+    Ok(cmd)
+}
+
+/// The git dir a linked worktree at `dir` needs mounted, i.e. the main
+/// repository's.
+pub fn worktree_git_dir(dir: &Path) -> Option<PathBuf> {
+    let repo = git2::Repository::open(dir).ok()?;
+    if !repo.is_worktree() {
+        return None;
+    }
+    std::fs::canonicalize(open_common_repository(dir).ok()?.path()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error::ErrorCode;
+    use crate::commands::lock_ext::LockExt;
+    use crate::commands::trust::TrustLevel;
+    use tempfile::TempDir;
+
+    fn parse(text: &str) -> DevContainer {
+        let dir = Path::new("/src/app-feature");
+        DevContainer::parse(dir, dir.join(".devcontainer/devcontainer.json"), text).unwrap()
+    }
+
+    #[test]
+    fn test_strip_jsonc() {
+        let text = r#"{
+            // comment
+            "image": "node:20", /* block */
+            "url": "http://x//y", "esc": "a\"//b",
+            "args": [1, 2,],
+        }"#;
+        let value: Value = serde_json::from_str(&strip_jsonc(text)).unwrap();
+        assert_eq!(value["image"], "node:20");
+        assert_eq!(value["url"], "http://x//y");
+        assert_eq!(value["esc"], "a\"//b");
+        assert_eq!(value["args"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_parse_substitutes_variables() {
+        let dc = parse(
+            r#"{
+                "image": "node:20",
+                "containerEnv": {"SRC": "${containerWorkspaceFolder}/src"},
+                "remoteEnv": {"PATH": "${containerEnv:PATH}:/opt/bin", "X": null},
+                "mounts": ["source=${localWorkspaceFolderBasename}-cache,target=/cache,type=volume"]
+            }"#,
+        );
+        assert_eq!(dc.workspace_folder, "/workspaces/app-feature");
+        assert_eq!(
+            dc.config.container_env["SRC"],
+            "/workspaces/app-feature/src"
+        );
+        assert_eq!(
+            dc.remote_env_script(),
+            "export PATH=\"${PATH}:/opt/bin\"; unset X; "
+        );
+        assert_eq!(dc.source(), Ok(Source::Image("node:20".to_string())));
+    }
+
+    #[test]
+    fn test_source_variants() {
+        let dc = parse(r#"{"build": {"dockerfile": "Dockerfile", "context": ".."}}"#);
+        assert_eq!(
+            dc.source(),
+            Ok(Source::Dockerfile {
+                dockerfile: PathBuf::from("/src/app-feature/.devcontainer/Dockerfile"),
+                context: PathBuf::from("/src/app-feature/.devcontainer/.."),
+                args: BTreeMap::new(),
+                target: None,
+            })
+        );
+
+        let dc = parse(r#"{"dockerComposeFile": ["../compose.yaml"], "service": "app"}"#);
+        assert!(matches!(dc.source(), Ok(Source::Compose { ref service, .. }) if service == "app"));
+        assert_eq!(
+            dc.container_labels(),
+            vec![
+                format!("{}=app-feature_devcontainer", PROJECT_LABEL),
+                format!("{}=app", SERVICE_LABEL),
+            ]
+        );
+
+        assert!(parse("{}").source().is_err());
+    }
+
+    #[test]
+    fn test_run_args() {
+        let dc = parse(
+            r#"{"image": "node:20", "workspaceFolder": "/app", "containerUser": "node",
+                "mounts": [{"type": "volume", "source": "x", "target": "/x"}], "runArgs": ["--init"]}"#,
+        );
+        let args = dc.run_args("node:20", Some(Path::new("/src/app/.git")));
+        let joined = args.join(" ");
+        assert!(joined.contains("--label devcontainer.local_folder=/src/app-feature"));
+        assert!(joined.contains("--mount type=bind,source=/src/app-feature,target=/app"));
+        assert!(joined.contains("--mount type=bind,source=/src/app/.git,target=/src/app/.git"));
+        assert!(joined.contains("--mount source=x,target=/x,type=volume"));
+        assert!(joined.contains("--user node --init --entrypoint /bin/sh node:20 -c"));
+        assert_eq!(args.last().map(String::as_str), Some("-"));
+    }
+
+    #[test]
+    fn test_lifecycle_and_exec_args() {
+        let commands = lifecycle_commands(&serde_json::json!({
+            "deps": "npm ci",
+            "hooks": ["git", "config", "core.hooksPath", ".githooks"]
+        }));
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0], vec!["/bin/sh", "-c", "npm ci"]);
+        assert!(lifecycle_commands(&serde_json::json!("")).is_empty());
+
+        let dc = parse(r#"{"image": "x", "remoteUser": "vscode", "remoteEnv": {"A": "1"}}"#);
+        let args = dc.exec_args("abc", false, &commands[0]);
+        assert_eq!(
+            args,
+            vec![
+                "exec",
+                "--user",
+                "vscode",
+                "--workdir",
+                "/workspaces/app-feature",
+                "abc",
+                "/bin/sh",
+                "-c",
+                "export A=\"1\"; exec \"$@\"",
+                "sh",
+                "/bin/sh",
+                "-c",
+                "npm ci",
+            ]
+        );
+        assert!(dc.shell_args("abc").contains(&"-it".to_string()));
+    }
+
+    #[test]
+    fn test_find_config() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(find_config(dir.path()), None);
+        std::fs::write(dir.path().join(".devcontainer.json"), "{}").unwrap();
+        std::fs::create_dir(dir.path().join(".devcontainer")).unwrap();
+        std::fs::write(
+            dir.path().join(".devcontainer/devcontainer.json"),
+            r#"{"image": "rust:1", // toolchain
+            }"#,
+        )
+        .unwrap();
+        let dc = DevContainer::load(dir.path()).unwrap();
+        assert_eq!(dc.config.image.as_deref(), Some("rust:1"));
+        assert_eq!(worktree_git_dir(dir.path()), None);
+    }
+
+    #[test]
+    fn test_untrusted_worktree_does_not_load() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join(".devcontainer.json"),
+            r#"{"image": "rust:1"}"#,
+        )
+        .unwrap();
+        let trust = TrustStoreState::default();
+        let error = DevContainer::load_trusted(&trust, dir.path()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::NotTrusted);
+
+        trust
+            .lock_recover()
+            .set(dir.path(), TrustLevel::Trusted)
+            .unwrap();
+        let dc = DevContainer::load_trusted(&trust, dir.path()).unwrap();
+        assert_eq!(dc.config.image.as_deref(), Some("rust:1"));
+    }
+}
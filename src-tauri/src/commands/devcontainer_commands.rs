@@ -0,0 +1,231 @@
+//! Tauri commands for worktree dev containers; config handling lives in
+//! devcontainer.rs.
+//!
+//! Bringing a container up or down runs `docker` as a background job (see
+//! jobs_commands.rs) whose progress messages are its output, including that
+//! of the lifecycle commands. Terminals in the container are opened by
+//! `create_terminal` with `devcontainer` set.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Command;
+use tauri::AppHandle;
+
+use super::devcontainer::{
+    find_config, find_container, lifecycle_commands, worktree_git_dir, ContainerRef, DevContainer,
+    Source,
+};
+use super::docker_commands::run_docker;
+use super::error::KiriError;
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::spawn_job;
+use super::latency::run_blocking;
+use super::trust_commands::trust_store;
+
+const DEVCONTAINER_JOB: &str = "devcontainer";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DevContainerInfo {
+    pub name: String,
+    pub config_file: String,
+    pub workspace_folder: String,
+    /// Container id, if one exists for the worktree
+    pub container_id: Option<String>,
+    pub running: bool,
+}
+
+fn docker(args: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>) -> Command {
+    let mut command = Command::new("docker");
+    command.args(args);
+    command
+}
+
+fn compose_command(devcontainer: &DevContainer, files: &[PathBuf]) -> Command {
+    let mut command = docker(["compose", "--ansi", "never", "--project-name"]);
+    command
+        .current_dir(&devcontainer.dir)
+        .arg(devcontainer.compose_project());
+    for file in files {
+        command.arg("--file").arg(file);
+    }
+    command
+}
+
+/// Build the image of an image or Dockerfile config, then start a new
+/// container from it.
+fn create_container(ctx: &JobContext, devcontainer: &DevContainer) -> Result<(), String> {
+    let image = match devcontainer.source()? {
+        Source::Image(image) => image,
+        Source::Dockerfile {
+            dockerfile,
+            context,
+            args,
+            target,
+        } => {
+            let tag = devcontainer.image_tag();
+            let mut command = docker(["build", "--tag", &tag, "--file"]);
+            command.arg(dockerfile);
+            for (key, value) in args {
+                command.arg("--build-arg").arg(format!("{}={}", key, value));
+            }
+            if let Some(target) = target {
+                command.args(["--target", &target]);
+            }
+            run_docker(ctx, command.arg(context))?;
+            tag
+        }
+        Source::Compose { .. } => {
+            return Err("Compose dev containers are started by docker compose".to_string())
+        }
+    };
+    if ctx.is_cancelled() {
+        return Ok(());
+    }
+    let git_dir = worktree_git_dir(&devcontainer.dir);
+    run_docker(
+        ctx,
+        &mut docker(devcontainer.run_args(&image, git_dir.as_deref())),
+    )
+}
+
+/// Run the lifecycle `command` of the config in `container`.
+fn run_lifecycle(
+    ctx: &JobContext,
+    devcontainer: &DevContainer,
+    container: &str,
+    command: &Option<serde_json::Value>,
+) -> Result<(), String> {
+    for args in command.iter().flat_map(lifecycle_commands) {
+        if ctx.is_cancelled() {
+            break;
+        }
+        run_docker(
+            ctx,
+            &mut docker(devcontainer.exec_args(container, false, &args)),
+        )?;
+    }
+    Ok(())
+}
+
+/// Create or start the worktree's container, then run its lifecycle
+/// commands: `postCreateCommand` for a new container, `postStartCommand`
+/// whenever it was not running.
+fn up(ctx: &JobContext, devcontainer: &DevContainer, rebuild: bool) -> Result<(), String> {
+    let labels = devcontainer.container_labels();
+    let existing = find_container(&labels)?;
+    let created = existing.is_none() || rebuild;
+    let started = created || existing.as_ref().is_some_and(|c| c.state != "running");
+    match (devcontainer.source()?, existing) {
+        (
+            Source::Compose {
+                files,
+                service,
+                run_services,
+            },
+            _,
+        ) => {
+            let mut command = compose_command(devcontainer, &files);
+            command.args(["up", "--detach"]);
+            if rebuild {
+                command.args(["--build", "--force-recreate"]);
+            }
+            run_docker(ctx, command.arg(service).args(run_services))?;
+        }
+        (_, Some(container)) if rebuild => {
+            run_docker(ctx, &mut docker(["rm", "--force", &container.id]))?;
+            create_container(ctx, devcontainer)?;
+        }
+        (_, Some(container)) if started => {
+            run_docker(ctx, &mut docker(["start", &container.id]))?;
+        }
+        (_, Some(_)) => {}
+        (_, None) => create_container(ctx, devcontainer)?,
+    }
+    if ctx.is_cancelled() || !started {
+        return Ok(());
+    }
+    let container = find_container(&labels)?
+        .ok_or("The dev container did not start")?
+        .id;
+    let config = &devcontainer.config;
+    if created {
+        run_lifecycle(ctx, devcontainer, &container, &config.post_create_command)?;
+    }
+    run_lifecycle(ctx, devcontainer, &container, &config.post_start_command)
+}
+
+fn down(ctx: &JobContext, devcontainer: &DevContainer, remove: bool) -> Result<(), String> {
+    if let Source::Compose { files, .. } = devcontainer.source()? {
+        let mut command = compose_command(devcontainer, &files);
+        command.arg(if remove { "down" } else { "stop" });
+        return run_docker(ctx, &mut command);
+    }
+    let Some(container) = find_container(&devcontainer.container_labels())? else {
+        return Ok(());
+    };
+    let mut command = if remove {
+        docker(["rm", "--force"])
+    } else {
+        docker(["stop", "--time", "5"])
+    };
+    run_docker(ctx, command.arg(&container.id))
+}
+
+/// The worktree's dev container config and container, or `None` if it has
+/// no `devcontainer.json`.
+#[tauri::command]
+pub async fn get_devcontainer(
+    worktree_path: String,
+) -> Result<Option<DevContainerInfo>, KiriError> {
+    let dir = normalize_input_path(&worktree_path);
+    run_blocking("get_devcontainer", move || {
+        if find_config(&dir).is_none() {
+            return Ok(None);
+        }
+        let devcontainer = DevContainer::load(&dir)?;
+        let container = find_container(&devcontainer.container_labels())?;
+        Ok(Some(DevContainerInfo {
+            name: devcontainer.name(),
+            config_file: path_to_wire(&devcontainer.config_file),
+            workspace_folder: devcontainer.workspace_folder.clone(),
+            running: container.as_ref().is_some_and(|c| c.state == "running"),
+            container_id: container.map(|ContainerRef { id, .. }| id),
+        }))
+    })
+    .await
+}
+
+/// Build, create or start the worktree's dev container in the background.
+/// With `rebuild`, an existing container is replaced. Returns the job to
+/// follow.
+#[tauri::command]
+pub fn devcontainer_up(
+    app: AppHandle,
+    worktree_path: String,
+    rebuild: Option<bool>,
+) -> Result<JobId, KiriError> {
+    let dir = normalize_input_path(&worktree_path);
+    let devcontainer = DevContainer::load_trusted(&trust_store(&app)?, &dir)?;
+    let label = format!("Dev container up ({})", devcontainer.name());
+    spawn_job(&app, DEVCONTAINER_JOB, &label, move |ctx| {
+        up(ctx, &devcontainer, rebuild.unwrap_or(false))
+    })
+    .map_err(KiriError::from)
+}
+
+/// Stop the worktree's dev container in the background, removing it if
+/// `remove` is set.
+#[tauri::command]
+pub fn devcontainer_down(
+    app: AppHandle,
+    worktree_path: String,
+    remove: Option<bool>,
+) -> Result<JobId, KiriError> {
+    let devcontainer = DevContainer::load(&normalize_input_path(&worktree_path))?;
+    let label = format!("Dev container down ({})", devcontainer.name());
+    spawn_job(&app, DEVCONTAINER_JOB, &label, move |ctx| {
+        down(ctx, &devcontainer, remove.unwrap_or(false))
+    })
+    .map_err(KiriError::from)
+}
@@ -0,0 +1,308 @@
+//! direnv (`.envrc`) support for terminals.
+//!
+//! The `.envrc` nearest a terminal's directory, up to its checkout root, is
+//! evaluated with `direnv export json` and the result is applied to the
+//! shell's environment. As with direnv itself nothing is evaluated until
+//! the user approves the file: kiri records the approved contents in
+//! `~/.kiri/direnv.json` (as git blob ids) and runs `direnv allow`, so an
+//! edited `.envrc` has to be approved again.
+//!
+//! Evaluation can be slow (`use nix`, `layout python`), so exports are
+//! cached per file contents until reloaded.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use super::error::user_path_io_error;
+use super::file_io::write_file_contents_atomic;
+use super::lock_ext::LockExt;
+use super::terminal::EnvOverrides;
+
+pub const ENVRC: &str = ".envrc";
+
+/// Variables direnv uses to track what it loaded. Cleared before an
+/// export so kiri's own environment (e.g. launched from a direnv shell)
+/// doesn't count as already loaded.
+const DIRENV_VARS: [&str; 4] = ["DIRENV_DIR", "DIRENV_FILE", "DIRENV_DIFF", "DIRENV_WATCHES"];
+
+/// The `.envrc` applying to `dir`: the nearest one in `dir` or its
+/// parents, stopping at the checkout root (the directory with `.git`).
+pub fn find_envrc(dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        let envrc = dir.join(ENVRC);
+        if envrc.is_file() {
+            return Some(envrc);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Id of the contents of `envrc`; approvals are tied to it.
+pub fn contents_id(envrc: &Path) -> Result<String, String> {
+    let contents =
+        std::fs::read(envrc).map_err(|e| user_path_io_error("Failed to read .envrc", envrc, e))?;
+    git2::Oid::hash_object(git2::ObjectType::Blob, &contents)
+        .map(|oid| oid.to_string())
+        .map_err(|e| user_path_io_error("Failed to hash .envrc", envrc, e))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Approval {
+    Approved,
+    /// Approved, but edited since
+    Changed,
+    Unapproved,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvrcStatus {
+    pub path: String,
+    pub approval: Approval,
+    pub direnv_installed: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ApprovalsFile {
+    /// `.envrc` path to the id of its approved contents
+    #[serde(default)]
+    approved: BTreeMap<PathBuf, String>,
+}
+
+/// Approved `.envrc` files and cached exports.
+#[derive(Debug, Default)]
+pub struct Direnv {
+    file: Option<PathBuf>,
+    approved: BTreeMap<PathBuf, String>,
+    /// `.envrc` path to the contents id and export it was evaluated for
+    exports: HashMap<PathBuf, (String, EnvOverrides)>,
+}
+
+pub type DirenvState = Arc<Mutex<Direnv>>;
+
+pub fn approvals_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("direnv.json"))
+}
+
+impl Direnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Approvals saved in `path`; none if it is missing or unreadable.
+    pub fn load(path: PathBuf) -> Self {
+        let approved = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<ApprovalsFile>(&contents) {
+                    Ok(file) => Some(file.approved),
+                    Err(e) => {
+                        log::warn!("failed to parse direnv approvals: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            approved,
+            exports: HashMap::new(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                user_path_io_error("Failed to create the settings folder", parent, e)
+            })?;
+        }
+        let file = ApprovalsFile {
+            approved: self.approved.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)
+            .map_err(|e| format!("Failed to serialize direnv approvals: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn approval(&self, envrc: &Path, id: &str) -> Approval {
+        match self.approved.get(envrc) {
+            Some(approved) if approved == id => Approval::Approved,
+            Some(_) => Approval::Changed,
+            None => Approval::Unapproved,
+        }
+    }
+
+    pub fn approve(&mut self, envrc: &Path, id: &str) -> Result<(), String> {
+        self.approved.insert(envrc.to_path_buf(), id.to_string());
+        self.save()
+    }
+
+    pub fn revoke(&mut self, envrc: &Path) -> Result<(), String> {
+        self.exports.remove(envrc);
+        if self.approved.remove(envrc).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn cached(&self, envrc: &Path, id: &str) -> Option<EnvOverrides> {
+        self.exports
+            .get(envrc)
+            .filter(|(cached_id, _)| cached_id == id)
+            .map(|(_, env)| env.clone())
+    }
+
+    pub fn cache(&mut self, envrc: &Path, id: &str, env: EnvOverrides) {
+        self.exports
+            .insert(envrc.to_path_buf(), (id.to_string(), env));
+    }
+
+    /// Drop the cached export so the next terminal evaluates `envrc` again.
+    pub fn reload(&mut self, envrc: &Path) {
+        self.exports.remove(envrc);
+    }
+}
+
+pub fn direnv_installed() -> bool {
+    Command::new("direnv")
+        .arg("version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+fn run_direnv(dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("direnv");
+    command.args(args).current_dir(dir);
+    for var in DIRENV_VARS {
+        command.env_remove(var);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run direnv: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "direnv {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Let direnv load `envrc` (`direnv allow`), or stop it (`deny`).
+pub fn set_allowed(envrc: &Path, allowed: bool) -> Result<(), String> {
+    let dir = envrc.parent().unwrap_or(Path::new("/"));
+    let path = envrc.to_string_lossy();
+    let action = if allowed { "allow" } else { "deny" };
+    run_direnv(dir, &[action, &path]).map(|_| ())
+}
+
+/// The variables the `.envrc` of `dir` sets (`Some`) and unsets (`None`).
+pub fn export(dir: &Path) -> Result<EnvOverrides, String> {
+    parse_export(&run_direnv(dir, &["export", "json"])?)
+}
+
+/// Output of `direnv export json`: empty when there is nothing to load.
+fn parse_export(output: &[u8]) -> Result<EnvOverrides, String> {
+    if output.iter().all(u8::is_ascii_whitespace) {
+        return Ok(EnvOverrides::new());
+    }
+    serde_json::from_slice(output).map_err(|e| format!("Failed to parse direnv export: {}", e))
+}
+
+/// Environment from the approved `.envrc` of `dir`, for a new terminal.
+/// Empty when there is none, it isn't approved, or evaluating it fails;
+/// failures are logged, as the terminal opens regardless.
+pub fn terminal_env(state: &DirenvState, dir: &Path) -> EnvOverrides {
+    let Some(envrc) = find_envrc(dir) else {
+        return EnvOverrides::new();
+    };
+    let id = match contents_id(&envrc) {
+        Ok(id) => id,
+        Err(e) => {
+            log::warn!("{}", e);
+            return EnvOverrides::new();
+        }
+    };
+    {
+        let direnv = state.lock_recover();
+        if direnv.approval(&envrc, &id) != Approval::Approved {
+            return EnvOverrides::new();
+        }
+        if let Some(env) = direnv.cached(&envrc, &id) {
+            return env;
+        }
+    }
+    match export(dir) {
+        Ok(env) => {
+            state.lock_recover().cache(&envrc, &id, env.clone());
+            env
+        }
+        Err(e) => {
+            log::warn!("Failed to load {}: {}", envrc.display(), e);
+            EnvOverrides::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_envrc_stops_at_checkout_root() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let nested = repo.join("src/app");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir(repo.join(".git")).unwrap();
+        std::fs::write(dir.path().join(ENVRC), "export A=1").unwrap();
+        assert_eq!(find_envrc(&nested), None);
+
+        std::fs::write(repo.join(ENVRC), "export A=2").unwrap();
+        assert_eq!(find_envrc(&nested), Some(repo.join(ENVRC)));
+    }
+
+    #[test]
+    fn test_approvals_follow_contents() {
+        let dir = TempDir::new().unwrap();
+        let envrc = dir.path().join(ENVRC);
+        std::fs::write(&envrc, "export A=1").unwrap();
+        let id = contents_id(&envrc).unwrap();
+        let file = dir.path().join("direnv.json");
+
+        let mut direnv = Direnv::load(file.clone());
+        assert_eq!(direnv.approval(&envrc, &id), Approval::Unapproved);
+        direnv.approve(&envrc, &id).unwrap();
+        direnv.cache(
+            &envrc,
+            &id,
+            EnvOverrides::from([("A".into(), Some("1".into()))]),
+        );
+
+        std::fs::write(&envrc, "export A=2").unwrap();
+        let edited = contents_id(&envrc).unwrap();
+        let direnv = Direnv::load(file);
+        assert_eq!(direnv.approval(&envrc, &id), Approval::Approved);
+        assert_eq!(direnv.approval(&envrc, &edited), Approval::Changed);
+        assert_eq!(direnv.cached(&envrc, &edited), None);
+    }
+
+    #[test]
+    fn test_parse_export() {
+        assert_eq!(parse_export(b"\n"), Ok(EnvOverrides::new()));
+        let env = parse_export(br#"{"A": "1", "OLD": null}"#).unwrap();
+        assert_eq!(env["A"].as_deref(), Some("1"));
+        assert_eq!(env["OLD"], None);
+    }
+}
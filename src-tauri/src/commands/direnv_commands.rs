@@ -0,0 +1,103 @@
+//! Tauri commands for approving `.envrc` files; see direnv.rs.
+//!
+//! The frontend checks [`get_envrc_status`] for a worktree and asks the
+//! user before calling [`approve_envrc`]. Terminals opened afterwards get
+//! the environment; open ones keep theirs.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tauri::{AppHandle, Manager};
+
+use super::direnv::{
+    approvals_file_path, contents_id, direnv_installed, find_envrc, set_allowed, Direnv,
+    DirenvState, EnvrcStatus,
+};
+use super::error::KiriError;
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+
+/// Load `~/.kiri/direnv.json` into [`DirenvState`]. Call before any
+/// terminal opens.
+pub fn load_direnv_approvals(app: &AppHandle) {
+    let Some(path) = approvals_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<DirenvState>() {
+        *state.lock_recover() = Direnv::load(path);
+    }
+}
+
+fn envrc_for(path: &str) -> Result<PathBuf, String> {
+    let dir = normalize_input_path(path);
+    find_envrc(&dir).ok_or_else(|| format!("No .envrc for {}", dir.display()))
+}
+
+fn status(state: &DirenvState, envrc: &Path) -> Result<EnvrcStatus, String> {
+    let id = contents_id(envrc)?;
+    Ok(EnvrcStatus {
+        path: path_to_wire(envrc),
+        approval: state.lock_recover().approval(envrc, &id),
+        direnv_installed: direnv_installed(),
+    })
+}
+
+/// The `.envrc` applying to `path` and whether it is approved, or `None`
+/// if there is none.
+#[tauri::command]
+pub async fn get_envrc_status(
+    state: tauri::State<'_, DirenvState>,
+    path: String,
+) -> Result<Option<EnvrcStatus>, KiriError> {
+    let dir = normalize_input_path(&path);
+    let state: DirenvState = Arc::clone(&state);
+    run_blocking("get_envrc_status", move || match find_envrc(&dir) {
+        Some(envrc) => status(&state, &envrc).map(Some),
+        None => Ok(None),
+    })
+    .await
+}
+
+/// Approve the current contents of the `.envrc` applying to `path`, and
+/// `direnv allow` it.
+#[tauri::command]
+pub async fn approve_envrc(
+    state: tauri::State<'_, DirenvState>,
+    path: String,
+) -> Result<EnvrcStatus, KiriError> {
+    let envrc = envrc_for(&path)?;
+    let state: DirenvState = Arc::clone(&state);
+    run_blocking("approve_envrc", move || {
+        let id = contents_id(&envrc)?;
+        set_allowed(&envrc, true)?;
+        state.lock_recover().approve(&envrc, &id)?;
+        status(&state, &envrc)
+    })
+    .await
+}
+
+/// Withdraw the approval of the `.envrc` applying to `path`, and
+/// `direnv deny` it.
+#[tauri::command]
+pub async fn revoke_envrc(
+    state: tauri::State<'_, DirenvState>,
+    path: String,
+) -> Result<EnvrcStatus, KiriError> {
+    let envrc = envrc_for(&path)?;
+    let state: DirenvState = Arc::clone(&state);
+    run_blocking("revoke_envrc", move || {
+        state.lock_recover().revoke(&envrc)?;
+        set_allowed(&envrc, false)?;
+        status(&state, &envrc)
+    })
+    .await
+}
+
+/// Evaluate the `.envrc` applying to `path` again for the next terminal,
+/// e.g. after a file it watches changed.
+#[tauri::command]
+pub fn reload_envrc(state: tauri::State<'_, DirenvState>, path: String) -> Result<(), KiriError> {
+    state.lock_recover().reload(&envrc_for(&path)?);
+    Ok(())
+}
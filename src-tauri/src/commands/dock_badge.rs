@@ -0,0 +1,144 @@
+//! Dock / taskbar badge and progress aggregation.
+//!
+//! Several producers feed the same badge: failing tasks, dirty worktrees,
+//! and so on. Each one reports its own count under a source name and the
+//! badge shows the sum. Progress works the same way per job id: the
+//! taskbar shows one bar, so concurrent jobs are combined into an average,
+//! and any failed job turns the bar into an error state.
+//!
+//! Platform calls happen in dock_badge_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobProgress {
+    /// Percent complete, 0–100.
+    Percent(u8),
+    /// Running, but with no measurable progress.
+    Indeterminate,
+    Failed,
+}
+
+/// What the taskbar/dock should show after combining all jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressSummary {
+    None,
+    Normal(u8),
+    Indeterminate,
+    Error(u8),
+}
+
+#[derive(Debug, Default)]
+pub struct DockBadge {
+    counts: BTreeMap<String, u32>,
+    jobs: BTreeMap<String, JobProgress>,
+}
+
+impl DockBadge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the count reported by `source`; zero removes it.
+    pub fn set_count(&mut self, source: &str, count: u32) {
+        if count == 0 {
+            self.counts.remove(source);
+        } else {
+            self.counts.insert(source.to_string(), count);
+        }
+    }
+
+    /// Sum of all sources, or `None` when the badge should be cleared.
+    pub fn badge_count(&self) -> Option<i64> {
+        let total: i64 = self.counts.values().map(|c| i64::from(*c)).sum();
+        (total > 0).then_some(total)
+    }
+
+    pub fn set_progress(&mut self, job_id: &str, progress: JobProgress) {
+        let progress = match progress {
+            JobProgress::Percent(p) => JobProgress::Percent(p.min(100)),
+            other => other,
+        };
+        self.jobs.insert(job_id.to_string(), progress);
+    }
+
+    pub fn clear_progress(&mut self, job_id: &str) {
+        self.jobs.remove(job_id);
+    }
+
+    pub fn progress(&self) -> ProgressSummary {
+        if self.jobs.is_empty() {
+            return ProgressSummary::None;
+        }
+        let percents: Vec<u32> = self
+            .jobs
+            .values()
+            .filter_map(|p| match p {
+                JobProgress::Percent(p) => Some(u32::from(*p)),
+                _ => None,
+            })
+            .collect();
+        let average = if percents.is_empty() {
+            0
+        } else {
+            (percents.iter().sum::<u32>() / percents.len() as u32) as u8
+        };
+
+        if self.jobs.values().any(|p| *p == JobProgress::Failed) {
+            ProgressSummary::Error(average)
+        } else if percents.is_empty() {
+            ProgressSummary::Indeterminate
+        } else {
+            ProgressSummary::Normal(average)
+        }
+    }
+}
+
+pub type DockBadgeState = Arc<Mutex<DockBadge>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge_sums_sources() {
+        let mut badge = DockBadge::new();
+        assert_eq!(badge.badge_count(), None);
+        badge.set_count("failing-tasks", 2);
+        badge.set_count("dirty-worktrees", 3);
+        assert_eq!(badge.badge_count(), Some(5));
+        badge.set_count("failing-tasks", 0);
+        assert_eq!(badge.badge_count(), Some(3));
+        badge.set_count("dirty-worktrees", 0);
+        assert_eq!(badge.badge_count(), None);
+    }
+
+    #[test]
+    fn test_progress_averages_jobs() {
+        let mut badge = DockBadge::new();
+        assert_eq!(badge.progress(), ProgressSummary::None);
+        badge.set_progress("install", JobProgress::Percent(20));
+        badge.set_progress("fetch", JobProgress::Percent(150));
+        assert_eq!(badge.progress(), ProgressSummary::Normal(60));
+        badge.clear_progress("fetch");
+        assert_eq!(badge.progress(), ProgressSummary::Normal(20));
+    }
+
+    #[test]
+    fn test_progress_indeterminate_and_error() {
+        let mut badge = DockBadge::new();
+        badge.set_progress("scan", JobProgress::Indeterminate);
+        assert_eq!(badge.progress(), ProgressSummary::Indeterminate);
+        badge.set_progress("install", JobProgress::Percent(40));
+        assert_eq!(badge.progress(), ProgressSummary::Normal(40));
+        badge.set_progress("build", JobProgress::Failed);
+        assert_eq!(badge.progress(), ProgressSummary::Error(40));
+        badge.clear_progress("build");
+        badge.clear_progress("install");
+        badge.clear_progress("scan");
+        assert_eq!(badge.progress(), ProgressSummary::None);
+    }
+}
@@ -0,0 +1,73 @@
+//! Tauri commands that drive the dock (macOS), taskbar (Windows) and
+//! launcher (Linux) badge and progress bar. Aggregation lives in
+//! dock_badge.rs.
+//!
+//! Background work in Rust reports through [`report_badge_count`] and
+//! [`report_job_progress`]; the commands below are the same entry points
+//! for producers that live in the frontend.
+
+use super::dock_badge::{DockBadgeState, JobProgress, ProgressSummary};
+use super::lock_ext::LockExt;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager};
+
+fn progress_bar_state(summary: ProgressSummary) -> ProgressBarState {
+    let (status, progress) = match summary {
+        ProgressSummary::None => (ProgressBarStatus::None, None),
+        ProgressSummary::Normal(p) => (ProgressBarStatus::Normal, Some(u64::from(p))),
+        ProgressSummary::Indeterminate => (ProgressBarStatus::Indeterminate, None),
+        ProgressSummary::Error(p) => (ProgressBarStatus::Error, Some(u64::from(p))),
+    };
+    ProgressBarState {
+        status: Some(status),
+        progress,
+    }
+}
+
+/// Push the current badge and progress to the platform. Platforms without
+/// support for one of them return an error, which is ignored.
+fn apply(app: &AppHandle) {
+    let Some(state) = app.try_state::<DockBadgeState>() else {
+        return;
+    };
+    let (count, progress) = {
+        let badge = state.lock_recover();
+        (badge.badge_count(), badge.progress())
+    };
+    // The dock badge is app-wide, but Windows draws progress per taskbar
+    // button, so every window gets the same state.
+    for window in app.webview_windows().values() {
+        let _ = window.set_badge_count(count);
+        let _ = window.set_progress_bar(progress_bar_state(progress));
+    }
+}
+
+/// Set the badge contribution of `source` (e.g. `failing-tasks`).
+pub fn report_badge_count(app: &AppHandle, source: &str, count: u32) {
+    if let Some(state) = app.try_state::<DockBadgeState>() {
+        state.lock_recover().set_count(source, count);
+    }
+    apply(app);
+}
+
+/// Report progress for `job_id`; `None` means the job finished.
+pub fn report_job_progress(app: &AppHandle, job_id: &str, progress: Option<JobProgress>) {
+    if let Some(state) = app.try_state::<DockBadgeState>() {
+        let mut badge = state.lock_recover();
+        match progress {
+            Some(p) => badge.set_progress(job_id, p),
+            None => badge.clear_progress(job_id),
+        }
+    }
+    apply(app);
+}
+
+#[tauri::command]
+pub fn set_badge_count(app: AppHandle, source: String, count: u32) {
+    report_badge_count(&app, &source, count);
+}
+
+#[tauri::command]
+pub fn set_job_progress(app: AppHandle, job_id: String, progress: Option<JobProgress>) {
+    report_job_progress(&app, &job_id, progress);
+}
@@ -0,0 +1,561 @@
+//! Docker Compose stacks per worktree.
+//!
+//! Every worktree with a compose file can run its own stack. The compose
+//! project is named after the worktree directory, as `docker compose` does
+//! by default, so each worktree's containers, networks and volumes are
+//! separate.
+//!
+//! Published ports would still collide, so a linked worktree doesn't run
+//! its compose file as written. Its resolved config (`docker compose
+//! config`) is rewritten to publish every host port from a block of
+//! [`PORT_BLOCK_SIZE`] ports in 20000-39999 reserved for the project, and
+//! fixed `container_name`s are dropped. The result is saved in the
+//! worktree's git dir, out of the working tree. The main checkout runs
+//! its compose file unchanged, on the ports everyone expects.
+//!
+//! A project's block is recorded as a label on its containers, so blocks
+//! in use are read back from Docker and survive restarts of the app.
+//! Compose files build images and run the project's containers, so `up`
+//! needs the worktree trusted (see [`ComposeStack::for_trusted_worktree`]).
+//! Commands, the `docker compose` runner and the bollard client live in
+//! docker_commands.rs.
+
+use bollard::models::ContainerSummary;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use super::error::KiriError;
+use super::trust::{require_trusted_in, TrustStoreState};
+
+/// Compose file names, in the order `docker compose` looks for them.
+pub const COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+pub const PORT_RANGE_START: u16 = 20000;
+pub const PORT_BLOCK_SIZE: u16 = 100;
+pub const PORT_BLOCKS: u16 = 200;
+
+pub const PROJECT_LABEL: &str = "com.docker.compose.project";
+pub const SERVICE_LABEL: &str = "com.docker.compose.service";
+/// Port block of a worktree stack, set on each of its containers.
+pub const PORT_BLOCK_LABEL: &str = "dev.kiri.port-block";
+
+/// Rewritten config of a linked worktree, in its git dir.
+const GENERATED_FILE: &str = "kiri-compose.json";
+
+/// Sockets of Docker Desktop, Colima and OrbStack, relative to home.
+const USER_SOCKETS: [&str; 3] = [
+    ".docker/run/docker.sock",
+    ".colima/default/docker.sock",
+    ".orbstack/run/docker.sock",
+];
+
+/// The Docker socket in the user's home, for when neither `DOCKER_HOST`
+/// nor `/var/run/docker.sock` is set up, as Docker Desktop, Colima and
+/// OrbStack may leave it on macOS.
+pub fn user_socket() -> Option<PathBuf> {
+    if cfg!(windows)
+        || std::env::var_os("DOCKER_HOST").is_some()
+        || Path::new("/var/run/docker.sock").exists()
+    {
+        return None;
+    }
+    let home = dirs::home_dir()?;
+    USER_SOCKETS
+        .iter()
+        .map(|socket| home.join(socket))
+        .find(|socket| socket.exists())
+}
+
+/// The compose file `docker compose` would pick in `dir`.
+pub fn find_compose_file(dir: &Path) -> Option<PathBuf> {
+    COMPOSE_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Compose project name for `dir`: its name, lowercased, keeping only
+/// characters compose allows.
+pub fn project_name(dir: &Path) -> String {
+    let name: String = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-' || *c == '_')
+        .collect();
+    let name = name.trim_start_matches(['-', '_']);
+    if name.is_empty() {
+        "kiri".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Payload of `docker-log`: a chunk of a container's logs.
+#[derive(Debug, Clone, Serialize)]
+pub struct DockerLogEvent {
+    pub container_id: String,
+    pub data: String,
+}
+
+/// The compose file and project of one worktree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComposeStack {
+    pub dir: PathBuf,
+    pub project: String,
+    pub file: PathBuf,
+    /// Where the port-isolated config goes; `None` for the main checkout
+    /// and for directories outside git.
+    pub generated: Option<PathBuf>,
+}
+
+impl ComposeStack {
+    pub fn for_worktree(dir: &Path) -> Result<Self, String> {
+        let file = find_compose_file(dir)
+            .ok_or_else(|| format!("No compose file in {}", dir.display()))?;
+        let generated = git2::Repository::open(dir)
+            .ok()
+            .filter(|repo| repo.is_worktree())
+            .map(|repo| repo.path().join(GENERATED_FILE));
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            project: project_name(dir),
+            file,
+            generated,
+        })
+    }
+
+    /// [`ComposeStack::for_worktree`], if the worktree is trusted to run it.
+    pub fn for_trusted_worktree(trust: &TrustStoreState, dir: &Path) -> Result<Self, KiriError> {
+        require_trusted_in(trust, dir)?;
+        Ok(Self::for_worktree(dir)?)
+    }
+
+    /// The file the stack was last brought up with.
+    pub fn active_file(&self) -> &Path {
+        match &self.generated {
+            Some(generated) if generated.is_file() => generated,
+            _ => &self.file,
+        }
+    }
+}
+
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// First host port of `block`.
+pub fn block_base(block: u16) -> u16 {
+    PORT_RANGE_START + block * PORT_BLOCK_SIZE
+}
+
+/// A block for `project`: the one its name hashes to if free, else the
+/// next free one. `used` are the blocks of other stacks; `is_free` checks
+/// that nothing else on the host listens in a block.
+pub fn allocate_block(
+    project: &str,
+    used: &HashSet<u16>,
+    is_free: impl Fn(u16) -> bool,
+) -> Option<u16> {
+    let preferred = (fnv1a(project) % u32::from(PORT_BLOCKS)) as u16;
+    (0..PORT_BLOCKS)
+        .map(|offset| (preferred + offset) % PORT_BLOCKS)
+        .find(|block| !used.contains(block) && is_free(*block))
+}
+
+/// Whether the first `count` ports of `block` can be bound on the host.
+pub fn ports_free(block: u16, count: u16) -> bool {
+    let base = block_base(block);
+    (base..base + count).all(|port| std::net::TcpListener::bind(("0.0.0.0", port)).is_ok())
+}
+
+/// Number of host ports a `published` value takes: `8080` or
+/// `8000-8010`. `None` when no host port is given.
+fn published_count(published: &Value) -> Option<u16> {
+    let text = match published {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) if !s.is_empty() => s.clone(),
+        _ => return None,
+    };
+    match text.split_once('-') {
+        Some((start, end)) => {
+            let (start, end): (u16, u16) = (start.parse().ok()?, end.parse().ok()?);
+            end.checked_sub(start).map(|span| span + 1)
+        }
+        None => text.parse::<u16>().ok().map(|_| 1),
+    }
+}
+
+fn published_ports(config: &Value) -> impl Iterator<Item = &Value> {
+    config
+        .get("services")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|services| services.values())
+        .filter_map(|service| service.get("ports").and_then(Value::as_array))
+        .flatten()
+        .filter_map(|port| port.get("published"))
+}
+
+/// Host ports the resolved `config` publishes.
+pub fn published_port_count(config: &Value) -> u32 {
+    published_ports(config)
+        .filter_map(published_count)
+        .map(u32::from)
+        .sum()
+}
+
+/// Rewrite a resolved compose config (`docker compose config --format
+/// json`) to run as `project` on the ports of `block`: published host ports
+/// are renumbered from the block's base in file order, `container_name`s
+/// are dropped, and every service is labelled with the block.
+pub fn isolate_stack(config: &mut Value, project: &str, block: u16) -> Result<(), String> {
+    let base = block_base(block);
+    let mut next = base;
+    let config = config
+        .as_object_mut()
+        .ok_or("Compose config is not an object")?;
+    config.insert("name".to_string(), Value::from(project));
+    let services = config
+        .get_mut("services")
+        .and_then(Value::as_object_mut)
+        .ok_or("Compose file has no services")?;
+    for service in services.values_mut().filter_map(Value::as_object_mut) {
+        service.remove("container_name");
+        let ports = service.get_mut("ports").and_then(Value::as_array_mut);
+        for port in ports.into_iter().flatten() {
+            let Some(count) = port.get("published").and_then(published_count) else {
+                continue;
+            };
+            if u32::from(next - base) + u32::from(count) > u32::from(PORT_BLOCK_SIZE) {
+                return Err(format!(
+                    "Compose file publishes more than {} ports",
+                    PORT_BLOCK_SIZE
+                ));
+            }
+            port["published"] = Value::from(match count {
+                1 => next.to_string(),
+                _ => format!("{}-{}", next, next + count - 1),
+            });
+            next += count;
+        }
+        let labels = service
+            .entry("labels")
+            .or_insert_with(|| Value::Object(Default::default()));
+        match labels {
+            Value::Object(labels) => {
+                labels.insert(PORT_BLOCK_LABEL.to_string(), Value::from(block.to_string()));
+            }
+            Value::Array(labels) => {
+                labels.push(Value::from(format!("{}={}", PORT_BLOCK_LABEL, block)));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn label<'a>(summary: &'a ContainerSummary, key: &str) -> Option<&'a str> {
+    summary.labels.as_ref()?.get(key).map(String::as_str)
+}
+
+/// Port block of each project among `containers`.
+pub fn port_blocks(containers: &[ContainerSummary]) -> HashMap<String, u16> {
+    containers
+        .iter()
+        .filter_map(|c| {
+            let project = label(c, PROJECT_LABEL)?;
+            let block = label(c, PORT_BLOCK_LABEL)?.parse().ok()?;
+            Some((project.to_string(), block))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct PortMapping {
+    pub container_port: u16,
+    /// Not published when `None`
+    pub host_port: Option<u16>,
+    pub protocol: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContainerStatus {
+    pub id: String,
+    pub name: String,
+    pub service: Option<String>,
+    pub image: String,
+    /// `running`, `exited`, `restarting`, ...
+    pub state: String,
+    /// Human-readable, e.g. `Up 5 minutes (healthy)`
+    pub status: String,
+    pub ports: Vec<PortMapping>,
+}
+
+impl From<ContainerSummary> for ContainerStatus {
+    fn from(summary: ContainerSummary) -> Self {
+        let service = label(&summary, SERVICE_LABEL).map(str::to_string);
+        // Docker lists a published port once per address family.
+        let mut ports: Vec<PortMapping> = summary
+            .ports
+            .unwrap_or_default()
+            .into_iter()
+            .map(|port| PortMapping {
+                container_port: port.private_port,
+                host_port: port.public_port,
+                protocol: port.typ.map(|t| t.to_string()).unwrap_or_default(),
+            })
+            .collect();
+        ports.sort();
+        ports.dedup();
+        let name = summary
+            .names
+            .and_then(|names| names.into_iter().next())
+            .unwrap_or_default();
+        ContainerStatus {
+            id: summary.id.unwrap_or_default(),
+            name: name.trim_start_matches('/').to_string(),
+            service,
+            image: summary.image.unwrap_or_default(),
+            state: summary.state.unwrap_or_default(),
+            status: summary.status.unwrap_or_default(),
+            ports,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComposeStatus {
+    pub project: String,
+    /// `None` when the worktree has no compose file
+    pub compose_file: Option<String>,
+    /// First host port of the stack's block, for linked worktrees
+    pub port_base: Option<u16>,
+    pub containers: Vec<ContainerStatus>,
+}
+
+/// Containers whose logs are being streamed, with the handle that stops
+/// each stream.
+#[derive(Default)]
+pub struct LogFollowers {
+    stops: HashMap<String, oneshot::Sender<()>>,
+}
+
+impl LogFollowers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim the log stream of `container`. Returns the receiver that
+    /// fires on [`Self::stop`], or `None` if the logs are already
+    /// streamed.
+    pub fn begin(&mut self, container: &str) -> Option<oneshot::Receiver<()>> {
+        self.stops.retain(|_, stop| !stop.is_closed());
+        if self.stops.contains_key(container) {
+            return None;
+        }
+        let (stop, stopped) = oneshot::channel();
+        self.stops.insert(container.to_string(), stop);
+        Some(stopped)
+    }
+
+    /// Stop streaming `container`. Returns whether it was streamed.
+    pub fn stop(&mut self, container: &str) -> bool {
+        self.stops
+            .remove(container)
+            .is_some_and(|stop| stop.send(()).is_ok())
+    }
+
+    /// Forget a stream that ended on its own, once its receiver is gone.
+    pub fn end(&mut self, container: &str) {
+        if self.stops.get(container).is_some_and(|s| s.is_closed()) {
+            self.stops.remove(container);
+        }
+    }
+}
+
+pub type LogFollowersState = Arc<Mutex<LogFollowers>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error::ErrorCode;
+    use crate::commands::lock_ext::LockExt;
+    use crate::commands::trust::TrustLevel;
+    use bollard::models::{Port, PortTypeEnum};
+    use tempfile::TempDir;
+
+    fn config() -> Value {
+        serde_json::json!({
+            "name": "app",
+            "services": {
+                "db": {
+                    "image": "postgres",
+                    "container_name": "app-db",
+                    "ports": [{"target": 5432, "published": "5432", "protocol": "tcp"}]
+                },
+                "web": {
+                    "image": "nginx",
+                    "labels": {"tier": "front"},
+                    "ports": [
+                        {"target": 80, "published": "8080", "host_ip": "127.0.0.1"},
+                        {"target": 9000},
+                        {"target": 9100, "published": "9100-9102"}
+                    ]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_project_name() {
+        assert_eq!(
+            project_name(Path::new("/src/My App-feature.x")),
+            "myapp-featurex"
+        );
+        assert_eq!(project_name(Path::new("/src/_kiri")), "kiri");
+        assert_eq!(project_name(Path::new("/src/日本")), "kiri");
+    }
+
+    #[test]
+    fn test_find_compose_file_prefers_compose_yaml() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(find_compose_file(dir.path()), None);
+        std::fs::write(dir.path().join("docker-compose.yml"), "services: {}").unwrap();
+        std::fs::write(dir.path().join("compose.yaml"), "services: {}").unwrap();
+        assert_eq!(
+            find_compose_file(dir.path()),
+            Some(dir.path().join("compose.yaml"))
+        );
+
+        let stack = ComposeStack::for_worktree(dir.path()).unwrap();
+        assert_eq!(stack.generated, None);
+        assert_eq!(stack.active_file(), dir.path().join("compose.yaml"));
+    }
+
+    #[test]
+    fn test_untrusted_worktree_has_no_stack_to_run() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("compose.yaml"), "services: {}").unwrap();
+        let trust = TrustStoreState::default();
+        let error = ComposeStack::for_trusted_worktree(&trust, dir.path()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::NotTrusted);
+
+        trust
+            .lock_recover()
+            .set(dir.path(), TrustLevel::Trusted)
+            .unwrap();
+        let stack = ComposeStack::for_trusted_worktree(&trust, dir.path()).unwrap();
+        assert_eq!(stack.file, dir.path().join("compose.yaml"));
+    }
+
+    #[test]
+    fn test_isolate_stack_renumbers_published_ports() {
+        let mut config = config();
+        assert_eq!(published_port_count(&config), 5);
+        isolate_stack(&mut config, "app-feature", 3).unwrap();
+
+        assert_eq!(config["name"], "app-feature");
+        let db = &config["services"]["db"];
+        assert!(db.get("container_name").is_none());
+        assert_eq!(db["ports"][0]["published"], "20300");
+        assert_eq!(db["labels"][PORT_BLOCK_LABEL], "3");
+
+        let web = &config["services"]["web"];
+        assert_eq!(web["ports"][0]["published"], "20301");
+        assert_eq!(web["ports"][0]["host_ip"], "127.0.0.1");
+        assert!(web["ports"][1].get("published").is_none());
+        assert_eq!(web["ports"][2]["published"], "20302-20304");
+        assert_eq!(web["labels"]["tier"], "front");
+    }
+
+    #[test]
+    fn test_isolate_stack_rejects_too_many_ports() {
+        let ports: Vec<Value> = (0..=PORT_BLOCK_SIZE)
+            .map(|p| serde_json::json!({"target": p, "published": (3000 + p).to_string()}))
+            .collect();
+        let mut config = serde_json::json!({"services": {"web": {"ports": ports}}});
+        assert!(isolate_stack(&mut config, "app", 0).is_err());
+    }
+
+    #[test]
+    fn test_allocate_block_skips_used_and_busy() {
+        let preferred = allocate_block("app", &HashSet::new(), |_| true).unwrap();
+        assert!(preferred < PORT_BLOCKS);
+        assert_eq!(
+            allocate_block("app", &HashSet::from([preferred]), |_| true),
+            Some((preferred + 1) % PORT_BLOCKS)
+        );
+        let next = allocate_block("app", &HashSet::new(), |b| b != preferred);
+        assert_eq!(next, Some((preferred + 1) % PORT_BLOCKS));
+        assert_eq!(allocate_block("app", &HashSet::new(), |_| false), None);
+        assert_eq!(block_base(PORT_BLOCKS - 1) + PORT_BLOCK_SIZE, 40000);
+    }
+
+    #[test]
+    fn test_container_status_from_summary() {
+        let port = |public_port, ip: &str| Port {
+            ip: Some(ip.to_string()),
+            private_port: 80,
+            public_port,
+            typ: Some(PortTypeEnum::TCP),
+        };
+        let labels = HashMap::from([
+            (PROJECT_LABEL.to_string(), "app-feature".to_string()),
+            (SERVICE_LABEL.to_string(), "web".to_string()),
+            (PORT_BLOCK_LABEL.to_string(), "3".to_string()),
+        ]);
+        let summary = ContainerSummary {
+            id: Some("abc".to_string()),
+            names: Some(vec!["/app-feature-web-1".to_string()]),
+            state: Some("running".to_string()),
+            ports: Some(vec![port(Some(20300), "0.0.0.0"), port(Some(20300), "::")]),
+            labels: Some(labels),
+            ..Default::default()
+        };
+        assert_eq!(
+            port_blocks(std::slice::from_ref(&summary)),
+            HashMap::from([("app-feature".to_string(), 3)])
+        );
+
+        let status = ContainerStatus::from(summary);
+        assert_eq!(status.name, "app-feature-web-1");
+        assert_eq!(status.service.as_deref(), Some("web"));
+        assert_eq!(
+            status.ports,
+            vec![PortMapping {
+                container_port: 80,
+                host_port: Some(20300),
+                protocol: "tcp".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_log_followers() {
+        let mut followers = LogFollowers::new();
+        let mut stopped = followers.begin("abc").unwrap();
+        assert!(followers.begin("abc").is_none());
+        assert!(followers.stop("abc"));
+        assert_eq!(stopped.try_recv(), Ok(()));
+        assert!(!followers.stop("abc"));
+
+        // A stream that ended on its own frees the container.
+        let stopped = followers.begin("abc").unwrap();
+        drop(stopped);
+        followers.end("abc");
+        assert!(followers.begin("abc").is_some());
+    }
+}
@@ -0,0 +1,318 @@
+//! Tauri commands for per-worktree Compose stacks; naming and port
+//! isolation live in docker.rs.
+//!
+//! `docker compose` itself brings stacks up and down, as background jobs
+//! (see jobs_commands.rs) whose progress messages are compose's output.
+//! Status and logs come straight from the Docker Engine API over the local
+//! socket, through bollard. Log lines go out as `docker-log` events,
+//! batched per container (see event_throttle.rs).
+
+use bollard::container::{ListContainersOptions, LogsOptions};
+use bollard::models::ContainerSummary;
+use bollard::Docker;
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc};
+use tauri::{AppHandle, Emitter};
+
+use super::docker::{
+    allocate_block, block_base, find_compose_file, isolate_stack, port_blocks, ports_free,
+    project_name, published_port_count, user_socket, ComposeStack, ComposeStatus, ContainerStatus,
+    DockerLogEvent, LogFollowersState, PORT_BLOCK_LABEL, PORT_BLOCK_SIZE, PROJECT_LABEL,
+};
+use super::error::KiriError;
+use super::event_throttle_commands::emit_throttled;
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::spawn_job;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::performance::CommandTimer;
+use super::trust_commands::trust_store;
+
+pub const DOCKER_LOG_EVENT: &str = "docker-log";
+const COMPOSE_JOB: &str = "docker-compose";
+const DOCKER_TIMEOUT_SECS: u64 = 120;
+/// Lines of docker output kept for the error of a failed run.
+const ERROR_LINES: usize = 10;
+const DEFAULT_LOG_TAIL: u32 = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComposeUp {
+    pub job_id: JobId,
+    pub project: String,
+    /// First host port of a linked worktree's stack
+    pub port_base: Option<u16>,
+}
+
+fn connect() -> Result<Docker, String> {
+    let docker = match user_socket() {
+        Some(socket) => Docker::connect_with_socket(
+            &socket.to_string_lossy(),
+            DOCKER_TIMEOUT_SECS,
+            bollard::API_DEFAULT_VERSION,
+        ),
+        None => Docker::connect_with_local_defaults(),
+    };
+    docker.map_err(|e| format!("Failed to connect to Docker: {}", e))
+}
+
+async fn list_containers(docker: &Docker, label: &str) -> Result<Vec<ContainerSummary>, String> {
+    let options = ListContainersOptions {
+        all: true,
+        filters: HashMap::from([("label".to_string(), vec![label.to_string()])]),
+        ..Default::default()
+    };
+    docker
+        .list_containers(Some(options))
+        .await
+        .map_err(|e| format!("Failed to list containers: {}", e))
+}
+
+fn compose_command(stack: &ComposeStack, file: &Path) -> Command {
+    let mut command = Command::new("docker");
+    command
+        .current_dir(&stack.dir)
+        .args(["compose", "--ansi", "never", "--project-directory"])
+        .arg(&stack.dir)
+        .arg("--project-name")
+        .arg(&stack.project)
+        .arg("--file")
+        .arg(file);
+    command
+}
+
+/// The stack's compose file as `docker compose` resolves it: includes,
+/// variables and relative paths expanded.
+fn resolved_config(stack: &ComposeStack) -> Result<serde_json::Value, String> {
+    let output = compose_command(stack, &stack.file)
+        .args(["config", "--format", "json"])
+        .output()
+        .map_err(|e| format!("Failed to run docker compose: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker compose config failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse compose config: {}", e))
+}
+
+/// Run a docker command as a job, reporting each line of its output as
+/// progress. Cancelling kills it.
+pub fn run_docker(ctx: &JobContext, command: &mut Command) -> Result<(), String> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run docker: {}", e))?;
+    let (tx, rx) = mpsc::channel::<String>();
+    let stdout = child
+        .stdout
+        .take()
+        .map(|s| Box::new(s) as Box<dyn Read + Send>);
+    let stderr = child
+        .stderr
+        .take()
+        .map(|s| Box::new(s) as Box<dyn Read + Send>);
+    for stream in [stdout, stderr].into_iter().flatten() {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+    let mut last_lines = VecDeque::with_capacity(ERROR_LINES);
+    for line in rx {
+        if ctx.is_cancelled() {
+            let _ = child.kill();
+            break;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        ctx.progress(None, Some(line.to_string()));
+        if last_lines.len() == ERROR_LINES {
+            last_lines.pop_front();
+        }
+        last_lines.push_back(line.to_string());
+    }
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for docker: {}", e))?;
+    if status.success() || ctx.is_cancelled() {
+        return Ok(());
+    }
+    if last_lines.is_empty() {
+        return Err(format!("docker failed: {}", status));
+    }
+    Err(Vec::from(last_lines).join("\n"))
+}
+
+/// Pick the stack's port block (keeping the one it already runs on) and
+/// write its port-isolated config. Returns the block.
+async fn isolate(docker: &Docker, stack: &ComposeStack, generated: &Path) -> Result<u16, String> {
+    let config = {
+        let stack = stack.clone();
+        run_blocking("compose_config", move || resolved_config(&stack)).await?
+    };
+    let blocks = port_blocks(&list_containers(docker, PORT_BLOCK_LABEL).await?);
+    let block = match blocks.get(&stack.project) {
+        Some(block) => *block,
+        None => {
+            let count = published_port_count(&config).min(u32::from(PORT_BLOCK_SIZE)) as u16;
+            let used = blocks.values().copied().collect();
+            allocate_block(&stack.project, &used, |block| ports_free(block, count))
+                .ok_or("No free port block left in 20000-39999")?
+        }
+    };
+    let project = stack.project.clone();
+    let generated = generated.to_path_buf();
+    run_blocking("compose_isolate", move || {
+        let mut config = config;
+        isolate_stack(&mut config, &project, block)?;
+        let contents = serde_json::to_vec_pretty(&config)
+            .map_err(|e| format!("Failed to serialize compose config: {}", e))?;
+        std::fs::write(&generated, contents)
+            .map_err(|e| format!("Failed to write {}: {}", generated.display(), e))
+    })
+    .await?;
+    Ok(block)
+}
+
+/// Bring up the worktree's compose stack in the background. Linked
+/// worktrees get their own block of host ports. Returns the job to follow.
+#[tauri::command]
+pub async fn compose_up(app: AppHandle, worktree_path: String) -> Result<ComposeUp, KiriError> {
+    let _timer = CommandTimer::start("compose_up");
+    let dir = normalize_input_path(&worktree_path);
+    let stack = ComposeStack::for_trusted_worktree(&trust_store(&app)?, &dir)?;
+    let block = match &stack.generated {
+        Some(generated) => Some(isolate(&connect()?, &stack, generated).await?),
+        None => None,
+    };
+    let project = stack.project.clone();
+    let label = format!("docker compose up ({})", project);
+    let job_id = spawn_job(&app, COMPOSE_JOB, &label, move |ctx| {
+        let mut command = compose_command(&stack, stack.active_file());
+        command.args(["up", "--detach", "--remove-orphans"]);
+        run_docker(ctx, &mut command)
+    })?;
+    Ok(ComposeUp {
+        job_id,
+        project,
+        port_base: block.map(block_base),
+    })
+}
+
+/// Stop and remove the worktree's compose stack in the background, with
+/// its volumes if `volumes` is set.
+#[tauri::command]
+pub fn compose_down(
+    app: AppHandle,
+    worktree_path: String,
+    volumes: Option<bool>,
+) -> Result<JobId, KiriError> {
+    let stack = ComposeStack::for_worktree(&normalize_input_path(&worktree_path))?;
+    let label = format!("docker compose down ({})", stack.project);
+    spawn_job(&app, COMPOSE_JOB, &label, move |ctx| {
+        let mut command = compose_command(&stack, stack.active_file());
+        command.args(["down", "--remove-orphans"]);
+        if volumes.unwrap_or(false) {
+            command.arg("--volumes");
+        }
+        run_docker(ctx, &mut command)
+    })
+    .map_err(KiriError::from)
+}
+
+/// Containers of the worktree's compose project, with their published
+/// ports.
+#[tauri::command]
+pub async fn get_compose_status(worktree_path: String) -> Result<ComposeStatus, KiriError> {
+    let _timer = CommandTimer::start("get_compose_status");
+    let dir = normalize_input_path(&worktree_path);
+    let project = project_name(&dir);
+    let label = format!("{}={}", PROJECT_LABEL, project);
+    let containers = list_containers(&connect()?, &label).await?;
+    let port_base = port_blocks(&containers).get(&project).copied();
+    let mut containers: Vec<ContainerStatus> =
+        containers.into_iter().map(ContainerStatus::from).collect();
+    containers.sort_by(|a, b| (&a.service, &a.name).cmp(&(&b.service, &b.name)));
+    Ok(ComposeStatus {
+        compose_file: find_compose_file(&dir).map(|f| path_to_wire(&f)),
+        port_base: port_base.map(block_base),
+        project,
+        containers,
+    })
+}
+
+/// Stream a container's logs as `docker-log` events, starting with the
+/// last `tail` lines (default 200), until [`stop_container_logs`] or the
+/// container goes away. Does nothing if the logs are already streamed.
+#[tauri::command]
+pub fn follow_container_logs(
+    app: AppHandle,
+    state: tauri::State<'_, LogFollowersState>,
+    container_id: String,
+    tail: Option<u32>,
+) -> Result<(), KiriError> {
+    let docker = connect()?;
+    let Some(mut stop) = state.lock_recover().begin(&container_id) else {
+        return Ok(());
+    };
+    let state: LogFollowersState = Arc::clone(&state);
+    tauri::async_runtime::spawn(async move {
+        let options = LogsOptions::<String> {
+            follow: true,
+            stdout: true,
+            stderr: true,
+            tail: tail.unwrap_or(DEFAULT_LOG_TAIL).to_string(),
+            ..Default::default()
+        };
+        let mut logs = docker.logs(&container_id, Some(options));
+        loop {
+            tokio::select! {
+                _ = &mut stop => break,
+                chunk = logs.next() => match chunk {
+                    Some(Ok(output)) => emit_throttled(
+                        &app,
+                        DOCKER_LOG_EVENT,
+                        &container_id,
+                        &DockerLogEvent {
+                            container_id: container_id.clone(),
+                            data: output.to_string(),
+                        },
+                    ),
+                    Some(Err(e)) => {
+                        log::warn!("Log stream of {} failed: {}", container_id, e);
+                        break;
+                    }
+                    None => break,
+                },
+            }
+        }
+        drop(stop);
+        state.lock_recover().end(&container_id);
+        let _ = app.emit("docker-log-ended", &container_id);
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_container_logs(
+    state: tauri::State<'_, LogFollowersState>,
+    container_id: String,
+) -> bool {
+    state.lock_recover().stop(&container_id)
+}
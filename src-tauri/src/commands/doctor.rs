@@ -0,0 +1,381 @@
+//! Environment doctor: checks of what kiri relies on from the system.
+//!
+//! [`run_checks`] looks at the git binary, the shell terminals start,
+//! PTY allocation, the inotify watch limit (Linux only), access to the
+//! Docker socket and free disk space. Each [`Finding`] says what was
+//! found and, when something is off, what to do about it, so a support
+//! request can start from the report instead of a round of questions.
+
+use portable_pty::{native_pty_system, PtySize};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::docker::user_socket;
+#[cfg(unix)]
+use super::error::user_path_io_error;
+use super::terminal::get_shell_path;
+
+/// Oldest git kiri is tested with.
+pub const MIN_GIT_VERSION: (u32, u32) = (2, 30);
+
+/// Watches below this run out on a large repository.
+pub const MIN_INOTIFY_WATCHES: u64 = 65_536;
+
+const MB: u64 = 1024 * 1024;
+const DISK_ERROR_BELOW: u64 = 500 * MB;
+const DISK_WARNING_BELOW: u64 = 2048 * MB;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Ok,
+    /// Not a problem, but worth knowing, e.g. Docker isn't installed.
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub check: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// What to do about it; `None` when nothing needs doing.
+    pub action: Option<String>,
+}
+
+impl Finding {
+    fn new(check: &'static str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity,
+            message: message.into(),
+            action: None,
+        }
+    }
+
+    fn action(mut self, action: impl Into<String>) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DoctorReport {
+    /// The worst severity found.
+    pub severity: Severity,
+    pub findings: Vec<Finding>,
+}
+
+/// `(major, minor)` from `git --version` output, e.g. `git version
+/// 2.39.3 (Apple Git-145)`.
+pub fn parse_git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.trim().strip_prefix("git version ")?;
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Judge the output of `git --version`, or why it couldn't run.
+pub fn git_finding(output: Result<String, String>) -> Finding {
+    const CHECK: &str = "git";
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            return Finding::new(CHECK, Severity::Error, format!("git could not run: {}", e))
+                .action("Install git and make sure it is on the PATH kiri starts with.");
+        }
+    };
+    match parse_git_version(&output) {
+        Some(version) if version < MIN_GIT_VERSION => Finding::new(
+            CHECK,
+            Severity::Warning,
+            format!("{} is older than kiri supports", output.trim()),
+        )
+        .action(format!(
+            "Update git to {}.{} or later.",
+            MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+        )),
+        Some(_) => Finding::new(CHECK, Severity::Ok, output.trim()),
+        None => Finding::new(
+            CHECK,
+            Severity::Warning,
+            format!("Unrecognized git version: {}", output.trim()),
+        ),
+    }
+}
+
+/// Judge the inotify watch limit; `None` where there is none to read.
+pub fn watch_limit_finding(limit: Option<u64>) -> Option<Finding> {
+    const CHECK: &str = "watcher";
+    let limit = limit?;
+    Some(if limit < MIN_INOTIFY_WATCHES {
+        Finding::new(
+            CHECK,
+            Severity::Warning,
+            format!(
+                "fs.inotify.max_user_watches is {}; large repositories stop updating",
+                limit
+            ),
+        )
+        .action(
+            "Raise it, e.g. `echo fs.inotify.max_user_watches=524288 | sudo tee \
+             /etc/sysctl.d/60-kiri.conf && sudo sysctl --system`.",
+        )
+    } else {
+        Finding::new(
+            CHECK,
+            Severity::Ok,
+            format!("fs.inotify.max_user_watches is {}", limit),
+        )
+    })
+}
+
+/// Judge the free space on the disk holding `~/.kiri`.
+pub fn disk_finding(available: Option<u64>) -> Finding {
+    const CHECK: &str = "disk";
+    let Some(available) = available else {
+        return Finding::new(CHECK, Severity::Info, "Free disk space could not be read");
+    };
+    let message = format!("{} MB free", available / MB);
+    if available < DISK_ERROR_BELOW {
+        Finding::new(CHECK, Severity::Error, message)
+            .action("Free up disk space; settings and logs can't be saved reliably.")
+    } else if available < DISK_WARNING_BELOW {
+        Finding::new(CHECK, Severity::Warning, message)
+            .action("Free up disk space before it runs out.")
+    } else {
+        Finding::new(CHECK, Severity::Ok, message)
+    }
+}
+
+fn check_git() -> Finding {
+    let output = Command::new("git")
+        .arg("--version")
+        .output()
+        .map_err(|e| e.to_string())
+        .and_then(|output| {
+            if output.status.success() {
+                Ok(String::from_utf8_lossy(&output.stdout).to_string())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            }
+        });
+    git_finding(output)
+}
+
+/// `profile` is the shell from the settings, empty for `$SHELL`.
+fn check_shell(profile: &str) -> Finding {
+    const CHECK: &str = "shell";
+    let (shell, source) = if profile.is_empty() {
+        if std::env::var_os("SHELL").is_none() {
+            return Finding::new(
+                CHECK,
+                Severity::Warning,
+                format!("$SHELL is not set; terminals start {}", get_shell_path()),
+            )
+            .action("Set a terminal profile in the settings, or $SHELL in your session.");
+        }
+        (get_shell_path(), "$SHELL")
+    } else {
+        (profile.to_string(), "the terminal profile")
+    };
+    let path = Path::new(&shell);
+    if !path.is_file() {
+        return Finding::new(
+            CHECK,
+            Severity::Error,
+            format!("{} from {} does not exist", shell, source),
+        )
+        .action("Point the terminal profile in the settings at an installed shell.");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let executable = path
+            .metadata()
+            .is_ok_and(|m| m.permissions().mode() & 0o111 != 0);
+        if !executable {
+            return Finding::new(
+                CHECK,
+                Severity::Error,
+                format!("{} from {} is not executable", shell, source),
+            )
+            .action("Point the terminal profile in the settings at an installed shell.");
+        }
+    }
+    Finding::new(CHECK, Severity::Ok, format!("{} from {}", shell, source))
+}
+
+fn check_pty() -> Finding {
+    const CHECK: &str = "pty";
+    let size = PtySize {
+        rows: 24,
+        cols: 80,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+    match native_pty_system().openpty(size) {
+        Ok(_) => Finding::new(CHECK, Severity::Ok, "A pseudo-terminal could be opened"),
+        Err(e) => Finding::new(
+            CHECK,
+            Severity::Error,
+            format!("No pseudo-terminal could be opened: {}", e),
+        )
+        .action(
+            "Close other terminals or raise the system's PTY limit (kern.tty.ptmx_max \
+             on macOS, /proc/sys/kernel/pty/max on Linux).",
+        ),
+    }
+}
+
+fn inotify_watch_limit() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The Docker socket to check, from `DOCKER_HOST` or the usual places.
+/// `Err` names a host that isn't a socket.
+fn docker_socket() -> Result<Option<PathBuf>, String> {
+    if let Ok(host) = std::env::var("DOCKER_HOST") {
+        return match host.strip_prefix("unix://") {
+            Some(path) => Ok(Some(PathBuf::from(path))),
+            None => Err(host),
+        };
+    }
+    Ok(user_socket().or_else(|| {
+        let default = PathBuf::from("/var/run/docker.sock");
+        default.exists().then_some(default)
+    }))
+}
+
+fn check_docker() -> Finding {
+    const CHECK: &str = "docker";
+    let socket = match docker_socket() {
+        Ok(Some(socket)) => socket,
+        Ok(None) => {
+            return Finding::new(
+                CHECK,
+                Severity::Info,
+                "No Docker socket found; only worktree compose stacks need Docker",
+            )
+        }
+        Err(host) => {
+            return Finding::new(
+                CHECK,
+                Severity::Info,
+                format!("Docker is reached at {}, which isn't checked", host),
+            )
+        }
+    };
+    #[cfg(unix)]
+    {
+        match std::os::unix::net::UnixStream::connect(&socket) {
+            Ok(_) => Finding::new(CHECK, Severity::Ok, "The Docker socket accepts connections"),
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Finding::new(
+                CHECK,
+                Severity::Warning,
+                user_path_io_error("No permission to use the Docker socket", &socket, e),
+            )
+            .action("Add your user to the `docker` group and log in again."),
+            Err(e) => Finding::new(
+                CHECK,
+                Severity::Warning,
+                user_path_io_error("The Docker socket does not accept connections", &socket, e),
+            )
+            .action("Start Docker."),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        log::info!("docker socket {} isn't checked", socket.display());
+        Finding::new(
+            CHECK,
+            Severity::Info,
+            "The Docker socket isn't checked on this platform",
+        )
+    }
+}
+
+/// Free space on the disk mounted deepest above `path`.
+fn available_space(path: &Path) -> Option<u64> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Run every check. `terminal_profile` is the shell from the settings.
+pub fn run_checks(terminal_profile: &str) -> DoctorReport {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+    let mut findings = vec![check_git(), check_shell(terminal_profile), check_pty()];
+    findings.extend(watch_limit_finding(inotify_watch_limit()));
+    findings.push(check_docker());
+    findings.push(disk_finding(available_space(&home)));
+    DoctorReport {
+        severity: findings
+            .iter()
+            .map(|finding| finding.severity)
+            .max()
+            .unwrap_or(Severity::Ok),
+        findings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_finding() {
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-145)\n"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.45.1.windows.1"),
+            Some((2, 45))
+        );
+        assert_eq!(parse_git_version("hub version 2.14.2"), None);
+
+        assert_eq!(
+            git_finding(Ok("git version 2.43.0\n".to_string())).severity,
+            Severity::Ok
+        );
+        let old = git_finding(Ok("git version 2.17.1".to_string()));
+        assert_eq!(old.severity, Severity::Warning);
+        assert_eq!(old.action.as_deref(), Some("Update git to 2.30 or later."));
+        let missing = git_finding(Err("No such file or directory".to_string()));
+        assert_eq!(missing.severity, Severity::Error);
+        assert!(missing.action.is_some());
+    }
+
+    #[test]
+    fn test_limits() {
+        assert!(watch_limit_finding(None).is_none());
+        assert_eq!(
+            watch_limit_finding(Some(8192)).unwrap().severity,
+            Severity::Warning
+        );
+        assert_eq!(
+            watch_limit_finding(Some(524_288)).unwrap().severity,
+            Severity::Ok
+        );
+
+        assert_eq!(disk_finding(Some(100 * MB)).severity, Severity::Error);
+        assert_eq!(disk_finding(Some(1024 * MB)).severity, Severity::Warning);
+        assert_eq!(disk_finding(Some(50 * 1024 * MB)).message, "51200 MB free");
+        assert_eq!(disk_finding(None).severity, Severity::Info);
+    }
+}
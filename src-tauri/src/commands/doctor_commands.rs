@@ -0,0 +1,23 @@
+//! Tauri command for the environment checks of doctor.rs.
+
+use std::sync::Arc;
+
+use super::doctor::{run_checks, DoctorReport};
+use super::error::KiriError;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::settings::SettingsState;
+
+/// Check git, the terminal shell, PTYs, watch limits, Docker and disk
+/// space, with what to do about each problem found.
+#[tauri::command]
+pub async fn run_doctor(
+    settings: tauri::State<'_, SettingsState>,
+) -> Result<DoctorReport, KiriError> {
+    let settings = Arc::clone(&settings);
+    run_blocking("run_doctor", move || {
+        let profile = settings.lock_recover().settings().terminal_profile.clone();
+        Ok(run_checks(&profile))
+    })
+    .await
+}
@@ -0,0 +1,317 @@
+//! Documents open in the editor, per window, with the hash of the file as
+//! it was last read or written and whether the editor holds unsaved
+//! changes.
+//!
+//! The backend keeps this rather than each editor tab so that the rules
+//! hold whatever the webview does: a save is refused when the file
+//! changed on disk since it was read (unless the user chose to overwrite),
+//! a worktree with unsaved documents isn't removed without saying so, and
+//! a reloaded window can ask which documents it had open. Hashes are only
+//! compared within one run of the app, so they come from the standard
+//! library's hasher rather than a stable digest.
+
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hasher;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use super::error::{ErrorCode, KiriError};
+use super::fs_path::display_path;
+use super::lock_ext::LockExt;
+
+/// Hash of a file's bytes, as sent to the frontend.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Hash of the file at `path` now; `None` when it can't be read, e.g.
+/// because it was deleted.
+pub fn disk_hash(path: &Path) -> Option<String> {
+    std::fs::read(path).ok().map(|bytes| content_hash(&bytes))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    pub window: String,
+    pub path: String,
+    /// Hash of the file when the editor last read or wrote it; `None` for
+    /// a file that didn't exist yet.
+    pub disk_hash: Option<String>,
+    pub dirty: bool,
+}
+
+/// A document whose file no longer matches what the editor read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskChange {
+    pub window: String,
+    pub path: String,
+    pub deleted: bool,
+    /// The editor has unsaved changes too, so reloading would lose them.
+    pub conflict: bool,
+}
+
+#[derive(Debug, Clone)]
+struct OpenDocument {
+    disk_hash: Option<String>,
+    dirty: bool,
+}
+
+/// Open documents by window label, then by path.
+#[derive(Debug, Default)]
+pub struct Documents {
+    windows: HashMap<String, BTreeMap<String, OpenDocument>>,
+}
+
+impl Documents {
+    /// Record that `window` read `path` while its hash was `disk_hash`.
+    /// Opening it again, e.g. to reload it, starts it over as saved.
+    pub fn open(&mut self, window: &str, path: &str, disk_hash: Option<String>) -> Document {
+        self.windows.entry(window.to_string()).or_default().insert(
+            path.to_string(),
+            OpenDocument {
+                disk_hash,
+                dirty: false,
+            },
+        );
+        self.get(window, path).expect("just opened")
+    }
+
+    pub fn get(&self, window: &str, path: &str) -> Option<Document> {
+        let open = self.windows.get(window)?.get(path)?;
+        Some(Document {
+            window: window.to_string(),
+            path: path.to_string(),
+            disk_hash: open.disk_hash.clone(),
+            dirty: open.dirty,
+        })
+    }
+
+    pub fn set_dirty(&mut self, window: &str, path: &str, dirty: bool) -> Result<(), String> {
+        let open = self
+            .windows
+            .get_mut(window)
+            .and_then(|documents| documents.get_mut(path))
+            .ok_or_else(|| format!("{} is not open in this window", path))?;
+        open.dirty = dirty;
+        Ok(())
+    }
+
+    /// Error when `window` has `path` open and the file's hash is no
+    /// longer the one it read. Paths that aren't open may be written.
+    pub fn check_save(
+        &self,
+        window: &str,
+        path: &str,
+        current: Option<&str>,
+    ) -> Result<(), String> {
+        let Some(open) = self.windows.get(window).and_then(|d| d.get(path)) else {
+            return Ok(());
+        };
+        if open.disk_hash.as_deref() == current {
+            return Ok(());
+        }
+        Err(match current {
+            Some(_) => format!("{} changed on disk since it was opened", path),
+            None => format!("{} was deleted since it was opened", path),
+        })
+    }
+
+    /// Record that `window` wrote `path`, leaving it with `disk_hash`.
+    /// Other windows with the file open keep the hash they read, so their
+    /// next save notices this one.
+    pub fn saved(&mut self, window: &str, path: &str, disk_hash: Option<String>) -> Document {
+        self.open(window, path, disk_hash)
+    }
+
+    pub fn close(&mut self, window: &str, path: &str) {
+        if let Some(documents) = self.windows.get_mut(window) {
+            documents.remove(path);
+            if documents.is_empty() {
+                self.windows.remove(window);
+            }
+        }
+    }
+
+    /// Forget a closed window's documents.
+    pub fn close_window(&mut self, window: &str) {
+        self.windows.remove(window);
+    }
+
+    /// Documents `window` has open, by path.
+    pub fn list(&self, window: &str) -> Vec<Document> {
+        self.windows
+            .get(window)
+            .into_iter()
+            .flat_map(|documents| documents.keys())
+            .filter_map(|path| self.get(window, path))
+            .collect()
+    }
+
+    /// Documents with unsaved changes in any window, limited to those
+    /// inside `root` when given.
+    pub fn unsaved(&self, root: Option<&Path>) -> Vec<Document> {
+        let mut unsaved: Vec<Document> = self
+            .windows
+            .iter()
+            .flat_map(|(window, documents)| {
+                documents
+                    .iter()
+                    .filter(|(path, open)| {
+                        open.dirty && root.map_or(true, |root| Path::new(path).starts_with(root))
+                    })
+                    .map(move |(path, open)| Document {
+                        window: window.clone(),
+                        path: path.clone(),
+                        disk_hash: open.disk_hash.clone(),
+                        dirty: open.dirty,
+                    })
+            })
+            .collect();
+        unsaved.sort_by(|a, b| (&a.path, &a.window).cmp(&(&b.path, &b.window)));
+        unsaved
+    }
+
+    /// Whether any window has `path` open.
+    pub fn is_open(&self, path: &str) -> bool {
+        self.windows.values().any(|d| d.contains_key(path))
+    }
+
+    /// Compare the documents open on `path` with its hash now, `None`
+    /// once deleted, and return the ones that no longer match.
+    pub fn disk_changes(&self, path: &str, current: Option<&str>) -> Vec<DiskChange> {
+        let mut changes: Vec<DiskChange> = self
+            .windows
+            .iter()
+            .filter_map(|(window, documents)| {
+                let open = documents.get(path)?;
+                (open.disk_hash.as_deref() != current).then(|| DiskChange {
+                    window: window.clone(),
+                    path: path.to_string(),
+                    deleted: current.is_none(),
+                    conflict: open.dirty,
+                })
+            })
+            .collect();
+        changes.sort_by(|a, b| a.window.cmp(&b.window));
+        changes
+    }
+}
+
+/// The documents open in this process.
+pub fn documents() -> &'static Mutex<Documents> {
+    static DOCUMENTS: OnceLock<Mutex<Documents>> = OnceLock::new();
+    DOCUMENTS.get_or_init(|| Mutex::new(Documents::default()))
+}
+
+/// Error with code `conflict` when a window has unsaved changes to a file
+/// inside `root`; for commands that would take those files away.
+pub fn require_no_unsaved(root: &Path) -> Result<(), KiriError> {
+    let root = display_path(root);
+    let unsaved = documents().lock_recover().unsaved(Some(Path::new(&root)));
+    if unsaved.is_empty() {
+        return Ok(());
+    }
+    let paths: BTreeSet<&str> = unsaved.iter().map(|d| d.path.as_str()).collect();
+    Err(KiriError::new(
+        ErrorCode::Conflict,
+        format!(
+            "{} has unsaved changes in {} file(s): {}",
+            root,
+            paths.len(),
+            paths.into_iter().collect::<Vec<_>>().join(", ")
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_is_refused_after_an_external_change() {
+        let mut documents = Documents::default();
+        let read = content_hash(b"fn main() {}\n");
+        documents.open("main", "/repo/src/main.rs", Some(read.clone()));
+        assert_eq!(
+            documents.check_save("main", "/repo/src/main.rs", Some(&read)),
+            Ok(())
+        );
+
+        let edited = content_hash(b"fn main() { todo!() }\n");
+        let err = documents
+            .check_save("main", "/repo/src/main.rs", Some(&edited))
+            .unwrap_err();
+        assert!(err.contains("changed on disk"));
+        assert!(documents
+            .check_save("main", "/repo/src/main.rs", None)
+            .unwrap_err()
+            .contains("deleted"));
+        // Not open here, so nothing to compare with.
+        assert_eq!(
+            documents.check_save("other", "/repo/src/main.rs", None),
+            Ok(())
+        );
+
+        documents.saved("main", "/repo/src/main.rs", Some(edited.clone()));
+        assert_eq!(
+            documents.check_save("main", "/repo/src/main.rs", Some(&edited)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_unsaved_documents_within_a_worktree() {
+        let mut documents = Documents::default();
+        documents.open("main", "/repo/README.md", None);
+        documents.open("main", "/repo-wt/feature/a.rs", None);
+        documents.open("second", "/repo-wt/feature/b.rs", None);
+        documents
+            .set_dirty("main", "/repo/README.md", true)
+            .unwrap();
+        documents
+            .set_dirty("main", "/repo-wt/feature/a.rs", true)
+            .unwrap();
+        assert!(documents
+            .set_dirty("second", "/repo/README.md", true)
+            .is_err());
+
+        let unsaved = documents.unsaved(Some(Path::new("/repo-wt/feature")));
+        assert_eq!(unsaved.len(), 1);
+        assert_eq!(unsaved[0].path, "/repo-wt/feature/a.rs");
+        // `/repo` is not a prefix of `/repo-wt` path-wise.
+        assert_eq!(documents.unsaved(Some(Path::new("/repo"))).len(), 1);
+        assert_eq!(documents.unsaved(None).len(), 2);
+
+        documents.close_window("main");
+        assert!(documents.unsaved(None).is_empty());
+        assert_eq!(documents.list("second").len(), 1);
+    }
+
+    #[test]
+    fn test_disk_changes_flag_conflicts() {
+        let mut documents = Documents::default();
+        let read = content_hash(b"a");
+        documents.open("main", "/repo/a.txt", Some(read.clone()));
+        documents.open("second", "/repo/a.txt", Some(read.clone()));
+        documents.set_dirty("second", "/repo/a.txt", true).unwrap();
+
+        assert!(documents
+            .disk_changes("/repo/a.txt", Some(&read))
+            .is_empty());
+        let changes = documents.disk_changes("/repo/a.txt", Some(&content_hash(b"b")));
+        assert_eq!(changes.len(), 2);
+        assert!(!changes[0].conflict);
+        assert!(changes[1].conflict);
+        assert!(documents.disk_changes("/repo/a.txt", None)[0].deleted);
+
+        documents.close("main", "/repo/a.txt");
+        documents.close("second", "/repo/a.txt");
+        assert!(!documents.is_open("/repo/a.txt"));
+    }
+}
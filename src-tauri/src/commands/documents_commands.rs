@@ -0,0 +1,135 @@
+//! Tauri commands and hooks for the open documents of documents.rs.
+//!
+//! The editor opens and saves files through these instead of `read_file`
+//! and `write_file`, and reports when a document gains or loses unsaved
+//! changes. When the watcher sees a file that a window has open change,
+//! that window gets a `document-changed-on-disk` event.
+
+use notify_debouncer_mini::DebouncedEvent;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+use super::documents::{disk_hash, documents, Document};
+use super::error::{ErrorCode, KiriError};
+use super::file::{read_file_with_format, write_file, FileWithFormat};
+use super::fs_path::{display_path, input_path};
+use super::lock_ext::LockExt;
+use super::text_format::WriteFormat;
+
+pub const DOCUMENT_CHANGED_ON_DISK_EVENT: &str = "document-changed-on-disk";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedDocument {
+    #[serde(flatten)]
+    pub file: FileWithFormat,
+    pub document: Document,
+}
+
+/// Read `path` for window `label` and track it as open and saved. Also
+/// used to reload a document, e.g. after it changed on disk. A file over
+/// the large-file limits is refused unless `force`; see large_file.rs.
+#[tauri::command]
+pub fn open_document(
+    label: String,
+    path: String,
+    force: Option<bool>,
+) -> Result<OpenedDocument, KiriError> {
+    let key = display_path(&input_path(&path)?);
+    // Hashed before reading: if the file changes in between, the next
+    // save reports a conflict rather than missing one.
+    let hash = disk_hash(Path::new(&key));
+    let file = read_file_with_format(path, force)?;
+    let document = documents().lock_recover().open(&label, &key, hash);
+    Ok(OpenedDocument { file, document })
+}
+
+/// Write a document of window `label`. Refused with code `conflict` when
+/// the file changed on disk since the window read it, unless `overwrite`
+/// says the user chose to replace it.
+#[tauri::command]
+pub fn save_document(
+    label: String,
+    path: String,
+    content: String,
+    format: Option<WriteFormat>,
+    overwrite: bool,
+) -> Result<Document, KiriError> {
+    let key = display_path(&input_path(&path)?);
+    if !overwrite {
+        let current = disk_hash(Path::new(&key));
+        documents()
+            .lock_recover()
+            .check_save(&label, &key, current.as_deref())
+            .map_err(|e| KiriError::new(ErrorCode::Conflict, e))?;
+    }
+    write_file(path, content, format)?;
+    let hash = disk_hash(Path::new(&key));
+    Ok(documents().lock_recover().saved(&label, &key, hash))
+}
+
+/// Record whether the editor in window `label` has unsaved changes to
+/// `path`.
+#[tauri::command]
+pub fn set_document_dirty(label: String, path: String, dirty: bool) -> Result<(), KiriError> {
+    let key = display_path(&input_path(&path)?);
+    Ok(documents().lock_recover().set_dirty(&label, &key, dirty)?)
+}
+
+#[tauri::command]
+pub fn close_document(label: String, path: String) -> Result<(), KiriError> {
+    let key = display_path(&input_path(&path)?);
+    documents().lock_recover().close(&label, &key);
+    Ok(())
+}
+
+/// Documents window `label` has open, e.g. to restore its tabs after the
+/// webview reloads.
+#[tauri::command]
+pub fn list_documents(label: String) -> Result<Vec<Document>, KiriError> {
+    Ok(documents().lock_recover().list(&label))
+}
+
+/// Documents with unsaved changes in any window, only those inside
+/// `path` when given. Check before quitting or closing a project.
+#[tauri::command]
+pub fn list_unsaved_documents(path: Option<String>) -> Result<Vec<Document>, KiriError> {
+    let root = path
+        .as_deref()
+        .map(|p| input_path(p).map(|p| display_path(&p)))
+        .transpose()?;
+    Ok(documents()
+        .lock_recover()
+        .unsaved(root.as_deref().map(Path::new)))
+}
+
+/// Drop the documents of a destroyed window.
+pub fn on_window_destroyed(label: &str) {
+    documents().lock_recover().close_window(label);
+}
+
+/// Tell windows whose open documents are among the changed paths that
+/// the file no longer matches what they read. Called from the watcher.
+pub fn on_files_changed(app: &AppHandle, events: &[DebouncedEvent]) {
+    let paths: BTreeSet<String> = events.iter().map(|e| display_path(&e.path)).collect();
+    let open: Vec<String> = {
+        let documents = documents().lock_recover();
+        paths.into_iter().filter(|p| documents.is_open(p)).collect()
+    };
+    for path in open {
+        // Hashed without holding the lock; files can be large.
+        let current = disk_hash(Path::new(&path));
+        let changes = documents()
+            .lock_recover()
+            .disk_changes(&path, current.as_deref());
+        for change in changes {
+            let _ = app.emit_to(
+                change.window.as_str(),
+                DOCUMENT_CHANGED_ON_DISK_EVENT,
+                &change,
+            );
+        }
+    }
+}
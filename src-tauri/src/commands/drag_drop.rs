@@ -3,6 +3,12 @@ use std::collections::HashSet;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 
+use super::error::KiriError;
+use super::fs_path::{display_path, input_path};
+use super::jobs::CancelToken;
+use super::latency::run_blocking;
+use super::operations::{self, OperationRequest};
+
 /// Maximum directory nesting depth followed during recursive copy/move.
 /// Guards against pathological deep trees and unexpected runtime cost.
 const MAX_COPY_DEPTH: usize = 64;
@@ -81,11 +87,16 @@ fn copy_file(source: &Path, target_dir: &Path) -> Result<String, String> {
     std::fs::copy(source, &target_path)
         .map_err(|e| format!("Failed to copy file: {}", e))?;
 
-    Ok(target_path.to_string_lossy().to_string())
+    Ok(display_path(&target_path))
 }
 
-/// Recursively copy a directory to target directory
-fn copy_directory(source: &Path, target_dir: &Path) -> Result<String, String> {
+/// Recursively copy a directory to target directory. A copy cancelled
+/// part way is removed again.
+fn copy_directory(
+    source: &Path,
+    target_dir: &Path,
+    cancel: &CancelToken,
+) -> Result<String, String> {
     let dir_name = source
         .file_name()
         .and_then(OsStr::to_str)
@@ -99,9 +110,15 @@ fn copy_directory(source: &Path, target_dir: &Path) -> Result<String, String> {
         .map_err(|e| format!("Failed to create directory: {}", e))?;
 
     // Copy contents recursively
-    copy_directory_contents(source, &target_path)?;
+    let mut visited = HashSet::new();
+    if let Err(e) = copy_directory_contents_inner(source, &target_path, &mut visited, 0, cancel) {
+        if cancel.is_cancelled() {
+            let _ = std::fs::remove_dir_all(&target_path);
+        }
+        return Err(e);
+    }
 
-    Ok(target_path.to_string_lossy().to_string())
+    Ok(display_path(&target_path))
 }
 
 /// Copy contents of source directory to target directory.
@@ -117,7 +134,7 @@ fn copy_directory(source: &Path, target_dir: &Path) -> Result<String, String> {
 /// would still be caught).
 fn copy_directory_contents(source: &Path, target: &Path) -> Result<(), String> {
     let mut visited = HashSet::new();
-    copy_directory_contents_inner(source, target, &mut visited, 0)
+    copy_directory_contents_inner(source, target, &mut visited, 0, &CancelToken::default())
 }
 
 fn copy_directory_contents_inner(
@@ -125,6 +142,7 @@ fn copy_directory_contents_inner(
     target: &Path,
     visited: &mut HashSet<PathBuf>,
     depth: usize,
+    cancel: &CancelToken,
 ) -> Result<(), String> {
     if depth > MAX_COPY_DEPTH {
         return Err(format!(
@@ -144,6 +162,7 @@ fn copy_directory_contents_inner(
         .map_err(|e| format!("Failed to read directory: {}", e))?;
 
     for entry in entries {
+        operations::check(cancel)?;
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
         // entry.file_type() does NOT follow symlinks (unlike path.is_dir()).
         let file_type = entry
@@ -168,7 +187,7 @@ fn copy_directory_contents_inner(
             let new_target = target.join(dir_name);
             std::fs::create_dir(&new_target)
                 .map_err(|e| format!("Failed to create subdirectory: {}", e))?;
-            copy_directory_contents_inner(&path, &new_target, visited, depth + 1)?;
+            copy_directory_contents_inner(&path, &new_target, visited, depth + 1, cancel)?;
         } else if file_type.is_file() {
             let file_name = path
                 .file_name()
@@ -184,13 +203,15 @@ fn copy_directory_contents_inner(
     Ok(())
 }
 
-/// Copy files/directories to specified directory
-#[tauri::command]
-pub fn copy_paths_to_directory(
+/// Copy files/directories to specified directory. Once `cancel` is
+/// tripped the remaining sources are reported as errors.
+fn copy_paths_to_directory_blocking(
     source_paths: Vec<String>,
     target_dir: String,
+    cancel: &CancelToken,
 ) -> Result<CopyResult, String> {
-    let target_path = Path::new(&target_dir);
+    let target_path = input_path(&target_dir)?;
+    let target_path = target_path.as_path();
 
     if !target_path.exists() {
         return Err(format!("Target directory does not exist: {}", target_dir));
@@ -204,7 +225,17 @@ pub fn copy_paths_to_directory(
     let mut errors: Vec<CopyError> = Vec::new();
 
     for source in &source_paths {
-        let source_path = Path::new(source);
+        let source_path = match operations::check(cancel).and_then(|()| input_path(source)) {
+            Ok(path) => path,
+            Err(e) => {
+                errors.push(CopyError {
+                    path: source.clone(),
+                    error: e,
+                });
+                continue;
+            }
+        };
+        let source_path = source_path.as_path();
 
         if !source_path.exists() {
             errors.push(CopyError {
@@ -215,7 +246,7 @@ pub fn copy_paths_to_directory(
         }
 
         let result = if source_path.is_dir() {
-            copy_directory(source_path, target_path)
+            copy_directory(source_path, target_path, cancel)
         } else {
             copy_file(source_path, target_path)
         };
@@ -238,10 +269,11 @@ pub fn copy_paths_to_directory(
 
 /// Move a file or directory to a target directory.
 /// Tries fs::rename first (fast, same filesystem), falls back to copy + delete.
-#[tauri::command]
-pub fn move_path(source: String, target_dir: String) -> Result<String, String> {
-    let source_path = Path::new(&source);
-    let target_dir_path = Path::new(&target_dir);
+fn move_path_blocking(source: String, target_dir: String) -> Result<String, String> {
+    let source_path = input_path(&source)?;
+    let source_path = source_path.as_path();
+    let target_dir_path = input_path(&target_dir)?;
+    let target_dir_path = target_dir_path.as_path();
 
     // Validate source exists
     if !source_path.exists() {
@@ -298,13 +330,13 @@ pub fn move_path(source: String, target_dir: String) -> Result<String, String> {
 
     // Try fs::rename first (fast, same filesystem)
     if std::fs::rename(source_path, &final_path).is_ok() {
-        return Ok(final_path.to_string_lossy().to_string());
+        return Ok(display_path(&final_path));
     }
 
     // Fall back to copy + delete for cross-device moves
     cross_device_move(source_path, &final_path)?;
 
-    Ok(final_path.to_string_lossy().to_string())
+    Ok(display_path(&final_path))
 }
 
 /// Cross-device move fallback: copy + delete.
@@ -329,6 +361,29 @@ fn cross_device_move(source_path: &Path, final_path: &Path) -> Result<(), String
     Ok(())
 }
 
+// Copies (and cross-device moves) recurse through whole trees, so they run
+// on the blocking pool; see latency.rs.
+
+#[tauri::command]
+pub async fn copy_paths_to_directory(
+    source_paths: Vec<String>,
+    target_dir: String,
+    operation: Option<OperationRequest>,
+) -> Result<CopyResult, KiriError> {
+    let operation = operations::begin(operation);
+    let cancel = operation.token().clone();
+    let result = run_blocking("copy_paths_to_directory", move || {
+        copy_paths_to_directory_blocking(source_paths, target_dir, &cancel)
+    })
+    .await;
+    operation.finish(result)
+}
+
+#[tauri::command]
+pub async fn move_path(source: String, target_dir: String) -> Result<String, KiriError> {
+    run_blocking("move_path", move || move_path_blocking(source, target_dir)).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -417,9 +472,10 @@ mod tests {
 
         fs::write(source_dir.path().join("test.txt"), "content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![source_dir.path().join("test.txt").to_string_lossy().to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -438,12 +494,13 @@ mod tests {
         fs::write(source_dir.path().join("file1.txt"), "content1").unwrap();
         fs::write(source_dir.path().join("file2.txt"), "content2").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 source_dir.path().join("file1.txt").to_string_lossy().to_string(),
                 source_dir.path().join("file2.txt").to_string_lossy().to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -462,9 +519,10 @@ mod tests {
         fs::write(source_dir.path().join("test.txt"), "source content").unwrap();
         fs::write(target_dir.path().join("test.txt"), "existing content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![source_dir.path().join("test.txt").to_string_lossy().to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -492,9 +550,10 @@ mod tests {
         fs::create_dir(&subdir).unwrap();
         fs::write(subdir.join("file.txt"), "content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![subdir.to_string_lossy().to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -515,9 +574,10 @@ mod tests {
         fs::write(nested.join("deep.txt"), "deep content").unwrap();
         fs::write(source_dir.path().join("level1").join("mid.txt"), "mid content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![source_dir.path().join("level1").to_string_lossy().to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -533,9 +593,10 @@ mod tests {
     fn test_copy_paths_to_directory_nonexistent_source() {
         let target_dir = tempdir().unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec!["/nonexistent/path/file.txt".to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -551,9 +612,10 @@ mod tests {
         let source_dir = tempdir().unwrap();
         fs::write(source_dir.path().join("test.txt"), "content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![source_dir.path().join("test.txt").to_string_lossy().to_string()],
             "/nonexistent/target/directory".to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_err());
@@ -569,9 +631,10 @@ mod tests {
         let target_file = target_dir.path().join("target.txt");
         fs::write(&target_file, "target").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![source_dir.path().join("source.txt").to_string_lossy().to_string()],
             target_file.to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_err());
@@ -585,12 +648,13 @@ mod tests {
 
         fs::write(source_dir.path().join("valid.txt"), "content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 source_dir.path().join("valid.txt").to_string_lossy().to_string(),
                 "/nonexistent/file.txt".to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -622,12 +686,37 @@ mod tests {
         fs::create_dir(&subdir).unwrap();
         fs::write(subdir.join("file.txt"), "content").unwrap();
 
-        let result = copy_directory(&subdir, target_dir.path());
+        let result = copy_directory(&subdir, target_dir.path(), &CancelToken::default());
         assert!(result.is_ok());
         assert!(target_dir.path().join("mydir").exists());
         assert!(target_dir.path().join("mydir").join("file.txt").exists());
     }
 
+    #[test]
+    fn test_cancelled_copy_leaves_nothing_behind() {
+        let source_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        let subdir = source_dir.path().join("mydir");
+        fs::create_dir(&subdir).unwrap();
+        fs::write(subdir.join("file.txt"), "content").unwrap();
+        let token = CancelToken::default();
+        token.cancel();
+
+        let result = copy_directory(&subdir, target_dir.path(), &token);
+        assert_eq!(result.unwrap_err(), operations::CANCELLED_MESSAGE);
+        assert!(!target_dir.path().join("mydir").exists());
+
+        let result = copy_paths_to_directory_blocking(
+            vec![subdir.to_string_lossy().to_string()],
+            target_dir.path().to_string_lossy().to_string(),
+            &token,
+        )
+        .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.errors[0].error, operations::CANCELLED_MESSAGE);
+    }
+
     #[test]
     fn test_move_path_file() {
         let source_dir = tempdir().unwrap();
@@ -636,7 +725,7 @@ mod tests {
         let source_file = source_dir.path().join("test.txt");
         fs::write(&source_file, "hello world").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -662,7 +751,7 @@ mod tests {
         fs::create_dir(subdir.join("nested")).unwrap();
         fs::write(subdir.join("nested").join("deep.txt"), "deep").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             subdir.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -684,7 +773,7 @@ mod tests {
         fs::write(&source_file, "source content").unwrap();
         fs::write(target_dir.path().join("test.txt"), "existing content").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -712,7 +801,7 @@ mod tests {
         let child = parent.join("child");
         fs::create_dir_all(&child).unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             parent.to_string_lossy().to_string(),
             child.to_string_lossy().to_string(),
         );
@@ -729,7 +818,7 @@ mod tests {
         let source_file = dir.path().join("test.txt");
         fs::write(&source_file, "content").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             dir.path().to_string_lossy().to_string(),
         );
@@ -743,7 +832,7 @@ mod tests {
     fn test_move_path_nonexistent_source() {
         let target_dir = tempdir().unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             "/nonexistent/path/file.txt".to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -759,7 +848,7 @@ mod tests {
         let source_file = source_dir.path().join("test.txt");
         fs::write(&source_file, "content").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             "/nonexistent/target/directory".to_string(),
         );
@@ -797,9 +886,10 @@ mod tests {
     fn test_copy_paths_to_directory_empty_sources() {
         let target_dir = tempdir().unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -820,7 +910,7 @@ mod tests {
         let target_file = target_dir.path().join("target.txt");
         fs::write(&target_file, "target").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             target_file.to_string_lossy().to_string(),
         );
@@ -885,9 +975,10 @@ mod tests {
         fs::create_dir(parent.join("empty_child")).unwrap();
         fs::write(parent.join("file.txt"), "content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![parent.to_string_lossy().to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -979,7 +1070,7 @@ mod tests {
         fs::write(existing.join("original.txt"), "original").unwrap();
 
         // Copy should create "mydir (1)" due to conflict
-        let result = copy_directory(&subdir, target_dir.path());
+        let result = copy_directory(&subdir, target_dir.path(), &CancelToken::default());
         assert!(result.is_ok());
         let path = result.unwrap();
         assert!(path.contains("mydir (1)"));
@@ -1084,12 +1175,13 @@ mod tests {
         fs::create_dir(&dir_b).unwrap();
         fs::write(dir_b.join("b.txt"), "b").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 dir_a.to_string_lossy().to_string(),
                 dir_b.to_string_lossy().to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -1110,7 +1202,7 @@ mod tests {
         fs::create_dir(&subdir).unwrap();
         fs::write(subdir.join("inner.txt"), "inner").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 source_dir
                     .path()
@@ -1120,6 +1212,7 @@ mod tests {
                 subdir.to_string_lossy().to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -1134,13 +1227,14 @@ mod tests {
     fn test_copy_paths_multiple_nonexistent_sources() {
         let target_dir = tempdir().unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 "/nonexistent/a.txt".to_string(),
                 "/nonexistent/b.txt".to_string(),
                 "/nonexistent/c.txt".to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -1162,7 +1256,7 @@ mod tests {
         let content = b"binary\x00content\xff\xfe";
         fs::write(&source_file, content).unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -1187,7 +1281,7 @@ mod tests {
         fs::write(dir.join("src").join("main.rs"), "fn main() {}").unwrap();
         fs::write(dir.join("src").join("utils").join("helpers.rs"), "pub fn help() {}").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             dir.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -1219,7 +1313,7 @@ mod tests {
         fs::create_dir(&existing).unwrap();
         fs::write(existing.join("existing.txt"), "existing").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             dir.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -1282,13 +1376,14 @@ mod tests {
         let content = "Hello, World!\nLine 2\n\tTabbed line";
         fs::write(source_dir.path().join("test.txt"), content).unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![source_dir
                 .path()
                 .join("test.txt")
                 .to_string_lossy()
                 .to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -1380,7 +1475,7 @@ mod tests {
         let permissions = std::fs::Permissions::from_mode(0o444);
         fs::set_permissions(target_dir.path(), permissions).unwrap();
 
-        let result = copy_directory(&subdir, target_dir.path());
+        let result = copy_directory(&subdir, target_dir.path(), &CancelToken::default());
         assert!(result.is_err());
         let err = result.unwrap_err();
         assert!(
@@ -1402,7 +1497,7 @@ mod tests {
         fs::create_dir(&source).unwrap();
 
         // Try to move a directory into itself
-        let result = move_path(
+        let result = move_path_blocking(
             source.to_string_lossy().to_string(),
             source.to_string_lossy().to_string(),
         );
@@ -1426,7 +1521,7 @@ mod tests {
         let grandchild = child.join("grandchild");
         fs::create_dir_all(&grandchild).unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             parent.to_string_lossy().to_string(),
             grandchild.to_string_lossy().to_string(),
         );
@@ -1450,12 +1545,13 @@ mod tests {
         fs::create_dir(&valid).unwrap();
         fs::write(valid.join("file.txt"), "content").unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 valid.to_string_lossy().to_string(),
                 "/nonexistent/dir".to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -1470,9 +1566,10 @@ mod tests {
     fn test_copy_paths_source_not_found_creates_error() {
         let target_dir = tempdir().unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec!["/nonexistent/file.txt".to_string()],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         assert!(result.is_ok());
@@ -1498,7 +1595,7 @@ mod tests {
         // Create existing file with same name in target
         fs::write(target_dir.join("test.txt"), "existing").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             target_dir.to_string_lossy().to_string(),
         );
@@ -1524,7 +1621,7 @@ mod tests {
         fs::create_dir(&target).unwrap();
         fs::write(source.join("file.txt"), "content").unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             source.to_string_lossy().to_string(),
             target.to_string_lossy().to_string(),
         );
@@ -1563,7 +1660,7 @@ mod tests {
         let permissions = std::fs::Permissions::from_mode(0o000);
         fs::set_permissions(&unreadable_dir, permissions).unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 source_dir
                     .path()
@@ -1578,6 +1675,7 @@ mod tests {
                     .to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         // Restore permissions for cleanup
@@ -1622,13 +1720,14 @@ mod tests {
         let permissions = std::fs::Permissions::from_mode(0o444);
         fs::set_permissions(&readonly_target, permissions).unwrap();
 
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![source_dir
                 .path()
                 .join("file.txt")
                 .to_string_lossy()
                 .to_string()],
             readonly_target.to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         // Restore permissions for cleanup
@@ -1675,7 +1774,7 @@ mod tests {
         fs::write(&source_file, "cross device content").unwrap();
 
         // This will use rename (same FS), but verifies the overall move works
-        let result = move_path(
+        let result = move_path_blocking(
             source_file.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -1711,7 +1810,7 @@ mod tests {
         )
         .unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             dir.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -1821,7 +1920,7 @@ mod tests {
         // gets an error too when the target is unwritable.
         // Actually, we want mixed: some succeed and some fail.
         // Let's use a writable target but with an unreadable source dir.
-        let result = copy_paths_to_directory(
+        let result = copy_paths_to_directory_blocking(
             vec![
                 source_dir
                     .path()
@@ -1831,6 +1930,7 @@ mod tests {
                 bad_dir.to_string_lossy().to_string(),
             ],
             target_dir.path().to_string_lossy().to_string(),
+            &CancelToken::default(),
         );
 
         // Restore permissions for cleanup
@@ -1861,7 +1961,7 @@ mod tests {
         let empty_dir = source_dir.path().join("empty");
         fs::create_dir(&empty_dir).unwrap();
 
-        let result = move_path(
+        let result = move_path_blocking(
             empty_dir.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -1893,7 +1993,7 @@ mod tests {
             fs::write(dir.join(format!("file_{}.txt", i)), format!("content_{}", i)).unwrap();
         }
 
-        let result = move_path(
+        let result = move_path_blocking(
             dir.to_string_lossy().to_string(),
             target_dir.path().to_string_lossy().to_string(),
         );
@@ -0,0 +1,302 @@
+//! Minimal `.editorconfig` resolver.
+//!
+//! Implements the subset of the spec (<https://spec.editorconfig.org>) the
+//! editor cares about: section globs with `*`, `**`, `?`, `[...]` and
+//! `{a,b}` alternation, `root = true`, and the formatting properties
+//! listed on [`EditorConfig`]. Unknown keys are ignored. Files closer to
+//! the target override files further up, and later sections override
+//! earlier ones within a file.
+
+use glob::{MatchOptions, Pattern};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Formatting properties resolved for a single file. `None` means no
+/// `.editorconfig` section set the property.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct EditorConfig {
+    /// `"tab"` or `"space"`.
+    pub indent_style: Option<String>,
+    /// Columns per indent level. `indent_size = tab` resolves to `tab_width`.
+    pub indent_size: Option<u8>,
+    pub tab_width: Option<u8>,
+    /// `"lf"`, `"crlf"` or `"cr"`.
+    pub end_of_line: Option<String>,
+    pub charset: Option<String>,
+    pub insert_final_newline: Option<bool>,
+    pub trim_trailing_whitespace: Option<bool>,
+}
+
+impl EditorConfig {
+    fn apply(&mut self, key: &str, value: &str) {
+        let value_lower = value.to_ascii_lowercase();
+        // `unset` clears a property inherited from a parent file/section.
+        let unset = value_lower == "unset";
+        match key {
+            "indent_style" => {
+                self.indent_style = (!unset).then(|| value_lower.clone());
+            }
+            "indent_size" => {
+                self.indent_size = if value_lower == "tab" {
+                    self.tab_width
+                } else {
+                    value.parse().ok()
+                };
+            }
+            "tab_width" => self.tab_width = value.parse().ok(),
+            "end_of_line" => self.end_of_line = (!unset).then(|| value_lower.clone()),
+            "charset" => self.charset = (!unset).then(|| value_lower.clone()),
+            "insert_final_newline" => self.insert_final_newline = parse_bool(&value_lower),
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = parse_bool(&value_lower),
+            _ => {}
+        }
+    }
+
+    /// Spec rule: if `indent_style = tab` and no size is given, size falls
+    /// back to `tab_width`; `tab_width` in turn defaults to `indent_size`.
+    fn finish(mut self) -> Self {
+        if self.indent_size.is_none() && self.indent_style.as_deref() == Some("tab") {
+            self.indent_size = self.tab_width;
+        }
+        if self.tab_width.is_none() {
+            self.tab_width = self.indent_size;
+        }
+        self
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// One parsed `.editorconfig` file.
+#[derive(Debug, Clone, Default)]
+pub struct EditorConfigFile {
+    pub root: bool,
+    pub sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+/// Parse the INI-like `.editorconfig` format. Comments start with `#` or
+/// `;`; keys are case-insensitive.
+pub fn parse_editorconfig(contents: &str) -> EditorConfigFile {
+    let mut file = EditorConfigFile::default();
+    let mut current: Option<(String, Vec<(String, String)>)> = None;
+
+    for raw in contents.lines() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            if let Some(section) = current.take() {
+                file.sections.push(section);
+            }
+            current = Some((line[1..line.len() - 1].to_string(), Vec::new()));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_string();
+        match current.as_mut() {
+            Some((_, props)) => props.push((key, value)),
+            None if key == "root" => file.root = value.eq_ignore_ascii_case("true"),
+            None => {}
+        }
+    }
+    if let Some(section) = current.take() {
+        file.sections.push(section);
+    }
+    file
+}
+
+/// Expand the first `{a,b,c}` group (recursively), skipping numeric
+/// ranges like `{1..3}` which are rare in practice.
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(open) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+    let Some(close_rel) = glob[open..].find('}') else {
+        return vec![glob.to_string()];
+    };
+    let close = open + close_rel;
+    let inner = &glob[open + 1..close];
+    if !inner.contains(',') {
+        return vec![glob.to_string()];
+    }
+    let (head, tail) = (&glob[..open], &glob[close + 1..]);
+    inner
+        .split(',')
+        .flat_map(|alt| expand_braces(&format!("{}{}{}", head, alt, tail)))
+        .collect()
+}
+
+/// Whether an editorconfig section glob matches `relative` (the target
+/// path relative to the directory holding the `.editorconfig`, using `/`).
+pub fn section_matches(section: &str, relative: &str) -> bool {
+    let options = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    expand_braces(section).iter().any(|glob| {
+        // Globs without a slash match the basename at any depth.
+        let glob = if glob.contains('/') {
+            glob.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", glob)
+        };
+        Pattern::new(&glob)
+            .map(|p| {
+                p.matches_with(relative, options)
+                    || (glob.starts_with("**/")
+                        && p.matches_with(&format!("/{}", relative), options))
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// `.editorconfig` files that apply to `file`, nearest last (so they can
+/// be applied in order). Stops at a file declaring `root = true`.
+fn applicable_configs(file: &Path) -> Vec<(PathBuf, EditorConfigFile)> {
+    let mut found = Vec::new();
+    let mut dir = file.parent();
+    while let Some(d) = dir {
+        let candidate = d.join(".editorconfig");
+        if let Ok(contents) = std::fs::read_to_string(&candidate) {
+            let parsed = parse_editorconfig(&contents);
+            let is_root = parsed.root;
+            found.push((d.to_path_buf(), parsed));
+            if is_root {
+                break;
+            }
+        }
+        dir = d.parent();
+    }
+    found.reverse();
+    found
+}
+
+/// Resolve the effective `.editorconfig` properties for `file`.
+pub fn resolve_editorconfig(file: &Path) -> EditorConfig {
+    let mut config = EditorConfig::default();
+    for (dir, parsed) in applicable_configs(file) {
+        let Ok(relative) = file.strip_prefix(&dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        for (glob, props) in &parsed.sections {
+            if section_matches(glob, &relative) {
+                for (key, value) in props {
+                    config.apply(key, value);
+                }
+            }
+        }
+    }
+    config.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_root_and_sections() {
+        let parsed = parse_editorconfig(
+            "# comment\nroot = true\n\n[*]\nindent_style = space\n[*.md]\ntrim_trailing_whitespace = false\n",
+        );
+        assert!(parsed.root);
+        assert_eq!(parsed.sections.len(), 2);
+        assert_eq!(parsed.sections[0].0, "*");
+        assert_eq!(
+            parsed.sections[0].1,
+            vec![("indent_style".to_string(), "space".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_section_matches_basename_anywhere() {
+        assert!(section_matches("*.rs", "main.rs"));
+        assert!(section_matches("*.rs", "src/deep/main.rs"));
+        assert!(!section_matches("*.rs", "main.ts"));
+    }
+
+    #[test]
+    fn test_section_matches_braces_and_paths() {
+        assert!(section_matches("*.{js,ts}", "src/a.ts"));
+        assert!(section_matches("*.{js,ts}", "a.js"));
+        assert!(section_matches("src/**/*.rs", "src/a/b.rs"));
+        assert!(section_matches("Makefile", "sub/Makefile"));
+        assert!(!section_matches("lib/*.rs", "src/lib/a.rs"));
+    }
+
+    #[test]
+    fn test_resolve_nearest_file_wins_and_root_stops() {
+        let dir = tempdir().unwrap();
+        let outer = dir.path().join("outer");
+        let inner = outer.join("project");
+        fs::create_dir_all(inner.join("src")).unwrap();
+        fs::write(dir.path().join(".editorconfig"), "[*]\ncharset = latin1\n").unwrap();
+        fs::write(
+            outer.join(".editorconfig"),
+            "root = true\n[*]\nindent_style = space\nindent_size = 4\nend_of_line = lf\n",
+        )
+        .unwrap();
+        fs::write(inner.join(".editorconfig"), "[*.rs]\nindent_size = 2\n").unwrap();
+        let file = inner.join("src").join("main.rs");
+
+        let cfg = resolve_editorconfig(&file);
+        assert_eq!(cfg.indent_style.as_deref(), Some("space"));
+        assert_eq!(cfg.indent_size, Some(2));
+        assert_eq!(cfg.tab_width, Some(2));
+        assert_eq!(cfg.end_of_line.as_deref(), Some("lf"));
+        // The outermost file is above `root = true` and must be ignored.
+        assert_eq!(cfg.charset, None);
+    }
+
+    #[test]
+    fn test_indent_size_tab_uses_tab_width() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*]\nindent_style = tab\ntab_width = 8\nindent_size = tab\n",
+        )
+        .unwrap();
+        let cfg = resolve_editorconfig(&dir.path().join("Makefile"));
+        assert_eq!(cfg.indent_size, Some(8));
+    }
+
+    #[test]
+    fn test_unset_clears_inherited_value() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*]\nend_of_line = crlf\n[*.sh]\nend_of_line = unset\n",
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_editorconfig(&dir.path().join("run.sh")).end_of_line,
+            None
+        );
+        assert_eq!(
+            resolve_editorconfig(&dir.path().join("a.bat"))
+                .end_of_line
+                .as_deref(),
+            Some("crlf")
+        );
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("*.{a,b}"), vec!["*.a", "*.b"]);
+        assert_eq!(expand_braces("{x,y}/{1,2}").len(), 4);
+        assert_eq!(expand_braces("{single}"), vec!["{single}"]);
+    }
+}
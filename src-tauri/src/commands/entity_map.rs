@@ -0,0 +1,201 @@
+//! Shared maps of independently locked entities (terminals, watchers).
+//!
+//! A manager used to be one `Arc<Mutex<...>>` around a `HashMap` of
+//! entities, so a PTY write blocked on a full buffer held up every other
+//! terminal's resize, liveness check and kill, and code that took the
+//! manager lock and then another lock (the git cache, the window registry)
+//! could deadlock against code taking them the other way round.
+//!
+//! [`EntityMap`] instead keeps each entity behind its own `Arc`: the map's
+//! `RwLock` is held only for the lookup, insert or removal, and whatever
+//! per-entity locking the entity needs (see `PtyInstance` in terminal.rs)
+//! happens after the map lock is released. A removed entity lives on
+//! until the last caller using it drops its `Arc`.
+//!
+//! # Lock ordering
+//!
+//! Locks are only ever taken in this order, and a lock further down never
+//! calls back up:
+//!
+//! 1. The window registry (`WindowRegistryState`). Released before calling
+//!    into terminals, watchers or the CLI server registry.
+//! 2. The watcher subscription table (`WatcherManager`), which is held
+//!    while a watcher is added to or removed from its map.
+//! 3. An `EntityMap`'s own lock. Never held outside its methods, so it is
+//!    never held while taking an entity's lock or any other lock.
+//! 4. One entity lock at a time (a terminal's writer, master or child).
+//! 5. Leaf locks that take no other lock: the git cache, the operation
+//!    registry, the terminal output bus.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, RwLock};
+
+use super::lock_ext::RwLockExt;
+
+/// Map from `K` to shared, independently locked entities `V`.
+pub struct EntityMap<K, V> {
+    entries: RwLock<HashMap<K, Arc<V>>>,
+}
+
+impl<K: Eq + Hash, V> EntityMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The entity at `key`, if any.
+    pub fn get<Q>(&self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.read_recover().get(key).cloned()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.read_recover().contains_key(key)
+    }
+
+    /// Insert `value` unless `key` is taken, in which case `value` is
+    /// handed back so the caller drops it outside the map lock.
+    pub fn try_insert(&self, key: K, value: V) -> Result<Arc<V>, V> {
+        let mut entries = self.entries.write_recover();
+        if entries.contains_key(&key) {
+            return Err(value);
+        }
+        let value = Arc::new(value);
+        entries.insert(key, Arc::clone(&value));
+        Ok(value)
+    }
+
+    /// Remove and return the entity at `key`.
+    pub fn remove<Q>(&self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.write_recover().remove(key)
+    }
+
+    /// Every key and entity, cloned out so the map lock is released
+    /// before the caller looks at them.
+    pub fn entries(&self) -> Vec<(K, Arc<V>)>
+    where
+        K: Clone,
+    {
+        self.entries
+            .read_recover()
+            .iter()
+            .map(|(k, v)| (k.clone(), Arc::clone(v)))
+            .collect()
+    }
+
+    /// Remove every entity, returning them so they are dropped (and their
+    /// resources released) after the map lock is gone.
+    pub fn drain(&self) -> Vec<Arc<V>> {
+        self.entries
+            .write_recover()
+            .drain()
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read_recover().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.read_recover().is_empty()
+    }
+}
+
+impl<K: Eq + Hash, V> Default for EntityMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::{Barrier, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_try_insert_hands_back_value_when_taken() {
+        let map: EntityMap<String, u32> = EntityMap::new();
+        assert!(map.try_insert("a".into(), 1).is_ok());
+        assert_eq!(map.try_insert("a".into(), 2).unwrap_err(), 2);
+        assert_eq!(*map.get("a").unwrap(), 1);
+        assert_eq!(map.drain().len(), 1);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_locked_entity_does_not_block_the_map_or_other_entities() {
+        let map: Arc<EntityMap<u32, Mutex<Vec<u8>>>> = Arc::new(EntityMap::new());
+        map.try_insert(1, Mutex::new(Vec::new())).ok();
+        map.try_insert(2, Mutex::new(Vec::new())).ok();
+
+        // Hold entity 1's lock on another thread, as a PTY write stuck on
+        // a full buffer would.
+        let (held_tx, held_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let entity = map.get(&1).unwrap();
+        let holder = thread::spawn(move || {
+            let _guard = entity.lock().unwrap();
+            held_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        held_rx.recv().unwrap();
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let worker_map = Arc::clone(&map);
+        thread::spawn(move || {
+            worker_map.get(&2).unwrap().lock().unwrap().push(1);
+            worker_map.try_insert(3, Mutex::new(Vec::new())).ok();
+            let removed = worker_map.remove(&1).is_some();
+            done_tx.send((removed, worker_map.len())).unwrap();
+        });
+        let outcome = done_rx.recv_timeout(Duration::from_secs(5));
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+
+        assert_eq!(outcome, Ok((true, 2)));
+    }
+
+    #[test]
+    fn test_concurrent_inserts_and_removes_stay_consistent() {
+        let map: Arc<EntityMap<u32, u32>> = Arc::new(EntityMap::new());
+        let barrier = Arc::new(Barrier::new(8));
+        let handles: Vec<_> = (0..8u32)
+            .map(|t| {
+                let map = Arc::clone(&map);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    for i in 0..100 {
+                        let key = t * 100 + i;
+                        assert!(map.try_insert(key, key).is_ok());
+                        if i % 2 == 0 {
+                            assert_eq!(map.remove(&key).as_deref(), Some(&key));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(map.len(), 400);
+    }
+}
@@ -0,0 +1,485 @@
+//! Named sets of environment variables (`dev`, `staging`, `test`, ...)
+//! defined per project or per worktree, one of which is active.
+//!
+//! Sets are saved in `~/.kiri/env_sets.json` under the main checkout (the
+//! project) or under a worktree. A worktree with an active set of its own
+//! uses it; otherwise the project's active set applies. The active set is
+//! laid over the environment of new terminals, tasks and init commands,
+//! after the settings layers and before an `.envrc`.
+//!
+//! Variables marked secret keep their value in the secret store (see
+//! secrets.rs) under a random key of the [`SECRET_NAMESPACE`] namespace;
+//! the file only records the key, and the webview never reads the value
+//! back.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::file_io::write_file_contents_atomic;
+use super::settings::check_env_name;
+use super::terminal::EnvOverrides;
+
+pub const SECRET_NAMESPACE: &str = "env";
+
+/// Longest set name, in characters.
+pub const MAX_SET_NAME_CHARS: usize = 40;
+
+pub fn env_sets_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("env_sets.json"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvScope {
+    Project,
+    Worktree,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvVar {
+    pub name: String,
+    /// The value of a plain variable; `None` for a secret.
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub secret: bool,
+    /// Key of a secret's value in the secret store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_key: Option<String>,
+}
+
+/// A variable as the settings UI sends it. A secret without a value keeps
+/// the one saved for the same name.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvVarInput {
+    pub name: String,
+    pub value: Option<String>,
+    #[serde(default)]
+    pub secret: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvSet {
+    pub name: String,
+    pub vars: Vec<EnvVar>,
+}
+
+impl EnvSet {
+    fn secret_keys(&self) -> impl Iterator<Item = &str> {
+        self.vars.iter().filter_map(|v| v.secret_key.as_deref())
+    }
+}
+
+/// The sets of one project or worktree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScopeEnvSets {
+    pub sets: Vec<EnvSet>,
+    pub active: Option<String>,
+}
+
+/// The set in effect in a worktree and where it is defined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveEnvSet {
+    pub scope: EnvScope,
+    pub name: String,
+}
+
+/// A set name trimmed; empty or too long is an error.
+pub fn clean_set_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Environment sets need a name".to_string());
+    }
+    if name.chars().count() > MAX_SET_NAME_CHARS {
+        return Err(format!(
+            "Environment set names can be at most {} characters",
+            MAX_SET_NAME_CHARS
+        ));
+    }
+    Ok(name.to_string())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EnvSetsFile {
+    /// Sets by main checkout or worktree path.
+    scopes: BTreeMap<String, ScopeEnvSets>,
+}
+
+#[derive(Debug, Default)]
+pub struct EnvSets {
+    file: Option<PathBuf>,
+    scopes: BTreeMap<String, ScopeEnvSets>,
+}
+
+pub type EnvSetsState = Arc<Mutex<EnvSets>>;
+
+fn not_found(name: &str) -> String {
+    format!("Environment set {} not found", name)
+}
+
+impl EnvSets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let scopes = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<EnvSetsFile>(&contents) {
+                    Ok(file) => Some(file.scopes),
+                    Err(e) => {
+                        log::warn!("failed to parse environment sets: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            scopes,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = EnvSetsFile {
+            scopes: self.scopes.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)
+            .map_err(|e| format!("Failed to serialize environment sets: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    /// Apply `change` to the sets of `scope` and save, or leave them as
+    /// they were when either fails.
+    fn update<T>(
+        &mut self,
+        scope: &str,
+        change: impl FnOnce(&mut ScopeEnvSets) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let previous = self.scopes.get(scope).cloned();
+        let sets = self.scopes.entry(scope.to_string()).or_default();
+        let result = change(sets);
+        if *sets == ScopeEnvSets::default() {
+            self.scopes.remove(scope);
+        }
+        let result = result.and_then(|result| self.save().map(|_| result));
+        if result.is_err() {
+            match previous {
+                Some(previous) => self.scopes.insert(scope.to_string(), previous),
+                None => self.scopes.remove(scope),
+            };
+        }
+        result
+    }
+
+    pub fn get(&self, scope: &str) -> ScopeEnvSets {
+        self.scopes.get(scope).cloned().unwrap_or_default()
+    }
+
+    /// Create or replace set `name` of `scope` with `vars`. Secret values
+    /// are handed to `store_secret` with their key; a secret sent without
+    /// a value keeps the key it had. Returns the set and the keys of
+    /// secrets it no longer has, for the caller to delete.
+    pub fn put(
+        &mut self,
+        scope: &str,
+        name: &str,
+        vars: Vec<EnvVarInput>,
+        mut store_secret: impl FnMut(&str, &str) -> Result<(), String>,
+    ) -> Result<(EnvSet, Vec<String>), String> {
+        let name = clean_set_name(name)?;
+        let mut seen = BTreeSet::new();
+        for var in &vars {
+            check_env_name(&var.name).map_err(|e| format!("Variable `{}` {}", var.name, e))?;
+            if !seen.insert(var.name.as_str()) {
+                return Err(format!("Variable `{}` is listed twice", var.name));
+            }
+        }
+        let previous = self
+            .scopes
+            .get(scope)
+            .and_then(|sets| sets.sets.iter().find(|set| set.name == name))
+            .cloned();
+        let saved_key = |var_name: &str| {
+            previous.as_ref().and_then(|set| {
+                set.vars
+                    .iter()
+                    .find(|v| v.name == var_name)
+                    .and_then(|v| v.secret_key.clone())
+            })
+        };
+
+        let mut set = EnvSet {
+            name: name.clone(),
+            vars: Vec::with_capacity(vars.len()),
+        };
+        for var in vars {
+            if !var.secret {
+                set.vars.push(EnvVar {
+                    name: var.name,
+                    value: Some(var.value.unwrap_or_default()),
+                    secret: false,
+                    secret_key: None,
+                });
+                continue;
+            }
+            let key = match (var.value, saved_key(&var.name)) {
+                (Some(value), key) => {
+                    let key = key.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                    store_secret(&key, &value)?;
+                    key
+                }
+                (None, Some(key)) => key,
+                (None, None) => return Err(format!("Secret `{}` needs a value", var.name)),
+            };
+            set.vars.push(EnvVar {
+                name: var.name,
+                value: None,
+                secret: true,
+                secret_key: Some(key),
+            });
+        }
+
+        let kept: BTreeSet<&str> = set.secret_keys().collect();
+        let stale = previous
+            .iter()
+            .flat_map(|previous| previous.secret_keys())
+            .filter(|key| !kept.contains(key))
+            .map(str::to_string)
+            .collect();
+        self.update(scope, |sets| {
+            match sets.sets.iter_mut().find(|s| s.name == name) {
+                Some(existing) => *existing = set.clone(),
+                None => sets.sets.push(set.clone()),
+            }
+            Ok(())
+        })?;
+        Ok((set, stale))
+    }
+
+    /// Remove set `name` of `scope`, deactivating it if it was active.
+    /// Returns the keys of its secrets, for the caller to delete.
+    pub fn remove(&mut self, scope: &str, name: &str) -> Result<Vec<String>, String> {
+        self.update(scope, |sets| {
+            let index = sets
+                .sets
+                .iter()
+                .position(|set| set.name == name)
+                .ok_or_else(|| not_found(name))?;
+            let set = sets.sets.remove(index);
+            if sets.active.as_deref() == Some(name) {
+                sets.active = None;
+            }
+            Ok(set.secret_keys().map(str::to_string).collect())
+        })
+    }
+
+    /// Make set `name` of `scope` the active one, or none with `None`.
+    pub fn set_active(&mut self, scope: &str, name: Option<&str>) -> Result<(), String> {
+        self.update(scope, |sets| {
+            if let Some(name) = name {
+                if !sets.sets.iter().any(|set| set.name == name) {
+                    return Err(not_found(name));
+                }
+            }
+            sets.active = name.map(str::to_string);
+            Ok(())
+        })
+    }
+
+    fn active_in(&self, scope: &str) -> Option<&EnvSet> {
+        let sets = self.scopes.get(scope)?;
+        let active = sets.active.as_deref()?;
+        sets.sets.iter().find(|set| set.name == active)
+    }
+
+    /// The set in effect in `worktree` of `project`: the worktree's active
+    /// set, or else the project's.
+    pub fn active(&self, project: &str, worktree: &str) -> Option<(EnvScope, &EnvSet)> {
+        self.active_in(worktree)
+            .map(|set| (EnvScope::Worktree, set))
+            .or_else(|| self.active_in(project).map(|set| (EnvScope::Project, set)))
+    }
+
+    /// Variables of the set in effect in `worktree`, secrets read with
+    /// `read_secret`. A secret that can't be read is left out.
+    pub fn overlay(
+        &self,
+        project: &str,
+        worktree: &str,
+        read_secret: impl Fn(&str) -> Option<String>,
+    ) -> EnvOverrides {
+        let Some((_, set)) = self.active(project, worktree) else {
+            return EnvOverrides::new();
+        };
+        set.vars
+            .iter()
+            .filter_map(|var| {
+                let value = match &var.secret_key {
+                    Some(key) if var.secret => match read_secret(key) {
+                        Some(value) => value,
+                        None => {
+                            log::warn!(
+                                "secret `{}` of environment set {} is missing",
+                                var.name,
+                                set.name
+                            );
+                            return None;
+                        }
+                    },
+                    _ => var.value.clone().unwrap_or_default(),
+                };
+                Some((var.name.clone(), Some(value)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn plain(name: &str, value: &str) -> EnvVarInput {
+        EnvVarInput {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+            secret: false,
+        }
+    }
+
+    fn secret(name: &str, value: Option<&str>) -> EnvVarInput {
+        EnvVarInput {
+            name: name.to_string(),
+            value: value.map(str::to_string),
+            secret: true,
+        }
+    }
+
+    #[test]
+    fn test_secrets_stay_out_of_the_file() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("env_sets.json");
+        let mut store = HashMap::new();
+        let mut sets = EnvSets::load(file.clone());
+        let (set, stale) = sets
+            .put(
+                "/repo",
+                " staging ",
+                vec![
+                    plain("API_URL", "https://staging"),
+                    secret("API_TOKEN", Some("s3cr3t")),
+                ],
+                |key, value| {
+                    store.insert(key.to_string(), value.to_string());
+                    Ok(())
+                },
+            )
+            .unwrap();
+        assert_eq!(set.name, "staging");
+        assert!(stale.is_empty());
+        let key = set.vars[1].secret_key.clone().unwrap();
+        assert_eq!(store[&key], "s3cr3t");
+        assert!(!std::fs::read_to_string(&file).unwrap().contains("s3cr3t"));
+
+        // Without a value the secret keeps its key; dropping it reports it.
+        let (set, _) = sets
+            .put(
+                "/repo",
+                "staging",
+                vec![secret("API_TOKEN", None)],
+                |_, _| panic!("nothing to store"),
+            )
+            .unwrap();
+        assert_eq!(set.vars[0].secret_key.as_deref(), Some(key.as_str()));
+        let (_, stale) = sets
+            .put("/repo", "staging", vec![plain("API_URL", "x")], |_, _| {
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(stale, vec![key]);
+        assert!(sets
+            .put("/repo", "dev", vec![secret("NEW", None)], |_, _| Ok(()))
+            .is_err());
+        assert!(sets
+            .put(
+                "/repo",
+                "dev",
+                vec![plain("A", "1"), plain("A", "2")],
+                |_, _| Ok(())
+            )
+            .is_err());
+
+        let reloaded = EnvSets::load(file);
+        assert_eq!(reloaded.get("/repo").sets.len(), 1);
+    }
+
+    #[test]
+    fn test_worktree_set_overrides_project_set() {
+        let mut sets = EnvSets::new();
+        let no_secrets = |_: &str, _: &str| Ok(());
+        sets.put("/repo", "dev", vec![plain("MODE", "dev")], no_secrets)
+            .unwrap();
+        sets.put(
+            "/repo-wt/feature",
+            "test",
+            vec![plain("MODE", "test")],
+            no_secrets,
+        )
+        .unwrap();
+        assert!(sets
+            .overlay("/repo", "/repo-wt/feature", |_| None)
+            .is_empty());
+        assert!(sets.set_active("/repo", Some("missing")).is_err());
+
+        sets.set_active("/repo", Some("dev")).unwrap();
+        let env = sets.overlay("/repo", "/repo-wt/feature", |_| None);
+        assert_eq!(env["MODE"].as_deref(), Some("dev"));
+
+        sets.set_active("/repo-wt/feature", Some("test")).unwrap();
+        let (scope, set) = sets.active("/repo", "/repo-wt/feature").unwrap();
+        assert_eq!((scope, set.name.as_str()), (EnvScope::Worktree, "test"));
+
+        sets.remove("/repo-wt/feature", "test").unwrap();
+        assert_eq!(
+            sets.active("/repo", "/repo-wt/feature").unwrap().0,
+            EnvScope::Project
+        );
+    }
+
+    #[test]
+    fn test_overlay_reads_secrets_and_skips_missing_ones() {
+        let mut sets = EnvSets::new();
+        let (set, _) = sets
+            .put(
+                "/repo",
+                "prod",
+                vec![secret("TOKEN", Some("t")), secret("OTHER", Some("o"))],
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        sets.set_active("/repo", Some("prod")).unwrap();
+        let token_key = set.vars[0].secret_key.clone().unwrap();
+        let env = sets.overlay("/repo", "/repo", |key| {
+            (key == token_key).then(|| "from-store".to_string())
+        });
+        assert_eq!(env.len(), 1);
+        assert_eq!(env["TOKEN"].as_deref(), Some("from-store"));
+    }
+}
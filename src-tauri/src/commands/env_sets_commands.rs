@@ -0,0 +1,182 @@
+//! Tauri commands for the environment sets of env_sets.rs.
+//!
+//! Commands take a directory and the scope to edit: the project (its main
+//! checkout) or the worktree the directory is in. Each change is announced
+//! with an `env-sets-changed` event carrying the scope's path. Terminals,
+//! tasks and init commands started afterwards get the active set through
+//! [`env_set_overlay`]; running ones keep their environment.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::auto_fetch::main_checkout;
+use super::env_sets::{
+    env_sets_file_path, ActiveEnvSet, EnvScope, EnvSet, EnvSets, EnvSetsState, EnvVarInput,
+    SECRET_NAMESPACE,
+};
+use super::error::KiriError;
+use super::fs_path::{display_path, normalize_input_path};
+use super::git_worktree::current_worktree_root;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::secrets;
+use super::terminal::EnvOverrides;
+
+pub const ENV_SETS_CHANGED_EVENT: &str = "env-sets-changed";
+
+/// The sets of one scope, and the set in effect in the directory asked
+/// about, which may come from the other scope.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvSetList {
+    pub scope: String,
+    pub sets: Vec<EnvSet>,
+    pub active: Option<String>,
+    pub effective: Option<ActiveEnvSet>,
+}
+
+/// Read `~/.kiri/env_sets.json` into the managed state. Call from setup.
+pub fn load_env_sets(app: &AppHandle) {
+    let Some(path) = env_sets_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<EnvSetsState>() {
+        *state.lock_recover() = EnvSets::load(path);
+    }
+}
+
+/// Keys of the project and the worktree `dir` is in. Outside a
+/// repository both are `dir`.
+fn scope_keys(dir: &Path) -> (String, String) {
+    let worktree = current_worktree_root(dir).unwrap_or_else(|| dir.to_path_buf());
+    let project = main_checkout(&worktree).unwrap_or_else(|| worktree.clone());
+    (display_path(&project), display_path(&worktree))
+}
+
+fn scope_key(dir: &Path, scope: EnvScope) -> String {
+    let (project, worktree) = scope_keys(dir);
+    match scope {
+        EnvScope::Project => project,
+        EnvScope::Worktree => worktree,
+    }
+}
+
+fn list(sets: &EnvSets, dir: &Path, scope: EnvScope) -> EnvSetList {
+    let (project, worktree) = scope_keys(dir);
+    let key = match scope {
+        EnvScope::Project => project.clone(),
+        EnvScope::Worktree => worktree.clone(),
+    };
+    let listed = sets.get(&key);
+    EnvSetList {
+        scope: key,
+        sets: listed.sets,
+        active: listed.active,
+        effective: sets
+            .active(&project, &worktree)
+            .map(|(scope, set)| ActiveEnvSet {
+                scope,
+                name: set.name.clone(),
+            }),
+    }
+}
+
+fn delete_secrets(keys: &[String]) {
+    for key in keys {
+        if let Err(e) = secrets::delete_secret(SECRET_NAMESPACE, key, None) {
+            log::warn!("failed to delete environment secret: {}", e);
+        }
+    }
+}
+
+/// Variables of the environment set in effect in `dir`, to lay over a new
+/// terminal's, task's or init command's environment.
+pub fn env_set_overlay(app: &AppHandle, dir: &Path) -> EnvOverrides {
+    let Some(state) = app.try_state::<EnvSetsState>() else {
+        return EnvOverrides::new();
+    };
+    let (project, worktree) = scope_keys(dir);
+    let overlay = state.lock_recover().overlay(&project, &worktree, |key| {
+        secrets::get_secret(SECRET_NAMESPACE, key)
+    });
+    overlay
+}
+
+/// The environment sets of `scope` for `path`. Secret values are left
+/// out.
+#[tauri::command]
+pub fn list_env_sets(
+    state: tauri::State<'_, EnvSetsState>,
+    path: String,
+    scope: EnvScope,
+) -> EnvSetList {
+    list(&state.lock_recover(), &normalize_input_path(&path), scope)
+}
+
+/// Create or replace set `name` of `scope` for `path`. Secret values go
+/// to the secret store; a secret sent without a value keeps its saved one.
+#[tauri::command]
+pub async fn save_env_set(
+    app: AppHandle,
+    state: tauri::State<'_, EnvSetsState>,
+    path: String,
+    scope: EnvScope,
+    name: String,
+    vars: Vec<EnvVarInput>,
+) -> Result<EnvSet, KiriError> {
+    let state: EnvSetsState = Arc::clone(&state);
+    run_blocking("save_env_set", move || {
+        let key = scope_key(&normalize_input_path(&path), scope);
+        let (set, stale) = state
+            .lock_recover()
+            .put(&key, &name, vars, |secret_key, value| {
+                secrets::set_secret(SECRET_NAMESPACE, secret_key, value)
+            })?;
+        delete_secrets(&stale);
+        let _ = app.emit(ENV_SETS_CHANGED_EVENT, &key);
+        Ok(set)
+    })
+    .await
+}
+
+/// Remove set `name` of `scope` for `path` and its secrets.
+#[tauri::command]
+pub async fn delete_env_set(
+    app: AppHandle,
+    state: tauri::State<'_, EnvSetsState>,
+    path: String,
+    scope: EnvScope,
+    name: String,
+) -> Result<(), KiriError> {
+    let state: EnvSetsState = Arc::clone(&state);
+    run_blocking("delete_env_set", move || {
+        let key = scope_key(&normalize_input_path(&path), scope);
+        let secret_keys = state.lock_recover().remove(&key, &name)?;
+        delete_secrets(&secret_keys);
+        let _ = app.emit(ENV_SETS_CHANGED_EVENT, &key);
+        Ok(())
+    })
+    .await
+}
+
+/// Make set `name` of `scope` the active one for `path`, or clear it with
+/// `None` so the project's set (for a worktree) or none applies.
+#[tauri::command]
+pub fn set_active_env_set(
+    app: AppHandle,
+    state: tauri::State<'_, EnvSetsState>,
+    path: String,
+    scope: EnvScope,
+    name: Option<String>,
+) -> Result<EnvSetList, KiriError> {
+    let dir = normalize_input_path(&path);
+    let key = scope_key(&dir, scope);
+    let mut sets = state.lock_recover();
+    sets.set_active(&key, name.as_deref())?;
+    let listed = list(&sets, &dir, scope);
+    drop(sets);
+    let _ = app.emit(ENV_SETS_CHANGED_EVENT, &key);
+    Ok(listed)
+}
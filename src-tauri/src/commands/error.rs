@@ -9,9 +9,188 @@
 //!
 //! Use [`user_message`] (or [`user_io_error`]) to log the full detail at
 //! the backend and return a redacted string to the caller.
+//!
+//! Commands return [`KiriError`], which reaches the frontend as `{code,
+//! message, retryable}`: the code is stable and meant for branching, the
+//! message for showing. The code is picked where the error is raised,
+//! with [`KiriError::new`]; helpers still returning `String` convert with
+//! `?` or `KiriError::from` and come out as [`ErrorCode::Internal`], as a
+//! message is never parsed for what it means. Likewise an error is shown
+//! in the user's language only when it is raised from a catalog key, with
+//! [`KiriError::localized`] or [`user_error`]; the key and its parameters
+//! are sent along as `key` and `params`, see i18n.rs.
 
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::Path;
 
+use super::i18n::{Locale, Message};
+use super::large_file::LargeFile;
+
+/// What went wrong, for callers to branch on. Serialized in snake_case;
+/// the values are part of the IPC contract and don't change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    /// An argument or setting was rejected.
+    InvalidInput,
+    /// The folder isn't trusted to run its commands, see trust.rs.
+    NotTrusted,
+    /// Signing in to a service is needed first.
+    Unauthenticated,
+    /// A service or a process kiri relies on isn't there or refused.
+    Unavailable,
+    Network,
+    Timeout,
+    Cancelled,
+    /// Not possible on this platform.
+    Unsupported,
+    /// The file changed on disk since it was read, or has unsaved changes
+    /// that the operation would lose; see documents.rs.
+    Conflict,
+    /// Too large for the editor to open; the details say why and what to
+    /// do instead, see large_file.rs.
+    FileTooLarge,
+    /// Anything else; the message says what.
+    Internal,
+}
+
+impl ErrorCode {
+    /// The code of a failed IO call.
+    pub fn of_io(kind: std::io::ErrorKind) -> ErrorCode {
+        use std::io::ErrorKind;
+        match kind {
+            ErrorKind::NotFound => ErrorCode::NotFound,
+            ErrorKind::AlreadyExists => ErrorCode::AlreadyExists,
+            ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            ErrorKind::InvalidInput | ErrorKind::InvalidData => ErrorCode::InvalidInput,
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => ErrorCode::Timeout,
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected => ErrorCode::Network,
+            ErrorKind::Interrupted => ErrorCode::Cancelled,
+            _ => ErrorCode::Internal,
+        }
+    }
+
+    /// Whether trying the same again may work.
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::Unavailable | ErrorCode::Network | ErrorCode::Timeout
+        )
+    }
+}
+
+/// Structured data sent with some codes, tagged by `kind`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ErrorDetails {
+    LargeFile(LargeFile),
+}
+
+/// The error every command returns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KiriError {
+    pub code: ErrorCode,
+    /// For showing to the user, in their language; never parsed.
+    pub message: String,
+    pub retryable: bool,
+    /// Catalog key of the message, when it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<&'static str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<&'static str, String>,
+    /// Boxed, as most errors have none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Box<ErrorDetails>>,
+}
+
+impl KiriError {
+    /// An error showing `message` as written, in English.
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            retryable: code.retryable(),
+            key: None,
+            params: BTreeMap::new(),
+            details: None,
+        }
+    }
+
+    /// An error showing catalog `message` in the current locale.
+    pub fn localized(code: ErrorCode, message: Message) -> Self {
+        Self {
+            code,
+            message: message.text(),
+            retryable: code.retryable(),
+            key: Some(message.key),
+            params: message.params,
+            details: None,
+        }
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn with_details(mut self, details: ErrorDetails) -> Self {
+        self.details = Some(Box::new(details));
+        self
+    }
+}
+
+impl fmt::Display for KiriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for KiriError {}
+
+/// Fallback for helpers that fail with a bare message: nothing is known
+/// of the cause, so the code is [`ErrorCode::Internal`].
+impl From<String> for KiriError {
+    fn from(message: String) -> Self {
+        KiriError::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<&str> for KiriError {
+    fn from(message: &str) -> Self {
+        KiriError::from(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for KiriError {
+    fn from(e: std::io::Error) -> Self {
+        KiriError::new(ErrorCode::of_io(e.kind()), e.to_string())
+    }
+}
+
+/// For Rust callers of a command that still deal in strings. A
+/// translated message goes back to English, as the rest of those strings
+/// are.
+impl From<KiriError> for String {
+    fn from(e: KiriError) -> Self {
+        match e.key {
+            Some(key) => Message {
+                key,
+                params: e.params,
+            }
+            .text_in(Locale::En),
+            None => e.message,
+        }
+    }
+}
+
 /// Log `detail` at `warn!` level and return `summary` for the caller.
 ///
 /// `summary` should be a short, user-readable string that does NOT contain
@@ -33,6 +212,34 @@ pub fn user_io_error(summary: &'static str, err: impl std::fmt::Display) -> Stri
     user_message(summary, err)
 }
 
+/// [`user_io_error`] for a failed operation on `path`, which goes to the
+/// log with the error.
+pub fn user_path_io_error(summary: &'static str, path: &Path, err: impl fmt::Display) -> String {
+    user_message(summary, format_args!("{}: {}", path.display(), err))
+}
+
+/// The last component of `path`, for messages that mustn't show the
+/// whole of it.
+pub fn path_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// [`user_message`] for commands: log `detail` and return an error showing
+/// catalog message `key` in the user's language.
+pub fn user_error(code: ErrorCode, key: &'static str, detail: impl fmt::Display) -> KiriError {
+    let message = Message::new(key);
+    log::warn!("{}: {}", message.text_in(Locale::En), detail);
+    KiriError::localized(code, message)
+}
+
+/// [`user_error`] for a failed IO call, coded by its kind.
+pub fn user_io_failure(key: &'static str, err: std::io::Error) -> KiriError {
+    user_error(ErrorCode::of_io(err.kind()), key, err)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,13 +260,74 @@ mod tests {
     }
 
     #[test]
-    fn user_io_error_returns_summary_only() {
-        let io_err = std::io::Error::new(
-            std::io::ErrorKind::PermissionDenied,
-            "owner=root mode=0600",
+    fn kiri_error_codes() {
+        // Wording doesn't decide the code: a hunk that's gone is no missing
+        // file, and a message about the network needn't be worth a retry.
+        for message in [
+            "Hunk not found in a.rs: the diff changed since it was shown",
+            "Failed to read network settings",
+            "Proxy check timed out",
+        ] {
+            let error = KiriError::from(message);
+            assert_eq!(error.code, ErrorCode::Internal, "{}", message);
+            assert!(!error.retryable, "{}", message);
+        }
+
+        let error = KiriError::from(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "no response",
+        ));
+        assert_eq!(error.code, ErrorCode::Timeout);
+        assert!(error.retryable);
+        assert_eq!(
+            serde_json::to_value(KiriError::new(ErrorCode::NotFound, "gone")).unwrap(),
+            serde_json::json!({ "code": "not_found", "message": "gone", "retryable": false })
+        );
+        assert_eq!(String::from(KiriError::from("x")), "x");
+    }
+
+    #[test]
+    fn kiri_error_carries_catalog_key() {
+        let error = KiriError::localized(
+            ErrorCode::NotTrusted,
+            Message::new("error.not_trusted").with("name", "app"),
+        );
+        assert_eq!(error.key, Some("error.not_trusted"));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap()["params"],
+            serde_json::json!({ "name": "app" })
+        );
+        assert_eq!(
+            String::from(error),
+            "app is not trusted; trust the folder to run its commands"
+        );
+
+        // Messages aren't looked up in the catalogs by their wording.
+        let error = KiriError::new(ErrorCode::NotFound, "Path does not exist");
+        assert_eq!(error.key, None);
+
+        let error = user_io_failure(
+            "error.read_failed",
+            std::io::Error::new(std::io::ErrorKind::PermissionDenied, "/home/secret"),
         );
+        assert_eq!(error.code, ErrorCode::PermissionDenied);
+        assert_eq!(error.key, Some("error.read_failed"));
+        assert!(!error.message.contains("secret"));
+    }
+
+    #[test]
+    fn user_io_error_returns_summary_only() {
+        let io_err =
+            std::io::Error::new(std::io::ErrorKind::PermissionDenied, "owner=root mode=0600");
         let msg = user_io_error("Failed to read file", io_err);
         assert_eq!(msg, "Failed to read file");
         assert!(!msg.contains("root"));
     }
+
+    #[test]
+    fn user_path_io_error_returns_summary_only() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        let msg = user_path_io_error("Failed to read file", Path::new("/home/secret"), io_err);
+        assert_eq!(msg, "Failed to read file");
+    }
 }
@@ -0,0 +1,361 @@
+//! Per-channel throttling for high-frequency events.
+//!
+//! Terminal output, watcher notifications and job progress can arrive far
+//! faster than the webview can take them, and every emit crosses the IPC
+//! bridge on its own. Each event name gets a [`Policy`]. Events are
+//! grouped by a key within their channel, e.g. the terminal id or the
+//! watched root:
+//!
+//! - [`Policy::Immediate`] passes events straight through.
+//! - [`Policy::Coalesce`] keeps only the newest payload per key, and emits
+//!   at most once per window. Use it for "something changed" and state
+//!   updates, where the latest value supersedes earlier ones.
+//! - [`Policy::Concat`] appends a string field of each payload per key, and
+//!   flushes once per window or when the batch reaches a size cap. Use it
+//!   for streams such as terminal output, where nothing may be dropped.
+//!
+//! The first event after a quiet period always goes out at once, so
+//! interactive echo gains no latency. Only bursts are batched. Time is
+//! passed in, so this module has no clock or thread of its own; the
+//! flusher and `emit_throttled` live in event_throttle_commands.rs.
+
+use serde_json::Value;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    Immediate,
+    Coalesce {
+        window: Duration,
+    },
+    Concat {
+        window: Duration,
+        /// String field of the payload that is appended.
+        field: &'static str,
+        /// Flush early once the batched field reaches this many bytes.
+        max_bytes: usize,
+    },
+}
+
+/// Policies for the app's high-frequency events.
+pub fn default_policies() -> HashMap<&'static str, Policy> {
+    HashMap::from([
+        (
+            "terminal-output",
+            Policy::Concat {
+                // About one frame.
+                window: Duration::from_millis(16),
+                field: "data",
+                max_bytes: 64 * 1024,
+            },
+        ),
+        (
+            "docker-log",
+            Policy::Concat {
+                window: Duration::from_millis(100),
+                field: "data",
+                max_bytes: 64 * 1024,
+            },
+        ),
+        (
+            "task-output",
+            Policy::Concat {
+                window: Duration::from_millis(16),
+                field: "data",
+                max_bytes: 64 * 1024,
+            },
+        ),
+        (
+            "agent-output",
+            Policy::Concat {
+                window: Duration::from_millis(16),
+                field: "data",
+                max_bytes: 64 * 1024,
+            },
+        ),
+        (
+            "test-progress",
+            Policy::Coalesce {
+                window: Duration::from_millis(100),
+            },
+        ),
+        (
+            "fs-changed",
+            Policy::Coalesce {
+                window: Duration::from_millis(500),
+            },
+        ),
+        (
+            "git-status-changed",
+            Policy::Coalesce {
+                window: Duration::from_millis(500),
+            },
+        ),
+        (
+            "todos-updated",
+            Policy::Coalesce {
+                window: Duration::from_millis(500),
+            },
+        ),
+        (
+            "job-updated",
+            Policy::Coalesce {
+                window: Duration::from_millis(100),
+            },
+        ),
+    ])
+}
+
+struct Slot {
+    last_emit: Instant,
+    window: Duration,
+    pending: Option<Value>,
+}
+
+/// An event ready to be emitted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emission {
+    pub event: &'static str,
+    pub payload: Value,
+}
+
+pub struct EventThrottle {
+    policies: HashMap<&'static str, Policy>,
+    slots: HashMap<(&'static str, String), Slot>,
+}
+
+impl Default for EventThrottle {
+    fn default() -> Self {
+        Self::new(default_policies())
+    }
+}
+
+fn field_len(payload: &Value, field: &str) -> usize {
+    payload
+        .get(field)
+        .and_then(Value::as_str)
+        .map_or(0, str::len)
+}
+
+/// Append `next`'s `field` to `batch`'s; other fields take `next`'s values.
+fn concat_into(batch: &mut Value, next: Value, field: &str) {
+    let mut text = batch
+        .get(field)
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string();
+    text.push_str(next.get(field).and_then(Value::as_str).unwrap_or(""));
+    *batch = next;
+    if let Some(obj) = batch.as_object_mut() {
+        obj.insert(field.to_string(), Value::String(text));
+    }
+}
+
+impl EventThrottle {
+    pub fn new(policies: HashMap<&'static str, Policy>) -> Self {
+        Self {
+            policies,
+            slots: HashMap::new(),
+        }
+    }
+
+    /// Offer an event. Returns it (or a batch containing it) when it
+    /// should be emitted now; otherwise it is held for [`Self::due`].
+    pub fn offer(
+        &mut self,
+        event: &'static str,
+        key: &str,
+        payload: Value,
+        now: Instant,
+    ) -> Option<Emission> {
+        let policy = self
+            .policies
+            .get(event)
+            .copied()
+            .unwrap_or(Policy::Immediate);
+        let window = match policy {
+            Policy::Immediate => return Some(Emission { event, payload }),
+            Policy::Coalesce { window } | Policy::Concat { window, .. } => window,
+        };
+
+        let slot = match self.slots.entry((event, key.to_string())) {
+            Entry::Vacant(entry) => {
+                entry.insert(Slot {
+                    last_emit: now,
+                    window,
+                    pending: None,
+                });
+                return Some(Emission { event, payload });
+            }
+            Entry::Occupied(entry) => entry.into_mut(),
+        };
+        if slot.pending.is_none() && now.duration_since(slot.last_emit) >= window {
+            slot.last_emit = now;
+            return Some(Emission { event, payload });
+        }
+
+        match policy {
+            Policy::Concat {
+                field, max_bytes, ..
+            } => {
+                let batch = match slot.pending.take() {
+                    Some(mut batch) => {
+                        concat_into(&mut batch, payload, field);
+                        batch
+                    }
+                    None => payload,
+                };
+                if field_len(&batch, field) >= max_bytes {
+                    slot.last_emit = now;
+                    return Some(Emission {
+                        event,
+                        payload: batch,
+                    });
+                }
+                slot.pending = Some(batch);
+            }
+            _ => slot.pending = Some(payload),
+        }
+        None
+    }
+
+    /// Held events whose window has passed. Order is only preserved
+    /// within a key.
+    pub fn due(&mut self, now: Instant) -> Vec<Emission> {
+        let mut ready = Vec::new();
+        self.slots.retain(|(event, _), slot| {
+            if now.duration_since(slot.last_emit) < slot.window {
+                return true;
+            }
+            match slot.pending.take() {
+                Some(payload) => {
+                    slot.last_emit = now;
+                    ready.push(Emission { event, payload });
+                    true
+                }
+                // Quiet for a whole window: forget the key.
+                None => false,
+            }
+        });
+        ready
+    }
+
+    /// When [`Self::due`] next has something to return, if anything is held.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.slots
+            .values()
+            .filter(|slot| slot.pending.is_some())
+            .map(|slot| slot.last_emit + slot.window)
+            .min()
+    }
+}
+
+/// App-wide throttle plus the condition variable that wakes the flusher
+/// when an event is held.
+#[derive(Default)]
+pub struct ThrottledEmitter {
+    pub throttle: Mutex<EventThrottle>,
+    pub wake: Condvar,
+}
+
+impl ThrottledEmitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+pub type ThrottledEmitterState = Arc<ThrottledEmitter>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn throttle() -> EventThrottle {
+        EventThrottle::new(HashMap::from([
+            ("coalesce", Policy::Coalesce { window: 100 * MS }),
+            (
+                "concat",
+                Policy::Concat {
+                    window: 10 * MS,
+                    field: "data",
+                    max_bytes: 8,
+                },
+            ),
+        ]))
+    }
+
+    #[test]
+    fn test_unlisted_events_pass_through() {
+        let mut t = throttle();
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(t.offer("other", "k", json!(1), now).is_some());
+        }
+        assert!(t.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_coalesce_emits_leading_then_latest() {
+        let mut t = throttle();
+        let start = Instant::now();
+        assert_eq!(
+            t.offer("coalesce", "a", json!(1), start).unwrap().payload,
+            json!(1)
+        );
+        assert!(t.offer("coalesce", "a", json!(2), start + MS).is_none());
+        assert!(t.offer("coalesce", "a", json!(3), start + 2 * MS).is_none());
+        // Other keys have their own window.
+        assert!(t.offer("coalesce", "b", json!(9), start + 2 * MS).is_some());
+
+        assert_eq!(t.next_deadline(), Some(start + 100 * MS));
+        assert!(t.due(start + 50 * MS).is_empty());
+        let flushed = t.due(start + 100 * MS);
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0].payload, json!(3));
+        assert!(t.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_concat_keeps_every_chunk_in_order() {
+        let mut t = throttle();
+        let start = Instant::now();
+        let chunk = |s: &str| json!({"id": 1, "data": s});
+        assert!(t.offer("concat", "1", chunk("a"), start).is_some());
+        assert!(t.offer("concat", "1", chunk("b"), start + MS).is_none());
+        assert!(t.offer("concat", "1", chunk("c"), start + 2 * MS).is_none());
+
+        let flushed = t.due(start + 10 * MS);
+        assert_eq!(flushed[0].payload, json!({"id": 1, "data": "bc"}));
+    }
+
+    #[test]
+    fn test_concat_flushes_at_size_cap() {
+        let mut t = throttle();
+        let start = Instant::now();
+        let chunk = |s: &str| json!({"id": 1, "data": s});
+        t.offer("concat", "1", chunk("x"), start);
+        assert!(t.offer("concat", "1", chunk("1234"), start + MS).is_none());
+        let early = t.offer("concat", "1", chunk("5678"), start + MS).unwrap();
+        assert_eq!(early.payload["data"], "12345678");
+        assert!(t.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_quiet_keys_are_forgotten() {
+        let mut t = throttle();
+        let start = Instant::now();
+        t.offer("coalesce", "a", json!(1), start);
+        assert!(t.due(start + 100 * MS).is_empty());
+        assert!(t.slots.is_empty());
+        // After the quiet period the next event goes out at once.
+        assert!(t
+            .offer("coalesce", "a", json!(2), start + 101 * MS)
+            .is_some());
+    }
+}
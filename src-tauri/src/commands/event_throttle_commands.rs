@@ -0,0 +1,97 @@
+//! Emitting through the event throttle, and the thread that flushes held
+//! events. Policies live in event_throttle.rs. Everything emitted here goes
+//! out in a versioned envelope; see events.rs.
+//!
+//! The flusher starts the first time an event is held, not at startup.
+
+use super::event_throttle::{Emission, ThrottledEmitterState};
+use super::events::{self, EventEnvelope};
+use super::lock_ext::LockExt;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Set once the flusher thread has been started.
+static FLUSHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+fn emit_now(app: &AppHandle, emission: Emission) {
+    let envelope = EventEnvelope::new(emission.event, emission.payload);
+    let _ = app.emit(emission.event, envelope);
+}
+
+/// Emit `event` to every window under its channel's throttle policy.
+/// `key` separates independent streams within the channel, e.g. one per
+/// terminal. Events without a policy go out at once. `event` must have a
+/// schema in events.rs.
+pub fn emit_throttled<S: Serialize>(app: &AppHandle, event: &'static str, key: &str, payload: &S) {
+    debug_assert!(events::schema(event).is_some(), "{event} has no schema");
+    let payload = match serde_json::to_value(payload) {
+        Ok(value) => value,
+        Err(e) => {
+            log::warn!("failed to serialize {} event: {}", event, e);
+            return;
+        }
+    };
+    let Some(emitter) = app.try_state::<ThrottledEmitterState>() else {
+        emit_now(app, Emission { event, payload });
+        return;
+    };
+    let ready = emitter
+        .throttle
+        .lock_recover()
+        .offer(event, key, payload, Instant::now());
+    match ready {
+        Some(emission) => emit_now(app, emission),
+        None => {
+            ensure_flusher(app, emitter.inner());
+            emitter.wake.notify_one();
+        }
+    }
+}
+
+/// Start the thread that emits held events once their window has passed,
+/// unless it is already running. It sleeps until the earliest deadline, or
+/// until an event is held.
+fn ensure_flusher(app: &AppHandle, emitter: &ThrottledEmitterState) {
+    if FLUSHER_STARTED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let emitter = Arc::clone(emitter);
+    let app = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("kiri-event-flusher".to_string())
+        .spawn(move || loop {
+            let ready = {
+                let mut throttle = emitter.throttle.lock_recover();
+                loop {
+                    let now = Instant::now();
+                    let ready = throttle.due(now);
+                    if !ready.is_empty() {
+                        break ready;
+                    }
+                    throttle = match throttle.next_deadline() {
+                        Some(deadline) => {
+                            emitter
+                                .wake
+                                .wait_timeout(throttle, deadline.saturating_duration_since(now))
+                                .unwrap_or_else(|e| e.into_inner())
+                                .0
+                        }
+                        None => emitter
+                            .wake
+                            .wait(throttle)
+                            .unwrap_or_else(|e| e.into_inner()),
+                    };
+                }
+            };
+            for emission in ready {
+                emit_now(&app, emission);
+            }
+        });
+    if let Err(e) = spawned {
+        FLUSHER_STARTED.store(false, Ordering::Release);
+        log::warn!("failed to start event flusher: {}", e);
+    }
+}
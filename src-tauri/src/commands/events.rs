@@ -0,0 +1,358 @@
+//! Versioned schema of the events the backend streams to the frontend.
+//!
+//! File changes, terminal and task output, job progress and the other
+//! events sent through `emit_throttled` go out wrapped in an
+//! [`EventEnvelope`] carrying [`EVENT_SCHEMA_VERSION`] and the event kind,
+//! so a frontend built against a different schema notices instead of
+//! misreading payloads. [`EVENT_SCHEMAS`] documents each kind's payload;
+//! the TypeScript types in `src/lib/services/events.generated.ts` are
+//! generated from it, and the tests below check it against the structs
+//! that are actually serialized.
+//!
+//! Bump [`EVENT_SCHEMA_VERSION`] when a payload changes incompatibly:
+//! a field is removed, renamed or changes type. Adding a kind or an
+//! optional field does not need a bump.
+//!
+//! To regenerate the TypeScript after changing the schema, run
+//! `KIRI_UPDATE_EVENT_TYPES=1 cargo test --lib events`.
+
+use serde::Serialize;
+use std::fmt::Write;
+
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// What the frontend receives for every event in [`EVENT_SCHEMAS`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventEnvelope<T> {
+    pub version: u32,
+    /// The event name, repeated so a payload can be checked on its own.
+    pub kind: &'static str,
+    pub payload: T,
+}
+
+impl<T> EventEnvelope<T> {
+    pub fn new(kind: &'static str, payload: T) -> Self {
+        Self {
+            version: EVENT_SCHEMA_VERSION,
+            kind,
+            payload,
+        }
+    }
+}
+
+/// One field of a payload, as serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    /// TypeScript type of the field.
+    pub ts_type: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventSchema {
+    pub kind: &'static str,
+    /// Name of the payload type, the same in Rust and TypeScript.
+    pub payload: &'static str,
+    pub doc: &'static str,
+    pub fields: &'static [FieldSchema],
+}
+
+/// A TypeScript union type used by payload fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnionSchema {
+    pub name: &'static str,
+    pub variants: &'static [&'static str],
+}
+
+const fn field(name: &'static str, ts_type: &'static str) -> FieldSchema {
+    FieldSchema { name, ts_type }
+}
+
+/// Union types referenced by [`EVENT_SCHEMAS`].
+pub const UNION_SCHEMAS: &[UnionSchema] = &[UnionSchema {
+    name: "JobStatus",
+    variants: &[
+        "{ state: 'queued' }",
+        "{ state: 'running' }",
+        "{ state: 'completed' }",
+        "{ state: 'failed'; error: string }",
+        "{ state: 'cancelled' }",
+    ],
+}];
+
+pub const EVENT_SCHEMAS: &[EventSchema] = &[
+    EventSchema {
+        kind: "fs-changed",
+        payload: "FsChangeEvent",
+        doc: "Files changed under a watched path.",
+        fields: &[field("path", "string")],
+    },
+    EventSchema {
+        kind: "git-status-changed",
+        payload: "GitChangeEvent",
+        doc: "The git state of a watched repository changed.",
+        fields: &[field("repo_root", "string")],
+    },
+    EventSchema {
+        kind: "terminal-output",
+        payload: "TerminalOutput",
+        doc: "A chunk of terminal output.",
+        fields: &[field("id", "number"), field("data", "string")],
+    },
+    EventSchema {
+        kind: "job-updated",
+        payload: "JobInfo",
+        doc: "A background job changed state or reported progress.",
+        fields: &[
+            field("id", "number"),
+            field("kind", "string"),
+            field("label", "string"),
+            field("status", "JobStatus"),
+            field("progress", "number | null"),
+            field("message", "string | null"),
+            field("createdMs", "number"),
+            field("updatedMs", "number"),
+        ],
+    },
+    EventSchema {
+        kind: "task-output",
+        payload: "TaskOutput",
+        doc: "A chunk of a task run's output.",
+        fields: &[field("run_id", "number"), field("data", "string")],
+    },
+    EventSchema {
+        kind: "agent-output",
+        payload: "AgentOutput",
+        doc: "A chunk of an agent session's output.",
+        fields: &[field("session_id", "number"), field("data", "string")],
+    },
+    EventSchema {
+        kind: "docker-log",
+        payload: "DockerLogEvent",
+        doc: "A chunk of a container's logs.",
+        fields: &[field("container_id", "string"), field("data", "string")],
+    },
+    EventSchema {
+        kind: "test-progress",
+        payload: "TestProgress",
+        doc: "Counts so far for a running test job.",
+        fields: &[
+            field("run_id", "number"),
+            field("passed", "number"),
+            field("failed", "number"),
+            field("skipped", "number"),
+            field("current", "string | null"),
+        ],
+    },
+    EventSchema {
+        kind: "todos-updated",
+        payload: "TodosUpdatedEvent",
+        doc: "A project's TODO scan was refreshed.",
+        fields: &[field("project", "string")],
+    },
+    EventSchema {
+        kind: "slow-filesystem",
+        payload: "SlowFilesystemEvent",
+        doc: "A filesystem operation ran over its time budget.",
+        fields: &[
+            field("path", "string"),
+            field("operation", "string"),
+            field("partial", "boolean"),
+        ],
+    },
+];
+
+/// The schema of event `kind`, if it is a versioned event.
+pub fn schema(kind: &str) -> Option<&'static EventSchema> {
+    EVENT_SCHEMAS.iter().find(|schema| schema.kind == kind)
+}
+
+/// TypeScript declarations for the schema, as checked in at
+/// `src/lib/services/events.generated.ts`.
+pub fn typescript_definitions() -> String {
+    let mut out = String::from(
+        "// Generated from src-tauri/src/commands/events.rs. Do not edit by hand;\n\
+         // regenerate with `KIRI_UPDATE_EVENT_TYPES=1 cargo test --lib events`.\n\n",
+    );
+    let _ = writeln!(
+        out,
+        "/** Bumped on any incompatible change to an event payload. */\n\
+         export const EVENT_SCHEMA_VERSION = {EVENT_SCHEMA_VERSION};\n"
+    );
+    for union in UNION_SCHEMAS {
+        let variants: Vec<String> = union.variants.iter().map(|v| format!("  | {v}")).collect();
+        let _ = writeln!(
+            out,
+            "export type {} =\n{};\n",
+            union.name,
+            variants.join("\n")
+        );
+    }
+    for schema in EVENT_SCHEMAS {
+        let _ = writeln!(out, "/** {} */", schema.doc);
+        let _ = writeln!(out, "export interface {} {{", schema.payload);
+        for field in schema.fields {
+            let _ = writeln!(out, "  {}: {};", field.name, field.ts_type);
+        }
+        out.push_str("}\n\n");
+    }
+    out.push_str("/** Payload type of each versioned backend event, by kind. */\n");
+    out.push_str("export interface BackendEvents {\n");
+    for schema in EVENT_SCHEMAS {
+        let _ = writeln!(out, "  '{}': {};", schema.kind, schema.payload);
+    }
+    out.push_str(
+        "}\n\n\
+         export type BackendEventKind = keyof BackendEvents;\n\n\
+         /** What the backend emits for every event in BackendEvents. */\n\
+         export interface EventEnvelope<K extends BackendEventKind = BackendEventKind> {\n  \
+           version: number;\n  \
+           kind: K;\n  \
+           payload: BackendEvents[K];\n\
+         }\n",
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agents::AgentOutput;
+    use crate::commands::docker::DockerLogEvent;
+    use crate::commands::event_throttle::default_policies;
+    use crate::commands::fs_budget::SlowFilesystemEvent;
+    use crate::commands::jobs::{JobInfo, JobStatus};
+    use crate::commands::tasks::TaskOutput;
+    use crate::commands::terminal::TerminalOutput;
+    use crate::commands::test_runner::TestProgress;
+    use crate::commands::todo_scanner::TodosUpdatedEvent;
+    use crate::commands::watcher::{FsChangeEvent, GitChangeEvent};
+    use serde_json::{json, Value};
+
+    fn sample_payloads() -> Vec<(&'static str, Value)> {
+        let data = || "output".to_string();
+        let job = JobInfo {
+            id: 1,
+            kind: "tests".to_string(),
+            label: "Run tests".to_string(),
+            status: JobStatus::Failed {
+                error: "exit 1".to_string(),
+            },
+            progress: Some(50),
+            message: None,
+            created_ms: 0,
+            updated_ms: 0,
+        };
+        vec![
+            ("fs-changed", json!(FsChangeEvent { path: data() })),
+            (
+                "git-status-changed",
+                json!(GitChangeEvent { repo_root: data() }),
+            ),
+            (
+                "terminal-output",
+                json!(TerminalOutput {
+                    id: 1,
+                    data: data()
+                }),
+            ),
+            ("job-updated", json!(job)),
+            (
+                "task-output",
+                json!(TaskOutput {
+                    run_id: 1,
+                    data: data()
+                }),
+            ),
+            (
+                "agent-output",
+                json!(AgentOutput {
+                    session_id: 1,
+                    data: data()
+                }),
+            ),
+            (
+                "docker-log",
+                json!(DockerLogEvent {
+                    container_id: data(),
+                    data: data(),
+                }),
+            ),
+            (
+                "test-progress",
+                json!(TestProgress {
+                    run_id: 1,
+                    passed: 1,
+                    failed: 0,
+                    skipped: 0,
+                    current: None,
+                }),
+            ),
+            (
+                "todos-updated",
+                json!(TodosUpdatedEvent { project: data() }),
+            ),
+            (
+                "slow-filesystem",
+                json!(SlowFilesystemEvent {
+                    path: data().into(),
+                    operation: "read_directory".to_string(),
+                    partial: true,
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_envelope_wire_format() {
+        let envelope = EventEnvelope::new(
+            "fs-changed",
+            FsChangeEvent {
+                path: "/repo".into(),
+            },
+        );
+        assert_eq!(
+            json!(envelope),
+            json!({ "version": 1, "kind": "fs-changed", "payload": { "path": "/repo" } })
+        );
+    }
+
+    #[test]
+    fn test_schemas_match_serialized_payloads() {
+        let samples = sample_payloads();
+        assert_eq!(samples.len(), EVENT_SCHEMAS.len());
+        for (kind, payload) in samples {
+            let schema = schema(kind).unwrap_or_else(|| panic!("no schema for {kind}"));
+            let mut serialized: Vec<&str> = payload
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .collect();
+            let mut documented: Vec<&str> = schema.fields.iter().map(|f| f.name).collect();
+            serialized.sort_unstable();
+            documented.sort_unstable();
+            assert_eq!(serialized, documented, "fields of {kind}");
+        }
+        // Every throttled event is a versioned one.
+        for kind in default_policies().keys() {
+            assert!(schema(kind).is_some(), "no schema for {kind}");
+        }
+    }
+
+    #[test]
+    fn test_generated_typescript_is_up_to_date() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../src/lib/services/events.generated.ts");
+        let generated = typescript_definitions();
+        if std::env::var_os("KIRI_UPDATE_EVENT_TYPES").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            checked_in == generated,
+            "{} is stale; regenerate it with KIRI_UPDATE_EVENT_TYPES=1 cargo test --lib events",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,558 @@
+//! Export of the working tree diff or of whole files, for sharing a
+//! review outside the app: as a `.patch` that `git apply` takes, as a
+//! standalone HTML page, or as a PDF.
+//!
+//! The HTML page carries its own styles; files in a language the syntax
+//! service knows are highlighted with it, diffs are coloured by line. The
+//! PDF is written here rather than by a rendering engine: monospaced text
+//! in the standard Courier fonts, which every viewer has, so characters
+//! outside Latin-1 print as `?`. The Tauri commands are in
+//! export_commands.rs.
+
+use git2::{DiffFormat, DiffOptions, Repository};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use super::fs_path::{path_from_wire, path_to_wire};
+use super::syntax::{HighlightSpan, SyntaxDocument, SyntaxLanguage};
+
+/// Files larger than this are exported without syntax highlighting.
+const MAX_HIGHLIGHT_BYTES: usize = 1024 * 1024;
+
+/// Characters per PDF line before it wraps, and lines per page.
+const PDF_COLUMNS: usize = 95;
+const PDF_LINES_PER_PAGE: usize = 66;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Patch,
+    Html,
+    Pdf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub path: String,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    /// Patch text for one file.
+    Diff,
+    /// A file's content.
+    File,
+}
+
+/// One file of an export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportSection {
+    /// Relative to the repository or project, in wire form.
+    pub path: String,
+    pub kind: SectionKind,
+    pub text: String,
+}
+
+/// The changes in the working tree and index of the repository at `root`
+/// against HEAD, untracked files included, one section per file. With
+/// `paths` (relative, in wire form) only those files are included.
+pub fn diff_sections(root: &Path, paths: &[String]) -> Result<Vec<ExportSection>, String> {
+    let repo = Repository::open(root).map_err(|e| e.to_string())?;
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .disable_pathspec_match(true);
+    for path in paths {
+        options.pathspec(path_from_wire(path));
+    }
+    // An unborn HEAD diffs against the empty tree.
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo
+        .diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut options))
+        .map_err(|e| e.to_string())?;
+
+    let mut sections: Vec<ExportSection> = Vec::new();
+    diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(path_to_wire)
+            .unwrap_or_default();
+        if line.origin() == 'F' || sections.last().map_or(true, |s| s.path != path) {
+            sections.push(ExportSection {
+                path,
+                kind: SectionKind::Diff,
+                text: String::new(),
+            });
+        }
+        let text = &mut sections.last_mut().expect("pushed above").text;
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            text.push(line.origin());
+        }
+        text.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(sections)
+}
+
+/// Patch text for `sections`. Files become patches that add them.
+pub fn render_patch(sections: &[ExportSection]) -> String {
+    let mut patch = String::new();
+    for section in sections {
+        match section.kind {
+            SectionKind::Diff => patch.push_str(&section.text),
+            SectionKind::File => patch.push_str(&new_file_patch(&section.path, &section.text)),
+        }
+    }
+    patch
+}
+
+fn new_file_patch(path: &str, text: &str) -> String {
+    let mut patch = format!(
+        "diff --git a/{path} b/{path}\nnew file mode 100644\n",
+        path = path
+    );
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    if lines.is_empty() {
+        return patch;
+    }
+    let _ = write!(
+        patch,
+        "--- /dev/null\n+++ b/{}\n@@ -0,0 +1,{} @@\n",
+        path,
+        lines.len()
+    );
+    for line in &lines {
+        patch.push('+');
+        patch.push_str(line);
+    }
+    if !text.ends_with('\n') {
+        patch.push_str("\n\\ No newline at end of file\n");
+    }
+    patch
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `text` as HTML with `spans` (UTF-16 offsets, enclosing spans first, as
+/// [`SyntaxDocument::highlights`] returns them) wrapped in `hl-` classes.
+fn highlighted_html(text: &str, spans: &[HighlightSpan]) -> String {
+    // Byte offset of each UTF-16 offset that starts a character.
+    let mut byte_at = Vec::with_capacity(text.len() + 1);
+    for (index, c) in text.char_indices() {
+        for _ in 0..c.len_utf16() {
+            byte_at.push(index);
+        }
+    }
+    byte_at.push(text.len());
+    let byte = |utf16: usize| byte_at[utf16.min(byte_at.len() - 1)];
+
+    let mut html = String::with_capacity(text.len() * 2);
+    let mut written = 0;
+    let mut open: Vec<usize> = Vec::new();
+    for span in spans {
+        let (from, to) = (byte(span.from), byte(span.to));
+        while let Some(&end) = open.last().filter(|&&end| end <= from) {
+            html.push_str(&escape_html(&text[written..end]));
+            html.push_str("</span>");
+            written = end;
+            open.pop();
+        }
+        if from < written || open.last().is_some_and(|&end| to > end) {
+            // Overlaps what's already open rather than nesting in it.
+            continue;
+        }
+        html.push_str(&escape_html(&text[written..from]));
+        let _ = write!(html, "<span class=\"hl-{}\">", span.kind.replace('.', "-"));
+        written = from;
+        open.push(to);
+    }
+    while let Some(end) = open.pop() {
+        html.push_str(&escape_html(&text[written..end]));
+        html.push_str("</span>");
+        written = end;
+    }
+    html.push_str(&escape_html(&text[written..]));
+    html
+}
+
+/// Class of a patch line in the HTML and colour in the PDF.
+fn diff_line_class(line: &str) -> &'static str {
+    const FILE_HEADERS: [&str; 5] = [
+        "diff ",
+        "--- a/",
+        "+++ b/",
+        "--- /dev/null",
+        "+++ /dev/null",
+    ];
+    if FILE_HEADERS.iter().any(|header| line.starts_with(header)) {
+        "meta"
+    } else if line.starts_with("@@") {
+        "hunk"
+    } else if line.starts_with('+') {
+        "add"
+    } else if line.starts_with('-') {
+        "del"
+    } else if line.starts_with(' ') || line.is_empty() {
+        "ctx"
+    } else {
+        "meta"
+    }
+}
+
+const HTML_STYLE: &str = "\
+body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif;margin:2rem;color:#1f2328;background:#fff}
+h1{font-size:1.4rem}h2{font-size:1rem;margin:0;padding:.5rem .75rem;background:#f6f8fa;border-bottom:1px solid #d0d7de}
+section{border:1px solid #d0d7de;border-radius:6px;margin:1rem 0;overflow:hidden}
+pre{margin:0;padding:.75rem;font:12px/1.5 ui-monospace,SFMono-Regular,Menlo,Consolas,monospace;overflow-x:auto}
+.line{display:block}.add{background:#e6ffec}.del{background:#ffebe9}.hunk{color:#0550ae;background:#ddf4ff}.meta{color:#57606a}
+[class^=hl-keyword],[class^=hl-operator]{color:#cf222e}[class^=hl-string]{color:#0a3069}
+[class^=hl-comment]{color:#6e7781;font-style:italic}[class^=hl-function],[class^=hl-method]{color:#8250df}
+[class^=hl-type],[class^=hl-constructor]{color:#953800}[class^=hl-number],[class^=hl-constant]{color:#0550ae}
+[class^=hl-property],[class^=hl-attribute]{color:#116329}
+";
+
+/// A standalone HTML page titled `title` showing `sections`.
+pub fn render_html(title: &str, sections: &[ExportSection]) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+        title = escape_html(title),
+        style = HTML_STYLE
+    );
+    for section in sections {
+        let _ = write!(
+            html,
+            "<section>\n<h2>{}</h2>\n<pre>",
+            escape_html(&section.path)
+        );
+        match section.kind {
+            SectionKind::Diff => {
+                for line in section.text.lines() {
+                    let _ = write!(
+                        html,
+                        "<span class=\"line {}\">{}\n</span>",
+                        diff_line_class(line),
+                        escape_html(line)
+                    );
+                }
+            }
+            SectionKind::File => {
+                let spans = SyntaxLanguage::for_path(Path::new(&section.path))
+                    .filter(|_| section.text.len() <= MAX_HIGHLIGHT_BYTES)
+                    .and_then(|language| {
+                        SyntaxDocument::parse(language, section.text.clone())
+                            .and_then(|document| document.highlights(None))
+                            .ok()
+                    })
+                    .unwrap_or_default();
+                html.push_str(&highlighted_html(&section.text, &spans));
+            }
+        }
+        html.push_str("</pre>\n</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PdfStyle {
+    Heading,
+    Text,
+    Add,
+    Delete,
+    Hunk,
+    Meta,
+}
+
+impl PdfStyle {
+    fn operators(self) -> &'static str {
+        match self {
+            PdfStyle::Heading => "/F2 9 Tf 0 0 0 rg",
+            PdfStyle::Text => "/F1 9 Tf 0 0 0 rg",
+            PdfStyle::Add => "/F1 9 Tf 0 0.45 0 rg",
+            PdfStyle::Delete => "/F1 9 Tf 0.75 0 0 rg",
+            PdfStyle::Hunk => "/F1 9 Tf 0 0.3 0.7 rg",
+            PdfStyle::Meta => "/F1 9 Tf 0.4 0.4 0.4 rg",
+        }
+    }
+}
+
+/// `line` as a PDF string literal in WinAnsi, which matches Latin-1 in
+/// the printable ranges.
+fn pdf_string(line: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for c in line.chars() {
+        let byte = match u32::from(c) {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => code as u8,
+            _ => b'?',
+        };
+        if matches!(byte, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(byte);
+    }
+    out.push(b')');
+    out
+}
+
+/// Lines of the PDF with their style, wrapped at [`PDF_COLUMNS`].
+fn pdf_lines(sections: &[ExportSection]) -> Vec<(PdfStyle, String)> {
+    let mut lines = Vec::new();
+    for (index, section) in sections.iter().enumerate() {
+        if index > 0 {
+            lines.push((PdfStyle::Text, String::new()));
+        }
+        lines.push((PdfStyle::Heading, section.path.clone()));
+        for line in section.text.lines() {
+            let style = match section.kind {
+                SectionKind::File => PdfStyle::Text,
+                SectionKind::Diff => match diff_line_class(line) {
+                    "add" => PdfStyle::Add,
+                    "del" => PdfStyle::Delete,
+                    "hunk" => PdfStyle::Hunk,
+                    "meta" => PdfStyle::Meta,
+                    _ => PdfStyle::Text,
+                },
+            };
+            let chars: Vec<char> = line.replace('\t', "    ").chars().collect();
+            if chars.is_empty() {
+                lines.push((style, String::new()));
+            }
+            for chunk in chars.chunks(PDF_COLUMNS) {
+                lines.push((style, chunk.iter().collect()));
+            }
+        }
+    }
+    lines
+}
+
+/// A PDF titled `title` showing `sections` on A4 pages.
+pub fn render_pdf(title: &str, sections: &[ExportSection]) -> Vec<u8> {
+    let lines = pdf_lines(sections);
+    let pages: Vec<&[(PdfStyle, String)]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(PDF_LINES_PER_PAGE).collect()
+    };
+
+    // Objects 1 to 4 are the catalog, the page tree and the two fonts;
+    // then each page and its content stream.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + 2 * i).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    for (index, page) in pages.iter().enumerate() {
+        let mut content = b"BT 11 TL 40 800 Td\n".to_vec();
+        let mut style = None;
+        for (line_style, line) in page.iter() {
+            if style != Some(*line_style) {
+                content.extend_from_slice(line_style.operators().as_bytes());
+                content.push(b'\n');
+                style = Some(*line_style);
+            }
+            content.extend(pdf_string(line));
+            content.extend_from_slice(b" Tj T*\n");
+        }
+        content.extend_from_slice(b"ET\nBT /F1 8 Tf 0.5 0.5 0.5 rg 40 30 Td ");
+        content.extend(pdf_string(&format!(
+            "{} - {} / {}",
+            title,
+            index + 1,
+            pages.len()
+        )));
+        content.extend_from_slice(b" Tj ET\n");
+
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                page_ids[index] + 1
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend_from_slice(b"endstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", index + 1).into_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .into_bytes(),
+    );
+    pdf
+}
+
+/// `sections` in `format`.
+pub fn render(format: ExportFormat, title: &str, sections: &[ExportSection]) -> Vec<u8> {
+    match format {
+        ExportFormat::Patch => render_patch(sections).into_bytes(),
+        ExportFormat::Html => render_html(title, sections).into_bytes(),
+        ExportFormat::Pdf => render_pdf(title, sections),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn repo_with_commit(dir: &Path) -> Repository {
+        let repo = Repository::init(dir).unwrap();
+        fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("dev", "dev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        drop(tree);
+        repo
+    }
+
+    #[test]
+    fn test_diff_sections_and_patch() {
+        let dir = TempDir::new().unwrap();
+        repo_with_commit(dir.path());
+        fs::write(dir.path().join("a.txt"), "one\n2\n").unwrap();
+        fs::write(dir.path().join("new.rs"), "fn main() {}").unwrap();
+
+        let sections = diff_sections(dir.path(), &[]).unwrap();
+        let paths: Vec<&str> = sections.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths, ["a.txt", "new.rs"]);
+        assert!(sections[0].text.starts_with("diff --git a/a.txt b/a.txt\n"));
+        assert!(sections[0].text.contains("\n-two\n+2\n"));
+        assert!(sections[1].text.contains("+fn main() {}"));
+
+        let only = diff_sections(dir.path(), &["new.rs".to_string()]).unwrap();
+        assert_eq!(only.len(), 1);
+
+        let file = ExportSection {
+            path: "notes.md".to_string(),
+            kind: SectionKind::File,
+            text: "a\nb".to_string(),
+        };
+        assert_eq!(
+            render_patch(&[file]),
+            "diff --git a/notes.md b/notes.md\nnew file mode 100644\n--- /dev/null\n\
+             +++ b/notes.md\n@@ -0,0 +1,2 @@\n+a\n+b\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn test_html_escapes_and_nests_highlights() {
+        let spans = [
+            HighlightSpan {
+                from: 0,
+                to: 7,
+                kind: "string",
+            },
+            HighlightSpan {
+                from: 1,
+                to: 4,
+                kind: "string.escape",
+            },
+        ];
+        // "é" is one UTF-16 unit but two bytes.
+        assert_eq!(
+            highlighted_html("\"é<b\" & x", &spans),
+            "<span class=\"hl-string\">&quot;<span class=\"hl-string-escape\">é&lt;b</span>&quot; &amp;</span> x"
+        );
+
+        let diff = ExportSection {
+            path: "<a>.txt".to_string(),
+            kind: SectionKind::Diff,
+            text: "@@ -1 +1 @@\n-old\n+new\n".to_string(),
+        };
+        let html = render_html("Review", &[diff]);
+        assert!(html.contains("<h2>&lt;a&gt;.txt</h2>"));
+        assert!(html.contains("<span class=\"line del\">-old\n</span>"));
+        assert!(html.contains("<span class=\"line add\">+new\n</span>"));
+    }
+
+    #[test]
+    fn test_pdf_pages_and_xref() {
+        let text: String = (0..100).map(|i| format!("+line (x) {}\n", i)).collect();
+        let section = ExportSection {
+            path: "a.txt".to_string(),
+            kind: SectionKind::Diff,
+            text,
+        };
+        let pdf = render_pdf("Changes — kiri", &[section]);
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        // Past the binary marker on the second line, everything is ASCII.
+        let header = 15;
+        let body = std::str::from_utf8(&pdf[header..]).unwrap();
+        assert!(body.contains("/Count 2"));
+        assert!(body.contains("(+line \\(x\\) 0) Tj"));
+        assert!(body.contains("(Changes ? kiri - 2 / 2)"));
+
+        // Every xref entry points at its object.
+        let xref = body.rfind("\nxref\n").unwrap() + 1;
+        let entries: Vec<usize> = body[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(entries.len(), 8);
+        for (index, offset) in entries.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+        let startxref: usize = body.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert_eq!(startxref, header + xref);
+    }
+}
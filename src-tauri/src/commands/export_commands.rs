@@ -0,0 +1,94 @@
+//! Tauri commands for export.rs. The frontend asks for the destination
+//! with a save dialog and passes it in.
+
+use std::path::Path;
+
+use super::error::{ErrorCode, KiriError};
+use super::export::{
+    diff_sections, render, ExportFormat, ExportResult, ExportSection, SectionKind,
+};
+use super::file_io::{read_file_contents, write_file_contents_atomic};
+use super::fs_path::{display_path, input_path, path_to_wire, resolve_within, strip_root};
+use super::latency::{run_blocking, run_blocking_coded};
+
+fn project_name(root: &Path) -> String {
+    root.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| display_path(root))
+}
+
+fn write_export(destination: &str, contents: Vec<u8>) -> Result<ExportResult, String> {
+    let path = input_path(destination)?;
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() || parent.is_dir() => {}
+        _ => return Err(format!("{} does not exist", display_path(&path))),
+    }
+    write_file_contents_atomic(&path, &contents)?;
+    Ok(ExportResult {
+        path: display_path(&path),
+        bytes: contents.len(),
+    })
+}
+
+/// Write the changes in the repository at `repo_path` to `destination` as
+/// a patch, HTML page or PDF. With `paths` (relative to the repository)
+/// only those files are included.
+#[tauri::command]
+pub async fn export_diff(
+    repo_path: String,
+    paths: Option<Vec<String>>,
+    format: ExportFormat,
+    destination: String,
+) -> Result<ExportResult, KiriError> {
+    run_blocking("export_diff", move || {
+        let root = input_path(&repo_path)?;
+        let sections = diff_sections(&root, &paths.unwrap_or_default())?;
+        if sections.is_empty() {
+            return Err("There are no changes to export".to_string());
+        }
+        let title = format!("Changes in {}", project_name(&root));
+        write_export(&destination, render(format, &title, &sections))
+    })
+    .await
+}
+
+/// Write the files at `paths`, absolute or relative to `root_path`, to
+/// `destination`. As a patch they are added as new files.
+#[tauri::command]
+pub async fn export_files(
+    root_path: String,
+    paths: Vec<String>,
+    format: ExportFormat,
+    destination: String,
+) -> Result<ExportResult, KiriError> {
+    run_blocking_coded("export_files", move || {
+        if paths.is_empty() {
+            return Err(KiriError::new(
+                ErrorCode::InvalidInput,
+                "Select files to export",
+            ));
+        }
+        let root = input_path(&root_path)?;
+        let canonical_root = root
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", display_path(&root), e))?;
+        let sections = paths
+            .iter()
+            .map(|path| {
+                let file = resolve_within(path, &root)?;
+                let relative = strip_root(&file, &canonical_root).unwrap_or_else(|| file.clone());
+                Ok(ExportSection {
+                    path: path_to_wire(&relative),
+                    kind: SectionKind::File,
+                    text: read_file_contents(&file)?,
+                })
+            })
+            .collect::<Result<Vec<_>, KiriError>>()?;
+        let title = format!("Files from {}", project_name(&root));
+        Ok(write_export(
+            &destination,
+            render(format, &title, &sections),
+        )?)
+    })
+    .await
+}
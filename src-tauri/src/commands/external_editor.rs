@@ -0,0 +1,398 @@
+//! Handing a file or worktree to an external editor.
+//!
+//! [`EditorKind`] covers VS Code and Cursor, the JetBrains IDEs and Zed.
+//! An editor counts as installed when its command-line launcher is on
+//! `PATH` or where its installer puts it, since apps started from the
+//! macOS Dock don't get the login shell's `PATH`. Nothing is run to find
+//! out: a JetBrains launcher starts the IDE whatever its arguments.
+//!
+//! [`editor_argv`] opens the worktree a file is in as the project and then
+//! goes to the file, so the editor's git and search see that worktree and
+//! not the main checkout. A worktree on a WSL share is opened in VS Code
+//! through its WSL remote rather than over the share.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// File name extensions a launcher may have on Windows.
+const WINDOWS_EXTENSIONS: [&str; 2] = ["exe", "cmd"];
+
+/// Hosts of the shares Windows exposes WSL distros on.
+const WSL_HOSTS: [&str; 2] = ["wsl.localhost", "wsl$"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditorKind {
+    Vscode,
+    Cursor,
+    Idea,
+    Webstorm,
+    Pycharm,
+    Goland,
+    Rustrover,
+    Zed,
+}
+
+/// Editors whose launchers take the same arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    VsCode,
+    JetBrains,
+    Zed,
+}
+
+impl EditorKind {
+    /// In the order an editor is picked when none is asked for.
+    pub const ALL: [EditorKind; 8] = [
+        EditorKind::Vscode,
+        EditorKind::Cursor,
+        EditorKind::Zed,
+        EditorKind::Idea,
+        EditorKind::Webstorm,
+        EditorKind::Pycharm,
+        EditorKind::Goland,
+        EditorKind::Rustrover,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EditorKind::Vscode => "VS Code",
+            EditorKind::Cursor => "Cursor",
+            EditorKind::Idea => "IntelliJ IDEA",
+            EditorKind::Webstorm => "WebStorm",
+            EditorKind::Pycharm => "PyCharm",
+            EditorKind::Goland => "GoLand",
+            EditorKind::Rustrover => "RustRover",
+            EditorKind::Zed => "Zed",
+        }
+    }
+
+    fn family(self) -> Family {
+        match self {
+            EditorKind::Vscode | EditorKind::Cursor => Family::VsCode,
+            EditorKind::Zed => Family::Zed,
+            _ => Family::JetBrains,
+        }
+    }
+
+    /// Names of the launcher, without an extension.
+    fn programs(self) -> &'static [&'static str] {
+        match self {
+            EditorKind::Vscode => &["code"],
+            EditorKind::Cursor => &["cursor"],
+            EditorKind::Idea => &["idea"],
+            EditorKind::Webstorm => &["webstorm"],
+            EditorKind::Pycharm => &["pycharm"],
+            EditorKind::Goland => &["goland"],
+            EditorKind::Rustrover => &["rustrover"],
+            // Distribution packages call it zeditor.
+            EditorKind::Zed => &["zed", "zeditor"],
+        }
+    }
+
+    /// Names of the macOS app bundle.
+    fn bundles(self) -> &'static [&'static str] {
+        match self {
+            EditorKind::Vscode => &["Visual Studio Code.app"],
+            EditorKind::Cursor => &["Cursor.app"],
+            EditorKind::Idea => &["IntelliJ IDEA.app", "IntelliJ IDEA CE.app"],
+            EditorKind::Webstorm => &["WebStorm.app"],
+            EditorKind::Pycharm => &["PyCharm.app", "PyCharm CE.app"],
+            EditorKind::Goland => &["GoLand.app"],
+            EditorKind::Rustrover => &["RustRover.app"],
+            EditorKind::Zed => &["Zed.app"],
+        }
+    }
+
+    /// Where the launcher is inside the macOS app bundle.
+    fn bundle_launcher(self) -> String {
+        match self.family() {
+            Family::VsCode => format!("Contents/Resources/app/bin/{}", self.programs()[0]),
+            Family::JetBrains => format!("Contents/MacOS/{}", self.programs()[0]),
+            Family::Zed => "Contents/MacOS/cli".to_string(),
+        }
+    }
+
+    /// Directories the installer puts the launcher in, besides `PATH`.
+    fn install_dirs(self, home: Option<&Path>) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        if cfg!(windows) {
+            let local = std::env::var_os("LOCALAPPDATA").map(PathBuf::from);
+            let programs = std::env::var_os("ProgramFiles").map(PathBuf::from);
+            let (local, programs) = (local.as_deref(), programs.as_deref());
+            let mut push = |base: Option<&Path>, rel: &str| {
+                dirs.extend(base.map(|base| base.join(rel)));
+            };
+            match self {
+                EditorKind::Vscode => {
+                    push(local, r"Programs\Microsoft VS Code\bin");
+                    push(programs, r"Microsoft VS Code\bin");
+                }
+                EditorKind::Cursor => push(local, r"Programs\cursor\resources\app\bin"),
+                EditorKind::Zed => push(local, r"Programs\Zed\bin"),
+                _ => push(local, r"JetBrains\Toolbox\scripts"),
+            }
+        } else if let Some(home) = home {
+            if cfg!(target_os = "macos") {
+                dirs.push(home.join("Library/Application Support/JetBrains/Toolbox/scripts"));
+            } else {
+                dirs.push(home.join(".local/share/JetBrains/Toolbox/scripts"));
+            }
+            // Where Zed's install script links its launcher.
+            dirs.push(home.join(".local/bin"));
+        }
+        dirs
+    }
+}
+
+/// Files that would be the launcher of `kind`, in the order they are
+/// tried: on `path_var` first, then where installers put it.
+pub fn launcher_candidates(
+    kind: EditorKind,
+    path_var: &OsStr,
+    home: Option<&Path>,
+) -> Vec<PathBuf> {
+    let dirs = std::env::split_paths(path_var).chain(kind.install_dirs(home));
+    let mut candidates: Vec<PathBuf> = dirs
+        .flat_map(|dir| {
+            kind.programs().iter().flat_map(move |program| {
+                if cfg!(windows) {
+                    WINDOWS_EXTENSIONS
+                        .iter()
+                        .map(|ext| dir.join(format!("{}.{}", program, ext)))
+                        .collect()
+                } else {
+                    vec![dir.join(program)]
+                }
+            })
+        })
+        .collect();
+    if cfg!(target_os = "macos") {
+        let apps = std::iter::once(PathBuf::from("/Applications"))
+            .chain(home.map(|home| home.join("Applications")));
+        for apps in apps {
+            for bundle in kind.bundles() {
+                candidates.push(apps.join(bundle).join(kind.bundle_launcher()));
+            }
+        }
+    }
+    candidates
+}
+
+/// The launcher of `kind` on this machine, if it is installed.
+pub fn find_launcher(kind: EditorKind) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    launcher_candidates(kind, &path_var, dirs::home_dir().as_deref())
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+/// The distro and Linux path of a path on a WSL share, e.g.
+/// `\\wsl.localhost\Ubuntu\home\me` is `/home/me` in `Ubuntu`.
+pub fn wsl_share(path: &str) -> Option<(String, String)> {
+    let rest = path
+        .strip_prefix(r"\\?\UNC\")
+        .or_else(|| path.strip_prefix(r"\\"))?;
+    let mut parts = rest.split(['\\', '/']).filter(|part| !part.is_empty());
+    let host = parts.next()?;
+    if !WSL_HOSTS.iter().any(|wsl| host.eq_ignore_ascii_case(wsl)) {
+        return None;
+    }
+    let distro = parts.next()?.to_string();
+    let linux: String = parts.map(|part| format!("/{}", part)).collect();
+    if linux.is_empty() {
+        return Some((distro, "/".to_string()));
+    }
+    Some((distro, linux))
+}
+
+/// What to open in the editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorTarget {
+    /// Opened as the project: the worktree of `file`, or a folder.
+    pub project: PathBuf,
+    pub file: Option<PathBuf>,
+    /// 1-based, as editors count them.
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// `path:line:column`, as VS Code's `--goto` and Zed take it.
+fn with_position(path: &str, line: Option<u32>, column: Option<u32>) -> String {
+    match (line, column) {
+        (Some(line), Some(column)) => format!("{}:{}:{}", path, line, column),
+        (Some(line), None) => format!("{}:{}", path, line),
+        _ => path.to_string(),
+    }
+}
+
+/// The command line that opens `target` with the launcher of `kind`. All
+/// paths must be absolute, so none can be taken for an option.
+pub fn editor_argv(kind: EditorKind, launcher: &Path, target: &EditorTarget) -> Vec<OsString> {
+    let mut argv = vec![launcher.as_os_str().to_os_string()];
+    let project = target.project.to_string_lossy();
+    let file = target.file.as_ref().map(|file| file.to_string_lossy());
+    match kind.family() {
+        Family::VsCode => {
+            let remote = wsl_share(&project);
+            // On the share, the distro's own paths are used with the remote.
+            let local = |path: &str| match &remote {
+                Some(_) => wsl_share(path).map_or(path.to_string(), |(_, linux)| linux),
+                None => path.to_string(),
+            };
+            if let Some((distro, _)) = &remote {
+                argv.push("--remote".into());
+                argv.push(format!("wsl+{}", distro).into());
+            }
+            // A project already open is focused rather than opened twice.
+            argv.push("--new-window".into());
+            argv.push(local(&project).into());
+            if let Some(file) = file {
+                if target.line.is_some() {
+                    argv.push("--goto".into());
+                }
+                argv.push(with_position(&local(&file), target.line, target.column).into());
+            }
+        }
+        Family::JetBrains => {
+            argv.push(target.project.clone().into_os_string());
+            if let Some(file) = &target.file {
+                if let Some(line) = target.line {
+                    argv.push("--line".into());
+                    argv.push(line.to_string().into());
+                    if let Some(column) = target.column {
+                        argv.push("--column".into());
+                        argv.push(column.to_string().into());
+                    }
+                }
+                argv.push(file.clone().into_os_string());
+            }
+        }
+        Family::Zed => {
+            argv.push(target.project.clone().into_os_string());
+            if let Some(file) = file {
+                argv.push(with_position(&file, target.line, target.column).into());
+            }
+        }
+    }
+    argv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn argv(kind: EditorKind, target: &EditorTarget) -> Vec<String> {
+        editor_argv(kind, Path::new("/bin/editor"), target)
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_editor_argv_opens_worktree_then_file() {
+        let target = EditorTarget {
+            project: PathBuf::from("/src/app-feature"),
+            file: Some(PathBuf::from("/src/app-feature/src/main.ts")),
+            line: Some(12),
+            column: Some(4),
+        };
+        assert_eq!(
+            argv(EditorKind::Cursor, &target),
+            vec![
+                "/bin/editor",
+                "--new-window",
+                "/src/app-feature",
+                "--goto",
+                "/src/app-feature/src/main.ts:12:4",
+            ]
+        );
+        assert_eq!(
+            argv(EditorKind::Rustrover, &target),
+            vec![
+                "/bin/editor",
+                "/src/app-feature",
+                "--line",
+                "12",
+                "--column",
+                "4",
+                "/src/app-feature/src/main.ts",
+            ]
+        );
+        let folder = EditorTarget {
+            file: None,
+            ..target.clone()
+        };
+        assert_eq!(
+            argv(EditorKind::Zed, &folder),
+            vec!["/bin/editor", "/src/app-feature"]
+        );
+        let no_line = EditorTarget {
+            line: None,
+            ..target
+        };
+        assert_eq!(
+            argv(EditorKind::Zed, &no_line)[2],
+            "/src/app-feature/src/main.ts"
+        );
+        assert_eq!(argv(EditorKind::Vscode, &no_line).len(), 4);
+    }
+
+    #[test]
+    fn test_wsl_share_opens_through_remote() {
+        assert_eq!(
+            wsl_share(r"\\wsl.localhost\Ubuntu-22.04\home\me\app"),
+            Some(("Ubuntu-22.04".to_string(), "/home/me/app".to_string()))
+        );
+        assert_eq!(
+            wsl_share(r"\\?\UNC\wsl$\Debian"),
+            Some(("Debian".to_string(), "/".to_string()))
+        );
+        assert_eq!(wsl_share(r"\\nas\share\app"), None);
+        assert_eq!(wsl_share("/home/me/app"), None);
+
+        let target = EditorTarget {
+            project: PathBuf::from(r"\\wsl.localhost\Ubuntu\home\me\app"),
+            file: Some(PathBuf::from(
+                r"\\wsl.localhost\Ubuntu\home\me\app\README.md",
+            )),
+            line: Some(3),
+            column: None,
+        };
+        assert_eq!(
+            argv(EditorKind::Vscode, &target),
+            vec![
+                "/bin/editor",
+                "--remote",
+                "wsl+Ubuntu",
+                "--new-window",
+                "/home/me/app",
+                "--goto",
+                "/home/me/app/README.md:3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_launcher_found_on_path() {
+        let dir = TempDir::new().unwrap();
+        let bin = dir.path().join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        let path_var = std::env::join_paths([dir.path().join("empty"), bin.clone()]).unwrap();
+        let candidates = launcher_candidates(EditorKind::Zed, &path_var, None);
+        assert!(candidates.iter().all(|candidate| !candidate.is_file()));
+
+        let name = if cfg!(windows) {
+            "zeditor.exe"
+        } else {
+            "zeditor"
+        };
+        std::fs::write(bin.join(name), "").unwrap();
+        let found = launcher_candidates(EditorKind::Zed, &path_var, None)
+            .into_iter()
+            .find(|candidate| candidate.is_file());
+        assert_eq!(found, Some(bin.join(name)));
+    }
+}
@@ -0,0 +1,124 @@
+//! Tauri commands for the external editors of external_editor.rs.
+//!
+//! The editor is started without a shell, as a launch on the user's
+//! behalf: it inherits kiri's environment, including what the WSL
+//! launchers of Windows editors need to find their way back into the
+//! distro when kiri itself runs in WSL. See process.rs.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use super::error::{user_error, ErrorCode, KiriError};
+use super::external_editor::{editor_argv, find_launcher, EditorKind, EditorTarget};
+use super::fs_path::{input_path, path_to_wire};
+use super::git_worktree::current_worktree_root;
+use super::latency::{run_blocking, run_blocking_coded};
+use super::process::{launch_command, path_arg};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalEditor {
+    pub kind: EditorKind,
+    pub label: &'static str,
+    pub installed: bool,
+    pub launcher: Option<String>,
+}
+
+/// The editor to use and its launcher: `editor` when given, otherwise the
+/// first installed one.
+fn pick_editor(editor: Option<EditorKind>) -> Result<(EditorKind, PathBuf), String> {
+    match editor {
+        Some(kind) => find_launcher(kind)
+            .map(|launcher| (kind, launcher))
+            .ok_or_else(|| {
+                format!(
+                    "{} not found: its command-line launcher is not installed",
+                    kind.label()
+                )
+            }),
+        None => EditorKind::ALL
+            .into_iter()
+            .find_map(|kind| Some((kind, find_launcher(kind)?)))
+            .ok_or_else(|| "No supported editor found".to_string()),
+    }
+}
+
+fn open_in_external_editor_blocking(
+    path: String,
+    editor: Option<EditorKind>,
+    line: Option<u32>,
+    column: Option<u32>,
+) -> Result<EditorKind, KiriError> {
+    let path = input_path(&path)?;
+    path_arg(&path)?;
+    if !path.exists() {
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.path_not_found",
+            path.display(),
+        ));
+    }
+    let target = if path.is_dir() {
+        EditorTarget {
+            project: path,
+            file: None,
+            line: None,
+            column: None,
+        }
+    } else {
+        let parent = path.parent().ok_or_else(|| {
+            user_error(ErrorCode::InvalidInput, "error.no_parent", path.display())
+        })?;
+        EditorTarget {
+            project: current_worktree_root(parent).unwrap_or_else(|| parent.to_path_buf()),
+            file: Some(path.clone()),
+            line: line.filter(|line| *line > 0),
+            column: column.filter(|column| *column > 0),
+        }
+    };
+    let (kind, launcher) = pick_editor(editor)?;
+
+    launch_command(&editor_argv(kind, &launcher, &target), None)?
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", kind.label(), e))?;
+    Ok(kind)
+}
+
+/// The editors kiri can open things in, and whether each is installed.
+#[tauri::command]
+pub async fn list_external_editors() -> Result<Vec<ExternalEditor>, KiriError> {
+    run_blocking("list_external_editors", || {
+        Ok(EditorKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let launcher = find_launcher(kind);
+                ExternalEditor {
+                    kind,
+                    label: kind.label(),
+                    installed: launcher.is_some(),
+                    launcher: launcher.map(|path| path_to_wire(&path)),
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Open `path` in `editor`, or in the first installed editor. A folder is
+/// opened as the project; a file is opened within the worktree it belongs
+/// to, at `line` and `column` when given. Returns the editor used.
+#[tauri::command]
+pub async fn open_in_external_editor(
+    path: String,
+    editor: Option<EditorKind>,
+    line: Option<u32>,
+    column: Option<u32>,
+) -> Result<EditorKind, KiriError> {
+    run_blocking_coded("open_in_external_editor", move || {
+        open_in_external_editor_blocking(path, editor, line, column)
+    })
+    .await
+}
@@ -1,45 +1,220 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
 
 use base64::Engine;
 
-use super::error::{user_io_error, user_path_error};
-use super::file_io::read_file_contents;
+use rayon::prelude::*;
+use serde::Serialize;
 
+use super::editorconfig::resolve_editorconfig;
+use super::error::{user_error, user_io_failure, ErrorCode, KiriError};
+use super::file_io::{read_file_contents, write_file_contents_atomic};
+use super::fs_path::{input_path, normalize_input_path};
+use super::large_file::{
+    beyond_read_only, check_lines, check_size, large_file_limits, read_chunk, FileChunk,
+};
+use super::latency::run_blocking;
+use super::text_format::{
+    apply_write_format, detect_text_format, TextFormat, WriteFormat, UTF8_BOM,
+};
+
+/// Most files [`read_files`] reads in one call.
+pub const MAX_BATCH_READ_FILES: usize = 256;
+
+/// One file of [`read_files`]: `{ "content": ... }`, or `{ "error": ... }`
+/// with what [`read_file`] would have failed with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileReadResult {
+    Content(String),
+    Error(KiriError),
+}
+
+/// File content together with its detected conventions.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileWithFormat {
+    /// Content with any UTF-8 BOM stripped; line endings are left as-is.
+    pub content: String,
+    pub format: TextFormat,
+}
+
+/// Read a text file for the editor. Files over the large-file limits are
+/// refused with code `file_too_large` unless `force` is set, which opens
+/// one read-only; see large_file.rs.
 #[tauri::command]
-pub fn read_file(path: String) -> Result<String, String> {
-    let path = Path::new(&path);
+pub fn read_file(path: String, force: Option<bool>) -> Result<String, KiriError> {
+    let path = input_path(&path)?;
+    let path = path.as_path();
 
     if !path.exists() {
-        return Err(user_path_error("File does not exist", path));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.file_missing",
+            path.display(),
+        ));
     }
 
     if !path.is_file() {
-        return Err(user_path_error("Path is not a file", path));
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.not_a_file",
+            path.display(),
+        ));
+    }
+
+    let force = force.unwrap_or(false);
+    let limits = large_file_limits();
+    let size = fs::metadata(path)
+        .map_err(|e| user_io_failure("error.read_failed", e))?
+        .len();
+    if let Some(large) = check_size(size, limits) {
+        if !force || beyond_read_only(size, limits) {
+            return Err(large.into_error());
+        }
     }
 
-    read_file_contents(path)
+    let content = read_file_contents(path)?;
+    match check_lines(&content, limits) {
+        Some(large) if !force => Err(large.into_error()),
+        _ => Ok(content),
+    }
+}
+
+/// Read several text files, like the configs and manifests a project
+/// reads when it opens, in parallel instead of one `read_file` call each.
+/// A file that can't be read, or is over the large-file limits, gets its
+/// error in its place without failing the others. Keyed by the paths as
+/// given.
+#[tauri::command]
+pub async fn read_files(paths: Vec<String>) -> Result<HashMap<String, FileReadResult>, KiriError> {
+    if paths.len() > MAX_BATCH_READ_FILES {
+        return Err(KiriError::new(
+            ErrorCode::InvalidInput,
+            format!("At most {} files can be read at once", MAX_BATCH_READ_FILES),
+        ));
+    }
+    run_blocking("read_files", move || {
+        Ok(paths
+            .into_par_iter()
+            .map(|path| {
+                let result = match read_file(path.clone(), None) {
+                    Ok(content) => FileReadResult::Content(content),
+                    Err(error) => FileReadResult::Error(error),
+                };
+                (path, result)
+            })
+            .collect())
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn read_file_as_base64(path: String) -> Result<String, String> {
-    let path = Path::new(&path);
+pub fn read_file_as_base64(path: String) -> Result<String, KiriError> {
+    let path = input_path(&path)?;
+    let path = path.as_path();
 
     if !path.exists() {
-        return Err(user_path_error("File does not exist", path));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.file_missing",
+            path.display(),
+        ));
     }
 
     if !path.is_file() {
-        return Err(user_path_error("Path is not a file", path));
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.not_a_file",
+            path.display(),
+        ));
     }
 
-    let bytes = fs::read(path).map_err(|e| user_io_error("Failed to read file", e))?;
+    let bytes = fs::read(path).map_err(|e| user_io_failure("error.read_failed", e))?;
     Ok(base64::engine::general_purpose::STANDARD.encode(&bytes))
 }
 
+/// Up to `length` bytes of `path` from `offset`, for paging through or
+/// showing as hex a file too large to open.
+#[tauri::command]
+pub fn read_file_chunk(path: String, offset: u64, length: u64) -> Result<FileChunk, KiriError> {
+    let path = input_path(&path)?;
+    if !path.is_file() {
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.not_a_file",
+            path.display(),
+        ));
+    }
+    read_chunk(&path, offset, length).map_err(|e| user_io_failure("error.read_failed", e))
+}
+
+/// Read a text file and report its line ending, BOM, indentation and
+/// `.editorconfig` settings so the editor can configure itself. `force`
+/// is as for [`read_file`].
+#[tauri::command]
+pub fn read_file_with_format(
+    path: String,
+    force: Option<bool>,
+) -> Result<FileWithFormat, KiriError> {
+    let content = read_file(path.clone(), force)?;
+    let format = detect_text_format(&normalize_input_path(&path), &content);
+    let content = match content.strip_prefix(UTF8_BOM) {
+        Some(stripped) => stripped.to_string(),
+        None => content,
+    };
+    Ok(FileWithFormat { content, format })
+}
+
+/// Write editor content to `path`, preserving the existing file's line
+/// endings and BOM unless `format` overrides them. New files follow
+/// `.editorconfig`. The write goes through a temp file and rename.
+#[tauri::command]
+pub fn write_file(
+    path: String,
+    content: String,
+    format: Option<WriteFormat>,
+) -> Result<(), KiriError> {
+    let path = input_path(&path)?;
+    let path = path.as_path();
+
+    if path.is_dir() {
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.not_a_file",
+            path.display(),
+        ));
+    }
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() || parent.is_dir() => {}
+        _ => {
+            return Err(user_error(
+                ErrorCode::NotFound,
+                "error.parent_directory_missing",
+                path.display(),
+            ))
+        }
+    }
+
+    let editorconfig = resolve_editorconfig(path);
+    // Unreadable or non-UTF-8 existing content just means nothing to preserve.
+    let existing = fs::read_to_string(path)
+        .ok()
+        .map(|current| detect_text_format(path, &current));
+    let format = format
+        .unwrap_or_default()
+        .resolve(existing.as_ref(), &editorconfig);
+
+    let output = apply_write_format(&content, &format);
+    write_file_contents_atomic(path, output.as_bytes())
+        .map_err(|e| user_error(ErrorCode::Internal, "error.write_failed", e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::error::{ErrorCode, ErrorDetails};
+    use crate::commands::large_file::DEFAULT_LARGE_FILE_LINE_LENGTH;
+    use crate::commands::text_format::{IndentStyle, LineEnding};
     use std::fs;
     use tempfile::tempdir;
 
@@ -49,24 +224,24 @@ mod tests {
         let file_path = dir.path().join("test.txt");
         fs::write(&file_path, "Hello, World!").unwrap();
 
-        let result = read_file(file_path.to_string_lossy().to_string());
+        let result = read_file(file_path.to_string_lossy().to_string(), None);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "Hello, World!");
     }
 
     #[test]
     fn test_read_nonexistent_file() {
-        let result = read_file("/nonexistent/path/file.txt".to_string());
+        let result = read_file("/nonexistent/path/file.txt".to_string(), None);
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 
     #[test]
     fn test_read_directory_instead_of_file() {
         let dir = tempdir().unwrap();
-        let result = read_file(dir.path().to_string_lossy().to_string());
+        let result = read_file(dir.path().to_string_lossy().to_string(), None);
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not a file"));
+        assert!(result.unwrap_err().message.contains("not a file"));
     }
 
     #[test]
@@ -75,11 +250,61 @@ mod tests {
         let file_path = dir.path().join("utf8.txt");
         fs::write(&file_path, "こんにちは世界🌍").unwrap();
 
-        let result = read_file(file_path.to_string_lossy().to_string());
+        let result = read_file(file_path.to_string_lossy().to_string(), None);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "こんにちは世界🌍");
     }
 
+    #[test]
+    fn test_read_file_refuses_long_lines_unless_forced() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("bundle.min.js");
+        let line = "x".repeat(DEFAULT_LARGE_FILE_LINE_LENGTH as usize + 1);
+        fs::write(&file_path, &line).unwrap();
+        let path = file_path.to_string_lossy().to_string();
+
+        let err = read_file(path.clone(), None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::FileTooLarge);
+        let Some(ErrorDetails::LargeFile(large)) = err.details.as_deref() else {
+            panic!("expected large file details");
+        };
+        assert_eq!(large.longest_line, Some(line.len()));
+        assert_eq!(read_file(path, Some(true)).unwrap(), line);
+    }
+
+    #[tokio::test]
+    async fn test_read_files_reports_errors_per_file() {
+        let dir = tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        fs::write(dir.path().join(".kiri.toml"), "[worktree]\n").unwrap();
+
+        let read = read_files(vec![
+            path("package.json"),
+            path(".kiri.toml"),
+            path("missing.json"),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(read.len(), 3);
+        assert_eq!(
+            read[&path("package.json")],
+            FileReadResult::Content("{}".to_string())
+        );
+        assert_eq!(
+            read[&path(".kiri.toml")],
+            FileReadResult::Content("[worktree]\n".to_string())
+        );
+        let FileReadResult::Error(error) = &read[&path("missing.json")] else {
+            panic!("expected an error for the missing file");
+        };
+        assert_eq!(error.code, ErrorCode::NotFound);
+
+        let too_many = vec![path("package.json"); MAX_BATCH_READ_FILES + 1];
+        let err = read_files(too_many).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+    }
+
     #[test]
     fn test_read_file_as_base64() {
         let dir = tempdir().unwrap();
@@ -96,7 +321,7 @@ mod tests {
     fn test_read_file_as_base64_nonexistent() {
         let result = read_file_as_base64("/nonexistent/path/file.png".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 
     #[test]
@@ -104,6 +329,89 @@ mod tests {
         let dir = tempdir().unwrap();
         let result = read_file_as_base64(dir.path().to_string_lossy().to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not a file"));
+        assert!(result.unwrap_err().message.contains("not a file"));
+    }
+
+    #[test]
+    fn test_read_file_with_format_strips_bom() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("bom.txt");
+        fs::write(&file_path, "\u{FEFF}a\r\n\tb\r\n").unwrap();
+
+        let result = read_file_with_format(file_path.to_string_lossy().to_string(), None).unwrap();
+        assert_eq!(result.content, "a\r\n\tb\r\n");
+        assert!(result.format.has_bom);
+        assert_eq!(result.format.line_ending, Some(LineEnding::Crlf));
+        assert_eq!(result.format.indent_style, Some(IndentStyle::Tab));
+    }
+
+    #[test]
+    fn test_write_file_preserves_crlf_and_bom() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("win.txt");
+        fs::write(&file_path, "\u{FEFF}old\r\n").unwrap();
+
+        write_file(
+            file_path.to_string_lossy().to_string(),
+            "new\nline\n".to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "\u{FEFF}new\r\nline\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_file_new_file_follows_editorconfig() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*.bat]\nend_of_line = crlf\ninsert_final_newline = true\n",
+        )
+        .unwrap();
+        let file_path = dir.path().join("run.bat");
+
+        write_file(
+            file_path.to_string_lossy().to_string(),
+            "echo hi".to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "echo hi\r\n");
+    }
+
+    #[test]
+    fn test_write_file_explicit_format_overrides() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("a.txt");
+        fs::write(&file_path, "old\r\n").unwrap();
+
+        let format = WriteFormat {
+            line_ending: Some(LineEnding::Lf),
+            ..Default::default()
+        };
+        write_file(
+            file_path.to_string_lossy().to_string(),
+            "x\r\ny\r\n".to_string(),
+            Some(format),
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "x\ny\n");
+        assert!(!dir.path().join("a.txt.kiri-tmp").exists());
+    }
+
+    #[test]
+    fn test_write_file_missing_parent() {
+        let result = write_file(
+            "/nonexistent/dir/file.txt".to_string(),
+            "x".to_string(),
+            None,
+        );
+        assert!(result
+            .unwrap_err()
+            .message
+            .contains("Parent directory does not exist"));
     }
 }
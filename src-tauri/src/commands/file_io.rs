@@ -2,9 +2,112 @@
 // system-level failures to test. Covered via E2E tests.
 
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Read file contents with error handling
 pub fn read_file_contents(path: &Path) -> Result<String, String> {
     fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))
 }
+
+fn tmp_path(path: &Path) -> Result<PathBuf, String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| "Invalid file path".to_string())?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".kiri-tmp");
+    Ok(path.with_file_name(tmp_name))
+}
+
+/// Flush the directory entry of `path`, so a rename into it survives a
+/// crash. Directories can't be opened for this on Windows, where the
+/// rename is durable once it returns.
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Write `contents` to a `.kiri-tmp` sibling, then rename it over `path`
+/// so a crash mid-write never leaves a truncated file behind. Both the
+/// contents and the rename are synced before it returns.
+pub fn write_file_contents_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let tmp_path = tmp_path(path)?;
+
+    let result = (|| {
+        let mut tmp_file = fs::File::create(&tmp_path)?;
+        tmp_file.write_all(contents)?;
+        tmp_file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+        fs::rename(&tmp_path, path)?;
+        sync_parent_dir(path)
+    })();
+
+    result.map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Failed to write file: {}", e)
+    })
+}
+
+/// What [`recover_atomic_write`] found next to a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteRecovery {
+    /// No interrupted write.
+    Clean,
+    /// A complete `.kiri-tmp` was left before its rename and now replaces
+    /// the file.
+    PromotedTemp,
+    /// An incomplete `.kiri-tmp` was removed; the file is as before the
+    /// interrupted write.
+    DiscardedTemp,
+    /// The file itself didn't pass `is_valid` and was moved to this path,
+    /// so the next write doesn't overwrite what may be recoverable by hand.
+    Quarantined(PathBuf),
+}
+
+/// Repair what a crash during [`write_file_contents_atomic`] left at
+/// `path`, deciding whether contents are whole with `is_valid`. Call it
+/// before reading a file that is only ever written that way.
+pub fn recover_atomic_write(
+    path: &Path,
+    is_valid: impl Fn(&[u8]) -> bool,
+) -> Result<WriteRecovery, String> {
+    let tmp_path = tmp_path(path)?;
+    let fail = |e: std::io::Error| format!("Failed to recover {}: {}", path.display(), e);
+    let mut recovery = WriteRecovery::Clean;
+    match fs::read(&tmp_path) {
+        // The temp file is only renamed after it is synced, so a whole one
+        // is newer than the file.
+        Ok(contents) if is_valid(&contents) => {
+            fs::rename(&tmp_path, path).map_err(fail)?;
+            sync_parent_dir(path).map_err(fail)?;
+            return Ok(WriteRecovery::PromotedTemp);
+        }
+        Ok(_) => {
+            fs::remove_file(&tmp_path).map_err(fail)?;
+            recovery = WriteRecovery::DiscardedTemp;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(fail(e)),
+    }
+    match fs::read(path) {
+        Ok(contents) if !is_valid(&contents) => {
+            let mut corrupt_name = path.as_os_str().to_os_string();
+            corrupt_name.push(".corrupt");
+            let corrupt_path = PathBuf::from(corrupt_name);
+            fs::rename(path, &corrupt_path).map_err(fail)?;
+            sync_parent_dir(path).map_err(fail)?;
+            Ok(WriteRecovery::Quarantined(corrupt_path))
+        }
+        Ok(_) => Ok(recovery),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(recovery),
+        Err(e) => Err(fail(e)),
+    }
+}
@@ -0,0 +1,228 @@
+//! Reading files line by line for search and diffs.
+//!
+//! Files are streamed through a reused buffer, so a search over a
+//! multi-hundred-MB log allocates only for the lines that match, and memory
+//! stays bounded whatever the size of the file: lines longer than
+//! [`MAX_LINE_BYTES`] are cut there.
+//!
+//! Files are never memory-mapped. The worktree holds logs and build output
+//! that other processes grow, rotate and truncate; reading a mapped page of
+//! a file truncated under it raises SIGBUS and takes the whole app down.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Longest line kept whole; the rest of a longer one is skipped.
+pub const MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Size of the chunks [`count_text_lines`] reads.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+/// The lines of a file as byte slices, split like `str::lines`: on `\n` or
+/// `\r\n`, with no empty line after a final newline.
+pub struct LineReader<R> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+}
+
+impl LineReader<File> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> LineReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            line: Vec::new(),
+        }
+    }
+
+    /// The next line, or `None` at the end of the file. A line cut at
+    /// [`MAX_LINE_BYTES`] ends at the last whole UTF-8 character before it.
+    pub fn next_line(&mut self) -> io::Result<Option<&[u8]>> {
+        self.line.clear();
+        let mut read_any = false;
+        let mut ended = false;
+        let mut cut = false;
+        while !ended {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            read_any = true;
+            let used = match available.iter().position(|&b| b == b'\n') {
+                Some(end) => {
+                    ended = true;
+                    end + 1
+                }
+                None => available.len(),
+            };
+            let room = MAX_LINE_BYTES - self.line.len();
+            cut |= used > room;
+            self.line.extend_from_slice(&available[..used.min(room)]);
+            self.reader.consume(used);
+        }
+        if !read_any {
+            return Ok(None);
+        }
+
+        if cut {
+            // Don't leave half a character at the cut.
+            if let Err(e) = std::str::from_utf8(&self.line) {
+                if e.error_len().is_none() {
+                    self.line.truncate(e.valid_up_to());
+                }
+            }
+        } else if self.line.ends_with(b"\n") {
+            self.line.pop();
+            if self.line.ends_with(b"\r") {
+                self.line.pop();
+            }
+        }
+        Ok(Some(&self.line))
+    }
+}
+
+/// Number of lines `fs::read_to_string(path)?.lines()` would give, read in
+/// chunks. Fails the same way when the file isn't valid UTF-8.
+pub fn count_text_lines(path: &Path) -> io::Result<usize> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; CHUNK_BYTES];
+    // Bytes of a character split across two chunks, kept at the front.
+    let mut carry = 0;
+    let mut newlines = 0;
+    let mut last = None;
+    loop {
+        let read = file.read(&mut buf[carry..])?;
+        if read == 0 {
+            break;
+        }
+        newlines += buf[carry..carry + read]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        let chunk = &buf[..carry + read];
+        last = chunk.last().copied();
+        let valid = match std::str::from_utf8(chunk) {
+            Ok(_) => chunk.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(invalid_utf8()),
+        };
+        let len = chunk.len();
+        buf.copy_within(valid..len, 0);
+        carry = len - valid;
+    }
+    if carry > 0 {
+        return Err(invalid_utf8());
+    }
+    Ok(newlines + usize::from(last.is_some_and(|b| b != b'\n')))
+}
+
+/// Prefix every line of `text` with `prefix`, joined by `\n`. Builds the
+/// result in one allocation instead of a `String` per line.
+pub fn prefix_lines(text: &str, prefix: &str) -> String {
+    let line_count = text.lines().count();
+    let mut out = String::with_capacity(text.len() + line_count * prefix.len());
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(prefix);
+        out.push_str(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn lines(text: &[u8]) -> Vec<String> {
+        let mut reader = LineReader::new(text);
+        let mut lines = Vec::new();
+        while let Some(line) = reader.next_line().unwrap() {
+            lines.push(String::from_utf8(line.to_vec()).unwrap());
+        }
+        lines
+    }
+
+    #[test]
+    fn test_lines_match_str_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        for text in [
+            "",
+            "\n",
+            "one",
+            "one\ntwo\n",
+            "a\r\nb\r\n\nc",
+            "x\n\n",
+            "end\r",
+            "ünïcödé\n",
+        ] {
+            let expected: Vec<&str> = text.lines().collect();
+            assert_eq!(lines(text.as_bytes()), expected, "{:?}", text);
+            fs::write(&path, text).unwrap();
+            assert_eq!(
+                count_text_lines(&path).unwrap(),
+                expected.len(),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_long_lines_are_cut_at_a_character() {
+        let mut text = "x".repeat(MAX_LINE_BYTES - 1).into_bytes();
+        text.extend_from_slice("é and more\nnext\n".as_bytes());
+        let lines = lines(&text);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), MAX_LINE_BYTES - 1);
+        assert_eq!(lines[1], "next");
+    }
+
+    #[test]
+    fn test_count_text_lines_across_chunks_and_invalid_utf8() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        // A two-byte character straddles the first chunk boundary.
+        let mut text = "a".repeat(CHUNK_BYTES - 1);
+        text.push_str("é\nb\n");
+        fs::write(&path, &text).unwrap();
+        assert_eq!(count_text_lines(&path).unwrap(), 2);
+
+        fs::write(&path, [b'o', b'k', 0xff, b'\n']).unwrap();
+        assert_eq!(
+            count_text_lines(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        fs::write(&path, [b'o', b'k', 0xc3]).unwrap();
+        assert!(count_text_lines(&path).is_err());
+    }
+
+    #[test]
+    fn test_prefix_lines() {
+        assert_eq!(prefix_lines("a\nb\n", "+ "), "+ a\n+ b");
+        assert_eq!(prefix_lines("", "+ "), "");
+        let text = "one\r\ntwo";
+        let expected = text
+            .lines()
+            .map(|l| format!("+ {}", l))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(prefix_lines(text, "+ "), expected);
+    }
+}
@@ -0,0 +1,427 @@
+//! Formatting files with the formatter their project uses.
+//!
+//! The formatter is picked by extension: prettier for web files, rustfmt,
+//! gofmt and black. Each runs in the file's directory with the worktree's
+//! runtimes activated (see toolchain.rs) and the file's text on stdin, so
+//! it finds the project's own config the way it would on the command line.
+//! The config file found is reported back so the editor can show which
+//! settings applied. The formatted text replaces the file atomically, and
+//! only if it changed. Formatters are project code (prettier runs from
+//! node_modules), so files in untrusted folders are not formatted.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::file_io::write_file_contents_atomic;
+use super::fs_path::path_to_wire;
+use super::toolchain;
+use super::trust::{require_trusted_in, TrustStoreState};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Formatter {
+    Prettier,
+    Rustfmt,
+    Gofmt,
+    Black,
+}
+
+const PRETTIER_EXTENSIONS: &[&str] = &[
+    "js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts", "json", "jsonc", "json5", "css", "scss",
+    "less", "html", "vue", "svelte", "md", "mdx", "yaml", "yml", "graphql", "gql",
+];
+
+const PRETTIER_CONFIGS: &[&str] = &[
+    ".prettierrc",
+    ".prettierrc.json",
+    ".prettierrc.yaml",
+    ".prettierrc.yml",
+    ".prettierrc.json5",
+    ".prettierrc.js",
+    ".prettierrc.cjs",
+    ".prettierrc.mjs",
+    ".prettierrc.toml",
+    "prettier.config.js",
+    "prettier.config.cjs",
+    "prettier.config.mjs",
+];
+
+impl Formatter {
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Self::Rustfmt),
+            "go" => Some(Self::Gofmt),
+            "py" | "pyi" => Some(Self::Black),
+            ext if PRETTIER_EXTENSIONS.contains(&ext) => Some(Self::Prettier),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Prettier => "prettier",
+            Self::Rustfmt => "rustfmt",
+            Self::Gofmt => "gofmt",
+            Self::Black => "black",
+        }
+    }
+
+    pub fn install_hint(self) -> &'static str {
+        match self {
+            Self::Prettier => "npm install --save-dev prettier",
+            Self::Rustfmt => "rustup component add rustfmt",
+            Self::Gofmt => "gofmt comes with Go: https://go.dev/dl/",
+            Self::Black => "pipx install black",
+        }
+    }
+
+    fn supports_range(self) -> bool {
+        matches!(self, Self::Prettier | Self::Black)
+    }
+}
+
+/// Lines to format, 1-based and inclusive.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FormatError {
+    /// No formatter handles this kind of file.
+    Unsupported { path: String },
+    NotInstalled {
+        formatter: Formatter,
+        install_hint: String,
+    },
+    /// The formatter can only format whole files.
+    RangeNotSupported { formatter: Formatter },
+    /// The formatter ran and failed, usually on a syntax error.
+    Failed { message: String },
+    /// The file's folder is not trusted to run its formatter.
+    NotTrusted { message: String },
+}
+
+impl From<String> for FormatError {
+    fn from(message: String) -> Self {
+        Self::Failed { message }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FormatResult {
+    pub formatter: Formatter,
+    /// The project config the formatter picked up, if any.
+    pub config_file: Option<String>,
+    pub changed: bool,
+    /// The file's text after formatting.
+    pub content: String,
+}
+
+/// Files named `names` from `dir` up to the checkout root, nearest first.
+fn find_upwards(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        for name in names {
+            let file = dir.join(name);
+            if file.is_file() {
+                return Some(file);
+            }
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Whether a `package.json` or `pyproject.toml` carries the formatter's
+/// config in a `prettier` key or a `[tool.black]` table.
+fn embeds_config(file: &Path, marker: &str) -> bool {
+    std::fs::read_to_string(file).is_ok_and(|text| text.contains(marker))
+}
+
+/// The config file `formatter` will use for a file in `dir`.
+pub fn find_config(formatter: Formatter, dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        let found = match formatter {
+            Formatter::Prettier => PRETTIER_CONFIGS
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|file| file.is_file())
+                .or_else(|| {
+                    let package = dir.join("package.json");
+                    embeds_config(&package, "\"prettier\"").then_some(package)
+                }),
+            Formatter::Rustfmt => ["rustfmt.toml", ".rustfmt.toml"]
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|file| file.is_file()),
+            Formatter::Black => {
+                let pyproject = dir.join("pyproject.toml");
+                embeds_config(&pyproject, "[tool.black]").then_some(pyproject)
+            }
+            Formatter::Gofmt => None,
+        };
+        if found.is_some() {
+            return found;
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// The `edition` of the crate `dir` is in, for rustfmt reading stdin,
+/// which otherwise parses as Rust 2015. Crates that inherit the edition
+/// from their workspace are looked up in the workspace's `Cargo.toml`.
+pub fn rust_edition(dir: &Path) -> Option<String> {
+    for dir in dir.ancestors() {
+        let edition = std::fs::read_to_string(dir.join("Cargo.toml"))
+            .ok()
+            .and_then(|text| {
+                text.lines().find_map(|line| {
+                    let value = line.trim().strip_prefix("edition")?.trim_start();
+                    let value = value.strip_prefix('=')?.trim();
+                    Some(value.trim_matches('"').to_string())
+                })
+            });
+        if edition.is_some() {
+            return edition;
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// UTF-16 offsets of the start of line `range.start` and the end of line
+/// `range.end` in `text`, as prettier's `--range-start`/`--range-end` take.
+fn utf16_line_span(text: &str, range: LineRange) -> (usize, usize) {
+    let mut start = None;
+    let mut offset = 0;
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        if i + 1 == range.start {
+            start = Some(offset);
+        }
+        offset += line.encode_utf16().count();
+        if i + 1 == range.end {
+            return (start.unwrap_or(offset), offset);
+        }
+    }
+    (start.unwrap_or(offset), offset)
+}
+
+fn command(formatter: Formatter, path: &Path, text: &str, range: Option<LineRange>) -> Command {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut command = match formatter {
+        Formatter::Prettier => {
+            // Prefer the project's own prettier over a global one.
+            let local = find_upwards(dir, &["node_modules/.bin/prettier"]);
+            let mut command = Command::new(local.as_deref().unwrap_or(Path::new("prettier")));
+            command.arg("--stdin-filepath").arg(path);
+            if let Some(range) = range {
+                let (start, end) = utf16_line_span(text, range);
+                command.arg(format!("--range-start={}", start));
+                command.arg(format!("--range-end={}", end));
+            }
+            command
+        }
+        Formatter::Rustfmt => {
+            let mut command = Command::new("rustfmt");
+            command.args(["--emit", "stdout"]);
+            if let Some(edition) = rust_edition(dir) {
+                command.args(["--edition", &edition]);
+            }
+            command
+        }
+        Formatter::Gofmt => Command::new("gofmt"),
+        Formatter::Black => {
+            let mut command = Command::new("black");
+            command.arg("--quiet").arg("--stdin-filename").arg(path);
+            if let Some(range) = range {
+                command.arg(format!("--line-ranges={}-{}", range.start, range.end));
+            }
+            command.arg("-");
+            command
+        }
+    };
+    command
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for (key, value) in toolchain::resolve(dir).env {
+        match value {
+            Some(value) => command.env(key, value),
+            None => command.env_remove(key),
+        };
+    }
+    command
+}
+
+/// Run `formatter` over `text` as the contents of `path`.
+pub fn run_formatter(
+    formatter: Formatter,
+    path: &Path,
+    text: &str,
+    range: Option<LineRange>,
+) -> Result<String, FormatError> {
+    let mut child = command(formatter, path, text, range)
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => FormatError::NotInstalled {
+                formatter,
+                install_hint: formatter.install_hint().to_string(),
+            },
+            _ => format!("Failed to start {}: {}", formatter.name(), e).into(),
+        })?;
+    // Written from a thread so a large file can't fill the stdout pipe
+    // while we are still writing stdin.
+    let stdin = child.stdin.take();
+    let input = text.to_string();
+    let writer = std::thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {}: {}", formatter.name(), e))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", formatter.name(), stderr.trim()).into());
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| format!("{} printed invalid UTF-8", formatter.name()).into())
+}
+
+/// Format the file at `path`, or only `range` of it, and write the result
+/// back if it changed.
+pub fn format_file(
+    trust: &TrustStoreState,
+    path: &Path,
+    range: Option<LineRange>,
+) -> Result<FormatResult, FormatError> {
+    let formatter = Formatter::for_path(path).ok_or_else(|| FormatError::Unsupported {
+        path: path_to_wire(path),
+    })?;
+    if range.is_some() && !formatter.supports_range() {
+        return Err(FormatError::RangeNotSupported { formatter });
+    }
+    let dir = path.parent().unwrap_or(Path::new("."));
+    require_trusted_in(trust, dir).map_err(|e| FormatError::NotTrusted { message: e.message })?;
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let formatted = run_formatter(formatter, path, &text, range)?;
+    let changed = formatted != text;
+    if changed {
+        write_file_contents_atomic(path, formatted.as_bytes())?;
+    }
+    Ok(FormatResult {
+        formatter,
+        config_file: find_config(formatter, dir).map(|f| path_to_wire(&f)),
+        changed,
+        content: formatted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_formatter_for_path() {
+        assert_eq!(
+            Formatter::for_path(Path::new("src/App.TSX")),
+            Some(Formatter::Prettier)
+        );
+        assert_eq!(
+            Formatter::for_path(Path::new("main.rs")),
+            Some(Formatter::Rustfmt)
+        );
+        assert_eq!(
+            Formatter::for_path(Path::new("app.pyi")),
+            Some(Formatter::Black)
+        );
+        assert_eq!(Formatter::for_path(Path::new("Makefile")), None);
+        assert!(matches!(
+            format_file(
+                &TrustStoreState::default(),
+                Path::new("/tmp/Makefile"),
+                None
+            ),
+            Err(FormatError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn test_untrusted_file_is_not_formatted() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, "fn main(){}\n").unwrap();
+        assert!(matches!(
+            format_file(&TrustStoreState::default(), &file, None),
+            Err(FormatError::NotTrusted { .. })
+        ));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn main(){}\n");
+    }
+
+    #[test]
+    fn test_find_config_stops_at_checkout_root() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let src = repo.join("web/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir(repo.join(".git")).unwrap();
+        std::fs::write(dir.path().join(".prettierrc"), "{}").unwrap();
+        assert_eq!(find_config(Formatter::Prettier, &src), None);
+
+        std::fs::write(repo.join("web/package.json"), r#"{"prettier": {}}"#).unwrap();
+        assert_eq!(
+            find_config(Formatter::Prettier, &src),
+            Some(repo.join("web/package.json"))
+        );
+        std::fs::write(repo.join("pyproject.toml"), "[tool.ruff]\n").unwrap();
+        assert_eq!(find_config(Formatter::Black, &src), None);
+    }
+
+    #[test]
+    fn test_rust_edition_from_crate_or_workspace() {
+        let dir = TempDir::new().unwrap();
+        let krate = dir.path().join("crates/core");
+        std::fs::create_dir_all(&krate).unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace.package]\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            krate.join("Cargo.toml"),
+            "[package]\nedition.workspace = true\n",
+        )
+        .unwrap();
+        assert_eq!(rust_edition(&krate).as_deref(), Some("2021"));
+    }
+
+    #[test]
+    fn test_utf16_line_span() {
+        let text = "a\né😀\nc\n";
+        assert_eq!(
+            utf16_line_span(text, LineRange { start: 2, end: 2 }),
+            (2, 6)
+        );
+        assert_eq!(
+            utf16_line_span(text, LineRange { start: 2, end: 9 }),
+            (2, 8)
+        );
+    }
+}
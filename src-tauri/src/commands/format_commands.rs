@@ -0,0 +1,35 @@
+//! Tauri commands for formatting files; see format.rs.
+
+use tauri::AppHandle;
+
+use super::format::{self, FormatError, FormatResult, Formatter, LineRange};
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+use super::trust_commands::trust_store;
+
+/// Format the file at `path` (or only `range` of its lines) with its
+/// project's formatter and write the result back. The editor calls this
+/// after saving and reloads from the returned content.
+///
+/// Errors are typed so the editor can offer `install_hint` when the
+/// formatter is missing, rather than show a failure.
+#[tauri::command]
+pub async fn format_file(
+    app: AppHandle,
+    path: String,
+    range: Option<LineRange>,
+) -> Result<FormatResult, FormatError> {
+    let path = normalize_input_path(&path);
+    let trust = trust_store(&app).map_err(|e| FormatError::Failed { message: e.message })?;
+    run_blocking("format_file", move || {
+        Ok(format::format_file(&trust, &path, range))
+    })
+    .await
+    .unwrap_or_else(|e| Err(FormatError::Failed { message: e.message }))
+}
+
+/// The formatter `format_file` would use for `path`, if any.
+#[tauri::command]
+pub fn get_formatter(path: String) -> Option<Formatter> {
+    Formatter::for_path(&normalize_input_path(&path))
+}
@@ -1,9 +1,23 @@
+use rayon::prelude::*;
 use serde::Serialize;
-use std::path::Path;
-
-use super::error::{user_io_error, user_path_error};
+use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::confirmation::{require_confirmation, PrivilegedAction};
+use super::error::{user_error, user_io_failure, ErrorCode, KiriError};
+use super::fs_budget::{is_slow_path, run_budgeted, Deadline, READ_DIRECTORY_BUDGET};
+use super::fs_dir_cache::{DirListingCache, SharedDirListingCache, DIR_CACHE_CAPACITY};
 use super::fs_gitignore::check_gitignore;
 use super::fs_io::{get_dir_entry, get_file_type, get_home_dir, open_repo, read_dir_entries};
+use super::fs_path::{
+    display_path, input_path, normalize_input_path, os_str_to_wire, resolve_within,
+};
+use super::latency::run_blocking_coded;
+use super::lock_ext::LockExt;
+use super::performance::CommandTimer;
+use super::process::{launch_command, path_arg};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct FileEntry {
@@ -14,11 +28,11 @@ pub struct FileEntry {
     pub is_gitignored: bool,
 }
 
-fn find_repo_root(path: &Path) -> Option<String> {
+fn find_repo_root(path: &Path) -> Option<PathBuf> {
     let mut current = path;
     loop {
         if current.join(".git").exists() {
-            return Some(current.to_string_lossy().to_string());
+            return Some(current.to_path_buf());
         }
         match current.parent() {
             Some(parent) => current = parent,
@@ -54,26 +68,91 @@ fn is_excluded(name: &str) -> bool {
 /// /" call from streaming millions of entries back over IPC.
 const MAX_DIRECTORY_ENTRIES: usize = 10_000;
 
+lazy_static::lazy_static! {
+    static ref DIR_CACHE: SharedDirListingCache<FileEntry> =
+        Mutex::new(DirListingCache::new(DIR_CACHE_CAPACITY));
+}
+
+/// Stat the surviving entries of a directory on the rayon pool.
+///
+/// `DirEntry::file_type` is free on local filesystems that report `d_type`,
+/// but on SMB/NFS mounts every call is a network round-trip. Fanning the
+/// calls out turns N sequential round-trips into roughly N / threads.
+/// Entries not yet stated when `deadline` passes are left out.
+fn stat_entries_parallel(
+    entries: Vec<DirEntry>,
+    deadline: Option<Deadline>,
+) -> Result<Vec<(DirEntry, bool)>, String> {
+    entries
+        .into_par_iter()
+        .filter(|_| !deadline.is_some_and(|d| d.expired()))
+        .map(|entry| {
+            let is_dir = get_file_type(&entry)?.is_dir();
+            Ok((entry, is_dir))
+        })
+        .collect()
+}
+
 /// Synchronous implementation of [`read_directory`], factored out so the
 /// async `#[tauri::command]` wrapper can drop into `spawn_blocking`.
-fn read_directory_blocking(path: String) -> Result<Vec<FileEntry>, String> {
-    let path = Path::new(&path);
+///
+/// `fast` skips the libgit2 ignore lookup (every entry reports
+/// `is_gitignored: false`), which is the dominant cost once stats are
+/// parallel. The tree uses it for the first paint of very large folders
+/// and refines with a full read afterwards.
+pub(crate) fn read_directory_blocking(
+    path: String,
+    fast: bool,
+) -> Result<Vec<FileEntry>, KiriError> {
+    read_directory_until(path, fast, None).map(|(entries, _)| entries)
+}
+
+/// [`read_directory_blocking`] that stops when `deadline` passes, with
+/// whether it did. A listing cut short holds the entries read and stated
+/// by then, and isn't cached.
+fn read_directory_until(
+    path: String,
+    fast: bool,
+    deadline: Option<Deadline>,
+) -> Result<(Vec<FileEntry>, bool), KiriError> {
+    let _span = tracing::info_span!("fs::read_directory", path = %path, fast).entered();
+    let expired = || deadline.is_some_and(|d| d.expired());
+    let path = input_path(&path)?;
+    let path = path.as_path();
 
     if !path.exists() {
-        return Err(user_path_error("Path does not exist", path));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.path_not_found",
+            path.display(),
+        ));
     }
 
     if !path.is_dir() {
-        return Err(user_path_error("Path is not a directory", path));
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.not_a_directory",
+            path.display(),
+        ));
     }
 
-    let repo = find_repo_root(path).and_then(|root| open_repo(&root));
+    let dir_mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    if let Some(mtime) = dir_mtime {
+        if let Some(cached) = DIR_CACHE.lock_recover().get(path, fast, mtime) {
+            return Ok((cached, false));
+        }
+    }
 
-    let mut entries: Vec<FileEntry> = Vec::new();
+    let mut raw_entries: Vec<DirEntry> = Vec::new();
+    let mut partial = false;
     let read_dir = read_dir_entries(path)?;
 
     for entry in read_dir {
-        if entries.len() >= MAX_DIRECTORY_ENTRIES {
+        if expired() {
+            partial = true;
+            break;
+        }
+        if raw_entries.len() >= MAX_DIRECTORY_ENTRIES {
             log::warn!(
                 "read_directory: truncating result at {} entries for {}",
                 MAX_DIRECTORY_ENTRIES,
@@ -82,28 +161,44 @@ fn read_directory_blocking(path: String) -> Result<Vec<FileEntry>, String> {
             break;
         }
         let entry = get_dir_entry(entry)?;
-        let file_name = entry.file_name().to_string_lossy().to_string();
-
-        if is_excluded(&file_name) {
+        if is_excluded(&entry.file_name().to_string_lossy()) {
             continue;
         }
+        raw_entries.push(entry);
+    }
 
-        let file_type = get_file_type(&entry)?;
-        let full_path = entry.path().to_string_lossy().to_string();
-        let is_dir = file_type.is_dir();
+    let read = raw_entries.len();
+    let stated = stat_entries_parallel(raw_entries, deadline)?;
+    partial |= stated.len() < read;
 
-        let is_gitignored = repo
-            .as_ref()
-            .is_some_and(|r| check_gitignore(r, &entry.path(), is_dir));
+    // libgit2's `Repository` is not `Sync`, so ignore checks stay on this
+    // thread after the parallel stat pass. Out of time, the listing goes
+    // without them.
+    let out_of_time = !fast && expired();
+    partial |= out_of_time;
+    let repo = if fast || out_of_time {
+        None
+    } else {
+        find_repo_root(path).and_then(|root| open_repo(&root))
+    };
 
-        entries.push(FileEntry {
-            name: file_name.clone(),
-            path: full_path,
-            is_dir,
-            is_hidden: is_hidden(&file_name),
-            is_gitignored,
-        });
-    }
+    let mut entries: Vec<FileEntry> = stated
+        .into_iter()
+        .map(|(entry, is_dir)| {
+            let file_name = os_str_to_wire(&entry.file_name());
+            let entry_path = entry.path();
+            let is_gitignored = repo
+                .as_ref()
+                .is_some_and(|r| check_gitignore(r, &entry_path, is_dir));
+            FileEntry {
+                is_hidden: is_hidden(&file_name),
+                name: file_name,
+                path: display_path(&entry_path),
+                is_dir,
+                is_gitignored,
+            }
+        })
+        .collect();
 
     entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
         (true, false) => std::cmp::Ordering::Less,
@@ -111,7 +206,13 @@ fn read_directory_blocking(path: String) -> Result<Vec<FileEntry>, String> {
         _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
     });
 
-    Ok(entries)
+    if let Some(mtime) = dir_mtime.filter(|_| !partial) {
+        DIR_CACHE
+            .lock_recover()
+            .insert(path, fast, mtime, SystemTime::now(), entries.clone());
+    }
+
+    Ok((entries, partial))
 }
 
 /// Asynchronous Tauri wrapper around [`read_directory_blocking`].
@@ -121,83 +222,153 @@ fn read_directory_blocking(path: String) -> Result<Vec<FileEntry>, String> {
 /// thread keeps the tokio runtime — and therefore every other in-flight
 /// command and websocket frame — responsive even while a slow first
 /// `read_directory` is in progress.
+///
+/// A listing gets [`READ_DIRECTORY_BUDGET`], ample for a local disk, and
+/// returns what it has by then; see fs_budget.rs. On a drive already known
+/// to be slow it is always fast.
 #[tauri::command]
-pub async fn read_directory(path: String) -> Result<Vec<FileEntry>, String> {
-    tokio::task::spawn_blocking(move || read_directory_blocking(path))
-        .await
-        .map_err(|e| format!("read_directory task panicked: {}", e))?
+pub async fn read_directory(path: String, fast: Option<bool>) -> Result<Vec<FileEntry>, KiriError> {
+    let _timer = CommandTimer::start("read_directory");
+    let dir = normalize_input_path(&path);
+    let fast = fast.unwrap_or(false) || is_slow_path(&dir);
+    tokio::task::spawn_blocking(move || {
+        run_budgeted(
+            "read_directory",
+            &dir,
+            READ_DIRECTORY_BUDGET,
+            move |deadline| read_directory_until(path, fast, Some(deadline)),
+        )
+    })
+    .await
+    .map_err(|e| format!("read_directory task panicked: {}", e))?
+}
+
+/// Number of cached directory listings, for memory sampling.
+pub fn directory_cache_len() -> usize {
+    DIR_CACHE.lock_recover().len()
+}
+
+/// The directory whose cached listings an edit to `path` may make stale,
+/// when `path` is an ignore file: that of a `.gitignore`, or the worktree
+/// of a `.git/info/exclude`.
+fn ignore_file_scope(path: &Path) -> Option<&Path> {
+    if path.file_name().is_some_and(|name| name == ".gitignore") {
+        path.parent()
+    } else if path.ends_with(".git/info/exclude") {
+        path.ancestors().nth(3)
+    } else {
+        None
+    }
+}
+
+/// Drop the cached listings an edit to `path` may have made stale, if it
+/// is an ignore file. Editing one doesn't bump the mtime of the
+/// directories it affects, so their `is_gitignored` flags would lag.
+/// Called by the file watcher for every changed path.
+pub fn invalidate_directory_cache_for(path: &Path) {
+    if let Some(scope) = ignore_file_scope(path) {
+        DIR_CACHE.lock_recover().invalidate_prefix(scope);
+    }
+}
+
+/// Drop cached directory listings under `path`, or all of them when
+/// `path` is omitted, for changes the watcher can't see, such as to the
+/// global excludes file.
+#[tauri::command]
+pub fn invalidate_directory_cache(path: Option<String>) {
+    let mut cache = DIR_CACHE.lock_recover();
+    match path {
+        Some(p) => cache.invalidate_prefix(&normalize_input_path(&p)),
+        None => cache.clear(),
+    }
 }
 
 #[tauri::command]
-pub fn get_home_directory() -> Result<String, String> {
-    get_home_dir()
+pub fn get_home_directory() -> Result<String, KiriError> {
+    get_home_dir().map_err(KiriError::from)
 }
 
 #[tauri::command]
-pub fn create_directory(parent_path: String, name: String) -> Result<String, String> {
-    let parent = Path::new(&parent_path);
+pub fn create_directory(parent_path: String, name: String) -> Result<String, KiriError> {
+    let parent = input_path(&parent_path)?;
+    let parent = parent.as_path();
 
     if !parent.exists() {
-        return Err(user_path_error("Parent path does not exist", parent));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.parent_missing",
+            parent.display(),
+        ));
     }
 
     if !parent.is_dir() {
-        return Err(user_path_error("Parent path is not a directory", parent));
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.parent_not_a_directory",
+            parent.display(),
+        ));
     }
 
-    // Support nested directory creation (e.g., "test/opt" creates both)
+    // Support nested directory creation (e.g., "test/opt" creates both),
+    // but not `..` or links that lead out of the parent.
+    resolve_within(&name, parent)?;
     let new_dir_path = parent.join(&name);
 
     std::fs::create_dir_all(&new_dir_path)
-        .map_err(|e| user_io_error("Failed to create directory", e))?;
+        .map_err(|e| user_io_failure("error.create_directory_failed", e))?;
 
-    Ok(new_dir_path.to_string_lossy().to_string())
+    Ok(display_path(&new_dir_path))
 }
 
-#[tauri::command]
-pub fn delete_path(path: String) -> Result<(), String> {
-    let path = Path::new(&path);
+fn delete_path_blocking(path: String) -> Result<(), KiriError> {
+    let path = input_path(&path)?;
+    let path = path.as_path();
 
     if !path.exists() {
-        return Err(user_path_error("Path does not exist", path));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.path_not_found",
+            path.display(),
+        ));
     }
 
     if path.is_dir() {
-        std::fs::remove_dir_all(path).map_err(|e| user_io_error("Failed to delete directory", e))
+        std::fs::remove_dir_all(path)
+            .map_err(|e| user_io_failure("error.delete_directory_failed", e))
     } else {
-        std::fs::remove_file(path).map_err(|e| user_io_error("Failed to delete file", e))
+        std::fs::remove_file(path).map_err(|e| user_io_failure("error.delete_file_failed", e))
     }
 }
 
+/// Show `path` selected in the platform's file manager. It must be
+/// absolute, so the file manager can't read it as an option.
 #[tauri::command]
-pub fn reveal_in_finder(path: String) -> Result<(), String> {
+pub fn reveal_in_finder(path: String) -> Result<(), KiriError> {
+    let path = input_path(&path)?;
+    let path = path_arg(&path)?;
     #[cfg(target_os = "macos")]
     {
-        std::process::Command::new("open")
-            .arg("-R")
-            .arg(&path)
+        launch_command(&["open", "-R"], None)?
+            .arg(path)
             .spawn()
             .map_err(|e| e.to_string())?;
     }
     #[cfg(target_os = "windows")]
     {
-        std::process::Command::new("explorer")
-            .arg("/select,")
-            .arg(&path)
+        launch_command(&["explorer", "/select,"], None)?
+            .arg(path)
             .spawn()
             .map_err(|e| e.to_string())?;
     }
     #[cfg(target_os = "linux")]
     {
         // Try various file managers
-        if std::process::Command::new("nautilus")
-            .arg("--select")
-            .arg(&path)
-            .spawn()
-            .is_err()
-        {
-            std::process::Command::new("xdg-open")
-                .arg(Path::new(&path).parent().unwrap_or(Path::new(&path)))
+        let nautilus = launch_command(&["nautilus", "--select"], None)?
+            .arg(path)
+            .spawn();
+        if nautilus.is_err() {
+            launch_command(&["xdg-open"], None)?
+                .arg(path.parent().unwrap_or(path))
                 .spawn()
                 .map_err(|e| e.to_string())?;
         }
@@ -211,40 +382,45 @@ pub fn reveal_in_finder(path: String) -> Result<(), String> {
 /// `..` so callers can't escape the parent. For cross-directory moves,
 /// use `move_path` instead.
 #[tauri::command]
-pub fn rename_path(path: String, new_name: String) -> Result<String, String> {
-    let source = Path::new(&path);
+pub fn rename_path(path: String, new_name: String) -> Result<String, KiriError> {
+    let source = input_path(&path)?;
+    let source = source.as_path();
 
     if !source.exists() {
-        return Err(user_path_error("Path does not exist", source));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.path_not_found",
+            source.display(),
+        ));
     }
 
     let trimmed = new_name.trim();
     if trimmed.is_empty() {
-        return Err("New name cannot be empty".to_string());
+        return Err("New name cannot be empty".into());
     }
     if trimmed.contains('/') || trimmed.contains('\\') || trimmed == "." || trimmed == ".." {
-        return Err("New name cannot contain path separators or be . / ..".to_string());
+        return Err("New name cannot contain path separators or be . / ..".into());
     }
 
     let parent = source
         .parent()
-        .ok_or_else(|| user_path_error("Path has no parent directory", source))?;
+        .ok_or_else(|| user_error(ErrorCode::InvalidInput, "error.no_parent", source.display()))?;
     let target = parent.join(trimmed);
 
     if target == source {
-        return Ok(source.to_string_lossy().to_string());
+        return Ok(display_path(source));
     }
 
     if target.exists() {
-        return Err(format!(
-            "Target already exists: {}",
-            target.to_string_lossy()
+        return Err(KiriError::new(
+            ErrorCode::AlreadyExists,
+            format!("Target already exists: {}", target.to_string_lossy()),
         ));
     }
 
-    std::fs::rename(source, &target).map_err(|e| user_io_error("Failed to rename", e))?;
+    std::fs::rename(source, &target).map_err(|e| user_io_failure("error.rename_failed", e))?;
 
-    Ok(target.to_string_lossy().to_string())
+    Ok(display_path(&target))
 }
 
 /// Create an empty file inside `parent_path`.
@@ -253,29 +429,38 @@ pub fn rename_path(path: String, new_name: String) -> Result<String, String> {
 /// create files outside the displayed directory. Errors if the target
 /// already exists rather than silently truncating.
 #[tauri::command]
-pub fn create_file(parent_path: String, name: String) -> Result<String, String> {
-    let parent = Path::new(&parent_path);
+pub fn create_file(parent_path: String, name: String) -> Result<String, KiriError> {
+    let parent = input_path(&parent_path)?;
+    let parent = parent.as_path();
 
     if !parent.exists() {
-        return Err(user_path_error("Parent path does not exist", parent));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.parent_missing",
+            parent.display(),
+        ));
     }
     if !parent.is_dir() {
-        return Err(user_path_error("Parent path is not a directory", parent));
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.parent_not_a_directory",
+            parent.display(),
+        ));
     }
 
     let trimmed = name.trim();
     if trimmed.is_empty() {
-        return Err("File name cannot be empty".to_string());
+        return Err("File name cannot be empty".into());
     }
     if trimmed.contains('/') || trimmed.contains('\\') || trimmed == "." || trimmed == ".." {
-        return Err("File name cannot contain path separators or be . / ..".to_string());
+        return Err("File name cannot contain path separators or be . / ..".into());
     }
 
     let target = parent.join(trimmed);
     if target.exists() {
-        return Err(format!(
-            "File already exists: {}",
-            target.to_string_lossy()
+        return Err(KiriError::new(
+            ErrorCode::AlreadyExists,
+            format!("File already exists: {}", target.to_string_lossy()),
         ));
     }
 
@@ -283,9 +468,9 @@ pub fn create_file(parent_path: String, name: String) -> Result<String, String>
         .write(true)
         .create_new(true)
         .open(&target)
-        .map_err(|e| user_io_error("Failed to create file", e))?;
+        .map_err(|e| user_io_failure("error.create_file_failed", e))?;
 
-    Ok(target.to_string_lossy().to_string())
+    Ok(display_path(&target))
 }
 
 /// Move a file or directory to the OS trash / recycle bin.
@@ -295,13 +480,17 @@ pub fn create_file(parent_path: String, name: String) -> Result<String, String>
 /// than immediately unlinking. On macOS / Windows the entry can be
 /// restored via `restore_from_trash`. On Linux the `trash` crate uses
 /// the freedesktop spec but restore listing is best-effort.
-#[tauri::command]
-pub fn move_to_trash(path: String) -> Result<(), String> {
-    let p = Path::new(&path);
+fn move_to_trash_blocking(path: String) -> Result<(), KiriError> {
+    let p = input_path(&path)?;
+    let p = p.as_path();
     if !p.exists() {
-        return Err(user_path_error("Path does not exist", p));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.path_not_found",
+            p.display(),
+        ));
     }
-    trash::delete(p).map_err(|e| format!("Failed to move to trash: {}", e))
+    Ok(trash::delete(p).map_err(|e| format!("Failed to move to trash: {}", e))?)
 }
 
 /// Whether the current OS supports programmatic restoration from the
@@ -330,7 +519,7 @@ pub fn trash_restore_supported() -> bool {
 /// error so the frontend can disable the undo affordance and surface
 /// a manual-restore hint.
 #[tauri::command]
-pub fn restore_from_trash(original_path: String) -> Result<String, String> {
+pub fn restore_from_trash(original_path: String) -> Result<String, KiriError> {
     #[cfg(any(
         target_os = "windows",
         all(
@@ -346,9 +535,10 @@ pub fn restore_from_trash(original_path: String) -> Result<String, String> {
         let items = os_limited::list().map_err(|e| format!("Failed to list trash: {}", e))?;
 
         // Pick the most-recently-trashed item whose original path matches.
+        let original = super::fs_path::path_from_wire(&original_path);
         let mut matching: Vec<trash::TrashItem> = items
             .into_iter()
-            .filter(|it| it.original_path() == Path::new(&original_path))
+            .filter(|it| it.original_path() == original)
             .collect();
         matching.sort_by_key(|it| it.time_deleted);
 
@@ -356,7 +546,7 @@ pub fn restore_from_trash(original_path: String) -> Result<String, String> {
             .pop()
             .ok_or_else(|| format!("No trash entry found for {}", original_path))?;
 
-        let restored_path = target.original_path().to_string_lossy().to_string();
+        let restored_path = display_path(&target.original_path());
         os_limited::restore_all([target])
             .map_err(|e| format!("Failed to restore from trash: {}", e))?;
         Ok(restored_path)
@@ -372,7 +562,10 @@ pub fn restore_from_trash(original_path: String) -> Result<String, String> {
     )))]
     {
         let _ = original_path;
-        Err("restore_from_trash is not supported on this platform".to_string())
+        Err(KiriError::new(
+            ErrorCode::Unsupported,
+            "restore_from_trash is not supported on this platform",
+        ))
     }
 }
 
@@ -384,35 +577,36 @@ pub fn restore_from_trash(original_path: String) -> Result<String, String> {
 /// pane inside kiri so it stays a small surface — the kiri-side pane
 /// opener is a separate concern.
 #[tauri::command]
-pub fn open_terminal_here(path: String) -> Result<(), String> {
-    let p = Path::new(&path);
+pub fn open_terminal_here(path: String) -> Result<(), KiriError> {
+    let p = input_path(&path)?;
+    let p = p.as_path();
     if !p.exists() {
-        return Err(user_path_error("Path does not exist", p));
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.path_not_found",
+            p.display(),
+        ));
     }
     let dir = if p.is_dir() {
         p.to_path_buf()
     } else {
         p.parent()
-            .ok_or_else(|| user_path_error("Path has no parent directory", p))?
+            .ok_or_else(|| user_error(ErrorCode::InvalidInput, "error.no_parent", p.display()))?
             .to_path_buf()
     };
 
     #[cfg(target_os = "macos")]
     {
-        std::process::Command::new("open")
-            .arg("-a")
-            .arg("Terminal")
+        launch_command(&["open", "-a", "Terminal"], Some(&dir))?
             .arg(&dir)
             .spawn()
             .map_err(|e| e.to_string())?;
     }
     #[cfg(target_os = "windows")]
     {
-        std::process::Command::new("cmd")
-            .arg("/C")
-            .arg("start")
-            .arg("cmd")
-            .current_dir(&dir)
+        // A console program started from a GUI process gets a window of
+        // its own.
+        launch_command(&["cmd"], Some(&dir))?
             .spawn()
             .map_err(|e| e.to_string())?;
     }
@@ -422,23 +616,42 @@ pub fn open_terminal_here(path: String) -> Result<(), String> {
         let candidates = ["x-terminal-emulator", "gnome-terminal", "konsole", "xterm"];
         let mut spawned = false;
         for cmd in candidates {
-            if std::process::Command::new(cmd)
-                .current_dir(&dir)
-                .spawn()
-                .is_ok()
-            {
+            if launch_command(&[cmd], Some(&dir))?.spawn().is_ok() {
                 spawned = true;
                 break;
             }
         }
         if !spawned {
-            return Err("No supported terminal emulator found".to_string());
+            return Err("No supported terminal emulator found".into());
         }
     }
 
     Ok(())
 }
 
+// Deleting a directory removes the whole tree, and trashing may need a
+// cross-volume copy, so both run on the blocking pool; see latency.rs.
+
+/// Delete `path` permanently. Needs a token from `request_confirmation`
+/// for [`PrivilegedAction::DeletePermanently`] on the same path.
+#[tauri::command]
+pub async fn delete_path(
+    path: String,
+    confirmation_token: Option<String>,
+) -> Result<(), KiriError> {
+    require_confirmation(
+        confirmation_token.as_deref(),
+        PrivilegedAction::DeletePermanently,
+        &input_path(&path)?,
+    )?;
+    run_blocking_coded("delete_path", move || delete_path_blocking(path)).await
+}
+
+#[tauri::command]
+pub async fn move_to_trash(path: String) -> Result<(), KiriError> {
+    run_blocking_coded("move_to_trash", move || move_to_trash_blocking(path)).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -446,6 +659,20 @@ mod tests {
     use std::fs;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_ignore_file_scope() {
+        assert_eq!(
+            ignore_file_scope(Path::new("/repo/src/.gitignore")),
+            Some(Path::new("/repo/src"))
+        );
+        assert_eq!(
+            ignore_file_scope(Path::new("/repo/.git/info/exclude")),
+            Some(Path::new("/repo"))
+        );
+        assert_eq!(ignore_file_scope(Path::new("/repo/src/main.rs")), None);
+        assert_eq!(ignore_file_scope(Path::new("/repo/info/exclude")), None);
+    }
+
     #[test]
     fn test_is_hidden() {
         assert!(is_hidden(".git"));
@@ -495,7 +722,7 @@ mod tests {
         fs::write(dir.path().join("file2.rs"), "fn main() {}").unwrap();
         fs::create_dir(dir.path().join("subdir")).unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -508,9 +735,9 @@ mod tests {
 
     #[test]
     fn test_read_directory_nonexistent() {
-        let result = read_directory_blocking("/nonexistent/path".to_string());
+        let result = read_directory_blocking("/nonexistent/path".to_string(), false);
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 
     #[test]
@@ -519,9 +746,9 @@ mod tests {
         let file_path = dir.path().join("file.txt");
         fs::write(&file_path, "content").unwrap();
 
-        let result = read_directory_blocking(file_path.to_string_lossy().to_string());
+        let result = read_directory_blocking(file_path.to_string_lossy().to_string(), false);
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not a directory"));
+        assert!(result.unwrap_err().message.contains("not a directory"));
     }
 
     #[test]
@@ -531,7 +758,7 @@ mod tests {
         fs::create_dir(dir.path().join("src")).unwrap();
         fs::create_dir(dir.path().join("node_modules")).unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -546,7 +773,7 @@ mod tests {
         fs::write(dir.path().join(".hidden"), "hidden").unwrap();
         fs::write(dir.path().join("visible.txt"), "visible").unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -570,7 +797,7 @@ mod tests {
         fs::create_dir(dir.path().join("banana")).unwrap();
         fs::create_dir(dir.path().join("cherry")).unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -601,7 +828,7 @@ mod tests {
 
         fs::write(dir.path().join("test.txt"), "content").unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -624,7 +851,7 @@ mod tests {
         // Create files
         fs::write(dir.path().join("normal.txt"), "normal").unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -643,7 +870,7 @@ mod tests {
         // Create files (not a git repo)
         fs::write(dir.path().join("file.txt"), "content").unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -700,6 +927,21 @@ mod tests {
         assert!(dir.path().join("test/opt/deep").exists());
     }
 
+    #[test]
+    fn test_create_directory_rejects_escape() {
+        let dir = tempdir().unwrap();
+        let parent = dir.path().join("parent");
+        fs::create_dir(&parent).unwrap();
+
+        let err = create_directory(
+            parent.to_string_lossy().to_string(),
+            "../escape".to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(err.code, ErrorCode::PermissionDenied);
+        assert!(!dir.path().join("escape").exists());
+    }
+
     #[test]
     fn test_create_directory_nonexistent_parent() {
         let result = create_directory(
@@ -707,7 +949,7 @@ mod tests {
             "new_folder".to_string(),
         );
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 
     #[test]
@@ -721,7 +963,7 @@ mod tests {
             "new_folder".to_string(),
         );
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not a directory"));
+        assert!(result.unwrap_err().message.contains("not a directory"));
     }
 
     #[test]
@@ -745,7 +987,7 @@ mod tests {
         fs::write(&file_path, "content").unwrap();
         assert!(file_path.exists());
 
-        let result = delete_path(file_path.to_string_lossy().to_string());
+        let result = delete_path_blocking(file_path.to_string_lossy().to_string());
         assert!(result.is_ok());
         assert!(!file_path.exists());
     }
@@ -758,16 +1000,16 @@ mod tests {
         fs::write(subdir.join("file.txt"), "content").unwrap();
         assert!(subdir.exists());
 
-        let result = delete_path(subdir.to_string_lossy().to_string());
+        let result = delete_path_blocking(subdir.to_string_lossy().to_string());
         assert!(result.is_ok());
         assert!(!subdir.exists());
     }
 
     #[test]
     fn test_delete_path_nonexistent() {
-        let result = delete_path("/nonexistent/path/to/delete".to_string());
+        let result = delete_path_blocking("/nonexistent/path/to/delete".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 
     #[test]
@@ -787,7 +1029,7 @@ mod tests {
         // Create a non-ignored directory
         fs::create_dir(dir.path().join("normal_dir")).unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -819,7 +1061,7 @@ mod tests {
         // Create a non-ignored file
         fs::write(dir.path().join("normal.txt"), "normal data").unwrap();
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -847,7 +1089,7 @@ mod tests {
         fs::write(nested_dir.join("file.txt"), "content").unwrap();
 
         // Read directory should handle the workdir path correctly
-        let result = read_directory_blocking(nested_dir.to_string_lossy().to_string());
+        let result = read_directory_blocking(nested_dir.to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         // Verify repo workdir is available
@@ -867,7 +1109,7 @@ mod tests {
         fs::write(dir.path().join("file.txt"), "content").unwrap();
 
         // Read the repo root directory
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string());
+        let result = read_directory_blocking(dir.path().to_string_lossy().to_string(), false);
         assert!(result.is_ok());
 
         let entries = result.unwrap();
@@ -1000,7 +1242,8 @@ mod tests {
             fs::write(dir.path().join(format!("f{}.txt", i)), b"").unwrap();
         }
 
-        let result = read_directory_blocking(dir.path().to_string_lossy().to_string()).unwrap();
+        let result =
+            read_directory_blocking(dir.path().to_string_lossy().to_string(), false).unwrap();
         assert!(
             result.len() <= MAX_DIRECTORY_ENTRIES,
             "expected at most {} entries, got {}",
@@ -1015,8 +1258,9 @@ mod tests {
         fs::write(dir.path().join("a.txt"), b"").unwrap();
         fs::create_dir(dir.path().join("subdir")).unwrap();
 
-        let blocking = read_directory_blocking(dir.path().to_string_lossy().to_string()).unwrap();
-        let async_result = read_directory(dir.path().to_string_lossy().to_string())
+        let blocking =
+            read_directory_blocking(dir.path().to_string_lossy().to_string(), false).unwrap();
+        let async_result = read_directory(dir.path().to_string_lossy().to_string(), None)
             .await
             .unwrap();
         assert_eq!(blocking.len(), async_result.len());
@@ -1026,6 +1270,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_directory_fast_mode_skips_gitignore() {
+        let dir = tempdir().unwrap();
+        Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".gitignore"), "ignored.log\n").unwrap();
+        fs::write(dir.path().join("ignored.log"), "").unwrap();
+
+        let path = dir.path().to_string_lossy().to_string();
+        let full = read_directory_blocking(path.clone(), false).unwrap();
+        let fast = read_directory_blocking(path, true).unwrap();
+
+        let full_entry = full.iter().find(|e| e.name == "ignored.log").unwrap();
+        let fast_entry = fast.iter().find(|e| e.name == "ignored.log").unwrap();
+        assert!(full_entry.is_gitignored);
+        assert!(!fast_entry.is_gitignored);
+        assert_eq!(full.len(), fast.len());
+    }
+
+    #[test]
+    fn test_read_directory_parallel_stat_classifies_dirs() {
+        let dir = tempdir().unwrap();
+        for i in 0..64 {
+            fs::create_dir(dir.path().join(format!("d{:02}", i))).unwrap();
+            fs::write(dir.path().join(format!("f{:02}.txt", i)), b"").unwrap();
+        }
+
+        let entries =
+            read_directory_blocking(dir.path().to_string_lossy().to_string(), true).unwrap();
+        assert_eq!(entries.len(), 128);
+        assert!(entries[..64].iter().all(|e| e.is_dir));
+        assert!(entries[64..].iter().all(|e| !e.is_dir));
+        assert_eq!(entries[0].name, "d00");
+    }
+
+    #[test]
+    fn test_invalidate_directory_cache_accepts_missing_path() {
+        invalidate_directory_cache(Some("/nonexistent/cache/entry".to_string()));
+        invalidate_directory_cache(None);
+    }
+
     // ------- rename_path -------
 
     #[test]
@@ -1070,7 +1354,7 @@ mod tests {
             "../escape.txt".to_string(),
         );
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("path separators"));
+        assert!(result.unwrap_err().message.contains("path separators"));
     }
 
     #[test]
@@ -1092,7 +1376,7 @@ mod tests {
 
         let result = rename_path(original.to_string_lossy().to_string(), "   ".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("empty"));
+        assert!(result.unwrap_err().message.contains("empty"));
     }
 
     #[test]
@@ -1105,7 +1389,7 @@ mod tests {
 
         let result = rename_path(a.to_string_lossy().to_string(), "b.txt".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("already exists"));
+        assert!(result.unwrap_err().message.contains("already exists"));
     }
 
     #[test]
@@ -1113,7 +1397,7 @@ mod tests {
         let result =
             rename_path("/nonexistent/foo.txt".to_string(), "bar.txt".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 
     // ------- create_file -------
@@ -1157,7 +1441,7 @@ mod tests {
             "sub/foo.txt".to_string(),
         );
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("path separators"));
+        assert!(result.unwrap_err().message.contains("path separators"));
     }
 
     #[test]
@@ -1177,7 +1461,7 @@ mod tests {
         let result =
             create_file(file_path.to_string_lossy().to_string(), "x.txt".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not a directory"));
+        assert!(result.unwrap_err().message.contains("not a directory"));
     }
 
     // ------- move_to_trash -------
@@ -1189,9 +1473,9 @@ mod tests {
 
     #[test]
     fn test_move_to_trash_rejects_nonexistent() {
-        let result = move_to_trash("/nonexistent/path/zzz".to_string());
+        let result = move_to_trash_blocking("/nonexistent/path/zzz".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 
     #[test]
@@ -1225,6 +1509,6 @@ mod tests {
     fn test_open_terminal_here_rejects_nonexistent() {
         let result = open_terminal_here("/definitely/does/not/exist".to_string());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("does not exist"));
+        assert!(result.unwrap_err().message.contains("does not exist"));
     }
 }
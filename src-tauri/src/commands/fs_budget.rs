@@ -0,0 +1,258 @@
+//! Time budgets for filesystem work that can hang on network drives.
+//!
+//! Listing a directory, searching file names or reading git status on an
+//! NFS or SMB mount can block for minutes in a single syscall. Those
+//! commands run their work with [`run_budgeted`]: the work gets a
+//! [`Deadline`] and returns what it has when the deadline passes, and if
+//! it doesn't come back shortly after, because a syscall is stuck, the
+//! command fails with a timeout instead of waiting for it. Either way the
+//! path is recorded in [`slow_paths`] for [`SLOW_FLAG_TTL`], the directory
+//! listing skips its optional git ignore lookup there, and a
+//! `slow-filesystem` event tells the UI, which can skip optional work of
+//! its own. The event is sent by the reporter fs_budget_commands.rs
+//! installs with [`set_slow_reporter`].
+//!
+//! A stuck syscall can't be interrupted, so its thread is left to finish
+//! on its own. At most [`MAX_STUCK_WORKERS`] are left at a time; beyond
+//! that, budgeted work on slow paths is refused rather than started.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::error::{path_name, ErrorCode, KiriError};
+use super::fs_path::{strip_root, wire_path};
+use super::i18n::Message;
+use super::lock_ext::LockExt;
+
+pub const READ_DIRECTORY_BUDGET: Duration = Duration::from_secs(3);
+pub const SEARCH_BUDGET: Duration = Duration::from_secs(10);
+pub const GIT_STATUS_BUDGET: Duration = Duration::from_secs(10);
+
+/// How long past its deadline work may take to return what it has before
+/// the caller gives up on it.
+pub const GRACE: Duration = Duration::from_secs(1);
+
+/// How long a path stays flagged slow after work on it ran over budget.
+pub const SLOW_FLAG_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Threads that may be left waiting on stuck syscalls at once.
+pub const MAX_STUCK_WORKERS: usize = 16;
+
+static STUCK_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now() + budget)
+    }
+
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.0
+    }
+}
+
+/// Payload of the `slow-filesystem` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlowFilesystemEvent {
+    #[serde(with = "wire_path")]
+    pub path: PathBuf,
+    /// `read_directory`, `search_files` or `get_git_status`.
+    pub operation: String,
+    /// Whether the result was cut short; otherwise the operation failed
+    /// with a timeout.
+    pub partial: bool,
+}
+
+const RUNNING: u8 = 0;
+const FINISHED: u8 = 1;
+const ABANDONED: u8 = 2;
+
+/// Run `work` on its own thread with a deadline `budget` from now. `None`
+/// when it hasn't returned [`GRACE`] after the deadline, or when too many
+/// earlier workers are still stuck to start another.
+pub fn run_with_budget<T: Send + 'static>(
+    name: &str,
+    budget: Duration,
+    work: impl FnOnce(Deadline) -> T + Send + 'static,
+) -> Option<T> {
+    if STUCK_WORKERS.load(Ordering::Acquire) >= MAX_STUCK_WORKERS {
+        log::warn!("{name}: {MAX_STUCK_WORKERS} earlier calls are still stuck; not starting");
+        return None;
+    }
+    let deadline = Deadline::after(budget);
+    let state = Arc::new(AtomicU8::new(RUNNING));
+    let (tx, rx) = mpsc::channel();
+    let worker_state = Arc::clone(&state);
+    let spawned = std::thread::Builder::new()
+        .name(format!("kiri-budget-{name}"))
+        .spawn(move || {
+            let _ = tx.send(work(deadline));
+            let finished = worker_state.compare_exchange(
+                RUNNING,
+                FINISHED,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+            if finished == Err(ABANDONED) {
+                STUCK_WORKERS.fetch_sub(1, Ordering::AcqRel);
+            }
+        });
+    if let Err(e) = spawned {
+        log::warn!("{name}: failed to start worker: {e}");
+        return None;
+    }
+    if let Ok(result) = rx.recv_timeout(budget + GRACE) {
+        return Some(result);
+    }
+    // Counted before it is marked abandoned, so the worker's decrement
+    // can't come first.
+    STUCK_WORKERS.fetch_add(1, Ordering::AcqRel);
+    match state.compare_exchange(RUNNING, ABANDONED, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => {
+            log::warn!("{name}: no result {GRACE:?} after its deadline; giving up");
+            None
+        }
+        // It finished just now.
+        Err(_) => {
+            STUCK_WORKERS.fetch_sub(1, Ordering::AcqRel);
+            rx.try_recv().ok()
+        }
+    }
+}
+
+/// Run `work` for `operation` on `path` under `budget`. The work returns
+/// its result and whether the deadline cut it short. A cut-short result,
+/// or none at all, reports `path` as slow; none at all is a timeout error.
+pub fn run_budgeted<T: Send + 'static, E: From<KiriError> + Send + 'static>(
+    operation: &'static str,
+    path: &Path,
+    budget: Duration,
+    work: impl FnOnce(Deadline) -> Result<(T, bool), E> + Send + 'static,
+) -> Result<T, E> {
+    match run_with_budget(operation, budget, work) {
+        Some(Ok((result, partial))) => {
+            if partial {
+                report_slow(path, operation, true);
+            }
+            Ok(result)
+        }
+        Some(Err(e)) => Err(e),
+        None => {
+            report_slow(path, operation, false);
+            Err(E::from(KiriError::localized(
+                ErrorCode::Timeout,
+                Message::new("error.slow_filesystem").with("name", path_name(path)),
+            )))
+        }
+    }
+}
+
+/// Paths where work recently ran over budget.
+#[derive(Debug, Default)]
+pub struct SlowPaths {
+    marked: HashMap<PathBuf, Instant>,
+}
+
+impl SlowPaths {
+    pub fn mark(&mut self, path: &Path, now: Instant) {
+        self.marked.insert(path.to_path_buf(), now + SLOW_FLAG_TTL);
+    }
+
+    /// Whether `path` or a directory above it was marked within
+    /// [`SLOW_FLAG_TTL`].
+    pub fn is_slow(&mut self, path: &Path, now: Instant) -> bool {
+        self.marked.retain(|_, until| *until > now);
+        self.marked
+            .keys()
+            .any(|root| strip_root(path, root).is_some())
+    }
+}
+
+pub fn slow_paths() -> &'static Mutex<SlowPaths> {
+    static SLOW_PATHS: OnceLock<Mutex<SlowPaths>> = OnceLock::new();
+    SLOW_PATHS.get_or_init(|| Mutex::new(SlowPaths::default()))
+}
+
+type SlowReporter = Box<dyn Fn(&SlowFilesystemEvent) + Send + Sync>;
+
+fn slow_reporter() -> &'static OnceLock<SlowReporter> {
+    static REPORTER: OnceLock<SlowReporter> = OnceLock::new();
+    &REPORTER
+}
+
+/// Install what [`report_slow`] calls besides marking the path. Only the
+/// first call takes effect.
+pub fn set_slow_reporter(reporter: impl Fn(&SlowFilesystemEvent) + Send + Sync + 'static) {
+    let _ = slow_reporter().set(Box::new(reporter));
+}
+
+/// Mark `path` slow and pass it to the reporter.
+pub fn report_slow(path: &Path, operation: &str, partial: bool) {
+    if partial {
+        log::warn!(
+            "{operation} on {} ran out of time; returning what it had",
+            path.display()
+        );
+    } else {
+        log::warn!("{operation} on {} timed out", path.display());
+    }
+    slow_paths().lock_recover().mark(path, Instant::now());
+    if let Some(reporter) = slow_reporter().get() {
+        reporter(&SlowFilesystemEvent {
+            path: path.to_path_buf(),
+            operation: operation.to_string(),
+            partial,
+        });
+    }
+}
+
+/// Whether `path` is on a filesystem that was recently slow.
+pub fn is_slow_path(path: &Path) -> bool {
+    slow_paths().lock_recover().is_slow(path, Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_work_returns_partial_result_at_deadline() {
+        let counted = run_with_budget("count", Duration::from_millis(50), |deadline| {
+            let mut n = 0u64;
+            while !deadline.expired() {
+                n += 1;
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            n
+        });
+        assert!(counted.is_some_and(|n| n > 0));
+    }
+
+    #[test]
+    fn test_stuck_work_is_abandoned() {
+        let started = Instant::now();
+        let result = run_with_budget("stuck", Duration::ZERO, |_| {
+            std::thread::sleep(GRACE * 3);
+        });
+        assert_eq!(result, None);
+        assert!(started.elapsed() < GRACE * 2);
+    }
+
+    #[test]
+    fn test_slow_paths_cover_subdirectories_until_they_expire() {
+        let mut slow = SlowPaths::default();
+        let now = Instant::now();
+        slow.mark(Path::new("/mnt/nfs/project"), now);
+        assert!(slow.is_slow(Path::new("/mnt/nfs/project"), now));
+        assert!(slow.is_slow(Path::new("/mnt/nfs/project/src"), now));
+        assert!(!slow.is_slow(Path::new("/mnt/nfs/other"), now));
+        assert!(!slow.is_slow(Path::new("/mnt/nfs/project"), now + SLOW_FLAG_TTL));
+    }
+}
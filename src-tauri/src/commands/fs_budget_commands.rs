@@ -0,0 +1,28 @@
+//! The `slow-filesystem` event and flag; the time budgets themselves live
+//! in fs_budget.rs.
+
+use tauri::AppHandle;
+
+use super::error::KiriError;
+use super::event_throttle_commands::emit_throttled;
+use super::fs_budget::{is_slow_path, set_slow_reporter};
+use super::fs_path::{display_path, input_path};
+
+pub const SLOW_FILESYSTEM_EVENT: &str = "slow-filesystem";
+
+/// Send a `slow-filesystem` event whenever a budgeted operation runs over.
+pub fn setup_slow_filesystem_events(app: &AppHandle) {
+    let app = app.clone();
+    set_slow_reporter(move |event| {
+        let key = display_path(&event.path);
+        emit_throttled(&app, SLOW_FILESYSTEM_EVENT, &key, event);
+    });
+}
+
+/// Whether `path` is on a drive that recently ran over a time budget, so
+/// the UI can skip optional work there, like refreshing git status.
+#[tauri::command]
+pub fn is_slow_filesystem(path: String) -> Result<bool, KiriError> {
+    let path = input_path(&path)?;
+    Ok(is_slow_path(&path))
+}
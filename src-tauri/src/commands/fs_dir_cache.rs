@@ -0,0 +1,188 @@
+//! Directory listing cache for [`crate::commands::fs::read_directory`].
+//!
+//! Re-expanding a folder in the file tree re-reads it from disk. On local
+//! SSDs that is cheap, but on network filesystems a large directory can
+//! take seconds to stat. Listings are cached keyed by `(path, fast)` and
+//! validated against the directory's own mtime, which every platform bumps
+//! when an entry is added, removed, or renamed.
+//!
+//! Two caveats keep the cache conservative:
+//!
+//! - A listing whose directory mtime is younger than [`RACY_WINDOW`] is
+//!   never stored. Coarse-grained filesystems (HFS+, FAT, some SMB
+//!   servers) report mtimes with one- or two-second resolution, so a
+//!   change landing in the same tick as the read would otherwise be
+//!   invisible. This mirrors git's "racily clean" index rule.
+//! - Edits to `.gitignore` do not touch the listed directory's mtime, so
+//!   `is_gitignored` would lag until the next entry change. The file
+//!   watcher drops the affected listings when an ignore file changes; see
+//!   `fs::invalidate_directory_cache_for`.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Maximum number of directory listings kept in memory.
+pub const DIR_CACHE_CAPACITY: usize = 256;
+
+/// Listings whose directory was modified more recently than this are not
+/// cached (see module docs).
+pub const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+type CacheKey = (PathBuf, bool);
+
+struct CachedListing<T> {
+    mtime: SystemTime,
+    entries: Vec<T>,
+}
+
+/// Bounded `(path, fast) -> listing` map with FIFO eviction.
+pub struct DirListingCache<T: Clone> {
+    capacity: usize,
+    listings: HashMap<CacheKey, CachedListing<T>>,
+    insertion_order: VecDeque<CacheKey>,
+}
+
+impl<T: Clone> DirListingCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            listings: HashMap::new(),
+            insertion_order: VecDeque::new(),
+        }
+    }
+
+    /// Return the cached listing if it was recorded for the same mtime.
+    pub fn get(&self, path: &Path, fast: bool, mtime: SystemTime) -> Option<Vec<T>> {
+        self.listings
+            .get(&(path.to_path_buf(), fast))
+            .filter(|cached| cached.mtime == mtime)
+            .map(|cached| cached.entries.clone())
+    }
+
+    /// Store a listing unless the directory changed within the racy window
+    /// relative to `now`.
+    pub fn insert(
+        &mut self,
+        path: &Path,
+        fast: bool,
+        mtime: SystemTime,
+        now: SystemTime,
+        entries: Vec<T>,
+    ) {
+        let age = now.duration_since(mtime).unwrap_or(Duration::ZERO);
+        if age < RACY_WINDOW {
+            return;
+        }
+
+        let key = (path.to_path_buf(), fast);
+        if self.listings.contains_key(&key) {
+            self.insertion_order.retain(|k| k != &key);
+        }
+        while self.listings.len() >= self.capacity && !self.listings.contains_key(&key) {
+            match self.insertion_order.pop_front() {
+                Some(oldest) => {
+                    self.listings.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        self.insertion_order.push_back(key.clone());
+        self.listings.insert(key, CachedListing { mtime, entries });
+    }
+
+    /// Drop every listing under `prefix` (including `prefix` itself).
+    pub fn invalidate_prefix(&mut self, prefix: &Path) {
+        self.listings.retain(|(path, _), _| !path.starts_with(prefix));
+        let listings = &self.listings;
+        self.insertion_order.retain(|k| listings.contains_key(k));
+    }
+
+    pub fn clear(&mut self) {
+        self.listings.clear();
+        self.insertion_order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.listings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.listings.is_empty()
+    }
+}
+
+/// Process-wide cache instance shared by every window.
+pub type SharedDirListingCache<T> = Mutex<DirListingCache<T>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_get_hits_on_matching_mtime() {
+        let mut cache = DirListingCache::new(4);
+        cache.insert(Path::new("/a"), false, at(100), at(200), vec![1, 2]);
+        assert_eq!(cache.get(Path::new("/a"), false, at(100)), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_get_misses_on_changed_mtime() {
+        let mut cache = DirListingCache::new(4);
+        cache.insert(Path::new("/a"), false, at(100), at(200), vec![1]);
+        assert_eq!(cache.get(Path::new("/a"), false, at(101)), None);
+    }
+
+    #[test]
+    fn test_fast_and_full_listings_are_separate() {
+        let mut cache = DirListingCache::new(4);
+        cache.insert(Path::new("/a"), true, at(100), at(200), vec![1]);
+        assert_eq!(cache.get(Path::new("/a"), false, at(100)), None);
+        assert_eq!(cache.get(Path::new("/a"), true, at(100)), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_insert_skips_racy_listing() {
+        let mut cache = DirListingCache::new(4);
+        cache.insert(Path::new("/a"), false, at(100), at(101), vec![1]);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_insert_evicts_oldest_at_capacity() {
+        let mut cache = DirListingCache::new(2);
+        cache.insert(Path::new("/a"), false, at(1), at(100), vec![1]);
+        cache.insert(Path::new("/b"), false, at(1), at(100), vec![2]);
+        cache.insert(Path::new("/c"), false, at(1), at(100), vec![3]);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(Path::new("/a"), false, at(1)), None);
+        assert_eq!(cache.get(Path::new("/c"), false, at(1)), Some(vec![3]));
+    }
+
+    #[test]
+    fn test_reinsert_same_key_does_not_evict_others() {
+        let mut cache = DirListingCache::new(2);
+        cache.insert(Path::new("/a"), false, at(1), at(100), vec![1]);
+        cache.insert(Path::new("/b"), false, at(1), at(100), vec![2]);
+        cache.insert(Path::new("/a"), false, at(2), at(100), vec![9]);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(Path::new("/b"), false, at(1)), Some(vec![2]));
+        assert_eq!(cache.get(Path::new("/a"), false, at(2)), Some(vec![9]));
+    }
+
+    #[test]
+    fn test_invalidate_prefix() {
+        let mut cache = DirListingCache::new(8);
+        cache.insert(Path::new("/repo"), false, at(1), at(100), vec![1]);
+        cache.insert(Path::new("/repo/src"), false, at(1), at(100), vec![2]);
+        cache.insert(Path::new("/other"), false, at(1), at(100), vec![3]);
+        cache.invalidate_prefix(Path::new("/repo"));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(Path::new("/other"), false, at(1)).is_some());
+    }
+}
@@ -6,8 +6,10 @@ use std::path::Path;
 
 use git2::Repository;
 
+use super::fs_path::path_to_wire;
+
 /// Open git repository from root path
-pub fn open_repo(root: &str) -> Option<Repository> {
+pub fn open_repo(root: &Path) -> Option<Repository> {
     Repository::open(root).ok()
 }
 
@@ -29,6 +31,6 @@ pub fn get_file_type(entry: &DirEntry) -> Result<fs::FileType, String> {
 /// Get home directory with error handling
 pub fn get_home_dir() -> Result<String, String> {
     dirs::home_dir()
-        .map(|p| p.to_string_lossy().to_string())
+        .map(|p| path_to_wire(&p))
         .ok_or_else(|| "Could not determine home directory".to_string())
 }
@@ -0,0 +1,172 @@
+//! Which filesystem a path is on, for choosing how to watch it.
+//!
+//! Native file events (inotify, FSEvents) only see changes made through
+//! the local kernel. On a network share, or a Windows drive mounted into
+//! WSL, changes made on the other side never arrive, and the watcher fails
+//! without an error. [`native_events_unreliable`] tells such filesystems
+//! apart so the watcher can poll them instead.
+//!
+//! The mount table comes from `/proc/self/mounts` on Linux and from the
+//! output of `mount` on macOS. On Windows only UNC paths are recognised.
+
+use std::path::{Path, PathBuf};
+
+/// Filesystem types whose changes native events miss.
+const UNRELIABLE_FS_TYPES: [&str; 13] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afpfs",
+    "webdav",
+    "davfs",
+    // WSL: Windows drives under /mnt (WSL 2 and WSL 1).
+    "9p",
+    "drvfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "fuse.gvfsd-fuse",
+];
+
+/// One line of the mount table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub path: PathBuf,
+    pub fs_type: String,
+}
+
+/// Undo the octal escapes (`\040` for a space) of `/proc/self/mounts`.
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escape = bytes.get(index + 1..index + 4).filter(|digits| {
+            bytes[index] == b'\\' && digits.iter().all(|d| (b'0'..=b'7').contains(d))
+        });
+        match escape {
+            Some(digits) => {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |value, d| value * 8 + u32::from(d - b'0'));
+                out.push(value as u8);
+                index += 4;
+            }
+            None => {
+                out.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parse `/proc/self/mounts`: device, mount point, type, options, ...
+pub fn parse_proc_mounts(text: &str) -> Vec<Mount> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let path = unescape_octal(fields.next()?);
+            let fs_type = fields.next()?.to_string();
+            Some(Mount {
+                path: PathBuf::from(path),
+                fs_type,
+            })
+        })
+        .collect()
+}
+
+/// Parse the output of BSD `mount`: `device on /path (type, options)`.
+pub fn parse_mount_output(text: &str) -> Vec<Mount> {
+    text.lines()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once(" on ")?;
+            let (path, details) = rest.rsplit_once(" (")?;
+            let fs_type = details.split([',', ')']).next()?.trim();
+            Some(Mount {
+                path: PathBuf::from(path),
+                fs_type: fs_type.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The mount `path` is on: the one with the longest mount point above it.
+pub fn mount_for<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.path))
+        .max_by_key(|mount| mount.path.components().count())
+}
+
+/// Whether changes on a filesystem of `fs_type` can go unreported by
+/// native events.
+pub fn native_events_unreliable(fs_type: &str) -> bool {
+    UNRELIABLE_FS_TYPES.contains(&fs_type)
+}
+
+fn mount_table() -> Vec<Mount> {
+    if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/proc/self/mounts")
+            .map(|text| parse_proc_mounts(&text))
+            .unwrap_or_default()
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("/sbin/mount")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| parse_mount_output(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Type of the filesystem `path` is on, when it can be told.
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if cfg!(windows) {
+        let text = path.to_string_lossy();
+        // Canonical paths of network drives are `\\?\UNC\server\share`.
+        let unc = text.starts_with(r"\\?\UNC\")
+            || (!text.starts_with(r"\\?\") && text.starts_with(r"\\"));
+        return unc.then(|| "smbfs".to_string());
+    }
+    mount_for(&mount_table(), &path).map(|mount| mount.fs_type.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proc_mounts_longest_prefix() {
+        let mounts = parse_proc_mounts(
+            "/dev/sda1 / ext4 rw,relatime 0 0\n\
+             C:\\134 /mnt/c 9p rw,noatime,aname=drvfs 0 0\n\
+             server:/export /home/me/My\\040Share nfs4 rw 0 0\n",
+        );
+        assert_eq!(mounts[2].path, PathBuf::from("/home/me/My Share"));
+
+        let on = |path: &str| mount_for(&mounts, Path::new(path)).map(|m| m.fs_type.as_str());
+        assert_eq!(on("/mnt/c/Users/me/project"), Some("9p"));
+        assert_eq!(on("/home/me/My Share/repo"), Some("nfs4"));
+        assert_eq!(on("/home/me/code"), Some("ext4"));
+        // A sibling whose name only starts the same is not under it.
+        assert_eq!(on("/mnt/cache"), Some("ext4"));
+    }
+
+    #[test]
+    fn test_mount_output_and_unreliable_types() {
+        let mounts = parse_mount_output(
+            "/dev/disk3s1s1 on / (apfs, sealed, local, read-only, journaled)\n\
+             //me@nas.local/code on /Volumes/code (smbfs, nodev, nosuid, mounted by me)\n",
+        );
+        let share = mount_for(&mounts, Path::new("/Volumes/code/app")).unwrap();
+        assert_eq!(share.fs_type, "smbfs");
+        assert!(native_events_unreliable(&share.fs_type));
+        assert!(!native_events_unreliable(&mounts[0].fs_type));
+    }
+}
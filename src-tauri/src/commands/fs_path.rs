@@ -0,0 +1,660 @@
+//! Input path normalization shared by every filesystem-touching command.
+//!
+//! Paths arrive over IPC exactly as the frontend (or the user) typed them:
+//! `~/src/app`, `C:/Users/me/project`, `\\nas\share\repo`. Handing those
+//! straight to `std::fs` works on macOS but fails on Windows in two common
+//! cases:
+//!
+//! - Deep `node_modules` trees exceed `MAX_PATH` (260 chars) and come back
+//!   as `os error 3` ("The system cannot find the path specified").
+//! - UNC network shares need the `\\?\UNC\` form to escape the same limit.
+//!
+//! [`normalize_input_path`] expands `~`, unifies separators, and adds the
+//! extended-length prefix when it is needed. [`display_path`] strips that
+//! prefix again so it never leaks back into the paths the UI shows.
+//!
+//! On top of that, [`input_path`] rejects paths no file can have (empty,
+//! or with a NUL byte), [`resolve_within`] keeps a path inside a root such
+//! as the project, and [`is_within`] / [`strip_root`] compare paths in
+//! Unicode NFC. macOS hands back decomposed (NFD) file names from some
+//! APIs while typed and pasted paths are composed, so the same file can
+//! arrive spelled two ways; byte comparison would call them different.
+//!
+//! A path need not be Unicode: Linux file names are any bytes and Windows
+//! ones may hold unpaired UTF-16 surrogates. Those can't go to the
+//! frontend as JSON strings unchanged, and `to_string_lossy` would send a
+//! name that no longer opens the file. [`path_to_wire`] instead maps each
+//! undecodable byte or surrogate to a code point of its own in the last
+//! private use block, U+10F800 to U+10FFFF, and [`normalize_input_path`]
+//! maps them back, so the frontend can hand any path it was given back to
+//! a command. A name that really contains one of those code points is
+//! escaped as its bytes or surrogates, so every path has one string form.
+//! Paths that are valid Unicode, the usual case, are sent unchanged.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+use super::error::{ErrorCode, KiriError};
+
+/// Longest path Win32 APIs accept without the `\\?\` prefix. `MAX_PATH` is
+/// 260 including the terminating NUL.
+const WINDOWS_MAX_PATH: usize = 259;
+
+const VERBATIM_PREFIX: &str = r"\\?\";
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// First code point [`path_to_wire`] maps undecodable bytes or surrogates
+/// to. Bytes 0x80 to 0xFF use U+10FF80 to U+10FFFF; surrogates 0xD800 to
+/// 0xDFFF use U+10F800 to U+10FFFF.
+const WIRE_BYTE_BASE: u32 = 0x10FF00;
+const WIRE_SURROGATE_BASE: u32 = 0x10F800;
+
+fn wire_byte(c: char) -> Option<u8> {
+    (c as u32)
+        .checked_sub(WIRE_BYTE_BASE)
+        .filter(|offset| *offset >= 0x80)
+        .map(|offset| offset as u8)
+}
+
+fn wire_surrogate(c: char) -> Option<u16> {
+    (c as u32)
+        .checked_sub(WIRE_SURROGATE_BASE)
+        .map(|offset| 0xD800 + offset as u16)
+}
+
+fn push_wire_byte(out: &mut String, byte: u8) {
+    out.extend(char::from_u32(WIRE_BYTE_BASE + u32::from(byte)));
+}
+
+fn push_wire_surrogate(out: &mut String, unit: u16) {
+    out.extend(char::from_u32(
+        WIRE_SURROGATE_BASE + u32::from(unit - 0xD800),
+    ));
+}
+
+/// Wire form of a byte string such as a Unix file name or a path from git:
+/// its UTF-8 text, with each byte that doesn't decode mapped to a code
+/// point of its own.
+pub fn bytes_to_wire(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (text, invalid) = match std::str::from_utf8(rest) {
+            Ok(text) => (text, &[][..]),
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                let len = e.error_len().unwrap_or(invalid.len());
+                // Checked by from_utf8 up to valid_up_to.
+                let text = std::str::from_utf8(valid).unwrap_or_default();
+                (text, &invalid[..len])
+            }
+        };
+        for c in text.chars() {
+            if wire_byte(c).is_some() {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    push_wire_byte(&mut out, byte);
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        for byte in invalid {
+            push_wire_byte(&mut out, *byte);
+        }
+        rest = &rest[text.len() + invalid.len()..];
+    }
+    out
+}
+
+/// The bytes [`bytes_to_wire`] encoded.
+pub fn bytes_from_wire(wire: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(wire.len());
+    for c in wire.chars() {
+        match wire_byte(c) {
+            Some(byte) => out.push(byte),
+            None => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    out
+}
+
+/// Wire form of a UTF-16 string such as a Windows file name, with each
+/// unpaired surrogate mapped to a code point of its own.
+pub fn wide_to_wire(units: impl IntoIterator<Item = u16>) -> String {
+    let mut out = String::new();
+    for decoded in char::decode_utf16(units) {
+        match decoded {
+            Ok(c) if wire_surrogate(c).is_some() => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    push_wire_surrogate(&mut out, *unit);
+                }
+            }
+            Ok(c) => out.push(c),
+            Err(e) => push_wire_surrogate(&mut out, e.unpaired_surrogate()),
+        }
+    }
+    out
+}
+
+/// The UTF-16 units [`wide_to_wire`] encoded.
+pub fn wide_from_wire(wire: &str) -> Vec<u16> {
+    let mut out = Vec::with_capacity(wire.len());
+    for c in wire.chars() {
+        match wire_surrogate(c) {
+            Some(unit) => out.push(unit),
+            None => out.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+        }
+    }
+    out
+}
+
+/// A path git reports as bytes. Git keeps them as the file system does on
+/// Unix and writes UTF-8 elsewhere.
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// `name` as a string for the frontend, losslessly; see the module docs.
+pub fn os_str_to_wire(name: &OsStr) -> String {
+    if let Some(text) = name.to_str() {
+        // Nearly every name: nothing to escape unless it uses the block.
+        if !text.chars().any(|c| wire_surrogate(c).is_some()) {
+            return text.to_string();
+        }
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        bytes_to_wire(name.as_bytes())
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        wide_to_wire(name.encode_wide())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        name.to_string_lossy().into_owned()
+    }
+}
+
+/// The name [`os_str_to_wire`] encoded.
+pub fn os_string_from_wire(wire: &str) -> OsString {
+    if !wire.chars().any(|c| wire_surrogate(c).is_some()) {
+        return OsString::from(wire);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        OsString::from_vec(bytes_from_wire(wire))
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        OsString::from_wide(&wide_from_wire(wire))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        OsString::from(wire)
+    }
+}
+
+/// `path` as a string for the frontend, losslessly. Use [`display_path`]
+/// for paths that may carry an extended-length prefix.
+pub fn path_to_wire(path: &Path) -> String {
+    os_str_to_wire(path.as_os_str())
+}
+
+/// The path [`path_to_wire`] encoded.
+pub fn path_from_wire(wire: &str) -> PathBuf {
+    PathBuf::from(os_string_from_wire(wire))
+}
+
+/// Serde adapter for `PathBuf` fields sent to or read from the frontend,
+/// for `#[serde(with = "wire_path")]`.
+pub mod wire_path {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::path::{Path, PathBuf};
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::display_path(path))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        let wire = String::deserialize(deserializer)?;
+        Ok(super::normalize_input_path(&wire))
+    }
+}
+
+/// Expand a leading `~` (alone, or followed by a separator) to the user's
+/// home directory. `~user` forms are left untouched. `input` is in wire
+/// form; see [`path_to_wire`].
+pub fn expand_tilde(input: &str) -> PathBuf {
+    let rest = match input.strip_prefix('~') {
+        Some(rest) => rest,
+        None => return path_from_wire(input),
+    };
+    if !(rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\')) {
+        return path_from_wire(input);
+    }
+    match dirs::home_dir() {
+        Some(home) => {
+            let rest = rest.trim_start_matches(['/', '\\']);
+            if rest.is_empty() {
+                home
+            } else {
+                home.join(path_from_wire(rest))
+            }
+        }
+        None => path_from_wire(input),
+    }
+}
+
+/// Rewrite an absolute Windows path into its extended-length form.
+///
+/// Returns `None` when the path is already verbatim, relative, or contains
+/// `..` (the `\\?\` form disables lexical `..` resolution, so prefixing it
+/// would change what the path points to). `.` components and forward
+/// slashes are folded away because verbatim paths are passed to the kernel
+/// as-is.
+///
+/// Pure string logic so it can be unit-tested on every platform.
+pub fn extended_length_form(path: &str) -> Option<String> {
+    if path.starts_with(VERBATIM_PREFIX) || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let unified = path.replace('/', "\\");
+
+    let (prefix, rest) = if let Some(unc) = unified.strip_prefix(r"\\") {
+        (VERBATIM_UNC_PREFIX.to_string(), unc.to_string())
+    } else if is_drive_absolute(&unified) {
+        (VERBATIM_PREFIX.to_string(), unified)
+    } else {
+        return None;
+    };
+
+    let mut parts: Vec<&str> = Vec::new();
+    for part in rest.split('\\') {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            other => parts.push(other),
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    let mut out = prefix;
+    out.push_str(&parts.join("\\"));
+    // `C:` alone is drive-relative; the root needs its trailing separator.
+    if parts.len() == 1 && is_drive_absolute(&format!("{}\\", parts[0])) {
+        out.push('\\');
+    }
+    Some(out)
+}
+
+/// `C:\...` — a drive letter followed by a colon and a separator.
+fn is_drive_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\'
+}
+
+/// Whether a Windows path needs the extended-length prefix: it is too long
+/// for the legacy API limit, or it points at a UNC share.
+pub fn needs_extended_length(path: &str) -> bool {
+    path.len() > WINDOWS_MAX_PATH || path.starts_with(r"\\") || path.starts_with("//")
+}
+
+/// Normalize a path received over IPC before handing it to `std::fs`.
+///
+/// On every platform a leading `~` is expanded. On Windows, forward
+/// slashes become backslashes and long or UNC paths gain the `\\?\`
+/// prefix. Other Unix paths are returned unchanged — a backslash is a
+/// legal filename character there and must not be rewritten.
+pub fn normalize_input_path(input: &str) -> PathBuf {
+    let expanded = expand_tilde(input);
+    if cfg!(windows) {
+        // Rewritten in wire form, so unpaired surrogates survive.
+        let raw = path_to_wire(&expanded);
+        if needs_extended_length(&raw) {
+            if let Some(verbatim) = extended_length_form(&raw) {
+                return path_from_wire(&verbatim);
+            }
+        }
+        return path_from_wire(&raw.replace('/', "\\"));
+    }
+    expanded
+}
+
+/// [`normalize_input_path`] for paths that must name a file: fails for an
+/// empty path or one with a NUL byte, which would otherwise reach `std::fs`
+/// or a child process and fail there with a less useful message.
+pub fn input_path(input: &str) -> Result<PathBuf, String> {
+    if input.trim().is_empty() {
+        return Err("Invalid path: the path is empty".to_string());
+    }
+    if input.contains('\0') {
+        return Err(format!(
+            "Invalid path: {} contains a NUL byte",
+            input.replace('\0', "\\0")
+        ));
+    }
+    Ok(normalize_input_path(input))
+}
+
+/// `path` in Unicode NFC, for comparing. Keep the original for filesystem
+/// calls: on Linux the two spellings are different file names.
+pub fn nfc_path(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(text) => PathBuf::from(text.nfc().collect::<String>()),
+        None => path.to_path_buf(),
+    }
+}
+
+/// What is left of `path` below `root`, comparing components in NFC.
+/// `None` when `path` is neither `root` nor inside it.
+pub fn strip_root(path: &Path, root: &Path) -> Option<PathBuf> {
+    let mut rest = path.components();
+    for root_part in root.components() {
+        let part = rest.next()?;
+        if nfc_path(Path::new(part.as_os_str())) != nfc_path(Path::new(root_part.as_os_str())) {
+            return None;
+        }
+    }
+    Some(rest.as_path().to_path_buf())
+}
+
+/// Whether `path` is `root` or inside it, comparing in NFC.
+pub fn is_within(path: &Path, root: &Path) -> bool {
+    strip_root(path, root).is_some()
+}
+
+/// Resolve `.` and `..` without touching the filesystem. `None` when a
+/// `..` would climb above the root or the start of a relative path.
+pub fn lexical_normalize(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if depth == 0 {
+                    return None;
+                }
+                out.pop();
+                depth -= 1;
+            }
+            Component::Normal(part) => {
+                out.push(part);
+                depth += 1;
+            }
+            Component::Prefix(_) | Component::RootDir => out.push(component.as_os_str()),
+        }
+    }
+    Some(out)
+}
+
+/// `path` with its longest existing ancestor canonicalized and the rest,
+/// which does not exist yet, appended unchanged.
+fn canonicalize_existing(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |acc, part| acc.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Resolve `input`, absolute or relative to `root`, and require the result
+/// to stay inside `root`. Catches `..` tricks such as `src/../../etc` and
+/// symlinks inside `root` that point out of it; a tail that does not exist
+/// yet (a file about to be created) is resolved lexically. Use the
+/// returned path, not `input`, for the filesystem call. Escaping `root`
+/// fails with `permission_denied`.
+pub fn resolve_within(input: &str, root: &Path) -> Result<PathBuf, KiriError> {
+    let outside = || {
+        KiriError::new(
+            ErrorCode::PermissionDenied,
+            format!("{} is outside {}", input, display_path(root)),
+        )
+    };
+    let path = input_path(input)?;
+    let joined = if path.is_absolute() {
+        path
+    } else {
+        root.join(path)
+    };
+    let lexical = lexical_normalize(&joined).ok_or_else(outside)?;
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", display_path(root), e))?;
+    let resolved = canonicalize_existing(&lexical);
+    if !is_within(&resolved, &root) {
+        return Err(outside());
+    }
+    Ok(resolved)
+}
+
+/// Render a path for the frontend in wire form, stripping any
+/// extended-length prefix that [`normalize_input_path`] added.
+pub fn display_path(path: &Path) -> String {
+    strip_verbatim_prefix(&path_to_wire(path))
+}
+
+/// String form of [`display_path`]: `\\?\UNC\server\share` becomes
+/// `\\server\share` and `\\?\C:\dir` becomes `C:\dir`.
+pub fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(unc) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        format!(r"\\{}", unc)
+    } else if let Some(local) = path.strip_prefix(VERBATIM_PREFIX) {
+        local.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_tilde_alone() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_tilde("~"), home);
+    }
+
+    #[test]
+    fn test_expand_tilde_with_subpath() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_tilde("~/projects/kiri"), home.join("projects/kiri"));
+    }
+
+    #[test]
+    fn test_expand_tilde_leaves_user_form_alone() {
+        assert_eq!(expand_tilde("~alice/x"), PathBuf::from("~alice/x"));
+    }
+
+    #[test]
+    fn test_expand_tilde_leaves_plain_paths_alone() {
+        assert_eq!(expand_tilde("/tmp/a~b"), PathBuf::from("/tmp/a~b"));
+    }
+
+    #[test]
+    fn test_extended_length_form_drive_path() {
+        assert_eq!(
+            extended_length_form(r"C:\Users\me\project").as_deref(),
+            Some(r"\\?\C:\Users\me\project")
+        );
+    }
+
+    #[test]
+    fn test_extended_length_form_unifies_forward_slashes() {
+        assert_eq!(
+            extended_length_form("C:/Users/me/./project/").as_deref(),
+            Some(r"\\?\C:\Users\me\project")
+        );
+    }
+
+    #[test]
+    fn test_extended_length_form_unc_share() {
+        assert_eq!(
+            extended_length_form(r"\\nas\share\repo").as_deref(),
+            Some(r"\\?\UNC\nas\share\repo")
+        );
+    }
+
+    #[test]
+    fn test_extended_length_form_drive_root_keeps_separator() {
+        assert_eq!(extended_length_form(r"C:\").as_deref(), Some(r"\\?\C:\"));
+    }
+
+    #[test]
+    fn test_extended_length_form_rejects_parent_components() {
+        assert_eq!(extended_length_form(r"C:\a\..\b"), None);
+    }
+
+    #[test]
+    fn test_extended_length_form_rejects_relative_and_verbatim() {
+        assert_eq!(extended_length_form(r"src\main.rs"), None);
+        assert_eq!(extended_length_form(r"\\?\C:\already"), None);
+    }
+
+    #[test]
+    fn test_needs_extended_length() {
+        assert!(!needs_extended_length(r"C:\short"));
+        assert!(needs_extended_length(r"\\server\share"));
+        let deep = format!(r"C:\{}", "node_modules\\".repeat(30));
+        assert!(needs_extended_length(&deep));
+    }
+
+    #[test]
+    fn test_strip_verbatim_prefix_round_trip() {
+        assert_eq!(strip_verbatim_prefix(r"\\?\C:\dir"), r"C:\dir");
+        assert_eq!(strip_verbatim_prefix(r"\\?\UNC\nas\share"), r"\\nas\share");
+        assert_eq!(strip_verbatim_prefix("/usr/local"), "/usr/local");
+    }
+
+    #[test]
+    fn test_input_path_rejects_empty_and_nul() {
+        assert!(input_path("").unwrap_err().starts_with("Invalid path"));
+        assert!(input_path("/tmp/a\0b").unwrap_err().contains("NUL byte"));
+        assert_eq!(
+            input_path("/tmp/a").unwrap(),
+            normalize_input_path("/tmp/a")
+        );
+    }
+
+    #[test]
+    fn test_is_within_compares_nfc() {
+        // "café" composed vs. decomposed, as HFS+ reports it.
+        let composed = Path::new("/Users/me/caf\u{e9}");
+        let decomposed = Path::new("/Users/me/cafe\u{301}/src/main.rs");
+        assert!(is_within(decomposed, composed));
+        assert_eq!(
+            strip_root(decomposed, composed),
+            Some(PathBuf::from("src/main.rs"))
+        );
+        assert!(!is_within(Path::new("/Users/me/cafeteria"), composed));
+    }
+
+    #[test]
+    fn test_lexical_normalize() {
+        assert_eq!(
+            lexical_normalize(Path::new("/repo/./src/../lib")),
+            Some(PathBuf::from("/repo/lib"))
+        );
+        assert_eq!(lexical_normalize(Path::new("/repo/../..")), None);
+        assert_eq!(lexical_normalize(Path::new("../x")), None);
+    }
+
+    #[test]
+    fn test_resolve_within() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("src")).unwrap();
+        let canonical = root.canonicalize().unwrap();
+
+        assert_eq!(
+            resolve_within("src/new/file.rs", root).unwrap(),
+            canonical.join("src/new/file.rs")
+        );
+        assert_eq!(
+            resolve_within("src/../../etc/passwd", root)
+                .unwrap_err()
+                .code,
+            ErrorCode::PermissionDenied
+        );
+        assert!(resolve_within("/etc/passwd", root).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc", root.join("link")).unwrap();
+            assert!(resolve_within("link/passwd", root).is_err());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_normalize_input_path_keeps_unix_backslashes() {
+        assert_eq!(
+            normalize_input_path(r"/tmp/odd\name"),
+            PathBuf::from(r"/tmp/odd\name")
+        );
+    }
+
+    #[test]
+    fn test_wire_form_round_trips_bytes_and_surrogates() {
+        assert_eq!(bytes_to_wire("src/café.rs".as_bytes()), "src/café.rs");
+
+        // Latin-1 "café", a truncated sequence, and a real U+10FFFF.
+        let bytes = b"caf\xe9/\xe2\x82/\xf4\x8f\xbf\xbf";
+        let wire = bytes_to_wire(bytes);
+        assert!(wire.starts_with("caf") && !wire.contains('\u{fffd}'));
+        assert_eq!(bytes_from_wire(&wire), bytes);
+
+        // An unpaired high surrogate, and a real U+10FFFF as a pair.
+        let units = [0x61, 0xD800, 0x62, 0xDBFF, 0xDFFF];
+        let wire = wide_to_wire(units);
+        assert_eq!(wire.chars().count(), 5);
+        assert_eq!(wide_from_wire(&wire), units);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_file_name_round_trips_through_input_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(OsStr::from_bytes(b"report-\xff.txt"));
+        if std::fs::write(&file, "x").is_err() {
+            // The file system insists on UTF-8 names (e.g. APFS).
+            return;
+        }
+        let wire = display_path(&file);
+        assert_eq!(input_path(&wire).unwrap(), file);
+        assert_eq!(
+            os_str_to_wire(file.file_name().unwrap()),
+            "report-\u{10ffff}.txt"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Which files are generated or vendored rather than written by hand.
+//!
+//! Lockfiles, build output, minified bundles, source maps and vendored
+//! dependencies change in bulk and are rarely read. Content search and the
+//! TODO scanner skip them, file search doesn't descend into their
+//! directories, and diffs flag them so the diff view starts them
+//! collapsed. A project adjusts the built-in rules with the `[generated]`
+//! table of `.kiri.toml`:
+//!
+//! ```toml
+//! [generated]
+//! paths = ["src/gen", "*.pb.go"]   # generated as well
+//! exclude = ["build"]              # never generated
+//! ```
+//!
+//! Patterns are globs relative to the directory of `.kiri.toml`, and one
+//! without a `/` matches a name at any depth, as in `.gitignore`. A
+//! pattern matching a directory covers everything in it.
+
+use glob::{MatchOptions, Pattern};
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+
+use super::project_config::load_config;
+
+/// Lockfiles of package managers, by file name.
+const LOCKFILES: [&str; 18] = [
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lock",
+    "bun.lockb",
+    "deno.lock",
+    "Cargo.lock",
+    "go.sum",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "Podfile.lock",
+    "pubspec.lock",
+    "mix.lock",
+    "flake.lock",
+];
+
+/// Directories build tools write their output to.
+const BUILD_DIRS: [&str; 8] = [
+    "dist",
+    "build",
+    "target",
+    ".next",
+    ".nuxt",
+    ".svelte-kit",
+    ".output",
+    "__pycache__",
+];
+
+/// Directories of third-party code.
+const VENDOR_DIRS: [&str; 5] = [
+    "node_modules",
+    "vendor",
+    "third_party",
+    "bower_components",
+    "Pods",
+];
+
+/// Extensions of the files source maps are written for, as in `app.js.map`.
+const MAPPED_EXTENSIONS: [&str; 5] = ["js", "mjs", "cjs", "css", "ts"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GeneratedKind {
+    Lockfile,
+    /// In a build output directory such as `dist/` or `target/`.
+    BuildOutput,
+    /// A `*.min.*` bundle.
+    Minified,
+    SourceMap,
+    /// In a directory of third-party code such as `node_modules/`.
+    Vendored,
+    /// Matched by `generated.paths` in `.kiri.toml`.
+    Configured,
+}
+
+/// A pattern of `.kiri.toml`, and whether it is matched against names.
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    by_name: bool,
+}
+
+impl Rule {
+    fn parse(pattern: &str) -> Option<Self> {
+        // `dir/**` and `dir` cover the same files.
+        let pattern = pattern.strip_suffix("/**").unwrap_or(pattern);
+        let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+        Some(Rule {
+            pattern: Pattern::new(pattern).ok()?,
+            by_name: !pattern.contains('/'),
+        })
+    }
+
+    /// Whether the rule matches `names`, the components of a relative
+    /// path, or a directory above it.
+    fn matches(&self, names: &[&str]) -> bool {
+        (1..=names.len()).any(|end| {
+            let candidate = if self.by_name {
+                names[end - 1].to_string()
+            } else {
+                names[..end].join("/")
+            };
+            self.pattern.matches_with(&candidate, MATCH_OPTIONS)
+        })
+    }
+}
+
+/// The built-in rules plus those of a project's `.kiri.toml`.
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedRules {
+    /// What paths are classified relative to: the directory of
+    /// `.kiri.toml`, or the directory the rules were loaded for.
+    base: PathBuf,
+    paths: Vec<Rule>,
+    exclude: Vec<Rule>,
+}
+
+fn parse_rules(patterns: &[String]) -> Vec<Rule> {
+    patterns
+        .iter()
+        .filter_map(|pattern| Rule::parse(pattern))
+        .collect()
+}
+
+impl GeneratedRules {
+    pub fn new(base: PathBuf, paths: &[String], exclude: &[String]) -> Self {
+        Self {
+            base,
+            paths: parse_rules(paths),
+            exclude: parse_rules(exclude),
+        }
+    }
+
+    /// The rules for files under `dir`, with the `.kiri.toml` that applies
+    /// to it.
+    pub fn load(dir: &Path) -> Self {
+        let load = load_config(dir);
+        let base = load
+            .path
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+            .unwrap_or(dir)
+            .to_path_buf();
+        Self::new(
+            base,
+            &load.config.generated_paths,
+            &load.config.generated_exclude,
+        )
+    }
+
+    /// What kind of generated file or directory `path` is, if it is one.
+    /// `path` is absolute, or relative to the directory the rules are for.
+    pub fn classify(&self, path: &Path, is_dir: bool) -> Option<GeneratedKind> {
+        let rel = path.strip_prefix(&self.base).unwrap_or(path);
+        let names: Vec<&str> = rel
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        let (&name, parents) = names.split_last()?;
+        if self.exclude.iter().any(|rule| rule.matches(&names)) {
+            return None;
+        }
+        if self.paths.iter().any(|rule| rule.matches(&names)) {
+            return Some(GeneratedKind::Configured);
+        }
+
+        let dirs = if is_dir { &names[..] } else { parents };
+        for dir in dirs {
+            if VENDOR_DIRS.contains(dir) {
+                return Some(GeneratedKind::Vendored);
+            }
+            if BUILD_DIRS.contains(dir) {
+                return Some(GeneratedKind::BuildOutput);
+            }
+        }
+        if is_dir {
+            return None;
+        }
+        if LOCKFILES.contains(&name) {
+            return Some(GeneratedKind::Lockfile);
+        }
+        let mapped = name.strip_suffix(".map").map(Path::new);
+        let mapped_ext = mapped
+            .and_then(Path::extension)
+            .and_then(|ext| ext.to_str());
+        if mapped_ext.is_some_and(|ext| MAPPED_EXTENSIONS.contains(&ext)) {
+            return Some(GeneratedKind::SourceMap);
+        }
+        if name.contains(".min.") {
+            return Some(GeneratedKind::Minified);
+        }
+        None
+    }
+
+    /// Whether `path` is generated; see [`GeneratedRules::classify`].
+    pub fn is_generated(&self, path: &Path, is_dir: bool) -> bool {
+        self.classify(path, is_dir).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn classify(rules: &GeneratedRules, path: &str) -> Option<GeneratedKind> {
+        rules.classify(Path::new(path), false)
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let rules = GeneratedRules::new(PathBuf::from("/src/app"), &[], &[]);
+        assert_eq!(
+            classify(&rules, "/src/app/pnpm-lock.yaml"),
+            Some(GeneratedKind::Lockfile)
+        );
+        assert_eq!(
+            classify(&rules, "/src/app/web/dist/index.html"),
+            Some(GeneratedKind::BuildOutput)
+        );
+        assert_eq!(
+            classify(&rules, "/src/app/public/jquery.min.js"),
+            Some(GeneratedKind::Minified)
+        );
+        assert_eq!(
+            classify(&rules, "/src/app/public/app.js.map"),
+            Some(GeneratedKind::SourceMap)
+        );
+        assert_eq!(
+            classify(&rules, "vendor/github.com/pkg/errors/errors.go"),
+            Some(GeneratedKind::Vendored)
+        );
+        assert_eq!(
+            rules.classify(Path::new("/src/app/node_modules"), true),
+            Some(GeneratedKind::Vendored)
+        );
+        assert_eq!(classify(&rules, "/src/app/src/build.rs"), None);
+        assert_eq!(classify(&rules, "/src/app/src/sitemap.map"), None);
+        assert_eq!(classify(&rules, "/src/app/src/main.ts"), None);
+        // Directories above the project don't count.
+        let nested = GeneratedRules::new(PathBuf::from("/work/build/app"), &[], &[]);
+        assert_eq!(classify(&nested, "/work/build/app/src/main.ts"), None);
+    }
+
+    #[test]
+    fn test_project_rules_from_kiri_toml() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("web/src")).unwrap();
+        std::fs::write(
+            root.join(".kiri.toml"),
+            "[generated]\npaths = ['src/gen/**', '*.pb.go']\nexclude = ['build']\n",
+        )
+        .unwrap();
+
+        let rules = GeneratedRules::load(&root.join("web/src"));
+        let at = |rel: &str| rules.classify(&root.join(rel), false);
+        assert_eq!(at("src/gen/api.ts"), Some(GeneratedKind::Configured));
+        assert_eq!(at("api/v1/user.pb.go"), Some(GeneratedKind::Configured));
+        assert_eq!(at("web/src/gen/api.ts"), None);
+        assert_eq!(at("build/release.sh"), None);
+        assert!(!rules.is_generated(&root.join("build"), true));
+        assert_eq!(at("dist/app.js"), Some(GeneratedKind::BuildOutput));
+    }
+}
@@ -0,0 +1,58 @@
+//! Tauri command for the generated-file classification of generated.rs.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::error::{ErrorCode, KiriError};
+use super::fs_path::input_path;
+use super::generated::{GeneratedKind, GeneratedRules};
+use super::git_worktree::current_worktree_root;
+use super::latency::run_blocking;
+
+/// Ceiling on paths per call.
+const MAX_CLASSIFY_PATHS: usize = 10_000;
+
+fn classify_paths_blocking(
+    paths: Vec<String>,
+) -> Result<HashMap<String, Option<GeneratedKind>>, String> {
+    // Paths of one worktree share its `.kiri.toml`.
+    let mut rules: HashMap<PathBuf, GeneratedRules> = HashMap::new();
+    let mut kinds = HashMap::with_capacity(paths.len());
+    for path in paths {
+        let resolved = input_path(&path)?;
+        let dir = if resolved.is_dir() {
+            resolved.clone()
+        } else {
+            resolved.parent().unwrap_or(Path::new("")).to_path_buf()
+        };
+        let root = current_worktree_root(&dir).unwrap_or(dir);
+        let kind = rules
+            .entry(root)
+            .or_insert_with_key(|root| GeneratedRules::load(root))
+            .classify(&resolved, resolved.is_dir());
+        kinds.insert(path, kind);
+    }
+    Ok(kinds)
+}
+
+/// Which of `paths` are generated, vendored or build output, keyed by the
+/// path as given. Each path follows the rules of its worktree's
+/// `.kiri.toml`.
+#[tauri::command]
+pub async fn classify_generated_paths(
+    paths: Vec<String>,
+) -> Result<HashMap<String, Option<GeneratedKind>>, KiriError> {
+    if paths.len() > MAX_CLASSIFY_PATHS {
+        return Err(KiriError::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "At most {} paths can be classified at once",
+                MAX_CLASSIFY_PATHS
+            ),
+        ));
+    }
+    run_blocking("classify_generated_paths", move || {
+        classify_paths_blocking(paths)
+    })
+    .await
+}
@@ -1,6 +1,19 @@
 use git2::{Diff, DiffOptions, Repository, StatusOptions};
 use serde::Serialize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use super::error::KiriError;
+use super::fs_budget::{run_budgeted, GIT_STATUS_BUDGET};
+use super::fs_path::{
+    bytes_to_wire, display_path, input_path, normalize_input_path, path_from_bytes, path_from_wire,
+    strip_root,
+};
+use super::generated::{GeneratedKind, GeneratedRules};
+use super::git_cache::{
+    begin_fill, cached_diffs, cached_status, git_cache, store_diffs, store_status,
+};
+use super::latency::{run_blocking, run_blocking_coded};
+use super::file_lines::{count_text_lines, prefix_lines};
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum GitFileStatus {
@@ -29,6 +42,22 @@ pub struct GitFileDiff {
     pub current_content_base64: Option<String>,
     /// Base64 encoded original file content from HEAD (for binary/image files)
     pub original_content_base64: Option<String>,
+    /// Set for lockfiles, build output and the like, which the diff view
+    /// starts collapsed
+    pub generated: Option<GeneratedKind>,
+}
+
+/// Line counts of one changed file, without its hunks
+#[derive(Debug, Clone, Serialize)]
+pub struct GitFileStat {
+    pub path: String,
+    /// Path before a rename
+    pub old_path: Option<String>,
+    pub status: GitFileStatus,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub is_binary: bool,
+    pub generated: Option<GeneratedKind>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -40,11 +69,11 @@ pub struct GitRepoInfo {
     pub deletions: usize,
 }
 
-fn find_repo_root(path: &Path) -> Option<String> {
+fn find_repo_root(path: &Path) -> Option<PathBuf> {
     let mut current = path;
     loop {
         if current.join(".git").exists() {
-            return Some(current.to_string_lossy().to_string());
+            return Some(current.to_path_buf());
         }
         match current.parent() {
             Some(parent) => current = parent,
@@ -54,7 +83,8 @@ fn find_repo_root(path: &Path) -> Option<String> {
 }
 
 /// Calculate total additions and deletions for the repository
-fn calculate_diff_stats(repo: &Repository, repo_root: &str) -> (usize, usize) {
+fn calculate_diff_stats(repo: &Repository, repo_root: &Path) -> (usize, usize) {
+    let _span = tracing::info_span!("git::diff_stats").entered();
     let mut total_additions: usize = 0;
     let mut total_deletions: usize = 0;
 
@@ -71,17 +101,14 @@ fn calculate_diff_stats(repo: &Repository, repo_root: &str) -> (usize, usize) {
     };
 
     for entry in statuses.iter() {
-        let file_path = match entry.path() {
-            Some(p) => p.to_string(),
-            None => continue,
-        };
+        let file_path = path_from_bytes(entry.path_bytes());
         let status = entry.status();
 
         // For untracked files, count all lines as additions
         if status.is_wt_new() {
-            let full_path = Path::new(repo_root).join(&file_path);
-            if let Ok(content) = std::fs::read_to_string(&full_path) {
-                total_additions += content.lines().count();
+            let full_path = repo_root.join(&file_path);
+            if let Ok(lines) = count_text_lines(&full_path) {
+                total_additions += lines;
             }
             continue;
         }
@@ -114,15 +141,21 @@ fn calculate_diff_stats(repo: &Repository, repo_root: &str) -> (usize, usize) {
     (total_additions, total_deletions)
 }
 
-#[tauri::command]
-pub fn get_git_status(path: String) -> Result<GitRepoInfo, String> {
-    let path = Path::new(&path);
+pub(crate) fn get_git_status_blocking(path: String) -> Result<GitRepoInfo, String> {
+    let _span = tracing::info_span!("git::status", path = %path).entered();
+    let path = input_path(&path)?;
 
     // Find repository root
-    let repo_root = find_repo_root(path).ok_or("Not a git repository")?;
+    let repo_root = find_repo_root(&path).ok_or("Not a git repository")?;
 
     let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
 
+    let cache = git_cache();
+    if let Some(info) = cached_status(cache, &repo_root, &repo) {
+        return Ok(info);
+    }
+    let fill = begin_fill(cache, &repo_root, &repo);
+
     // Get current branch
     let branch = repo
         .head()
@@ -140,7 +173,7 @@ pub fn get_git_status(path: String) -> Result<GitRepoInfo, String> {
     let mut entries: Vec<GitStatusEntry> = Vec::new();
 
     for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("").to_string();
+        let path = bytes_to_wire(entry.path_bytes());
         let status = entry.status();
 
         // Status mapping is in git_status_map.rs (excluded from coverage)
@@ -158,58 +191,60 @@ pub fn get_git_status(path: String) -> Result<GitRepoInfo, String> {
     // Calculate diff statistics (additions and deletions)
     let (additions, deletions) = calculate_diff_stats(&repo, &repo_root);
 
-    Ok(GitRepoInfo {
-        root: repo_root,
+    let info = GitRepoInfo {
+        root: display_path(&repo_root),
         branch,
         statuses: entries,
         additions,
         deletions,
-    })
+    };
+    if let Some(fill) = fill {
+        store_status(cache, fill, &info);
+    }
+    Ok(info)
 }
 
 #[tauri::command]
-pub fn get_git_file_status(repo_path: String, file_path: String) -> Result<Option<GitFileStatus>, String> {
-    let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+pub fn get_git_file_status(
+    repo_path: String,
+    file_path: String,
+) -> Result<Option<GitFileStatus>, KiriError> {
+    let repo = Repository::open(input_path(&repo_path)?).map_err(|e| e.to_string())?;
 
-    let relative_path = Path::new(&file_path)
-        .strip_prefix(&repo_path)
-        .map_err(|e| e.to_string())?;
+    // Compared in NFC: on macOS the two can spell the same name differently.
+    let relative_path = strip_root(&path_from_wire(&file_path), &path_from_wire(&repo_path))
+        .ok_or_else(|| format!("{} is not inside {}", file_path, repo_path))?;
 
     let status = repo
-        .status_file(relative_path)
+        .status_file(&relative_path)
         .map_err(|e| e.to_string())?;
 
     // Status mapping is in git_status_map.rs (excluded from coverage)
     Ok(super::git_status_map::map_file_status(status))
 }
 
-#[tauri::command]
-pub fn get_git_diff(repo_path: String, file_path: String) -> Result<String, String> {
-    let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+pub(crate) fn get_git_diff_blocking(repo_path: String, file_path: String) -> Result<String, String> {
+    let _span = tracing::info_span!("git::diff", file = %file_path).entered();
+    let repo_root = input_path(&repo_path)?;
+    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
 
     // Check file status first
-    let relative_path = Path::new(&file_path);
-    let file_status = repo.status_file(relative_path).ok();
+    let relative_path = path_from_wire(&file_path);
+    let file_status = repo.status_file(&relative_path).ok();
 
     // For untracked files, return the entire file content
     if let Some(status) = file_status {
         if status.is_wt_new() {
-            let full_path = Path::new(&repo_path).join(&file_path);
+            let full_path = repo_root.join(&relative_path);
             return std::fs::read_to_string(&full_path)
-                .map(|content| {
-                    content
-                        .lines()
-                        .map(|line| format!("+ {}", line))
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                })
+                .map(|text| prefix_lines(&text, "+ "))
                 .map_err(|e| e.to_string());
         }
     }
 
     // Get diff between HEAD and working directory for the specific file
     let mut diff_opts = DiffOptions::new();
-    diff_opts.pathspec(&file_path);
+    diff_opts.pathspec(&relative_path);
 
     let diff: Diff = repo
         .diff_index_to_workdir(None, Some(&mut diff_opts))
@@ -252,9 +287,16 @@ use super::git_diff::{
     get_current_file_base64, get_file_diff_internal, get_original_file_base64, is_image_file,
 };
 
-#[tauri::command]
-pub fn get_all_git_diffs(repo_path: String) -> Result<Vec<GitFileDiff>, String> {
-    let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+fn get_all_git_diffs_blocking(repo_path: String) -> Result<Vec<GitFileDiff>, String> {
+    let _span = tracing::info_span!("git::all_diffs", repo = %repo_path).entered();
+    let repo_root = input_path(&repo_path)?;
+    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
+
+    let cache = git_cache();
+    if let Some(diffs) = cached_diffs(cache, Path::new(&repo_path), &repo) {
+        return Ok(diffs);
+    }
+    let fill = begin_fill(cache, Path::new(&repo_path), &repo);
 
     // Get status
     let mut opts = StatusOptions::new();
@@ -263,11 +305,13 @@ pub fn get_all_git_diffs(repo_path: String) -> Result<Vec<GitFileDiff>, String>
         .include_ignored(false);
 
     let statuses = repo.statuses(Some(&mut opts)).map_err(|e| e.to_string())?;
+    let generated_rules = GeneratedRules::load(&repo_root);
 
     let mut diffs: Vec<GitFileDiff> = Vec::new();
 
     for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("").to_string();
+        let relative_path = path_from_bytes(entry.path_bytes());
+        let path = bytes_to_wire(entry.path_bytes());
         let status = entry.status();
 
         // Status mapping is in git_status_map.rs (excluded from coverage)
@@ -281,20 +325,21 @@ pub fn get_all_git_diffs(repo_path: String) -> Result<Vec<GitFileDiff>, String>
 
         let (diff, current_content_base64, original_content_base64) = if is_binary {
             // For binary files, get base64 encoded content instead of text diff
-            let current = get_current_file_base64(&repo_path, &path);
+            let current = get_current_file_base64(&repo_root, &relative_path);
             let original = if file_status != GitFileStatus::Untracked {
-                get_original_file_base64(&repo, &path)
+                get_original_file_base64(&repo, &relative_path)
             } else {
                 None
             };
             (String::new(), current, original)
         } else {
             // For text files, get the regular diff
-            let diff = get_file_diff_internal(&repo, &repo_path, &path);
+            let diff = get_file_diff_internal(&repo, &repo_root, &relative_path);
             (diff, None, None)
         };
 
         diffs.push(GitFileDiff {
+            generated: generated_rules.classify(&repo_root.join(&relative_path), false),
             path,
             status: file_status,
             diff,
@@ -307,9 +352,135 @@ pub fn get_all_git_diffs(repo_path: String) -> Result<Vec<GitFileDiff>, String>
     // Sort by path alphabetically
     diffs.sort_by(|a, b| a.path.cmp(&b.path));
 
+    if let Some(fill) = fill {
+        store_diffs(cache, fill, &diffs);
+    }
     Ok(diffs)
 }
 
+/// Per-file insertions and deletions of the working tree, staged and
+/// unstaged, against `base` (a branch, tag or commit) or HEAD without one.
+/// Untracked files count as all insertions; binary files only get the flag.
+pub(crate) fn get_diff_stats_blocking(
+    repo_path: String,
+    base: Option<String>,
+) -> Result<Vec<GitFileStat>, String> {
+    let _span = tracing::info_span!("git::file_stats", repo = %repo_path).entered();
+    let repo_root = input_path(&repo_path)?;
+    let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
+
+    // Without a commit yet, everything is new.
+    let base_tree = match base.as_deref() {
+        Some(base) => Some(
+            repo.revparse_single(base)
+                .and_then(|object| object.peel_to_tree())
+                .map_err(|e| format!("Unknown revision {}: {}", base, e.message()))?,
+        ),
+        None => repo.head().ok().and_then(|head| head.peel_to_tree().ok()),
+    };
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .context_lines(0);
+    let mut diff = repo
+        .diff_tree_to_workdir_with_index(base_tree.as_ref(), Some(&mut diff_opts))
+        .map_err(|e| e.to_string())?;
+    diff.find_similar(None).map_err(|e| e.to_string())?;
+    let generated_rules = GeneratedRules::load(&repo_root);
+
+    let mut stats: Vec<GitFileStat> = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let Some(delta) = diff.get_delta(index) else {
+            continue;
+        };
+        // Status mapping is in git_status_map.rs (excluded from coverage)
+        let Some(status) = super::git_status_map::map_delta(delta.status()) else {
+            continue;
+        };
+        let path_bytes = |file: git2::DiffFile| file.path_bytes().map(bytes_to_wire);
+        let path = path_bytes(delta.new_file())
+            .or_else(|| path_bytes(delta.old_file()))
+            .unwrap_or_default();
+        let old_path = match status {
+            GitFileStatus::Renamed => path_bytes(delta.old_file()),
+            _ => None,
+        };
+
+        // Counting the lines of a patch doesn't keep its text; libgit2
+        // gives no patch for a binary file.
+        let patch = git2::Patch::from_diff(&diff, index).map_err(|e| e.to_string())?;
+        let (insertions, deletions, is_binary) = match patch {
+            Some(patch) if !patch.delta().flags().is_binary() => {
+                let (_, insertions, deletions) = patch.line_stats().map_err(|e| e.to_string())?;
+                (insertions, deletions, false)
+            }
+            _ => (0, 0, true),
+        };
+
+        stats.push(GitFileStat {
+            generated: generated_rules.classify(&repo_root.join(path_from_wire(&path)), false),
+            path,
+            old_path,
+            status,
+            insertions,
+            deletions,
+            is_binary,
+        });
+    }
+
+    stats.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(stats)
+}
+
+// These walk the worktree, so they run on the blocking pool; see latency.rs.
+
+#[tauri::command]
+pub async fn get_git_status(path: String) -> Result<GitRepoInfo, KiriError> {
+    // libgit2 can't stop a status walk part way, so on a drive that stops
+    // responding this gives up on it rather than returning part of it.
+    let repo = normalize_input_path(&path);
+    run_blocking_coded("get_git_status", move || {
+        run_budgeted("get_git_status", &repo, GIT_STATUS_BUDGET, move |_| {
+            get_git_status_blocking(path)
+                .map(|info| (info, false))
+                .map_err(KiriError::from)
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_git_diff(repo_path: String, file_path: String) -> Result<String, KiriError> {
+    run_blocking("get_git_diff", move || {
+        get_git_diff_blocking(repo_path, file_path)
+    })
+    .await
+}
+
+/// `+`/`-` counts per changed file, for badges shown before the full diff
+/// is loaded.
+#[tauri::command]
+pub async fn get_diff_stats(
+    repo_path: String,
+    base: Option<String>,
+) -> Result<Vec<GitFileStat>, KiriError> {
+    run_blocking("get_diff_stats", move || {
+        get_diff_stats_blocking(repo_path, base)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_all_git_diffs(repo_path: String) -> Result<Vec<GitFileDiff>, KiriError> {
+    run_blocking("get_all_git_diffs", move || {
+        get_all_git_diffs_blocking(repo_path)
+    })
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -342,7 +513,7 @@ mod tests {
     #[test]
     fn test_get_git_status_not_a_repo() {
         let dir = tempdir().unwrap();
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Not a git repository"));
     }
@@ -351,7 +522,7 @@ mod tests {
     fn test_get_git_status_in_real_repo() {
         // Use current directory which should be in a git repo
         let current_dir = std::env::current_dir().unwrap();
-        let result = get_git_status(current_dir.to_string_lossy().to_string());
+        let result = get_git_status_blocking(current_dir.to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -372,7 +543,7 @@ mod tests {
     #[test]
     fn test_get_git_diff_invalid_repo() {
         let dir = tempdir().unwrap();
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "file.txt".to_string()
         );
@@ -382,7 +553,7 @@ mod tests {
     #[test]
     fn test_get_all_git_diffs_invalid_repo() {
         let dir = tempdir().unwrap();
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_err());
     }
 
@@ -405,6 +576,7 @@ mod tests {
             is_binary: false,
             current_content_base64: None,
             original_content_base64: None,
+            generated: None,
         };
         assert_eq!(diff.path, "test.txt");
         assert_eq!(diff.status, GitFileStatus::Added);
@@ -437,7 +609,7 @@ mod tests {
         // Initialize git repo
         Repository::init(dir.path()).unwrap();
 
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -475,7 +647,7 @@ mod tests {
         // Create untracked file
         fs::write(dir.path().join("new.txt"), "line1\nline2\nline3").unwrap();
 
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "new.txt".to_string()
         );
@@ -497,7 +669,7 @@ mod tests {
         fs::write(dir.path().join("file1.txt"), "content1").unwrap();
         fs::write(dir.path().join("file2.txt"), "content2").unwrap();
 
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let diffs = result.unwrap();
@@ -522,7 +694,7 @@ mod tests {
         let tree = repo.find_tree(tree_id).unwrap();
         repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[]).unwrap();
 
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -550,7 +722,7 @@ mod tests {
         // Modify the file
         fs::write(dir.path().join("test.txt"), "modified content").unwrap();
 
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "test.txt".to_string()
         );
@@ -578,7 +750,7 @@ mod tests {
         repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[]).unwrap();
 
         // File is committed and unchanged - diff should be empty
-        let diff = get_file_diff_internal(&repo, &dir.path().to_string_lossy(), "test.txt");
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("test.txt"));
         assert!(diff.is_empty());
     }
 
@@ -644,7 +816,7 @@ mod tests {
         Repository::init(dir.path()).unwrap();
 
         // Empty repo with no files
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
         assert!(result.unwrap().is_empty());
     }
@@ -685,7 +857,7 @@ mod tests {
         index.add_path(Path::new("staged.txt")).unwrap();
         index.write().unwrap();
 
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -740,7 +912,7 @@ mod tests {
         fs::write(dir.path().join("ignored.txt"), "ignored content").unwrap();
 
         // Get status with include_ignored
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -771,7 +943,7 @@ mod tests {
         // Delete the file
         fs::remove_file(dir.path().join("file.txt")).unwrap();
 
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let diffs = result.unwrap();
@@ -797,7 +969,7 @@ mod tests {
         repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[]).unwrap();
 
         // No changes - file is committed and unchanged
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "clean.txt".to_string()
         );
@@ -830,7 +1002,7 @@ mod tests {
         index.write().unwrap();
 
         // Get diff - should show staged changes
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "staged.txt".to_string()
         );
@@ -860,7 +1032,7 @@ mod tests {
         // Modify middle line
         fs::write(dir.path().join("multiline.txt"), "line1\nline2\nmodified\nline4\nline5").unwrap();
 
-        let diff = get_file_diff_internal(&repo, &dir.path().to_string_lossy(), "multiline.txt");
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("multiline.txt"));
         // Should contain context lines (space prefix) and changed lines
         assert!(!diff.is_empty());
     }
@@ -871,7 +1043,7 @@ mod tests {
         let repo = Repository::init(dir.path()).unwrap();
 
         // Try to get diff for non-existent file
-        let diff = get_file_diff_internal(&repo, &dir.path().to_string_lossy(), "nonexistent.txt");
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("nonexistent.txt"));
         assert!(diff.is_empty());
     }
 
@@ -883,7 +1055,7 @@ mod tests {
         // Create untracked file
         fs::write(dir.path().join("untracked.txt"), "content").unwrap();
 
-        let diff = get_file_diff_internal(&repo, &dir.path().to_string_lossy(), "untracked.txt");
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("untracked.txt"));
         // Should return content as additions
         assert!(diff.contains("+ content"));
     }
@@ -934,7 +1106,7 @@ mod tests {
         index.write().unwrap();
 
         // Check status for renamed file
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -1003,7 +1175,7 @@ mod tests {
         let _ = repo.merge(&[&branch1_commit], None, None);
 
         // Now check if we have conflicted status
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         // The merge may or may not produce a conflict depending on git internals
@@ -1035,7 +1207,7 @@ mod tests {
         index.add_path(Path::new("renamed.txt")).unwrap();
         index.write().unwrap();
 
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
         // Should have at least one entry (either renamed or add+delete)
     }
@@ -1055,7 +1227,7 @@ mod tests {
         repo.commit(Some("HEAD"), &sig, &sig, "Initial empty commit", &tree, &[]).unwrap();
 
         // Test diff on a path that doesn't match any changes
-        let diff = get_file_diff_internal(&repo, &dir.path().to_string_lossy(), "nonexistent_file.txt");
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("nonexistent_file.txt"));
         assert!(diff.is_empty());
     }
 
@@ -1069,7 +1241,7 @@ mod tests {
         fs::write(&file_path, "test content").unwrap();
 
         // Try to get diff - should return the file content as additions
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "test.txt".to_string()
         );
@@ -1095,7 +1267,7 @@ mod tests {
         repo.commit(Some("HEAD"), &sig, &sig, "Commit", &tree, &[]).unwrap();
 
         // Don't modify the file - it should be "unchanged" and skipped
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -1112,7 +1284,7 @@ mod tests {
         fs::write(dir.path().join("binary.bin"), vec![0u8, 1, 2, 255, 254]).unwrap();
 
         // Try to get diff for a file that might have encoding issues
-        let diff = get_file_diff_internal(&repo, &dir.path().to_string_lossy(), "binary.bin");
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("binary.bin"));
         // Should handle gracefully - either return content or empty string
         assert!(diff.is_empty() || !diff.is_empty());
     }
@@ -1126,7 +1298,7 @@ mod tests {
         fs::write(dir.path().join("file1.txt"), "line1\nline2\nline3").unwrap();
         fs::write(dir.path().join("file2.txt"), "single line").unwrap();
 
-        let (additions, deletions) = calculate_diff_stats(&repo, &dir.path().to_string_lossy());
+        let (additions, deletions) = calculate_diff_stats(&repo, dir.path());
 
         // 3 lines in file1 + 1 line in file2 = 4 additions
         assert_eq!(additions, 4);
@@ -1153,7 +1325,7 @@ mod tests {
         // Modify the file
         fs::write(dir.path().join("test.txt"), "modified line 1\nnew line 2\nnew line 3").unwrap();
 
-        let (additions, deletions) = calculate_diff_stats(&repo, &dir.path().to_string_lossy());
+        let (additions, deletions) = calculate_diff_stats(&repo, dir.path());
 
         // Should have some additions and deletions
         assert!(additions > 0 || deletions > 0);
@@ -1164,7 +1336,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let repo = Repository::init(dir.path()).unwrap();
 
-        let (additions, deletions) = calculate_diff_stats(&repo, &dir.path().to_string_lossy());
+        let (additions, deletions) = calculate_diff_stats(&repo, dir.path());
 
         assert_eq!(additions, 0);
         assert_eq!(deletions, 0);
@@ -1178,7 +1350,7 @@ mod tests {
         // Create untracked files
         fs::write(dir.path().join("file.txt"), "line1\nline2").unwrap();
 
-        let result = get_git_status(dir.path().to_string_lossy().to_string());
+        let result = get_git_status_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -1210,7 +1382,7 @@ mod tests {
         index.add_path(Path::new("test.txt")).unwrap();
         index.write().unwrap();
 
-        let (additions, deletions) = calculate_diff_stats(&repo, &dir.path().to_string_lossy());
+        let (additions, deletions) = calculate_diff_stats(&repo, dir.path());
 
         // Should count staged changes
         assert!(additions > 0);
@@ -1251,7 +1423,7 @@ mod tests {
         // At this point the working directory matches the index, so
         // diff_index_to_workdir has 0 deltas. The code should fall back to
         // diff_tree_to_index which shows the staged diff.
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "file.txt".to_string(),
         );
@@ -1297,7 +1469,7 @@ mod tests {
         // Make a working directory change to b.txt (unstaged = diff_index_to_workdir)
         fs::write(dir.path().join("b.txt"), "modified b1\nline b2\nnew b3").unwrap();
 
-        let (additions, deletions) = calculate_diff_stats(&repo, &dir.path().to_string_lossy());
+        let (additions, deletions) = calculate_diff_stats(&repo, dir.path());
 
         // Both staged (a.txt) and unstaged (b.txt) changes should be counted
         // a.txt staged: 1 deletion (line a1) + 2 insertions (modified a1, new a3)
@@ -1331,7 +1503,7 @@ mod tests {
         let modified = "line 1\nline 2\nline 3\nline 4\nCHANGED\nline 6\nline 7\nline 8\nline 9\nline 10";
         fs::write(dir.path().join("ctx.txt"), modified).unwrap();
 
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "ctx.txt".to_string(),
         );
@@ -1369,7 +1541,7 @@ mod tests {
         ];
         fs::write(dir.path().join("image.png"), &png_header).unwrap();
 
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let diffs = result.unwrap();
@@ -1421,7 +1593,7 @@ mod tests {
         ];
         fs::write(dir.path().join("icon.png"), &modified_png).unwrap();
 
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let diffs = result.unwrap();
@@ -1449,7 +1621,7 @@ mod tests {
         let png_bytes: Vec<u8> = vec![0x89, 0x50, 0x4E, 0x47];
         fs::write(dir.path().join("new.png"), &png_bytes).unwrap();
 
-        let result = get_all_git_diffs(dir.path().to_string_lossy().to_string());
+        let result = get_all_git_diffs_blocking(dir.path().to_string_lossy().to_string());
         assert!(result.is_ok());
 
         let diffs = result.unwrap();
@@ -1483,7 +1655,7 @@ mod tests {
         idx.write().unwrap();
 
         // get_git_diff should find the staged change via fallback
-        let result = get_git_diff(
+        let result = get_git_diff_blocking(
             dir.path().to_string_lossy().to_string(),
             "test.txt".to_string(),
         );
@@ -1492,6 +1664,45 @@ mod tests {
         assert!(!diff.is_empty());
     }
 
+    #[test]
+    fn test_get_diff_stats_per_file() {
+        let dir = tempdir().unwrap();
+        init_repo_with_commit(dir.path());
+        fs::write(dir.path().join("README.md"), "changed\nagain\n").unwrap();
+        fs::write(dir.path().join("new.txt"), "a\nb\nc\n").unwrap();
+        fs::write(dir.path().join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0, 1]).unwrap();
+        run_git(dir.path(), &["add", "logo.png"]);
+
+        let stats =
+            get_diff_stats_blocking(dir.path().to_string_lossy().to_string(), None).unwrap();
+        let summary: Vec<(&str, GitFileStatus, usize, usize, bool)> = stats
+            .iter()
+            .map(|s| (s.path.as_str(), s.status.clone(), s.insertions, s.deletions, s.is_binary))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("README.md", GitFileStatus::Modified, 2, 1, false),
+                ("logo.png", GitFileStatus::Added, 0, 0, true),
+                ("new.txt", GitFileStatus::Untracked, 3, 0, false),
+            ]
+        );
+
+        run_git(dir.path(), &["add", "-A"]);
+        run_git(dir.path(), &["commit", "-q", "-m", "second"]);
+        let since_first = get_diff_stats_blocking(
+            dir.path().to_string_lossy().to_string(),
+            Some("HEAD~1".to_string()),
+        )
+        .unwrap();
+        assert_eq!(since_first.len(), 3);
+        assert!(get_diff_stats_blocking(
+            dir.path().to_string_lossy().to_string(),
+            Some("no-such-branch".to_string()),
+        )
+        .is_err());
+    }
+
     fn run_git(dir: &std::path::Path, args: &[&str]) {
         let status = std::process::Command::new("git")
             .args(args)
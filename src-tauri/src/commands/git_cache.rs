@@ -0,0 +1,381 @@
+//! Result cache for `get_git_status` and `get_all_git_diffs`.
+//!
+//! The UI polls both every few seconds, and each call walks the whole
+//! worktree. A cached result is reused while its fingerprint still matches:
+//! HEAD (ref name and commit), the index mtime, and the size and mtime of
+//! every path the result reported as dirty. That catches commits, staging
+//! and edits to already-changed files even when the git directory lives
+//! outside the watched tree, as it does for linked worktrees. Files that
+//! become dirty are caught by the file watcher instead: only repositories
+//! under a watched root are cached, and every watcher event for a root
+//! evicts the repositories beneath it.
+//!
+//! The git commands take no managed state, so the cache is process-wide
+//! (see [`git_cache`]). Watcher wiring lives in watcher_commands.rs.
+
+use git2::Repository;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use super::git::{GitFileDiff, GitFileStatus, GitRepoInfo};
+use super::lock_ext::LockExt;
+
+/// Results with more dirty paths than this are not cached; stat-ing them
+/// all on every lookup would approach the cost of a fresh status walk.
+const MAX_DIRTY_PATHS: usize = 5_000;
+
+/// Size and mtime of a dirty path; `None` once it no longer exists.
+type PathStamp = Option<(SystemTime, u64)>;
+
+#[derive(Debug, Clone, PartialEq)]
+struct RepoFingerprint {
+    head: Option<String>,
+    index_mtime: Option<SystemTime>,
+    dirty: BTreeMap<String, PathStamp>,
+}
+
+/// HEAD as `<ref>@<commit>`, so switching between branches on the same
+/// commit still changes the fingerprint. Unborn branches have no commit.
+fn read_head(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let name = head.symbolic_target().unwrap_or("").to_string();
+    let commit = repo
+        .head()
+        .ok()
+        .and_then(|h| h.target())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default();
+    Some(format!("{}@{}", name, commit))
+}
+
+fn read_index_mtime(repo: &Repository) -> Option<SystemTime> {
+    std::fs::metadata(repo.path().join("index"))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+fn stamp(path: &Path) -> PathStamp {
+    let meta = std::fs::symlink_metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn stamp_all<'a>(
+    root: &Path,
+    paths: impl IntoIterator<Item = &'a str>,
+) -> BTreeMap<String, PathStamp> {
+    paths
+        .into_iter()
+        .map(|p| (p.to_string(), stamp(&root.join(p))))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct Cached<T> {
+    fingerprint: RepoFingerprint,
+    value: T,
+}
+
+#[derive(Debug, Default)]
+struct RepoEntry {
+    status: Option<Cached<GitRepoInfo>>,
+    diffs: Option<Cached<Vec<GitFileDiff>>>,
+}
+
+/// State captured before computing a result, so [`store_status`] and
+/// [`store_diffs`] can discard it if anything changed in the meantime.
+pub struct CacheFill {
+    root: PathBuf,
+    generation: u64,
+    head: Option<String>,
+    index_mtime: Option<SystemTime>,
+}
+
+#[derive(Debug, Default)]
+pub struct GitCache {
+    watched: Vec<PathBuf>,
+    repos: HashMap<PathBuf, RepoEntry>,
+    /// Bumped by every invalidation; fills that started earlier are
+    /// dropped.
+    generation: u64,
+}
+
+impl GitCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start caching repositories under `root`. Call once a watcher covers
+    /// it.
+    pub fn watch(&mut self, root: &Path) {
+        if !self.watched.iter().any(|w| w == root) {
+            self.watched.push(root.to_path_buf());
+        }
+    }
+
+    /// Stop caching under `root` and drop what was cached there.
+    pub fn unwatch(&mut self, root: &Path) {
+        self.watched.retain(|w| w != root);
+        let watched = &self.watched;
+        self.repos
+            .retain(|repo, _| watched.iter().any(|w| repo.starts_with(w)));
+        self.generation += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.watched.clear();
+        self.repos.clear();
+        self.generation += 1;
+    }
+
+    /// Evict repositories inside `root`, or containing it, after the
+    /// watcher reported a change there.
+    pub fn invalidate(&mut self, root: &Path) {
+        self.repos
+            .retain(|repo, _| !repo.starts_with(root) && !root.starts_with(repo));
+        self.generation += 1;
+    }
+
+    /// Number of repositories with cached results.
+    pub fn len(&self) -> usize {
+        self.repos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.repos.is_empty()
+    }
+
+    fn is_covered(&self, repo_root: &Path) -> bool {
+        self.watched.iter().any(|w| repo_root.starts_with(w))
+    }
+}
+
+/// The cache behind the git commands.
+pub fn git_cache() -> &'static Mutex<GitCache> {
+    static CACHE: OnceLock<Mutex<GitCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(GitCache::new()))
+}
+
+fn lookup<T: Clone>(
+    cache: &Mutex<GitCache>,
+    root: &Path,
+    repo: &Repository,
+    slot: fn(&RepoEntry) -> Option<&Cached<T>>,
+) -> Option<T> {
+    let cached = {
+        let cache = cache.lock_recover();
+        if !cache.is_covered(root) {
+            return None;
+        }
+        slot(cache.repos.get(root)?)?.clone()
+    };
+    // Stat outside the lock; other repositories' lookups needn't wait.
+    let current = RepoFingerprint {
+        head: read_head(repo),
+        index_mtime: read_index_mtime(repo),
+        dirty: stamp_all(root, cached.fingerprint.dirty.keys().map(String::as_str)),
+    };
+    (current == cached.fingerprint).then_some(cached.value)
+}
+
+fn store<T>(
+    cache: &Mutex<GitCache>,
+    fill: CacheFill,
+    dirty: Vec<&str>,
+    value: T,
+    slot: fn(&mut RepoEntry) -> &mut Option<Cached<T>>,
+) {
+    if dirty.len() > MAX_DIRTY_PATHS {
+        return;
+    }
+    let fingerprint = RepoFingerprint {
+        head: fill.head,
+        index_mtime: fill.index_mtime,
+        dirty: stamp_all(&fill.root, dirty),
+    };
+    let mut cache = cache.lock_recover();
+    if cache.generation != fill.generation || !cache.is_covered(&fill.root) {
+        return;
+    }
+    *slot(cache.repos.entry(fill.root).or_default()) = Some(Cached { fingerprint, value });
+}
+
+/// Cached status for the repository at `root`, if still valid.
+pub fn cached_status(
+    cache: &Mutex<GitCache>,
+    root: &Path,
+    repo: &Repository,
+) -> Option<GitRepoInfo> {
+    lookup(cache, root, repo, |entry| entry.status.as_ref())
+}
+
+/// Cached diffs for the repository at `root`, if still valid.
+pub fn cached_diffs(
+    cache: &Mutex<GitCache>,
+    root: &Path,
+    repo: &Repository,
+) -> Option<Vec<GitFileDiff>> {
+    lookup(cache, root, repo, |entry| entry.diffs.as_ref())
+}
+
+/// Call before computing a result for `root`; `None` means the result
+/// won't be cached.
+pub fn begin_fill(cache: &Mutex<GitCache>, root: &Path, repo: &Repository) -> Option<CacheFill> {
+    let generation = {
+        let cache = cache.lock_recover();
+        if !cache.is_covered(root) {
+            return None;
+        }
+        cache.generation
+    };
+    Some(CacheFill {
+        root: root.to_path_buf(),
+        generation,
+        head: read_head(repo),
+        index_mtime: read_index_mtime(repo),
+    })
+}
+
+pub fn store_status(cache: &Mutex<GitCache>, fill: CacheFill, info: &GitRepoInfo) {
+    // Ignored entries are whole directories such as `target/`; their
+    // contents churn without changing the status.
+    let dirty = info
+        .statuses
+        .iter()
+        .filter(|s| s.status != GitFileStatus::Ignored)
+        .map(|s| s.path.as_str())
+        .collect();
+    store(cache, fill, dirty, info.clone(), |entry| &mut entry.status);
+}
+
+pub fn store_diffs(cache: &Mutex<GitCache>, fill: CacheFill, diffs: &[GitFileDiff]) {
+    let dirty = diffs.iter().map(|d| d.path.as_str()).collect();
+    store(cache, fill, dirty, diffs.to_vec(), |entry| &mut entry.diffs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::git::GitStatusEntry;
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    fn commit_all(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap();
+    }
+
+    /// Repository with one commit and `dirty.txt` modified on disk.
+    fn setup() -> (TempDir, Repository) {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join("dirty.txt"), "one\n").unwrap();
+        commit_all(&repo, "init");
+        fs::write(dir.path().join("dirty.txt"), "one\ntwo\n").unwrap();
+        (dir, repo)
+    }
+
+    fn info(root: &Path) -> GitRepoInfo {
+        GitRepoInfo {
+            root: root.to_string_lossy().to_string(),
+            branch: Some("main".to_string()),
+            statuses: vec![GitStatusEntry {
+                path: "dirty.txt".to_string(),
+                status: GitFileStatus::Modified,
+            }],
+            additions: 1,
+            deletions: 0,
+        }
+    }
+
+    fn fill_status(cache: &Mutex<GitCache>, root: &Path, repo: &Repository) {
+        let fill = begin_fill(cache, root, repo).unwrap();
+        store_status(cache, fill, &info(root));
+    }
+
+    #[test]
+    fn test_hit_while_nothing_changed() {
+        let (dir, repo) = setup();
+        let cache = Mutex::new(GitCache::new());
+        cache.lock().unwrap().watch(dir.path());
+        assert!(cached_status(&cache, dir.path(), &repo).is_none());
+
+        fill_status(&cache, dir.path(), &repo);
+        let hit = cached_status(&cache, dir.path(), &repo).unwrap();
+        assert_eq!(hit.additions, 1);
+        // Diffs are cached separately.
+        assert!(cached_diffs(&cache, dir.path(), &repo).is_none());
+    }
+
+    #[test]
+    fn test_unwatched_repos_are_not_cached() {
+        let (dir, repo) = setup();
+        let cache = Mutex::new(GitCache::new());
+        assert!(begin_fill(&cache, dir.path(), &repo).is_none());
+
+        cache.lock().unwrap().watch(dir.path());
+        fill_status(&cache, dir.path(), &repo);
+        cache.lock().unwrap().unwatch(dir.path());
+        assert!(cached_status(&cache, dir.path(), &repo).is_none());
+    }
+
+    #[test]
+    fn test_editing_a_dirty_file_misses() {
+        let (dir, repo) = setup();
+        let cache = Mutex::new(GitCache::new());
+        cache.lock().unwrap().watch(dir.path());
+        fill_status(&cache, dir.path(), &repo);
+
+        fs::write(dir.path().join("dirty.txt"), "one\ntwo\nthree\n").unwrap();
+        assert!(cached_status(&cache, dir.path(), &repo).is_none());
+    }
+
+    #[test]
+    fn test_commit_misses() {
+        let (dir, repo) = setup();
+        let cache = Mutex::new(GitCache::new());
+        cache.lock().unwrap().watch(dir.path());
+        fill_status(&cache, dir.path(), &repo);
+
+        commit_all(&repo, "second");
+        assert!(cached_status(&cache, dir.path(), &repo).is_none());
+    }
+
+    #[test]
+    fn test_invalidate_evicts_and_drops_in_flight_fills() {
+        let (dir, repo) = setup();
+        let cache = Mutex::new(GitCache::new());
+        cache.lock().unwrap().watch(dir.path());
+        fill_status(&cache, dir.path(), &repo);
+
+        cache.lock().unwrap().invalidate(dir.path());
+        assert!(cached_status(&cache, dir.path(), &repo).is_none());
+
+        let fill = begin_fill(&cache, dir.path(), &repo).unwrap();
+        cache.lock().unwrap().invalidate(&dir.path().join("src"));
+        store_status(&cache, fill, &info(dir.path()));
+        assert!(cached_status(&cache, dir.path(), &repo).is_none());
+    }
+
+    #[test]
+    fn test_invalidate_leaves_other_repos() {
+        let (a, repo_a) = setup();
+        let (b, _repo_b) = setup();
+        let cache = Mutex::new(GitCache::new());
+        cache.lock().unwrap().watch(a.path());
+        fill_status(&cache, a.path(), &repo_a);
+
+        cache.lock().unwrap().invalidate(b.path());
+        assert!(cached_status(&cache, a.path(), &repo_a).is_some());
+    }
+}
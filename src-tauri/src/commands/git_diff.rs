@@ -5,6 +5,8 @@ use base64::Engine;
 use git2::{DiffOptions, Repository};
 use std::path::Path;
 
+use super::file_lines::prefix_lines;
+
 /// Binary file extensions that should be displayed as images
 const IMAGE_EXTENSIONS: &[&str] = &[
     "png", "jpg", "jpeg", "gif", "ico", "webp", "bmp", "svg", "tiff", "tif",
@@ -19,40 +21,34 @@ pub fn is_image_file(path: &str) -> bool {
 }
 
 /// Get base64 encoded content of the current working directory file
-pub fn get_current_file_base64(repo_path: &str, file_path: &str) -> Option<String> {
-    let full_path = Path::new(repo_path).join(file_path);
+pub fn get_current_file_base64(repo_path: &Path, file_path: &Path) -> Option<String> {
+    let full_path = repo_path.join(file_path);
     std::fs::read(&full_path)
         .ok()
         .map(|bytes| base64::engine::general_purpose::STANDARD.encode(&bytes))
 }
 
 /// Get base64 encoded content of the file from HEAD
-pub fn get_original_file_base64(repo: &Repository, file_path: &str) -> Option<String> {
+pub fn get_original_file_base64(repo: &Repository, file_path: &Path) -> Option<String> {
     let head = repo.head().ok()?;
     let tree = head.peel_to_tree().ok()?;
-    let entry = tree.get_path(Path::new(file_path)).ok()?;
+    let entry = tree.get_path(file_path).ok()?;
     let blob = repo.find_blob(entry.id()).ok()?;
     Some(base64::engine::general_purpose::STANDARD.encode(blob.content()))
 }
 
 /// Get file diff - internal implementation with error handling
 /// Returns empty string on any error
-pub fn get_file_diff_internal(repo: &Repository, repo_path: &str, file_path: &str) -> String {
-    let relative_path = Path::new(file_path);
-    let file_status = repo.status_file(relative_path).ok();
+pub fn get_file_diff_internal(repo: &Repository, repo_path: &Path, file_path: &Path) -> String {
+    let file_status = repo.status_file(file_path).ok();
 
     // For untracked files, return the entire file content
     if let Some(status) = file_status {
         if status.is_wt_new() {
-            let full_path = Path::new(repo_path).join(file_path);
-            if let Ok(content) = std::fs::read_to_string(&full_path) {
-                return content
-                    .lines()
-                    .map(|line| format!("+ {}", line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-            }
-            return String::new();
+            let full_path = repo_path.join(file_path);
+            return std::fs::read_to_string(&full_path)
+                .map(|text| prefix_lines(&text, "+ "))
+                .unwrap_or_default();
         }
     }
 
@@ -143,10 +139,7 @@ mod tests {
         let dir = tempdir().unwrap();
         fs::write(dir.path().join("test.bin"), b"\x00\x01\x02").unwrap();
 
-        let result = get_current_file_base64(
-            dir.path().to_str().unwrap(),
-            "test.bin",
-        );
+        let result = get_current_file_base64(dir.path(), Path::new("test.bin"));
         assert!(result.is_some());
         assert_eq!(result.unwrap(), "AAEC");
     }
@@ -154,10 +147,7 @@ mod tests {
     #[test]
     fn test_get_current_file_base64_nonexistent() {
         let dir = tempdir().unwrap();
-        let result = get_current_file_base64(
-            dir.path().to_str().unwrap(),
-            "nonexistent.bin",
-        );
+        let result = get_current_file_base64(dir.path(), Path::new("nonexistent.bin"));
         assert!(result.is_none());
     }
 
@@ -167,7 +157,7 @@ mod tests {
         let repo = Repository::init(dir.path()).unwrap();
 
         // No commits, so head() will fail
-        let result = get_original_file_base64(&repo, "file.txt");
+        let result = get_original_file_base64(&repo, Path::new("file.txt"));
         assert!(result.is_none());
     }
 
@@ -189,7 +179,7 @@ mod tests {
         repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
             .unwrap();
 
-        let result = get_original_file_base64(&repo, "test.txt");
+        let result = get_original_file_base64(&repo, Path::new("test.txt"));
         assert!(result.is_some());
         // "hello" in base64
         assert_eq!(result.unwrap(), "aGVsbG8=");
@@ -214,7 +204,7 @@ mod tests {
             .unwrap();
 
         // Try to get a file that doesn't exist in the tree
-        let result = get_original_file_base64(&repo, "nonexistent.txt");
+        let result = get_original_file_base64(&repo, Path::new("nonexistent.txt"));
         assert!(result.is_none());
     }
 
@@ -226,11 +216,7 @@ mod tests {
         // Create an untracked file
         fs::write(dir.path().join("new.txt"), "line1\nline2").unwrap();
 
-        let diff = get_file_diff_internal(
-            &repo,
-            dir.path().to_str().unwrap(),
-            "new.txt",
-        );
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("new.txt"));
         assert!(diff.contains("+ line1"));
         assert!(diff.contains("+ line2"));
     }
@@ -254,11 +240,7 @@ mod tests {
             .unwrap();
 
         // No changes
-        let diff = get_file_diff_internal(
-            &repo,
-            dir.path().to_str().unwrap(),
-            "stable.txt",
-        );
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("stable.txt"));
         assert!(diff.is_empty());
     }
 
@@ -281,11 +263,7 @@ mod tests {
         // Modify the file
         fs::write(dir.path().join("mod.txt"), "modified").unwrap();
 
-        let diff = get_file_diff_internal(
-            &repo,
-            dir.path().to_str().unwrap(),
-            "mod.txt",
-        );
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("mod.txt"));
         assert!(!diff.is_empty());
         assert!(diff.contains("- original") || diff.contains("-original"));
         assert!(diff.contains("+ modified") || diff.contains("+modified"));
@@ -317,11 +295,7 @@ mod tests {
             .unwrap();
         index.write().unwrap();
 
-        let diff = get_file_diff_internal(
-            &repo,
-            dir.path().to_str().unwrap(),
-            "staged.txt",
-        );
+        let diff = get_file_diff_internal(&repo, dir.path(), Path::new("staged.txt"));
         // Staged changes should be detected via diff_tree_to_index fallback
         assert!(!diff.is_empty());
     }
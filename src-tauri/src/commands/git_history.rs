@@ -1,6 +1,14 @@
 use git2::{Oid, Repository, Sort};
 use serde::Serialize;
 use std::collections::HashSet;
+use std::path::Path;
+
+use super::fs_path::{input_path, path_to_wire};
+use super::generated::{GeneratedKind, GeneratedRules};
+use super::jobs::CancelToken;
+use super::network::remote_git_command;
+use super::operations::output_cancellable;
+use super::retry::{is_transient_git_failure, retry_blocking, RetryReport, NETWORK_RETRY};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CommitInfo {
@@ -24,6 +32,7 @@ pub struct CommitFileDiff {
     pub diff: String,
     pub additions: usize,
     pub deletions: usize,
+    pub generated: Option<GeneratedKind>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -38,12 +47,14 @@ pub struct CommitDiffResult {
 pub struct PushResult {
     pub success: bool,
     pub message: String,
+    pub retry: RetryReport,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct FetchResult {
     pub success: bool,
     pub message: String,
+    pub retry: RetryReport,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -64,7 +75,7 @@ pub struct PullResult {
 /// value on every call, so the previous implementation paid for them
 /// twice per commit. On a 5 000-commit log that's 20 000 redundant
 /// allocations; cache them locally and consume each once.
-fn build_commit_info(
+pub(crate) fn build_commit_info(
     commit: &git2::Commit,
     is_pushed: bool,
     branch_type: &str,
@@ -127,7 +138,7 @@ pub fn get_commit_log(
     max_count: Option<usize>,
     skip: Option<usize>,
 ) -> Result<Vec<CommitInfo>, String> {
-    let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+    let repo = Repository::open(input_path(&repo_path)?).map_err(|e| e.to_string())?;
     let max_count = max_count.unwrap_or(50);
     let skip = skip.unwrap_or(0);
 
@@ -291,7 +302,7 @@ pub fn get_commit_diff(
     repo_path: String,
     commit_hash: String,
 ) -> Result<CommitDiffResult, String> {
-    let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+    let repo = Repository::open(input_path(&repo_path)?).map_err(|e| e.to_string())?;
     let oid = Oid::from_str(&commit_hash).map_err(|e| e.to_string())?;
     let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
     let commit_tree = commit.tree().map_err(|e| e.to_string())?;
@@ -308,6 +319,10 @@ pub fn get_commit_diff(
         .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)
         .map_err(|e| e.to_string())?;
 
+    let generated_rules = repo
+        .workdir()
+        .map(GeneratedRules::load)
+        .unwrap_or_default();
     let mut files: Vec<CommitFileDiff> = Vec::new();
     let mut total_additions: usize = 0;
     let mut total_deletions: usize = 0;
@@ -322,7 +337,7 @@ pub fn get_commit_diff(
                 .new_file()
                 .path()
                 .or_else(|| delta.old_file().path())
-                .map(|p| p.to_string_lossy().to_string())
+                .map(path_to_wire)
                 .unwrap_or_default();
 
             let status = match delta.status() {
@@ -367,6 +382,7 @@ pub fn get_commit_diff(
             total_deletions += dels;
 
             files.push(CommitFileDiff {
+                generated: generated_rules.classify(Path::new(&path), false),
                 path,
                 status,
                 diff: diff_text,
@@ -389,7 +405,7 @@ pub fn get_commit_diff(
 /// Get the number of commits on the current branch ahead of the default branch (main/master).
 /// Returns 0 if on the default branch itself or if no default branch is found.
 pub fn get_branch_ahead_count(repo_path: String) -> Result<usize, String> {
-    let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+    let repo = Repository::open(input_path(&repo_path)?).map_err(|e| e.to_string())?;
 
     let head = repo.head().map_err(|e| e.to_string())?;
     let head_oid = head
@@ -435,31 +451,72 @@ pub fn get_branch_ahead_count(repo_path: String) -> Result<usize, String> {
     Ok(count)
 }
 
-/// Fetch from remote using git command
-pub fn fetch_remote(repo_path: String, remote: Option<String>) -> Result<FetchResult, String> {
-    let remote_name = remote.unwrap_or_else(|| "origin".to_string());
+/// Run a network git command, killing it if `cancel` is tripped.
+fn run_remote(
+    command: &mut std::process::Command,
+    verb: &str,
+    cancel: &CancelToken,
+) -> Result<std::process::Output, String> {
+    output_cancellable(command, cancel).map_err(|e| {
+        if cancel.is_cancelled() {
+            e
+        } else {
+            format!("Failed to execute git {}: {}", verb, e)
+        }
+    })
+}
 
-    let output = std::process::Command::new("git")
-        .args(["fetch", &remote_name])
-        .current_dir(&repo_path)
-        // Clear inherited GIT_DIR/GIT_WORK_TREE so git operates on the
-        // target repo_path, not the parent worktree (e.g. during pre-commit hooks).
-        .env_remove("GIT_DIR")
-        .env_remove("GIT_WORK_TREE")
-        .output()
-        .map_err(|e| format!("Failed to execute git fetch: {}", e))?;
+/// What to retry of a network git command's outcome: a transient error
+/// running it, or the stderr of a transient failure.
+fn transient_git_failure(outcome: &Result<std::process::Output, String>) -> Option<String> {
+    let message = match outcome {
+        Ok(output) if output.status.success() => return None,
+        Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        Err(e) => e.clone(),
+    };
+    is_transient_git_failure(&message).then_some(message)
+}
+
+/// Fetch from remote using git command, retrying transient failures.
+pub fn fetch_remote(
+    repo_path: String,
+    remote: Option<String>,
+    cancel: &CancelToken,
+) -> Result<FetchResult, String> {
+    let remote_name = remote.unwrap_or_else(|| "origin".to_string());
+    let repo_path = input_path(&repo_path)?;
+
+    let (output, retry) = retry_blocking(
+        "git fetch",
+        &NETWORK_RETRY,
+        cancel,
+        || {
+            let mut command = remote_git_command();
+            command
+                .args(["fetch", &remote_name])
+                .current_dir(&repo_path)
+                // Clear inherited GIT_DIR/GIT_WORK_TREE so git operates on the
+                // target repo_path, not the parent worktree (e.g. during pre-commit hooks).
+                .env_remove("GIT_DIR")
+                .env_remove("GIT_WORK_TREE");
+            run_remote(&mut command, "fetch", cancel)
+        },
+        transient_git_failure,
+    );
+    let output = output?;
 
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     Ok(FetchResult {
         success: output.status.success(),
         message: stderr,
+        retry,
     })
 }
 
 /// Get behind/ahead count relative to upstream tracking branch
 pub fn get_behind_ahead_count(repo_path: String) -> Result<BehindAheadCount, String> {
-    let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+    let repo = Repository::open(input_path(&repo_path)?).map_err(|e| e.to_string())?;
 
     let head = match repo.head() {
         Ok(h) => h,
@@ -511,23 +568,24 @@ pub fn pull_commits(
     repo_path: String,
     remote: Option<String>,
     branch: Option<String>,
+    cancel: &CancelToken,
 ) -> Result<PullResult, String> {
     let remote_name = remote.unwrap_or_else(|| "origin".to_string());
 
     let branch_name = match branch {
         Some(b) => b,
         None => {
-            let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+            let repo = Repository::open(input_path(&repo_path)?).map_err(|e| e.to_string())?;
             let head = repo.head().map_err(|e| e.to_string())?;
             head.shorthand().unwrap_or("HEAD").to_string()
         }
     };
 
-    let output = std::process::Command::new("git")
+    let mut command = remote_git_command();
+    command
         .args(["pull", &remote_name, &branch_name])
-        .current_dir(&repo_path)
-        .output()
-        .map_err(|e| format!("Failed to execute git pull: {}", e))?;
+        .current_dir(input_path(&repo_path)?);
+    let output = run_remote(&mut command, "pull", cancel)?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -553,18 +611,20 @@ pub fn pull_commits(
     }
 }
 
-/// Push commits to remote using git command
+/// Push commits to remote using git command. Pushing a branch again is
+/// harmless, so transient failures are retried.
 pub fn push_commits(
     repo_path: String,
     remote: Option<String>,
     branch: Option<String>,
+    cancel: &CancelToken,
 ) -> Result<PushResult, String> {
     let remote_name = remote.unwrap_or_else(|| "origin".to_string());
 
     let branch_name = match branch {
         Some(b) => b,
         None => {
-            let repo = Repository::open(&repo_path).map_err(|e| e.to_string())?;
+            let repo = Repository::open(input_path(&repo_path)?).map_err(|e| e.to_string())?;
             let head = repo.head().map_err(|e| e.to_string())?;
             head.shorthand()
                 .unwrap_or("HEAD")
@@ -572,11 +632,21 @@ pub fn push_commits(
         }
     };
 
-    let output = std::process::Command::new("git")
-        .args(["push", &remote_name, &branch_name])
-        .current_dir(&repo_path)
-        .output()
-        .map_err(|e| format!("Failed to execute git push: {}", e))?;
+    let repo_path = input_path(&repo_path)?;
+    let (output, retry) = retry_blocking(
+        "git push",
+        &NETWORK_RETRY,
+        cancel,
+        || {
+            let mut command = remote_git_command();
+            command
+                .args(["push", &remote_name, &branch_name])
+                .current_dir(&repo_path);
+            run_remote(&mut command, "push", cancel)
+        },
+        transient_git_failure,
+    );
+    let output = output?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -590,6 +660,7 @@ pub fn push_commits(
                 // git push often writes progress to stderr even on success
                 format!("{}{}", stdout, stderr)
             },
+            retry,
         })
     } else {
         Ok(PushResult {
@@ -599,6 +670,7 @@ pub fn push_commits(
             } else {
                 stderr
             },
+            retry,
         })
     }
 }
@@ -841,10 +913,15 @@ mod tests {
         let result = PushResult {
             success: true,
             message: "Everything up-to-date".to_string(),
+            retry: RetryReport {
+                attempts: 2,
+                failures: vec!["fatal: early EOF".to_string()],
+            },
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("\"success\":true"));
         assert!(json.contains("Everything up-to-date"));
+        assert!(json.contains("\"attempts\":2"));
     }
 
     #[test]
@@ -876,6 +953,7 @@ mod tests {
             diff: "+ new line\n- old line\n".to_string(),
             additions: 1,
             deletions: 1,
+            generated: None,
         };
         let json = serde_json::to_string(&diff).unwrap();
         assert!(json.contains("\"status\":\"Modified\""));
@@ -1252,6 +1330,7 @@ mod tests {
         let result = FetchResult {
             success: true,
             message: "".to_string(),
+            retry: RetryReport::default(),
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("\"success\":true"));
@@ -1283,7 +1362,11 @@ mod tests {
     fn test_fetch_remote_not_a_repo() {
         let dir = tempdir().unwrap();
         // fetch_remote uses git CLI, so it will fail on a non-repo directory
-        let result = fetch_remote(dir.path().to_string_lossy().to_string(), None);
+        let result = fetch_remote(
+            dir.path().to_string_lossy().to_string(),
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_ok());
         // git fetch will fail but the function returns FetchResult with success: false
         assert!(!result.unwrap().success);
@@ -1294,7 +1377,11 @@ mod tests {
         let dir = tempdir().unwrap();
         let _repo = create_repo_with_commit(dir.path());
 
-        let result = fetch_remote(dir.path().to_string_lossy().to_string(), None);
+        let result = fetch_remote(
+            dir.path().to_string_lossy().to_string(),
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_ok());
         // No remote configured, git fetch will fail
         assert!(!result.unwrap().success);
@@ -1493,7 +1580,12 @@ mod tests {
     fn test_push_commits_not_a_repo() {
         let dir = tempdir().unwrap();
         // push_commits tries to open the repo first, so it should return an error
-        let result = push_commits(dir.path().to_string_lossy().to_string(), None, None);
+        let result = push_commits(
+            dir.path().to_string_lossy().to_string(),
+            None,
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_err());
     }
 
@@ -1503,7 +1595,12 @@ mod tests {
         let _repo = create_repo_with_commit(dir.path());
 
         // push_commits uses git CLI; no remote configured means push will fail
-        let result = push_commits(dir.path().to_string_lossy().to_string(), None, None);
+        let result = push_commits(
+            dir.path().to_string_lossy().to_string(),
+            None,
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_ok());
         let push_result = result.unwrap();
         assert!(!push_result.success);
@@ -1513,7 +1610,12 @@ mod tests {
     fn test_pull_commits_not_a_repo() {
         let dir = tempdir().unwrap();
         // pull_commits tries to open the repo to detect the branch, so it should error
-        let result = pull_commits(dir.path().to_string_lossy().to_string(), None, None);
+        let result = pull_commits(
+            dir.path().to_string_lossy().to_string(),
+            None,
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_err());
     }
 
@@ -1523,7 +1625,12 @@ mod tests {
         let _repo = create_repo_with_commit(dir.path());
 
         // pull_commits uses git CLI; no remote configured means pull will fail
-        let result = pull_commits(dir.path().to_string_lossy().to_string(), None, None);
+        let result = pull_commits(
+            dir.path().to_string_lossy().to_string(),
+            None,
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_ok());
         let pull_result = result.unwrap();
         assert!(!pull_result.success);
@@ -1538,6 +1645,7 @@ mod tests {
         let result = fetch_remote(
             dir.path().to_string_lossy().to_string(),
             Some("upstream".to_string()),
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         // Should fail because "upstream" remote does not exist
@@ -1598,7 +1706,12 @@ mod tests {
         add_commit(&remote_repo, remote_dir.path(), "new_file.txt", "new content\n", "Remote commit");
 
         // Pull from local clone
-        let result = pull_commits(local_dir.path().to_string_lossy().to_string(), None, None);
+        let result = pull_commits(
+            local_dir.path().to_string_lossy().to_string(),
+            None,
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_ok());
         let pull_result = result.unwrap();
         assert!(pull_result.success, "Pull failed: {}", pull_result.message);
@@ -1667,7 +1780,12 @@ mod tests {
         add_commit(&local_repo, local_dir.path(), "new_file.txt", "content\n", "Local commit");
 
         // Push
-        let result = push_commits(local_dir.path().to_string_lossy().to_string(), None, None);
+        let result = push_commits(
+            local_dir.path().to_string_lossy().to_string(),
+            None,
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_ok());
         let push_result = result.unwrap();
         assert!(push_result.success, "Push failed: {}", push_result.message);
@@ -2067,6 +2185,7 @@ mod tests {
             dir.path().to_string_lossy().to_string(),
             Some("origin".to_string()),
             Some("master".to_string()),
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         // No remote configured, so it should fail
@@ -2084,6 +2203,7 @@ mod tests {
             dir.path().to_string_lossy().to_string(),
             Some("origin".to_string()),
             Some("master".to_string()),
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         // No remote configured, so it should fail
@@ -2313,6 +2433,7 @@ mod tests {
                 diff: "+ line\n".to_string(),
                 additions: 1,
                 deletions: 0,
+                generated: None,
             }],
             total_additions: 1,
             total_deletions: 0,
@@ -2355,7 +2476,11 @@ mod tests {
         add_commit(&remote_repo, remote_dir.path(), "remote.txt", "remote\n", "Remote commit");
 
         // Fetch to update remote tracking branches
-        let fetch_result = fetch_remote(local_dir.path().to_string_lossy().to_string(), None);
+        let fetch_result = fetch_remote(
+            local_dir.path().to_string_lossy().to_string(),
+            None,
+            &CancelToken::default(),
+        );
         assert!(fetch_result.is_ok());
         assert!(fetch_result.unwrap().success);
 
@@ -2411,7 +2536,11 @@ mod tests {
         .unwrap();
 
         // Fetch should succeed
-        let result = fetch_remote(local_dir.path().to_string_lossy().to_string(), None);
+        let result = fetch_remote(
+            local_dir.path().to_string_lossy().to_string(),
+            None,
+            &CancelToken::default(),
+        );
         assert!(result.is_ok());
         assert!(result.unwrap().success);
     }
@@ -2495,6 +2624,7 @@ mod tests {
             local_dir.path().to_string_lossy().to_string(),
             None,
             None,
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         let pull_result = result.unwrap();
@@ -2513,6 +2643,7 @@ mod tests {
             dir.path().to_string_lossy().to_string(),
             None,
             None,
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         let pull_result = result.unwrap();
@@ -2532,6 +2663,7 @@ mod tests {
             dir.path().to_string_lossy().to_string(),
             None,
             None,
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         let push_result = result.unwrap();
@@ -2578,6 +2710,7 @@ mod tests {
             local_dir.path().to_string_lossy().to_string(),
             Some("origin".to_string()),
             Some(branch_name),
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         let push_result = result.unwrap();
@@ -2728,6 +2861,7 @@ mod tests {
             local_dir.path().to_string_lossy().to_string(),
             Some("origin".to_string()),
             Some(branch_name),
+            &CancelToken::default(),
         );
         assert!(result.is_ok());
         let pull_result = result.unwrap();
@@ -1,56 +1,96 @@
+//! Tauri command wrappers for git history. Every one of them walks history
+//! or talks to a remote, so the work in git_history.rs runs on the
+//! blocking pool; see latency.rs.
+
+use super::error::KiriError;
 use super::git_history::{
-    BehindAheadCount, CommitDiffResult, CommitInfo, FetchResult, PullResult, PushResult,
+    self, BehindAheadCount, CommitDiffResult, CommitInfo, FetchResult, PullResult, PushResult,
 };
+use super::latency::run_blocking;
+use super::operations::{self, OperationRequest};
 
 #[tauri::command]
-pub fn get_commit_log(
+pub async fn get_commit_log(
     repo_path: String,
     max_count: Option<usize>,
     skip: Option<usize>,
-) -> Result<Vec<CommitInfo>, String> {
-    super::git_history::get_commit_log(repo_path, max_count, skip)
+) -> Result<Vec<CommitInfo>, KiriError> {
+    run_blocking("get_commit_log", move || {
+        git_history::get_commit_log(repo_path, max_count, skip)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_commit_diff(
+pub async fn get_commit_diff(
     repo_path: String,
     commit_hash: String,
-) -> Result<CommitDiffResult, String> {
-    super::git_history::get_commit_diff(repo_path, commit_hash)
+) -> Result<CommitDiffResult, KiriError> {
+    run_blocking("get_commit_diff", move || {
+        git_history::get_commit_diff(repo_path, commit_hash)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn push_commits(
+pub async fn push_commits(
     repo_path: String,
     remote: Option<String>,
     branch: Option<String>,
-) -> Result<PushResult, String> {
-    super::git_history::push_commits(repo_path, remote, branch)
+    operation: Option<OperationRequest>,
+) -> Result<PushResult, KiriError> {
+    let operation = operations::begin(operation);
+    let cancel = operation.token().clone();
+    let result = run_blocking("push_commits", move || {
+        git_history::push_commits(repo_path, remote, branch, &cancel)
+    })
+    .await;
+    operation.finish(result)
 }
 
 #[tauri::command]
-pub fn fetch_remote(
+pub async fn fetch_remote(
     repo_path: String,
     remote: Option<String>,
-) -> Result<FetchResult, String> {
-    super::git_history::fetch_remote(repo_path, remote)
+    operation: Option<OperationRequest>,
+) -> Result<FetchResult, KiriError> {
+    let operation = operations::begin(operation);
+    let cancel = operation.token().clone();
+    let result = run_blocking("fetch_remote", move || {
+        git_history::fetch_remote(repo_path, remote, &cancel)
+    })
+    .await;
+    operation.finish(result)
 }
 
 #[tauri::command]
-pub fn get_behind_ahead_count(repo_path: String) -> Result<BehindAheadCount, String> {
-    super::git_history::get_behind_ahead_count(repo_path)
+pub async fn get_behind_ahead_count(repo_path: String) -> Result<BehindAheadCount, KiriError> {
+    run_blocking("get_behind_ahead_count", move || {
+        git_history::get_behind_ahead_count(repo_path)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_branch_ahead_count(repo_path: String) -> Result<usize, String> {
-    super::git_history::get_branch_ahead_count(repo_path)
+pub async fn get_branch_ahead_count(repo_path: String) -> Result<usize, KiriError> {
+    run_blocking("get_branch_ahead_count", move || {
+        git_history::get_branch_ahead_count(repo_path)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn pull_commits(
+pub async fn pull_commits(
     repo_path: String,
     remote: Option<String>,
     branch: Option<String>,
-) -> Result<PullResult, String> {
-    super::git_history::pull_commits(repo_path, remote, branch)
+    operation: Option<OperationRequest>,
+) -> Result<PullResult, KiriError> {
+    let operation = operations::begin(operation);
+    let cancel = operation.token().clone();
+    let result = run_blocking("pull_commits", move || {
+        git_history::pull_commits(repo_path, remote, branch, &cancel)
+    })
+    .await;
+    operation.finish(result)
 }
@@ -0,0 +1,549 @@
+//! Pre-commit hooks configured through husky, lefthook or pre-commit.
+//!
+//! Each framework's config is read into separate hooks so a run can say
+//! which one failed:
+//!
+//! - husky: each command line of `.husky/pre-commit` (or the husky 4
+//!   `package.json` entry). A script with shell control flow stays one hook.
+//! - lefthook: each of the `pre-commit` commands, run with `lefthook run
+//!   pre-commit --commands <name>`. A hook that also has scripts or jobs is
+//!   run whole.
+//! - pre-commit: each hook id whose stages include pre-commit, run with
+//!   `pre-commit run <id>`.
+//!
+//! All of them check the staged files, as `git commit` would. The runner
+//! that executes them as a job lives in git_hooks_commands.rs; it only gets
+//! hooks from [`runnable_hooks`], so an untrusted checkout runs none.
+
+use git2::Repository;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::error::KiriError;
+use super::fs_path::path_to_wire;
+use super::jobs::JobId;
+use super::trust::{require_trusted_in, TrustStoreState};
+
+pub const HOOK_JOB_KIND: &str = "pre-commit";
+
+/// Finished runs kept for `get_pre_commit_run`.
+const MAX_HOOK_RUNS: usize = 20;
+
+const LEFTHOOK_CONFIGS: &[&str] = &[
+    "lefthook.yml",
+    ".lefthook.yml",
+    "lefthook.yaml",
+    ".lefthook.yaml",
+];
+
+const PRE_COMMIT_CONFIGS: &[&str] = &[".pre-commit-config.yaml", ".pre-commit-config.yml"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookFramework {
+    Husky,
+    Lefthook,
+    PreCommit,
+}
+
+impl HookFramework {
+    fn key(self) -> &'static str {
+        match self {
+            HookFramework::Husky => "husky",
+            HookFramework::Lefthook => "lefthook",
+            HookFramework::PreCommit => "pre-commit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Hook {
+    /// `<framework>:<name>`, used to pick hooks to run.
+    pub id: String,
+    pub framework: HookFramework,
+    pub name: String,
+    /// What the config runs, for display.
+    pub command: String,
+    /// Config file it comes from, relative to the checkout.
+    pub config: String,
+    #[serde(skip)]
+    pub argv: Vec<String>,
+}
+
+impl Hook {
+    fn new(
+        framework: HookFramework,
+        name: &str,
+        command: &str,
+        config: &str,
+        argv: Vec<String>,
+    ) -> Self {
+        Self {
+            id: format!("{}:{}", framework.key(), name),
+            framework,
+            name: name.to_string(),
+            command: command.to_string(),
+            config: config.to_string(),
+            argv,
+        }
+    }
+}
+
+fn argv(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// `s` without the quotes around it, if it is quoted.
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| s.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(s)
+}
+
+/// `key: value` of a YAML line, unquoted.
+fn yaml_entry(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().split_once(':')?;
+    Some((unquote(key), unquote(value)))
+}
+
+/// Items of an inline YAML list such as `[commit, push]`.
+fn yaml_inline_list(value: &str) -> Vec<String> {
+    value
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(|item| unquote(item).to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Lines of a shell script that make it more than a list of commands.
+fn has_control_flow(line: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "{", "}", "function",
+    ];
+    let first = line.split_whitespace().next().unwrap_or_default();
+    KEYWORDS.contains(&first.trim_end_matches(';')) || line.contains("()") || line.ends_with('\\')
+}
+
+/// Hooks of a `.husky/pre-commit` script.
+pub fn parse_husky_script(text: &str, config: &str) -> Vec<Hook> {
+    let commands: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        // husky 4-8 scripts source its helper first.
+        .filter(|line| !(line.starts_with(". ") && line.contains("husky.sh")))
+        .collect();
+    if commands.iter().any(|line| has_control_flow(line)) {
+        return vec![Hook::new(
+            HookFramework::Husky,
+            "pre-commit",
+            config,
+            config,
+            argv(&["sh", "-e", config]),
+        )];
+    }
+    commands
+        .into_iter()
+        .map(|line| {
+            Hook::new(
+                HookFramework::Husky,
+                line,
+                line,
+                config,
+                argv(&["sh", "-e", "-c", line]),
+            )
+        })
+        .collect()
+}
+
+/// The husky 4 `"husky": {"hooks": {"pre-commit": ..}}` entry.
+fn husky_package_json(text: &str) -> Option<Hook> {
+    let package: serde_json::Value = serde_json::from_str(text).ok()?;
+    let command = package.pointer("/husky/hooks/pre-commit")?.as_str()?;
+    Some(Hook::new(
+        HookFramework::Husky,
+        command,
+        command,
+        "package.json",
+        argv(&["sh", "-e", "-c", command]),
+    ))
+}
+
+/// Hooks of a lefthook config's `pre-commit` section.
+pub fn parse_lefthook(text: &str, config: &str) -> Vec<Hook> {
+    let mut found = false;
+    let mut in_hook = false;
+    // The key under `pre-commit` being read, e.g. `commands`.
+    let mut group: Option<(String, usize)> = None;
+    let mut entry_indent: Option<usize> = None;
+    let mut commands: Vec<(String, String)> = Vec::new();
+    let mut whole = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let depth = indent(line);
+        if depth == 0 {
+            in_hook = trimmed.trim_end_matches(':') == "pre-commit";
+            found |= in_hook;
+            group = None;
+            continue;
+        }
+        if !in_hook {
+            continue;
+        }
+        let Some((key, value)) = yaml_entry(trimmed) else {
+            continue;
+        };
+        if group
+            .as_ref()
+            .map_or(true, |(_, group_indent)| depth <= *group_indent)
+        {
+            whole |= matches!(key, "scripts" | "jobs");
+            group = Some((key.to_string(), depth));
+            entry_indent = None;
+            continue;
+        }
+        if group.as_ref().map(|(name, _)| name.as_str()) != Some("commands") {
+            continue;
+        }
+        match entry_indent {
+            Some(entry) if depth > entry => {
+                if let (Some(last), "run") = (commands.last_mut(), key) {
+                    last.1 = value.to_string();
+                }
+            }
+            _ => {
+                entry_indent = Some(depth);
+                commands.push((key.to_string(), String::new()));
+            }
+        }
+    }
+    if !found {
+        return Vec::new();
+    }
+    if whole || commands.is_empty() {
+        return vec![Hook::new(
+            HookFramework::Lefthook,
+            "pre-commit",
+            "lefthook run pre-commit",
+            config,
+            argv(&["lefthook", "run", "pre-commit"]),
+        )];
+    }
+    commands
+        .into_iter()
+        .map(|(name, run)| {
+            let argv = argv(&["lefthook", "run", "pre-commit", "--commands", &name]);
+            Hook::new(HookFramework::Lefthook, &name, &run, config, argv)
+        })
+        .collect()
+}
+
+/// Hooks of a `.pre-commit-config.yaml` that run at commit time.
+pub fn parse_pre_commit(text: &str, config: &str) -> Vec<Hook> {
+    #[derive(Default)]
+    struct Item {
+        id: String,
+        name: Option<String>,
+        stages: Option<Vec<String>>,
+    }
+    let mut default_stages: Option<Vec<String>> = None;
+    let mut items: Vec<Item> = Vec::new();
+    let mut hooks_indent: Option<usize> = None;
+    let mut item_indent: Option<usize> = None;
+    let mut in_stages = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let depth = indent(line);
+        if depth == 0 {
+            hooks_indent = None;
+            if let Some(("default_stages", value)) = yaml_entry(trimmed) {
+                default_stages = Some(yaml_inline_list(value));
+            }
+            continue;
+        }
+        if trimmed == "hooks:" {
+            hooks_indent = Some(depth);
+            item_indent = None;
+            continue;
+        }
+        let Some(hooks) = hooks_indent else {
+            continue;
+        };
+        let is_item = trimmed.starts_with('-');
+        if depth < hooks || (depth == hooks && !is_item) {
+            hooks_indent = None;
+            continue;
+        }
+        let mut content = trimmed;
+        if is_item && item_indent.map_or(true, |item| item == depth) {
+            item_indent = Some(depth);
+            items.push(Item::default());
+            in_stages = false;
+            content = trimmed.trim_start_matches('-').trim_start();
+        } else if is_item && in_stages {
+            let stage = unquote(trimmed.trim_start_matches('-'));
+            if let Some(stages) = items.last_mut().and_then(|item| item.stages.as_mut()) {
+                stages.push(stage.to_string());
+            }
+            continue;
+        }
+        let (Some(item), Some((key, value))) = (items.last_mut(), yaml_entry(content)) else {
+            continue;
+        };
+        in_stages = false;
+        match key {
+            "id" => item.id = value.to_string(),
+            "name" => item.name = Some(value.to_string()),
+            "stages" => {
+                in_stages = value.is_empty();
+                item.stages = Some(yaml_inline_list(value));
+            }
+            _ => {}
+        }
+    }
+
+    let at_commit = |stages: &[String]| {
+        stages
+            .iter()
+            .any(|stage| matches!(stage.as_str(), "pre-commit" | "commit"))
+    };
+    items
+        .into_iter()
+        .filter(|item| !item.id.is_empty())
+        .filter(
+            |item| match item.stages.as_ref().or(default_stages.as_ref()) {
+                Some(stages) => at_commit(stages),
+                None => true,
+            },
+        )
+        .map(|item| {
+            let command = item.name.unwrap_or_else(|| item.id.clone());
+            let argv = argv(&["pre-commit", "run", &item.id]);
+            Hook::new(HookFramework::PreCommit, &item.id, &command, config, argv)
+        })
+        .collect()
+}
+
+/// Root of the checkout containing `path`, where hook configs live.
+pub fn checkout_root(path: &Path) -> Result<PathBuf, String> {
+    let repo =
+        Repository::discover(path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    repo.workdir()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| "The repository has no working tree".to_string())
+}
+
+/// Every pre-commit hook configured in the checkout at `root`.
+pub fn detect_hooks(root: &Path) -> Vec<Hook> {
+    let read = |name: &str| std::fs::read_to_string(root.join(name)).ok();
+    let mut hooks = Vec::new();
+    if let Some(text) = read(".husky/pre-commit") {
+        hooks.extend(parse_husky_script(&text, ".husky/pre-commit"));
+    } else if let Some(hook) = read("package.json").and_then(|text| husky_package_json(&text)) {
+        hooks.push(hook);
+    }
+    if let Some((name, text)) = LEFTHOOK_CONFIGS
+        .iter()
+        .find_map(|name| read(name).map(|text| (*name, text)))
+    {
+        hooks.extend(parse_lefthook(&text, name));
+    }
+    if let Some((name, text)) = PRE_COMMIT_CONFIGS
+        .iter()
+        .find_map(|name| read(name).map(|text| (*name, text)))
+    {
+        hooks.extend(parse_pre_commit(&text, name));
+    }
+    hooks
+}
+
+/// The hooks of the checkout at `root`, if it is trusted to run them.
+pub fn runnable_hooks(trust: &TrustStoreState, root: &Path) -> Result<Vec<Hook>, KiriError> {
+    require_trusted_in(trust, root)?;
+    Ok(detect_hooks(root))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStatus {
+    Passed,
+    Failed,
+    /// Stopped, or never started, because the run was cancelled.
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookResult {
+    pub id: String,
+    pub framework: HookFramework,
+    pub name: String,
+    pub status: HookStatus,
+    pub exit_code: Option<i32>,
+    /// Output of the hook, stdout and stderr interleaved, trimmed to its
+    /// end if long.
+    pub output: String,
+    /// Why the hook couldn't be started, e.g. the tool isn't installed.
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookRun {
+    pub id: JobId,
+    pub repo: String,
+    pub results: Vec<HookResult>,
+    /// Every hook passed; the commit may go ahead.
+    pub passed: bool,
+}
+
+impl HookRun {
+    pub fn new(id: JobId, repo: &Path, results: Vec<HookResult>) -> Self {
+        Self {
+            id,
+            repo: path_to_wire(repo),
+            passed: results.iter().all(|r| r.status == HookStatus::Passed),
+            results,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct HookRuns {
+    runs: BTreeMap<JobId, HookRun>,
+}
+
+pub type HookRunsState = Arc<Mutex<HookRuns>>;
+
+impl HookRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, run: HookRun) {
+        self.runs.insert(run.id, run);
+        while self.runs.len() > MAX_HOOK_RUNS {
+            self.runs.pop_first();
+        }
+    }
+
+    pub fn get(&self, id: JobId) -> Option<HookRun> {
+        self.runs.get(&id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error::ErrorCode;
+    use crate::commands::lock_ext::LockExt;
+    use crate::commands::trust::TrustLevel;
+    use tempfile::TempDir;
+
+    fn ids(hooks: &[Hook]) -> Vec<&str> {
+        hooks.iter().map(|hook| hook.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_untrusted_checkout_has_no_runnable_hooks() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".husky")).unwrap();
+        std::fs::write(root.join(".husky/pre-commit"), "npm test\n").unwrap();
+        let trust = TrustStoreState::default();
+        let error = runnable_hooks(&trust, root).unwrap_err();
+        assert_eq!(error.code, ErrorCode::NotTrusted);
+
+        trust.lock_recover().set(root, TrustLevel::Trusted).unwrap();
+        assert_eq!(
+            ids(&runnable_hooks(&trust, root).unwrap()),
+            vec!["husky:npm test"]
+        );
+    }
+
+    #[test]
+    fn test_detect_hooks() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".husky")).unwrap();
+        std::fs::write(
+            root.join(".husky/pre-commit"),
+            "#!/usr/bin/env sh\n. \"$(dirname -- \"$0\")/_/husky.sh\"\n\nnpx lint-staged\nnpm test\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("lefthook.yml"),
+            "pre-push:\n  commands:\n    audit:\n      run: npm audit\n\npre-commit:\n  parallel: true\n  commands:\n    eslint:\n      glob: \"*.{js,ts}\"\n      run: npx eslint {staged_files}\n    \"types\":\n      run: npx tsc --noEmit\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join(".pre-commit-config.yaml"),
+            "repos:\n-   repo: https://github.com/pre-commit/pre-commit-hooks\n    rev: v4.5.0\n    hooks:\n    -   id: trailing-whitespace\n    -   id: end-of-file-fixer\n        name: Fix end of files\n-   repo: local\n    hooks:\n      - id: pytest\n        stages:\n          - pre-push\n      - id: mypy\n        stages: [commit]\n",
+        )
+        .unwrap();
+
+        let hooks = detect_hooks(root);
+        assert_eq!(
+            ids(&hooks),
+            vec![
+                "husky:npx lint-staged",
+                "husky:npm test",
+                "lefthook:eslint",
+                "lefthook:types",
+                "pre-commit:trailing-whitespace",
+                "pre-commit:end-of-file-fixer",
+                "pre-commit:mypy",
+            ]
+        );
+        assert_eq!(hooks[1].argv, vec!["sh", "-e", "-c", "npm test"]);
+        assert_eq!(hooks[2].command, "npx eslint {staged_files}");
+        assert_eq!(
+            hooks[3].argv,
+            vec!["lefthook", "run", "pre-commit", "--commands", "types"]
+        );
+        assert_eq!(hooks[5].command, "Fix end of files");
+        assert_eq!(hooks[6].argv, vec!["pre-commit", "run", "mypy"]);
+    }
+
+    #[test]
+    fn test_whole_hooks_and_stages() {
+        let script = "if [ -n \"$CI\" ]; then\n  exit 0\nfi\nnpm test\n";
+        let hooks = parse_husky_script(script, ".husky/pre-commit");
+        assert_eq!(ids(&hooks), vec!["husky:pre-commit"]);
+        assert_eq!(hooks[0].argv, vec!["sh", "-e", ".husky/pre-commit"]);
+
+        let lefthook = "pre-commit:\n  commands:\n    lint:\n      run: make lint\n  scripts:\n    \"check.sh\":\n      runner: bash\n";
+        let hooks = parse_lefthook(lefthook, "lefthook.yml");
+        assert_eq!(ids(&hooks), vec!["lefthook:pre-commit"]);
+        assert!(parse_lefthook("pre-push:\n  commands:\n    a:\n      run: b\n", "l").is_empty());
+
+        let config = "default_stages: [pre-push]\nrepos:\n- repo: local\n  hooks:\n  - id: slow\n  - id: fmt\n    stages: [pre-commit]\n";
+        assert_eq!(
+            ids(&parse_pre_commit(config, ".pre-commit-config.yaml")),
+            vec!["pre-commit:fmt"]
+        );
+
+        let package = r#"{"husky": {"hooks": {"pre-commit": "lint-staged"}}}"#;
+        assert_eq!(
+            husky_package_json(package).map(|hook| hook.id),
+            Some("husky:lint-staged".to_string())
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Runner and Tauri commands for pre-commit hooks; detection lives in
+//! git_hooks.rs.
+//!
+//! [`run_pre_commit_hooks`] runs the hooks one after another as a job of
+//! kind `pre-commit` and returns its id, which `cancel_job` accepts. A
+//! failing hook doesn't stop the others, so each failure is reported on
+//! its own. When the run ends, `pre-commit-finished` carries the
+//! [`HookRun`]; the commit should only go ahead if it passed.
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::error::{ErrorCode, KiriError};
+use super::fs_path::normalize_input_path;
+use super::git_hooks::{
+    checkout_root, detect_hooks, runnable_hooks, Hook, HookResult, HookRun, HookRunsState,
+    HookStatus, HOOK_JOB_KIND,
+};
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::spawn_job;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::toolchain;
+use super::trust_commands::trust_store;
+
+pub const PRE_COMMIT_FINISHED_EVENT: &str = "pre-commit-finished";
+
+/// Output kept per hook; longer output keeps its end, where the errors are.
+const MAX_HOOK_OUTPUT: usize = 64 * 1024;
+
+/// How often a running hook checks whether the job was cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+fn read_lines(
+    stream: impl Read + Send + 'static,
+    tx: mpsc::Sender<String>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Drop the start of `output` beyond [`MAX_HOOK_OUTPUT`].
+fn keep_tail(output: &mut String) {
+    if output.len() <= MAX_HOOK_OUTPUT {
+        return;
+    }
+    let mut cut = output.len() - MAX_HOOK_OUTPUT;
+    while !output.is_char_boundary(cut) {
+        cut += 1;
+    }
+    output.drain(..cut);
+}
+
+/// The process for `hook` in `root`, with the worktree's runtimes and its
+/// `node_modules/.bin` on the PATH, as when git runs it from an npm setup.
+fn hook_command(root: &Path, hook: &Hook) -> Command {
+    let mut command = Command::new(&hook.argv[0]);
+    command
+        .args(&hook.argv[1..])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut env = toolchain::resolve(root).env;
+    let bin = root.join("node_modules").join(".bin");
+    if bin.is_dir() {
+        let path = env
+            .get("PATH")
+            .cloned()
+            .flatten()
+            .or_else(|| std::env::var("PATH").ok())
+            .unwrap_or_default();
+        let dirs = std::iter::once(bin).chain(std::env::split_paths(&path));
+        if let Ok(joined) = std::env::join_paths(dirs) {
+            env.insert(
+                "PATH".to_string(),
+                Some(joined.to_string_lossy().to_string()),
+            );
+        }
+    }
+    for (key, value) in env {
+        match value {
+            Some(value) => command.env(key, value),
+            None => command.env_remove(key),
+        };
+    }
+    command
+}
+
+fn run_hook(ctx: &JobContext, root: &Path, hook: &Hook) -> HookResult {
+    let started = Instant::now();
+    let mut result = HookResult {
+        id: hook.id.clone(),
+        framework: hook.framework,
+        name: hook.name.clone(),
+        status: HookStatus::Failed,
+        exit_code: None,
+        output: String::new(),
+        error: None,
+        duration_ms: 0,
+    };
+    if ctx.is_cancelled() {
+        result.status = HookStatus::Cancelled;
+        return result;
+    }
+    let mut child = match hook_command(root, hook).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            result.error = Some(match e.kind() {
+                std::io::ErrorKind::NotFound => format!("{} is not installed", hook.argv[0]),
+                _ => format!("Failed to start {}: {}", hook.argv[0], e),
+            });
+            return result;
+        }
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(read_lines(stdout, tx.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(read_lines(stderr, tx.clone()));
+    }
+    drop(tx);
+
+    let mut cancelled = false;
+    loop {
+        if ctx.is_cancelled() && !cancelled {
+            let _ = child.kill();
+            cancelled = true;
+        }
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => {
+                result.output.push_str(&line);
+                result.output.push('\n');
+                keep_tail(&mut result.output);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+    match child.wait() {
+        Ok(status) => {
+            result.exit_code = status.code();
+            result.status = if cancelled {
+                HookStatus::Cancelled
+            } else if status.success() {
+                HookStatus::Passed
+            } else {
+                HookStatus::Failed
+            };
+        }
+        Err(e) => result.error = Some(format!("Failed to wait for {}: {}", hook.name, e)),
+    }
+    result.duration_ms = started.elapsed().as_millis() as u64;
+    result
+}
+
+/// Pre-commit hooks configured through husky, lefthook or pre-commit in
+/// the checkout containing `repo_path`.
+#[tauri::command]
+pub async fn list_pre_commit_hooks(repo_path: String) -> Result<Vec<Hook>, KiriError> {
+    run_blocking("list_pre_commit_hooks", move || {
+        let root = checkout_root(&normalize_input_path(&repo_path))?;
+        Ok(detect_hooks(&root))
+    })
+    .await
+}
+
+/// Run the pre-commit hooks of the checkout containing `repo_path` against
+/// the staged files: those in `hooks` (by id), or all of them. Returns the
+/// run id.
+#[tauri::command]
+pub fn run_pre_commit_hooks(
+    app: AppHandle,
+    repo_path: String,
+    hooks: Option<Vec<String>>,
+) -> Result<JobId, KiriError> {
+    let root = checkout_root(&normalize_input_path(&repo_path))?;
+    let mut selected = runnable_hooks(&trust_store(&app)?, &root)?;
+    if let Some(ids) = &hooks {
+        if let Some(unknown) = ids.iter().find(|id| !selected.iter().any(|h| &h.id == *id)) {
+            return Err(KiriError::new(
+                ErrorCode::NotFound,
+                format!("No pre-commit hook {}", unknown),
+            ));
+        }
+        selected.retain(|hook| ids.contains(&hook.id));
+    }
+    if selected.is_empty() {
+        return Err(KiriError::new(
+            ErrorCode::NotFound,
+            format!("No pre-commit hooks configured in {}", root.display()),
+        ));
+    }
+
+    let label = format!("Pre-commit hooks in {}", root.display());
+    let job_app = app.clone();
+    spawn_job(&app, HOOK_JOB_KIND, &label, move |ctx| {
+        let total = selected.len();
+        let mut results = Vec::with_capacity(total);
+        for (index, hook) in selected.iter().enumerate() {
+            let percent = (index * 100 / total) as u8;
+            ctx.progress(Some(percent), Some(hook.name.clone()));
+            results.push(run_hook(ctx, &root, hook));
+        }
+        let run = HookRun::new(ctx.id(), &root, results);
+        let failed = run
+            .results
+            .iter()
+            .filter(|r| r.status == HookStatus::Failed)
+            .count();
+        let _ = job_app.emit(PRE_COMMIT_FINISHED_EVENT, &run);
+        if let Some(state) = job_app.try_state::<HookRunsState>() {
+            state.lock_recover().insert(run);
+        }
+        match failed {
+            0 => Ok(()),
+            _ => Err(format!("{} of {} pre-commit hooks failed", failed, total)),
+        }
+    })
+    .map_err(KiriError::from)
+}
+
+/// A finished run, for a window that missed its `pre-commit-finished`.
+#[tauri::command]
+pub fn get_pre_commit_run(
+    state: tauri::State<'_, HookRunsState>,
+    run_id: JobId,
+) -> Option<HookRun> {
+    state.lock_recover().get(run_id)
+}
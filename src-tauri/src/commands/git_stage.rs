@@ -0,0 +1,354 @@
+//! Staging and committing through libgit2, for the commit workflow of the
+//! changes panel.
+//!
+//! Paths are relative to the repository root, as in the results of
+//! `get_git_status` and `get_all_git_diffs`. A hunk is named by the start
+//! lines of its `@@ -old +new @@` header in the unstaged diff, so staging a
+//! hunk from a diff that has since changed fails instead of staging
+//! something else.
+//!
+//! Commits are made without running hooks; the pre-commit hooks of
+//! git_hooks.rs run as a job of their own before the commit. Tauri
+//! wrappers live in git_stage_commands.rs.
+
+use git2::{ApplyLocation, ApplyOptions, DiffOptions, Repository, Signature};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::path::{Component, Path, PathBuf};
+
+use super::error::{ErrorCode, KiriError};
+use super::fs_path::path_from_wire;
+use super::git_history::{build_commit_info, CommitInfo};
+
+/// Who a commit is attributed to instead of `user.name` / `user.email`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+fn open_repo(repo_root: &Path) -> Result<Repository, String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+    if repo.workdir().is_none() {
+        return Err("Repository has no working directory".to_string());
+    }
+    Ok(repo)
+}
+
+/// `file_path` as a path inside the repository, refusing ones that leave it.
+fn relative_path(file_path: &str) -> Result<PathBuf, KiriError> {
+    let path = path_from_wire(file_path);
+    let inside = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if file_path.is_empty() || !inside {
+        return Err(KiriError::new(
+            ErrorCode::InvalidInput,
+            format!("Invalid path: {}", file_path),
+        ));
+    }
+    Ok(path)
+}
+
+/// Stage the working tree state of `file_path`: its content, or its
+/// removal once it is deleted.
+pub fn stage_file(repo_root: &Path, file_path: &str) -> Result<(), KiriError> {
+    let repo = open_repo(repo_root)?;
+    let path = relative_path(file_path)?;
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    let exists = repo_root.join(&path).symlink_metadata().is_ok();
+    if exists {
+        index.add_path(&path).map_err(|e| e.to_string())?;
+    } else if index.get_path(&path, 0).is_some() {
+        index.remove_path(&path).map_err(|e| e.to_string())?;
+    } else {
+        return Err(KiriError::new(
+            ErrorCode::NotFound,
+            format!("File not found: {}", file_path),
+        ));
+    }
+    Ok(index.write().map_err(|e| e.to_string())?)
+}
+
+/// Put `file_path` in the index back to HEAD, leaving the working tree as
+/// it is. Before the first commit the file is taken out of the index.
+pub fn unstage_file(repo_root: &Path, file_path: &str) -> Result<(), KiriError> {
+    let repo = open_repo(repo_root)?;
+    let path = relative_path(file_path)?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    match head {
+        Some(head) => repo.reset_default(Some(head.as_object()), [&path]),
+        None => {
+            let mut index = repo.index().map_err(|e| e.to_string())?;
+            // Not in the index is already unstaged.
+            let _ = index.remove_path(&path);
+            index.write()
+        }
+    }
+    .map_err(|e| KiriError::from(e.to_string()))
+}
+
+/// Stage the hunk of the unstaged diff of `file_path` whose header starts
+/// at `old_start` and `new_start`.
+pub fn stage_hunk(
+    repo_root: &Path,
+    file_path: &str,
+    old_start: u32,
+    new_start: u32,
+) -> Result<(), KiriError> {
+    let repo = open_repo(repo_root)?;
+    let path = relative_path(file_path)?;
+    let mut diff_opts = DiffOptions::new();
+    diff_opts
+        .pathspec(&path)
+        .disable_pathspec_match(true)
+        .include_untracked(true)
+        .show_untracked_content(true);
+    let diff = repo
+        .diff_index_to_workdir(None, Some(&mut diff_opts))
+        .map_err(|e| e.to_string())?;
+
+    let found = Cell::new(false);
+    let mut apply_opts = ApplyOptions::new();
+    apply_opts.hunk_callback(|hunk| {
+        let matches =
+            hunk.is_some_and(|hunk| hunk.old_start() == old_start && hunk.new_start() == new_start);
+        if matches {
+            found.set(true);
+        }
+        matches
+    });
+    repo.apply(&diff, ApplyLocation::Index, Some(&mut apply_opts))
+        .map_err(|e| e.to_string())?;
+    if !found.get() {
+        return Err(KiriError::new(
+            ErrorCode::Conflict,
+            format!(
+                "Hunk not found in {}: the diff changed since it was shown",
+                file_path
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Commit the index with `message`, cleaned up as `git commit` does. The
+/// author is `author` when given, else the configured user. With `amend`
+/// HEAD is replaced instead: its message is kept when `message` is blank,
+/// and its author unless `author` is given.
+pub fn create_commit(
+    repo_root: &Path,
+    message: &str,
+    author: Option<CommitAuthor>,
+    amend: bool,
+) -> Result<CommitInfo, KiriError> {
+    let repo = open_repo(repo_root)?;
+    let message = git2::message_prettify(message, Some(b'#')).map_err(|e| e.to_string())?;
+    let committer = repo.signature().map_err(|_| {
+        KiriError::new(
+            ErrorCode::InvalidInput,
+            "No committer identity: set user.name and user.email in git config",
+        )
+    })?;
+    let author = author
+        .map(|author| Signature::now(&author.name, &author.email))
+        .transpose()
+        .map_err(|e| {
+            KiriError::new(
+                ErrorCode::InvalidInput,
+                format!("Invalid author: {}", e.message()),
+            )
+        })?;
+
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    if index.has_conflicts() {
+        return Err(KiriError::new(
+            ErrorCode::Conflict,
+            "Resolve the conflicts before committing",
+        ));
+    }
+    let tree_id = index.write_tree().map_err(|e| e.to_string())?;
+    let tree = repo.find_tree(tree_id).map_err(|e| e.to_string())?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+
+    let oid = if amend {
+        let head = head.ok_or_else(|| {
+            KiriError::new(
+                ErrorCode::InvalidInput,
+                "Nothing to amend: the branch has no commits yet",
+            )
+        })?;
+        let message = (!message.trim().is_empty()).then_some(message.as_str());
+        head.amend(
+            Some("HEAD"),
+            author.as_ref(),
+            Some(&committer),
+            None,
+            message,
+            Some(&tree),
+        )
+        .map_err(|e| e.to_string())?
+    } else {
+        if message.trim().is_empty() {
+            return Err(KiriError::new(
+                ErrorCode::InvalidInput,
+                "Commit message is empty",
+            ));
+        }
+        if head.as_ref().map(|head| head.tree_id()) == Some(tree_id) {
+            return Err(KiriError::new(
+                ErrorCode::InvalidInput,
+                "Nothing to commit: no changes are staged",
+            ));
+        }
+        let parents: Vec<&git2::Commit> = head.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            author.as_ref().unwrap_or(&committer),
+            &committer,
+            &message,
+            &tree,
+            &parents,
+        )
+        .map_err(|e| e.to_string())?
+    };
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    Ok(build_commit_info(&commit, false, "current", 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn run_git(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .output()
+            .expect("git command failed to start");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    fn init_repo_with_commit(dir: &Path) {
+        run_git(dir, &["init", "-q", "-b", "main"]);
+        run_git(dir, &["config", "user.email", "test@example.com"]);
+        run_git(dir, &["config", "user.name", "Test"]);
+        run_git(dir, &["config", "commit.gpgsign", "false"]);
+        fs::write(dir.join("README.md"), "init\n").unwrap();
+        run_git(dir, &["add", "README.md"]);
+        run_git(dir, &["commit", "-q", "-m", "init"]);
+    }
+
+    #[test]
+    fn test_stage_and_unstage_files() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        init_repo_with_commit(root);
+        fs::write(root.join("README.md"), "changed\n").unwrap();
+        fs::write(root.join("new.txt"), "new\n").unwrap();
+
+        stage_file(root, "README.md").unwrap();
+        stage_file(root, "new.txt").unwrap();
+        assert_eq!(
+            run_git(root, &["diff", "--cached", "--name-only"]),
+            "README.md\nnew.txt\n"
+        );
+
+        unstage_file(root, "README.md").unwrap();
+        assert_eq!(
+            run_git(root, &["diff", "--cached", "--name-only"]),
+            "new.txt\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("README.md")).unwrap(),
+            "changed\n"
+        );
+
+        fs::remove_file(root.join("README.md")).unwrap();
+        stage_file(root, "README.md").unwrap();
+        assert_eq!(
+            run_git(root, &["diff", "--cached", "--name-status"]),
+            "D\tREADME.md\nA\tnew.txt\n"
+        );
+        assert_eq!(
+            stage_file(root, "../outside.txt").unwrap_err().code,
+            ErrorCode::InvalidInput
+        );
+        assert_eq!(
+            stage_file(root, "missing.txt").unwrap_err().code,
+            ErrorCode::NotFound
+        );
+    }
+
+    #[test]
+    fn test_stage_hunk_stages_only_that_hunk() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        init_repo_with_commit(root);
+        let lines: Vec<String> = (1..=20).map(|n| format!("line {}\n", n)).collect();
+        fs::write(root.join("list.txt"), lines.concat()).unwrap();
+        run_git(root, &["add", "list.txt"]);
+        run_git(root, &["commit", "-q", "-m", "list"]);
+
+        let mut edited = lines.clone();
+        edited[1] = "line two\n".to_string();
+        edited[17] = "line eighteen\n".to_string();
+        fs::write(root.join("list.txt"), edited.concat()).unwrap();
+
+        // Hunks `@@ -1,5 +1,5 @@` and `@@ -15,6 +15,6 @@`.
+        assert_eq!(
+            stage_hunk(root, "list.txt", 3, 3).unwrap_err().code,
+            ErrorCode::Conflict
+        );
+        stage_hunk(root, "list.txt", 15, 15).unwrap();
+        let staged = run_git(root, &["diff", "--cached", "-U0"]);
+        assert!(staged.contains("+line eighteen"));
+        assert!(!staged.contains("+line two"));
+        let unstaged = run_git(root, &["diff", "-U0"]);
+        assert!(unstaged.contains("+line two"));
+        assert!(!unstaged.contains("+line eighteen"));
+    }
+
+    #[test]
+    fn test_create_commit_with_author_and_amend() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        init_repo_with_commit(root);
+        assert!(create_commit(root, "empty", None, false)
+            .unwrap_err()
+            .message
+            .contains("Nothing to commit"));
+
+        fs::write(root.join("a.txt"), "a\n").unwrap();
+        stage_file(root, "a.txt").unwrap();
+        assert!(create_commit(root, "  \n# only a comment\n", None, false).is_err());
+        let author = CommitAuthor {
+            name: "Pair".to_string(),
+            email: "pair@example.com".to_string(),
+        };
+        let commit = create_commit(root, "Add a\n\n# comment\n", Some(author), false).unwrap();
+        assert_eq!(commit.message_body, "Add a\n");
+        assert_eq!(commit.author, "Pair");
+        assert_eq!(commit.parent_ids.len(), 1);
+        assert_eq!(
+            run_git(root, &["log", "-1", "--format=%an <%ae> / %cn"]),
+            "Pair <pair@example.com> / Test\n"
+        );
+
+        fs::write(root.join("b.txt"), "b\n").unwrap();
+        stage_file(root, "b.txt").unwrap();
+        let amended = create_commit(root, "", None, true).unwrap();
+        assert_eq!(amended.message, "Add a");
+        assert_eq!(amended.author, "Pair");
+        assert_eq!(amended.parent_ids, commit.parent_ids);
+        assert_eq!(
+            run_git(root, &["show", "--name-only", "--format="]),
+            "a.txt\nb.txt\n"
+        );
+    }
+}
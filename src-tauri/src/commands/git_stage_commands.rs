@@ -0,0 +1,76 @@
+//! Tauri command wrappers for staging and committing; see git_stage.rs.
+//!
+//! Each one changes the index or HEAD, so it drops what the git cache holds
+//! for the repository rather than wait for the watcher to notice.
+
+use std::path::Path;
+
+use super::error::KiriError;
+use super::fs_path::input_path;
+use super::git_cache::git_cache;
+use super::git_history::CommitInfo;
+use super::git_stage::{self, CommitAuthor};
+use super::latency::run_blocking_coded;
+use super::lock_ext::LockExt;
+
+/// Run `change` on the repository at `repo_path`, then evict it from the
+/// git cache.
+fn change_repo<T>(
+    repo_path: &str,
+    change: impl FnOnce(&Path) -> Result<T, KiriError>,
+) -> Result<T, KiriError> {
+    let repo_root = input_path(repo_path)?;
+    let result = change(&repo_root);
+    git_cache().lock_recover().invalidate(&repo_root);
+    result
+}
+
+#[tauri::command]
+pub async fn stage_file(repo_path: String, file_path: String) -> Result<(), KiriError> {
+    run_blocking_coded("stage_file", move || {
+        change_repo(&repo_path, |root| git_stage::stage_file(root, &file_path))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn unstage_file(repo_path: String, file_path: String) -> Result<(), KiriError> {
+    run_blocking_coded("unstage_file", move || {
+        change_repo(&repo_path, |root| git_stage::unstage_file(root, &file_path))
+    })
+    .await
+}
+
+/// Stage one hunk of the unstaged diff of `file_path`, named by the start
+/// lines of its `@@ -old_start +new_start @@` header.
+#[tauri::command]
+pub async fn stage_hunk(
+    repo_path: String,
+    file_path: String,
+    old_start: u32,
+    new_start: u32,
+) -> Result<(), KiriError> {
+    run_blocking_coded("stage_hunk", move || {
+        change_repo(&repo_path, |root| {
+            git_stage::stage_hunk(root, &file_path, old_start, new_start)
+        })
+    })
+    .await
+}
+
+/// Commit what is staged, as `author` when given. With `amend` HEAD is
+/// replaced instead. Returns the new commit.
+#[tauri::command]
+pub async fn create_commit(
+    repo_path: String,
+    message: String,
+    author: Option<CommitAuthor>,
+    amend: Option<bool>,
+) -> Result<CommitInfo, KiriError> {
+    run_blocking_coded("create_commit", move || {
+        change_repo(&repo_path, |root| {
+            git_stage::create_commit(root, &message, author, amend.unwrap_or(false))
+        })
+    })
+    .await
+}
@@ -2,7 +2,7 @@
 // (rename, conflict) to fully test. Covered via E2E tests.
 
 use super::git::GitFileStatus;
-use git2::Status;
+use git2::{Delta, Status};
 
 /// Map git2 Status to GitFileStatus
 /// Returns None for unchanged files that should be skipped
@@ -45,6 +45,21 @@ pub fn map_file_status(status: Status) -> Option<GitFileStatus> {
     }
 }
 
+/// Map a git2 diff Delta to GitFileStatus (get_diff_stats)
+/// Returns None for unmodified entries
+pub fn map_delta(delta: Delta) -> Option<GitFileStatus> {
+    match delta {
+        Delta::Added => Some(GitFileStatus::Added),
+        Delta::Untracked => Some(GitFileStatus::Untracked),
+        Delta::Modified | Delta::Typechange => Some(GitFileStatus::Modified),
+        Delta::Deleted => Some(GitFileStatus::Deleted),
+        Delta::Renamed | Delta::Copied => Some(GitFileStatus::Renamed),
+        Delta::Conflicted => Some(GitFileStatus::Conflicted),
+        Delta::Ignored => Some(GitFileStatus::Ignored),
+        Delta::Unmodified | Delta::Unreadable => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,4 +138,11 @@ mod tests {
     fn test_map_file_status_current_returns_none() {
         assert_eq!(map_file_status(Status::CURRENT), None);
     }
+
+    #[test]
+    fn test_map_delta() {
+        assert_eq!(map_delta(Delta::Untracked), Some(GitFileStatus::Untracked));
+        assert_eq!(map_delta(Delta::Copied), Some(GitFileStatus::Renamed));
+        assert_eq!(map_delta(Delta::Unmodified), None);
+    }
 }
@@ -0,0 +1,587 @@
+//! Git worktree listing via git2.
+//!
+//! Used by the Worktrees menu and exposed to the frontend as
+//! `list_worktrees`. Works from the main checkout or from any linked
+//! worktree: both resolve to the same common repository, so every window
+//! of a repo sees the same list.
+//!
+//! Worktree metadata lives in the common repository's config, keyed by
+//! branch (`branch.<name>.kiri-issue-*`), so it follows the branch and is
+//! visible from every worktree.
+
+use git2::{Repository, WorktreeLockStatus};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use super::confirmation::{require_confirmation, PrivilegedAction};
+use super::documents::require_no_unsaved;
+use super::error::KiriError;
+use super::fs_path::{display_path, input_path, normalize_input_path};
+use super::performance::CommandTimer;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct WorktreeInfo {
+    /// Worktree name (`git worktree list` shows the directory; git2 names
+    /// linked worktrees after it). The main checkout is named after its
+    /// directory as well.
+    pub name: String,
+    pub path: String,
+    pub branch: Option<String>,
+    pub is_main: bool,
+    pub is_locked: bool,
+    /// Issue the branch was created for, see [`set_branch_issue`]
+    pub issue: Option<IssueLink>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct IssueLink {
+    /// As shown by the tracker: `#123` or `PROJ-42`
+    pub id: String,
+    pub title: String,
+    pub url: String,
+}
+
+fn issue_key(branch: &str, field: &str) -> String {
+    format!("branch.{}.kiri-issue-{}", branch, field)
+}
+
+fn branch_issue(config: &git2::Config, branch: &str) -> Option<IssueLink> {
+    let get = |field| config.get_string(&issue_key(branch, field)).ok();
+    Some(IssueLink {
+        id: get("id")?,
+        title: get("title").unwrap_or_default(),
+        url: get("url").unwrap_or_default(),
+    })
+}
+
+/// Record the issue `branch` was created for. Shows up as
+/// [`WorktreeInfo::issue`] on every worktree that checks it out.
+pub fn set_branch_issue(repo_path: &Path, branch: &str, issue: &IssueLink) -> Result<(), String> {
+    let repo = open_common_repository(repo_path)?;
+    let mut config = repo
+        .config()
+        .and_then(|c| c.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| format!("Failed to open repository config: {}", e))?;
+    for (field, value) in [
+        ("id", &issue.id),
+        ("title", &issue.title),
+        ("url", &issue.url),
+    ] {
+        config
+            .set_str(&issue_key(branch, field), value)
+            .map_err(|e| format!("Failed to record issue for {}: {}", branch, e))?;
+    }
+    Ok(())
+}
+
+fn head_branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if head.is_branch() {
+        head.shorthand().map(str::to_string)
+    } else {
+        None
+    }
+}
+
+/// git2 reports workdirs with a trailing slash; strip it so paths compare
+/// equal to the ones the frontend registers windows with.
+fn trim_trailing_separator(path: &Path) -> &Path {
+    path.components().as_path()
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| display_path(path))
+}
+
+/// Open the repository that owns the worktree list: for a linked worktree
+/// that is the main repository, reached through the `commondir` file in
+/// its `.git/worktrees/<name>` directory.
+pub fn open_common_repository(path: &Path) -> Result<Repository, String> {
+    let repo = Repository::discover(path).map_err(|e| format!("Not a git repository: {}", e))?;
+    if !repo.is_worktree() {
+        return Ok(repo);
+    }
+    let gitdir = repo.path();
+    let common = std::fs::read_to_string(gitdir.join("commondir"))
+        .map_err(|e| format!("Failed to read worktree commondir: {}", e))?;
+    let common = Path::new(common.trim());
+    let common = if common.is_absolute() {
+        common.to_path_buf()
+    } else {
+        gitdir.join(common)
+    };
+    Repository::open(common).map_err(|e| format!("Failed to open main repository: {}", e))
+}
+
+/// Path of the checkout `path` belongs to (main checkout or linked
+/// worktree), used to tell which menu entry is the current one.
+pub fn current_worktree_root(path: &Path) -> Option<PathBuf> {
+    Repository::discover(path).ok().and_then(|repo| {
+        repo.workdir()
+            .map(|p| trim_trailing_separator(p).to_path_buf())
+    })
+}
+
+/// List the main checkout followed by linked worktrees in name order.
+/// Worktrees whose directory no longer exists (prunable) are skipped.
+pub fn list_worktrees_for(path: &Path) -> Result<Vec<WorktreeInfo>, String> {
+    let _span = tracing::info_span!("worktree::list", path = %path.display()).entered();
+    let repo = open_common_repository(path)?;
+    let config = repo.config().ok();
+    let issue = |branch: &Option<String>| -> Option<IssueLink> {
+        branch_issue(config.as_ref()?, branch.as_deref()?)
+    };
+    let mut worktrees = Vec::new();
+
+    if let Some(workdir) = repo.workdir() {
+        let branch = head_branch(&repo);
+        worktrees.push(WorktreeInfo {
+            name: dir_name(workdir),
+            path: display_path(trim_trailing_separator(workdir)),
+            issue: issue(&branch),
+            branch,
+            is_main: true,
+            is_locked: false,
+        });
+    }
+
+    let names = repo
+        .worktrees()
+        .map_err(|e| format!("Failed to list worktrees: {}", e))?;
+    let mut linked: Vec<WorktreeInfo> = names
+        .iter()
+        .flatten()
+        .filter_map(|name| {
+            let worktree = repo.find_worktree(name).ok()?;
+            if worktree.validate().is_err() {
+                return None;
+            }
+            let branch = Repository::open_from_worktree(&worktree)
+                .ok()
+                .and_then(|r| head_branch(&r));
+            Some(WorktreeInfo {
+                name: name.to_string(),
+                path: display_path(trim_trailing_separator(worktree.path())),
+                issue: issue(&branch),
+                branch,
+                is_main: false,
+                is_locked: matches!(worktree.is_locked(), Ok(WorktreeLockStatus::Locked(_))),
+            })
+        })
+        .collect();
+    linked.sort_by(|a, b| a.name.cmp(&b.name));
+    worktrees.extend(linked);
+    Ok(worktrees)
+}
+
+/// Reject branch names git would refuse, plus anything starting with `-`
+/// so a name can never be read as a `git worktree` option.
+pub fn validate_branch_name(branch: &str) -> Result<(), String> {
+    let invalid = branch.is_empty()
+        || branch.starts_with('-')
+        || branch.starts_with('/')
+        || branch.ends_with('/')
+        || branch.ends_with('.')
+        || branch.ends_with(".lock")
+        || branch.contains("..")
+        || branch.contains("//")
+        || branch.contains("@{")
+        || branch
+            .chars()
+            .any(|c| c.is_control() || " ~^:?*[\\".contains(c));
+    if invalid {
+        Err(format!("Invalid branch name: {}", branch))
+    } else {
+        Ok(())
+    }
+}
+
+/// Default location for a new worktree: a sibling of the main checkout
+/// named `<repo>-<branch>`, with `/` in the branch replaced by `-`.
+pub fn default_worktree_path(main_checkout: &Path, branch: &str) -> PathBuf {
+    let repo_name = dir_name(main_checkout);
+    let dir = format!("{}-{}", repo_name, branch.replace('/', "-"));
+    main_checkout
+        .parent()
+        .map(|p| p.join(&dir))
+        .unwrap_or_else(|| PathBuf::from(dir))
+}
+
+/// Create a worktree for `branch` in the repository containing `repo_path`.
+///
+/// An existing local branch is checked out as-is; a branch that only
+/// exists on a remote is created tracking it (git's DWIM); otherwise a new
+/// branch is created from HEAD. Returns the new worktree. If a worktree
+/// for the branch already exists it is returned unchanged.
+pub fn create_worktree_for(
+    repo_path: &Path,
+    branch: &str,
+    target: Option<&Path>,
+) -> Result<WorktreeInfo, String> {
+    let _span = tracing::info_span!("worktree::create", branch).entered();
+    validate_branch_name(branch)?;
+    let repo = open_common_repository(repo_path)?;
+    let main_checkout = repo
+        .workdir()
+        .map(|p| trim_trailing_separator(p).to_path_buf())
+        .ok_or_else(|| "Repository has no working directory".to_string())?;
+
+    if let Some(existing) = list_worktrees_for(&main_checkout)?
+        .into_iter()
+        .find(|w| w.branch.as_deref() == Some(branch))
+    {
+        return Ok(existing);
+    }
+
+    let target = target
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| default_worktree_path(&main_checkout, branch));
+    if target.exists() {
+        return Err(format!(
+            "Worktree target already exists: {}",
+            display_path(&target)
+        ));
+    }
+
+    let local_exists = repo.find_branch(branch, git2::BranchType::Local).is_ok();
+    let remote_exists = repo
+        .branches(Some(git2::BranchType::Remote))
+        .map(|branches| {
+            branches.flatten().any(|(b, _)| {
+                b.name()
+                    .ok()
+                    .flatten()
+                    .and_then(|n| n.split_once('/'))
+                    .is_some_and(|(_, name)| name == branch)
+            })
+        })
+        .unwrap_or(false);
+
+    let target_str = target.to_string_lossy().to_string();
+    let mut args = vec!["worktree", "add"];
+    if !local_exists && !remote_exists {
+        args.extend(["-b", branch, "--", target_str.as_str()]);
+    } else {
+        args.extend(["--", target_str.as_str(), branch]);
+    }
+
+    let output = tracing::info_span!("worktree::git_add")
+        .in_scope(|| {
+            std::process::Command::new("git")
+                .args(&args)
+                .current_dir(&main_checkout)
+                // Clear inherited GIT_DIR/GIT_WORK_TREE so git operates on the
+                // target repo, not the parent worktree (e.g. during pre-commit hooks).
+                .env_remove("GIT_DIR")
+                .env_remove("GIT_WORK_TREE")
+                .output()
+        })
+        .map_err(|e| format!("Failed to execute git worktree add: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git worktree add failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let target_display = display_path(&target);
+    list_worktrees_for(&main_checkout)?
+        .into_iter()
+        .find(|w| w.path == target_display || w.branch.as_deref() == Some(branch))
+        .ok_or_else(|| "Worktree was created but could not be found".to_string())
+}
+
+/// Remove the linked worktree at `target` from the repository containing
+/// `repo_path`. Unless `force` is set, git refuses to remove a worktree
+/// with uncommitted changes or untracked files. The main checkout can't be
+/// removed.
+pub fn remove_worktree_for(repo_path: &Path, target: &Path, force: bool) -> Result<(), String> {
+    let _span = tracing::info_span!("worktree::remove", force).entered();
+    let repo = open_common_repository(repo_path)?;
+    let main_checkout = repo
+        .workdir()
+        .map(|p| trim_trailing_separator(p).to_path_buf())
+        .ok_or_else(|| "Repository has no working directory".to_string())?;
+
+    // git2 may report the path through a different spelling of a symlinked
+    // parent (e.g. /var and /private/var on macOS).
+    let canonical = |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.into());
+    let wanted = canonical(target);
+    let worktree = list_worktrees_for(&main_checkout)?
+        .into_iter()
+        .find(|w| canonical(Path::new(&w.path)) == wanted)
+        .ok_or_else(|| {
+            format!(
+                "Not a worktree of this repository: {}",
+                display_path(target)
+            )
+        })?;
+    if worktree.is_main {
+        return Err("The main checkout can't be removed".to_string());
+    }
+
+    let mut args = vec!["worktree", "remove"];
+    if force {
+        args.push("--force");
+    }
+    args.extend(["--", worktree.path.as_str()]);
+    let output = std::process::Command::new("git")
+        .args(&args)
+        .current_dir(&main_checkout)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .output()
+        .map_err(|e| format!("Failed to execute git worktree remove: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git worktree remove failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn create_worktree(
+    repo_path: String,
+    branch: String,
+    path: Option<String>,
+) -> Result<WorktreeInfo, KiriError> {
+    let _timer = CommandTimer::start("create_worktree");
+    tokio::task::spawn_blocking(move || {
+        let target = path.map(|p| normalize_input_path(&p));
+        create_worktree_for(
+            &normalize_input_path(&repo_path),
+            &branch,
+            target.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| format!("create_worktree task panicked: {}", e))?
+    .map_err(KiriError::from)
+}
+
+/// Remove the linked worktree at `path`. With `force` its uncommitted
+/// changes and untracked files go too, which needs a token from
+/// `request_confirmation` for [`PrivilegedAction::ForceRemoveWorktree`] on
+/// `path`. A worktree with documents open with unsaved changes is kept
+/// unless `discard_unsaved` is set.
+#[tauri::command]
+pub async fn remove_worktree(
+    repo_path: String,
+    path: String,
+    force: bool,
+    confirmation_token: Option<String>,
+    discard_unsaved: Option<bool>,
+) -> Result<(), KiriError> {
+    let target = input_path(&path)?;
+    if !discard_unsaved.unwrap_or(false) {
+        require_no_unsaved(&target)?;
+    }
+    if force {
+        require_confirmation(
+            confirmation_token.as_deref(),
+            PrivilegedAction::ForceRemoveWorktree,
+            &target,
+        )?;
+    }
+    let _timer = CommandTimer::start("remove_worktree");
+    tokio::task::spawn_blocking(move || {
+        remove_worktree_for(&normalize_input_path(&repo_path), &target, force)
+    })
+    .await
+    .map_err(|e| format!("remove_worktree task panicked: {}", e))?
+    .map_err(KiriError::from)
+}
+
+#[tauri::command]
+pub async fn list_worktrees(path: String) -> Result<Vec<WorktreeInfo>, KiriError> {
+    let _timer = CommandTimer::start("list_worktrees");
+    tokio::task::spawn_blocking(move || list_worktrees_for(&normalize_input_path(&path)))
+        .await
+        .map_err(|e| format!("list_worktrees task panicked: {}", e))?
+        .map_err(KiriError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_INDEX_FILE")
+            .output()
+            .expect("run git");
+        assert!(
+            status.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&status.stderr)
+        );
+    }
+
+    fn init_repo(dir: &Path) {
+        git(dir, &["init", "-q", "-b", "main"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(dir, &["config", "user.name", "Test"]);
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "init"]);
+    }
+
+    #[test]
+    fn test_list_worktrees_main_only() {
+        let dir = tempdir().unwrap();
+        let repo_dir = dir.path().join("repo");
+        std::fs::create_dir(&repo_dir).unwrap();
+        init_repo(&repo_dir);
+
+        let worktrees = list_worktrees_for(&repo_dir).unwrap();
+        assert_eq!(worktrees.len(), 1);
+        assert!(worktrees[0].is_main);
+        assert_eq!(worktrees[0].name, "repo");
+        assert_eq!(worktrees[0].branch.as_deref(), Some("main"));
+        assert!(!worktrees[0].path.ends_with('/'));
+    }
+
+    #[test]
+    fn test_list_worktrees_from_linked_worktree() {
+        let dir = tempdir().unwrap();
+        let repo_dir = dir.path().join("repo");
+        std::fs::create_dir(&repo_dir).unwrap();
+        init_repo(&repo_dir);
+        let wt_dir = dir.path().join("feature-wt");
+        git(
+            &repo_dir,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "-b",
+                "feature",
+                wt_dir.to_str().unwrap(),
+            ],
+        );
+
+        let from_main = list_worktrees_for(&repo_dir).unwrap();
+        let from_linked = list_worktrees_for(&wt_dir).unwrap();
+        assert_eq!(from_main, from_linked);
+        assert_eq!(from_main.len(), 2);
+        assert!(!from_main[1].is_main);
+        assert_eq!(from_main[1].branch.as_deref(), Some("feature"));
+
+        let current = current_worktree_root(&wt_dir.join("a.txt")).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(current).unwrap(),
+            std::fs::canonicalize(&wt_dir).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_remove_worktree_needs_force_for_local_changes() {
+        let dir = tempdir().unwrap();
+        let repo_dir = dir.path().join("repo");
+        std::fs::create_dir(&repo_dir).unwrap();
+        init_repo(&repo_dir);
+        let wt_dir = dir.path().join("feature-wt");
+        git(
+            &repo_dir,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "-b",
+                "feature",
+                wt_dir.to_str().unwrap(),
+            ],
+        );
+        std::fs::write(wt_dir.join("scratch.txt"), "wip").unwrap();
+
+        assert!(remove_worktree_for(&repo_dir, &repo_dir, true).is_err());
+        assert!(remove_worktree_for(&repo_dir, &wt_dir, false).is_err());
+        assert!(wt_dir.exists());
+        remove_worktree_for(&repo_dir, &wt_dir, true).unwrap();
+        assert!(!wt_dir.exists());
+        assert_eq!(list_worktrees_for(&repo_dir).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_validate_branch_name() {
+        assert!(validate_branch_name("feature/login").is_ok());
+        assert!(validate_branch_name("fix-123").is_ok());
+        assert!(validate_branch_name("").is_err());
+        assert!(validate_branch_name("-f").is_err());
+        assert!(validate_branch_name("a..b").is_err());
+        assert!(validate_branch_name("has space").is_err());
+        assert!(validate_branch_name("x.lock").is_err());
+    }
+
+    #[test]
+    fn test_default_worktree_path() {
+        assert_eq!(
+            default_worktree_path(Path::new("/src/kiri"), "feature/a"),
+            PathBuf::from("/src/kiri-feature-a")
+        );
+    }
+
+    #[test]
+    fn test_create_worktree_new_and_existing_branch() {
+        let dir = tempdir().unwrap();
+        let repo_dir = dir.path().join("repo");
+        std::fs::create_dir(&repo_dir).unwrap();
+        init_repo(&repo_dir);
+        git(&repo_dir, &["branch", "existing"]);
+
+        let created = create_worktree_for(&repo_dir, "feature/new", None).unwrap();
+        assert_eq!(created.branch.as_deref(), Some("feature/new"));
+        assert!(created.path.ends_with("repo-feature-new"));
+
+        let target = dir.path().join("custom");
+        let existing = create_worktree_for(&repo_dir, "existing", Some(&target)).unwrap();
+        assert_eq!(existing.branch.as_deref(), Some("existing"));
+        assert!(target.join("a.txt").exists());
+
+        // Asking again for the same branch returns the existing worktree.
+        let again = create_worktree_for(&repo_dir, "existing", None).unwrap();
+        assert_eq!(again, existing);
+    }
+
+    #[test]
+    fn test_branch_issue_shows_on_its_worktree() {
+        let dir = tempdir().unwrap();
+        let repo_dir = dir.path().join("repo");
+        std::fs::create_dir(&repo_dir).unwrap();
+        init_repo(&repo_dir);
+        let created = create_worktree_for(&repo_dir, "42-fix-crash", None).unwrap();
+        assert_eq!(created.issue, None);
+
+        let issue = IssueLink {
+            id: "#42".to_string(),
+            title: "Fix crash".to_string(),
+            url: "https://github.com/o/r/issues/42".to_string(),
+        };
+        // Recorded from the linked worktree, read back from the main one.
+        set_branch_issue(Path::new(&created.path), "42-fix-crash", &issue).unwrap();
+        let worktrees = list_worktrees_for(&repo_dir).unwrap();
+        assert_eq!(worktrees[0].issue, None);
+        assert_eq!(worktrees[1].issue.as_ref(), Some(&issue));
+    }
+
+    #[test]
+    fn test_list_worktrees_not_a_repo() {
+        let dir = tempdir().unwrap();
+        // A tempdir might sit inside a checkout on dev machines; only
+        // assert on a path that cannot be discovered.
+        if Repository::discover(dir.path()).is_err() {
+            assert!(list_worktrees_for(dir.path()).is_err());
+        }
+    }
+}
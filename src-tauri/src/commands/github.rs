@@ -0,0 +1,560 @@
+//! GitHub: device-flow sign-in, the [`CodeHost`] implementation, and
+//! [`GitHubIssues`] for issues.rs.
+//!
+//! Sign-in uses the OAuth device flow. The frontend shows the user code,
+//! the user approves it on github.com, and the frontend polls until a
+//! token comes back, which is saved like every host token (see
+//! code_host.rs). The OAuth app's client id comes from
+//! `KIRI_GITHUB_CLIENT_ID`, read at runtime or at build time. The sign-in
+//! commands live in github_commands.rs; signing in through a browser
+//! redirect instead is in oauth.rs.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::code_host::{
+    http_client, save_token, send_json, CheckRun, CodeHost, HostKind, NewPullRequest,
+    PullRequestSummary, RemoteRepo, Review,
+};
+use super::error::{ErrorCode, KiriError};
+use super::issues::{parse_issue_number, Issue, IssueProvider, IssueTracker};
+
+pub const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_LOGIN: &str = "https://github.com/login";
+const CLIENT_ID_ENV: &str = "KIRI_GITHUB_CLIENT_ID";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    pub interval: u64,
+}
+
+/// Outcome of one device-flow poll.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DevicePoll {
+    Pending,
+    /// Poll less often, every `interval` seconds from now on
+    SlowDown {
+        interval: u64,
+    },
+    Authorized {
+        login: String,
+    },
+    Expired,
+    Denied,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitHubAuthStatus {
+    pub configured: bool,
+    pub authenticated: bool,
+    pub login: Option<String>,
+}
+
+// --- GitHub API payloads ----------------------------------------------------
+
+#[derive(Deserialize)]
+struct ApiUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct ApiRef {
+    #[serde(rename = "ref")]
+    name: String,
+    sha: String,
+    repo: Option<ApiRepo>,
+}
+
+#[derive(Deserialize)]
+struct ApiRepo {
+    full_name: String,
+    #[serde(default)]
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiPull {
+    number: u64,
+    title: String,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    merged_at: Option<String>,
+    html_url: String,
+    updated_at: String,
+    user: Option<ApiUser>,
+    head: ApiRef,
+    base: ApiRef,
+}
+
+impl ApiPull {
+    fn into_summary(self) -> PullRequestSummary {
+        let state = if self.merged_at.is_some() {
+            "merged".to_string()
+        } else {
+            self.state
+        };
+        // A deleted fork leaves `head.repo` empty.
+        let from_fork = match (&self.head.repo, &self.base.repo) {
+            (Some(head), Some(base)) => head.full_name != base.full_name,
+            _ => true,
+        };
+        PullRequestSummary {
+            number: self.number,
+            title: self.title,
+            state,
+            draft: self.draft,
+            author: self.user.map(|u| u.login).unwrap_or_default(),
+            head_branch: self.head.name,
+            head_sha: self.head.sha,
+            base_branch: self.base.name,
+            from_fork,
+            url: self.html_url,
+            updated_at: self.updated_at,
+            worktree_path: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiCheckRuns {
+    check_runs: Vec<ApiCheckRun>,
+}
+
+#[derive(Deserialize)]
+struct ApiCheckRun {
+    name: String,
+    status: String,
+    conclusion: Option<String>,
+    html_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiReview {
+    user: Option<ApiUser>,
+    state: String,
+    submitted_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiToken {
+    access_token: Option<String>,
+    error: Option<String>,
+    interval: Option<u64>,
+}
+
+fn parse_device_poll(token: ApiToken) -> Result<(DevicePoll, Option<String>), String> {
+    if let Some(access_token) = token.access_token {
+        return Ok((
+            DevicePoll::Authorized {
+                login: String::new(),
+            },
+            Some(access_token),
+        ));
+    }
+    let poll = match token.error.as_deref() {
+        Some("authorization_pending") => DevicePoll::Pending,
+        Some("slow_down") => DevicePoll::SlowDown {
+            interval: token.interval.unwrap_or(10),
+        },
+        Some("expired_token") => DevicePoll::Expired,
+        Some("access_denied") => DevicePoll::Denied,
+        Some(other) => return Err(format!("GitHub sign-in failed: {}", other)),
+        None => return Err("GitHub sign-in failed: empty response".to_string()),
+    };
+    Ok((poll, None))
+}
+
+/// OAuth client id of the kiri GitHub app, if this build has one.
+pub fn client_id() -> Option<String> {
+    std::env::var(CLIENT_ID_ENV)
+        .ok()
+        .or_else(|| option_env!("KIRI_GITHUB_CLIENT_ID").map(str::to_string))
+        .filter(|id| !id.is_empty())
+}
+
+// --- Client -----------------------------------------------------------------
+
+pub struct GitHubClient {
+    http: reqwest::Client,
+    token: Option<String>,
+}
+
+impl GitHubClient {
+    pub fn new(token: Option<String>) -> Result<Self, String> {
+        Ok(Self {
+            http: http_client()?,
+            token,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", GITHUB_API, path))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, KiriError> {
+        send_json(HostKind::GitHub, self.request(reqwest::Method::GET, path)).await
+    }
+
+    pub async fn current_user(&self) -> Result<String, KiriError> {
+        Ok(self.get::<ApiUser>("/user").await?.login)
+    }
+
+    /// Start the device flow. Show `user_code` and `verification_uri` to
+    /// the user, then call [`Self::poll_device_flow`] every `interval`.
+    pub async fn start_device_flow(&self, client_id: &str) -> Result<DeviceCode, KiriError> {
+        let request = self
+            .http
+            .post(format!("{}/device/code", GITHUB_LOGIN))
+            .header("Accept", "application/json")
+            .form(&[("client_id", client_id), ("scope", "repo")]);
+        send_json(HostKind::GitHub, request).await
+    }
+
+    /// Poll once. On success the token is saved and the login returned.
+    pub async fn poll_device_flow(
+        &self,
+        client_id: &str,
+        device_code: &str,
+    ) -> Result<DevicePoll, KiriError> {
+        let request = self
+            .http
+            .post(format!("{}/oauth/access_token", GITHUB_LOGIN))
+            .header("Accept", "application/json")
+            .form(&[
+                ("client_id", client_id),
+                ("device_code", device_code),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ]);
+        let (poll, token) = parse_device_poll(send_json(HostKind::GitHub, request).await?)?;
+        let Some(token) = token else {
+            return Ok(poll);
+        };
+        let login = GitHubClient::new(Some(token.clone()))?
+            .current_user()
+            .await?;
+        save_token(HostKind::GitHub, &token)?;
+        Ok(DevicePoll::Authorized { login })
+    }
+}
+
+#[async_trait]
+impl CodeHost for GitHubClient {
+    fn kind(&self) -> HostKind {
+        HostKind::GitHub
+    }
+
+    async fn default_branch(&self, repo: &RemoteRepo) -> Result<String, KiriError> {
+        let info: ApiRepo = self.get(&format!("/repos/{}", repo.path())).await?;
+        info.default_branch.ok_or_else(|| {
+            KiriError::new(
+                ErrorCode::NotFound,
+                format!("{} has no default branch", info.full_name),
+            )
+        })
+    }
+
+    async fn list_pulls(
+        &self,
+        repo: &RemoteRepo,
+        state: &str,
+    ) -> Result<Vec<PullRequestSummary>, KiriError> {
+        // GitHub has no `merged` filter; merged PRs are closed ones.
+        let api_state = if state == "merged" { "closed" } else { state };
+        let pulls: Vec<ApiPull> = self
+            .get(&format!(
+                "/repos/{}/pulls?state={}&sort=updated&direction=desc&per_page=50",
+                repo.path(),
+                urlencoding::encode(api_state)
+            ))
+            .await?;
+        Ok(pulls
+            .into_iter()
+            .map(ApiPull::into_summary)
+            .filter(|pull| state != "merged" || pull.state == "merged")
+            .collect())
+    }
+
+    async fn pull_for_branch(
+        &self,
+        repo: &RemoteRepo,
+        branch: &str,
+    ) -> Result<Option<PullRequestSummary>, KiriError> {
+        let pulls: Vec<ApiPull> = self
+            .get(&format!(
+                "/repos/{}/pulls?state=all&head={}&per_page=1",
+                repo.path(),
+                urlencoding::encode(&format!("{}:{}", repo.owner, branch))
+            ))
+            .await?;
+        Ok(pulls.into_iter().next().map(ApiPull::into_summary))
+    }
+
+    async fn get_pull(
+        &self,
+        repo: &RemoteRepo,
+        number: u64,
+    ) -> Result<PullRequestSummary, KiriError> {
+        let pull: ApiPull = self
+            .get(&format!("/repos/{}/pulls/{}", repo.path(), number))
+            .await?;
+        Ok(pull.into_summary())
+    }
+
+    async fn checks(&self, repo: &RemoteRepo, sha: &str) -> Result<Vec<CheckRun>, KiriError> {
+        let runs: ApiCheckRuns = self
+            .get(&format!(
+                "/repos/{}/commits/{}/check-runs?per_page=100",
+                repo.path(),
+                sha
+            ))
+            .await?;
+        Ok(runs
+            .check_runs
+            .into_iter()
+            .map(|run| CheckRun {
+                name: run.name,
+                status: run.status,
+                conclusion: run.conclusion,
+                url: run.html_url,
+            })
+            .collect())
+    }
+
+    async fn reviews(&self, repo: &RemoteRepo, number: u64) -> Result<Vec<Review>, KiriError> {
+        let reviews: Vec<ApiReview> = self
+            .get(&format!(
+                "/repos/{}/pulls/{}/reviews?per_page=100",
+                repo.path(),
+                number
+            ))
+            .await?;
+        Ok(reviews
+            .into_iter()
+            .map(|review| Review {
+                author: review.user.map(|u| u.login).unwrap_or_default(),
+                state: review.state,
+                submitted_at: review.submitted_at,
+            })
+            .collect())
+    }
+
+    async fn create_pull(
+        &self,
+        repo: &RemoteRepo,
+        new: &NewPullRequest,
+    ) -> Result<PullRequestSummary, KiriError> {
+        let request = self
+            .request(
+                reqwest::Method::POST,
+                &format!("/repos/{}/pulls", repo.path()),
+            )
+            .json(&serde_json::json!({
+                "title": new.title,
+                "head": new.head,
+                "base": new.base,
+                "body": new.body,
+                "draft": new.draft,
+            }));
+        let pull: ApiPull = send_json(HostKind::GitHub, request).await?;
+        Ok(pull.into_summary())
+    }
+
+    fn pull_head_ref(&self, number: u64) -> Option<String> {
+        Some(format!("refs/pull/{}/head", number))
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ApiIssue {
+    number: u64,
+    title: String,
+    /// `open` or `closed`
+    state: String,
+    html_url: String,
+    #[serde(default)]
+    labels: Vec<ApiLabel>,
+    updated_at: String,
+    /// Present when the issue is a pull request
+    pull_request: Option<serde_json::Value>,
+}
+
+impl ApiIssue {
+    fn into_issue(self) -> Issue {
+        Issue {
+            provider: IssueProvider::GitHub,
+            id: format!("#{}", self.number),
+            title: self.title,
+            state: self.state,
+            url: self.html_url,
+            labels: self.labels.into_iter().map(|l| l.name).collect(),
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Issues of one repository. GitHub lists pull requests as issues too;
+/// those are left out.
+pub struct GitHubIssues {
+    client: GitHubClient,
+    repo: RemoteRepo,
+}
+
+impl GitHubIssues {
+    pub fn new(token: Option<String>, repo: RemoteRepo) -> Result<Self, String> {
+        Ok(Self {
+            client: GitHubClient::new(token)?,
+            repo,
+        })
+    }
+}
+
+#[async_trait]
+impl IssueTracker for GitHubIssues {
+    fn provider(&self) -> IssueProvider {
+        IssueProvider::GitHub
+    }
+
+    async fn assigned_issues(&self) -> Result<Vec<Issue>, KiriError> {
+        let login = self.client.current_user().await?;
+        let issues: Vec<ApiIssue> = self
+            .client
+            .get(&format!(
+                "/repos/{}/issues?assignee={}&state=open&sort=updated&per_page=50",
+                self.repo.path(),
+                urlencoding::encode(&login)
+            ))
+            .await?;
+        Ok(issues
+            .into_iter()
+            .filter(|issue| issue.pull_request.is_none())
+            .map(ApiIssue::into_issue)
+            .collect())
+    }
+
+    async fn get_issue(&self, id: &str) -> Result<Issue, KiriError> {
+        let number = parse_issue_number(id)?;
+        let issue: ApiIssue = self
+            .client
+            .get(&format!("/repos/{}/issues/{}", self.repo.path(), number))
+            .await?;
+        if issue.pull_request.is_some() {
+            return Err(KiriError::new(
+                ErrorCode::InvalidInput,
+                format!("#{} is a pull request, not an issue", number),
+            ));
+        }
+        Ok(issue.into_issue())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pull_from_api_payload() {
+        let pull: ApiPull = serde_json::from_value(serde_json::json!({
+            "number": 42,
+            "title": "Add thing",
+            "state": "closed",
+            "draft": false,
+            "merged_at": "2024-05-01T00:00:00Z",
+            "html_url": "https://github.com/o/r/pull/42",
+            "updated_at": "2024-05-01T00:00:00Z",
+            "user": {"login": "octocat"},
+            "head": {"ref": "feature/x", "sha": "abc123", "repo": {"full_name": "o/r"}},
+            "base": {"ref": "main", "sha": "def456", "repo": {"full_name": "o/r"}}
+        }))
+        .unwrap();
+        let summary = pull.into_summary();
+        assert_eq!(summary.state, "merged");
+        assert_eq!(summary.author, "octocat");
+        assert_eq!(summary.head_branch, "feature/x");
+        assert_eq!(summary.base_branch, "main");
+        assert!(!summary.from_fork);
+        assert_eq!(summary.worktree_path, None);
+    }
+
+    #[test]
+    fn test_pull_from_fork() {
+        let pull: ApiPull = serde_json::from_value(serde_json::json!({
+            "number": 7,
+            "title": "Fix",
+            "state": "open",
+            "merged_at": null,
+            "html_url": "https://github.com/o/r/pull/7",
+            "updated_at": "2024-05-01T00:00:00Z",
+            "user": null,
+            "head": {"ref": "main", "sha": "abc", "repo": {"full_name": "someone/r"}},
+            "base": {"ref": "main", "sha": "def", "repo": {"full_name": "o/r"}}
+        }))
+        .unwrap();
+        let summary = pull.into_summary();
+        assert_eq!(summary.state, "open");
+        assert!(summary.from_fork);
+        assert_eq!(summary.author, "");
+    }
+
+    #[test]
+    fn test_issue_from_api_payload() {
+        let issue: ApiIssue = serde_json::from_value(serde_json::json!({
+            "number": 42,
+            "title": "Crash on start",
+            "state": "open",
+            "html_url": "https://github.com/o/r/issues/42",
+            "labels": [{"name": "bug"}, {"name": "p1"}],
+            "updated_at": "2024-05-01T00:00:00Z"
+        }))
+        .unwrap();
+        assert!(issue.pull_request.is_none());
+        let issue = issue.into_issue();
+        assert_eq!(issue.id, "#42");
+        assert_eq!(issue.labels, vec!["bug", "p1"]);
+    }
+
+    #[test]
+    fn test_parse_device_poll() {
+        let token = |json| serde_json::from_value::<ApiToken>(json).unwrap();
+        assert_eq!(
+            parse_device_poll(token(serde_json::json!({"error": "authorization_pending"})))
+                .unwrap(),
+            (DevicePoll::Pending, None)
+        );
+        assert_eq!(
+            parse_device_poll(token(
+                serde_json::json!({"error": "slow_down", "interval": 15})
+            ))
+            .unwrap()
+            .0,
+            DevicePoll::SlowDown { interval: 15 }
+        );
+        assert_eq!(
+            parse_device_poll(token(serde_json::json!({"access_token": "gho_x"})))
+                .unwrap()
+                .1
+                .as_deref(),
+            Some("gho_x")
+        );
+        assert!(parse_device_poll(token(serde_json::json!({"error": "bad"}))).is_err());
+    }
+}
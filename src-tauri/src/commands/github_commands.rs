@@ -0,0 +1,48 @@
+//! Tauri commands for signing in to GitHub; see github.rs. Pull request
+//! commands for every host live in code_host_commands.rs.
+
+use super::code_host::{self, HostKind};
+use super::error::KiriError;
+use super::github::{self, DeviceCode, DevicePoll, GitHubAuthStatus, GitHubClient};
+
+fn client_id() -> Result<String, String> {
+    github::client_id().ok_or_else(|| "GitHub sign-in is not configured in this build".to_string())
+}
+
+#[tauri::command]
+pub async fn github_auth_status() -> Result<GitHubAuthStatus, KiriError> {
+    let configured = github::client_id().is_some();
+    let Some(token) = code_host::load_token(HostKind::GitHub) else {
+        return Ok(GitHubAuthStatus {
+            configured,
+            authenticated: false,
+            login: None,
+        });
+    };
+    // A revoked token reads as signed out rather than as an error.
+    let login = GitHubClient::new(Some(token))?.current_user().await.ok();
+    Ok(GitHubAuthStatus {
+        configured,
+        authenticated: login.is_some(),
+        login,
+    })
+}
+
+#[tauri::command]
+pub async fn github_start_device_flow() -> Result<DeviceCode, KiriError> {
+    GitHubClient::new(None)?
+        .start_device_flow(&client_id()?)
+        .await
+}
+
+#[tauri::command]
+pub async fn github_poll_device_flow(device_code: String) -> Result<DevicePoll, KiriError> {
+    GitHubClient::new(None)?
+        .poll_device_flow(&client_id()?, &device_code)
+        .await
+}
+
+#[tauri::command]
+pub fn github_sign_out() -> Result<(), KiriError> {
+    code_host::clear_token(HostKind::GitHub).map_err(KiriError::from)
+}
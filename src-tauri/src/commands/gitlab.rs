@@ -0,0 +1,425 @@
+//! GitLab implementation of [`CodeHost`], for gitlab.com and self-hosted
+//! instances. Merge requests are reported as pull requests numbered by
+//! their iid, and commit statuses (one per pipeline job) as checks.
+//! Approvals are the only review verdicts GitLab reports.
+//!
+//! [`GitLabIssues`] lists the project's issues for issues.rs.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::code_host::{
+    http_client, send_json, CheckRun, CodeHost, HostKind, NewPullRequest, PullRequestSummary,
+    RemoteRepo, Review,
+};
+use super::error::{ErrorCode, KiriError};
+use super::issues::{parse_issue_number, Issue, IssueProvider, IssueTracker};
+
+#[derive(Deserialize)]
+struct ApiUser {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct ApiProject {
+    path_with_namespace: String,
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiMergeRequest {
+    iid: u64,
+    title: String,
+    /// `opened`, `closed`, `locked` or `merged`
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    author: Option<ApiUser>,
+    source_branch: String,
+    target_branch: String,
+    source_project_id: u64,
+    target_project_id: u64,
+    sha: Option<String>,
+    web_url: String,
+    updated_at: String,
+}
+
+impl ApiMergeRequest {
+    fn into_summary(self) -> PullRequestSummary {
+        let state = match self.state.as_str() {
+            "opened" | "locked" => "open",
+            "merged" => "merged",
+            _ => "closed",
+        };
+        PullRequestSummary {
+            number: self.iid,
+            title: self.title,
+            state: state.to_string(),
+            draft: self.draft,
+            author: self.author.map(|u| u.username).unwrap_or_default(),
+            head_branch: self.source_branch,
+            head_sha: self.sha.unwrap_or_default(),
+            base_branch: self.target_branch,
+            from_fork: self.source_project_id != self.target_project_id,
+            url: self.web_url,
+            updated_at: self.updated_at,
+            worktree_path: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiCommitStatus {
+    name: String,
+    status: String,
+    target_url: Option<String>,
+}
+
+/// Map a job status onto the GitHub check vocabulary used by
+/// [`CheckRun`]. Manual jobs don't run unless started, so they neither
+/// pass nor block.
+fn check_from_status(status: ApiCommitStatus) -> CheckRun {
+    let (state, conclusion) = match status.status.as_str() {
+        "success" => ("completed", Some("success")),
+        "failed" => ("completed", Some("failure")),
+        "canceled" => ("completed", Some("cancelled")),
+        "skipped" => ("completed", Some("skipped")),
+        "manual" => ("completed", Some("neutral")),
+        "running" => ("in_progress", None),
+        _ => ("queued", None),
+    };
+    CheckRun {
+        name: status.name,
+        status: state.to_string(),
+        conclusion: conclusion.map(str::to_string),
+        url: status.target_url,
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiApprovals {
+    #[serde(default)]
+    approved_by: Vec<ApiApprover>,
+}
+
+#[derive(Deserialize)]
+struct ApiApprover {
+    user: ApiUser,
+}
+
+/// GitLab filters by `opened`; `all` is the default when no state is sent.
+fn state_filter(state: &str) -> Option<&str> {
+    match state {
+        "open" => Some("opened"),
+        "all" => None,
+        other => Some(other),
+    }
+}
+
+pub struct GitLabClient {
+    http: reqwest::Client,
+    api: String,
+    token: Option<String>,
+}
+
+impl GitLabClient {
+    /// Client for the instance at `host`, e.g. `gitlab.com`.
+    pub fn new(host: &str, token: Option<String>) -> Result<Self, String> {
+        Ok(Self {
+            http: http_client()?,
+            api: format!("https://{}/api/v4", host),
+            token,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self.http.request(method, format!("{}{}", self.api, path));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, KiriError> {
+        send_json(HostKind::GitLab, self.request(reqwest::Method::GET, path)).await
+    }
+
+    pub async fn current_user(&self) -> Result<String, KiriError> {
+        Ok(self.get::<ApiUser>("/user").await?.username)
+    }
+}
+
+/// Projects are addressed by their URL-encoded path.
+fn project(repo: &RemoteRepo) -> String {
+    format!("/projects/{}", urlencoding::encode(&repo.path()))
+}
+
+#[async_trait]
+impl CodeHost for GitLabClient {
+    fn kind(&self) -> HostKind {
+        HostKind::GitLab
+    }
+
+    async fn default_branch(&self, repo: &RemoteRepo) -> Result<String, KiriError> {
+        let info: ApiProject = self.get(&project(repo)).await?;
+        info.default_branch.ok_or_else(|| {
+            KiriError::new(
+                ErrorCode::NotFound,
+                format!("{} has no default branch", info.path_with_namespace),
+            )
+        })
+    }
+
+    async fn list_pulls(
+        &self,
+        repo: &RemoteRepo,
+        state: &str,
+    ) -> Result<Vec<PullRequestSummary>, KiriError> {
+        let mut path = format!(
+            "{}/merge_requests?order_by=updated_at&sort=desc&per_page=50",
+            project(repo)
+        );
+        if let Some(state) = state_filter(state) {
+            path.push_str(&format!("&state={}", urlencoding::encode(state)));
+        }
+        let requests: Vec<ApiMergeRequest> = self.get(&path).await?;
+        Ok(requests
+            .into_iter()
+            .map(ApiMergeRequest::into_summary)
+            .collect())
+    }
+
+    async fn pull_for_branch(
+        &self,
+        repo: &RemoteRepo,
+        branch: &str,
+    ) -> Result<Option<PullRequestSummary>, KiriError> {
+        let requests: Vec<ApiMergeRequest> = self
+            .get(&format!(
+                "{}/merge_requests?source_branch={}&order_by=updated_at&sort=desc&per_page=20",
+                project(repo),
+                urlencoding::encode(branch)
+            ))
+            .await?;
+        // The filter also matches same-named branches in forks.
+        Ok(requests
+            .into_iter()
+            .map(ApiMergeRequest::into_summary)
+            .find(|pull| !pull.from_fork))
+    }
+
+    async fn get_pull(
+        &self,
+        repo: &RemoteRepo,
+        number: u64,
+    ) -> Result<PullRequestSummary, KiriError> {
+        let request: ApiMergeRequest = self
+            .get(&format!("{}/merge_requests/{}", project(repo), number))
+            .await?;
+        Ok(request.into_summary())
+    }
+
+    async fn checks(&self, repo: &RemoteRepo, sha: &str) -> Result<Vec<CheckRun>, KiriError> {
+        let statuses: Vec<ApiCommitStatus> = self
+            .get(&format!(
+                "{}/repository/commits/{}/statuses?per_page=100",
+                project(repo),
+                sha
+            ))
+            .await?;
+        Ok(statuses.into_iter().map(check_from_status).collect())
+    }
+
+    async fn reviews(&self, repo: &RemoteRepo, number: u64) -> Result<Vec<Review>, KiriError> {
+        let approvals: ApiApprovals = self
+            .get(&format!(
+                "{}/merge_requests/{}/approvals",
+                project(repo),
+                number
+            ))
+            .await?;
+        Ok(approvals
+            .approved_by
+            .into_iter()
+            .map(|approver| Review {
+                author: approver.user.username,
+                state: "APPROVED".to_string(),
+                submitted_at: None,
+            })
+            .collect())
+    }
+
+    async fn create_pull(
+        &self,
+        repo: &RemoteRepo,
+        new: &NewPullRequest,
+    ) -> Result<PullRequestSummary, KiriError> {
+        // Drafts are marked by a title prefix.
+        let title = if new.draft {
+            format!("Draft: {}", new.title)
+        } else {
+            new.title.clone()
+        };
+        let request = self
+            .request(
+                reqwest::Method::POST,
+                &format!("{}/merge_requests", project(repo)),
+            )
+            .json(&serde_json::json!({
+                "source_branch": new.head,
+                "target_branch": new.base,
+                "title": title,
+                "description": new.body,
+            }));
+        let created: ApiMergeRequest = send_json(HostKind::GitLab, request).await?;
+        Ok(created.into_summary())
+    }
+
+    fn pull_head_ref(&self, number: u64) -> Option<String> {
+        Some(format!("refs/merge-requests/{}/head", number))
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiIssue {
+    iid: u64,
+    title: String,
+    /// `opened` or `closed`
+    state: String,
+    web_url: String,
+    #[serde(default)]
+    labels: Vec<String>,
+    updated_at: String,
+}
+
+impl ApiIssue {
+    fn into_issue(self) -> Issue {
+        let state = if self.state == "opened" {
+            "open"
+        } else {
+            "closed"
+        };
+        Issue {
+            provider: IssueProvider::GitLab,
+            id: format!("#{}", self.iid),
+            title: self.title,
+            state: state.to_string(),
+            url: self.web_url,
+            labels: self.labels,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Issues of one project.
+pub struct GitLabIssues {
+    client: GitLabClient,
+    repo: RemoteRepo,
+}
+
+impl GitLabIssues {
+    pub fn new(token: Option<String>, repo: RemoteRepo) -> Result<Self, String> {
+        Ok(Self {
+            client: GitLabClient::new(&repo.host, token)?,
+            repo,
+        })
+    }
+}
+
+#[async_trait]
+impl IssueTracker for GitLabIssues {
+    fn provider(&self) -> IssueProvider {
+        IssueProvider::GitLab
+    }
+
+    async fn assigned_issues(&self) -> Result<Vec<Issue>, KiriError> {
+        let issues: Vec<ApiIssue> = self
+            .client
+            .get(&format!(
+                "{}/issues?scope=assigned_to_me&state=opened&order_by=updated_at&per_page=50",
+                project(&self.repo)
+            ))
+            .await?;
+        Ok(issues.into_iter().map(ApiIssue::into_issue).collect())
+    }
+
+    async fn get_issue(&self, id: &str) -> Result<Issue, KiriError> {
+        let number = parse_issue_number(id)?;
+        let issue: ApiIssue = self
+            .client
+            .get(&format!("{}/issues/{}", project(&self.repo), number))
+            .await?;
+        Ok(issue.into_issue())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_request_from_api_payload() {
+        let request: ApiMergeRequest = serde_json::from_value(serde_json::json!({
+            "iid": 12,
+            "title": "Draft: Add thing",
+            "state": "opened",
+            "draft": true,
+            "author": {"username": "dev"},
+            "source_branch": "feature/x",
+            "target_branch": "main",
+            "source_project_id": 5,
+            "target_project_id": 3,
+            "sha": "abc123",
+            "web_url": "https://gitlab.com/g/r/-/merge_requests/12",
+            "updated_at": "2024-05-01T00:00:00Z"
+        }))
+        .unwrap();
+        let summary = request.into_summary();
+        assert_eq!(summary.number, 12);
+        assert_eq!(summary.state, "open");
+        assert!(summary.draft);
+        assert!(summary.from_fork);
+        assert_eq!(summary.head_sha, "abc123");
+        assert_eq!(summary.author, "dev");
+    }
+
+    #[test]
+    fn test_job_statuses_map_to_checks() {
+        let check = |status: &str| {
+            check_from_status(ApiCommitStatus {
+                name: "test".to_string(),
+                status: status.to_string(),
+                target_url: None,
+            })
+        };
+        assert_eq!(check("failed").conclusion.as_deref(), Some("failure"));
+        assert_eq!(check("canceled").conclusion.as_deref(), Some("cancelled"));
+        assert_eq!(check("running").status, "in_progress");
+        assert_eq!(check("pending").conclusion, None);
+        assert_eq!(check("manual").conclusion.as_deref(), Some("neutral"));
+    }
+
+    #[test]
+    fn test_issue_from_api_payload() {
+        let issue: ApiIssue = serde_json::from_value(serde_json::json!({
+            "iid": 8,
+            "title": "Crash on start",
+            "state": "opened",
+            "web_url": "https://gitlab.com/g/r/-/issues/8",
+            "labels": ["bug"],
+            "updated_at": "2024-05-01T00:00:00Z"
+        }))
+        .unwrap();
+        let issue = issue.into_issue();
+        assert_eq!(issue.id, "#8");
+        assert_eq!(issue.state, "open");
+        assert_eq!(issue.labels, vec!["bug"]);
+    }
+
+    #[test]
+    fn test_state_filter() {
+        assert_eq!(state_filter("open"), Some("opened"));
+        assert_eq!(state_filter("merged"), Some("merged"));
+        assert_eq!(state_filter("all"), None);
+    }
+}
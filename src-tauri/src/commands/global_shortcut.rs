@@ -0,0 +1,213 @@
+//! Global shortcut bindings: accelerator parsing, conflict detection and
+//! persistence. Registration with the OS happens in
+//! global_shortcut_commands.rs through `tauri-plugin-global-shortcut`.
+//!
+//! Bindings are stored in `~/.kiri/shortcuts.json` so they are registered
+//! at startup, before any webview has loaded.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::accelerator::{accelerator_key, normalize_accelerator};
+use super::file_io::write_file_contents_atomic;
+
+/// Actions a global shortcut can trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShortcutAction {
+    /// Bring kiri to the front and open quick-open.
+    QuickOpen,
+    /// Bring kiri to the front and open a terminal.
+    NewTerminal,
+    NewWindow,
+    /// Bring kiri to the front.
+    ShowApp,
+}
+
+/// Shortcuts the app menu or the OS already uses. Registering one of these
+/// globally would steal it from every other application.
+const RESERVED: [&str; 12] = [
+    "CmdOrCtrl+A",
+    "CmdOrCtrl+C",
+    "CmdOrCtrl+V",
+    "CmdOrCtrl+X",
+    "CmdOrCtrl+Z",
+    "CmdOrCtrl+Shift+Z",
+    "CmdOrCtrl+O",
+    "CmdOrCtrl+Shift+N",
+    "CmdOrCtrl+W",
+    "CmdOrCtrl+Q",
+    "CmdOrCtrl+H",
+    "CmdOrCtrl+M",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ShortcutConflict {
+    /// Already bound to another kiri action.
+    Action { action: ShortcutAction },
+    /// Used by the app menu or the OS.
+    Reserved,
+}
+
+impl ShortcutConflict {
+    pub fn message(&self, accelerator: &str) -> String {
+        match self {
+            ShortcutConflict::Action { action } => format!(
+                "{} is already assigned to {}",
+                accelerator,
+                serde_json::to_string(action)
+                    .unwrap_or_default()
+                    .trim_matches('"')
+            ),
+            ShortcutConflict::Reserved => {
+                format!("{} is reserved by kiri or the system", accelerator)
+            }
+        }
+    }
+}
+
+/// Bindings keyed by action. Serialized as-is to `shortcuts.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShortcutBindings {
+    pub bindings: BTreeMap<ShortcutAction, String>,
+}
+
+impl ShortcutBindings {
+    /// Check `accelerator` (canonical form) before binding it to `action`.
+    /// Rebinding an action to the shortcut it already has is not a
+    /// conflict.
+    pub fn find_conflict(
+        &self,
+        action: ShortcutAction,
+        accelerator: &str,
+    ) -> Option<ShortcutConflict> {
+        let wanted = accelerator_key(accelerator);
+        if RESERVED.iter().any(|r| accelerator_key(r) == wanted) {
+            return Some(ShortcutConflict::Reserved);
+        }
+        self.bindings
+            .iter()
+            .find(|(a, accel)| **a != action && accelerator_key(accel) == wanted)
+            .map(|(a, _)| ShortcutConflict::Action { action: *a })
+    }
+}
+
+pub type GlobalShortcutState = Arc<Mutex<ShortcutBindings>>;
+
+/// `~/.kiri/shortcuts.json` — persisted global shortcut bindings.
+pub fn shortcuts_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("shortcuts.json"))
+}
+
+/// Load bindings, dropping entries that no longer parse. A missing or
+/// unreadable file yields no bindings.
+pub fn load_shortcuts_file(path: &Path) -> ShortcutBindings {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return ShortcutBindings::default();
+    };
+    let mut loaded: ShortcutBindings = match serde_json::from_str(&contents) {
+        Ok(b) => b,
+        Err(e) => {
+            log::warn!("failed to parse shortcuts file: {}", e);
+            return ShortcutBindings::default();
+        }
+    };
+    loaded
+        .bindings
+        .retain(|action, accel| match normalize_accelerator(accel) {
+            Ok(canonical) => {
+                *accel = canonical;
+                true
+            }
+            Err(e) => {
+                log::warn!("dropping shortcut for {:?}: {}", action, e);
+                false
+            }
+        });
+    loaded
+}
+
+pub fn write_shortcuts_file(path: &Path, bindings: &ShortcutBindings) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create shortcuts directory: {}", e))?;
+    }
+    let json = serde_json::to_vec_pretty(bindings)
+        .map_err(|e| format!("Failed to serialize shortcuts: {}", e))?;
+    write_file_contents_atomic(path, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_conflict_with_other_action() {
+        let mut b = ShortcutBindings::default();
+        b.bindings
+            .insert(ShortcutAction::QuickOpen, "CmdOrCtrl+Shift+P".to_string());
+        assert_eq!(
+            b.find_conflict(ShortcutAction::NewTerminal, "CmdOrCtrl+Shift+P"),
+            Some(ShortcutConflict::Action {
+                action: ShortcutAction::QuickOpen
+            })
+        );
+        assert_eq!(
+            b.find_conflict(ShortcutAction::QuickOpen, "CmdOrCtrl+Shift+P"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cmd_or_ctrl_conflicts_with_platform_modifier() {
+        let mut b = ShortcutBindings::default();
+        b.bindings
+            .insert(ShortcutAction::ShowApp, "CmdOrCtrl+Alt+K".to_string());
+        let native = if cfg!(target_os = "macos") {
+            "Super+Alt+K"
+        } else {
+            "Ctrl+Alt+K"
+        };
+        assert!(b.find_conflict(ShortcutAction::NewWindow, native).is_some());
+    }
+
+    #[test]
+    fn test_reserved_shortcuts() {
+        let b = ShortcutBindings::default();
+        assert_eq!(
+            b.find_conflict(ShortcutAction::QuickOpen, "CmdOrCtrl+C"),
+            Some(ShortcutConflict::Reserved)
+        );
+        assert_eq!(
+            b.find_conflict(ShortcutAction::QuickOpen, "CmdOrCtrl+Alt+C"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_shortcuts_file_round_trip_drops_invalid() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("shortcuts.json");
+        let mut b = ShortcutBindings::default();
+        b.bindings
+            .insert(ShortcutAction::NewTerminal, "ctrl+alt+t".to_string());
+        b.bindings
+            .insert(ShortcutAction::ShowApp, "not a shortcut".to_string());
+        write_shortcuts_file(&path, &b).unwrap();
+
+        let loaded = load_shortcuts_file(&path);
+        assert_eq!(loaded.bindings.len(), 1);
+        assert_eq!(
+            loaded.bindings.get(&ShortcutAction::NewTerminal).unwrap(),
+            "Ctrl+Alt+T"
+        );
+        assert_eq!(
+            load_shortcuts_file(&dir.path().join("missing.json")),
+            ShortcutBindings::default()
+        );
+    }
+}
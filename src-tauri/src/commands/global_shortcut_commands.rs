@@ -0,0 +1,178 @@
+//! Tauri commands and OS registration for global shortcuts. Parsing,
+//! conflict detection and persistence live in global_shortcut.rs.
+//!
+//! Shortcuts are registered from Rust so they fire while no kiri window
+//! is focused. Actions that need the UI (quick-open, new terminal) focus
+//! a window and emit `global-shortcut` with the action name to it.
+
+use super::accelerator::normalize_accelerator;
+use super::error::{ErrorCode, KiriError};
+use super::global_shortcut::{
+    load_shortcuts_file, shortcuts_file_path, write_shortcuts_file, GlobalShortcutState,
+    ShortcutAction, ShortcutBindings,
+};
+use super::lock_ext::LockExt;
+use super::single_instance_commands::focus_any_window;
+use super::window::create_window_impl;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use tauri::{App, AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+fn parse_shortcut(accelerator: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(accelerator).map_err(|e| format!("Invalid shortcut {}: {}", accelerator, e))
+}
+
+fn persist(bindings: &ShortcutBindings) -> Result<(), String> {
+    match shortcuts_file_path() {
+        Some(path) => write_shortcuts_file(&path, bindings),
+        None => Ok(()),
+    }
+}
+
+fn emit_to_front_window(app: &AppHandle, action: ShortcutAction) {
+    let windows = app.webview_windows();
+    let target = windows
+        .values()
+        .find(|w| w.is_focused().unwrap_or(false))
+        .or_else(|| windows.get("main"))
+        .or_else(|| windows.values().next());
+    if let Some(window) = target {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        let _ = window.emit("global-shortcut", action);
+    }
+}
+
+fn run_action(app: &AppHandle, action: ShortcutAction) {
+    match action {
+        ShortcutAction::QuickOpen | ShortcutAction::NewTerminal => {
+            emit_to_front_window(app, action)
+        }
+        ShortcutAction::NewWindow => {
+            if let Err(e) = create_window_impl(app, None, None, None, None, None, None) {
+                log::error!("failed to create window from global shortcut: {e}");
+            }
+        }
+        ShortcutAction::ShowApp => focus_any_window(app),
+    }
+}
+
+fn on_shortcut(app: &AppHandle, pressed: &Shortcut) {
+    let action = {
+        let state = app.state::<GlobalShortcutState>();
+        let bindings = state.lock_recover();
+        bindings
+            .bindings
+            .iter()
+            .find(|(_, accel)| parse_shortcut(accel).is_ok_and(|s| &s == pressed))
+            .map(|(action, _)| *action)
+    };
+    if let Some(action) = action {
+        run_action(app, action);
+    }
+}
+
+/// Install the global-shortcut plugin and register the persisted
+/// bindings.
+pub fn setup_global_shortcuts(app: &App) -> Result<(), Box<dyn std::error::Error>> {
+    app.handle().plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, shortcut, event| {
+                if event.state() == ShortcutState::Pressed {
+                    on_shortcut(app, shortcut);
+                }
+            })
+            .build(),
+    )?;
+
+    load_global_shortcuts(app.handle());
+    Ok(())
+}
+
+/// Register the bindings in `~/.kiri/shortcuts.json` in place of the
+/// current ones. A binding the OS refuses (another app owns it) is logged
+/// and kept, so the settings UI can still show and change it.
+pub fn load_global_shortcuts(app: &AppHandle) {
+    let Some(path) = shortcuts_file_path() else {
+        return;
+    };
+    let loaded = load_shortcuts_file(&path);
+    if let Err(e) = app.global_shortcut().unregister_all() {
+        log::warn!("failed to unregister global shortcuts: {e}");
+    }
+    for (action, accel) in &loaded.bindings {
+        let result = parse_shortcut(accel)
+            .and_then(|s| app.global_shortcut().register(s).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::warn!("failed to register global shortcut {accel} for {action:?}: {e}");
+        }
+    }
+    *app.state::<GlobalShortcutState>().lock_recover() = loaded;
+}
+
+/// Current bindings, keyed by action.
+#[tauri::command]
+pub fn list_global_shortcuts(
+    state: tauri::State<'_, GlobalShortcutState>,
+) -> BTreeMap<ShortcutAction, String> {
+    state.lock_recover().bindings.clone()
+}
+
+/// Bind `accelerator` to `action`, replacing the action's previous
+/// shortcut. Fails without changing anything if the shortcut is invalid,
+/// bound to another action, reserved, or taken by another application.
+/// Returns the accelerator in canonical form.
+#[tauri::command]
+pub fn register_global_shortcut(
+    app: AppHandle,
+    state: tauri::State<'_, GlobalShortcutState>,
+    action: ShortcutAction,
+    accelerator: String,
+) -> Result<String, KiriError> {
+    let canonical = normalize_accelerator(&accelerator)?;
+    let mut bindings = state.lock_recover();
+    if let Some(conflict) = bindings.find_conflict(action, &canonical) {
+        return Err(KiriError::new(
+            ErrorCode::AlreadyExists,
+            conflict.message(&canonical),
+        ));
+    }
+
+    let previous = bindings.bindings.get(&action).cloned();
+    if previous.as_deref() == Some(canonical.as_str()) {
+        return Ok(canonical);
+    }
+
+    let shortcut = parse_shortcut(&canonical)?;
+    app.global_shortcut().register(shortcut).map_err(|e| {
+        format!(
+            "{} is already in use by another application: {}",
+            canonical, e
+        )
+    })?;
+    if let Some(old) = previous.as_deref().and_then(|p| parse_shortcut(p).ok()) {
+        let _ = app.global_shortcut().unregister(old);
+    }
+
+    bindings.bindings.insert(action, canonical.clone());
+    persist(&bindings)?;
+    Ok(canonical)
+}
+
+/// Remove the shortcut bound to `action`, if any.
+#[tauri::command]
+pub fn unregister_global_shortcut(
+    app: AppHandle,
+    state: tauri::State<'_, GlobalShortcutState>,
+    action: ShortcutAction,
+) -> Result<(), KiriError> {
+    let mut bindings = state.lock_recover();
+    let Some(accel) = bindings.bindings.remove(&action) else {
+        return Ok(());
+    };
+    if let Ok(shortcut) = parse_shortcut(&accel) {
+        let _ = app.global_shortcut().unregister(shortcut);
+    }
+    persist(&bindings).map_err(KiriError::from)
+}
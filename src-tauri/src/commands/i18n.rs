@@ -0,0 +1,209 @@
+//! Translations of the messages the backend shows: command errors,
+//! notifications and confirmation dialogs.
+//!
+//! A [`Message`] is a catalog key plus named parameters; [`Message::text`]
+//! renders it in the current [`Locale`], set from the `language` setting.
+//! The catalogs are `src-tauri/locales/<locale>.json`, flat objects from
+//! key to text with `{name}` placeholders, built into the binary. A key
+//! missing from a catalog falls back to English.
+//!
+//! An error is translated when it is raised from an `error.*` key, with
+//! [`KiriError::localized`] or [`user_error`]; errors written as English
+//! strings stay English. Parameters never carry a full path, as the
+//! message reaches the renderer (see error.rs); pass a basename instead.
+//!
+//! [`KiriError::localized`]: super::error::KiriError::localized
+//! [`user_error`]: super::error::user_error
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use super::settings::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Ja,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Ja];
+
+    /// The locale of a POSIX or BCP 47 tag such as `ja_JP.UTF-8` or
+    /// `ja-JP`; `None` for a language without a catalog.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['_', '-', '.', '@']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "ja" => Some(Locale::Ja),
+            _ => None,
+        }
+    }
+
+    /// The user's system language, from the locale variables or, on
+    /// macOS, where apps started from the Finder don't get them, the
+    /// `AppleLocale` default. English when neither has a catalog.
+    pub fn system() -> Locale {
+        let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty() && value != "C" && value != "POSIX");
+        if let Some(locale) = from_env.as_deref().and_then(Locale::from_tag) {
+            return locale;
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let output =
+                super::process::argv_command(&["defaults", "read", "-g", "AppleLocale"], None)
+                    .and_then(|mut command| command.output().map_err(|e| e.to_string()));
+            if let Ok(output) = output {
+                let tag = String::from_utf8_lossy(&output.stdout);
+                if let Some(locale) = Locale::from_tag(tag.trim()) {
+                    return locale;
+                }
+            }
+        }
+        Locale::En
+    }
+
+    fn catalog(self) -> &'static BTreeMap<String, String> {
+        static EN: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+        static JA: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+        let (cell, source) = match self {
+            Locale::En => (&EN, include_str!("../../locales/en.json")),
+            Locale::Ja => (&JA, include_str!("../../locales/ja.json")),
+        };
+        cell.get_or_init(|| {
+            serde_json::from_str(source).unwrap_or_else(|e| {
+                log::error!("invalid {:?} message catalog: {}", self, e);
+                BTreeMap::new()
+            })
+        })
+    }
+}
+
+static LOCALE: AtomicU8 = AtomicU8::new(0);
+
+/// The locale messages are shown in.
+pub fn locale() -> Locale {
+    Locale::ALL[LOCALE.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_locale(locale: Locale) {
+    let index = Locale::ALL.iter().position(|l| *l == locale).unwrap_or(0);
+    LOCALE.store(index as u8, Ordering::Relaxed);
+}
+
+/// Apply the `language` setting.
+pub fn apply_language(language: Language) {
+    set_locale(match language {
+        Language::System => Locale::system(),
+        Language::En => Locale::En,
+        Language::Ja => Locale::Ja,
+    });
+}
+
+/// A catalog key with its parameters, for the frontend to branch on and
+/// for [`Message::text`] to render.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Message {
+    pub key: &'static str,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<&'static str, String>,
+}
+
+impl Message {
+    pub fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            params: BTreeMap::new(),
+        }
+    }
+
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.params.insert(name, value.to_string());
+        self
+    }
+
+    /// The message in the current locale.
+    pub fn text(&self) -> String {
+        self.text_in(locale())
+    }
+
+    pub fn text_in(&self, locale: Locale) -> String {
+        let template = locale
+            .catalog()
+            .get(self.key)
+            .or_else(|| Locale::En.catalog().get(self.key))
+            .map_or(self.key, String::as_str);
+        let mut text = template.to_string();
+        for (name, value) in &self.params {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(template: &str) -> BTreeSet<&str> {
+        template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn test_catalogs_have_the_same_keys_and_placeholders() {
+        let en = Locale::En.catalog();
+        assert!(!en.is_empty());
+        for locale in Locale::ALL {
+            let catalog = locale.catalog();
+            let keys: BTreeSet<_> = catalog.keys().collect();
+            assert_eq!(keys, en.keys().collect(), "keys of {:?}", locale);
+            for (key, text) in catalog {
+                assert_eq!(
+                    placeholders(text),
+                    placeholders(&en[key]),
+                    "{key} in {locale:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_message_text() {
+        let message = Message::new("notification.agent_waiting.title").with("agent", "Codex");
+        assert_eq!(message.text_in(Locale::En), "Codex needs input");
+        assert_eq!(message.text_in(Locale::Ja), "Codex が入力を待っています");
+        // An unknown key shows as itself.
+        assert_eq!(
+            Message::new("no.such.key").text_in(Locale::Ja),
+            "no.such.key"
+        );
+
+        assert_eq!(Locale::from_tag("ja_JP.UTF-8"), Some(Locale::Ja));
+        assert_eq!(Locale::from_tag("en-GB"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_error_message_params() {
+        let trust = Message::new("error.not_trusted").with("name", "app");
+        assert_eq!(
+            trust.text_in(Locale::Ja),
+            "app は信頼されていません。コマンドを実行するにはフォルダを信頼してください"
+        );
+        assert_eq!(
+            Message::new("error.path_not_found").text_in(Locale::En),
+            "Path does not exist"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Issues assigned to the user, and worktrees created from them.
+//!
+//! [`IssueTracker`] is implemented for GitHub and GitLab issues of the
+//! checkout's `origin` repository (github.rs, gitlab.rs), reusing the code
+//! host sign-in, and for Jira (jira.rs), which is configured separately
+//! because a Jira site can't be derived from a remote.
+//!
+//! A worktree created from an issue gets a branch named after it
+//! (`123-fix-login-crash`, `PROJ-42-fix-login-crash`), and the issue is
+//! recorded as that branch's metadata (see git_worktree.rs). The Tauri
+//! commands live in issues_commands.rs.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::code_host::{self, HostKind};
+use super::error::{ErrorCode, KiriError};
+use super::git_worktree::IssueLink;
+use super::github::GitHubIssues;
+use super::gitlab::GitLabIssues;
+use super::jira::{JiraClient, JiraConfig};
+
+/// Longest title slug kept in a branch name.
+const MAX_SLUG_LEN: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueProvider {
+    GitHub,
+    GitLab,
+    Jira,
+}
+
+impl IssueProvider {
+    pub fn label(self) -> &'static str {
+        match self {
+            IssueProvider::GitHub => "GitHub",
+            IssueProvider::GitLab => "GitLab",
+            IssueProvider::Jira => "Jira",
+        }
+    }
+
+    /// The tracker an issue id refers to: Jira for keys like `PROJ-42`,
+    /// otherwise the issues of the repository's code host.
+    pub fn infer(id: &str, host: Option<HostKind>) -> Result<Self, String> {
+        if is_jira_key(id) {
+            return Ok(IssueProvider::Jira);
+        }
+        match host {
+            Some(HostKind::GitHub) => Ok(IssueProvider::GitHub),
+            Some(HostKind::GitLab) => Ok(IssueProvider::GitLab),
+            _ => Err(format!(
+                "No issue tracker for {}; use a Jira key such as PROJ-42",
+                id
+            )),
+        }
+    }
+}
+
+/// `PROJ-42`: an uppercase project key, a dash and a number.
+pub fn is_jira_key(id: &str) -> bool {
+    let Some((project, number)) = id.split_once('-') else {
+        return false;
+    };
+    project.starts_with(|c: char| c.is_ascii_uppercase())
+        && project
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Issue number from `123` or `#123`.
+pub fn parse_issue_number(id: &str) -> Result<u64, String> {
+    let id = id.trim();
+    id.strip_prefix('#')
+        .unwrap_or(id)
+        .parse()
+        .map_err(|_| format!("Invalid issue number: {}", id))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub provider: IssueProvider,
+    /// As shown by the tracker: `#123` or `PROJ-42`
+    pub id: String,
+    pub title: String,
+    /// `open` or `closed`
+    pub state: String,
+    pub url: String,
+    pub labels: Vec<String>,
+    pub updated_at: String,
+}
+
+impl Issue {
+    pub fn link(&self) -> IssueLink {
+        IssueLink {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            url: self.url.clone(),
+        }
+    }
+}
+
+/// Lowercase ASCII words of `title` joined by `-`, cut at a word boundary
+/// to at most [`MAX_SLUG_LEN`] bytes. Other characters separate words.
+fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for word in title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let extra = if slug.is_empty() { 0 } else { 1 } + word.len();
+        if slug.len() + extra > MAX_SLUG_LEN {
+            if slug.is_empty() {
+                slug.push_str(&word[..MAX_SLUG_LEN]);
+            }
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    slug
+}
+
+/// Branch name for a worktree created from `issue`: its id without `#`,
+/// then a slug of its title.
+pub fn issue_branch_name(issue: &Issue) -> String {
+    let id = issue.id.trim_start_matches('#');
+    match slug(&issue.title) {
+        slug if slug.is_empty() => id.to_string(),
+        slug => format!("{}-{}", id, slug),
+    }
+}
+
+#[async_trait]
+pub trait IssueTracker: Send + Sync {
+    fn provider(&self) -> IssueProvider;
+
+    /// Open issues assigned to the signed-in user, most recently updated
+    /// first.
+    async fn assigned_issues(&self) -> Result<Vec<Issue>, KiriError>;
+
+    /// One issue by the id shown in the tracker.
+    async fn get_issue(&self, id: &str) -> Result<Issue, KiriError>;
+}
+
+/// Tracker for `provider`, scoped to the `origin` repository of the
+/// checkout at `repo_path` for GitHub and GitLab.
+pub fn tracker_for(
+    provider: IssueProvider,
+    repo_path: &Path,
+) -> Result<Box<dyn IssueTracker>, KiriError> {
+    if provider == IssueProvider::Jira {
+        let config = JiraConfig::load()
+            .ok_or_else(|| KiriError::new(ErrorCode::Unauthenticated, "Jira is not configured"))?;
+        return Ok(Box::new(JiraClient::new(config)?));
+    }
+    let remote = code_host::origin_remote(repo_path)?;
+    let token = code_host::load_token(remote.kind).ok_or_else(|| {
+        KiriError::new(
+            ErrorCode::Unauthenticated,
+            format!("Not signed in to {}", remote.kind.label()),
+        )
+    })?;
+    match (provider, remote.kind) {
+        (IssueProvider::GitHub, HostKind::GitHub) => {
+            Ok(Box::new(GitHubIssues::new(Some(token), remote)?))
+        }
+        (IssueProvider::GitLab, HostKind::GitLab) => {
+            Ok(Box::new(GitLabIssues::new(Some(token), remote)?))
+        }
+        _ => Err(KiriError::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "Remote 'origin' is on {}, not {}",
+                remote.kind.label(),
+                provider.label()
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(id: &str, title: &str) -> Issue {
+        Issue {
+            provider: IssueProvider::GitHub,
+            id: id.to_string(),
+            title: title.to_string(),
+            state: "open".to_string(),
+            url: String::new(),
+            labels: Vec::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_issue_branch_name() {
+        assert_eq!(
+            issue_branch_name(&issue("#123", "Fix login crash on macOS 14!")),
+            "123-fix-login-crash-on-macos-14"
+        );
+        assert_eq!(
+            issue_branch_name(&issue("PROJ-42", "[UI] Dark mode: sidebar")),
+            "PROJ-42-ui-dark-mode-sidebar"
+        );
+        // Non-ASCII titles fall back to the id alone.
+        assert_eq!(issue_branch_name(&issue("#7", "ログイン画面")), "7");
+    }
+
+    #[test]
+    fn test_slug_is_cut_at_word_boundary() {
+        let slug = slug("Make the worktree sidebar remember its scroll position per window");
+        assert!(slug.len() <= MAX_SLUG_LEN);
+        assert_eq!(slug, "make-the-worktree-sidebar-remember-its");
+        assert_eq!(super::slug(&"x".repeat(60)).len(), MAX_SLUG_LEN);
+    }
+
+    #[test]
+    fn test_branch_names_are_valid() {
+        for title in ["", "...", "a/b\\c", "-leading", "x.lock"] {
+            let name = issue_branch_name(&issue("#1", title));
+            assert!(
+                super::super::git_worktree::validate_branch_name(&name).is_ok(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_infer_provider() {
+        assert_eq!(
+            IssueProvider::infer("PROJ-42", Some(HostKind::GitHub)),
+            Ok(IssueProvider::Jira)
+        );
+        assert_eq!(
+            IssueProvider::infer("#12", Some(HostKind::GitLab)),
+            Ok(IssueProvider::GitLab)
+        );
+        assert!(IssueProvider::infer("12", Some(HostKind::Bitbucket)).is_err());
+        assert!(!is_jira_key("proj-42"));
+        assert!(!is_jira_key("PROJ-"));
+        assert!(is_jira_key("AB2_X-7"));
+    }
+
+    #[test]
+    fn test_parse_issue_number() {
+        assert_eq!(parse_issue_number("#123"), Ok(123));
+        assert_eq!(parse_issue_number(" 45 "), Ok(45));
+        assert!(parse_issue_number("PROJ-1").is_err());
+    }
+}
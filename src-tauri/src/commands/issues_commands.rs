@@ -0,0 +1,88 @@
+//! Tauri commands for assigned issues and worktrees created from them; see
+//! issues.rs.
+
+use std::path::Path;
+
+use super::code_host;
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::git_worktree::{create_worktree_for, set_branch_issue, WorktreeInfo};
+use super::issues::{issue_branch_name, tracker_for, Issue, IssueProvider};
+use super::jira::JiraConfig;
+use super::latency::run_blocking;
+use super::performance::CommandTimer;
+
+/// `provider` if given, else the tracker `issue_id` belongs to, else the
+/// issues of the `origin` host, else Jira.
+fn resolve_provider(
+    repo_path: &Path,
+    provider: Option<IssueProvider>,
+    issue_id: Option<&str>,
+) -> Result<IssueProvider, String> {
+    if let Some(provider) = provider {
+        return Ok(provider);
+    }
+    let host = code_host::origin_remote(repo_path).ok().map(|r| r.kind);
+    match issue_id {
+        Some(id) => IssueProvider::infer(id, host),
+        None => Ok(IssueProvider::infer("", host).unwrap_or(IssueProvider::Jira)),
+    }
+}
+
+/// Open issues assigned to the signed-in user, from `provider` or the
+/// checkout's code host (Jira if it has no issue tracker).
+#[tauri::command]
+pub async fn list_assigned_issues(
+    repo_path: String,
+    provider: Option<IssueProvider>,
+) -> Result<Vec<Issue>, KiriError> {
+    let _timer = CommandTimer::start("list_assigned_issues");
+    let path = normalize_input_path(&repo_path);
+    let provider = resolve_provider(&path, provider, None)?;
+    tracker_for(provider, &path)?.assigned_issues().await
+}
+
+/// Create (or reuse) a worktree for an issue: `#123`/`123` on the code
+/// host, or a Jira key such as `PROJ-42`. The branch is named after the
+/// issue unless `branch` is given, and the issue is recorded on it.
+#[tauri::command]
+pub async fn create_worktree_from_issue(
+    repo_path: String,
+    issue_id: String,
+    provider: Option<IssueProvider>,
+    branch: Option<String>,
+) -> Result<WorktreeInfo, KiriError> {
+    let path = normalize_input_path(&repo_path);
+    let provider = resolve_provider(&path, provider, Some(&issue_id))?;
+    let issue = tracker_for(provider, &path)?.get_issue(&issue_id).await?;
+    let branch = branch.unwrap_or_else(|| issue_branch_name(&issue));
+    let link = issue.link();
+    run_blocking("create_worktree_from_issue", move || {
+        let worktree = create_worktree_for(&path, &branch, None)?;
+        set_branch_issue(&path, &branch, &link)?;
+        Ok(WorktreeInfo {
+            issue: Some(link),
+            ..worktree
+        })
+    })
+    .await
+}
+
+/// Save the Jira site, account email and API token.
+#[tauri::command]
+pub fn set_jira_credentials(site: String, email: String, token: String) -> Result<(), KiriError> {
+    JiraConfig::new(&site, &email, &token)?
+        .save()
+        .map_err(KiriError::from)
+}
+
+/// The configured Jira site, if any.
+#[tauri::command]
+pub fn get_jira_site() -> Option<String> {
+    JiraConfig::load().map(|config| config.site)
+}
+
+#[tauri::command]
+pub fn clear_jira_credentials() -> Result<(), KiriError> {
+    JiraConfig::clear().map_err(KiriError::from)
+}
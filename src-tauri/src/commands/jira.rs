@@ -0,0 +1,227 @@
+//! Jira Cloud implementation of [`IssueTracker`].
+//!
+//! Jira isn't a code host, so its site and API token are set by the user
+//! rather than derived from a remote. They are saved together in the OS
+//! keychain as the secret `jira`/`credentials` and sent with basic auth (account email and API token).
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::code_host::{http_client, send_json_as};
+use super::error::{ErrorCode, KiriError};
+use super::issues::{is_jira_key, Issue, IssueProvider, IssueTracker};
+use super::secrets;
+
+const SECRET_NAMESPACE: &str = "jira";
+const SECRET_KEY: &str = "credentials";
+/// Where versions before the keychain kept the settings, under `~/.kiri`.
+const LEGACY_FILE: &str = "jira";
+const FIELDS: &str = "summary,status,labels,updated";
+const ASSIGNED_JQL: &str =
+    "assignee = currentUser() AND statusCategory != Done ORDER BY updated DESC";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JiraConfig {
+    /// e.g. `https://example.atlassian.net`
+    pub site: String,
+    pub email: String,
+    pub token: String,
+}
+
+/// `example.atlassian.net` or `https://example.atlassian.net/` as
+/// `https://example.atlassian.net`.
+pub fn normalize_site(site: &str) -> Result<String, String> {
+    let site = site.trim().trim_end_matches('/');
+    if site.starts_with("http://") {
+        return Err("Jira site must use https".to_string());
+    }
+    let host = site.strip_prefix("https://").unwrap_or(site);
+    if host.is_empty() || host.contains('/') {
+        return Err(format!("Invalid Jira site: {}", site));
+    }
+    Ok(format!("https://{}", host))
+}
+
+impl JiraConfig {
+    pub fn new(site: &str, email: &str, token: &str) -> Result<Self, String> {
+        let (email, token) = (email.trim(), token.trim());
+        if email.is_empty() || token.is_empty() {
+            return Err("Jira email and API token are required".to_string());
+        }
+        Ok(Self {
+            site: normalize_site(site)?,
+            email: email.to_string(),
+            token: token.to_string(),
+        })
+    }
+
+    pub fn load() -> Option<Self> {
+        let contents = secrets::get_secret_migrating(SECRET_NAMESPACE, SECRET_KEY, LEGACY_FILE)?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let contents = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize Jira settings: {}", e))?;
+        secrets::set_secret(SECRET_NAMESPACE, SECRET_KEY, &contents)
+    }
+
+    pub fn clear() -> Result<(), String> {
+        secrets::delete_secret(SECRET_NAMESPACE, SECRET_KEY, Some(LEGACY_FILE))
+    }
+}
+
+// --- Jira API payloads ------------------------------------------------------
+
+#[derive(Deserialize)]
+struct ApiSearch {
+    issues: Vec<ApiIssue>,
+}
+
+#[derive(Deserialize)]
+struct ApiStatusCategory {
+    /// `new`, `indeterminate` or `done`
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct ApiStatus {
+    #[serde(rename = "statusCategory")]
+    category: ApiStatusCategory,
+}
+
+#[derive(Deserialize)]
+struct ApiFields {
+    summary: String,
+    status: ApiStatus,
+    #[serde(default)]
+    labels: Vec<String>,
+    updated: String,
+}
+
+#[derive(Deserialize)]
+struct ApiIssue {
+    key: String,
+    fields: ApiFields,
+}
+
+impl ApiIssue {
+    fn into_issue(self, site: &str) -> Issue {
+        let state = if self.fields.status.category.key == "done" {
+            "closed"
+        } else {
+            "open"
+        };
+        Issue {
+            provider: IssueProvider::Jira,
+            url: format!("{}/browse/{}", site, self.key),
+            id: self.key,
+            title: self.fields.summary,
+            state: state.to_string(),
+            labels: self.fields.labels,
+            updated_at: self.fields.updated,
+        }
+    }
+}
+
+pub struct JiraClient {
+    http: reqwest::Client,
+    config: JiraConfig,
+}
+
+impl JiraClient {
+    pub fn new(config: JiraConfig) -> Result<Self, String> {
+        Ok(Self {
+            http: http_client()?,
+            config,
+        })
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, KiriError> {
+        let request = self
+            .http
+            .get(format!("{}/rest/api/3{}", self.config.site, path))
+            .header("Accept", "application/json")
+            .basic_auth(&self.config.email, Some(&self.config.token));
+        send_json_as("Jira", request).await
+    }
+}
+
+#[async_trait]
+impl IssueTracker for JiraClient {
+    fn provider(&self) -> IssueProvider {
+        IssueProvider::Jira
+    }
+
+    async fn assigned_issues(&self) -> Result<Vec<Issue>, KiriError> {
+        let search: ApiSearch = self
+            .get(&format!(
+                "/search/jql?jql={}&fields={}&maxResults=50",
+                urlencoding::encode(ASSIGNED_JQL),
+                FIELDS
+            ))
+            .await?;
+        Ok(search
+            .issues
+            .into_iter()
+            .map(|issue| issue.into_issue(&self.config.site))
+            .collect())
+    }
+
+    async fn get_issue(&self, id: &str) -> Result<Issue, KiriError> {
+        let key = id.trim();
+        if !is_jira_key(key) {
+            return Err(KiriError::new(
+                ErrorCode::InvalidInput,
+                format!("Invalid Jira issue key: {}", key),
+            ));
+        }
+        let issue: ApiIssue = self
+            .get(&format!("/issue/{}?fields={}", key, FIELDS))
+            .await?;
+        Ok(issue.into_issue(&self.config.site))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_site() {
+        let expected = Ok("https://example.atlassian.net".to_string());
+        assert_eq!(normalize_site("example.atlassian.net"), expected);
+        assert_eq!(normalize_site(" https://example.atlassian.net/ "), expected);
+        assert!(normalize_site("http://example.atlassian.net").is_err());
+        assert!(normalize_site("https://example.atlassian.net/jira").is_err());
+        assert!(normalize_site("").is_err());
+    }
+
+    #[test]
+    fn test_issue_from_api_payload() {
+        let search: ApiSearch = serde_json::from_value(serde_json::json!({
+            "issues": [{
+                "id": "10001",
+                "key": "PROJ-42",
+                "fields": {
+                    "summary": "Fix login crash",
+                    "status": {"name": "In Review", "statusCategory": {"key": "indeterminate"}},
+                    "labels": ["backend"],
+                    "updated": "2024-05-01T00:00:00.000+0000"
+                }
+            }],
+            "nextPageToken": null
+        }))
+        .unwrap();
+        let issue = search
+            .issues
+            .into_iter()
+            .next()
+            .unwrap()
+            .into_issue("https://example.atlassian.net");
+        assert_eq!(issue.id, "PROJ-42");
+        assert_eq!(issue.state, "open");
+        assert_eq!(issue.url, "https://example.atlassian.net/browse/PROJ-42");
+        assert_eq!(issue.labels, vec!["backend"]);
+    }
+}
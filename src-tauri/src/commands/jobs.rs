@@ -0,0 +1,467 @@
+//! Background job bookkeeping: ids, queueing under per-kind concurrency
+//! caps, progress, cancellation and a short history of finished jobs.
+//!
+//! Long-running work (port scans, package installs, indexing, directory
+//! sizes) is submitted as a job of some `kind`. The manager is generic
+//! over the queued task so this module stays free of Tauri; the runner
+//! that executes tasks and emits events lives in jobs_commands.rs.
+//!
+//! Jobs live in Rust state, so a window reload picks them up again with
+//! `list_jobs` instead of losing track of work in flight.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::clock::now_ms;
+
+pub type JobId = u64;
+
+/// Jobs of one kind that may run at once unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 2;
+
+/// Finished jobs kept for `list_jobs`; older ones are pruned.
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed { error: String },
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed { .. } | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: JobId,
+    /// Subsystem that owns the job, e.g. `port-scan`.
+    pub kind: String,
+    /// Human-readable description shown in the UI.
+    pub label: String,
+    pub status: JobStatus,
+    /// Percent complete, when the job can measure it.
+    pub progress: Option<u8>,
+    /// Latest status line from the job.
+    pub message: Option<String>,
+    pub created_ms: u64,
+    pub updated_ms: u64,
+}
+
+/// Cooperative cancellation flag handed to a running task.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Whether both are clones of the same token.
+    pub fn same_as(&self, other: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+struct Job<T> {
+    info: JobInfo,
+    cancel: CancelToken,
+    /// Present while queued; taken when the job starts.
+    task: Option<T>,
+}
+
+/// Callback a [`JobContext`] reports progress through.
+pub type ProgressFn = Arc<dyn Fn(JobId, Option<u8>, Option<String>) + Send + Sync>;
+
+/// Handle a running task uses to report progress and check for
+/// cancellation.
+pub struct JobContext {
+    id: JobId,
+    cancel: CancelToken,
+    report: ProgressFn,
+}
+
+impl JobContext {
+    pub fn new(id: JobId, cancel: CancelToken, report: ProgressFn) -> Self {
+        Self { id, cancel, report }
+    }
+
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Tasks should check this between units of work and return early.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn progress(&self, percent: Option<u8>, message: Option<String>) {
+        (self.report)(self.id, percent, message);
+    }
+}
+
+/// Work queued on the app-wide manager. It runs on a blocking thread.
+pub type JobTask = Box<dyn FnOnce(&JobContext) -> Result<(), String> + Send>;
+
+/// A job that was just started and should now be executed.
+pub struct StartedJob<T> {
+    pub id: JobId,
+    pub task: T,
+    pub cancel: CancelToken,
+}
+
+pub struct JobManager<T> {
+    jobs: BTreeMap<JobId, Job<T>>,
+    next_id: JobId,
+    limits: HashMap<String, usize>,
+}
+
+pub type JobState = Arc<Mutex<JobManager<JobTask>>>;
+
+impl<T> Default for JobManager<T> {
+    fn default() -> Self {
+        Self {
+            jobs: BTreeMap::new(),
+            next_id: 1,
+            limits: HashMap::new(),
+        }
+    }
+}
+
+impl<T> JobManager<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap how many jobs of `kind` run at once (minimum 1).
+    pub fn set_concurrency(&mut self, kind: &str, limit: usize) {
+        self.limits.insert(kind.to_string(), limit.max(1));
+    }
+
+    /// Queue `task` as a new job. Call [`Self::start_runnable`] afterwards
+    /// to start whatever the caps allow.
+    pub fn submit(&mut self, kind: &str, label: &str, task: T) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let now = now_ms();
+        self.jobs.insert(
+            id,
+            Job {
+                info: JobInfo {
+                    id,
+                    kind: kind.to_string(),
+                    label: label.to_string(),
+                    status: JobStatus::Queued,
+                    progress: None,
+                    message: None,
+                    created_ms: now,
+                    updated_ms: now,
+                },
+                cancel: CancelToken::default(),
+                task: Some(task),
+            },
+        );
+        id
+    }
+
+    /// Mark queued jobs as running, oldest first, as far as each kind's
+    /// cap allows, and hand back their tasks.
+    pub fn start_runnable(&mut self) -> Vec<StartedJob<T>> {
+        let mut running: HashMap<String, usize> = HashMap::new();
+        for job in self.jobs.values() {
+            if job.info.status == JobStatus::Running {
+                *running.entry(job.info.kind.clone()).or_default() += 1;
+            }
+        }
+
+        let mut started = Vec::new();
+        for (id, job) in self.jobs.iter_mut() {
+            if job.info.status != JobStatus::Queued {
+                continue;
+            }
+            let count = running.entry(job.info.kind.clone()).or_default();
+            let limit = self
+                .limits
+                .get(&job.info.kind)
+                .copied()
+                .unwrap_or(DEFAULT_CONCURRENCY);
+            if *count >= limit {
+                continue;
+            }
+            let Some(task) = job.task.take() else {
+                continue;
+            };
+            *count += 1;
+            job.info.status = JobStatus::Running;
+            job.info.updated_ms = now_ms();
+            started.push(StartedJob {
+                id: *id,
+                task,
+                cancel: job.cancel.clone(),
+            });
+        }
+        started
+    }
+
+    /// Record progress from a running job. Ignored once it has finished.
+    pub fn set_progress(
+        &mut self,
+        id: JobId,
+        percent: Option<u8>,
+        message: Option<String>,
+    ) -> Option<JobInfo> {
+        let job = self.jobs.get_mut(&id)?;
+        if job.info.status.is_finished() {
+            return None;
+        }
+        job.info.progress = percent.map(|p| p.min(100));
+        if message.is_some() {
+            job.info.message = message;
+        }
+        job.info.updated_ms = now_ms();
+        Some(job.info.clone())
+    }
+
+    /// Record the outcome of a running job. A job whose cancel token was
+    /// set ends as cancelled whatever the task returned.
+    pub fn finish(&mut self, id: JobId, result: Result<(), String>) -> Option<JobInfo> {
+        let job = self.jobs.get_mut(&id)?;
+        job.info.status = if job.cancel.is_cancelled() {
+            JobStatus::Cancelled
+        } else {
+            match result {
+                Ok(()) => {
+                    job.info.progress = Some(100);
+                    JobStatus::Completed
+                }
+                Err(error) => JobStatus::Failed { error },
+            }
+        };
+        job.info.updated_ms = now_ms();
+        let info = job.info.clone();
+        self.prune();
+        Some(info)
+    }
+
+    /// Request cancellation. A queued job is cancelled at once; a running
+    /// one is signalled and finishes as cancelled when its task returns.
+    pub fn cancel(&mut self, id: JobId) -> Result<JobInfo, String> {
+        let job = self
+            .jobs
+            .get_mut(&id)
+            .ok_or_else(|| format!("No job with id {}", id))?;
+        if job.info.status.is_finished() {
+            return Ok(job.info.clone());
+        }
+        job.cancel.cancel();
+        if job.info.status == JobStatus::Queued {
+            job.task = None;
+            job.info.status = JobStatus::Cancelled;
+            job.info.updated_ms = now_ms();
+        }
+        Ok(job.info.clone())
+    }
+
+    /// [`Self::cancel`] every job that has not finished, for app exit.
+    /// Returns how many there were.
+    pub fn cancel_all(&mut self) -> usize {
+        let ids: Vec<JobId> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| !job.info.status.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            let _ = self.cancel(*id);
+        }
+        ids.len()
+    }
+
+    pub fn get(&self, id: JobId) -> Option<JobInfo> {
+        self.jobs.get(&id).map(|job| job.info.clone())
+    }
+
+    /// Every known job in submission order.
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.jobs.values().map(|job| job.info.clone()).collect()
+    }
+
+    /// Drop the oldest finished jobs beyond [`MAX_FINISHED_JOBS`].
+    fn prune(&mut self) {
+        let finished: Vec<JobId> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.info.status.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        let excess = finished.len().saturating_sub(MAX_FINISHED_JOBS);
+        for id in finished.into_iter().take(excess) {
+            self.jobs.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(manager: &JobManager<&'static str>, id: JobId) -> JobStatus {
+        manager.get(id).unwrap().status
+    }
+
+    #[test]
+    fn test_concurrency_cap_per_kind() {
+        let mut manager = JobManager::new();
+        manager.set_concurrency("install", 1);
+        let a = manager.submit("install", "npm install", "a");
+        let b = manager.submit("install", "pnpm install", "b");
+        let c = manager.submit("port-scan", "scan", "c");
+
+        let started: Vec<_> = manager.start_runnable().into_iter().map(|s| s.id).collect();
+        assert_eq!(started, vec![a, c]);
+        assert_eq!(status(&manager, b), JobStatus::Queued);
+        assert!(manager.start_runnable().is_empty());
+
+        manager.finish(a, Ok(()));
+        let started: Vec<_> = manager.start_runnable().into_iter().map(|s| s.id).collect();
+        assert_eq!(started, vec![b]);
+    }
+
+    #[test]
+    fn test_progress_and_completion() {
+        let mut manager = JobManager::new();
+        let id = manager.submit("index", "Index files", ());
+        manager.start_runnable();
+        let info = manager
+            .set_progress(id, Some(140), Some("halfway".to_string()))
+            .unwrap();
+        assert_eq!(info.progress, Some(100));
+        assert_eq!(info.message.as_deref(), Some("halfway"));
+
+        let info = manager.finish(id, Ok(())).unwrap();
+        assert_eq!(info.status, JobStatus::Completed);
+        assert!(manager.set_progress(id, Some(10), None).is_none());
+    }
+
+    #[test]
+    fn test_failure_is_recorded() {
+        let mut manager = JobManager::new();
+        let id = manager.submit("install", "npm install", "a");
+        manager.start_runnable();
+        manager.finish(id, Err("exit code 1".to_string()));
+        assert_eq!(
+            status(&manager, id),
+            JobStatus::Failed {
+                error: "exit code 1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_cancel_queued_and_running() {
+        let mut manager = JobManager::new();
+        manager.set_concurrency("scan", 1);
+        let running = manager.submit("scan", "first", "a");
+        let queued = manager.submit("scan", "second", "b");
+        let started = manager.start_runnable();
+        let token = started[0].cancel.clone();
+
+        assert_eq!(manager.cancel(queued).unwrap().status, JobStatus::Cancelled);
+        assert!(manager.start_runnable().is_empty());
+
+        assert_eq!(manager.cancel(running).unwrap().status, JobStatus::Running);
+        assert!(token.is_cancelled());
+        // The task ignores the token and reports success; it still counts
+        // as cancelled.
+        manager.finish(running, Ok(()));
+        assert_eq!(status(&manager, running), JobStatus::Cancelled);
+
+        assert!(manager.cancel(999).is_err());
+    }
+
+    #[test]
+    fn test_cancel_all_skips_finished_jobs() {
+        let mut manager = JobManager::new();
+        manager.set_concurrency("scan", 1);
+        let done = manager.submit("scan", "done", "a");
+        manager.start_runnable();
+        manager.finish(done, Ok(()));
+        let running = manager.submit("scan", "running", "b");
+        let queued = manager.submit("scan", "queued", "c");
+        let started = manager.start_runnable();
+
+        assert_eq!(manager.cancel_all(), 2);
+        assert!(started[0].cancel.is_cancelled());
+        assert_eq!(status(&manager, done), JobStatus::Completed);
+        assert_eq!(status(&manager, running), JobStatus::Running);
+        assert_eq!(status(&manager, queued), JobStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_finished_jobs_are_pruned() {
+        let mut manager = JobManager::new();
+        manager.set_concurrency("dir-size", usize::MAX);
+        let ids: Vec<JobId> = (0..MAX_FINISHED_JOBS + 5)
+            .map(|_| manager.submit("dir-size", "size", ()))
+            .collect();
+        let active = manager.submit("dir-size", "still running", ());
+        manager.start_runnable();
+        for id in &ids {
+            manager.finish(*id, Ok(()));
+        }
+
+        let listed = manager.list();
+        assert_eq!(listed.len(), MAX_FINISHED_JOBS + 1);
+        assert_eq!(listed[0].id, ids[5]);
+        assert!(listed.iter().any(|j| j.id == active));
+    }
+
+    #[test]
+    fn test_context_reports_through_callback() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let report: ProgressFn = Arc::new(move |id, percent, message| {
+            sink.lock().unwrap().push((id, percent, message));
+        });
+        let cancel = CancelToken::default();
+        let ctx = JobContext::new(7, cancel.clone(), report);
+
+        ctx.progress(Some(40), Some("scanning".to_string()));
+        assert!(!ctx.is_cancelled());
+        cancel.cancel();
+        assert!(ctx.is_cancelled());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(7, Some(40), Some("scanning".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_job_info_serialization() {
+        let mut manager = JobManager::new();
+        let id = manager.submit("install", "npm install", ());
+        manager.start_runnable();
+        manager.finish(id, Err("boom".to_string()));
+        let json = serde_json::to_value(manager.get(id).unwrap()).unwrap();
+        assert_eq!(json["kind"], "install");
+        assert_eq!(json["status"]["state"], "failed");
+        assert_eq!(json["status"]["error"], "boom");
+        assert!(json["createdMs"].is_u64());
+    }
+}
@@ -0,0 +1,130 @@
+//! Runner and Tauri commands for background jobs. Queueing and bookkeeping
+//! live in jobs.rs.
+//!
+//! Rust modules start work with [`spawn_job`]. Every state change is
+//! broadcast as `job-updated` with the job's [`JobInfo`] (at most every
+//! 100 ms per job, see event_throttle.rs), and running jobs
+//! feed the dock / taskbar progress bar.
+
+use super::dock_badge::JobProgress;
+use super::dock_badge_commands::report_job_progress;
+use super::error::KiriError;
+use super::event_throttle_commands::emit_throttled;
+use super::jobs::{JobContext, JobId, JobInfo, JobState, JobStatus, JobTask, StartedJob};
+use super::lock_ext::LockExt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+pub const JOB_UPDATED_EVENT: &str = "job-updated";
+
+fn publish(app: &AppHandle, info: &JobInfo) {
+    let progress = match info.status {
+        JobStatus::Running => Some(match info.progress {
+            Some(p) => JobProgress::Percent(p),
+            None => JobProgress::Indeterminate,
+        }),
+        _ => None,
+    };
+    report_job_progress(app, &format!("job-{}", info.id), progress);
+    emit_throttled(app, JOB_UPDATED_EVENT, &info.id.to_string(), info);
+}
+
+/// Queue `task` as a job of `kind` and start it if the kind's concurrency
+/// cap allows. Returns the job id straight away.
+pub fn spawn_job<F>(app: &AppHandle, kind: &str, label: &str, task: F) -> Result<JobId, String>
+where
+    F: FnOnce(&JobContext) -> Result<(), String> + Send + 'static,
+{
+    let state = app
+        .try_state::<JobState>()
+        .ok_or("Job manager is not available")?;
+    let info = {
+        let mut manager = state.lock_recover();
+        let id = manager.submit(kind, label, Box::new(task) as JobTask);
+        manager.get(id)
+    };
+    let info = info.ok_or("Job was dropped before it was queued")?;
+    publish(app, &info);
+    start_runnable(app);
+    Ok(info.id)
+}
+
+/// Start every queued job the caps allow, each on a blocking thread.
+fn start_runnable(app: &AppHandle) {
+    let Some(state) = app.try_state::<JobState>() else {
+        return;
+    };
+    let (started, infos): (Vec<_>, Vec<_>) = {
+        let mut manager = state.lock_recover();
+        manager
+            .start_runnable()
+            .into_iter()
+            .filter_map(|job| manager.get(job.id).map(|info| (job, info)))
+            .unzip()
+    };
+    for info in &infos {
+        publish(app, info);
+    }
+    for job in started {
+        let app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || run_job(app, job));
+    }
+}
+
+fn run_job(app: AppHandle, job: StartedJob<JobTask>) {
+    let reporter = app.clone();
+    let ctx = JobContext::new(
+        job.id,
+        job.cancel,
+        Arc::new(move |id, percent, message| {
+            let Some(state) = reporter.try_state::<JobState>() else {
+                return;
+            };
+            let info = state.lock_recover().set_progress(id, percent, message);
+            if let Some(info) = info {
+                publish(&reporter, &info);
+            }
+        }),
+    );
+
+    // A panicking task would otherwise leave its job running forever and
+    // hold one of its kind's slots.
+    let result = catch_unwind(AssertUnwindSafe(|| (job.task)(&ctx)))
+        .unwrap_or_else(|_| Err("Job panicked".to_string()));
+    if let Err(e) = &result {
+        log::warn!("job {} failed: {}", job.id, e);
+    }
+
+    let info = app
+        .try_state::<JobState>()
+        .and_then(|state| state.lock_recover().finish(job.id, result));
+    if let Some(info) = info {
+        publish(&app, &info);
+    }
+    start_runnable(&app);
+}
+
+/// Every running, queued and recently finished job. Windows call this
+/// after a reload to pick up work that is still in flight.
+#[tauri::command]
+pub fn list_jobs(state: tauri::State<'_, JobState>) -> Vec<JobInfo> {
+    state.lock_recover().list()
+}
+
+/// Cancel a job. Queued jobs are dropped at once; running jobs are asked
+/// to stop and report `cancelled` when they do.
+#[tauri::command]
+pub fn cancel_job(app: AppHandle, id: JobId) -> Result<JobInfo, KiriError> {
+    cancel(&app, id).map_err(KiriError::from)
+}
+
+/// [`cancel_job`] for Rust modules that stop jobs they started.
+pub fn cancel(app: &AppHandle, id: JobId) -> Result<JobInfo, String> {
+    let state = app
+        .try_state::<JobState>()
+        .ok_or("Job manager is not available")?;
+    let info = state.lock_recover().cancel(id)?;
+    publish(app, &info);
+    Ok(info)
+}
@@ -0,0 +1,369 @@
+//! User-configurable menu keybindings: the remappable commands, their
+//! default accelerators, validation and conflict detection. The menu reads
+//! the effective accelerators when it is built (menu.rs); commands that
+//! change the keymap live in keymap_commands.rs.
+//!
+//! Only overrides are stored, in `~/.kiri/keymap.json`, so changing a
+//! default in a later release reaches users who never touched it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::accelerator::{accelerator_key, normalize_accelerator};
+use super::file_io::write_file_contents_atomic;
+
+/// A menu item whose accelerator the user may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeymapCommand {
+    /// Menu item id, also the keymap key.
+    pub id: &'static str,
+    /// Top-level menu the item lives in.
+    pub menu: &'static str,
+    pub label: &'static str,
+    pub default: Option<&'static str>,
+}
+
+/// Go and Terminal items start unbound: the webview already handles those
+/// shortcuts itself, and a menu accelerator would intercept the keystroke
+/// before it reaches the page.
+pub const KEYMAP_COMMANDS: [KeymapCommand; 8] = [
+    KeymapCommand {
+        id: "new_window",
+        menu: "File",
+        label: "New Window",
+        default: Some("CmdOrCtrl+Shift+N"),
+    },
+    KeymapCommand {
+        id: "open",
+        menu: "File",
+        label: "Open...",
+        default: Some("CmdOrCtrl+O"),
+    },
+    KeymapCommand {
+        id: "go_quick_open",
+        menu: "Go",
+        label: "Go to File...",
+        default: None,
+    },
+    KeymapCommand {
+        id: "go_search_content",
+        menu: "Go",
+        label: "Search in Files...",
+        default: None,
+    },
+    KeymapCommand {
+        id: "go_diff_view",
+        menu: "Go",
+        label: "Changes",
+        default: None,
+    },
+    KeymapCommand {
+        id: "go_commit_history",
+        menu: "Go",
+        label: "Commit History",
+        default: None,
+    },
+    KeymapCommand {
+        id: "terminal_new",
+        menu: "Terminal",
+        label: "New Terminal",
+        default: None,
+    },
+    KeymapCommand {
+        id: "terminal_clear",
+        menu: "Terminal",
+        label: "Clear Terminal",
+        default: None,
+    },
+];
+
+/// Accelerators of the predefined Edit/Window/app menu items, which cannot
+/// be remapped and must not be shadowed.
+const RESERVED: [&str; 10] = [
+    "CmdOrCtrl+Z",
+    "CmdOrCtrl+Shift+Z",
+    "CmdOrCtrl+X",
+    "CmdOrCtrl+C",
+    "CmdOrCtrl+V",
+    "CmdOrCtrl+A",
+    "CmdOrCtrl+W",
+    "CmdOrCtrl+M",
+    "CmdOrCtrl+Q",
+    "CmdOrCtrl+H",
+];
+
+pub fn keymap_command(id: &str) -> Option<&'static KeymapCommand> {
+    KEYMAP_COMMANDS.iter().find(|c| c.id == id)
+}
+
+/// One row of the keymap as shown in settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeymapEntry {
+    pub id: String,
+    pub menu: String,
+    pub label: String,
+    pub accelerator: Option<String>,
+    pub default: Option<String>,
+    /// Whether the user changed this binding.
+    pub customized: bool,
+}
+
+/// User overrides keyed by command id. `None` unbinds a command that has
+/// a default. Serialized as-is to `keymap.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Keymap {
+    pub overrides: BTreeMap<String, Option<String>>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Effective accelerator for `id`: the override if any, else the
+    /// default.
+    pub fn accelerator(&self, id: &str) -> Option<String> {
+        match self.overrides.get(id) {
+            Some(accel) => accel.clone(),
+            None => keymap_command(id).and_then(|c| c.default.map(str::to_string)),
+        }
+    }
+
+    pub fn entries(&self) -> Vec<KeymapEntry> {
+        KEYMAP_COMMANDS
+            .iter()
+            .map(|c| KeymapEntry {
+                id: c.id.to_string(),
+                menu: c.menu.to_string(),
+                label: c.label.to_string(),
+                accelerator: self.accelerator(c.id),
+                default: c.default.map(str::to_string),
+                customized: self.overrides.contains_key(c.id),
+            })
+            .collect()
+    }
+
+    /// Another command whose effective accelerator is `accelerator`.
+    fn bound_elsewhere(&self, id: &str, accelerator: &str) -> Option<&'static KeymapCommand> {
+        let wanted = accelerator_key(accelerator);
+        KEYMAP_COMMANDS.iter().find(|c| {
+            c.id != id
+                && self
+                    .accelerator(c.id)
+                    .is_some_and(|other| accelerator_key(&other) == wanted)
+        })
+    }
+
+    /// Bind `accelerator` to `id`, or unbind it with `None`. Fails without
+    /// changing anything if the command is unknown, the accelerator is
+    /// invalid, or another item already uses it.
+    pub fn set(&mut self, id: &str, accelerator: Option<&str>) -> Result<(), String> {
+        let command = keymap_command(id).ok_or_else(|| format!("Unknown command: {}", id))?;
+        let canonical = accelerator.map(normalize_accelerator).transpose()?;
+        if let Some(accel) = canonical.as_deref() {
+            let wanted = accelerator_key(accel);
+            if RESERVED.iter().any(|r| accelerator_key(r) == wanted) {
+                return Err(format!("{} is reserved by the Edit or Window menu", accel));
+            }
+            if let Some(other) = self.bound_elsewhere(id, accel) {
+                return Err(format!(
+                    "{} is already assigned to {} > {}",
+                    accel, other.menu, other.label
+                ));
+            }
+        }
+        if canonical.as_deref() == command.default {
+            self.overrides.remove(id);
+        } else {
+            self.overrides.insert(id.to_string(), canonical);
+        }
+        Ok(())
+    }
+
+    /// Restore the default for `id`, or for every command with `None`.
+    /// A default that now clashes with a user binding elsewhere is left
+    /// unbound rather than producing a duplicate.
+    pub fn reset(&mut self, id: Option<&str>) -> Result<(), String> {
+        let Some(id) = id else {
+            self.overrides.clear();
+            return Ok(());
+        };
+        keymap_command(id).ok_or_else(|| format!("Unknown command: {}", id))?;
+        self.overrides.remove(id);
+        let clashes = self
+            .accelerator(id)
+            .is_some_and(|accel| self.bound_elsewhere(id, &accel).is_some());
+        if clashes {
+            self.overrides.insert(id.to_string(), None);
+        }
+        Ok(())
+    }
+}
+
+pub type KeymapState = Arc<Mutex<Keymap>>;
+
+/// `~/.kiri/keymap.json` — persisted keymap overrides.
+pub fn keymap_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("keymap.json"))
+}
+
+/// Load overrides, dropping unknown commands and accelerators that no
+/// longer parse. A missing or unreadable file yields the defaults.
+pub fn load_keymap_file(path: &Path) -> Keymap {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Keymap::default();
+    };
+    let mut loaded: Keymap = match serde_json::from_str(&contents) {
+        Ok(k) => k,
+        Err(e) => {
+            log::warn!("failed to parse keymap file: {}", e);
+            return Keymap::default();
+        }
+    };
+    loaded.overrides.retain(|id, accel| {
+        if keymap_command(id).is_none() {
+            log::warn!("dropping keybinding for unknown command {}", id);
+            return false;
+        }
+        match accel.as_deref().map(normalize_accelerator).transpose() {
+            Ok(canonical) => {
+                *accel = canonical;
+                true
+            }
+            Err(e) => {
+                log::warn!("dropping keybinding for {}: {}", id, e);
+                false
+            }
+        }
+    });
+    loaded
+}
+
+pub fn write_keymap_file(path: &Path, keymap: &Keymap) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create keymap directory: {}", e))?;
+    }
+    let json = serde_json::to_vec_pretty(keymap)
+        .map_err(|e| format!("Failed to serialize keymap: {}", e))?;
+    write_file_contents_atomic(path, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_defaults() {
+        let keymap = Keymap::new();
+        assert_eq!(
+            keymap.accelerator("new_window").as_deref(),
+            Some("CmdOrCtrl+Shift+N")
+        );
+        assert_eq!(keymap.accelerator("go_quick_open"), None);
+        assert_eq!(keymap.accelerator("unknown"), None);
+        assert!(keymap.entries().iter().all(|e| !e.customized));
+    }
+
+    #[test]
+    fn test_set_normalizes_and_tracks_override() {
+        let mut keymap = Keymap::new();
+        keymap.set("go_quick_open", Some("cmdorctrl+p")).unwrap();
+        assert_eq!(
+            keymap.accelerator("go_quick_open").as_deref(),
+            Some("CmdOrCtrl+P")
+        );
+        let entry = keymap
+            .entries()
+            .into_iter()
+            .find(|e| e.id == "go_quick_open")
+            .unwrap();
+        assert!(entry.customized);
+        assert_eq!(entry.menu, "Go");
+    }
+
+    #[test]
+    fn test_set_default_clears_override() {
+        let mut keymap = Keymap::new();
+        keymap.set("open", None).unwrap();
+        assert_eq!(keymap.accelerator("open"), None);
+        keymap.set("open", Some("CmdOrCtrl+O")).unwrap();
+        assert!(keymap.overrides.is_empty());
+    }
+
+    #[test]
+    fn test_set_rejects_conflicts() {
+        let mut keymap = Keymap::new();
+        let err = keymap
+            .set("terminal_new", Some("CmdOrCtrl+Shift+N"))
+            .unwrap_err();
+        assert!(err.contains("File > New Window"), "{err}");
+        assert!(keymap.set("terminal_new", Some("CmdOrCtrl+C")).is_err());
+        assert!(keymap.set("terminal_new", Some("T")).is_err());
+        assert!(keymap.set("nope", Some("CmdOrCtrl+T")).is_err());
+        assert!(keymap.overrides.is_empty());
+
+        // Rebinding a command to its own accelerator is fine.
+        keymap.set("new_window", Some("CmdOrCtrl+Shift+N")).unwrap();
+    }
+
+    #[test]
+    fn test_freed_accelerator_can_be_reused() {
+        let mut keymap = Keymap::new();
+        keymap.set("open", Some("CmdOrCtrl+Shift+O")).unwrap();
+        keymap.set("go_quick_open", Some("CmdOrCtrl+O")).unwrap();
+        assert_eq!(
+            keymap.accelerator("go_quick_open").as_deref(),
+            Some("CmdOrCtrl+O")
+        );
+    }
+
+    #[test]
+    fn test_reset_leaves_clashing_default_unbound() {
+        let mut keymap = Keymap::new();
+        keymap.set("open", Some("CmdOrCtrl+Shift+O")).unwrap();
+        keymap.set("go_quick_open", Some("CmdOrCtrl+O")).unwrap();
+        keymap.reset(Some("open")).unwrap();
+        assert_eq!(keymap.accelerator("open"), None);
+
+        keymap.reset(None).unwrap();
+        assert_eq!(keymap.accelerator("open").as_deref(), Some("CmdOrCtrl+O"));
+        assert_eq!(keymap.accelerator("go_quick_open"), None);
+        assert!(keymap.reset(Some("nope")).is_err());
+    }
+
+    #[test]
+    fn test_keymap_file_round_trip_drops_invalid() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("keymap.json");
+        let mut keymap = Keymap::new();
+        keymap
+            .overrides
+            .insert("terminal_new".to_string(), Some("ctrl+alt+t".to_string()));
+        keymap.overrides.insert("open".to_string(), None);
+        keymap
+            .overrides
+            .insert("go_diff_view".to_string(), Some("not a key".to_string()));
+        keymap.overrides.insert(
+            "removed_command".to_string(),
+            Some("CmdOrCtrl+K".to_string()),
+        );
+        write_keymap_file(&path, &keymap).unwrap();
+
+        let loaded = load_keymap_file(&path);
+        assert_eq!(loaded.overrides.len(), 2);
+        assert_eq!(
+            loaded.accelerator("terminal_new").as_deref(),
+            Some("Ctrl+Alt+T")
+        );
+        assert_eq!(loaded.accelerator("open"), None);
+        assert_eq!(
+            load_keymap_file(&dir.path().join("missing.json")),
+            Keymap::default()
+        );
+    }
+}
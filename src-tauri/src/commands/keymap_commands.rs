@@ -0,0 +1,73 @@
+//! Tauri commands for the menu keymap. Validation and persistence live in
+//! keymap.rs.
+//!
+//! Every change rebuilds the menu so the new accelerators take effect at
+//! once, and broadcasts `keymap-changed` with the full keymap so open
+//! settings views and shortcut hints stay in sync.
+
+use super::error::KiriError;
+use super::keymap::{
+    keymap_file_path, load_keymap_file, write_keymap_file, Keymap, KeymapEntry, KeymapState,
+};
+use super::lock_ext::LockExt;
+use super::menu::rebuild_app_menu;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Load `~/.kiri/keymap.json` into [`KeymapState`]. Call before the menu
+/// is first built.
+pub fn load_keymap(app: &AppHandle) {
+    let Some(path) = keymap_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<KeymapState>() {
+        *state.lock_recover() = load_keymap_file(&path);
+    }
+}
+
+/// Persist `keymap`, rebuild the menu and notify every window.
+fn commit(app: &AppHandle, keymap: &Keymap) -> Result<Vec<KeymapEntry>, String> {
+    if let Some(path) = keymap_file_path() {
+        write_keymap_file(&path, keymap)?;
+    }
+    let entries = keymap.entries();
+    rebuild_app_menu(app);
+    let _ = app.emit("keymap-changed", &entries);
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn get_keymap(state: tauri::State<'_, KeymapState>) -> Vec<KeymapEntry> {
+    state.lock_recover().entries()
+}
+
+/// Bind `accelerator` to the menu command `id`, or unbind it with `null`.
+/// Fails without changing anything on an invalid or conflicting
+/// accelerator. Returns the updated keymap.
+#[tauri::command]
+pub fn set_keybinding(
+    app: AppHandle,
+    state: tauri::State<'_, KeymapState>,
+    id: String,
+    accelerator: Option<String>,
+) -> Result<Vec<KeymapEntry>, KiriError> {
+    // Work on a copy so the state lock isn't held while the menu, which
+    // reads the keymap, is rebuilt.
+    let mut keymap = state.lock_recover().clone();
+    keymap.set(&id, accelerator.as_deref())?;
+    *state.lock_recover() = keymap.clone();
+    commit(&app, &keymap).map_err(KiriError::from)
+}
+
+/// Restore the default binding for `id`, or for every command when `id`
+/// is omitted. Returns the updated keymap.
+#[tauri::command]
+pub fn reset_keybinding(
+    app: AppHandle,
+    state: tauri::State<'_, KeymapState>,
+    id: Option<String>,
+) -> Result<Vec<KeymapEntry>, KiriError> {
+    let mut keymap = state.lock_recover().clone();
+    keymap.reset(id.as_deref())?;
+    *state.lock_recover() = keymap.clone();
+    commit(&app, &keymap).map_err(KiriError::from)
+}
@@ -0,0 +1,229 @@
+//! Guard against opening files the editor can't handle.
+//!
+//! Before `read_file` returns a file it checks it against the
+//! [`LargeFileLimits`] from settings: a file above `largeFileBytes`, or
+//! with a line longer than `largeFileLineLength` (minified bundles,
+//! one-line JSON), is refused with code `file_too_large` and a
+//! [`LargeFile`] in the error's details. It says what was over the limit
+//! and which ways of opening the file still work: read-only anyway
+//! (`read_file` with `force`), page by page, or as hex. Pages of either
+//! come from [`read_chunk`].
+
+use base64::Engine;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use super::error::{ErrorCode, ErrorDetails, KiriError};
+use super::i18n::Message;
+
+pub const DEFAULT_LARGE_FILE_BYTES: u64 = 10 * 1024 * 1024;
+pub const MIN_LARGE_FILE_BYTES: u64 = 1024 * 1024;
+pub const MAX_LARGE_FILE_BYTES: u64 = 1024 * 1024 * 1024;
+
+pub const DEFAULT_LARGE_FILE_LINE_LENGTH: u32 = 20_000;
+pub const MIN_LARGE_FILE_LINE_LENGTH: u32 = 1_000;
+pub const MAX_LARGE_FILE_LINE_LENGTH: u32 = 10_000_000;
+
+/// Files more than this many times over the size limit aren't offered
+/// read-only; the webview would choke on them all the same.
+const READ_ONLY_FACTOR: u64 = 4;
+
+/// Largest page [`read_chunk`] returns.
+pub const MAX_CHUNK_BYTES: u64 = 1024 * 1024;
+
+static MAX_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_LARGE_FILE_BYTES);
+static MAX_LINE_LENGTH: AtomicU32 = AtomicU32::new(DEFAULT_LARGE_FILE_LINE_LENGTH);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LargeFileLimits {
+    pub max_bytes: u64,
+    /// In bytes.
+    pub max_line_length: u32,
+}
+
+/// The limits `read_file` applies, from settings.
+pub fn large_file_limits() -> LargeFileLimits {
+    LargeFileLimits {
+        max_bytes: MAX_BYTES.load(Ordering::Relaxed),
+        max_line_length: MAX_LINE_LENGTH.load(Ordering::Relaxed),
+    }
+}
+
+/// Apply the `largeFileBytes` and `largeFileLineLength` settings.
+pub fn apply_large_file_limits(limits: LargeFileLimits) {
+    MAX_BYTES.store(limits.max_bytes, Ordering::Relaxed);
+    MAX_LINE_LENGTH.store(limits.max_line_length, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LargeFileReason {
+    Size,
+    LineLength,
+}
+
+/// A way to show a file too large for the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpenStrategy {
+    /// The whole file, without editing.
+    ReadOnly,
+    /// Page through it as text.
+    Chunked,
+    Hex,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LargeFile {
+    pub reason: LargeFileReason,
+    pub size: u64,
+    /// Known when the file was read, i.e. for [`LargeFileReason::LineLength`].
+    pub line_count: Option<usize>,
+    pub longest_line: Option<usize>,
+    pub limits: LargeFileLimits,
+    /// Best first.
+    pub strategies: Vec<OpenStrategy>,
+}
+
+impl LargeFile {
+    pub fn into_error(self) -> KiriError {
+        let key = match self.reason {
+            LargeFileReason::Size => "error.file_too_large",
+            LargeFileReason::LineLength => "error.file_lines_too_long",
+        };
+        KiriError::localized(ErrorCode::FileTooLarge, Message::new(key))
+            .with_details(ErrorDetails::LargeFile(self))
+    }
+}
+
+/// Whether `size` is past what even a forced read-only open allows.
+pub fn beyond_read_only(size: u64, limits: LargeFileLimits) -> bool {
+    size > limits.max_bytes.saturating_mul(READ_ONLY_FACTOR)
+}
+
+/// A [`LargeFile`] when a file of `size` bytes is over the limit.
+pub fn check_size(size: u64, limits: LargeFileLimits) -> Option<LargeFile> {
+    if size <= limits.max_bytes {
+        return None;
+    }
+    let mut strategies = vec![OpenStrategy::Chunked, OpenStrategy::Hex];
+    if !beyond_read_only(size, limits) {
+        strategies.insert(0, OpenStrategy::ReadOnly);
+    }
+    Some(LargeFile {
+        reason: LargeFileReason::Size,
+        size,
+        line_count: None,
+        longest_line: None,
+        limits,
+        strategies,
+    })
+}
+
+/// A [`LargeFile`] when `text` has a line over the limit.
+pub fn check_lines(text: &str, limits: LargeFileLimits) -> Option<LargeFile> {
+    let mut line_count = 0;
+    let mut longest_line = 0;
+    for line in text.as_bytes().split(|b| *b == b'\n') {
+        line_count += 1;
+        longest_line = longest_line.max(line.len());
+    }
+    if longest_line <= limits.max_line_length as usize {
+        return None;
+    }
+    Some(LargeFile {
+        reason: LargeFileReason::LineLength,
+        size: text.len() as u64,
+        line_count: Some(line_count),
+        longest_line: Some(longest_line),
+        limits,
+        strategies: vec![OpenStrategy::ReadOnly, OpenStrategy::Hex],
+    })
+}
+
+/// Bytes of a file from `offset`; see [`read_chunk`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChunk {
+    pub offset: u64,
+    #[serde(rename = "dataBase64", serialize_with = "serialize_base64")]
+    pub data: Vec<u8>,
+    /// Of the whole file.
+    pub size: u64,
+    pub eof: bool,
+}
+
+fn serialize_base64<S: serde::Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(data))
+}
+
+/// Up to `length` bytes of `path` from `offset`, at most
+/// [`MAX_CHUNK_BYTES`]. A page of text may start or end inside a UTF-8
+/// character; the caller decodes across pages.
+pub fn read_chunk(path: &Path, offset: u64, length: u64) -> io::Result<FileChunk> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let offset = offset.min(size);
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    file.take(length.min(MAX_CHUNK_BYTES))
+        .read_to_end(&mut data)?;
+    let eof = offset + data.len() as u64 >= size;
+    Ok(FileChunk {
+        offset,
+        data,
+        size,
+        eof,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const LIMITS: LargeFileLimits = LargeFileLimits {
+        max_bytes: 100,
+        max_line_length: 10,
+    };
+
+    #[test]
+    fn test_checks_and_strategies() {
+        assert_eq!(check_size(100, LIMITS), None);
+        let large = check_size(101, LIMITS).unwrap();
+        assert_eq!(large.reason, LargeFileReason::Size);
+        assert_eq!(large.strategies[0], OpenStrategy::ReadOnly);
+        let huge = check_size(401, LIMITS).unwrap();
+        assert!(!huge.strategies.contains(&OpenStrategy::ReadOnly));
+
+        assert_eq!(check_lines("short\nlines\n", LIMITS), None);
+        let minified = check_lines("a\nfunction(){return 1}\n", LIMITS).unwrap();
+        assert_eq!(minified.reason, LargeFileReason::LineLength);
+        assert_eq!(
+            (minified.line_count, minified.longest_line),
+            (Some(3), Some(20))
+        );
+    }
+
+    #[test]
+    fn test_read_chunk() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("log.txt");
+        std::fs::write(&path, "0123456789").unwrap();
+
+        let chunk = read_chunk(&path, 2, 4).unwrap();
+        assert_eq!(
+            (chunk.data.as_slice(), chunk.size, chunk.eof),
+            (&b"2345"[..], 10, false)
+        );
+        let tail = read_chunk(&path, 8, 100).unwrap();
+        assert_eq!((tail.data.as_slice(), tail.eof), (&b"89"[..], true));
+        let past = read_chunk(&path, 50, 4).unwrap();
+        assert_eq!((past.offset, past.data.len(), past.eof), (10, 0, true));
+    }
+}
@@ -0,0 +1,222 @@
+//! Latency budget for the command invoke path.
+//!
+//! A synchronous `#[tauri::command]` runs on the thread that dispatched the
+//! IPC message, and until it returns that thread serves nothing else. Sync
+//! commands must therefore finish within [`SYNC_COMMAND_BUDGET`]. In
+//! practice that means lookups in managed state, single `stat`/`open`
+//! calls, and small reads or writes.
+//!
+//! Anything that can take longer is an async command that hands its body
+//! to [`run_blocking`]. That covers directory walks and searches, git
+//! status, diff and history, network operations (push, fetch, pull),
+//! recursive copies, moves and deletes, and subprocesses. The runtime stays
+//! free to dispatch other commands meanwhile.
+//!
+//! The invoke-handler wrapper in performance_commands.rs logs sync commands
+//! that overrun the budget while timing is enabled. The tests below check
+//! that the async commands never stall the runtime.
+
+use std::time::Duration;
+
+use super::error::KiriError;
+use super::performance::CommandTimer;
+
+/// Longest a synchronous command may hold the invoke path.
+pub const SYNC_COMMAND_BUDGET: Duration = Duration::from_millis(50);
+
+/// Run `f` on tokio's blocking pool and time it as `command`. Its error
+/// becomes the command's [`KiriError`], with code `internal`.
+pub async fn run_blocking<T: Send + 'static>(
+    command: &'static str,
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, KiriError> {
+    run_blocking_coded(command, move || f().map_err(KiriError::from)).await
+}
+
+/// [`run_blocking`] for a body that picks the code of its errors itself.
+pub async fn run_blocking_coded<T: Send + 'static>(
+    command: &'static str,
+    f: impl FnOnce() -> Result<T, KiriError> + Send + 'static,
+) -> Result<T, KiriError> {
+    let _timer = CommandTimer::start(command);
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("{} task panicked: {}", command, e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::confirmation::{confirmations, PrivilegedAction};
+    use crate::commands::lock_ext::LockExt;
+    use crate::commands::{drag_drop, fs as fs_commands, git, git_history_commands, search};
+    use git2::Repository;
+    use std::fs;
+    use std::future::Future;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+    use tempfile::tempdir;
+
+    /// Await `fut` next to a task that ticks every millisecond, and return
+    /// the longest gap between ticks. The tests use tokio's current-thread
+    /// runtime, so work done inline in `fut` delays the ticker for exactly
+    /// as long as it would delay other commands.
+    async fn max_stall<F: Future>(fut: F) -> (F::Output, Duration) {
+        let done = Arc::new(AtomicBool::new(false));
+        let ticking = done.clone();
+        let ticker = tokio::spawn(async move {
+            let mut last = Instant::now();
+            let mut worst = Duration::ZERO;
+            while !ticking.load(Ordering::Acquire) {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                let now = Instant::now();
+                worst = worst.max(now - last);
+                last = now;
+            }
+            worst
+        });
+        tokio::task::yield_now().await;
+        let output = fut.await;
+        done.store(true, Ordering::Release);
+        (output, ticker.await.unwrap())
+    }
+
+    fn s(path: &Path) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    /// A committed repository with enough files that walking it takes a
+    /// while, some of them modified or untracked.
+    fn busy_repo(root: &Path) {
+        let repo = Repository::init(root).unwrap();
+        for d in 0..20 {
+            let dir = root.join(format!("dir{}", d));
+            fs::create_dir(&dir).unwrap();
+            for f in 0..50 {
+                fs::write(dir.join(format!("file{}.rs", f)), "fn main() {}\n".repeat(20)).unwrap();
+            }
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        for d in 0..20 {
+            let dir = root.join(format!("dir{}", d));
+            fs::write(dir.join("file0.rs"), "fn main() { changed() }\n").unwrap();
+            fs::write(dir.join("new.rs"), "fn new() {}\n").unwrap();
+        }
+    }
+
+    fn assert_within_budget(command: &str, stall: Duration) {
+        assert!(
+            stall < SYNC_COMMAND_BUDGET,
+            "{} blocked the runtime for {:?}",
+            command,
+            stall
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_stall_detects_inline_blocking() {
+        let (_, stall) = max_stall(async {
+            std::thread::sleep(Duration::from_millis(120));
+        })
+        .await;
+        assert!(stall >= Duration::from_millis(100), "stall was {:?}", stall);
+    }
+
+    #[tokio::test]
+    async fn test_run_blocking_reports_panics() {
+        let result: Result<(), KiriError> = run_blocking("panicky", || panic!("boom")).await;
+        assert!(result
+            .unwrap_err()
+            .message
+            .starts_with("panicky task panicked"));
+    }
+
+    #[tokio::test]
+    async fn test_git_commands_stay_within_budget() {
+        let dir = tempdir().unwrap();
+        busy_repo(dir.path());
+        let root = s(dir.path());
+
+        let (status, stall) = max_stall(git::get_git_status(root.clone())).await;
+        assert!(status.unwrap().statuses.len() >= 40);
+        assert_within_budget("get_git_status", stall);
+
+        let (diffs, stall) = max_stall(git::get_all_git_diffs(root.clone())).await;
+        assert!(diffs.unwrap().len() >= 40);
+        assert_within_budget("get_all_git_diffs", stall);
+
+        let (diff, stall) =
+            max_stall(git::get_git_diff(root.clone(), "dir0/file0.rs".to_string())).await;
+        assert!(diff.unwrap().contains("changed"));
+        assert_within_budget("get_git_diff", stall);
+
+        let (log, stall) =
+            max_stall(git_history_commands::get_commit_log(root, Some(10), None)).await;
+        assert_eq!(log.unwrap().len(), 1);
+        assert_within_budget("get_commit_log", stall);
+    }
+
+    #[tokio::test]
+    async fn test_search_stays_within_budget() {
+        let dir = tempdir().unwrap();
+        busy_repo(dir.path());
+
+        let (results, stall) = max_stall(search::search_content(
+            s(dir.path()),
+            "main".to_string(),
+            10_000,
+            Vec::new(),
+            None,
+        ))
+        .await;
+        assert!(!results.unwrap().is_empty());
+        assert_within_budget("search_content", stall);
+    }
+
+    #[tokio::test]
+    async fn test_recursive_fs_commands_stay_within_budget() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir(&source).unwrap();
+        busy_repo(&source);
+        let copies = dir.path().join("copies");
+        fs::create_dir(&copies).unwrap();
+        let moved = dir.path().join("moved");
+        fs::create_dir(&moved).unwrap();
+
+        let (copy, stall) = max_stall(drag_drop::copy_paths_to_directory(
+            vec![s(&source)],
+            s(&copies),
+            None,
+        ))
+        .await;
+        assert!(copy.unwrap().success);
+        assert_within_budget("copy_paths_to_directory", stall);
+
+        let (moved_to, stall) =
+            max_stall(drag_drop::move_path(s(&copies.join("source")), s(&moved))).await;
+        let moved_to = moved_to.unwrap();
+        assert_within_budget("move_path", stall);
+
+        let token = confirmations().lock_recover().issue(
+            PrivilegedAction::DeletePermanently,
+            Path::new(&moved_to),
+            Instant::now(),
+        );
+        let (deleted, stall) =
+            max_stall(fs_commands::delete_path(moved_to.clone(), Some(token))).await;
+        deleted.unwrap();
+        assert!(!Path::new(&moved_to).exists());
+        assert_within_budget("delete_path", stall);
+    }
+}
@@ -0,0 +1,105 @@
+//! localtunnel (`lt`, from the `localtunnel` npm package); see tunnel.rs.
+//!
+//! No account is needed. A subdomain of `loca.lt` can be asked for, and is
+//! given if nobody else holds it.
+
+use serde::{Deserialize, Serialize};
+
+use super::tunnel::{Capabilities, LogEvent, ProviderKind, TunnelProvider};
+
+lazy_static::lazy_static! {
+    static ref SUBDOMAIN: regex::Regex =
+        regex::Regex::new(r"^[a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?$").expect("pattern compiles");
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Localtunnel {
+    #[serde(default)]
+    pub subdomain: Option<String>,
+}
+
+impl TunnelProvider for Localtunnel {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Localtunnel
+    }
+
+    fn label(&self) -> &'static str {
+        "localtunnel"
+    }
+
+    fn program(&self) -> &'static str {
+        "lt"
+    }
+
+    fn install_hint(&self) -> &'static str {
+        "npm install -g localtunnel"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            requires_account: false,
+            custom_domain: false,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match &self.subdomain {
+            Some(subdomain) if !SUBDOMAIN.is_match(subdomain) => {
+                Err(format!("Invalid subdomain: {}", subdomain))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn args(&self, port: u16) -> Vec<String> {
+        let mut args: Vec<String> = vec!["--port".into(), port.to_string()];
+        if let Some(subdomain) = &self.subdomain {
+            args.extend(["--subdomain".into(), subdomain.clone()]);
+        }
+        args
+    }
+
+    fn log_event(&self, line: &str) -> Option<LogEvent> {
+        if let Some(url) = line.trim().strip_prefix("your url is:") {
+            return Some(LogEvent::Ready {
+                url: Some(url.trim().to_string()),
+            });
+        }
+        line.trim()
+            .strip_prefix("Error:")
+            .map(|error| LogEvent::Error(error.trim().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_and_log_events() {
+        let lt = Localtunnel {
+            subdomain: Some("my-app".to_string()),
+        };
+        assert!(lt.validate().is_ok());
+        assert_eq!(
+            lt.args(8080),
+            vec!["--port", "8080", "--subdomain", "my-app"]
+        );
+        let bad = Localtunnel {
+            subdomain: Some("-x".to_string()),
+        };
+        assert!(bad.validate().is_err());
+        assert_eq!(
+            lt.log_event("your url is: https://my-app.loca.lt"),
+            Some(LogEvent::Ready {
+                url: Some("https://my-app.loca.lt".to_string())
+            })
+        );
+        assert_eq!(
+            lt.log_event("Error: connection refused: localtunnel.me:40000"),
+            Some(LogEvent::Error(
+                "connection refused: localtunnel.me:40000".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Structured, rotating file logs for release builds.
+//!
+//! [`install`] makes [`FileLogger`] the `log` logger. Each record is one
+//! JSON line (`ts`, `level`, `target`, `msg`) in `~/.kiri/logs/kiri.log`.
+//! Once the file would pass [`MAX_FILE_SIZE`] it becomes `kiri.log.1`,
+//! older files move up one and the oldest beyond [`MAX_ROTATED`] is
+//! deleted, so logs never take more than a few tens of megabytes.
+//!
+//! Levels are a default plus overrides by module path, the most specific
+//! winning: `{"default": "info", "modules": {"app_lib::commands::git":
+//! "debug"}}`. They can change while running and are saved in
+//! `~/.kiri/logging.json`. The commands live in logging_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use super::clock::now_ms;
+use super::file_io::write_file_contents_atomic;
+use super::lock_ext::LockExt;
+
+pub const LOG_FILE: &str = "kiri.log";
+
+/// Size at which the log file is rotated.
+pub const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Rotated files kept next to the current one.
+pub const MAX_ROTATED: usize = 4;
+
+pub fn logs_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("logs"))
+}
+
+pub fn logging_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("logging.json"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn to_filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogLevels {
+    pub default: LogLevel,
+    /// Overrides by module path, e.g. `app_lib::commands::git`.
+    pub modules: BTreeMap<String, LogLevel>,
+}
+
+impl Default for LogLevels {
+    fn default() -> Self {
+        Self {
+            default: LogLevel::Info,
+            modules: BTreeMap::new(),
+        }
+    }
+}
+
+impl LogLevels {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(levels) => Some(levels),
+                Err(e) => {
+                    log::warn!("failed to parse log levels: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to serialize log levels: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for module in self.modules.keys() {
+            let valid = !module.is_empty()
+                && module.split("::").all(|part| {
+                    !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+                });
+            if !valid {
+                return Err(format!("`{}` is not a module path", module));
+            }
+        }
+        Ok(())
+    }
+
+    /// The level for records from `target`: the override of the longest
+    /// module path it is in, or the default.
+    pub fn level_for(&self, target: &str) -> log::LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module.as_str()
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+            .to_filter()
+    }
+
+    /// The most verbose level any module logs at.
+    pub fn max_level(&self) -> log::LevelFilter {
+        self.modules
+            .values()
+            .map(|level| level.to_filter())
+            .fold(self.default.to_filter(), std::cmp::max)
+    }
+}
+
+/// `kiri.log` in a directory, rotated by size.
+#[derive(Debug)]
+pub struct RotatingFile {
+    dir: PathBuf,
+    max_size: u64,
+    file: Option<File>,
+    size: u64,
+}
+
+/// The log files in `dir`, current first, then from newest to oldest.
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    std::iter::once(dir.join(LOG_FILE))
+        .chain((1..=MAX_ROTATED).map(|n| dir.join(format!("{}.{}", LOG_FILE, n))))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+impl RotatingFile {
+    pub fn new(dir: PathBuf, max_size: u64) -> Self {
+        Self {
+            dir,
+            max_size,
+            file: None,
+            size: 0,
+        }
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("{}.{}", LOG_FILE, n))
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        let _ = std::fs::remove_file(self.rotated(MAX_ROTATED));
+        for n in (1..MAX_ROTATED).rev() {
+            let _ = std::fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        std::fs::rename(self.dir.join(LOG_FILE), self.rotated(1))
+    }
+
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.file.is_none() {
+            std::fs::create_dir_all(&self.dir)?;
+            self.size = std::fs::metadata(self.dir.join(LOG_FILE))
+                .map(|m| m.len())
+                .unwrap_or(0);
+        }
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+            self.size = 0;
+        }
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(LOG_FILE))?,
+        };
+        writeln!(self.file.insert(file), "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LogLine<'a> {
+    ts: u64,
+    level: &'a str,
+    target: &'a str,
+    msg: &'a str,
+}
+
+/// One record as a JSON line.
+pub fn format_record(ts_ms: u64, level: log::Level, target: &str, message: &str) -> String {
+    let line = LogLine {
+        ts: ts_ms,
+        level: level.as_str(),
+        target,
+        msg: message,
+    };
+    serde_json::to_string(&line).unwrap_or_default()
+}
+
+pub struct FileLogger {
+    levels: Mutex<LogLevels>,
+    file: Mutex<RotatingFile>,
+}
+
+impl log::Log for FileLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.levels.lock_recover().level_for(metadata.target())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let ts_ms = now_ms();
+        let line = format_record(
+            ts_ms,
+            record.level(),
+            record.target(),
+            &record.args().to_string(),
+        );
+        // Nowhere to report a failure to log.
+        let _ = self.file.lock_recover().write_line(&line);
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock_recover().flush();
+    }
+}
+
+static LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+/// Log to `dir` at `levels` from now on. Fails if a logger is already
+/// installed.
+pub fn install(dir: PathBuf, levels: LogLevels) -> Result<(), String> {
+    let max_level = levels.max_level();
+    let logger = LOGGER.get_or_init(|| FileLogger {
+        levels: Mutex::new(levels),
+        file: Mutex::new(RotatingFile::new(dir, MAX_FILE_SIZE)),
+    });
+    log::set_logger(logger).map_err(|e| format!("Failed to install the file logger: {}", e))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// The levels in effect, `None` without a file logger.
+pub fn levels() -> Option<LogLevels> {
+    LOGGER
+        .get()
+        .map(|logger| logger.levels.lock_recover().clone())
+}
+
+/// Switch to `levels`; a no-op without a file logger.
+pub fn set_levels(levels: LogLevels) {
+    if let Some(logger) = LOGGER.get() {
+        log::set_max_level(levels.max_level());
+        *logger.levels.lock_recover() = levels;
+    }
+}
+
+/// Write out buffered records, e.g. before the logs are read.
+pub fn flush() {
+    if let Some(logger) = LOGGER.get() {
+        log::Log::flush(logger);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_level_for_module() {
+        let levels: LogLevels = serde_json::from_str(
+            r#"{"default": "warn", "modules": {
+                "app_lib::commands": "info",
+                "app_lib::commands::git": "trace",
+                "app_lib::commands::watcher": "off"
+            }}"#,
+        )
+        .unwrap();
+        assert!(levels.validate().is_ok());
+        assert_eq!(levels.level_for("tauri::app"), log::LevelFilter::Warn);
+        assert_eq!(
+            levels.level_for("app_lib::commands::pty"),
+            log::LevelFilter::Info
+        );
+        assert_eq!(
+            levels.level_for("app_lib::commands::git"),
+            log::LevelFilter::Trace
+        );
+        assert_eq!(
+            levels.level_for("app_lib::commands::git::blame"),
+            log::LevelFilter::Trace
+        );
+        assert_eq!(
+            levels.level_for("app_lib::commands::github"),
+            log::LevelFilter::Info
+        );
+        assert_eq!(
+            levels.level_for("app_lib::commands::watcher"),
+            log::LevelFilter::Off
+        );
+        assert_eq!(levels.max_level(), log::LevelFilter::Trace);
+
+        let mut bad = LogLevels::default();
+        bad.modules.insert("app_lib::".to_string(), LogLevel::Debug);
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest_files() {
+        let dir = TempDir::new().unwrap();
+        let mut file = RotatingFile::new(dir.path().to_path_buf(), 100);
+        for i in 0..40 {
+            let line = format_record(i, log::Level::Info, "kiri", &format!("line {:02}", i));
+            file.write_line(&line).unwrap();
+        }
+        file.flush().unwrap();
+
+        let files = log_files(dir.path());
+        assert_eq!(files.len(), MAX_ROTATED + 1);
+        let current = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(current
+            .trim_end()
+            .ends_with(r#"{"ts":39,"level":"INFO","target":"kiri","msg":"line 39"}"#));
+        for path in &files {
+            assert!(std::fs::metadata(path).unwrap().len() <= 100);
+        }
+        let oldest = std::fs::read_to_string(files.last().unwrap()).unwrap();
+        assert!(!oldest.contains("line 00"));
+
+        // A reopened file carries on where it was.
+        let mut reopened = RotatingFile::new(dir.path().to_path_buf(), 100);
+        reopened.write_line("{}").unwrap();
+        assert_eq!(log_files(dir.path()).len(), MAX_ROTATED + 1);
+    }
+}
@@ -0,0 +1,66 @@
+//! Tauri commands for the file logs of logging.rs and the support bundle
+//! of support_bundle.rs.
+
+use super::crash_report::crash_dir;
+use super::error::KiriError;
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::latency::run_blocking;
+use super::logging::{self, logging_file_path, logs_dir, LogLevels};
+use super::support_bundle::{build_bundle, write_zip, SupportBundleSummary};
+
+/// Log to `~/.kiri/logs` at the saved levels. Call once, early in `run()`;
+/// release builds only, debug builds log through the log plugin.
+pub fn install_file_logging() {
+    let Some(dir) = logs_dir() else {
+        return;
+    };
+    let levels = logging_file_path()
+        .map(|path| LogLevels::load(&path))
+        .unwrap_or_default();
+    if let Err(e) = logging::install(dir, levels) {
+        eprintln!("{}", e);
+    }
+}
+
+/// The log levels in effect, or the saved ones without file logging.
+#[tauri::command]
+pub fn get_log_levels() -> LogLevels {
+    logging::levels().unwrap_or_else(|| {
+        logging_file_path()
+            .map(|path| LogLevels::load(&path))
+            .unwrap_or_default()
+    })
+}
+
+/// Change the log levels now and for later launches.
+#[tauri::command]
+pub fn set_log_levels(levels: LogLevels) -> Result<(), KiriError> {
+    levels.validate()?;
+    if let Some(path) = logging_file_path() {
+        levels.save(&path)?;
+    }
+    logging::set_levels(levels);
+    Ok(())
+}
+
+/// Write logs, settings and crash reports, redacted, to `path` as a zip
+/// for attaching to an issue.
+#[tauri::command]
+pub async fn export_support_bundle(path: String) -> Result<SupportBundleSummary, KiriError> {
+    run_blocking("export_support_bundle", move || {
+        let (Some(home), Some(logs), Some(crashes)) = (dirs::home_dir(), logs_dir(), crash_dir())
+        else {
+            return Err("Could not find the home directory".to_string());
+        };
+        logging::flush();
+        let entries = build_bundle(&home.join(".kiri"), &logs, &crashes, Some(&home));
+        let path = normalize_input_path(&path);
+        let bytes = write_zip(&path, &entries)?;
+        Ok(SupportBundleSummary {
+            path: path_to_wire(&path),
+            files: entries.into_iter().map(|entry| entry.name).collect(),
+            bytes,
+        })
+    })
+    .await
+}
@@ -0,0 +1,701 @@
+//! Language servers run by the backend on behalf of the editor.
+//!
+//! One server runs per language and project root (the outermost directory
+//! of the checkout with one of the language's root markers, e.g.
+//! `Cargo.toml`). It is
+//! spawned with the worktree's runtimes activated (see toolchain.rs) and
+//! spoken to over stdio with `Content-Length` framed JSON-RPC. Servers load
+//! project code (plugins, build scripts), so only trusted roots get one.
+//!
+//! The backend owns the protocol plumbing: the `initialize` handshake,
+//! request ids and their responses, document versions for full-text sync,
+//! and replies to the requests servers send their client. Everything else
+//! the server sends is handed to the caller as a [`ServerEvent`]; the
+//! commands in lsp_commands.rs forward it to the editor.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use super::error::KiriError;
+use super::fs_path::path_to_wire;
+use super::lock_ext::LockExt;
+use super::toolchain;
+use super::trust::{require_trusted_in, TrustStoreState};
+
+pub type ServerId = u32;
+
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+/// JSON-RPC `MethodNotFound`.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A language server kiri knows how to run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerConfig {
+    /// Name shown to the user, also the server's key with the root
+    pub name: &'static str,
+    pub command: &'static str,
+    pub args: &'static [&'static str],
+    /// LSP language ids of the documents it serves, by file extension
+    pub languages: &'static [(&'static str, &'static str)],
+    /// Files marking a project root
+    pub root_markers: &'static [&'static str],
+}
+
+pub const SERVERS: &[ServerConfig] = &[
+    ServerConfig {
+        name: "rust-analyzer",
+        command: "rust-analyzer",
+        args: &[],
+        languages: &[("rs", "rust")],
+        root_markers: &["Cargo.toml"],
+    },
+    ServerConfig {
+        name: "typescript-language-server",
+        command: "typescript-language-server",
+        args: &["--stdio"],
+        languages: &[
+            ("ts", "typescript"),
+            ("tsx", "typescriptreact"),
+            ("js", "javascript"),
+            ("jsx", "javascriptreact"),
+            ("mjs", "javascript"),
+            ("cjs", "javascript"),
+        ],
+        root_markers: &["tsconfig.json", "jsconfig.json", "package.json"],
+    },
+    ServerConfig {
+        name: "pyright",
+        command: "pyright-langserver",
+        args: &["--stdio"],
+        languages: &[("py", "python"), ("pyi", "python")],
+        root_markers: &[
+            "pyproject.toml",
+            "setup.py",
+            "setup.cfg",
+            "requirements.txt",
+        ],
+    },
+    ServerConfig {
+        name: "gopls",
+        command: "gopls",
+        args: &[],
+        languages: &[("go", "go")],
+        root_markers: &["go.work", "go.mod"],
+    },
+];
+
+/// The server for a file and the file's language id.
+pub fn server_for_path(path: &Path) -> Option<(&'static ServerConfig, &'static str)> {
+    let ext = path.extension()?.to_str()?;
+    SERVERS.iter().find_map(|server| {
+        let (_, language) = server.languages.iter().find(|(e, _)| *e == ext)?;
+        Some((server, *language))
+    })
+}
+
+/// Root of the project `file` belongs to for `server`: the outermost
+/// directory with a root marker within the checkout, else the checkout
+/// root, else the file's directory. Outermost, so a Cargo workspace gets
+/// one server rather than one per crate.
+pub fn project_root(server: &ServerConfig, file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(file);
+    let mut root = None;
+    for ancestor in dir.ancestors() {
+        if server
+            .root_markers
+            .iter()
+            .any(|marker| ancestor.join(marker).is_file())
+        {
+            root = Some(ancestor);
+        }
+        if ancestor.join(".git").exists() {
+            return root.unwrap_or(ancestor).to_path_buf();
+        }
+    }
+    root.unwrap_or(dir).to_path_buf()
+}
+
+pub fn path_to_uri(path: &Path) -> Result<String, String> {
+    url::Url::from_file_path(path)
+        .map(String::from)
+        .map_err(|_| format!("Not an absolute path: {}", path.display()))
+}
+
+// --- Framing ----------------------------------------------------------------
+
+pub fn encode_message(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    let mut out = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    out.extend_from_slice(body.as_bytes());
+    out
+}
+
+/// Read one message, or `None` at end of stream.
+pub fn read_message(reader: &mut impl BufRead) -> std::io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "missing Content-Length")
+    })?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// A message from a server, by what it needs from the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Incoming {
+    Response {
+        id: i64,
+        result: Result<Value, String>,
+    },
+    Request {
+        id: Value,
+        method: String,
+        params: Value,
+    },
+    Notification {
+        method: String,
+        params: Value,
+    },
+}
+
+pub fn classify(mut message: Value) -> Option<Incoming> {
+    let params = message
+        .get_mut("params")
+        .map(Value::take)
+        .unwrap_or(Value::Null);
+    let method = message
+        .get("method")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    match (message.get("id").cloned(), method) {
+        (Some(id), Some(method)) => Some(Incoming::Request { id, method, params }),
+        (None, Some(method)) => Some(Incoming::Notification { method, params }),
+        (Some(id), None) => {
+            let result = match message.get("error") {
+                Some(error) => Err(error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Unknown error")
+                    .to_string()),
+                None => Ok(message
+                    .get_mut("result")
+                    .map(Value::take)
+                    .unwrap_or(Value::Null)),
+            };
+            Some(Incoming::Response {
+                id: id.as_i64()?,
+                result,
+            })
+        }
+        (None, None) => None,
+    }
+}
+
+/// The client's answer to a request from the server: empty settings,
+/// acknowledged registrations, declined edits.
+pub fn reply_to_server_request(id: Value, method: &str, params: &Value) -> Value {
+    let result = match method {
+        "workspace/configuration" => {
+            let items = params
+                .get("items")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            Value::Array(vec![Value::Null; items])
+        }
+        "window/workDoneProgress/create"
+        | "client/registerCapability"
+        | "client/unregisterCapability"
+        | "window/showMessageRequest" => Value::Null,
+        "workspace/applyEdit" => json!({ "applied": false }),
+        _ => {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": METHOD_NOT_FOUND, "message": format!("{} is not supported", method) },
+            })
+        }
+    };
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn initialize_params(root: &Path) -> Result<Value, String> {
+    let uri = path_to_uri(root)?;
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(json!({
+        "processId": std::process::id(),
+        "clientInfo": { "name": "kiri", "version": env!("CARGO_PKG_VERSION") },
+        "rootUri": uri,
+        "workspaceFolders": [{ "uri": uri, "name": name }],
+        "capabilities": {
+            "workspace": { "configuration": true, "workspaceFolders": true },
+            "window": { "workDoneProgress": true },
+            "textDocument": {
+                "synchronization": { "didSave": true },
+                "publishDiagnostics": { "relatedInformation": true },
+                "completion": { "completionItem": { "snippetSupport": false } },
+                "hover": { "contentFormat": ["markdown", "plaintext"] },
+                "definition": {},
+                "references": {},
+                "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+            },
+        },
+    }))
+}
+
+// --- Documents --------------------------------------------------------------
+
+/// Open documents of one server and their versions.
+#[derive(Debug, Default)]
+pub struct Documents {
+    versions: HashMap<String, i64>,
+}
+
+impl Documents {
+    /// `textDocument/didOpen`, or a full `didChange` if already open.
+    pub fn open(&mut self, uri: &str, language: &str, text: &str) -> (String, Value) {
+        if let Some(change) = self.change(uri, text) {
+            return change;
+        }
+        self.versions.insert(uri.to_string(), 1);
+        let params = json!({
+            "textDocument": { "uri": uri, "languageId": language, "version": 1, "text": text },
+        });
+        ("textDocument/didOpen".to_string(), params)
+    }
+
+    /// `textDocument/didChange` replacing the whole text; `None` if the
+    /// document isn't open.
+    pub fn change(&mut self, uri: &str, text: &str) -> Option<(String, Value)> {
+        let version = self.versions.get_mut(uri)?;
+        *version += 1;
+        let params = json!({
+            "textDocument": { "uri": uri, "version": *version },
+            "contentChanges": [{ "text": text }],
+        });
+        Some(("textDocument/didChange".to_string(), params))
+    }
+
+    pub fn close(&mut self, uri: &str) -> Option<(String, Value)> {
+        self.versions.remove(uri)?;
+        let params = json!({ "textDocument": { "uri": uri } });
+        Some(("textDocument/didClose".to_string(), params))
+    }
+
+    pub fn is_open(&self, uri: &str) -> bool {
+        self.versions.contains_key(uri)
+    }
+
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+}
+
+// --- Servers ----------------------------------------------------------------
+
+/// What a server sends that the backend doesn't handle itself.
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    Notification { method: String, params: Value },
+    Exited,
+}
+
+type Pending = HashMap<i64, oneshot::Sender<Result<Value, String>>>;
+
+pub struct LspServer {
+    pub id: ServerId,
+    pub config: &'static ServerConfig,
+    pub root: PathBuf,
+    pub documents: Mutex<Documents>,
+    /// From the `initialize` response
+    pub capabilities: Mutex<Value>,
+    stdin: Mutex<ChildStdin>,
+    child: Mutex<Child>,
+    pending: Arc<Mutex<Pending>>,
+    next_request: AtomicI64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LspServerInfo {
+    pub id: ServerId,
+    pub name: String,
+    pub root: String,
+    pub open_documents: usize,
+    pub capabilities: Value,
+}
+
+fn write_to(stdin: &Mutex<ChildStdin>, message: &Value) -> Result<(), String> {
+    let mut stdin = stdin.lock_recover();
+    stdin
+        .write_all(&encode_message(message))
+        .and_then(|_| stdin.flush())
+        .map_err(|e| format!("Failed to write to language server: {}", e))
+}
+
+impl LspServer {
+    /// Spawn `config`'s server for `root`, if `root` is trusted. `on_event`
+    /// is called from the reader thread.
+    pub fn spawn(
+        id: ServerId,
+        config: &'static ServerConfig,
+        root: &Path,
+        trust: &TrustStoreState,
+        on_event: impl Fn(ServerEvent) + Send + 'static,
+    ) -> Result<Arc<Self>, KiriError> {
+        require_trusted_in(trust, root)?;
+        let mut command = Command::new(config.command);
+        command
+            .args(config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        for (key, value) in toolchain::resolve(root).env {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        let mut child = command.spawn().map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("{} is not installed", config.command),
+            _ => format!("Failed to start {}: {}", config.name, e),
+        })?;
+        let stdin = child.stdin.take().ok_or("Language server has no stdin")?;
+        let stdout = child.stdout.take().ok_or("Language server has no stdout")?;
+        if let Some(stderr) = child.stderr.take() {
+            let name = config.name;
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    log::debug!("[{}] {}", name, line);
+                }
+            });
+        }
+        let server = Arc::new(Self {
+            id,
+            config,
+            root: root.to_path_buf(),
+            documents: Mutex::new(Documents::default()),
+            capabilities: Mutex::new(Value::Null),
+            stdin: Mutex::new(stdin),
+            child: Mutex::new(child),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_request: AtomicI64::new(1),
+        });
+        server.start_reader(stdout, on_event);
+        Ok(server)
+    }
+
+    fn start_reader(
+        self: &Arc<Self>,
+        stdout: impl Read + Send + 'static,
+        on_event: impl Fn(ServerEvent) + Send + 'static,
+    ) {
+        // The reader holds only what it needs, so dropping the last
+        // handle to the server still kills it.
+        let pending = Arc::clone(&self.pending);
+        let server = Arc::downgrade(self);
+        let name = self.config.name;
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            loop {
+                let message = match read_message(&mut reader) {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::warn!("[{}] unreadable message: {}", name, e);
+                        break;
+                    }
+                };
+                match classify(message) {
+                    Some(Incoming::Response { id, result }) => {
+                        if let Some(waiter) = pending.lock_recover().remove(&id) {
+                            let _ = waiter.send(result);
+                        }
+                    }
+                    Some(Incoming::Request { id, method, params }) => {
+                        let reply = reply_to_server_request(id, &method, &params);
+                        if let Some(server) = server.upgrade() {
+                            if let Err(e) = write_to(&server.stdin, &reply) {
+                                log::warn!("[{}] {}", name, e);
+                            }
+                        }
+                    }
+                    Some(Incoming::Notification { method, params }) => {
+                        on_event(ServerEvent::Notification { method, params })
+                    }
+                    None => {}
+                }
+            }
+            // Fail whatever is still waiting rather than let it time out.
+            pending.lock_recover().clear();
+            on_event(ServerEvent::Exited);
+        });
+    }
+
+    pub fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        write_to(&self.stdin, &message)
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        self.request_with_timeout(method, params, REQUEST_TIMEOUT)
+            .await
+    }
+
+    async fn request_with_timeout(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let id = self.next_request.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock_recover().insert(id, tx);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(e) = write_to(&self.stdin, &message) {
+            self.pending.lock_recover().remove(&id);
+            return Err(e);
+        }
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(format!("{} exited", self.config.name)),
+            Err(_) => {
+                self.pending.lock_recover().remove(&id);
+                let _ = self.notify("$/cancelRequest", json!({ "id": id }));
+                Err(format!("{} timed out on {}", self.config.name, method))
+            }
+        }
+    }
+
+    /// The `initialize` handshake; must come first.
+    pub async fn initialize(&self) -> Result<(), String> {
+        let result = self
+            .request("initialize", initialize_params(&self.root)?)
+            .await?;
+        *self.capabilities.lock_recover() =
+            result.get("capabilities").cloned().unwrap_or(Value::Null);
+        self.notify("initialized", json!({}))
+    }
+
+    /// Ask the server to exit, killing it if it doesn't in time.
+    pub async fn shutdown(&self) {
+        let _ = self
+            .request_with_timeout("shutdown", Value::Null, SHUTDOWN_TIMEOUT)
+            .await;
+        let _ = self.notify("exit", Value::Null);
+        self.kill();
+    }
+
+    pub fn kill(&self) {
+        let mut child = self.child.lock_recover();
+        if matches!(child.try_wait(), Ok(None)) {
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+    }
+
+    pub fn info(&self) -> LspServerInfo {
+        LspServerInfo {
+            id: self.id,
+            name: self.config.name.to_string(),
+            root: path_to_wire(&self.root),
+            open_documents: self.documents.lock_recover().len(),
+            capabilities: self.capabilities.lock_recover().clone(),
+        }
+    }
+}
+
+impl Drop for LspServer {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Running servers by id and by server name and root.
+#[derive(Default)]
+pub struct LspManager {
+    next_id: ServerId,
+    servers: HashMap<ServerId, Arc<LspServer>>,
+    by_root: HashMap<(&'static str, PathBuf), ServerId>,
+}
+
+pub type LspState = Arc<Mutex<LspManager>>;
+
+impl LspManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_id(&mut self) -> ServerId {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn get(&self, id: ServerId) -> Option<Arc<LspServer>> {
+        self.servers.get(&id).cloned()
+    }
+
+    pub fn find(&self, config: &ServerConfig, root: &Path) -> Option<Arc<LspServer>> {
+        let id = self.by_root.get(&(config.name, root.to_path_buf()))?;
+        self.get(*id)
+    }
+
+    /// Add a started server, or keep the one that won a race to start for
+    /// the same root. Returns the server to use.
+    pub fn insert(&mut self, server: Arc<LspServer>) -> Arc<LspServer> {
+        if let Some(existing) = self.find(server.config, &server.root) {
+            return existing;
+        }
+        self.by_root
+            .insert((server.config.name, server.root.clone()), server.id);
+        self.servers.insert(server.id, Arc::clone(&server));
+        server
+    }
+
+    pub fn remove(&mut self, id: ServerId) -> Option<Arc<LspServer>> {
+        let server = self.servers.remove(&id)?;
+        self.by_root.retain(|_, server_id| *server_id != id);
+        Some(server)
+    }
+
+    /// The server with `uri` open, if any.
+    pub fn with_document(&self, uri: &str) -> Option<Arc<LspServer>> {
+        self.servers
+            .values()
+            .find(|server| server.documents.lock_recover().is_open(uri))
+            .cloned()
+    }
+
+    pub fn list(&self) -> Vec<LspServerInfo> {
+        let mut servers: Vec<LspServerInfo> =
+            self.servers.values().map(|server| server.info()).collect();
+        servers.sort_by_key(|server| server.id);
+        servers
+    }
+
+    pub fn drain(&mut self) -> Vec<Arc<LspServer>> {
+        self.by_root.clear();
+        self.servers.drain().map(|(_, server)| server).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error::ErrorCode;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_framing_round_trip() {
+        let message = json!({"jsonrpc": "2.0", "id": 1, "result": {"ok": "ü"}});
+        let mut bytes = encode_message(&message);
+        bytes.extend(encode_message(&json!({"jsonrpc": "2.0", "method": "x"})));
+        let mut reader = std::io::Cursor::new(bytes);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert!(read_message(&mut reader).unwrap().is_some());
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_classify() {
+        let response = classify(json!({"jsonrpc": "2.0", "id": 3, "result": [1]}));
+        assert_eq!(
+            response,
+            Some(Incoming::Response {
+                id: 3,
+                result: Ok(json!([1]))
+            })
+        );
+        let error = classify(json!({"id": 4, "error": {"code": -1, "message": "boom"}}));
+        assert!(
+            matches!(error, Some(Incoming::Response { result: Err(ref e), .. }) if e == "boom")
+        );
+        let request = classify(json!({"id": "a", "method": "workspace/configuration",
+            "params": {"items": [{}, {}]}}));
+        let Some(Incoming::Request { id, method, params }) = request else {
+            panic!("expected a request");
+        };
+        assert_eq!(
+            reply_to_server_request(id, &method, &params)["result"],
+            json!([null, null])
+        );
+        assert!(matches!(
+            classify(json!({"method": "textDocument/publishDiagnostics", "params": {}})),
+            Some(Incoming::Notification { .. })
+        ));
+    }
+
+    #[test]
+    fn test_documents_versions() {
+        let mut documents = Documents::default();
+        let (method, params) = documents.open("file:///a.rs", "rust", "fn main() {}");
+        assert_eq!(method, "textDocument/didOpen");
+        assert_eq!(params["textDocument"]["version"], 1);
+        let (method, params) = documents.open("file:///a.rs", "rust", "fn main() { }");
+        assert_eq!(method, "textDocument/didChange");
+        assert_eq!(params["textDocument"]["version"], 2);
+        assert_eq!(documents.change("file:///b.rs", ""), None);
+        assert!(documents.close("file:///a.rs").is_some());
+        assert!(documents.is_empty());
+    }
+
+    #[test]
+    fn test_server_and_root_for_path() {
+        let (server, language) = server_for_path(Path::new("/src/app/main.tsx")).unwrap();
+        assert_eq!(server.name, "typescript-language-server");
+        assert_eq!(language, "typescriptreact");
+        assert!(server_for_path(Path::new("/src/README.md")).is_none());
+
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let krate = repo.join("crates/core/src");
+        std::fs::create_dir_all(&krate).unwrap();
+        std::fs::create_dir(repo.join(".git")).unwrap();
+        std::fs::write(repo.join("Cargo.toml"), "[workspace]").unwrap();
+        std::fs::write(repo.join("crates/core/Cargo.toml"), "[package]").unwrap();
+        let (rust, _) = server_for_path(Path::new("lib.rs")).unwrap();
+        assert_eq!(project_root(rust, &krate.join("lib.rs")), repo);
+    }
+
+    #[test]
+    fn test_untrusted_root_gets_no_server() {
+        let dir = TempDir::new().unwrap();
+        let (rust, _) = server_for_path(Path::new("lib.rs")).unwrap();
+        let trust = TrustStoreState::default();
+        let spawned = LspServer::spawn(1, rust, dir.path(), &trust, |_| {});
+        assert_eq!(spawned.err().map(|e| e.code), Some(ErrorCode::NotTrusted));
+    }
+}
@@ -0,0 +1,199 @@
+//! Tauri commands for language servers; the servers themselves are run by
+//! lsp.rs.
+//!
+//! The editor addresses documents by path. Opening one starts its server
+//! if needed; later calls go to the server that has it open. Whatever a
+//! server sends on its own (diagnostics, progress, log messages) arrives
+//! as `lsp-message` events, and `lsp-server-exited` tells when a server
+//! stopped, after which its documents need opening again.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking_coded;
+use super::lock_ext::LockExt;
+use super::lsp::{
+    path_to_uri, project_root, server_for_path, LspServer, LspServerInfo, LspState, ServerConfig,
+    ServerEvent, ServerId,
+};
+use super::performance::CommandTimer;
+use super::trust_commands::trust_store;
+
+pub const LSP_MESSAGE_EVENT: &str = "lsp-message";
+pub const LSP_EXITED_EVENT: &str = "lsp-server-exited";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LspMessageEvent {
+    pub server_id: ServerId,
+    pub method: String,
+    pub params: Value,
+}
+
+/// The running server for `path`'s language and project, started and
+/// initialized if there is none yet.
+async fn ensure_server(
+    app: &AppHandle,
+    state: &LspState,
+    config: &'static ServerConfig,
+    path: &Path,
+) -> Result<Arc<LspServer>, KiriError> {
+    let root = project_root(config, path);
+    if let Some(server) = state.lock_recover().find(config, &root) {
+        return Ok(server);
+    }
+    let trust = trust_store(app)?;
+    let id = state.lock_recover().next_id();
+    let (app, events) = (app.clone(), Arc::clone(state));
+    let on_event = move |event| match event {
+        ServerEvent::Notification { method, params } => {
+            let payload = LspMessageEvent {
+                server_id: id,
+                method,
+                params,
+            };
+            let _ = app.emit(LSP_MESSAGE_EVENT, &payload);
+        }
+        ServerEvent::Exited => {
+            let removed = events.lock_recover().remove(id);
+            if removed.is_some() {
+                let _ = app.emit(LSP_EXITED_EVENT, id);
+            }
+        }
+    };
+    // Resolving the runtimes to spawn with may run mise.
+    let server = run_blocking_coded("lsp_spawn", move || {
+        LspServer::spawn(id, config, &root, &trust, on_event)
+    })
+    .await?;
+    server.initialize().await?;
+    Ok(state.lock_recover().insert(server))
+}
+
+/// The server that has `path` open, and its URI.
+fn server_with_document(state: &LspState, path: &str) -> Result<(Arc<LspServer>, String), String> {
+    let path = normalize_input_path(path);
+    let uri = path_to_uri(&path)?;
+    let server = state
+        .lock_recover()
+        .with_document(&uri)
+        .ok_or_else(|| format!("{} is not open in a language server", path.display()))?;
+    Ok((server, uri))
+}
+
+/// Open `path` with its current `text`, starting its language server if
+/// needed. Opening an open document replaces its text.
+#[tauri::command]
+pub async fn lsp_did_open(
+    app: AppHandle,
+    state: tauri::State<'_, LspState>,
+    path: String,
+    text: String,
+) -> Result<LspServerInfo, KiriError> {
+    let _timer = CommandTimer::start("lsp_did_open");
+    let path = normalize_input_path(&path);
+    let (config, language) = server_for_path(&path)
+        .ok_or_else(|| format!("No language server for {}", path.display()))?;
+    let server = ensure_server(&app, &state, config, &path).await?;
+    let uri = path_to_uri(&path)?;
+    let (method, params) = server.documents.lock_recover().open(&uri, language, &text);
+    server.notify(&method, params)?;
+    Ok(server.info())
+}
+
+/// Replace the text of an open document.
+#[tauri::command]
+pub fn lsp_did_change(
+    state: tauri::State<'_, LspState>,
+    path: String,
+    text: String,
+) -> Result<(), KiriError> {
+    let (server, uri) = server_with_document(&state, &path)?;
+    let change = server.documents.lock_recover().change(&uri, &text);
+    match change {
+        Some((method, params)) => server.notify(&method, params).map_err(KiriError::from),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn lsp_did_save(state: tauri::State<'_, LspState>, path: String) -> Result<(), KiriError> {
+    let (server, uri) = server_with_document(&state, &path)?;
+    server
+        .notify(
+            "textDocument/didSave",
+            json!({ "textDocument": { "uri": uri } }),
+        )
+        .map_err(KiriError::from)
+}
+
+/// Close a document. Does nothing if it isn't open.
+#[tauri::command]
+pub fn lsp_did_close(state: tauri::State<'_, LspState>, path: String) -> Result<(), KiriError> {
+    let Ok((server, uri)) = server_with_document(&state, &path) else {
+        return Ok(());
+    };
+    let close = server.documents.lock_recover().close(&uri);
+    match close {
+        Some((method, params)) => server.notify(&method, params).map_err(KiriError::from),
+        None => Ok(()),
+    }
+}
+
+/// Send `method` (e.g. `textDocument/completion`) to the server that has
+/// `path` open and return its result. `params` is sent as given; the
+/// document's URI is at `textDocument.uri` for the editor to fill in.
+#[tauri::command]
+pub async fn lsp_request(
+    state: tauri::State<'_, LspState>,
+    path: String,
+    method: String,
+    params: Value,
+) -> Result<Value, KiriError> {
+    let _timer = CommandTimer::start("lsp_request");
+    let (server, _) = server_with_document(&state, &path)?;
+    server
+        .request(&method, params)
+        .await
+        .map_err(KiriError::from)
+}
+
+/// The URI language servers know `path` by.
+#[tauri::command]
+pub fn lsp_document_uri(path: String) -> Result<String, KiriError> {
+    path_to_uri(&normalize_input_path(&path)).map_err(KiriError::from)
+}
+
+#[tauri::command]
+pub fn list_lsp_servers(state: tauri::State<'_, LspState>) -> Vec<LspServerInfo> {
+    state.lock_recover().list()
+}
+
+/// Shut a server down. Its documents are closed with it.
+#[tauri::command]
+pub async fn stop_lsp_server(
+    state: tauri::State<'_, LspState>,
+    server_id: ServerId,
+) -> Result<(), KiriError> {
+    let _timer = CommandTimer::start("stop_lsp_server");
+    let server = state.lock_recover().remove(server_id);
+    if let Some(server) = server {
+        server.shutdown().await;
+    }
+    Ok(())
+}
+
+/// Kill every language server; for app exit, when there is no time for
+/// a clean shutdown.
+pub fn stop_all_lsp_servers(app: &AppHandle) {
+    if let Some(state) = app.try_state::<LspState>() {
+        let servers = state.lock_recover().drain();
+        for server in servers {
+            server.kill();
+        }
+    }
+}
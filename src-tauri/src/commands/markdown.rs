@@ -0,0 +1,296 @@
+//! Markdown rendering for the preview pane.
+//!
+//! The webview gets a finished, sanitized HTML fragment instead of running
+//! its own markdown pipeline and reading files. GitHub's extensions are on
+//! (tables, task lists, strikethrough, footnotes), and headings get
+//! GitHub-style ids so in-page links such as `#installation` work.
+//!
+//! HTML written in the document is kept but cleaned with ammonia, which
+//! drops scripts, styles, event handlers and unknown URL schemes. Relative
+//! URLs resolve against the document's directory, or the project root for
+//! a leading `/`, and must stay inside the project:
+//!
+//! - an image is inlined as a `data:` URL, so the webview needs no file
+//!   access;
+//! - a link becomes `kiri-file:` plus the project-relative path and any
+//!   `#fragment`, which the preview opens in the editor.
+//!
+//! A relative URL leading out of the project is removed.
+
+use ammonia::Builder;
+use base64::Engine;
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::bookmarks::relative_key;
+use super::fs_path::{lexical_normalize, resolve_within, strip_root};
+
+/// Scheme of links to project files in the rendered HTML.
+pub const FILE_LINK_SCHEME: &str = "kiri-file";
+
+/// Images larger than this are left out rather than inlined.
+const MAX_INLINE_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkdownPreview {
+    pub html: String,
+    /// Text of the first heading.
+    pub title: Option<String>,
+}
+
+/// Where the relative URLs of a document resolve.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkBase<'a> {
+    /// The project; nothing outside it is linked or inlined.
+    pub root: &'a Path,
+    /// The document's directory.
+    pub dir: &'a Path,
+}
+
+fn image_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        // Scripts in an SVG don't run when it is shown through <img>.
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+/// GitHub's anchor for a heading: lowercase, punctuation dropped and
+/// spaces as `-`. Repeats get `-1`, `-2`, ...
+fn heading_slug(text: &str, used: &mut HashMap<String, usize>) -> String {
+    let base: String = text
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect();
+    let count = used.entry(base.clone()).or_insert(0);
+    let slug = match *count {
+        0 => base,
+        n => format!("{}-{}", base, n),
+    };
+    *count += 1;
+    slug
+}
+
+/// Give every heading an id, and return the first heading's text.
+fn add_heading_ids(events: &mut [Event<'_>]) -> Option<String> {
+    let mut used = HashMap::new();
+    let mut title = None;
+    let mut index = 0;
+    while index < events.len() {
+        if matches!(events[index], Event::Start(Tag::Heading { .. })) {
+            let start = index;
+            let mut text = String::new();
+            index += 1;
+            while index < events.len() && !matches!(events[index], Event::End(TagEnd::Heading(_))) {
+                if let Event::Text(part) | Event::Code(part) = &events[index] {
+                    text.push_str(part);
+                }
+                index += 1;
+            }
+            let slug = heading_slug(&text, &mut used);
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+                *id = Some(slug.into());
+            }
+            let text = text.trim();
+            if title.is_none() && !text.is_empty() {
+                title = Some(text.to_string());
+            }
+        }
+        index += 1;
+    }
+    title
+}
+
+/// `path` as a `data:` URL, when it is a small enough image.
+fn image_data_url(path: &Path) -> Option<String> {
+    let mime = image_mime(path)?;
+    let size = std::fs::metadata(path).ok()?.len();
+    if size > MAX_INLINE_IMAGE_BYTES {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// What the `href` of a link or `src` of an image (`element`) becomes;
+/// `None` removes it.
+fn rewrite_url(element: &str, url: &str, base: LinkBase<'_>) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() || url.starts_with('#') || url.starts_with("//") {
+        return Some(url.to_string());
+    }
+    if let Ok(absolute) = url::Url::parse(url) {
+        // Only this module makes data: and kiri-file: URLs.
+        return match absolute.scheme() {
+            "data" => None,
+            scheme if scheme == FILE_LINK_SCHEME => None,
+            _ => Some(url.to_string()),
+        };
+    }
+
+    let (path, fragment) = match url.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (url, None),
+    };
+    let path = path.split('?').next().unwrap_or_default();
+    let path = urlencoding::decode(path).ok()?;
+    let joined = match path.strip_prefix('/') {
+        Some(from_root) => base.root.join(from_root),
+        None => base.dir.join(path.as_ref()),
+    };
+    let rel = strip_root(&lexical_normalize(&joined)?, base.root)?;
+
+    if element == "img" {
+        let file = resolve_within(&joined.to_string_lossy(), base.root).ok()?;
+        return image_data_url(&file);
+    }
+    let mut link = format!("{}:{}", FILE_LINK_SCHEME, relative_key(&rel));
+    if let Some(fragment) = fragment {
+        link.push('#');
+        link.push_str(fragment);
+    }
+    Some(link)
+}
+
+fn sanitize(html: &str, base: LinkBase<'_>) -> String {
+    let (root, dir) = (base.root.to_path_buf(), base.dir.to_path_buf());
+    Builder::default()
+        .add_tags(&["details", "summary", "input"])
+        .add_tag_attributes("input", &["type", "checked", "disabled"])
+        .add_tag_attributes("details", &["open"])
+        .add_generic_attributes(&["align"])
+        .add_tag_attributes("h1", &["id"])
+        .add_tag_attributes("h2", &["id"])
+        .add_tag_attributes("h3", &["id"])
+        .add_tag_attributes("h4", &["id"])
+        .add_tag_attributes("h5", &["id"])
+        .add_tag_attributes("h6", &["id"])
+        // Footnote definitions, which footnote references link to.
+        .add_tag_attributes("div", &["id"])
+        .add_url_schemes(&["data", FILE_LINK_SCHEME])
+        .attribute_filter(move |element, attribute, value| {
+            let base = LinkBase {
+                root: &root,
+                dir: &dir,
+            };
+            match (element, attribute) {
+                ("a", "href") | ("img", "src") => rewrite_url(element, value, base).map(Cow::Owned),
+                _ => Some(value.into()),
+            }
+        })
+        .clean(html)
+        .to_string()
+}
+
+/// Render `text` as a sanitized HTML fragment.
+pub fn render_markdown(text: &str, base: LinkBase<'_>) -> MarkdownPreview {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut events: Vec<Event> = Parser::new_ext(text, options).collect();
+    let title = add_heading_ids(&mut events);
+    let mut rendered = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut rendered, events.into_iter());
+    MarkdownPreview {
+        html: sanitize(&rendered, base),
+        title,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_heading_slug() {
+        let mut used = HashMap::new();
+        assert_eq!(
+            heading_slug("Getting Started!", &mut used),
+            "getting-started"
+        );
+        assert_eq!(
+            heading_slug("Getting started", &mut used),
+            "getting-started-1"
+        );
+        assert_eq!(heading_slug("`cargo` & npm_ci", &mut used), "cargo--npm_ci");
+    }
+
+    #[test]
+    fn test_rewrite_url_stays_in_project() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let docs = root.join("docs");
+        std::fs::create_dir_all(docs.join("img")).unwrap();
+        std::fs::write(docs.join("img/logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+        let base = LinkBase {
+            root: &root,
+            dir: &docs,
+        };
+
+        assert_eq!(
+            rewrite_url("a", "../src/main%20file.rs#L3", base).as_deref(),
+            Some("kiri-file:src/main file.rs#L3")
+        );
+        assert_eq!(
+            rewrite_url("a", "/README.md", base).as_deref(),
+            Some("kiri-file:README.md")
+        );
+        assert_eq!(rewrite_url("a", "../../etc/passwd", base), None);
+        assert_eq!(
+            rewrite_url("a", "https://example.com/x", base).as_deref(),
+            Some("https://example.com/x")
+        );
+        assert_eq!(rewrite_url("a", "#usage", base).as_deref(), Some("#usage"));
+        assert_eq!(rewrite_url("a", "data:text/html,<b>", base), None);
+
+        assert_eq!(
+            rewrite_url("img", "img/logo.png", base).as_deref(),
+            Some("data:image/png;base64,iVBORw==")
+        );
+        assert_eq!(rewrite_url("img", "img/missing.png", base), None);
+    }
+
+    #[test]
+    fn test_render_sanitizes_and_resolves() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let base = LinkBase {
+            root: &root,
+            dir: &root,
+        };
+        let text = "# Intro\n\n- [x] done\n\n<script>alert(1)</script>\n\
+                    <a href=\"docs/a.md\" onclick=\"x()\">guide</a>\n\n[up](../secret)\n";
+        let preview = render_markdown(text, base);
+        assert_eq!(preview.title.as_deref(), Some("Intro"));
+        assert!(preview.html.contains("<h1 id=\"intro\">Intro</h1>"));
+        assert!(preview.html.contains("checked"));
+        assert!(!preview.html.contains("script"));
+        assert!(!preview.html.contains("onclick"));
+        assert!(preview.html.contains("href=\"kiri-file:docs/a.md\""));
+        assert!(!preview.html.contains("secret\""));
+    }
+}
@@ -0,0 +1,45 @@
+//! Tauri command for the markdown preview of markdown.rs.
+
+use std::path::Path;
+
+use super::error::KiriError;
+use super::file_io::read_file_contents;
+use super::fs_path::input_path;
+use super::git_worktree::current_worktree_root;
+use super::latency::run_blocking;
+use super::markdown::{render_markdown, LinkBase, MarkdownPreview};
+
+/// Render the markdown file at `path`, or `text` when given (the editor's
+/// unsaved contents), as an HTML fragment for the preview. Relative links
+/// and images resolve inside the repository the file is in, or inside its
+/// directory outside a repository.
+#[tauri::command]
+pub async fn render_markdown_preview(
+    path: String,
+    text: Option<String>,
+) -> Result<MarkdownPreview, KiriError> {
+    let path = input_path(&path)?;
+    run_blocking("render_markdown_preview", move || {
+        let text = match text {
+            Some(text) => text,
+            None => read_file_contents(&path)?,
+        };
+        let dir = path
+            .parent()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| format!("Not a file: {}", path.display()))?;
+        // Canonical on both sides, so a symlinked checkout still compares
+        // as inside its repository.
+        let dir = dir.canonicalize().unwrap_or(dir);
+        let root = current_worktree_root(&dir).unwrap_or_else(|| dir.clone());
+        let root = root.canonicalize().unwrap_or(root);
+        Ok(render_markdown(
+            &text,
+            LinkBase {
+                root: &root,
+                dir: &dir,
+            },
+        ))
+    })
+    .await
+}
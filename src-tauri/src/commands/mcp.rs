@@ -0,0 +1,493 @@
+//! MCP server exposing read-only project tools to local AI agents.
+//!
+//! The server speaks MCP's JSON-RPC, one message per line, on the socket
+//! `~/.kiri/mcp.sock`; agents start `kiri-cli mcp`, which relays its stdio
+//! to that socket. It is off until the user turns it on. The tools
+//! (mcp_tools.rs) only see the projects and worktrees open in kiri, each
+//! can be denied in settings, and every call is recorded in an audit log
+//! at `~/.kiri/mcp-audit.jsonl`. The listener lives in mcp_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::clock::now_ms;
+use super::file_io::write_file_contents_atomic;
+use super::lock_ext::LockExt;
+use super::mcp_tools::{call_tool, find_tool, TOOLS};
+
+/// Protocol versions the server speaks, newest first.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Audit entries kept in memory for the settings panel.
+const AUDIT_MEMORY: usize = 500;
+
+/// The audit log is rotated to `mcp-audit.jsonl.1` past this size.
+const AUDIT_FILE_MAX_BYTES: u64 = 4 * 1024 * 1024;
+
+// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub fn mcp_socket_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("mcp.sock"))
+}
+
+fn settings_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("mcp.json"))
+}
+
+fn audit_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("mcp-audit.jsonl"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct McpSettings {
+    #[serde(default)]
+    enabled: bool,
+    /// Tools whose permission differs from the default, allow.
+    #[serde(default)]
+    permissions: BTreeMap<String, ToolPermission>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub permission: ToolPermission,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Ok,
+    Denied,
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub time_ms: u64,
+    /// Name the agent gave in `initialize`.
+    pub client: Option<String>,
+    pub tool: String,
+    pub arguments: Value,
+    pub outcome: AuditOutcome,
+    pub duration_ms: u64,
+}
+
+/// MCP settings and the recent audit log.
+#[derive(Debug, Default)]
+pub struct Mcp {
+    file: Option<PathBuf>,
+    audit_file: Option<PathBuf>,
+    settings: McpSettings,
+    audit: VecDeque<AuditEntry>,
+}
+
+pub type McpState = Arc<Mutex<Mcp>>;
+
+impl Mcp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settings saved in `~/.kiri/mcp.json`; the defaults if it is missing
+    /// or unreadable.
+    pub fn load() -> Self {
+        Self::load_from(settings_file_path(), audit_file_path())
+    }
+
+    fn load_from(file: Option<PathBuf>, audit_file: Option<PathBuf>) -> Self {
+        let settings = file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(
+                |contents| match serde_json::from_str::<McpSettings>(&contents) {
+                    Ok(settings) => Some(settings),
+                    Err(e) => {
+                        log::warn!("failed to parse MCP settings: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        Self {
+            file,
+            audit_file,
+            settings,
+            audit: VecDeque::new(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_vec_pretty(&self.settings)
+            .map_err(|e| format!("Failed to serialize MCP settings: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<(), String> {
+        self.settings.enabled = enabled;
+        self.save()
+    }
+
+    pub fn permission(&self, tool: &str) -> ToolPermission {
+        self.settings
+            .permissions
+            .get(tool)
+            .copied()
+            .unwrap_or(ToolPermission::Allow)
+    }
+
+    pub fn set_permission(&mut self, tool: &str, permission: ToolPermission) -> Result<(), String> {
+        if find_tool(tool).is_none() {
+            return Err(format!("Unknown tool {}", tool));
+        }
+        match permission {
+            ToolPermission::Allow => self.settings.permissions.remove(tool),
+            ToolPermission::Deny => self
+                .settings
+                .permissions
+                .insert(tool.to_string(), permission),
+        };
+        self.save()
+    }
+
+    pub fn tools(&self) -> Vec<ToolInfo> {
+        TOOLS
+            .iter()
+            .map(|tool| ToolInfo {
+                name: tool.name,
+                description: tool.description,
+                permission: self.permission(tool.name),
+            })
+            .collect()
+    }
+
+    /// Keep `entry` in memory and append it to the audit log file.
+    pub fn record(&mut self, entry: AuditEntry) {
+        if let Some(path) = &self.audit_file {
+            if let Err(e) = append_audit(path, &entry) {
+                log::warn!("failed to write MCP audit log: {}", e);
+            }
+        }
+        self.audit.push_back(entry);
+        while self.audit.len() > AUDIT_MEMORY {
+            self.audit.pop_front();
+        }
+    }
+
+    /// The latest `limit` audit entries, newest first.
+    pub fn audit(&self, limit: usize) -> Vec<AuditEntry> {
+        self.audit.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+fn append_audit(path: &Path, entry: &AuditEntry) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::metadata(path).is_ok_and(|m| m.len() > AUDIT_FILE_MAX_BYTES) {
+        let mut rotated = path.as_os_str().to_os_string();
+        rotated.push(".1");
+        std::fs::rename(path, rotated)?;
+    }
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(&line)
+}
+
+/// State of one agent's connection.
+#[derive(Debug, Default)]
+pub struct McpSession {
+    client: Option<String>,
+}
+
+fn reply(id: &Value, result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_reply(id: &Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({"content": [{"type": "text", "text": text}], "isError": is_error})
+}
+
+fn initialize(session: &mut McpSession, params: &Value) -> Value {
+    session.client = params["clientInfo"]["name"].as_str().map(str::to_string);
+    let requested = params["protocolVersion"].as_str().unwrap_or_default();
+    let version = PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| **v == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": {"tools": {"listChanged": false}},
+        "serverInfo": {"name": "kiri", "version": env!("CARGO_PKG_VERSION")},
+        "instructions": "Read-only access to the projects open in kiri. Call list_projects \
+                         first; other tools only accept paths inside those projects."
+    })
+}
+
+fn list_tools(state: &McpState) -> Value {
+    let mcp = state.lock_recover();
+    let tools: Vec<Value> = TOOLS
+        .iter()
+        .filter(|tool| mcp.permission(tool.name) == ToolPermission::Allow)
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": tool.input_schema(),
+                "annotations": {"readOnlyHint": true, "openWorldHint": false}
+            })
+        })
+        .collect();
+    json!({ "tools": tools })
+}
+
+/// Handle one line from an agent. Returns the reply to send, if any, and
+/// the audit entry of a tool call. `roots` gives the workspace roots.
+pub fn handle_line(
+    state: &McpState,
+    session: &mut McpSession,
+    line: &str,
+    roots: impl FnOnce() -> Vec<PathBuf>,
+) -> (Option<Value>, Option<AuditEntry>) {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = format!("Parse error: {}", e);
+            return (Some(error_reply(&Value::Null, PARSE_ERROR, &error)), None);
+        }
+    };
+    let Some(method) = message["method"].as_str() else {
+        // A response to a server request; the server sends none.
+        if message.get("id").is_some() && message.get("result").is_none() {
+            let reply = error_reply(&message["id"], INVALID_REQUEST, "Invalid request");
+            return (Some(reply), None);
+        }
+        return (None, None);
+    };
+    let Some(id) = message.get("id") else {
+        // Notifications, e.g. `notifications/initialized`, need no reply.
+        return (None, None);
+    };
+    let params = &message["params"];
+    let result = match method {
+        "initialize" => initialize(session, params),
+        "ping" => json!({}),
+        "tools/list" => list_tools(state),
+        "tools/call" => {
+            let Some(name) = params["name"].as_str() else {
+                let reply = error_reply(id, INVALID_PARAMS, "Missing tool name");
+                return (Some(reply), None);
+            };
+            if find_tool(name).is_none() {
+                let error = format!("Unknown tool {}", name);
+                return (Some(error_reply(id, INVALID_PARAMS, &error)), None);
+            }
+            let arguments = params
+                .get("arguments")
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+            let started = Instant::now();
+            let permission = state.lock_recover().permission(name);
+            let (result, outcome) = match permission {
+                ToolPermission::Deny => (
+                    tool_result(
+                        format!("{} is turned off in kiri's MCP settings", name),
+                        true,
+                    ),
+                    AuditOutcome::Denied,
+                ),
+                ToolPermission::Allow => match call_tool(name, &arguments, &roots()) {
+                    Ok(value) => {
+                        let text = serde_json::to_string_pretty(&value).unwrap_or_default();
+                        let mut result = tool_result(text, false);
+                        if value.is_object() {
+                            result["structuredContent"] = value;
+                        }
+                        (result, AuditOutcome::Ok)
+                    }
+                    Err(message) => (
+                        tool_result(message.clone(), true),
+                        AuditOutcome::Error { message },
+                    ),
+                },
+            };
+            let entry = AuditEntry {
+                time_ms: now_ms(),
+                client: session.client.clone(),
+                tool: name.to_string(),
+                arguments,
+                outcome,
+                duration_ms: started.elapsed().as_millis() as u64,
+            };
+            state.lock_recover().record(entry.clone());
+            return (Some(reply(id, result)), Some(entry));
+        }
+        _ => {
+            let error = format!("Method not found: {}", method);
+            return (Some(error_reply(id, METHOD_NOT_FOUND, &error)), None);
+        }
+    };
+    (Some(reply(id, result)), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn call(state: &McpState, session: &mut McpSession, message: Value, root: &Path) -> Value {
+        let line = message.to_string();
+        handle_line(state, session, &line, || vec![root.to_path_buf()])
+            .0
+            .unwrap()
+    }
+
+    #[test]
+    fn test_handshake_and_tools() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("README.md"), "# hi\n").unwrap();
+        let state: McpState = Arc::new(Mutex::new(Mcp::new()));
+        let mut session = McpSession::default();
+
+        let init = call(
+            &state,
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+                "protocolVersion": "2025-03-26",
+                "clientInfo": {"name": "agent", "version": "1"}
+            }}),
+            &root,
+        );
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        let note = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(
+            handle_line(&state, &mut session, &note.to_string(), Vec::new)
+                .0
+                .is_none()
+        );
+
+        let file = root.join("README.md");
+        let read = call(
+            &state,
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {
+                "name": "read_file", "arguments": {"path": file}
+            }}),
+            &root,
+        );
+        assert_eq!(read["result"]["isError"], false);
+        assert_eq!(read["result"]["structuredContent"]["content"], "# hi\n");
+
+        let unknown = call(
+            &state,
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "resources/list"}),
+            &root,
+        );
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        let garbage = handle_line(&state, &mut session, "{", Vec::new).0.unwrap();
+        assert_eq!(garbage["error"]["code"], PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_permissions_and_audit() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_path_buf();
+        let state: McpState = Arc::new(Mutex::new(Mcp::load_from(
+            Some(root.join("mcp.json")),
+            Some(root.join("audit.jsonl")),
+        )));
+        let mut session = McpSession::default();
+        state
+            .lock_recover()
+            .set_permission("read_file", ToolPermission::Deny)
+            .unwrap();
+        assert!(state
+            .lock_recover()
+            .set_permission("rm_rf", ToolPermission::Deny)
+            .is_err());
+
+        let list = call(
+            &state,
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+            &root,
+        );
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert!(!names.contains(&"read_file"));
+        assert!(names.contains(&"git_status"));
+
+        let denied = call(
+            &state,
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {
+                "name": "read_file", "arguments": {"path": "/etc/passwd"}
+            }}),
+            &root,
+        );
+        assert_eq!(denied["result"]["isError"], true);
+        call(
+            &state,
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {
+                "name": "list_directory", "arguments": {"path": "/"}
+            }}),
+            &root,
+        );
+
+        let audit = state.lock_recover().audit(10);
+        assert_eq!(audit.len(), 2);
+        assert_eq!(audit[1].outcome, AuditOutcome::Denied);
+        assert!(matches!(audit[0].outcome, AuditOutcome::Error { .. }));
+        let logged = std::fs::read_to_string(root.join("audit.jsonl")).unwrap();
+        assert_eq!(logged.lines().count(), 2);
+
+        let reloaded = Mcp::load_from(Some(root.join("mcp.json")), None);
+        assert_eq!(reloaded.permission("read_file"), ToolPermission::Deny);
+        assert!(!reloaded.enabled());
+    }
+}
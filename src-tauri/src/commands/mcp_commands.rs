@@ -0,0 +1,245 @@
+//! MCP server listener and its Tauri commands; see mcp.rs.
+//!
+//! The listener serves `~/.kiri/mcp.sock` the same way the per-window CLI
+//! server serves its sockets, except that each line is an MCP message.
+//! Every tool call is sent to the frontend as an `mcp-tool-called` event
+//! with its [`AuditEntry`].
+
+use interprocess::local_socket::tokio::prelude::*;
+use interprocess::local_socket::{GenericFilePath, ListenerOptions, ToFsName};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::oneshot;
+
+use super::error::KiriError;
+use super::fs_path::path_to_wire;
+use super::lock_ext::{LockExt, RwLockExt};
+use super::mcp::{
+    handle_line, mcp_socket_path, AuditEntry, Mcp, McpSession, McpState, ToolInfo, ToolPermission,
+};
+use super::window::WindowRegistryState;
+
+pub const MCP_TOOL_CALLED_EVENT: &str = "mcp-tool-called";
+
+/// The running listener, if the server is on.
+#[derive(Default)]
+pub struct McpServer {
+    stop: Option<oneshot::Sender<()>>,
+}
+
+impl McpServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn running(&self) -> bool {
+        self.stop.as_ref().is_some_and(|tx| !tx.is_closed())
+    }
+
+    fn stop(&mut self) {
+        let Some(tx) = self.stop.take() else {
+            return;
+        };
+        let _ = tx.send(());
+        // As with the CLI server, don't count on the listener task's own
+        // cleanup running before the runtime is torn down.
+        if let Some(path) = mcp_socket_path() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+pub type McpServerState = Arc<Mutex<McpServer>>;
+
+#[derive(Debug, Serialize)]
+pub struct McpStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub socket_path: Option<String>,
+    pub tools: Vec<ToolInfo>,
+}
+
+/// Load `~/.kiri/mcp.json` and start the server if it is enabled.
+pub fn start_mcp_server_if_enabled(app: &AppHandle) {
+    let Some(state) = app.try_state::<McpState>() else {
+        return;
+    };
+    let enabled = {
+        let mut mcp = state.lock_recover();
+        *mcp = Mcp::load();
+        mcp.enabled()
+    };
+    if enabled {
+        if let Err(e) = start_server(app) {
+            log::warn!("failed to start MCP server: {}", e);
+        }
+    }
+}
+
+/// Stop the server and remove its socket. Called on exit.
+pub fn stop_mcp_server(app: &AppHandle) {
+    if let Some(server) = app.try_state::<McpServerState>() {
+        server.lock_recover().stop();
+    }
+}
+
+/// Workspace roots the tools may read: what the open windows show.
+fn workspace_roots(app: &AppHandle) -> Vec<PathBuf> {
+    app.try_state::<WindowRegistryState>()
+        .map(|registry| registry.read_recover().contexts().roots())
+        .unwrap_or_default()
+}
+
+fn start_server(app: &AppHandle) -> Result<(), String> {
+    let server = app
+        .try_state::<McpServerState>()
+        .ok_or("MCP server state is not available")?;
+    let mut server = server.lock_recover();
+    if server.running() {
+        return Ok(());
+    }
+    let app = app.clone();
+    let stop = tauri::async_runtime::block_on(async move { spawn_listener(app) })
+        .map_err(|e| format!("Failed to start MCP server: {}", e))?;
+    server.stop = Some(stop);
+    Ok(())
+}
+
+fn spawn_listener(app: AppHandle) -> std::io::Result<oneshot::Sender<()>> {
+    let socket_path =
+        mcp_socket_path().ok_or_else(|| std::io::Error::other("no home dir for socket path"))?;
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Best-effort: ensure no stale socket file blocks bind.
+    let _ = std::fs::remove_file(&socket_path);
+
+    let name = socket_path.as_os_str().to_fs_name::<GenericFilePath>()?;
+    let listener = ListenerOptions::new().name(name).create_tokio()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut stop_rx => break,
+                conn = listener.accept() => {
+                    match conn {
+                        Ok(stream) => {
+                            let app = app.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = handle_connection(stream, app).await {
+                                    log::warn!("mcp connection error: {e}");
+                                }
+                            });
+                        }
+                        Err(e) => log::warn!("mcp accept failed: {e}"),
+                    }
+                }
+            }
+        }
+        let _ = std::fs::remove_file(&socket_path);
+    });
+    Ok(stop_tx)
+}
+
+async fn handle_connection(
+    stream: interprocess::local_socket::tokio::Stream,
+    app: AppHandle,
+) -> std::io::Result<()> {
+    use interprocess::local_socket::traits::tokio::Stream as _;
+    let Some(state) = app.try_state::<McpState>().map(|s| s.inner().clone()) else {
+        return Ok(());
+    };
+    let (reader, mut writer) = stream.split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut session = McpSession::default();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Tools read files and run git, so keep them off the runtime.
+        let state = state.clone();
+        let app_for_roots = app.clone();
+        let (returned, (reply, entry)) = tokio::task::spawn_blocking(move || {
+            let result = handle_line(&state, &mut session, &line, || {
+                workspace_roots(&app_for_roots)
+            });
+            (session, result)
+        })
+        .await
+        .map_err(std::io::Error::other)?;
+        session = returned;
+        if let Some(entry) = entry {
+            let _ = app.emit(MCP_TOOL_CALLED_EVENT, &entry);
+        }
+        if let Some(reply) = reply {
+            let mut bytes = serde_json::to_vec(&reply)?;
+            bytes.push(b'\n');
+            writer.write_all(&bytes).await?;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_mcp_status(
+    state: tauri::State<'_, McpState>,
+    server: tauri::State<'_, McpServerState>,
+) -> McpStatus {
+    let mcp = state.lock_recover();
+    McpStatus {
+        enabled: mcp.enabled(),
+        running: server.lock_recover().running(),
+        socket_path: mcp_socket_path().map(|p| path_to_wire(&p)),
+        tools: mcp.tools(),
+    }
+}
+
+/// Turn the server on or off; the choice is remembered across launches.
+#[tauri::command]
+pub fn set_mcp_enabled(
+    app: AppHandle,
+    state: tauri::State<'_, McpState>,
+    server: tauri::State<'_, McpServerState>,
+    enabled: bool,
+) -> Result<(), KiriError> {
+    state.lock_recover().set_enabled(enabled)?;
+    if enabled {
+        start_server(&app).map_err(KiriError::from)
+    } else {
+        server.lock_recover().stop();
+        Ok(())
+    }
+}
+
+/// Allow or deny one tool. Denied tools are hidden from `tools/list` and
+/// calls to them fail without running.
+#[tauri::command]
+pub fn set_mcp_tool_permission(
+    state: tauri::State<'_, McpState>,
+    tool: String,
+    permission: ToolPermission,
+) -> Result<(), KiriError> {
+    state
+        .lock_recover()
+        .set_permission(&tool, permission)
+        .map_err(KiriError::from)
+}
+
+/// The latest tool calls, newest first.
+#[tauri::command]
+pub fn get_mcp_audit_log(
+    state: tauri::State<'_, McpState>,
+    limit: Option<usize>,
+) -> Vec<AuditEntry> {
+    state.lock_recover().audit(limit.unwrap_or(100))
+}
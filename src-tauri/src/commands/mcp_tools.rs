@@ -0,0 +1,307 @@
+//! Tools the MCP server exposes; see mcp.rs.
+//!
+//! Every tool is read-only and confined to the workspace roots: the
+//! projects and worktrees open in kiri windows. Paths are canonicalized
+//! before the check, so `..` and symlinks can't reach outside them.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use super::fs::read_directory_blocking;
+use super::fs_path::{input_path, is_within, path_to_wire};
+use super::git::{get_git_diff_blocking, get_git_status_blocking};
+use super::git_history::get_commit_log;
+use super::git_worktree::{current_worktree_root, list_worktrees_for};
+use super::search::{search_content_blocking, search_files_blocking};
+
+/// Largest file `read_file` returns.
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+
+const DEFAULT_MAX_RESULTS: usize = 50;
+const MAX_RESULTS: usize = 500;
+
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    input_schema: fn() -> Value,
+}
+
+impl ToolSpec {
+    pub fn input_schema(&self) -> Value {
+        (self.input_schema)()
+    }
+}
+
+fn path_schema(description: &str) -> Value {
+    json!({"type": "string", "description": description})
+}
+
+fn max_results_schema() -> Value {
+    json!({"type": "integer", "minimum": 1, "maximum": MAX_RESULTS})
+}
+
+pub const TOOLS: &[ToolSpec] = &[
+    ToolSpec {
+        name: "list_projects",
+        description: "List the projects and worktrees open in kiri. Other tools only accept \
+                      paths inside these.",
+        input_schema: || json!({"type": "object", "properties": {}}),
+    },
+    ToolSpec {
+        name: "list_worktrees",
+        description: "List the git worktrees of the repository containing `path`, with their \
+                      branches.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {"path": path_schema("A path in the repository")},
+                "required": ["path"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "list_directory",
+        description: "List the entries of a directory, marking directories and gitignored \
+                      entries.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {"path": path_schema("Absolute directory path")},
+                "required": ["path"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "read_file",
+        description: "Read a UTF-8 text file, up to 1 MiB.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {"path": path_schema("Absolute file path")},
+                "required": ["path"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "search_files",
+        description: "Find files and directories under `root` whose names fuzzy-match `query`.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "root": path_schema("Directory to search"),
+                    "query": {"type": "string"},
+                    "max_results": max_results_schema()
+                },
+                "required": ["root", "query"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "search_content",
+        description: "Search the text files under `root` for `query`, returning matching \
+                      lines.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "root": path_schema("Directory to search"),
+                    "query": {"type": "string", "minLength": 2},
+                    "max_results": max_results_schema()
+                },
+                "required": ["root", "query"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "git_status",
+        description: "Branch, changed files and line counts of the worktree containing `path`.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {"path": path_schema("A path in the worktree")},
+                "required": ["path"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "git_log",
+        description: "Recent commits of the worktree containing `path`, newest first.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": path_schema("A path in the worktree"),
+                    "max_count": max_results_schema()
+                },
+                "required": ["path"]
+            })
+        },
+    },
+    ToolSpec {
+        name: "git_diff",
+        description: "Uncommitted changes to one file, or staged ones if it has none in the \
+                      working tree.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {"path": path_schema("Absolute path of the file")},
+                "required": ["path"]
+            })
+        },
+    },
+];
+
+pub fn find_tool(name: &str) -> Option<&'static ToolSpec> {
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+fn string_arg<'a>(args: &'a Value, name: &str) -> Result<&'a str, String> {
+    args.get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing string argument `{}`", name))
+}
+
+fn limit_arg(args: &Value, name: &str) -> usize {
+    args.get(name)
+        .and_then(Value::as_u64)
+        .map_or(DEFAULT_MAX_RESULTS, |n| (n as usize).clamp(1, MAX_RESULTS))
+}
+
+/// `path` resolved, if it is inside one of `roots`.
+pub fn resolve_in_roots(path: &str, roots: &[PathBuf]) -> Result<PathBuf, String> {
+    let resolved = input_path(path)?
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", path, e))?;
+    let inside = roots.iter().any(|root| {
+        root.canonicalize()
+            .is_ok_and(|root| is_within(&resolved, &root))
+    });
+    if !inside {
+        return Err(format!("{} is outside the projects open in kiri", path));
+    }
+    Ok(resolved)
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+fn path_string(path: &Path) -> String {
+    path_to_wire(path)
+}
+
+fn read_text(path: &Path) -> Result<Value, String> {
+    let len = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if len > MAX_READ_BYTES {
+        return Err(format!(
+            "{} is {} bytes; read_file returns files up to {}",
+            path.display(),
+            len,
+            MAX_READ_BYTES
+        ));
+    }
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let text = String::from_utf8(bytes)
+        .map_err(|_| format!("{} is not a UTF-8 text file", path.display()))?;
+    Ok(json!({"path": path_string(path), "content": text}))
+}
+
+/// Run tool `name`. `roots` are the workspace roots.
+pub fn call_tool(name: &str, args: &Value, roots: &[PathBuf]) -> Result<Value, String> {
+    match name {
+        "list_projects" => Ok(json!({
+            "roots": roots.iter().map(|root| path_string(root)).collect::<Vec<_>>()
+        })),
+        "list_worktrees" => {
+            let path = resolve_in_roots(string_arg(args, "path")?, roots)?;
+            to_value(list_worktrees_for(&path)?)
+        }
+        "list_directory" => {
+            let path = resolve_in_roots(string_arg(args, "path")?, roots)?;
+            to_value(read_directory_blocking(path_string(&path), false)?)
+        }
+        "read_file" => read_text(&resolve_in_roots(string_arg(args, "path")?, roots)?),
+        "search_files" => {
+            let root = resolve_in_roots(string_arg(args, "root")?, roots)?;
+            let query = string_arg(args, "query")?.to_string();
+            let results =
+                search_files_blocking(path_string(&root), query, limit_arg(args, "max_results"))?;
+            to_value(results)
+        }
+        "search_content" => {
+            let root = resolve_in_roots(string_arg(args, "root")?, roots)?;
+            let query = string_arg(args, "query")?.to_string();
+            let max_results = limit_arg(args, "max_results");
+            to_value(search_content_blocking(
+                path_string(&root),
+                query,
+                max_results,
+                Vec::new(),
+            )?)
+        }
+        "git_status" => {
+            let path = resolve_in_roots(string_arg(args, "path")?, roots)?;
+            to_value(get_git_status_blocking(path_string(&path))?)
+        }
+        "git_log" => {
+            let path = resolve_in_roots(string_arg(args, "path")?, roots)?;
+            let root = current_worktree_root(&path).ok_or("Not a git repository")?;
+            let max_count = limit_arg(args, "max_count");
+            to_value(get_commit_log(path_string(&root), Some(max_count), None)?)
+        }
+        "git_diff" => {
+            let path = resolve_in_roots(string_arg(args, "path")?, roots)?;
+            let root = current_worktree_root(&path).ok_or("Not a git repository")?;
+            let root = root.canonicalize().unwrap_or(root);
+            let relative = path.strip_prefix(&root).map_err(|e| e.to_string())?;
+            let diff = get_git_diff_blocking(path_string(&root), path_string(relative))?;
+            Ok(json!({"path": path_string(&path), "diff": diff}))
+        }
+        _ => Err(format!("Unknown tool {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tools_stay_inside_roots() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(project.join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "token").unwrap();
+        let roots = vec![project.clone()];
+
+        let file = project.join("main.rs");
+        let read = call_tool("read_file", &json!({"path": file}), &roots).unwrap();
+        assert_eq!(read["content"], "fn main() {}\n");
+
+        let outside = project.join("../secret.txt");
+        let err = call_tool("read_file", &json!({"path": outside}), &roots).unwrap_err();
+        assert!(err.contains("outside the projects"), "{}", err);
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("secret.txt"), project.join("link"))
+                .unwrap();
+            let link = project.join("link");
+            assert!(call_tool("read_file", &json!({"path": link}), &roots).is_err());
+        }
+        assert!(call_tool("read_file", &json!({}), &roots).is_err());
+
+        let listed = call_tool("list_directory", &json!({"path": project}), &roots).unwrap();
+        assert!(listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|entry| entry["name"] == "main.rs"));
+        assert!(TOOLS
+            .iter()
+            .all(|tool| tool.input_schema()["type"] == "object"));
+    }
+}
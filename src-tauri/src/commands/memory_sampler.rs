@@ -0,0 +1,130 @@
+//! Sampled memory history for leak reports.
+//!
+//! `get_memory_metrics` is a single snapshot, and release builds used to
+//! report nothing. With the "collect memory metrics" setting on (always on
+//! in debug builds), a background thread records RSS plus per-subsystem
+//! counters every [`SAMPLE_INTERVAL`]. The counters cover terminals,
+//! watchers, windows, jobs and cache sizes, so a report shows which of
+//! them grew along with memory. Only the last [`MAX_SAMPLES`] are kept.
+//!
+//! The sampler thread and settings commands live in
+//! memory_sampler_commands.rs.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// One hour of history at the default interval.
+pub const MAX_SAMPLES: usize = 360;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MemorySample {
+    /// Unix time in milliseconds
+    pub timestamp_ms: u64,
+    /// Resident Set Size in bytes
+    pub rss: u64,
+    /// Virtual memory size in bytes
+    pub vms: u64,
+    /// Live objects per subsystem, e.g. `terminals` or `dir_cache_entries`
+    pub counters: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MemoryHistory {
+    pub enabled: bool,
+    pub interval_ms: u64,
+    /// Oldest first
+    pub samples: Vec<MemorySample>,
+    /// RSS change from the oldest to the newest sample
+    pub rss_growth_bytes: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct MemorySampler {
+    samples: VecDeque<MemorySample>,
+}
+
+pub type MemorySamplerState = Arc<Mutex<MemorySampler>>;
+
+impl MemorySampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, sample: MemorySample) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn history(&self, enabled: bool) -> MemoryHistory {
+        let rss_growth_bytes = match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) => last.rss as i64 - first.rss as i64,
+            _ => 0,
+        };
+        MemoryHistory {
+            enabled,
+            interval_ms: SAMPLE_INTERVAL.as_millis() as u64,
+            samples: self.samples.iter().cloned().collect(),
+            rss_growth_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp_ms: u64, rss: u64) -> MemorySample {
+        MemorySample {
+            timestamp_ms,
+            rss,
+            vms: rss * 2,
+            counters: BTreeMap::from([("terminals".to_string(), 2)]),
+        }
+    }
+
+    #[test]
+    fn test_history_reports_growth() {
+        let mut sampler = MemorySampler::new();
+        assert_eq!(sampler.history(true).rss_growth_bytes, 0);
+
+        sampler.push(sample(0, 100));
+        sampler.push(sample(10_000, 80));
+        sampler.push(sample(20_000, 250));
+        let history = sampler.history(true);
+        assert_eq!(history.samples.len(), 3);
+        assert_eq!(history.rss_growth_bytes, 150);
+        assert_eq!(history.interval_ms, 10_000);
+    }
+
+    #[test]
+    fn test_keeps_only_recent_samples() {
+        let mut sampler = MemorySampler::new();
+        for i in 0..MAX_SAMPLES as u64 + 10 {
+            sampler.push(sample(i, i));
+        }
+        let history = sampler.history(true);
+        assert_eq!(history.samples.len(), MAX_SAMPLES);
+        assert_eq!(history.samples[0].timestamp_ms, 10);
+
+        sampler.clear();
+        assert!(sampler.history(false).samples.is_empty());
+    }
+
+    #[test]
+    fn test_sample_serialization() {
+        let json = serde_json::to_value(sample(5, 1024)).unwrap();
+        assert_eq!(json["timestamp_ms"], 5);
+        assert_eq!(json["rss"], 1024);
+        assert_eq!(json["counters"]["terminals"], 2);
+    }
+}
@@ -0,0 +1,127 @@
+//! Background memory sampler and its settings commands. History and the
+//! setting key live in memory_sampler.rs.
+
+use super::clock::now_ms;
+use super::error::KiriError;
+use super::fs::directory_cache_len;
+use super::git_cache::git_cache;
+use super::jobs::JobState;
+use super::lock_ext::LockExt;
+use super::memory_sampler::{MemoryHistory, MemorySample, MemorySamplerState, SAMPLE_INTERVAL};
+use super::performance::{
+    get_memory_usage, memory_metrics_enabled, memory_metrics_opt_in, set_memory_metrics_opt_in,
+};
+use super::settings::SettingsState;
+use super::settings_commands::update_settings_impl;
+use super::terminal::TerminalState;
+use super::watcher::WatcherState;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+
+/// Set while the sampler thread is alive.
+static SAMPLER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn subsystem_counters(app: &AppHandle) -> BTreeMap<String, u64> {
+    let mut counters = BTreeMap::new();
+    let mut set = |name: &str, count: usize| {
+        counters.insert(name.to_string(), count as u64);
+    };
+    if let Some(state) = app.try_state::<TerminalState>() {
+        set("terminals", state.len());
+    }
+    if let Some(state) = app.try_state::<WatcherState>() {
+        set("watchers", state.len());
+    }
+    if let Some(state) = app.try_state::<JobState>() {
+        let jobs = state.lock_recover().list();
+        set(
+            "active_jobs",
+            jobs.iter().filter(|j| !j.status.is_finished()).count(),
+        );
+    }
+    set("windows", app.webview_windows().len());
+    set("dir_cache_entries", directory_cache_len());
+    set("git_cache_repos", git_cache().lock_recover().len());
+    counters
+}
+
+fn take_sample(app: &AppHandle) {
+    let memory = get_memory_usage();
+    let sample = MemorySample {
+        timestamp_ms: now_ms(),
+        rss: memory.rss,
+        vms: memory.vms,
+        counters: subsystem_counters(app),
+    };
+    if let Some(state) = app.try_state::<MemorySamplerState>() {
+        state.lock_recover().push(sample);
+    }
+}
+
+/// Start the sampler thread if sampling is enabled and it isn't already
+/// running. It exits on its own once sampling is switched off.
+fn ensure_sampler(app: &AppHandle) {
+    if !memory_metrics_enabled() || SAMPLER_RUNNING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let app = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("kiri-memory-sampler".to_string())
+        .spawn(move || {
+            while memory_metrics_enabled() {
+                take_sample(&app);
+                std::thread::sleep(SAMPLE_INTERVAL);
+            }
+            SAMPLER_RUNNING.store(false, Ordering::Release);
+        });
+    if let Err(e) = spawned {
+        SAMPLER_RUNNING.store(false, Ordering::Release);
+        log::warn!("failed to start memory sampler: {}", e);
+    }
+}
+
+/// Apply the persisted setting and start sampling if it is on. Call from
+/// setup.
+pub fn setup_memory_sampler(app: &AppHandle) {
+    let enabled = app
+        .try_state::<SettingsState>()
+        .is_some_and(|settings| settings.lock_recover().settings().collect_memory_metrics);
+    set_memory_metrics_opt_in(enabled);
+    ensure_sampler(app);
+}
+
+/// Whether the user opted into memory metrics. Debug builds collect them
+/// regardless.
+#[tauri::command]
+pub fn get_memory_metrics_enabled() -> bool {
+    memory_metrics_opt_in()
+}
+
+/// Turn sampled memory metrics on or off and remember the choice for next
+/// launch. Turning them off discards the history.
+#[tauri::command]
+pub fn set_memory_metrics_enabled(app: AppHandle, enabled: bool) -> Result<(), KiriError> {
+    update_settings_impl(&app, serde_json::json!({ "collectMemoryMetrics": enabled }))
+        .map(|_| ())
+        .map_err(KiriError::from)
+}
+
+/// Start or stop sampling. Called when the `collectMemoryMetrics` setting
+/// changes; turning it off discards the history.
+pub fn apply_memory_metrics_setting(app: &AppHandle, enabled: bool) {
+    set_memory_metrics_opt_in(enabled);
+    if enabled {
+        ensure_sampler(app);
+    } else if !memory_metrics_enabled() {
+        if let Some(state) = app.try_state::<MemorySamplerState>() {
+            state.lock_recover().clear();
+        }
+    }
+}
+
+/// Samples collected so far, oldest first.
+#[tauri::command]
+pub fn get_memory_history(state: tauri::State<'_, MemorySamplerState>) -> MemoryHistory {
+    state.lock_recover().history(memory_metrics_enabled())
+}
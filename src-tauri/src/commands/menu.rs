@@ -1,20 +1,18 @@
 use serde::Deserialize;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use tauri::{
     menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
-    App, Emitter, Listener, Manager,
+    App, AppHandle, Emitter, Listener, Manager,
 };
 
-#[derive(Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
-struct RecentProject {
-    path: String,
-    name: String,
-    #[allow(dead_code)]
-    last_opened: f64,
-    #[allow(dead_code)]
-    git_branch: Option<String>,
-}
+use super::fs_path::display_path;
+use super::git_worktree::{current_worktree_root, list_worktrees_for, WorktreeInfo};
+use super::keymap::{Keymap, KeymapState, KEYMAP_COMMANDS};
+use super::lock_ext::{LockExt, RwLockExt};
+use super::recent_projects::{RecentProject, RecentProjectsState};
+use super::settings::SettingsState;
+use super::window::{route_to_project, WindowRegistryState};
 
 const MAX_RECENT_MENU_ITEMS: usize = 5;
 
@@ -41,35 +39,47 @@ struct ToolsState {
     startup_command: String,
 }
 
-fn load_recent_projects_from_store(app: &App) -> Vec<RecentProject> {
-    use tauri_plugin_store::StoreExt;
-    match app.store("kiri-settings.json") {
-        Ok(store) => match store.get("recentProjects") {
-            Some(value) => serde_json::from_value::<Vec<RecentProject>>(value).unwrap_or_default(),
-            None => Vec::new(),
-        },
-        Err(_) => Vec::new(),
-    }
+/// Menu contents that change at runtime. Managed as app state so window
+/// focus and watcher events can rebuild the menu, not just the listeners
+/// registered in [`setup_menu`].
+pub struct MenuModel {
+    recent_projects: Vec<RecentProject>,
+    tools: ToolsState,
+    /// Worktrees of the focused window's repository.
+    worktrees: Vec<WorktreeInfo>,
+    /// Checkout the focused window has open, shown checked in the menu.
+    current_worktree: Option<String>,
+    /// Project path the worktree list was loaded for.
+    worktree_source: Option<String>,
 }
 
-fn load_startup_command_from_store(app: &App) -> String {
-    use tauri_plugin_store::StoreExt;
-    match app.store("kiri-settings.json") {
-        Ok(store) => {
-            if let Some(value) = store.get("globalSettings") {
-                value
-                    .get("startupCommand")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("none")
-                    .to_string()
-            } else {
-                "none".to_string()
-            }
-        }
-        Err(_) => "none".to_string(),
+impl MenuModel {
+    /// `(name, path)` of the projects listed under Open Recent.
+    pub fn recent_projects(&self) -> Vec<(String, String)> {
+        self.recent_projects
+            .iter()
+            .take(MAX_RECENT_MENU_ITEMS)
+            .map(|p| (p.name.clone(), p.path.clone()))
+            .collect()
     }
 }
 
+pub type MenuModelState = Arc<Mutex<MenuModel>>;
+
+fn load_recent_projects(app: &App) -> Vec<RecentProject> {
+    app.try_state::<RecentProjectsState>()
+        .map(|state| state.lock_recover().list().to_vec())
+        .unwrap_or_default()
+}
+
+fn load_startup_command(app: &App) -> String {
+    app.try_state::<SettingsState>()
+        .map(|settings| settings.lock_recover().settings().startup_command)
+        .unwrap_or_default()
+        .as_str()
+        .to_string()
+}
+
 fn build_recent_submenu(
     handle: &tauri::AppHandle,
     projects: &[RecentProject],
@@ -108,21 +118,107 @@ fn build_recent_submenu(
     )?)
 }
 
-fn rebuild_menu(
+fn worktree_menu_label(worktree: &WorktreeInfo) -> String {
+    match &worktree.branch {
+        Some(branch) if branch != &worktree.name => format!("{} ({})", worktree.name, branch),
+        _ => worktree.name.clone(),
+    }
+}
+
+fn build_worktree_submenu(
     handle: &tauri::AppHandle,
-    projects: &[RecentProject],
-    tools: &ToolsState,
-) -> Result<Menu<tauri::Wry>, Box<dyn std::error::Error>> {
-    let new_window = MenuItem::with_id(
+    worktrees: &[WorktreeInfo],
+    current: Option<&str>,
+) -> Result<Submenu<tauri::Wry>, Box<dyn std::error::Error>> {
+    let mut items: Vec<Box<dyn IsMenuItem<tauri::Wry>>> = Vec::new();
+
+    if worktrees.is_empty() {
+        let empty_item = MenuItem::with_id(
+            handle,
+            "worktree_empty",
+            "(No Worktrees)",
+            false,
+            None::<&str>,
+        )?;
+        items.push(Box::new(empty_item));
+    } else {
+        for (i, worktree) in worktrees.iter().enumerate() {
+            let id = format!("worktree_{}", i);
+            let item = CheckMenuItem::with_id(
+                handle,
+                &id,
+                worktree_menu_label(worktree),
+                true,
+                current == Some(worktree.path.as_str()),
+                None::<&str>,
+            )?;
+            items.push(Box::new(item));
+        }
+    }
+
+    let item_refs: Vec<&dyn IsMenuItem<tauri::Wry>> = items.iter().map(|i| i.as_ref()).collect();
+    Ok(Submenu::with_id_and_items(
         handle,
-        "new_window",
-        "New Window",
+        "worktrees",
+        "Worktrees",
         true,
-        Some("CmdOrCtrl+Shift+N"),
-    )?;
-    let open = MenuItem::with_id(handle, "open", "Open...", true, Some("CmdOrCtrl+O"))?;
+        &item_refs,
+    )?)
+}
+
+/// Menu item for keymap command `id` with its current accelerator.
+fn keymap_item(
+    handle: &tauri::AppHandle,
+    keymap: &Keymap,
+    id: &str,
+) -> Result<MenuItem<tauri::Wry>, Box<dyn std::error::Error>> {
+    let label = KEYMAP_COMMANDS
+        .iter()
+        .find(|c| c.id == id)
+        .map_or(id, |c| c.label);
+    Ok(MenuItem::with_id(
+        handle,
+        id,
+        label,
+        true,
+        keymap.accelerator(id).as_deref(),
+    )?)
+}
+
+/// Submenu holding every keymap command that belongs to `menu`.
+fn build_keymap_submenu(
+    handle: &tauri::AppHandle,
+    keymap: &Keymap,
+    menu: &str,
+) -> Result<Submenu<tauri::Wry>, Box<dyn std::error::Error>> {
+    let items = KEYMAP_COMMANDS
+        .iter()
+        .filter(|c| c.menu == menu)
+        .map(|c| keymap_item(handle, keymap, c.id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let item_refs: Vec<&dyn IsMenuItem<tauri::Wry>> = items
+        .iter()
+        .map(|i| i as &dyn IsMenuItem<tauri::Wry>)
+        .collect();
+    Ok(Submenu::with_items(handle, menu, true, &item_refs)?)
+}
+
+fn rebuild_menu(
+    handle: &tauri::AppHandle,
+    model: &MenuModel,
+) -> Result<Menu<tauri::Wry>, Box<dyn std::error::Error>> {
+    let projects = &model.recent_projects;
+    let tools = &model.tools;
+    let keymap = handle
+        .try_state::<KeymapState>()
+        .map(|state| state.lock_recover().clone())
+        .unwrap_or_default();
+    let new_window = keymap_item(handle, &keymap, "new_window")?;
+    let open = keymap_item(handle, &keymap, "open")?;
     let close_window = PredefinedMenuItem::close_window(handle, Some("Close Window"))?;
     let open_recent = build_recent_submenu(handle, projects)?;
+    let worktrees =
+        build_worktree_submenu(handle, &model.worktrees, model.current_worktree.as_deref())?;
 
     let file_menu = Submenu::with_items(
         handle,
@@ -133,11 +229,15 @@ fn rebuild_menu(
             &PredefinedMenuItem::separator(handle)?,
             &open,
             &open_recent,
+            &worktrees,
             &PredefinedMenuItem::separator(handle)?,
             &close_window,
         ],
     )?;
 
+    let go_menu = build_keymap_submenu(handle, &keymap, "Go")?;
+    let terminal_menu = build_keymap_submenu(handle, &keymap, "Terminal")?;
+
     // Edit menu
     let undo = PredefinedMenuItem::undo(handle, Some("Undo"))?;
     let redo = PredefinedMenuItem::redo(handle, Some("Redo"))?;
@@ -238,7 +338,14 @@ fn rebuild_menu(
         Ok(Menu::with_items(
             handle,
             &[
-                &app_menu, &file_menu, &edit_menu, &view_menu, &tools_menu, &window_menu,
+                &app_menu,
+                &file_menu,
+                &edit_menu,
+                &view_menu,
+                &go_menu,
+                &terminal_menu,
+                &tools_menu,
+                &window_menu,
             ],
         )?)
     }
@@ -247,37 +354,113 @@ fn rebuild_menu(
     Ok(Menu::with_items(
         handle,
         &[
-            &file_menu, &edit_menu, &view_menu, &tools_menu, &window_menu,
+            &file_menu,
+            &edit_menu,
+            &view_menu,
+            &go_menu,
+            &terminal_menu,
+            &tools_menu,
+            &window_menu,
         ],
     )?)
 }
 
+fn apply_menu(handle: &AppHandle, model: &MenuModel) {
+    match rebuild_menu(handle, model) {
+        Ok(menu) => {
+            let _ = handle.set_menu(menu);
+        }
+        Err(e) => log::warn!("failed to rebuild menu: {e}"),
+    }
+    super::tray_commands::update_tray_menu(handle, model);
+}
+
+/// Show `projects` under Open Recent, in the menu bar and the tray.
+pub fn set_recent_projects(app: &AppHandle, projects: Vec<RecentProject>) {
+    if let Some(model) = app.try_state::<MenuModelState>() {
+        let mut model = model.lock_recover();
+        model.recent_projects = projects;
+        apply_menu(app, &model);
+    }
+}
+
+/// Rebuild the menu from the current model, e.g. after the keymap changed.
+pub fn rebuild_app_menu(app: &AppHandle) {
+    if let Some(model) = app.try_state::<MenuModelState>() {
+        apply_menu(app, &model.lock_recover());
+    }
+}
+
+/// Reload the Worktrees submenu for `project_path` on a blocking thread
+/// and rebuild the menu if the list or current checkout changed.
+pub fn refresh_worktree_menu(app: &AppHandle, project_path: &str) {
+    let Some(model) = app.try_state::<MenuModelState>() else {
+        return;
+    };
+    let model: MenuModelState = Arc::clone(&model);
+    let app = app.clone();
+    let project_path = project_path.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = Path::new(&project_path);
+        let worktrees = list_worktrees_for(path).unwrap_or_default();
+        let current = current_worktree_root(path).map(|root| display_path(&root));
+
+        let mut model = model.lock_recover();
+        model.worktree_source = Some(project_path);
+        if model.worktrees != worktrees || model.current_worktree != current {
+            model.worktrees = worktrees;
+            model.current_worktree = current;
+            apply_menu(&app, &model);
+        }
+    });
+}
+
+/// Point the Worktrees submenu at the project of the window that just
+/// gained focus.
+pub fn on_window_focused(app: &AppHandle, label: &str) {
+    let project_path = app
+        .try_state::<WindowRegistryState>()
+        .and_then(|registry| registry.read_recover().get_path_for_label(label).cloned());
+    if let Some(path) = project_path {
+        refresh_worktree_menu(app, &path);
+    }
+}
+
+/// Called when the watcher sees git metadata change under `repo_root`.
+/// Only the repository the menu is showing needs a refresh.
+pub fn on_git_changed(app: &AppHandle, repo_root: &str) {
+    let source = app
+        .try_state::<MenuModelState>()
+        .and_then(|model| model.lock_recover().worktree_source.clone());
+    if source.as_deref() == Some(repo_root) {
+        refresh_worktree_menu(app, repo_root);
+    }
+}
+
 pub fn setup_menu(app: &App) -> Result<(), Box<dyn std::error::Error>> {
     let handle = app.handle();
 
     // Load initial state
-    let initial_projects = load_recent_projects_from_store(app);
-    let initial_startup_cmd = load_startup_command_from_store(app);
-
-    // Shared state
-    let recent_paths: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(
-        initial_projects.iter().map(|p| p.path.clone()).collect(),
-    ));
-    let recent_projects_state: Arc<Mutex<Vec<RecentProject>>> =
-        Arc::new(Mutex::new(initial_projects.clone()));
-    let tools_state: Arc<Mutex<ToolsState>> = Arc::new(Mutex::new(ToolsState {
-        startup_command: initial_startup_cmd,
+    let model: MenuModelState = Arc::new(Mutex::new(MenuModel {
+        recent_projects: load_recent_projects(app),
+        tools: ToolsState {
+            startup_command: load_startup_command(app),
+        },
+        worktrees: Vec::new(),
+        current_worktree: None,
+        worktree_source: None,
     }));
+    app.manage(Arc::clone(&model));
 
     // Build initial menu
     {
-        let tools = tools_state.lock().unwrap();
-        let menu = rebuild_menu(handle, &initial_projects, &tools)?;
+        let model = model.lock_recover();
+        let menu = rebuild_menu(handle, &model)?;
         app.set_menu(menu)?;
     }
 
     // Handle menu events
-    let recent_paths_for_events = Arc::clone(&recent_paths);
+    let model_for_events = Arc::clone(&model);
     app.on_menu_event(move |app_handle, event| {
         let id = event.id().as_ref();
         match id {
@@ -308,18 +491,38 @@ pub fn setup_menu(app: &App) -> Result<(), Box<dyn std::error::Error>> {
                 // Startup command is idempotent, safe to broadcast
                 let _ = app_handle.emit("menu-set-startup-command", cmd);
             }
+            _ if id.starts_with("go_") || id.starts_with("terminal_") => {
+                // The webview owns these actions; hand it the command id.
+                let _ = emit_to_focused_window(app_handle, "menu-command", id);
+            }
             _ if id.starts_with("recent_") => {
                 if let Ok(index) = id.strip_prefix("recent_").unwrap().parse::<usize>() {
-                    if let Ok(paths) = recent_paths_for_events.lock() {
-                        if let Some(path) = paths.get(index) {
-                            // Route to the focused window only — otherwise every
-                            // window's listener fires and they race on the
-                            // recentProjects bump + focus_or_create_window call.
-                            let _ = emit_to_focused_window(
-                                app_handle,
-                                "menu-open-recent",
-                                path.clone(),
-                            );
+                    let path = model_for_events
+                        .lock_recover()
+                        .recent_projects
+                        .get(index)
+                        .map(|p| p.path.clone());
+                    if let Some(path) = path {
+                        // Route to the focused window only — otherwise every
+                        // window's listener fires and they race on the
+                        // recentProjects bump + focus_or_create_window call.
+                        let _ = emit_to_focused_window(app_handle, "menu-open-recent", path);
+                    }
+                }
+            }
+            _ if id.starts_with("worktree_") => {
+                if let Ok(index) = id.strip_prefix("worktree_").unwrap().parse::<usize>() {
+                    let path = {
+                        let model = model_for_events.lock_recover();
+                        // Check items toggle themselves on click; rebuild so
+                        // the check mark keeps tracking the focused checkout.
+                        apply_menu(app_handle, &model);
+                        model.worktrees.get(index).map(|w| w.path.clone())
+                    };
+                    if let Some(path) = path {
+                        let registry = app_handle.state::<WindowRegistryState>();
+                        if let Err(e) = route_to_project(app_handle, &registry, &path) {
+                            log::error!("failed to open worktree window: {e}");
                         }
                     }
                 }
@@ -330,31 +533,20 @@ pub fn setup_menu(app: &App) -> Result<(), Box<dyn std::error::Error>> {
 
     // Listen for update-recent-menu events from frontend
     {
-        let recent_paths = Arc::clone(&recent_paths);
-        let recent_projects = Arc::clone(&recent_projects_state);
-        let tools = Arc::clone(&tools_state);
+        let model = Arc::clone(&model);
         let handle = app.handle().clone();
         app.listen("update-recent-menu", move |event| {
             if let Ok(projects) = serde_json::from_str::<Vec<RecentProject>>(event.payload()) {
-                if let Ok(mut paths) = recent_paths.lock() {
-                    *paths = projects.iter().map(|p| p.path.clone()).collect();
-                }
-                if let Ok(mut stored) = recent_projects.lock() {
-                    *stored = projects.clone();
-                }
-                if let Ok(tools) = tools.lock() {
-                    if let Ok(new_menu) = rebuild_menu(&handle, &projects, &tools) {
-                        let _ = handle.set_menu(new_menu);
-                    }
-                }
+                let mut model = model.lock_recover();
+                model.recent_projects = projects;
+                apply_menu(&handle, &model);
             }
         });
     }
 
     // Listen for tools state updates from frontend
     {
-        let recent_projects = Arc::clone(&recent_projects_state);
-        let tools = Arc::clone(&tools_state);
+        let model = Arc::clone(&model);
         let handle = app.handle().clone();
         app.listen("update-tools-menu", move |event| {
             #[derive(Deserialize)]
@@ -363,18 +555,21 @@ pub fn setup_menu(app: &App) -> Result<(), Box<dyn std::error::Error>> {
                 startup_command: Option<String>,
             }
             if let Ok(update) = serde_json::from_str::<ToolsUpdate>(event.payload()) {
-                if let Ok(mut t) = tools.lock() {
-                    if let Some(cmd) = update.startup_command {
-                        t.startup_command = cmd;
-                    }
-                }
-                if let (Ok(projects), Ok(tools)) =
-                    (recent_projects.lock(), tools.lock())
-                {
-                    if let Ok(new_menu) = rebuild_menu(&handle, &projects, &tools) {
-                        let _ = handle.set_menu(new_menu);
-                    }
+                let mut model = model.lock_recover();
+                if let Some(cmd) = update.startup_command {
+                    model.tools.startup_command = cmd;
                 }
+                apply_menu(&handle, &model);
+            }
+        });
+    }
+
+    // Listen for worktree changes the frontend makes itself (create/remove)
+    {
+        let handle = app.handle().clone();
+        app.listen("update-worktree-menu", move |event| {
+            if let Ok(project_path) = serde_json::from_str::<String>(event.payload()) {
+                refresh_worktree_menu(&handle, &project_path);
             }
         });
     }
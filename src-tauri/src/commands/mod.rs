@@ -1,29 +1,199 @@
+pub mod accelerator;
+pub mod agents;
+pub mod agents_commands;
+pub mod auto_fetch;
+pub mod auto_fetch_commands;
+pub mod bitbucket;
+pub mod bookmarks;
+pub mod bookmarks_commands;
+pub mod changelog;
+pub mod changelog_commands;
+pub mod ci_status;
+pub mod ci_status_commands;
 pub mod cli_install;
 pub mod cli_install_paths;
 pub mod cli_server;
+pub mod clock;
+pub mod cloudflare_tunnel;
+pub mod code_host;
+pub mod code_host_commands;
+pub mod codeowners;
+pub mod codeowners_commands;
+pub mod command_history;
+pub mod command_history_commands;
+pub mod confirmation;
+pub mod confirmation_commands;
+pub mod conventional_commit;
+pub mod conventional_commit_commands;
+pub mod crash_report;
+pub mod crash_report_commands;
+pub mod deep_link;
+pub mod deep_link_commands;
+pub mod dependency_scan;
+pub mod dependency_scan_commands;
+pub mod devcontainer;
+pub mod devcontainer_commands;
+pub mod direnv;
+pub mod direnv_commands;
 pub mod error;
 pub mod lock_ext;
 pub mod skill_install;
+pub mod dock_badge;
+pub mod dock_badge_commands;
+pub mod docker;
+pub mod docker_commands;
+pub mod doctor;
+pub mod doctor_commands;
+pub mod documents;
+pub mod documents_commands;
 pub mod drag_drop;
+pub mod editorconfig;
+pub mod entity_map;
+pub mod env_sets;
+pub mod env_sets_commands;
+pub mod event_throttle;
+pub mod event_throttle_commands;
+pub mod events;
+pub mod export;
+pub mod export_commands;
+pub mod external_editor;
+pub mod external_editor_commands;
 pub mod file;
 pub mod file_io;
+pub mod file_lines;
+pub mod format;
+pub mod format_commands;
 pub mod fs;
+pub mod fs_budget;
+pub mod fs_budget_commands;
+pub mod fs_dir_cache;
 pub mod fs_gitignore;
 pub mod fs_io;
+pub mod fs_mounts;
+pub mod fs_path;
+pub mod generated;
+pub mod generated_commands;
 pub mod git;
+pub mod git_cache;
 pub mod git_diff;
 pub mod git_history;
 pub mod git_history_commands;
+pub mod git_hooks;
+pub mod git_hooks_commands;
+pub mod git_stage;
+pub mod git_stage_commands;
 pub mod git_status_map;
+pub mod git_worktree;
+pub mod github;
+pub mod github_commands;
+pub mod gitlab;
+pub mod global_shortcut;
+pub mod global_shortcut_commands;
+pub mod i18n;
+pub mod issues;
+pub mod issues_commands;
+pub mod jira;
+pub mod jobs;
+pub mod jobs_commands;
+pub mod keymap;
+pub mod keymap_commands;
+pub mod large_file;
+pub mod latency;
+pub mod localtunnel;
+pub mod logging;
+pub mod logging_commands;
+pub mod lsp;
+pub mod lsp_commands;
+pub mod markdown;
+pub mod markdown_commands;
+pub mod mcp;
+pub mod mcp_commands;
+pub mod mcp_tools;
+pub mod memory_sampler;
+pub mod memory_sampler_commands;
 pub mod menu;
+pub mod monorepo;
+pub mod monorepo_commands;
+pub mod network;
+pub mod network_commands;
+pub mod ngrok;
+pub mod notifications;
+pub mod notifications_commands;
+pub mod oauth;
+pub mod oauth_commands;
+pub mod operations;
+pub mod operations_commands;
+pub mod path_reference;
+pub mod path_reference_commands;
 pub mod performance;
 pub mod performance_commands;
+pub mod preview;
+pub mod process;
+pub mod project_config;
+pub mod project_config_commands;
+pub mod project_stats;
+pub mod project_stats_commands;
+pub mod recent_projects;
+pub mod recent_projects_commands;
+pub mod retry;
 pub mod search;
+pub mod secrets;
+pub mod secrets_commands;
+pub mod session;
+pub mod session_commands;
+pub mod shutdown;
+pub mod shutdown_commands;
+pub mod settings;
+pub mod settings_commands;
+pub mod settings_layers;
+pub mod settings_layers_commands;
+pub mod settings_sync;
+pub mod settings_sync_commands;
+pub mod single_instance;
+pub mod single_instance_commands;
+pub mod ssh_config;
+pub mod ssh_config_commands;
+pub mod startup;
+pub mod startup_commands;
+pub mod support_bundle;
+pub mod syntax;
+pub mod syntax_commands;
+pub mod tailscale_funnel;
+pub mod task_watch;
+pub mod task_watch_commands;
+pub mod tasks;
+pub mod tasks_commands;
+pub mod telemetry;
+pub mod telemetry_commands;
 pub mod terminal;
 pub mod terminal_commands;
+pub mod test_runner;
+pub mod test_runner_commands;
+pub mod text_format;
+pub mod trace;
+pub mod trace_commands;
+pub mod tray;
+pub mod tray_commands;
+pub mod trust;
+pub mod trust_commands;
+pub mod todo_scanner;
+pub mod todo_scanner_commands;
+pub mod toolchain;
+pub mod toolchain_commands;
+pub mod tunnel;
+pub mod tunnel_commands;
+pub mod updater;
+pub mod updater_commands;
 pub mod watcher;
 pub mod watcher_commands;
 pub mod window;
+pub mod window_context;
+pub mod window_geometry;
+pub mod window_geometry_commands;
+pub mod window_tabs;
+pub mod window_tabs_commands;
+pub mod worktree_drift;
+pub mod worktree_drift_commands;
 
 pub use drag_drop::*;
 pub use file::*;
@@ -37,8 +207,166 @@ pub use terminal_commands::*;
 pub use watcher::*;
 pub use watcher_commands::*;
 pub use git_history_commands::*;
+pub use git_worktree::{create_worktree, list_worktrees, remove_worktree};
 pub use window::*;
 pub use cli_server::{
     cli_resolve_pending, cli_update_pane_map, CliServerRegistry, CliServerRegistryState,
 };
+pub use preview::get_preview;
+pub use todo_scanner::{TodoScanner, TodoScannerState};
+pub use todo_scanner_commands::get_todos;
+pub use session::{SessionState, SessionStore};
+pub use session_commands::{restore_session, save_session};
+pub use single_instance_commands::LauncherState;
+pub use dock_badge::{DockBadge, DockBadgeState};
+pub use dock_badge_commands::{set_badge_count, set_job_progress};
+pub use global_shortcut::{GlobalShortcutState, ShortcutBindings};
+pub use global_shortcut_commands::{
+    list_global_shortcuts, register_global_shortcut, unregister_global_shortcut,
+};
+pub use tray::{TraySettings, TrayState};
+pub use tray_commands::{get_tray_enabled, set_tray_enabled};
+pub use window_geometry_commands::{get_window_geometry, set_window_geometry};
+pub use window_tabs::{WindowTabbing, WindowTabbingState};
+pub use window_tabs_commands::{
+    get_tab_groups, get_window_tabbing, merge_all_windows, move_tab_to_new_window,
+    open_in_new_tab, set_window_tabbing,
+};
+pub use keymap::{Keymap, KeymapState};
+pub use keymap_commands::{get_keymap, reset_keybinding, set_keybinding};
+pub use trace_commands::export_trace;
+pub use jobs::{JobManager, JobState};
+pub use jobs_commands::{cancel_job, list_jobs};
+pub use memory_sampler::{MemorySampler, MemorySamplerState};
+pub use memory_sampler_commands::{
+    get_memory_history, get_memory_metrics_enabled, set_memory_metrics_enabled,
+};
+pub use event_throttle::{ThrottledEmitter, ThrottledEmitterState};
+pub use startup_commands::get_startup_report;
+pub use ci_status::{CiStatusCache, CiStatusState};
+pub use ci_status_commands::get_ci_status;
+pub use code_host_commands::{
+    checkout_pull_request, clear_code_host_token, create_pull_request, get_branch_pull_request,
+    get_branch_status, get_code_host, get_pull_request_status, list_pull_requests,
+    open_pull_request_url, set_code_host_token,
+};
+pub use github_commands::{
+    github_auth_status, github_poll_device_flow, github_sign_out, github_start_device_flow,
+};
+pub use docker::{LogFollowers, LogFollowersState};
+pub use docker_commands::{
+    compose_down, compose_up, follow_container_logs, get_compose_status, stop_container_logs,
+};
+pub use devcontainer_commands::{devcontainer_down, devcontainer_up, get_devcontainer};
+pub use direnv::{Direnv, DirenvState};
+pub use direnv_commands::{approve_envrc, get_envrc_status, reload_envrc, revoke_envrc};
+pub use env_sets::{EnvSets, EnvSetsState};
+pub use env_sets_commands::{delete_env_set, list_env_sets, save_env_set, set_active_env_set};
+pub use toolchain_commands::get_toolchain_status;
+pub use lsp::{LspManager, LspState};
+pub use lsp_commands::{
+    list_lsp_servers, lsp_did_change, lsp_did_close, lsp_did_open, lsp_did_save,
+    lsp_document_uri, lsp_request, stop_lsp_server,
+};
+pub use syntax::{SyntaxDocuments, SyntaxState};
+pub use syntax_commands::{
+    syntax_close, syntax_edit, syntax_highlights, syntax_open, syntax_outline,
+};
+pub use format_commands::{format_file, get_formatter};
+pub use tasks::{TaskRuns, TaskRunsState};
+pub use tasks_commands::{
+    get_task_output, list_task_runs, list_tasks, resize_task, restart_task, run_task, stop_task,
+    write_task_input,
+};
+pub use task_watch::{TaskWatches, TaskWatchesState};
+pub use task_watch_commands::{list_task_watches, stop_task_watch, watch_task};
+pub use monorepo_commands::get_affected_packages;
+pub use ssh_config_commands::{list_ssh_hosts, probe_ssh_host};
+pub use markdown_commands::render_markdown_preview;
+pub use worktree_drift_commands::check_worktree_drift;
+pub use command_history::{CommandHistory, CommandHistoryState};
+pub use command_history_commands::{clear_command_history, search_command_history};
+pub use external_editor_commands::{list_external_editors, open_in_external_editor};
+pub use generated_commands::classify_generated_paths;
+pub use git_stage_commands::{create_commit, stage_file, stage_hunk, unstage_file};
+pub use test_runner_commands::{
+    detect_test_frameworks, get_test_run, list_test_runs, run_tests, TestRuns, TestRunsState,
+};
+pub use tunnel::{Tunnels, TunnelsState};
+pub use tunnel_commands::{
+    delete_tunnel, get_tunnel_status, list_tunnel_providers, list_tunnels, save_tunnel,
+    start_tunnel, stop_tunnel,
+};
+pub use mcp::{Mcp, McpState};
+pub use mcp_commands::{
+    get_mcp_audit_log, get_mcp_status, set_mcp_enabled, set_mcp_tool_permission, McpServer,
+    McpServerState,
+};
+pub use agents::{AgentSessions, AgentSessionsState};
+pub use agents_commands::{
+    get_agent_output, list_agent_clis, list_agent_sessions, resize_agent_session,
+    start_agent_session, stop_agent_session, write_agent_input,
+};
+pub use notifications::{Notifications, NotificationsState};
+pub use notifications_commands::{get_notification_rules, notify_event, set_notification_rule};
+pub use secrets_commands::{delete_secret, has_secret, set_secret};
+pub use network_commands::{get_network_settings, set_network_settings};
+pub use oauth_commands::{
+    cancel_browser_sign_in, sign_in_with_browser, OAuthSignIn, OAuthSignInState,
+};
+pub use conventional_commit_commands::{lint_commit_message, suggest_commit_metadata};
+pub use changelog_commands::generate_changelog;
+pub use dependency_scan::{DependencyReports, DependencyReportsState};
+pub use project_stats::{ProjectStatsCache, ProjectStatsState};
+pub use project_stats_commands::get_project_stats;
+pub use export_commands::{export_diff, export_files};
+pub use path_reference_commands::copy_path_reference;
+pub use dependency_scan_commands::{get_dependency_report, scan_dependencies};
+pub use codeowners_commands::get_owners;
+pub use git_hooks::{HookRuns, HookRunsState};
+pub use git_hooks_commands::{get_pre_commit_run, list_pre_commit_hooks, run_pre_commit_hooks};
+pub use auto_fetch::{AutoFetch, AutoFetchState};
+pub use auto_fetch_commands::{
+    get_auto_fetch_settings, set_auto_fetch_settings, set_repo_auto_fetch,
+};
+pub use settings::{SettingsState, SettingsStore};
+pub use settings_commands::{get_settings, update_settings};
+pub use project_config::{ProjectConfigs, ProjectConfigsState};
+pub use project_config_commands::get_project_config;
+pub use settings_sync_commands::{export_settings, import_settings};
+pub use settings_layers::{WorktreeSettings, WorktreeSettingsState};
+pub use settings_layers_commands::{
+    get_worktree_settings, resolve_worktree_settings, run_worktree_init, set_worktree_settings,
+};
+pub use trust::{TrustStore, TrustStoreState};
+pub use trust_commands::{get_trust_state, set_trust_state};
+pub use recent_projects::{RecentProjects, RecentProjectsState};
+pub use bookmarks::{Bookmarks, BookmarksState};
+pub use bookmarks_commands::{
+    add_bookmark, list_bookmarks, remove_bookmark, reorder_bookmarks, update_bookmark,
+};
+pub use recent_projects_commands::{
+    add_recent_project, clear_recent_projects, list_recent_projects, pin_recent_project,
+    remove_recent_project, validate_recent_projects,
+};
+pub use confirmation_commands::request_confirmation;
+pub use fs_budget_commands::is_slow_filesystem;
+pub use telemetry_commands::{
+    clear_usage_analytics, export_usage_analytics, get_usage_analytics,
+    set_usage_analytics_enabled,
+};
+pub use crash_report_commands::{clear_crash_reports, get_crash_reports};
+pub use updater::{Updater, UpdaterState};
+pub use updater_commands::{check_for_updates, get_update_status};
+pub use logging_commands::{export_support_bundle, get_log_levels, set_log_levels};
+pub use doctor_commands::run_doctor;
+pub use documents_commands::{
+    close_document, list_documents, list_unsaved_documents, open_document, save_document,
+    set_document_dirty,
+};
+pub use operations_commands::cancel_operation;
+pub use issues_commands::{
+    clear_jira_credentials, create_worktree_from_issue, get_jira_site, list_assigned_issues,
+    set_jira_credentials,
+};
 pub use skill_install::{install_kiri_skill, kiri_skill_status};
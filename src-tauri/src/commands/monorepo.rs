@@ -0,0 +1,504 @@
+//! Workspace packages of a monorepo and which of them a change affects,
+//! so the task runner can offer to test only those.
+//!
+//! Packages come from the workspace manifests at the project root: the
+//! `workspaces` of `package.json` or the `packages` of
+//! `pnpm-workspace.yaml` for JavaScript, and `[workspace] members` of
+//! `Cargo.toml` for Rust. A package depends on another when its manifest
+//! names it in any dependency table. Manifests are read line by line, as
+//! elsewhere in kiri, rather than with a full TOML or YAML parser.
+//!
+//! A package is affected when a changed file is inside it, or when it
+//! depends, directly or not, on an affected package.
+
+use git2::{DiffOptions, Repository};
+use glob::Pattern;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use super::bookmarks::relative_key;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageKind {
+    Npm,
+    Cargo,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub kind: PackageKind,
+    /// Directory relative to the project root, `/`-separated.
+    pub path: String,
+    /// Other workspace packages it depends on, by name.
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedPackage {
+    #[serde(flatten)]
+    pub package: WorkspacePackage,
+    /// Files inside the package changed, rather than only a dependency.
+    pub changed: bool,
+    /// The dependency it is affected through, when not changed itself.
+    pub via: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedPackages {
+    pub packages: Vec<AffectedPackage>,
+    pub total_packages: usize,
+    pub changed_files: usize,
+    /// Changed files outside every package, e.g. a root lockfile or CI
+    /// config; when there are any, testing everything may be safer.
+    pub outside_packages: Vec<String>,
+}
+
+/// Items of a `key = [...]` array in `[section]` of a TOML file, which
+/// may span several lines.
+fn toml_string_array(text: &str, section: &str, key: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = "";
+    let mut in_array = false;
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let rest = if in_array {
+            line
+        } else {
+            if line.starts_with('[') {
+                current = line;
+                continue;
+            }
+            let Some((k, v)) = line.split_once('=') else {
+                continue;
+            };
+            if current != section || k.trim() != key {
+                continue;
+            }
+            let Some(v) = v.trim().strip_prefix('[') else {
+                continue;
+            };
+            in_array = true;
+            v
+        };
+        let (rest, closed) = match rest.split_once(']') {
+            Some((rest, _)) => (rest, true),
+            None => (rest, false),
+        };
+        items.extend(
+            rest.split(',')
+                .map(|item| item.trim().trim_matches(['"', '\'']))
+                .filter(|item| !item.is_empty())
+                .map(str::to_string),
+        );
+        if closed {
+            in_array = false;
+        }
+    }
+    items
+}
+
+/// `[package] name` of a Cargo.toml.
+fn cargo_package_name(text: &str) -> Option<String> {
+    let mut section = "";
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            section = line;
+        } else if section == "[package]" {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == "name" {
+                    return Some(value.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Names in the dependency tables of a Cargo.toml, including
+/// target-specific ones and `[dependencies.name]` tables.
+fn cargo_dependency_names(text: &str) -> BTreeSet<String> {
+    const TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+    let mut names = BTreeSet::new();
+    let mut in_table = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_end_matches(']');
+            let parts: Vec<&str> = header.split('.').collect();
+            in_table = false;
+            if let Some(index) = parts.iter().position(|part| TABLES.contains(part)) {
+                match parts.get(index + 1) {
+                    Some(name) => {
+                        names.insert(name.trim_matches('"').to_string());
+                    }
+                    None => in_table = true,
+                }
+            }
+            continue;
+        }
+        if in_table && !line.starts_with('#') {
+            if let Some((key, _)) = line.split_once('=') {
+                let name = key.split('.').next().unwrap_or_default();
+                names.insert(name.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Names in the dependency fields of a package.json.
+fn npm_dependency_names(package: &serde_json::Value) -> BTreeSet<String> {
+    [
+        "dependencies",
+        "devDependencies",
+        "peerDependencies",
+        "optionalDependencies",
+    ]
+    .iter()
+    .filter_map(|field| package[field].as_object())
+    .flat_map(|deps| deps.keys().cloned())
+    .collect()
+}
+
+/// `packages` of a pnpm-workspace.yaml.
+fn pnpm_workspace_patterns(text: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in text.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = line.trim_end() == "packages:";
+            continue;
+        }
+        if let (true, Some(item)) = (in_packages, line.trim().strip_prefix('-')) {
+            patterns.push(item.trim().trim_matches(['"', '\'']).to_string());
+        }
+    }
+    patterns
+}
+
+/// Workspace patterns of the root package.json: an array, or yarn's
+/// `{ "packages": [...] }`.
+fn npm_workspace_patterns(package: &serde_json::Value) -> Vec<String> {
+    let workspaces = &package["workspaces"];
+    workspaces
+        .as_array()
+        .or_else(|| workspaces["packages"].as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|pattern| pattern.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Directories under `root` matching `patterns` that contain `manifest`.
+/// Patterns starting with `!` exclude.
+fn member_dirs(root: &Path, patterns: &[String], manifest: &str) -> Vec<PathBuf> {
+    let (excludes, includes): (Vec<&String>, Vec<&String>) =
+        patterns.iter().partition(|p| p.starts_with('!'));
+    let excludes: Vec<Pattern> = excludes
+        .iter()
+        .filter_map(|p| Pattern::new(p.trim_start_matches('!').trim_end_matches('/')).ok())
+        .collect();
+    let mut dirs = BTreeSet::new();
+    for pattern in includes {
+        let full = root.join(pattern.trim_end_matches('/'));
+        let Ok(paths) = glob::glob(&full.to_string_lossy()) else {
+            continue;
+        };
+        for dir in paths.flatten() {
+            let Ok(rel) = dir.strip_prefix(root) else {
+                continue;
+            };
+            let key = relative_key(rel);
+            if dir.join(manifest).is_file()
+                && !key.split('/').any(|part| part == "node_modules")
+                && !excludes.iter().any(|exclude| exclude.matches(&key))
+            {
+                dirs.insert(dir);
+            }
+        }
+    }
+    dirs.into_iter().collect()
+}
+
+/// Workspace packages of the project at `root`, by path.
+pub fn discover_packages(root: &Path) -> Vec<WorkspacePackage> {
+    let mut found: Vec<(WorkspacePackage, BTreeSet<String>)> = Vec::new();
+
+    let package_json = std::fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok());
+    let mut npm_patterns = package_json
+        .as_ref()
+        .map(npm_workspace_patterns)
+        .unwrap_or_default();
+    if let Ok(text) = std::fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        npm_patterns.extend(pnpm_workspace_patterns(&text));
+    }
+    for dir in member_dirs(root, &npm_patterns, "package.json") {
+        let Some(package) = std::fs::read_to_string(dir.join("package.json"))
+            .ok()
+            .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+        else {
+            continue;
+        };
+        let Some(name) = package["name"].as_str() else {
+            continue;
+        };
+        found.push((
+            package_at(root, &dir, name, PackageKind::Npm),
+            npm_dependency_names(&package),
+        ));
+    }
+
+    if let Ok(text) = std::fs::read_to_string(root.join("Cargo.toml")) {
+        let members = toml_string_array(&text, "[workspace]", "members");
+        let mut excluded = toml_string_array(&text, "[workspace]", "exclude");
+        excluded.iter_mut().for_each(|p| p.insert(0, '!'));
+        let patterns: Vec<String> = members.into_iter().chain(excluded).collect();
+        for dir in member_dirs(root, &patterns, "Cargo.toml") {
+            let Ok(manifest) = std::fs::read_to_string(dir.join("Cargo.toml")) else {
+                continue;
+            };
+            let Some(name) = cargo_package_name(&manifest) else {
+                continue;
+            };
+            found.push((
+                package_at(root, &dir, &name, PackageKind::Cargo),
+                cargo_dependency_names(&manifest),
+            ));
+        }
+    }
+
+    let names: BTreeSet<(PackageKind, String)> = found
+        .iter()
+        .map(|(package, _)| (package.kind, package.name.clone()))
+        .collect();
+    let mut packages: Vec<WorkspacePackage> = found
+        .into_iter()
+        .map(|(mut package, deps)| {
+            package.dependencies = deps
+                .into_iter()
+                .filter(|dep| *dep != package.name && names.contains(&(package.kind, dep.clone())))
+                .collect();
+            package
+        })
+        .collect();
+    packages.sort_by(|a, b| a.path.cmp(&b.path).then(a.kind.cmp(&b.kind)));
+    packages
+}
+
+fn package_at(root: &Path, dir: &Path, name: &str, kind: PackageKind) -> WorkspacePackage {
+    WorkspacePackage {
+        name: name.to_string(),
+        kind,
+        path: relative_key(dir.strip_prefix(root).unwrap_or(dir)),
+        dependencies: Vec::new(),
+    }
+}
+
+/// Paths, relative to the checkout, that differ from `base` (a branch,
+/// tag or commit; their merge base with HEAD is compared) or from HEAD,
+/// counting staged, unstaged and untracked changes.
+pub fn changed_paths(checkout: &Path, base: Option<&str>) -> Result<Vec<String>, String> {
+    let repo = Repository::open(checkout).map_err(|e| e.to_string())?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let base_commit = match (base, &head) {
+        (Some(base), Some(head)) => {
+            let base = repo
+                .revparse_single(base)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| format!("Unknown base {}: {}", base, e))?;
+            let oid = repo
+                .merge_base(base.id(), head.id())
+                .map_err(|e| format!("No common history with {}: {}", base.id(), e))?;
+            Some(repo.find_commit(oid).map_err(|e| e.to_string())?)
+        }
+        (_, head) => head.clone(),
+    };
+    let tree = base_commit
+        .map(|commit| commit.tree())
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let mut opts = DiffOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let diff = repo
+        .diff_tree_to_workdir_with_index(tree.as_ref(), Some(&mut opts))
+        .map_err(|e| e.to_string())?;
+    let mut paths = BTreeSet::new();
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path() {
+                paths.insert(relative_key(path));
+            }
+        }
+    }
+    Ok(paths.into_iter().collect())
+}
+
+/// The packages `changed` (paths relative to the root) affect: those
+/// containing a changed file, then everything depending on them.
+pub fn affected_packages(packages: &[WorkspacePackage], changed: &[String]) -> AffectedPackages {
+    let owner = |path: &str| {
+        packages
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                path.strip_prefix(p.path.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|(_, p)| p.path.len())
+            .map(|(index, _)| index)
+    };
+
+    // index -> the package it is affected through, or None when changed.
+    let mut reached: BTreeMap<usize, Option<String>> = BTreeMap::new();
+    let mut outside_packages = Vec::new();
+    let mut queue = VecDeque::new();
+    for path in changed {
+        match owner(path) {
+            Some(index) => {
+                if reached.insert(index, None).is_none() {
+                    queue.push_back(index);
+                }
+            }
+            None => outside_packages.push(path.clone()),
+        }
+    }
+    while let Some(index) = queue.pop_front() {
+        let dependency = &packages[index];
+        for (dependent, package) in packages.iter().enumerate() {
+            if package.kind == dependency.kind
+                && package.dependencies.contains(&dependency.name)
+                && !reached.contains_key(&dependent)
+            {
+                reached.insert(dependent, Some(dependency.name.clone()));
+                queue.push_back(dependent);
+            }
+        }
+    }
+
+    AffectedPackages {
+        packages: reached
+            .into_iter()
+            .map(|(index, via)| AffectedPackage {
+                package: packages[index].clone(),
+                changed: via.is_none(),
+                via,
+            })
+            .collect(),
+        total_packages: packages.len(),
+        changed_files: changed.len(),
+        outside_packages,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, text: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_parsing() {
+        let cargo = "[workspace]\nmembers = [\n  \"crates/*\", # all\n  \"tools/cli\",\n]\nexclude = [\"crates/old\"]\n";
+        assert_eq!(
+            toml_string_array(cargo, "[workspace]", "members"),
+            vec!["crates/*", "tools/cli"]
+        );
+        assert_eq!(
+            toml_string_array(cargo, "[workspace]", "exclude"),
+            vec!["crates/old"]
+        );
+
+        let member = "[package]\nname = \"app\"\n\n[dependencies]\ncore = { path = \"../core\" }\nserde.workspace = true\n\n[target.'cfg(unix)'.dev-dependencies]\nutil = \"1\"\n\n[build-dependencies.codegen]\npath = \"../codegen\"\n";
+        assert_eq!(cargo_package_name(member).as_deref(), Some("app"));
+        let deps: Vec<String> = cargo_dependency_names(member).into_iter().collect();
+        assert_eq!(deps, vec!["codegen", "core", "serde", "util"]);
+
+        let pnpm = "packages:\n  - 'apps/*'\n  - \"!apps/legacy\"\ncatalog:\n  - nope\n";
+        assert_eq!(
+            pnpm_workspace_patterns(pnpm),
+            vec!["apps/*", "!apps/legacy"]
+        );
+    }
+
+    #[test]
+    fn test_discover_npm_and_cargo_packages() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            "package.json",
+            r#"{"workspaces": ["packages/*", "!packages/skip"]}"#,
+        );
+        write(root, "packages/ui/package.json", r#"{"name": "@acme/ui"}"#);
+        write(
+            root,
+            "packages/web/package.json",
+            r#"{"name": "web", "dependencies": {"@acme/ui": "workspace:*", "react": "18"}}"#,
+        );
+        write(root, "packages/skip/package.json", r#"{"name": "skip"}"#);
+        write(
+            root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        );
+        write(
+            root,
+            "crates/core/Cargo.toml",
+            "[package]\nname = \"core\"\n",
+        );
+
+        let packages = discover_packages(root);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["core", "@acme/ui", "web"]);
+        assert_eq!(packages[2].path, "packages/web");
+        assert_eq!(packages[2].dependencies, vec!["@acme/ui"]);
+    }
+
+    #[test]
+    fn test_affected_follows_dependents() {
+        let package = |name: &str, path: &str, deps: &[&str]| WorkspacePackage {
+            name: name.to_string(),
+            kind: PackageKind::Npm,
+            path: path.to_string(),
+            dependencies: deps.iter().map(|d| d.to_string()).collect(),
+        };
+        let packages = vec![
+            package("core", "packages/core", &[]),
+            package("ui", "packages/ui", &["core"]),
+            package("web", "apps/web", &["ui"]),
+            package("docs", "apps/docs", &[]),
+            package("core-extra", "packages/core-extra", &[]),
+        ];
+        let changed = vec![
+            "packages/core/src/index.ts".to_string(),
+            "pnpm-lock.yaml".to_string(),
+        ];
+        let affected = affected_packages(&packages, &changed);
+        let names: Vec<(&str, bool)> = affected
+            .packages
+            .iter()
+            .map(|a| (a.package.name.as_str(), a.changed))
+            .collect();
+        assert_eq!(names, vec![("core", true), ("ui", false), ("web", false)]);
+        assert_eq!(affected.packages[2].via.as_deref(), Some("ui"));
+        assert_eq!(affected.outside_packages, vec!["pnpm-lock.yaml"]);
+        assert_eq!(affected.total_packages, 5);
+    }
+}
@@ -0,0 +1,26 @@
+//! Tauri command for the affected-package detection of monorepo.rs.
+
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::git_worktree::current_worktree_root;
+use super::latency::run_blocking;
+use super::monorepo::{affected_packages, changed_paths, discover_packages, AffectedPackages};
+
+/// Workspace packages of the checkout `path` is in that its changes
+/// affect: uncommitted ones, plus commits since `base` (a branch, tag or
+/// commit) when given. Lets the task runner offer to test only those.
+#[tauri::command]
+pub async fn get_affected_packages(
+    path: String,
+    base: Option<String>,
+) -> Result<AffectedPackages, KiriError> {
+    let dir = normalize_input_path(&path);
+    run_blocking("get_affected_packages", move || {
+        let root = current_worktree_root(&dir)
+            .ok_or_else(|| format!("{} is not in a git repository", dir.display()))?;
+        let packages = discover_packages(&root);
+        let changed = changed_paths(&root, base.as_deref())?;
+        Ok(affected_packages(&packages, &changed))
+    })
+    .await
+}
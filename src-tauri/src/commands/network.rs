@@ -0,0 +1,487 @@
+//! Proxy and custom CA settings for outbound connections.
+//!
+//! Code host, CI and issue tracker calls build their client through
+//! [`configure_client`]; git and tunnel CLIs get the same settings through
+//! [`configure_command`]. By default the system proxy is used: the
+//! `HTTPS_PROXY` family of variables, else the macOS network settings
+//! (`scutil --proxy`) or the Windows Internet Settings. It can be replaced
+//! with a manual proxy or turned off.
+//!
+//! A CA bundle (PEM) adds roots for TLS-intercepting proxies. HTTP clients
+//! trust it on top of their built-in roots, but git uses it instead of its
+//! own (`GIT_SSL_CAINFO`), so it should be a complete bundle, as corporate
+//! ones usually are. Settings are kept in `~/.kiri/network.json`.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+use super::error::{user_path_error, user_path_io_error};
+use super::file_io::write_file_contents_atomic;
+use super::lock_ext::LockExt;
+
+/// Variables naming a proxy, in order of preference. Both cases are set
+/// for child processes: curl, and so git, only reads `http_proxy` in
+/// lowercase.
+const PROXY_VARS: [&str; 6] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+const NO_PROXY_VARS: [&str; 2] = ["NO_PROXY", "no_proxy"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    #[default]
+    System,
+    Manual,
+    /// Connect directly, even if the system has a proxy.
+    Off,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkSettings {
+    #[serde(default)]
+    pub proxy: ProxyMode,
+    /// Proxy of [`ProxyMode::Manual`], e.g. `http://proxy.corp:8080`.
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// Hosts the manual proxy is bypassed for, comma separated as in
+    /// `NO_PROXY`.
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// PEM file of extra CA certificates.
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+}
+
+/// The proxy connections go through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProxyServer {
+    pub url: String,
+    /// Hosts reached directly, comma separated.
+    pub no_proxy: Option<String>,
+}
+
+/// The settings, and the system proxy they resolved to.
+#[derive(Debug, Default)]
+pub struct Network {
+    file: Option<PathBuf>,
+    settings: NetworkSettings,
+    /// Looked up on first use, since that spawns a process.
+    system_proxy: Option<Option<ProxyServer>>,
+}
+
+pub fn network_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("network.json"))
+}
+
+/// The settings behind every outbound connection.
+pub fn network() -> &'static Mutex<Network> {
+    static NETWORK: OnceLock<Mutex<Network>> = OnceLock::new();
+    NETWORK.get_or_init(|| Mutex::new(Network::new()))
+}
+
+/// `url` with a scheme, if it is an HTTP(S) proxy.
+fn normalize_proxy_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    let with_scheme = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    };
+    let parsed =
+        url::Url::parse(&with_scheme).map_err(|e| format!("Invalid proxy {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!(
+            "Invalid proxy {}: only HTTP(S) proxies are supported",
+            url
+        ));
+    }
+    Ok(with_scheme.trim_end_matches('/').to_string())
+}
+
+/// A bypass pattern in `NO_PROXY` form: `*.corp` becomes `.corp`.
+fn no_proxy_entry(pattern: &str) -> Option<String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() || pattern == "<local>" {
+        return None;
+    }
+    Some(match pattern.strip_prefix("*.") {
+        Some(domain) => format!(".{}", domain),
+        None => pattern.to_string(),
+    })
+}
+
+fn join_no_proxy<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let entries: Vec<String> = patterns.into_iter().filter_map(no_proxy_entry).collect();
+    (!entries.is_empty()).then(|| entries.join(","))
+}
+
+/// The proxy named by the environment, read through `var`.
+fn env_proxy(var: impl Fn(&str) -> Option<String>) -> Option<ProxyServer> {
+    let url = PROXY_VARS
+        .iter()
+        .filter_map(|name| var(name))
+        .find_map(|value| normalize_proxy_url(&value).ok())?;
+    let no_proxy = NO_PROXY_VARS.iter().find_map(|name| var(name));
+    Some(ProxyServer {
+        url,
+        no_proxy: no_proxy.and_then(|list| join_no_proxy(list.split(','))),
+    })
+}
+
+/// The proxy in the output of `scutil --proxy`, HTTPS preferred.
+fn parse_scutil_proxy(output: &str) -> Option<ProxyServer> {
+    let mut values = std::collections::HashMap::new();
+    let mut exceptions = Vec::new();
+    let mut in_exceptions = false;
+    for line in output.lines().map(str::trim) {
+        if line.starts_with("ExceptionsList") {
+            in_exceptions = true;
+            continue;
+        }
+        if in_exceptions {
+            match line.split_once(" : ") {
+                Some((_, pattern)) => exceptions.push(pattern),
+                None => in_exceptions = false,
+            }
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(" : ") {
+            values.insert(key, value);
+        }
+    }
+    let url = ["HTTPS", "HTTP"].iter().find_map(|scheme| {
+        if values.get(format!("{}Enable", scheme).as_str()) != Some(&"1") {
+            return None;
+        }
+        let host = values.get(format!("{}Proxy", scheme).as_str())?;
+        let port = values.get(format!("{}Port", scheme).as_str());
+        let address = match port {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        normalize_proxy_url(&address).ok()
+    })?;
+    Some(ProxyServer {
+        url,
+        no_proxy: join_no_proxy(exceptions),
+    })
+}
+
+/// The proxy in the output of `reg query` on the Internet Settings key.
+/// `ProxyServer` is either one `host:port` or per scheme, as in
+/// `http=host:port;https=host:port`.
+fn parse_windows_proxy(output: &str) -> Option<ProxyServer> {
+    let mut values = std::collections::HashMap::new();
+    for line in output.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(name), Some(kind), Some(value)) = (parts.next(), parts.next(), parts.next()) {
+            if kind.starts_with("REG_") {
+                values.insert(name, value);
+            }
+        }
+    }
+    if values.get("ProxyEnable") != Some(&"0x1") {
+        return None;
+    }
+    let server = values.get("ProxyServer")?;
+    let address = if server.contains('=') {
+        let entries: Vec<(&str, &str)> = server
+            .split(';')
+            .filter_map(|entry| entry.split_once('='))
+            .collect();
+        ["https", "http"]
+            .iter()
+            .find_map(|scheme| entries.iter().find(|(s, _)| s == scheme))
+            .map(|(_, address)| *address)?
+    } else {
+        server
+    };
+    Some(ProxyServer {
+        url: normalize_proxy_url(address).ok()?,
+        no_proxy: values
+            .get("ProxyOverride")
+            .and_then(|list| join_no_proxy(list.split(';'))),
+    })
+}
+
+fn system_proxy() -> Option<ProxyServer> {
+    if let Some(proxy) = env_proxy(|name| std::env::var(name).ok()) {
+        return Some(proxy);
+    }
+    let query = |program: &str, args: &[&str]| {
+        let output = Command::new(program).args(args).output().ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    if cfg!(target_os = "macos") {
+        parse_scutil_proxy(&query("scutil", &["--proxy"])?)
+    } else if cfg!(windows) {
+        const KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings";
+        parse_windows_proxy(&query("reg", &["query", KEY])?)
+    } else {
+        None
+    }
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let settings = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    log::warn!("failed to parse network settings: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            settings,
+            system_proxy: None,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                user_path_io_error("Failed to create the settings folder", parent, e)
+            })?;
+        }
+        let json = serde_json::to_vec_pretty(&self.settings)
+            .map_err(|e| format!("Failed to serialize network settings: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn settings(&self) -> &NetworkSettings {
+        &self.settings
+    }
+
+    /// Check and save `settings`. The CA bundle has to parse, so a typo
+    /// doesn't surface later as every request failing.
+    pub fn set_settings(&mut self, mut settings: NetworkSettings) -> Result<(), String> {
+        settings.proxy_url = settings
+            .proxy_url
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| normalize_proxy_url(&url))
+            .transpose()?;
+        if settings.proxy == ProxyMode::Manual && settings.proxy_url.is_none() {
+            return Err("A manual proxy needs a URL".to_string());
+        }
+        settings.no_proxy = settings
+            .no_proxy
+            .and_then(|list| join_no_proxy(list.split(',')));
+        if let Some(path) = &settings.ca_bundle {
+            read_ca_bundle(path)?;
+        }
+        self.settings = settings;
+        self.system_proxy = None;
+        self.save()
+    }
+
+    /// The proxy to use, if any.
+    pub fn proxy(&mut self) -> Option<ProxyServer> {
+        match self.settings.proxy {
+            ProxyMode::System => self.system_proxy.get_or_insert_with(system_proxy).clone(),
+            ProxyMode::Manual => Some(ProxyServer {
+                url: self.settings.proxy_url.clone()?,
+                no_proxy: self.settings.no_proxy.clone(),
+            }),
+            ProxyMode::Off => None,
+        }
+    }
+}
+
+fn read_ca_bundle(path: &std::path::Path) -> Result<Vec<reqwest::Certificate>, String> {
+    let pem = std::fs::read(path)
+        .map_err(|e| user_path_io_error("Failed to read the CA bundle", path, e))?;
+    let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+        .map_err(|e| user_path_io_error("Failed to parse the CA bundle", path, e))?;
+    if certificates.is_empty() {
+        return Err(user_path_error("No certificates in the CA bundle", path));
+    }
+    Ok(certificates)
+}
+
+/// Apply the proxy and CA bundle to an HTTP client.
+pub fn configure_client(
+    mut builder: reqwest::ClientBuilder,
+) -> Result<reqwest::ClientBuilder, String> {
+    let (mode, proxy, ca_bundle) = {
+        let mut network = network().lock_recover();
+        let proxy = network.proxy();
+        let settings = network.settings();
+        (settings.proxy, proxy, settings.ca_bundle.clone())
+    };
+    if let Some(proxy) = proxy {
+        let no_proxy = proxy
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string);
+        let proxy = reqwest::Proxy::all(&proxy.url)
+            .map_err(|e| format!("Invalid proxy {}: {}", proxy.url, e))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    } else if mode == ProxyMode::Off {
+        builder = builder.no_proxy();
+    }
+    if let Some(path) = ca_bundle {
+        for certificate in read_ca_bundle(&path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    Ok(builder)
+}
+
+/// Apply the proxy and CA bundle to a CLI that connects out, such as git.
+pub fn configure_command(command: &mut Command) {
+    let (proxy, ca_bundle) = {
+        let mut network = network().lock_recover();
+        (network.proxy(), network.settings().ca_bundle.clone())
+    };
+    for name in PROXY_VARS.iter().chain(&NO_PROXY_VARS) {
+        command.env_remove(name);
+    }
+    if let Some(proxy) = proxy {
+        for name in &PROXY_VARS[..4] {
+            command.env(name, &proxy.url);
+        }
+        if let Some(no_proxy) = &proxy.no_proxy {
+            for name in NO_PROXY_VARS {
+                command.env(name, no_proxy);
+            }
+        }
+    }
+    if let Some(path) = ca_bundle {
+        command.env("GIT_SSL_CAINFO", &path);
+        // Node (localtunnel) adds these to its roots rather than replacing
+        // them.
+        command.env("NODE_EXTRA_CA_CERTS", &path);
+    }
+}
+
+/// `git` for commands that reach a remote, such as fetch and push, with
+/// the proxy and CA bundle applied.
+pub fn remote_git_command() -> Command {
+    let mut command = Command::new("git");
+    configure_command(&mut command);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_system_proxy_sources() {
+        let env = |name: &str| match name {
+            "https_proxy" => Some("proxy.corp:3128".to_string()),
+            "NO_PROXY" => Some("localhost, *.corp".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            env_proxy(env),
+            Some(ProxyServer {
+                url: "http://proxy.corp:3128".to_string(),
+                no_proxy: Some("localhost,.corp".to_string()),
+            })
+        );
+        assert_eq!(env_proxy(|_| None), None);
+        assert_eq!(env_proxy(|_| Some("socks5://proxy:1080".to_string())), None);
+
+        let scutil = "<dictionary> {
+  ExceptionsList : <array> {
+    0 : *.local
+    1 : 169.254/16
+  }
+  FTPPassive : 1
+  HTTPEnable : 1
+  HTTPPort : 8080
+  HTTPProxy : web.corp
+  HTTPSEnable : 1
+  HTTPSPort : 8443
+  HTTPSProxy : secure.corp
+}
+";
+        assert_eq!(
+            parse_scutil_proxy(scutil),
+            Some(ProxyServer {
+                url: "http://secure.corp:8443".to_string(),
+                no_proxy: Some(".local,169.254/16".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_scutil_proxy("<dictionary> {\n  HTTPEnable : 0\n}\n"),
+            None
+        );
+
+        let reg = r"
+HKEY_CURRENT_USER\Software\Microsoft\Windows\CurrentVersion\Internet Settings
+    ProxyEnable    REG_DWORD    0x1
+    ProxyServer    REG_SZ    http=web.corp:80;https=secure.corp:443
+    ProxyOverride    REG_SZ    *.corp;<local>
+";
+        assert_eq!(
+            parse_windows_proxy(reg),
+            Some(ProxyServer {
+                url: "http://secure.corp:443".to_string(),
+                no_proxy: Some(".corp".to_string()),
+            })
+        );
+        assert_eq!(parse_windows_proxy(&reg.replace("0x1", "0x0")), None);
+    }
+
+    #[test]
+    fn test_settings_validate_and_persist() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("network.json");
+        let mut network = Network::load(path.clone());
+        assert_eq!(network.settings(), &NetworkSettings::default());
+
+        let manual = |url: &str| NetworkSettings {
+            proxy: ProxyMode::Manual,
+            proxy_url: Some(url.to_string()),
+            no_proxy: Some("localhost,*.internal".to_string()),
+            ca_bundle: None,
+        };
+        assert!(network.set_settings(manual("")).is_err());
+        assert!(network.set_settings(manual("socks5://proxy:1080")).is_err());
+        let missing_ca = NetworkSettings {
+            ca_bundle: Some(dir.path().join("missing.pem")),
+            ..manual("proxy:8080")
+        };
+        assert!(network.set_settings(missing_ca).is_err());
+
+        network.set_settings(manual("proxy:8080")).unwrap();
+        let expected = ProxyServer {
+            url: "http://proxy:8080".to_string(),
+            no_proxy: Some("localhost,.internal".to_string()),
+        };
+        assert_eq!(network.proxy(), Some(expected.clone()));
+
+        let mut reloaded = Network::load(path);
+        assert_eq!(reloaded.proxy(), Some(expected));
+        let off = NetworkSettings {
+            proxy: ProxyMode::Off,
+            ..reloaded.settings().clone()
+        };
+        reloaded.set_settings(off).unwrap();
+        assert_eq!(reloaded.proxy(), None);
+    }
+}
@@ -0,0 +1,42 @@
+//! Tauri commands for proxy and CA settings; see network.rs.
+
+use serde::Serialize;
+
+use super::error::KiriError;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::network::{network, network_file_path, Network, NetworkSettings, ProxyServer};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkStatus {
+    pub settings: NetworkSettings,
+    /// The proxy in use, e.g. the one found in the system settings.
+    pub proxy: Option<ProxyServer>,
+}
+
+/// Load `~/.kiri/network.json`.
+pub fn load_network_settings() {
+    if let Some(path) = network_file_path() {
+        *network().lock_recover() = Network::load(path);
+    }
+}
+
+#[tauri::command]
+pub async fn get_network_settings() -> Result<NetworkStatus, KiriError> {
+    run_blocking("get_network_settings", || {
+        let mut network = network().lock_recover();
+        Ok(NetworkStatus {
+            proxy: network.proxy(),
+            settings: network.settings().clone(),
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_network_settings(settings: NetworkSettings) -> Result<(), KiriError> {
+    run_blocking("set_network_settings", move || {
+        network().lock_recover().set_settings(settings)
+    })
+    .await
+}
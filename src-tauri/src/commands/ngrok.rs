@@ -0,0 +1,143 @@
+//! ngrok tunnels; see tunnel.rs.
+//!
+//! `ngrok http` is run with its log on stdout in logfmt instead of its
+//! terminal UI. It needs an authtoken, either added with `ngrok config
+//! add-authtoken` or saved as the secret `tunnel`/`ngrok`, which is passed
+//! in `NGROK_AUTHTOKEN`; a reserved domain can be given per tunnel.
+
+use serde::{Deserialize, Serialize};
+
+use super::tunnel::{valid_hostname, Capabilities, LogEvent, ProviderKind, TunnelProvider};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ngrok {
+    /// Reserved domain, e.g. `app.ngrok.dev`; a random one if `None`.
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+/// The value of `key` in a logfmt line, unquoted.
+fn logfmt_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(&format!("{}=", key))? + key.len() + 1;
+    let rest = &line[start..];
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next(),
+        None => rest.split_whitespace().next(),
+    }
+}
+
+impl TunnelProvider for Ngrok {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Ngrok
+    }
+
+    fn label(&self) -> &'static str {
+        "ngrok"
+    }
+
+    fn program(&self) -> &'static str {
+        "ngrok"
+    }
+
+    fn install_hint(&self) -> &'static str {
+        "brew install ngrok"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            requires_account: true,
+            custom_domain: true,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match &self.domain {
+            Some(domain) if !valid_hostname(domain) => Err(format!("Invalid domain: {}", domain)),
+            _ => Ok(()),
+        }
+    }
+
+    fn args(&self, port: u16) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            "http".into(),
+            port.to_string(),
+            "--log".into(),
+            "stdout".into(),
+            "--log-format".into(),
+            "logfmt".into(),
+        ];
+        if let Some(domain) = &self.domain {
+            args.push(format!("--domain={}", domain));
+        }
+        args
+    }
+
+    fn credential_env(&self) -> Option<&'static str> {
+        Some("NGROK_AUTHTOKEN")
+    }
+
+    fn log_event(&self, line: &str) -> Option<LogEvent> {
+        if let Some(message) = line.strip_prefix("ERROR:") {
+            return Some(LogEvent::Error(message.trim().to_string()));
+        }
+        match logfmt_value(line, "lvl") {
+            Some("eror" | "crit") => {
+                let message = logfmt_value(line, "err").or_else(|| logfmt_value(line, "msg"));
+                Some(LogEvent::Error(message.unwrap_or(line).to_string()))
+            }
+            _ if logfmt_value(line, "msg") == Some("started tunnel") => {
+                let url = logfmt_value(line, "url").map(str::to_string);
+                Some(LogEvent::Ready { url })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_and_log_events() {
+        let ngrok = Ngrok {
+            domain: Some("app.ngrok.dev".to_string()),
+        };
+        assert!(ngrok.validate().is_ok());
+        assert_eq!(
+            ngrok.args(3000),
+            vec![
+                "http",
+                "3000",
+                "--log",
+                "stdout",
+                "--log-format",
+                "logfmt",
+                "--domain=app.ngrok.dev"
+            ]
+        );
+        let bad = Ngrok {
+            domain: Some("--config=x".to_string()),
+        };
+        assert!(bad.validate().is_err());
+
+        let line = "t=2024-05-01T10:00:00+0000 lvl=info msg=\"started tunnel\" obj=tunnels name=command_line addr=http://localhost:3000 url=https://ab12.ngrok-free.app";
+        assert_eq!(
+            ngrok.log_event(line),
+            Some(LogEvent::Ready {
+                url: Some("https://ab12.ngrok-free.app".to_string())
+            })
+        );
+        let line = "t=2024-05-01T10:00:00+0000 lvl=eror msg=\"session closing\" obj=tunnels.session err=\"authentication failed: invalid authtoken\"";
+        assert_eq!(
+            ngrok.log_event(line),
+            Some(LogEvent::Error(
+                "authentication failed: invalid authtoken".to_string()
+            ))
+        );
+        assert_eq!(
+            ngrok.log_event("t=2024 lvl=info msg=\"open config file\""),
+            None
+        );
+    }
+}
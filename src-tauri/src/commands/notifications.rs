@@ -0,0 +1,442 @@
+//! Rules deciding which background events become system notifications.
+//!
+//! Subsystems report a [`NotificationEvent`] (a task finished, tests
+//! failed, an agent is waiting for input, a dev server found its port
+//! taken, a new release is out) and [`Notifications::decide`] applies the user's rule for that
+//! kind of event: whether it is on, its quiet hours, and whether it plays
+//! a sound. The same event repeating within [`REPEAT_WINDOW`] is shown
+//! once. Rules are saved in `~/.kiri/notifications.json`; showing the
+//! notification is up to notifications_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::file_io::write_file_contents_atomic;
+use super::i18n::Message;
+
+/// An event with the same key as one shown this recently is dropped.
+pub const REPEAT_WINDOW: Duration = Duration::from_secs(30);
+
+lazy_static::lazy_static! {
+    static ref PORT_IN_USE: regex::Regex = regex::Regex::new(
+        r"(?i)EADDRINUSE|address already in use|port \d+ is (?:already )?in use"
+    )
+    .expect("pattern compiles");
+    static ref PORT_NUMBER: regex::Regex =
+        regex::Regex::new(r"(?i)(?:port |:)(\d{2,5})\b").expect("pattern compiles");
+}
+
+pub fn notifications_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("notifications.json"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    TaskFinished,
+    TestsFailed,
+    AgentWaiting,
+    PortConflict,
+    UpdateAvailable,
+}
+
+impl RuleKind {
+    pub const ALL: [RuleKind; 5] = [
+        RuleKind::TaskFinished,
+        RuleKind::TestsFailed,
+        RuleKind::AgentWaiting,
+        RuleKind::PortConflict,
+        RuleKind::UpdateAvailable,
+    ];
+}
+
+/// Minutes after local midnight, `start` inclusive and `end` exclusive.
+/// A range with `end` before `start` runs past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl QuietHours {
+    pub fn contains(&self, minute: u16) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub enabled: bool,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub sound: bool,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quiet_hours: None,
+            sound: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// A task run exited on its own, e.g. a worktree's setup command.
+    TaskFinished {
+        task: String,
+        dir: String,
+        code: u32,
+    },
+    TestsFailed {
+        framework: String,
+        dir: String,
+        failed: usize,
+    },
+    AgentWaiting {
+        agent: String,
+        worktree: String,
+    },
+    /// A task's server could not listen because its port is taken.
+    PortConflict {
+        task: String,
+        dir: String,
+        port: Option<u16>,
+    },
+    /// A newer release is out on the user's update channel.
+    UpdateAvailable {
+        version: String,
+        channel: String,
+    },
+}
+
+fn dir_name(dir: &str) -> &str {
+    Path::new(dir)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(dir)
+}
+
+impl NotificationEvent {
+    pub fn kind(&self) -> RuleKind {
+        match self {
+            NotificationEvent::TaskFinished { .. } => RuleKind::TaskFinished,
+            NotificationEvent::TestsFailed { .. } => RuleKind::TestsFailed,
+            NotificationEvent::AgentWaiting { .. } => RuleKind::AgentWaiting,
+            NotificationEvent::PortConflict { .. } => RuleKind::PortConflict,
+            NotificationEvent::UpdateAvailable { .. } => RuleKind::UpdateAvailable,
+        }
+    }
+
+    /// Identifies repeats of the same event.
+    fn key(&self) -> String {
+        match self {
+            NotificationEvent::TaskFinished { task, dir, .. }
+            | NotificationEvent::PortConflict { task, dir, .. } => {
+                format!("{:?}:{}:{}", self.kind(), dir, task)
+            }
+            NotificationEvent::TestsFailed { dir, .. } => format!("{:?}:{}", self.kind(), dir),
+            NotificationEvent::AgentWaiting { worktree, .. } => {
+                format!("{:?}:{}", self.kind(), worktree)
+            }
+            NotificationEvent::UpdateAvailable { version, .. } => {
+                format!("{:?}:{}", self.kind(), version)
+            }
+        }
+    }
+
+    fn title_and_body(&self) -> (Message, Message) {
+        match self {
+            NotificationEvent::TaskFinished { task, dir, code } => {
+                let title = if *code == 0 {
+                    "notification.task_finished.title"
+                } else {
+                    "notification.task_failed.title"
+                };
+                (
+                    Message::new(title).with("task", task),
+                    Message::new("notification.task_finished.body")
+                        .with("code", code)
+                        .with("dir", dir_name(dir)),
+                )
+            }
+            NotificationEvent::TestsFailed {
+                framework,
+                dir,
+                failed,
+            } => (
+                Message::new("notification.tests_failed.title").with("failed", failed),
+                Message::new("notification.tests_failed.body")
+                    .with("framework", framework)
+                    .with("dir", dir_name(dir)),
+            ),
+            NotificationEvent::AgentWaiting { agent, worktree } => (
+                Message::new("notification.agent_waiting.title").with("agent", agent),
+                Message::new("notification.agent_waiting.body").with("dir", dir_name(worktree)),
+            ),
+            NotificationEvent::PortConflict { task, dir, port } => (
+                match port {
+                    Some(port) => {
+                        Message::new("notification.port_conflict.title").with("port", port)
+                    }
+                    None => Message::new("notification.port_conflict_unknown.title"),
+                },
+                Message::new("notification.port_conflict.body")
+                    .with("task", task)
+                    .with("dir", dir_name(dir)),
+            ),
+            NotificationEvent::UpdateAvailable { version, channel } => (
+                Message::new("notification.update_available.title").with("version", version),
+                Message::new("notification.update_available.body").with("channel", channel),
+            ),
+        }
+    }
+}
+
+/// The port a task's output says is taken: `Some(None)` when the output
+/// reports a conflict without naming the port.
+pub fn port_conflict(output: &str) -> Option<Option<u16>> {
+    let line = output.lines().find(|line| PORT_IN_USE.is_match(line))?;
+    Some(
+        PORT_NUMBER
+            .captures(line)
+            .and_then(|caps| caps[1].parse().ok()),
+    )
+}
+
+/// What to show; the texts are rendered in the user's language when shown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    pub title: Message,
+    pub body: Message,
+    pub sound: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SettingsFile {
+    #[serde(default)]
+    rules: BTreeMap<RuleKind, Rule>,
+}
+
+#[derive(Debug, Default)]
+pub struct Notifications {
+    file: Option<PathBuf>,
+    rules: BTreeMap<RuleKind, Rule>,
+    last_shown: HashMap<String, Instant>,
+}
+
+pub type NotificationsState = Arc<Mutex<Notifications>>;
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let rules = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<SettingsFile>(&contents) {
+                    Ok(file) => Some(file.rules),
+                    Err(e) => {
+                        log::warn!("failed to parse notification rules: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            rules,
+            last_shown: HashMap::new(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = SettingsFile {
+            rules: self.rules.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)
+            .map_err(|e| format!("Failed to serialize notification rules: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn rule(&self, kind: RuleKind) -> Rule {
+        self.rules.get(&kind).cloned().unwrap_or_default()
+    }
+
+    /// Every rule, defaults included.
+    pub fn rules(&self) -> BTreeMap<RuleKind, Rule> {
+        RuleKind::ALL
+            .into_iter()
+            .map(|kind| (kind, self.rule(kind)))
+            .collect()
+    }
+
+    pub fn set_rule(&mut self, kind: RuleKind, rule: Rule) -> Result<(), String> {
+        if let Some(quiet) = rule.quiet_hours {
+            if quiet.start >= 24 * 60 || quiet.end >= 24 * 60 {
+                return Err("Quiet hours must be minutes after midnight, below 1440".to_string());
+            }
+        }
+        if rule == Rule::default() {
+            self.rules.remove(&kind);
+        } else {
+            self.rules.insert(kind, rule);
+        }
+        self.save()
+    }
+
+    /// The notification to show for `event`, if its rule lets it through.
+    /// `minute` is the local time of day; quiet hours are skipped when it
+    /// is unknown.
+    pub fn decide(
+        &mut self,
+        event: &NotificationEvent,
+        minute: Option<u16>,
+        now: Instant,
+    ) -> Option<Notification> {
+        let rule = self.rule(event.kind());
+        if !rule.enabled {
+            return None;
+        }
+        if let (Some(quiet), Some(minute)) = (rule.quiet_hours, minute) {
+            if quiet.contains(minute) {
+                return None;
+            }
+        }
+        self.last_shown
+            .retain(|_, shown| now.saturating_duration_since(*shown) < REPEAT_WINDOW);
+        let key = event.key();
+        if self.last_shown.contains_key(&key) {
+            return None;
+        }
+        self.last_shown.insert(key, now);
+        let (title, body) = event.title_and_body();
+        Some(Notification {
+            title,
+            body,
+            sound: rule.sound,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::i18n::Locale;
+    use tempfile::TempDir;
+
+    fn agent_waiting(worktree: &str) -> NotificationEvent {
+        NotificationEvent::AgentWaiting {
+            agent: "Codex".into(),
+            worktree: worktree.into(),
+        }
+    }
+
+    #[test]
+    fn test_rules_quiet_hours_and_repeats() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notifications.json");
+        let mut notifications = Notifications::load(path.clone());
+        let now = Instant::now();
+
+        let shown = notifications
+            .decide(&agent_waiting("/w/feature"), Some(600), now)
+            .unwrap();
+        assert_eq!(shown.title.text_in(Locale::En), "Codex needs input");
+        assert_eq!(shown.body.text_in(Locale::En), "Waiting in feature");
+        assert_eq!(shown.body.text_in(Locale::Ja), "feature で待機中");
+        assert!(notifications
+            .decide(&agent_waiting("/w/feature"), Some(600), now)
+            .is_none());
+        assert!(notifications
+            .decide(&agent_waiting("/w/feature"), Some(600), now + REPEAT_WINDOW)
+            .is_some());
+        assert!(notifications
+            .decide(&agent_waiting("/w/other"), Some(600), now)
+            .is_some());
+
+        // 22:00 to 07:00.
+        let night = Rule {
+            quiet_hours: Some(QuietHours {
+                start: 22 * 60,
+                end: 7 * 60,
+            }),
+            ..Rule::default()
+        };
+        notifications
+            .set_rule(RuleKind::TestsFailed, night.clone())
+            .unwrap();
+        let failed = NotificationEvent::TestsFailed {
+            framework: "vitest".into(),
+            dir: "/w/app".into(),
+            failed: 3,
+        };
+        assert!(notifications.decide(&failed, Some(23 * 60), now).is_none());
+        assert!(notifications.decide(&failed, Some(60), now).is_none());
+        assert!(notifications.decide(&failed, Some(12 * 60), now).is_some());
+
+        let off = Rule {
+            enabled: false,
+            ..Rule::default()
+        };
+        notifications.set_rule(RuleKind::PortConflict, off).unwrap();
+        let conflict = NotificationEvent::PortConflict {
+            task: "dev".into(),
+            dir: "/w/app".into(),
+            port: Some(3000),
+        };
+        assert!(notifications.decide(&conflict, None, now).is_none());
+        let too_late = Rule {
+            quiet_hours: Some(QuietHours {
+                start: 0,
+                end: 24 * 60,
+            }),
+            ..Rule::default()
+        };
+        assert!(notifications
+            .set_rule(RuleKind::TaskFinished, too_late)
+            .is_err());
+
+        let reloaded = Notifications::load(path);
+        assert_eq!(reloaded.rule(RuleKind::TestsFailed), night);
+        assert!(!reloaded.rule(RuleKind::PortConflict).enabled);
+        assert!(reloaded.rule(RuleKind::AgentWaiting).enabled);
+    }
+
+    #[test]
+    fn test_port_conflict() {
+        assert_eq!(
+            port_conflict("Error: listen EADDRINUSE: address already in use :::3000\n"),
+            Some(Some(3000))
+        );
+        assert_eq!(
+            port_conflict("Port 5173 is in use, trying another one..."),
+            Some(Some(5173))
+        );
+        assert_eq!(
+            port_conflict("OSError: [Errno 48] Address already in use"),
+            Some(None)
+        );
+        assert_eq!(port_conflict("Listening on http://localhost:3000"), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Showing notifications for background events; the rules are in
+//! notifications.rs.
+//!
+//! Rust subsystems call [`notify`] when something the user may want to
+//! hear about happens; the frontend reports its own events through
+//! [`notify_event`], so every notification passes the same rules.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use super::error::KiriError;
+use super::lock_ext::LockExt;
+use super::notifications::{
+    notifications_file_path, NotificationEvent, Notifications, NotificationsState, Rule, RuleKind,
+};
+
+/// Load `~/.kiri/notifications.json` into [`NotificationsState`].
+pub fn load_notification_rules(app: &AppHandle) {
+    let Some(path) = notifications_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<NotificationsState>() {
+        *state.lock_recover() = Notifications::load(path);
+    }
+}
+
+/// Minutes since local midnight, for quiet hours. Without a time zone
+/// database at hand this asks `date`, which knows the user's zone.
+fn local_minute_of_day() -> Option<u16> {
+    #[cfg(unix)]
+    {
+        let output = std::process::Command::new("date")
+            .arg("+%H %M")
+            .output()
+            .ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut parts = text.split_whitespace().map(|part| part.parse::<u16>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(hour)), Some(Ok(minute))) => Some(hour * 60 + minute),
+            _ => None,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Show a system notification for `event` if its rule allows it. Returns
+/// whether one was shown.
+pub fn notify(app: &AppHandle, event: NotificationEvent) -> bool {
+    let Some(state) = app.try_state::<NotificationsState>() else {
+        return false;
+    };
+    let minute = local_minute_of_day();
+    let Some(notification) = state.lock_recover().decide(&event, minute, Instant::now()) else {
+        return false;
+    };
+    let mut builder = app
+        .notification()
+        .builder()
+        .title(notification.title.text())
+        .body(notification.body.text());
+    if notification.sound {
+        builder = builder.sound("Ping");
+    }
+    if let Err(e) = builder.show() {
+        log::warn!("failed to show notification: {}", e);
+        return false;
+    }
+    true
+}
+
+/// Every notification rule, defaults included.
+#[tauri::command]
+pub fn get_notification_rules(
+    state: tauri::State<'_, NotificationsState>,
+) -> BTreeMap<RuleKind, Rule> {
+    state.lock_recover().rules()
+}
+
+#[tauri::command]
+pub fn set_notification_rule(
+    state: tauri::State<'_, NotificationsState>,
+    kind: RuleKind,
+    rule: Rule,
+) -> Result<(), KiriError> {
+    state
+        .lock_recover()
+        .set_rule(kind, rule)
+        .map_err(KiriError::from)
+}
+
+/// Notify about an event the frontend noticed. Returns whether a
+/// notification was shown.
+#[tauri::command]
+pub fn notify_event(app: AppHandle, event: NotificationEvent) -> bool {
+    notify(&app, event)
+}
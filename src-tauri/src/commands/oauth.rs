@@ -0,0 +1,445 @@
+//! OAuth authorization-code sign-in through a loopback redirect, for
+//! GitHub and GitLab.
+//!
+//! The browser is sent to the provider's authorize page with a
+//! `redirect_uri` of `http://127.0.0.1:<port>/callback`, answered by a
+//! listener that only lives until the redirect arrives or
+//! [`CALLBACK_TIMEOUT`] passes. The `state` parameter is a fresh random
+//! value that the redirect has to bring back, and the code is bound to the
+//! attempt with PKCE (S256). The listener takes the first free port of
+//! [`CALLBACK_PORTS`], so OAuth apps that need an exact redirect URI
+//! (GitLab) can register one, and otherwise any port the system offers,
+//! which GitHub accepts for loopback redirects. Ports in the range
+//! reserved for worktree stacks (docker.rs) are never used.
+//!
+//! Client ids come from `KIRI_GITHUB_CLIENT_ID` and `KIRI_GITLAB_CLIENT_ID`
+//! (GitHub also needs `KIRI_GITHUB_CLIENT_SECRET`), read at runtime or at
+//! build time. The command lives in oauth_commands.rs.
+
+use base64::Engine;
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::code_host::{http_client, send_json, HostKind};
+use super::docker::{block_base, PORT_BLOCKS, PORT_RANGE_START};
+use super::error::{ErrorCode, KiriError};
+use super::github;
+
+pub const CALLBACK_PATH: &str = "/callback";
+
+/// Ports tried before any free one, in order.
+pub const CALLBACK_PORTS: [u16; 3] = [47651, 47652, 47653];
+
+/// How long the user has to approve in the browser.
+pub const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often the listener checks for a connection, a timeout or
+/// cancellation.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// System-assigned ports tried before giving up, when they keep landing
+/// in the reserved range.
+const EPHEMERAL_ATTEMPTS: usize = 16;
+
+/// Longest request head read from the browser.
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// Where and as which app to sign in.
+#[derive(Debug, Clone)]
+pub struct OAuthProvider {
+    pub kind: HostKind,
+    /// Instance the token is for, e.g. `gitlab.com`.
+    pub host: String,
+    authorize_url: String,
+    token_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    scope: &'static str,
+}
+
+/// Runtime or build-time value of a sign-in setting.
+fn setting(runtime: &str, built: Option<&'static str>) -> Option<String> {
+    std::env::var(runtime)
+        .ok()
+        .or_else(|| built.map(str::to_string))
+        .filter(|value| !value.is_empty())
+}
+
+impl OAuthProvider {
+    /// The provider for `kind`; `host` picks a self-hosted GitLab.
+    pub fn new(kind: HostKind, host: Option<&str>) -> Result<Self, String> {
+        let not_configured = || format!("{} sign-in is not configured in this build", kind.label());
+        match kind {
+            HostKind::GitHub => Ok(Self {
+                kind,
+                host: "github.com".to_string(),
+                authorize_url: "https://github.com/login/oauth/authorize".to_string(),
+                token_url: "https://github.com/login/oauth/access_token".to_string(),
+                client_id: github::client_id().ok_or_else(not_configured)?,
+                client_secret: Some(
+                    setting(
+                        "KIRI_GITHUB_CLIENT_SECRET",
+                        option_env!("KIRI_GITHUB_CLIENT_SECRET"),
+                    )
+                    .ok_or_else(not_configured)?,
+                ),
+                scope: "repo",
+            }),
+            HostKind::GitLab => {
+                let host = host.map(str::trim).filter(|h| !h.is_empty());
+                let host = host.unwrap_or("gitlab.com").trim_end_matches('/');
+                if host.contains(['/', ':', '?', '#', ' ']) {
+                    return Err(format!("Invalid GitLab host: {}", host));
+                }
+                Ok(Self {
+                    kind,
+                    host: host.to_string(),
+                    authorize_url: format!("https://{}/oauth/authorize", host),
+                    token_url: format!("https://{}/oauth/token", host),
+                    client_id: setting(
+                        "KIRI_GITLAB_CLIENT_ID",
+                        option_env!("KIRI_GITLAB_CLIENT_ID"),
+                    )
+                    .ok_or_else(not_configured)?,
+                    client_secret: None,
+                    scope: "api",
+                })
+            }
+            HostKind::Bitbucket => Err("Bitbucket signs in with an app password".to_string()),
+        }
+    }
+
+    /// The page to send the browser to.
+    pub fn authorize_url(&self, attempt: &Attempt, redirect_uri: &str) -> String {
+        let challenge = attempt.challenge();
+        let query = [
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", self.scope),
+            ("state", &attempt.state),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ];
+        let query: Vec<String> = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+            .collect();
+        format!("{}?{}", self.authorize_url, query.join("&"))
+    }
+
+    /// Trade the code from the callback for an access token.
+    pub async fn exchange_code(
+        &self,
+        attempt: &Attempt,
+        code: &str,
+        redirect_uri: &str,
+    ) -> Result<String, String> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("client_id", self.client_id.as_str()),
+            ("code", code),
+            ("code_verifier", attempt.verifier.as_str()),
+            ("redirect_uri", redirect_uri),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let request = http_client()?
+            .post(&self.token_url)
+            .header("Accept", "application/json")
+            .form(&form);
+        // GitHub reports errors with status 200.
+        let token: TokenResponse = send_json(self.kind, request).await?;
+        match token.access_token.filter(|t| !t.is_empty()) {
+            Some(token) => Ok(token),
+            None => Err(format!(
+                "{} sign-in failed: {}",
+                self.kind.label(),
+                token
+                    .error_description
+                    .or(token.error)
+                    .unwrap_or_else(|| "no token in response".to_string())
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// The secrets of one sign-in attempt.
+pub struct Attempt {
+    pub state: String,
+    /// PKCE code verifier
+    pub verifier: String,
+}
+
+impl Attempt {
+    pub fn new() -> Self {
+        let random = || uuid::Uuid::new_v4().simple().to_string();
+        Self {
+            state: random(),
+            verifier: format!("{}{}", random(), random()),
+        }
+    }
+
+    /// The S256 PKCE challenge of the verifier.
+    pub fn challenge(&self) -> String {
+        pkce_challenge(&self.verifier)
+    }
+}
+
+impl Default for Attempt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn pkce_challenge(verifier: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, verifier.as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(digest.as_ref())
+}
+
+/// Whether `port` belongs to the blocks reserved for worktree stacks.
+pub fn is_reserved_port(port: u16) -> bool {
+    (PORT_RANGE_START..block_base(PORT_BLOCKS)).contains(&port)
+}
+
+/// A loopback listener for the callback, on the first free port of
+/// [`CALLBACK_PORTS`] or else one the system picks outside the reserved
+/// range.
+pub fn bind_callback_listener() -> Result<TcpListener, String> {
+    for port in CALLBACK_PORTS {
+        if let Ok(listener) = TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+            return Ok(listener);
+        }
+    }
+    // Held until the loop ends, so the system doesn't offer them again.
+    let mut rejected = Vec::new();
+    for _ in 0..EPHEMERAL_ATTEMPTS {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .map_err(|e| format!("Failed to listen for the sign-in callback: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to listen for the sign-in callback: {}", e))?
+            .port();
+        if !is_reserved_port(port) {
+            return Ok(listener);
+        }
+        rejected.push(listener);
+    }
+    Err("No free port for the sign-in callback".to_string())
+}
+
+pub fn redirect_uri(listener: &TcpListener) -> Result<String, String> {
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read callback address: {}", e))?
+        .port();
+    Ok(format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH))
+}
+
+/// What a request to the listener carried.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Callback {
+    /// Not the callback path, e.g. `/favicon.ico`
+    Other,
+    /// The callback, but not for this attempt
+    WrongState,
+    Code(String),
+    /// The provider reported an error, e.g. the user denied access
+    Error(String),
+}
+
+/// Interpret the request target `target` of a request to the listener.
+pub fn parse_callback(target: &str, state: &str) -> Callback {
+    let Ok(url) = url::Url::parse(&format!("http://127.0.0.1{}", target)) else {
+        return Callback::Other;
+    };
+    if url.path() != CALLBACK_PATH {
+        return Callback::Other;
+    }
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if param("state").as_deref() != Some(state) {
+        return Callback::WrongState;
+    }
+    if let Some(error) = param("error") {
+        return Callback::Error(param("error_description").unwrap_or(error));
+    }
+    match param("code").filter(|code| !code.is_empty()) {
+        Some(code) => Callback::Code(code),
+        None => Callback::Error("no code in callback".to_string()),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Read one request from `stream`, answer it, and return what it carried.
+fn answer(mut stream: TcpStream, state: &str) -> Callback {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while head.len() < MAX_REQUEST_HEAD && !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let callback = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(target)) => parse_callback(target, state),
+        _ => Callback::Other,
+    };
+    let (status, message) = match &callback {
+        Callback::Code(_) => ("200 OK", "Signed in. You can close this tab.".to_string()),
+        Callback::Error(error) => ("200 OK", format!("Sign-in failed: {}", error)),
+        Callback::WrongState => ("400 Bad Request", "Unknown sign-in attempt.".to_string()),
+        Callback::Other => ("404 Not Found", "Not found.".to_string()),
+    };
+    let body = format!(
+        "<!doctype html><meta charset=\"utf-8\"><title>kiri</title><p>{}</p>",
+        escape_html(&message)
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+    callback
+}
+
+/// Serve `listener` until the callback for `state` arrives, and return its
+/// code. Requests for other attempts are turned away without ending the
+/// wait.
+pub fn wait_for_callback(
+    listener: TcpListener,
+    state: &str,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<String, KiriError> {
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to listen for the sign-in callback: {}", e))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(KiriError::new(
+                ErrorCode::Cancelled,
+                "Sign-in was cancelled",
+            ));
+        }
+        if Instant::now() >= deadline {
+            return Err(KiriError::new(ErrorCode::Timeout, "Sign-in timed out"));
+        }
+        match listener.accept() {
+            Ok((stream, _)) => match answer(stream, state) {
+                Callback::Code(code) => return Ok(code),
+                Callback::Error(error) => {
+                    return Err(KiriError::new(
+                        ErrorCode::Unauthenticated,
+                        format!("Sign-in failed: {}", error),
+                    ))
+                }
+                Callback::WrongState | Callback::Other => {}
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_POLL),
+            Err(e) => return Err(format!("Failed to accept the sign-in callback: {}", e).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_callback_and_pkce() {
+        assert_eq!(
+            parse_callback("/callback?code=abc&state=s1", "s1"),
+            Callback::Code("abc".to_string())
+        );
+        assert_eq!(
+            parse_callback("/callback?code=abc&state=s2", "s1"),
+            Callback::WrongState
+        );
+        assert_eq!(
+            parse_callback("/callback?code=abc", "s1"),
+            Callback::WrongState
+        );
+        assert_eq!(
+            parse_callback(
+                "/callback?error=access_denied&error_description=The+user+denied&state=s1",
+                "s1"
+            ),
+            Callback::Error("The user denied".to_string())
+        );
+        assert_eq!(parse_callback("/favicon.ico", "s1"), Callback::Other);
+
+        // RFC 7636, appendix B
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        let attempt = Attempt::new();
+        assert_eq!(attempt.verifier.len(), 64);
+        assert_ne!(attempt.state, Attempt::new().state);
+
+        assert!(is_reserved_port(20000));
+        assert!(is_reserved_port(39999));
+        assert!(!is_reserved_port(40000));
+        assert!(CALLBACK_PORTS.iter().all(|port| !is_reserved_port(*port)));
+    }
+
+    #[test]
+    fn test_wait_for_callback() {
+        let listener = bind_callback_listener().unwrap();
+        let uri = redirect_uri(&listener).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!is_reserved_port(port));
+        assert!(uri.ends_with("/callback"));
+
+        let client = std::thread::spawn(move || {
+            let get = |target: &str| {
+                let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+                write!(stream, "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n", target).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response
+            };
+            let forged = get("/callback?code=evil&state=other");
+            let real = get("/callback?code=good&state=s1");
+            (forged, real)
+        });
+        let cancelled = AtomicBool::new(false);
+        let code = wait_for_callback(listener, "s1", Duration::from_secs(10), &cancelled);
+        assert_eq!(code, Ok("good".to_string()));
+        let (forged, real) = client.join().unwrap();
+        assert!(forged.starts_with("HTTP/1.1 400"));
+        assert!(real.starts_with("HTTP/1.1 200"));
+
+        let listener = bind_callback_listener().unwrap();
+        cancelled.store(true, Ordering::Relaxed);
+        assert!(wait_for_callback(listener, "s1", Duration::from_secs(10), &cancelled).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Tauri commands for browser sign-in; see oauth.rs.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+
+use super::code_host::{self, HostKind};
+use super::error::KiriError;
+use super::github::GitHubClient;
+use super::gitlab::GitLabClient;
+use super::latency::run_blocking_coded;
+use super::lock_ext::LockExt;
+use super::oauth::{
+    bind_callback_listener, redirect_uri, wait_for_callback, Attempt, OAuthProvider,
+    CALLBACK_TIMEOUT,
+};
+
+/// The sign-in waiting for its callback, if any. Starting another one
+/// cancels it, so only one listener is open at a time.
+#[derive(Debug, Default)]
+pub struct OAuthSignIn {
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+pub type OAuthSignInState = Arc<Mutex<OAuthSignIn>>;
+
+impl OAuthSignIn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the previous attempt and return the flag of a new one.
+    fn begin(&mut self) -> Arc<AtomicBool> {
+        self.cancel();
+        let flag = Arc::new(AtomicBool::new(false));
+        self.cancel = Some(flag.clone());
+        flag
+    }
+
+    fn cancel(&mut self) {
+        if let Some(flag) = self.cancel.take() {
+            flag.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Sign in to GitHub or GitLab (`host` for a self-hosted one) in the
+/// browser. Resolves once the user approved and the token is saved, with
+/// the account's login.
+#[tauri::command]
+pub async fn sign_in_with_browser(
+    app: AppHandle,
+    state: tauri::State<'_, OAuthSignInState>,
+    kind: HostKind,
+    host: Option<String>,
+) -> Result<String, KiriError> {
+    let provider = OAuthProvider::new(kind, host.as_deref())?;
+    let listener = bind_callback_listener()?;
+    let redirect_uri = redirect_uri(&listener)?;
+    let attempt = Attempt::new();
+    let cancelled = state.lock_recover().begin();
+    app.opener()
+        .open_url(
+            provider.authorize_url(&attempt, &redirect_uri),
+            None::<&str>,
+        )
+        .map_err(|e| format!("Failed to open the browser: {}", e))?;
+
+    let code = {
+        let expected = attempt.state.clone();
+        run_blocking_coded("oauth_callback", move || {
+            wait_for_callback(listener, &expected, CALLBACK_TIMEOUT, &cancelled)
+        })
+        .await?
+    };
+    let token = provider
+        .exchange_code(&attempt, &code, &redirect_uri)
+        .await?;
+    let login = match kind {
+        HostKind::GitLab => {
+            GitLabClient::new(&provider.host, Some(token.clone()))?
+                .current_user()
+                .await?
+        }
+        _ => {
+            GitHubClient::new(Some(token.clone()))?
+                .current_user()
+                .await?
+        }
+    };
+    code_host::save_token(kind, &token)?;
+    Ok(login)
+}
+
+/// Stop waiting for the browser.
+#[tauri::command]
+pub fn cancel_browser_sign_in(state: tauri::State<'_, OAuthSignInState>) {
+    state.lock_recover().cancel();
+}
@@ -0,0 +1,288 @@
+//! Cancellation for long-running commands that the frontend awaits.
+//!
+//! A command that can run for a long time (content search, copies, git
+//! fetch / pull / push) takes an optional [`OperationRequest`] naming an
+//! id the caller picked. While the command runs the id is registered here
+//! with a [`CancelToken`]; `cancel_operation(id)` trips the token and the
+//! command stops at its next check, failing with code `cancelled`.
+//! When the window that started an operation closes, its operations are
+//! cancelled with it.
+//!
+//! Background jobs (installs, scans) have their own ids and tokens in
+//! jobs.rs; `cancel_operation` forwards `job-<id>` to them.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use super::error::{ErrorCode, KiriError};
+use super::i18n::Message;
+use super::jobs::CancelToken;
+use super::lock_ext::LockExt;
+
+/// How often a child process is checked for exit or cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub const CANCELLED_MESSAGE: &str = "Operation cancelled";
+
+/// The operation a command call belongs to, as sent by the frontend.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationRequest {
+    pub id: String,
+    /// Window that started the operation; closing it cancels the operation.
+    pub window_label: Option<String>,
+}
+
+struct Entry {
+    token: CancelToken,
+    window_label: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Operations {
+    entries: HashMap<String, Entry>,
+}
+
+impl Operations {
+    /// Register `id`. Reusing the id of an operation still running
+    /// replaces it; the earlier call can no longer be cancelled by id.
+    fn begin(&mut self, id: &str, window_label: Option<String>) -> CancelToken {
+        let token = CancelToken::default();
+        self.entries.insert(
+            id.to_string(),
+            Entry {
+                token: token.clone(),
+                window_label,
+            },
+        );
+        token
+    }
+
+    fn end(&mut self, id: &str, token: &CancelToken) {
+        if self
+            .entries
+            .get(id)
+            .is_some_and(|entry| entry.token.same_as(token))
+        {
+            self.entries.remove(id);
+        }
+    }
+
+    /// Cancel the operation `id`. Returns whether one was running.
+    pub fn cancel(&mut self, id: &str) -> bool {
+        match self.entries.get(id) {
+            Some(entry) => {
+                entry.token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel every operation started by the window `label`. Returns how
+    /// many there were.
+    pub fn cancel_window(&mut self, label: &str) -> usize {
+        let mut cancelled = 0;
+        for entry in self.entries.values() {
+            if entry.window_label.as_deref() == Some(label) {
+                entry.token.cancel();
+                cancelled += 1;
+            }
+        }
+        cancelled
+    }
+
+    /// Cancel every operation, for app exit. Returns how many there were.
+    pub fn cancel_all(&mut self) -> usize {
+        for entry in self.entries.values() {
+            entry.token.cancel();
+        }
+        self.entries.len()
+    }
+
+    pub fn running(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// The operations currently running.
+pub fn operations() -> &'static Mutex<Operations> {
+    static OPERATIONS: OnceLock<Mutex<Operations>> = OnceLock::new();
+    OPERATIONS.get_or_init(|| Mutex::new(Operations::default()))
+}
+
+/// A registered operation; unregisters it when dropped.
+pub struct OperationGuard {
+    id: Option<String>,
+    token: CancelToken,
+}
+
+impl OperationGuard {
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+
+    /// `result` of the command, failing as `cancelled` when the operation
+    /// was cancelled, whatever error that made the work stop with.
+    pub fn finish<T>(&self, result: Result<T, KiriError>) -> Result<T, KiriError> {
+        result.map_err(|e| {
+            if self.token.is_cancelled() {
+                KiriError::localized(ErrorCode::Cancelled, Message::new("error.cancelled"))
+            } else {
+                e
+            }
+        })
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            operations().lock_recover().end(id, &self.token);
+        }
+    }
+}
+
+/// Register the operation a command call belongs to. Without a request the
+/// token is never cancelled.
+pub fn begin(request: Option<OperationRequest>) -> OperationGuard {
+    match request {
+        Some(request) => {
+            let token = operations()
+                .lock_recover()
+                .begin(&request.id, request.window_label);
+            OperationGuard {
+                id: Some(request.id),
+                token,
+            }
+        }
+        None => OperationGuard {
+            id: None,
+            token: CancelToken::default(),
+        },
+    }
+}
+
+/// Fail with "Operation cancelled" once `cancel` is tripped.
+pub fn check(cancel: &CancelToken) -> Result<(), String> {
+    if cancel.is_cancelled() {
+        Err(CANCELLED_MESSAGE.to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// [`Command::output`] that kills the child and fails with "Operation
+/// cancelled" when `cancel` is tripped.
+pub fn output_cancellable(command: &mut Command, cancel: &CancelToken) -> Result<Output, String> {
+    check(cancel)?;
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let stdout = child.stdout.take().map(read_all);
+    let stderr = child.stderr.take().map(read_all);
+
+    let status = loop {
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(CANCELLED_MESSAGE.to_string());
+        }
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None => std::thread::sleep(POLL_INTERVAL),
+        }
+    };
+    let join = |reader: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    Ok(Output {
+        status,
+        stdout: join(stdout),
+        stderr: join(stderr),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_by_id_and_by_window() {
+        let mut ops = Operations::default();
+        let search = ops.begin("search-1", Some("main".to_string()));
+        let copy = ops.begin("copy-1", Some("main".to_string()));
+        let fetch = ops.begin("fetch-1", Some("other".to_string()));
+
+        assert!(ops.cancel("search-1"));
+        assert!(search.is_cancelled());
+        assert!(!ops.cancel("missing"));
+
+        assert_eq!(ops.cancel_window("main"), 2);
+        assert!(copy.is_cancelled());
+        assert!(!fetch.is_cancelled());
+
+        ops.end("search-1", &search);
+        assert_eq!(ops.running(), 2);
+        // A stale guard does not unregister a newer operation with its id.
+        let newer = ops.begin("copy-1", None);
+        ops.end("copy-1", &copy);
+        assert!(ops.cancel("copy-1"));
+        assert!(newer.is_cancelled());
+    }
+
+    #[test]
+    fn test_finish_reports_cancellation() {
+        let guard = begin(Some(OperationRequest {
+            id: "finish-1".to_string(),
+            window_label: None,
+        }));
+        let killed = || Err::<(), _>(KiriError::from("Failed to execute git fetch: killed"));
+        assert_eq!(
+            guard.finish(killed()).unwrap_err().code,
+            ErrorCode::Internal
+        );
+        guard.token().cancel();
+        assert_eq!(
+            guard.finish(killed()).unwrap_err().code,
+            ErrorCode::Cancelled
+        );
+        assert!(guard.finish(Ok(())).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_cancellable() {
+        let token = CancelToken::default();
+        let output = output_cancellable(Command::new("echo").arg("hi"), &token).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hi\n");
+
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        let started = std::time::Instant::now();
+        let err = output_cancellable(Command::new("sleep").arg("10"), &token).unwrap_err();
+        assert_eq!(err, CANCELLED_MESSAGE);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,28 @@
+//! Tauri command for cancelling operations; the registry is in
+//! operations.rs.
+
+use tauri::AppHandle;
+
+use super::error::{ErrorCode, KiriError};
+use super::jobs_commands;
+use super::lock_ext::LockExt;
+use super::operations::operations;
+
+/// Cancel a running operation by the id its command was given, or a
+/// background job by `job-<id>`. Fails with `not_found` when nothing with
+/// that id is running.
+#[tauri::command]
+pub fn cancel_operation(app: AppHandle, id: String) -> Result<(), KiriError> {
+    if operations().lock_recover().cancel(&id) {
+        return Ok(());
+    }
+    if let Some(job_id) = id.strip_prefix("job-").and_then(|n| n.parse().ok()) {
+        return jobs_commands::cancel(&app, job_id)
+            .map(|_| ())
+            .map_err(KiriError::from);
+    }
+    Err(KiriError::new(
+        ErrorCode::NotFound,
+        format!("No running operation {}", id),
+    ))
+}
@@ -0,0 +1,136 @@
+//! The ways a path can be copied from a context menu: as is, relative to
+//! the project, as a `file://` URL, or as `path:line` for pasting into a
+//! terminal or chat. Formatting lives here so the file tree, editor tabs
+//! and search results produce the same text on every platform.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use super::fs_path::{display_path, strip_root};
+use super::lsp::path_to_uri;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PathReferenceKind {
+    Absolute,
+    Relative,
+    FileUrl,
+    /// Project-relative when the path is inside the project, otherwise
+    /// absolute, followed by `:line`.
+    PathLine,
+}
+
+/// `path` below `root` with `/` separators, whatever the platform; `.`
+/// for the root itself. Falls back to comparing canonical paths so a
+/// symlinked checkout still matches.
+fn relative_to(path: &Path, root: &Path) -> Option<String> {
+    let rest = strip_root(path, root).or_else(|| {
+        let path = path.canonicalize().ok()?;
+        let root = root.canonicalize().ok()?;
+        strip_root(&path, &root)
+    })?;
+    let parts: Vec<String> = rest
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if parts.is_empty() {
+        return Some(".".to_string());
+    }
+    Some(parts.join("/"))
+}
+
+/// The text copied for `path` as `kind`. `root` is the project the path
+/// belongs to; `line` is 1-based and required for [`PathReferenceKind::PathLine`].
+pub fn format_path_reference(
+    path: &Path,
+    root: Option<&Path>,
+    kind: PathReferenceKind,
+    line: Option<u32>,
+) -> Result<String, String> {
+    match kind {
+        PathReferenceKind::Absolute => Ok(display_path(path)),
+        PathReferenceKind::Relative => {
+            let root =
+                root.ok_or_else(|| format!("{} is not inside a project", display_path(path)))?;
+            relative_to(path, root).ok_or_else(|| {
+                format!(
+                    "{} is not inside {}",
+                    display_path(path),
+                    display_path(root)
+                )
+            })
+        }
+        PathReferenceKind::FileUrl => path_to_uri(&PathBuf::from(display_path(path))),
+        PathReferenceKind::PathLine => {
+            let line = line.ok_or_else(|| "A line number is required".to_string())?;
+            let base = root
+                .and_then(|root| relative_to(path, root))
+                .unwrap_or_else(|| display_path(path));
+            Ok(format!("{}:{}", base, line.max(1)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_reference_uses_forward_slashes() {
+        let root = Path::new("/repo");
+        let path = root.join("src").join("commands").join("mod.rs");
+        assert_eq!(
+            format_path_reference(&path, Some(root), PathReferenceKind::Relative, None).unwrap(),
+            "src/commands/mod.rs"
+        );
+        assert_eq!(
+            format_path_reference(root, Some(root), PathReferenceKind::Relative, None).unwrap(),
+            "."
+        );
+        let err = format_path_reference(
+            Path::new("/repo-wt/a.rs"),
+            Some(root),
+            PathReferenceKind::Relative,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.contains("is not inside"));
+    }
+
+    #[test]
+    fn test_path_line_falls_back_to_the_absolute_path() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            format_path_reference(
+                Path::new("/repo/src/lib.rs"),
+                Some(root),
+                PathReferenceKind::PathLine,
+                Some(42)
+            )
+            .unwrap(),
+            "src/lib.rs:42"
+        );
+        let outside = Path::new("/elsewhere/notes.md");
+        assert_eq!(
+            format_path_reference(outside, Some(root), PathReferenceKind::PathLine, Some(3))
+                .unwrap(),
+            format!("{}:3", display_path(outside))
+        );
+        assert!(format_path_reference(outside, None, PathReferenceKind::PathLine, None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_url_is_percent_encoded() {
+        assert_eq!(
+            format_path_reference(
+                Path::new("/repo/docs/read me#1.md"),
+                None,
+                PathReferenceKind::FileUrl,
+                None
+            )
+            .unwrap(),
+            "file:///repo/docs/read%20me%231.md"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Tauri command for path_reference.rs. The text goes on the clipboard
+//! from Rust so copying doesn't depend on the webview's clipboard
+//! permissions, which differ between platforms.
+
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use super::error::KiriError;
+use super::fs_path::input_path;
+use super::path_reference::{format_path_reference, PathReferenceKind};
+
+/// Working directory of the repository containing `path`, if any.
+fn repository_root(path: &Path) -> Option<PathBuf> {
+    let start = if path.is_dir() { path } else { path.parent()? };
+    let repo = git2::Repository::discover(start).ok()?;
+    repo.workdir().map(Path::to_path_buf)
+}
+
+/// Copy `path` to the clipboard as `kind` and return the copied text.
+/// Relative forms are relative to `project_path`, or to the repository
+/// containing `path` when not given.
+#[tauri::command]
+pub fn copy_path_reference(
+    app: AppHandle,
+    path: String,
+    kind: PathReferenceKind,
+    project_path: Option<String>,
+    line: Option<u32>,
+) -> Result<String, KiriError> {
+    let path = input_path(&path)?;
+    let root = match project_path {
+        Some(project) => Some(input_path(&project)?),
+        None => repository_root(&path),
+    };
+    let text = format_path_reference(&path, root.as_deref(), kind, line)?;
+    app.clipboard()
+        .write_text(text.clone())
+        .map_err(|e| format!("Failed to copy to the clipboard: {}", e))?;
+    Ok(text)
+}
@@ -1,9 +1,57 @@
-//! Performance measurement module (debug builds only)
+//! Performance measurement module
 //!
 //! Provides memory metrics and command timing tracking for development.
-//! All functionality is compiled out in release builds.
+//! Debug builds always collect; release builds only when launched with
+//! `KIRI_PERF=1`, and otherwise every call here is a cheap no-op. Memory
+//! metrics can also be switched on from settings in release builds, for
+//! users reporting leaks; see memory_sampler.rs.
+//!
+//! Every Tauri command is timed by the invoke-handler wrapper in
+//! performance_commands.rs. Async commands return to that wrapper before
+//! they finish, so they time themselves with [`CommandTimer`] instead,
+//! which also tells the wrapper to leave them alone; see
+//! [`record_dispatch`].
+//! Timings are also taken in release builds when the user opted into
+//! usage analytics, which count them; see telemetry.rs.
 
 use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::lock_ext::LockExt;
+use super::telemetry;
+
+/// Environment variable that turns timing on in release builds.
+pub const PERF_ENV_VAR: &str = "KIRI_PERF";
+
+/// Whether timings and memory metrics are being collected.
+pub fn timing_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        cfg!(debug_assertions)
+            || std::env::var(PERF_ENV_VAR).is_ok_and(|v| !v.is_empty() && v != "0")
+    })
+}
+
+static MEMORY_METRICS_OPT_IN: AtomicBool = AtomicBool::new(false);
+
+/// Record the "collect memory metrics" setting.
+pub fn set_memory_metrics_opt_in(enabled: bool) {
+    MEMORY_METRICS_OPT_IN.store(enabled, Ordering::Relaxed);
+}
+
+/// The "collect memory metrics" setting as last recorded.
+pub fn memory_metrics_opt_in() -> bool {
+    MEMORY_METRICS_OPT_IN.load(Ordering::Relaxed)
+}
+
+/// Whether memory metrics are collected: whenever timing is, or when the
+/// user opted in from settings.
+pub fn memory_metrics_enabled() -> bool {
+    timing_enabled() || memory_metrics_opt_in()
+}
 
 /// Memory usage metrics
 #[derive(Debug, Clone, Serialize, Default)]
@@ -25,6 +73,22 @@ pub struct CommandTiming {
     pub duration_ms: f64,
     /// Timestamp (ms since tracker start)
     pub timestamp_ms: u64,
+    /// Size of the serialized arguments, when the command went through
+    /// the invoke handler
+    pub arg_bytes: Option<u64>,
+}
+
+/// Aggregate timings for one command
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CommandStats {
+    pub command: String,
+    pub count: usize,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    /// 95th percentile duration
+    pub p95_ms: f64,
+    /// Largest argument payload seen, if any call reported one
+    pub max_arg_bytes: Option<u64>,
 }
 
 /// Full performance report
@@ -34,19 +98,132 @@ pub struct PerformanceReport {
     pub memory: MemoryMetrics,
     /// Command timings
     pub command_timings: Vec<CommandTiming>,
+    /// Per-command aggregates of `command_timings`, slowest total first
+    pub command_stats: Vec<CommandStats>,
     /// App uptime in milliseconds
     pub app_uptime_ms: u64,
 }
 
+/// Group timings by command, slowest total first.
+pub fn summarize_timings(timings: &[CommandTiming]) -> Vec<CommandStats> {
+    let mut by_command: BTreeMap<&str, Vec<&CommandTiming>> = BTreeMap::new();
+    for timing in timings {
+        by_command.entry(&timing.command).or_default().push(timing);
+    }
+    let mut stats: Vec<CommandStats> = by_command
+        .into_iter()
+        .map(|(command, entries)| {
+            let mut durations: Vec<f64> = entries.iter().map(|t| t.duration_ms).collect();
+            durations.sort_by(|a, b| a.total_cmp(b));
+            let p95_index = (durations.len() * 95).div_ceil(100).saturating_sub(1);
+            CommandStats {
+                command: command.to_string(),
+                count: durations.len(),
+                total_ms: durations.iter().sum(),
+                max_ms: durations.last().copied().unwrap_or(0.0),
+                p95_ms: durations[p95_index],
+                max_arg_bytes: entries.iter().filter_map(|t| t.arg_bytes).max(),
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    stats
+}
+
+/// Records how long a command took when dropped. For async commands,
+/// whose futures outlive the invoke handler:
+///
+/// ```ignore
+/// let _timer = CommandTimer::start("read_directory");
+/// ```
+pub struct CommandTimer {
+    command: &'static str,
+    start: Option<Instant>,
+}
+
+impl CommandTimer {
+    pub fn start(command: &'static str) -> Self {
+        let enabled = timing_enabled() || telemetry::enabled();
+        if enabled {
+            SELF_TIMED.lock_recover().insert(command);
+        }
+        Self {
+            command,
+            start: enabled.then(Instant::now),
+        }
+    }
+}
+
+impl Drop for CommandTimer {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            record_command(self.command, start.elapsed().as_secs_f64() * 1000.0, None);
+        }
+    }
+}
+
+/// Commands seen starting a [`CommandTimer`].
+static SELF_TIMED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// How long a dispatch timing waits for its command to turn out to be
+/// self-timed.
+const DISPATCH_SETTLE: Duration = Duration::from_secs(1);
+
+struct PendingDispatch {
+    command: String,
+    duration_ms: f64,
+    arg_bytes: u64,
+    at: Instant,
+}
+
+static PENDING_DISPATCHES: Mutex<Vec<PendingDispatch>> = Mutex::new(Vec::new());
+
+/// Whether `command` times itself with a [`CommandTimer`].
+pub fn is_self_timed(command: &str) -> bool {
+    SELF_TIMED.lock_recover().contains(command)
+}
+
+/// Record how long the invoke handler took to dispatch `command`. For a
+/// sync command that is the whole call; an async one is only spawned, and
+/// times itself. The first call of an async command may start its timer
+/// only after the handler returned, so the timing is held for
+/// [`DISPATCH_SETTLE`] and dropped if the command turned out to time
+/// itself by then.
+pub fn record_dispatch(command: &str, duration_ms: f64, arg_bytes: u64) {
+    let mut pending = PENDING_DISPATCHES.lock_recover();
+    pending.push(PendingDispatch {
+        command: command.to_string(),
+        duration_ms,
+        arg_bytes,
+        at: Instant::now(),
+    });
+    settle_dispatches(&mut pending, DISPATCH_SETTLE);
+}
+
+/// Count the pending dispatch timings older than `settle`.
+fn settle_dispatches(pending: &mut Vec<PendingDispatch>, settle: Duration) {
+    let (ready, waiting): (Vec<_>, Vec<_>) = pending
+        .drain(..)
+        .partition(|dispatch| dispatch.at.elapsed() >= settle);
+    *pending = waiting;
+    for dispatch in ready {
+        if !is_self_timed(&dispatch.command) {
+            record_command(
+                &dispatch.command,
+                dispatch.duration_ms,
+                Some(dispatch.arg_bytes),
+            );
+        }
+    }
+}
+
 // ============================================================================
-// Debug build implementation
+// Tracker
 // ============================================================================
 
-#[cfg(debug_assertions)]
-mod debug_impl {
+mod tracker_impl {
     use super::*;
     use std::sync::Mutex;
-    use std::time::Instant;
     use sysinfo::{Pid, System};
 
     lazy_static::lazy_static! {
@@ -74,11 +251,21 @@ mod debug_impl {
         }
 
         pub fn record(&mut self, command: &str, duration_ms: f64) {
+            self.record_with_args(command, duration_ms, None);
+        }
+
+        pub fn record_with_args(
+            &mut self,
+            command: &str,
+            duration_ms: f64,
+            arg_bytes: Option<u64>,
+        ) {
             let timestamp_ms = self.start_time.elapsed().as_millis() as u64;
             self.timings.push(CommandTiming {
                 command: command.to_string(),
                 duration_ms,
                 timestamp_ms,
+                arg_bytes,
             });
 
             // Keep only last 1000 entries to prevent memory growth
@@ -124,14 +311,26 @@ mod debug_impl {
 
     /// Get current memory usage for this process
     pub fn get_memory_usage() -> MemoryMetrics {
+        if !memory_metrics_enabled() {
+            return MemoryMetrics::default();
+        }
         let pid = Pid::from_u32(std::process::id());
         get_memory_usage_for_pid(pid)
     }
 
     /// Record a command timing
     pub fn record_timing(command: &str, duration_ms: f64) {
+        record_command(command, duration_ms, None);
+    }
+
+    /// Record a command timing along with its argument size
+    pub fn record_command(command: &str, duration_ms: f64, arg_bytes: Option<u64>) {
+        telemetry::record(command, duration_ms);
+        if !timing_enabled() {
+            return;
+        }
         if let Ok(mut tracker) = PERF_TRACKER.lock() {
-            tracker.record(command, duration_ms);
+            tracker.record_with_args(command, duration_ms, arg_bytes);
         }
     }
 
@@ -144,6 +343,7 @@ mod debug_impl {
 
         PerformanceReport {
             memory: get_memory_usage(),
+            command_stats: summarize_timings(&timings),
             command_timings: timings,
             app_uptime_ms: uptime,
         }
@@ -151,6 +351,7 @@ mod debug_impl {
 
     /// Get full performance report
     pub fn get_report() -> PerformanceReport {
+        settle_dispatches(&mut PENDING_DISPATCHES.lock_recover(), DISPATCH_SETTLE);
         let tracker_data = if let Ok(tracker) = PERF_TRACKER.lock() {
             Some((tracker.get_timings(), tracker.uptime_ms()))
         } else {
@@ -168,44 +369,11 @@ mod debug_impl {
     }
 }
 
-// ============================================================================
-// Release build implementation (no-op)
-// ============================================================================
-
-#[cfg(not(debug_assertions))]
-mod release_impl {
-    use super::*;
-
-    /// No-op in release builds
-    pub fn get_memory_usage() -> MemoryMetrics {
-        MemoryMetrics::default()
-    }
-
-    /// No-op in release builds
-    pub fn record_timing(_command: &str, _duration_ms: f64) {}
-
-    /// No-op in release builds
-    pub fn get_report() -> PerformanceReport {
-        PerformanceReport {
-            memory: MemoryMetrics::default(),
-            command_timings: Vec::new(),
-            app_uptime_ms: 0,
-        }
-    }
-
-    /// No-op in release builds
-    pub fn clear_timings() {}
-}
-
 // ============================================================================
 // Public API
 // ============================================================================
 
-#[cfg(debug_assertions)]
-pub use debug_impl::*;
-
-#[cfg(not(debug_assertions))]
-pub use release_impl::*;
+pub use tracker_impl::*;
 
 // ============================================================================
 // Tests
@@ -229,6 +397,7 @@ mod tests {
             command: "test_command".to_string(),
             duration_ms: 42.5,
             timestamp_ms: 1000,
+            arg_bytes: None,
         };
         assert_eq!(timing.command, "test_command");
         assert!((timing.duration_ms - 42.5).abs() < f64::EPSILON);
@@ -291,7 +460,7 @@ mod tests {
     #[cfg(debug_assertions)]
     #[test]
     fn test_performance_tracker_record_limit() {
-        use super::debug_impl::PerformanceTracker;
+        use super::tracker_impl::PerformanceTracker;
 
         let mut tracker = PerformanceTracker::new();
 
@@ -308,6 +477,28 @@ mod tests {
         assert_eq!(timings[999].command, "cmd_1004");
     }
 
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_dispatch_of_self_timed_command_is_dropped() {
+        record_dispatch("test_dispatch_async", 0.1, 2);
+        record_dispatch("test_dispatch_sync", 3.0, 2);
+        // The async command's own timer starts after the handler returned.
+        drop(CommandTimer::start("test_dispatch_async"));
+        assert!(is_self_timed("test_dispatch_async"));
+        settle_dispatches(&mut PENDING_DISPATCHES.lock_recover(), Duration::ZERO);
+
+        let timings = get_report().command_timings;
+        let of = |command: &str| {
+            timings
+                .iter()
+                .filter(|t| t.command == command)
+                .map(|t| t.arg_bytes)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(of("test_dispatch_async"), vec![None]);
+        assert_eq!(of("test_dispatch_sync"), vec![Some(2)]);
+    }
+
     #[test]
     fn test_performance_report_serialization() {
         let report = PerformanceReport {
@@ -321,13 +512,16 @@ mod tests {
                     command: "open_file".to_string(),
                     duration_ms: 15.5,
                     timestamp_ms: 100,
+                    arg_bytes: None,
                 },
                 CommandTiming {
                     command: "save_file".to_string(),
                     duration_ms: 8.2,
                     timestamp_ms: 200,
+                    arg_bytes: None,
                 },
             ],
+            command_stats: Vec::new(),
             app_uptime_ms: 5000,
         };
 
@@ -380,7 +574,7 @@ mod tests {
     #[cfg(debug_assertions)]
     #[test]
     fn test_performance_tracker_new() {
-        use super::debug_impl::PerformanceTracker;
+        use super::tracker_impl::PerformanceTracker;
 
         let tracker = PerformanceTracker::new();
 
@@ -399,6 +593,7 @@ mod tests {
             command: "read_dir".to_string(),
             duration_ms: std::f64::consts::PI,
             timestamp_ms: 42,
+            arg_bytes: None,
         };
 
         let json = serde_json::to_string(&timing).unwrap();
@@ -413,7 +608,7 @@ mod tests {
     #[test]
     fn test_get_memory_usage_for_nonexistent_pid_returns_zeros() {
         use sysinfo::Pid;
-        use super::debug_impl::get_memory_usage_for_pid;
+        use super::tracker_impl::get_memory_usage_for_pid;
 
         // Use a very high PID that is extremely unlikely to exist
         let fake_pid = Pid::from_u32(u32::MAX - 1);
@@ -431,7 +626,7 @@ mod tests {
     #[cfg(debug_assertions)]
     #[test]
     fn test_build_report_with_none_returns_empty_timings() {
-        use super::debug_impl::build_report;
+        use super::tracker_impl::build_report;
 
         // Simulate the fallback path (tracker data unavailable)
         let report = build_report(None);
@@ -451,13 +646,14 @@ mod tests {
     #[cfg(debug_assertions)]
     #[test]
     fn test_build_report_with_some_uses_provided_data() {
-        use super::debug_impl::build_report;
+        use super::tracker_impl::build_report;
 
         let timings = vec![
             CommandTiming {
                 command: "test_cmd".to_string(),
                 duration_ms: 5.0,
                 timestamp_ms: 100,
+                arg_bytes: None,
             },
         ];
         let report = build_report(Some((timings, 9999)));
@@ -466,4 +662,53 @@ mod tests {
         assert_eq!(report.command_timings[0].command, "test_cmd");
         assert_eq!(report.app_uptime_ms, 9999);
     }
+
+    fn timing(command: &str, duration_ms: f64, arg_bytes: Option<u64>) -> CommandTiming {
+        CommandTiming {
+            command: command.to_string(),
+            duration_ms,
+            timestamp_ms: 0,
+            arg_bytes,
+        }
+    }
+
+    #[test]
+    fn test_summarize_timings_groups_and_sorts() {
+        let mut timings: Vec<CommandTiming> = (1..=20)
+            .map(|i| timing("read_file", i as f64, Some(i * 10)))
+            .collect();
+        timings.push(timing("git_status", 500.0, None));
+
+        let stats = summarize_timings(&timings);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].command, "git_status");
+        assert_eq!(stats[0].count, 1);
+        assert_eq!(stats[0].p95_ms, 500.0);
+        assert_eq!(stats[0].max_arg_bytes, None);
+
+        let read = &stats[1];
+        assert_eq!(read.count, 20);
+        assert_eq!(read.total_ms, 210.0);
+        assert_eq!(read.max_ms, 20.0);
+        assert_eq!(read.p95_ms, 19.0);
+        assert_eq!(read.max_arg_bytes, Some(200));
+    }
+
+    #[test]
+    fn test_summarize_timings_empty() {
+        assert!(summarize_timings(&[]).is_empty());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_command_timer_records_on_drop() {
+        {
+            let _timer = CommandTimer::start("test_timer_cmd");
+        }
+        let report = get_report();
+        assert!(report
+            .command_timings
+            .iter()
+            .any(|t| t.command == "test_timer_cmd" && t.arg_bytes.is_none()));
+    }
 }
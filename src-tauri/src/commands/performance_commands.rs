@@ -1,14 +1,78 @@
-//! Tauri command wrappers for performance measurement
+//! Tauri command wrappers for performance measurement, and the
+//! invoke-handler wrapper that times every command.
 //!
-//! These commands are only available in debug builds.
+//! The commands return empty data unless timing is enabled (debug builds,
+//! or `KIRI_PERF=1` in release).
 
+use super::error::KiriError;
+use super::latency::SYNC_COMMAND_BUDGET;
 use super::performance::{self, MemoryMetrics, PerformanceReport};
+use super::telemetry;
+use std::time::Instant;
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::Runtime;
+
+/// Reading the report shouldn't show up in it.
+const UNTIMED_COMMANDS: [&str; 9] = [
+    "get_memory_metrics",
+    "get_memory_history",
+    "get_performance_report",
+    "record_command_timing",
+    "clear_performance_timings",
+    "export_trace",
+    "get_usage_analytics",
+    "clear_usage_analytics",
+    "export_usage_analytics",
+];
+
+fn payload_bytes(body: &InvokeBody) -> u64 {
+    match body {
+        InvokeBody::Json(value) => serde_json::to_vec(value).map_or(0, |v| v.len()) as u64,
+        InvokeBody::Raw(bytes) => bytes.len() as u64,
+    }
+}
+
+/// Wrap the `generate_handler!` output so every command invocation is
+/// timed and recorded with the size of its arguments. Async commands time
+/// themselves through [`performance::CommandTimer`] (`run_blocking` starts
+/// one), and their dispatch isn't counted; see
+/// [`performance::record_dispatch`]. Sync commands that overrun the budget
+/// in latency.rs are logged.
+pub fn timed_handler<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        if !(performance::timing_enabled() || telemetry::enabled())
+            || UNTIMED_COMMANDS.contains(&command)
+            || performance::is_self_timed(command)
+        {
+            return handler(invoke);
+        }
+        let command = command.to_string();
+        let arg_bytes = payload_bytes(invoke.message.payload());
+        let start = Instant::now();
+        let handled = handler(invoke);
+        let elapsed = start.elapsed();
+        if elapsed > SYNC_COMMAND_BUDGET {
+            log::warn!(
+                "{} blocked the invoke path for {} ms (budget {} ms); make it async",
+                command,
+                elapsed.as_millis(),
+                SYNC_COMMAND_BUDGET.as_millis()
+            );
+        }
+        performance::record_dispatch(&command, elapsed.as_secs_f64() * 1000.0, arg_bytes);
+        handled
+    }
+}
 
 /// Get current memory metrics
 ///
-/// Returns memory usage information for the current process.
+/// Returns memory usage information for the current process. Release
+/// builds report zeros unless memory metrics are enabled in settings.
 #[tauri::command]
-pub fn get_memory_metrics() -> Result<MemoryMetrics, String> {
+pub fn get_memory_metrics() -> Result<MemoryMetrics, KiriError> {
     Ok(performance::get_memory_usage())
 }
 
@@ -16,7 +80,7 @@ pub fn get_memory_metrics() -> Result<MemoryMetrics, String> {
 ///
 /// Returns memory metrics, command timings, and app uptime.
 #[tauri::command]
-pub fn get_performance_report() -> Result<PerformanceReport, String> {
+pub fn get_performance_report() -> Result<PerformanceReport, KiriError> {
     Ok(performance::get_report())
 }
 
@@ -25,7 +89,7 @@ pub fn get_performance_report() -> Result<PerformanceReport, String> {
 /// This allows the frontend to report operation timings to the backend
 /// for centralized performance tracking.
 #[tauri::command]
-pub fn record_command_timing(command: String, duration_ms: f64) -> Result<(), String> {
+pub fn record_command_timing(command: String, duration_ms: f64) -> Result<(), KiriError> {
     performance::record_timing(&command, duration_ms);
     Ok(())
 }
@@ -34,7 +98,7 @@ pub fn record_command_timing(command: String, duration_ms: f64) -> Result<(), St
 ///
 /// Useful for resetting performance tracking between sessions.
 #[tauri::command]
-pub fn clear_performance_timings() -> Result<(), String> {
+pub fn clear_performance_timings() -> Result<(), KiriError> {
     performance::clear_timings();
     Ok(())
 }
@@ -0,0 +1,702 @@
+//! Lightweight file previews for FileTree hover / quick look.
+//!
+//! The hover card only needs a glance at a file, so shipping whole files
+//! across IPC (as `read_file_as_base64` does for the editor) wastes memory
+//! in both processes. [`get_preview`] returns just enough to render:
+//!
+//! - **Images** (PNG / JPEG) are decoded and downscaled to fit a square of
+//!   `max_size` pixels and re-encoded as PNG.
+//! - **Text** returns the first `max_lines` lines of the file.
+//! - **PDF** returns the page count, read from the two ends of the file
+//!   only, and its first page as a thumbnail. We bundle no PDF engine, so
+//!   the page is rendered by the platform's: Quick Look on macOS,
+//!   poppler's `pdftoppm` elsewhere. Without one there is no page image.
+//! - Anything else is reported as binary with its size.
+//!
+//! Thumbnails are cached on disk under `~/.kiri/cache/thumbnails`, named
+//! by a SHA-256 of path, size, mtime, and bound. The cache is pruned to
+//! [`THUMBNAIL_CACHE_MAX_BYTES`], dropping the least recently written
+//! first, and of entries older than [`THUMBNAIL_CACHE_MAX_AGE`].
+
+use base64::Engine;
+use image::DynamicImage;
+use ring::digest::{digest, SHA256};
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::error::{user_error, user_io_failure, ErrorCode, KiriError};
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::performance::CommandTimer;
+use super::process::{argv_command, path_arg};
+
+/// Default number of text lines returned for a preview.
+pub const DEFAULT_PREVIEW_LINES: usize = 40;
+/// Upper bound on requested text lines.
+pub const MAX_PREVIEW_LINES: usize = 500;
+/// Default bounding box (pixels) for image thumbnails.
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+/// Upper bound on the requested thumbnail bounding box.
+pub const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// Bytes read from the head of a file to decide text vs binary and to
+/// build the text preview. Lines past this window are not returned.
+const TEXT_SNIFF_BYTES: usize = 64 * 1024;
+
+/// Images larger than this are not decoded for a thumbnail; decoding a
+/// 200 MB TIFF-in-a-PNG just to show a hover card is not worth it.
+const MAX_IMAGE_DECODE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Bytes read from each end of a PDF for its page count. The page tree
+/// root sits near the start of a linearized file and near the end of one
+/// written or updated in place.
+const PDF_SCAN_WINDOW: u64 = 1024 * 1024;
+
+/// How long the platform's renderer gets for the first page of a PDF.
+const PDF_RENDER_TIMEOUT: Duration = Duration::from_secs(5);
+const RENDER_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Size the thumbnail cache is pruned to.
+pub const THUMBNAIL_CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+/// Age past which a cached thumbnail is dropped.
+pub const THUMBNAIL_CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A PNG thumbnail.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Thumbnail {
+    /// Always `image/png`: thumbnails are re-encoded regardless of source.
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub data_base64: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FilePreview {
+    Image {
+        /// Always `image/png`: thumbnails are re-encoded regardless of source.
+        mime: String,
+        width: u32,
+        height: u32,
+        data_base64: String,
+    },
+    Text {
+        lines: Vec<String>,
+        /// True when the file has more content than was returned.
+        truncated: bool,
+    },
+    Pdf {
+        page_count: Option<usize>,
+        size: u64,
+        /// The first page, when the platform can render it.
+        page: Option<Thumbnail>,
+    },
+    Binary {
+        size: u64,
+    },
+}
+
+/// `~/.kiri/cache/thumbnails` — on-disk thumbnail cache.
+pub fn thumbnail_cache_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("cache").join("thumbnails"))
+}
+
+fn extension_lower(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+fn is_thumbnailable(ext: &str) -> bool {
+    matches!(ext, "png" | "jpg" | "jpeg")
+}
+
+/// Cache file name for a thumbnail. Includes size and mtime so an edited
+/// file never serves a stale thumbnail, and is the same across runs and
+/// builds.
+fn thumbnail_cache_key(path: &Path, size: u64, mtime_ns: u128, max_size: u32) -> String {
+    let text = format!(
+        "{}\0{}\0{}\0{}",
+        path_to_wire(path),
+        size,
+        mtime_ns,
+        max_size
+    );
+    let hash = digest(&SHA256, text.as_bytes());
+    let hex: String = hash.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}.png", hex)
+}
+
+/// Remove cached thumbnails older than `max_age`, then the least recently
+/// written ones until the rest fit in `max_bytes`.
+fn prune_thumbnail_cache(dir: &Path, max_bytes: u64, max_age: Duration) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    let mut kept = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if !meta.is_file() {
+            continue;
+        }
+        let written = meta.modified().unwrap_or(UNIX_EPOCH);
+        if now.duration_since(written).unwrap_or_default() > max_age {
+            let _ = fs::remove_file(entry.path());
+        } else {
+            kept.push((written, meta.len(), entry.path()));
+        }
+    }
+    kept.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+    let mut total = 0;
+    for (_, len, path) in kept {
+        total += len;
+        if total > max_bytes {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn cached_thumbnail(cache_path: &Path) -> Option<Thumbnail> {
+    let png = fs::read(cache_path).ok()?;
+    let img = image::load_from_memory_with_format(&png, image::ImageFormat::Png).ok()?;
+    Some(thumbnail(&png, &img))
+}
+
+/// Best-effort: a read-only home or full disk must not fail the preview.
+fn store_thumbnail(cache_path: &Path, png: &[u8]) {
+    let Some(dir) = cache_path.parent() else {
+        return;
+    };
+    let _ = fs::create_dir_all(dir);
+    if let Err(e) = fs::write(cache_path, png) {
+        log::debug!("thumbnail cache write failed: {}", e);
+        return;
+    }
+    prune_thumbnail_cache(dir, THUMBNAIL_CACHE_MAX_BYTES, THUMBNAIL_CACHE_MAX_AGE);
+}
+
+fn thumbnail(png: &[u8], img: &DynamicImage) -> Thumbnail {
+    Thumbnail {
+        mime: "image/png".to_string(),
+        width: img.width(),
+        height: img.height(),
+        data_base64: base64::engine::general_purpose::STANDARD.encode(png),
+    }
+}
+
+/// `img` fitted into a `max_size` square, as PNG.
+fn encode_thumbnail(img: DynamicImage, max_size: u32) -> Result<(Vec<u8>, Thumbnail), KiriError> {
+    let img = if img.width() > max_size || img.height() > max_size {
+        img.thumbnail(max_size, max_size)
+    } else {
+        img
+    };
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| user_error(ErrorCode::Internal, "error.encode_thumbnail_failed", e))?;
+    let thumbnail = thumbnail(&png, &img);
+    Ok((png, thumbnail))
+}
+
+/// Heuristic used by git and most editors: a NUL byte in the first block
+/// means binary.
+fn looks_binary(head: &[u8]) -> bool {
+    head.contains(&0)
+}
+
+/// Split `head` into at most `max_lines` lines. `file_len` tells whether
+/// bytes beyond the sniffed window exist.
+fn text_preview(head: &[u8], file_len: u64, max_lines: usize) -> FilePreview {
+    let text = String::from_utf8_lossy(head);
+    let mut lines: Vec<String> = Vec::new();
+    let mut truncated = file_len > head.len() as u64;
+    for (i, line) in text.lines().enumerate() {
+        if i >= max_lines {
+            truncated = true;
+            break;
+        }
+        lines.push(line.to_string());
+    }
+    FilePreview::Text { lines, truncated }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// `bytes` starts with the PDF name `name`, not just a name beginning
+/// with it.
+fn starts_with_name(bytes: &[u8], name: &[u8]) -> bool {
+    bytes.starts_with(name)
+        && !bytes
+            .get(name.len())
+            .is_some_and(|b| b.is_ascii_alphanumeric())
+}
+
+/// Page count of a PDF from `bytes`, the whole file when `whole` and its
+/// two ends otherwise. The root of the page tree carries the total as its
+/// `/Count`, so the largest `/Count` of a `/Type /Pages` node wins; a
+/// whole file without one has its `/Type /Page` objects counted instead.
+/// Good enough for an "N pages" label; `None` if nothing matches.
+fn count_pdf_pages(bytes: &[u8], whole: bool) -> Option<usize> {
+    let needle = b"/Type";
+    let mut tree_count: Option<usize> = None;
+    let mut page_objects = 0;
+    let mut i = 0;
+    while let Some(found) = find(&bytes[i..], needle) {
+        let mut j = i + found + needle.len();
+        while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+            j += 1;
+        }
+        if starts_with_name(&bytes[j..], b"/Pages") {
+            let start = bytes[..j].windows(2).rposition(|w| w == b"<<").unwrap_or(0);
+            let end = find(&bytes[j..], b">>").map_or(bytes.len(), |end| j + end);
+            let count = find(&bytes[start..end], b"/Count").and_then(|at| {
+                let digits: String = bytes[start + at + b"/Count".len()..end]
+                    .iter()
+                    .skip_while(|b| b.is_ascii_whitespace())
+                    .take_while(|b| b.is_ascii_digit())
+                    .map(|&b| char::from(b))
+                    .collect();
+                digits.parse::<usize>().ok()
+            });
+            tree_count = tree_count.max(count);
+        } else if starts_with_name(&bytes[j..], b"/Page") {
+            page_objects += 1;
+        }
+        i = j;
+    }
+    tree_count
+        .or((whole && page_objects > 0).then_some(page_objects))
+        .filter(|&count| count > 0)
+}
+
+/// All of a PDF when it is small, else [`PDF_SCAN_WINDOW`] bytes from
+/// each end; with whether that is the whole file.
+fn read_pdf_ends(path: &Path) -> io::Result<(Vec<u8>, bool)> {
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut bytes = Vec::new();
+    if size <= 2 * PDF_SCAN_WINDOW {
+        (&mut file)
+            .take(2 * PDF_SCAN_WINDOW)
+            .read_to_end(&mut bytes)?;
+        return Ok((bytes, true));
+    }
+    (&mut file).take(PDF_SCAN_WINDOW).read_to_end(&mut bytes)?;
+    // Keep a token cut at the end of the head from joining the tail.
+    bytes.push(b'\n');
+    file.seek(SeekFrom::End(-(PDF_SCAN_WINDOW as i64)))?;
+    file.take(PDF_SCAN_WINDOW).read_to_end(&mut bytes)?;
+    Ok((bytes, false))
+}
+
+/// Whether `argv` ran and succeeded within [`PDF_RENDER_TIMEOUT`].
+fn run_renderer(argv: &[&OsStr]) -> bool {
+    let Ok(mut command) = argv_command(argv, None) else {
+        return false;
+    };
+    let Ok(mut child) = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    let deadline = Instant::now() + PDF_RENDER_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if Instant::now() >= deadline => {
+                log::debug!("rendering a PDF page timed out");
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+            Ok(None) => std::thread::sleep(RENDER_POLL_INTERVAL),
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Render the first page of the PDF at `path` to the PNG `out`, at most
+/// `max_size` pixels on its long side.
+#[cfg(target_os = "macos")]
+fn render_pdf_page(path: &Path, max_size: u32, out: &Path) -> bool {
+    // Quick Look names its output after the input, in a directory given.
+    let dir = out.with_extension("render");
+    if fs::create_dir_all(&dir).is_err() {
+        return false;
+    }
+    let max = max_size.to_string();
+    let rendered = run_renderer(&[
+        OsStr::new("qlmanage"),
+        OsStr::new("-t"),
+        OsStr::new("-s"),
+        OsStr::new(&max),
+        OsStr::new("-o"),
+        dir.as_os_str(),
+        path.as_os_str(),
+    ]) && path
+        .file_name()
+        .map(|name| {
+            let mut name = name.to_os_string();
+            name.push(".png");
+            dir.join(name)
+        })
+        .is_some_and(|png| fs::rename(png, out).is_ok());
+    let _ = fs::remove_dir_all(&dir);
+    rendered
+}
+
+#[cfg(not(target_os = "macos"))]
+fn render_pdf_page(path: &Path, max_size: u32, out: &Path) -> bool {
+    // `pdftoppm` adds the `.png` to the prefix it is given.
+    let prefix = out.with_extension("");
+    let max = max_size.to_string();
+    run_renderer(&[
+        OsStr::new("pdftoppm"),
+        OsStr::new("-png"),
+        OsStr::new("-singlefile"),
+        OsStr::new("-f"),
+        OsStr::new("1"),
+        OsStr::new("-l"),
+        OsStr::new("1"),
+        OsStr::new("-scale-to"),
+        OsStr::new(&max),
+        path.as_os_str(),
+        prefix.as_os_str(),
+    ]) && out.is_file()
+}
+
+/// The first page of the PDF at `path` as a thumbnail, from the cache or
+/// rendered into it.
+fn pdf_page(
+    path: &Path,
+    size: u64,
+    mtime_ns: u128,
+    max_size: u32,
+    cache_dir: Option<&Path>,
+) -> Option<Thumbnail> {
+    path_arg(path).ok()?;
+    let key = thumbnail_cache_key(path, size, mtime_ns, max_size);
+    if let Some(cached) = cache_dir.and_then(|dir| cached_thumbnail(&dir.join(&key))) {
+        return Some(cached);
+    }
+
+    let out = std::env::temp_dir().join(format!("kiri-preview-{}", key));
+    let rendered = render_pdf_page(path, max_size, &out)
+        .then(|| fs::read(&out).ok())
+        .flatten();
+    let _ = fs::remove_file(&out);
+    let img = image::load_from_memory(&rendered?).ok()?;
+    let (png, page) = encode_thumbnail(img, max_size).ok()?;
+    if let Some(dir) = cache_dir {
+        store_thumbnail(&dir.join(&key), &png);
+    }
+    Some(page)
+}
+
+fn image_preview(
+    path: &Path,
+    size: u64,
+    mtime_ns: u128,
+    max_size: u32,
+    cache_dir: Option<&Path>,
+) -> Result<FilePreview, KiriError> {
+    let cache_path =
+        cache_dir.map(|d| d.join(thumbnail_cache_key(path, size, mtime_ns, max_size)));
+    let thumbnail = match cache_path.as_deref().and_then(cached_thumbnail) {
+        Some(cached) => cached,
+        None => {
+            let img = image::open(path)
+                .map_err(|e| user_error(ErrorCode::InvalidInput, "error.decode_image_failed", e))?;
+            let (png, thumbnail) = encode_thumbnail(img, max_size)?;
+            if let Some(cache_path) = &cache_path {
+                store_thumbnail(cache_path, &png);
+            }
+            thumbnail
+        }
+    };
+    Ok(FilePreview::Image {
+        mime: thumbnail.mime,
+        width: thumbnail.width,
+        height: thumbnail.height,
+        data_base64: thumbnail.data_base64,
+    })
+}
+
+/// Build a preview for `path`. `cache_dir` is injectable so tests don't
+/// write into the real home directory.
+pub fn build_preview(
+    path: &Path,
+    max_lines: usize,
+    max_size: u32,
+    cache_dir: Option<&Path>,
+) -> Result<FilePreview, KiriError> {
+    if !path.exists() {
+        return Err(user_error(
+            ErrorCode::NotFound,
+            "error.file_missing",
+            path.display(),
+        ));
+    }
+    if !path.is_file() {
+        return Err(user_error(
+            ErrorCode::InvalidInput,
+            "error.not_a_file",
+            path.display(),
+        ));
+    }
+
+    let meta = fs::metadata(path).map_err(|e| user_io_failure("error.read_failed", e))?;
+    let size = meta.len();
+    let mtime_ns = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let ext = extension_lower(path);
+
+    if is_thumbnailable(&ext) && size <= MAX_IMAGE_DECODE_BYTES {
+        return image_preview(path, size, mtime_ns, max_size, cache_dir);
+    }
+
+    if ext == "pdf" {
+        let (bytes, whole) =
+            read_pdf_ends(path).map_err(|e| user_io_failure("error.read_failed", e))?;
+        return Ok(FilePreview::Pdf {
+            page_count: count_pdf_pages(&bytes, whole),
+            size,
+            page: pdf_page(path, size, mtime_ns, max_size, cache_dir),
+        });
+    }
+
+    let mut head = Vec::with_capacity(TEXT_SNIFF_BYTES.min(size as usize));
+    fs::File::open(path)
+        .and_then(|f| f.take(TEXT_SNIFF_BYTES as u64).read_to_end(&mut head))
+        .map_err(|e| user_io_failure("error.read_failed", e))?;
+
+    if looks_binary(&head) {
+        return Ok(FilePreview::Binary { size });
+    }
+    Ok(text_preview(&head, size, max_lines))
+}
+
+/// Return a small preview of a file for hover / quick look.
+///
+/// `max_lines` defaults to [`DEFAULT_PREVIEW_LINES`] and `max_size` to
+/// [`DEFAULT_THUMBNAIL_SIZE`]; both are clamped to their maxima.
+#[tauri::command]
+pub async fn get_preview(
+    path: String,
+    max_lines: Option<usize>,
+    max_size: Option<u32>,
+) -> Result<FilePreview, KiriError> {
+    let _timer = CommandTimer::start("get_preview");
+    let max_lines = max_lines
+        .unwrap_or(DEFAULT_PREVIEW_LINES)
+        .clamp(1, MAX_PREVIEW_LINES);
+    let max_size = max_size
+        .unwrap_or(DEFAULT_THUMBNAIL_SIZE)
+        .clamp(16, MAX_THUMBNAIL_SIZE);
+    tokio::task::spawn_blocking(move || {
+        let path = normalize_input_path(&path);
+        build_preview(&path, max_lines, max_size, thumbnail_cache_dir().as_deref())
+    })
+    .await
+    .map_err(|e| format!("get_preview task panicked: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_png(path: &Path, w: u32, h: u32) {
+        let img = image::RgbImage::from_pixel(w, h, image::Rgb([200, 10, 10]));
+        img.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_text_preview_first_lines() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "one\ntwo\nthree\nfour\n").unwrap();
+
+        let preview = build_preview(&file, 2, 256, None).unwrap();
+        assert_eq!(
+            preview,
+            FilePreview::Text {
+                lines: vec!["one".to_string(), "two".to_string()],
+                truncated: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_text_preview_whole_small_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.rs");
+        fs::write(&file, "fn main() {}\n").unwrap();
+
+        let preview = build_preview(&file, 40, 256, None).unwrap();
+        assert_eq!(
+            preview,
+            FilePreview::Text {
+                lines: vec!["fn main() {}".to_string()],
+                truncated: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_binary_detection() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("blob.bin");
+        fs::write(&file, [0x7f, b'E', b'L', b'F', 0, 1, 2]).unwrap();
+
+        assert_eq!(
+            build_preview(&file, 40, 256, None).unwrap(),
+            FilePreview::Binary { size: 7 }
+        );
+    }
+
+    #[test]
+    fn test_image_thumbnail_is_downscaled_and_cached() {
+        let dir = tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let file = dir.path().join("big.png");
+        write_png(&file, 800, 400);
+
+        let preview = build_preview(&file, 40, 100, Some(&cache)).unwrap();
+        match preview {
+            FilePreview::Image { width, height, mime, .. } => {
+                assert_eq!(mime, "image/png");
+                assert_eq!((width, height), (100, 50));
+            }
+            other => panic!("expected image preview, got {:?}", other),
+        }
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+
+        // Second call is served from the cache and yields the same thumbnail.
+        let again = build_preview(&file, 40, 100, Some(&cache)).unwrap();
+        assert!(matches!(again, FilePreview::Image { width: 100, height: 50, .. }));
+    }
+
+    #[test]
+    fn test_small_image_not_upscaled() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("icon.png");
+        write_png(&file, 16, 16);
+
+        let preview = build_preview(&file, 40, 256, None).unwrap();
+        assert!(matches!(preview, FilePreview::Image { width: 16, height: 16, .. }));
+    }
+
+    #[test]
+    fn test_pdf_page_count() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("doc.pdf");
+        fs::write(
+            &file,
+            b"%PDF-1.4\n1 0 obj << /Type /Pages /Count 2 >>\n2 0 obj << /Type /Page >>\n3 0 obj << /Type/Page >>\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            build_preview(&file, 40, 256, None).unwrap(),
+            FilePreview::Pdf {
+                page_count: Some(2),
+                size: fs::metadata(&file).unwrap().len(),
+                page: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_pdf_page_count_reads_only_the_ends() {
+        // A lone `/Type /Page` doesn't count, as the middle isn't read.
+        let mut pdf = b"%PDF-1.7\n1 0 obj << /Type /Page >>\n".to_vec();
+        pdf.resize(3 * PDF_SCAN_WINDOW as usize, b' ');
+        pdf.extend_from_slice(b"2 0 obj << /Kids [1 0 R] /Type /Pages /Count 7 >>\n");
+        pdf.extend_from_slice(b"3 0 obj << /Type /Pages /Count 3 >>\n%%EOF\n");
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("big.pdf");
+        fs::write(&file, &pdf).unwrap();
+
+        let (bytes, whole) = read_pdf_ends(&file).unwrap();
+        assert!(!whole);
+        assert!(bytes.len() <= 2 * PDF_SCAN_WINDOW as usize + 1);
+        assert_eq!(count_pdf_pages(&bytes, whole), Some(7));
+        assert_eq!(count_pdf_pages(b"<< /Type /PageLabel >>", true), None);
+    }
+
+    #[test]
+    fn test_preview_missing_file() {
+        let err = build_preview(Path::new("/nonexistent/preview.txt"), 40, 256, None).unwrap_err();
+        assert!(err.message.contains("does not exist"));
+    }
+
+    #[test]
+    fn test_preview_directory_rejected() {
+        let dir = tempdir().unwrap();
+        let err = build_preview(dir.path(), 40, 256, None).unwrap_err();
+        assert!(err.message.contains("not a file"));
+    }
+
+    #[test]
+    fn test_cache_key_changes_with_mtime() {
+        let p = Path::new("/a.png");
+        assert_ne!(
+            thumbnail_cache_key(p, 10, 1, 256),
+            thumbnail_cache_key(p, 10, 2, 256)
+        );
+        // Stable across runs, so the cache survives a restart or update.
+        assert_eq!(
+            thumbnail_cache_key(p, 10, 1, 256),
+            "de2380e2faf8eed35c8d8af50e7f52113d1cea16563d3b521f46d15f2ae89614.png"
+        );
+    }
+
+    #[test]
+    fn test_prune_thumbnail_cache() {
+        let dir = tempdir().unwrap();
+        let now = SystemTime::now();
+        let write = |name: &str, len: usize, age_secs: u64| {
+            let path = dir.path().join(name);
+            fs::write(&path, vec![0u8; len]).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age_secs))
+                .unwrap();
+        };
+        write("stale.png", 10, 40 * 24 * 60 * 60);
+        write("old.png", 100, 30);
+        write("older.png", 100, 20);
+        write("new.png", 100, 10);
+
+        prune_thumbnail_cache(dir.path(), 250, THUMBNAIL_CACHE_MAX_AGE);
+        let mut left: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["new.png", "older.png"]);
+    }
+}
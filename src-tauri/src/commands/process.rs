@@ -0,0 +1,273 @@
+//! Starting child processes without a shell.
+//!
+//! Commands that launch a program (init commands, reveal in the file
+//! manager, tunnels) build it with [`argv_command`] or [`launch_command`]:
+//! the program and its arguments are passed as a list, so a path or
+//! setting containing `;`, `$(...)` or a leading `-` reaches the program
+//! as a plain argument instead of being interpreted, and it starts only in
+//! an existing absolute directory.
+//!
+//! The two differ in the environment the child gets. A launch on the
+//! user's behalf — a terminal, the file manager, an editor, the init
+//! commands of a trusted worktree — inherits kiri's whole environment
+//! through [`launch_command`], as the desktop session, proxy and
+//! toolchain variables (`XAUTHORITY`, `HTTPS_PROXY`, `JAVA_HOME`, ...) are
+//! what it needs to work. Anything else, such as a helper run on the
+//! contents of an untrusted file, uses [`argv_command`] and starts from an
+//! empty environment plus [`ENV_ALLOWLIST`].
+//!
+//! Commands configured as one string, like a project's init commands, are
+//! split with [`split_argv`], which refuses anything that needs a shell.
+//! The one place a user's shell runs a command is `user_shell_command` in
+//! tasks_commands.rs, for project tasks and agent sessions. Git hooks and
+//! dev container lifecycle commands are shell scripts by their own formats
+//! and keep running as such; see git_hooks.rs and devcontainer.rs.
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+use super::error::user_path_error;
+
+/// Variables a child of [`argv_command`] inherits from kiri's environment;
+/// anything else must be set explicitly.
+pub const ENV_ALLOWLIST: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TERM",
+    "TMPDIR",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "XDG_RUNTIME_DIR",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "SSH_AUTH_SOCK",
+    // Windows
+    "PATHEXT",
+    "SystemRoot",
+    "SystemDrive",
+    "windir",
+    "ComSpec",
+    "USERNAME",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "ProgramData",
+    "ProgramFiles",
+    "TEMP",
+    "TMP",
+];
+
+/// Programs that would run their arguments as a script.
+const SHELLS: &[&str] = &[
+    "sh",
+    "bash",
+    "zsh",
+    "dash",
+    "ksh",
+    "fish",
+    "csh",
+    "tcsh",
+    "cmd",
+    "powershell",
+    "pwsh",
+];
+
+/// Characters that mean something only to a shell.
+const SHELL_SYNTAX: &[char] = &['|', '&', ';', '<', '>', '(', ')', '$', '`', '*', '?', '\n'];
+
+fn is_shell(program: &OsStr) -> bool {
+    Path::new(program)
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .is_some_and(|name| SHELLS.contains(&name.to_ascii_lowercase().as_str()))
+}
+
+/// A [`Command`] for `argv[0]` with the rest of `argv` as its arguments,
+/// started in `cwd` when given, with only the [`ENV_ALLOWLIST`] variables
+/// of kiri's environment. Fails for an empty argv, a `cwd` that is not an
+/// existing absolute directory, or a shell given arguments; a shell may
+/// only be opened interactively.
+pub fn argv_command<S: AsRef<OsStr>>(argv: &[S], cwd: Option<&Path>) -> Result<Command, String> {
+    let mut command = launch_command(argv, cwd)?;
+    command.env_clear();
+    for name in ENV_ALLOWLIST {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+    Ok(command)
+}
+
+/// [`argv_command`] inheriting kiri's whole environment, for launches on
+/// the user's behalf.
+pub fn launch_command<S: AsRef<OsStr>>(argv: &[S], cwd: Option<&Path>) -> Result<Command, String> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| "No program to run".to_string())?;
+    let program = program.as_ref();
+    if program.is_empty() {
+        return Err("No program to run".to_string());
+    }
+    if is_shell(program) && !args.is_empty() {
+        return Err(format!(
+            "Refusing to pass arguments to {}: commands run without a shell",
+            program.to_string_lossy()
+        ));
+    }
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(cwd) = cwd {
+        if !cwd.is_absolute() || !cwd.is_dir() {
+            return Err(user_path_error("Not a directory", cwd));
+        }
+        command.current_dir(cwd);
+    }
+    Ok(command)
+}
+
+/// `path` as an argument for another program. It must be absolute, so it
+/// can't be taken for an option.
+pub fn path_arg(path: &Path) -> Result<&Path, String> {
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Err(user_path_error("Not an absolute path", path))
+    }
+}
+
+/// Split a configured command line into its argv. Words are separated by
+/// whitespace; single quotes, double quotes and backslashes work as in a
+/// POSIX shell. Anything else a shell would interpret — pipes,
+/// redirections, `;`, `&&`, variables, substitutions, globs or a leading
+/// `NAME=value` — is an error, since the command runs without one.
+pub fn split_argv(line: &str) -> Result<Vec<String>, String> {
+    let needs_shell = |what: &str| {
+        format!(
+            "`{}` uses {}, which needs a shell; put it in a script and run that",
+            line.trim(),
+            what
+        )
+    };
+    let unterminated = || format!("Unterminated quote in `{}`", line.trim());
+    let mut argv = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('$') | Some('`') => return Err(needs_shell("substitution")),
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated()),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    word.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c if SHELL_SYNTAX.contains(&c) => {
+                return Err(needs_shell(&format!("`{}`", c.escape_default())));
+            }
+            c if c.is_whitespace() => argv.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    argv.extend(word);
+    match argv.first() {
+        None => Err("The command is empty".to_string()),
+        Some(program) if program.contains('=') => Err(needs_shell("a variable assignment")),
+        Some(_) => Ok(argv),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_argv_quoting() {
+        assert_eq!(
+            split_argv("  npm   run 'build:all' \"my dir\" a\\ b ''").unwrap(),
+            vec!["npm", "run", "build:all", "my dir", "a b", ""]
+        );
+        assert_eq!(
+            split_argv(r#"echo "say \"hi\"" 'it''s'"#).unwrap(),
+            vec!["echo", r#"say "hi""#, "its"]
+        );
+        // Quoted, shell syntax is just text.
+        assert_eq!(split_argv("echo 'a && b'").unwrap(), vec!["echo", "a && b"]);
+    }
+
+    #[test]
+    fn test_split_argv_refuses_shell_syntax() {
+        for line in [
+            "npm ci && npm run build",
+            "cat a | grep b",
+            "echo $HOME",
+            "echo \"$(whoami)\"",
+            "rm -rf build/*",
+            "make > log",
+            "NODE_ENV=test npm test",
+            "echo 'unterminated",
+            "   ",
+        ] {
+            assert!(split_argv(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_argv_command_checks() {
+        let cwd = std::env::temp_dir();
+        assert!(argv_command(&["sh", "-c", "rm -rf /"], None).is_err());
+        assert!(argv_command(&["/bin/bash", "script.sh"], None).is_err());
+        assert!(argv_command::<&str>(&[], None).is_err());
+        assert!(argv_command(&["ls"], Some(Path::new("relative"))).is_err());
+        assert!(argv_command(&["ls"], Some(&cwd.join("kiri-missing-dir"))).is_err());
+        // An interactive shell is fine.
+        assert!(argv_command(&["cmd"], None).is_ok());
+
+        let command = argv_command(&["git", "status"], Some(&cwd)).unwrap();
+        assert_eq!(command.get_current_dir(), Some(cwd.as_path()));
+        for (name, _) in command.get_envs() {
+            assert!(ENV_ALLOWLIST.iter().any(|allowed| *allowed == name));
+        }
+
+        // A launch inherits everything and is checked the same way.
+        let command = launch_command(&["git", "status"], Some(&cwd)).unwrap();
+        assert_eq!(command.get_envs().count(), 0);
+        assert!(launch_command(&["sh", "-c", "rm -rf /"], None).is_err());
+        assert!(launch_command(&["ls"], Some(Path::new("relative"))).is_err());
+
+        assert!(path_arg(Path::new("-rf")).is_err());
+        assert!(path_arg(&cwd).is_ok());
+    }
+}
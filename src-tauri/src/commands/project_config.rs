@@ -0,0 +1,810 @@
+//! Project-level configuration committed as `.kiri.toml`.
+//!
+//! The file sits at the root of a checkout (or of a package inside one)
+//! and covers what a project wants kiri to do for everyone working on it:
+//!
+//! ```toml
+//! [worktree]
+//! copy = [".env", "config/*.local.json"]  # copied into new worktrees
+//! init = ["npm install"]                  # run in order in a new worktree
+//!
+//! [ports]
+//! strategy = "offset"                     # "shared", "offset" or "free"
+//! base = 3000
+//! step = 10
+//!
+//! [ignore]
+//! dirs = ["dist", "coverage"]
+//!
+//! [generated]
+//! paths = ["src/gen", "*.pb.go"]          # generated, see generated.rs
+//! exclude = ["build"]                     # never generated
+//!
+//! [env]
+//! NODE_ENV = "development"                # set in terminals of the project
+//!
+//! [tasks.dev]
+//! command = "npm run dev"
+//! dir = "web"
+//! description = "Dev server"
+//! ```
+//!
+//! Only the part of TOML such a file needs is read: tables, dotted keys,
+//! strings, integers, booleans and arrays of those. Every problem becomes
+//! a [`ConfigError`] with its line, and the entries that are valid still
+//! apply, so a typo in one task doesn't lose the others. Watching and
+//! reload events live in project_config_commands.rs.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::fs_path::path_to_wire;
+use super::settings::check_env_name;
+
+pub const CONFIG_FILE: &str = ".kiri.toml";
+
+const DEFAULT_PORT_BASE: u16 = 3000;
+const DEFAULT_PORT_STEP: u16 = 10;
+
+/// How the dev servers of a project's worktrees get their ports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "strategy", rename_all = "kebab-case")]
+pub enum PortStrategy {
+    /// Every worktree uses the ports the project configures itself.
+    #[default]
+    Shared,
+    /// The n-th worktree gets `base + n * step`.
+    Offset { base: u16, step: u16 },
+    /// Each worktree gets a free port when its server starts.
+    Free,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigTask {
+    pub name: String,
+    pub command: String,
+    /// Relative to the directory of `.kiri.toml`.
+    pub dir: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectConfig {
+    /// Glob patterns of untracked files copied into new worktrees.
+    pub copy_patterns: Vec<String>,
+    /// Commands run one after another in a new worktree.
+    pub init_commands: Vec<String>,
+    pub ports: PortStrategy,
+    /// Directories left out of the file tree, search and watchers.
+    pub ignored_dirs: Vec<String>,
+    /// Patterns of generated files besides the built-in ones.
+    pub generated_paths: Vec<String>,
+    /// Patterns of files never taken for generated.
+    pub generated_exclude: Vec<String>,
+    pub tasks: Vec<ConfigTask>,
+    /// Variables set in terminals, over those of the app settings.
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigError {
+    /// 1-based; `None` when the file couldn't be read.
+    pub line: Option<usize>,
+    /// Dotted key the error is about, e.g. `tasks.dev.command`.
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn at(line: usize, key: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            key: key.map(String::from),
+            message: message.into(),
+        }
+    }
+}
+
+/// The outcome of loading the configuration that applies to a directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigLoad {
+    /// The `.kiri.toml` found, if any.
+    pub path: Option<String>,
+    pub config: ProjectConfig,
+    pub errors: Vec<ConfigError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Bool(_) => "a boolean",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+/// `key = value` with the table it is in folded into the key.
+#[derive(Debug)]
+struct Entry {
+    line: usize,
+    key: Vec<String>,
+    value: Value,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Whitespace, newlines and comments, as allowed inside an array.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            if !(self.eat('\n') || self.eat('\r')) {
+                return;
+            }
+        }
+    }
+
+    /// Move to the start of the next line after an error.
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                return;
+            }
+        }
+    }
+
+    /// The rest of the line must be blank or a comment.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.skip_comment();
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(format!("unexpected `{}` after value", c)),
+        }
+    }
+
+    /// A dotted key, up to `end`.
+    fn key(&mut self, end: char) -> Result<Vec<String>, String> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(is_bare_key_char) {
+                        self.bump();
+                    }
+                    if start == self.pos {
+                        return Err("expected a key".to_string());
+                    }
+                    self.src[start..self.pos].to_string()
+                }
+            };
+            parts.push(part);
+            self.skip_spaces();
+            if self.eat('.') {
+                continue;
+            }
+            if self.eat(end) {
+                return Ok(parts);
+            }
+            return Err(match self.peek() {
+                None | Some('\n') => format!("expected `{}`", end),
+                Some(c) => format!("unexpected `{}` in key", c),
+            });
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.bump();
+        if self.src[self.pos..].starts_with("\"\"") {
+            return Err("multi-line strings are not supported".to_string());
+        }
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err("unterminated string".to_string()),
+                Some('"') => return Ok(out),
+                Some('\\') => match self.bump() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('r') => out.push('\r'),
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('u') => {
+                        let start = self.pos;
+                        for _ in 0..4 {
+                            self.bump();
+                        }
+                        let c = u32::from_str_radix(&self.src[start..self.pos], 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or("invalid \\u escape")?;
+                        out.push(c);
+                    }
+                    _ => return Err("invalid escape in string".to_string()),
+                },
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.bump();
+        let start = self.pos;
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err("unterminated string".to_string()),
+                Some('\'') => return Ok(self.src[start..self.pos - 1].to_string()),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.eat(']') {
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_blank();
+                    if !self.eat(',') && self.peek() != Some(']') {
+                        return Err("expected `,` or `]` in array".to_string());
+                    }
+                }
+            }
+            Some('{') => Err("inline tables are not supported".to_string()),
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| is_bare_key_char(c) || c == '+' || c == '.')
+                {
+                    self.bump();
+                }
+                let word = &self.src[start..self.pos];
+                match word {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "" => Err("expected a value".to_string()),
+                    _ => word
+                        .replace('_', "")
+                        .parse()
+                        .map(Value::Integer)
+                        .map_err(|_| format!("unsupported value `{}`", word)),
+                }
+            }
+        }
+    }
+
+    /// One line: blank, a comment, a table header or a key / value pair.
+    fn statement(&mut self, table: &mut Vec<String>) -> Result<Option<Entry>, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('#' | '\n' | '\r') => {
+                self.end_of_line()?;
+                Ok(None)
+            }
+            Some('[') => {
+                self.bump();
+                if self.peek() == Some('[') {
+                    return Err("arrays of tables are not supported".to_string());
+                }
+                *table = self.key(']')?;
+                self.end_of_line()?;
+                Ok(None)
+            }
+            _ => {
+                let line = self.line;
+                let mut key = table.clone();
+                key.extend(self.key('=')?);
+                self.skip_spaces();
+                let value = self.value()?;
+                self.end_of_line()?;
+                Ok(Some(Entry { line, key, value }))
+            }
+        }
+    }
+}
+
+/// Key / value pairs of `text`, and errors for the lines that aren't.
+fn parse_document(text: &str) -> (Vec<Entry>, Vec<ConfigError>) {
+    let mut parser = Parser {
+        src: text,
+        pos: 0,
+        line: 1,
+    };
+    let mut table = Vec::new();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut errors = Vec::new();
+    while parser.peek().is_some() {
+        let line = parser.line;
+        match parser.statement(&mut table) {
+            Ok(Some(entry)) => {
+                if entries.iter().any(|e| e.key == entry.key) {
+                    let key = entry.key.join(".");
+                    let message = format!("`{}` is defined twice", key);
+                    errors.push(ConfigError::at(entry.line, Some(&key), message));
+                } else {
+                    entries.push(entry);
+                }
+            }
+            Ok(None) => {}
+            Err(message) => {
+                if parser.line == line {
+                    parser.skip_line();
+                }
+                errors.push(ConfigError::at(line, None, message));
+            }
+        }
+    }
+    (entries, errors)
+}
+
+/// A path that stays inside the project: relative and without `..`.
+fn check_relative(path: &str) -> Result<(), String> {
+    if path.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    let path = Path::new(path);
+    let escapes = path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes || path.has_root() {
+        return Err("must be a relative path inside the project".to_string());
+    }
+    Ok(())
+}
+
+fn check_pattern(pattern: &str) -> Result<(), String> {
+    check_relative(pattern)?;
+    glob::Pattern::new(pattern)
+        .map(|_| ())
+        .map_err(|e| format!("is not a valid pattern: {}", e))
+}
+
+fn check_command(command: &str) -> Result<(), String> {
+    if command.trim().is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    Ok(())
+}
+
+/// Maps entries onto a [`ProjectConfig`], collecting what doesn't fit.
+struct Schema {
+    config: ProjectConfig,
+    errors: Vec<ConfigError>,
+}
+
+impl Schema {
+    fn error(&mut self, entry: &Entry, message: impl Into<String>) {
+        let key = entry.key.join(".");
+        self.errors
+            .push(ConfigError::at(entry.line, Some(&key), message));
+    }
+
+    fn string(&mut self, entry: &Entry, check: fn(&str) -> Result<(), String>) -> Option<String> {
+        let Value::String(s) = &entry.value else {
+            let message = format!("must be a string, not {}", entry.value.type_name());
+            self.error(entry, message);
+            return None;
+        };
+        match check(s) {
+            Ok(()) => Some(s.clone()),
+            Err(message) => {
+                self.error(entry, message);
+                None
+            }
+        }
+    }
+
+    /// An array of strings; invalid items are reported and left out.
+    fn strings(&mut self, entry: &Entry, check: fn(&str) -> Result<(), String>) -> Vec<String> {
+        let Value::Array(items) = &entry.value else {
+            let message = format!("must be an array, not {}", entry.value.type_name());
+            self.error(entry, message);
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        for item in items {
+            match item {
+                Value::String(s) => match check(s) {
+                    Ok(()) => out.push(s.clone()),
+                    Err(message) => self.error(entry, format!("`{}` {}", s, message)),
+                },
+                other => {
+                    let message = format!("items must be strings, not {}", other.type_name());
+                    self.error(entry, message);
+                }
+            }
+        }
+        out
+    }
+
+    fn port_number(&mut self, entry: &Entry, min: i64) -> Option<u16> {
+        match entry.value {
+            Value::Integer(n) if n >= min && n <= i64::from(u16::MAX) => Some(n as u16),
+            Value::Integer(_) => {
+                self.error(entry, format!("must be between {} and {}", min, u16::MAX));
+                None
+            }
+            _ => {
+                let message = format!("must be an integer, not {}", entry.value.type_name());
+                self.error(entry, message);
+                None
+            }
+        }
+    }
+
+    fn apply(entries: &[Entry]) -> (ProjectConfig, Vec<ConfigError>) {
+        let mut schema = Schema {
+            config: ProjectConfig::default(),
+            errors: Vec::new(),
+        };
+        let mut strategy: Option<(&Entry, String)> = None;
+        let (mut base, mut step) = (None, None);
+        let mut tasks: BTreeMap<String, (usize, HashMap<&str, &Entry>)> = BTreeMap::new();
+        for entry in entries {
+            let key: Vec<&str> = entry.key.iter().map(String::as_str).collect();
+            match key.as_slice() {
+                ["worktree", "copy"] => {
+                    schema.config.copy_patterns = schema.strings(entry, check_pattern)
+                }
+                ["worktree", "init"] => {
+                    schema.config.init_commands = schema.strings(entry, check_command)
+                }
+                ["ports", "strategy"] => {
+                    let check = |s: &str| match s {
+                        "shared" | "offset" | "free" => Ok(()),
+                        _ => Err("must be \"shared\", \"offset\" or \"free\"".to_string()),
+                    };
+                    if let Some(s) = schema.string(entry, check) {
+                        strategy = Some((entry, s));
+                    }
+                }
+                ["ports", "base"] => base = Some((entry, schema.port_number(entry, 1))),
+                ["ports", "step"] => step = Some((entry, schema.port_number(entry, 1))),
+                ["ignore", "dirs"] => {
+                    schema.config.ignored_dirs = schema.strings(entry, check_relative)
+                }
+                ["generated", "paths"] => {
+                    schema.config.generated_paths = schema.strings(entry, check_pattern)
+                }
+                ["generated", "exclude"] => {
+                    schema.config.generated_exclude = schema.strings(entry, check_pattern)
+                }
+                ["env", name] => {
+                    if let Err(message) = check_env_name(name) {
+                        schema.error(entry, message);
+                    } else if let Some(value) = schema.string(entry, |_| Ok(())) {
+                        schema.config.env.insert(name.to_string(), value);
+                    }
+                }
+                ["tasks", name, field @ ("command" | "dir" | "description")] => {
+                    tasks
+                        .entry(name.to_string())
+                        .or_insert_with(|| (entry.line, HashMap::new()))
+                        .1
+                        .insert(*field, entry);
+                }
+                _ => {
+                    let message = format!("unknown key `{}`", entry.key.join("."));
+                    schema.error(entry, message);
+                }
+            }
+        }
+
+        schema.config.ports = match strategy.as_ref().map(|(_, s)| s.as_str()) {
+            Some("offset") => PortStrategy::Offset {
+                base: base.and_then(|(_, b)| b).unwrap_or(DEFAULT_PORT_BASE),
+                step: step.and_then(|(_, s)| s).unwrap_or(DEFAULT_PORT_STEP),
+            },
+            other => {
+                // Out of range values were reported already.
+                for (entry, _) in base.into_iter().chain(step).filter(|(_, n)| n.is_some()) {
+                    schema.error(entry, "only applies to the \"offset\" strategy");
+                }
+                match other {
+                    Some("free") => PortStrategy::Free,
+                    _ => PortStrategy::Shared,
+                }
+            }
+        };
+
+        for (name, (line, fields)) in tasks {
+            let Some(command) = fields.get("command") else {
+                let key = format!("tasks.{}.command", name);
+                let error = ConfigError::at(line, Some(&key), "is required");
+                schema.errors.push(error);
+                continue;
+            };
+            let Some(command) = schema.string(command, check_command) else {
+                continue;
+            };
+            let dir = match fields.get("dir") {
+                Some(dir) => match schema.string(dir, check_relative) {
+                    Some(dir) => Some(dir),
+                    None => continue,
+                },
+                None => None,
+            };
+            let description = fields
+                .get("description")
+                .and_then(|entry| schema.string(entry, |_| Ok(())));
+            schema.config.tasks.push(ConfigTask {
+                name,
+                command,
+                dir,
+                description,
+            });
+        }
+        schema.errors.sort_by_key(|e| e.line);
+        (schema.config, schema.errors)
+    }
+}
+
+/// Parse and validate the text of a `.kiri.toml`.
+pub fn parse_config(text: &str) -> (ProjectConfig, Vec<ConfigError>) {
+    let (entries, mut errors) = parse_document(text);
+    let (config, schema_errors) = Schema::apply(&entries);
+    errors.extend(schema_errors);
+    errors.sort_by_key(|e| e.line);
+    (config, errors)
+}
+
+/// The `.kiri.toml` that applies to `dir`: the nearest one in it or a
+/// parent, up to the checkout root.
+pub fn find_config(dir: &Path) -> Option<PathBuf> {
+    for ancestor in dir.ancestors() {
+        let candidate = ancestor.join(CONFIG_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+pub fn load_config(dir: &Path) -> ConfigLoad {
+    let Some(path) = find_config(dir) else {
+        return ConfigLoad::default();
+    };
+    let (config, errors) = match std::fs::read_to_string(&path) {
+        Ok(text) => parse_config(&text),
+        Err(e) => {
+            let error = ConfigError {
+                line: None,
+                key: None,
+                message: format!("Failed to read {}: {}", path.display(), e),
+            };
+            (ProjectConfig::default(), vec![error])
+        }
+    };
+    ConfigLoad {
+        path: Some(path_to_wire(&path)),
+        config,
+        errors,
+    }
+}
+
+/// Loaded configurations by the directory they were asked for.
+#[derive(Debug, Default)]
+pub struct ProjectConfigs {
+    loaded: HashMap<PathBuf, ConfigLoad>,
+}
+
+pub type ProjectConfigsState = Arc<Mutex<ProjectConfigs>>;
+
+impl ProjectConfigs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&mut self, dir: &Path) -> ConfigLoad {
+        self.loaded
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| load_config(dir))
+            .clone()
+    }
+
+    /// Reload the configurations that a change to a `.kiri.toml` under
+    /// `root` may affect. Returns those that changed, by directory.
+    pub fn reload_under(&mut self, root: &Path) -> Vec<(PathBuf, ConfigLoad)> {
+        let mut changed = Vec::new();
+        for (dir, load) in self.loaded.iter_mut() {
+            let affected = dir.starts_with(root)
+                || root.starts_with(dir)
+                || load
+                    .path
+                    .as_deref()
+                    .is_some_and(|path| Path::new(path).starts_with(root));
+            if !affected {
+                continue;
+            }
+            let fresh = load_config(dir);
+            if fresh != *load {
+                *load = fresh.clone();
+                changed.push((dir.clone(), fresh));
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_config() {
+        let text = r#"
+# Project settings
+[worktree]
+copy = [
+  ".env",         # secrets
+  'config/*.local.json',
+]
+init = ["npm install", "npm run \"db:migrate\""]
+
+[ports]
+strategy = "offset"
+base = 4_000
+
+[ignore]
+dirs = ["dist", "../outside"]
+
+[tasks.dev]
+command = "npm run dev"
+dir = "web"
+
+[tasks."build:docs"]
+description = "Docs"
+
+[tasks]
+lint.command = "npm run lint"
+lint.colour = true
+"#;
+        let (config, errors) = parse_config(text);
+        assert_eq!(config.copy_patterns, vec![".env", "config/*.local.json"]);
+        assert_eq!(config.init_commands[1], "npm run \"db:migrate\"");
+        assert_eq!(
+            config.ports,
+            PortStrategy::Offset {
+                base: 4000,
+                step: 10
+            }
+        );
+        assert_eq!(config.ignored_dirs, vec!["dist"]);
+        let names: Vec<&str> = config.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["dev", "lint"]);
+        assert_eq!(config.tasks[0].dir.as_deref(), Some("web"));
+
+        let described: Vec<(Option<usize>, Option<&str>)> =
+            errors.iter().map(|e| (e.line, e.key.as_deref())).collect();
+        assert_eq!(
+            described,
+            vec![
+                (Some(15), Some("ignore.dirs")),
+                (Some(22), Some("tasks.build:docs.command")),
+                (Some(26), Some("tasks.lint.colour")),
+            ]
+        );
+
+        let (config, errors) = parse_config("[ports]\nbase = 70000\nstrategy = 'shared'\nx = {}\n");
+        assert_eq!(config.ports, PortStrategy::Shared);
+        let lines: Vec<Option<usize>> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![Some(2), Some(4)]);
+
+        let (config, errors) = parse_config("[env]\nNODE_ENV = 'dev'\n\"A=B\" = 'x'\nN = 1\n");
+        assert_eq!(config.env.keys().collect::<Vec<_>>(), vec!["NODE_ENV"]);
+        assert_eq!(errors.len(), 2);
+
+        let (config, errors) =
+            parse_config("[generated]\npaths = ['src/gen', '*.pb.go']\nexclude = ['/build']\n");
+        assert_eq!(config.generated_paths, vec!["src/gen", "*.pb.go"]);
+        assert!(config.generated_exclude.is_empty());
+        assert_eq!(errors[0].key.as_deref(), Some("generated.exclude"));
+    }
+
+    #[test]
+    fn test_find_and_reload() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("web/src")).unwrap();
+        let web = root.join("web/src");
+
+        let mut configs = ProjectConfigs::new();
+        assert_eq!(configs.get(&web), ConfigLoad::default());
+
+        std::fs::write(root.join(CONFIG_FILE), "[ignore]\ndirs = [\"dist\"]\n").unwrap();
+        let changed = configs.reload_under(root);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].1.config.ignored_dirs, vec!["dist"]);
+        assert!(configs.reload_under(root).is_empty());
+
+        std::fs::write(
+            root.join("web").join(CONFIG_FILE),
+            "[ports]\nstrategy = 1\n",
+        )
+        .unwrap();
+        let load = configs.reload_under(root).remove(0).1;
+        assert!(load.path.unwrap().ends_with("web/.kiri.toml"));
+        assert_eq!(load.errors[0].key.as_deref(), Some("ports.strategy"));
+    }
+}
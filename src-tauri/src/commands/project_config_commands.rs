@@ -0,0 +1,64 @@
+//! Tauri commands and watcher glue for `.kiri.toml`; parsing and
+//! validation live in project_config.rs.
+//!
+//! The project watchers report changes to a `.kiri.toml` through
+//! [`on_project_config_changed`], which reloads the configurations loaded
+//! so far and emits `project-config-reloaded` for each one that changed,
+//! with its validation errors.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::error::KiriError;
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::project_config::{ConfigLoad, ProjectConfigsState, CONFIG_FILE};
+
+pub const PROJECT_CONFIG_RELOADED_EVENT: &str = "project-config-reloaded";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectConfigReloaded {
+    /// Directory the configuration was loaded for.
+    pub dir: String,
+    #[serde(flatten)]
+    pub load: ConfigLoad,
+}
+
+/// Whether `path` is a project configuration file.
+pub fn is_project_config(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == CONFIG_FILE)
+}
+
+/// Called from the file watcher of `watched_path` when a `.kiri.toml`
+/// under it changed.
+pub fn on_project_config_changed(app: &AppHandle, watched_path: &str) {
+    let Some(state) = app.try_state::<ProjectConfigsState>() else {
+        return;
+    };
+    let root = normalize_input_path(watched_path);
+    let changed = state.lock_recover().reload_under(&root);
+    for (dir, load) in changed {
+        let event = ProjectConfigReloaded {
+            dir: path_to_wire(&dir),
+            load,
+        };
+        let _ = app.emit(PROJECT_CONFIG_RELOADED_EVENT, &event);
+    }
+}
+
+/// The `.kiri.toml` that applies to `path`, parsed, with its validation
+/// errors. Without one the defaults apply.
+#[tauri::command]
+pub async fn get_project_config(
+    state: tauri::State<'_, ProjectConfigsState>,
+    path: String,
+) -> Result<ConfigLoad, KiriError> {
+    let state = Arc::clone(&state);
+    run_blocking("get_project_config", move || {
+        Ok(state.lock_recover().get(&normalize_input_path(&path)))
+    })
+    .await
+}
@@ -0,0 +1,359 @@
+//! Project statistics for the dashboard: lines of code per language,
+//! counted with tokei, and commit activity from git2.
+//!
+//! Counting a large tree takes a while, so stats are computed in a job
+//! (see project_stats_commands.rs) and cached per project. A cached
+//! result is served as is until HEAD moves or it is older than
+//! [`STATS_TTL_MS`], and marked `stale` after that while a new one is
+//! computed.
+
+use git2::{Repository, Sort};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::fs_path::display_path;
+use super::jobs::JobId;
+use super::search::DEFAULT_EXCLUDED_DIRS;
+
+/// Weeks of history in [`CommitActivity::weekly`].
+pub const ACTIVITY_WEEKS: usize = 52;
+
+/// Authors listed in [`CommitActivity::top_authors`].
+pub const MAX_TOP_AUTHORS: usize = 10;
+
+/// How long cached stats are served without recounting, since edits to
+/// the working tree don't move HEAD.
+pub const STATS_TTL_MS: u64 = 10 * 60 * 1000;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+const WEEK_SECS: i64 = 7 * DAY_SECS;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageStats {
+    pub name: String,
+    pub files: usize,
+    pub code: usize,
+    pub comments: usize,
+    pub blanks: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorCommits {
+    pub name: String,
+    pub commits: u32,
+}
+
+/// Commits on HEAD's history over the last [`ACTIVITY_WEEKS`] weeks, by
+/// author date.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitActivity {
+    pub total_commits: u32,
+    /// Commits per week, oldest first; the last entry is the week up to
+    /// now.
+    pub weekly: Vec<u32>,
+    /// Commits per day of the week, Sunday first, in each commit's own
+    /// time zone.
+    pub by_weekday: Vec<u32>,
+    /// Commits per hour of the day, in each commit's own time zone.
+    pub by_hour: Vec<u32>,
+    /// Most active authors, most commits first.
+    pub top_authors: Vec<AuthorCommits>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub project: String,
+    /// Commit the stats were computed at; `None` outside a repository or
+    /// before the first commit.
+    pub head: Option<String>,
+    /// Most code first.
+    pub languages: Vec<LanguageStats>,
+    pub totals: LanguageStats,
+    /// `None` outside a repository.
+    pub activity: Option<CommitActivity>,
+    /// Milliseconds since the Unix epoch.
+    pub computed_ms: u64,
+    /// HEAD moved or the stats are older than [`STATS_TTL_MS`].
+    pub stale: bool,
+}
+
+/// Lines per language under `root`, most code first. Files git ignores
+/// and the usual build and dependency directories are skipped.
+pub fn count_lines(root: &Path) -> Vec<LanguageStats> {
+    let mut languages = tokei::Languages::new();
+    languages.get_statistics(&[root], DEFAULT_EXCLUDED_DIRS, &tokei::Config::default());
+    let mut stats: Vec<LanguageStats> = languages
+        .iter()
+        .map(|(language_type, language)| (language_type, language.summarise()))
+        .filter(|(_, language)| !language.is_empty())
+        .map(|(language_type, language)| LanguageStats {
+            name: language_type.name().to_string(),
+            files: language.reports.len(),
+            code: language.code,
+            comments: language.comments,
+            blanks: language.blanks,
+        })
+        .collect();
+    stats.sort_by(|a, b| b.code.cmp(&a.code).then_with(|| a.name.cmp(&b.name)));
+    stats
+}
+
+pub fn totals(languages: &[LanguageStats]) -> LanguageStats {
+    languages.iter().fold(
+        LanguageStats {
+            name: "Total".to_string(),
+            files: 0,
+            code: 0,
+            comments: 0,
+            blanks: 0,
+        },
+        |mut total, language| {
+            total.files += language.files;
+            total.code += language.code;
+            total.comments += language.comments;
+            total.blanks += language.blanks;
+            total
+        },
+    )
+}
+
+/// The commit HEAD points at, if `root` is in a repository with one.
+pub fn head_commit(root: &Path) -> Option<String> {
+    let repo = Repository::discover(root).ok()?;
+    let head = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(head.id().to_string())
+}
+
+/// Commit activity of the repository containing `root` as of `now`
+/// (seconds since the Unix epoch). `None` outside a repository.
+pub fn commit_activity(root: &Path, now: i64) -> Result<Option<CommitActivity>, String> {
+    let Ok(repo) = Repository::discover(root) else {
+        return Ok(None);
+    };
+    let mut activity = CommitActivity {
+        total_commits: 0,
+        weekly: vec![0; ACTIVITY_WEEKS],
+        by_weekday: vec![0; 7],
+        by_hour: vec![0; 24],
+        top_authors: Vec::new(),
+    };
+    if repo.head().is_err() {
+        // No commits yet.
+        return Ok(Some(activity));
+    }
+
+    let since = now - ACTIVITY_WEEKS as i64 * WEEK_SECS;
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    walk.set_sorting(Sort::TIME)
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    walk.push_head()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    let mut authors: HashMap<String, u32> = HashMap::new();
+    for oid in walk {
+        let oid = oid.map_err(|e| format!("Failed to walk history: {}", e))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("Failed to read commit {}: {}", oid, e))?;
+        // Newest committed first, so nothing past this one is recent.
+        if commit.time().seconds() < since {
+            break;
+        }
+        let author = commit.author();
+        let when = author.when();
+        let seconds = when.seconds();
+        if !(since..=now).contains(&seconds) {
+            continue;
+        }
+        activity.total_commits += 1;
+        let weeks_ago = ((now - seconds) / WEEK_SECS) as usize;
+        activity.weekly[ACTIVITY_WEEKS - 1 - weeks_ago.min(ACTIVITY_WEEKS - 1)] += 1;
+        let local = seconds + i64::from(when.offset_minutes()) * 60;
+        // 1970-01-01 was a Thursday.
+        activity.by_weekday[(local.div_euclid(DAY_SECS) + 4).rem_euclid(7) as usize] += 1;
+        activity.by_hour[(local.rem_euclid(DAY_SECS) / 3600) as usize] += 1;
+        let name = author.name().unwrap_or("Unknown").to_string();
+        *authors.entry(name).or_default() += 1;
+    }
+
+    let mut authors: Vec<AuthorCommits> = authors
+        .into_iter()
+        .map(|(name, commits)| AuthorCommits { name, commits })
+        .collect();
+    authors.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+    authors.truncate(MAX_TOP_AUTHORS);
+    activity.top_authors = authors;
+    Ok(Some(activity))
+}
+
+/// Cached stats by project, and the projects being counted now.
+#[derive(Debug, Default)]
+pub struct ProjectStatsCache {
+    stats: HashMap<String, ProjectStats>,
+    /// Projects with a job under way; the id is `None` until
+    /// [`ProjectStatsCache::set_job`] records it.
+    running: HashMap<String, Option<JobId>>,
+}
+
+pub type ProjectStatsState = Arc<Mutex<ProjectStatsCache>>;
+
+impl ProjectStatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn store(&mut self, stats: ProjectStats) {
+        self.running.remove(&stats.project);
+        self.stats.insert(stats.project.clone(), stats);
+    }
+
+    /// The cached stats for `root`, marked stale when `head` isn't the
+    /// commit they were computed at or they are older than
+    /// [`STATS_TTL_MS`] at `now_ms`.
+    pub fn get(&self, root: &Path, head: Option<&str>, now_ms: u64) -> Option<ProjectStats> {
+        let mut stats = self.stats.get(&display_path(root))?.clone();
+        stats.stale = stats.head.as_deref() != head
+            || now_ms.saturating_sub(stats.computed_ms) > STATS_TTL_MS;
+        Some(stats)
+    }
+
+    /// Claim `root` for a new job. `Err` with the running job's id, if
+    /// known, when one is already under way.
+    pub fn begin(&mut self, root: &Path) -> Result<(), Option<JobId>> {
+        let key = display_path(root);
+        if let Some(job) = self.running.get(&key) {
+            return Err(*job);
+        }
+        self.running.insert(key, None);
+        Ok(())
+    }
+
+    pub fn set_job(&mut self, root: &Path, job: JobId) {
+        if let Some(running) = self.running.get_mut(&display_path(root)) {
+            *running = Some(job);
+        }
+    }
+
+    /// Release `root` after a job that stored nothing.
+    pub fn abandon(&mut self, root: &Path) {
+        self.running.remove(&display_path(root));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn commit_at(repo: &Repository, author: &str, seconds: i64, offset_minutes: i32) {
+        let time = git2::Time::new(seconds, offset_minutes);
+        let signature = git2::Signature::new(author, "dev@example.com", &time).unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "change",
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_commit_activity_histograms() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        assert_eq!(
+            commit_activity(dir.path(), 0)
+                .unwrap()
+                .unwrap()
+                .total_commits,
+            0
+        );
+
+        // Thursday 2024-01-04 12:00 UTC.
+        let now = 1_704_369_600;
+        commit_at(&repo, "old", now - 60 * WEEK_SECS, 0);
+        // Wednesday 23:00 UTC, which is Thursday 08:00 in UTC+9.
+        commit_at(&repo, "ana", now - 13 * 3600, 9 * 60);
+        commit_at(&repo, "ana", now - 8 * DAY_SECS, 0);
+        commit_at(&repo, "ben", now - 3600, 0);
+
+        let activity = commit_activity(dir.path(), now).unwrap().unwrap();
+        assert_eq!(activity.total_commits, 3);
+        assert_eq!(activity.weekly[ACTIVITY_WEEKS - 1], 2);
+        assert_eq!(activity.weekly[ACTIVITY_WEEKS - 2], 1);
+        assert_eq!(activity.by_weekday[4], 2);
+        assert_eq!(activity.by_weekday[3], 1);
+        assert_eq!(activity.by_hour[8], 1);
+        assert_eq!(activity.by_hour[11], 1);
+        assert_eq!(
+            activity.top_authors,
+            [
+                AuthorCommits {
+                    name: "ana".to_string(),
+                    commits: 2
+                },
+                AuthorCommits {
+                    name: "ben".to_string(),
+                    commits: 1
+                },
+            ]
+        );
+
+        let outside = TempDir::new().unwrap();
+        assert_eq!(commit_activity(outside.path(), now).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cached_stats_go_stale() {
+        let root = Path::new("/src/kiri");
+        let languages = vec![LanguageStats {
+            name: "Rust".to_string(),
+            files: 2,
+            code: 100,
+            comments: 10,
+            blanks: 5,
+        }];
+        let mut cache = ProjectStatsCache::new();
+        assert_eq!(cache.begin(root), Ok(()));
+        cache.set_job(root, 7);
+        assert_eq!(cache.begin(root), Err(Some(7)));
+        cache.store(ProjectStats {
+            project: display_path(root),
+            head: Some("abc".to_string()),
+            totals: totals(&languages),
+            languages,
+            activity: None,
+            computed_ms: 1_000,
+            stale: false,
+        });
+        assert_eq!(cache.begin(root), Ok(()));
+        cache.abandon(root);
+
+        let fresh = cache.get(root, Some("abc"), 2_000).unwrap();
+        assert!(!fresh.stale);
+        assert_eq!(fresh.totals.code, 100);
+        assert!(cache.get(root, Some("def"), 2_000).unwrap().stale);
+        assert!(
+            cache
+                .get(root, Some("abc"), 2_000 + STATS_TTL_MS)
+                .unwrap()
+                .stale
+        );
+        assert!(cache.get(Path::new("/src/other"), None, 0).is_none());
+    }
+}
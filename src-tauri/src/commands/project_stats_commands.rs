@@ -0,0 +1,115 @@
+//! Job and Tauri command for the project dashboard's statistics; counting
+//! and the cache live in project_stats.rs.
+//!
+//! [`get_project_stats`] returns the cached stats and, when there are
+//! none or they are stale, starts a job of kind `project-stats`. When it
+//! ends the new stats are cached and sent as `project-stats-updated`.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+use super::clock::now_ms;
+use super::error::KiriError;
+use super::fs_path::{display_path, input_path};
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::spawn_job;
+use super::lock_ext::LockExt;
+use super::project_stats::{
+    commit_activity, count_lines, head_commit, totals, ProjectStats, ProjectStatsState,
+};
+
+pub const PROJECT_STATS_UPDATED_EVENT: &str = "project-stats-updated";
+
+const PROJECT_STATS_JOB_KIND: &str = "project-stats";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStatsResponse {
+    /// The cached stats, possibly stale; `None` before the first count.
+    pub stats: Option<ProjectStats>,
+    /// The job computing new stats, if one is under way.
+    pub job: Option<JobId>,
+}
+
+/// Stats for `root` as of now. `None` if cancelled.
+fn compute(ctx: &JobContext, root: &Path) -> Result<Option<ProjectStats>, String> {
+    let head = head_commit(root);
+    ctx.progress(Some(0), Some("Counting lines".to_string()));
+    let languages = count_lines(root);
+    if ctx.is_cancelled() {
+        return Ok(None);
+    }
+    ctx.progress(Some(70), Some("Reading history".to_string()));
+    let computed_ms = now_ms();
+    let activity = commit_activity(root, (computed_ms / 1000) as i64)?;
+    Ok(Some(ProjectStats {
+        project: display_path(root),
+        head,
+        totals: totals(&languages),
+        languages,
+        activity,
+        computed_ms,
+        stale: false,
+    }))
+}
+
+/// Lines of code per language and commit activity for the project at
+/// `path`. Counting runs in a job; with `refresh` a new count starts even
+/// when the cached one is fresh.
+#[tauri::command]
+pub fn get_project_stats(
+    app: AppHandle,
+    state: tauri::State<'_, ProjectStatsState>,
+    path: String,
+    refresh: Option<bool>,
+) -> Result<ProjectStatsResponse, KiriError> {
+    let root = input_path(&path)?;
+    let head = head_commit(&root);
+    let stats = state.lock_recover().get(&root, head.as_deref(), now_ms());
+    if !refresh.unwrap_or(false) && stats.as_ref().is_some_and(|s| !s.stale) {
+        return Ok(ProjectStatsResponse { stats, job: None });
+    }
+    if let Err(job) = state.lock_recover().begin(&root) {
+        return Ok(ProjectStatsResponse { stats, job });
+    }
+
+    let label = format!("Project statistics for {}", display_path(&root));
+    let job_app = app.clone();
+    let job_state = Arc::clone(&state);
+    let job_root = root.clone();
+    let spawned = spawn_job(
+        &app,
+        PROJECT_STATS_JOB_KIND,
+        &label,
+        move |ctx| match compute(ctx, &job_root) {
+            Ok(Some(stats)) => {
+                let _ = job_app.emit(PROJECT_STATS_UPDATED_EVENT, &stats);
+                job_state.lock_recover().store(stats);
+                Ok(())
+            }
+            Ok(None) => {
+                job_state.lock_recover().abandon(&job_root);
+                Ok(())
+            }
+            Err(e) => {
+                job_state.lock_recover().abandon(&job_root);
+                Err(e)
+            }
+        },
+    );
+    match spawned {
+        Ok(job) => {
+            state.lock_recover().set_job(&root, job);
+            Ok(ProjectStatsResponse {
+                stats,
+                job: Some(job),
+            })
+        }
+        Err(e) => {
+            state.lock_recover().abandon(&root);
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Recently opened projects, for the start screen and File > Open Recent.
+//!
+//! The list is saved in `~/.kiri/recent-projects.json`, most recently
+//! opened first with pinned projects ahead of the rest. Pinned projects
+//! stay until they are unpinned or removed; the others are trimmed to
+//! [`MAX_RECENT_PROJECTS`]. Validating the list drops projects whose
+//! folder is gone and refreshes the branch of the rest.
+
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::file_io::write_file_contents_atomic;
+use super::fs_path::display_path;
+
+/// Unpinned projects kept in the list.
+pub const MAX_RECENT_PROJECTS: usize = 10;
+
+pub fn recent_projects_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("recent-projects.json"))
+}
+
+/// The repository a project is in, as of when it was last looked at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VcsInfo {
+    /// `None` on a detached HEAD.
+    pub branch: Option<String>,
+    /// Whether the project is a linked worktree rather than a main
+    /// checkout.
+    pub is_worktree: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentProject {
+    pub path: String,
+    pub name: String,
+    /// When it was last opened, from [`now_ms`](super::clock::now_ms).
+    pub last_opened: u64,
+    /// `None` outside a repository.
+    #[serde(default)]
+    pub vcs: Option<VcsInfo>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// What [`inspect_project`] found at a listed project's path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectCheck {
+    Missing,
+    Present(Option<VcsInfo>),
+}
+
+/// The repository containing `path`, if any.
+pub fn read_vcs_info(path: &Path) -> Option<VcsInfo> {
+    let repo = Repository::discover(path).ok()?;
+    let branch = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(str::to_string));
+    Some(VcsInfo {
+        branch,
+        is_worktree: repo.is_worktree(),
+    })
+}
+
+pub fn inspect_project(path: &Path) -> ProjectCheck {
+    if path.is_dir() {
+        ProjectCheck::Present(read_vcs_info(path))
+    } else {
+        ProjectCheck::Missing
+    }
+}
+
+/// Projects from the `recentProjects` key the frontend kept in
+/// `kiri-settings.json` before the list moved here. Entries that don't
+/// parse are skipped.
+pub fn import_legacy(value: serde_json::Value) -> Vec<RecentProject> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Legacy {
+        path: String,
+        name: String,
+        last_opened: f64,
+        git_branch: Option<String>,
+    }
+    let serde_json::Value::Array(entries) = value else {
+        return Vec::new();
+    };
+    entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value::<Legacy>(entry).ok())
+        .map(|legacy| RecentProject {
+            path: legacy.path,
+            name: legacy.name,
+            last_opened: legacy.last_opened.max(0.0) as u64,
+            vcs: legacy.git_branch.map(|branch| VcsInfo {
+                branch: Some(branch),
+                is_worktree: false,
+            }),
+            pinned: false,
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecentProjectsFile {
+    projects: Vec<RecentProject>,
+}
+
+#[derive(Debug, Default)]
+pub struct RecentProjects {
+    file: Option<PathBuf>,
+    projects: Vec<RecentProject>,
+}
+
+pub type RecentProjectsState = Arc<Mutex<RecentProjects>>;
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| display_path(path))
+}
+
+/// Pinned first, then most recently opened first; unpinned projects past
+/// [`MAX_RECENT_PROJECTS`] are dropped.
+fn arrange(projects: &mut Vec<RecentProject>) {
+    projects.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then(b.last_opened.cmp(&a.last_opened))
+    });
+    let mut unpinned = 0;
+    projects.retain(|p| {
+        unpinned += usize::from(!p.pinned);
+        p.pinned || unpinned <= MAX_RECENT_PROJECTS
+    });
+}
+
+impl RecentProjects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let mut projects = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<RecentProjectsFile>(&contents) {
+                    Ok(file) => Some(file.projects),
+                    Err(e) => {
+                        log::warn!("failed to parse recent projects: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        arrange(&mut projects);
+        Self {
+            file: Some(path),
+            projects,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = RecentProjectsFile {
+            projects: self.projects.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)
+            .map_err(|e| format!("Failed to serialize recent projects: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    /// Apply `change` and save, or leave the list as it was when saving
+    /// fails.
+    fn update<T>(
+        &mut self,
+        change: impl FnOnce(&mut Vec<RecentProject>) -> T,
+    ) -> Result<T, String> {
+        let previous = self.projects.clone();
+        let result = change(&mut self.projects);
+        arrange(&mut self.projects);
+        if let Err(e) = self.save() {
+            self.projects = previous;
+            return Err(e);
+        }
+        Ok(result)
+    }
+
+    pub fn list(&self) -> &[RecentProject] {
+        &self.projects
+    }
+
+    /// Move `path` to the top of the list, adding it if needed, with the
+    /// repository it is in now.
+    pub fn record_open(
+        &mut self,
+        path: &Path,
+        vcs: Option<VcsInfo>,
+        now: u64,
+    ) -> Result<(), String> {
+        let key = display_path(path);
+        self.update(|projects| {
+            let pinned = projects.iter().any(|p| p.path == key && p.pinned);
+            projects.retain(|p| p.path != key);
+            projects.push(RecentProject {
+                path: key.clone(),
+                name: dir_name(path),
+                last_opened: now,
+                vcs,
+                pinned,
+            });
+        })
+    }
+
+    /// Add projects that aren't listed yet, e.g. from [`import_legacy`].
+    pub fn import(&mut self, imported: Vec<RecentProject>) -> Result<(), String> {
+        self.update(|projects| {
+            for project in imported {
+                if !projects.iter().any(|p| p.path == project.path) {
+                    projects.push(project);
+                }
+            }
+        })
+    }
+
+    pub fn set_pinned(&mut self, path: &Path, pinned: bool) -> Result<(), String> {
+        let key = display_path(path);
+        if !self.projects.iter().any(|p| p.path == key) {
+            return Err(format!("{} is not a recent project", key));
+        }
+        self.update(|projects| {
+            for project in projects.iter_mut().filter(|p| p.path == key) {
+                project.pinned = pinned;
+            }
+        })
+    }
+
+    /// Returns whether `path` was listed.
+    pub fn remove(&mut self, path: &Path) -> Result<bool, String> {
+        let key = display_path(path);
+        self.update(|projects| {
+            let before = projects.len();
+            projects.retain(|p| p.path != key);
+            projects.len() != before
+        })
+    }
+
+    /// Remove every project that isn't pinned.
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.update(|projects| projects.retain(|p| p.pinned))
+    }
+
+    /// Apply the results of [`inspect_project`], by path: missing projects
+    /// are removed, pinned ones too, and the rest get their current
+    /// repository. Projects without a result are left alone. Returns the
+    /// removed paths.
+    pub fn apply_checks(
+        &mut self,
+        checks: HashMap<String, ProjectCheck>,
+    ) -> Result<Vec<String>, String> {
+        self.update(|projects| {
+            let mut removed = Vec::new();
+            projects.retain_mut(|project| match checks.get(&project.path) {
+                Some(ProjectCheck::Missing) => {
+                    removed.push(project.path.clone());
+                    false
+                }
+                Some(ProjectCheck::Present(vcs)) => {
+                    project.vcs = vcs.clone();
+                    true
+                }
+                None => true,
+            });
+            removed
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn paths(store: &RecentProjects) -> Vec<&str> {
+        store.list().iter().map(|p| p.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_pinned_first_and_unpinned_trimmed() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("recent-projects.json");
+        let mut store = RecentProjects::load(file.clone());
+        for i in 0..=MAX_RECENT_PROJECTS as u64 {
+            store
+                .record_open(Path::new(&format!("/src/p{}", i)), None, i)
+                .unwrap();
+        }
+        assert_eq!(store.list().len(), MAX_RECENT_PROJECTS);
+        assert_eq!(store.list()[0].name, "p10");
+        assert!(!paths(&store).contains(&"/src/p0"));
+
+        store.set_pinned(Path::new("/src/p1"), true).unwrap();
+        store.record_open(Path::new("/src/p11"), None, 11).unwrap();
+        store.record_open(Path::new("/src/p1"), None, 12).unwrap();
+        assert!(store.set_pinned(Path::new("/src/p0"), true).is_err());
+        store.clear().unwrap();
+
+        let store = RecentProjects::load(file);
+        assert_eq!(paths(&store), ["/src/p1"]);
+        assert!(store.list()[0].pinned);
+        assert_eq!(store.list()[0].last_opened, 12);
+    }
+
+    #[test]
+    fn test_apply_checks_drops_missing_and_refreshes_vcs() {
+        let dir = TempDir::new().unwrap();
+        let mut store = RecentProjects::new();
+        let project = dir.path().join("kiri");
+        std::fs::create_dir(&project).unwrap();
+        let gone = dir.path().join("gone");
+        store.record_open(&project, None, 1).unwrap();
+        store.record_open(&gone, None, 2).unwrap();
+        store.set_pinned(&gone, true).unwrap();
+        store.record_open(Path::new("/src/new"), None, 3).unwrap();
+
+        git2::Repository::init(&project).unwrap();
+        let checks = [&project, &gone]
+            .into_iter()
+            .map(|p| (display_path(p), inspect_project(p)))
+            .collect();
+        let removed = store.apply_checks(checks).unwrap();
+        assert_eq!(removed, [display_path(&gone)]);
+        assert_eq!(paths(&store), ["/src/new", display_path(&project).as_str()]);
+        // No commit yet, so no branch either.
+        let vcs = VcsInfo {
+            branch: None,
+            is_worktree: false,
+        };
+        assert_eq!(store.list()[1].vcs, Some(vcs));
+        assert!(!store.remove(&gone).unwrap());
+    }
+
+    #[test]
+    fn test_import_legacy() {
+        let value = serde_json::json!([
+            { "path": "/src/kiri", "name": "kiri", "lastOpened": 1700000000000.0, "gitBranch": "main" },
+            { "path": "/src/bad" },
+            { "path": "/src/plain", "name": "plain", "lastOpened": 5, "gitBranch": null },
+        ]);
+        let projects = import_legacy(value);
+        assert_eq!(projects.len(), 2);
+        assert_eq!(projects[0].last_opened, 1_700_000_000_000);
+        assert_eq!(
+            projects[0].vcs.as_ref().unwrap().branch.as_deref(),
+            Some("main")
+        );
+        assert_eq!(projects[1].vcs, None);
+        assert!(import_legacy(serde_json::json!({})).is_empty());
+    }
+}
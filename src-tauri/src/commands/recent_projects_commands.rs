@@ -0,0 +1,167 @@
+//! Tauri commands for the recent projects of recent_projects.rs.
+//!
+//! Every change is saved, sent to all windows as a
+//! `recent-projects-changed` event carrying the new list, and shown in
+//! File > Open Recent and the tray.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::clock::now_ms;
+use super::error::KiriError;
+use super::fs_path::{input_path, normalize_input_path};
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::menu::set_recent_projects;
+use super::recent_projects::{
+    import_legacy, inspect_project, read_vcs_info, recent_projects_file_path, RecentProject,
+    RecentProjects, RecentProjectsState,
+};
+
+pub const RECENT_PROJECTS_CHANGED_EVENT: &str = "recent-projects-changed";
+
+/// Where the frontend kept the list before, under `recentProjects`.
+const LEGACY_STORE: &str = "kiri-settings.json";
+
+/// Read `~/.kiri/recent-projects.json` into the managed state. Call from
+/// setup, before the menu is built. The first time, the list the frontend
+/// kept in its settings store is imported.
+pub fn load_recent_projects(app: &AppHandle) {
+    let Some(path) = recent_projects_file_path() else {
+        return;
+    };
+    let Some(state) = app.try_state::<RecentProjectsState>() else {
+        return;
+    };
+    let first_run = !path.exists();
+    let mut store = RecentProjects::load(path);
+    if first_run {
+        use tauri_plugin_store::StoreExt;
+        let legacy = app
+            .store(LEGACY_STORE)
+            .ok()
+            .and_then(|store| store.get("recentProjects"))
+            .map(import_legacy)
+            .unwrap_or_default();
+        if !legacy.is_empty() {
+            if let Err(e) = store.import(legacy) {
+                log::warn!("failed to import recent projects: {}", e);
+            }
+        }
+    }
+    *state.lock_recover() = store;
+}
+
+/// Tell the windows and the menus about the new list.
+fn changed(app: &AppHandle, projects: &[RecentProject]) {
+    let _ = app.emit(RECENT_PROJECTS_CHANGED_EVENT, projects);
+    set_recent_projects(app, projects.to_vec());
+}
+
+/// Apply `change` to the list and publish the result.
+fn update(
+    app: &AppHandle,
+    state: &RecentProjectsState,
+    change: impl FnOnce(&mut RecentProjects) -> Result<(), String>,
+) -> Result<Vec<RecentProject>, String> {
+    let projects = {
+        let mut store = state.lock_recover();
+        change(&mut store)?;
+        store.list().to_vec()
+    };
+    changed(app, &projects);
+    Ok(projects)
+}
+
+/// Recent projects, pinned first, then most recently opened first.
+#[tauri::command]
+pub fn list_recent_projects(
+    state: tauri::State<'_, RecentProjectsState>,
+) -> Result<Vec<RecentProject>, KiriError> {
+    Ok(state.lock_recover().list().to_vec())
+}
+
+/// Record that the project at `path` was opened, moving it to the top.
+#[tauri::command]
+pub async fn add_recent_project(
+    app: AppHandle,
+    state: tauri::State<'_, RecentProjectsState>,
+    path: String,
+) -> Result<Vec<RecentProject>, KiriError> {
+    let state = Arc::clone(&state);
+    run_blocking("add_recent_project", move || {
+        let dir = input_path(&path)?;
+        let vcs = read_vcs_info(&dir);
+        update(&app, &state, |store| store.record_open(&dir, vcs, now_ms()))
+    })
+    .await
+}
+
+/// Pin or unpin the project at `path`. Pinned projects are listed first
+/// and aren't trimmed from the list.
+#[tauri::command]
+pub fn pin_recent_project(
+    app: AppHandle,
+    state: tauri::State<'_, RecentProjectsState>,
+    path: String,
+    pinned: bool,
+) -> Result<Vec<RecentProject>, KiriError> {
+    let dir = input_path(&path)?;
+    Ok(update(&app, &state, |store| {
+        store.set_pinned(&dir, pinned)
+    })?)
+}
+
+#[tauri::command]
+pub fn remove_recent_project(
+    app: AppHandle,
+    state: tauri::State<'_, RecentProjectsState>,
+    path: String,
+) -> Result<Vec<RecentProject>, KiriError> {
+    let dir = input_path(&path)?;
+    Ok(update(&app, &state, |store| store.remove(&dir).map(drop))?)
+}
+
+/// Remove every project that isn't pinned.
+#[tauri::command]
+pub fn clear_recent_projects(
+    app: AppHandle,
+    state: tauri::State<'_, RecentProjectsState>,
+) -> Result<Vec<RecentProject>, KiriError> {
+    Ok(update(&app, &state, RecentProjects::clear)?)
+}
+
+/// Remove projects whose folder no longer exists and refresh the branch
+/// of the others. The folders are checked without holding the list, so
+/// a slow drive doesn't hold up the other commands.
+#[tauri::command]
+pub async fn validate_recent_projects(
+    app: AppHandle,
+    state: tauri::State<'_, RecentProjectsState>,
+) -> Result<Vec<RecentProject>, KiriError> {
+    let state = Arc::clone(&state);
+    run_blocking("validate_recent_projects", move || {
+        let paths: Vec<String> = state
+            .lock_recover()
+            .list()
+            .iter()
+            .map(|p| p.path.clone())
+            .collect();
+        let checks: HashMap<_, _> = paths
+            .into_iter()
+            .map(|path| {
+                let check = inspect_project(&normalize_input_path(&path));
+                (path, check)
+            })
+            .collect();
+        update(&app, &state, |store| {
+            let removed = store.apply_checks(checks)?;
+            if !removed.is_empty() {
+                log::info!("dropped missing recent projects: {:?}", removed);
+            }
+            Ok(())
+        })
+    })
+    .await
+}
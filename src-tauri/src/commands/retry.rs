@@ -0,0 +1,284 @@
+//! Retries for network operations that fail on a flaky connection.
+//!
+//! Fetch, push and code host API reads are safe to repeat. When one fails
+//! transiently (a dropped connection, a timeout, a 503 from the host), it
+//! is tried again after an exponentially growing, jittered wait, up to the
+//! attempts of its [`RetryPolicy`]. An API request is transient when its
+//! [`KiriError`] is retryable; git tells only through its stderr, see
+//! [`is_transient_git_failure`]. Pull and API writes aren't retried: repeating a merge
+//! or a `POST` could apply it twice.
+//!
+//! Each retried operation logs its attempts; git commands also return
+//! them as a [`RetryReport`] next to their result.
+
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+
+use super::error::KiriError;
+use super::jobs::CancelToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in all, the first included.
+    pub max_attempts: u32,
+    /// Longest wait after the first failure; it doubles after each one.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+/// Policy for fetch, push and API reads.
+pub const NETWORK_RETRY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    base_delay: Duration::from_millis(500),
+    max_delay: Duration::from_secs(8),
+};
+
+/// A single attempt, for operations that aren't safe to repeat.
+pub const NO_RETRY: RetryPolicy = RetryPolicy {
+    max_attempts: 1,
+    base_delay: Duration::ZERO,
+    max_delay: Duration::ZERO,
+};
+
+/// How often a wait between attempts checks for cancellation.
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+impl RetryPolicy {
+    /// The wait after `failures` failed attempts: half the backoff for
+    /// that many failures, plus `jitter` (0 to 1) of the other half, so
+    /// clients that failed together don't retry together.
+    pub fn delay(&self, failures: u32, jitter: f64) -> Duration {
+        let factor = 1u32
+            .checked_shl(failures.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let backoff = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        backoff / 2 + (backoff / 2).mul_f64(jitter.clamp(0.0, 1.0))
+    }
+}
+
+/// Attempts an operation took.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RetryReport {
+    /// Attempts made, the last included.
+    pub attempts: u32,
+    /// Why each retried attempt failed, oldest first.
+    pub failures: Vec<String>,
+}
+
+/// What git and the curl under it print for failures of the connection,
+/// lowercased. Git exits 128 for these and for a rejected push alike, so
+/// its stderr is all there is to tell them apart.
+const TRANSIENT_GIT_STDERR: &[&str] = &[
+    "could not resolve host",
+    "could not read from remote repository",
+    "connection timed out",
+    "operation timed out",
+    "connection refused",
+    "connection reset",
+    "the remote end hung up unexpectedly",
+    "early eof",
+    "rpc failed",
+    "returned error: 502",
+    "returned error: 503",
+    "returned error: 504",
+];
+
+/// Whether a git network command that failed with `stderr` may work if
+/// repeated.
+pub fn is_transient_git_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    TRANSIENT_GIT_STDERR
+        .iter()
+        .any(|needle| stderr.contains(needle))
+}
+
+/// The error of a git command's `outcome` if it is transient, for the
+/// `transient_failure` argument of [`retry_blocking`].
+pub fn transient_git_error<T>(outcome: &Result<T, String>) -> Option<String> {
+    outcome
+        .as_ref()
+        .err()
+        .filter(|stderr| is_transient_git_failure(stderr))
+        .cloned()
+}
+
+/// The error of `outcome` if its code is retryable, for the
+/// `transient_failure` argument of [`retry_async`].
+pub fn retryable_error<T>(outcome: &Result<T, KiriError>) -> Option<String> {
+    outcome
+        .as_ref()
+        .err()
+        .filter(|e| e.retryable)
+        .map(|e| e.message.clone())
+}
+
+fn jitter() -> f64 {
+    // The top 53 bits of a random UUID, as a fraction of 1.
+    (uuid::Uuid::new_v4().as_u128() >> 75) as f64 / (1u64 << 53) as f64
+}
+
+/// Bookkeeping shared by the blocking and async loops.
+struct Attempts<'a> {
+    operation: &'a str,
+    policy: &'a RetryPolicy,
+    report: RetryReport,
+}
+
+impl<'a> Attempts<'a> {
+    fn new(operation: &'a str, policy: &'a RetryPolicy) -> Self {
+        Self {
+            operation,
+            policy,
+            report: RetryReport::default(),
+        }
+    }
+
+    /// Record an attempt that ended with `failure`, if transient; the
+    /// wait before the next one, or `None` when this one is final.
+    fn next_delay(&mut self, failure: Option<String>) -> Option<Duration> {
+        self.report.attempts += 1;
+        let attempts = self.report.attempts;
+        let Some(failure) = failure.filter(|_| attempts < self.policy.max_attempts) else {
+            if attempts > 1 {
+                log::info!("{} finished after {} attempts", self.operation, attempts);
+            }
+            return None;
+        };
+        let delay = self.policy.delay(attempts, jitter());
+        log::warn!(
+            "{} failed on attempt {} ({}); retrying in {:?}",
+            self.operation,
+            attempts,
+            failure,
+            delay
+        );
+        self.report.failures.push(failure);
+        Some(delay)
+    }
+}
+
+/// Run `attempt` until `transient_failure` finds nothing to retry in its
+/// outcome or `policy` runs out of attempts, and return the last outcome.
+/// A cancelled wait skips to the next attempt, which is expected to see
+/// `cancel` and stop.
+pub fn retry_blocking<T>(
+    operation: &str,
+    policy: &RetryPolicy,
+    cancel: &CancelToken,
+    mut attempt: impl FnMut() -> T,
+    transient_failure: impl Fn(&T) -> Option<String>,
+) -> (T, RetryReport) {
+    let mut attempts = Attempts::new(operation, policy);
+    loop {
+        let outcome = attempt();
+        let Some(delay) = attempts.next_delay(transient_failure(&outcome)) else {
+            return (outcome, attempts.report);
+        };
+        let mut waited = Duration::ZERO;
+        while waited < delay && !cancel.is_cancelled() {
+            let step = CANCEL_POLL.min(delay - waited);
+            std::thread::sleep(step);
+            waited += step;
+        }
+    }
+}
+
+/// [`retry_blocking`] for async work, such as API requests.
+pub async fn retry_async<T, F: Future<Output = T>>(
+    operation: &str,
+    policy: &RetryPolicy,
+    mut attempt: impl FnMut() -> F,
+    transient_failure: impl Fn(&T) -> Option<String>,
+) -> (T, RetryReport) {
+    let mut attempts = Attempts::new(operation, policy);
+    loop {
+        let outcome = attempt().await;
+        let Some(delay) = attempts.next_delay(transient_failure(&outcome)) else {
+            return (outcome, attempts.report);
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(2),
+    };
+
+    #[test]
+    fn test_delay_doubles_up_to_the_cap() {
+        let delays: Vec<_> = (1..=6).map(|n| NETWORK_RETRY.delay(n, 1.0)).collect();
+        assert_eq!(
+            delays,
+            [500, 1000, 2000, 4000, 8000, 8000].map(Duration::from_millis)
+        );
+        assert_eq!(NETWORK_RETRY.delay(2, 0.0), Duration::from_millis(500));
+        assert_eq!(NETWORK_RETRY.delay(40, 1.0), NETWORK_RETRY.max_delay);
+    }
+
+    #[test]
+    fn test_retries_transient_failures_only() {
+        let mut outcomes = vec![
+            Err("fatal: the remote end hung up unexpectedly".to_string()),
+            Err("Connection timed out".to_string()),
+            Ok(()),
+        ]
+        .into_iter();
+        let (outcome, report) = retry_blocking(
+            "fetch",
+            &FAST,
+            &CancelToken::default(),
+            || outcomes.next().unwrap(),
+            transient_git_error,
+        );
+        assert_eq!(outcome, Ok(()));
+        assert_eq!(report.attempts, 3);
+        assert_eq!(report.failures.len(), 2);
+
+        let rejected = "! [rejected] main -> main (fetch first)".to_string();
+        let (outcome, report) = retry_blocking(
+            "push",
+            &FAST,
+            &CancelToken::default(),
+            || Err::<(), _>(rejected.clone()),
+            transient_git_error,
+        );
+        assert_eq!(outcome, Err(rejected));
+        assert_eq!(report.attempts, 1);
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let (outcome, report) = retry_blocking(
+            "fetch",
+            &FAST,
+            &CancelToken::default(),
+            || Err::<(), _>("Could not resolve host: github.com".to_string()),
+            transient_git_error,
+        );
+        assert!(outcome.is_err());
+        assert_eq!(report.attempts, FAST.max_attempts);
+        assert_eq!(report.failures.len(), 2);
+    }
+
+    #[test]
+    fn test_retries_by_code_not_wording() {
+        use crate::commands::error::ErrorCode;
+        let unavailable = KiriError::new(ErrorCode::Unavailable, "GitHub API error (503)");
+        assert_eq!(
+            retryable_error::<()>(&Err(unavailable)).as_deref(),
+            Some("GitHub API error (503)")
+        );
+        let invalid = KiriError::new(ErrorCode::InvalidInput, "Invalid network proxy URL");
+        assert_eq!(retryable_error::<()>(&Err(invalid)), None);
+        assert!(!is_transient_git_failure(
+            "error: failed to push some refs: network settings rejected"
+        ));
+    }
+}
@@ -1,9 +1,18 @@
 use glob::Pattern;
 use serde::Serialize;
 use std::fs;
-use std::io::{BufRead, BufReader};
 use std::path::Path;
 
+use super::error::KiriError;
+use super::fs_budget::{run_budgeted, Deadline, SEARCH_BUDGET};
+use super::fs_path::{display_path, input_path, normalize_input_path, os_str_to_wire};
+use super::generated::GeneratedRules;
+use super::jobs::CancelToken;
+use super::latency::run_blocking;
+use super::file_lines::LineReader;
+use super::operations::{self, OperationRequest};
+use super::performance::CommandTimer;
+
 #[derive(Debug, Clone, Serialize)]
 pub struct FileSearchResult {
     pub path: String,
@@ -83,15 +92,32 @@ const MAX_FILE_SEARCH_RESULTS: usize = 5_000;
 /// hand-built loops) without affecting any realistic project.
 const MAX_SEARCH_DEPTH: usize = 32;
 
-fn collect_files(
-    dir: &Path,
-    query: &str,
-    results: &mut Vec<FileSearchResult>,
+/// When [`collect_files`] stops before the end of the tree: once the
+/// search is cancelled, or with what it has found at the deadline.
+#[derive(Debug, Clone, Default)]
+struct FileWalkStop {
+    cancel: CancelToken,
+    deadline: Option<Deadline>,
+}
+
+impl FileWalkStop {
+    fn reached(&self) -> bool {
+        self.cancel.is_cancelled() || self.deadline.is_some_and(|d| d.expired())
+    }
+}
+
+/// What [`collect_files`] looks for and where it stops.
+struct FileWalk<'a> {
+    query: &'a str,
     max_results: usize,
     ignore_hidden: bool,
-    depth: usize,
-) {
-    if results.len() >= max_results || depth > MAX_SEARCH_DEPTH {
+    /// Generated directories aren't descended into; see generated.rs.
+    generated: &'a GeneratedRules,
+    stop: &'a FileWalkStop,
+}
+
+fn collect_files(dir: &Path, walk: &FileWalk, results: &mut Vec<FileSearchResult>, depth: usize) {
+    if results.len() >= walk.max_results || depth > MAX_SEARCH_DEPTH {
         return;
     }
 
@@ -101,32 +127,26 @@ fn collect_files(
     };
 
     for entry in entries.flatten() {
-        if results.len() >= max_results {
+        if results.len() >= walk.max_results || walk.stop.reached() {
             break;
         }
 
         let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
+        let name = os_str_to_wire(&entry.file_name());
 
-        if ignore_hidden && name.starts_with('.') {
+        if walk.ignore_hidden && name.starts_with('.') {
             continue;
         }
 
         let is_dir = path.is_dir();
 
         if is_dir {
-            let dir_name = name.to_lowercase();
-            if dir_name != "node_modules"
-                && dir_name != "target"
-                && dir_name != ".git"
-                && dir_name != "dist"
-                && dir_name != "build"
-            {
-                collect_files(&path, query, results, max_results, ignore_hidden, depth + 1);
+            if name != ".git" && !walk.generated.is_generated(&path, true) {
+                collect_files(&path, walk, results, depth + 1);
             }
-        } else if let Some(score) = fuzzy_match(query, &name) {
+        } else if let Some(score) = fuzzy_match(walk.query, &name) {
             results.push(FileSearchResult {
-                path: path.to_string_lossy().to_string(),
+                path: display_path(&path),
                 name,
                 is_dir,
                 score,
@@ -135,12 +155,40 @@ fn collect_files(
     }
 }
 
-fn search_files_blocking(
+pub(crate) fn search_files_blocking(
+    root_path: String,
+    query: String,
+    max_results: usize,
+) -> Result<Vec<FileSearchResult>, String> {
+    search_files_cancellable(root_path, query, max_results, &CancelToken::default())
+}
+
+/// [`search_files_blocking`] that stops with "Operation cancelled" once
+/// `cancel` is tripped.
+fn search_files_cancellable(
     root_path: String,
     query: String,
     max_results: usize,
+    cancel: &CancelToken,
 ) -> Result<Vec<FileSearchResult>, String> {
-    let root = Path::new(&root_path);
+    let stop = FileWalkStop {
+        cancel: cancel.clone(),
+        deadline: None,
+    };
+    search_files_until(root_path, query, max_results, &stop).map(|(results, _)| results)
+}
+
+/// [`search_files_cancellable`] that also stops at `stop`'s deadline, with
+/// the matches found by then and whether it got there.
+fn search_files_until(
+    root_path: String,
+    query: String,
+    max_results: usize,
+    stop: &FileWalkStop,
+) -> Result<(Vec<FileSearchResult>, bool), String> {
+    let _span = tracing::info_span!("search::files", root = %root_path, query = %query).entered();
+    let root = input_path(&root_path)?;
+    let root = root.as_path();
 
     if !root.exists() {
         return Err("Path does not exist".to_string());
@@ -153,30 +201,70 @@ fn search_files_blocking(
         n => n.min(MAX_FILE_SEARCH_RESULTS),
     };
 
+    let generated = GeneratedRules::load(root);
+    let walk = FileWalk {
+        query: &query,
+        max_results: effective_max,
+        ignore_hidden: true,
+        generated: &generated,
+        stop,
+    };
     let mut results = Vec::new();
-    collect_files(root, &query, &mut results, effective_max, true, 0);
+    collect_files(root, &walk, &mut results, 0);
+    operations::check(&stop.cancel)?;
+    let partial = stop.reached();
 
     results.sort_by_key(|r| std::cmp::Reverse(r.score));
 
-    Ok(results)
+    Ok((results, partial))
 }
 
 /// Tauri command wrapper. The walk runs on a `spawn_blocking` thread so
-/// large queries don't stall the IPC runtime.
+/// large queries don't stall the IPC runtime. It gets [`SEARCH_BUDGET`]
+/// and returns the matches found by then; see fs_budget.rs.
 #[tauri::command]
 pub async fn search_files(
     root_path: String,
     query: String,
     max_results: usize,
-) -> Result<Vec<FileSearchResult>, String> {
-    tokio::task::spawn_blocking(move || search_files_blocking(root_path, query, max_results))
-        .await
-        .map_err(|e| format!("search_files task panicked: {}", e))?
+    operation: Option<OperationRequest>,
+) -> Result<Vec<FileSearchResult>, KiriError> {
+    let _timer = CommandTimer::start("search_files");
+    let operation = operations::begin(operation);
+    let root = normalize_input_path(&root_path);
+    let cancel = operation.token().clone();
+    let result = tokio::task::spawn_blocking(move || {
+        run_budgeted("search_files", &root, SEARCH_BUDGET, move |deadline| {
+            let stop = FileWalkStop {
+                cancel,
+                deadline: Some(deadline),
+            };
+            search_files_until(root_path, query, max_results, &stop).map_err(KiriError::from)
+        })
+    })
+    .await
+    .map_err(|e| format!("search_files task panicked: {}", e))?;
+    operation.finish(result)
+}
+
+/// Directories the bookmark search for a moved file never descends into.
+/// Search and the TODO scanner skip generated files instead; see
+/// generated.rs.
+pub(crate) const DEFAULT_EXCLUDED_DIRS: &[&str] = &["node_modules", "target", ".git", "dist", "build"];
+
+/// Whether a file's extension marks it as text worth scanning line-by-line.
+pub(crate) fn is_searchable_file(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    matches!(
+        ext,
+        "rs" | "ts" | "tsx" | "js" | "jsx" | "svelte" | "html" | "css" | "scss" | "json" | "md"
+            | "toml" | "yaml" | "yml" | "txt"
+    )
 }
 
 /// Check if a path should be excluded based on the exclude patterns.
 /// Supports both simple names (e.g., "node_modules") and glob patterns (e.g., "**/*.min.js")
-fn should_exclude(path: &Path, exclude_patterns: &[Pattern]) -> bool {
+pub(crate) fn should_exclude(path: &Path, exclude_patterns: &[Pattern]) -> bool {
     let path_str = path.to_string_lossy();
 
     for pattern in exclude_patterns {
@@ -208,7 +296,7 @@ fn should_exclude(path: &Path, exclude_patterns: &[Pattern]) -> bool {
 
 /// Parse exclude pattern strings into glob Patterns.
 /// Simple names like "node_modules" are converted to patterns that match them anywhere.
-fn parse_exclude_patterns(patterns: &[String]) -> Vec<Pattern> {
+pub(crate) fn parse_exclude_patterns(patterns: &[String]) -> Vec<Pattern> {
     patterns
         .iter()
         .filter_map(|p| {
@@ -228,24 +316,27 @@ fn search_file_content(
     query: &str,
     max_matches_per_file: usize,
 ) -> Option<ContentSearchResult> {
-    let file = fs::File::open(file_path).ok()?;
-    let reader = BufReader::new(file);
+    // Streamed a line at a time; see file_lines.rs.
+    let mut lines = LineReader::open(file_path).ok()?;
     let query_lower = query.to_lowercase();
 
     let mut matches = Vec::new();
+    let mut line_num = 0;
 
-    for (line_num, line_result) in reader.lines().enumerate() {
+    while let Ok(Some(line_bytes)) = lines.next_line() {
         if matches.len() >= max_matches_per_file {
             break;
         }
+        line_num += 1;
 
-        if let Ok(line) = line_result {
+        // Lines that aren't valid UTF-8 are skipped
+        if let Ok(line) = std::str::from_utf8(line_bytes) {
             let line_lower = line.to_lowercase();
 
             if let Some(start) = line_lower.find(&query_lower) {
                 matches.push(ContentMatch {
-                    line: line_num + 1,
-                    content: line.clone(),
+                    line: line_num,
+                    content: line.to_string(),
                     start,
                     end: start + query.len(),
                 });
@@ -257,26 +348,33 @@ fn search_file_content(
         None
     } else {
         Some(ContentSearchResult {
-            path: file_path.to_string_lossy().to_string(),
+            path: display_path(file_path),
             name: file_path
                 .file_name()
-                .map(|n| n.to_string_lossy().to_string())
+                .map(os_str_to_wire)
                 .unwrap_or_default(),
             matches,
         })
     }
 }
 
-fn collect_content_matches(
-    dir: &Path,
-    query: &str,
-    results: &mut Vec<ContentSearchResult>,
+/// What [`collect_content_matches`] looks for and where it stops.
+struct ContentWalk<'a> {
+    query: &'a str,
     max_results: usize,
     max_matches_per_file: usize,
     ignore_hidden: bool,
-    exclude_patterns: &[Pattern],
+    exclude_patterns: &'a [Pattern],
+    generated: &'a GeneratedRules,
+    cancel: &'a CancelToken,
+}
+
+fn collect_content_matches(
+    dir: &Path,
+    walk: &ContentWalk,
+    results: &mut Vec<ContentSearchResult>,
 ) {
-    if results.len() >= max_results {
+    if results.len() >= walk.max_results {
         return;
     }
 
@@ -286,99 +384,137 @@ fn collect_content_matches(
     };
 
     for entry in entries.flatten() {
-        if results.len() >= max_results {
+        if results.len() >= walk.max_results || walk.cancel.is_cancelled() {
             break;
         }
 
         let path = entry.path();
         let name = entry.file_name().to_string_lossy().to_string();
 
-        if ignore_hidden && name.starts_with('.') {
+        if walk.ignore_hidden && name.starts_with('.') {
             continue;
         }
 
         // Check custom exclude patterns
-        if should_exclude(&path, exclude_patterns) {
+        if should_exclude(&path, walk.exclude_patterns) {
             continue;
         }
 
-        if path.is_file() {
-            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-
-            let searchable = matches!(
-                ext,
-                "rs" | "ts" | "tsx" | "js" | "jsx" | "svelte" | "html" | "css" | "scss" | "json"
-                    | "md" | "toml" | "yaml" | "yml" | "txt"
-            );
+        let is_dir = path.is_dir();
+        if walk.generated.is_generated(&path, is_dir) {
+            continue;
+        }
 
-            if searchable {
-                if let Some(result) = search_file_content(&path, query, max_matches_per_file) {
+        if !is_dir && path.is_file() {
+            if is_searchable_file(&path) {
+                if let Some(result) =
+                    search_file_content(&path, walk.query, walk.max_matches_per_file)
+                {
                     results.push(result);
                 }
             }
-        } else if path.is_dir() {
-            collect_content_matches(
-                &path,
-                query,
-                results,
-                max_results,
-                max_matches_per_file,
-                ignore_hidden,
-                exclude_patterns,
-            );
+        } else if is_dir {
+            collect_content_matches(&path, walk, results);
         }
     }
 }
 
-#[tauri::command]
-pub fn search_content(
+pub(crate) fn search_content_blocking(
+    root_path: String,
+    query: String,
+    max_results: usize,
+    exclude_patterns: Vec<String>,
+) -> Result<Vec<ContentSearchResult>, String> {
+    search_content_cancellable(
+        root_path,
+        query,
+        max_results,
+        exclude_patterns,
+        &CancelToken::default(),
+    )
+}
+
+/// [`search_content_blocking`] that stops with "Operation cancelled" once
+/// `cancel` is tripped.
+fn search_content_cancellable(
     root_path: String,
     query: String,
     max_results: usize,
     exclude_patterns: Vec<String>,
+    cancel: &CancelToken,
 ) -> Result<Vec<ContentSearchResult>, String> {
+    let _span = tracing::info_span!("search::content", root = %root_path).entered();
     if query.len() < 2 {
         return Ok(Vec::new());
     }
 
-    let root = Path::new(&root_path);
+    let root = input_path(&root_path)?;
+    let root = root.as_path();
 
     if !root.exists() {
         return Err("Path does not exist".to_string());
     }
 
-    // Default excluded directories (always excluded)
-    let mut all_patterns = vec![
-        "node_modules".to_string(),
-        "target".to_string(),
-        ".git".to_string(),
-        "dist".to_string(),
-        "build".to_string(),
-    ];
-    all_patterns.extend(exclude_patterns);
-
-    let parsed_patterns = parse_exclude_patterns(&all_patterns);
+    let parsed_patterns = parse_exclude_patterns(&exclude_patterns);
+    let generated = GeneratedRules::load(root);
 
     let mut results = Vec::new();
-    collect_content_matches(
-        root,
-        &query,
-        &mut results,
+    let walk = ContentWalk {
+        query: &query,
         max_results,
-        10,
-        true,
-        &parsed_patterns,
-    );
+        max_matches_per_file: 10,
+        ignore_hidden: true,
+        exclude_patterns: &parsed_patterns,
+        generated: &generated,
+        cancel,
+    };
+    collect_content_matches(root, &walk, &mut results);
+    operations::check(cancel)?;
 
     Ok(results)
 }
 
+// Content search reads every file it visits, so it runs on the blocking
+// pool; see latency.rs.
+
+#[tauri::command]
+pub async fn search_content(
+    root_path: String,
+    query: String,
+    max_results: usize,
+    exclude_patterns: Vec<String>,
+    operation: Option<OperationRequest>,
+) -> Result<Vec<ContentSearchResult>, KiriError> {
+    let operation = operations::begin(operation);
+    let cancel = operation.token().clone();
+    let result = run_blocking("search_content", move || {
+        search_content_cancellable(root_path, query, max_results, exclude_patterns, &cancel)
+    })
+    .await;
+    operation.finish(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::tempdir;
 
+    fn test_walk<'a>(
+        query: &'a str,
+        max_results: usize,
+        generated: &'a GeneratedRules,
+        stop: &'a FileWalkStop,
+    ) -> FileWalk<'a> {
+        FileWalk {
+            query,
+            max_results,
+            ignore_hidden: false,
+            generated,
+            stop,
+        }
+    }
+
     #[test]
     fn test_fuzzy_match() {
         assert!(fuzzy_match("ft", "FileTree.svelte").is_some());
@@ -571,7 +707,7 @@ mod tests {
     fn test_search_content_short_query() {
         let dir = tempdir().unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "a".to_string(),
             10,
@@ -584,7 +720,7 @@ mod tests {
 
     #[test]
     fn test_search_content_nonexistent_path() {
-        let result = search_content(
+        let result = search_content_blocking(
             "/nonexistent/path".to_string(),
             "test".to_string(),
             10,
@@ -599,7 +735,7 @@ mod tests {
 
         fs::write(dir.path().join("test.rs"), "fn main() {\n    println!(\"hello\");\n}").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "println".to_string(),
             10,
@@ -619,7 +755,7 @@ mod tests {
 
         fs::write(dir.path().join("test.rs"), "test line 1\ntest line 2\ntest line 3").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "test".to_string(),
             10,
@@ -638,7 +774,7 @@ mod tests {
 
         fs::write(dir.path().join("test.rs"), "Hello World\nhello world\nHELLO WORLD").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "hello".to_string(),
             10,
@@ -659,7 +795,7 @@ mod tests {
         // Non-searchable file (binary extension)
         fs::write(dir.path().join("test.exe"), "test content").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "test".to_string(),
             10,
@@ -678,7 +814,7 @@ mod tests {
 
         fs::write(dir.path().join("test.rs"), "hello world").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "world".to_string(),
             10,
@@ -732,7 +868,7 @@ mod tests {
         fs::write(dir.path().join("src").join("main.rs"), "fn main() { hello() }").unwrap();
         fs::write(dir.path().join("lib.rs"), "pub fn hello() {}").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "hello".to_string(),
             10,
@@ -753,7 +889,7 @@ mod tests {
             fs::write(dir.path().join(format!("file{}.rs", i)), "matching content").unwrap();
         }
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "matching".to_string(),
             5,
@@ -789,7 +925,7 @@ mod tests {
         fs::create_dir(dir.path().join("src")).unwrap();
         fs::write(dir.path().join("src").join("main.rs"), "matching content").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "matching".to_string(),
             10,
@@ -803,6 +939,31 @@ mod tests {
         assert!(results[0].path.contains("src"));
     }
 
+    #[test]
+    fn test_search_content_skips_generated_files() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("build")).unwrap();
+        fs::create_dir_all(dir.path().join("dist")).unwrap();
+        fs::write(dir.path().join("package-lock.json"), "\"left-pad\"").unwrap();
+        fs::write(dir.path().join("app.min.js"), "leftPad()").unwrap();
+        fs::write(dir.path().join("dist").join("app.js"), "leftPad()").unwrap();
+        fs::write(dir.path().join("build").join("release.ts"), "leftPad()").unwrap();
+        fs::write(dir.path().join("main.ts"), "leftPad()").unwrap();
+        fs::write(dir.path().join(".kiri.toml"), "[generated]\nexclude = [\"build\"]\n").unwrap();
+
+        let results = search_content_blocking(
+            dir.path().to_string_lossy().to_string(),
+            "left".to_string(),
+            10,
+            vec![],
+        )
+        .unwrap();
+        let mut names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["main.ts", "release.ts"]);
+    }
+
     #[test]
     fn test_search_content_max_matches_per_file() {
         let dir = tempdir().unwrap();
@@ -811,7 +972,7 @@ mod tests {
         let content = (0..20).map(|_| "match").collect::<Vec<_>>().join("\n");
         fs::write(dir.path().join("test.rs"), content).unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "match".to_string(),
             10,
@@ -876,7 +1037,7 @@ mod tests {
         // Create a visible searchable file
         fs::write(dir.path().join("visible.rs"), "matching content here").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "matching".to_string(),
             10,
@@ -901,7 +1062,7 @@ mod tests {
         fs::create_dir(dir.path().join("src")).unwrap();
         fs::write(dir.path().join("src").join("main.rs"), "matching content").unwrap();
 
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "matching".to_string(),
             10,
@@ -945,7 +1106,7 @@ mod tests {
         fs::write(dir.path().join("valid.rs"), "searchable content").unwrap();
 
         // Search should work even with permission issues on some files
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "searchable".to_string(),
             10,
@@ -970,7 +1131,7 @@ mod tests {
         fs::write(dir.path().join("src").join("main.rs"), "matching content").unwrap();
 
         // Exclude "vendor" directory
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "matching".to_string(),
             10,
@@ -994,7 +1155,7 @@ mod tests {
         fs::write(dir.path().join("styles.min.css"), "matching content").unwrap();
 
         // Exclude minified files
-        let result = search_content(
+        let result = search_content_blocking(
             dir.path().to_string_lossy().to_string(),
             "matching".to_string(),
             10,
@@ -1056,7 +1217,9 @@ mod tests {
 
         let mut results = Vec::new();
         // Set max_results to 2 so we hit the early return
-        collect_files(dir.path(), "file", &mut results, 2, false, 0);
+        let (generated, stop) = (GeneratedRules::default(), FileWalkStop::default());
+        let walk = test_walk("file", 2, &generated, &stop);
+        collect_files(dir.path(), &walk, &mut results, 0);
         assert_eq!(results.len(), 2);
     }
 
@@ -1064,7 +1227,9 @@ mod tests {
     fn test_collect_files_unreadable_directory() {
         // Test with a non-existent directory (read_dir fails)
         let mut results = Vec::new();
-        collect_files(Path::new("/nonexistent/path"), "test", &mut results, 100, false, 0);
+        let (generated, stop) = (GeneratedRules::default(), FileWalkStop::default());
+        let walk = test_walk("test", 100, &generated, &stop);
+        collect_files(Path::new("/nonexistent/path"), &walk, &mut results, 0);
         assert!(results.is_empty());
     }
 
@@ -1116,7 +1281,9 @@ mod tests {
         fs::write(p.join("needle.txt"), b"").unwrap();
 
         let mut results = Vec::new();
-        collect_files(dir.path(), "needle", &mut results, 100, false, 0);
+        let (generated, stop) = (GeneratedRules::default(), FileWalkStop::default());
+        let walk = test_walk("needle", 100, &generated, &stop);
+        collect_files(dir.path(), &walk, &mut results, 0);
         assert!(
             results.is_empty(),
             "needle.txt sits below MAX_SEARCH_DEPTH and must be unreachable"
@@ -1160,7 +1327,16 @@ mod tests {
         let mut results = Vec::new();
         let exclude_patterns = parse_exclude_patterns(&[]);
         // Set max_results to 2 to trigger early returns
-        collect_content_matches(dir.path(), "matching", &mut results, 2, 10, false, &exclude_patterns);
+        let walk = ContentWalk {
+            query: "matching",
+            max_results: 2,
+            max_matches_per_file: 10,
+            ignore_hidden: false,
+            exclude_patterns: &exclude_patterns,
+            generated: &GeneratedRules::default(),
+            cancel: &CancelToken::default(),
+        };
+        collect_content_matches(dir.path(), &walk, &mut results);
         assert_eq!(results.len(), 2);
     }
 
@@ -1168,10 +1344,34 @@ mod tests {
     fn test_collect_content_matches_unreadable_directory() {
         let mut results = Vec::new();
         let exclude_patterns = parse_exclude_patterns(&[]);
-        collect_content_matches(Path::new("/nonexistent"), "query", &mut results, 100, 10, false, &exclude_patterns);
+        let walk = ContentWalk {
+            query: "query",
+            max_results: 100,
+            max_matches_per_file: 10,
+            ignore_hidden: false,
+            exclude_patterns: &exclude_patterns,
+            generated: &GeneratedRules::default(),
+            cancel: &CancelToken::default(),
+        };
+        collect_content_matches(Path::new("/nonexistent"), &walk, &mut results);
         assert!(results.is_empty());
     }
 
+    #[test]
+    fn test_search_content_cancelled() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "needle").unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let token = CancelToken::default();
+        token.cancel();
+
+        let result =
+            search_content_cancellable(root.clone(), "needle".to_string(), 10, vec![], &token);
+        assert_eq!(result.unwrap_err(), operations::CANCELLED_MESSAGE);
+        let result = search_files_cancellable(root, "a".to_string(), 10, &token);
+        assert_eq!(result.unwrap_err(), operations::CANCELLED_MESSAGE);
+    }
+
     #[test]
     fn test_should_exclude_path_component() {
         let patterns = parse_exclude_patterns(&["node_modules".to_string()]);
@@ -0,0 +1,448 @@
+//! Secrets (access tokens, credentials) in the OS keychain.
+//!
+//! Each integration keeps its secrets under its own namespace, e.g. the
+//! GitHub token is `github`/`token`; in the keychain that is the service
+//! `kiri.github` and the account `token`. macOS stores them in the login
+//! keychain through `security`, Linux in the Secret Service through
+//! `secret-tool`; the secret itself goes over stdin, never on a command
+//! line. Where neither is available, e.g. on Windows or a headless Linux
+//! box, secrets fall back to files under `~/.kiri/secrets` readable by
+//! the owner only.
+//!
+//! Older versions kept tokens in plain files such as `~/.kiri/github-token`;
+//! [`get_secret_migrating`] moves one into the store on first read.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use super::error::user_path_io_error;
+
+/// `security` exits with this when no item matches.
+#[cfg(target_os = "macos")]
+const SEC_ITEM_NOT_FOUND: i32 = 44;
+
+/// A place secrets can be kept.
+pub trait SecretStore {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, String>;
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<(), String>;
+    /// Deleting a secret that isn't there is not an error.
+    fn delete(&self, service: &str, account: &str) -> Result<(), String>;
+}
+
+fn run(program: &str, args: &[&str], stdin: Option<&str>) -> std::io::Result<Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input.as_bytes())?;
+    }
+    child.wait_with_output()
+}
+
+fn failure(action: &str, output: &Output) -> String {
+    format!(
+        "Failed to {}: {}",
+        action,
+        String::from_utf8_lossy(&output.stderr).trim()
+    )
+}
+
+/// The macOS login keychain, through `security`.
+#[cfg(target_os = "macos")]
+pub struct MacKeychain;
+
+#[cfg(target_os = "macos")]
+impl MacKeychain {
+    /// A double-quoted argument for `security -i`, which splits its input
+    /// lines like a shell.
+    fn quote(value: &str) -> String {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl SecretStore for MacKeychain {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        let output = run(
+            "security",
+            &["find-generic-password", "-s", service, "-a", account, "-w"],
+            None,
+        )
+        .map_err(|e| format!("Failed to run security: {}", e))?;
+        match output.status.code() {
+            Some(0) => {
+                let secret = String::from_utf8_lossy(&output.stdout);
+                Ok(Some(secret.trim_end_matches('\n').to_string()))
+            }
+            Some(SEC_ITEM_NOT_FOUND) => Ok(None),
+            _ => Err(failure("read from the keychain", &output)),
+        }
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        // `-i` reads the command from stdin, keeping the secret out of
+        // the process list.
+        let command = format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            Self::quote(service),
+            Self::quote(account),
+            Self::quote(secret)
+        );
+        let output = run("security", &["-i"], Some(&command))
+            .map_err(|e| format!("Failed to run security: {}", e))?;
+        if output.status.success() && output.stderr.is_empty() {
+            Ok(())
+        } else {
+            Err(failure("save to the keychain", &output))
+        }
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), String> {
+        let output = run(
+            "security",
+            &["delete-generic-password", "-s", service, "-a", account],
+            None,
+        )
+        .map_err(|e| format!("Failed to run security: {}", e))?;
+        match output.status.code() {
+            Some(0) | Some(SEC_ITEM_NOT_FOUND) => Ok(()),
+            _ => Err(failure("delete from the keychain", &output)),
+        }
+    }
+}
+
+/// The freedesktop Secret Service (GNOME Keyring, KWallet), through
+/// `secret-tool`.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub struct SecretService;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl SecretService {
+    /// Whether `secret-tool` is installed and a Secret Service answers.
+    fn available() -> bool {
+        run(
+            "secret-tool",
+            &["search", "service", "kiri.probe", "account", "probe"],
+            None,
+        )
+        .is_ok_and(|output| {
+            // Nothing found is exit 1 with empty stderr; no service or no
+            // session bus reports an error.
+            output.stderr.is_empty()
+        })
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl SecretStore for SecretService {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        let output = run(
+            "secret-tool",
+            &["lookup", "service", service, "account", account],
+            None,
+        )
+        .map_err(|e| format!("Failed to run secret-tool: {}", e))?;
+        if output.status.success() {
+            Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+        } else if output.stderr.is_empty() {
+            Ok(None)
+        } else {
+            Err(failure("read from the Secret Service", &output))
+        }
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        let label = format!("--label=kiri: {} {}", service, account);
+        let output = run(
+            "secret-tool",
+            &["store", &label, "service", service, "account", account],
+            Some(secret),
+        )
+        .map_err(|e| format!("Failed to run secret-tool: {}", e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(failure("save to the Secret Service", &output))
+        }
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), String> {
+        let output = run(
+            "secret-tool",
+            &["clear", "service", service, "account", account],
+            None,
+        )
+        .map_err(|e| format!("Failed to run secret-tool: {}", e))?;
+        if output.status.success() || output.stderr.is_empty() {
+            Ok(())
+        } else {
+            Err(failure("delete from the Secret Service", &output))
+        }
+    }
+}
+
+/// Files under `dir` readable by the owner only, one per secret.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, service: &str, account: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", service, account))
+    }
+}
+
+impl SecretStore for FileStore {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        read_secret_file(&self.path(service, account))
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        write_secret_file(&self.path(service, account), secret)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), String> {
+        remove_secret_file(&self.path(service, account))
+    }
+}
+
+fn read_secret_file(path: &Path) -> Result<Option<String>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            let contents = contents.trim();
+            Ok((!contents.is_empty()).then(|| contents.to_string()))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(user_path_io_error("Failed to read the secret", path, e)),
+    }
+}
+
+fn write_secret_file(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| user_path_io_error("Failed to create the secrets folder", dir, e))?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| user_path_io_error("Failed to save the secret", path, e))?;
+    // The mode above only applies to a new file; tighten an existing one
+    // before the secret goes in.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| user_path_io_error("Failed to save the secret", path, e))?;
+    }
+    file.write_all(contents.as_bytes())
+        .map_err(|e| user_path_io_error("Failed to save the secret", path, e))
+}
+
+fn remove_secret_file(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(user_path_io_error("Failed to remove the secret", path, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn kiri_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".kiri"))
+}
+
+/// The best store on this machine.
+fn default_store() -> Result<Box<dyn SecretStore>, String> {
+    #[cfg(target_os = "macos")]
+    {
+        Ok(Box::new(MacKeychain))
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        #[cfg(unix)]
+        if SecretService::available() {
+            return Ok(Box::new(SecretService));
+        }
+        let dir = kiri_dir().ok_or("Failed to locate home directory")?;
+        Ok(Box::new(FileStore::new(dir.join("secrets"))))
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Keychain service and account of `key` in `namespace`.
+fn locate(namespace: &str, key: &str) -> Result<(String, String), String> {
+    if !is_valid_name(namespace) || !is_valid_name(key) {
+        return Err(format!(
+            "Invalid secret name {}/{}: use lowercase letters, digits, - and _",
+            namespace, key
+        ));
+    }
+    Ok((format!("kiri.{}", namespace), key.to_string()))
+}
+
+fn get_in(store: &dyn SecretStore, namespace: &str, key: &str) -> Result<Option<String>, String> {
+    let (service, account) = locate(namespace, key)?;
+    store.get(&service, &account)
+}
+
+fn set_in(store: &dyn SecretStore, namespace: &str, key: &str, value: &str) -> Result<(), String> {
+    let (service, account) = locate(namespace, key)?;
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("Secret {}/{} is empty", namespace, key));
+    }
+    store.set(&service, &account, value)
+}
+
+fn delete_in(store: &dyn SecretStore, namespace: &str, key: &str) -> Result<(), String> {
+    let (service, account) = locate(namespace, key)?;
+    store.delete(&service, &account)
+}
+
+/// Move `legacy`, a plain secret file of an older version, into the store
+/// as `namespace`/`key`, unless the store already has that secret.
+fn migrate_in(
+    store: &dyn SecretStore,
+    namespace: &str,
+    key: &str,
+    legacy: &Path,
+) -> Result<Option<String>, String> {
+    if let Some(secret) = get_in(store, namespace, key)? {
+        return Ok(Some(secret));
+    }
+    let Some(secret) = read_secret_file(legacy)? else {
+        return Ok(None);
+    };
+    set_in(store, namespace, key, &secret)?;
+    remove_secret_file(legacy)?;
+    log::info!("moved {} into the secret store", legacy.display());
+    Ok(Some(secret))
+}
+
+/// `key` of `namespace`, if saved. Store errors are logged and read as
+/// missing, like a secret that was never saved.
+pub fn get_secret(namespace: &str, key: &str) -> Option<String> {
+    default_store()
+        .and_then(|store| get_in(store.as_ref(), namespace, key))
+        .unwrap_or_else(|e| {
+            log::warn!("failed to read secret {}/{}: {}", namespace, key, e);
+            None
+        })
+}
+
+/// [`get_secret`], first moving `~/.kiri/<legacy_file>` into the store if
+/// an older version left the secret there.
+pub fn get_secret_migrating(namespace: &str, key: &str, legacy_file: &str) -> Option<String> {
+    let result = default_store().and_then(|store| {
+        let legacy = kiri_dir().ok_or("Failed to locate home directory")?;
+        migrate_in(store.as_ref(), namespace, key, &legacy.join(legacy_file))
+    });
+    result.unwrap_or_else(|e| {
+        log::warn!("failed to read secret {}/{}: {}", namespace, key, e);
+        None
+    })
+}
+
+pub fn set_secret(namespace: &str, key: &str, value: &str) -> Result<(), String> {
+    set_in(default_store()?.as_ref(), namespace, key, value)
+}
+
+/// Delete `key` of `namespace`, and `~/.kiri/<legacy_file>` if given, so
+/// signing out leaves no copy behind.
+pub fn delete_secret(namespace: &str, key: &str, legacy_file: Option<&str>) -> Result<(), String> {
+    delete_in(default_store()?.as_ref(), namespace, key)?;
+    match (legacy_file, kiri_dir()) {
+        (Some(name), Some(dir)) => remove_secret_file(&dir.join(name)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_store_and_migration() {
+        let dir = TempDir::new().unwrap();
+        let store = FileStore::new(dir.path().join("secrets"));
+
+        assert_eq!(get_in(&store, "gitlab", "token").unwrap(), None);
+        set_in(&store, "gitlab", "token", " glpat-1 \n").unwrap();
+        assert_eq!(
+            get_in(&store, "gitlab", "token").unwrap().as_deref(),
+            Some("glpat-1")
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let path = dir.path().join("secrets/kiri.gitlab.token");
+            let mode = std::fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        delete_in(&store, "gitlab", "token").unwrap();
+        delete_in(&store, "gitlab", "token").unwrap();
+        assert_eq!(get_in(&store, "gitlab", "token").unwrap(), None);
+
+        assert!(set_in(&store, "../etc", "token", "x").is_err());
+        assert!(set_in(&store, "github", "Token", "x").is_err());
+        assert!(set_in(&store, "github", "token", "  ").is_err());
+
+        let legacy = dir.path().join("github-token");
+        std::fs::write(&legacy, "gho_old\n").unwrap();
+        let migrated = migrate_in(&store, "github", "token", &legacy).unwrap();
+        assert_eq!(migrated.as_deref(), Some("gho_old"));
+        assert!(!legacy.exists());
+        assert_eq!(
+            get_in(&store, "github", "token").unwrap().as_deref(),
+            Some("gho_old")
+        );
+        assert_eq!(
+            migrate_in(&store, "jira", "credentials", &legacy).unwrap(),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_store_tightens_existing_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new().unwrap();
+        let store = FileStore::new(dir.path().to_path_buf());
+        let path = dir.path().join("kiri.github.token");
+        std::fs::write(&path, "gho_old\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        set_in(&store, "github", "token", "gho_new").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(
+            get_in(&store, "github", "token").unwrap().as_deref(),
+            Some("gho_new")
+        );
+    }
+}
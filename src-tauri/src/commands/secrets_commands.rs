@@ -0,0 +1,33 @@
+//! Tauri commands for secrets; see secrets.rs.
+//!
+//! The webview can save and delete secrets and ask whether one is saved,
+//! but never read one back: secrets are only used by the Rust side.
+
+use super::error::KiriError;
+use super::latency::run_blocking;
+use super::secrets;
+
+#[tauri::command]
+pub async fn set_secret(namespace: String, key: String, value: String) -> Result<(), KiriError> {
+    run_blocking("set_secret", move || {
+        secrets::set_secret(&namespace, &key, &value)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_secret(namespace: String, key: String) -> Result<(), KiriError> {
+    run_blocking("delete_secret", move || {
+        secrets::delete_secret(&namespace, &key, None)
+    })
+    .await
+}
+
+/// Whether `key` of `namespace` is saved.
+#[tauri::command]
+pub async fn has_secret(namespace: String, key: String) -> Result<bool, KiriError> {
+    run_blocking("has_secret", move || {
+        Ok(secrets::get_secret(&namespace, &key).is_some())
+    })
+    .await
+}
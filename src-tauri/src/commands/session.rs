@@ -0,0 +1,285 @@
+//! Session restore: per-window snapshot of the open project, open files,
+//! terminals, sidebar layout and worktree selection.
+//!
+//! The frontend pushes its window's state with `save_session` whenever it
+//! changes. The store is keyed by window label in memory and persisted to
+//! `~/.kiri/session.json` as an ordered list, since labels are not stable
+//! across launches. On startup the saved windows are reopened and each new
+//! window pulls its snapshot with `restore_session`.
+//!
+//! Closing a window drops its snapshot, except when it is the last window
+//! open (that is how the app is quit on Windows/Linux, and often on macOS)
+//! or the app is already exiting and tearing every window down.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::file_io::write_file_contents_atomic;
+use super::window_geometry::WindowGeometry;
+
+/// Bumped when the on-disk format changes incompatibly. Files with a
+/// different version are ignored rather than half-restored.
+pub const SESSION_FILE_VERSION: u32 = 1;
+
+/// `~/.kiri/session.json` — persisted session snapshot.
+pub fn session_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("session.json"))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SidebarLayout {
+    pub visible: bool,
+    pub width: Option<f64>,
+    /// Expanded directories in the file tree.
+    pub expanded_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WindowSession {
+    pub project_path: Option<String>,
+    pub open_files: Vec<String>,
+    pub active_file: Option<String>,
+    /// Terminal ids open at save time. PTYs do not survive a restart, so
+    /// the frontend uses these to recreate the same number of panes.
+    pub terminal_ids: Vec<u32>,
+    pub active_terminal_id: Option<u32>,
+    pub sidebar: SidebarLayout,
+    /// Selected worktree path, when the project has several.
+    pub worktree: Option<String>,
+    /// Position and size, validated against the current monitors when
+    /// the window is reopened.
+    pub geometry: Option<WindowGeometry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SessionFile {
+    version: u32,
+    windows: Vec<WindowSession>,
+}
+
+/// In-memory session snapshots keyed by window label. Insertion order is
+/// tracked so the persisted list reopens windows in the order they were
+/// first seen.
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    windows: HashMap<String, WindowSession>,
+    order: Vec<String>,
+    /// Snapshots loaded from disk that have not been claimed by a window
+    /// yet, keyed by the label the startup code assigned them.
+    pending: HashMap<String, WindowSession>,
+    exiting: bool,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn save(&mut self, label: &str, session: WindowSession) {
+        if !self.windows.contains_key(label) {
+            self.order.push(label.to_string());
+        }
+        self.windows.insert(label.to_string(), session);
+    }
+
+    pub fn get(&self, label: &str) -> Option<&WindowSession> {
+        self.windows.get(label)
+    }
+
+    /// Called when a window is destroyed. Returns `true` if the snapshot was
+    /// dropped (see module docs for why the last window is kept).
+    pub fn close_window(&mut self, label: &str, remaining_windows: usize) -> bool {
+        self.pending.remove(label);
+        if remaining_windows == 0 || self.exiting {
+            return false;
+        }
+        self.order.retain(|l| l != label);
+        self.windows.remove(label).is_some()
+    }
+
+    /// Stop dropping snapshots as windows close: the app is quitting.
+    pub fn begin_exit(&mut self) {
+        self.exiting = true;
+    }
+
+    /// Assign a loaded snapshot to a window label until that window calls
+    /// [`SessionStore::take_pending`].
+    pub fn set_pending(&mut self, label: &str, session: WindowSession) {
+        self.save(label, session.clone());
+        self.pending.insert(label.to_string(), session);
+    }
+
+    pub fn take_pending(&mut self, label: &str) -> Option<WindowSession> {
+        self.pending.remove(label)
+    }
+
+    /// Snapshots in window order, skipping windows with nothing open.
+    pub fn snapshot(&self) -> Vec<WindowSession> {
+        self.order
+            .iter()
+            .filter_map(|label| self.windows.get(label))
+            .filter(|s| s.project_path.is_some() || !s.open_files.is_empty())
+            .cloned()
+            .collect()
+    }
+}
+
+pub type SessionState = Arc<Mutex<SessionStore>>;
+
+/// Load the persisted window list. A missing, unreadable or
+/// version-mismatched file yields an empty session.
+pub fn load_session_file(path: &Path) -> Vec<WindowSession> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<SessionFile>(&contents) {
+        Ok(file) if file.version == SESSION_FILE_VERSION => file.windows,
+        Ok(file) => {
+            log::info!(
+                "ignoring session file with version {} (expected {})",
+                file.version,
+                SESSION_FILE_VERSION
+            );
+            Vec::new()
+        }
+        Err(e) => {
+            log::warn!("failed to parse session file: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn write_session_file(path: &Path, windows: Vec<WindowSession>) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create session directory: {}", e))?;
+    }
+    let file = SessionFile {
+        version: SESSION_FILE_VERSION,
+        windows,
+    };
+    let json = serde_json::to_vec_pretty(&file)
+        .map_err(|e| format!("Failed to serialize session: {}", e))?;
+    write_file_contents_atomic(path, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn session(project: &str) -> WindowSession {
+        WindowSession {
+            project_path: Some(project.to_string()),
+            open_files: vec![format!("{}/README.md", project)],
+            terminal_ids: vec![1, 2],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_and_get() {
+        let mut store = SessionStore::new();
+        store.save("main", session("/a"));
+        assert_eq!(store.get("main"), Some(&session("/a")));
+        assert_eq!(store.get("other"), None);
+    }
+
+    #[test]
+    fn test_snapshot_keeps_first_seen_order() {
+        let mut store = SessionStore::new();
+        store.save("window-2", session("/b"));
+        store.save("main", session("/a"));
+        store.save("window-2", session("/b2"));
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot[0].project_path.as_deref(), Some("/b2"));
+        assert_eq!(snapshot[1].project_path.as_deref(), Some("/a"));
+    }
+
+    #[test]
+    fn test_snapshot_skips_empty_windows() {
+        let mut store = SessionStore::new();
+        store.save("main", WindowSession::default());
+        store.save("window-2", session("/a"));
+        assert_eq!(store.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn test_close_window_drops_snapshot_when_others_remain() {
+        let mut store = SessionStore::new();
+        store.save("main", session("/a"));
+        store.save("window-2", session("/b"));
+        assert!(store.close_window("window-2", 1));
+        assert_eq!(store.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn test_close_last_window_keeps_snapshot() {
+        let mut store = SessionStore::new();
+        store.save("main", session("/a"));
+        assert!(!store.close_window("main", 0));
+        assert_eq!(store.snapshot(), vec![session("/a")]);
+    }
+
+    #[test]
+    fn test_close_during_exit_keeps_snapshots() {
+        let mut store = SessionStore::new();
+        store.save("main", session("/a"));
+        store.save("window-2", session("/b"));
+        store.begin_exit();
+        assert!(!store.close_window("window-2", 1));
+        assert!(!store.close_window("main", 0));
+        assert_eq!(store.snapshot().len(), 2);
+    }
+
+    #[test]
+    fn test_pending_is_taken_once() {
+        let mut store = SessionStore::new();
+        store.set_pending("main", session("/a"));
+        assert_eq!(store.take_pending("main"), Some(session("/a")));
+        assert_eq!(store.take_pending("main"), None);
+        // Still part of the snapshot until the window saves its own state.
+        assert_eq!(store.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn test_session_file_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nested").join("session.json");
+        write_session_file(&path, vec![session("/a"), session("/b")]).unwrap();
+        assert_eq!(load_session_file(&path), vec![session("/a"), session("/b")]);
+    }
+
+    #[test]
+    fn test_load_session_file_missing_or_corrupt() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        assert!(load_session_file(&path).is_empty());
+        std::fs::write(&path, "{not json").unwrap();
+        assert!(load_session_file(&path).is_empty());
+    }
+
+    #[test]
+    fn test_load_session_file_ignores_other_versions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        std::fs::write(
+            &path,
+            r#"{"version":999,"windows":[{"project_path":"/a"}]}"#,
+        )
+        .unwrap();
+        assert!(load_session_file(&path).is_empty());
+    }
+
+    #[test]
+    fn test_window_session_tolerates_missing_fields() {
+        let parsed: WindowSession = serde_json::from_str(r#"{"project_path":"/a"}"#).unwrap();
+        assert_eq!(parsed.project_path.as_deref(), Some("/a"));
+        assert!(parsed.open_files.is_empty());
+        assert!(!parsed.sidebar.visible);
+    }
+}
@@ -0,0 +1,122 @@
+//! Tauri command wrappers and lifecycle hooks for session restore.
+//! The snapshot store and file format live in session.rs.
+
+use super::error::KiriError;
+use super::lock_ext::LockExt;
+use super::session::{
+    load_session_file, session_file_path, write_session_file, SessionState, WindowSession,
+};
+use super::window::{create_window_impl, WindowRegistryState};
+use super::window_geometry::WindowGeometry;
+use super::window_geometry_commands::{apply_geometry, capture_geometry};
+use tauri::{AppHandle, Manager};
+
+/// Label of the window Tauri creates from `tauri.conf.json`.
+const MAIN_WINDOW_LABEL: &str = "main";
+
+fn persist(state: &SessionState) -> Result<(), String> {
+    let Some(path) = session_file_path() else {
+        return Ok(());
+    };
+    let windows = state.lock_recover().snapshot();
+    write_session_file(&path, windows)
+}
+
+/// Record the current state of window `label` and persist the session.
+/// Geometry is captured from the live window unless the caller sent one.
+#[tauri::command]
+pub fn save_session(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    label: String,
+    mut session: WindowSession,
+) -> Result<(), KiriError> {
+    if session.geometry.is_none() {
+        session.geometry = app
+            .get_webview_window(&label)
+            .and_then(|w| capture_geometry(&w).ok());
+    }
+    state.lock_recover().save(&label, session);
+    persist(&state).map_err(KiriError::from)
+}
+
+/// Return the snapshot a window should restore: the one assigned at
+/// startup if it has not been claimed yet, otherwise its last saved state.
+#[tauri::command]
+pub fn restore_session(
+    state: tauri::State<'_, SessionState>,
+    label: String,
+) -> Result<Option<WindowSession>, KiriError> {
+    let mut store = state.lock_recover();
+    Ok(store
+        .take_pending(&label)
+        .or_else(|| store.get(&label).cloned()))
+}
+
+/// Reopen the windows from the previous session. The first snapshot goes
+/// to the main window; every other one gets a new window. Each window
+/// then pulls its snapshot with `restore_session`.
+pub fn restore_windows_on_startup(app: &AppHandle) {
+    let Some(path) = session_file_path() else {
+        return;
+    };
+    let mut windows = load_session_file(&path).into_iter();
+    let state = app.state::<SessionState>();
+    let registry = app.state::<WindowRegistryState>();
+
+    if let Some(first) = windows.next() {
+        restore_geometry(app, MAIN_WINDOW_LABEL, first.geometry.as_ref());
+        state.lock_recover().set_pending(MAIN_WINDOW_LABEL, first);
+    }
+    for session in windows {
+        match create_window_impl(
+            app,
+            Some(&registry),
+            None,
+            None,
+            None,
+            None,
+            session.project_path.clone(),
+        ) {
+            Ok(label) => {
+                restore_geometry(app, &label, session.geometry.as_ref());
+                state.lock_recover().set_pending(&label, session);
+            }
+            Err(e) => log::warn!("failed to restore session window: {}", e),
+        }
+    }
+}
+
+fn restore_geometry(app: &AppHandle, label: &str, geometry: Option<&WindowGeometry>) {
+    let (Some(window), Some(geometry)) = (app.get_webview_window(label), geometry) else {
+        return;
+    };
+    if let Err(e) = apply_geometry(&window, geometry) {
+        log::warn!("failed to restore geometry for {}: {}", label, e);
+    }
+}
+
+/// Drop a destroyed window's snapshot (unless it was the last one open)
+/// and persist the result.
+pub fn on_window_destroyed(app: &AppHandle, label: &str) {
+    let Some(state) = app.try_state::<SessionState>() else {
+        return;
+    };
+    let remaining = app
+        .webview_windows()
+        .keys()
+        .filter(|l| l.as_str() != label)
+        .count();
+    if state.lock_recover().close_window(label, remaining) {
+        if let Err(e) = persist(&state) {
+            log::warn!("failed to persist session: {}", e);
+        }
+    }
+}
+
+/// Stop dropping snapshots as the remaining windows close during quit.
+pub fn on_exit_requested(app: &AppHandle) {
+    if let Some(state) = app.try_state::<SessionState>() {
+        state.lock_recover().begin_exit();
+    }
+}
@@ -0,0 +1,442 @@
+//! Typed app-wide settings with defaults, validation and versioned
+//! migrations.
+//!
+//! Settings are saved in `~/.kiri/settings.json` with the
+//! [`SETTINGS_VERSION`] they were written in. An older file goes through
+//! [`MIGRATIONS`] on load; version 0 is the old `kiri-settings.json`
+//! store layout, imported once when no settings file exists yet. A value
+//! that is missing, of the wrong type or out of range falls back to its
+//! default on load, so one bad entry can't take the others with it;
+//! updates are checked strictly instead and rejected as a whole.
+//!
+//! Applying a change to the running app and announcing it is up to
+//! settings_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::file_io::write_file_contents_atomic;
+use super::large_file::{
+    LargeFileLimits, DEFAULT_LARGE_FILE_BYTES, DEFAULT_LARGE_FILE_LINE_LENGTH,
+    MAX_LARGE_FILE_BYTES, MAX_LARGE_FILE_LINE_LENGTH, MIN_LARGE_FILE_BYTES,
+    MIN_LARGE_FILE_LINE_LENGTH,
+};
+use super::watcher::{DEFAULT_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS, MIN_POLL_INTERVAL_MS};
+
+/// Version of the layout [`Settings`] are written in.
+pub const SETTINGS_VERSION: u32 = 1;
+
+pub const MIN_FONT_SIZE: u32 = 8;
+pub const MAX_FONT_SIZE: u32 = 32;
+
+/// Migration from version `i` to `i + 1`, by index.
+pub const MIGRATIONS: [fn(&mut Map<String, Value>); SETTINGS_VERSION as usize] = [from_store];
+
+pub fn settings_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("settings.json"))
+}
+
+/// Command typed into new terminals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupCommand {
+    #[default]
+    None,
+    Claude,
+    Codex,
+}
+
+impl StartupCommand {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StartupCommand::None => "none",
+            StartupCommand::Claude => "claude",
+            StartupCommand::Codex => "codex",
+        }
+    }
+}
+
+/// Releases offered as updates, see updater.rs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Pre-releases too.
+    Beta,
+}
+
+impl UpdateChannel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+        }
+    }
+}
+
+/// Language of messages from the backend, see i18n.rs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// The system language, or English when kiri has no translation.
+    #[default]
+    System,
+    En,
+    Ja,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32,
+    pub font_size: u32,
+    pub startup_command: StartupCommand,
+    pub show_tray_icon: bool,
+    pub open_windows_as_tabs: bool,
+    /// Opts release builds into memory sampling.
+    pub collect_memory_metrics: bool,
+    /// Opts into counting command usage locally, see telemetry.rs.
+    pub collect_usage_analytics: bool,
+    /// Shell new terminals start; empty for the login shell in `$SHELL`.
+    /// A project's worktrees can override it, see settings_layers.rs.
+    pub terminal_profile: String,
+    /// Variables set in every terminal.
+    pub terminal_env: BTreeMap<String, String>,
+    /// Look for a new release in the background.
+    pub check_for_updates: bool,
+    pub update_channel: UpdateChannel,
+    pub language: Language,
+    /// Files above this many bytes aren't opened in the editor as is; see
+    /// large_file.rs.
+    pub large_file_bytes: u64,
+    /// Nor files with a line longer than this, in bytes.
+    pub large_file_line_length: u32,
+    /// How often folders that can't be watched natively (network shares,
+    /// WSL mounts) are rescanned; see watcher.rs.
+    pub watcher_poll_interval_ms: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            font_size: 13,
+            startup_command: StartupCommand::None,
+            show_tray_icon: false,
+            open_windows_as_tabs: false,
+            collect_memory_metrics: false,
+            collect_usage_analytics: false,
+            terminal_profile: String::new(),
+            terminal_env: BTreeMap::new(),
+            check_for_updates: true,
+            update_channel: UpdateChannel::Stable,
+            language: Language::System,
+            large_file_bytes: DEFAULT_LARGE_FILE_BYTES,
+            large_file_line_length: DEFAULT_LARGE_FILE_LINE_LENGTH,
+            watcher_poll_interval_ms: DEFAULT_POLL_INTERVAL_MS,
+        }
+    }
+}
+
+/// Version 0, the `kiri-settings.json` store: font size and startup
+/// command sat under `globalSettings`, the rest at the top level next to
+/// entries that aren't settings (recent projects, per-project search
+/// excludes), which are left out.
+fn from_store(map: &mut Map<String, Value>) {
+    let mut settings = Map::new();
+    if let Some(Value::Object(global)) = map.get("globalSettings") {
+        for key in ["fontSize", "startupCommand"] {
+            if let Some(value) = global.get(key) {
+                settings.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+    for key in ["showTrayIcon", "openWindowsAsTabs", "collectMemoryMetrics"] {
+        if let Some(value) = map.get(key) {
+            settings.insert(key.to_string(), value.clone());
+        }
+    }
+    *map = settings;
+}
+
+/// Bring a saved object up to [`SETTINGS_VERSION`]. One written by a newer
+/// kiri is left as is; what this version knows of it still loads.
+pub fn migrate(mut map: Map<String, Value>) -> Map<String, Value> {
+    let version = map.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+    for migration in MIGRATIONS.iter().skip(version) {
+        migration(&mut map);
+    }
+    if version < MIGRATIONS.len() {
+        map.insert("version".to_string(), SETTINGS_VERSION.into());
+    }
+    map
+}
+
+/// A shell to start terminals with: an absolute path.
+pub fn check_shell(shell: &str) -> Result<(), String> {
+    if !Path::new(shell).is_absolute() {
+        return Err("must be an absolute path to a shell".to_string());
+    }
+    Ok(())
+}
+
+/// An environment variable name a shell can take.
+pub fn check_env_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err("is not a valid variable name".to_string());
+    }
+    Ok(())
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_FONT_SIZE..=MAX_FONT_SIZE).contains(&self.font_size) {
+            return Err(format!(
+                "fontSize must be between {} and {}",
+                MIN_FONT_SIZE, MAX_FONT_SIZE
+            ));
+        }
+        if !self.terminal_profile.is_empty() {
+            check_shell(&self.terminal_profile).map_err(|e| format!("terminalProfile {}", e))?;
+        }
+        for name in self.terminal_env.keys() {
+            check_env_name(name).map_err(|e| format!("terminalEnv `{}` {}", name, e))?;
+        }
+        if !(MIN_LARGE_FILE_BYTES..=MAX_LARGE_FILE_BYTES).contains(&self.large_file_bytes) {
+            return Err(format!(
+                "largeFileBytes must be between {} and {}",
+                MIN_LARGE_FILE_BYTES, MAX_LARGE_FILE_BYTES
+            ));
+        }
+        if !(MIN_LARGE_FILE_LINE_LENGTH..=MAX_LARGE_FILE_LINE_LENGTH)
+            .contains(&self.large_file_line_length)
+        {
+            return Err(format!(
+                "largeFileLineLength must be between {} and {}",
+                MIN_LARGE_FILE_LINE_LENGTH, MAX_LARGE_FILE_LINE_LENGTH
+            ));
+        }
+        if !(MIN_POLL_INTERVAL_MS..=MAX_POLL_INTERVAL_MS).contains(&self.watcher_poll_interval_ms) {
+            return Err(format!(
+                "watcherPollIntervalMs must be between {} and {}",
+                MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn large_file_limits(&self) -> LargeFileLimits {
+        LargeFileLimits {
+            max_bytes: self.large_file_bytes,
+            max_line_length: self.large_file_line_length,
+        }
+    }
+
+    fn to_map(&self) -> Map<String, Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(map)) => map,
+            _ => Map::new(),
+        }
+    }
+
+    /// Settings from a migrated object, each invalid value replaced by its
+    /// default. Returns the keys that were replaced.
+    pub fn from_map(stored: &Map<String, Value>) -> (Settings, Vec<String>) {
+        let mut map = Settings::default().to_map();
+        let mut invalid = Vec::new();
+        let keys: Vec<String> = map.keys().filter(|k| *k != "version").cloned().collect();
+        for key in keys {
+            let Some(value) = stored.get(&key) else {
+                continue;
+            };
+            let mut candidate = map.clone();
+            candidate.insert(key.clone(), value.clone());
+            match serde_json::from_value::<Settings>(Value::Object(candidate.clone())) {
+                Ok(settings) if settings.validate().is_ok() => map = candidate,
+                _ => invalid.push(key),
+            }
+        }
+        let settings = serde_json::from_value(Value::Object(map)).unwrap_or_default();
+        (settings, invalid)
+    }
+
+    /// These settings with `patch`, an object of camelCase keys, applied.
+    /// Returns the keys whose value changed.
+    pub fn patched(&self, patch: &Value) -> Result<(Settings, Vec<String>), String> {
+        let Value::Object(patch) = patch else {
+            return Err("Settings update must be an object".to_string());
+        };
+        let mut map = self.to_map();
+        for (key, value) in patch {
+            if key == "version" || !map.contains_key(key) {
+                return Err(format!("Unknown setting {}", key));
+            }
+            map.insert(key.clone(), value.clone());
+        }
+        let settings: Settings = serde_json::from_value(Value::Object(map))
+            .map_err(|e| format!("Invalid settings: {}", e))?;
+        settings.validate()?;
+        let changed = self.changed_keys(&settings);
+        Ok((settings, changed))
+    }
+
+    /// Keys whose value differs in `other`.
+    pub fn changed_keys(&self, other: &Settings) -> Vec<String> {
+        let before = self.to_map();
+        other
+            .to_map()
+            .into_iter()
+            .filter(|(key, value)| before.get(key) != Some(value))
+            .map(|(key, _)| key)
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SettingsStore {
+    file: Option<PathBuf>,
+    settings: Settings,
+}
+
+pub type SettingsState = Arc<Mutex<SettingsStore>>;
+
+impl SettingsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load `path`, or migrate `legacy` (the old store's entries) when it
+    /// doesn't exist yet. A migrated file is written back.
+    pub fn load(path: PathBuf, legacy: impl FnOnce() -> Option<Map<String, Value>>) -> Self {
+        let stored = match std::fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<Map<String, Value>>(&contents) {
+                Ok(map) => Some(map),
+                Err(e) => {
+                    log::warn!("failed to parse settings: {}", e);
+                    None
+                }
+            },
+            Err(_) => legacy(),
+        };
+        let migrated = stored.as_ref().is_some_and(|map| {
+            let version = map.get("version").and_then(Value::as_u64).unwrap_or(0);
+            version < u64::from(SETTINGS_VERSION)
+        });
+        let (settings, invalid) = Settings::from_map(&migrate(stored.unwrap_or_default()));
+        if !invalid.is_empty() {
+            log::warn!("invalid settings reset to default: {}", invalid.join(", "));
+        }
+        let store = Self {
+            file: Some(path),
+            settings,
+        };
+        if migrated {
+            if let Err(e) = store.save() {
+                log::warn!("failed to save migrated settings: {}", e);
+            }
+        }
+        store
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_vec_pretty(&self.settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Apply and save `patch`. Returns the keys whose value changed.
+    pub fn update(&mut self, patch: &Value) -> Result<Vec<String>, String> {
+        let (settings, changed) = self.settings.patched(patch)?;
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+        let previous = std::mem::replace(&mut self.settings, settings);
+        if let Err(e) = self.save() {
+            self.settings = previous;
+            return Err(e);
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("not an object"),
+        }
+    }
+
+    #[test]
+    fn test_migrate_legacy_store() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.json");
+        let legacy = object(json!({
+            "globalSettings": { "fontSize": 16, "startupCommand": "claude" },
+            "showTrayIcon": true,
+            "openWindowsAsTabs": "yes",
+            "recentProjects": [{ "name": "kiri", "path": "/kiri" }],
+        }));
+        let store = SettingsStore::load(path.clone(), || Some(legacy));
+        let settings = store.settings();
+        assert_eq!(settings.font_size, 16);
+        assert_eq!(settings.startup_command, StartupCommand::Claude);
+        assert!(settings.show_tray_icon);
+        assert!(!settings.open_windows_as_tabs);
+
+        let saved = object(serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap());
+        assert_eq!(saved["version"], json!(SETTINGS_VERSION));
+        assert!(!saved.contains_key("recentProjects"));
+        let reloaded = SettingsStore::load(path, || panic!("legacy store read again"));
+        assert_eq!(reloaded.settings(), settings);
+
+        let (settings, invalid) = Settings::from_map(&object(
+            json!({ "version": 1, "fontSize": 99, "showTrayIcon": true }),
+        ));
+        assert_eq!(settings.font_size, 13);
+        assert!(settings.show_tray_icon);
+        assert_eq!(invalid, vec!["fontSize"]);
+    }
+
+    #[test]
+    fn test_update_validates() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut store = SettingsStore::load(path.clone(), || None);
+        assert_eq!(store.settings(), &Settings::default());
+
+        let changed = store
+            .update(&json!({ "fontSize": 14, "startupCommand": "none" }))
+            .unwrap();
+        assert_eq!(changed, vec!["fontSize"]);
+        assert!(store.update(&json!({ "fontSize": 40 })).is_err());
+        assert!(store.update(&json!({ "startupCommand": "vim" })).is_err());
+        assert!(store.update(&json!({ "theme": "dark" })).is_err());
+        assert!(store.update(&json!({ "version": 0 })).is_err());
+        assert!(store.update(&json!(14)).is_err());
+        assert_eq!(store.settings().font_size, 14);
+
+        let reloaded = SettingsStore::load(path, || None);
+        assert_eq!(reloaded.settings().font_size, 14);
+    }
+}
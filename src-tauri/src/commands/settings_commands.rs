@@ -0,0 +1,136 @@
+//! Tauri commands for the typed settings in settings.rs.
+//!
+//! [`update_settings`] checks and saves a partial update, applies what
+//! changed to the running app (tray icon, window tabbing, memory
+//! sampling, usage analytics, update channel, language, large-file
+//! limits, watcher poll interval) and emits `settings-changed` to every window with the new
+//! settings and the keys that changed.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use super::error::KiriError;
+use super::i18n;
+use super::large_file::apply_large_file_limits;
+use super::lock_ext::LockExt;
+use super::memory_sampler_commands::apply_memory_metrics_setting;
+use super::settings::{settings_file_path, Settings, SettingsState, SettingsStore};
+use super::telemetry_commands::apply_usage_analytics_setting;
+use super::tray_commands::apply_tray_setting;
+use super::updater_commands::apply_update_channel_setting;
+use super::watcher::apply_poll_interval;
+use super::window_tabs::WindowTabbingState;
+
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+/// Store the settings lived in before settings.json.
+const LEGACY_STORE: &str = "kiri-settings.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsChanged {
+    pub settings: Settings,
+    pub changed: Vec<String>,
+}
+
+/// Read `~/.kiri/settings.json` into the managed state, importing the old
+/// store on first run. Call from setup before anything reads settings.
+pub fn load_settings(app: &AppHandle) {
+    let Some(path) = settings_file_path() else {
+        return;
+    };
+    let legacy = || {
+        let store = app.store(LEGACY_STORE).ok()?;
+        Some(store.entries().into_iter().collect::<Map<String, Value>>())
+    };
+    if let Some(state) = app.try_state::<SettingsState>() {
+        let store = SettingsStore::load(path, legacy);
+        i18n::apply_language(store.settings().language);
+        apply_large_file_limits(store.settings().large_file_limits());
+        apply_poll_interval(store.settings().watcher_poll_interval_ms);
+        *state.lock_recover() = store;
+    }
+}
+
+/// Apply `changed` settings to the running app and announce them.
+fn apply_changes(app: &AppHandle, settings: &Settings, changed: Vec<String>) -> Result<(), String> {
+    if changed.is_empty() {
+        return Ok(());
+    }
+    let mut result = Ok(());
+    for key in &changed {
+        match key.as_str() {
+            "showTrayIcon" => result = apply_tray_setting(app, settings.show_tray_icon),
+            "openWindowsAsTabs" => {
+                if let Some(tabbing) = app.try_state::<WindowTabbingState>() {
+                    tabbing.lock_recover().open_as_tabs = settings.open_windows_as_tabs;
+                }
+            }
+            "collectMemoryMetrics" => {
+                apply_memory_metrics_setting(app, settings.collect_memory_metrics)
+            }
+            "collectUsageAnalytics" => {
+                result = apply_usage_analytics_setting(settings.collect_usage_analytics)
+            }
+            "updateChannel" => apply_update_channel_setting(app),
+            "language" => i18n::apply_language(settings.language),
+            "largeFileBytes" | "largeFileLineLength" => {
+                apply_large_file_limits(settings.large_file_limits())
+            }
+            "watcherPollIntervalMs" => apply_poll_interval(settings.watcher_poll_interval_ms),
+            _ => {}
+        }
+    }
+    let _ = app.emit(
+        SETTINGS_CHANGED_EVENT,
+        &SettingsChanged {
+            settings: settings.clone(),
+            changed,
+        },
+    );
+    result
+}
+
+/// Apply `patch` to the settings, then to the running app, and announce
+/// it. Shared with the older per-setting commands.
+pub fn update_settings_impl(app: &AppHandle, patch: Value) -> Result<Settings, String> {
+    let state = app
+        .try_state::<SettingsState>()
+        .ok_or("Settings are not available")?;
+    let (settings, changed) = {
+        let mut store = state.lock_recover();
+        let changed = store.update(&patch)?;
+        (store.settings().clone(), changed)
+    };
+    apply_changes(app, &settings, changed).map(|_| settings)
+}
+
+/// Re-read `~/.kiri/settings.json`, e.g. after an import, and apply what
+/// changed.
+pub fn reload_settings(app: &AppHandle) -> Result<(), String> {
+    let (Some(path), Some(state)) = (settings_file_path(), app.try_state::<SettingsState>()) else {
+        return Ok(());
+    };
+    let (settings, changed) = {
+        let mut store = state.lock_recover();
+        let reloaded = SettingsStore::load(path, || None);
+        let changed = store.settings().changed_keys(reloaded.settings());
+        *store = reloaded;
+        (store.settings().clone(), changed)
+    };
+    apply_changes(app, &settings, changed)
+}
+
+#[tauri::command]
+pub fn get_settings(state: tauri::State<'_, SettingsState>) -> Settings {
+    state.lock_recover().settings().clone()
+}
+
+/// Update the settings named in `patch`, an object of camelCase keys. An
+/// unknown key or invalid value rejects the whole update. Returns the
+/// settings after it.
+#[tauri::command]
+pub fn update_settings(app: AppHandle, patch: Value) -> Result<Settings, KiriError> {
+    update_settings_impl(&app, patch).map_err(KiriError::from)
+}
@@ -0,0 +1,473 @@
+//! Settings that a project and each of its worktrees can override.
+//!
+//! Init commands, terminal environment, dev server port and terminal
+//! profile are looked up in three layers, each over the one before:
+//!
+//! 1. app: [`Settings`] (terminal profile and environment)
+//! 2. project: the `.kiri.toml` of the checkout (init commands,
+//!    environment, port strategy)
+//! 3. worktree: [`WorktreeOverrides`] saved per worktree in
+//!    `~/.kiri/worktrees.json`
+//!
+//! [`resolve`] merges them and records where each value came from, so the
+//! settings UI can show what a worktree inherits. Worktree overrides name
+//! paths on this machine, so they stay out of settings exports. Reading the
+//! layers for a directory lives in settings_layers_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use super::file_io::{recover_atomic_write, write_file_contents_atomic, WriteRecovery};
+use super::fs_path::display_path;
+use super::git_worktree::list_worktrees_for;
+use super::network;
+use super::process::launch_command;
+use super::project_config::{PortStrategy, ProjectConfig};
+use super::settings::{check_env_name, check_shell, Settings};
+use super::terminal::EnvOverrides;
+
+pub fn worktree_settings_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("worktrees.json"))
+}
+
+/// What one worktree sets over its project; unset fields are inherited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorktreeOverrides {
+    /// Replaces the project's `worktree.init` commands.
+    pub init_commands: Option<Vec<String>>,
+    /// Variables to set, or unset with `null`, over the inherited ones.
+    pub env: EnvOverrides,
+    /// Port pinned for the worktree's dev server.
+    pub port: Option<u16>,
+    pub terminal_profile: Option<String>,
+}
+
+impl WorktreeOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(commands) = &self.init_commands {
+            if commands.iter().any(|c| c.trim().is_empty()) {
+                return Err("Init commands must not be empty".to_string());
+            }
+        }
+        for name in self.env.keys() {
+            check_env_name(name).map_err(|e| format!("Variable `{}` {}", name, e))?;
+        }
+        if self.port == Some(0) {
+            return Err("Port must be between 1 and 65535".to_string());
+        }
+        if let Some(shell) = &self.terminal_profile {
+            check_shell(shell).map_err(|e| format!("Terminal profile {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layer {
+    App,
+    Project,
+    Worktree,
+}
+
+/// The settings in effect in a worktree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedSettings {
+    pub init_commands: Vec<String>,
+    pub env: EnvOverrides,
+    pub port: Option<u16>,
+    /// `None` for the login shell.
+    pub terminal_profile: Option<String>,
+    /// Layer each value came from, by camelCase key; variables as
+    /// `env.NAME`. Values left at their default have no entry.
+    pub sources: BTreeMap<String, Layer>,
+}
+
+impl ResolvedSettings {
+    /// Environment for a terminal: [`Self::env`] with `PORT` set to the
+    /// worktree's port unless a layer sets it already.
+    pub fn terminal_env(&self) -> EnvOverrides {
+        let mut env = self.env.clone();
+        if let Some(port) = self.port {
+            env.entry("PORT".to_string())
+                .or_insert_with(|| Some(port.to_string()));
+        }
+        env
+    }
+}
+
+/// Merge the layers for a worktree. `worktree_index` is its position in
+/// the repository's worktree list (the main checkout first), which the
+/// "offset" port strategy needs; without it only a pinned port applies.
+pub fn resolve(
+    app: &Settings,
+    project: &ProjectConfig,
+    worktree: &WorktreeOverrides,
+    worktree_index: Option<usize>,
+) -> ResolvedSettings {
+    let mut resolved = ResolvedSettings::default();
+    let mut sources = BTreeMap::new();
+    let mut set_source = |key: &str, layer: Layer| {
+        sources.insert(key.to_string(), layer);
+    };
+
+    if let Some(commands) = &worktree.init_commands {
+        resolved.init_commands = commands.clone();
+        set_source("initCommands", Layer::Worktree);
+    } else if !project.init_commands.is_empty() {
+        resolved.init_commands = project.init_commands.clone();
+        set_source("initCommands", Layer::Project);
+    }
+
+    let inherited = app
+        .terminal_env
+        .iter()
+        .map(|(name, value)| (name, Some(value), Layer::App))
+        .chain(
+            project
+                .env
+                .iter()
+                .map(|(name, value)| (name, Some(value), Layer::Project)),
+        );
+    let overridden = worktree
+        .env
+        .iter()
+        .map(|(name, value)| (name, value.as_ref(), Layer::Worktree));
+    for (name, value, layer) in inherited.chain(overridden) {
+        resolved.env.insert(name.clone(), value.cloned());
+        set_source(&format!("env.{}", name), layer);
+    }
+
+    if let Some(port) = worktree.port {
+        resolved.port = Some(port);
+        set_source("port", Layer::Worktree);
+    } else if let (PortStrategy::Offset { base, step }, Some(index)) =
+        (&project.ports, worktree_index)
+    {
+        let offset = u32::from(*step).saturating_mul(index as u32);
+        let port = u32::from(*base).saturating_add(offset);
+        resolved.port = u16::try_from(port).ok();
+        if resolved.port.is_some() {
+            set_source("port", Layer::Project);
+        }
+    }
+
+    if let Some(shell) = &worktree.terminal_profile {
+        resolved.terminal_profile = Some(shell.clone());
+        set_source("terminalProfile", Layer::Worktree);
+    } else if !app.terminal_profile.is_empty() {
+        resolved.terminal_profile = Some(app.terminal_profile.clone());
+        set_source("terminalProfile", Layer::App);
+    }
+    resolved.sources = sources;
+    resolved
+}
+
+/// Worktree overrides by worktree path.
+#[derive(Debug, Default)]
+pub struct WorktreeSettings {
+    file: Option<PathBuf>,
+    overrides: BTreeMap<String, WorktreeOverrides>,
+}
+
+pub type WorktreeSettingsState = Arc<Mutex<WorktreeSettings>>;
+
+/// [`resolve`] for the worktree at `worktree`, looking up its index in
+/// the repository's worktree list when the port strategy needs it.
+pub fn resolve_for_worktree(
+    app: &Settings,
+    project: &ProjectConfig,
+    overrides: &WorktreeOverrides,
+    worktree: &Path,
+) -> ResolvedSettings {
+    // Listing worktrees opens the repository; only the offset strategy
+    // needs it.
+    let index = match (&project.ports, overrides.port) {
+        (PortStrategy::Offset { .. }, None) => {
+            let path = display_path(worktree);
+            list_worktrees_for(worktree)
+                .ok()
+                .and_then(|list| list.iter().position(|w| w.path == path))
+        }
+        _ => None,
+    };
+    resolve(app, project, overrides, index)
+}
+
+fn key(worktree: &Path) -> String {
+    worktree.to_string_lossy().to_string()
+}
+
+impl WorktreeSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the overrides saved at `path`, first repairing what a crash
+    /// while saving left there and dropping entries that no longer pass
+    /// validation.
+    pub fn load(path: PathBuf) -> Self {
+        let parses = |contents: &[u8]| {
+            serde_json::from_slice::<BTreeMap<String, WorktreeOverrides>>(contents).is_ok()
+        };
+        match recover_atomic_write(&path, parses) {
+            Ok(WriteRecovery::Clean) => {}
+            Ok(WriteRecovery::Quarantined(corrupt)) => log::warn!(
+                "worktree settings were unreadable; moved them to {}",
+                corrupt.display()
+            ),
+            Ok(recovery) => {
+                log::warn!("recovered interrupted worktree settings save: {recovery:?}")
+            }
+            Err(e) => log::warn!("{}", e),
+        }
+        let mut overrides: BTreeMap<String, WorktreeOverrides> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(overrides) => Some(overrides),
+                Err(e) => {
+                    log::warn!("failed to parse worktree settings: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        overrides.retain(|worktree, entry| match entry.validate() {
+            Ok(()) if !entry.is_empty() => true,
+            Ok(()) => false,
+            Err(e) => {
+                log::warn!("dropping worktree settings of {}: {}", worktree, e);
+                false
+            }
+        });
+        Self {
+            file: Some(path),
+            overrides,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_vec_pretty(&self.overrides)
+            .map_err(|e| format!("Failed to serialize worktree settings: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    pub fn get(&self, worktree: &Path) -> WorktreeOverrides {
+        self.overrides
+            .get(&key(worktree))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Replace the overrides of `worktree`; empty ones are removed.
+    pub fn set(&mut self, worktree: &Path, overrides: WorktreeOverrides) -> Result<(), String> {
+        overrides.validate()?;
+        let previous = if overrides.is_empty() {
+            self.overrides.remove(&key(worktree))
+        } else {
+            self.overrides.insert(key(worktree), overrides)
+        };
+        if let Err(e) = self.save() {
+            match previous {
+                Some(previous) => self.overrides.insert(key(worktree), previous),
+                None => self.overrides.remove(&key(worktree)),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+/// The process for init command `argv` of `worktree`, with `env` set over
+/// kiri's environment. An init command like `npm install` runs on the
+/// user's behalf and needs their proxy and toolchain variables, so it
+/// inherits the environment (see process.rs) and goes through the
+/// configured proxy as git does.
+pub fn init_command(
+    worktree: &Path,
+    argv: &[String],
+    env: EnvOverrides,
+) -> Result<Command, String> {
+    let mut command = launch_command(argv, Some(worktree))?;
+    network::configure_command(&mut command);
+    for (key, value) in env {
+        match value {
+            Some(value) => command.env(key, value),
+            None => command.env_remove(key),
+        };
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_layers() {
+        let app = Settings {
+            terminal_profile: "/bin/zsh".to_string(),
+            terminal_env: BTreeMap::from([
+                ("EDITOR".to_string(), "vim".to_string()),
+                ("NODE_ENV".to_string(), "production".to_string()),
+            ]),
+            ..Settings::default()
+        };
+        let project = ProjectConfig {
+            init_commands: vec!["npm install".to_string()],
+            ports: PortStrategy::Offset {
+                base: 3000,
+                step: 10,
+            },
+            env: BTreeMap::from([("NODE_ENV".to_string(), "development".to_string())]),
+            ..ProjectConfig::default()
+        };
+
+        let resolved = resolve(&app, &project, &WorktreeOverrides::default(), Some(2));
+        assert_eq!(resolved.init_commands, vec!["npm install"]);
+        assert_eq!(resolved.env["NODE_ENV"].as_deref(), Some("development"));
+        assert_eq!(resolved.port, Some(3020));
+        assert_eq!(resolved.terminal_profile.as_deref(), Some("/bin/zsh"));
+        assert_eq!(resolved.sources["env.EDITOR"], Layer::App);
+        assert_eq!(resolved.sources["env.NODE_ENV"], Layer::Project);
+        assert_eq!(resolved.sources["port"], Layer::Project);
+        assert_eq!(resolved.terminal_env()["PORT"].as_deref(), Some("3020"));
+        assert_eq!(
+            resolve(&app, &project, &WorktreeOverrides::default(), None).port,
+            None
+        );
+
+        let worktree = WorktreeOverrides {
+            init_commands: Some(Vec::new()),
+            env: BTreeMap::from([
+                ("EDITOR".to_string(), None),
+                ("PORT".to_string(), Some("8080".to_string())),
+            ]),
+            port: Some(4100),
+            terminal_profile: Some("/bin/bash".to_string()),
+        };
+        let resolved = resolve(&app, &project, &worktree, Some(2));
+        assert!(resolved.init_commands.is_empty());
+        assert_eq!(resolved.env["EDITOR"], None);
+        assert_eq!(resolved.port, Some(4100));
+        assert_eq!(resolved.terminal_env()["PORT"].as_deref(), Some("8080"));
+        assert_eq!(resolved.terminal_profile.as_deref(), Some("/bin/bash"));
+        assert!(["initCommands", "env.EDITOR", "port", "terminalProfile"]
+            .iter()
+            .all(|key| resolved.sources[*key] == Layer::Worktree));
+    }
+
+    #[test]
+    fn test_worktree_settings_persist() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("worktrees.json");
+        let worktree = Path::new("/src/kiri-feature");
+        let mut settings = WorktreeSettings::load(path.clone());
+        assert!(settings.get(worktree).is_empty());
+
+        let overrides = WorktreeOverrides {
+            port: Some(4100),
+            ..WorktreeOverrides::default()
+        };
+        settings.set(worktree, overrides.clone()).unwrap();
+        let invalid = WorktreeOverrides {
+            terminal_profile: Some("zsh".to_string()),
+            ..WorktreeOverrides::default()
+        };
+        assert!(settings.set(worktree, invalid).is_err());
+        assert_eq!(
+            WorktreeSettings::load(path.clone()).get(worktree),
+            overrides
+        );
+
+        settings
+            .set(worktree, WorktreeOverrides::default())
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), "{}");
+    }
+
+    #[test]
+    fn test_worktree_settings_recover_interrupted_save() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("worktrees.json");
+        let tmp = dir.path().join("worktrees.json.kiri-tmp");
+        let worktree = Path::new("/src/kiri-feature");
+
+        // Crashed before the rename: the whole temp file wins over a torn one.
+        std::fs::write(&path, r#"{"/src/kiri-feature": {"po"#).unwrap();
+        std::fs::write(&tmp, r#"{"/src/kiri-feature": {"port": 4100}}"#).unwrap();
+        assert_eq!(
+            WorktreeSettings::load(path.clone()).get(worktree).port,
+            Some(4100)
+        );
+        assert!(!tmp.exists());
+
+        // Crashed while writing the temp file: the last save stays.
+        std::fs::write(&tmp, r#"{"/src/kiri-feature": {"port": 41"#).unwrap();
+        assert_eq!(
+            WorktreeSettings::load(path.clone()).get(worktree).port,
+            Some(4100)
+        );
+        assert!(!tmp.exists());
+
+        // An unreadable file is set aside rather than overwritten, and
+        // entries that no longer validate are dropped.
+        std::fs::write(&path, "\0\0\0").unwrap();
+        assert!(WorktreeSettings::load(path.clone())
+            .get(worktree)
+            .is_empty());
+        assert!(dir.path().join("worktrees.json.corrupt").exists());
+        std::fs::write(&path, r#"{"/src/kiri-feature": {"port": 0}}"#).unwrap();
+        assert!(WorktreeSettings::load(path).get(worktree).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_init_command_gets_proxy_and_env() {
+        use super::super::lock_ext::LockExt;
+        use super::super::network::{network, NetworkSettings, ProxyMode};
+
+        let dir = TempDir::new().unwrap();
+        network()
+            .lock_recover()
+            .set_settings(NetworkSettings {
+                proxy: ProxyMode::Manual,
+                proxy_url: Some("http://proxy.test:3128".to_string()),
+                ..NetworkSettings::default()
+            })
+            .unwrap();
+        let env = EnvOverrides::from([("KIRI_INIT_TEST".to_string(), Some("1".to_string()))]);
+        let output = init_command(dir.path(), &["env".to_string()], env)
+            .unwrap()
+            .output();
+        network()
+            .lock_recover()
+            .set_settings(NetworkSettings::default())
+            .unwrap();
+
+        let stdout = String::from_utf8(output.unwrap().stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert!(
+            lines.contains(&"HTTPS_PROXY=http://proxy.test:3128"),
+            "{stdout}"
+        );
+        assert!(lines.contains(&"KIRI_INIT_TEST=1"), "{stdout}");
+        assert!(lines.iter().any(|line| line.starts_with("PATH=")));
+    }
+}
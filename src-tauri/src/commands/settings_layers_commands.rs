@@ -0,0 +1,215 @@
+//! Tauri commands for the per-worktree settings of settings_layers.rs.
+//!
+//! [`resolve_settings_for`] reads the three layers for a directory: the
+//! app settings, the `.kiri.toml` that applies to it and the overrides of
+//! the worktree it is in. New terminals start with what it resolves, and
+//! [`run_worktree_init`] runs the resolved init commands of a trusted
+//! worktree (see trust.rs) as a job of kind `worktree-init`.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::confirmation::{require_confirmation, PrivilegedAction};
+use super::env_sets_commands::env_set_overlay;
+use super::error::KiriError;
+use super::fs_path::{display_path, normalize_input_path};
+use super::git_worktree::current_worktree_root;
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::spawn_job;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::process::split_argv;
+use super::project_config::ProjectConfigsState;
+use super::settings::SettingsState;
+use super::settings_layers::{
+    init_command, resolve_for_worktree, worktree_settings_file_path, ResolvedSettings,
+    WorktreeOverrides, WorktreeSettings, WorktreeSettingsState,
+};
+use super::toolchain;
+use super::trust_commands::require_trusted;
+
+pub const WORKTREE_SETTINGS_CHANGED_EVENT: &str = "worktree-settings-changed";
+
+const INIT_JOB_KIND: &str = "worktree-init";
+
+/// How often a running init command checks whether the job was cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorktreeSettingsChanged {
+    pub worktree: String,
+    pub overrides: WorktreeOverrides,
+    pub resolved: ResolvedSettings,
+}
+
+/// Read `~/.kiri/worktrees.json` into the managed state. Call from setup.
+pub fn load_worktree_settings(app: &AppHandle) {
+    let Some(path) = worktree_settings_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<WorktreeSettingsState>() {
+        *state.lock_recover() = WorktreeSettings::load(path);
+    }
+}
+
+/// The worktree `dir` is in, or `dir` itself outside a repository.
+fn worktree_of(dir: &Path) -> PathBuf {
+    current_worktree_root(dir).unwrap_or_else(|| dir.to_path_buf())
+}
+
+/// Settings in effect in `dir`, layered app → project → worktree.
+pub fn resolve_settings_for(app: &AppHandle, dir: &Path) -> ResolvedSettings {
+    let worktree = worktree_of(dir);
+    let settings = app
+        .try_state::<SettingsState>()
+        .map(|state| state.lock_recover().settings().clone())
+        .unwrap_or_default();
+    let project = app
+        .try_state::<ProjectConfigsState>()
+        .map(|state| state.lock_recover().get(dir).config)
+        .unwrap_or_default();
+    let overrides = app
+        .try_state::<WorktreeSettingsState>()
+        .map(|state| state.lock_recover().get(&worktree))
+        .unwrap_or_default();
+    resolve_for_worktree(&settings, &project, &overrides, &worktree)
+}
+
+/// Run one init command, `command` split into `argv`. No shell is
+/// involved; see [`init_command`].
+fn run_init_command(
+    app: &AppHandle,
+    ctx: &JobContext,
+    worktree: &Path,
+    command: &str,
+    argv: &[String],
+    resolved: &ResolvedSettings,
+) -> Result<(), String> {
+    // Checked per command, as trust can be revoked while the job runs.
+    require_trusted(app, worktree)?;
+    let mut env = toolchain::resolve(worktree).env;
+    env.extend(resolved.terminal_env());
+    env.extend(env_set_overlay(app, worktree));
+    let mut child = init_command(worktree, argv, env)?
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start `{}`: {}", command, e))?;
+    loop {
+        if ctx.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(());
+        }
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                return Err(match status.code() {
+                    Some(code) => format!("`{}` exited with {}", command, code),
+                    None => format!("`{}` was terminated", command),
+                })
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(format!("Failed to wait for `{}`: {}", command, e)),
+        }
+    }
+}
+
+/// Overrides saved for the worktree containing `path`.
+#[tauri::command]
+pub fn get_worktree_settings(
+    state: tauri::State<'_, WorktreeSettingsState>,
+    path: String,
+) -> WorktreeOverrides {
+    let worktree = worktree_of(&normalize_input_path(&path));
+    state.lock_recover().get(&worktree)
+}
+
+/// Replace the overrides of the worktree containing `path`; fields left
+/// out are inherited from the project and app settings again.
+#[tauri::command]
+pub fn set_worktree_settings(
+    app: AppHandle,
+    path: String,
+    overrides: WorktreeOverrides,
+) -> Result<ResolvedSettings, KiriError> {
+    let state = app
+        .try_state::<WorktreeSettingsState>()
+        .ok_or("Worktree settings are not available")?;
+    let worktree = worktree_of(&normalize_input_path(&path));
+    state.lock_recover().set(&worktree, overrides.clone())?;
+    let resolved = resolve_settings_for(&app, &worktree);
+    let event = WorktreeSettingsChanged {
+        worktree: display_path(&worktree),
+        overrides,
+        resolved: resolved.clone(),
+    };
+    let _ = app.emit(WORKTREE_SETTINGS_CHANGED_EVENT, &event);
+    Ok(resolved)
+}
+
+/// Settings in effect in `path`, with the layer each value came from.
+#[tauri::command]
+pub async fn resolve_worktree_settings(
+    app: AppHandle,
+    path: String,
+) -> Result<ResolvedSettings, KiriError> {
+    run_blocking("resolve_worktree_settings", move || {
+        Ok(resolve_settings_for(&app, &normalize_input_path(&path)))
+    })
+    .await
+}
+
+/// Run the init commands in effect for the worktree containing `path`, one
+/// after another, stopping at the first that fails. Returns the job id.
+/// Needs a token from `request_confirmation` for
+/// [`PrivilegedAction::RunInitCommands`] on `path`.
+#[tauri::command]
+pub async fn run_worktree_init(
+    app: AppHandle,
+    path: String,
+    confirmation_token: Option<String>,
+) -> Result<JobId, KiriError> {
+    let path = normalize_input_path(&path);
+    require_confirmation(
+        confirmation_token.as_deref(),
+        PrivilegedAction::RunInitCommands,
+        &path,
+    )?;
+    run_blocking("run_worktree_init", move || {
+        let worktree = worktree_of(&path);
+        let resolved = resolve_settings_for(&app, &worktree);
+        if resolved.init_commands.is_empty() {
+            return Err(format!(
+                "No init commands configured for {}",
+                worktree.display()
+            ));
+        }
+        // Split up front, so a command that needs a shell fails the call
+        // rather than the job halfway through.
+        let commands = resolved
+            .init_commands
+            .iter()
+            .map(|command| Ok((command.clone(), split_argv(command)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let label = format!("Initialize {}", worktree.display());
+        let job_app = app.clone();
+        spawn_job(&app, INIT_JOB_KIND, &label, move |ctx| {
+            let total = commands.len();
+            for (index, (command, argv)) in commands.iter().enumerate() {
+                if ctx.is_cancelled() {
+                    break;
+                }
+                let percent = (index * 100 / total) as u8;
+                ctx.progress(Some(percent), Some(command.clone()));
+                run_init_command(&job_app, ctx, &worktree, command, argv, &resolved)?;
+            }
+            Ok(())
+        })
+    })
+    .await
+}
@@ -0,0 +1,87 @@
+//! Tauri commands exporting and importing the settings archive of
+//! settings_sync.rs.
+//!
+//! An import writes the files, then reloads each into the running app as
+//! its own setup does (the menu is rebuilt for new keybindings, global
+//! shortcuts are registered again) and emits `settings-imported`, so open
+//! windows can re-read what they show.
+
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::auto_fetch_commands::load_auto_fetch_settings;
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::global_shortcut_commands::load_global_shortcuts;
+use super::keymap::KeymapState;
+use super::keymap_commands::load_keymap;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::menu::rebuild_app_menu;
+use super::network_commands::load_network_settings;
+use super::notifications_commands::load_notification_rules;
+use super::settings_commands::reload_settings;
+use super::settings_sync::{
+    build_archive, read_archive, restore_archive, write_archive, ImportSummary,
+};
+
+pub const SETTINGS_IMPORTED_EVENT: &str = "settings-imported";
+
+fn kiri_dir() -> Result<PathBuf, String> {
+    dirs::home_dir()
+        .map(|h| h.join(".kiri"))
+        .ok_or_else(|| "Could not find the home directory".to_string())
+}
+
+/// Load an imported file into the running app.
+fn reload(app: &AppHandle, file: &str) -> Result<(), String> {
+    match file {
+        "settings.json" => reload_settings(app)?,
+        "keymap.json" => {
+            load_keymap(app);
+            rebuild_app_menu(app);
+            if let Some(keymap) = app.try_state::<KeymapState>() {
+                let entries = keymap.lock_recover().entries();
+                let _ = app.emit("keymap-changed", &entries);
+            }
+        }
+        "shortcuts.json" => load_global_shortcuts(app),
+        "notifications.json" => load_notification_rules(app),
+        "network.json" => load_network_settings(),
+        "auto_fetch.json" => load_auto_fetch_settings(app),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Write settings, keybindings, global shortcuts, notification rules,
+/// network and auto-fetch settings to `path` as one file. Secrets are not
+/// included. Returns the files exported.
+#[tauri::command]
+pub async fn export_settings(path: String) -> Result<Vec<String>, KiriError> {
+    run_blocking("export_settings", move || {
+        let archive = build_archive(&kiri_dir()?);
+        write_archive(&normalize_input_path(&path), &archive)?;
+        Ok(archive.files.into_keys().collect())
+    })
+    .await
+}
+
+/// Replace the settings with those exported to `path` and apply them.
+#[tauri::command]
+pub async fn import_settings(app: AppHandle, path: String) -> Result<ImportSummary, KiriError> {
+    let summary = run_blocking("import_settings", move || {
+        let archive = read_archive(&normalize_input_path(&path))?;
+        restore_archive(&kiri_dir()?, &archive)
+    })
+    .await?;
+    let mut result = Ok(());
+    for file in &summary.imported {
+        if let Err(e) = reload(&app, file) {
+            log::warn!("failed to apply imported {}: {}", file, e);
+            result = Err(e);
+        }
+    }
+    let _ = app.emit(SETTINGS_IMPORTED_EVENT, &summary);
+    result.map(|_| summary).map_err(KiriError::from)
+}
@@ -0,0 +1,138 @@
+//! Coordinated teardown when kiri exits.
+//!
+//! Each kind of resource that can outlive the app (terminals, watchers,
+//! task and agent processes, tunnels, language servers, sockets) has a
+//! step that stops it. [`Shutdown::run`] starts every step on its own
+//! thread and waits for them up to a shared deadline, so one hung step
+//! (a shell that ignores SIGKILL, a tunnel binary stuck on the network)
+//! neither blocks the others nor keeps the app from exiting. Steps that
+//! miss the deadline or panic are reported so they can be logged as
+//! leaks. The steps themselves are in shutdown_commands.rs.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long exit waits for all steps together.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How a step ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Stopped,
+    /// Still running at the deadline; whatever it was stopping may leak.
+    TimedOut,
+    /// Panicked, or its thread could not be started.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    pub name: &'static str,
+    pub outcome: StepOutcome,
+    /// Time until the step finished, or the deadline.
+    pub elapsed: Duration,
+}
+
+type Step = Box<dyn FnOnce() + Send>;
+
+/// The steps to run on exit.
+#[derive(Default)]
+pub struct Shutdown {
+    steps: Vec<(&'static str, Step)>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a step. `name` labels its thread and its report.
+    pub fn step(mut self, name: &'static str, run: impl FnOnce() + Send + 'static) -> Self {
+        self.steps.push((name, Box::new(run)));
+        self
+    }
+
+    /// Run every step concurrently and wait up to `timeout` for them.
+    /// Reports come back in the order the steps were added.
+    pub fn run(self, timeout: Duration) -> Vec<StepReport> {
+        let started = Instant::now();
+        let names: Vec<&'static str> = self.steps.iter().map(|(name, _)| *name).collect();
+        let mut finished: Vec<Option<(StepOutcome, Duration)>> = vec![None; names.len()];
+        let (tx, rx) = mpsc::channel();
+        for (index, (name, run)) in self.steps.into_iter().enumerate() {
+            let tx = tx.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("kiri-shutdown-{}", name))
+                .spawn(move || {
+                    let outcome = match panic::catch_unwind(AssertUnwindSafe(run)) {
+                        Ok(()) => StepOutcome::Stopped,
+                        Err(_) => StepOutcome::Failed,
+                    };
+                    let _ = tx.send((index, outcome, started.elapsed()));
+                });
+            if spawned.is_err() {
+                finished[index] = Some((StepOutcome::Failed, Duration::ZERO));
+            }
+        }
+        drop(tx);
+
+        let deadline = started + timeout;
+        while finished.iter().any(Option::is_none) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(remaining) {
+                Ok((index, outcome, elapsed)) => finished[index] = Some((outcome, elapsed)),
+                Err(_) => break,
+            }
+        }
+
+        names
+            .into_iter()
+            .zip(finished)
+            .map(|(name, finished)| {
+                let (outcome, elapsed) = finished.unwrap_or((StepOutcome::TimedOut, timeout));
+                StepReport {
+                    name,
+                    outcome,
+                    elapsed,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_hung_step_times_out_without_holding_up_the_rest() {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stopped);
+        let started = Instant::now();
+        let reports = Shutdown::new()
+            .step("hung", || thread::sleep(Duration::from_secs(10)))
+            .step("quick", move || flag.store(true, Ordering::SeqCst))
+            .run(Duration::from_millis(200));
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(stopped.load(Ordering::SeqCst));
+        assert_eq!(reports[0].name, "hung");
+        assert_eq!(reports[0].outcome, StepOutcome::TimedOut);
+        assert_eq!(reports[1].outcome, StepOutcome::Stopped);
+    }
+
+    #[test]
+    fn test_panicking_step_is_reported() {
+        let reports = Shutdown::new()
+            .step("broken", || panic!("step failed"))
+            .step("fine", || {})
+            .run(Duration::from_secs(5));
+
+        let outcomes: Vec<StepOutcome> = reports.iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, vec![StepOutcome::Failed, StepOutcome::Stopped]);
+    }
+}
@@ -0,0 +1,130 @@
+//! What kiri stops on exit; the coordinator is in shutdown.rs.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentSessionsState;
+use super::cli_server::CliServerRegistryState;
+use super::git_cache::git_cache;
+use super::jobs::JobState;
+use super::lock_ext::LockExt;
+use super::lsp_commands::stop_all_lsp_servers;
+use super::mcp_commands::stop_mcp_server;
+use super::operations::operations;
+use super::shutdown::{Shutdown, StepOutcome, SHUTDOWN_TIMEOUT};
+use super::single_instance_commands::stop_launcher;
+use super::task_watch::TaskWatchesState;
+use super::tasks::TaskRunsState;
+use super::telemetry_commands::flush_usage_analytics;
+use super::terminal::TerminalState;
+use super::tunnel_commands::stop_all_tunnels;
+use super::watcher::WatcherState;
+
+/// Set once shutdown has started, so a second exit event is a no-op.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Stop everything that could outlive the app: CLI sockets, terminals and
+/// their shells, watchers, jobs and operations, task and agent processes,
+/// language servers, tunnels and the MCP server. Waits at most
+/// [`SHUTDOWN_TIMEOUT`] and logs whatever did not stop in time as a
+/// possible leak. Called from `RunEvent::Exit`.
+pub fn shutdown(app: &AppHandle) {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let started = Instant::now();
+    let with_app = |run: fn(&AppHandle)| {
+        let app = app.clone();
+        move || run(&app)
+    };
+
+    let reports = Shutdown::new()
+        .step("cli-servers", with_app(stop_cli_servers))
+        .step("launcher", with_app(stop_launcher))
+        .step("terminals", with_app(stop_terminals))
+        .step("watchers", with_app(stop_watchers))
+        .step("jobs", with_app(cancel_jobs))
+        .step("processes", with_app(kill_processes))
+        .step("lsp", with_app(stop_all_lsp_servers))
+        .step("tunnels", with_app(stop_all_tunnels))
+        .step("mcp", with_app(stop_mcp_server))
+        .step("analytics", flush_usage_analytics)
+        .run(SHUTDOWN_TIMEOUT);
+
+    for report in &reports {
+        match report.outcome {
+            StepOutcome::Stopped => {}
+            StepOutcome::TimedOut => log::warn!(
+                "shutdown: {} still stopping after {:?}; its resources may leak",
+                report.name,
+                report.elapsed
+            ),
+            StepOutcome::Failed => {
+                log::warn!("shutdown: {} failed; its resources may leak", report.name)
+            }
+        }
+    }
+    log::info!("shutdown finished in {:?}", started.elapsed());
+}
+
+fn stop_cli_servers(app: &AppHandle) {
+    if let Some(registry) = app.try_state::<CliServerRegistryState>() {
+        registry.stop_all();
+    }
+}
+
+/// Kill every shell and wait for it, so none is left as a zombie.
+fn stop_terminals(app: &AppHandle) {
+    let Some(state) = app.try_state::<TerminalState>() else {
+        return;
+    };
+    let terminals = state.remove_all();
+    for terminal in &terminals {
+        terminal.kill();
+    }
+    log::info!("shutdown: stopped {} terminal(s)", terminals.len());
+}
+
+fn stop_watchers(app: &AppHandle) {
+    let Some(state) = app.try_state::<WatcherState>() else {
+        return;
+    };
+    let watchers = state.remove_all();
+    git_cache().lock_recover().clear();
+    // Before processes are killed, so a change can't start a task again.
+    let task_watches = app
+        .try_state::<TaskWatchesState>()
+        .map(|state| state.lock_recover().clear().len())
+        .unwrap_or(0);
+    log::info!(
+        "shutdown: stopped {} watcher(s) and {} task watch(es)",
+        watchers.len(),
+        task_watches
+    );
+}
+
+/// Signal background jobs and frontend-awaited operations to stop. Their
+/// threads end with the process; this lets the ones that own a child
+/// process (test runs, hooks, git) kill it first.
+fn cancel_jobs(app: &AppHandle) {
+    let jobs = app
+        .try_state::<JobState>()
+        .map(|state| state.lock_recover().cancel_all())
+        .unwrap_or(0);
+    let operations = operations().lock_recover().cancel_all();
+    log::info!("shutdown: cancelled {jobs} job(s) and {operations} operation(s)");
+}
+
+/// Kill the processes of running tasks and agent sessions.
+fn kill_processes(app: &AppHandle) {
+    let tasks = app
+        .try_state::<TaskRunsState>()
+        .map(|state| state.lock_recover().kill_all())
+        .unwrap_or(0);
+    let agents = app
+        .try_state::<AgentSessionsState>()
+        .map(|state| state.lock_recover().kill_all())
+        .unwrap_or(0);
+    log::info!("shutdown: killed {tasks} task(s) and {agents} agent session(s)");
+}
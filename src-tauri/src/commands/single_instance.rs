@@ -0,0 +1,366 @@
+//! Single-instance launcher.
+//!
+//! The first kiri process listens on `~/.kiri/launcher.sock`. A second
+//! launch (`kiri .` from a shell, a file manager "Open with", or a
+//! `kiri://` link on Windows/Linux) connects to that socket, forwards its
+//! arguments and working directory as one newline-delimited JSON
+//! [`LaunchRequest`], waits for an acknowledgement, and exits instead of
+//! starting a second app.
+//!
+//! The socket deliberately lives outside `~/.kiri/instances`, which the
+//! `kiri` CLI enumerates as one socket per window.
+
+use interprocess::local_socket::tokio::prelude::*;
+use interprocess::local_socket::traits::Stream as _;
+use interprocess::local_socket::{GenericFilePath, ListenerOptions, ToFsName};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+
+use super::deep_link::is_deep_link;
+use super::fs_path::{display_path, normalize_input_path};
+use super::lock_ext::LockExt;
+
+/// How long a second launch waits for the running instance to acknowledge
+/// before giving up and starting on its own.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(3);
+
+const ACK: &str = "ok";
+
+/// `~/.kiri/launcher.sock` — the app-level socket for forwarded launches.
+pub fn launcher_socket_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("launcher.sock"))
+}
+
+/// Arguments of a launch, as seen by the process that received them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchRequest {
+    /// Process arguments without the executable name.
+    pub args: Vec<String>,
+    /// Working directory of the launching process, used to resolve
+    /// relative paths such as `.`.
+    pub cwd: String,
+}
+
+impl LaunchRequest {
+    /// Build a request from `std::env::args()`-style input, dropping the
+    /// executable name.
+    pub fn from_env<I, S>(args: I, cwd: &Path) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            args: args.into_iter().skip(1).map(Into::into).collect(),
+            cwd: display_path(cwd),
+        }
+    }
+}
+
+/// What a single launch argument asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchTarget {
+    /// A directory to open (or focus, if a window already shows it).
+    Directory(String),
+    /// A `kiri://` URL to hand to the deep-link dispatcher.
+    DeepLink(String),
+}
+
+/// Classify the arguments of `request`. Flags (anything starting with `-`)
+/// are ignored, relative paths are resolved against the request's `cwd`,
+/// and a file argument opens its parent directory. Paths that do not exist
+/// are dropped with a warning rather than failing the whole launch.
+pub fn launch_targets(request: &LaunchRequest) -> Vec<LaunchTarget> {
+    let cwd = normalize_input_path(&request.cwd);
+    let mut targets = Vec::new();
+
+    for arg in &request.args {
+        if is_deep_link(arg) {
+            targets.push(LaunchTarget::DeepLink(arg.clone()));
+            continue;
+        }
+        if arg.is_empty() || arg.starts_with('-') {
+            continue;
+        }
+
+        let path = normalize_input_path(arg);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            cwd.join(path)
+        };
+        let canonical = match std::fs::canonicalize(&path) {
+            Ok(p) => p,
+            Err(e) => {
+                log::warn!("launch: ignoring {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let dir = if canonical.is_dir() {
+            canonical
+        } else {
+            match canonical.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => continue,
+            }
+        };
+        let target = LaunchTarget::Directory(display_path(&dir));
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    targets
+}
+
+/// Try to hand `request` to an already-running instance listening on
+/// `socket_path`.
+///
+/// Returns `Ok(true)` once the running instance acknowledged the request,
+/// and `Ok(false)` when nothing is listening (the caller should start
+/// normally). Other I/O failures are returned as errors.
+pub fn forward_to_running_instance(
+    socket_path: &Path,
+    request: &LaunchRequest,
+) -> std::io::Result<bool> {
+    if !socket_path.exists() {
+        return Ok(false);
+    }
+    let name = socket_path.as_os_str().to_fs_name::<GenericFilePath>()?;
+    let stream = match interprocess::local_socket::Stream::connect(name) {
+        Ok(stream) => stream,
+        // A leftover socket from a crashed instance refuses connections.
+        Err(_) => return Ok(false),
+    };
+
+    let mut line = serde_json::to_vec(request).map_err(std::io::Error::other)?;
+    line.push(b'\n');
+
+    // The blocking stream has no read timeout, so the exchange runs on a
+    // helper thread and we stop waiting after FORWARD_TIMEOUT.
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = (|| -> std::io::Result<String> {
+            let mut stream = stream;
+            stream.write_all(&line)?;
+            stream.flush()?;
+            let mut reply = String::new();
+            BufReader::new(stream).read_line(&mut reply)?;
+            Ok(reply)
+        })();
+        let _ = tx.send(result);
+    });
+
+    match rx.recv_timeout(FORWARD_TIMEOUT) {
+        Ok(Ok(reply)) => Ok(reply.trim() == ACK),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "running instance did not acknowledge the launch",
+        )),
+    }
+}
+
+/// Handle for the launcher listener; dropping it leaves the listener
+/// running, [`LauncherHandle::stop`] shuts it down and removes the socket.
+pub struct LauncherHandle {
+    socket_path: PathBuf,
+    stop: std::sync::Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl LauncherHandle {
+    pub fn stop(&self) {
+        if let Some(tx) = self.stop.lock_recover().take() {
+            let _ = tx.send(());
+        }
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+/// Listen on `socket_path` and call `on_launch` for every forwarded
+/// request. Must be called from within a Tokio runtime.
+///
+/// Fails with `AddrInUse` if another live instance already owns the
+/// socket, so two instances racing at startup never steal each other's
+/// listener.
+pub fn spawn_listener<F>(socket_path: PathBuf, on_launch: F) -> std::io::Result<LauncherHandle>
+where
+    F: Fn(LaunchRequest) + Send + Sync + 'static,
+{
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if socket_path.exists() {
+        let name = socket_path.as_os_str().to_fs_name::<GenericFilePath>()?;
+        if interprocess::local_socket::Stream::connect(name).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                "another kiri instance owns the launcher socket",
+            ));
+        }
+        // Stale socket from a previous session; it would block bind.
+        let _ = std::fs::remove_file(&socket_path);
+    }
+
+    let name = socket_path.as_os_str().to_fs_name::<GenericFilePath>()?;
+    let listener = ListenerOptions::new().name(name).create_tokio()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+    let on_launch = std::sync::Arc::new(on_launch);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut stop_rx => break,
+                conn = listener.accept() => match conn {
+                    Ok(stream) => {
+                        let on_launch = on_launch.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_connection(stream, on_launch.as_ref()).await {
+                                log::warn!("launcher connection error: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("launcher accept failed: {e}"),
+                },
+            }
+        }
+    });
+
+    Ok(LauncherHandle {
+        socket_path,
+        stop: std::sync::Mutex::new(Some(stop_tx)),
+    })
+}
+
+async fn handle_connection<F>(
+    stream: interprocess::local_socket::tokio::Stream,
+    on_launch: &F,
+) -> std::io::Result<()>
+where
+    F: Fn(LaunchRequest),
+{
+    use interprocess::local_socket::traits::tokio::Stream as _;
+    let (reader, mut writer) = stream.split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    if let Some(line) = lines.next_line().await? {
+        let request: LaunchRequest = serde_json::from_str(&line)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        on_launch(request);
+        writer.write_all(format!("{ACK}\n").as_bytes()).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    fn request(args: &[&str], cwd: &Path) -> LaunchRequest {
+        LaunchRequest {
+            args: args.iter().map(|s| s.to_string()).collect(),
+            cwd: display_path(cwd),
+        }
+    }
+
+    #[test]
+    fn test_from_env_drops_executable() {
+        let req = LaunchRequest::from_env(["/usr/bin/kiri", "."], Path::new("/tmp"));
+        assert_eq!(req.args, vec!["."]);
+        assert_eq!(req.cwd, "/tmp");
+    }
+
+    #[test]
+    fn test_launch_targets_resolves_relative_to_cwd() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let root = display_path(&std::fs::canonicalize(dir.path()).unwrap());
+
+        let targets = launch_targets(&request(&[".", "sub", "--verbose"], dir.path()));
+        assert_eq!(
+            targets,
+            vec![
+                LaunchTarget::Directory(root.clone()),
+                LaunchTarget::Directory(format!("{}/sub", root)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_launch_targets_file_opens_parent_and_dedupes() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "").unwrap();
+        let root = display_path(&std::fs::canonicalize(dir.path()).unwrap());
+
+        let targets = launch_targets(&request(&["a.txt", "."], dir.path()));
+        assert_eq!(targets, vec![LaunchTarget::Directory(root)]);
+    }
+
+    #[test]
+    fn test_launch_targets_keeps_deep_links_and_skips_missing() {
+        let dir = TempDir::new().unwrap();
+        let targets = launch_targets(&request(
+            &["does-not-exist", "kiri://open?path=/a"],
+            dir.path(),
+        ));
+        assert_eq!(
+            targets,
+            vec![LaunchTarget::DeepLink("kiri://open?path=/a".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_forward_without_listener_returns_false() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("launcher.sock");
+        let req = request(&["."], dir.path());
+        assert!(!forward_to_running_instance(&socket, &req).unwrap());
+
+        // A stale socket file with no listener behind it is also "not running".
+        std::fs::write(&socket, "").unwrap();
+        assert!(!forward_to_running_instance(&socket, &req).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_forward_reaches_listener() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("launcher.sock");
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let handle = spawn_listener(socket.clone(), move |req| {
+            sink.lock().unwrap().push(req);
+        })
+        .unwrap();
+
+        let req = request(&["."], dir.path());
+        let forwarded = {
+            let socket = socket.clone();
+            let req = req.clone();
+            tokio::task::spawn_blocking(move || forward_to_running_instance(&socket, &req))
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        assert!(forwarded);
+        assert_eq!(*received.lock().unwrap(), vec![req]);
+
+        // A second listener must not steal the live socket.
+        let err = spawn_listener(socket.clone(), |_| {}).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+        handle.stop();
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,111 @@
+//! Tauri glue for the single-instance launcher. Socket handling and
+//! argument parsing live in single_instance.rs.
+
+use super::deep_link_commands::handle_deep_link;
+use super::lock_ext::LockExt;
+use super::single_instance::{
+    forward_to_running_instance, launch_targets, launcher_socket_path, spawn_listener,
+    LaunchRequest, LaunchTarget, LauncherHandle,
+};
+use super::window::{route_to_project, WindowRegistryState};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+pub type LauncherState = Arc<Mutex<Option<LauncherHandle>>>;
+
+fn current_launch_request() -> LaunchRequest {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    LaunchRequest::from_env(std::env::args(), &cwd)
+}
+
+/// Called first thing in `run()`: if another kiri instance is running,
+/// hand it this process's arguments and return `true` so the caller exits
+/// instead of starting a second app. Any failure falls back to a normal
+/// start.
+pub fn forward_launch_if_running() -> bool {
+    let Some(socket) = launcher_socket_path() else {
+        return false;
+    };
+    match forward_to_running_instance(&socket, &current_launch_request()) {
+        Ok(forwarded) => forwarded,
+        Err(e) => {
+            eprintln!("kiri: could not reach running instance, starting a new one: {e}");
+            false
+        }
+    }
+}
+
+/// Start listening for forwarded launches. Best-effort: if the socket
+/// cannot be bound, this instance simply does not receive them.
+pub fn start_launcher(app: &AppHandle) {
+    let Some(socket) = launcher_socket_path() else {
+        return;
+    };
+    let handle_for_launch = app.clone();
+    let result = tauri::async_runtime::block_on(async move {
+        spawn_listener(socket, move |request| {
+            handle_forwarded_launch(&handle_for_launch, request)
+        })
+    });
+    match result {
+        Ok(handle) => *app.state::<LauncherState>().lock_recover() = Some(handle),
+        Err(e) => log::warn!("failed to start launcher socket: {e}"),
+    }
+}
+
+/// Stop the listener and remove its socket. Called on application exit.
+pub fn stop_launcher(app: &AppHandle) {
+    if let Some(handle) = app.state::<LauncherState>().lock_recover().take() {
+        handle.stop();
+    }
+}
+
+/// Open whatever this process was launched with (directories and
+/// `kiri://` links). Used for the first instance's own arguments.
+pub fn open_startup_targets(app: &AppHandle) {
+    open_targets(app, launch_targets(&current_launch_request()));
+}
+
+/// A second launch with nothing to open (plain `kiri`) still brings the
+/// app to the front.
+fn handle_forwarded_launch(app: &AppHandle, request: LaunchRequest) {
+    let targets = launch_targets(&request);
+    if targets.is_empty() {
+        focus_any_window(app);
+    } else {
+        open_targets(app, targets);
+    }
+}
+
+fn open_targets(app: &AppHandle, targets: Vec<LaunchTarget>) {
+    for target in targets {
+        // Window creation and focus must happen on the main thread; the
+        // listener runs on the async runtime.
+        let app_for_target = app.clone();
+        let _ = app.run_on_main_thread(move || match target {
+            LaunchTarget::Directory(path) => {
+                let registry = app_for_target.state::<WindowRegistryState>();
+                if let Err(e) = route_to_project(&app_for_target, &registry, &path) {
+                    log::warn!("launch: failed to open window: {e}");
+                }
+            }
+            LaunchTarget::DeepLink(url) => handle_deep_link(&app_for_target, &url),
+        });
+    }
+}
+
+/// Bring the main window (or any window) to the front.
+pub fn focus_any_window(app: &AppHandle) {
+    let app_for_focus = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        let windows = app_for_focus.webview_windows();
+        let window = windows
+            .get("main")
+            .or_else(|| windows.values().next())
+            .cloned();
+        if let Some(window) = window {
+            let _ = window.unminimize();
+            let _ = window.set_focus();
+        }
+    });
+}
@@ -12,6 +12,8 @@ use std::io;
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
 
+use super::error::KiriError;
+
 /// What needs to happen to bring the installed skill up to the bundled source version.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -295,8 +297,8 @@ pub fn kiri_skill_status(app: tauri::AppHandle) -> SkillStatus {
 /// Tauri command: copy the bundled skill into ~/.claude/skills/kiri-cli/SKILL.md.
 /// `force=true` overwrites even when versions match.
 #[tauri::command]
-pub fn install_kiri_skill(app: tauri::AppHandle, force: bool) -> Result<InstallReport, String> {
-    install_skill_inner(&app, force).map_err(|e| e.to_string())
+pub fn install_kiri_skill(app: tauri::AppHandle, force: bool) -> Result<InstallReport, KiriError> {
+    install_skill_inner(&app, force).map_err(KiriError::from)
 }
 
 #[cfg(test)]
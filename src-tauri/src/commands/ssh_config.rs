@@ -0,0 +1,371 @@
+//! Host inventory from the user's SSH config, for the SSH terminal picker.
+//!
+//! `~/.ssh/config` is read the way `ssh` reads it, minus what only makes
+//! sense at connect time: keywords are case-insensitive and take their
+//! arguments after whitespace or `=`, `Include` is expanded in place
+//! (globs, relative to `~/.ssh`), and for each setting the first value
+//! that applies to a host wins. Every alias of a `Host` line without
+//! wildcards or negation is a host of the inventory; wildcard blocks such
+//! as `Host *` only contribute defaults. `Match` blocks depend on the
+//! connection and are skipped.
+//!
+//! [`probe_error`] makes sense of a failed `ssh` probe; the probe itself
+//! runs in ssh_config_commands.rs.
+
+use glob::{MatchOptions, Pattern};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// How deep `Include` may nest, as in OpenSSH.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// One `Keyword arguments` line of a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLine {
+    /// Lowercased.
+    pub keyword: String,
+    pub args: Vec<String>,
+    pub source: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshHost {
+    /// The name to pass to `ssh`.
+    pub alias: String,
+    pub host_name: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    /// As written, e.g. `~/.ssh/id_ed25519`.
+    pub identity_files: Vec<String>,
+    /// `ProxyJump` hosts, in the order they are passed through.
+    pub proxy_jump: Vec<String>,
+    /// File and line of the `Host` line naming it.
+    pub source: String,
+    pub line: usize,
+}
+
+/// Why an `ssh` probe failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SshProbeError {
+    /// No key or agent identity was accepted; a password would be needed.
+    AuthFailed,
+    /// The host key is unknown or has changed.
+    HostKey,
+    Unresolved,
+    Refused,
+    Timeout,
+    Other,
+}
+
+/// Split a config line into words, honouring double quotes.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        words.push(current);
+    }
+    words
+}
+
+/// The lines of one config file, without expanding `Include`.
+pub fn parse_lines(text: &str, source: &str) -> Vec<ConfigLine> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            // `Keyword=value` and `Keyword = value` are the same as
+            // `Keyword value`.
+            let split = line
+                .find(|c: char| c.is_whitespace() || c == '=')
+                .unwrap_or(line.len());
+            let (keyword, rest) = line.split_at(split);
+            let rest = rest.trim_start();
+            let rest = rest.strip_prefix('=').unwrap_or(rest);
+            Some(ConfigLine {
+                keyword: keyword.to_ascii_lowercase(),
+                args: words(rest),
+                source: source.to_string(),
+                line: index + 1,
+            })
+        })
+        .collect()
+}
+
+fn read_config(path: &Path, ssh_dir: &Path, depth: usize, lines: &mut Vec<ConfigLine>) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    for line in parse_lines(&text, &path.to_string_lossy()) {
+        if line.keyword != "include" {
+            lines.push(line);
+            continue;
+        }
+        if depth >= MAX_INCLUDE_DEPTH {
+            log::warn!("ssh config Include nested too deeply at {}", line.source);
+            continue;
+        }
+        for arg in &line.args {
+            let pattern = match arg.strip_prefix("~/") {
+                Some(rest) => ssh_dir.parent().unwrap_or(ssh_dir).join(rest),
+                None => ssh_dir.join(arg),
+            };
+            let Ok(paths) = glob::glob(&pattern.to_string_lossy()) else {
+                continue;
+            };
+            for included in paths.flatten() {
+                read_config(&included, ssh_dir, depth + 1, lines);
+            }
+        }
+    }
+}
+
+/// `config` with its `Include`s expanded; relative includes are looked
+/// up in `ssh_dir`. A missing file has no lines.
+pub fn read_config_lines(config: &Path, ssh_dir: &Path) -> Vec<ConfigLine> {
+    let mut lines = Vec::new();
+    read_config(config, ssh_dir, 0, &mut lines);
+    lines
+}
+
+fn is_pattern(name: &str) -> bool {
+    name.contains(['*', '?', '!'])
+}
+
+/// Whether a `Host` line's patterns apply to `alias`: one matches and no
+/// negated one does.
+fn host_matches(patterns: &[String], alias: &str) -> bool {
+    let options = MatchOptions {
+        case_sensitive: false,
+        ..MatchOptions::new()
+    };
+    let matches =
+        |pattern: &str| Pattern::new(pattern).is_ok_and(|p| p.matches_with(alias, options));
+    let mut matched = false;
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if matches(negated) => return false,
+            Some(_) => {}
+            None => matched |= matches(pattern),
+        }
+    }
+    matched
+}
+
+/// The settings that apply to `alias`, first value winning.
+fn resolve(lines: &[ConfigLine], alias: &str) -> SshHost {
+    let mut host = SshHost {
+        alias: alias.to_string(),
+        ..SshHost::default()
+    };
+    // Lines before the first `Host` apply to every host.
+    let mut applies = true;
+    let mut jump_set = false;
+    for line in lines {
+        match line.keyword.as_str() {
+            "host" => {
+                applies = host_matches(&line.args, alias);
+                continue;
+            }
+            "match" => {
+                applies = false;
+                continue;
+            }
+            _ if !applies => continue,
+            _ => {}
+        }
+        let Some(value) = line.args.first() else {
+            continue;
+        };
+        match line.keyword.as_str() {
+            "hostname" if host.host_name.is_none() => {
+                host.host_name = Some(value.replace("%h", alias));
+            }
+            "user" if host.user.is_none() => host.user = Some(value.clone()),
+            "port" if host.port.is_none() => host.port = value.parse().ok(),
+            "identityfile"
+                if !value.eq_ignore_ascii_case("none") && !host.identity_files.contains(value) =>
+            {
+                host.identity_files.push(value.clone());
+            }
+            "proxyjump" if !jump_set => {
+                jump_set = true;
+                if !value.eq_ignore_ascii_case("none") {
+                    host.proxy_jump = value.split(',').map(str::to_string).collect();
+                }
+            }
+            _ => {}
+        }
+    }
+    host
+}
+
+/// Every host named by a `Host` line, in file order, with its settings.
+pub fn inventory(lines: &[ConfigLine]) -> Vec<SshHost> {
+    let mut hosts: Vec<SshHost> = Vec::new();
+    for line in lines.iter().filter(|line| line.keyword == "host") {
+        for alias in &line.args {
+            if is_pattern(alias) || hosts.iter().any(|host| host.alias == *alias) {
+                continue;
+            }
+            hosts.push(SshHost {
+                source: line.source.clone(),
+                line: line.line,
+                ..resolve(lines, alias)
+            });
+        }
+    }
+    hosts
+}
+
+/// Classify the stderr of a failed `ssh` run.
+pub fn probe_error(stderr: &str) -> SshProbeError {
+    let stderr = stderr.to_ascii_lowercase();
+    if stderr.contains("permission denied") || stderr.contains("too many authentication failures") {
+        SshProbeError::AuthFailed
+    } else if stderr.contains("host key verification failed")
+        || stderr.contains("remote host identification has changed")
+    {
+        SshProbeError::HostKey
+    } else if stderr.contains("could not resolve hostname") {
+        SshProbeError::Unresolved
+    } else if stderr.contains("connection refused") {
+        SshProbeError::Refused
+    } else if stderr.contains("timed out") {
+        SshProbeError::Timeout
+    } else {
+        SshProbeError::Other
+    }
+}
+
+/// `~/.ssh`, where the config and relative includes live.
+pub fn ssh_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CONFIG: &str = "\
+# personal
+Host web web-alias
+    HostName %h.example.com
+    User=deploy
+    IdentityFile ~/.ssh/web_key
+
+Host db
+    HostName 10.0.0.5
+    Port 2222
+    ProxyJump bastion,edge
+
+Host bastion
+    ProxyJump none
+
+Host *.internal !skip.internal
+    User ops
+
+Match host db
+    User nobody
+
+Host *
+    User fallback
+    IdentityFile \"~/.ssh/id ed25519\"
+    Port 22
+";
+
+    #[test]
+    fn test_inventory_first_value_wins() {
+        let hosts = inventory(&parse_lines(CONFIG, "/home/me/.ssh/config"));
+        let aliases: Vec<&str> = hosts.iter().map(|h| h.alias.as_str()).collect();
+        assert_eq!(aliases, vec!["web", "web-alias", "db", "bastion"]);
+
+        let web = &hosts[0];
+        assert_eq!(web.host_name.as_deref(), Some("web.example.com"));
+        assert_eq!(web.user.as_deref(), Some("deploy"));
+        assert_eq!(web.port, Some(22));
+        assert_eq!(
+            web.identity_files,
+            vec!["~/.ssh/web_key", "~/.ssh/id ed25519"]
+        );
+        assert_eq!(web.line, 2);
+
+        let db = &hosts[2];
+        assert_eq!(db.user.as_deref(), Some("fallback"));
+        assert_eq!((db.port, db.proxy_jump.len()), (Some(2222), 2));
+        assert!(hosts[3].proxy_jump.is_empty());
+
+        assert!(host_matches(&["*.internal".to_string()], "API.internal"));
+        assert!(!host_matches(
+            &["*.internal".to_string(), "!skip.internal".to_string()],
+            "skip.internal"
+        ));
+    }
+
+    #[test]
+    fn test_include_expands_in_place() {
+        let dir = TempDir::new().unwrap();
+        let ssh = dir.path().join(".ssh");
+        std::fs::create_dir_all(ssh.join("conf.d")).unwrap();
+        std::fs::write(
+            ssh.join("config"),
+            "Include conf.d/*\nHost main\n    User a\n",
+        )
+        .unwrap();
+        std::fs::write(ssh.join("conf.d/work"), "Host work\n    User b\n").unwrap();
+
+        let hosts = inventory(&read_config_lines(&ssh.join("config"), &ssh));
+        let names: Vec<(&str, Option<&str>)> = hosts
+            .iter()
+            .map(|h| (h.alias.as_str(), h.user.as_deref()))
+            .collect();
+        assert_eq!(names, vec![("work", Some("b")), ("main", Some("a"))]);
+        assert!(hosts[0].source.ends_with("work"));
+    }
+
+    #[test]
+    fn test_probe_error() {
+        assert_eq!(
+            probe_error("deploy@web: Permission denied (publickey)."),
+            SshProbeError::AuthFailed
+        );
+        assert_eq!(
+            probe_error("ssh: Could not resolve hostname nope: nodename nor servname provided"),
+            SshProbeError::Unresolved
+        );
+        assert_eq!(
+            probe_error("ssh: connect to host db port 2222: Operation timed out"),
+            SshProbeError::Timeout
+        );
+        assert_eq!(
+            probe_error("kex_exchange_identification"),
+            SshProbeError::Other
+        );
+    }
+}
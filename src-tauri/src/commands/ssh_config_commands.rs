@@ -0,0 +1,124 @@
+//! Tauri commands for the SSH host inventory of ssh_config.rs and a
+//! connectivity probe.
+//!
+//! The probe runs `ssh` in batch mode, so it never prompts: a host that
+//! would need a password, or whose key isn't known yet, fails with a
+//! reason instead of hanging. It runs `exit` on the host, proving that
+//! the connection, host key and authentication all work.
+
+use serde::Serialize;
+use std::io::Read;
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::error::KiriError;
+use super::latency::run_blocking;
+use super::process::argv_command;
+use super::ssh_config::{
+    inventory, probe_error, read_config_lines, ssh_dir, SshHost, SshProbeError,
+};
+
+/// Seconds `ssh` may take to connect, unless the probe asks otherwise.
+const DEFAULT_PROBE_TIMEOUT_SECS: u64 = 5;
+const MAX_PROBE_TIMEOUT_SECS: u64 = 30;
+
+/// Time past the connect timeout for authentication and `exit`.
+const PROBE_GRACE: Duration = Duration::from_secs(5);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshProbe {
+    pub host: String,
+    pub ok: bool,
+    pub error: Option<SshProbeError>,
+    /// The last line `ssh` printed, when it failed.
+    pub message: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Hosts of `~/.ssh/config` and the files it includes.
+#[tauri::command]
+pub async fn list_ssh_hosts() -> Result<Vec<SshHost>, KiriError> {
+    run_blocking("list_ssh_hosts", move || {
+        let dir = ssh_dir().ok_or_else(|| "Home directory not found".to_string())?;
+        Ok(inventory(&read_config_lines(&dir.join("config"), &dir)))
+    })
+    .await
+}
+
+/// Connect to `host` (an alias or `user@host`) without prompting and
+/// report whether it worked, giving up after `timeout_secs`.
+#[tauri::command]
+pub async fn probe_ssh_host(
+    host: String,
+    timeout_secs: Option<u64>,
+) -> Result<SshProbe, KiriError> {
+    run_blocking("probe_ssh_host", move || {
+        let host = host.trim().to_string();
+        if host.is_empty() || host.starts_with('-') || host.contains(char::is_whitespace) {
+            return Err(format!("Invalid SSH host: {}", host));
+        }
+        let timeout = timeout_secs
+            .unwrap_or(DEFAULT_PROBE_TIMEOUT_SECS)
+            .clamp(1, MAX_PROBE_TIMEOUT_SECS);
+        let connect_timeout = format!("ConnectTimeout={}", timeout);
+        let argv = [
+            "ssh",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            &connect_timeout,
+            "-T",
+            &host,
+            "exit",
+        ];
+        let mut command = argv_command(&argv, None)?;
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        let started = Instant::now();
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to start ssh: {}", e))?;
+        let deadline = started + Duration::from_secs(timeout) + PROBE_GRACE;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break None;
+                }
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(e) => return Err(format!("Failed to wait for ssh: {}", e)),
+            }
+        };
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        let message = stderr
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())
+            .map(str::to_string);
+        let ok = status.is_some_and(|status| status.success());
+        let error = match status {
+            _ if ok => None,
+            None => Some(SshProbeError::Timeout),
+            Some(_) => Some(probe_error(&stderr)),
+        };
+        Ok(SshProbe {
+            host,
+            ok,
+            error,
+            message: message.filter(|_| !ok),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        })
+    })
+    .await
+}
@@ -0,0 +1,210 @@
+//! Startup profiling.
+//!
+//! `run()` marks the process start, and every step of the setup hook is
+//! timed as a phase. The time between the two is the runtime and plugin
+//! init that Tauri does before calling setup, which includes creating the
+//! windows declared in tauri.conf.json. The first page load closes the
+//! profile.
+//!
+//! Work that isn't needed to show the first window is deferred. Installing
+//! the `kiri` CLI runs in the background and is awaited by the first
+//! terminal. The event flusher thread starts with the first throttled
+//! event. The CLI install is still reported, as a deferred phase, so it
+//! can't hide regressions.
+//!
+//! Recording is always on. It is a dozen entries, written once.
+
+use super::lock_ext::LockExt;
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Phase covering everything before the setup hook runs.
+pub const RUNTIME_INIT_PHASE: &str = "runtime_init";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StartupPhase {
+    pub name: String,
+    /// Milliseconds from process start
+    pub start_ms: f64,
+    pub duration_ms: f64,
+    /// Ran off the startup path, in the background or on first use
+    pub deferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StartupReport {
+    /// In the order they finished
+    pub phases: Vec<StartupPhase>,
+    /// Process start to the end of the setup hook
+    pub setup_done_ms: Option<f64>,
+    /// Process start to the first finished page load
+    pub first_window_ms: Option<f64>,
+    pub first_window_label: Option<String>,
+}
+
+pub struct StartupProfile {
+    origin: Instant,
+    phases: Vec<StartupPhase>,
+    setup_started: Option<Instant>,
+    setup_done: Option<Instant>,
+    first_window: Option<(String, Instant)>,
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_micros() as f64 / 1000.0
+}
+
+impl StartupProfile {
+    pub fn new(origin: Instant) -> Self {
+        Self {
+            origin,
+            phases: Vec::new(),
+            setup_started: None,
+            setup_done: None,
+            first_window: None,
+        }
+    }
+
+    fn offset_ms(&self, at: Instant) -> f64 {
+        as_ms(at.saturating_duration_since(self.origin))
+    }
+
+    pub fn record(&mut self, name: &str, start: Instant, end: Instant, deferred: bool) {
+        self.phases.push(StartupPhase {
+            name: name.to_string(),
+            start_ms: self.offset_ms(start),
+            duration_ms: as_ms(end.saturating_duration_since(start)),
+            deferred,
+        });
+    }
+
+    /// Mark the start of the setup hook; the time before it becomes the
+    /// [`RUNTIME_INIT_PHASE`].
+    pub fn begin_setup(&mut self, at: Instant) {
+        if self.setup_started.is_none() {
+            self.setup_started = Some(at);
+            self.record(RUNTIME_INIT_PHASE, self.origin, at, false);
+        }
+    }
+
+    pub fn finish_setup(&mut self, at: Instant) {
+        self.setup_done.get_or_insert(at);
+    }
+
+    /// Record a finished page load. Only the first one counts.
+    pub fn window_loaded(&mut self, label: &str, at: Instant) {
+        if self.first_window.is_none() {
+            self.first_window = Some((label.to_string(), at));
+        }
+    }
+
+    pub fn report(&self) -> StartupReport {
+        StartupReport {
+            phases: self.phases.clone(),
+            setup_done_ms: self.setup_done.map(|at| self.offset_ms(at)),
+            first_window_ms: self
+                .first_window
+                .as_ref()
+                .map(|(_, at)| self.offset_ms(*at)),
+            first_window_label: self.first_window.as_ref().map(|(label, _)| label.clone()),
+        }
+    }
+}
+
+/// The process-wide profile. The first call fixes the origin, so `run()`
+/// calls this before anything else.
+pub fn startup_profile() -> &'static Mutex<StartupProfile> {
+    static PROFILE: OnceLock<Mutex<StartupProfile>> = OnceLock::new();
+    PROFILE.get_or_init(|| Mutex::new(StartupProfile::new(Instant::now())))
+}
+
+fn timed<T>(name: &str, deferred: bool, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let value = f();
+    startup_profile()
+        .lock_recover()
+        .record(name, start, Instant::now(), deferred);
+    value
+}
+
+/// Call at the top of the setup hook.
+pub fn begin_setup() {
+    startup_profile().lock_recover().begin_setup(Instant::now());
+}
+
+/// Call at the end of the setup hook.
+pub fn finish_setup() {
+    startup_profile()
+        .lock_recover()
+        .finish_setup(Instant::now());
+}
+
+/// Call when a webview finishes loading.
+pub fn window_loaded(label: &str) {
+    startup_profile()
+        .lock_recover()
+        .window_loaded(label, Instant::now());
+}
+
+/// Run one step of the setup hook and record it.
+pub fn time_phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    timed(name, false, f)
+}
+
+/// Run deferred startup work and record it.
+pub fn time_deferred<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    timed(name, true, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_report_offsets_from_origin() {
+        let origin = Instant::now();
+        let mut profile = StartupProfile::new(origin);
+        profile.begin_setup(origin + 40 * MS);
+        profile.record("menu", origin + 40 * MS, origin + 45 * MS, false);
+        profile.finish_setup(origin + 50 * MS);
+        profile.record("cli_install", origin + 50 * MS, origin + 80 * MS, true);
+
+        let report = profile.report();
+        let names: Vec<&str> = report.phases.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec![RUNTIME_INIT_PHASE, "menu", "cli_install"]);
+        assert_eq!(report.phases[0].duration_ms, 40.0);
+        assert_eq!(report.phases[1].start_ms, 40.0);
+        assert_eq!(report.phases[1].duration_ms, 5.0);
+        assert!(report.phases[2].deferred);
+        assert_eq!(report.setup_done_ms, Some(50.0));
+        assert_eq!(report.first_window_ms, None);
+    }
+
+    #[test]
+    fn test_only_first_window_and_setup_count() {
+        let origin = Instant::now();
+        let mut profile = StartupProfile::new(origin);
+        profile.begin_setup(origin + 10 * MS);
+        profile.begin_setup(origin + 20 * MS);
+        profile.window_loaded("main", origin + 90 * MS);
+        profile.window_loaded("window-2", origin + 120 * MS);
+
+        let report = profile.report();
+        assert_eq!(report.phases.len(), 1);
+        assert_eq!(report.first_window_ms, Some(90.0));
+        assert_eq!(report.first_window_label.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn test_time_phase_returns_value() {
+        assert_eq!(time_phase("test_phase", || 7), 7);
+        let report = startup_profile().lock().unwrap().report();
+        assert!(report
+            .phases
+            .iter()
+            .any(|p| p.name == "test_phase" && !p.deferred));
+    }
+}
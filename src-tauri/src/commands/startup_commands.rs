@@ -0,0 +1,11 @@
+//! Tauri command for the startup profile recorded in startup.rs.
+
+use super::lock_ext::LockExt;
+use super::startup::{startup_profile, StartupReport};
+
+/// Where startup time went: runtime and plugin init, each setup step, the
+/// first window's page load, and work deferred off the startup path.
+#[tauri::command]
+pub fn get_startup_report() -> StartupReport {
+    startup_profile().lock_recover().report()
+}
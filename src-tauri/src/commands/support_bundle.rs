@@ -0,0 +1,266 @@
+//! Support bundle: logs, settings and diagnostics in one zip.
+//!
+//! [`build_bundle`] collects the log files (logging.rs), the settings an
+//! export carries (settings_sync.rs, secrets already stripped), the crash
+//! reports (crash_report.rs) and `system.json` with the version and
+//! platform. Everything is redacted on the way in: home paths as in crash
+//! reports, and the values of terminal environment variables, which is
+//! where tokens end up. [`write_zip`] stores the entries uncompressed, so
+//! any unzip reads the result without kiri needing a compression library.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+use super::clock::now_ms;
+use super::crash_report::{list_reports, redact};
+use super::file_io::write_file_contents_atomic;
+use super::logging::log_files;
+use super::settings_sync::build_archive;
+
+pub const BUNDLE_FORMAT: &str = "kiri-support";
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleEntry {
+    /// Path inside the zip, e.g. `logs/kiri.log`.
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SupportBundleSummary {
+    pub path: String,
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+fn system_info() -> Value {
+    serde_json::json!({
+        "format": BUNDLE_FORMAT,
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "createdMs": now_ms(),
+    })
+}
+
+/// Replace the values of variables set in terminals.
+fn redact_settings(file: &str, value: &mut Value) {
+    if file != "settings.json" {
+        return;
+    }
+    if let Some(Value::Object(env)) = value.get_mut("terminalEnv") {
+        for value in env.values_mut() {
+            *value = Value::String(REDACTED.to_string());
+        }
+    }
+}
+
+/// The redacted contents of a support bundle.
+pub fn build_bundle(
+    kiri_dir: &Path,
+    logs_dir: &Path,
+    crash_dir: &Path,
+    home: Option<&Path>,
+) -> Vec<BundleEntry> {
+    let json = |value: &Value| {
+        let text = serde_json::to_string_pretty(value).unwrap_or_default();
+        redact(&text, home).into_bytes()
+    };
+    let mut entries = vec![BundleEntry {
+        name: "system.json".to_string(),
+        data: json(&system_info()),
+    }];
+    for (name, mut value) in build_archive(kiri_dir).files {
+        redact_settings(&name, &mut value);
+        entries.push(BundleEntry {
+            name: format!("settings/{}", name),
+            data: json(&value),
+        });
+    }
+    for path in log_files(logs_dir) {
+        let (Some(name), Ok(contents)) = (path.file_name(), std::fs::read(&path)) else {
+            continue;
+        };
+        entries.push(BundleEntry {
+            name: format!("logs/{}", name.to_string_lossy()),
+            data: redact(&String::from_utf8_lossy(&contents), home).into_bytes(),
+        });
+    }
+    for report in list_reports(crash_dir) {
+        // Reports are redacted when written.
+        if let Ok(data) = serde_json::to_vec_pretty(&report) {
+            entries.push(BundleEntry {
+                name: format!("crashes/{}.json", report.id),
+                data,
+            });
+        }
+    }
+    entries
+}
+
+// ============================================================================
+// Zip
+// ============================================================================
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// 1980-01-01 00:00, the earliest time a zip entry can have.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Names are UTF-8.
+const FLAG_UTF8: u16 = 1 << 11;
+
+/// `entries` as a zip archive without compression.
+pub fn zip_entries(entries: &[BundleEntry]) -> Result<Vec<u8>, String> {
+    let too_large = || "The support bundle is too large".to_string();
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for entry in entries {
+        let name = entry.name.as_bytes();
+        let size = u32::try_from(entry.data.len()).map_err(|_| too_large())?;
+        let offset = u32::try_from(out.len()).map_err(|_| too_large())?;
+        let crc = crc32(&entry.data);
+        // Fields shared by the local and the central header, from the
+        // version needed on.
+        let mut common = Vec::with_capacity(26);
+        for field in [20, FLAG_UTF8, 0, DOS_TIME, DOS_DATE] {
+            common.extend_from_slice(&u16::to_le_bytes(field));
+        }
+        for field in [crc, size, size] {
+            common.extend_from_slice(&field.to_le_bytes());
+        }
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&common);
+        out.extend_from_slice(name);
+        out.extend_from_slice(&entry.data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        // Comment length, disk, internal and external attributes.
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name);
+    }
+    let count = u16::try_from(entries.len()).map_err(|_| too_large())?;
+    let central_offset = u32::try_from(out.len()).map_err(|_| too_large())?;
+    let central_size = central.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&central_size.to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    Ok(out)
+}
+
+/// Write `entries` to `path` as a zip. Returns its size.
+pub fn write_zip(path: &Path, entries: &[BundleEntry]) -> Result<u64, String> {
+    let zip = zip_entries(entries)?;
+    write_file_contents_atomic(path, &zip)?;
+    Ok(zip.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bundle_is_redacted() {
+        let home = TempDir::new().unwrap();
+        let kiri_dir = home.path().join(".kiri");
+        let logs_dir = kiri_dir.join("logs");
+        std::fs::create_dir_all(&logs_dir).unwrap();
+        std::fs::write(
+            kiri_dir.join("settings.json"),
+            r#"{"version": 1, "terminalEnv": {"GITHUB_TOKEN": "ghp_secret"}}"#,
+        )
+        .unwrap();
+        let log = format!(
+            "{{\"msg\":\"opened {}/work/acme/app\"}}\n",
+            home.path().display()
+        );
+        std::fs::write(logs_dir.join("kiri.log"), log).unwrap();
+
+        let entries = build_bundle(
+            &kiri_dir,
+            &logs_dir,
+            &kiri_dir.join("diagnostics/crashes"),
+            Some(home.path()),
+        );
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["system.json", "settings/settings.json", "logs/kiri.log"]
+        );
+        let text = |name: &str| {
+            let entry = entries.iter().find(|e| e.name == name).unwrap();
+            String::from_utf8(entry.data.clone()).unwrap()
+        };
+        assert!(text("settings/settings.json").contains("\"GITHUB_TOKEN\": \"<redacted>\""));
+        assert!(!text("settings/settings.json").contains("ghp_secret"));
+        assert!(text("logs/kiri.log").contains("opened ~/…/app"));
+    }
+
+    #[test]
+    fn test_zip_layout() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let entries = vec![
+            BundleEntry {
+                name: "system.json".to_string(),
+                data: b"{}".to_vec(),
+            },
+            BundleEntry {
+                name: "logs/kiri.log".to_string(),
+                data: b"hello\n".to_vec(),
+            },
+        ];
+        let zip = zip_entries(&entries).unwrap();
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        // The local header of the first entry: crc and sizes of `{}`.
+        assert_eq!(&zip[14..18], &crc32(b"{}").to_le_bytes());
+        assert_eq!(&zip[18..22], &2u32.to_le_bytes());
+        let end = zip.len() - 22;
+        assert_eq!(&zip[end..end + 4], b"PK\x05\x06");
+        assert_eq!(&zip[end + 10..end + 12], &2u16.to_le_bytes());
+        let central_offset = u32::from_le_bytes(zip[end + 16..end + 20].try_into().unwrap());
+        assert_eq!(
+            &zip[central_offset as usize..central_offset as usize + 4],
+            b"PK\x01\x02"
+        );
+    }
+}
@@ -0,0 +1,580 @@
+//! Syntax trees for open files, parsed with tree-sitter.
+//!
+//! Parsing a large file in the webview stalls typing, so the editor hands
+//! a file's text over once and afterwards sends only its edits. Each batch
+//! of edits re-parses incrementally from the previous tree. Highlight spans
+//! and the symbol outline are read from the current tree with the
+//! grammars' own `highlights.scm` and `tags.scm` queries.
+//!
+//! Offsets crossing the IPC boundary are UTF-16 code units, as CodeMirror
+//! counts them; tree-sitter works in bytes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Query, QueryCursor, Tree};
+
+use super::lock_ext::LockExt;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyntaxLanguage {
+    Rust,
+    TypeScript,
+    Tsx,
+    JavaScript,
+    Python,
+    Go,
+}
+
+impl SyntaxLanguage {
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Self::Rust),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "js" | "mjs" | "cjs" | "jsx" => Some(Self::JavaScript),
+            "py" | "pyi" => Some(Self::Python),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    fn language(self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    /// Highlight and tags query sources. The TypeScript grammars only ship
+    /// what differs from JavaScript, so theirs go after JavaScript's, the
+    /// way the grammar's own tooling combines them.
+    fn query_sources(self) -> (Vec<&'static str>, Vec<&'static str>) {
+        use tree_sitter_javascript as js;
+        use tree_sitter_typescript as ts;
+        match self {
+            Self::Rust => (
+                vec![tree_sitter_rust::HIGHLIGHTS_QUERY],
+                vec![tree_sitter_rust::TAGS_QUERY],
+            ),
+            Self::TypeScript => (
+                vec![js::HIGHLIGHT_QUERY, ts::HIGHLIGHTS_QUERY],
+                vec![js::TAGS_QUERY, ts::TAGS_QUERY],
+            ),
+            Self::Tsx => (
+                vec![
+                    js::HIGHLIGHT_QUERY,
+                    js::JSX_HIGHLIGHT_QUERY,
+                    ts::HIGHLIGHTS_QUERY,
+                ],
+                vec![js::TAGS_QUERY, ts::TAGS_QUERY],
+            ),
+            Self::JavaScript => (
+                vec![js::HIGHLIGHT_QUERY, js::JSX_HIGHLIGHT_QUERY],
+                vec![js::TAGS_QUERY],
+            ),
+            Self::Python => (
+                vec![tree_sitter_python::HIGHLIGHTS_QUERY],
+                vec![tree_sitter_python::TAGS_QUERY],
+            ),
+            Self::Go => (
+                vec![tree_sitter_go::HIGHLIGHTS_QUERY],
+                vec![tree_sitter_go::TAGS_QUERY],
+            ),
+        }
+    }
+}
+
+struct Grammar {
+    language: Language,
+    highlights: Query,
+    tags: Query,
+}
+
+impl Grammar {
+    fn compile(language: SyntaxLanguage) -> Result<Self, String> {
+        let (highlights, tags) = language.query_sources();
+        let ts_language = language.language();
+        let query = |sources: Vec<&str>, name: &str| {
+            Query::new(&ts_language, &sources.join("\n"))
+                .map_err(|e| format!("Failed to compile {:?} {} query: {}", language, name, e))
+        };
+        Ok(Self {
+            highlights: query(highlights, "highlights")?,
+            tags: query(tags, "tags")?,
+            language: ts_language,
+        })
+    }
+}
+
+/// The grammar and compiled queries for `language`, built on first use.
+fn grammar(language: SyntaxLanguage) -> Result<&'static Grammar, String> {
+    static GRAMMARS: [OnceLock<Result<Grammar, String>>; 6] = [
+        OnceLock::new(),
+        OnceLock::new(),
+        OnceLock::new(),
+        OnceLock::new(),
+        OnceLock::new(),
+        OnceLock::new(),
+    ];
+    GRAMMARS[language as usize]
+        .get_or_init(|| Grammar::compile(language))
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// A range of UTF-16 offsets.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TextRange {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Replace `from..to` (UTF-16 offsets) with `text`. Edits in a batch apply
+/// one after another, each to the text the previous one left.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextEdit {
+    pub from: usize,
+    pub to: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub from: usize,
+    pub to: usize,
+    /// Capture name from the highlight query, e.g. `keyword` or
+    /// `function.method`.
+    pub kind: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct OutlineSymbol {
+    pub name: String,
+    /// `function`, `method`, `class`, `interface`, `module`, ... as the
+    /// tags query names the definition.
+    pub kind: &'static str,
+    pub range: TextRange,
+    pub name_range: TextRange,
+    pub children: Vec<OutlineSymbol>,
+}
+
+/// Line starts of a text, to turn byte offsets into tree-sitter points and
+/// UTF-16 offsets and back.
+struct LineIndex {
+    /// Byte offset of each line start.
+    bytes: Vec<usize>,
+    /// UTF-16 offset of each line start.
+    utf16: Vec<usize>,
+    ascii: bool,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let mut index = Self {
+            bytes: vec![0],
+            utf16: vec![0],
+            ascii: text.is_ascii(),
+        };
+        let mut utf16 = 0;
+        let mut line_start = 0;
+        for (i, _) in text.match_indices('\n') {
+            if !index.ascii {
+                utf16 += text[line_start..=i].encode_utf16().count();
+            } else {
+                utf16 = i + 1;
+            }
+            line_start = i + 1;
+            index.bytes.push(line_start);
+            index.utf16.push(utf16);
+        }
+        index
+    }
+
+    fn line_of_byte(&self, byte: usize) -> usize {
+        self.bytes.partition_point(|&start| start <= byte) - 1
+    }
+
+    fn point(&self, byte: usize) -> Point {
+        let row = self.line_of_byte(byte);
+        Point::new(row, byte - self.bytes[row])
+    }
+
+    fn to_utf16(&self, text: &str, byte: usize) -> usize {
+        if self.ascii {
+            return byte;
+        }
+        let row = self.line_of_byte(byte);
+        self.utf16[row] + text[self.bytes[row]..byte].encode_utf16().count()
+    }
+
+    fn to_byte(&self, text: &str, offset: usize) -> Result<usize, String> {
+        let out_of_range = || format!("Offset {} is outside the document", offset);
+        if self.ascii {
+            return if offset <= text.len() {
+                Ok(offset)
+            } else {
+                Err(out_of_range())
+            };
+        }
+        let row = self.utf16.partition_point(|&start| start <= offset) - 1;
+        let mut byte = self.bytes[row];
+        let mut utf16 = self.utf16[row];
+        for c in text[byte..].chars() {
+            if utf16 >= offset {
+                break;
+            }
+            utf16 += c.len_utf16();
+            byte += c.len_utf8();
+        }
+        match utf16.cmp(&offset) {
+            std::cmp::Ordering::Equal => Ok(byte),
+            std::cmp::Ordering::Greater => {
+                Err(format!("Offset {} splits a surrogate pair", offset))
+            }
+            std::cmp::Ordering::Less => Err(out_of_range()),
+        }
+    }
+}
+
+/// An open file's text and its current syntax tree.
+pub struct SyntaxDocument {
+    language: SyntaxLanguage,
+    text: String,
+    lines: LineIndex,
+    tree: Tree,
+}
+
+fn parse(language: SyntaxLanguage, text: &str, old: Option<&Tree>) -> Result<Tree, String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&grammar(language)?.language)
+        .map_err(|e| format!("Failed to load {:?} grammar: {}", language, e))?;
+    parser
+        .parse(text, old)
+        .ok_or_else(|| format!("Failed to parse {:?} text", language))
+}
+
+impl SyntaxDocument {
+    pub fn parse(language: SyntaxLanguage, text: String) -> Result<Self, String> {
+        let tree = parse(language, &text, None)?;
+        Ok(Self {
+            language,
+            lines: LineIndex::new(&text),
+            text,
+            tree,
+        })
+    }
+
+    pub fn language(&self) -> SyntaxLanguage {
+        self.language
+    }
+
+    fn range(&self, node: Node) -> TextRange {
+        TextRange {
+            from: self.lines.to_utf16(&self.text, node.start_byte()),
+            to: self.lines.to_utf16(&self.text, node.end_byte()),
+        }
+    }
+
+    /// Apply `edits` and re-parse incrementally. Returns the ranges whose
+    /// syntax changed beyond the edited text itself, e.g. everything after
+    /// a newly opened string, which need highlighting again. If an edit
+    /// doesn't fit the text, none of them is applied.
+    pub fn edit(&mut self, edits: &[TextEdit]) -> Result<Vec<TextRange>, String> {
+        let mut text = self.text.clone();
+        let mut lines = LineIndex::new(&text);
+        let mut tree = self.tree.clone();
+        for edit in edits {
+            if edit.to < edit.from {
+                return Err(format!(
+                    "Edit {}..{} ends before it starts",
+                    edit.from, edit.to
+                ));
+            }
+            let start_byte = lines.to_byte(&text, edit.from)?;
+            let old_end_byte = lines.to_byte(&text, edit.to)?;
+            let start_position = lines.point(start_byte);
+            let old_end_position = lines.point(old_end_byte);
+            text.replace_range(start_byte..old_end_byte, &edit.text);
+            lines = LineIndex::new(&text);
+            let new_end_byte = start_byte + edit.text.len();
+            tree.edit(&InputEdit {
+                start_byte,
+                old_end_byte,
+                new_end_byte,
+                start_position,
+                old_end_position,
+                new_end_position: lines.point(new_end_byte),
+            });
+        }
+        let new_tree = parse(self.language, &text, Some(&tree))?;
+        let changed = new_tree
+            .changed_ranges(&tree)
+            .map(|range| TextRange {
+                from: lines.to_utf16(&text, range.start_byte),
+                to: lines.to_utf16(&text, range.end_byte),
+            })
+            .collect();
+        self.text = text;
+        self.lines = lines;
+        self.tree = new_tree;
+        Ok(changed)
+    }
+
+    /// Highlight spans within `range`, or the whole text, in document order
+    /// with enclosing spans before the spans inside them. Where several
+    /// query patterns capture the same text, the first one wins.
+    pub fn highlights(&self, range: Option<TextRange>) -> Result<Vec<HighlightSpan>, String> {
+        let query = &grammar(self.language)?.highlights;
+        let names = query.capture_names();
+        let mut cursor = QueryCursor::new();
+        if let Some(range) = range {
+            let from = self.lines.to_byte(&self.text, range.from)?;
+            let to = self.lines.to_byte(&self.text, range.to)?;
+            cursor.set_byte_range(from..to);
+        }
+        let mut spans: HashMap<(usize, usize), (usize, &'static str)> = HashMap::new();
+        let captures = cursor.captures(query, self.tree.root_node(), self.text.as_bytes());
+        for (m, index) in captures {
+            let capture = m.captures[index];
+            let name = names[capture.index as usize];
+            if name.starts_with('_') {
+                continue;
+            }
+            let node = capture.node;
+            let span = (node.start_byte(), node.end_byte());
+            let entry = spans.entry(span).or_insert((m.pattern_index, name));
+            if m.pattern_index < entry.0 {
+                *entry = (m.pattern_index, name);
+            }
+        }
+        let mut spans: Vec<((usize, usize), &'static str)> = spans
+            .into_iter()
+            .map(|(span, (_, name))| (span, name))
+            .collect();
+        spans.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        Ok(spans
+            .into_iter()
+            .map(|((from, to), kind)| HighlightSpan {
+                from: self.lines.to_utf16(&self.text, from),
+                to: self.lines.to_utf16(&self.text, to),
+                kind,
+            })
+            .collect())
+    }
+
+    /// Definitions in the text, nested by where they are defined.
+    pub fn outline(&self) -> Result<Vec<OutlineSymbol>, String> {
+        let query = &grammar(self.language)?.tags;
+        let names = query.capture_names();
+        let mut cursor = QueryCursor::new();
+        let mut definitions: HashMap<(usize, usize), (usize, OutlineSymbol)> = HashMap::new();
+        for m in cursor.matches(query, self.tree.root_node(), self.text.as_bytes()) {
+            let mut definition = None;
+            let mut name = None;
+            for capture in m.captures {
+                let capture_name = names[capture.index as usize];
+                if capture_name == "name" {
+                    name = Some(capture.node);
+                } else if let Some(kind) = capture_name.strip_prefix("definition.") {
+                    definition = Some((capture.node, kind));
+                }
+            }
+            let (Some((node, kind)), Some(name)) = (definition, name) else {
+                continue;
+            };
+            let span = (node.start_byte(), node.end_byte());
+            if definitions
+                .get(&span)
+                .is_some_and(|(pattern, _)| *pattern <= m.pattern_index)
+            {
+                continue;
+            }
+            let symbol = OutlineSymbol {
+                name: self.text[name.byte_range()].to_string(),
+                kind,
+                range: self.range(node),
+                name_range: self.range(name),
+                children: Vec::new(),
+            };
+            definitions.insert(span, (m.pattern_index, symbol));
+        }
+        let mut symbols: Vec<OutlineSymbol> = definitions
+            .into_values()
+            .map(|(_, symbol)| symbol)
+            .collect();
+        symbols.sort_by(|a, b| {
+            a.range
+                .from
+                .cmp(&b.range.from)
+                .then(b.range.to.cmp(&a.range.to))
+        });
+        Ok(nest(&mut symbols.into_iter().peekable(), usize::MAX))
+    }
+}
+
+/// Take the symbols from `symbols` that end by `end`, each with the
+/// symbols inside it as children.
+fn nest(
+    symbols: &mut std::iter::Peekable<std::vec::IntoIter<OutlineSymbol>>,
+    end: usize,
+) -> Vec<OutlineSymbol> {
+    let mut nested = Vec::new();
+    while let Some(mut symbol) = symbols.next_if(|symbol| symbol.range.to <= end) {
+        symbol.children = nest(symbols, symbol.range.to);
+        nested.push(symbol);
+    }
+    nested
+}
+
+/// Open documents by path. Each has its own lock so parsing one file
+/// doesn't hold up the others.
+#[derive(Default)]
+pub struct SyntaxDocuments {
+    documents: HashMap<PathBuf, Arc<Mutex<SyntaxDocument>>>,
+}
+
+pub type SyntaxState = Arc<Mutex<SyntaxDocuments>>;
+
+impl SyntaxDocuments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: PathBuf, document: SyntaxDocument) {
+        self.documents.insert(path, Arc::new(Mutex::new(document)));
+    }
+
+    pub fn get(&self, path: &Path) -> Result<Arc<Mutex<SyntaxDocument>>, String> {
+        self.documents
+            .get(path)
+            .cloned()
+            .ok_or_else(|| format!("{} is not open for syntax", path.display()))
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.documents.remove(path);
+    }
+}
+
+/// Run `f` on the open document at `path`.
+pub fn with_document<T>(
+    state: &SyntaxState,
+    path: &Path,
+    f: impl FnOnce(&mut SyntaxDocument) -> Result<T, String>,
+) -> Result<T, String> {
+    let document = state.lock_recover().get(path)?;
+    let mut document = document.lock_recover();
+    f(&mut document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = "struct Point { x: i32 }\n\nimpl Point {\n    fn norm(&self) -> i32 { self.x }\n}\n\nfn main() {}\n";
+
+    fn edit(from: usize, to: usize, text: &str) -> TextEdit {
+        TextEdit {
+            from,
+            to,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_queries_compile_for_every_language() {
+        for language in [
+            SyntaxLanguage::Rust,
+            SyntaxLanguage::TypeScript,
+            SyntaxLanguage::Tsx,
+            SyntaxLanguage::JavaScript,
+            SyntaxLanguage::Python,
+            SyntaxLanguage::Go,
+        ] {
+            grammar(language).unwrap();
+        }
+        assert_eq!(
+            SyntaxLanguage::for_path(Path::new("src/App.TSX")),
+            Some(SyntaxLanguage::Tsx)
+        );
+        assert_eq!(SyntaxLanguage::for_path(Path::new("README.md")), None);
+    }
+
+    #[test]
+    fn test_highlights_and_outline() {
+        let document = SyntaxDocument::parse(SyntaxLanguage::Rust, RUST.to_string()).unwrap();
+        let spans = document.highlights(None).unwrap();
+        assert!(spans.contains(&HighlightSpan {
+            from: 0,
+            to: 6,
+            kind: "keyword"
+        }));
+        assert!(spans.windows(2).all(|w| w[0].from <= w[1].from));
+        let in_range = document
+            .highlights(Some(TextRange { from: 0, to: 10 }))
+            .unwrap();
+        assert!(in_range.iter().all(|span| span.from < 10));
+
+        let outline = document.outline().unwrap();
+        let names: Vec<(&str, &str)> = outline.iter().map(|s| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(
+            names,
+            vec![("Point", "class"), ("norm", "method"), ("main", "function")]
+        );
+        assert_eq!(outline[0].name_range, TextRange { from: 7, to: 12 });
+    }
+
+    #[test]
+    fn test_outline_nests_definitions() {
+        let source = "class A:\n    def f(self):\n        pass\n\ndef g():\n    pass\n";
+        let document = SyntaxDocument::parse(SyntaxLanguage::Python, source.to_string()).unwrap();
+        let outline = document.outline().unwrap();
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].name, "A");
+        assert_eq!(outline[0].children[0].name, "f");
+        assert_eq!(outline[1].name, "g");
+    }
+
+    #[test]
+    fn test_incremental_edits_use_utf16_offsets() {
+        let source = "// 😀 é\nfn a() {}\n";
+        let mut document = SyntaxDocument::parse(SyntaxLanguage::Rust, source.to_string()).unwrap();
+        // "// 😀 é\n" is 8 UTF-16 code units; rename `a` to `bee`, then
+        // add a second function.
+        let fn_a = 8 + 3;
+        document
+            .edit(&[edit(fn_a, fn_a + 1, "bee"), edit(20, 20, "fn c() {}\n")])
+            .unwrap();
+        assert_eq!(document.text, "// 😀 é\nfn bee() {}\nfn c() {}\n");
+        let names: Vec<String> = document
+            .outline()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["bee", "c"]);
+        assert_eq!(
+            document.tree.root_node().to_sexp(),
+            parse(SyntaxLanguage::Rust, &document.text, None)
+                .unwrap()
+                .root_node()
+                .to_sexp()
+        );
+
+        // Inside the emoji's surrogate pair, and past the end.
+        assert!(document.edit(&[edit(4, 4, "x")]).is_err());
+        assert!(document
+            .edit(&[edit(0, 0, "x"), edit(100, 100, "")])
+            .is_err());
+        assert_eq!(document.text, "// 😀 é\nfn bee() {}\nfn c() {}\n");
+    }
+}
@@ -0,0 +1,88 @@
+//! Tauri commands for syntax highlighting and outlines; see syntax.rs.
+//!
+//! The editor opens a file with [`syntax_open`], then reports each change
+//! with [`syntax_edit`] and asks for highlight spans of the visible range
+//! with [`syntax_highlights`]. Files in other languages are left to the
+//! editor's own highlighting.
+
+use std::sync::Arc;
+
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::syntax::{
+    with_document, HighlightSpan, OutlineSymbol, SyntaxDocument, SyntaxLanguage, SyntaxState,
+    TextEdit, TextRange,
+};
+
+/// Parse `text` as the contents of `path`. Returns the language, or `None`
+/// if there is no grammar for it. Opening an open file replaces its text.
+#[tauri::command]
+pub async fn syntax_open(
+    state: tauri::State<'_, SyntaxState>,
+    path: String,
+    text: String,
+) -> Result<Option<SyntaxLanguage>, KiriError> {
+    let path = normalize_input_path(&path);
+    let Some(language) = SyntaxLanguage::for_path(&path) else {
+        return Ok(None);
+    };
+    let state: SyntaxState = Arc::clone(&state);
+    run_blocking("syntax_open", move || {
+        let document = SyntaxDocument::parse(language, text)?;
+        state.lock_recover().insert(path, document);
+        Ok(Some(language))
+    })
+    .await
+}
+
+/// Apply edits to an open file. Returns the ranges to highlight again
+/// besides the edited text.
+#[tauri::command]
+pub async fn syntax_edit(
+    state: tauri::State<'_, SyntaxState>,
+    path: String,
+    edits: Vec<TextEdit>,
+) -> Result<Vec<TextRange>, KiriError> {
+    let path = normalize_input_path(&path);
+    let state: SyntaxState = Arc::clone(&state);
+    run_blocking("syntax_edit", move || {
+        with_document(&state, &path, |document| document.edit(&edits))
+    })
+    .await
+}
+
+/// Highlight spans of an open file, within `range` if given.
+#[tauri::command]
+pub async fn syntax_highlights(
+    state: tauri::State<'_, SyntaxState>,
+    path: String,
+    range: Option<TextRange>,
+) -> Result<Vec<HighlightSpan>, KiriError> {
+    let path = normalize_input_path(&path);
+    let state: SyntaxState = Arc::clone(&state);
+    run_blocking("syntax_highlights", move || {
+        with_document(&state, &path, |document| document.highlights(range))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn syntax_outline(
+    state: tauri::State<'_, SyntaxState>,
+    path: String,
+) -> Result<Vec<OutlineSymbol>, KiriError> {
+    let path = normalize_input_path(&path);
+    let state: SyntaxState = Arc::clone(&state);
+    run_blocking("syntax_outline", move || {
+        with_document(&state, &path, |document| document.outline())
+    })
+    .await
+}
+
+/// Drop an open file's tree. Does nothing if it isn't open.
+#[tauri::command]
+pub fn syntax_close(state: tauri::State<'_, SyntaxState>, path: String) {
+    state.lock_recover().remove(&normalize_input_path(&path));
+}
@@ -0,0 +1,88 @@
+//! Tailscale Funnel; see tunnel.rs.
+//!
+//! `tailscale funnel <port>` serves the port on the node's `ts.net` name
+//! for as long as it runs in the foreground. The node has to be logged in
+//! and Funnel enabled for the tailnet.
+
+use serde::{Deserialize, Serialize};
+
+use super::tunnel::{Capabilities, LogEvent, ProviderKind, TunnelProvider};
+
+lazy_static::lazy_static! {
+    static ref FUNNEL_URL: regex::Regex =
+        regex::Regex::new(r"https://[A-Za-z0-9.-]+\.ts\.net\S*").expect("pattern compiles");
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TailscaleFunnel {}
+
+impl TunnelProvider for TailscaleFunnel {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::TailscaleFunnel
+    }
+
+    fn label(&self) -> &'static str {
+        "Tailscale Funnel"
+    }
+
+    fn program(&self) -> &'static str {
+        "tailscale"
+    }
+
+    fn version_args(&self) -> &'static [&'static str] {
+        &["version"]
+    }
+
+    fn install_hint(&self) -> &'static str {
+        "Install Tailscale from https://tailscale.com/download"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            requires_account: true,
+            custom_domain: false,
+        }
+    }
+
+    fn args(&self, port: u16) -> Vec<String> {
+        vec!["funnel".into(), port.to_string()]
+    }
+
+    fn log_event(&self, line: &str) -> Option<LogEvent> {
+        if let Some(url) = FUNNEL_URL.find(line) {
+            return Some(LogEvent::Ready {
+                url: Some(url.as_str().trim_end_matches('/').to_string()),
+            });
+        }
+        let line = line.trim();
+        let error = line
+            .strip_prefix("error:")
+            .or_else(|| line.strip_prefix("Funnel is not enabled"))
+            .map(|_| line.to_string())?;
+        Some(LogEvent::Error(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_and_log_events() {
+        let funnel = TailscaleFunnel {};
+        assert_eq!(funnel.args(3000), vec!["funnel", "3000"]);
+        assert_eq!(
+            funnel.log_event("https://laptop.tail1234.ts.net/"),
+            Some(LogEvent::Ready {
+                url: Some("https://laptop.tail1234.ts.net".to_string())
+            })
+        );
+        assert_eq!(funnel.log_event("|-- proxy http://127.0.0.1:3000"), None);
+        assert_eq!(
+            funnel.log_event("error: funnel not available; \"funnel\" node attribute not set"),
+            Some(LogEvent::Error(
+                "error: funnel not available; \"funnel\" node attribute not set".to_string()
+            ))
+        );
+    }
+}
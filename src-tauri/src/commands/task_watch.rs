@@ -0,0 +1,288 @@
+//! Watch mode for tasks: run a task again whenever files it cares about
+//! change, like nodemon but for any task or command line.
+//!
+//! A watch owns one file watcher on the task's directory (see watcher.rs)
+//! and at most one run at a time. [`WatchFilter`] decides which changes
+//! count; the runner in task_watch_commands.rs stops the current run and
+//! starts the next once a debounced batch of changes matches.
+
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::fs_path::strip_root;
+use super::jobs::JobId;
+use super::search::DEFAULT_EXCLUDED_DIRS;
+use super::tasks::TaskDef;
+use super::watcher::{WatcherInstance, DEFAULT_DEBOUNCE_MS};
+
+/// Shortest and longest quiet period before a rerun, in milliseconds.
+pub const MIN_WATCH_DEBOUNCE_MS: u64 = 50;
+pub const MAX_WATCH_DEBOUNCE_MS: u64 = 10_000;
+
+/// Identifies one watch; `stop_task_watch` takes it back.
+pub type WatchId = u64;
+
+/// What a watch reacts to, as `watch_task` takes it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatchOptions {
+    /// See [`WatchFilter`].
+    pub patterns: Vec<String>,
+    /// Quiet period before a rerun; see [`watch_debounce_ms`].
+    pub debounce_ms: Option<u64>,
+}
+
+/// `debounce_ms` within bounds, or the watcher default.
+pub fn watch_debounce_ms(debounce_ms: Option<u64>) -> u64 {
+    debounce_ms
+        .unwrap_or(DEFAULT_DEBOUNCE_MS)
+        .clamp(MIN_WATCH_DEBOUNCE_MS, MAX_WATCH_DEBOUNCE_MS)
+}
+
+/// Which changed paths under a task's directory trigger a rerun.
+///
+/// Patterns are globs relative to the directory. One without a `/`
+/// matches the file name anywhere, as in a `.gitignore`; `**` spans
+/// directories. With no patterns every change does. Changes inside `.git`
+/// and the directories search skips (`node_modules`, `target`, ...) never
+/// count, so a build writing its output doesn't trigger itself.
+#[derive(Debug, Clone)]
+pub struct WatchFilter {
+    dir: PathBuf,
+    patterns: Vec<Pattern>,
+}
+
+impl WatchFilter {
+    pub fn new(dir: &Path, patterns: &[String]) -> Result<Self, String> {
+        let patterns = patterns
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(|p| Pattern::new(p).map_err(|e| format!("Invalid pattern `{}`: {}", p, e)))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            patterns,
+        })
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let Some(rel) = strip_root(path, &self.dir) else {
+            return false;
+        };
+        let parts: Vec<String> = rel
+            .components()
+            .filter_map(|part| match part {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        let Some(name) = parts.last() else {
+            return false;
+        };
+        if parts
+            .iter()
+            .any(|part| DEFAULT_EXCLUDED_DIRS.contains(&part.as_str()))
+        {
+            return false;
+        }
+        if self.patterns.is_empty() {
+            return true;
+        }
+        let rel = parts.join("/");
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        self.patterns.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_with(&rel, options)
+            } else {
+                pattern.matches_with(name, options)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskWatch {
+    pub id: WatchId,
+    pub task: TaskDef,
+    pub patterns: Vec<String>,
+    pub debounce_ms: u64,
+    /// The latest run, which may have finished.
+    pub run_id: Option<JobId>,
+    /// Times the task was started again after a change.
+    pub reruns: u32,
+}
+
+struct WatchEntry<H> {
+    watch: TaskWatch,
+    /// Dropping it stops watching.
+    handle: H,
+}
+
+/// Active watches, each with the handle that keeps its file watcher
+/// running.
+pub struct TaskWatches<H = WatcherInstance> {
+    watches: BTreeMap<WatchId, WatchEntry<H>>,
+    next_id: WatchId,
+}
+
+pub type TaskWatchesState = Arc<Mutex<TaskWatches>>;
+
+impl<H> Default for TaskWatches<H> {
+    fn default() -> Self {
+        Self {
+            watches: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl TaskWatches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<H> TaskWatches<H> {
+    /// An id for a new watch, so its file watcher's callback can name it
+    /// before [`Self::add`].
+    pub fn reserve_id(&mut self) -> WatchId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn add(
+        &mut self,
+        id: WatchId,
+        task: TaskDef,
+        patterns: Vec<String>,
+        debounce_ms: u64,
+        handle: H,
+    ) -> TaskWatch {
+        let watch = TaskWatch {
+            id,
+            task,
+            patterns,
+            debounce_ms,
+            run_id: None,
+            reruns: 0,
+        };
+        self.watches.insert(
+            id,
+            WatchEntry {
+                watch: watch.clone(),
+                handle,
+            },
+        );
+        watch
+    }
+
+    pub fn get(&self, id: WatchId) -> Option<&TaskWatch> {
+        self.watches.get(&id).map(|entry| &entry.watch)
+    }
+
+    /// Record that watch `id` started `run`; every run after the first
+    /// counts as a rerun.
+    pub fn set_run(&mut self, id: WatchId, run: JobId) -> Option<TaskWatch> {
+        let watch = &mut self.watches.get_mut(&id)?.watch;
+        if watch.run_id.is_some() {
+            watch.reruns += 1;
+        }
+        watch.run_id = Some(run);
+        Some(watch.clone())
+    }
+
+    /// Watches in `dir`, or all of them, oldest first.
+    pub fn list(&self, dir: Option<&str>) -> Vec<TaskWatch> {
+        self.watches
+            .values()
+            .map(|entry| &entry.watch)
+            .filter(|watch| dir.map_or(true, |dir| watch.task.dir == dir))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove watch `id`. Dropping the returned handle stops its file
+    /// watcher; do that after releasing the lock.
+    pub fn remove(&mut self, id: WatchId) -> Option<(TaskWatch, H)> {
+        self.watches
+            .remove(&id)
+            .map(|entry| (entry.watch, entry.handle))
+    }
+
+    /// Remove every watch, for app exit.
+    pub fn clear(&mut self) -> Vec<(TaskWatch, H)> {
+        std::mem::take(&mut self.watches)
+            .into_values()
+            .map(|entry| (entry.watch, entry.handle))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(patterns: &[&str]) -> WatchFilter {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        WatchFilter::new(Path::new("/repo"), &patterns).unwrap()
+    }
+
+    #[test]
+    fn test_filter_patterns() {
+        let any = filter(&[]);
+        assert!(any.matches(Path::new("/repo/src/main.rs")));
+        assert!(!any.matches(Path::new("/repo/target/debug/app")));
+        assert!(!any.matches(Path::new("/repo/.git/index")));
+        assert!(!any.matches(Path::new("/elsewhere/main.rs")));
+
+        let rust = filter(&["*.rs", "config/*.toml"]);
+        assert!(rust.matches(Path::new("/repo/src/deep/lib.rs")));
+        assert!(rust.matches(Path::new("/repo/config/app.toml")));
+        assert!(!rust.matches(Path::new("/repo/config/nested/app.toml")));
+        assert!(!rust.matches(Path::new("/repo/README.md")));
+
+        let deep = filter(&["src/**/*.ts"]);
+        assert!(deep.matches(Path::new("/repo/src/a/b/c.ts")));
+        assert!(!deep.matches(Path::new("/repo/test/c.ts")));
+
+        assert!(WatchFilter::new(Path::new("/repo"), &["[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_reruns_count_runs_after_the_first() {
+        let task = TaskDef::command(
+            "cargo test",
+            vec!["cargo".to_string(), "test".to_string()],
+            Path::new("/repo"),
+        );
+        let mut watches: TaskWatches<()> = TaskWatches::default();
+        let id = watches.reserve_id();
+        let watch = watches.add(
+            id,
+            task,
+            vec!["*.rs".to_string()],
+            watch_debounce_ms(None),
+            (),
+        );
+        assert_eq!(watch.id, id);
+        assert_eq!(watch.task.id, "command:cargo test");
+
+        assert_eq!(watches.set_run(id, 10).unwrap().reruns, 0);
+        let watch = watches.set_run(id, 11).unwrap();
+        assert_eq!((watch.run_id, watch.reruns), (Some(11), 1));
+        assert_eq!(watches.list(Some("/repo")).len(), 1);
+        assert!(watches.list(Some("/other")).is_empty());
+
+        assert!(watches.remove(id).is_some());
+        assert!(watches.set_run(id, 12).is_none());
+        assert_eq!(watch_debounce_ms(Some(1)), MIN_WATCH_DEBOUNCE_MS);
+    }
+}
@@ -0,0 +1,203 @@
+//! Runner and Tauri commands for task watch mode; matching and the list
+//! of watches live in task_watch.rs.
+//!
+//! [`watch_task`] starts a task, or any command line such as an init
+//! command, and a file watcher on its directory. When a debounced batch
+//! of changes matches the watch's patterns the current run is stopped,
+//! waiting for its process to exit so the two never overlap (a dev server
+//! would find its port taken), and a new run starts. Runs are ordinary
+//! task runs (see tasks_commands.rs); each new one is announced with
+//! `task-watch-updated`.
+
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::jobs::JobId;
+use super::jobs_commands;
+use super::latency::run_blocking_coded;
+use super::lock_ext::LockExt;
+use super::process::split_argv;
+use super::task_watch::{
+    watch_debounce_ms, TaskWatch, TaskWatchesState, WatchFilter, WatchId, WatchOptions,
+};
+use super::tasks::{discover_tasks, TaskDef, TaskRunsState};
+use super::tasks_commands::start_run;
+use super::trust_commands::require_trusted;
+use super::watcher::start_watcher_with_debounce;
+
+pub const TASK_WATCH_UPDATED_EVENT: &str = "task-watch-updated";
+pub const TASK_WATCH_STOPPED_EVENT: &str = "task-watch-stopped";
+
+/// How long a rerun waits for the previous run's process to exit.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn watches_state(app: &AppHandle) -> Result<TaskWatchesState, String> {
+    app.try_state::<TaskWatchesState>()
+        .map(|state| state.inner().clone())
+        .ok_or_else(|| "Task watch is not available".to_string())
+}
+
+/// The task `task_id` of `dir`, or `command` run in `dir`.
+fn watched_task(
+    dir: &Path,
+    task_id: Option<String>,
+    command: Option<String>,
+) -> Result<TaskDef, String> {
+    match (task_id, command) {
+        (Some(task_id), None) => discover_tasks(dir)
+            .into_iter()
+            .find(|task| task.id == task_id)
+            .ok_or_else(|| format!("No task {} in {}", task_id, dir.display())),
+        (None, Some(command)) => {
+            let line = command.trim();
+            Ok(TaskDef::command(line, split_argv(line)?, dir))
+        }
+        _ => Err("Give either a task or a command to watch".to_string()),
+    }
+}
+
+/// Stop `run` and wait for its process to exit, at most [`STOP_TIMEOUT`].
+fn stop_and_wait(app: &AppHandle, run: JobId) {
+    let _ = jobs_commands::cancel(app, run);
+    let Some(runs) = app.try_state::<TaskRunsState>() else {
+        return;
+    };
+    runs.lock_recover().kill(run);
+    let deadline = Instant::now() + STOP_TIMEOUT;
+    while Instant::now() < deadline {
+        let running = runs
+            .lock_recover()
+            .get(run)
+            .is_some_and(|r| r.finished_ms.is_none());
+        if !running {
+            return;
+        }
+        thread::sleep(STOP_POLL_INTERVAL);
+    }
+    log::warn!("task run {} did not stop within {:?}", run, STOP_TIMEOUT);
+}
+
+/// Stop the current run of watch `id` and start the next. Called on the
+/// watch's file watcher thread, so reruns of one watch never overlap.
+fn rerun(app: &AppHandle, id: WatchId, (cols, rows): (Option<u16>, Option<u16>)) {
+    let Ok(watches) = watches_state(app) else {
+        return;
+    };
+    let Some(watch) = watches.lock_recover().get(id).cloned() else {
+        return;
+    };
+    if let Some(run) = watch.run_id {
+        stop_and_wait(app, run);
+    }
+    let run = match start_run(app, watch.task.clone(), cols, rows) {
+        Ok(run) => run,
+        Err(e) => {
+            log::warn!("failed to rerun {}: {}", watch.task.name, e);
+            return;
+        }
+    };
+    let updated = watches.lock_recover().set_run(id, run);
+    match updated {
+        Some(watch) => {
+            let _ = app.emit(TASK_WATCH_UPDATED_EVENT, &watch);
+        }
+        // Stopped while the run was starting.
+        None => stop_and_wait(app, run),
+    }
+}
+
+/// Run the task `task_id` of the project at `path`, or `command` in
+/// `path`, and run it again whenever files matching the patterns of
+/// `options` change. See [`WatchFilter`] for the patterns; without any,
+/// every change outside `.git` and build output counts.
+#[tauri::command]
+pub async fn watch_task(
+    app: AppHandle,
+    path: String,
+    task_id: Option<String>,
+    command: Option<String>,
+    options: Option<WatchOptions>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+) -> Result<TaskWatch, KiriError> {
+    let dir = normalize_input_path(&path);
+    run_blocking_coded("watch_task", move || {
+        let task = watched_task(&dir, task_id, command)?;
+        let task_dir = Path::new(&task.dir).to_path_buf();
+        require_trusted(&app, &task_dir)?;
+        let WatchOptions {
+            patterns,
+            debounce_ms,
+        } = options.unwrap_or_default();
+        let filter = WatchFilter::new(&task_dir, &patterns)?;
+        let debounce_ms = watch_debounce_ms(debounce_ms);
+        let watches = watches_state(&app)?;
+
+        let id = watches.lock_recover().reserve_id();
+        let watcher_app = app.clone();
+        let watcher = start_watcher_with_debounce(
+            &task_dir,
+            Duration::from_millis(debounce_ms),
+            move |events, _| {
+                if events.iter().any(|event| filter.matches(&event.path)) {
+                    rerun(&watcher_app, id, (cols, rows));
+                }
+            },
+        )?;
+        watches
+            .lock_recover()
+            .add(id, task.clone(), patterns, debounce_ms, watcher);
+
+        let run = match start_run(&app, task, cols, rows) {
+            Ok(run) => run,
+            Err(e) => {
+                let removed = watches.lock_recover().remove(id);
+                drop(removed);
+                return Err(e);
+            }
+        };
+        let watch = watches
+            .lock_recover()
+            .set_run(id, run)
+            .ok_or_else(|| format!("Task watch {} was stopped", id))?;
+        let _ = app.emit(TASK_WATCH_UPDATED_EVENT, &watch);
+        Ok(watch)
+    })
+    .await
+}
+
+/// Stop watch `watch_id` and its current run.
+#[tauri::command]
+pub fn stop_task_watch(
+    app: AppHandle,
+    state: tauri::State<'_, TaskWatchesState>,
+    watch_id: WatchId,
+) -> Result<(), KiriError> {
+    let removed = state.lock_recover().remove(watch_id);
+    let (watch, watcher) = removed.ok_or_else(|| format!("No task watch {}", watch_id))?;
+    drop(watcher);
+    if let Some(run) = watch.run_id {
+        let _ = jobs_commands::cancel(&app, run);
+        if let Some(runs) = app.try_state::<TaskRunsState>() {
+            runs.lock_recover().kill(run);
+        }
+    }
+    let _ = app.emit(TASK_WATCH_STOPPED_EVENT, &watch);
+    Ok(())
+}
+
+/// Watches of tasks in `path`, or of all projects, oldest first.
+#[tauri::command]
+pub fn list_task_watches(
+    state: tauri::State<'_, TaskWatchesState>,
+    path: Option<String>,
+) -> Vec<TaskWatch> {
+    let dir = path.map(|p| normalize_input_path(&p).to_string_lossy().to_string());
+    state.lock_recover().list(dir.as_deref())
+}
@@ -0,0 +1,529 @@
+//! Runnable tasks of a project: npm scripts, just recipes, make targets
+//! and cargo aliases.
+//!
+//! [`discover_tasks`] lists what a project directory defines, and
+//! [`TaskRuns`] keeps track of tasks started from it. Runs are background
+//! jobs (see jobs.rs) on a PTY, so colored output and prompts work as in a
+//! terminal; the runner lives in tasks_commands.rs. Finished runs stay in
+//! the history with their exit code and the tail of their output.
+
+use portable_pty::{ChildKiller, MasterPty};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::clock::now_ms;
+use super::fs_path::path_to_wire;
+use super::jobs::JobId;
+use super::terminal::create_pty_size;
+
+/// Job kind of task runs.
+pub const TASK_JOB_KIND: &str = "task";
+
+/// Task runs that may be running at once. Dev servers and watchers run
+/// until stopped, so this is well above the default job cap.
+pub const TASK_CONCURRENCY: usize = 16;
+
+/// Finished runs kept in the history; older ones are pruned.
+const MAX_FINISHED_RUNS: usize = 50;
+
+/// Output kept per run for a window that opens it later.
+const OUTPUT_TAIL_BYTES: usize = 64 * 1024;
+
+const JUSTFILES: &[&str] = &["justfile", "Justfile", ".justfile"];
+const MAKEFILES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+const CARGO_CONFIGS: &[&str] = &[".cargo/config.toml", ".cargo/config"];
+
+/// Payload of `task-output`: a chunk of a run's output.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskOutput {
+    pub run_id: JobId,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSource {
+    Npm,
+    Just,
+    Make,
+    Cargo,
+    /// A command line given by the user, e.g. one of the project's init
+    /// commands.
+    Command,
+}
+
+impl TaskSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Npm => "npm",
+            Self::Just => "just",
+            Self::Make => "make",
+            Self::Cargo => "cargo",
+            Self::Command => "command",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TaskDef {
+    /// `source:name`, unique within a project.
+    pub id: String,
+    pub source: TaskSource,
+    pub name: String,
+    /// What the task runs, e.g. the script of an npm script.
+    pub detail: Option<String>,
+    /// Program and arguments that run the task.
+    pub argv: Vec<String>,
+    /// Directory the task runs in.
+    pub dir: String,
+}
+
+impl TaskDef {
+    fn new(
+        source: TaskSource,
+        name: &str,
+        detail: Option<String>,
+        argv: Vec<String>,
+        dir: &Path,
+    ) -> Self {
+        Self {
+            id: format!("{}:{}", source.as_str(), name),
+            source,
+            name: name.to_string(),
+            detail,
+            argv,
+            dir: path_to_wire(dir),
+        }
+    }
+
+    /// A task running `line`, already split into `argv`, in `dir`.
+    pub fn command(line: &str, argv: Vec<String>, dir: &Path) -> Self {
+        Self::new(TaskSource::Command, line, None, argv, dir)
+    }
+}
+
+/// The package manager a project uses: its `packageManager` field, else
+/// its lockfile, else npm.
+pub fn package_manager(dir: &Path, package: &serde_json::Value) -> &'static str {
+    if let Some(field) = package["packageManager"].as_str() {
+        for manager in ["pnpm", "yarn", "bun", "npm"] {
+            if field.starts_with(manager) {
+                return manager;
+            }
+        }
+    }
+    let lockfiles = [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+    ];
+    lockfiles
+        .iter()
+        .find(|(file, _)| dir.join(file).is_file())
+        .map_or("npm", |(_, manager)| manager)
+}
+
+fn npm_scripts(dir: &Path) -> Vec<TaskDef> {
+    let Ok(text) = std::fs::read_to_string(dir.join("package.json")) else {
+        return Vec::new();
+    };
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(&text) else {
+        return Vec::new();
+    };
+    let manager = package_manager(dir, &package);
+    let Some(scripts) = package["scripts"].as_object() else {
+        return Vec::new();
+    };
+    scripts
+        .iter()
+        .map(|(name, script)| {
+            let argv = vec![manager.to_string(), "run".to_string(), name.clone()];
+            let detail = script.as_str().map(str::to_string);
+            TaskDef::new(TaskSource::Npm, name, detail, argv, dir)
+        })
+        .collect()
+}
+
+/// Public recipes of a justfile with their doc comments. Recipes starting
+/// with `_` or marked `[private]` are left out.
+pub fn parse_just_recipes(text: &str) -> Vec<(String, Option<String>)> {
+    let mut recipes = Vec::new();
+    let mut comment = None;
+    let mut private = false;
+    for line in text.lines() {
+        if let Some(doc) = line.strip_prefix('#') {
+            comment = Some(doc.trim().to_string());
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+        let (comment, was_private) = (comment.take(), std::mem::take(&mut private));
+        if line.starts_with(char::is_whitespace) || line.is_empty() {
+            continue;
+        }
+        let Some((head, _)) = line.split_once(':') else {
+            continue;
+        };
+        if line[head.len()..].starts_with(":=") {
+            continue;
+        }
+        let mut words = head.split_whitespace();
+        let Some(name) = words.next().map(|w| w.trim_start_matches('@')) else {
+            continue;
+        };
+        if ["set", "alias", "export", "import", "mod"].contains(&name) && words.next().is_some() {
+            continue;
+        }
+        if name.starts_with('_') || was_private {
+            continue;
+        }
+        if name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            recipes.push((name.to_string(), comment));
+        }
+    }
+    recipes
+}
+
+/// Explicit targets of a makefile, in order. Pattern rules, special
+/// targets such as `.PHONY` and variable assignments are skipped.
+pub fn parse_make_targets(text: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(['\t', ' ', '#', '.']) {
+            continue;
+        }
+        let Some((head, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with('=') || rest.starts_with(":=") || head.contains(['=', '%', '$']) {
+            continue;
+        }
+        for target in head.split_whitespace() {
+            if !targets.iter().any(|t| t == target) {
+                targets.push(target.to_string());
+            }
+        }
+    }
+    targets
+}
+
+/// `[alias]` entries of a cargo config, with the text they expand to.
+pub fn parse_cargo_aliases(text: &str) -> Vec<(String, String)> {
+    let mut aliases = Vec::new();
+    let mut in_alias = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_alias = line == "[alias]";
+            continue;
+        }
+        if !in_alias {
+            continue;
+        }
+        if let Some((name, value)) = line.split_once('=') {
+            let name = name.trim().trim_matches('"');
+            if !name.is_empty() && !name.starts_with('#') {
+                aliases.push((name.to_string(), value.trim().to_string()));
+            }
+        }
+    }
+    aliases
+}
+
+fn first_file(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    names
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|f| f.is_file())
+}
+
+/// The tasks a project directory defines. Cargo aliases are also taken
+/// from configs further up, up to the checkout root, as cargo merges them.
+pub fn discover_tasks(dir: &Path) -> Vec<TaskDef> {
+    let mut tasks = npm_scripts(dir);
+    if let Some(justfile) = first_file(dir, JUSTFILES) {
+        let text = std::fs::read_to_string(justfile).unwrap_or_default();
+        for (name, comment) in parse_just_recipes(&text) {
+            let argv = vec!["just".to_string(), name.clone()];
+            tasks.push(TaskDef::new(TaskSource::Just, &name, comment, argv, dir));
+        }
+    }
+    if let Some(makefile) = first_file(dir, MAKEFILES) {
+        let text = std::fs::read_to_string(makefile).unwrap_or_default();
+        for name in parse_make_targets(&text) {
+            let argv = vec!["make".to_string(), name.clone()];
+            tasks.push(TaskDef::new(TaskSource::Make, &name, None, argv, dir));
+        }
+    }
+    for config_dir in dir.ancestors() {
+        if let Some(config) = first_file(config_dir, CARGO_CONFIGS) {
+            let text = std::fs::read_to_string(config).unwrap_or_default();
+            for (name, expansion) in parse_cargo_aliases(&text) {
+                let id = format!("cargo:{}", name);
+                if tasks.iter().any(|task| task.id == id) {
+                    continue;
+                }
+                let argv = vec!["cargo".to_string(), name.clone()];
+                tasks.push(TaskDef::new(
+                    TaskSource::Cargo,
+                    &name,
+                    Some(expansion),
+                    argv,
+                    dir,
+                ));
+            }
+        }
+        if config_dir.join(".git").exists() {
+            break;
+        }
+    }
+    tasks
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TaskRunStatus {
+    Running,
+    Exited {
+        code: u32,
+    },
+    Stopped,
+    /// The task could not be started or waited on.
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TaskRun {
+    /// Id of the run's job.
+    pub id: JobId,
+    pub task: TaskDef,
+    pub status: TaskRunStatus,
+    pub started_ms: u64,
+    pub finished_ms: Option<u64>,
+}
+
+/// The PTY of a running task.
+pub struct RunHandle {
+    pub master: Box<dyn MasterPty + Send>,
+    pub writer: Box<dyn Write + Send>,
+    pub killer: Box<dyn ChildKiller + Send + Sync>,
+}
+
+#[derive(Default)]
+pub struct TaskRuns {
+    runs: BTreeMap<JobId, TaskRun>,
+    output: BTreeMap<JobId, String>,
+    handles: BTreeMap<JobId, RunHandle>,
+}
+
+pub type TaskRunsState = Arc<Mutex<TaskRuns>>;
+
+impl TaskRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&mut self, id: JobId, task: TaskDef) -> TaskRun {
+        let run = TaskRun {
+            id,
+            task,
+            status: TaskRunStatus::Running,
+            started_ms: now_ms(),
+            finished_ms: None,
+        };
+        self.runs.insert(id, run.clone());
+        self.output.insert(id, String::new());
+        run
+    }
+
+    pub fn attach(&mut self, id: JobId, handle: RunHandle) {
+        self.handles.insert(id, handle);
+    }
+
+    /// Keep `data` in the run's output tail.
+    pub fn append_output(&mut self, id: JobId, data: &str) {
+        let Some(output) = self.output.get_mut(&id) else {
+            return;
+        };
+        output.push_str(data);
+        if output.len() > OUTPUT_TAIL_BYTES {
+            let mut cut = output.len() - OUTPUT_TAIL_BYTES;
+            while !output.is_char_boundary(cut) {
+                cut += 1;
+            }
+            output.drain(..cut);
+        }
+    }
+
+    pub fn finish(&mut self, id: JobId, status: TaskRunStatus) -> Option<TaskRun> {
+        self.handles.remove(&id);
+        let run = self.runs.get_mut(&id)?;
+        run.status = status;
+        run.finished_ms = Some(now_ms());
+        let run = run.clone();
+        self.prune();
+        Some(run)
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&TaskRun> {
+        self.runs.get(&id)
+    }
+
+    pub fn output(&self, id: JobId) -> Option<&str> {
+        self.output.get(&id).map(String::as_str)
+    }
+
+    /// Runs in `dir`, or all of them, newest first.
+    pub fn list(&self, dir: Option<&str>) -> Vec<TaskRun> {
+        self.runs
+            .values()
+            .rev()
+            .filter(|run| dir.map_or(true, |dir| run.task.dir == dir))
+            .cloned()
+            .collect()
+    }
+
+    /// Kill a running task's process. Returns `false` if it isn't running.
+    pub fn kill(&mut self, id: JobId) -> bool {
+        match self.handles.get_mut(&id) {
+            Some(handle) => {
+                let _ = handle.killer.kill();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Kill every running task's process, for app exit. Returns how many
+    /// there were.
+    pub fn kill_all(&mut self) -> usize {
+        for handle in self.handles.values_mut() {
+            let _ = handle.killer.kill();
+        }
+        self.handles.len()
+    }
+
+    pub fn write(&mut self, id: JobId, data: &str) -> Result<(), String> {
+        let handle = self
+            .handles
+            .get_mut(&id)
+            .ok_or_else(|| format!("Task run {} is not running", id))?;
+        handle
+            .writer
+            .write_all(data.as_bytes())
+            .and_then(|_| handle.writer.flush())
+            .map_err(|e| format!("Failed to write to task: {}", e))
+    }
+
+    pub fn resize(&self, id: JobId, cols: u16, rows: u16) -> Result<(), String> {
+        let handle = self
+            .handles
+            .get(&id)
+            .ok_or_else(|| format!("Task run {} is not running", id))?;
+        handle
+            .master
+            .resize(create_pty_size(cols, rows))
+            .map_err(|e| format!("Failed to resize task: {}", e))
+    }
+
+    /// Drop the oldest finished runs beyond [`MAX_FINISHED_RUNS`].
+    fn prune(&mut self) {
+        let finished: Vec<JobId> = self
+            .runs
+            .values()
+            .filter(|run| run.finished_ms.is_some())
+            .map(|run| run.id)
+            .collect();
+        let excess = finished.len().saturating_sub(MAX_FINISHED_RUNS);
+        for id in finished.into_iter().take(excess) {
+            self.runs.remove(&id);
+            self.output.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_just_recipes() {
+        let text = "set shell := [\"bash\", \"-c\"]\nversion := \"1\"\nalias b := build\n\n# Build everything\nbuild target='debug': deps\n    cargo build\n\n@test *args:\n    cargo test {{args}}\n\n_helper:\n    true\n\n[private]\nsecret:\n    true\n";
+        assert_eq!(
+            parse_just_recipes(text),
+            vec![
+                ("build".to_string(), Some("Build everything".to_string())),
+                ("test".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_make_targets() {
+        let text = ".PHONY: all test\nCC := gcc\nFLAGS ::= -O2\nall: build test\n\tdo\nbuild test: deps\n%.o: %.c\n$(OUT): x\n";
+        assert_eq!(parse_make_targets(text), vec!["all", "build", "test"]);
+    }
+
+    #[test]
+    fn test_discover_tasks() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::create_dir(root.join(".cargo")).unwrap();
+        std::fs::write(
+            root.join("package.json"),
+            r#"{"scripts": {"dev": "vite"}, "packageManager": "pnpm@9.0.0"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join(".cargo/config.toml"),
+            "[build]\njobs = 4\n[alias]\nxtask = \"run -p xtask --\"\n",
+        )
+        .unwrap();
+        let tasks = discover_tasks(root);
+        let ids: Vec<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["npm:dev", "cargo:xtask"]);
+        assert_eq!(tasks[0].argv, vec!["pnpm", "run", "dev"]);
+        assert_eq!(tasks[0].detail.as_deref(), Some("vite"));
+    }
+
+    #[test]
+    fn test_runs_history_and_output_tail() {
+        let mut runs = TaskRuns::new();
+        let task = TaskDef::new(
+            TaskSource::Make,
+            "all",
+            None,
+            vec!["make".into()],
+            Path::new("/p"),
+        );
+        runs.start(1, task.clone());
+        runs.append_output(1, &"é".repeat(OUTPUT_TAIL_BYTES));
+        assert!(runs.output(1).unwrap().len() <= OUTPUT_TAIL_BYTES);
+        assert!(!runs.kill(1));
+        let run = runs.finish(1, TaskRunStatus::Exited { code: 2 }).unwrap();
+        assert_eq!(run.status, TaskRunStatus::Exited { code: 2 });
+
+        for id in 2..=(MAX_FINISHED_RUNS as JobId + 1) {
+            runs.start(id, task.clone());
+            runs.finish(id, TaskRunStatus::Stopped);
+        }
+        let listed = runs.list(Some("/p"));
+        assert_eq!(listed.len(), MAX_FINISHED_RUNS);
+        assert_eq!(listed[0].id, MAX_FINISHED_RUNS as JobId + 1);
+        assert!(runs.get(1).is_none());
+        assert!(runs.list(Some("/other")).is_empty());
+    }
+}
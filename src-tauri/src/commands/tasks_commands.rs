@@ -0,0 +1,364 @@
+//! Runner and Tauri commands for project tasks; discovery and run history
+//! live in tasks.rs.
+//!
+//! A run is a job of kind `task`, so it shows in `list_jobs` and
+//! `cancel_job` stops it. Its id is the job id. Output streams as
+//! `task-output` events and each change of a run's status is sent as
+//! `task-run-updated` with its [`TaskRun`]. Tasks only run in a trusted
+//! folder, see trust.rs.
+
+use portable_pty::CommandBuilder;
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::command_history::CommandOrigin;
+use super::command_history_commands::record_command;
+use super::direnv::{self, DirenvState};
+use super::env_sets_commands::env_set_overlay;
+use super::error::KiriError;
+use super::event_throttle_commands::emit_throttled;
+use super::fs_path::normalize_input_path;
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::{self, spawn_job};
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::notifications::{port_conflict, NotificationEvent};
+use super::notifications_commands::notify;
+use super::tasks::{
+    discover_tasks, RunHandle, TaskDef, TaskOutput, TaskRun, TaskRunStatus, TaskRunsState,
+    TaskSource, TASK_JOB_KIND,
+};
+use super::terminal::{
+    apply_env_overrides, build_shell_command, get_shell_path, open_pty_with_command,
+    read_utf8_chunks, resolve_terminal_size,
+};
+use super::toolchain;
+use super::trust_commands::{is_trusted, require_trusted};
+
+pub const TASK_OUTPUT_EVENT: &str = "task-output";
+pub const TASK_RUN_UPDATED_EVENT: &str = "task-run-updated";
+
+/// How often a run checks whether its process exited or it was cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn runs_state(app: &AppHandle) -> Result<TaskRunsState, String> {
+    app.try_state::<TaskRunsState>()
+        .map(|state| state.inner().clone())
+        .ok_or_else(|| "Task runner is not available".to_string())
+}
+
+/// User shell command: the PTY command for `argv` in `dir`, run by the
+/// user's login shell so the PATH from their profile applies as in a
+/// terminal, with the worktree's runtimes and approved `.envrc` on top.
+/// The argv goes in as the shell's positional parameters, never as part
+/// of a script.
+///
+/// Project tasks and agent CLIs are the only commands kiri hands to the
+/// user's shell; everything else is started with
+/// [`argv_command`](super::process::argv_command).
+pub(crate) fn user_shell_command(app: &AppHandle, argv: &[String], dir: &str) -> CommandBuilder {
+    let shell = get_shell_path();
+    let posix = ["sh", "bash", "zsh", "dash", "ksh"]
+        .iter()
+        .any(|name| Path::new(&shell).file_name() == Some(name.as_ref()));
+    let mut cmd = if posix {
+        let mut cmd = build_shell_command(&shell, Some(dir), None);
+        cmd.args(["-c", "exec \"$0\" \"$@\""]);
+        cmd.args(argv);
+        cmd
+    } else {
+        let mut cmd = CommandBuilder::new(&argv[0]);
+        cmd.args(&argv[1..]);
+        cmd.cwd(dir);
+        cmd.env("TERM", "xterm-256color");
+        cmd
+    };
+    let dir = Path::new(dir);
+    let mut env = toolchain::resolve(dir).env;
+    env.extend(env_set_overlay(app, dir));
+    if let Some(direnv_state) = app.try_state::<DirenvState>() {
+        if is_trusted(app, dir) {
+            env.extend(direnv::terminal_env(&direnv_state, dir));
+        }
+    }
+    apply_env_overrides(&mut cmd, &env, None);
+    cmd
+}
+
+fn publish_run(app: &AppHandle, run: &TaskRun) {
+    let _ = app.emit(TASK_RUN_UPDATED_EVENT, run);
+}
+
+/// Stream the PTY's output to the webview and the run's output tail until
+/// the task and everything it started have exited.
+/// A server reporting its port taken raises a port conflict notification.
+fn forward_output(
+    app: AppHandle,
+    runs: TaskRunsState,
+    task: TaskDef,
+    id: JobId,
+    reader: Box<dyn Read + Send>,
+) {
+    read_utf8_chunks(reader, |chunk| {
+        runs.lock_recover().append_output(id, chunk);
+        if let Some(port) = port_conflict(chunk) {
+            let event = NotificationEvent::PortConflict {
+                task: task.name.clone(),
+                dir: task.dir.clone(),
+                port,
+            };
+            notify(&app, event);
+        }
+        let payload = TaskOutput {
+            run_id: id,
+            data: chunk.to_string(),
+        };
+        emit_throttled(&app, TASK_OUTPUT_EVENT, &id.to_string(), &payload);
+    });
+}
+
+/// Body of a run's job: start `task` on a PTY and wait for it to exit or
+/// for the job to be cancelled.
+fn run_task_job(
+    app: &AppHandle,
+    ctx: &JobContext,
+    task: TaskDef,
+    (cols, rows): (u16, u16),
+) -> Result<(), String> {
+    let runs = runs_state(app)?;
+    let id = ctx.id();
+    let run = runs.lock_recover().start(id, task.clone());
+    publish_run(app, &run);
+
+    let command = user_shell_command(app, &task.argv, &task.dir);
+    let started_at = Instant::now();
+    let started = open_pty_with_command(cols, rows, command).and_then(|pty| {
+        let reader = pty
+            .pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| format!("Failed to read task output: {}", e))?;
+        let writer = pty
+            .pair
+            .master
+            .take_writer()
+            .map_err(|e| format!("Failed to open task input: {}", e))?;
+        Ok((pty, reader, writer))
+    });
+    let (pty, reader, writer) = match started {
+        Ok(started) => started,
+        Err(error) => {
+            let run = runs.lock_recover().finish(
+                id,
+                TaskRunStatus::Failed {
+                    error: error.clone(),
+                },
+            );
+            if let Some(run) = run {
+                publish_run(app, &run);
+            }
+            return Err(error);
+        }
+    };
+    let mut child = pty.child;
+    // Only the child holds the PTY's slave end now, so the reader sees EOF
+    // once it exits.
+    drop(pty.pair.slave);
+    runs.lock_recover().attach(
+        id,
+        RunHandle {
+            master: pty.pair.master,
+            writer,
+            killer: child.clone_killer(),
+        },
+    );
+    // Not joined: a daemon the task started may keep the PTY open long
+    // after the task itself exited.
+    {
+        let (app, runs, task) = (app.clone(), runs.clone(), task.clone());
+        thread::spawn(move || forward_output(app, runs, task, id, reader));
+    }
+
+    let mut killed = false;
+    let exit = loop {
+        if ctx.is_cancelled() && !killed {
+            let _ = child.kill();
+            killed = true;
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => break Err(format!("Failed to wait for task: {}", e)),
+        }
+    };
+
+    let (status, result) = match exit {
+        Ok(_) if killed => (TaskRunStatus::Stopped, Ok(())),
+        Ok(status) if status.success() => (TaskRunStatus::Exited { code: 0 }, Ok(())),
+        Ok(status) => {
+            let code = status.exit_code();
+            let error = format!("{} exited with code {}", task.name, code);
+            (TaskRunStatus::Exited { code }, Err(error))
+        }
+        Err(error) => (
+            TaskRunStatus::Failed {
+                error: error.clone(),
+            },
+            Err(error),
+        ),
+    };
+    let command_line = match task.source {
+        TaskSource::Command => task.name.clone(),
+        _ => task.argv.join(" "),
+    };
+    let exit_code = match status {
+        TaskRunStatus::Exited { code } => i32::try_from(code).ok(),
+        _ => None,
+    };
+    record_command(
+        app,
+        CommandOrigin::Task,
+        &command_line,
+        &task.dir,
+        exit_code,
+        started_at.elapsed(),
+    );
+    if let TaskRunStatus::Exited { code } = status {
+        let event = NotificationEvent::TaskFinished {
+            task: task.name.clone(),
+            dir: task.dir.clone(),
+            code,
+        };
+        notify(app, event);
+    }
+    if let Some(run) = runs.lock_recover().finish(id, status) {
+        publish_run(app, &run);
+    }
+    result
+}
+
+/// Start `task` as a new run. Returns the run id.
+pub(crate) fn start_run(
+    app: &AppHandle,
+    task: TaskDef,
+    cols: Option<u16>,
+    rows: Option<u16>,
+) -> Result<JobId, KiriError> {
+    require_trusted(app, Path::new(&task.dir))?;
+    let size = resolve_terminal_size(cols, rows);
+    let label = format!("{} {}", task.argv.join(" "), task.dir);
+    let job_app = app.clone();
+    Ok(spawn_job(app, TASK_JOB_KIND, &label, move |ctx| {
+        run_task_job(&job_app, ctx, task, size)
+    })?)
+}
+
+/// Tasks the project at `path` defines: npm scripts, just recipes, make
+/// targets and cargo aliases.
+#[tauri::command]
+pub async fn list_tasks(path: String) -> Result<Vec<TaskDef>, KiriError> {
+    let dir = normalize_input_path(&path);
+    run_blocking("list_tasks", move || Ok(discover_tasks(&dir))).await
+}
+
+/// Start the task `task_id` (e.g. `npm:dev`) of the project at `path`.
+/// Returns the run id.
+#[tauri::command]
+pub async fn run_task(
+    app: AppHandle,
+    path: String,
+    task_id: String,
+    cols: Option<u16>,
+    rows: Option<u16>,
+) -> Result<JobId, KiriError> {
+    let dir = normalize_input_path(&path);
+    let tasks = run_blocking("run_task", move || Ok(discover_tasks(&dir))).await?;
+    let task = tasks
+        .into_iter()
+        .find(|task| task.id == task_id)
+        .ok_or_else(|| format!("No task {} in {}", task_id, path))?;
+    start_run(&app, task, cols, rows)
+}
+
+/// Stop a run if it is still going and start its task again. Returns the
+/// id of the new run.
+#[tauri::command]
+pub fn restart_task(
+    app: AppHandle,
+    state: tauri::State<'_, TaskRunsState>,
+    run_id: JobId,
+) -> Result<JobId, KiriError> {
+    let run = state
+        .lock_recover()
+        .get(run_id)
+        .cloned()
+        .ok_or_else(|| format!("No task run {}", run_id))?;
+    if run.finished_ms.is_none() {
+        stop_task(app.clone(), state, run_id)?;
+    }
+    start_run(&app, run.task, None, None)
+}
+
+/// Stop a run, killing its process.
+#[tauri::command]
+pub fn stop_task(
+    app: AppHandle,
+    state: tauri::State<'_, TaskRunsState>,
+    run_id: JobId,
+) -> Result<(), KiriError> {
+    jobs_commands::cancel(&app, run_id)?;
+    state.lock_recover().kill(run_id);
+    Ok(())
+}
+
+/// Runs of tasks in `path`, or of all projects, newest first.
+#[tauri::command]
+pub fn list_task_runs(
+    state: tauri::State<'_, TaskRunsState>,
+    path: Option<String>,
+) -> Vec<TaskRun> {
+    let dir = path.map(|p| normalize_input_path(&p).to_string_lossy().to_string());
+    state.lock_recover().list(dir.as_deref())
+}
+
+/// The last output of a run, for showing it after a window reload.
+#[tauri::command]
+pub fn get_task_output(
+    state: tauri::State<'_, TaskRunsState>,
+    run_id: JobId,
+) -> Result<String, KiriError> {
+    state
+        .lock_recover()
+        .output(run_id)
+        .map(str::to_string)
+        .ok_or_else(|| format!("No task run {}", run_id))
+        .map_err(KiriError::from)
+}
+
+#[tauri::command]
+pub fn write_task_input(
+    state: tauri::State<'_, TaskRunsState>,
+    run_id: JobId,
+    data: String,
+) -> Result<(), KiriError> {
+    state
+        .lock_recover()
+        .write(run_id, &data)
+        .map_err(KiriError::from)
+}
+
+#[tauri::command]
+pub fn resize_task(
+    state: tauri::State<'_, TaskRunsState>,
+    run_id: JobId,
+    cols: u16,
+    rows: u16,
+) -> Result<(), KiriError> {
+    state
+        .lock_recover()
+        .resize(run_id, cols, rows)
+        .map_err(KiriError::from)
+}
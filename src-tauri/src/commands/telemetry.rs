@@ -0,0 +1,298 @@
+//! Opt-in usage analytics, kept on this machine.
+//!
+//! With the "collect usage analytics" setting on, every command timed by
+//! performance.rs is also counted here: calls, total and longest duration
+//! per command, across launches, in `~/.kiri/usage.json`. Nothing is sent
+//! anywhere. [`UsageStats::bundle`] makes the anonymized export a user can
+//! attach to an issue: command names and numbers only, with the start
+//! rounded to the day, so maintainers see which subsystems are used and
+//! where time goes. Turning the setting off discards what was collected.
+//! Loading, flushing and the commands live in telemetry_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use super::clock::now_ms;
+use super::file_io::write_file_contents_atomic;
+use super::lock_ext::LockExt;
+
+pub const BUNDLE_FORMAT: &str = "kiri-usage";
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Distinct commands counted; the frontend reports its own names, so this
+/// keeps a misbehaving caller from growing the file without bound.
+const MAX_COMMANDS: usize = 500;
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+pub fn usage_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("usage.json"))
+}
+
+static OPT_IN: AtomicBool = AtomicBool::new(false);
+
+/// Record the "collect usage analytics" setting.
+pub fn set_enabled(enabled: bool) {
+    OPT_IN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    OPT_IN.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommandUsage {
+    pub count: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandUsageReport {
+    pub command: String,
+    pub count: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    /// When collection started, ms since the epoch.
+    pub since_ms: u64,
+    pub commands: BTreeMap<String, CommandUsage>,
+}
+
+/// What an export contains.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageBundle {
+    pub format: &'static str,
+    pub version: u32,
+    pub app_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// Day collection started, in days since the epoch.
+    pub since_day: u64,
+    /// Busiest first.
+    pub commands: Vec<CommandUsageReport>,
+}
+
+/// Whether `command` can go into an export: an identifier, not something
+/// a caller might have put a path or other data into.
+fn is_command_name(command: &str) -> bool {
+    !command.is_empty()
+        && command.len() <= 64
+        && command
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':' | '.'))
+}
+
+fn round_ms(ms: f64) -> f64 {
+    (ms * 10.0).round() / 10.0
+}
+
+impl UsageStats {
+    pub fn record(&mut self, command: &str, duration_ms: f64, now_ms: u64) {
+        if self.since_ms == 0 {
+            self.since_ms = now_ms;
+        }
+        if !self.commands.contains_key(command) && self.commands.len() >= MAX_COMMANDS {
+            return;
+        }
+        let usage = self.commands.entry(command.to_string()).or_default();
+        usage.count += 1;
+        usage.total_ms += duration_ms;
+        usage.max_ms = usage.max_ms.max(duration_ms);
+    }
+
+    /// Add the figures of `other`.
+    pub fn merge(&mut self, other: UsageStats) {
+        if self.since_ms == 0 || (other.since_ms != 0 && other.since_ms < self.since_ms) {
+            self.since_ms = other.since_ms;
+        }
+        for (command, usage) in other.commands {
+            let merged = self.commands.entry(command).or_default();
+            merged.count += usage.count;
+            merged.total_ms += usage.total_ms;
+            merged.max_ms = merged.max_ms.max(usage.max_ms);
+        }
+    }
+
+    /// Per-command figures, most total time first.
+    pub fn report(&self) -> Vec<CommandUsageReport> {
+        let mut report: Vec<CommandUsageReport> = self
+            .commands
+            .iter()
+            .map(|(command, usage)| CommandUsageReport {
+                command: command.clone(),
+                count: usage.count,
+                total_ms: round_ms(usage.total_ms),
+                mean_ms: round_ms(usage.total_ms / usage.count.max(1) as f64),
+                max_ms: round_ms(usage.max_ms),
+            })
+            .collect();
+        report.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+        report
+    }
+
+    /// The anonymized export; commands whose name isn't an identifier are
+    /// left out.
+    pub fn bundle(&self) -> UsageBundle {
+        let mut commands = self.report();
+        commands.retain(|usage| is_command_name(&usage.command));
+        commands.sort_by_key(|usage| std::cmp::Reverse(usage.count));
+        UsageBundle {
+            format: BUNDLE_FORMAT,
+            version: BUNDLE_VERSION,
+            app_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            since_day: self.since_ms / MS_PER_DAY,
+            commands,
+        }
+    }
+}
+
+// ============================================================================
+// Collector
+// ============================================================================
+
+#[derive(Debug, Default)]
+struct Collector {
+    file: Option<PathBuf>,
+    stats: UsageStats,
+    /// Recorded since the last flush.
+    dirty: bool,
+}
+
+static COLLECTOR: Mutex<Option<Collector>> = Mutex::new(None);
+
+/// Count a call of `command`. A no-op unless the user opted in.
+pub fn record(command: &str, duration_ms: f64) {
+    if !enabled() {
+        return;
+    }
+    let mut collector = COLLECTOR.lock_recover();
+    let collector = collector.get_or_insert_with(Collector::default);
+    collector.stats.record(command, duration_ms, now_ms());
+    collector.dirty = true;
+}
+
+/// Read `path` as the stats collected so far; they are saved back there.
+pub fn load(path: PathBuf) {
+    let stats = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| match serde_json::from_str(&contents) {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                log::warn!("failed to parse usage analytics: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default();
+    let mut collector = COLLECTOR.lock_recover();
+    let collector = collector.get_or_insert_with(Collector::default);
+    // Calls recorded before loading count too.
+    let recorded = std::mem::replace(&mut collector.stats, stats);
+    collector.stats.merge(recorded);
+    collector.file = Some(path);
+}
+
+/// Save what was recorded since the last flush.
+pub fn flush() -> Result<(), String> {
+    let (path, json) = {
+        let mut collector = COLLECTOR.lock_recover();
+        let Some(collector) = collector.as_mut().filter(|c| c.dirty) else {
+            return Ok(());
+        };
+        let Some(path) = collector.file.clone() else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&collector.stats)
+            .map_err(|e| format!("Failed to serialize usage analytics: {}", e))?;
+        collector.dirty = false;
+        (path, json)
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    write_file_contents_atomic(&path, &json)
+}
+
+pub fn snapshot() -> UsageStats {
+    COLLECTOR
+        .lock_recover()
+        .as_ref()
+        .map(|collector| collector.stats.clone())
+        .unwrap_or_default()
+}
+
+/// Discard everything collected, saved or not.
+pub fn clear() -> Result<(), String> {
+    let path = {
+        let mut collector = COLLECTOR.lock_recover();
+        let collector = collector.get_or_insert_with(Collector::default);
+        collector.stats = UsageStats::default();
+        collector.dirty = false;
+        collector.file.clone()
+    };
+    match path {
+        Some(path) => match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to delete {}: {}", path.display(), e))
+            }
+            _ => Ok(()),
+        },
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_report() {
+        let mut stats = UsageStats::default();
+        stats.record("get_git_status", 12.0, 1_000);
+        stats.record("get_git_status", 30.04, 2_000);
+        stats.record("read_directory", 5.0, 3_000);
+        assert_eq!(stats.since_ms, 1_000);
+
+        let report = stats.report();
+        assert_eq!(report[0].command, "get_git_status");
+        assert_eq!(report[0].count, 2);
+        assert_eq!(report[0].total_ms, 42.0);
+        assert_eq!(report[0].mean_ms, 21.0);
+        assert_eq!(report[0].max_ms, 30.0);
+
+        for i in 0..MAX_COMMANDS {
+            stats.record(&format!("command_{}", i), 1.0, 4_000);
+        }
+        assert_eq!(stats.commands.len(), MAX_COMMANDS);
+        stats.record("get_git_status", 1.0, 5_000);
+        assert_eq!(stats.commands["get_git_status"].count, 3);
+    }
+
+    #[test]
+    fn test_bundle_is_anonymized() {
+        let mut stats = UsageStats::default();
+        stats.record("terminal:render", 2.0, 3 * MS_PER_DAY + 12_345);
+        stats.record("terminal:render", 2.0, 3 * MS_PER_DAY + 20_000);
+        stats.record("open /home/me/secret-project", 1.0, 3 * MS_PER_DAY);
+        stats.record("search_files", 80.0, 3 * MS_PER_DAY);
+
+        let bundle = stats.bundle();
+        assert_eq!(bundle.since_day, 3);
+        let names: Vec<&str> = bundle.commands.iter().map(|c| c.command.as_str()).collect();
+        assert_eq!(names, vec!["terminal:render", "search_files"]);
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert!(!json.contains("secret"));
+        assert!(!json.contains("12345"));
+    }
+}
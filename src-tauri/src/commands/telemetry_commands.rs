@@ -0,0 +1,111 @@
+//! Tauri commands for the usage analytics of telemetry.rs, and the thread
+//! that saves them.
+
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::error::KiriError;
+use super::file_io::write_file_contents_atomic;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::settings::SettingsState;
+use super::settings_commands::update_settings_impl;
+use super::telemetry::{self, usage_file_path, CommandUsageReport, UsageBundle};
+
+/// How often collected figures are saved.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub enabled: bool,
+    /// When collection started, ms since the epoch; 0 if nothing was yet.
+    pub since_ms: u64,
+    pub commands: Vec<CommandUsageReport>,
+}
+
+fn flush_logged() {
+    if let Err(e) = telemetry::flush() {
+        log::warn!("failed to save usage analytics: {}", e);
+    }
+}
+
+/// Apply the persisted setting, load what was collected before and start
+/// saving periodically. Call from setup.
+pub fn setup_usage_analytics(app: &AppHandle) {
+    let enabled = app
+        .try_state::<SettingsState>()
+        .is_some_and(|settings| settings.lock_recover().settings().collect_usage_analytics);
+    telemetry::set_enabled(enabled);
+    if let Some(path) = usage_file_path() {
+        telemetry::load(path);
+    }
+    let spawned = std::thread::Builder::new()
+        .name("kiri-usage-flush".to_string())
+        .spawn(|| loop {
+            std::thread::sleep(FLUSH_INTERVAL);
+            flush_logged();
+        });
+    if let Err(e) = spawned {
+        log::warn!("failed to start usage analytics flush: {}", e);
+    }
+}
+
+/// Save what the flush thread hasn't yet. Call on exit.
+pub fn flush_usage_analytics() {
+    flush_logged();
+}
+
+/// Start or stop collecting. Called when the `collectUsageAnalytics`
+/// setting changes; turning it off discards what was collected.
+pub fn apply_usage_analytics_setting(enabled: bool) -> Result<(), String> {
+    telemetry::set_enabled(enabled);
+    if enabled {
+        Ok(())
+    } else {
+        telemetry::clear()
+    }
+}
+
+/// Turn usage analytics on or off and remember the choice.
+#[tauri::command]
+pub fn set_usage_analytics_enabled(app: AppHandle, enabled: bool) -> Result<(), KiriError> {
+    update_settings_impl(
+        &app,
+        serde_json::json!({ "collectUsageAnalytics": enabled }),
+    )
+    .map(|_| ())
+    .map_err(KiriError::from)
+}
+
+/// What was collected so far, most total time first.
+#[tauri::command]
+pub fn get_usage_analytics() -> UsageReport {
+    let stats = telemetry::snapshot();
+    UsageReport {
+        enabled: telemetry::enabled(),
+        since_ms: stats.since_ms,
+        commands: stats.report(),
+    }
+}
+
+/// Discard what was collected; collection goes on if it is enabled.
+#[tauri::command]
+pub fn clear_usage_analytics() -> Result<(), KiriError> {
+    telemetry::clear().map_err(KiriError::from)
+}
+
+/// Write the anonymized figures to `path` for attaching to an issue.
+/// Returns what was written.
+#[tauri::command]
+pub async fn export_usage_analytics(path: String) -> Result<UsageBundle, KiriError> {
+    run_blocking("export_usage_analytics", move || {
+        let bundle = telemetry::snapshot().bundle();
+        let json = serde_json::to_vec_pretty(&bundle)
+            .map_err(|e| format!("Failed to serialize usage analytics: {}", e))?;
+        write_file_contents_atomic(&normalize_input_path(&path), &json)?;
+        Ok(bundle)
+    })
+    .await
+}
@@ -1,9 +1,12 @@
+use super::entity_map::EntityMap;
+use super::fs_path::path_to_wire;
 use super::lock_ext::LockExt;
 use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtyPair, PtySize};
 use serde::Serialize;
-use std::collections::HashMap;
-use std::io::Write;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
 use std::str;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Serialize)]
@@ -12,25 +15,103 @@ pub struct TerminalOutput {
     pub data: String,
 }
 
+/// One running shell. The master, writer and child each have their own
+/// lock, so a write blocked on a full PTY buffer does not hold up a resize,
+/// liveness check or kill of the same terminal.
 pub struct PtyInstance {
-    pub master: Box<dyn MasterPty + Send>,
-    pub writer: Box<dyn Write + Send>,
-    pub child: Box<dyn portable_pty::Child + Send + Sync>,
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    child: Mutex<Box<dyn portable_pty::Child + Send + Sync>>,
     pub shell_pid: Option<u32>,
 }
 
+impl PtyInstance {
+    pub fn new(
+        master: Box<dyn MasterPty + Send>,
+        writer: Box<dyn Write + Send>,
+        child: Box<dyn portable_pty::Child + Send + Sync>,
+        shell_pid: Option<u32>,
+    ) -> Self {
+        Self {
+            master: Mutex::new(master),
+            writer: Mutex::new(writer),
+            child: Mutex::new(child),
+            shell_pid,
+        }
+    }
+
+    /// Write `data` to the shell and flush it.
+    pub fn write(&self, data: &[u8]) -> std::io::Result<()> {
+        let mut writer = self.writer.lock_recover();
+        writer.write_all(data)?;
+        writer.flush()
+    }
+
+    pub fn resize(&self, size: PtySize) -> Result<(), String> {
+        self.master
+            .lock_recover()
+            .resize(size)
+            .map_err(|e| e.to_string())
+    }
+
+    /// `Ok(true)` while the shell has not exited.
+    pub fn is_running(&self) -> std::io::Result<bool> {
+        self.child
+            .lock_recover()
+            .try_wait()
+            .map(|status| status.is_none())
+    }
+
+    /// Kill the shell and reap it.
+    pub fn kill(&self) {
+        let mut child = self.child.lock_recover();
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// The open terminals, by id. See entity_map.rs for the locking scheme.
 pub struct TerminalManager {
-    pub instances: HashMap<u32, PtyInstance>,
-    pub next_id: u32,
+    instances: EntityMap<u32, PtyInstance>,
+    next_id: AtomicU32,
 }
 
 impl TerminalManager {
     pub fn new() -> Self {
         Self {
-            instances: HashMap::new(),
-            next_id: 1,
+            instances: EntityMap::new(),
+            next_id: AtomicU32::new(1),
         }
     }
+
+    /// Add a terminal under a fresh id and return the id.
+    pub fn insert(&self, instance: PtyInstance) -> u32 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // Ids are never reused, so the slot is always free.
+        let _ = self.instances.try_insert(id, instance);
+        id
+    }
+
+    pub fn get(&self, id: u32) -> Option<Arc<PtyInstance>> {
+        self.instances.get(&id)
+    }
+
+    pub fn remove(&self, id: u32) -> Option<Arc<PtyInstance>> {
+        self.instances.remove(&id)
+    }
+
+    /// Remove every terminal, for app exit.
+    pub fn remove_all(&self) -> Vec<Arc<PtyInstance>> {
+        self.instances.drain()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
 }
 
 impl Default for TerminalManager {
@@ -39,7 +120,7 @@ impl Default for TerminalManager {
     }
 }
 
-pub type TerminalState = Arc<Mutex<TerminalManager>>;
+pub type TerminalState = Arc<TerminalManager>;
 
 /// Per-terminal broadcast bus so the cli_server (and anything else that
 /// wants the raw PTY byte stream in-process) can subscribe without going
@@ -140,7 +221,7 @@ pub fn get_process_cwd(pid: u32) -> Option<String> {
     );
     sys.process(pid)
         .and_then(|p| p.cwd())
-        .map(|p| p.to_string_lossy().to_string())
+        .map(path_to_wire)
 }
 
 /// Per-PTY environment that exposes the in-terminal `kiri` command.
@@ -199,6 +280,24 @@ pub fn build_shell_command(
     cmd
 }
 
+/// Variables to set (`Some`) or unset (`None`) in a shell on top of kiri's
+/// own environment, e.g. from the worktree's `.envrc` (see direnv.rs).
+pub type EnvOverrides = BTreeMap<String, Option<String>>;
+
+/// Apply `env` to a command from [`build_shell_command`]. A `PATH` from
+/// `env` keeps `cli_env.bin_dir` in front.
+pub fn apply_env_overrides(cmd: &mut CommandBuilder, env: &EnvOverrides, cli_env: Option<&CliEnv>) {
+    for (key, value) in env {
+        match value {
+            Some(value) => cmd.env(key, value),
+            None => cmd.env_remove(key),
+        }
+    }
+    if let (Some(cli_env), Some(Some(path))) = (cli_env, env.get("PATH")) {
+        cmd.env("PATH", format!("{}:{}", cli_env.bin_dir.display(), path));
+    }
+}
+
 /// Find the last valid UTF-8 boundary in a byte slice.
 /// Returns the number of bytes that form valid UTF-8 from the start.
 /// Any remaining bytes are incomplete multi-byte sequences that should be
@@ -234,6 +333,75 @@ pub fn find_utf8_boundary(buf: &[u8]) -> usize {
     0
 }
 
+/// Read `reader` until it closes or fails, passing its output to
+/// `on_chunk` in pieces that end on a UTF-8 boundary. A character split
+/// across reads is held back for the next one.
+pub fn read_utf8_chunks(mut reader: impl Read, mut on_chunk: impl FnMut(&str)) {
+    let mut buf = [0u8; 4096];
+    // Buffer for incomplete UTF-8 sequences from previous reads
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        // Calculate where to start reading (after any pending bytes)
+        let read_start = pending.len();
+        let read_len = buf.len() - read_start;
+
+        if read_len == 0 {
+            // Buffer is full of pending bytes, which shouldn't happen
+            // Reset and continue
+            pending.clear();
+            continue;
+        }
+
+        // Copy pending bytes to the start of buffer
+        buf[..read_start].copy_from_slice(&pending);
+
+        match reader.read(&mut buf[read_start..]) {
+            Ok(0) => break, // EOF
+            Ok(n) => {
+                let total_len = read_start + n;
+                let data_slice = &buf[..total_len];
+
+                // Find the last valid UTF-8 boundary
+                let valid_len = find_utf8_boundary(data_slice);
+
+                if valid_len > 0 {
+                    // Safety: we just validated this is valid UTF-8
+                    on_chunk(unsafe { str::from_utf8_unchecked(&data_slice[..valid_len]) });
+                }
+
+                // Save any incomplete bytes for the next read
+                pending.clear();
+                if valid_len < total_len {
+                    pending.extend_from_slice(&data_slice[valid_len..]);
+                }
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+/// Read the output of terminal `terminal_id` from `reader` on its own
+/// thread until the shell exits, publishing each chunk to `bus` and then
+/// passing it to `on_output`. Chunks end on a UTF-8 boundary (see
+/// [`read_utf8_chunks`]). The terminal's bus channel is closed at the end.
+pub fn spawn_output_reader(
+    reader: Box<dyn Read + Send>,
+    terminal_id: u32,
+    bus: TerminalOutputBusState,
+    mut on_output: impl FnMut(&str) + Send + 'static,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        read_utf8_chunks(reader, |chunk| {
+            // Publish to in-process bus first so cli_server sentinel
+            // detection sees the same bytes the frontend receives.
+            bus.publish(terminal_id, chunk.as_bytes());
+            on_output(chunk);
+        });
+        bus.close(terminal_id);
+    })
+}
+
 /// Result of opening a PTY with a spawned shell
 pub struct PtyWithShell {
     pub pair: PtyPair,
@@ -245,9 +413,9 @@ pub struct PtyWithShell {
 ///
 /// Without this guard, any early-return between `open_pty_with_shell`
 /// and the terminal manager insertion (e.g. `try_clone_reader` succeeds
-/// but `take_writer` fails, or `state.lock()` returns an error) would
-/// leak the shell process and its PTY file descriptors because the
-/// reader / writer are dropped but the child is never waited on.
+/// but `take_writer` fails) would leak the shell process and its PTY file
+/// descriptors because the reader / writer are dropped but the child is
+/// never waited on.
 /// Wrapping the freshly-spawned PTY in this guard makes cleanup happen
 /// automatically on every early-return path; `commit` is called only
 /// after the instance is successfully owned by the manager.
@@ -301,6 +469,17 @@ pub fn open_pty_with_shell(
     rows: u16,
     cwd: Option<&str>,
     cli_env: Option<&CliEnv>,
+) -> Result<PtyWithShell, String> {
+    let shell = get_shell_path();
+    open_pty_with_command(cols, rows, build_shell_command(&shell, cwd, cli_env))
+}
+
+/// Open a PTY and spawn `cmd` in it instead of the user's shell, e.g. a
+/// `docker exec` into a dev container.
+pub fn open_pty_with_command(
+    cols: u16,
+    rows: u16,
+    cmd: CommandBuilder,
 ) -> Result<PtyWithShell, String> {
     let pty_system = native_pty_system();
 
@@ -308,9 +487,6 @@ pub fn open_pty_with_shell(
         .openpty(create_pty_size(cols, rows))
         .map_err(|e| format!("Failed to open PTY: {}", e))?;
 
-    let shell = get_shell_path();
-    let cmd = build_shell_command(&shell, cwd, cli_env);
-
     let child = pair
         .slave
         .spawn_command(cmd)
@@ -326,15 +502,14 @@ mod tests {
     #[test]
     fn test_terminal_manager_new() {
         let manager = TerminalManager::new();
-        assert!(manager.instances.is_empty());
-        assert_eq!(manager.next_id, 1);
+        assert!(manager.is_empty());
+        assert!(manager.get(1).is_none());
     }
 
     #[test]
     fn test_terminal_manager_default() {
         let manager = TerminalManager::default();
-        assert!(manager.instances.is_empty());
-        assert_eq!(manager.next_id, 1);
+        assert_eq!(manager.len(), 0);
     }
 
     #[test]
@@ -487,6 +662,31 @@ mod tests {
         assert_eq!(envs.get("KIRI_TERMINAL").map(String::as_str), Some("1"));
     }
 
+    #[test]
+    fn test_apply_env_overrides_keeps_kiri_bin_first() {
+        let cli_env = CliEnv {
+            bin_dir: std::path::PathBuf::from("/tmp/kiri-bin"),
+            socket: std::path::PathBuf::from("/tmp/kiri.sock"),
+            window_label: "window-1".into(),
+        };
+        let mut cmd = build_shell_command("/bin/bash", None, Some(&cli_env));
+        let env = EnvOverrides::from([
+            ("PATH".to_string(), Some("/repo/bin:/usr/bin".to_string())),
+            ("VIRTUAL_ENV".to_string(), Some("/repo/.venv".to_string())),
+            ("TERM_PROGRAM".to_string(), None),
+        ]);
+        apply_env_overrides(&mut cmd, &env, Some(&cli_env));
+        assert_eq!(
+            cmd.get_env("PATH").and_then(|p| p.to_str()),
+            Some("/tmp/kiri-bin:/repo/bin:/usr/bin")
+        );
+        assert_eq!(
+            cmd.get_env("VIRTUAL_ENV").and_then(|p| p.to_str()),
+            Some("/repo/.venv")
+        );
+        assert_eq!(cmd.get_env("TERM_PROGRAM"), None);
+    }
+
     #[test]
     fn test_open_pty_with_shell_default_size() {
         let result = open_pty_with_shell(80, 24, None, None);
@@ -622,6 +822,29 @@ mod tests {
         assert!(rx.recv().await.is_err());
     }
 
+    /// Hands out its bytes a few at a time.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(2);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_utf8_chunks_keeps_characters_whole() {
+        let text = "a日本語🎉b";
+        let mut chunks = Vec::new();
+        read_utf8_chunks(Trickle(text.as_bytes()), |chunk| {
+            chunks.push(chunk.to_string())
+        });
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), text);
+    }
+
     #[test]
     fn test_find_utf8_boundary_empty() {
         assert_eq!(find_utf8_boundary(&[]), 0);
@@ -2,15 +2,23 @@
 //! These are thin wrappers that delegate to the core logic in terminal.rs
 
 use super::cli_install;
-use super::lock_ext::LockExt;
+use super::devcontainer;
+use super::direnv::{self, DirenvState};
+use super::env_sets_commands::env_set_overlay;
+use super::error::{ErrorCode, KiriError};
+use super::event_throttle_commands::emit_throttled;
+use super::lock_ext::{LockExt, RwLockExt};
+use super::settings_layers_commands::resolve_settings_for;
 use super::terminal::{
-    create_pty_size, find_utf8_boundary, get_process_cwd, open_pty_with_shell, resolve_cwd,
-    resolve_terminal_size, CliEnv, PtyCleanupGuard, PtyInstance, TerminalOutput,
-    TerminalOutputBusState, TerminalState,
+    apply_env_overrides, build_shell_command, create_pty_size, get_process_cwd, get_shell_path,
+    open_pty_with_command, resolve_cwd, resolve_terminal_size, spawn_output_reader, CliEnv,
+    PtyCleanupGuard, PtyInstance, TerminalOutput, TerminalOutputBusState, TerminalState,
 };
+use super::trust_commands::is_trusted;
+use super::window::WindowRegistryState;
 use lazy_static::lazy_static;
 use serde::Serialize;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::str;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -19,6 +27,14 @@ use tauri::{AppHandle, Emitter};
 
 const PROCESS_SNAPSHOT_TTL: Duration = Duration::from_millis(1500);
 
+/// Payload of `toolchain-warnings`, emitted when a new terminal's pinned
+/// runtimes couldn't all be activated.
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalToolchainWarnings {
+    pub terminal_id: u32,
+    pub warnings: Vec<ToolchainWarning>,
+}
+
 /// Process info returned by get_terminal_process_info
 #[derive(Debug, Clone, Serialize)]
 pub struct TerminalProcessInfo {
@@ -115,31 +131,70 @@ fn cli_env_for(window_label: Option<&str>) -> Option<CliEnv> {
     })
 }
 
+/// Open a terminal running the user's login shell in `cwd`, with its
+/// pinned runtimes activated (see toolchain.rs), the shell, environment
+/// and port of its settings layers (see settings_layers.rs) and, in a
+/// trusted folder (see trust.rs), the environment of its approved `.envrc`
+/// (see direnv.rs), or, with
+/// `devcontainer`, a shell in the running dev container of the worktree
+/// at `cwd` (see devcontainer.rs).
 #[tauri::command]
 pub fn create_terminal(
     app: AppHandle,
     state: tauri::State<'_, TerminalState>,
     bus: tauri::State<'_, TerminalOutputBusState>,
+    direnv_state: tauri::State<'_, DirenvState>,
     cwd: Option<String>,
     cols: Option<u16>,
     rows: Option<u16>,
+    registry: tauri::State<'_, WindowRegistryState>,
     window_label: Option<String>,
-) -> Result<u32, String> {
+    devcontainer: Option<bool>,
+) -> Result<u32, KiriError> {
     let (initial_cols, initial_rows) = resolve_terminal_size(cols, rows);
-    let resolved_cwd = resolve_cwd(cwd);
-    let cli_env = cli_env_for(window_label.as_deref());
+    let in_container = devcontainer.unwrap_or(false);
+    let mut toolchain_warnings = Vec::new();
 
     // Wrap the freshly-spawned PTY in a cleanup guard so that any
-    // early-return below (reader/writer extraction, state lock failure)
-    // kills + reaps the shell instead of leaking the FD and process.
-    // The guard is `commit()`-ed once the PtyInstance is safely owned
-    // by the manager.
-    let mut pty_guard = PtyCleanupGuard::new(open_pty_with_shell(
-        initial_cols,
-        initial_rows,
-        resolved_cwd.as_deref(),
-        cli_env.as_ref(),
-    )?);
+    // early-return below (reader/writer extraction) kills + reaps the
+    // shell instead of leaking the FD and process. The guard is
+    // `commit()`-ed once the PtyInstance is safely owned by the manager.
+    let mut pty_guard = if in_container {
+        let dir = cwd.ok_or("A dev container terminal needs the worktree as cwd")?;
+        let cmd = devcontainer::terminal_command(std::path::Path::new(&dir))?;
+        PtyCleanupGuard::new(open_pty_with_command(initial_cols, initial_rows, cmd)?)
+    } else {
+        let resolved_cwd = resolve_cwd(cwd);
+        let cli_env = cli_env_for(window_label.as_deref());
+        if cli_env.is_some() {
+            // Usually already done by the background install at startup.
+            cli_install::ensure_installed_once(&app);
+        }
+        let layered = resolved_cwd
+            .as_deref()
+            .map(|dir| resolve_settings_for(&app, std::path::Path::new(dir)));
+        let shell = layered
+            .as_ref()
+            .and_then(|settings| settings.terminal_profile.clone())
+            .unwrap_or_else(get_shell_path);
+        let mut cmd = build_shell_command(&shell, resolved_cwd.as_deref(), cli_env.as_ref());
+        if let (Some(dir), Some(layered)) = (resolved_cwd.as_deref(), layered) {
+            let dir = std::path::Path::new(dir);
+            let toolchain = toolchain::resolve(dir);
+            // Settings layers over the pinned runtimes, then the active
+            // environment set, and an .envrc has the last word, e.g. over
+            // PATH.
+            let mut env = toolchain.env;
+            env.extend(layered.terminal_env());
+            env.extend(env_set_overlay(&app, dir));
+            if is_trusted(&app, dir) {
+                env.extend(direnv::terminal_env(&direnv_state, dir));
+            }
+            apply_env_overrides(&mut cmd, &env, cli_env.as_ref());
+            toolchain_warnings = toolchain.warnings;
+        }
+        PtyCleanupGuard::new(open_pty_with_command(initial_cols, initial_rows, cmd)?)
+    };
 
     let mut reader = pty_guard
         .as_mut()
@@ -154,92 +209,55 @@ pub fn create_terminal(
         .take_writer()
         .map_err(|e| e.to_string())?;
 
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
-    let id = manager.next_id;
-    manager.next_id += 1;
-
     // Get shell PID for foreground process checking
     let shell_pid = pty_guard.as_mut().child.process_id();
 
     // Send bindkey -e to enable emacs mode for keyboard navigation
     // This ensures Option+Arrow and Cmd+Arrow work correctly regardless of user's shell config
     // Use clear to hide the command from the user
+    // (not in containers, whose shell is bash or sh rather than the user's)
     let mut writer = writer;
-    let _ = writer.write_all(b"bindkey -e && clear\n");
-    let _ = writer.flush();
+    if !in_container {
+        let _ = writer.write_all(b"bindkey -e && clear\n");
+        let _ = writer.flush();
+    }
 
     // Take ownership out of the guard now that nothing else can fail.
     let pty_with_shell = pty_guard.commit();
-    manager.instances.insert(
-        id,
-        PtyInstance {
-            master: pty_with_shell.pair.master,
-            writer,
-            child: pty_with_shell.child,
-            shell_pid,
-        },
-    );
+    let id = state.insert(PtyInstance::new(
+        pty_with_shell.pair.master,
+        writer,
+        pty_with_shell.child,
+        shell_pid,
+    ));
+
+    if let Some(label) = window_label.as_deref() {
+        registry
+            .write_recover()
+            .contexts_mut()
+            .attach_terminal(label, id);
+    }
+    super::tray_commands::refresh_tray(&app);
+    if !toolchain_warnings.is_empty() {
+        let payload = TerminalToolchainWarnings {
+            terminal_id: id,
+            warnings: toolchain_warnings,
+        };
+        let _ = app.emit("toolchain-warnings", &payload);
+    }
 
     // Spawn thread to read PTY output
     let terminal_id = id;
-    let bus_for_task: TerminalOutputBusState = bus.inner().clone();
-    thread::spawn(move || {
-        let mut buf = [0u8; 4096];
-        // Buffer for incomplete UTF-8 sequences from previous reads
-        let mut pending: Vec<u8> = Vec::new();
-
-        loop {
-            // Calculate where to start reading (after any pending bytes)
-            let read_start = pending.len();
-            let read_len = buf.len() - read_start;
-
-            if read_len == 0 {
-                // Buffer is full of pending bytes, which shouldn't happen
-                // Reset and continue
-                pending.clear();
-                continue;
-            }
-
-            // Copy pending bytes to the start of buffer
-            buf[..read_start].copy_from_slice(&pending);
-
-            match reader.read(&mut buf[read_start..]) {
-                Ok(0) => break, // EOF
-                Ok(n) => {
-                    let total_len = read_start + n;
-                    let data_slice = &buf[..total_len];
-
-                    // Find the last valid UTF-8 boundary
-                    let valid_len = find_utf8_boundary(data_slice);
-
-                    if valid_len > 0 {
-                        let raw_chunk = &data_slice[..valid_len];
-                        // Publish to in-process bus first so cli_server
-                        // sentinel detection sees the same bytes the
-                        // frontend receives.
-                        bus_for_task.publish(terminal_id, raw_chunk);
-
-                        // Safety: we just validated this is valid UTF-8
-                        let data = unsafe { str::from_utf8_unchecked(raw_chunk) };
-                        let _ = app.emit(
-                            "terminal-output",
-                            TerminalOutput {
-                                id: terminal_id,
-                                data: data.to_string(),
-                            },
-                        );
-                    }
-
-                    // Save any incomplete bytes for the next read
-                    pending.clear();
-                    if valid_len < total_len {
-                        pending.extend_from_slice(&data_slice[valid_len..]);
-                    }
-                }
-                Err(_) => break,
-            }
-        }
-        bus_for_task.close(terminal_id);
+    spawn_output_reader(reader, terminal_id, bus.inner().clone(), move |data| {
+        emit_throttled(
+            &app,
+            "terminal-output",
+            &terminal_id.to_string(),
+            &TerminalOutput {
+                id: terminal_id,
+                data: data.to_string(),
+            },
+        );
     });
 
     Ok(id)
@@ -250,18 +268,15 @@ pub fn write_terminal(
     state: tauri::State<'_, TerminalState>,
     id: u32,
     data: String,
-) -> Result<(), String> {
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
-
-    if let Some(instance) = manager.instances.get_mut(&id) {
-        instance
-            .writer
-            .write_all(data.as_bytes())
-            .map_err(|e| e.to_string())?;
-        instance.writer.flush().map_err(|e| e.to_string())?;
+) -> Result<(), KiriError> {
+    if let Some(instance) = state.get(id) {
+        instance.write(data.as_bytes()).map_err(|e| e.to_string())?;
         Ok(())
     } else {
-        Err(format!("Terminal {} not found", id))
+        Err(KiriError::new(
+            ErrorCode::NotFound,
+            format!("Terminal {} not found", id),
+        ))
     }
 }
 
@@ -271,39 +286,62 @@ pub fn resize_terminal(
     id: u32,
     cols: u16,
     rows: u16,
-) -> Result<(), String> {
-    let manager = state.lock().map_err(|e| e.to_string())?;
-
-    if let Some(instance) = manager.instances.get(&id) {
-        instance
-            .master
-            .resize(create_pty_size(cols, rows))
-            .map_err(|e| e.to_string())?;
+) -> Result<(), KiriError> {
+    if let Some(instance) = state.get(id) {
+        instance.resize(create_pty_size(cols, rows))?;
         log::info!("Resized terminal {} to {}x{}", id, cols, rows);
         Ok(())
     } else {
-        Err(format!("Terminal {} not found", id))
+        Err(KiriError::new(
+            ErrorCode::NotFound,
+            format!("Terminal {} not found", id),
+        ))
     }
 }
 
+/// Remove terminal `id` from the manager, close its output bus and kill
+/// the shell on a background thread. Returns `false` if it was unknown.
+pub fn shutdown_terminal(state: &TerminalState, bus: &TerminalOutputBusState, id: u32) -> bool {
+    let Some(instance) = state.remove(id) else {
+        return false;
+    };
+    bus.close(id);
+    thread::spawn(move || instance.kill());
+    true
+}
+
+/// Close a terminal. When `window_label` is given, the terminal must
+/// belong to that window.
 #[tauri::command]
 pub fn close_terminal(
+    app: AppHandle,
     state: tauri::State<'_, TerminalState>,
     bus: tauri::State<'_, TerminalOutputBusState>,
+    registry: tauri::State<'_, WindowRegistryState>,
     id: u32,
-) -> Result<(), String> {
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
-
-    if let Some(mut instance) = manager.instances.remove(&id) {
-        bus.close(id);
-        drop(manager);
-        thread::spawn(move || {
-            let _ = instance.child.kill();
-            let _ = instance.child.wait();
-        });
+    window_label: Option<String>,
+) -> Result<(), KiriError> {
+    {
+        let mut reg = registry.write_recover();
+        if let Some(label) = window_label.as_deref() {
+            if !reg.contexts().owns_terminal(label, id) {
+                return Err(KiriError::new(
+                    ErrorCode::InvalidInput,
+                    format!("Terminal {} does not belong to this window", id),
+                ));
+            }
+        }
+        reg.contexts_mut().detach_terminal(id);
+    }
+
+    if shutdown_terminal(&state, &bus, id) {
+        super::tray_commands::refresh_tray(&app);
         Ok(())
     } else {
-        Err(format!("Terminal {} not found", id))
+        Err(KiriError::new(
+            ErrorCode::NotFound,
+            format!("Terminal {} not found", id),
+        ))
     }
 }
 
@@ -313,21 +351,18 @@ pub fn close_terminal(
 pub fn get_terminal_process_info(
     state: tauri::State<'_, TerminalState>,
     id: u32,
-) -> Result<TerminalProcessInfo, String> {
+) -> Result<TerminalProcessInfo, KiriError> {
     let default_info = TerminalProcessInfo {
         name: "Terminal".to_string(),
         memory_bytes: 0,
     };
 
     let shell_pid = {
-        let mut manager = state.lock().map_err(|e| e.to_string())?;
-        let Some(instance) = manager.instances.get_mut(&id) else {
+        let Some(instance) = state.get(id) else {
             return Ok(default_info);
         };
-        match instance.child.try_wait() {
-            Ok(Some(_)) => return Ok(default_info),
-            Ok(None) => {}
-            Err(_) => return Ok(default_info),
+        if !instance.is_running().unwrap_or(false) {
+            return Ok(default_info);
         }
         instance.shell_pid
     };
@@ -344,7 +379,7 @@ pub fn get_terminal_process_info(
 pub fn get_foreground_process_name(
     state: tauri::State<'_, TerminalState>,
     id: u32,
-) -> Result<String, String> {
+) -> Result<String, KiriError> {
     // Delegate to get_terminal_process_info for consistency
     let info = get_terminal_process_info(state, id)?;
     Ok(info.name)
@@ -355,13 +390,10 @@ pub fn get_foreground_process_name(
 pub fn get_terminal_cwd(
     state: tauri::State<'_, TerminalState>,
     id: u32,
-) -> Result<Option<String>, String> {
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
-    if let Some(instance) = manager.instances.get_mut(&id) {
-        match instance.child.try_wait() {
-            Ok(Some(_)) => return Ok(None),
-            Ok(None) => {}
-            Err(_) => return Ok(None),
+) -> Result<Option<String>, KiriError> {
+    if let Some(instance) = state.get(id) {
+        if !instance.is_running().unwrap_or(false) {
+            return Ok(None);
         }
         if let Some(shell_pid) = instance.shell_pid {
             Ok(get_process_cwd(shell_pid))
@@ -377,16 +409,18 @@ pub fn get_terminal_cwd(
 /// Returns true if there's a child process of the shell (command running),
 /// false if the shell is idle (waiting at prompt)
 #[tauri::command]
-pub fn is_terminal_alive(state: tauri::State<'_, TerminalState>, id: u32) -> Result<bool, String> {
+pub fn is_terminal_alive(
+    state: tauri::State<'_, TerminalState>,
+    id: u32,
+) -> Result<bool, KiriError> {
     let shell_pid = {
-        let mut manager = state.lock().map_err(|e| e.to_string())?;
-        let Some(instance) = manager.instances.get_mut(&id) else {
+        let Some(instance) = state.get(id) else {
             return Ok(false);
         };
-        match instance.child.try_wait() {
-            Ok(Some(_)) => return Ok(false),
-            Ok(None) => {}
-            Err(e) => return Err(format!("Failed to check terminal status: {}", e)),
+        match instance.is_running() {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => return Err(format!("Failed to check terminal status: {}", e).into()),
         }
         instance.shell_pid
     };
@@ -0,0 +1,618 @@
+//! Running a project's tests and reading their results: jest and vitest
+//! through their JSON reporters, `cargo test` and pytest through their
+//! console output, which is stable where their machine formats are not
+//! (cargo's JSON needs nightly, pytest's JUnit XML needs an XML parser).
+//!
+//! Every framework's results come out as flat [`TestResult`]s with a path
+//! such as `["src/app.test.ts", "App", "renders"]`, which [`TestSummary`]
+//! folds into the tree the test explorer shows. The runner lives in
+//! test_runner_commands.rs.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::jobs::JobId;
+use super::trust::{require_trusted_in, TrustStoreState};
+
+/// Payload of `test-progress`: counts so far for a running test job.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestProgress {
+    pub run_id: JobId,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Latest line of output, e.g. the test file being run.
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TestFramework {
+    Jest,
+    Vitest,
+    Cargo,
+    Pytest,
+}
+
+impl TestFramework {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Jest => "jest",
+            Self::Vitest => "vitest",
+            Self::Cargo => "cargo test",
+            Self::Pytest => "pytest",
+        }
+    }
+}
+
+/// What to run. `path` is a test file or directory relative to the
+/// project (for cargo, a `tests/*.rs` file or a crate directory), and
+/// `name` filters tests by name. Both empty runs everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TestScope {
+    pub path: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TestResult {
+    /// File (or test binary), then suites, then the test's name.
+    pub path: Vec<String>,
+    pub outcome: TestOutcome,
+    pub duration_ms: Option<u64>,
+    /// Failure message, for failed tests.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TestNode {
+    pub name: String,
+    /// For suites, failed if any test in it failed, else passed if any
+    /// passed.
+    pub outcome: TestOutcome,
+    /// For suites, the sum of their tests' durations.
+    pub duration_ms: Option<u64>,
+    pub message: Option<String>,
+    pub children: Vec<TestNode>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub tree: Vec<TestNode>,
+}
+
+fn insert(nodes: &mut Vec<TestNode>, result: &TestResult, depth: usize) {
+    let name = &result.path[depth];
+    let index = match nodes.iter().position(|node| &node.name == name) {
+        Some(index) => index,
+        None => {
+            nodes.push(TestNode {
+                name: name.clone(),
+                outcome: TestOutcome::Skipped,
+                duration_ms: None,
+                message: None,
+                children: Vec::new(),
+            });
+            nodes.len() - 1
+        }
+    };
+    let node = &mut nodes[index];
+    if depth + 1 == result.path.len() {
+        node.outcome = result.outcome;
+        node.duration_ms = result.duration_ms;
+        node.message = result.message.clone();
+    } else {
+        insert(&mut node.children, result, depth + 1);
+    }
+}
+
+/// Give suites the outcome and duration of what they contain.
+fn aggregate(node: &mut TestNode) {
+    if node.children.is_empty() {
+        return;
+    }
+    node.children.iter_mut().for_each(aggregate);
+    let outcomes = || node.children.iter().map(|child| child.outcome);
+    node.outcome = if outcomes().any(|o| o == TestOutcome::Failed) {
+        TestOutcome::Failed
+    } else if outcomes().any(|o| o == TestOutcome::Passed) {
+        TestOutcome::Passed
+    } else {
+        TestOutcome::Skipped
+    };
+    let durations: Vec<u64> = node.children.iter().filter_map(|c| c.duration_ms).collect();
+    node.duration_ms = (!durations.is_empty()).then(|| durations.iter().sum());
+}
+
+impl TestSummary {
+    pub fn from_results(results: &[TestResult]) -> Self {
+        let mut tree = Vec::new();
+        for result in results.iter().filter(|r| !r.path.is_empty()) {
+            insert(&mut tree, result, 0);
+        }
+        tree.iter_mut().for_each(aggregate);
+        let count = |outcome| results.iter().filter(|r| r.outcome == outcome).count();
+        Self {
+            passed: count(TestOutcome::Passed),
+            failed: count(TestOutcome::Failed),
+            skipped: count(TestOutcome::Skipped),
+            tree,
+        }
+    }
+}
+
+fn package_json_mentions(dir: &Path, package: &str) -> bool {
+    let Ok(text) = std::fs::read_to_string(dir.join("package.json")) else {
+        return false;
+    };
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) else {
+        return false;
+    };
+    ["dependencies", "devDependencies"]
+        .iter()
+        .any(|key| json[key].get(package).is_some())
+}
+
+/// Test frameworks the project at `dir` is set up for.
+pub fn detect_frameworks(dir: &Path) -> Vec<TestFramework> {
+    let mut frameworks = Vec::new();
+    if package_json_mentions(dir, "vitest") {
+        frameworks.push(TestFramework::Vitest);
+    }
+    if package_json_mentions(dir, "jest") {
+        frameworks.push(TestFramework::Jest);
+    }
+    if dir.join("Cargo.toml").is_file() {
+        frameworks.push(TestFramework::Cargo);
+    }
+    let pytest_markers = ["pytest.ini", "conftest.py", "tox.ini", "setup.cfg"];
+    let pyproject = std::fs::read_to_string(dir.join("pyproject.toml")).unwrap_or_default();
+    if pyproject.contains("pytest") || pytest_markers.iter().any(|f| dir.join(f).is_file()) {
+        frameworks.push(TestFramework::Pytest);
+    }
+    frameworks
+}
+
+/// Program and arguments for one run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+/// The command running `scope` with `framework` in the project at `root`,
+/// which must be trusted: the tests are the project's code. jest and
+/// vitest write their JSON report to `report`.
+pub fn test_command(
+    trust: &TrustStoreState,
+    framework: TestFramework,
+    root: &Path,
+    scope: &TestScope,
+    report: &Path,
+) -> Result<TestCommand, String> {
+    require_trusted_in(trust, root)?;
+    let report = report.to_string_lossy().to_string();
+    let mut args: Vec<String> = Vec::new();
+    let program = match framework {
+        TestFramework::Jest | TestFramework::Vitest => {
+            let bin = root.join("node_modules/.bin").join(match framework {
+                TestFramework::Jest => "jest",
+                _ => "vitest",
+            });
+            if !bin.is_file() {
+                return Err(format!(
+                    "{} is not installed in this project; run your package manager's install",
+                    framework.name()
+                ));
+            }
+            if framework == TestFramework::Vitest {
+                args.extend(["run", "--reporter=default", "--reporter=json"].map(String::from));
+            } else {
+                args.extend(["--json", "--verbose"].map(String::from));
+            }
+            args.push(format!("--outputFile={}", report));
+            args.extend(scope.path.clone());
+            if let Some(name) = &scope.name {
+                args.extend(["-t".to_string(), name.clone()]);
+            }
+            bin
+        }
+        TestFramework::Cargo => {
+            args.extend(["test", "--color", "never"].map(String::from));
+            if let Some(path) = &scope.path {
+                let target = Path::new(path);
+                if root.join(target).join("Cargo.toml").is_file() {
+                    let manifest = root.join(target).join("Cargo.toml");
+                    args.push("--manifest-path".to_string());
+                    args.push(manifest.to_string_lossy().to_string());
+                } else if let Some(name) = target
+                    .strip_prefix("tests")
+                    .ok()
+                    .and_then(|file| file.file_stem())
+                {
+                    args.push("--test".to_string());
+                    args.push(name.to_string_lossy().to_string());
+                }
+            }
+            if let Some(name) = &scope.name {
+                args.extend(["--".to_string(), name.clone()]);
+            }
+            PathBuf::from("cargo")
+        }
+        TestFramework::Pytest => {
+            let python = [".venv/bin/python", "venv/bin/python"]
+                .iter()
+                .map(|p| root.join(p))
+                .find(|p| p.is_file())
+                .unwrap_or_else(|| PathBuf::from("python3"));
+            let flags = ["-m", "pytest", "-v", "-rA", "--color=no"];
+            args.extend(flags.map(String::from));
+            args.extend(["--durations=0", "--durations-min=0"].map(String::from));
+            args.extend(scope.path.clone());
+            if let Some(name) = &scope.name {
+                args.extend(["-k".to_string(), name.clone()]);
+            }
+            python
+        }
+    };
+    Ok(TestCommand { program, args })
+}
+
+fn relative(root: &Path, file: &str) -> String {
+    Path::new(file)
+        .strip_prefix(root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| file.to_string())
+}
+
+/// Results from a jest or vitest JSON report. A file that failed before
+/// running any test (e.g. a syntax error) becomes one failed result.
+pub fn parse_jest_report(text: &str, root: &Path) -> Result<Vec<TestResult>, String> {
+    let report: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse test report: {}", e))?;
+    let mut results = Vec::new();
+    for file in report["testResults"].as_array().into_iter().flatten() {
+        let name = relative(root, file["name"].as_str().unwrap_or_default());
+        let assertions = file["assertionResults"].as_array();
+        if assertions.map_or(true, |a| a.is_empty()) {
+            if file["status"] == "failed" {
+                results.push(TestResult {
+                    path: vec![name],
+                    outcome: TestOutcome::Failed,
+                    duration_ms: None,
+                    message: file["message"].as_str().map(str::to_string),
+                });
+            }
+            continue;
+        }
+        for test in assertions.into_iter().flatten() {
+            let mut path = vec![name.clone()];
+            for title in test["ancestorTitles"].as_array().into_iter().flatten() {
+                path.extend(title.as_str().map(str::to_string));
+            }
+            path.push(test["title"].as_str().unwrap_or_default().to_string());
+            let outcome = match test["status"].as_str() {
+                Some("passed") => TestOutcome::Passed,
+                Some("failed") => TestOutcome::Failed,
+                _ => TestOutcome::Skipped,
+            };
+            let failures: Vec<&str> = test["failureMessages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|m| m.as_str())
+                .collect();
+            results.push(TestResult {
+                path,
+                outcome,
+                duration_ms: test["duration"].as_f64().map(|d| d.round() as u64),
+                message: (!failures.is_empty()).then(|| failures.join("\n")),
+            });
+        }
+    }
+    Ok(results)
+}
+
+lazy_static::lazy_static! {
+    static ref CARGO_TEST_LINE: Regex =
+        Regex::new(r"^test (.+) \.\.\. (ok|FAILED|ignored)").expect("pattern compiles");
+    static ref CARGO_BINARY_LINE: Regex =
+        Regex::new(r"^\s*(?:Running (?:unittests )?|Doc-tests )(\S+)").expect("pattern compiles");
+    static ref PYTEST_VERBOSE_LINE: Regex =
+        Regex::new(r"^(\S.*?::.+?) (PASSED|FAILED|SKIPPED|ERROR|XFAIL|XPASS)(?: |$)")
+            .expect("pattern compiles");
+    static ref PYTEST_DURATION_LINE: Regex =
+        Regex::new(r"^(\d+(?:\.\d+)?)s (?:setup|call|teardown)\s+(\S.*)$")
+            .expect("pattern compiles");
+    static ref PYTEST_SUMMARY_LINE: Regex =
+        Regex::new(r"^(FAILED|ERROR) (.+?)(?: - (.*))?$").expect("pattern compiles");
+}
+
+/// Reads `cargo test` output line by line. Results stream from stdout as
+/// tests finish; the test binaries they belong to are named on stderr, so
+/// results are only filed under their binary by [`Self::finish`].
+#[derive(Default)]
+pub struct CargoTestParser {
+    /// Number of `running N tests` blocks seen, one per test binary.
+    block: usize,
+    results: Vec<(usize, String, TestOutcome)>,
+    messages: HashMap<String, String>,
+    capturing: Option<(String, Vec<String>)>,
+    binaries: Vec<String>,
+}
+
+impl CargoTestParser {
+    pub fn stdout_line(&mut self, line: &str) -> Option<TestResult> {
+        if line.starts_with("---- ") || line == "failures:" || line.starts_with("test result:") {
+            if let Some((name, lines)) = self.capturing.take() {
+                self.messages
+                    .insert(name, lines.join("\n").trim().to_string());
+            }
+        }
+        if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" stdout ----"))
+        {
+            self.capturing = Some((name.to_string(), Vec::new()));
+            return None;
+        }
+        if let Some((_, lines)) = &mut self.capturing {
+            lines.push(line.to_string());
+            return None;
+        }
+        if line.starts_with("running ") && line.ends_with(" tests") || line == "running 1 test" {
+            self.block += 1;
+            return None;
+        }
+        let captures = CARGO_TEST_LINE.captures(line)?;
+        let name = captures[1].to_string();
+        let outcome = match &captures[2] {
+            "ok" => TestOutcome::Passed,
+            "FAILED" => TestOutcome::Failed,
+            _ => TestOutcome::Skipped,
+        };
+        self.results.push((self.block, name.clone(), outcome));
+        Some(TestResult {
+            path: vec![name],
+            outcome,
+            duration_ms: None,
+            message: None,
+        })
+    }
+
+    pub fn stderr_line(&mut self, line: &str) {
+        if let Some(captures) = CARGO_BINARY_LINE.captures(line) {
+            let binary = &captures[1];
+            self.binaries.push(if line.contains("Doc-tests") {
+                format!("Doc-tests {}", binary)
+            } else {
+                binary.to_string()
+            });
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<TestResult> {
+        if let Some((name, lines)) = self.capturing.take() {
+            self.messages
+                .insert(name, lines.join("\n").trim().to_string());
+        }
+        self.results
+            .into_iter()
+            .map(|(block, name, outcome)| {
+                let binary = self
+                    .binaries
+                    .get(block.wrapping_sub(1))
+                    .cloned()
+                    .unwrap_or_else(|| format!("test binary {}", block));
+                let mut path = vec![binary];
+                // Doc-tests are named `src/lib.rs - item (line 3)`.
+                if name.contains(" - ") {
+                    path.push(name.clone());
+                } else {
+                    path.extend(name.split("::").map(str::to_string));
+                }
+                TestResult {
+                    path,
+                    outcome,
+                    duration_ms: None,
+                    message: self.messages.remove(&name),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Reads `pytest -v -rA --durations=0` output line by line.
+#[derive(Default)]
+pub struct PytestParser {
+    results: Vec<(String, TestOutcome)>,
+    durations: HashMap<String, f64>,
+    messages: HashMap<String, String>,
+}
+
+fn pytest_path(node_id: &str) -> Vec<String> {
+    node_id.split("::").map(str::to_string).collect()
+}
+
+impl PytestParser {
+    pub fn line(&mut self, line: &str) -> Option<TestResult> {
+        if let Some(captures) = PYTEST_DURATION_LINE.captures(line) {
+            let seconds: f64 = captures[1].parse().unwrap_or(0.0);
+            *self.durations.entry(captures[2].to_string()).or_default() += seconds;
+            return None;
+        }
+        if let Some(captures) = PYTEST_SUMMARY_LINE.captures(line) {
+            let node_id = captures[2].to_string();
+            let message = captures.get(3).map_or("", |m| m.as_str()).to_string();
+            if !self.results.iter().any(|(id, _)| *id == node_id) {
+                // Collection errors have no line of their own.
+                self.results.push((node_id.clone(), TestOutcome::Failed));
+            }
+            self.messages.insert(node_id, message);
+            return None;
+        }
+        let captures = PYTEST_VERBOSE_LINE.captures(line)?;
+        let node_id = captures[1].to_string();
+        let outcome = match &captures[2] {
+            "PASSED" | "XPASS" => TestOutcome::Passed,
+            "FAILED" | "ERROR" => TestOutcome::Failed,
+            _ => TestOutcome::Skipped,
+        };
+        // A test failing in teardown gets a second line; the worst wins.
+        match self.results.iter_mut().find(|(id, _)| *id == node_id) {
+            Some(existing) if outcome == TestOutcome::Failed => existing.1 = outcome,
+            Some(_) => return None,
+            None => self.results.push((node_id.clone(), outcome)),
+        }
+        Some(TestResult {
+            path: pytest_path(&node_id),
+            outcome,
+            duration_ms: None,
+            message: None,
+        })
+    }
+
+    pub fn finish(mut self) -> Vec<TestResult> {
+        self.results
+            .into_iter()
+            .map(|(node_id, outcome)| TestResult {
+                path: pytest_path(&node_id),
+                outcome,
+                duration_ms: self
+                    .durations
+                    .get(&node_id)
+                    .map(|s| (s * 1000.0).round() as u64),
+                message: self.messages.remove(&node_id).filter(|m| !m.is_empty()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::lock_ext::LockExt;
+    use crate::commands::trust::TrustLevel;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_jest_report_to_tree() {
+        let report = r#"{"testResults": [
+            {"name": "/p/src/a.test.ts", "status": "failed", "assertionResults": [
+                {"ancestorTitles": ["App"], "title": "renders", "status": "passed", "duration": 12},
+                {"ancestorTitles": ["App"], "title": "clicks", "status": "failed", "duration": 3,
+                 "failureMessages": ["Expected 1"]},
+                {"ancestorTitles": [], "title": "later", "status": "todo", "duration": null}
+            ]},
+            {"name": "/p/src/b.test.ts", "status": "failed", "message": "SyntaxError",
+             "assertionResults": []}
+        ]}"#;
+        let results = parse_jest_report(report, Path::new("/p")).unwrap();
+        let summary = TestSummary::from_results(&results);
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (1, 2, 1));
+        let file = &summary.tree[0];
+        assert_eq!(file.name, "src/a.test.ts");
+        assert_eq!(file.outcome, TestOutcome::Failed);
+        assert_eq!(file.duration_ms, Some(15));
+        let app = &file.children[0];
+        assert_eq!(app.children[1].message.as_deref(), Some("Expected 1"));
+        assert_eq!(summary.tree[1].message.as_deref(), Some("SyntaxError"));
+    }
+
+    #[test]
+    fn test_cargo_output() {
+        let mut parser = CargoTestParser::default();
+        parser.stderr_line("     Running unittests src/lib.rs (target/debug/deps/app-1)");
+        parser.stderr_line("     Running tests/api.rs (target/debug/deps/api-2)");
+        let stdout = "\nrunning 2 tests\ntest a::ok ... ok\ntest a::bad ... FAILED\n\nfailures:\n\n---- a::bad stdout ----\nthread 'a::bad' panicked at src/a.rs:3:5:\nboom\n\n\nfailures:\n    a::bad\n\ntest result: FAILED. 1 passed; 1 failed\n\nrunning 1 test\ntest slow ... ignored, needs network\n";
+        let streamed: Vec<TestResult> = stdout
+            .lines()
+            .filter_map(|line| parser.stdout_line(line))
+            .collect();
+        assert_eq!(streamed.len(), 3);
+        let results = parser.finish();
+        assert_eq!(results[0].path, vec!["src/lib.rs", "a", "ok"]);
+        assert_eq!(
+            results[1].message.as_deref(),
+            Some("thread 'a::bad' panicked at src/a.rs:3:5:\nboom")
+        );
+        assert_eq!(results[2].path, vec!["tests/api.rs", "slow"]);
+        assert_eq!(results[2].outcome, TestOutcome::Skipped);
+    }
+
+    #[test]
+    fn test_pytest_output() {
+        let mut parser = PytestParser::default();
+        let output = "tests/test_a.py::test_one PASSED                [ 33%]\ntests/test_a.py::TestB::test_two[x y] FAILED    [ 66%]\ntests/test_a.py::test_skip SKIPPED (no db)      [100%]\n============ slowest durations ============\n0.50s call     tests/test_a.py::test_one\n0.25s setup    tests/test_a.py::test_one\n=== short test summary info ===\nPASSED tests/test_a.py::test_one\nFAILED tests/test_a.py::TestB::test_two[x y] - assert 1 == 2\nERROR tests/test_c.py - ImportError: no module\n";
+        let streamed = output.lines().filter_map(|l| parser.line(l)).count();
+        assert_eq!(streamed, 3);
+        let results = parser.finish();
+        assert_eq!(results[0].duration_ms, Some(750));
+        assert_eq!(
+            results[1].path,
+            vec!["tests/test_a.py", "TestB", "test_two[x y]"]
+        );
+        assert_eq!(results[1].message.as_deref(), Some("assert 1 == 2"));
+        assert_eq!(results[3].path, vec!["tests/test_c.py"]);
+        assert_eq!(results[3].outcome, TestOutcome::Failed);
+    }
+
+    #[test]
+    fn test_detect_frameworks_and_commands() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(
+            root.join("package.json"),
+            r#"{"devDependencies": {"vitest": "^2"}}"#,
+        )
+        .unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]").unwrap();
+        assert_eq!(
+            detect_frameworks(root),
+            vec![TestFramework::Vitest, TestFramework::Cargo]
+        );
+
+        let report = Path::new("/tmp/report.json");
+        let scope = TestScope {
+            path: Some("tests/api.rs".to_string()),
+            name: Some("login".to_string()),
+        };
+        let trust = TrustStoreState::default();
+        trust.lock_recover().set(root, TrustLevel::Trusted).unwrap();
+        let cargo = test_command(&trust, TestFramework::Cargo, root, &scope, report).unwrap();
+        assert_eq!(
+            cargo.args,
+            vec!["test", "--color", "never", "--test", "api", "--", "login"]
+        );
+        assert!(test_command(&trust, TestFramework::Vitest, root, &scope, report).is_err());
+    }
+
+    #[test]
+    fn test_untrusted_project_runs_no_tests() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Cargo.toml"), "[package]").unwrap();
+        let error = test_command(
+            &TrustStoreState::default(),
+            TestFramework::Cargo,
+            root,
+            &TestScope::default(),
+            Path::new("/tmp/report.json"),
+        )
+        .unwrap_err();
+        assert!(error.contains("not trusted"), "{error}");
+    }
+}
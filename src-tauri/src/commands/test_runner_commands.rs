@@ -0,0 +1,339 @@
+//! Runner and Tauri commands for the test explorer; commands and result
+//! parsing live in test_runner.rs.
+//!
+//! [`run_tests`] starts a job of kind `tests` and returns its id, which
+//! `cancel_job` accepts. While it runs, `test-progress` events carry the
+//! counts so far and the latest output line; when it ends,
+//! `test-run-finished` carries the [`TestRun`] with its result tree.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::error::KiriError;
+use super::event_throttle_commands::emit_throttled;
+use super::fs_path::{normalize_input_path, path_to_wire};
+use super::jobs::{JobContext, JobId};
+use super::jobs_commands::spawn_job;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::notifications::NotificationEvent;
+use super::notifications_commands::notify;
+use super::test_runner::{
+    detect_frameworks, parse_jest_report, test_command, CargoTestParser, PytestParser,
+    TestFramework, TestOutcome, TestProgress, TestResult, TestScope, TestSummary,
+};
+use super::toolchain;
+use super::trust_commands::{require_trusted, trust_store};
+
+pub const TEST_PROGRESS_EVENT: &str = "test-progress";
+pub const TEST_RUN_FINISHED_EVENT: &str = "test-run-finished";
+
+const TEST_JOB_KIND: &str = "tests";
+
+/// Finished runs kept for `get_test_run`.
+const MAX_TEST_RUNS: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TestRun {
+    pub id: JobId,
+    pub framework: TestFramework,
+    pub root: String,
+    pub scope: TestScope,
+    /// `None` if the run was cancelled or its results couldn't be read.
+    pub summary: Option<TestSummary>,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct TestRuns {
+    runs: BTreeMap<JobId, TestRun>,
+}
+
+pub type TestRunsState = Arc<Mutex<TestRuns>>;
+
+impl TestRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(&mut self, run: TestRun) {
+        self.runs.insert(run.id, run);
+        while self.runs.len() > MAX_TEST_RUNS {
+            self.runs.pop_first();
+        }
+    }
+}
+
+enum Line {
+    Stdout(String),
+    Stderr(String),
+}
+
+fn read_lines(
+    stream: impl Read + Send + 'static,
+    tx: mpsc::Sender<Line>,
+    wrap: fn(String) -> Line,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if tx.send(wrap(line)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Parses output as it arrives, for whichever framework is running.
+enum OutputParser {
+    Cargo(CargoTestParser),
+    Pytest(PytestParser),
+    /// jest and vitest results come from their report file instead.
+    Report,
+}
+
+impl OutputParser {
+    fn line(&mut self, line: &Line) -> Option<TestResult> {
+        match (self, line) {
+            (Self::Cargo(parser), Line::Stdout(line)) => parser.stdout_line(line),
+            (Self::Cargo(parser), Line::Stderr(line)) => {
+                parser.stderr_line(line);
+                None
+            }
+            (Self::Pytest(parser), Line::Stdout(line)) => parser.line(line),
+            _ => None,
+        }
+    }
+}
+
+struct Finished {
+    results: Option<Vec<TestResult>>,
+    exit_code: Option<i32>,
+    error: Option<String>,
+}
+
+/// Run the tests and collect their results; `None` results if cancelled.
+fn execute(
+    app: &AppHandle,
+    ctx: &JobContext,
+    framework: TestFramework,
+    root: &Path,
+    scope: &TestScope,
+) -> Result<Finished, String> {
+    let report = std::env::temp_dir().join(format!("kiri-test-report-{}.json", ctx.id()));
+    let spec = test_command(&trust_store(app)?, framework, root, scope, &report)?;
+    let mut command = Command::new(&spec.program);
+    command
+        .args(&spec.args)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .env("CI", "1");
+    for (key, value) in toolchain::resolve(root).env {
+        match value {
+            Some(value) => command.env(key, value),
+            None => command.env_remove(key),
+        };
+    }
+    let mut child = command.spawn().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("{} is not installed", spec.program.display()),
+        _ => format!("Failed to start {}: {}", framework.name(), e),
+    })?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(read_lines(stdout, tx.clone(), Line::Stdout));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(read_lines(stderr, tx.clone(), Line::Stderr));
+    }
+    drop(tx);
+
+    let mut parser = match framework {
+        TestFramework::Cargo => OutputParser::Cargo(CargoTestParser::default()),
+        TestFramework::Pytest => OutputParser::Pytest(PytestParser::default()),
+        TestFramework::Jest | TestFramework::Vitest => OutputParser::Report,
+    };
+    let mut progress = TestProgress {
+        run_id: ctx.id(),
+        passed: 0,
+        failed: 0,
+        skipped: 0,
+        current: None,
+    };
+    let mut cancelled = false;
+    loop {
+        if ctx.is_cancelled() && !cancelled {
+            let _ = child.kill();
+            cancelled = true;
+        }
+        let line = match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(line) => line,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        if let Some(result) = parser.line(&line) {
+            match result.outcome {
+                TestOutcome::Passed => progress.passed += 1,
+                TestOutcome::Failed => progress.failed += 1,
+                TestOutcome::Skipped => progress.skipped += 1,
+            }
+        }
+        let (Line::Stdout(text) | Line::Stderr(text)) = line;
+        if !text.trim().is_empty() {
+            progress.current = Some(text.trim().chars().take(200).collect());
+        }
+        emit_throttled(app, TEST_PROGRESS_EVENT, &ctx.id().to_string(), &progress);
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for {}: {}", framework.name(), e))?;
+
+    let report_text = std::fs::read_to_string(&report);
+    let _ = std::fs::remove_file(&report);
+    if cancelled {
+        return Ok(Finished {
+            results: None,
+            exit_code: status.code(),
+            error: None,
+        });
+    }
+    let (results, error) = match parser {
+        OutputParser::Cargo(parser) => (Some(parser.finish()), None),
+        OutputParser::Pytest(parser) => (Some(parser.finish()), None),
+        OutputParser::Report => match report_text {
+            Ok(text) => match parse_jest_report(&text, root) {
+                Ok(results) => (Some(results), None),
+                Err(e) => (None, Some(e)),
+            },
+            Err(_) => (
+                None,
+                Some(format!(
+                    "{} exited without writing a report; see its output",
+                    framework.name()
+                )),
+            ),
+        },
+    };
+    Ok(Finished {
+        results,
+        exit_code: status.code(),
+        error,
+    })
+}
+
+fn finish_run(app: &AppHandle, run: TestRun) {
+    let _ = app.emit(TEST_RUN_FINISHED_EVENT, &run);
+    if let Some(summary) = run.summary.as_ref().filter(|summary| summary.failed > 0) {
+        let event = NotificationEvent::TestsFailed {
+            framework: run.framework.name().to_string(),
+            dir: run.root.clone(),
+            failed: summary.failed,
+        };
+        notify(app, event);
+    }
+    if let Some(state) = app.try_state::<TestRunsState>() {
+        state.lock_recover().insert(run);
+    }
+}
+
+/// Run `framework`'s tests in `scope` for the project at `path`, or the
+/// first framework the project is set up for. Returns the run id.
+#[tauri::command]
+pub fn run_tests(
+    app: AppHandle,
+    path: String,
+    framework: Option<TestFramework>,
+    scope: Option<TestScope>,
+) -> Result<JobId, KiriError> {
+    let root: PathBuf = normalize_input_path(&path);
+    require_trusted(&app, &root)?;
+    let framework = match framework {
+        Some(framework) => framework,
+        None => *detect_frameworks(&root)
+            .first()
+            .ok_or_else(|| format!("No test framework found in {}", path))?,
+    };
+    let scope = scope.unwrap_or_default();
+    let label = format!("{} in {}", framework.name(), root.display());
+    let job_app = app.clone();
+    spawn_job(&app, TEST_JOB_KIND, &label, move |ctx| {
+        let mut run = TestRun {
+            id: ctx.id(),
+            framework,
+            root: path_to_wire(&root),
+            scope: scope.clone(),
+            summary: None,
+            exit_code: None,
+            error: None,
+        };
+        let outcome = execute(&job_app, ctx, framework, &root, &scope);
+        let result = match outcome {
+            Ok(finished) => {
+                run.summary = finished.results.as_deref().map(TestSummary::from_results);
+                run.exit_code = finished.exit_code;
+                run.error = finished.error.clone();
+                match (finished.error, &run.summary) {
+                    (Some(error), _) => Err(error),
+                    (None, Some(summary)) if summary.failed > 0 => {
+                        Err(format!("{} tests failed", summary.failed))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Err(error) => {
+                run.error = Some(error.clone());
+                Err(error)
+            }
+        };
+        finish_run(&job_app, run);
+        result
+    })
+    .map_err(KiriError::from)
+}
+
+/// Test frameworks the project at `path` is set up for, most likely first.
+#[tauri::command]
+pub async fn detect_test_frameworks(path: String) -> Result<Vec<TestFramework>, KiriError> {
+    let root = normalize_input_path(&path);
+    run_blocking("detect_test_frameworks", move || {
+        Ok(detect_frameworks(&root))
+    })
+    .await
+}
+
+/// A finished run, for a window that missed its `test-run-finished`.
+#[tauri::command]
+pub fn get_test_run(state: tauri::State<'_, TestRunsState>, run_id: JobId) -> Option<TestRun> {
+    state.lock_recover().runs.get(&run_id).cloned()
+}
+
+/// Finished runs for the project at `path`, or all of them, newest first.
+#[tauri::command]
+pub fn list_test_runs(
+    state: tauri::State<'_, TestRunsState>,
+    path: Option<String>,
+) -> Vec<TestRun> {
+    let root = path.map(|p| normalize_input_path(&p).to_string_lossy().to_string());
+    state
+        .lock_recover()
+        .runs
+        .values()
+        .rev()
+        .filter(|run| root.as_deref().map_or(true, |root| run.root == root))
+        .cloned()
+        .collect()
+}
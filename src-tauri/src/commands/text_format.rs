@@ -0,0 +1,376 @@
+//! Text file convention detection: line endings, UTF-8 BOM, indentation
+//! and final newline, combined with the file's `.editorconfig` settings.
+//!
+//! The editor uses [`TextFormat`] to configure itself when a file is
+//! opened, and `write_file` uses it to write content back without
+//! silently converting a CRLF file to LF or dropping its BOM.
+
+use super::editorconfig::{resolve_editorconfig, EditorConfig};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const UTF8_BOM: &str = "\u{FEFF}";
+
+/// Number of leading lines inspected for indentation. Enough to see a
+/// representative sample without scanning huge generated files.
+const INDENT_SAMPLE_LINES: usize = 2000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// Parse an `.editorconfig` `end_of_line` value.
+    pub fn from_editorconfig(value: &str) -> Option<Self> {
+        match value {
+            "lf" => Some(LineEnding::Lf),
+            "crlf" => Some(LineEnding::Crlf),
+            "cr" => Some(LineEnding::Cr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    Tab,
+    Space,
+}
+
+/// Conventions detected from a file's content plus its `.editorconfig`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TextFormat {
+    /// Dominant line ending, `None` for single-line content.
+    pub line_ending: Option<LineEnding>,
+    /// More than one line ending style appears in the content.
+    pub mixed_line_endings: bool,
+    pub has_bom: bool,
+    pub indent_style: Option<IndentStyle>,
+    /// Columns per indent level for space-indented content.
+    pub indent_width: Option<u8>,
+    pub final_newline: bool,
+    pub editorconfig: EditorConfig,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct LineEndingCounts {
+    lf: usize,
+    crlf: usize,
+    cr: usize,
+}
+
+fn count_line_endings(content: &str) -> LineEndingCounts {
+    let mut counts = LineEndingCounts::default();
+    let bytes = content.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                counts.crlf += 1;
+                i += 1;
+            }
+            b'\r' => counts.cr += 1,
+            b'\n' => counts.lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    counts
+}
+
+/// Return the dominant line ending and whether styles are mixed. Ties
+/// prefer LF, then CRLF.
+pub fn detect_line_ending(content: &str) -> (Option<LineEnding>, bool) {
+    let counts = count_line_endings(content);
+    let styles = [counts.lf, counts.crlf, counts.cr]
+        .iter()
+        .filter(|&&n| n > 0)
+        .count();
+    let dominant = if styles == 0 {
+        None
+    } else if counts.lf >= counts.crlf && counts.lf >= counts.cr {
+        Some(LineEnding::Lf)
+    } else if counts.crlf >= counts.cr {
+        Some(LineEnding::Crlf)
+    } else {
+        Some(LineEnding::Cr)
+    };
+    (dominant, styles > 1)
+}
+
+/// Guess indentation from leading whitespace. Width is the most common
+/// positive difference between consecutive space-indented lines, which
+/// copes with continuation lines and alignment better than a GCD.
+pub fn detect_indentation(content: &str) -> (Option<IndentStyle>, Option<u8>) {
+    let mut tab_lines = 0usize;
+    let mut space_lines = 0usize;
+    let mut deltas = [0usize; 9];
+    let mut previous_spaces = 0usize;
+
+    for line in content.lines().take(INDENT_SAMPLE_LINES) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            previous_spaces = 0;
+            continue;
+        }
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        // A single leading space is usually a block comment continuation
+        // (` * foo`), not indentation.
+        if spaces > 1 {
+            space_lines += 1;
+        }
+        if spaces > previous_spaces {
+            let delta = spaces - previous_spaces;
+            if (2..deltas.len()).contains(&delta) {
+                deltas[delta] += 1;
+            }
+        }
+        previous_spaces = spaces;
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        return (None, None);
+    }
+    if tab_lines > space_lines {
+        return (Some(IndentStyle::Tab), None);
+    }
+    let width = deltas
+        .iter()
+        .enumerate()
+        .filter(|(_, &n)| n > 0)
+        // Prefer the larger width on ties so 4-space files with a few
+        // 2-space alignments still read as 4.
+        .max_by_key(|(w, &n)| (n, *w))
+        .map(|(w, _)| w as u8);
+    (Some(IndentStyle::Space), width)
+}
+
+/// Detect conventions from `content` (as read from disk, BOM included)
+/// and resolve `.editorconfig` settings for `path`.
+pub fn detect_text_format(path: &Path, content: &str) -> TextFormat {
+    let (line_ending, mixed_line_endings) = detect_line_ending(content);
+    let (indent_style, indent_width) = detect_indentation(content);
+    TextFormat {
+        line_ending,
+        mixed_line_endings,
+        has_bom: content.starts_with(UTF8_BOM),
+        indent_style,
+        indent_width,
+        final_newline: content.ends_with('\n') || content.ends_with('\r'),
+        editorconfig: resolve_editorconfig(path),
+    }
+}
+
+/// Options for `write_file`. Unset fields fall back to the existing
+/// file's conventions, then `.editorconfig`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+pub struct WriteFormat {
+    pub line_ending: Option<LineEnding>,
+    pub bom: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    pub trim_trailing_whitespace: Option<bool>,
+}
+
+impl WriteFormat {
+    /// Fill unset fields: line ending and BOM are preserved from the file
+    /// being overwritten when there is one, so saving never rewrites every
+    /// line; whitespace fixes only come from `.editorconfig`.
+    pub fn resolve(self, existing: Option<&TextFormat>, editorconfig: &EditorConfig) -> Self {
+        let config_eol = editorconfig
+            .end_of_line
+            .as_deref()
+            .and_then(LineEnding::from_editorconfig);
+        let config_bom = editorconfig
+            .charset
+            .as_deref()
+            .map(|charset| charset == "utf-8-bom");
+        Self {
+            line_ending: self
+                .line_ending
+                .or_else(|| existing.and_then(|f| f.line_ending))
+                .or(config_eol),
+            bom: self
+                .bom
+                .or_else(|| existing.map(|f| f.has_bom))
+                .or(config_bom),
+            insert_final_newline: self
+                .insert_final_newline
+                .or(editorconfig.insert_final_newline),
+            trim_trailing_whitespace: self
+                .trim_trailing_whitespace
+                .or(editorconfig.trim_trailing_whitespace),
+        }
+    }
+}
+
+/// Apply a resolved [`WriteFormat`] to editor content.
+pub fn apply_write_format(content: &str, format: &WriteFormat) -> String {
+    let body = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let normalized = body.replace("\r\n", "\n").replace('\r', "\n");
+
+    let mut lines: Vec<&str> = normalized.split('\n').collect();
+    if format.trim_trailing_whitespace == Some(true) {
+        for line in lines.iter_mut() {
+            *line = line.trim_end_matches([' ', '\t']);
+        }
+    }
+    let mut text = lines.join("\n");
+    if format.insert_final_newline == Some(true) && !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+
+    let eol = format.line_ending.unwrap_or(LineEnding::Lf);
+    if eol != LineEnding::Lf {
+        text = text.replace('\n', eol.as_str());
+    }
+    if format.bom == Some(true) {
+        text.insert_str(0, UTF8_BOM);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_line_ending_lf() {
+        assert_eq!(detect_line_ending("a\nb\n"), (Some(LineEnding::Lf), false));
+    }
+
+    #[test]
+    fn test_detect_line_ending_crlf() {
+        assert_eq!(
+            detect_line_ending("a\r\nb\r\n"),
+            (Some(LineEnding::Crlf), false)
+        );
+    }
+
+    #[test]
+    fn test_detect_line_ending_cr_and_none() {
+        assert_eq!(detect_line_ending("a\rb\r"), (Some(LineEnding::Cr), false));
+        assert_eq!(detect_line_ending("single line"), (None, false));
+    }
+
+    #[test]
+    fn test_detect_line_ending_mixed_picks_dominant() {
+        assert_eq!(
+            detect_line_ending("a\r\nb\r\nc\n"),
+            (Some(LineEnding::Crlf), true)
+        );
+    }
+
+    #[test]
+    fn test_detect_indentation_tabs() {
+        let src = "fn a() {\n\tlet x = 1;\n\tif x {\n\t\ty();\n\t}\n}\n";
+        assert_eq!(detect_indentation(src), (Some(IndentStyle::Tab), None));
+    }
+
+    #[test]
+    fn test_detect_indentation_two_spaces() {
+        let src = "a:\n  b:\n    c: 1\n  d: 2\n";
+        assert_eq!(detect_indentation(src), (Some(IndentStyle::Space), Some(2)));
+    }
+
+    #[test]
+    fn test_detect_indentation_four_spaces() {
+        let src = "def f():\n    if x:\n        return 1\n    return 2\n";
+        assert_eq!(detect_indentation(src), (Some(IndentStyle::Space), Some(4)));
+    }
+
+    #[test]
+    fn test_detect_indentation_ignores_block_comment_space() {
+        let src = "/**\n * doc\n */\nfn a() {}\n";
+        assert_eq!(detect_indentation(src), (None, None));
+    }
+
+    #[test]
+    fn test_detect_text_format_bom_and_final_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let format = detect_text_format(&dir.path().join("a.txt"), "\u{FEFF}x\r\ny");
+        assert!(format.has_bom);
+        assert!(!format.final_newline);
+        assert_eq!(format.line_ending, Some(LineEnding::Crlf));
+    }
+
+    #[test]
+    fn test_apply_write_format_converts_to_crlf_and_bom() {
+        let format = WriteFormat {
+            line_ending: Some(LineEnding::Crlf),
+            bom: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(apply_write_format("a\nb\n", &format), "\u{FEFF}a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_apply_write_format_normalizes_mixed_input() {
+        let format = WriteFormat {
+            line_ending: Some(LineEnding::Lf),
+            ..Default::default()
+        };
+        assert_eq!(apply_write_format("a\r\nb\rc\n", &format), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn test_apply_write_format_whitespace_rules() {
+        let format = WriteFormat {
+            insert_final_newline: Some(true),
+            trim_trailing_whitespace: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(apply_write_format("a  \nb\t", &format), "a\nb\n");
+        assert_eq!(apply_write_format("", &format), "");
+    }
+
+    #[test]
+    fn test_resolve_prefers_explicit_then_existing_then_editorconfig() {
+        let config = EditorConfig {
+            end_of_line: Some("lf".to_string()),
+            charset: Some("utf-8-bom".to_string()),
+            ..Default::default()
+        };
+        let existing = TextFormat {
+            line_ending: Some(LineEnding::Crlf),
+            mixed_line_endings: false,
+            has_bom: false,
+            indent_style: None,
+            indent_width: None,
+            final_newline: true,
+            editorconfig: config.clone(),
+        };
+
+        let resolved = WriteFormat::default().resolve(Some(&existing), &config);
+        assert_eq!(resolved.line_ending, Some(LineEnding::Crlf));
+        assert_eq!(resolved.bom, Some(false));
+
+        let resolved = WriteFormat::default().resolve(None, &config);
+        assert_eq!(resolved.line_ending, Some(LineEnding::Lf));
+        assert_eq!(resolved.bom, Some(true));
+
+        let explicit = WriteFormat {
+            line_ending: Some(LineEnding::Cr),
+            ..Default::default()
+        };
+        assert_eq!(
+            explicit.resolve(Some(&existing), &config).line_ending,
+            Some(LineEnding::Cr)
+        );
+    }
+}
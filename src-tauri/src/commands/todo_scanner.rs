@@ -0,0 +1,502 @@
+//! Project-wide TODO / FIXME / HACK comment scanner.
+//!
+//! Walks a project with the same file filters as content search
+//! ([`super::search`]) and extracts comment tags line-by-line. Results are
+//! cached per project together with each file's mtime, so a rescan after
+//! a watcher `fs-changed` event only re-reads files that actually changed
+//! — the walk itself is stat-only.
+//!
+//! Tauri glue (commands, watcher hook, background rescans) lives in
+//! `todo_scanner_commands.rs`.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::fs_path::path_to_wire;
+use super::generated::GeneratedRules;
+use super::search::is_searchable_file;
+
+/// Tags scanned when the caller does not configure any.
+pub const DEFAULT_TODO_TAGS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+/// Ceiling on files examined per project so a mis-rooted scan (e.g. `~`)
+/// stays bounded.
+const MAX_TODO_FILES: usize = 20_000;
+
+/// Same recursion ceiling as file search.
+const MAX_TODO_DEPTH: usize = 32;
+
+/// Payload of `todos-updated`: `project` has a fresh scan.
+#[derive(Debug, Clone, Serialize)]
+pub struct TodosUpdatedEvent {
+    pub project: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TodoItem {
+    /// 1-based line number.
+    pub line: usize,
+    /// 0-based byte column of the tag within the line.
+    pub column: usize,
+    pub tag: String,
+    /// Comment text after the tag, with `:` / `(owner)` and closing
+    /// comment markers stripped.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TodoFile {
+    pub path: String,
+    pub items: Vec<TodoItem>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedFile {
+    mtime: Option<SystemTime>,
+    items: Vec<TodoItem>,
+}
+
+/// Cached scan result for one project root.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectTodos {
+    tags: Vec<String>,
+    dirty: bool,
+    files: HashMap<PathBuf, CachedFile>,
+    /// A scan of the project is running.
+    scanning: bool,
+    /// Paths changed while it runs, which it may have missed.
+    pending: HashSet<PathBuf>,
+}
+
+impl ProjectTodos {
+    /// Files with at least one match, sorted by path.
+    pub fn grouped(&self) -> Vec<TodoFile> {
+        let mut out: Vec<TodoFile> = self
+            .files
+            .iter()
+            .filter(|(_, f)| !f.items.is_empty())
+            .map(|(path, f)| TodoFile {
+                path: path_to_wire(path),
+                items: f.items.clone(),
+            })
+            .collect();
+        out.sort_by(|a, b| a.path.cmp(&b.path));
+        out
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+/// Per-project cache shared by every window.
+#[derive(Default)]
+pub struct TodoScanner {
+    projects: HashMap<String, ProjectTodos>,
+}
+
+impl TodoScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the cached project if it is clean and was scanned with `tags`.
+    pub fn fresh(&self, root: &str, tags: &[String]) -> Option<&ProjectTodos> {
+        self.projects
+            .get(root)
+            .filter(|p| !p.dirty && p.tags == tags)
+    }
+
+    /// A copy of the cached project for a rescan to reuse, so the scan
+    /// can run without holding the lock.
+    pub fn snapshot(&self, root: &str) -> Option<ProjectTodos> {
+        self.projects.get(root).cloned()
+    }
+
+    pub fn store(&mut self, root: &str, todos: ProjectTodos) {
+        self.projects.insert(root.to_string(), todos);
+    }
+
+    /// Mark every cached project that contains `changed_path` (or lives
+    /// beneath it) as needing a rescan, and note the path for the scans
+    /// already running. Returns the affected roots.
+    pub fn mark_dirty(&mut self, changed_path: &str) -> Vec<String> {
+        let changed = Path::new(changed_path);
+        let mut affected = Vec::new();
+        for (root, todos) in self.projects.iter_mut() {
+            let root_path = Path::new(root);
+            if changed.starts_with(root_path) || root_path.starts_with(changed) {
+                todos.dirty = true;
+                if todos.scanning {
+                    todos.pending.insert(changed.to_path_buf());
+                }
+                affected.push(root.clone());
+            }
+        }
+        affected
+    }
+
+    /// Claim the scan of `root`, adding a dirty entry for a project not
+    /// cached yet. Returns `false` if a scan is already running; the
+    /// changes it misses are left for it in the pending set.
+    pub fn begin_scan(&mut self, root: &str) -> bool {
+        let todos = self
+            .projects
+            .entry(root.to_string())
+            .or_insert_with(|| ProjectTodos {
+                dirty: true,
+                ..Default::default()
+            });
+        !std::mem::replace(&mut todos.scanning, true)
+    }
+
+    /// Store the result of the scan claimed by [`Self::begin_scan`].
+    /// Returns `true`, keeping the claim, if paths changed while it ran:
+    /// the caller must scan again.
+    pub fn finish_scan(&mut self, root: &str, todos: ProjectTodos) -> bool {
+        let entry = self.projects.entry(root.to_string()).or_default();
+        let rescan = !entry.pending.is_empty();
+        entry.pending.clear();
+        entry.tags = todos.tags;
+        entry.files = todos.files;
+        entry.dirty = rescan;
+        entry.scanning = rescan;
+        rescan
+    }
+
+    /// Give up the scan claimed by [`Self::begin_scan`] after it failed,
+    /// dropping the project from the cache.
+    pub fn abandon_scan(&mut self, root: &str) {
+        self.projects.remove(root);
+    }
+
+    pub fn tags_for(&self, root: &str) -> Option<Vec<String>> {
+        self.projects.get(root).map(|p| p.tags.clone())
+    }
+}
+
+pub type TodoScannerState = Arc<Mutex<TodoScanner>>;
+
+/// Normalize user-configured tags: trimmed, non-empty, deduplicated, and
+/// falling back to [`DEFAULT_TODO_TAGS`].
+pub fn normalize_tags(tags: Option<Vec<String>>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags.unwrap_or_default() {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !out.contains(&tag) {
+            out.push(tag);
+        }
+    }
+    if out.is_empty() {
+        out = DEFAULT_TODO_TAGS.iter().map(|t| t.to_string()).collect();
+    }
+    out
+}
+
+/// Build the matcher for `tags`. A tag only counts when it follows a
+/// comment marker, so identifiers like `TODO_LIST` or a string literal
+/// `"TODO"` in code are not reported.
+pub fn build_tag_regex(tags: &[String]) -> Result<Regex, String> {
+    let alternatives: Vec<String> = tags.iter().map(|t| regex::escape(t)).collect();
+    let pattern = format!(
+        r"(?://+|#+|/\*+|^\s*\*|<!--|--|;+)\s*(?P<tag>{})\b(?:\([^)]*\))?:?\s*(?P<text>.*)",
+        alternatives.join("|")
+    );
+    Regex::new(&pattern).map_err(|e| format!("Invalid TODO tag pattern: {}", e))
+}
+
+fn clean_text(raw: &str) -> String {
+    raw.trim()
+        .trim_end_matches("-->")
+        .trim_end_matches("*/")
+        .trim()
+        .to_string()
+}
+
+/// Extract tagged comments from a single file's lines.
+pub fn scan_lines<I>(lines: I, matcher: &Regex) -> Vec<TodoItem>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut items = Vec::new();
+    for (idx, line) in lines.into_iter().enumerate() {
+        if let Some(caps) = matcher.captures(&line) {
+            let tag = &caps["tag"];
+            let column = caps.name("tag").map(|m| m.start()).unwrap_or(0);
+            items.push(TodoItem {
+                line: idx + 1,
+                column,
+                tag: tag.to_string(),
+                text: clean_text(caps.name("text").map(|m| m.as_str()).unwrap_or("")),
+            });
+        }
+    }
+    items
+}
+
+fn scan_file(path: &Path, matcher: &Regex) -> Vec<TodoItem> {
+    match fs::File::open(path) {
+        Ok(file) => scan_lines(BufReader::new(file).lines().map_while(Result::ok), matcher),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn collect_candidate_files(
+    dir: &Path,
+    generated: &GeneratedRules,
+    out: &mut Vec<(PathBuf, Option<SystemTime>)>,
+    depth: usize,
+) {
+    if depth > MAX_TODO_DEPTH || out.len() >= MAX_TODO_FILES {
+        return;
+    }
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if out.len() >= MAX_TODO_FILES {
+            break;
+        }
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if generated.is_generated(&path, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            collect_candidate_files(&path, generated, out, depth + 1);
+        } else if file_type.is_file() && is_searchable_file(&path) {
+            let mtime = entry.metadata().ok().and_then(|m| m.modified().ok());
+            out.push((path, mtime));
+        }
+    }
+}
+
+/// Scan `root` for `tags`, reusing per-file results from `previous` when
+/// the file's mtime is unchanged and the tag set is the same.
+pub fn scan_project(
+    root: &Path,
+    tags: Vec<String>,
+    previous: Option<ProjectTodos>,
+) -> Result<ProjectTodos, String> {
+    if !root.is_dir() {
+        return Err("Project path is not a directory".to_string());
+    }
+    let matcher = build_tag_regex(&tags)?;
+    let generated = GeneratedRules::load(root);
+
+    let mut previous_files = previous
+        .filter(|p| p.tags == tags)
+        .map(|p| p.files)
+        .unwrap_or_default();
+
+    let mut candidates = Vec::new();
+    collect_candidate_files(root, &generated, &mut candidates, 0);
+
+    let mut files = HashMap::with_capacity(candidates.len());
+    for (path, mtime) in candidates {
+        let cached = previous_files
+            .remove(&path)
+            .filter(|c| c.mtime.is_some() && c.mtime == mtime);
+        let entry = match cached {
+            Some(c) => c,
+            None => CachedFile {
+                mtime,
+                items: scan_file(&path, &matcher),
+            },
+        };
+        files.insert(path, entry);
+    }
+
+    Ok(ProjectTodos {
+        tags,
+        files,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn default_tags() -> Vec<String> {
+        normalize_tags(None)
+    }
+
+    fn lines(src: &str) -> Vec<String> {
+        src.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_normalize_tags_defaults() {
+        assert_eq!(default_tags(), vec!["TODO", "FIXME", "HACK"]);
+        assert_eq!(
+            normalize_tags(Some(vec![" XXX ".into(), "".into(), "XXX".into()])),
+            vec!["XXX"]
+        );
+    }
+
+    #[test]
+    fn test_scan_lines_comment_styles() {
+        let re = build_tag_regex(&default_tags()).unwrap();
+        let items = scan_lines(
+            lines(
+                "// TODO: first\n# FIXME second\n/* HACK(alice): third */\n<!-- TODO fourth -->\n  * TODO in block",
+            ),
+            &re,
+        );
+        let texts: Vec<_> = items
+            .iter()
+            .map(|i| (i.line, i.tag.as_str(), i.text.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                (1, "TODO", "first"),
+                (2, "FIXME", "second"),
+                (3, "HACK", "third"),
+                (4, "TODO", "fourth"),
+                (5, "TODO", "in block"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_lines_ignores_non_comments() {
+        let re = build_tag_regex(&default_tags()).unwrap();
+        let items = scan_lines(
+            lines("let TODO_LIST = 1;\nconst s = \"TODO later\";\n// TODOS are fine"),
+            &re,
+        );
+        assert!(items.is_empty(), "unexpected: {:?}", items);
+    }
+
+    #[test]
+    fn test_scan_lines_reports_column() {
+        let re = build_tag_regex(&default_tags()).unwrap();
+        let items = scan_lines(lines("let x = 1; // TODO: y"), &re);
+        assert_eq!(items[0].column, 14);
+    }
+
+    #[test]
+    fn test_custom_tags() {
+        let re = build_tag_regex(&["XXX".to_string()]).unwrap();
+        let items = scan_lines(lines("// XXX: custom\n// TODO: not configured"), &re);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].tag, "XXX");
+    }
+
+    #[test]
+    fn test_scan_project_groups_by_file_and_skips_excluded() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::write(
+            dir.path().join("src/a.rs"),
+            "// TODO: a\nfn a() {}\n// FIXME: b\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/b.ts"), "const x = 1;\n").unwrap();
+        fs::write(
+            dir.path().join("node_modules/pkg/i.js"),
+            "// TODO: vendored\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/app.min.js"), "// TODO: minified\n").unwrap();
+
+        let todos = scan_project(dir.path(), default_tags(), None).unwrap();
+        let grouped = todos.grouped();
+        assert_eq!(grouped.len(), 1);
+        assert!(grouped[0].path.ends_with("a.rs"));
+        assert_eq!(grouped[0].items.len(), 2);
+    }
+
+    #[test]
+    fn test_scan_project_reuses_unchanged_files() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.rs");
+        fs::write(&file, "// TODO: original\n").unwrap();
+        let first = scan_project(dir.path(), default_tags(), None).unwrap();
+
+        // Tamper with the cached result; an unchanged mtime must reuse it.
+        let mut tampered = first.clone();
+        tampered.files.get_mut(&file).unwrap().items[0].text = "cached".into();
+        let second = scan_project(dir.path(), default_tags(), Some(tampered)).unwrap();
+        assert_eq!(second.grouped()[0].items[0].text, "cached");
+    }
+
+    #[test]
+    fn test_scan_project_rescans_when_tags_change() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "// NOTE: x\n").unwrap();
+        let first = scan_project(dir.path(), default_tags(), None).unwrap();
+        assert!(first.grouped().is_empty());
+        let second = scan_project(dir.path(), vec!["NOTE".into()], Some(first)).unwrap();
+        assert_eq!(second.grouped().len(), 1);
+    }
+
+    #[test]
+    fn test_scanner_mark_dirty_and_fresh() {
+        let mut scanner = TodoScanner::new();
+        scanner.store(
+            "/repo",
+            ProjectTodos {
+                tags: default_tags(),
+                ..Default::default()
+            },
+        );
+        assert!(scanner.fresh("/repo", &default_tags()).is_some());
+
+        assert_eq!(
+            scanner.mark_dirty("/repo/src/main.rs"),
+            vec!["/repo".to_string()]
+        );
+        assert!(scanner.fresh("/repo", &default_tags()).is_none());
+        assert!(scanner.mark_dirty("/elsewhere").is_empty());
+    }
+
+    #[test]
+    fn test_scanner_scan_claim() {
+        let mut scanner = TodoScanner::new();
+        assert!(scanner.begin_scan("/repo"));
+        assert!(!scanner.begin_scan("/repo"));
+        // The entry stays cached, dirty, while the scan runs.
+        assert!(scanner.snapshot("/repo").unwrap().is_dirty());
+        assert!(!scanner.finish_scan("/repo", ProjectTodos::default()));
+        assert!(scanner.begin_scan("/repo"));
+        scanner.abandon_scan("/repo");
+        assert!(scanner.snapshot("/repo").is_none());
+    }
+
+    #[test]
+    fn test_scanner_rescans_changes_made_during_a_scan() {
+        let mut scanner = TodoScanner::new();
+        assert!(scanner.begin_scan("/repo"));
+        assert_eq!(
+            scanner.mark_dirty("/repo/src/main.rs"),
+            vec!["/repo".to_string()]
+        );
+        let todos = ProjectTodos {
+            tags: default_tags(),
+            ..Default::default()
+        };
+        assert!(scanner.finish_scan("/repo", todos.clone()));
+        assert!(scanner.fresh("/repo", &default_tags()).is_none());
+        assert!(!scanner.begin_scan("/repo"), "the claim is kept");
+
+        assert!(!scanner.finish_scan("/repo", todos));
+        assert!(scanner.fresh("/repo", &default_tags()).is_some());
+    }
+}
@@ -0,0 +1,106 @@
+//! Tauri command wrappers and watcher glue for the TODO scanner.
+//! The scanning and caching logic lives in todo_scanner.rs.
+
+use super::error::KiriError;
+use super::event_throttle_commands::emit_throttled;
+use super::fs_path::normalize_input_path;
+use super::lock_ext::LockExt;
+use super::performance::CommandTimer;
+use super::todo_scanner::{
+    normalize_tags, scan_project, TodoFile, TodoScannerState, TodosUpdatedEvent,
+};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+/// Scan (or return the cached scan of) `project` for tagged comments,
+/// grouped by file.
+///
+/// `tags` defaults to `TODO`, `FIXME`, `HACK`. The scan runs on a
+/// blocking thread without holding the cache lock, so concurrent callers
+/// for other projects are not serialized behind it. While another scan of
+/// the project runs, this one only reports and leaves storing to it; if
+/// files change during this scan, a background rescan picks them up.
+#[tauri::command]
+pub async fn get_todos(
+    app: AppHandle,
+    state: tauri::State<'_, TodoScannerState>,
+    project: String,
+    tags: Option<Vec<String>>,
+) -> Result<Vec<TodoFile>, KiriError> {
+    let _timer = CommandTimer::start("get_todos");
+    let tags = normalize_tags(tags);
+    if let Some(cached) = state.lock_recover().fresh(&project, &tags) {
+        return Ok(cached.grouped());
+    }
+
+    let state = Arc::clone(&state);
+    tokio::task::spawn_blocking(move || {
+        let (claimed, previous) = {
+            let mut scanner = state.lock_recover();
+            (scanner.begin_scan(&project), scanner.snapshot(&project))
+        };
+        let scanned = scan_project(&normalize_input_path(&project), tags, previous);
+        if !claimed {
+            return Ok(scanned?.grouped());
+        }
+        let todos = scanned.inspect_err(|_| state.lock_recover().abandon_scan(&project))?;
+        let grouped = todos.grouped();
+        if state.lock_recover().finish_scan(&project, todos) {
+            spawn_rescan(&app, state, project);
+        }
+        Ok(grouped)
+    })
+    .await
+    .map_err(|e| format!("get_todos task panicked: {}", e))?
+}
+
+/// Called from the file watcher when a project's files change. Marks the
+/// cached scan dirty and refreshes it in the background. Projects that
+/// were never scanned are left alone, and a project already being scanned
+/// is rescanned once that scan finishes.
+pub fn on_project_files_changed(app: &AppHandle, changed_path: &str) {
+    let Some(state) = app.try_state::<TodoScannerState>() else {
+        return;
+    };
+    let state: TodoScannerState = Arc::clone(&state);
+
+    let roots = state.lock_recover().mark_dirty(changed_path);
+    for root in roots {
+        if state.lock_recover().begin_scan(&root) {
+            spawn_rescan(app, Arc::clone(&state), root);
+        }
+    }
+}
+
+/// Rescan `root`, whose scan the caller has claimed, until no file changes
+/// while a scan runs, then emit `todos-updated`.
+fn spawn_rescan(app: &AppHandle, state: TodoScannerState, root: String) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || loop {
+        let (previous, tags) = {
+            let scanner = state.lock_recover();
+            (scanner.snapshot(&root), scanner.tags_for(&root))
+        };
+        let tags = normalize_tags(tags);
+        match scan_project(&normalize_input_path(&root), tags, previous) {
+            Ok(todos) => {
+                if state.lock_recover().finish_scan(&root, todos) {
+                    continue;
+                }
+                emit_throttled(
+                    &app,
+                    "todos-updated",
+                    &root,
+                    &TodosUpdatedEvent {
+                        project: root.clone(),
+                    },
+                );
+            }
+            Err(e) => {
+                log::warn!("background TODO rescan failed: {}", e);
+                state.lock_recover().abandon_scan(&root);
+            }
+        }
+        break;
+    });
+}
@@ -0,0 +1,351 @@
+//! mise / asdf runtime versions for terminals.
+//!
+//! A worktree pins runtimes in `.tool-versions` (asdf's file, which mise
+//! also reads) or `mise.toml`, in its own directory or a parent up to the
+//! checkout root. With mise installed, `mise ls --current --json` lists the
+//! pinned versions and `mise env --json` gives the environment that
+//! activates them. Without it, versions from `.tool-versions` are looked up
+//! in asdf's installs and their `bin` directories put first on `PATH`.
+//!
+//! Problems are reported as [`ToolchainWarning`]s rather than errors: the
+//! terminal opens with whatever could be activated, and the frontend shows
+//! what is missing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::fs_path::path_to_wire;
+use super::terminal::EnvOverrides;
+
+pub const TOOL_VERSIONS: &str = ".tool-versions";
+/// mise config files, which only mise reads.
+pub const MISE_CONFIGS: [&str; 2] = [".mise.toml", "mise.toml"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Manager {
+    Mise,
+    Asdf,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolRequest {
+    pub tool: String,
+    /// As pinned, e.g. `20` or `3.12.1`
+    pub version: String,
+    pub installed: bool,
+    /// File that pins it
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolchainWarning {
+    /// A pinned version isn't installed.
+    Missing {
+        tool: String,
+        version: String,
+        source: Option<String>,
+    },
+    /// Versions are pinned but no manager that reads `files` is installed.
+    NoManager { files: Vec<String> },
+    /// The manager failed, e.g. mise refusing an untrusted config.
+    Failed { message: String },
+}
+
+/// What activating the runtimes for a directory came to.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Toolchain {
+    pub manager: Option<Manager>,
+    pub files: Vec<String>,
+    pub tools: Vec<ToolRequest>,
+    pub warnings: Vec<ToolchainWarning>,
+    /// Environment for a terminal in the directory
+    #[serde(skip)]
+    pub env: EnvOverrides,
+}
+
+/// Version files for `dir`, nearest first, from `dir` up to the checkout
+/// root (the directory with `.git`).
+pub fn find_version_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in dir.ancestors() {
+        for name in MISE_CONFIGS.iter().chain([&TOOL_VERSIONS]) {
+            let file = dir.join(name);
+            if file.is_file() {
+                files.push(file);
+            }
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    files
+}
+
+/// `tool version` lines of a `.tool-versions` file. Later versions on a
+/// line are fallbacks; only the first is used.
+pub fn parse_tool_versions(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?;
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?.to_string(), fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+fn program_installed(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+fn run_mise(dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("mise")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run mise: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "mise {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[derive(Deserialize)]
+struct MiseSource {
+    path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MiseVersion {
+    version: String,
+    requested_version: Option<String>,
+    #[serde(default)]
+    installed: bool,
+    source: Option<MiseSource>,
+}
+
+/// Tools of `mise ls --current --json`: tool name to its current versions.
+fn parse_mise_ls(output: &[u8]) -> Result<Vec<ToolRequest>, String> {
+    let tools: BTreeMap<String, Vec<MiseVersion>> = serde_json::from_slice(output)
+        .map_err(|e| format!("Failed to parse mise ls output: {}", e))?;
+    Ok(tools
+        .into_iter()
+        .flat_map(|(tool, versions)| {
+            versions.into_iter().map(move |v| ToolRequest {
+                tool: tool.clone(),
+                version: v.requested_version.unwrap_or(v.version),
+                installed: v.installed,
+                source: v.source.and_then(|s| s.path),
+            })
+        })
+        .collect())
+}
+
+fn resolve_with_mise(dir: &Path, toolchain: &mut Toolchain) -> Result<(), String> {
+    toolchain.tools = parse_mise_ls(&run_mise(dir, &["ls", "--current", "--json"])?)?;
+    let env: BTreeMap<String, String> = serde_json::from_slice(&run_mise(dir, &["env", "--json"])?)
+        .map_err(|e| format!("Failed to parse mise env output: {}", e))?;
+    toolchain.env = env.into_iter().map(|(k, v)| (k, Some(v))).collect();
+    Ok(())
+}
+
+/// asdf's installs directory: `$ASDF_DATA_DIR/installs`, by default
+/// `~/.asdf/installs`.
+pub fn asdf_installs_dir() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("ASDF_DATA_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".asdf")))?;
+    Some(data_dir.join("installs"))
+}
+
+/// Install directory of an asdf version: `path:<dir>` as given, others
+/// under `installs`. `None` for `system`, which needs nothing activated.
+fn asdf_install_dir(installs: &Path, tool: &str, version: &str) -> Option<PathBuf> {
+    if version == "system" {
+        return None;
+    }
+    match version.strip_prefix("path:") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(installs.join(tool).join(version.replace(':', "-"))),
+    }
+}
+
+/// Activate the versions of `.tool-versions` files from asdf's
+/// `installs`, nearest file first.
+fn resolve_with_asdf(installs: &Path, files: &[PathBuf], toolchain: &mut Toolchain) {
+    let mut bins = Vec::new();
+    for file in files.iter().filter(|f| f.ends_with(TOOL_VERSIONS)) {
+        let Ok(text) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        for (tool, version) in parse_tool_versions(&text) {
+            if toolchain.tools.iter().any(|t| t.tool == tool) {
+                continue;
+            }
+            let install = asdf_install_dir(installs, &tool, &version);
+            let installed = install.as_ref().map_or(true, |dir| dir.is_dir());
+            if let Some(dir) = install.filter(|_| installed) {
+                bins.push(dir.join("bin"));
+            }
+            toolchain.tools.push(ToolRequest {
+                tool,
+                version,
+                installed,
+                source: Some(path_to_wire(file)),
+            });
+        }
+    }
+    if bins.is_empty() {
+        return;
+    }
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    match std::env::join_paths(bins.into_iter().chain(std::env::split_paths(&path))) {
+        Ok(path) => {
+            let path = path.to_string_lossy().to_string();
+            toolchain.env.insert("PATH".to_string(), Some(path));
+        }
+        Err(e) => toolchain.warnings.push(ToolchainWarning::Failed {
+            message: format!("Failed to build PATH: {}", e),
+        }),
+    }
+}
+
+/// Runtimes pinned for `dir`, the environment activating them and
+/// warnings about what couldn't be.
+pub fn resolve(dir: &Path) -> Toolchain {
+    let files = find_version_files(dir);
+    let mut toolchain = Toolchain {
+        files: files.iter().map(|f| path_to_wire(f)).collect(),
+        ..Toolchain::default()
+    };
+    if files.is_empty() {
+        return toolchain;
+    }
+    if program_installed("mise") {
+        toolchain.manager = Some(Manager::Mise);
+        if let Err(message) = resolve_with_mise(dir, &mut toolchain) {
+            toolchain
+                .warnings
+                .push(ToolchainWarning::Failed { message });
+        }
+    } else if let Some(installs) = asdf_installs_dir().filter(|d| d.is_dir()) {
+        toolchain.manager = Some(Manager::Asdf);
+        resolve_with_asdf(&installs, &files, &mut toolchain);
+        let mise_only: Vec<String> = files
+            .iter()
+            .filter(|f| !f.ends_with(TOOL_VERSIONS))
+            .map(|f| path_to_wire(f))
+            .collect();
+        if !mise_only.is_empty() {
+            toolchain
+                .warnings
+                .push(ToolchainWarning::NoManager { files: mise_only });
+        }
+    } else {
+        toolchain.warnings.push(ToolchainWarning::NoManager {
+            files: toolchain.files.clone(),
+        });
+    }
+    let missing = toolchain.tools.iter().filter(|t| !t.installed);
+    let missing: Vec<ToolchainWarning> = missing
+        .map(|t| ToolchainWarning::Missing {
+            tool: t.tool.clone(),
+            version: t.version.clone(),
+            source: t.source.clone(),
+        })
+        .collect();
+    toolchain.warnings.extend(missing);
+    toolchain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_tool_versions() {
+        let text = "# runtimes\nnodejs 20.11.0 18.19.0\npython 3.12.1 # latest\n\nruby\n";
+        assert_eq!(
+            parse_tool_versions(text),
+            vec![
+                ("nodejs".to_string(), "20.11.0".to_string()),
+                ("python".to_string(), "3.12.1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_version_files_nearest_first() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let app = repo.join("app");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::create_dir(repo.join(".git")).unwrap();
+        std::fs::write(dir.path().join(TOOL_VERSIONS), "nodejs 18.0.0").unwrap();
+        std::fs::write(repo.join(TOOL_VERSIONS), "nodejs 20.0.0").unwrap();
+        std::fs::write(app.join("mise.toml"), "[tools]\nnode = \"22\"").unwrap();
+        assert_eq!(
+            find_version_files(&app),
+            vec![app.join("mise.toml"), repo.join(TOOL_VERSIONS)]
+        );
+    }
+
+    #[test]
+    fn test_resolve_with_asdf() {
+        let dir = TempDir::new().unwrap();
+        let installs = dir.path().join("installs");
+        std::fs::create_dir_all(installs.join("nodejs/20.11.0/bin")).unwrap();
+        let app = dir.path().join("app");
+        std::fs::create_dir(&app).unwrap();
+        std::fs::write(app.join(TOOL_VERSIONS), "nodejs 20.11.0\npython 3.12.1\n").unwrap();
+        std::fs::write(
+            dir.path().join(TOOL_VERSIONS),
+            "nodejs 18.0.0\nruby system\n",
+        )
+        .unwrap();
+
+        let mut toolchain = Toolchain::default();
+        let files = vec![app.join(TOOL_VERSIONS), dir.path().join(TOOL_VERSIONS)];
+        resolve_with_asdf(&installs, &files, &mut toolchain);
+        let installed: Vec<_> = toolchain
+            .tools
+            .iter()
+            .map(|t| (t.tool.as_str(), t.version.as_str(), t.installed))
+            .collect();
+        assert_eq!(
+            installed,
+            vec![
+                ("nodejs", "20.11.0", true),
+                ("python", "3.12.1", false),
+                ("ruby", "system", true),
+            ]
+        );
+        let path = toolchain.env["PATH"].clone().unwrap();
+        let bin = installs.join("nodejs/20.11.0/bin");
+        assert!(path.starts_with(&*bin.to_string_lossy()));
+    }
+
+    #[test]
+    fn test_parse_mise_ls() {
+        let output = br#"{
+            "node": [{"version": "20.11.0", "requested_version": "20", "installed": true,
+                      "source": {"type": "mise.toml", "path": "/repo/mise.toml"}}],
+            "python": [{"version": "3.12.1", "installed": false}]
+        }"#;
+        let tools = parse_mise_ls(output).unwrap();
+        assert_eq!(tools[0].version, "20");
+        assert_eq!(tools[0].source.as_deref(), Some("/repo/mise.toml"));
+        assert!(!tools[1].installed);
+    }
+}
@@ -0,0 +1,14 @@
+//! Tauri commands for pinned runtime versions; see toolchain.rs.
+
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+use super::toolchain::{resolve, Toolchain};
+
+/// Runtimes pinned for `path` by `.tool-versions` or `mise.toml`, whether
+/// they are installed, and warnings about what a terminal there would miss.
+#[tauri::command]
+pub async fn get_toolchain_status(path: String) -> Result<Toolchain, KiriError> {
+    let dir = normalize_input_path(&path);
+    run_blocking("get_toolchain_status", move || Ok(resolve(&dir))).await
+}
@@ -0,0 +1,338 @@
+//! On-demand span recording for end-to-end profiling.
+//!
+//! git, search, worktree and fs code opens `tracing` spans. Nothing is kept
+//! while no recording is running, so the spans cost one atomic load each.
+//! [`TraceRecorder::start`] begins collecting every span that closes, and
+//! [`chrome_trace_json`] turns the result into the Trace Event Format read
+//! by chrome://tracing, Perfetto and speedscope (which also renders it as a
+//! flamegraph). The export command lives in trace_commands.rs.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::file_io::write_file_contents_atomic;
+use super::lock_ext::LockExt;
+
+/// Longest recording `export_trace` accepts.
+pub const MAX_TRACE_DURATION: Duration = Duration::from_secs(300);
+
+/// Spans beyond this are counted but not kept, bounding memory when a
+/// recording covers a very busy stretch.
+const MAX_TRACE_EVENTS: usize = 200_000;
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Small stable per-thread id; `ThreadId::as_u64` is unstable.
+    static THREAD_ID: u64 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+/// One closed span as a Trace Event Format "complete" event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEvent {
+    pub name: String,
+    /// Module path the span was opened in.
+    pub cat: String,
+    pub ph: &'static str,
+    /// Start, in microseconds since the recording began.
+    pub ts: u64,
+    /// Duration in microseconds.
+    pub dur: u64,
+    pub pid: u32,
+    pub tid: u64,
+    pub args: BTreeMap<String, String>,
+}
+
+/// Result of a finished recording.
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+    /// Spans that closed after [`MAX_TRACE_EVENTS`] was reached.
+    pub dropped: usize,
+}
+
+struct Recording {
+    start: Instant,
+    trace: Trace,
+}
+
+#[derive(Default)]
+struct RecorderInner {
+    active: AtomicBool,
+    recording: Mutex<Option<Recording>>,
+}
+
+/// Collects spans from a [`ChromeTraceLayer`] while a recording runs.
+#[derive(Clone, Default)]
+pub struct TraceRecorder {
+    inner: Arc<RecorderInner>,
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.inner.active.load(Ordering::Acquire)
+    }
+
+    /// Begin a recording. Only one can run at a time.
+    pub fn start(&self) -> Result<(), String> {
+        let mut recording = self.inner.recording.lock_recover();
+        if recording.is_some() {
+            return Err("A trace is already being recorded".to_string());
+        }
+        *recording = Some(Recording {
+            start: Instant::now(),
+            trace: Trace::default(),
+        });
+        self.inner.active.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// End the recording and return what it collected. Spans still open
+    /// at this point are not included.
+    pub fn stop(&self) -> Trace {
+        self.inner.active.store(false, Ordering::Release);
+        self.inner
+            .recording
+            .lock_recover()
+            .take()
+            .map(|r| r.trace)
+            .unwrap_or_default()
+    }
+
+    pub fn layer(&self) -> ChromeTraceLayer {
+        ChromeTraceLayer {
+            recorder: self.clone(),
+        }
+    }
+
+    fn push(&self, name: &str, target: &str, timing: SpanTiming, end: Instant) {
+        let mut guard = self.inner.recording.lock_recover();
+        let Some(recording) = guard.as_mut() else {
+            return;
+        };
+        if recording.trace.events.len() >= MAX_TRACE_EVENTS {
+            recording.trace.dropped += 1;
+            return;
+        }
+        let ts = timing.start.saturating_duration_since(recording.start);
+        recording.trace.events.push(TraceEvent {
+            name: name.to_string(),
+            cat: target.to_string(),
+            ph: "X",
+            ts: ts.as_micros() as u64,
+            dur: end.saturating_duration_since(timing.start).as_micros() as u64,
+            pid: std::process::id(),
+            tid: timing.tid,
+            args: timing.args,
+        });
+    }
+}
+
+/// Per-span state kept in the registry's span extensions.
+struct SpanTiming {
+    start: Instant,
+    tid: u64,
+    args: BTreeMap<String, String>,
+}
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+/// `tracing` layer feeding a [`TraceRecorder`].
+pub struct ChromeTraceLayer {
+    recorder: TraceRecorder,
+}
+
+impl<S> Layer<S> for ChromeTraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !self.recorder.is_recording() {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut args = BTreeMap::new();
+        attrs.record(&mut FieldVisitor(&mut args));
+        span.extensions_mut().insert(SpanTiming {
+            start: Instant::now(),
+            tid: THREAD_ID.with(|id| *id),
+            args,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<SpanTiming>() {
+            values.record(&mut FieldVisitor(&mut timing.args));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let end = Instant::now();
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+        self.recorder
+            .push(span.name(), span.metadata().target(), timing, end);
+    }
+}
+
+/// The recorder behind the process-wide subscriber.
+pub fn global_recorder() -> &'static TraceRecorder {
+    static RECORDER: OnceLock<TraceRecorder> = OnceLock::new();
+    RECORDER.get_or_init(TraceRecorder::new)
+}
+
+/// Install the subscriber that feeds [`global_recorder`]. Logging goes
+/// through the `log` crate, so this is the only `tracing` subscriber.
+pub fn install_trace_subscriber() {
+    let subscriber = tracing_subscriber::registry().with(global_recorder().layer());
+    if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
+        log::warn!("failed to install trace subscriber: {}", e);
+    }
+}
+
+/// Trace Event Format document for `trace`.
+pub fn chrome_trace_json(trace: &Trace) -> serde_json::Value {
+    serde_json::json!({
+        "traceEvents": trace.events,
+        "displayTimeUnit": "ms",
+        "otherData": {
+            "droppedEvents": trace.dropped,
+        },
+    })
+}
+
+/// `~/.kiri/traces/kiri-trace-<unix ms>.json` — default export location.
+pub fn default_trace_path(unix_ms: u128) -> Option<PathBuf> {
+    dirs::home_dir().map(|h| {
+        h.join(".kiri")
+            .join("traces")
+            .join(format!("kiri-trace-{}.json", unix_ms))
+    })
+}
+
+pub fn write_trace_file(path: &Path, trace: &Trace) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create trace directory: {}", e))?;
+    }
+    let json = serde_json::to_vec(&chrome_trace_json(trace))
+        .map_err(|e| format!("Failed to serialize trace: {}", e))?;
+    write_file_contents_atomic(path, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn with_recorder<T>(recorder: &TraceRecorder, f: impl FnOnce() -> T) -> T {
+        let subscriber = tracing_subscriber::registry().with(recorder.layer());
+        tracing::subscriber::with_default(subscriber, f)
+    }
+
+    #[test]
+    fn test_records_nested_spans_with_fields() {
+        let recorder = TraceRecorder::new();
+        recorder.start().unwrap();
+        with_recorder(&recorder, || {
+            let outer = tracing::info_span!("worktree::create", branch = "feature");
+            let _outer = outer.enter();
+            let inner = tracing::info_span!("worktree::git_add", status = tracing::field::Empty);
+            inner.record("status", 0);
+            drop(inner);
+        });
+        let trace = recorder.stop();
+
+        assert_eq!(trace.events.len(), 2);
+        // Children close first.
+        let inner = &trace.events[0];
+        let outer = &trace.events[1];
+        assert_eq!(inner.name, "worktree::git_add");
+        assert_eq!(inner.args.get("status").map(String::as_str), Some("0"));
+        assert_eq!(outer.name, "worktree::create");
+        assert_eq!(
+            outer.args.get("branch").map(String::as_str),
+            Some("feature")
+        );
+        assert_eq!(outer.ph, "X");
+        assert!(outer.ts <= inner.ts);
+        assert!(outer.ts + outer.dur >= inner.ts + inner.dur);
+        assert_eq!(outer.tid, inner.tid);
+    }
+
+    #[test]
+    fn test_ignores_spans_outside_recording() {
+        let recorder = TraceRecorder::new();
+        with_recorder(&recorder, || {
+            let _span = tracing::info_span!("before").entered();
+        });
+        recorder.start().unwrap();
+        let trace = recorder.stop();
+        assert!(trace.events.is_empty());
+        assert!(!recorder.is_recording());
+    }
+
+    #[test]
+    fn test_only_one_recording_at_a_time() {
+        let recorder = TraceRecorder::new();
+        recorder.start().unwrap();
+        assert!(recorder.start().is_err());
+        recorder.stop();
+        assert!(recorder.start().is_ok());
+    }
+
+    #[test]
+    fn test_write_trace_file_is_chrome_format() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nested").join("trace.json");
+        let recorder = TraceRecorder::new();
+        recorder.start().unwrap();
+        with_recorder(&recorder, || {
+            let _span = tracing::info_span!("git::status").entered();
+        });
+        write_trace_file(&path, &recorder.stop()).unwrap();
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let events = parsed["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["name"], "git::status");
+        assert_eq!(events[0]["ph"], "X");
+        assert!(events[0]["dur"].is_u64());
+        assert_eq!(parsed["otherData"]["droppedEvents"], 0);
+    }
+}
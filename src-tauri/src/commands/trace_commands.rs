@@ -0,0 +1,37 @@
+//! Tauri command for recording a performance trace. Span collection lives
+//! in trace.rs.
+
+use super::clock::now_ms;
+use super::error::KiriError;
+use super::fs_path::{display_path, normalize_input_path};
+use super::trace::{default_trace_path, global_recorder, write_trace_file, MAX_TRACE_DURATION};
+use std::time::Duration;
+
+/// Record every span for `duration_ms` (capped at five minutes) and write
+/// a chrome://tracing file to `path`, or to `~/.kiri/traces/` when omitted.
+/// Returns the written path. Start it, then reproduce the slow operation,
+/// e.g. creating a worktree.
+#[tauri::command]
+pub async fn export_trace(duration_ms: u64, path: Option<String>) -> Result<String, KiriError> {
+    let path = match path {
+        Some(p) => normalize_input_path(&p),
+        None => default_trace_path(now_ms().into()).ok_or("Could not determine home directory")?,
+    };
+
+    let recorder = global_recorder();
+    recorder.start()?;
+    tokio::time::sleep(Duration::from_millis(duration_ms).min(MAX_TRACE_DURATION)).await;
+    let trace = recorder.stop();
+    log::info!(
+        "recorded trace: {} span(s), {} dropped",
+        trace.events.len(),
+        trace.dropped
+    );
+
+    tokio::task::spawn_blocking(move || {
+        write_trace_file(&path, &trace).map(|_| display_path(&path))
+    })
+    .await
+    .map_err(|e| format!("export_trace task panicked: {}", e))?
+    .map_err(KiriError::from)
+}
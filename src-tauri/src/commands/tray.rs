@@ -0,0 +1,94 @@
+//! System tray model: the setting that toggles the icon, the quick-action
+//! item ids and the status line. The Tauri tray itself is built in
+//! tray_commands.rs.
+//!
+//! Tray menu events reach the app-wide `on_menu_event` handler in
+//! menu.rs too, so every tray id carries a `tray_` prefix that the main
+//! menu ignores.
+
+use std::sync::{Arc, Mutex};
+
+/// Identifier of the single tray icon, used to look it up or remove it.
+pub const TRAY_ID: &str = "kiri-tray";
+
+#[derive(Debug, Default)]
+pub struct TraySettings {
+    pub enabled: bool,
+}
+
+pub type TrayState = Arc<Mutex<TraySettings>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    Recent(usize),
+    NewWindow,
+    NewWorktree,
+    Show,
+    Quit,
+}
+
+impl TrayAction {
+    pub fn id(&self) -> String {
+        match self {
+            TrayAction::Recent(i) => format!("tray_recent_{}", i),
+            TrayAction::NewWindow => "tray_new_window".to_string(),
+            TrayAction::NewWorktree => "tray_new_worktree".to_string(),
+            TrayAction::Show => "tray_show".to_string(),
+            TrayAction::Quit => "tray_quit".to_string(),
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "tray_new_window" => Some(TrayAction::NewWindow),
+            "tray_new_worktree" => Some(TrayAction::NewWorktree),
+            "tray_show" => Some(TrayAction::Show),
+            "tray_quit" => Some(TrayAction::Quit),
+            _ => id
+                .strip_prefix("tray_recent_")
+                .and_then(|i| i.parse().ok())
+                .map(TrayAction::Recent),
+        }
+    }
+}
+
+/// Disabled status line at the top of the tray menu.
+pub fn status_label(terminals: usize) -> String {
+    match terminals {
+        0 => "No terminals running".to_string(),
+        1 => "1 terminal running".to_string(),
+        n => format!("{} terminals running", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_ids_round_trip() {
+        for action in [
+            TrayAction::Recent(3),
+            TrayAction::NewWindow,
+            TrayAction::NewWorktree,
+            TrayAction::Show,
+            TrayAction::Quit,
+        ] {
+            assert_eq!(TrayAction::from_id(&action.id()), Some(action));
+        }
+    }
+
+    #[test]
+    fn test_main_menu_ids_are_not_tray_actions() {
+        assert_eq!(TrayAction::from_id("recent_0"), None);
+        assert_eq!(TrayAction::from_id("new_window"), None);
+        assert_eq!(TrayAction::from_id("tray_recent_x"), None);
+    }
+
+    #[test]
+    fn test_status_label() {
+        assert_eq!(status_label(0), "No terminals running");
+        assert_eq!(status_label(1), "1 terminal running");
+        assert_eq!(status_label(4), "4 terminals running");
+    }
+}
@@ -0,0 +1,213 @@
+//! Optional system tray icon with quick actions: recent projects, the
+//! number of running terminals, new window and new worktree. Ids and the
+//! persisted setting live in tray.rs.
+
+use super::error::KiriError;
+use super::lock_ext::LockExt;
+use super::menu::{MenuModel, MenuModelState};
+use super::settings::SettingsState;
+use super::settings_commands::update_settings_impl;
+use super::single_instance_commands::focus_any_window;
+use super::terminal::TerminalState;
+use super::tray::{status_label, TrayAction, TrayState, TRAY_ID};
+use super::window::{create_window_impl, route_to_project, WindowRegistryState};
+use tauri::{
+    menu::{Menu, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{TrayIconBuilder, TrayIconEvent},
+    App, AppHandle, Emitter, Manager,
+};
+
+fn terminal_count(app: &AppHandle) -> usize {
+    app.try_state::<TerminalState>()
+        .map(|state| state.len())
+        .unwrap_or(0)
+}
+
+fn build_tray_menu(app: &AppHandle, model: &MenuModel) -> Result<Menu<tauri::Wry>, tauri::Error> {
+    let status = MenuItem::with_id(
+        app,
+        "tray_status",
+        status_label(terminal_count(app)),
+        false,
+        None::<&str>,
+    )?;
+
+    let recent = Submenu::with_id(app, "tray_recent", "Open Recent", true)?;
+    let projects = model.recent_projects();
+    if projects.is_empty() {
+        recent.append(&MenuItem::with_id(
+            app,
+            "tray_recent_none",
+            "No Recent Projects",
+            false,
+            None::<&str>,
+        )?)?;
+    }
+    for (i, (name, _path)) in projects.iter().enumerate() {
+        recent.append(&MenuItem::with_id(
+            app,
+            TrayAction::Recent(i).id(),
+            name,
+            true,
+            None::<&str>,
+        )?)?;
+    }
+
+    let item = |action: TrayAction, text: &str| {
+        MenuItem::with_id(app, action.id(), text, true, None::<&str>)
+    };
+    Menu::with_items(
+        app,
+        &[
+            &status,
+            &PredefinedMenuItem::separator(app)?,
+            &recent,
+            &item(TrayAction::NewWindow, "New Window")?,
+            &item(TrayAction::NewWorktree, "New Worktree…")?,
+            &PredefinedMenuItem::separator(app)?,
+            &item(TrayAction::Show, "Show kiri")?,
+            &item(TrayAction::Quit, "Quit kiri")?,
+        ],
+    )
+}
+
+fn handle_tray_action(app: &AppHandle, action: TrayAction) {
+    match action {
+        TrayAction::Recent(index) => {
+            let path = app.try_state::<MenuModelState>().and_then(|model| {
+                model
+                    .lock_recover()
+                    .recent_projects()
+                    .get(index)
+                    .map(|(_, path)| path.clone())
+            });
+            if let Some(path) = path {
+                let registry = app.state::<WindowRegistryState>();
+                if let Err(e) = route_to_project(app, &registry, &path) {
+                    log::error!("failed to open recent project from tray: {e}");
+                }
+            }
+        }
+        TrayAction::NewWindow => {
+            if let Err(e) = create_window_impl(app, None, None, None, None, None, None) {
+                log::error!("failed to create window from tray: {e}");
+            }
+        }
+        TrayAction::NewWorktree => {
+            // Branch name and base come from the frontend's worktree dialog.
+            // Send it to one window only so the dialog does not open in all.
+            let windows = app.webview_windows();
+            let target = windows
+                .values()
+                .find(|w| w.is_focused().unwrap_or(false))
+                .or_else(|| windows.get("main"))
+                .or_else(|| windows.values().next());
+            if let Some(window) = target {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+                let _ = window.emit("tray-new-worktree", ());
+            }
+        }
+        TrayAction::Show => focus_any_window(app),
+        TrayAction::Quit => app.exit(0),
+    }
+}
+
+fn show_tray(app: &AppHandle) -> Result<(), String> {
+    if app.tray_by_id(TRAY_ID).is_some() {
+        return Ok(());
+    }
+    let menu = {
+        let model = app.state::<MenuModelState>();
+        let model = model.lock_recover();
+        build_tray_menu(app, &model).map_err(|e| format!("Failed to build tray menu: {}", e))?
+    };
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("kiri")
+        .menu(&menu)
+        .on_menu_event(|app, event| {
+            if let Some(action) = TrayAction::from_id(event.id().as_ref()) {
+                handle_tray_action(app, action);
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            // Refresh the terminal count just before the menu can open.
+            if let TrayIconEvent::Enter { .. } = event {
+                refresh_tray(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder
+        .build(app)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to create tray icon: {}", e))
+}
+
+/// Rebuild the tray menu from `model`. No-op while the tray is hidden.
+/// Called by menu.rs with the model already locked.
+pub fn update_tray_menu(app: &AppHandle, model: &MenuModel) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_tray_menu(app, model) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => log::warn!("failed to rebuild tray menu: {e}"),
+    }
+}
+
+/// Rebuild the tray menu, e.g. after a terminal was opened or closed.
+pub fn refresh_tray(app: &AppHandle) {
+    if app.tray_by_id(TRAY_ID).is_none() {
+        return;
+    }
+    if let Some(model) = app.try_state::<MenuModelState>() {
+        update_tray_menu(app, &model.lock_recover());
+    }
+}
+
+/// Show the icon if the user enabled it. Must run after `setup_menu`,
+/// which manages the menu model the tray reads.
+pub fn setup_tray(app: &App) {
+    let handle = app.handle();
+    let enabled = app
+        .try_state::<SettingsState>()
+        .is_some_and(|settings| settings.lock_recover().settings().show_tray_icon);
+    if enabled {
+        if let Err(e) = show_tray(handle) {
+            log::warn!("{e}");
+            return;
+        }
+        app.state::<TrayState>().lock_recover().enabled = true;
+    }
+}
+
+#[tauri::command]
+pub fn get_tray_enabled(state: tauri::State<'_, TrayState>) -> bool {
+    state.lock_recover().enabled
+}
+
+/// Show or hide the tray icon. Called when the `showTrayIcon` setting
+/// changes.
+pub fn apply_tray_setting(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    if enabled {
+        show_tray(app)?;
+    } else {
+        app.remove_tray_by_id(TRAY_ID);
+    }
+    if let Some(state) = app.try_state::<TrayState>() {
+        state.lock_recover().enabled = enabled;
+    }
+    Ok(())
+}
+
+/// Show or hide the tray icon and remember the choice for next launch.
+#[tauri::command]
+pub fn set_tray_enabled(app: AppHandle, enabled: bool) -> Result<(), KiriError> {
+    update_settings_impl(&app, serde_json::json!({ "showTrayIcon": enabled }))
+        .map(|_| ())
+        .map_err(KiriError::from)
+}
@@ -0,0 +1,210 @@
+//! Workspace trust: whether kiri may run what a folder brings with it.
+//!
+//! A project's init commands, tasks and `.envrc` are code from whoever
+//! wrote the checkout, and so are its tests, pre-commit hooks, dev
+//! container and the formatters and language servers it configures or
+//! installs. None of it runs until the user trusts the folder. Decisions
+//! are saved in `~/.kiri/trust.json` by folder and cover everything below
+//! it; a linked worktree follows the decision for its main checkout. A
+//! folder nobody decided on yet is [`TrustLevel::Unknown`] and treated as
+//! untrusted. Enforcement lives with the runners, which call
+//! [`require_trusted_in`] before they spawn anything; see
+//! trust_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::auto_fetch::main_checkout;
+use super::error::{path_name, ErrorCode, KiriError};
+use super::file_io::write_file_contents_atomic;
+use super::i18n::Message;
+use super::lock_ext::LockExt;
+
+pub fn trust_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("trust.json"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustLevel {
+    /// Not decided yet; the UI should ask.
+    Unknown,
+    Trusted,
+    Untrusted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrustState {
+    pub level: TrustLevel,
+    /// Folder the decision was made for, or the one to ask about.
+    pub folder: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TrustFile {
+    /// Trusted (`true`) or untrusted folders.
+    folders: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Default)]
+pub struct TrustStore {
+    file: Option<PathBuf>,
+    folders: BTreeMap<String, bool>,
+}
+
+pub type TrustStoreState = Arc<Mutex<TrustStore>>;
+
+/// The main checkout `dir` belongs to, or `dir` outside a repository.
+pub fn project_of(dir: &Path) -> PathBuf {
+    main_checkout(dir).unwrap_or_else(|| dir.to_path_buf())
+}
+
+/// Error `not_trusted` unless the folder `dir` is in is trusted.
+pub fn require_trusted_in(trust: &TrustStoreState, dir: &Path) -> Result<(), KiriError> {
+    let project = project_of(dir);
+    trust.lock_recover().require(dir, &project)
+}
+
+fn key(folder: &Path) -> String {
+    folder.to_string_lossy().to_string()
+}
+
+impl TrustStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: PathBuf) -> Self {
+        let folders = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<TrustFile>(&contents) {
+                    Ok(file) => Some(file.folders),
+                    Err(e) => {
+                        log::warn!("failed to parse workspace trust: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            folders,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = TrustFile {
+            folders: self.folders.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)
+            .map_err(|e| format!("Failed to serialize workspace trust: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    /// The decision for the nearest of `dir` and its parents.
+    fn decision<'a>(&self, dir: &'a Path) -> Option<(&'a Path, bool)> {
+        dir.ancestors()
+            .find_map(|folder| self.folders.get(&key(folder)).map(|t| (folder, *t)))
+    }
+
+    /// Trust of `dir`, which belongs to the project at `project` (its main
+    /// checkout, or `dir` itself outside a repository).
+    pub fn state(&self, dir: &Path, project: &Path) -> TrustState {
+        match self.decision(dir).or_else(|| self.decision(project)) {
+            Some((folder, trusted)) => TrustState {
+                level: if trusted {
+                    TrustLevel::Trusted
+                } else {
+                    TrustLevel::Untrusted
+                },
+                folder: key(folder),
+            },
+            None => TrustState {
+                level: TrustLevel::Unknown,
+                folder: key(project),
+            },
+        }
+    }
+
+    /// Error `not_trusted` unless `dir` of the project at `project` is
+    /// trusted.
+    pub fn require(&self, dir: &Path, project: &Path) -> Result<(), KiriError> {
+        let state = self.state(dir, project);
+        match state.level {
+            TrustLevel::Trusted => Ok(()),
+            _ => Err(KiriError::localized(
+                ErrorCode::NotTrusted,
+                Message::new("error.not_trusted").with("name", path_name(Path::new(&state.folder))),
+            )),
+        }
+    }
+
+    /// Record `level` for `folder`; [`TrustLevel::Unknown`] forgets the
+    /// decision so the UI asks again.
+    pub fn set(&mut self, folder: &Path, level: TrustLevel) -> Result<(), String> {
+        let previous = match level {
+            TrustLevel::Unknown => self.folders.remove(&key(folder)),
+            TrustLevel::Trusted => self.folders.insert(key(folder), true),
+            TrustLevel::Untrusted => self.folders.insert(key(folder), false),
+        };
+        if let Err(e) = self.save() {
+            match previous {
+                Some(previous) => self.folders.insert(key(folder), previous),
+                None => self.folders.remove(&key(folder)),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_unknown_until_decided() {
+        let store = TrustStore::new();
+        let project = Path::new("/src/kiri");
+        let state = store.state(&project.join("web"), project);
+        assert_eq!(state.level, TrustLevel::Unknown);
+        assert_eq!(state.folder, "/src/kiri");
+        let error = store.require(project, project).unwrap_err();
+        assert_eq!(error.code, ErrorCode::NotTrusted);
+        assert_eq!(error.params["name"], "kiri");
+    }
+
+    #[test]
+    fn test_decisions_cover_subfolders_and_worktrees() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("trust.json");
+        let project = Path::new("/src/kiri");
+        let worktree = Path::new("/src/kiri-feature");
+        let mut store = TrustStore::load(path.clone());
+        store.set(project, TrustLevel::Trusted).unwrap();
+        store
+            .set(&project.join("vendor"), TrustLevel::Untrusted)
+            .unwrap();
+
+        let store = TrustStore::load(path);
+        assert!(store.require(&project.join("web/src"), project).is_ok());
+        assert!(store.require(&worktree.join("web"), project).is_ok());
+        let vendor = store.state(&project.join("vendor/lib"), project);
+        assert_eq!(vendor.level, TrustLevel::Untrusted);
+        assert_eq!(vendor.folder, "/src/kiri/vendor");
+        assert!(store
+            .require(Path::new("/tmp/x"), Path::new("/tmp/x"))
+            .is_err());
+    }
+}
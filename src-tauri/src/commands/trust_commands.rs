@@ -0,0 +1,87 @@
+//! Tauri commands and checks for the workspace trust of trust.rs.
+//!
+//! The frontend asks [`get_trust_state`] when a project opens and, while
+//! it is unknown, asks the user before calling [`set_trust_state`]. The
+//! runners check [`require_trusted`] themselves (worktree init commands,
+//! tasks, tests, pre-commit hooks, formatters, dev containers, language
+//! servers) and leave `.envrc` unevaluated unless [`is_trusted`], so a
+//! window that skipped the prompt still can't run a project's code.
+
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::trust::{
+    project_of, require_trusted_in, trust_file_path, TrustLevel, TrustState, TrustStore,
+    TrustStoreState,
+};
+
+pub const TRUST_CHANGED_EVENT: &str = "trust-changed";
+
+/// Read `~/.kiri/trust.json` into the managed state. Call from setup,
+/// before restored windows open terminals.
+pub fn load_trust_store(app: &AppHandle) {
+    let Some(path) = trust_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<TrustStoreState>() {
+        *state.lock_recover() = TrustStore::load(path);
+    }
+}
+
+fn state_for(store: &TrustStoreState, dir: &Path) -> TrustState {
+    store.lock_recover().state(dir, &project_of(dir))
+}
+
+/// The trust decisions, for runners that check them with
+/// [`require_trusted_in`] where they spawn.
+pub fn trust_store(app: &AppHandle) -> Result<TrustStoreState, KiriError> {
+    let store = app
+        .try_state::<TrustStoreState>()
+        .ok_or("Workspace trust is not available")?;
+    Ok(Arc::clone(&store))
+}
+
+/// Error `not_trusted` unless the folder `dir` is in is trusted.
+pub fn require_trusted(app: &AppHandle, dir: &Path) -> Result<(), KiriError> {
+    require_trusted_in(&trust_store(app)?, dir)
+}
+
+pub fn is_trusted(app: &AppHandle, dir: &Path) -> bool {
+    require_trusted(app, dir).is_ok()
+}
+
+/// Whether the folder containing `path` is trusted, and which folder the
+/// decision is (or would be) about.
+#[tauri::command]
+pub async fn get_trust_state(
+    state: tauri::State<'_, TrustStoreState>,
+    path: String,
+) -> Result<TrustState, KiriError> {
+    let state = Arc::clone(&state);
+    run_blocking("get_trust_state", move || {
+        Ok(state_for(&state, &normalize_input_path(&path)))
+    })
+    .await
+}
+
+/// Trust or distrust `path` and everything below it, or forget the
+/// decision with `unknown`. Terminals opened before keep their
+/// environment.
+#[tauri::command]
+pub fn set_trust_state(
+    app: AppHandle,
+    state: tauri::State<'_, TrustStoreState>,
+    path: String,
+    level: TrustLevel,
+) -> Result<TrustState, KiriError> {
+    let folder = normalize_input_path(&path);
+    state.lock_recover().set(&folder, level)?;
+    let trust = state_for(&state, &folder);
+    let _ = app.emit(TRUST_CHANGED_EVENT, &trust);
+    Ok(trust)
+}
@@ -0,0 +1,568 @@
+//! Tunnels exposing a worktree's dev server on a public URL.
+//!
+//! A tunnel forwards a public URL to `http://localhost:<port>` through a
+//! provider's CLI, behind the [`TunnelProvider`] trait: Cloudflare
+//! (`cloudflared`, cloudflare_tunnel.rs), ngrok (ngrok.rs), localtunnel
+//! (`lt`, localtunnel.rs) and Tailscale Funnel (tailscale_funnel.rs).
+//! Cloudflare quick tunnels and localtunnel need no account.
+//!
+//! The CLI is always started with an explicit argv, and names and domains
+//! from a config are validated before use, so nothing can be read as a
+//! flag. Configs are kept per worktree and port in `~/.kiri/tunnels.json`;
+//! the processes live in [`Tunnels`] and the runner in tunnel_commands.rs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+
+use super::cloudflare_tunnel::CloudflareTunnel;
+use super::file_io::write_file_contents_atomic;
+use super::localtunnel::Localtunnel;
+use super::ngrok::Ngrok;
+use super::process::argv_command;
+use super::tailscale_funnel::TailscaleFunnel;
+
+lazy_static::lazy_static! {
+    static ref HOSTNAME_LABEL: regex::Regex =
+        regex::Regex::new(r"^[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?$")
+            .expect("pattern compiles");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Cloudflare,
+    Ngrok,
+    Localtunnel,
+    TailscaleFunnel,
+}
+
+impl ProviderKind {
+    pub const ALL: [ProviderKind; 4] = [
+        ProviderKind::Cloudflare,
+        ProviderKind::Ngrok,
+        ProviderKind::Localtunnel,
+        ProviderKind::TailscaleFunnel,
+    ];
+
+    /// The provider with default options, for capability discovery.
+    pub fn provider(self) -> &'static dyn TunnelProvider {
+        static CLOUDFLARE: CloudflareTunnel = CloudflareTunnel::Quick;
+        static NGROK: Ngrok = Ngrok { domain: None };
+        static LOCALTUNNEL: Localtunnel = Localtunnel { subdomain: None };
+        static TAILSCALE_FUNNEL: TailscaleFunnel = TailscaleFunnel {};
+        match self {
+            ProviderKind::Cloudflare => &CLOUDFLARE,
+            ProviderKind::Ngrok => &NGROK,
+            ProviderKind::Localtunnel => &LOCALTUNNEL,
+            ProviderKind::TailscaleFunnel => &TAILSCALE_FUNNEL,
+        }
+    }
+}
+
+/// What a line of a provider's log says about its tunnel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEvent {
+    /// The tunnel is up, at `url` if known.
+    Ready {
+        url: Option<String>,
+    },
+    Error(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// Needs a signed-in account (or a logged-in node for Tailscale).
+    pub requires_account: bool,
+    /// Can serve on a domain of the user's choosing.
+    pub custom_domain: bool,
+}
+
+/// A tunnel provider, configured with one tunnel's options.
+pub trait TunnelProvider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+
+    fn label(&self) -> &'static str;
+
+    /// The CLI that runs tunnels.
+    fn program(&self) -> &'static str;
+
+    /// Arguments that make [`Self::program`] print its version.
+    fn version_args(&self) -> &'static [&'static str] {
+        &["--version"]
+    }
+
+    fn install_hint(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Check the options before they are saved or run.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Arguments exposing `port`. Call [`Self::validate`] first.
+    fn args(&self, port: u16) -> Vec<String>;
+
+    /// The environment variable the CLI reads its credential from, for
+    /// providers whose credential can be kept in the keychain as the secret
+    /// `tunnel`/[`Self::program`]. `None` if the CLI manages its own login.
+    fn credential_env(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Interpret a line the CLI printed on stdout or stderr.
+    fn log_event(&self, line: &str) -> Option<LogEvent>;
+}
+
+/// Per-provider options of a tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum TunnelOptions {
+    Cloudflare(CloudflareTunnel),
+    Ngrok(Ngrok),
+    Localtunnel(Localtunnel),
+    TailscaleFunnel(TailscaleFunnel),
+}
+
+impl TunnelOptions {
+    pub fn provider(&self) -> &dyn TunnelProvider {
+        match self {
+            TunnelOptions::Cloudflare(options) => options,
+            TunnelOptions::Ngrok(options) => options,
+            TunnelOptions::Localtunnel(options) => options,
+            TunnelOptions::TailscaleFunnel(options) => options,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelConfig {
+    pub worktree: String,
+    pub port: u16,
+    #[serde(flatten)]
+    pub options: TunnelOptions,
+}
+
+impl TunnelConfig {
+    fn key(&self) -> TunnelKey {
+        (self.worktree.clone(), self.port)
+    }
+}
+
+/// Worktree path and local port; a worktree has at most one tunnel per port.
+pub type TunnelKey = (String, u16);
+
+/// Whether `hostname` is a fully qualified DNS name, e.g. `dev.example.com`.
+pub fn valid_hostname(hostname: &str) -> bool {
+    hostname.len() <= 253
+        && hostname.contains('.')
+        && hostname
+            .split('.')
+            .all(|label| HOSTNAME_LABEL.is_match(label))
+}
+
+/// Check a config before it is saved or run.
+pub fn validate(config: &TunnelConfig) -> Result<(), String> {
+    if config.port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    config.options.provider().validate()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderInfo {
+    pub provider: ProviderKind,
+    pub label: &'static str,
+    pub installed: bool,
+    /// First line the CLI printed for its version.
+    pub version: Option<String>,
+    pub install_hint: &'static str,
+    #[serde(flatten)]
+    pub capabilities: Capabilities,
+}
+
+/// Whether `kind`'s CLI is installed, and what it can do.
+pub fn provider_info(kind: ProviderKind) -> ProviderInfo {
+    let provider = kind.provider();
+    let output = argv_command(&[provider.program()], None).and_then(|mut command| {
+        command
+            .args(provider.version_args())
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| e.to_string())
+    });
+    let version = output.ok().map(|output| {
+        let text = if output.stdout.is_empty() {
+            output.stderr
+        } else {
+            output.stdout
+        };
+        String::from_utf8_lossy(&text)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    });
+    ProviderInfo {
+        provider: provider.kind(),
+        label: provider.label(),
+        installed: version.is_some(),
+        version: version.filter(|v| !v.is_empty()),
+        install_hint: provider.install_hint(),
+        capabilities: provider.capabilities(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TunnelStatus {
+    Starting,
+    Running {
+        /// `None` until the provider reports it, and for tunnels that have
+        /// none of their own, e.g. a named Cloudflare tunnel without a
+        /// hostname.
+        url: Option<String>,
+    },
+    Stopped,
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tunnel {
+    #[serde(flatten)]
+    pub config: TunnelConfig,
+    pub status: TunnelStatus,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TunnelsFile {
+    #[serde(default)]
+    tunnels: Vec<TunnelConfig>,
+}
+
+/// A running tunnel process.
+struct RunningTunnel {
+    child: Child,
+    /// Tells the monitor of an earlier run of the same tunnel to stop.
+    generation: u64,
+    last_error: Option<String>,
+}
+
+/// Saved tunnel configs and the tunnels that are running.
+#[derive(Default)]
+pub struct Tunnels {
+    file: Option<PathBuf>,
+    configs: BTreeMap<TunnelKey, TunnelConfig>,
+    statuses: BTreeMap<TunnelKey, TunnelStatus>,
+    running: HashMap<TunnelKey, RunningTunnel>,
+    next_generation: u64,
+}
+
+pub type TunnelsState = Arc<Mutex<Tunnels>>;
+
+pub fn tunnels_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".kiri").join("tunnels.json"))
+}
+
+impl Tunnels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configs saved in `path`; none if it is missing or unreadable.
+    pub fn load(path: PathBuf) -> Self {
+        let configs = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<TunnelsFile>(&contents) {
+                    Ok(file) => Some(file.tunnels),
+                    Err(e) => {
+                        log::warn!("failed to parse tunnel configs: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+        Self {
+            file: Some(path),
+            configs: configs
+                .into_iter()
+                .filter(|config| validate(config).is_ok())
+                .map(|config| (config.key(), config))
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = TunnelsFile {
+            tunnels: self.configs.values().cloned().collect(),
+        };
+        let json = serde_json::to_vec_pretty(&file)
+            .map_err(|e| format!("Failed to serialize tunnel configs: {}", e))?;
+        write_file_contents_atomic(path, &json)
+    }
+
+    /// Save `config`, replacing the one for the same worktree and port.
+    pub fn save_config(&mut self, config: TunnelConfig) -> Result<(), String> {
+        validate(&config)?;
+        self.configs.insert(config.key(), config);
+        self.save()
+    }
+
+    /// Forget a saved config. A running tunnel is left running.
+    pub fn remove_config(&mut self, key: &TunnelKey) -> Result<bool, String> {
+        if self.configs.remove(key).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn get(&self, key: &TunnelKey) -> Option<Tunnel> {
+        let config = self.configs.get(key)?.clone();
+        let status = self
+            .statuses
+            .get(key)
+            .cloned()
+            .unwrap_or(TunnelStatus::Stopped);
+        Some(Tunnel { config, status })
+    }
+
+    /// Tunnels of `worktree`, or of all worktrees, by port.
+    pub fn list(&self, worktree: Option<&str>) -> Vec<Tunnel> {
+        self.configs
+            .keys()
+            .filter(|(path, _)| worktree.map_or(true, |w| path == w))
+            .filter_map(|key| self.get(key))
+            .collect()
+    }
+
+    /// Track a just-started tunnel process. Returns the generation its
+    /// monitor should pass to [`Self::poll_exit`].
+    pub fn started(&mut self, key: TunnelKey, child: Child) -> u64 {
+        self.next_generation += 1;
+        let generation = self.next_generation;
+        self.running.insert(
+            key.clone(),
+            RunningTunnel {
+                child,
+                generation,
+                last_error: None,
+            },
+        );
+        self.statuses.insert(key, TunnelStatus::Starting);
+        generation
+    }
+
+    /// Record the status of a tunnel that failed to start.
+    pub fn failed(&mut self, key: TunnelKey, error: String) {
+        self.statuses.insert(key, TunnelStatus::Failed { error });
+    }
+
+    /// Apply a line of a running tunnel's log. Returns the tunnel if its
+    /// status changed.
+    pub fn log_line(&mut self, key: &TunnelKey, generation: u64, line: &str) -> Option<Tunnel> {
+        let config = self.configs.get(key)?;
+        let event = config.options.provider().log_event(line)?;
+        let running = self.running.get_mut(key)?;
+        if running.generation != generation {
+            return None;
+        }
+        let status = match event {
+            LogEvent::Error(error) => {
+                running.last_error = Some(error);
+                return None;
+            }
+            LogEvent::Ready { url } => TunnelStatus::Running { url },
+        };
+        let current = self.statuses.get(key);
+        // A later connection without a URL doesn't hide the one reported.
+        if current == Some(&status)
+            || (matches!(current, Some(TunnelStatus::Running { url: Some(_) }))
+                && status == TunnelStatus::Running { url: None })
+        {
+            return None;
+        }
+        self.statuses.insert(key.clone(), status);
+        self.get(key)
+    }
+
+    /// Check whether a tunnel's process has exited. Returns `None` while it
+    /// runs, and the final state once it exited or was replaced.
+    pub fn poll_exit(&mut self, key: &TunnelKey, generation: u64) -> Option<Option<Tunnel>> {
+        let Some(running) = self.running.get_mut(key) else {
+            return Some(None);
+        };
+        if running.generation != generation {
+            return Some(None);
+        }
+        let status = match running.child.try_wait() {
+            Ok(None) => return None,
+            Ok(Some(status)) => status,
+            Err(e) => {
+                log::warn!("failed to wait for tunnel: {}", e);
+                return None;
+            }
+        };
+        let running = self.running.remove(key)?;
+        let program = self
+            .configs
+            .get(key)
+            .map_or("tunnel", |config| config.options.provider().program());
+        let error = running.last_error.unwrap_or_else(|| match status.code() {
+            Some(code) => format!("{} exited with code {}", program, code),
+            None => format!("{} was terminated", program),
+        });
+        self.statuses
+            .insert(key.clone(), TunnelStatus::Failed { error });
+        Some(self.get(key))
+    }
+
+    /// Kill a tunnel's process. Returns `false` if it wasn't running.
+    pub fn stop(&mut self, key: &TunnelKey) -> bool {
+        let Some(mut running) = self.running.remove(key) else {
+            return false;
+        };
+        let _ = running.child.kill();
+        let _ = running.child.wait();
+        self.statuses.insert(key.clone(), TunnelStatus::Stopped);
+        true
+    }
+
+    /// Kill every running tunnel, e.g. when the app quits.
+    pub fn stop_all(&mut self) {
+        let keys: Vec<TunnelKey> = self.running.keys().cloned().collect();
+        for key in keys {
+            self.stop(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn named(name: &str, hostname: Option<&str>) -> TunnelConfig {
+        TunnelConfig {
+            worktree: "/repo".to_string(),
+            port: 5173,
+            options: TunnelOptions::Cloudflare(CloudflareTunnel::Named {
+                name: name.to_string(),
+                hostname: hostname.map(str::to_string),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_config_serialization() {
+        let config = TunnelConfig {
+            worktree: "/repo".to_string(),
+            port: 3000,
+            options: TunnelOptions::Ngrok(Ngrok {
+                domain: Some("app.ngrok.dev".to_string()),
+            }),
+        };
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "worktree": "/repo",
+                "port": 3000,
+                "provider": "ngrok",
+                "domain": "app.ngrok.dev"
+            })
+        );
+        let parsed: TunnelConfig = serde_json::from_value(serde_json::json!({
+            "worktree": "/repo",
+            "port": 5173,
+            "provider": "cloudflare",
+            "kind": "named",
+            "name": "app"
+        }))
+        .unwrap();
+        assert_eq!(parsed, named("app", None));
+        assert!(validate(&TunnelConfig { port: 0, ..parsed }).is_err());
+    }
+
+    #[test]
+    fn test_configs_persist() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tunnels.json");
+        let mut tunnels = Tunnels::load(path.clone());
+        tunnels
+            .save_config(named("app", Some("dev.example.com")))
+            .unwrap();
+        assert!(tunnels.save_config(named("-x", None)).is_err());
+
+        let tunnels = Tunnels::load(path);
+        let listed = tunnels.list(Some("/repo"));
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].config, named("app", Some("dev.example.com")));
+        assert_eq!(listed[0].status, TunnelStatus::Stopped);
+        assert!(tunnels.list(Some("/other")).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_running_status() {
+        let mut tunnels = Tunnels::new();
+        let config = named("app", Some("dev.example.com"));
+        let key = config.key();
+        tunnels.save_config(config).unwrap();
+        let child = Command::new("sleep")
+            .arg("30")
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let generation = tunnels.started(key.clone(), child);
+        assert_eq!(tunnels.poll_exit(&key, generation), None);
+
+        let tunnel = tunnels
+            .log_line(&key, generation, "INF Registered tunnel connection")
+            .unwrap();
+        assert_eq!(
+            tunnel.status,
+            TunnelStatus::Running {
+                url: Some("https://dev.example.com".to_string())
+            }
+        );
+        assert!(tunnels
+            .log_line(&key, generation, "INF Registered tunnel connection")
+            .is_none());
+
+        assert!(tunnels.stop(&key));
+        assert_eq!(tunnels.get(&key).unwrap().status, TunnelStatus::Stopped);
+        assert_eq!(tunnels.poll_exit(&key, generation), Some(None));
+
+        let child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+        let generation = tunnels.started(key.clone(), child);
+        let finished = loop {
+            if let Some(finished) = tunnels.poll_exit(&key, generation) {
+                break finished;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(
+            finished.unwrap().status,
+            TunnelStatus::Failed {
+                error: "cloudflared exited with code 3".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Tauri commands for tunnels; see tunnel.rs.
+//!
+//! [`list_tunnel_providers`] reports which providers are installed and
+//! what they can do. [`start_tunnel`] saves the config and starts the
+//! provider's CLI; every change of a tunnel's status, whatever its
+//! provider, including its public URL becoming known, is sent as a
+//! `tunnel-status` event with the [`Tunnel`].
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::latency::run_blocking;
+use super::lock_ext::LockExt;
+use super::network;
+use super::process::argv_command;
+use super::secrets;
+use super::tunnel::{
+    provider_info, tunnels_file_path, validate, ProviderInfo, ProviderKind, Tunnel, TunnelConfig,
+    TunnelKey, Tunnels, TunnelsState,
+};
+
+pub const TUNNEL_STATUS_EVENT: &str = "tunnel-status";
+
+/// Secret namespace of tunnel credentials, keyed by the provider's CLI.
+const TUNNEL_SECRETS: &str = "tunnel";
+
+/// How often a tunnel's monitor checks whether its process exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Load `~/.kiri/tunnels.json` into [`TunnelsState`].
+pub fn load_tunnel_configs(app: &AppHandle) {
+    let Some(path) = tunnels_file_path() else {
+        return;
+    };
+    if let Some(state) = app.try_state::<TunnelsState>() {
+        *state.lock_recover() = Tunnels::load(path);
+    }
+}
+
+/// Stop every tunnel so none outlives the app.
+pub fn stop_all_tunnels(app: &AppHandle) {
+    if let Some(state) = app.try_state::<TunnelsState>() {
+        state.lock_recover().stop_all();
+    }
+}
+
+fn key(worktree: &str, port: u16) -> TunnelKey {
+    let worktree = normalize_input_path(worktree);
+    (worktree.to_string_lossy().to_string(), port)
+}
+
+fn publish(app: &AppHandle, tunnel: &Tunnel) {
+    let _ = app.emit(TUNNEL_STATUS_EVENT, tunnel);
+}
+
+/// Apply a tunnel process's output to the tunnel's status.
+fn follow_log(
+    app: AppHandle,
+    state: TunnelsState,
+    key: TunnelKey,
+    generation: u64,
+    log: impl Read,
+) {
+    for line in BufReader::new(log).lines().map_while(Result::ok) {
+        let changed = state.lock_recover().log_line(&key, generation, &line);
+        if let Some(tunnel) = changed {
+            publish(&app, &tunnel);
+        }
+    }
+}
+
+/// Wait for the tunnel's process to exit, unless it is stopped or
+/// restarted first.
+fn monitor(app: AppHandle, state: TunnelsState, key: TunnelKey, generation: u64) {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let exited = state.lock_recover().poll_exit(&key, generation);
+        match exited {
+            None => continue,
+            Some(Some(tunnel)) => {
+                publish(&app, &tunnel);
+                break;
+            }
+            Some(None) => break,
+        }
+    }
+}
+
+/// Every tunnel provider, whether its CLI is installed and what it can do.
+#[tauri::command]
+pub async fn list_tunnel_providers() -> Result<Vec<ProviderInfo>, KiriError> {
+    run_blocking("list_tunnel_providers", || {
+        Ok(ProviderKind::ALL.into_iter().map(provider_info).collect())
+    })
+    .await
+}
+
+/// Saved tunnels of `worktree`, or of all worktrees, with their status.
+#[tauri::command]
+pub fn list_tunnels(
+    state: tauri::State<'_, TunnelsState>,
+    worktree: Option<String>,
+) -> Vec<Tunnel> {
+    let worktree = worktree.map(|w| normalize_input_path(&w).to_string_lossy().to_string());
+    state.lock_recover().list(worktree.as_deref())
+}
+
+#[tauri::command]
+pub fn get_tunnel_status(
+    state: tauri::State<'_, TunnelsState>,
+    worktree: String,
+    port: u16,
+) -> Option<Tunnel> {
+    state.lock_recover().get(&key(&worktree, port))
+}
+
+/// Save a tunnel config without starting it.
+#[tauri::command]
+pub fn save_tunnel(
+    state: tauri::State<'_, TunnelsState>,
+    mut config: TunnelConfig,
+) -> Result<(), KiriError> {
+    config.worktree = key(&config.worktree, config.port).0;
+    state
+        .lock_recover()
+        .save_config(config)
+        .map_err(KiriError::from)
+}
+
+/// Stop a tunnel if it is running and forget its config.
+#[tauri::command]
+pub fn delete_tunnel(
+    state: tauri::State<'_, TunnelsState>,
+    worktree: String,
+    port: u16,
+) -> Result<bool, KiriError> {
+    let key = key(&worktree, port);
+    let mut tunnels = state.lock_recover();
+    tunnels.stop(&key);
+    tunnels.remove_config(&key).map_err(KiriError::from)
+}
+
+/// Save `config` and start its tunnel, restarting it if it was running.
+#[tauri::command]
+pub fn start_tunnel(
+    app: AppHandle,
+    state: tauri::State<'_, TunnelsState>,
+    mut config: TunnelConfig,
+) -> Result<Tunnel, KiriError> {
+    validate(&config)?;
+    let key = key(&config.worktree, config.port);
+    config.worktree = key.0.clone();
+    let provider = config.options.provider();
+    let (program, args) = (provider.program(), provider.args(config.port));
+    let mut argv = vec![program.to_string()];
+    argv.extend(args);
+    let mut command = argv_command(&argv, Some(Path::new(&key.0)))?;
+    // Read before locking: the keychain may take a moment, or prompt.
+    let credential = provider
+        .credential_env()
+        .and_then(|var| Some((var, secrets::get_secret(TUNNEL_SECRETS, program)?)));
+    let state: TunnelsState = Arc::clone(&state);
+    let mut tunnels = state.lock_recover();
+    tunnels.save_config(config)?;
+    tunnels.stop(&key);
+
+    network::configure_command(&mut command);
+    if let Some((var, secret)) = credential {
+        command.env(var, secret);
+    }
+    let spawned = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            let error = match e.kind() {
+                std::io::ErrorKind::NotFound => format!("{} is not installed", program),
+                _ => format!("Failed to start {}: {}", program, e),
+            };
+            tunnels.failed(key.clone(), error.clone());
+            if let Some(tunnel) = tunnels.get(&key) {
+                publish(&app, &tunnel);
+            }
+            return Err(error.into());
+        }
+    };
+    let logs: Vec<Box<dyn Read + Send>> = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let generation = tunnels.started(key.clone(), child);
+    let tunnel = tunnels
+        .get(&key)
+        .ok_or_else(|| "Tunnel config was not saved".to_string())?;
+    drop(tunnels);
+
+    for log in logs {
+        let (app, state, key) = (app.clone(), state.clone(), key.clone());
+        thread::spawn(move || follow_log(app, state, key, generation, log));
+    }
+    {
+        let (app, state) = (app.clone(), state.clone());
+        thread::spawn(move || monitor(app, state, key, generation));
+    }
+    publish(&app, &tunnel);
+    Ok(tunnel)
+}
+
+/// Stop a tunnel. Returns `false` if it wasn't running.
+#[tauri::command]
+pub fn stop_tunnel(
+    app: AppHandle,
+    state: tauri::State<'_, TunnelsState>,
+    worktree: String,
+    port: u16,
+) -> bool {
+    let key = key(&worktree, port);
+    let mut tunnels = state.lock_recover();
+    if !tunnels.stop(&key) {
+        return false;
+    }
+    if let Some(tunnel) = tunnels.get(&key) {
+        publish(&app, &tunnel);
+    }
+    true
+}
@@ -0,0 +1,294 @@
+//! Checking for new kiri releases.
+//!
+//! Releases come from the GitHub releases of kiri's repository. On the
+//! stable channel only full releases count; beta also offers
+//! pre-releases. The newest release above the running version is the
+//! update, with its release notes. [`Updater`] remembers the last check
+//! so the background checker (updater_commands.rs) runs at most every
+//! [`CHECK_INTERVAL`] and announces each version once.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::code_host::{http_client, send_json_as};
+use super::settings::UpdateChannel;
+
+pub const RELEASES_URL: &str = "https://api.github.com/repos/t09tanaka/kiri/releases?per_page=30";
+
+/// Time between background checks, successful or not.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// A release version: `1.2.3`, `v1.2.3` or `1.3.0-beta.2`. Build metadata
+/// after `+` is ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release identifiers, e.g. `["beta", "2"]`.
+    pub pre: Vec<String>,
+}
+
+impl Version {
+    pub fn parse(text: &str) -> Option<Version> {
+        let text = text.trim();
+        let text = text.strip_prefix('v').unwrap_or(text);
+        let text = text.split('+').next()?;
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, pre.split('.').map(str::to_string).collect()),
+            None => (text, Vec::new()),
+        };
+        let mut numbers = core.split('.').map(|n| n.parse::<u64>());
+        let major = numbers.next()?.ok()?;
+        let minor = numbers.next().unwrap_or(Ok(0)).ok()?;
+        let patch = numbers.next().unwrap_or(Ok(0)).ok()?;
+        if numbers.next().is_some() || pre.iter().any(String::is_empty) {
+            return None;
+        }
+        Some(Version {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+/// Semver precedence: numeric identifiers compare as numbers and sort
+/// before alphanumeric ones.
+fn compare_identifier(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                // A pre-release comes before its release.
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self
+                    .pre
+                    .iter()
+                    .zip(&other.pre)
+                    .map(|(a, b)| compare_identifier(a, b))
+                    .find(|order| order.is_ne())
+                    .unwrap_or_else(|| self.pre.len().cmp(&other.pre.len())),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A release as the GitHub API returns it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Release notes, in markdown.
+    #[serde(default)]
+    pub body: Option<String>,
+    pub html_url: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub published_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub name: String,
+    pub notes: String,
+    /// The release page, where the downloads are.
+    pub url: String,
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    /// When the check ran, ms since the epoch.
+    pub checked_ms: u64,
+    /// `None` when the running version is the newest.
+    pub update: Option<UpdateInfo>,
+}
+
+/// The newest of `releases` on `channel` above `current`.
+pub fn pick_update(
+    current: &str,
+    channel: UpdateChannel,
+    releases: &[Release],
+) -> Option<UpdateInfo> {
+    let current = Version::parse(current)?;
+    releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| Some((Version::parse(&release.tag_name)?, release)))
+        .filter(|(version, release)| {
+            channel == UpdateChannel::Beta || !(release.prerelease || version.is_prerelease())
+        })
+        .filter(|(version, _)| *version > current)
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, release)| {
+            let version = release.tag_name.trim().trim_start_matches('v').to_string();
+            UpdateInfo {
+                name: release
+                    .name
+                    .clone()
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| version.clone()),
+                version,
+                notes: release.body.clone().unwrap_or_default(),
+                url: release.html_url.clone(),
+                published_at: release.published_at.clone(),
+                prerelease: release.prerelease,
+            }
+        })
+}
+
+/// kiri's latest releases, newest first.
+pub async fn fetch_releases() -> Result<Vec<Release>, String> {
+    Ok(send_json_as("GitHub", http_client()?.get(RELEASES_URL)).await?)
+}
+
+#[derive(Debug, Default)]
+pub struct Updater {
+    last_check: Option<UpdateCheck>,
+    /// When a check last started, ms since the epoch.
+    last_attempt_ms: Option<u64>,
+    /// The update last announced.
+    announced: Option<String>,
+}
+
+pub type UpdaterState = Arc<Mutex<Updater>>;
+
+impl Updater {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn last_check(&self) -> Option<&UpdateCheck> {
+        self.last_check.as_ref()
+    }
+
+    /// Whether a background check is due, and if so note that one starts.
+    pub fn start_if_due(&mut self, now_ms: u64) -> bool {
+        let due = self.last_attempt_ms.map_or(true, |last| {
+            now_ms.saturating_sub(last) >= CHECK_INTERVAL.as_millis() as u64
+        });
+        if due {
+            self.last_attempt_ms = Some(now_ms);
+        }
+        due
+    }
+
+    /// Keep `check`. Returns whether its update wasn't announced yet.
+    pub fn record(&mut self, check: UpdateCheck) -> bool {
+        let version = check.update.as_ref().map(|update| update.version.clone());
+        self.last_check = Some(check);
+        match version {
+            Some(version) if self.announced.as_ref() != Some(&version) => {
+                self.announced = Some(version);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget the last check, e.g. when the channel changes, so the next
+    /// tick checks again.
+    pub fn reset(&mut self) {
+        self.last_check = None;
+        self.last_attempt_ms = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool) -> Release {
+        Release {
+            tag_name: tag.to_string(),
+            name: None,
+            body: Some(format!("Notes for {}", tag)),
+            html_url: format!("https://github.com/t09tanaka/kiri/releases/tag/{}", tag),
+            prerelease,
+            draft: false,
+            published_at: None,
+        }
+    }
+
+    #[test]
+    fn test_version_order() {
+        let v = |text| Version::parse(text).unwrap();
+        assert!(v("v1.2.3") < v("1.10.0"));
+        assert!(v("1.3.0-beta.2") < v("1.3.0"));
+        assert!(v("1.3.0-beta.2") < v("1.3.0-beta.10"));
+        assert!(v("1.3.0-alpha") < v("1.3.0-beta"));
+        assert!(v("1.3.0-beta") < v("1.3.0-beta.1"));
+        assert_eq!(v("2.0"), v("2.0.0+build.5"));
+        assert!(Version::parse("nightly").is_none());
+        assert!(Version::parse("1.2.3.4").is_none());
+    }
+
+    #[test]
+    fn test_pick_update_by_channel() {
+        let mut draft = release("v2.0.0", false);
+        draft.draft = true;
+        let releases = vec![
+            draft,
+            release("v1.4.0-beta.1", true),
+            release("v1.3.1", false),
+            release("v1.3.0", false),
+            release("v1.2.0", false),
+        ];
+
+        let stable = pick_update("1.3.0", UpdateChannel::Stable, &releases).unwrap();
+        assert_eq!(stable.version, "1.3.1");
+        assert_eq!(stable.name, "1.3.1");
+        assert_eq!(stable.notes, "Notes for v1.3.1");
+        let beta = pick_update("1.3.0", UpdateChannel::Beta, &releases).unwrap();
+        assert_eq!(beta.version, "1.4.0-beta.1");
+        assert!(beta.prerelease);
+        assert!(pick_update("1.4.0-beta.1", UpdateChannel::Stable, &releases).is_none());
+
+        let mut updater = Updater::new();
+        assert!(updater.start_if_due(1_000));
+        assert!(!updater.start_if_due(2_000));
+        let check = UpdateCheck {
+            current_version: "1.3.0".to_string(),
+            channel: UpdateChannel::Stable,
+            checked_ms: 2_000,
+            update: Some(stable),
+        };
+        assert!(updater.record(check.clone()));
+        assert!(!updater.record(check));
+        assert!(updater.start_if_due(1_000 + CHECK_INTERVAL.as_millis() as u64));
+    }
+}
@@ -0,0 +1,117 @@
+//! Background update checks and their commands; versions and channels are
+//! in updater.rs.
+//!
+//! With the "check for updates" setting on, the checker thread looks for
+//! a new release every [`CHECK_INTERVAL`](super::updater::CHECK_INTERVAL).
+//! A version found for the first time is sent as `update-available` and
+//! passed to the notification rules as
+//! [`NotificationEvent::UpdateAvailable`]. [`check_for_updates`] checks
+//! right away, whatever the setting.
+
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::clock::now_ms;
+use super::error::KiriError;
+use super::lock_ext::LockExt;
+use super::notifications::NotificationEvent;
+use super::notifications_commands::notify;
+use super::settings::{SettingsState, UpdateChannel};
+use super::updater::{fetch_releases, pick_update, UpdateCheck, UpdaterState};
+
+pub const UPDATE_AVAILABLE_EVENT: &str = "update-available";
+
+/// Wait before the first check, to keep it out of startup.
+const STARTUP_DELAY: Duration = Duration::from_secs(30);
+
+/// How often the checker looks whether a check is due.
+const TICK: Duration = Duration::from_secs(15 * 60);
+
+/// The "check for updates" setting and the channel.
+fn update_settings(app: &AppHandle) -> (bool, UpdateChannel) {
+    app.try_state::<SettingsState>()
+        .map(|settings| {
+            let store = settings.lock_recover();
+            let settings = store.settings();
+            (settings.check_for_updates, settings.update_channel)
+        })
+        .unwrap_or((false, UpdateChannel::Stable))
+}
+
+/// Check now, keep the result and announce an update not seen before.
+async fn run_check(app: &AppHandle) -> Result<UpdateCheck, String> {
+    let (_, channel) = update_settings(app);
+    let releases = fetch_releases().await?;
+    let current_version = env!("CARGO_PKG_VERSION");
+    let check = UpdateCheck {
+        current_version: current_version.to_string(),
+        channel,
+        checked_ms: now_ms(),
+        update: pick_update(current_version, channel, &releases),
+    };
+    let state = app
+        .try_state::<UpdaterState>()
+        .ok_or("Updates are not available")?;
+    let announce = state.lock_recover().record(check.clone());
+    if let (true, Some(update)) = (announce, &check.update) {
+        let _ = app.emit(UPDATE_AVAILABLE_EVENT, update);
+        notify(
+            app,
+            NotificationEvent::UpdateAvailable {
+                version: update.version.clone(),
+                channel: channel.as_str().to_string(),
+            },
+        );
+    }
+    Ok(check)
+}
+
+fn check_if_due(app: &AppHandle) {
+    let (enabled, _) = update_settings(app);
+    let Some(state) = app.try_state::<UpdaterState>() else {
+        return;
+    };
+    if !enabled || !state.lock_recover().start_if_due(now_ms()) {
+        return;
+    }
+    if let Err(e) = tauri::async_runtime::block_on(run_check(app)) {
+        log::warn!("update check failed: {}", e);
+    }
+}
+
+/// Start the checker thread. Call once from setup.
+pub fn start_update_checks(app: &AppHandle) {
+    let app = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("kiri-update-check".to_string())
+        .spawn(move || {
+            std::thread::sleep(STARTUP_DELAY);
+            loop {
+                check_if_due(&app);
+                std::thread::sleep(TICK);
+            }
+        });
+    if let Err(e) = spawned {
+        log::warn!("failed to start update checks: {}", e);
+    }
+}
+
+/// Called when the `updateChannel` setting changes: the next tick checks
+/// the new channel.
+pub fn apply_update_channel_setting(app: &AppHandle) {
+    if let Some(state) = app.try_state::<UpdaterState>() {
+        state.lock_recover().reset();
+    }
+}
+
+/// Look for a new release on the configured channel now.
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<UpdateCheck, KiriError> {
+    run_check(&app).await.map_err(KiriError::from)
+}
+
+/// The result of the last check, if one ran.
+#[tauri::command]
+pub fn get_update_status(state: tauri::State<'_, UpdaterState>) -> Option<UpdateCheck> {
+    state.lock_recover().last_check().cloned()
+}
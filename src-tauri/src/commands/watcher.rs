@@ -1,8 +1,18 @@
-use notify_debouncer_mini::DebouncedEventKind;
+use super::clock::now_ms;
+use super::entity_map::EntityMap;
+use super::fs_mounts::{filesystem_type, native_events_unreliable};
+use super::fs_path::path_to_wire;
+use super::lock_ext::LockExt;
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_mini::{
+    new_debouncer_opt, Config, DebounceEventResult, DebouncedEvent, DebouncedEventKind, Debouncer,
+};
 use serde::Serialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct FsChangeEvent {
@@ -24,8 +34,12 @@ pub enum PathClassification {
 }
 
 /// Classify a path to determine if it's a git-related path or a regular fs path
-pub fn classify_path(path: &str) -> PathClassification {
-    if path.contains("/.git/") || path.ends_with("/.git") {
+pub fn classify_path(path: impl AsRef<Path>) -> PathClassification {
+    if path
+        .as_ref()
+        .components()
+        .any(|part| part.as_os_str() == ".git")
+    {
         PathClassification::GitPath
     } else {
         PathClassification::FsPath
@@ -47,9 +61,7 @@ where
     let mut result = EventClassificationResult::default();
 
     for event in events {
-        let path_str = event.path.to_string_lossy();
-
-        match classify_path(&path_str) {
+        match classify_path(&event.path) {
             PathClassification::GitPath => {
                 // Only trigger git change on specific events
                 if matches!(event.kind, DebouncedEventKind::Any) {
@@ -73,21 +85,266 @@ pub fn path_exists(path: &str) -> bool {
 /// Default debounce duration in milliseconds
 pub const DEFAULT_DEBOUNCE_MS: u64 = 300;
 
+/// How often a polling watcher rescans, from the `watcherPollIntervalMs`
+/// setting.
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 2000;
+pub const MIN_POLL_INTERVAL_MS: u64 = 500;
+pub const MAX_POLL_INTERVAL_MS: u64 = 60_000;
+
+static POLL_INTERVAL_MS: AtomicU64 = AtomicU64::new(DEFAULT_POLL_INTERVAL_MS);
+
+/// Apply the `watcherPollIntervalMs` setting. Watchers already polling
+/// keep their interval until they are restarted.
+pub fn apply_poll_interval(ms: u64) {
+    POLL_INTERVAL_MS.store(ms, Ordering::Relaxed);
+}
+
+pub fn poll_interval() -> Duration {
+    Duration::from_millis(POLL_INTERVAL_MS.load(Ordering::Relaxed))
+}
+
+/// How a watcher learns about changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WatcherBackend {
+    /// The platform's file events (inotify, FSEvents,
+    /// ReadDirectoryChangesW).
+    Native,
+    /// Rescanning the tree every poll interval.
+    Poll,
+}
+
+/// Why a watcher polls instead of using native events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PollReason {
+    /// The path is on a filesystem whose changes native events miss.
+    #[serde(rename_all = "camelCase")]
+    Filesystem { fs_type: String },
+    /// Native events couldn't be set up, e.g. out of inotify watches.
+    NativeFailed { error: String },
+}
+
+/// What a watcher has delivered so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherCounts {
+    /// Debounced batches passed on.
+    pub batches: u64,
+    /// Changed paths in them.
+    pub events: u64,
+    /// Backend errors; the changes they were about are lost.
+    pub dropped: u64,
+    pub last_error: Option<String>,
+    /// Milliseconds since the Unix epoch of the last batch.
+    pub last_event_at: Option<u64>,
+}
+
+/// Counters a watcher updates from its event thread.
+#[derive(Debug, Default)]
+pub struct WatcherStats {
+    counts: Mutex<WatcherCounts>,
+}
+
+impl WatcherStats {
+    fn record_batch(&self, events: usize) {
+        let now = now_ms();
+        let mut counts = self.counts.lock_recover();
+        counts.batches += 1;
+        counts.events += events as u64;
+        counts.last_event_at = Some(now);
+    }
+
+    fn record_error(&self, error: &notify::Error) {
+        let mut counts = self.counts.lock_recover();
+        counts.dropped += 1;
+        counts.last_error = Some(error.to_string());
+    }
+
+    pub fn counts(&self) -> WatcherCounts {
+        self.counts.lock_recover().clone()
+    }
+}
+
+/// The running debouncer of either backend; dropping it stops watching.
+pub enum WatcherDebouncer {
+    Native(Debouncer<RecommendedWatcher>),
+    Poll(Debouncer<PollWatcher>),
+}
+
+impl WatcherDebouncer {
+    fn watcher(&mut self) -> &mut dyn Watcher {
+        match self {
+            Self::Native(debouncer) => debouncer.watcher(),
+            Self::Poll(debouncer) => debouncer.watcher(),
+        }
+    }
+}
+
 pub struct WatcherInstance {
     #[allow(dead_code)]
-    pub debouncer: notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>,
-    #[allow(dead_code)]
+    pub debouncer: WatcherDebouncer,
     pub root_path: PathBuf,
+    /// `None` for native events.
+    pub poll_reason: Option<PollReason>,
+    pub stats: Arc<WatcherStats>,
+}
+
+impl WatcherInstance {
+    pub fn backend(&self) -> WatcherBackend {
+        match self.debouncer {
+            WatcherDebouncer::Native(_) => WatcherBackend::Native,
+            WatcherDebouncer::Poll(_) => WatcherBackend::Poll,
+        }
+    }
+}
+
+/// Start a recursive watcher on `root_path` that passes each debounced
+/// batch of events, with its classification, to `on_events`. Errors from
+/// the watcher backend are logged, counted in the watcher's stats and
+/// skipped. Paths on network filesystems and WSL mounts, or where native
+/// events fail to start, are polled instead.
+pub fn start_watcher(
+    root_path: &Path,
+    on_events: impl Fn(&[DebouncedEvent], &EventClassificationResult) + Send + 'static,
+) -> Result<WatcherInstance, String> {
+    start_watcher_with_debounce(root_path, Duration::from_millis(DEFAULT_DEBOUNCE_MS), on_events)
+}
+
+/// [`start_watcher`] with events batched over `debounce` instead of
+/// [`DEFAULT_DEBOUNCE_MS`].
+pub fn start_watcher_with_debounce(
+    root_path: &Path,
+    debounce: Duration,
+    on_events: impl Fn(&[DebouncedEvent], &EventClassificationResult) + Send + 'static,
+) -> Result<WatcherInstance, String> {
+    let stats = Arc::new(WatcherStats::default());
+    // Shared by the native attempt and the polling fallback.
+    let on_events = Arc::new(Mutex::new(on_events));
+    let handler = || {
+        let (stats, on_events) = (Arc::clone(&stats), Arc::clone(&on_events));
+        move |result: DebounceEventResult| match result {
+            Ok(events) => {
+                stats.record_batch(events.len());
+                (on_events.lock_recover())(&events, &classify_events(events.iter()));
+            }
+            Err(e) => {
+                log::warn!("file watcher error: {}", e);
+                stats.record_error(&e);
+            }
+        }
+    };
+    let config = Config::default().with_timeout(debounce);
+
+    let mut poll_reason = filesystem_type(root_path)
+        .filter(|fs_type| native_events_unreliable(fs_type))
+        .map(|fs_type| PollReason::Filesystem { fs_type });
+    if poll_reason.is_none() {
+        let native = new_debouncer_opt::<_, RecommendedWatcher>(config.clone(), handler())
+            .map(WatcherDebouncer::Native)
+            .and_then(|mut debouncer| {
+                debouncer
+                    .watcher()
+                    .watch(root_path, RecursiveMode::Recursive)
+                    .map(|_| debouncer)
+            });
+        match native {
+            Ok(debouncer) => {
+                return Ok(WatcherInstance {
+                    debouncer,
+                    root_path: root_path.to_path_buf(),
+                    poll_reason: None,
+                    stats,
+                })
+            }
+            Err(e) => {
+                poll_reason = Some(PollReason::NativeFailed {
+                    error: e.to_string(),
+                })
+            }
+        }
+    }
+
+    log::info!(
+        "Polling {} for changes: {:?}",
+        root_path.display(),
+        poll_reason
+    );
+    let poll_config = notify::Config::default().with_poll_interval(poll_interval());
+    let mut debouncer = new_debouncer_opt::<_, PollWatcher>(
+        config.with_notify_config(poll_config),
+        handler(),
+    )
+    .map(WatcherDebouncer::Poll)
+    .map_err(|e| e.to_string())?;
+    debouncer
+        .watcher()
+        .watch(root_path, RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+
+    Ok(WatcherInstance {
+        debouncer,
+        root_path: root_path.to_path_buf(),
+        poll_reason,
+        stats,
+    })
+}
+
+/// Identifies one `start_watching` call; `stop_watching` takes it back.
+pub type SubscriptionId = u64;
+
+/// Returned by `start_watching`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchSubscription {
+    pub id: SubscriptionId,
+    pub path: String,
 }
 
+/// One running watcher, for `get_watcher_health`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherStatus {
+    pub path: String,
+    pub backend: WatcherBackend,
+    pub poll_reason: Option<PollReason>,
+    pub subscriptions: usize,
+    #[serde(flatten)]
+    pub counts: WatcherCounts,
+}
+
+/// Returned by `get_watcher_health`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherHealth {
+    pub native: usize,
+    pub polling: usize,
+    pub subscriptions: usize,
+    /// For watchers started from now on.
+    pub poll_interval_ms: u64,
+    /// Sorted by path.
+    pub watchers: Vec<WatcherStatus>,
+}
+
+/// The running watchers, by watched path, shared by everything that
+/// subscribed to the same path. A watcher starts with its first
+/// subscription and stops when the last one is dropped, so one view
+/// stopping does not cut off another's updates. Starting or stopping one
+/// path does not wait on the others; see entity_map.rs for the locking
+/// scheme, in which `subscriptions` comes before the watcher map.
 pub struct WatcherManager {
-    pub instances: HashMap<String, WatcherInstance>,
+    instances: EntityMap<String, WatcherInstance>,
+    /// Watched path of each live subscription.
+    subscriptions: Mutex<HashMap<SubscriptionId, String>>,
+    next_id: AtomicU64,
 }
 
 impl WatcherManager {
     pub fn new() -> Self {
         Self {
-            instances: HashMap::new(),
+            instances: EntityMap::new(),
+            subscriptions: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
         }
     }
 
@@ -95,6 +352,97 @@ impl WatcherManager {
     pub fn is_watching(&self, path: &str) -> bool {
         self.instances.contains_key(path)
     }
+
+    /// Subscribe to `path`, calling `start` for a watcher when none is
+    /// running for it yet.
+    pub fn subscribe(
+        &self,
+        path: &str,
+        mut start: impl FnMut() -> Result<WatcherInstance, String>,
+    ) -> Result<SubscriptionId, String> {
+        loop {
+            if !self.instances.contains_key(path) {
+                // When a concurrent call got there first, the spare
+                // watcher is dropped here, which stops it.
+                let _ = self.instances.try_insert(path.to_string(), start()?);
+            }
+            let mut subscriptions = self.subscriptions.lock_recover();
+            // Unless the last subscriber of a concurrent stop removed the
+            // watcher in between; then start it again.
+            if self.instances.contains_key(path) {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                subscriptions.insert(id, path.to_string());
+                return Ok(id);
+            }
+        }
+    }
+
+    /// Drop a subscription. When it was the last one for its path the
+    /// watcher is removed and returned; dropping it stops watching.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> Option<Arc<WatcherInstance>> {
+        let mut subscriptions = self.subscriptions.lock_recover();
+        let path = subscriptions.remove(&id)?;
+        if subscriptions.values().any(|other| *other == path) {
+            return None;
+        }
+        self.instances.remove(&path)
+    }
+
+    /// The running watchers with how many subscriptions each has.
+    pub fn watchers(&self) -> Vec<(Arc<WatcherInstance>, usize)> {
+        let subscriptions = self.subscriptions.lock_recover();
+        self.instances
+            .entries()
+            .into_iter()
+            .map(|(path, instance)| {
+                let count = subscriptions.values().filter(|p| **p == path).count();
+                (instance, count)
+            })
+            .collect()
+    }
+
+    /// Backends, subscriptions and event counts of the running watchers.
+    pub fn health(&self) -> WatcherHealth {
+        let mut watchers: Vec<WatcherStatus> = self
+            .watchers()
+            .into_iter()
+            .map(|(instance, subscriptions)| WatcherStatus {
+                path: path_to_wire(&instance.root_path),
+                backend: instance.backend(),
+                poll_reason: instance.poll_reason.clone(),
+                subscriptions,
+                counts: instance.stats.counts(),
+            })
+            .collect();
+        watchers.sort_by(|a, b| a.path.cmp(&b.path));
+        let polling = watchers
+            .iter()
+            .filter(|w| w.backend == WatcherBackend::Poll)
+            .count();
+        WatcherHealth {
+            native: watchers.len() - polling,
+            polling,
+            subscriptions: watchers.iter().map(|w| w.subscriptions).sum(),
+            poll_interval_ms: poll_interval().as_millis() as u64,
+            watchers,
+        }
+    }
+
+    /// Stop every watcher, returning the ones that were running.
+    pub fn remove_all(&self) -> Vec<Arc<WatcherInstance>> {
+        let mut subscriptions = self.subscriptions.lock_recover();
+        subscriptions.clear();
+        self.instances.drain()
+    }
+
+    /// Number of running watchers.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
 }
 
 impl Default for WatcherManager {
@@ -103,7 +451,7 @@ impl Default for WatcherManager {
     }
 }
 
-pub type WatcherState = Arc<Mutex<WatcherManager>>;
+pub type WatcherState = Arc<WatcherManager>;
 
 #[cfg(test)]
 mod tests {
@@ -113,13 +461,13 @@ mod tests {
     #[test]
     fn test_watcher_manager_new() {
         let manager = WatcherManager::new();
-        assert!(manager.instances.is_empty());
+        assert!(manager.is_empty());
     }
 
     #[test]
     fn test_watcher_manager_default() {
         let manager = WatcherManager::default();
-        assert!(manager.instances.is_empty());
+        assert!(manager.is_empty());
     }
 
     #[test]
@@ -128,6 +476,56 @@ mod tests {
         assert!(!manager.is_watching("/some/path"));
     }
 
+    fn idle_watcher() -> Result<WatcherInstance, String> {
+        let debouncer = notify_debouncer_mini::new_debouncer(
+            std::time::Duration::from_millis(DEFAULT_DEBOUNCE_MS),
+            |_: notify_debouncer_mini::DebounceEventResult| {},
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(WatcherInstance {
+            debouncer: WatcherDebouncer::Native(debouncer),
+            root_path: PathBuf::from("/repo"),
+            poll_reason: None,
+            stats: Arc::default(),
+        })
+    }
+
+    #[test]
+    fn test_subscriptions_share_one_watcher_until_the_last_stops() {
+        let manager = WatcherManager::new();
+        let mut started = 0;
+        let mut start = || {
+            started += 1;
+            idle_watcher()
+        };
+        let first = manager.subscribe("/repo", &mut start).unwrap();
+        let second = manager.subscribe("/repo", &mut start).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(started, 1);
+        let health = manager.health();
+        assert_eq!((health.native, health.polling, health.subscriptions), (1, 0, 2));
+        assert_eq!(health.watchers[0].counts, WatcherCounts::default());
+
+        assert!(manager.unsubscribe(first).is_none());
+        assert!(manager.is_watching("/repo"));
+        assert!(manager.unsubscribe(second).is_some());
+        assert!(!manager.is_watching("/repo"));
+        // Stopping twice is harmless.
+        assert!(manager.unsubscribe(second).is_none());
+    }
+
+    #[test]
+    fn test_failed_start_leaves_no_subscription() {
+        let manager = WatcherManager::new();
+        let err = manager.subscribe("/repo", || Err("no such directory".to_string()));
+        assert_eq!(err, Err("no such directory".to_string()));
+        assert!(manager.is_empty());
+
+        let id = manager.subscribe("/repo", idle_watcher).unwrap();
+        assert_eq!(manager.remove_all().len(), 1);
+        assert!(manager.unsubscribe(id).is_none());
+    }
+
     #[test]
     fn test_fs_change_event_struct() {
         let event = FsChangeEvent {
@@ -183,6 +581,15 @@ mod tests {
         assert_eq!(classify_path("/repo/.git"), PathClassification::GitPath);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_classify_path_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(OsStr::from_bytes(b"/repo/caf\xe9/.git/HEAD"));
+        assert_eq!(classify_path(path), PathClassification::GitPath);
+    }
+
     #[test]
     fn test_classify_path_regular_file() {
         assert_eq!(
@@ -1,103 +1,172 @@
 //! Tauri command wrappers for file watcher functionality
 //! These are thin wrappers that delegate to the core logic in watcher.rs
 
+use super::documents_commands::on_files_changed;
+use super::error::{ErrorCode, KiriError};
+use super::event_throttle_commands::emit_throttled;
+use super::fs::invalidate_directory_cache_for;
+use super::fs_path::input_path;
+use super::git_cache::git_cache;
+use super::lock_ext::{LockExt, RwLockExt};
+use super::menu::on_git_changed;
+use super::project_config_commands::{is_project_config, on_project_config_changed};
+use super::todo_scanner_commands::on_project_files_changed;
 use super::watcher::{
-    classify_events, path_exists, FsChangeEvent, GitChangeEvent, WatcherInstance, WatcherState,
-    DEFAULT_DEBOUNCE_MS,
+    start_watcher, FsChangeEvent, GitChangeEvent, SubscriptionId, WatchSubscription, WatcherHealth,
+    WatcherInstance, WatcherState,
 };
-use notify::RecursiveMode;
-use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
-use std::path::PathBuf;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter};
-
+use super::window::WindowRegistryState;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Subscribe to changes under `path`, starting a watcher unless one is
+/// already running for it. When `window_label` is given the path must lie
+/// within that window's project or worktree, and the subscription is owned
+/// by the window so closing it drops the subscription.
 #[tauri::command]
 pub fn start_watching(
     app: AppHandle,
     state: tauri::State<'_, WatcherState>,
+    registry: tauri::State<'_, WindowRegistryState>,
     path: String,
-) -> Result<(), String> {
-    let root_path = PathBuf::from(&path);
+    window_label: Option<String>,
+) -> Result<WatchSubscription, KiriError> {
+    let root_path = input_path(&path)?;
+
+    if !root_path.exists() {
+        return Err(KiriError::new(
+            ErrorCode::NotFound,
+            format!("Path does not exist: {}", path),
+        ));
+    }
 
-    if !path_exists(&path) {
-        return Err(format!("Path does not exist: {}", path));
+    if let Some(label) = window_label.as_deref() {
+        if let Some(context) = registry.read_recover().context(label) {
+            if !context.is_path_in_scope(&root_path) {
+                return Err(KiriError::new(
+                    ErrorCode::PermissionDenied,
+                    "Path is outside this window's project",
+                ));
+            }
+        }
     }
 
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
+    let id = state.subscribe(&path, || start_app_watcher(&app, &path, &root_path))?;
 
-    // Already watching this path
-    if manager.is_watching(&path) {
-        return Ok(());
+    // Git results under this root are only cached now that changes to it
+    // reach the cache.
+    git_cache().lock_recover().watch(&root_path);
+
+    if let Some(label) = window_label.as_deref() {
+        registry
+            .write_recover()
+            .contexts_mut()
+            .attach_watcher(label, id);
     }
 
-    let app_handle = app.clone();
-    let watched_path = path.clone();
-
-    // Create debounced watcher with default delay
-    let mut debouncer = new_debouncer(
-        Duration::from_millis(DEFAULT_DEBOUNCE_MS),
-        move |result: DebounceEventResult| {
-            if let Ok(events) = result {
-                let classification = classify_events(events.iter());
-
-                // Emit consolidated events
-                if classification.fs_changed {
-                    let _ = app_handle.emit(
-                        "fs-changed",
-                        FsChangeEvent {
-                            path: watched_path.clone(),
-                        },
-                    );
-                }
-
-                if classification.git_changed {
-                    let _ = app_handle.emit(
-                        "git-status-changed",
-                        GitChangeEvent {
-                            repo_root: watched_path.clone(),
-                        },
-                    );
-                }
-            }
-        },
-    )
-    .map_err(|e| e.to_string())?;
-
-    // Start watching the directory recursively
-    debouncer
-        .watcher()
-        .watch(&root_path, RecursiveMode::Recursive)
-        .map_err(|e| e.to_string())?;
-
-    manager.instances.insert(
-        path,
-        WatcherInstance {
-            debouncer,
-            root_path,
-        },
-    );
+    Ok(WatchSubscription { id, path })
+}
 
-    Ok(())
+/// Start a recursive, debounced watcher on `root_path` that emits
+/// `fs-changed` and `git-status-changed` for `path`.
+fn start_app_watcher(
+    app: &AppHandle,
+    path: &str,
+    root_path: &Path,
+) -> Result<WatcherInstance, String> {
+    let app_handle = app.clone();
+    let watched_path = path.to_string();
+    let watched_root = root_path.to_path_buf();
+
+    start_watcher(root_path, move |events, classification| {
+        if classification.fs_changed || classification.git_changed {
+            git_cache().lock_recover().invalidate(&watched_root);
+        }
+        for event in events {
+            invalidate_directory_cache_for(&event.path);
+        }
+
+        // Emit consolidated events
+        if events.iter().any(|event| is_project_config(&event.path)) {
+            on_project_config_changed(&app_handle, &watched_path);
+        }
+
+        if classification.fs_changed {
+            on_project_files_changed(&app_handle, &watched_path);
+            on_files_changed(&app_handle, events);
+            emit_throttled(
+                &app_handle,
+                "fs-changed",
+                &watched_path,
+                &FsChangeEvent {
+                    path: watched_path.clone(),
+                },
+            );
+        }
+
+        if classification.git_changed {
+            on_git_changed(&app_handle, &watched_path);
+            emit_throttled(
+                &app_handle,
+                "git-status-changed",
+                &watched_path,
+                &GitChangeEvent {
+                    repo_root: watched_path.clone(),
+                },
+            );
+        }
+    })
 }
 
+/// Drop subscription `subscription_id`. The watcher stops once no other
+/// subscription for its path is left. When `window_label` is given, the
+/// subscription must belong to that window.
 #[tauri::command]
-pub fn stop_watching(state: tauri::State<'_, WatcherState>, path: String) -> Result<(), String> {
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
+pub fn stop_watching(
+    state: tauri::State<'_, WatcherState>,
+    registry: tauri::State<'_, WindowRegistryState>,
+    subscription_id: SubscriptionId,
+    window_label: Option<String>,
+) -> Result<(), KiriError> {
+    {
+        let mut reg = registry.write_recover();
+        if let Some(label) = window_label.as_deref() {
+            if !reg.contexts().owns_watcher(label, subscription_id) {
+                return Err(KiriError::new(
+                    ErrorCode::InvalidInput,
+                    format!(
+                        "Watch subscription {} does not belong to this window",
+                        subscription_id
+                    ),
+                ));
+            }
+        }
+        reg.contexts_mut().detach_watcher(subscription_id);
+    }
 
-    if manager.instances.remove(&path).is_some() {
-        log::info!("Stopped watching: {}", path);
+    if let Some(instance) = state.unsubscribe(subscription_id) {
+        git_cache().lock_recover().unwatch(&instance.root_path);
+        log::info!("Stopped watching: {}", instance.root_path.display());
     }
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn stop_all_watching(state: tauri::State<'_, WatcherState>) -> Result<(), String> {
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
-
-    let count = manager.instances.len();
-    manager.instances.clear();
+pub fn stop_all_watching(state: tauri::State<'_, WatcherState>) -> Result<(), KiriError> {
+    let count = state.remove_all().len();
+    git_cache().lock_recover().clear();
     log::info!("Stopped all watchers ({})", count);
 
     Ok(())
 }
+
+/// Which backend each running watcher uses (native events, or polling on
+/// network filesystems and WSL mounts), its subscriptions, and how many
+/// events it delivered or lost to backend errors.
+#[tauri::command]
+pub fn get_watcher_health(
+    state: tauri::State<'_, WatcherState>,
+) -> Result<WatcherHealth, KiriError> {
+    Ok(state.health())
+}
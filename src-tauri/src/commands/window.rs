@@ -1,11 +1,18 @@
 use crate::commands::cli_server::{self, CliServerRegistryState};
-use crate::commands::lock_ext::LockExt;
+use crate::commands::git_cache::git_cache;
+use crate::commands::lock_ext::{LockExt, RwLockExt};
+use crate::commands::operations::operations;
 use crate::commands::terminal::{TerminalOutputBusState, TerminalState};
+use crate::commands::terminal_commands::shutdown_terminal;
+use crate::commands::watcher::WatcherState;
+use crate::commands::window_context::{WindowContext, WindowContexts};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
 
+use super::error::KiriError;
+
 static WINDOW_COUNTER: AtomicU32 = AtomicU32::new(1);
 
 /// Registry to track which windows are associated with which project paths
@@ -15,6 +22,8 @@ pub struct WindowRegistry {
     path_to_label: HashMap<String, String>,
     /// Maps window labels to project paths
     label_to_path: HashMap<String, String>,
+    /// Per-window project context (worktree, watchers, terminals)
+    contexts: WindowContexts,
 }
 
 impl WindowRegistry {
@@ -35,6 +44,7 @@ impl WindowRegistry {
             .insert(path.to_string(), label.to_string());
         self.label_to_path
             .insert(label.to_string(), path.to_string());
+        self.contexts.set_project(label, path);
     }
 
     /// Unregister a window by its label
@@ -44,6 +54,28 @@ impl WindowRegistry {
         }
     }
 
+    /// Unregister a window and hand back its context so the caller can
+    /// release the terminals and watchers it owned.
+    pub fn release(&mut self, label: &str) -> Option<WindowContext> {
+        self.unregister_by_label(label);
+        self.contexts.release(label)
+    }
+
+    /// Project context for a window label
+    pub fn context(&self, label: &str) -> Option<&WindowContext> {
+        self.contexts.get(label)
+    }
+
+    /// Per-window contexts
+    pub fn contexts(&self) -> &WindowContexts {
+        &self.contexts
+    }
+
+    /// Mutable access to per-window contexts
+    pub fn contexts_mut(&mut self) -> &mut WindowContexts {
+        &mut self.contexts
+    }
+
     /// Get the window label for a project path
     pub fn get_label_for_path(&self, path: &str) -> Option<&String> {
         self.path_to_label.get(path)
@@ -54,13 +86,30 @@ impl WindowRegistry {
         self.label_to_path.get(label)
     }
 
+    /// Label of the window that owns `path`: the window registered for
+    /// exactly that path, else the one whose project root or selected
+    /// worktree contains it.
+    pub fn owner_of(&self, path: &str) -> Option<String> {
+        self.path_to_label
+            .get(path)
+            .or_else(|| {
+                self.contexts
+                    .owner_of(std::path::Path::new(path))
+                    .and_then(|label| self.label_to_path.get_key_value(label).map(|(l, _)| l))
+            })
+            .cloned()
+    }
+
     /// Get all registered project paths
     pub fn get_all_paths(&self) -> Vec<String> {
         self.path_to_label.keys().cloned().collect()
     }
 }
 
-pub type WindowRegistryState = Arc<Mutex<WindowRegistry>>;
+/// Shared window registry. Lookups (which window owns a path, a window's
+/// context) take the read lock; see entity_map.rs for how it is ordered
+/// against the terminal and watcher locks.
+pub type WindowRegistryState = Arc<RwLock<WindowRegistry>>;
 
 /// Generate window title from an optional project path
 fn window_title(project_path: Option<&str>) -> String {
@@ -133,11 +182,24 @@ pub fn create_window_impl(
         builder = builder.position(pos_x as f64, pos_y as f64);
     }
 
+    // Share one tabbing identifier so any two kiri windows can be merged
+    // into native tabs, and join the focused window's tab bar when the
+    // "open as tabs" setting is on.
+    #[cfg(target_os = "macos")]
+    {
+        builder = builder.tabbing_identifier(super::window_tabs::TABBING_IDENTIFIER);
+    }
+    let tab_parent = super::window_tabs_commands::tab_parent_for_new_window(app);
+
     builder.build().map_err(|e| e.to_string())?;
 
+    if let Some(parent) = tab_parent {
+        super::window_tabs_commands::add_as_tab(app, &parent, &label);
+    }
+
     // Register the window with its project path
     if let (Some(path), Some(registry)) = (project_path, registry) {
-        if let Ok(mut reg) = registry.lock() {
+        if let Ok(mut reg) = registry.write() {
             reg.register(&label, &path);
         }
     }
@@ -154,8 +216,80 @@ pub fn create_window(
     width: Option<f64>,
     height: Option<f64>,
     project_path: Option<String>,
-) -> Result<(), String> {
-    create_window_impl(&app, Some(&registry), x, y, width, height, project_path).map(|_| ())
+) -> Result<(), KiriError> {
+    create_window_impl(&app, Some(&registry), x, y, width, height, project_path)
+        .map(|_| ())
+        .map_err(KiriError::from)
+}
+
+/// Where a project request ended up.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", content = "label", rename_all = "camelCase")]
+pub enum RouteOutcome {
+    /// An existing window that owns the path was focused.
+    Focused(String),
+    /// No live window owned the path, so one was created for it.
+    Created(String),
+}
+
+fn focus_or_create(
+    app: &AppHandle,
+    registry: &WindowRegistryState,
+    project_path: String,
+    owner: Option<String>,
+) -> Result<RouteOutcome, String> {
+    if let Some(label) = owner {
+        if let Some(window) = app.get_webview_window(&label) {
+            let _ = window.unminimize();
+            window
+                .set_focus()
+                .map_err(|e| format!("Failed to focus window: {}", e))?;
+            return Ok(RouteOutcome::Focused(label));
+        }
+        // Window no longer exists, clean up registry
+        registry.write_recover().unregister_by_label(&label);
+    }
+    let label = create_window_impl(
+        app,
+        Some(registry),
+        None,
+        None,
+        None,
+        None,
+        Some(project_path),
+    )?;
+    Ok(RouteOutcome::Created(label))
+}
+
+/// Bring up the window that owns `path` (a project, worktree, or any path
+/// inside one), or create a window with `path` as its project. The entry
+/// point for everything that addresses a project by path rather than by
+/// window: the Worktrees menu, tray, deep links, second launches and
+/// notifications.
+pub fn route_to_project(
+    app: &AppHandle,
+    registry: &WindowRegistryState,
+    path: &str,
+) -> Result<RouteOutcome, String> {
+    let owner = registry.read_recover().owner_of(path);
+    focus_or_create(app, registry, path.to_string(), owner)
+}
+
+/// Focus the window showing exactly `project_path`, or create one. Returns
+/// `true` when an existing window was focused. Unlike
+/// [`route_to_project`], a window for a parent directory does not count,
+/// so a subdirectory can still be opened as its own project.
+pub fn focus_or_create_window_impl(
+    app: &AppHandle,
+    registry: &WindowRegistryState,
+    project_path: String,
+) -> Result<bool, String> {
+    let owner = registry
+        .read_recover()
+        .get_label_for_path(&project_path)
+        .cloned();
+    focus_or_create(app, registry, project_path, owner)
+        .map(|outcome| matches!(outcome, RouteOutcome::Focused(_)))
 }
 
 /// Focus an existing window for the given project path, or create a new one if not found
@@ -164,30 +298,20 @@ pub fn focus_or_create_window(
     app: AppHandle,
     registry: tauri::State<WindowRegistryState>,
     project_path: String,
-) -> Result<bool, String> {
-    // Check if a window already exists for this path
-    let existing_label = {
-        let reg = registry.lock().map_err(|e| format!("Lock error: {}", e))?;
-        reg.get_label_for_path(&project_path).cloned()
-    };
-
-    if let Some(label) = existing_label {
-        // Check if the window still exists
-        if let Some(window) = app.get_webview_window(&label) {
-            // Window exists, focus it
-            window.set_focus().map_err(|e| format!("Failed to focus window: {}", e))?;
-            return Ok(true); // Indicates existing window was focused
-        } else {
-            // Window no longer exists, clean up registry
-            if let Ok(mut reg) = registry.lock() {
-                reg.unregister_by_label(&label);
-            }
-        }
-    }
+) -> Result<bool, KiriError> {
+    focus_or_create_window_impl(&app, &registry, project_path).map_err(KiriError::from)
+}
 
-    // No existing window, create a new one
-    create_window_impl(&app, Some(&registry), None, None, None, None, Some(project_path))?;
-    Ok(false) // Indicates new window was created
+/// Focus the window that owns `path` or open one for it. Used when the
+/// user acts on something that points at a path, such as a terminal
+/// notification.
+#[tauri::command]
+pub fn focus_project(
+    app: AppHandle,
+    registry: tauri::State<WindowRegistryState>,
+    path: String,
+) -> Result<RouteOutcome, KiriError> {
+    route_to_project(&app, &registry, &path).map_err(KiriError::from)
 }
 
 /// Register a window with a project path (for windows not created via create_window)
@@ -200,8 +324,8 @@ pub fn register_window(
     bus: tauri::State<TerminalOutputBusState>,
     label: String,
     project_path: String,
-) -> Result<(), String> {
-    if let Ok(mut reg) = registry.lock() {
+) -> Result<(), KiriError> {
+    if let Ok(mut reg) = registry.write() {
         reg.register(&label, &project_path);
     }
     start_cli_server_if_absent(&app, &cli_registry, &terminals, &bus, &label);
@@ -209,8 +333,10 @@ pub fn register_window(
 }
 
 /// Release all backend resources tied to a window label: drop its
-/// project-path registry entry and stop its per-window CLI server (which
-/// removes the socket file).
+/// project-path registry entry, stop its per-window CLI server (which
+/// removes the socket file), kill the terminals it created, cancel the
+/// operations it started, and drop its watch subscriptions, stopping each
+/// watcher no other subscription still uses.
 ///
 /// Idempotent — safe to call for a label that was never registered or has
 /// already been cleaned up, so the frontend `unregister_window` command and
@@ -219,13 +345,37 @@ pub fn register_window(
 pub fn cleanup_window_resources(
     registry: &WindowRegistryState,
     cli_registry: &CliServerRegistryState,
+    terminals: &TerminalState,
+    bus: &TerminalOutputBusState,
+    watchers: &WatcherState,
     label: &str,
 ) {
     // Recover a poisoned lock rather than silently skipping cleanup: the
     // whole point of this function is to guarantee teardown, and the inner
     // map is a valid value even if a previous holder panicked mid-mutation.
-    registry.lock_recover().unregister_by_label(label);
+    let context = registry.write_recover().release(label).unwrap_or_default();
     cli_registry.stop_and_remove(label);
+    let cancelled = operations().lock_recover().cancel_window(label);
+
+    for id in &context.terminal_ids {
+        shutdown_terminal(terminals, bus, *id);
+    }
+    let mut stopped_watchers = 0;
+    for id in &context.watch_subscriptions {
+        if let Some(instance) = watchers.unsubscribe(*id) {
+            git_cache().lock_recover().unwatch(&instance.root_path);
+            stopped_watchers += 1;
+        }
+    }
+    if !context.terminal_ids.is_empty() || !context.watch_subscriptions.is_empty() || cancelled > 0
+    {
+        log::info!(
+            "released window {label}: {} terminal(s), {} watcher(s) stopped, {} operation(s)",
+            context.terminal_ids.len(),
+            stopped_watchers,
+            cancelled
+        );
+    }
 }
 
 /// Unregister a window from the registry (called when window is closed)
@@ -233,9 +383,37 @@ pub fn cleanup_window_resources(
 pub fn unregister_window(
     registry: tauri::State<WindowRegistryState>,
     cli_registry: tauri::State<CliServerRegistryState>,
+    terminals: tauri::State<TerminalState>,
+    bus: tauri::State<TerminalOutputBusState>,
+    watchers: tauri::State<WatcherState>,
     label: String,
-) -> Result<(), String> {
-    cleanup_window_resources(&registry, &cli_registry, &label);
+) -> Result<(), KiriError> {
+    cleanup_window_resources(&registry, &cli_registry, &terminals, &bus, &watchers, &label);
+    Ok(())
+}
+
+/// Return the project context (root, worktree, watchers, terminals) a
+/// window currently owns.
+#[tauri::command]
+pub fn get_window_context(
+    registry: tauri::State<WindowRegistryState>,
+    label: String,
+) -> Result<Option<WindowContext>, KiriError> {
+    Ok(registry.read_recover().context(&label).cloned())
+}
+
+/// Record the worktree a window has switched to. Paths inside it count as
+/// in scope for that window's commands.
+#[tauri::command]
+pub fn set_window_worktree(
+    registry: tauri::State<WindowRegistryState>,
+    label: String,
+    worktree: Option<String>,
+) -> Result<(), KiriError> {
+    registry
+        .write_recover()
+        .contexts_mut()
+        .set_worktree(&label, worktree);
     Ok(())
 }
 
@@ -415,6 +593,27 @@ mod tests {
         assert_eq!(reg.get_label_for_path("/path/a"), None);
     }
 
+    #[test]
+    fn test_registry_register_sets_context_project() {
+        let mut reg = WindowRegistry::new();
+        reg.register("window-1", "/path/a");
+        assert_eq!(
+            reg.context("window-1").and_then(|c| c.project_path.as_deref()),
+            Some("/path/a")
+        );
+    }
+
+    #[test]
+    fn test_registry_release_returns_context_and_unregisters() {
+        let mut reg = WindowRegistry::new();
+        reg.register("window-1", "/path/a");
+        reg.contexts_mut().attach_terminal("window-1", 4);
+        let released = reg.release("window-1").unwrap();
+        assert!(released.terminal_ids.contains(&4));
+        assert_eq!(reg.get_label_for_path("/path/a"), None);
+        assert!(reg.context("window-1").is_none());
+    }
+
     #[test]
     fn test_registry_multiple_windows_independent() {
         let mut reg = WindowRegistry::new();
@@ -427,4 +626,33 @@ mod tests {
         assert_eq!(reg.get_label_for_path("/path/b"), Some(&"window-2".to_string()));
         assert_eq!(reg.get_label_for_path("/path/c"), Some(&"window-1".to_string()));
     }
+
+    #[test]
+    fn test_registry_owner_of_exact_then_containing() {
+        let mut reg = WindowRegistry::new();
+        reg.register("window-1", "/repo");
+        reg.register("window-2", "/repo/packages/app");
+        reg.contexts_mut()
+            .set_worktree("window-1", Some("/worktrees/feature".to_string()));
+
+        assert_eq!(reg.owner_of("/repo").as_deref(), Some("window-1"));
+        assert_eq!(reg.owner_of("/repo/src").as_deref(), Some("window-1"));
+        assert_eq!(
+            reg.owner_of("/repo/packages/app/src").as_deref(),
+            Some("window-2")
+        );
+        assert_eq!(
+            reg.owner_of("/worktrees/feature/src").as_deref(),
+            Some("window-1")
+        );
+        assert_eq!(reg.owner_of("/elsewhere"), None);
+    }
+
+    #[test]
+    fn test_registry_owner_of_ignores_unregistered_windows() {
+        let mut reg = WindowRegistry::new();
+        reg.register("window-1", "/repo");
+        reg.unregister_by_label("window-1");
+        assert_eq!(reg.owner_of("/repo/src"), None);
+    }
 }
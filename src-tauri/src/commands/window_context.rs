@@ -0,0 +1,237 @@
+//! Per-window project context owned by [`super::window::WindowRegistry`].
+//!
+//! Each window tracks the project root it opened, the worktree it has
+//! selected, and the watchers and terminals it started. Commands use this
+//! to check that a request stays inside the calling window's scope, and
+//! window teardown uses it to release exactly the resources that window
+//! owned instead of relying on the frontend to clean up.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::fs_path::{is_within, lexical_normalize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct WindowContext {
+    pub project_path: Option<String>,
+    pub worktree: Option<String>,
+    pub watch_subscriptions: HashSet<u64>,
+    pub terminal_ids: HashSet<u32>,
+}
+
+impl WindowContext {
+    /// Whether `path` is inside the window's project root or selected
+    /// worktree, compared in NFC after resolving `..`. A window with no
+    /// project open has no restriction.
+    pub fn is_path_in_scope(&self, path: &Path) -> bool {
+        let roots: Vec<&String> = self
+            .project_path
+            .iter()
+            .chain(self.worktree.iter())
+            .collect();
+        if roots.is_empty() {
+            return true;
+        }
+        let Some(path) = lexical_normalize(path) else {
+            return false;
+        };
+        roots
+            .iter()
+            .any(|root| is_within(&path, Path::new(root.as_str())))
+    }
+}
+
+/// All window contexts keyed by window label.
+#[derive(Debug, Default)]
+pub struct WindowContexts {
+    contexts: HashMap<String, WindowContext>,
+}
+
+impl WindowContexts {
+    pub fn get(&self, label: &str) -> Option<&WindowContext> {
+        self.contexts.get(label)
+    }
+
+    fn entry(&mut self, label: &str) -> &mut WindowContext {
+        self.contexts.entry(label.to_string()).or_default()
+    }
+
+    /// Switching project clears the worktree selection, which belonged to
+    /// the previous project.
+    pub fn set_project(&mut self, label: &str, path: &str) {
+        let ctx = self.entry(label);
+        if ctx.project_path.as_deref() != Some(path) {
+            ctx.worktree = None;
+        }
+        ctx.project_path = Some(path.to_string());
+    }
+
+    pub fn set_worktree(&mut self, label: &str, worktree: Option<String>) {
+        self.entry(label).worktree = worktree;
+    }
+
+    pub fn attach_terminal(&mut self, label: &str, id: u32) {
+        self.entry(label).terminal_ids.insert(id);
+    }
+
+    /// Forget terminal `id` in whichever window owns it.
+    pub fn detach_terminal(&mut self, id: u32) {
+        for ctx in self.contexts.values_mut() {
+            ctx.terminal_ids.remove(&id);
+        }
+    }
+
+    pub fn owns_terminal(&self, label: &str, id: u32) -> bool {
+        self.contexts
+            .get(label)
+            .is_some_and(|ctx| ctx.terminal_ids.contains(&id))
+    }
+
+    pub fn attach_watcher(&mut self, label: &str, subscription_id: u64) {
+        self.entry(label)
+            .watch_subscriptions
+            .insert(subscription_id);
+    }
+
+    /// Forget watch subscription `subscription_id` in whichever window
+    /// owns it.
+    pub fn detach_watcher(&mut self, subscription_id: u64) {
+        for ctx in self.contexts.values_mut() {
+            ctx.watch_subscriptions.remove(&subscription_id);
+        }
+    }
+
+    pub fn owns_watcher(&self, label: &str, subscription_id: u64) -> bool {
+        self.contexts
+            .get(label)
+            .is_some_and(|ctx| ctx.watch_subscriptions.contains(&subscription_id))
+    }
+
+    /// Label of the window that owns `path`: the one whose project root or
+    /// selected worktree contains it. The most specific root wins, so a
+    /// worktree nested inside another project still routes to the window
+    /// showing that worktree; ties go to the lowest label.
+    pub fn owner_of(&self, path: &Path) -> Option<&str> {
+        self.contexts
+            .iter()
+            .filter_map(|(label, ctx)| {
+                ctx.project_path
+                    .iter()
+                    .chain(ctx.worktree.iter())
+                    .filter(|root| path.starts_with(root.as_str()))
+                    .map(|root| Path::new(root).components().count())
+                    .max()
+                    .map(|depth| (depth, label.as_str()))
+            })
+            .max_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.cmp(a.1)))
+            .map(|(_, label)| label)
+    }
+
+    /// Project roots and selected worktrees of every window, deduplicated.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        let roots: BTreeSet<&String> = self
+            .contexts
+            .values()
+            .flat_map(|ctx| ctx.project_path.iter().chain(ctx.worktree.iter()))
+            .collect();
+        roots.into_iter().map(PathBuf::from).collect()
+    }
+
+    /// Remove and return the context for `label` so the caller can release
+    /// its terminals and watchers.
+    pub fn release(&mut self, label: &str) -> Option<WindowContext> {
+        self.contexts.remove(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_project_resets_worktree_on_change() {
+        let mut contexts = WindowContexts::default();
+        contexts.set_project("w1", "/repo");
+        contexts.set_worktree("w1", Some("/repo-wt".to_string()));
+        contexts.set_project("w1", "/repo");
+        assert_eq!(
+            contexts.get("w1").unwrap().worktree.as_deref(),
+            Some("/repo-wt")
+        );
+        contexts.set_project("w1", "/other");
+        assert_eq!(contexts.get("w1").unwrap().worktree, None);
+    }
+
+    #[test]
+    fn test_terminal_ownership() {
+        let mut contexts = WindowContexts::default();
+        contexts.attach_terminal("w1", 3);
+        assert!(contexts.owns_terminal("w1", 3));
+        assert!(!contexts.owns_terminal("w2", 3));
+        contexts.detach_terminal(3);
+        assert!(!contexts.owns_terminal("w1", 3));
+    }
+
+    #[test]
+    fn test_watcher_ownership() {
+        let mut contexts = WindowContexts::default();
+        contexts.attach_watcher("w1", 1);
+        contexts.attach_watcher("w2", 2);
+        assert!(contexts.owns_watcher("w1", 1));
+        assert!(!contexts.owns_watcher("w1", 2));
+        contexts.detach_watcher(1);
+        assert!(!contexts.owns_watcher("w1", 1));
+        assert!(contexts.owns_watcher("w2", 2));
+    }
+
+    #[test]
+    fn test_release_returns_owned_resources() {
+        let mut contexts = WindowContexts::default();
+        contexts.set_project("w1", "/repo");
+        contexts.attach_terminal("w1", 1);
+        contexts.attach_watcher("w1", 7);
+        let released = contexts.release("w1").unwrap();
+        assert!(released.terminal_ids.contains(&1));
+        assert!(released.watch_subscriptions.contains(&7));
+        assert!(contexts.get("w1").is_none());
+        assert!(contexts.release("w1").is_none());
+    }
+
+    #[test]
+    fn test_path_scope() {
+        let ctx = WindowContext {
+            project_path: Some("/repo".to_string()),
+            worktree: Some("/worktrees/feature".to_string()),
+            ..Default::default()
+        };
+        assert!(ctx.is_path_in_scope(Path::new("/repo/src/main.rs")));
+        assert!(ctx.is_path_in_scope(Path::new("/worktrees/feature/a")));
+        assert!(!ctx.is_path_in_scope(Path::new("/repo-other/a")));
+        assert!(!ctx.is_path_in_scope(Path::new("/etc/passwd")));
+        assert!(!ctx.is_path_in_scope(Path::new("/repo/../etc/passwd")));
+        assert!(WindowContext::default().is_path_in_scope(Path::new("/anywhere")));
+    }
+
+    #[test]
+    fn test_owner_of_prefers_most_specific_root() {
+        let mut contexts = WindowContexts::default();
+        contexts.set_project("w1", "/repo");
+        contexts.set_project("w2", "/other");
+        contexts.set_worktree("w2", Some("/repo/.worktrees/feature".to_string()));
+        assert_eq!(contexts.owner_of(Path::new("/repo/src")), Some("w1"));
+        assert_eq!(
+            contexts.owner_of(Path::new("/repo/.worktrees/feature/src")),
+            Some("w2")
+        );
+        assert_eq!(contexts.owner_of(Path::new("/other")), Some("w2"));
+        assert_eq!(contexts.owner_of(Path::new("/repo-other")), None);
+    }
+
+    #[test]
+    fn test_owner_of_ties_go_to_lowest_label() {
+        let mut contexts = WindowContexts::default();
+        contexts.set_project("w2", "/repo");
+        contexts.set_project("w1", "/repo");
+        assert_eq!(contexts.owner_of(Path::new("/repo/a")), Some("w1"));
+    }
+}
@@ -0,0 +1,220 @@
+//! Saved window geometry and validation against the current monitors.
+//!
+//! Geometry is stored in physical pixels together with the scale factor of
+//! the monitor the window was on, because logical coordinates are
+//! ambiguous on a desktop that mixes DPIs. At restore time the saved
+//! rectangle is matched against the monitors that exist *now*: a window
+//! whose monitor was disconnected is moved (and rescaled) onto the primary
+//! monitor instead of reopening off-screen.
+
+use serde::{Deserialize, Serialize};
+
+/// Minimum part of the window, in logical pixels, that must overlap a
+/// monitor's work area for the saved position to be kept. Enough to grab
+/// the title bar and drag the window back.
+const MIN_VISIBLE_LOGICAL: f64 = 64.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometry {
+    /// Outer position in physical pixels.
+    pub x: i32,
+    pub y: i32,
+    /// Inner size in physical pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Scale factor of the monitor the geometry was captured on.
+    pub scale_factor: f64,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// A monitor's work area (screen minus menu bar, dock or taskbar) in
+/// physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl MonitorArea {
+    fn overlap(&self, g: &WindowGeometry) -> (i64, i64) {
+        let left = i64::from(self.x).max(i64::from(g.x));
+        let top = i64::from(self.y).max(i64::from(g.y));
+        let right =
+            (i64::from(self.x) + i64::from(self.width)).min(i64::from(g.x) + i64::from(g.width));
+        let bottom =
+            (i64::from(self.y) + i64::from(self.height)).min(i64::from(g.y) + i64::from(g.height));
+        ((right - left).max(0), (bottom - top).max(0))
+    }
+
+    /// Whether the window's top edge (where the title bar is) lies inside
+    /// this monitor with enough of it showing to be dragged.
+    fn shows_title_bar(&self, g: &WindowGeometry) -> bool {
+        let (w, h) = self.overlap(g);
+        let min = (MIN_VISIBLE_LOGICAL * self.scale_factor).round() as i64;
+        let top_inside =
+            g.y >= self.y && i64::from(g.y) < i64::from(self.y) + i64::from(self.height);
+        top_inside && w >= min.min(i64::from(g.width)) && h > 0
+    }
+}
+
+/// Fit `saved` onto `monitors`.
+///
+/// The saved position is kept when the window's title bar is reachable on
+/// the monitor it overlaps most; the size is then clamped to that monitor.
+/// Otherwise the window is centered on `primary` (or the first monitor),
+/// with its size converted to that monitor's scale factor so it keeps the
+/// same logical size. With no monitor information the geometry is returned
+/// unchanged.
+pub fn fit_to_monitors(
+    saved: &WindowGeometry,
+    monitors: &[MonitorArea],
+    primary: Option<&MonitorArea>,
+) -> WindowGeometry {
+    let best = monitors
+        .iter()
+        .map(|m| {
+            let (w, h) = m.overlap(saved);
+            (m, w * h)
+        })
+        .filter(|(_, area)| *area > 0)
+        .max_by_key(|(_, area)| *area)
+        .map(|(m, _)| m)
+        .filter(|m| m.shows_title_bar(saved));
+
+    let (monitor, center) = match best.or(primary).or(monitors.first()) {
+        Some(m) => (m, best.is_none()),
+        None => return *saved,
+    };
+
+    let (mut width, mut height) = (saved.width, saved.height);
+    if saved.scale_factor > 0.0 && (monitor.scale_factor - saved.scale_factor).abs() > f64::EPSILON
+    {
+        let ratio = monitor.scale_factor / saved.scale_factor;
+        width = (f64::from(width) * ratio).round() as u32;
+        height = (f64::from(height) * ratio).round() as u32;
+    }
+    width = width.min(monitor.width);
+    height = height.min(monitor.height);
+
+    let (x, y) = if center {
+        (
+            monitor.x + ((monitor.width - width) / 2) as i32,
+            monitor.y + ((monitor.height - height) / 2) as i32,
+        )
+    } else {
+        let max_x = monitor.x + (monitor.width - width) as i32;
+        let max_y = monitor.y + (monitor.height - height) as i32;
+        (
+            saved.x.clamp(monitor.x, max_x),
+            saved.y.clamp(monitor.y, max_y),
+        )
+    };
+
+    WindowGeometry {
+        x,
+        y,
+        width,
+        height,
+        scale_factor: monitor.scale_factor,
+        maximized: saved.maximized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(x: i32, y: i32, width: u32, height: u32, scale_factor: f64) -> MonitorArea {
+        MonitorArea {
+            x,
+            y,
+            width,
+            height,
+            scale_factor,
+        }
+    }
+
+    fn geometry(x: i32, y: i32, width: u32, height: u32, scale_factor: f64) -> WindowGeometry {
+        WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+            scale_factor,
+            maximized: false,
+        }
+    }
+
+    #[test]
+    fn test_visible_geometry_is_kept() {
+        let screens = [monitor(0, 0, 2560, 1440, 1.0)];
+        let saved = geometry(100, 100, 1200, 800, 1.0);
+        assert_eq!(fit_to_monitors(&saved, &screens, None), saved);
+    }
+
+    #[test]
+    fn test_disconnected_monitor_recenters_on_primary() {
+        // Saved on a second monitor to the right that no longer exists.
+        let primary = monitor(0, 0, 1920, 1080, 1.0);
+        let saved = geometry(2500, 200, 1200, 800, 1.0);
+        let fitted = fit_to_monitors(&saved, &[primary], Some(&primary));
+        assert_eq!((fitted.x, fitted.y), (360, 140));
+        assert_eq!((fitted.width, fitted.height), (1200, 800));
+    }
+
+    #[test]
+    fn test_partially_offscreen_is_clamped_onto_monitor() {
+        let screens = [monitor(0, 0, 1920, 1080, 1.0)];
+        let saved = geometry(1500, 500, 1200, 800, 1.0);
+        let fitted = fit_to_monitors(&saved, &screens, None);
+        assert_eq!((fitted.x, fitted.y), (720, 280));
+    }
+
+    #[test]
+    fn test_title_bar_above_screen_is_recentered() {
+        let screens = [monitor(0, 0, 1920, 1080, 1.0)];
+        let saved = geometry(100, -600, 1200, 800, 1.0);
+        let fitted = fit_to_monitors(&saved, &screens, None);
+        assert_eq!((fitted.x, fitted.y), (360, 140));
+    }
+
+    #[test]
+    fn test_rescales_size_for_different_dpi() {
+        // Saved on a 1x external display, restored onto a 2x laptop panel.
+        let retina = monitor(0, 0, 2880, 1800, 2.0);
+        let saved = geometry(3000, 0, 1000, 700, 1.0);
+        let fitted = fit_to_monitors(&saved, &[retina], Some(&retina));
+        assert_eq!((fitted.width, fitted.height), (2000, 1400));
+        assert_eq!(fitted.scale_factor, 2.0);
+    }
+
+    #[test]
+    fn test_size_clamped_to_smaller_monitor() {
+        let small = monitor(0, 0, 1280, 720, 1.0);
+        let saved = geometry(0, 0, 2000, 1200, 1.0);
+        let fitted = fit_to_monitors(&saved, &[small], None);
+        assert_eq!(
+            (fitted.x, fitted.y, fitted.width, fitted.height),
+            (0, 0, 1280, 720)
+        );
+    }
+
+    #[test]
+    fn test_picks_monitor_with_largest_overlap() {
+        let left = monitor(0, 0, 1920, 1080, 1.0);
+        let right = monitor(1920, 0, 2560, 1440, 1.5);
+        let saved = geometry(2000, 100, 1200, 800, 1.5);
+        assert_eq!(fit_to_monitors(&saved, &[left, right], Some(&left)), saved);
+    }
+
+    #[test]
+    fn test_no_monitors_returns_saved() {
+        let saved = geometry(-5000, -5000, 800, 600, 1.0);
+        assert_eq!(fit_to_monitors(&saved, &[], None), saved);
+    }
+}
@@ -0,0 +1,81 @@
+//! Tauri commands for reading and restoring window geometry. The fitting
+//! logic lives in window_geometry.rs.
+
+use super::error::KiriError;
+use super::window_geometry::{fit_to_monitors, MonitorArea, WindowGeometry};
+use tauri::{Monitor, PhysicalPosition, PhysicalSize, WebviewWindow};
+
+fn monitor_area(monitor: &Monitor) -> MonitorArea {
+    let area = monitor.work_area();
+    MonitorArea {
+        x: area.position.x,
+        y: area.position.y,
+        width: area.size.width,
+        height: area.size.height,
+        scale_factor: monitor.scale_factor(),
+    }
+}
+
+/// Capture the current geometry of `window`.
+pub fn capture_geometry(window: &WebviewWindow) -> Result<WindowGeometry, String> {
+    let position = window
+        .outer_position()
+        .map_err(|e| format!("Failed to read window position: {}", e))?;
+    let size = window
+        .inner_size()
+        .map_err(|e| format!("Failed to read window size: {}", e))?;
+    Ok(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale_factor: window.scale_factor().unwrap_or(1.0),
+        maximized: window.is_maximized().unwrap_or(false),
+    })
+}
+
+/// Apply `saved` to `window` after fitting it onto the monitors that are
+/// connected now. Returns the geometry actually applied.
+pub fn apply_geometry(
+    window: &WebviewWindow,
+    saved: &WindowGeometry,
+) -> Result<WindowGeometry, String> {
+    let monitors: Vec<MonitorArea> = window
+        .available_monitors()
+        .map_err(|e| format!("Failed to list monitors: {}", e))?
+        .iter()
+        .map(monitor_area)
+        .collect();
+    let primary = window
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .map(|m| monitor_area(&m));
+    let fitted = fit_to_monitors(saved, &monitors, primary.as_ref());
+
+    window
+        .set_size(PhysicalSize::new(fitted.width, fitted.height))
+        .map_err(|e| format!("Failed to resize window: {}", e))?;
+    window
+        .set_position(PhysicalPosition::new(fitted.x, fitted.y))
+        .map_err(|e| format!("Failed to move window: {}", e))?;
+    if fitted.maximized {
+        let _ = window.maximize();
+    }
+    Ok(fitted)
+}
+
+#[tauri::command]
+pub fn get_window_geometry(window: WebviewWindow) -> Result<WindowGeometry, KiriError> {
+    capture_geometry(&window).map_err(KiriError::from)
+}
+
+/// Restore geometry saved earlier, clamped or re-centered so the window
+/// never lands on a monitor that is no longer connected.
+#[tauri::command]
+pub fn set_window_geometry(
+    window: WebviewWindow,
+    geometry: WindowGeometry,
+) -> Result<WindowGeometry, KiriError> {
+    apply_geometry(&window, &geometry).map_err(KiriError::from)
+}
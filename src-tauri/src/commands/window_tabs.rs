@@ -0,0 +1,101 @@
+//! Native window tabbing (macOS): the "open project windows as tabs"
+//! setting and tab-group bookkeeping. AppKit calls live in
+//! window_tabs_commands.rs.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Shared by every kiri window so AppKit lets any two of them be merged.
+pub const TABBING_IDENTIFIER: &str = "kiri-project";
+
+#[derive(Debug, Default)]
+pub struct WindowTabbing {
+    /// Open new project windows as a tab of the focused window.
+    pub open_as_tabs: bool,
+}
+
+pub type WindowTabbingState = Arc<Mutex<WindowTabbing>>;
+
+/// Tab state of one window as reported by AppKit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowTabInfo {
+    pub label: String,
+    /// Labels of every window in its tab group, itself included. Empty
+    /// when the window shows no tab bar.
+    pub tabs: Vec<String>,
+    /// Selected tab of the group, if AppKit reported one.
+    pub selected: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TabGroup {
+    pub labels: Vec<String>,
+    pub selected: Option<String>,
+}
+
+/// Collapse per-window tab info into distinct groups, in the order the
+/// windows were given. A window without tabs forms a group of one.
+pub fn group_tabs(windows: &[WindowTabInfo]) -> Vec<TabGroup> {
+    let mut seen = HashSet::new();
+    let mut groups = Vec::new();
+    for window in windows {
+        if seen.contains(&window.label) {
+            continue;
+        }
+        let labels = if window.tabs.is_empty() {
+            vec![window.label.clone()]
+        } else {
+            window.tabs.clone()
+        };
+        seen.extend(labels.iter().cloned());
+        groups.push(TabGroup {
+            selected: window
+                .selected
+                .clone()
+                .or_else(|| (labels.len() == 1).then(|| labels[0].clone())),
+            labels,
+        });
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(label: &str, tabs: &[&str], selected: Option<&str>) -> WindowTabInfo {
+        WindowTabInfo {
+            label: label.to_string(),
+            tabs: tabs.iter().map(|s| s.to_string()).collect(),
+            selected: selected.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_group_tabs_merges_siblings() {
+        let windows = [
+            info("main", &["main", "window-2"], Some("window-2")),
+            info("window-2", &["main", "window-2"], Some("window-2")),
+            info("window-3", &[], None),
+        ];
+        assert_eq!(
+            group_tabs(&windows),
+            vec![
+                TabGroup {
+                    labels: vec!["main".to_string(), "window-2".to_string()],
+                    selected: Some("window-2".to_string()),
+                },
+                TabGroup {
+                    labels: vec!["window-3".to_string()],
+                    selected: Some("window-3".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_tabs_empty() {
+        assert!(group_tabs(&[]).is_empty());
+    }
+}
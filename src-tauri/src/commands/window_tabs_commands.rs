@@ -0,0 +1,237 @@
+//! Tauri commands for native window tabs. On macOS these drive AppKit's
+//! NSWindow tab groups; elsewhere windows never share a tab group, so the
+//! queries report one group per window and the tab actions fail with an
+//! explanatory error.
+
+use super::error::{ErrorCode, KiriError};
+use super::lock_ext::LockExt;
+use super::settings::SettingsState;
+use super::settings_commands::update_settings_impl;
+use super::window::WindowRegistryState;
+use super::window_tabs::{group_tabs, TabGroup, WindowTabbingState};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+#[cfg(target_os = "macos")]
+mod native {
+    use super::super::window_tabs::WindowTabInfo;
+    use objc2_app_kit::{NSWindow, NSWindowOrderingMode};
+    use tauri::{AppHandle, Manager, WebviewWindow};
+
+    /// Run `f` with the window's NSWindow. Must be called on the main
+    /// thread, where AppKit objects live.
+    fn with_ns_window<T>(window: &WebviewWindow, f: impl FnOnce(&NSWindow) -> T) -> Option<T> {
+        let ptr = window.ns_window().ok()? as *const NSWindow;
+        // SAFETY: Tauri returns the live NSWindow backing `window`, which
+        // outlives this call because `window` keeps it open.
+        let ns_window = unsafe { ptr.as_ref()? };
+        Some(f(ns_window))
+    }
+
+    fn label_for(app: &AppHandle, target: &NSWindow) -> Option<String> {
+        let target = target as *const NSWindow as *mut std::ffi::c_void;
+        app.webview_windows()
+            .into_iter()
+            .find(|(_, w)| w.ns_window().ok() == Some(target))
+            .map(|(label, _)| label)
+    }
+
+    pub fn tab_info(app: &AppHandle) -> Vec<WindowTabInfo> {
+        let mut windows: Vec<_> = app.webview_windows().into_iter().collect();
+        windows.sort_by(|a, b| a.0.cmp(&b.0));
+        windows
+            .into_iter()
+            .map(|(label, window)| {
+                let (tabs, selected) = with_ns_window(&window, |ns| {
+                    let tabs = ns
+                        .tabbedWindows()
+                        .map(|tabs| tabs.iter().filter_map(|w| label_for(app, &w)).collect())
+                        .unwrap_or_default();
+                    let selected = ns
+                        .tabGroup()
+                        .and_then(|group| group.selectedWindow())
+                        .and_then(|w| label_for(app, &w));
+                    (tabs, selected)
+                })
+                .unwrap_or_default();
+                WindowTabInfo {
+                    label,
+                    tabs,
+                    selected,
+                }
+            })
+            .collect()
+    }
+
+    pub fn add_tab(parent: &WebviewWindow, child: &WebviewWindow) -> bool {
+        with_ns_window(parent, |parent| {
+            with_ns_window(child, |child| {
+                parent.addTabbedWindow_ordered(child, NSWindowOrderingMode::Above)
+            })
+        })
+        .flatten()
+        .is_some()
+    }
+
+    pub fn merge_all(window: &WebviewWindow) -> bool {
+        with_ns_window(window, |ns| ns.mergeAllWindows(None)).is_some()
+    }
+
+    pub fn move_to_new_window(window: &WebviewWindow) -> bool {
+        with_ns_window(window, |ns| ns.moveTabToNewWindow(None)).is_some()
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn check(done: bool) -> Result<(), KiriError> {
+    if done {
+        Ok(())
+    } else {
+        Err("Window is not backed by a native window".into())
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn unsupported() -> Result<(), KiriError> {
+    Err(KiriError::new(
+        ErrorCode::Unsupported,
+        "Native window tabs are only available on macOS",
+    ))
+}
+
+/// The window a new window should join as a tab, if "open as tabs" is on.
+/// Call before building the new window, while the parent still has focus.
+pub fn tab_parent_for_new_window(app: &AppHandle) -> Option<String> {
+    let enabled = app
+        .try_state::<WindowTabbingState>()
+        .is_some_and(|state| state.lock_recover().open_as_tabs);
+    if !enabled || cfg!(not(target_os = "macos")) {
+        return None;
+    }
+    app.webview_windows()
+        .into_iter()
+        .find(|(_, w)| w.is_focused().unwrap_or(false))
+        .map(|(label, _)| label)
+}
+
+/// Attach window `child` as a tab of `parent` on the main thread.
+pub fn add_as_tab(app: &AppHandle, parent: &str, child: &str) {
+    #[cfg(target_os = "macos")]
+    {
+        let app_for_tab = app.clone();
+        let (parent, child) = (parent.to_string(), child.to_string());
+        let _ = app.run_on_main_thread(move || {
+            if let (Some(parent), Some(child)) = (
+                app_for_tab.get_webview_window(&parent),
+                app_for_tab.get_webview_window(&child),
+            ) {
+                native::add_tab(&parent, &child);
+            }
+        });
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = (app, parent, child);
+}
+
+#[tauri::command]
+pub fn get_window_tabbing(state: tauri::State<'_, WindowTabbingState>) -> bool {
+    state.lock_recover().open_as_tabs
+}
+
+/// Turn "open new project windows as tabs" on or off and persist it.
+#[tauri::command]
+pub fn set_window_tabbing(app: AppHandle, enabled: bool) -> Result<(), KiriError> {
+    update_settings_impl(&app, serde_json::json!({ "openWindowsAsTabs": enabled }))
+        .map(|_| ())
+        .map_err(KiriError::from)
+}
+
+/// Set [`WindowTabbingState`] from the `openWindowsAsTabs` setting.
+pub fn load_window_tabbing(app: &AppHandle) {
+    let Some(settings) = app.try_state::<SettingsState>() else {
+        return;
+    };
+    let enabled = settings.lock_recover().settings().open_windows_as_tabs;
+    if let Some(state) = app.try_state::<WindowTabbingState>() {
+        state.lock_recover().open_as_tabs = enabled;
+    }
+}
+
+/// Current tab groups, one entry per group of windows sharing a tab bar.
+#[tauri::command]
+pub fn get_tab_groups(app: AppHandle) -> Vec<TabGroup> {
+    #[cfg(target_os = "macos")]
+    let windows = native::tab_info(&app);
+    #[cfg(not(target_os = "macos"))]
+    let windows: Vec<_> = {
+        let mut labels: Vec<String> = app.webview_windows().into_keys().collect();
+        labels.sort();
+        labels
+            .into_iter()
+            .map(|label| super::window_tabs::WindowTabInfo {
+                label,
+                tabs: Vec::new(),
+                selected: None,
+            })
+            .collect()
+    };
+    group_tabs(&windows)
+}
+
+/// Merge every kiri window into the calling window's tab bar.
+#[tauri::command]
+pub fn merge_all_windows(window: WebviewWindow) -> Result<(), KiriError> {
+    #[cfg(target_os = "macos")]
+    {
+        check(native::merge_all(&window))
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = window;
+        unsupported()
+    }
+}
+
+/// Detach the calling window's tab into its own window.
+#[tauri::command]
+pub fn move_tab_to_new_window(window: WebviewWindow) -> Result<(), KiriError> {
+    #[cfg(target_os = "macos")]
+    {
+        check(native::move_to_new_window(&window))
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = window;
+        unsupported()
+    }
+}
+
+/// Open `project_path` (typically a worktree) as a new tab of the calling
+/// window, regardless of the "open as tabs" setting. Returns the new
+/// window's label.
+#[tauri::command]
+pub fn open_in_new_tab(
+    app: AppHandle,
+    window: WebviewWindow,
+    registry: tauri::State<'_, WindowRegistryState>,
+    project_path: String,
+) -> Result<String, KiriError> {
+    #[cfg(target_os = "macos")]
+    {
+        let label = super::window::create_window_impl(
+            &app,
+            Some(&registry),
+            None,
+            None,
+            None,
+            None,
+            Some(project_path),
+        )?;
+        add_as_tab(&app, window.label(), &label);
+        Ok(label)
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (app, window, registry, project_path);
+        unsupported().map(|_| String::new())
+    }
+}
@@ -0,0 +1,346 @@
+//! Drift between a worktree and what it was provisioned from.
+//!
+//! A new worktree gets copies of the main checkout's untracked assets (the
+//! `worktree.copy` patterns of `.kiri.toml`, and compose files kept out of
+//! git) and installs its own dependencies. Both go stale: someone adds a
+//! variable to the main `.env`, or a pull changes the lockfile while
+//! `node_modules` stays as it was. [`check_drift`] lists what is out of
+//! date so the user knows to copy again or re-run the init commands.
+//!
+//! Copied files are compared by SHA-256 with the main checkout's. Installed
+//! dependencies are checked against the marker each package manager
+//! leaves in `node_modules`: pnpm's copy of the lockfile by hash, and the
+//! npm and yarn markers by being at least as new as the lockfile.
+
+use ring::digest::{digest, Digest, SHA256};
+use serde::Serialize;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::bookmarks::relative_key;
+use super::docker::{ComposeStack, COMPOSE_FILES};
+use super::fs_path::{path_to_wire, strip_root};
+
+/// Compose overrides, which are often local and left out of git.
+const COMPOSE_OVERRIDES: [&str; 4] = [
+    "compose.override.yaml",
+    "compose.override.yml",
+    "docker-compose.override.yaml",
+    "docker-compose.override.yml",
+];
+
+/// How a package manager's install marker relates to its lockfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    /// A copy of the lockfile as installed.
+    Copy(&'static str),
+    /// Written on every install, so older than the lockfile means stale.
+    Newer(&'static str),
+}
+
+impl Marker {
+    /// Relative to the directory of the lockfile.
+    fn path(self) -> &'static str {
+        let (Marker::Copy(path) | Marker::Newer(path)) = self;
+        path
+    }
+}
+
+/// Lockfiles and the markers an install leaves, first found wins.
+const LOCKFILES: [(&str, &[Marker]); 4] = [
+    (
+        "pnpm-lock.yaml",
+        &[Marker::Copy("node_modules/.pnpm/lock.yaml")],
+    ),
+    (
+        "package-lock.json",
+        &[Marker::Newer("node_modules/.package-lock.json")],
+    ),
+    (
+        "npm-shrinkwrap.json",
+        &[Marker::Newer("node_modules/.package-lock.json")],
+    ),
+    (
+        "yarn.lock",
+        &[
+            Marker::Newer("node_modules/.yarn-state.yml"),
+            Marker::Newer("node_modules/.yarn-integrity"),
+            // Plug'n'Play installs have no node_modules.
+            Marker::Newer(".pnp.cjs"),
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DriftKind {
+    /// Matched by a `worktree.copy` pattern.
+    Copied,
+    Compose,
+    Dependencies,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DriftStatus {
+    /// The worktree's copy differs from the main checkout's.
+    Changed,
+    /// In the main checkout but not in the worktree.
+    Missing,
+    /// A lockfile without installed dependencies.
+    NotInstalled,
+    /// The lockfile, or the compose file of a running stack, changed
+    /// since it was last applied.
+    Outdated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftItem {
+    pub kind: DriftKind,
+    /// Relative to the worktree; the lockfile for dependencies.
+    pub path: String,
+    pub status: DriftStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeDrift {
+    pub worktree: String,
+    pub main_checkout: String,
+    /// Files and lockfiles compared, whether or not they drifted.
+    pub checked: usize,
+    pub items: Vec<DriftItem>,
+}
+
+fn file_hash(path: &Path) -> Option<Digest> {
+    let bytes = std::fs::read(path).ok()?;
+    Some(digest(&SHA256, &bytes))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// Files of `main` matched by the `worktree.copy` patterns, relative and
+/// sorted.
+pub fn copied_files(main: &Path, patterns: &[String]) -> Vec<String> {
+    let mut files: Vec<String> = patterns
+        .iter()
+        .filter_map(|pattern| glob::glob(&main.join(pattern).to_string_lossy()).ok())
+        .flat_map(|paths| paths.flatten())
+        .filter(|path| path.is_file())
+        .filter_map(|path| strip_root(&path, main).map(|rel| relative_key(&rel)))
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Compose files of `main` that git doesn't track, so a worktree only has
+/// them by copying.
+fn untracked_compose_files(main: &Path) -> Vec<String> {
+    let index = git2::Repository::open(main)
+        .ok()
+        .and_then(|repo| repo.index().ok());
+    COMPOSE_FILES
+        .iter()
+        .chain(COMPOSE_OVERRIDES.iter())
+        .filter(|name| main.join(name).is_file())
+        .filter(|name| {
+            index
+                .as_ref()
+                .map_or(true, |index| index.get_path(Path::new(name), 0).is_none())
+        })
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// How the worktree's copy of `rel` compares with the main checkout's.
+fn compare_file(main: &Path, worktree: &Path, rel: &str) -> Option<DriftStatus> {
+    let source = file_hash(&main.join(rel))?;
+    match file_hash(&worktree.join(rel)) {
+        None => Some(DriftStatus::Missing),
+        Some(copy) if copy.as_ref() != source.as_ref() => Some(DriftStatus::Changed),
+        Some(_) => None,
+    }
+}
+
+/// Whether the dependencies installed in `dir` match `lockfile`.
+fn dependency_status(dir: &Path, lockfile: &Path, markers: &[Marker]) -> Option<DriftStatus> {
+    let Some(marker) = markers
+        .iter()
+        .find(|marker| dir.join(marker.path()).is_file())
+    else {
+        return Some(DriftStatus::NotInstalled);
+    };
+    let installed = dir.join(marker.path());
+    let stale = match marker {
+        Marker::Copy(_) => match (file_hash(lockfile), file_hash(&installed)) {
+            (Some(lock), Some(installed)) => lock.as_ref() != installed.as_ref(),
+            _ => false,
+        },
+        Marker::Newer(_) => match (modified(lockfile), modified(&installed)) {
+            (Some(lock), Some(installed)) => installed < lock,
+            _ => false,
+        },
+    };
+    stale.then_some(DriftStatus::Outdated)
+}
+
+/// What in `worktree` is out of date with the main checkout `main`, with
+/// `copy_patterns` from its `.kiri.toml`.
+pub fn check_drift(main: &Path, worktree: &Path, copy_patterns: &[String]) -> WorktreeDrift {
+    let mut drift = WorktreeDrift {
+        worktree: path_to_wire(worktree),
+        main_checkout: path_to_wire(main),
+        checked: 0,
+        items: Vec::new(),
+    };
+    let mut push = |kind: DriftKind, path: &str, status: Option<DriftStatus>| {
+        drift.checked += 1;
+        if let Some(status) = status {
+            drift.items.push(DriftItem {
+                kind,
+                path: path.to_string(),
+                status,
+            });
+        }
+    };
+
+    // The main checkout is what the others are compared with.
+    if main != worktree {
+        let copied = copied_files(main, copy_patterns);
+        for rel in &copied {
+            push(DriftKind::Copied, rel, compare_file(main, worktree, rel));
+        }
+        for rel in untracked_compose_files(main) {
+            if !copied.contains(&rel) {
+                push(DriftKind::Compose, &rel, compare_file(main, worktree, &rel));
+            }
+        }
+    }
+
+    // A stack started before its compose file changed runs the old config.
+    if let Ok(stack) = ComposeStack::for_worktree(worktree) {
+        if let Some(generated) = stack.generated.as_deref().and_then(modified) {
+            let rel = strip_root(&stack.file, worktree)
+                .map(|rel| relative_key(&rel))
+                .unwrap_or_default();
+            let outdated = modified(&stack.file).is_some_and(|file| file > generated);
+            push(
+                DriftKind::Compose,
+                &rel,
+                outdated.then_some(DriftStatus::Outdated),
+            );
+        }
+    }
+
+    for (name, markers) in LOCKFILES {
+        let lockfile = worktree.join(name);
+        if !lockfile.is_file() {
+            continue;
+        }
+        let status = dependency_status(worktree, &lockfile, markers);
+        push(DriftKind::Dependencies, name, status);
+    }
+    drift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, rel: &str, text: &str) {
+        let path = dir.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    fn statuses(drift: &WorktreeDrift) -> Vec<(&str, DriftStatus)> {
+        drift
+            .items
+            .iter()
+            .map(|item| (item.path.as_str(), item.status))
+            .collect()
+    }
+
+    #[test]
+    fn test_copied_and_compose_files_drift() {
+        let dir = TempDir::new().unwrap();
+        let (main, worktree) = (dir.path().join("main"), dir.path().join("wt"));
+        write(&main, ".env", "A=1\nB=2\n");
+        write(&main, "config/app.local.json", "{}");
+        write(&main, "compose.override.yaml", "services: {}");
+        write(&worktree, ".env", "A=1\n");
+        write(&worktree, "compose.override.yaml", "services: {}");
+
+        let patterns = vec![".env".to_string(), "config/*.local.json".to_string()];
+        assert_eq!(
+            copied_files(&main, &patterns),
+            vec![".env", "config/app.local.json"]
+        );
+        let drift = check_drift(&main, &worktree, &patterns);
+        assert_eq!(drift.checked, 3);
+        assert_eq!(
+            statuses(&drift),
+            vec![
+                (".env", DriftStatus::Changed),
+                ("config/app.local.json", DriftStatus::Missing),
+            ]
+        );
+        assert!(check_drift(&main, &main, &patterns).items.is_empty());
+    }
+
+    #[test]
+    fn test_dependencies_against_install_markers() {
+        let dir = TempDir::new().unwrap();
+        let (main, worktree) = (dir.path().join("main"), dir.path().join("wt"));
+        std::fs::create_dir_all(&main).unwrap();
+        write(&worktree, "pnpm-lock.yaml", "lockfileVersion: '9.0'\n");
+        write(&worktree, "package-lock.json", "{}");
+        let drift = check_drift(&main, &worktree, &[]);
+        assert_eq!(
+            statuses(&drift),
+            vec![
+                ("pnpm-lock.yaml", DriftStatus::NotInstalled),
+                ("package-lock.json", DriftStatus::NotInstalled),
+            ]
+        );
+
+        write(
+            &worktree,
+            "node_modules/.pnpm/lock.yaml",
+            "lockfileVersion: '9.0'\n",
+        );
+        write(&worktree, "node_modules/.package-lock.json", "{}");
+        let old = SystemTime::now() - Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(worktree.join("node_modules/.package-lock.json"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let drift = check_drift(&main, &worktree, &[]);
+        assert_eq!(
+            statuses(&drift),
+            vec![("package-lock.json", DriftStatus::Outdated)]
+        );
+
+        write(
+            &worktree,
+            "pnpm-lock.yaml",
+            "lockfileVersion: '9.0'\nx: 1\n",
+        );
+        assert_eq!(
+            check_drift(&main, &worktree, &[]).items[0],
+            DriftItem {
+                kind: DriftKind::Dependencies,
+                path: "pnpm-lock.yaml".to_string(),
+                status: DriftStatus::Outdated,
+            }
+        );
+    }
+}
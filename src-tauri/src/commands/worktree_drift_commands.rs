@@ -0,0 +1,26 @@
+//! Tauri command for the provisioning drift checks of worktree_drift.rs.
+
+use super::auto_fetch::main_checkout;
+use super::error::KiriError;
+use super::fs_path::normalize_input_path;
+use super::git_worktree::current_worktree_root;
+use super::latency::run_blocking;
+use super::project_config::load_config;
+use super::worktree_drift::{check_drift, WorktreeDrift};
+
+/// What the worktree at `worktree` has out of date since it was set up:
+/// copied files that changed in the main checkout and dependencies older
+/// than their lockfile. The copy patterns come from the main checkout's
+/// `.kiri.toml`.
+#[tauri::command]
+pub async fn check_worktree_drift(worktree: String) -> Result<WorktreeDrift, KiriError> {
+    let dir = normalize_input_path(&worktree);
+    run_blocking("check_worktree_drift", move || {
+        let root = current_worktree_root(&dir)
+            .ok_or_else(|| format!("{} is not in a git repository", dir.display()))?;
+        let main = main_checkout(&root).unwrap_or_else(|| root.clone());
+        let config = load_config(&main).config;
+        Ok(check_drift(&main, &root, &config.copy_patterns))
+    })
+    .await
+}
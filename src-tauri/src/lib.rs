@@ -7,62 +7,305 @@ use commands::{
     copy_paths_to_directory, create_directory, create_file, move_path, move_to_trash,
     open_terminal_here, rename_path, restore_from_trash, trash_restore_supported,
     create_terminal, create_window, delete_path, fetch_remote,
-    focus_or_create_window, get_all_git_diffs, get_behind_ahead_count,
-    get_branch_ahead_count, get_commit_diff, get_commit_log, get_git_diff, get_git_file_status,
+    focus_or_create_window, focus_project, get_all_git_diffs, get_behind_ahead_count,
+    get_branch_ahead_count, get_commit_diff, get_commit_log, get_diff_stats, get_git_diff, get_git_file_status,
     get_git_status, get_home_directory, get_memory_metrics, get_performance_report,
     install_kiri_skill, is_terminal_alive, kiri_skill_status, pull_commits,
     push_commits, read_directory, read_file, read_file_as_base64, record_command_timing,
     register_window, resize_terminal, reveal_in_finder,
     search_content, search_files, setup_menu, start_watching, stop_all_watching,
+    get_watcher_health,
     stop_watching, unregister_window, write_terminal, CliServerRegistry, CliServerRegistryState,
     TerminalOutputBus, TerminalOutputBusState, TerminalState,
     WatcherState, WindowRegistry, WindowRegistryState,
+    get_preview, get_todos, invalidate_directory_cache, read_file_chunk, read_file_with_format, read_files,
+    write_file,
+    close_document, list_documents, list_unsaved_documents, open_document, save_document,
+    set_document_dirty,
+    TodoScanner, TodoScannerState,
+    restore_session, save_session, SessionState, SessionStore,
+    get_window_context, set_window_worktree, list_worktrees, create_worktree, remove_worktree,
+    LauncherState, get_window_geometry, set_window_geometry,
+    get_tray_enabled, set_tray_enabled, TraySettings, TrayState,
+    list_global_shortcuts, register_global_shortcut, unregister_global_shortcut,
+    GlobalShortcutState, ShortcutBindings,
+    set_badge_count, set_job_progress, DockBadge, DockBadgeState,
+    get_tab_groups, get_window_tabbing, merge_all_windows, move_tab_to_new_window,
+    open_in_new_tab, set_window_tabbing, WindowTabbing, WindowTabbingState,
+    get_keymap, reset_keybinding, set_keybinding, Keymap, KeymapState,
+    export_trace,
+    cancel_job, list_jobs, JobManager, JobState,
+    get_memory_history, get_memory_metrics_enabled, set_memory_metrics_enabled, MemorySampler,
+    MemorySamplerState,
+    ThrottledEmitter, ThrottledEmitterState,
+    get_startup_report,
+    checkout_pull_request, create_pull_request, get_branch_pull_request, get_pull_request_status,
+    github_auth_status, github_poll_device_flow, github_sign_out, github_start_device_flow,
+    list_pull_requests, open_pull_request_url,
+    clear_code_host_token, get_branch_status, get_code_host, set_code_host_token,
+    get_ci_status, CiStatusCache, CiStatusState,
+    clear_jira_credentials, create_worktree_from_issue, get_jira_site, list_assigned_issues,
+    set_jira_credentials,
+    compose_down, compose_up, follow_container_logs, get_compose_status, stop_container_logs,
+    LogFollowers, LogFollowersState,
+    devcontainer_down, devcontainer_up, get_devcontainer,
+    approve_envrc, get_envrc_status, reload_envrc, revoke_envrc, Direnv,
+    DirenvState, get_toolchain_status,
+    delete_env_set, list_env_sets, save_env_set, set_active_env_set, EnvSets, EnvSetsState,
+    list_lsp_servers, lsp_did_change, lsp_did_close, lsp_did_open, lsp_did_save,
+    lsp_document_uri, lsp_request, stop_lsp_server, LspManager, LspState,
+    syntax_close, syntax_edit, syntax_highlights, syntax_open, syntax_outline, SyntaxDocuments,
+    SyntaxState, format_file, get_formatter,
+    get_task_output, list_task_runs, list_tasks, resize_task, restart_task, run_task, stop_task,
+    write_task_input, TaskRuns, TaskRunsState,
+    list_task_watches, stop_task_watch, watch_task, TaskWatches, TaskWatchesState,
+    get_affected_packages, list_ssh_hosts, probe_ssh_host, render_markdown_preview,
+    check_worktree_drift, clear_command_history, search_command_history, CommandHistory,
+    CommandHistoryState, list_external_editors, open_in_external_editor, classify_generated_paths,
+    stage_file, unstage_file, stage_hunk, create_commit,
+    detect_test_frameworks, get_test_run, list_test_runs, run_tests, TestRuns, TestRunsState,
+    delete_tunnel, get_tunnel_status, list_tunnel_providers, list_tunnels, save_tunnel, start_tunnel,
+    stop_tunnel, Tunnels, TunnelsState,
+    get_mcp_audit_log, get_mcp_status, set_mcp_enabled, set_mcp_tool_permission, Mcp, McpServer,
+    McpServerState, McpState,
+    get_agent_output, list_agent_clis, list_agent_sessions, resize_agent_session,
+    start_agent_session, stop_agent_session, write_agent_input, AgentSessions, AgentSessionsState,
+    get_notification_rules, notify_event, set_notification_rule, Notifications, NotificationsState,
+    delete_secret, has_secret, set_secret, get_network_settings, set_network_settings,
+    cancel_browser_sign_in, sign_in_with_browser, OAuthSignIn, OAuthSignInState,
+    lint_commit_message, suggest_commit_metadata, generate_changelog,
+    get_dependency_report, scan_dependencies, DependencyReports, DependencyReportsState, get_owners,
+    get_project_stats, ProjectStatsCache, ProjectStatsState, export_diff, export_files,
+    copy_path_reference,
+    get_pre_commit_run, list_pre_commit_hooks, run_pre_commit_hooks, HookRuns, HookRunsState,
+    get_auto_fetch_settings, set_auto_fetch_settings, set_repo_auto_fetch, AutoFetch, AutoFetchState,
+    get_settings, update_settings, SettingsState, SettingsStore,
+    get_project_config, ProjectConfigs, ProjectConfigsState, export_settings, import_settings,
+    get_worktree_settings, resolve_worktree_settings, run_worktree_init, set_worktree_settings,
+    WorktreeSettings, WorktreeSettingsState, get_trust_state, set_trust_state, TrustStore,
+    TrustStoreState, clear_usage_analytics, export_usage_analytics, get_usage_analytics,
+    set_usage_analytics_enabled, clear_crash_reports, get_crash_reports, check_for_updates,
+    get_update_status, Updater, UpdaterState, export_support_bundle, get_log_levels,
+    set_log_levels, run_doctor, cancel_operation, request_confirmation, is_slow_filesystem,
+    add_recent_project, clear_recent_projects, list_recent_projects, pin_recent_project,
+    remove_recent_project, validate_recent_projects, RecentProjects, RecentProjectsState,
+    add_bookmark, list_bookmarks, remove_bookmark, reorder_bookmarks, update_bookmark, Bookmarks,
+    BookmarksState,
 };
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Before anything that could panic, so every crash leaves a report.
+    commands::crash_report::install_panic_hook();
+
+    // Release builds log to rotating files; debug builds use the log
+    // plugin installed in setup.
+    if !cfg!(debug_assertions) {
+        commands::logging_commands::install_file_logging();
+    }
+
+    // Fixes the origin that get_startup_report measures from.
+    commands::startup::startup_profile();
+
+    // `kiri .` while kiri is already running: hand the arguments to the
+    // running instance (which opens or focuses the window) and exit.
+    if commands::single_instance_commands::forward_launch_if_running() {
+        return;
+    }
+
+    // Spans are only kept while `export_trace` is recording.
+    commands::trace::install_trace_subscriber();
+
+    let mut jobs = JobManager::new();
+    jobs.set_concurrency(
+        commands::tasks::TASK_JOB_KIND,
+        commands::tasks::TASK_CONCURRENCY,
+    );
+
     tauri::Builder::default()
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_store::Builder::default().build())
-        .manage(Arc::new(Mutex::new(commands::TerminalManager::new())) as TerminalState)
+        .manage(Arc::new(commands::TerminalManager::new()) as TerminalState)
         .manage(Arc::new(TerminalOutputBus::new()) as TerminalOutputBusState)
         .manage(Arc::new(CliServerRegistry::new()) as CliServerRegistryState)
-        .manage(Arc::new(Mutex::new(commands::WatcherManager::new())) as WatcherState)
-        .manage(Arc::new(Mutex::new(WindowRegistry::new())) as WindowRegistryState)
+        .manage(Arc::new(commands::WatcherManager::new()) as WatcherState)
+        .manage(Arc::new(RwLock::new(WindowRegistry::new())) as WindowRegistryState)
+        .manage(Arc::new(Mutex::new(TodoScanner::new())) as TodoScannerState)
+        .manage(Arc::new(Mutex::new(SessionStore::new())) as SessionState)
+        .manage(Arc::new(Mutex::new(None)) as LauncherState)
+        .manage(Arc::new(Mutex::new(TraySettings::default())) as TrayState)
+        .manage(Arc::new(Mutex::new(ShortcutBindings::default())) as GlobalShortcutState)
+        .manage(Arc::new(Mutex::new(DockBadge::new())) as DockBadgeState)
+        .manage(Arc::new(Mutex::new(WindowTabbing::default())) as WindowTabbingState)
+        .manage(Arc::new(Mutex::new(Keymap::new())) as KeymapState)
+        .manage(Arc::new(Mutex::new(jobs)) as JobState)
+        .manage(Arc::new(Mutex::new(MemorySampler::new())) as MemorySamplerState)
+        .manage(Arc::new(ThrottledEmitter::new()) as ThrottledEmitterState)
+        .manage(Arc::new(Mutex::new(CiStatusCache::new())) as CiStatusState)
+        .manage(Arc::new(Mutex::new(LogFollowers::new())) as LogFollowersState)
+        .manage(Arc::new(Mutex::new(Direnv::new())) as DirenvState)
+        .manage(Arc::new(Mutex::new(EnvSets::new())) as EnvSetsState)
+        .manage(Arc::new(Mutex::new(LspManager::new())) as LspState)
+        .manage(Arc::new(Mutex::new(SyntaxDocuments::new())) as SyntaxState)
+        .manage(Arc::new(Mutex::new(TaskRuns::new())) as TaskRunsState)
+        .manage(Arc::new(Mutex::new(TaskWatches::new())) as TaskWatchesState)
+        .manage(Arc::new(Mutex::new(CommandHistory::new())) as CommandHistoryState)
+        .manage(Arc::new(Mutex::new(TestRuns::new())) as TestRunsState)
+        .manage(Arc::new(Mutex::new(Tunnels::new())) as TunnelsState)
+        .manage(Arc::new(Mutex::new(Mcp::new())) as McpState)
+        .manage(Arc::new(Mutex::new(McpServer::new())) as McpServerState)
+        .manage(Arc::new(Mutex::new(AgentSessions::new())) as AgentSessionsState)
+        .manage(Arc::new(Mutex::new(Notifications::new())) as NotificationsState)
+        .manage(Arc::new(Mutex::new(OAuthSignIn::new())) as OAuthSignInState)
+        .manage(Arc::new(Mutex::new(DependencyReports::new())) as DependencyReportsState)
+        .manage(Arc::new(Mutex::new(ProjectStatsCache::new())) as ProjectStatsState)
+        .manage(Arc::new(Mutex::new(HookRuns::new())) as HookRunsState)
+        .manage(Arc::new(Mutex::new(AutoFetch::new())) as AutoFetchState)
+        .manage(Arc::new(Mutex::new(SettingsStore::new())) as SettingsState)
+        .manage(Arc::new(Mutex::new(ProjectConfigs::new())) as ProjectConfigsState)
+        .manage(Arc::new(Mutex::new(WorktreeSettings::new())) as WorktreeSettingsState)
+        .manage(Arc::new(Mutex::new(TrustStore::new())) as TrustStoreState)
+        .manage(Arc::new(Mutex::new(RecentProjects::new())) as RecentProjectsState)
+        .manage(Arc::new(Mutex::new(Bookmarks::new())) as BookmarksState)
+        .manage(Arc::new(Mutex::new(Updater::new())) as UpdaterState)
         .setup(|app| {
+            // Each step is timed for get_startup_report; see startup.rs.
+            use commands::startup::time_phase;
+            commands::startup::begin_setup();
+
             // Sweep socket files left behind by a previous session that
             // crashed or was force-quit before its exit cleanup ran. Only
             // dead sockets are removed, so a concurrently running kiri
             // instance is unaffected. Done before any window registers.
-            tauri::async_runtime::block_on(commands::cli_server::sweep_dead_sockets());
+            time_phase("sweep_dead_sockets", || {
+                tauri::async_runtime::block_on(commands::cli_server::sweep_dead_sockets())
+            });
+
+            // Everything below reads the typed settings.
+            time_phase("settings", || {
+                commands::settings_commands::load_settings(app.handle())
+            });
+
+            // Load before any window is created so restored windows
+            // already honour "open as tabs".
+            time_phase("window_tabbing", || {
+                commands::window_tabs_commands::load_window_tabbing(app.handle())
+            });
 
-            // Setup menu bar
-            setup_menu(app)?;
+            // Before the menu lists them under Open Recent
+            time_phase("recent_projects", || {
+                commands::recent_projects_commands::load_recent_projects(app.handle())
+            });
+
+            // Setup menu bar, with the user's keybindings
+            time_phase("menu", || {
+                commands::keymap_commands::load_keymap(app.handle());
+                setup_menu(app)
+            })?;
+            time_phase("tray", || commands::tray_commands::setup_tray(app));
+            // Before restored windows open terminals in their worktrees
+            time_phase("direnv_approvals", || {
+                commands::direnv_commands::load_direnv_approvals(app.handle())
+            });
+            time_phase("env_sets", || {
+                commands::env_sets_commands::load_env_sets(app.handle())
+            });
+            time_phase("workspace_trust", || {
+                commands::trust_commands::load_trust_store(app.handle())
+            });
+            time_phase("bookmarks", || {
+                commands::bookmarks_commands::load_bookmarks(app.handle())
+            });
+            time_phase("command_history", || {
+                commands::command_history_commands::load_command_history(app.handle())
+            });
+            time_phase("worktree_settings", || {
+                commands::settings_layers_commands::load_worktree_settings(app.handle())
+            });
+            time_phase("network_settings", || {
+                commands::network_commands::load_network_settings()
+            });
+            time_phase("tunnel_configs", || {
+                commands::tunnel_commands::load_tunnel_configs(app.handle())
+            });
+            time_phase("notification_rules", || {
+                commands::notifications_commands::load_notification_rules(app.handle())
+            });
+            time_phase("auto_fetch", || {
+                commands::auto_fetch_commands::load_auto_fetch_settings(app.handle());
+                commands::auto_fetch_commands::start_auto_fetch(app.handle());
+            });
+            time_phase("update_checks", || {
+                commands::updater_commands::start_update_checks(app.handle())
+            });
+            time_phase("mcp_server", || {
+                commands::mcp_commands::start_mcp_server_if_enabled(app.handle())
+            });
+            time_phase("global_shortcuts", || {
+                commands::global_shortcut_commands::setup_global_shortcuts(app)
+            })?;
+            time_phase("memory_sampler", || {
+                commands::memory_sampler_commands::setup_memory_sampler(app.handle())
+            });
+            time_phase("usage_analytics", || {
+                commands::telemetry_commands::setup_usage_analytics(app.handle())
+            });
+            time_phase("slow_filesystem_events", || {
+                commands::fs_budget_commands::setup_slow_filesystem_events(app.handle())
+            });
+
+            // Reopen the previous session's windows. Each window pulls its
+            // own open files/terminals via `restore_session` once mounted.
+            time_phase("restore_windows", || {
+                commands::session_commands::restore_windows_on_startup(app.handle())
+            });
+
+            // Open directories and `kiri://` links passed on the command
+            // line (macOS delivers links through RunEvent::Opened below),
+            // then accept the same from later launches.
+            time_phase("startup_targets", || {
+                commands::single_instance_commands::open_startup_targets(app.handle());
+                commands::single_instance_commands::start_launcher(app.handle());
+            });
 
             if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-                app.handle()
-                    .plugin(tauri_plugin_mcp_bridge::init())?;
+                time_phase("debug_plugins", || -> tauri::Result<()> {
+                    app.handle().plugin(
+                        tauri_plugin_log::Builder::default()
+                            .level(log::LevelFilter::Info)
+                            .build(),
+                    )?;
+                    app.handle().plugin(tauri_plugin_mcp_bridge::init())
+                })?;
             }
 
             // Install the kiri-cli binary into ~/.kiri/bin so that PTYs
-            // spawned with that dir on PATH can invoke it as `kiri`.
-            // Best-effort: we log and continue if it fails so that a
-            // missing/broken cli does not prevent the app from launching.
-            if let Err(e) = commands::cli_install::ensure_installed(app.handle()) {
-                log::warn!("failed to install kiri CLI: {e}");
+            // spawned with that dir on PATH can invoke it as `kiri`. It is
+            // not needed for the first window, so it runs in the background
+            // and the first terminal waits for it. Best-effort: failures
+            // are logged so a missing/broken cli does not prevent the app
+            // from launching.
+            let handle = app.handle().clone();
+            if let Err(e) = std::thread::Builder::new()
+                .name("kiri-cli-install".to_string())
+                .spawn(move || commands::cli_install::ensure_installed_once(&handle))
+            {
+                log::warn!("failed to start kiri CLI install: {e}");
             }
 
+            commands::startup::finish_setup();
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            if let tauri::webview::PageLoadEvent::Finished = payload.event() {
+                commands::startup::window_loaded(webview.label());
+            }
+        })
         .on_window_event(|window, event| {
             // Authoritative cleanup keyed on the real window lifecycle. The
             // frontend's onCloseRequested also unregisters, but it can be
@@ -70,73 +313,355 @@ pub fn run() {
             // it here too guarantees a destroyed window never leaves its CLI
             // server (and socket) running as a ghost the `kiri` CLI would
             // still list. Idempotent, so the double-call is harmless.
+            if let tauri::WindowEvent::Focused(true) = event {
+                commands::menu::on_window_focused(window.app_handle(), window.label());
+            }
             if let tauri::WindowEvent::Destroyed = event {
                 let app = window.app_handle();
                 let label = window.label().to_string();
                 let registry = app.state::<WindowRegistryState>();
                 let cli_registry = app.state::<CliServerRegistryState>();
-                cleanup_window_resources(&registry, &cli_registry, &label);
+                let terminals = app.state::<TerminalState>();
+                let bus = app.state::<TerminalOutputBusState>();
+                let watchers = app.state::<WatcherState>();
+                cleanup_window_resources(
+                    &registry,
+                    &cli_registry,
+                    &terminals,
+                    &bus,
+                    &watchers,
+                    &label,
+                );
+                commands::session_commands::on_window_destroyed(app, &label);
+                commands::documents_commands::on_window_destroyed(&label);
             }
         })
-        .invoke_handler(tauri::generate_handler![
-            read_directory,
-            create_directory,
-            get_home_directory,
-            create_terminal,
-            write_terminal,
-            resize_terminal,
-            close_terminal,
-            is_terminal_alive,
-            get_foreground_process_name,
-            get_terminal_process_info,
-            get_terminal_cwd,
-            read_file,
-            read_file_as_base64,
-            get_git_status,
-            get_git_file_status,
-            get_git_diff,
-            get_all_git_diffs,
-            search_files,
-            search_content,
-            create_window,
-            focus_or_create_window,
-            register_window,
-            unregister_window,
-            reveal_in_finder,
-            delete_path,
-            start_watching,
-            stop_watching,
-            stop_all_watching,
-            // Performance commands (debug builds only)
-            get_memory_metrics,
-            get_performance_report,
-            record_command_timing,
-            clear_performance_timings,
-            // Core file operations (#82, #84, #90)
-            rename_path,
-            create_file,
-            move_to_trash,
-            restore_from_trash,
-            trash_restore_supported,
-            open_terminal_here,
-            // Drag and drop
-            copy_paths_to_directory,
-            move_path,
-            // Git history
-            get_commit_log,
-            get_commit_diff,
-            push_commits,
-            fetch_remote,
-            get_behind_ahead_count,
-            get_branch_ahead_count,
-            pull_commits,
-            // CLI server (per-window socket)
-            cli_resolve_pending,
-            cli_update_pane_map,
-            // Skill install (manual; frontend gates with confirmation dialog)
-            kiri_skill_status,
-            install_kiri_skill,
-        ])
+        // Every command is timed for get_performance_report; see
+        // performance_commands::timed_handler.
+        .invoke_handler(commands::performance_commands::timed_handler(
+            tauri::generate_handler![
+                read_directory,
+                invalidate_directory_cache,
+                create_directory,
+                get_home_directory,
+                create_terminal,
+                write_terminal,
+                resize_terminal,
+                close_terminal,
+                is_terminal_alive,
+                get_foreground_process_name,
+                get_terminal_process_info,
+                get_terminal_cwd,
+                read_file,
+                read_file_as_base64,
+                read_file_with_format,
+                read_file_chunk,
+                read_files,
+                write_file,
+                // Open documents and their unsaved state
+                open_document,
+                save_document,
+                set_document_dirty,
+                close_document,
+                list_documents,
+                list_unsaved_documents,
+                get_preview,
+                get_git_status,
+                get_git_file_status,
+                get_git_diff,
+                get_all_git_diffs,
+                get_diff_stats,
+                search_files,
+                search_content,
+                get_todos,
+                create_window,
+                focus_or_create_window,
+                focus_project,
+                register_window,
+                unregister_window,
+                get_window_context,
+                set_window_worktree,
+                get_window_geometry,
+                set_window_geometry,
+                get_tray_enabled,
+                set_tray_enabled,
+                list_global_shortcuts,
+                register_global_shortcut,
+                unregister_global_shortcut,
+                set_badge_count,
+                set_job_progress,
+                get_window_tabbing,
+                set_window_tabbing,
+                get_tab_groups,
+                merge_all_windows,
+                move_tab_to_new_window,
+                open_in_new_tab,
+                get_keymap,
+                set_keybinding,
+                reset_keybinding,
+                save_session,
+                restore_session,
+                reveal_in_finder,
+                delete_path,
+                start_watching,
+                stop_watching,
+                stop_all_watching,
+                get_watcher_health,
+                // Performance commands (debug builds, or KIRI_PERF=1)
+                get_memory_metrics,
+                get_performance_report,
+                record_command_timing,
+                clear_performance_timings,
+                export_trace,
+                list_jobs,
+                cancel_job,
+                get_memory_metrics_enabled,
+                set_memory_metrics_enabled,
+                get_memory_history,
+                get_startup_report,
+                // Core file operations (#82, #84, #90)
+                rename_path,
+                create_file,
+                move_to_trash,
+                restore_from_trash,
+                trash_restore_supported,
+                open_terminal_here,
+                // Drag and drop
+                copy_paths_to_directory,
+                move_path,
+                // Git history
+                get_commit_log,
+                get_commit_diff,
+                push_commits,
+                fetch_remote,
+                get_behind_ahead_count,
+                get_branch_ahead_count,
+                pull_commits,
+                list_worktrees,
+                create_worktree,
+                remove_worktree,
+                // Staging and committing
+                stage_file,
+                unstage_file,
+                stage_hunk,
+                create_commit,
+                // Code hosting: GitHub sign-in, then PRs/MRs on any host
+                github_auth_status,
+                github_start_device_flow,
+                github_poll_device_flow,
+                github_sign_out,
+                get_code_host,
+                set_code_host_token,
+                clear_code_host_token,
+                list_pull_requests,
+                get_pull_request_status,
+                get_branch_pull_request,
+                get_branch_status,
+                get_ci_status,
+                create_pull_request,
+                checkout_pull_request,
+                open_pull_request_url,
+                // Issues: GitHub/GitLab issues of origin, or Jira
+                list_assigned_issues,
+                create_worktree_from_issue,
+                set_jira_credentials,
+                get_jira_site,
+                clear_jira_credentials,
+                // Docker Compose stack per worktree
+                compose_up,
+                compose_down,
+                get_compose_status,
+                follow_container_logs,
+                stop_container_logs,
+                // Dev container per worktree
+                get_devcontainer,
+                devcontainer_up,
+                devcontainer_down,
+                // direnv (.envrc) approval for terminals
+                get_envrc_status,
+                approve_envrc,
+                revoke_envrc,
+                reload_envrc,
+                // Named environment sets per project and worktree
+                list_env_sets,
+                save_env_set,
+                delete_env_set,
+                set_active_env_set,
+                // mise / asdf runtimes for terminals
+                get_toolchain_status,
+                // Language servers for the editor
+                lsp_did_open,
+                lsp_did_change,
+                lsp_did_save,
+                lsp_did_close,
+                lsp_request,
+                lsp_document_uri,
+                list_lsp_servers,
+                stop_lsp_server,
+                // tree-sitter highlighting and outline
+                syntax_open,
+                syntax_edit,
+                syntax_highlights,
+                syntax_outline,
+                syntax_close,
+                // Formatter dispatch on save
+                format_file,
+                get_formatter,
+                // Project tasks (npm scripts, just/make, cargo aliases)
+                list_tasks,
+                run_task,
+                restart_task,
+                stop_task,
+                list_task_runs,
+                get_task_output,
+                write_task_input,
+                resize_task,
+                // Watch mode: rerun a task or command when files change
+                watch_task,
+                stop_task_watch,
+                list_task_watches,
+                // Packages of a monorepo affected by a change
+                get_affected_packages,
+                // SSH host inventory and connectivity probe
+                list_ssh_hosts,
+                probe_ssh_host,
+                // Markdown preview rendering
+                render_markdown_preview,
+                // Worktree drift from the main checkout and installed dependencies
+                check_worktree_drift,
+                // Command history shared across worktrees
+                search_command_history,
+                clear_command_history,
+                // Handing files and worktrees to VS Code, JetBrains IDEs or Zed
+                list_external_editors,
+                open_in_external_editor,
+                // Generated files
+                classify_generated_paths,
+                // Test runner (jest, vitest, cargo test, pytest)
+                detect_test_frameworks,
+                run_tests,
+                get_test_run,
+                list_test_runs,
+                // Tunnels (cloudflare, ngrok, localtunnel, tailscale funnel)
+                list_tunnel_providers,
+                list_tunnels,
+                get_tunnel_status,
+                save_tunnel,
+                delete_tunnel,
+                start_tunnel,
+                stop_tunnel,
+                // MCP server (read-only project tools for local agents)
+                get_mcp_status,
+                set_mcp_enabled,
+                set_mcp_tool_permission,
+                get_mcp_audit_log,
+                // Agent sessions (claude, codex) per worktree
+                list_agent_clis,
+                start_agent_session,
+                stop_agent_session,
+                list_agent_sessions,
+                get_agent_output,
+                write_agent_input,
+                resize_agent_session,
+                // Notification rules for background events
+                get_notification_rules,
+                set_notification_rule,
+                notify_event,
+                // Secrets in the OS keychain (write-only from the webview)
+                set_secret,
+                delete_secret,
+                has_secret,
+                // Proxy and custom CA for outbound connections
+                get_network_settings,
+                set_network_settings,
+                // Browser sign-in (OAuth loopback redirect)
+                sign_in_with_browser,
+                cancel_browser_sign_in,
+                // Conventional commit message assistant
+                lint_commit_message,
+                suggest_commit_metadata,
+                // Changelog generation
+                generate_changelog,
+                // Dependency, license and vulnerability report
+                scan_dependencies,
+                get_dependency_report,
+                // Project dashboard statistics
+                get_project_stats,
+                // Export of diffs and files as patch, HTML or PDF
+                export_diff,
+                export_files,
+                // Copy a path as absolute, relative, file:// URL or path:line
+                copy_path_reference,
+                // CODEOWNERS lookup
+                get_owners,
+                // Pre-commit hooks (husky, lefthook, pre-commit)
+                list_pre_commit_hooks,
+                run_pre_commit_hooks,
+                get_pre_commit_run,
+                // Background fetch
+                get_auto_fetch_settings,
+                set_auto_fetch_settings,
+                set_repo_auto_fetch,
+                // Typed settings
+                get_settings,
+                update_settings,
+                // Project configuration (.kiri.toml)
+                get_project_config,
+                // Settings export / import between machines
+                export_settings,
+                import_settings,
+                // Per-worktree settings overrides
+                get_worktree_settings,
+                set_worktree_settings,
+                resolve_worktree_settings,
+                run_worktree_init,
+                // Workspace trust
+                get_trust_state,
+                set_trust_state,
+                // Recent projects (start screen, Open Recent)
+                list_recent_projects,
+                add_recent_project,
+                pin_recent_project,
+                remove_recent_project,
+                clear_recent_projects,
+                validate_recent_projects,
+                // Bookmarked files and directories
+                list_bookmarks,
+                add_bookmark,
+                update_bookmark,
+                remove_bookmark,
+                reorder_bookmarks,
+                // Native confirmation of destructive operations
+                request_confirmation,
+                // Network drives that ran over their time budgets
+                is_slow_filesystem,
+                // Opt-in local usage analytics
+                get_usage_analytics,
+                set_usage_analytics_enabled,
+                clear_usage_analytics,
+                export_usage_analytics,
+                // Crash reports
+                get_crash_reports,
+                clear_crash_reports,
+                // Update checks
+                check_for_updates,
+                get_update_status,
+                // File logs and support bundle
+                get_log_levels,
+                set_log_levels,
+                export_support_bundle,
+                // Environment doctor
+                run_doctor,
+                // Cancelling long-running commands
+                cancel_operation,
+                // CLI server (per-window socket)
+                cli_resolve_pending,
+                cli_update_pane_map,
+                // Skill install (manual; frontend gates with confirmation dialog)
+                kiri_skill_status,
+                install_kiri_skill,
+            ],
+        ))
         .build(tauri::generate_context!())
         .unwrap_or_else(|err| {
             // Print to stderr (so it lands in stderr-capture logs) AND log
@@ -147,13 +672,24 @@ pub fn run() {
             std::process::exit(1);
         })
         .run(|app_handle, event| {
+            // macOS delivers `kiri://` links to the running app here.
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            if let tauri::RunEvent::Opened { urls } = &event {
+                for url in urls {
+                    commands::deep_link_commands::handle_deep_link(app_handle, url.as_str());
+                }
+            }
+            // Freeze the session before windows are torn down so quitting
+            // with several windows open restores all of them next launch.
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                commands::session_commands::on_exit_requested(app_handle);
+            }
             // On app exit the Tokio runtime is torn down, so the per-window
-            // listener tasks may never run their own socket cleanup. Stop and
-            // remove every CLI server here so quitting leaves no stale sockets
-            // behind for the next launch to mistake for live windows.
+            // listener tasks may never run their own socket cleanup, and
+            // shells, watchers and child processes would outlive the app.
+            // Stop all of it here, with a deadline; see shutdown.rs.
             if let tauri::RunEvent::Exit = event {
-                let cli_registry = app_handle.state::<CliServerRegistryState>();
-                cli_registry.stop_all();
+                commands::shutdown_commands::shutdown(app_handle);
             }
         });
 }
@@ -0,0 +1,291 @@
+//! End-to-end tests of the command layer, calling the command functions
+//! and the state they manage directly rather than through the webview.
+//!
+//! Each test builds a fixture repository (see support/mod.rs) and drives
+//! one flow the app depends on: provisioning a worktree and reading it
+//! back, watching a checkout for file and git changes, and running a
+//! terminal in it. They run on every platform CI builds for; waits poll
+//! with a timeout instead of sleeping a fixed time.
+
+mod support;
+
+use app_lib::commands::fs::read_directory;
+use app_lib::commands::git::{get_git_status, GitFileStatus};
+use app_lib::commands::git_worktree::{create_worktree, list_worktrees, remove_worktree};
+use app_lib::commands::project_config::load_config;
+use app_lib::commands::settings::Settings;
+use app_lib::commands::settings_layers::{resolve_for_worktree, WorktreeOverrides};
+use app_lib::commands::terminal::{
+    open_pty_with_command, spawn_output_reader, PtyInstance, TerminalManager, TerminalOutputBus,
+    TerminalOutputBusState, TerminalState,
+};
+use app_lib::commands::terminal_commands::shutdown_terminal;
+use app_lib::commands::watcher::{start_watcher, EventClassificationResult, WatcherManager};
+use portable_pty::CommandBuilder;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use support::{block_on, commit_all_in, wait_for, write_in, FixtureRepo};
+
+const KIRI_TOML: &str = "[ports]\nstrategy = \"offset\"\nbase = 3000\nstep = 10\n";
+
+fn port_of(worktree: &Path) -> Option<u16> {
+    let project = load_config(worktree).config;
+    resolve_for_worktree(
+        &Settings::default(),
+        &project,
+        &WorktreeOverrides::default(),
+        worktree,
+    )
+    .port
+}
+
+#[test]
+fn worktree_provisioning_end_to_end() {
+    let repo = FixtureRepo::new(&[("README.md", "# fixture\n"), (".kiri.toml", KIRI_TOML)]);
+
+    let created = block_on(create_worktree(
+        repo.path_string(),
+        "feature/login".to_string(),
+        None,
+    ))
+    .expect("create worktree");
+    assert_eq!(created.branch.as_deref(), Some("feature/login"));
+    assert!(!created.is_main);
+    let worktree = Path::new(&created.path).to_path_buf();
+    assert!(worktree.join("README.md").exists());
+
+    // Creating it again returns the same worktree.
+    let again = block_on(create_worktree(
+        repo.path_string(),
+        "feature/login".to_string(),
+        None,
+    ))
+    .expect("create worktree again");
+    assert_eq!(again.path, created.path);
+
+    let listed = block_on(list_worktrees(created.path.clone())).expect("list worktrees");
+    assert_eq!(listed.len(), 2);
+    assert!(listed[0].is_main);
+
+    let entries = block_on(read_directory(created.path.clone(), None)).expect("read worktree");
+    assert!(entries.iter().any(|e| e.name == "README.md" && !e.is_dir));
+
+    // Each worktree gets its own port from the offset strategy.
+    assert_eq!(port_of(&repo.root), Some(3000));
+    assert_eq!(port_of(&worktree), Some(3010));
+
+    // Changes in the worktree don't show in the main checkout.
+    write_in(&worktree, "README.md", "# changed\n");
+    let status = block_on(get_git_status(created.path.clone())).expect("worktree status");
+    assert_eq!(status.branch.as_deref(), Some("feature/login"));
+    assert!(status
+        .statuses
+        .iter()
+        .any(|s| s.path == "README.md" && s.status == GitFileStatus::Modified));
+    let main_status = block_on(get_git_status(repo.path_string())).expect("main status");
+    assert!(main_status.statuses.is_empty());
+
+    // A dirty worktree needs force, and force needs a confirmation.
+    assert!(block_on(remove_worktree(
+        repo.path_string(),
+        created.path.clone(),
+        false,
+        None,
+        None
+    ))
+    .is_err());
+    assert!(block_on(remove_worktree(
+        repo.path_string(),
+        created.path.clone(),
+        true,
+        None,
+        None
+    ))
+    .is_err());
+
+    commit_all_in(&worktree, "change readme");
+    block_on(remove_worktree(
+        repo.path_string(),
+        created.path.clone(),
+        false,
+        None,
+        None,
+    ))
+    .expect("remove clean worktree");
+    assert!(!worktree.exists());
+    let listed = block_on(list_worktrees(repo.path_string())).expect("list worktrees");
+    assert_eq!(listed.len(), 1);
+}
+
+/// Receive classifications until one satisfies `want`, calling `poke`
+/// before each wait so a watcher that is still attaching sees a change.
+fn wait_for_change(
+    rx: &Receiver<EventClassificationResult>,
+    what: &str,
+    want: impl Fn(&EventClassificationResult) -> bool,
+    poke: impl Fn(),
+) {
+    wait_for(what, || {
+        poke();
+        rx.recv_timeout(Duration::from_millis(500))
+            .ok()
+            .filter(|classification| want(classification))
+    });
+    while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+}
+
+#[test]
+fn watcher_reports_file_and_git_changes() {
+    let repo = FixtureRepo::new(&[("src/main.rs", "fn main() {}\n")]);
+    let path = repo.path_string();
+    let manager = WatcherManager::new();
+    let (tx, rx) = channel();
+
+    let start = || {
+        let tx = tx.clone();
+        start_watcher(&repo.root, move |_, classification| {
+            let _ = tx.send(classification.clone());
+        })
+    };
+    let first = manager.subscribe(&path, start).expect("watch");
+    // A second view of the same checkout shares the watcher.
+    let second = manager.subscribe(&path, start).expect("watch again");
+    assert_eq!(manager.len(), 1);
+
+    wait_for_change(
+        &rx,
+        "the watcher to attach",
+        |c| c.fs_changed,
+        || repo.write(".watcher-warmup", "x"),
+    );
+
+    wait_for_change(
+        &rx,
+        "a file change",
+        |c| c.fs_changed,
+        || repo.write("src/lib.rs", "pub fn lib() {}\n"),
+    );
+
+    repo.commit_all("add lib");
+    wait_for_change(&rx, "a git change", |c| c.git_changed, || {});
+
+    assert!(manager.unsubscribe(first).is_none());
+    assert!(manager.is_watching(&path));
+    assert!(manager.unsubscribe(second).is_some());
+    assert!(manager.is_empty());
+}
+
+/// A terminal started by [`start_terminal`].
+struct Terminal {
+    id: u32,
+    /// Output as passed to the frontend.
+    output: Receiver<String>,
+    /// Output as published on the in-process bus.
+    bus_output: broadcast::Receiver<Vec<u8>>,
+    reading: JoinHandle<()>,
+}
+
+/// Start `command` in a terminal registered with `manager`, streaming its
+/// output to `bus` the way `create_terminal` does.
+fn start_terminal(
+    manager: &TerminalState,
+    bus: &TerminalOutputBusState,
+    command: CommandBuilder,
+) -> Terminal {
+    let pty = open_pty_with_command(80, 24, command).expect("spawn terminal");
+    let reader = pty.pair.master.try_clone_reader().expect("pty reader");
+    let writer = pty.pair.master.take_writer().expect("pty writer");
+    let shell_pid = pty.child.process_id();
+    let id = manager.insert(PtyInstance::new(
+        pty.pair.master,
+        writer,
+        pty.child,
+        shell_pid,
+    ));
+    let bus_output = bus.subscribe(id);
+    let (tx, output) = channel();
+    let reading = spawn_output_reader(reader, id, Arc::clone(bus), move |data| {
+        let _ = tx.send(data.to_string());
+    });
+    Terminal {
+        id,
+        output,
+        bus_output,
+        reading,
+    }
+}
+
+/// Collect output from `rx` until it contains `needle`.
+fn wait_for_output(rx: &Receiver<String>, needle: &str) -> String {
+    let mut output = String::new();
+    wait_for(&format!("terminal output {needle:?}"), || {
+        while let Ok(chunk) = rx.try_recv() {
+            output.push_str(&chunk);
+        }
+        output.contains(needle).then(|| output.clone())
+    })
+}
+
+#[test]
+fn terminal_runs_command_and_streams_output() {
+    let repo = FixtureRepo::new(&[("README.md", "# fixture\n")]);
+    let manager: TerminalState = Arc::new(TerminalManager::new());
+    let bus: TerminalOutputBusState = Arc::new(TerminalOutputBus::new());
+
+    let mut command = if cfg!(windows) {
+        let mut command = CommandBuilder::new("cmd");
+        command.args(["/C", "echo kiri-harness-ok"]);
+        command
+    } else {
+        let mut command = CommandBuilder::new("sh");
+        command.args(["-c", "echo kiri-harness-ok"]);
+        command
+    };
+    command.cwd(&repo.root);
+    let mut terminal = start_terminal(&manager, &bus, command);
+
+    wait_for_output(&terminal.output, "kiri-harness-ok");
+    // The bus saw the same bytes as the frontend.
+    let published = terminal.bus_output.try_recv().expect("bus output");
+    assert!(!published.is_empty());
+
+    assert!(shutdown_terminal(&manager, &bus, terminal.id));
+    assert!(manager.is_empty());
+    assert!(!shutdown_terminal(&manager, &bus, terminal.id));
+}
+
+#[cfg(unix)]
+#[test]
+fn login_shell_starts_in_worktree_with_kiri_environment() {
+    use app_lib::commands::terminal::build_shell_command;
+
+    let repo = FixtureRepo::new(&[("README.md", "# fixture\n")]);
+    let manager: TerminalState = Arc::new(TerminalManager::new());
+    let bus: TerminalOutputBusState = Arc::new(TerminalOutputBus::new());
+
+    let command = build_shell_command("/bin/sh", Some(&repo.path_string()), None);
+    let terminal = start_terminal(&manager, &bus, command);
+    let instance = manager.get(terminal.id).expect("terminal registered");
+
+    // Quoted apart so the echoed input doesn't match.
+    instance
+        .write(b"echo \"env:$TERM_PROGRAM\"\"@$(pwd -P)\"\n")
+        .expect("write to shell");
+    wait_for_output(
+        &terminal.output,
+        &format!("env:kiri@{}", repo.root.display()),
+    );
+
+    instance.write(b"exit\n").expect("write to shell");
+    // The reader stops once the shell exits.
+    terminal.reading.join().expect("reader thread");
+    wait_for("the shell to exit", || {
+        (!instance.is_running().unwrap_or(true)).then_some(())
+    });
+    assert!(shutdown_terminal(&manager, &bus, terminal.id));
+}
@@ -13,10 +13,14 @@
 //! Each test runs against a hermetic `tempfile::TempDir` and only uses
 //! the public API of `app_lib::commands` (no Tauri runtime required).
 
+use app_lib::commands::confirmation::{confirmations, PrivilegedAction};
+use app_lib::commands::error::ErrorCode;
 use app_lib::commands::fs::{create_directory, delete_path, read_directory};
+use app_lib::commands::lock_ext::LockExt;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tempfile::TempDir;
 
 fn temp() -> TempDir {
@@ -28,6 +32,17 @@ fn write(path: &PathBuf, body: &[u8]) {
     f.write_all(body).expect("write body");
 }
 
+/// A token for deleting `path`, as `request_confirmation` issues once the
+/// user confirms.
+fn confirmed(path: &Path) -> Option<String> {
+    let token = confirmations().lock_recover().issue(
+        PrivilegedAction::DeletePermanently,
+        path,
+        Instant::now(),
+    );
+    Some(token)
+}
+
 // --- Unicode -----------------------------------------------------------------
 
 #[tokio::test]
@@ -36,7 +51,7 @@ async fn read_directory_handles_emoji_filenames() {
     let p = dir.path().join("🐉.txt");
     write(&p, b"hi");
 
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read_directory should succeed for emoji filename");
 
@@ -52,14 +67,14 @@ async fn read_directory_handles_cjk_directory_names() {
     fs::create_dir(&sub).expect("create cjk dir");
     write(&sub.join("テスト.md"), "# テスト".as_bytes());
 
-    let outer = read_directory(dir.path().to_string_lossy().to_string())
+    let outer = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("outer");
     assert_eq!(outer.len(), 1);
     assert_eq!(outer[0].name, "プロジェクト");
     assert!(outer[0].is_dir);
 
-    let inner = read_directory(sub.to_string_lossy().to_string())
+    let inner = read_directory(sub.to_string_lossy().to_string(), None)
         .await
         .expect("inner");
     assert_eq!(inner.len(), 1);
@@ -71,7 +86,7 @@ async fn read_directory_handles_rtl_filenames() {
     let dir = temp();
     write(&dir.path().join("مرحبا.txt"), b"hello");
 
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("rtl");
     assert_eq!(entries.len(), 1);
@@ -101,7 +116,7 @@ async fn read_directory_returns_zero_byte_files() {
     let empty = dir.path().join("empty.log");
     File::create(&empty).expect("create empty file");
 
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read");
     assert_eq!(entries.len(), 1);
@@ -109,17 +124,32 @@ async fn read_directory_returns_zero_byte_files() {
     assert!(!entries[0].is_dir);
 }
 
-#[test]
-fn delete_path_removes_zero_byte_file() {
+#[tokio::test]
+async fn delete_path_removes_zero_byte_file() {
     let dir = temp();
     let empty = dir.path().join("empty.log");
     File::create(&empty).expect("touch");
     assert!(empty.exists());
 
-    delete_path(empty.to_string_lossy().to_string()).expect("delete");
+    delete_path(empty.to_string_lossy().to_string(), confirmed(&empty))
+        .await
+        .expect("delete");
     assert!(!empty.exists());
 }
 
+#[tokio::test]
+async fn delete_path_refuses_without_confirmation() {
+    let dir = temp();
+    let file = dir.path().join("keep.txt");
+    write(&file, b"keep");
+
+    let err = delete_path(file.to_string_lossy().to_string(), None)
+        .await
+        .expect_err("unconfirmed delete");
+    assert_eq!(err.code, ErrorCode::PermissionDenied);
+    assert!(file.exists());
+}
+
 // --- Mixed line endings ------------------------------------------------------
 
 #[tokio::test]
@@ -133,7 +163,7 @@ async fn read_directory_unaffected_by_mixed_line_endings_in_content() {
     write(&dir.path().join("lf.txt"), b"line1\nline2\nline3");
     write(&dir.path().join("mixed.txt"), b"a\r\nb\nc\r");
 
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read");
     let mut names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
@@ -152,7 +182,7 @@ async fn read_directory_handles_long_filename_within_pathmax() {
     let long = "a".repeat(200);
     write(&dir.path().join(&long), b"x");
 
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read");
     assert_eq!(entries.len(), 1);
@@ -181,7 +211,7 @@ async fn read_directory_reports_eacces_on_unreadable_directory() {
     perms.set_mode(0o000);
     fs::set_permissions(&sub, perms).expect("chmod 000");
 
-    let result = read_directory(sub.to_string_lossy().to_string()).await;
+    let result = read_directory(sub.to_string_lossy().to_string(), None).await;
 
     // Restore so TempDir's drop can clean up.
     let mut restore = fs::metadata(&sub).expect("metadata").permissions();
@@ -213,12 +243,13 @@ fn nix_is_root() -> bool {
 async fn read_directory_errors_for_missing_path() {
     let dir = temp();
     let missing = dir.path().join("does-not-exist");
-    let result = read_directory(missing.to_string_lossy().to_string()).await;
+    let result = read_directory(missing.to_string_lossy().to_string(), None).await;
     assert!(result.is_err());
-    let msg = result.unwrap_err();
+    let err = result.unwrap_err();
+    assert_eq!(err.code, ErrorCode::NotFound);
     assert!(
-        msg.contains("does not exist"),
-        "unexpected error message: {msg}"
+        err.message.contains("does not exist"),
+        "unexpected error message: {err}"
     );
 }
 
@@ -228,20 +259,21 @@ async fn read_directory_errors_when_path_is_a_file() {
     let f = dir.path().join("not-a-dir.txt");
     write(&f, b"hello");
 
-    let result = read_directory(f.to_string_lossy().to_string()).await;
+    let result = read_directory(f.to_string_lossy().to_string(), None).await;
     assert!(result.is_err());
-    let msg = result.unwrap_err();
+    let err = result.unwrap_err();
+    assert_eq!(err.code, ErrorCode::InvalidInput);
     assert!(
-        msg.contains("not a directory"),
-        "unexpected error message: {msg}"
+        err.message.contains("not a directory"),
+        "unexpected error message: {err}"
     );
 }
 
-#[test]
-fn delete_path_errors_for_missing_path() {
+#[tokio::test]
+async fn delete_path_errors_for_missing_path() {
     let dir = temp();
     let missing = dir.path().join("ghost");
-    let result = delete_path(missing.to_string_lossy().to_string());
+    let result = delete_path(missing.to_string_lossy().to_string(), confirmed(&missing)).await;
     assert!(result.is_err());
 }
 
@@ -254,13 +286,13 @@ async fn read_directory_does_not_crash_on_backslash_separators() {
     // surface a sensible "does not exist" error rather than a panic.
     let dir = temp();
     let weird = dir.path().join("with\\backslash");
-    let result = read_directory(weird.to_string_lossy().to_string()).await;
+    let result = read_directory(weird.to_string_lossy().to_string(), None).await;
     assert!(result.is_err(), "non-existent backslash path should Err");
 
     // Now create a real file whose name contains a backslash and make
     // sure enumeration surfaces it unchanged.
     write(&dir.path().join("a\\b.txt"), b"x");
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read");
     let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
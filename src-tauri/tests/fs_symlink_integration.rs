@@ -17,17 +17,31 @@
 
 #![cfg(unix)]
 
+use app_lib::commands::confirmation::{confirmations, PrivilegedAction};
 use app_lib::commands::fs::{create_directory, delete_path, read_directory};
+use app_lib::commands::lock_ext::LockExt;
 use std::fs::{self, File};
 use std::io::Write;
 use std::os::unix::fs::symlink;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tempfile::TempDir;
 
 fn temp() -> TempDir {
     tempfile::tempdir().expect("temp dir")
 }
 
+/// A token for deleting `path`, as `request_confirmation` issues once the
+/// user confirms.
+fn confirmed(path: &Path) -> Option<String> {
+    let token = confirmations().lock_recover().issue(
+        PrivilegedAction::DeletePermanently,
+        path,
+        Instant::now(),
+    );
+    Some(token)
+}
+
 fn write_file(path: &PathBuf, body: &[u8]) {
     let mut f = File::create(path).expect("create file");
     f.write_all(body).expect("write body");
@@ -44,7 +58,7 @@ async fn read_directory_reports_symlink_to_file_as_non_dir() {
     let link = dir.path().join("link.txt");
     symlink(&target, &link).expect("symlink file");
 
-    let mut entries = read_directory(dir.path().to_string_lossy().to_string())
+    let mut entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read_directory");
     entries.sort_by(|a, b| a.name.cmp(&b.name));
@@ -74,7 +88,7 @@ async fn read_directory_reports_symlink_to_directory_as_non_dir() {
     let link_dir = dir.path().join("link-dir");
     symlink(&target_dir, &link_dir).expect("symlink dir");
 
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read_directory");
 
@@ -102,7 +116,7 @@ async fn read_directory_includes_broken_symlinks_in_listing() {
     let dangling = dir.path().join("dangling");
     symlink(dir.path().join("does-not-exist"), &dangling).expect("dangling symlink");
 
-    let entries = read_directory(dir.path().to_string_lossy().to_string())
+    let entries = read_directory(dir.path().to_string_lossy().to_string(), None)
         .await
         .expect("read_directory");
 
@@ -116,8 +130,8 @@ async fn read_directory_includes_broken_symlinks_in_listing() {
 
 // --- delete_path: symlink semantics -----------------------------------------
 
-#[test]
-fn delete_path_on_symlink_to_file_removes_only_the_link() {
+#[tokio::test]
+async fn delete_path_on_symlink_to_file_removes_only_the_link() {
     let dir = temp();
     let target = dir.path().join("target.txt");
     write_file(&target, b"keep me");
@@ -125,7 +139,9 @@ fn delete_path_on_symlink_to_file_removes_only_the_link() {
     let link = dir.path().join("link.txt");
     symlink(&target, &link).expect("symlink");
 
-    delete_path(link.to_string_lossy().to_string()).expect("delete symlink");
+    delete_path(link.to_string_lossy().to_string(), confirmed(&link))
+        .await
+        .expect("delete symlink");
 
     // Symlink path itself uses symlink_metadata to check existence (it
     // does not follow the link), so the link must be gone…
@@ -142,8 +158,8 @@ fn delete_path_on_symlink_to_file_removes_only_the_link() {
     assert_eq!(fs::read(&target).expect("read target"), b"keep me");
 }
 
-#[test]
-fn delete_path_on_broken_symlink_does_not_silently_succeed() {
+#[tokio::test]
+async fn delete_path_on_broken_symlink_does_not_silently_succeed() {
     // delete_path uses `Path::exists()`, which FOLLOWS symlinks. A broken
     // symlink reports `exists() == false` even though the link entry is
     // physically present. This test pins down current behavior: delete_path
@@ -154,7 +170,7 @@ fn delete_path_on_broken_symlink_does_not_silently_succeed() {
     let dangling = dir.path().join("dangling");
     symlink(dir.path().join("nonexistent-target"), &dangling).expect("dangling");
 
-    let result = delete_path(dangling.to_string_lossy().to_string());
+    let result = delete_path(dangling.to_string_lossy().to_string(), confirmed(&dangling)).await;
     assert!(
         result.is_err(),
         "current implementation cannot clean up broken symlinks; saw Ok"
@@ -166,8 +182,8 @@ fn delete_path_on_broken_symlink_does_not_silently_succeed() {
     );
 }
 
-#[test]
-fn delete_path_on_symlink_to_directory_does_not_recurse_into_target() {
+#[tokio::test]
+async fn delete_path_on_symlink_to_directory_does_not_recurse_into_target() {
     // delete_path uses `path.is_dir()`, which follows symlinks. The
     // important regression guard is: even if we route into the dir
     // branch, std::fs::remove_dir_all on macOS / Linux must NOT recurse
@@ -185,7 +201,7 @@ fn delete_path_on_symlink_to_directory_does_not_recurse_into_target() {
 
     // Either succeeds (symlink removed) or errs (refused) — both are
     // acceptable behaviors; what's NOT acceptable is wiping the target.
-    let _ = delete_path(link_dir.to_string_lossy().to_string());
+    let _ = delete_path(link_dir.to_string_lossy().to_string(), confirmed(&link_dir)).await;
 
     assert!(
         target_dir.exists() && target_dir.is_dir(),
@@ -26,6 +26,15 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 use tempfile::TempDir;
 
+/// The git commands are async (they run on the blocking pool). Drive each
+/// call on its own small runtime so the threads below stay independent.
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("runtime")
+        .block_on(fut)
+}
+
 fn run(cmd: &str, args: &[&str], cwd: &Path) {
     let status = Command::new(cmd)
         .args(args)
@@ -70,7 +79,7 @@ fn parallel_get_git_status_does_not_race() {
     let path = dir.path().to_string_lossy().to_string();
 
     // Reference shape from a serial call.
-    let reference = get_git_status(path.clone()).expect("baseline");
+    let reference = block_on(get_git_status(path.clone())).expect("baseline");
     let baseline_count = reference.statuses.len();
     assert!(baseline_count >= 2, "seed should produce statuses");
 
@@ -86,7 +95,7 @@ fn parallel_get_git_status_does_not_race() {
 
         handles.push(thread::spawn(move || {
             for _ in 0..8 {
-                match get_git_status((*path).clone()) {
+                match block_on(get_git_status((*path).clone())) {
                     Ok(info) => {
                         if info.statuses.len() != baseline_count {
                             mismatches.fetch_add(1, Ordering::Relaxed);
@@ -129,7 +138,7 @@ fn parallel_get_all_git_diffs_does_not_race() {
         let errors = Arc::clone(&errors);
         handles.push(thread::spawn(move || {
             for _ in 0..4 {
-                if get_all_git_diffs((*path).clone()).is_err() {
+                if block_on(get_all_git_diffs((*path).clone())).is_err() {
                     errors.fetch_add(1, Ordering::Relaxed);
                 }
             }
@@ -175,7 +184,7 @@ fn status_returns_a_value_under_concurrent_writes() {
 
     let mut errors = 0usize;
     for _ in 0..50 {
-        if get_git_status((*path_str).clone()).is_err() {
+        if block_on(get_git_status((*path_str).clone())).is_err() {
             errors += 1;
         }
     }
@@ -0,0 +1,122 @@
+//! Fixtures shared by the command layer integration tests: throwaway
+//! repositories built with the system `git`, and waits that poll instead
+//! of sleeping a fixed time, so slow CI runners don't flake.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// How long a wait may take before the test fails.
+pub const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run a command and fail the test unless it succeeds.
+pub fn run(program: &str, args: &[&str], cwd: &Path) {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .output()
+        .unwrap_or_else(|e| panic!("failed to spawn {program}: {e}"));
+    assert!(
+        output.status.success(),
+        "{program} {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Drive an async command to completion on its own runtime.
+pub fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("runtime")
+        .block_on(fut)
+}
+
+/// Poll `check` until it returns `Some`, failing the test after
+/// [`WAIT_TIMEOUT`].
+pub fn wait_for<T>(what: &str, mut check: impl FnMut() -> Option<T>) -> T {
+    let deadline = Instant::now() + WAIT_TIMEOUT;
+    loop {
+        if let Some(value) = check() {
+            return value;
+        }
+        assert!(Instant::now() < deadline, "timed out waiting for {what}");
+        std::thread::sleep(Duration::from_millis(25));
+    }
+}
+
+/// A repository with one commit on `main`, in a temporary directory that
+/// also holds the worktrees created next to it.
+pub struct FixtureRepo {
+    // Keeps the directory alive; dropped last.
+    _dir: TempDir,
+    pub root: PathBuf,
+}
+
+impl FixtureRepo {
+    /// `files` are committed as the initial commit, by path relative to
+    /// the checkout.
+    pub fn new(files: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let root = resolved(dir.path()).join("repo");
+        fs::create_dir(&root).expect("repo dir");
+
+        run("git", &["init", "-q", "-b", "main"], &root);
+        run("git", &["config", "user.email", "t@example.com"], &root);
+        run("git", &["config", "user.name", "Test"], &root);
+        run("git", &["config", "commit.gpgsign", "false"], &root);
+        run("git", &["config", "core.autocrlf", "false"], &root);
+        let repo = Self { _dir: dir, root };
+        for (path, contents) in files {
+            repo.write(path, contents);
+        }
+        repo.commit_all("init");
+        repo
+    }
+
+    pub fn path_string(&self) -> String {
+        self.root.to_string_lossy().into_owned()
+    }
+
+    pub fn write(&self, path: &str, contents: &str) {
+        write_in(&self.root, path, contents);
+    }
+
+    pub fn commit_all(&self, message: &str) {
+        commit_all_in(&self.root, message);
+    }
+}
+
+/// `path` with symlinks (macOS `/var`) and short names (Windows
+/// `RUNNER~1`) resolved, the way git reports worktree paths.
+pub fn resolved(path: &Path) -> PathBuf {
+    let canonical = path.canonicalize().expect("canonical path");
+    // Without the `\\?\` prefix of verbatim Windows paths.
+    match canonical.to_str().and_then(|p| p.strip_prefix(r"\\?\")) {
+        Some(plain) => PathBuf::from(plain),
+        None => canonical,
+    }
+}
+
+pub fn write_in(checkout: &Path, path: &str, contents: &str) {
+    let file = checkout.join(path);
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent).expect("parent dir");
+    }
+    fs::write(file, contents).expect("write fixture file");
+}
+
+pub fn commit_all_in(checkout: &Path, message: &str) {
+    run("git", &["add", "-A"], checkout);
+    run(
+        "git",
+        &["commit", "-q", "--allow-empty", "-m", message],
+        checkout,
+    );
+}
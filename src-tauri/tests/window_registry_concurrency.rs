@@ -2,20 +2,21 @@
 //!
 //! Two windows can race on register / unregister via the
 //! `windowService.registerWindow` and `windowService.unregisterWindow`
-//! Tauri commands. The registry sits behind an `Arc<Mutex<...>>`, so
+//! Tauri commands. The registry sits behind an `Arc<RwLock<...>>`, so
 //! the surface contract is "no panic, no data loss, no orphaned
-//! mapping" under concurrent access.
+//! mapping" under concurrent access, and lookups do not wait on each
+//! other.
 //!
 //! These tests poke `WindowRegistry` directly because the underlying
 //! struct is `pub` in `commands::window` and doesn't require a Tauri
 //! runtime to construct.
 
 use app_lib::commands::window::WindowRegistry;
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, RwLock};
 use std::thread;
 
-fn empty() -> Arc<Mutex<WindowRegistry>> {
-    Arc::new(Mutex::new(WindowRegistry::new()))
+fn empty() -> Arc<RwLock<WindowRegistry>> {
+    Arc::new(RwLock::new(WindowRegistry::new()))
 }
 
 #[test]
@@ -28,7 +29,7 @@ fn concurrent_registers_keep_label_path_mapping_consistent() {
         handles.push(thread::spawn(move || {
             let label = format!("win-{i}");
             let path = format!("/projects/p-{i}");
-            let mut guard = registry.lock().expect("lock");
+            let mut guard = registry.write().expect("lock");
             guard.register(&label, &path);
         }));
     }
@@ -37,7 +38,7 @@ fn concurrent_registers_keep_label_path_mapping_consistent() {
         h.join().expect("thread");
     }
 
-    let guard = registry.lock().expect("lock");
+    let guard = registry.read().expect("lock");
     for i in 0..32 {
         let label = format!("win-{i}");
         let path = format!("/projects/p-{i}");
@@ -51,11 +52,11 @@ fn concurrent_registers_keep_label_path_mapping_consistent() {
 fn re_registering_a_label_with_new_path_cleans_old_mapping() {
     let registry = empty();
     {
-        let mut g = registry.lock().expect("lock");
+        let mut g = registry.write().expect("lock");
         g.register("main", "/projects/old");
         g.register("main", "/projects/new");
     }
-    let g = registry.lock().expect("lock");
+    let g = registry.read().expect("lock");
     assert_eq!(g.get_path_for_label("main"), Some(&"/projects/new".to_string()));
     assert!(g.get_label_for_path("/projects/old").is_none());
     assert_eq!(g.get_label_for_path("/projects/new"), Some(&"main".to_string()));
@@ -68,7 +69,7 @@ fn re_registering_a_label_with_new_path_cleans_old_mapping() {
 fn concurrent_unregister_does_not_leak_orphan_mappings() {
     let registry = empty();
     for i in 0..16 {
-        let mut g = registry.lock().expect("lock");
+        let mut g = registry.write().expect("lock");
         g.register(&format!("w{i}"), &format!("/p/{i}"));
     }
 
@@ -76,7 +77,7 @@ fn concurrent_unregister_does_not_leak_orphan_mappings() {
     for i in 0..16 {
         let registry = Arc::clone(&registry);
         handles.push(thread::spawn(move || {
-            let mut g = registry.lock().expect("lock");
+            let mut g = registry.write().expect("lock");
             g.unregister_by_label(&format!("w{i}"));
         }));
     }
@@ -84,7 +85,7 @@ fn concurrent_unregister_does_not_leak_orphan_mappings() {
         h.join().expect("thread");
     }
 
-    let g = registry.lock().expect("lock");
+    let g = registry.read().expect("lock");
     assert!(g.get_all_paths().is_empty(), "no path mappings should remain");
     for i in 0..16 {
         assert!(g.get_path_for_label(&format!("w{i}")).is_none());
@@ -108,7 +109,7 @@ fn two_windows_focusing_same_project_resolve_to_same_label() {
             let mut i: u32 = 0;
             while !stop.load(std::sync::atomic::Ordering::Relaxed) {
                 let label = format!("main-{}", i % 2);
-                registry.lock().expect("lock").register(&label, &path);
+                registry.write().expect("lock").register(&label, &path);
                 i = i.wrapping_add(1);
             }
         })
@@ -118,7 +119,7 @@ fn two_windows_focusing_same_project_resolve_to_same_label() {
     // must always be present (never None) once the writer has gone
     // through at least one register.
     for _ in 0..1_000 {
-        let g = registry.lock().expect("lock");
+        let g = registry.read().expect("lock");
         if g.get_label_for_path(&path).is_some() {
             // We expect at most one path entry for /projects/shared
             // because re-registers must clean orphans.
@@ -133,3 +134,26 @@ fn two_windows_focusing_same_project_resolve_to_same_label() {
     stop.store(true, std::sync::atomic::Ordering::Relaxed);
     writer.join().expect("writer");
 }
+
+#[test]
+fn lookups_do_not_wait_for_each_other() {
+    let registry = empty();
+    registry.write().expect("lock").register("main", "/projects/a");
+
+    // One lookup holds the read lock while another runs on a second
+    // thread; with a single mutex the second would block until the first
+    // let go.
+    let held = registry.read().expect("lock");
+    let (tx, rx) = mpsc::channel();
+    let other = {
+        let registry = Arc::clone(&registry);
+        thread::spawn(move || {
+            let owner = registry.read().expect("lock").owner_of("/projects/a/src");
+            tx.send(owner).expect("send");
+        })
+    };
+    let owner = rx.recv_timeout(std::time::Duration::from_secs(5));
+    drop(held);
+    other.join().expect("thread");
+    assert_eq!(owner, Ok(Some("main".to_string())));
+}
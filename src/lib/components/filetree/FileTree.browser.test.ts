@@ -16,6 +16,7 @@ vi.mock('@/lib/services/gitService', () => ({
 vi.mock('@/lib/services/eventService', () => ({
   eventService: {
     listen: vi.fn().mockResolvedValue(() => {}),
+    listenBackend: vi.fn().mockResolvedValue(() => {}),
     listenCurrentWindow: vi.fn().mockResolvedValue(() => {}),
     emit: vi.fn().mockResolvedValue(undefined),
   },
@@ -28,10 +29,11 @@ vi.mock('@/lib/services/fileService', () => ({
     readFile: vi.fn().mockResolvedValue(''),
     writeFile: vi.fn().mockResolvedValue(undefined),
     readDirectory: vi.fn().mockResolvedValue([]),
+    isSlowFilesystem: vi.fn().mockResolvedValue(false),
     getHomeDirectory: vi.fn().mockResolvedValue('/home/user'),
     revealInFinder: vi.fn().mockResolvedValue(undefined),
     createDirectory: vi.fn().mockResolvedValue(''),
-    deletePath: vi.fn().mockResolvedValue(undefined),
+    deletePath: vi.fn().mockResolvedValue(true),
   },
 }));
 
@@ -19,6 +19,7 @@ vi.mock('@/lib/services/gitService', () => ({
 vi.mock('@/lib/services/eventService', () => ({
   eventService: {
     listen: vi.fn().mockResolvedValue(() => {}),
+    listenBackend: vi.fn().mockResolvedValue(() => {}),
     listenCurrentWindow: vi.fn().mockResolvedValue(() => {}),
     emit: vi.fn().mockResolvedValue(undefined),
   },
@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from './invoke';
 import { eventService } from '@/lib/services/eventService';
 import type { PaneColor } from '@/lib/stores/terminalStore';
 
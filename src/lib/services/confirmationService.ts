@@ -0,0 +1,18 @@
+import { invoke } from './invoke';
+
+/** Operations the backend only runs with a confirmation token. */
+export type PrivilegedAction = 'deletePermanently' | 'forceRemoveWorktree' | 'runInitCommands';
+
+/**
+ * Backend-issued confirmation for destructive operations
+ * Wraps the request_confirmation command for testability
+ */
+export const confirmationService = {
+  /**
+   * Ask the user in a native dialog to confirm `action` on `target`.
+   * Resolves to a single-use token for the privileged command, or null
+   * when the user cancels.
+   */
+  requestConfirmation: (action: PrivilegedAction, target: string): Promise<string | null> =>
+    invoke('request_confirmation', { action, target }),
+};
@@ -16,6 +16,7 @@ vi.mock('@tauri-apps/api/window', () => ({
 
 import { listen, emit } from '@tauri-apps/api/event';
 import { eventService } from './eventService';
+import { EVENT_SCHEMA_VERSION } from './events.generated';
 
 describe('eventService', () => {
   beforeEach(() => {
@@ -46,6 +47,38 @@ describe('eventService', () => {
     });
   });
 
+  describe('listenBackend', () => {
+    const deliver = (payload: unknown) => {
+      const wrapped = vi.mocked(listen).mock.calls[0][1];
+      wrapped({ event: 'fs-changed', id: 1, payload });
+    };
+
+    it('should unwrap the payload of a current-version envelope', async () => {
+      vi.mocked(listen).mockResolvedValue(vi.fn());
+      const handler = vi.fn();
+
+      await eventService.listenBackend('fs-changed', handler);
+      deliver({ version: EVENT_SCHEMA_VERSION, kind: 'fs-changed', payload: { path: '/repo' } });
+
+      expect(listen).toHaveBeenCalledWith('fs-changed', expect.any(Function));
+      expect(handler).toHaveBeenCalledWith({ path: '/repo' });
+    });
+
+    it('should drop events with another schema version or no envelope', async () => {
+      vi.mocked(listen).mockResolvedValue(vi.fn());
+      const warn = vi.spyOn(console, 'warn').mockImplementation(() => {});
+      const handler = vi.fn();
+
+      await eventService.listenBackend('fs-changed', handler);
+      deliver({ version: EVENT_SCHEMA_VERSION + 1, kind: 'fs-changed', payload: { path: '/a' } });
+      deliver({ path: '/b' });
+
+      expect(handler).not.toHaveBeenCalled();
+      expect(warn).toHaveBeenCalledTimes(1);
+      warn.mockRestore();
+    });
+  });
+
   describe('listenCurrentWindow', () => {
     it('should call getCurrentWindow().listen with event name and handler', async () => {
       const mockUnlisten = vi.fn();
@@ -1,5 +1,22 @@
 import { listen, emit, type UnlistenFn, type EventCallback } from '@tauri-apps/api/event';
 import { getCurrentWindow } from '@tauri-apps/api/window';
+import {
+  EVENT_SCHEMA_VERSION,
+  type BackendEventKind,
+  type BackendEvents,
+  type EventEnvelope,
+} from './events.generated';
+
+const warnedKinds = new Set<string>();
+
+function warnVersionMismatch(kind: string, version: unknown) {
+  if (warnedKinds.has(kind)) return;
+  warnedKinds.add(kind);
+  console.warn(
+    `[events] Dropping '${kind}' events with schema version ${String(version)}; ` +
+      `this build expects version ${EVENT_SCHEMA_VERSION}`
+  );
+}
 
 /**
  * Tauri event service
@@ -12,6 +29,24 @@ export const eventService = {
   listen: <T>(event: string, handler: EventCallback<T>): Promise<UnlistenFn> =>
     listen(event, handler),
 
+  /**
+   * Listen for a versioned backend event (see events.generated.ts) and
+   * receive its payload. Events from a backend with a different schema
+   * version are dropped with a warning instead of being misread.
+   */
+  listenBackend: <K extends BackendEventKind>(
+    kind: K,
+    handler: (payload: BackendEvents[K]) => void
+  ): Promise<UnlistenFn> =>
+    listen<EventEnvelope<K>>(kind, (event) => {
+      const envelope = event.payload;
+      if (envelope?.version !== EVENT_SCHEMA_VERSION) {
+        warnVersionMismatch(kind, envelope?.version);
+        return;
+      }
+      handler(envelope.payload);
+    }),
+
   /**
    * Listen for a Tauri event scoped to the current window only
    */
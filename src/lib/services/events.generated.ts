@@ -0,0 +1,102 @@
+// Generated from src-tauri/src/commands/events.rs. Do not edit by hand;
+// regenerate with `KIRI_UPDATE_EVENT_TYPES=1 cargo test --lib events`.
+
+/** Bumped on any incompatible change to an event payload. */
+export const EVENT_SCHEMA_VERSION = 1;
+
+export type JobStatus =
+  | { state: 'queued' }
+  | { state: 'running' }
+  | { state: 'completed' }
+  | { state: 'failed'; error: string }
+  | { state: 'cancelled' };
+
+/** Files changed under a watched path. */
+export interface FsChangeEvent {
+  path: string;
+}
+
+/** The git state of a watched repository changed. */
+export interface GitChangeEvent {
+  repo_root: string;
+}
+
+/** A chunk of terminal output. */
+export interface TerminalOutput {
+  id: number;
+  data: string;
+}
+
+/** A background job changed state or reported progress. */
+export interface JobInfo {
+  id: number;
+  kind: string;
+  label: string;
+  status: JobStatus;
+  progress: number | null;
+  message: string | null;
+  createdMs: number;
+  updatedMs: number;
+}
+
+/** A chunk of a task run's output. */
+export interface TaskOutput {
+  run_id: number;
+  data: string;
+}
+
+/** A chunk of an agent session's output. */
+export interface AgentOutput {
+  session_id: number;
+  data: string;
+}
+
+/** A chunk of a container's logs. */
+export interface DockerLogEvent {
+  container_id: string;
+  data: string;
+}
+
+/** Counts so far for a running test job. */
+export interface TestProgress {
+  run_id: number;
+  passed: number;
+  failed: number;
+  skipped: number;
+  current: string | null;
+}
+
+/** A project's TODO scan was refreshed. */
+export interface TodosUpdatedEvent {
+  project: string;
+}
+
+/** A filesystem operation ran over its time budget. */
+export interface SlowFilesystemEvent {
+  path: string;
+  operation: string;
+  partial: boolean;
+}
+
+/** Payload type of each versioned backend event, by kind. */
+export interface BackendEvents {
+  'fs-changed': FsChangeEvent;
+  'git-status-changed': GitChangeEvent;
+  'terminal-output': TerminalOutput;
+  'job-updated': JobInfo;
+  'task-output': TaskOutput;
+  'agent-output': AgentOutput;
+  'docker-log': DockerLogEvent;
+  'test-progress': TestProgress;
+  'todos-updated': TodosUpdatedEvent;
+  'slow-filesystem': SlowFilesystemEvent;
+}
+
+export type BackendEventKind = keyof BackendEvents;
+
+/** What the backend emits for every event in BackendEvents. */
+export interface EventEnvelope<K extends BackendEventKind = BackendEventKind> {
+  version: number;
+  kind: K;
+  payload: BackendEvents[K];
+}
@@ -1,5 +1,30 @@
-import { invoke } from '@tauri-apps/api/core';
+import { invoke, toKiriError, type KiriError } from './invoke';
+import { confirmationService } from './confirmationService';
 import type { FileEntry } from '@/lib/components/filetree/types';
+import type { GeneratedKind } from '@/lib/stores/gitStore';
+
+/** One file of `readFiles`: its content, or why it couldn't be read. */
+export type FileReadResult = { content: string } | { error: KiriError };
+
+/** `FileReadResult` as sent, with the error still a plain payload. */
+type RawFileReadResult = { content: string } | { error: unknown };
+
+export type EditorKind =
+  | 'vscode'
+  | 'cursor'
+  | 'idea'
+  | 'webstorm'
+  | 'pycharm'
+  | 'goland'
+  | 'rustrover'
+  | 'zed';
+
+export interface ExternalEditor {
+  kind: EditorKind;
+  label: string;
+  installed: boolean;
+  launcher: string | null;
+}
 
 /**
  * File system operations service
@@ -7,9 +32,37 @@ import type { FileEntry } from '@/lib/components/filetree/types';
  */
 export const fileService = {
   /**
-   * Read file contents as UTF-8 text
+   * Read file contents as UTF-8 text. A file over the large-file limits
+   * rejects with code `file_too_large` and its stats in `details`, unless
+   * `force` opens it anyway (read-only)
    */
-  readFile: (path: string): Promise<string> => invoke('read_file', { path }),
+  readFile: (path: string, force?: boolean): Promise<string> =>
+    invoke('read_file', { path, force }),
+
+  /**
+   * Read several small files in parallel, keyed by the paths as given. A file
+   * that can't be read gets its error in its place instead of rejecting all
+   */
+  readFiles: async (paths: string[]): Promise<Record<string, FileReadResult>> => {
+    const read = await invoke<Record<string, RawFileReadResult>>('read_files', { paths });
+    return Object.fromEntries(
+      Object.entries(read).map(([path, result]): [string, FileReadResult] => [
+        path,
+        'error' in result ? { error: toKiriError(result.error) as KiriError } : result,
+      ])
+    );
+  },
+
+  /**
+   * Up to `length` bytes from `offset`, for paging through or showing as hex
+   * a file too large to open
+   */
+  readFileChunk: (
+    path: string,
+    offset: number,
+    length: number
+  ): Promise<{ offset: number; dataBase64: string; size: number; eof: boolean }> =>
+    invoke('read_file_chunk', { path, offset, length }),
 
   /**
    * Read file contents as base64-encoded string (for binary files like images)
@@ -21,6 +74,12 @@ export const fileService = {
    */
   readDirectory: (path: string): Promise<FileEntry[]> => invoke('read_directory', { path }),
 
+  /**
+   * Whether the drive holding `path` recently ran out of time on a listing,
+   * search or git status, so optional work there should be skipped
+   */
+  isSlowFilesystem: (path: string): Promise<boolean> => invoke('is_slow_filesystem', { path }),
+
   /**
    * Get home directory path
    */
@@ -32,9 +91,18 @@ export const fileService = {
   revealInFinder: (path: string): Promise<void> => invoke('reveal_in_finder', { path }),
 
   /**
-   * Delete file or directory
+   * Permanently delete a file or directory once the user confirms in a
+   * native dialog. Resolves to false when they cancel.
    */
-  deletePath: (path: string): Promise<void> => invoke('delete_path', { path }),
+  deletePath: async (path: string): Promise<boolean> => {
+    const confirmationToken = await confirmationService.requestConfirmation(
+      'deletePermanently',
+      path
+    );
+    if (confirmationToken === null) return false;
+    await invoke('delete_path', { path, confirmationToken });
+    return true;
+  },
 
   /**
    * Move file or directory to target directory
@@ -90,4 +158,28 @@ export const fileService = {
    * Open the OS-native terminal app at `path` (or its parent if `path` is a file).
    */
   openTerminalHere: (path: string): Promise<void> => invoke('open_terminal_here', { path }),
+
+  /**
+   * External editors kiri can hand files to, and which are installed
+   */
+  listExternalEditors: (): Promise<ExternalEditor[]> => invoke('list_external_editors'),
+
+  /**
+   * Open `path` in `editor` (the first installed one by default). A file opens
+   * within its worktree, at `line` and `column` when given. Resolves to the
+   * editor used
+   */
+  openInExternalEditor: (
+    path: string,
+    editor?: EditorKind,
+    line?: number,
+    column?: number
+  ): Promise<EditorKind> => invoke('open_in_external_editor', { path, editor, line, column }),
+
+  /**
+   * Which of `paths` are lockfiles, build output, minified, source maps or
+   * vendored, by the built-in rules and `[generated]` of `.kiri.toml`
+   */
+  classifyGeneratedPaths: (paths: string[]): Promise<Record<string, GeneratedKind | null>> =>
+    invoke('classify_generated_paths', { paths }),
 };
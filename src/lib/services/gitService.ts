@@ -1,5 +1,5 @@
-import { invoke } from '@tauri-apps/api/core';
-import type { GitRepoInfo, GitFileDiff } from '@/lib/stores/gitStore';
+import { invoke } from './invoke';
+import type { GeneratedKind, GitRepoInfo, GitFileDiff, GitFileStatus } from '@/lib/stores/gitStore';
 
 export interface CommitInfo {
   id: string;
@@ -15,12 +15,25 @@ export interface CommitInfo {
   graph_column: number;
 }
 
+/** Line counts of one changed file, without its hunks */
+export interface GitFileStat {
+  path: string;
+  /** Path before a rename */
+  old_path: string | null;
+  status: GitFileStatus;
+  insertions: number;
+  deletions: number;
+  is_binary: boolean;
+  generated: GeneratedKind | null;
+}
+
 export interface CommitFileDiff {
   path: string;
   status: string;
   diff: string;
   additions: number;
   deletions: number;
+  generated: GeneratedKind | null;
 }
 
 export interface CommitDiffResult {
@@ -30,14 +43,22 @@ export interface CommitDiffResult {
   total_deletions: number;
 }
 
+/** Attempts a network git command took; earlier ones failed transiently. */
+export interface RetryReport {
+  attempts: number;
+  failures: string[];
+}
+
 export interface PushResult {
   success: boolean;
   message: string;
+  retry: RetryReport;
 }
 
 export interface FetchResult {
   success: boolean;
   message: string;
+  retry: RetryReport;
 }
 
 export interface BehindAheadCount {
@@ -50,6 +71,12 @@ export interface PullResult {
   message: string;
 }
 
+/** Who a commit is attributed to instead of the configured git user */
+export interface CommitAuthor {
+  name: string;
+  email: string;
+}
+
 /**
  * Git operations service
  * Wraps Tauri git commands for testability
@@ -73,6 +100,13 @@ export const gitService = {
   getAllDiffs: (repoPath: string): Promise<GitFileDiff[]> =>
     invoke('get_all_git_diffs', { repoPath }),
 
+  /**
+   * Get +/- counts per changed file against `base` (HEAD by default),
+   * without loading the diffs themselves
+   */
+  getDiffStats: (repoPath: string, base?: string): Promise<GitFileStat[]> =>
+    invoke('get_diff_stats', { repoPath, base: base ?? null }),
+
   /**
    * Get commit log for a repository
    */
@@ -119,4 +153,43 @@ export const gitService = {
    */
   pullCommits: (repoPath: string, remote?: string, branch?: string): Promise<PullResult> =>
     invoke('pull_commits', { repoPath, remote: remote ?? null, branch: branch ?? null }),
+
+  /**
+   * Stage a file as it is in the working tree, or its deletion
+   */
+  stageFile: (repoPath: string, filePath: string): Promise<void> =>
+    invoke('stage_file', { repoPath, filePath }),
+
+  /**
+   * Put a file in the index back to HEAD, keeping the working tree as is
+   */
+  unstageFile: (repoPath: string, filePath: string): Promise<void> =>
+    invoke('unstage_file', { repoPath, filePath }),
+
+  /**
+   * Stage one hunk of a file's unstaged diff, named by the start lines of its
+   * `@@ -oldStart +newStart @@` header
+   */
+  stageHunk: (
+    repoPath: string,
+    filePath: string,
+    oldStart: number,
+    newStart: number
+  ): Promise<void> => invoke('stage_hunk', { repoPath, filePath, oldStart, newStart }),
+
+  /**
+   * Commit what is staged, as `author` when given. With `amend` HEAD is
+   * replaced; a blank message then keeps its message
+   */
+  createCommit: (
+    repoPath: string,
+    message: string,
+    options: { author?: CommitAuthor; amend?: boolean } = {}
+  ): Promise<CommitInfo> =>
+    invoke('create_commit', {
+      repoPath,
+      message,
+      author: options.author ?? null,
+      amend: options.amend ?? false,
+    }),
 };
@@ -0,0 +1,57 @@
+import { describe, it, expect, vi, beforeEach, type Mock } from 'vitest';
+import { invoke, KiriError, hasErrorCode } from './invoke';
+
+vi.mock('@tauri-apps/api/core', () => ({
+  invoke: vi.fn(),
+}));
+
+describe('invoke', () => {
+  let mockInvoke: Mock;
+
+  beforeEach(async () => {
+    vi.clearAllMocks();
+    const core = await import('@tauri-apps/api/core');
+    mockInvoke = core.invoke as Mock;
+  });
+
+  it('should pass the command and arguments through', async () => {
+    mockInvoke.mockResolvedValue('contents');
+
+    await expect(invoke('read_file', { path: '/a.txt' })).resolves.toBe('contents');
+    expect(mockInvoke).toHaveBeenCalledWith('read_file', { path: '/a.txt' });
+  });
+
+  it('should reject with a KiriError for a command error', async () => {
+    mockInvoke.mockRejectedValue({
+      code: 'not_found',
+      message: 'File does not exist',
+      retryable: false,
+    });
+
+    const error = await invoke('read_file', { path: '/missing' }).catch((e: unknown) => e);
+    expect(error).toBeInstanceOf(KiriError);
+    expect(hasErrorCode(error, 'not_found')).toBe(true);
+    expect((error as KiriError).retryable).toBe(false);
+    expect(String(error)).toBe('File does not exist');
+  });
+
+  it('should keep the catalog key and parameters of a translated error', async () => {
+    mockInvoke.mockRejectedValue({
+      code: 'not_trusted',
+      message: 'app は信頼されていません。コマンドを実行するにはフォルダを信頼してください',
+      retryable: false,
+      key: 'error.not_trusted',
+      params: { name: 'app' },
+    });
+
+    const error = (await invoke('run_task').catch((e: unknown) => e)) as KiriError;
+    expect(error.key).toBe('error.not_trusted');
+    expect(error.params).toEqual({ name: 'app' });
+  });
+
+  it('should rethrow other errors unchanged', async () => {
+    mockInvoke.mockRejectedValue('plain failure');
+
+    await expect(invoke('read_file')).rejects.toBe('plain failure');
+  });
+});
@@ -0,0 +1,122 @@
+import { invoke as tauriInvoke } from '@tauri-apps/api/core';
+
+/**
+ * Stable error codes, mirroring `ErrorCode` in src-tauri/src/commands/error.rs
+ */
+export type KiriErrorCode =
+  | 'not_found'
+  | 'already_exists'
+  | 'permission_denied'
+  | 'invalid_input'
+  | 'not_trusted'
+  | 'unauthenticated'
+  | 'unavailable'
+  | 'network'
+  | 'timeout'
+  | 'cancelled'
+  | 'unsupported'
+  | 'conflict'
+  | 'file_too_large'
+  | 'internal';
+
+/**
+ * Structured data sent with some codes, mirroring `ErrorDetails` in
+ * src-tauri/src/commands/error.rs
+ */
+export type KiriErrorDetails = {
+  kind: 'largeFile';
+  reason: 'size' | 'lineLength';
+  size: number;
+  lineCount: number | null;
+  longestLine: number | null;
+  limits: { maxBytes: number; maxLineLength: number };
+  strategies: Array<'readOnly' | 'chunked' | 'hex'>;
+};
+
+/**
+ * The error a failed command rejects with. Branch on `code`, show `message`.
+ * `toString()` is the message, so `String(error)` reads as it always has.
+ * The message is already in the user's language; `key` and `params` name
+ * its entry in the backend's message catalogs when it has one.
+ */
+export class KiriError extends Error {
+  readonly code: KiriErrorCode;
+  readonly retryable: boolean;
+  readonly key?: string;
+  readonly params?: Record<string, string>;
+  readonly details?: KiriErrorDetails;
+
+  constructor(
+    code: KiriErrorCode,
+    message: string,
+    retryable = false,
+    key?: string,
+    params?: Record<string, string>,
+    details?: KiriErrorDetails
+  ) {
+    super(message);
+    this.name = 'KiriError';
+    this.code = code;
+    this.retryable = retryable;
+    this.key = key;
+    this.params = params;
+    this.details = details;
+  }
+
+  override toString(): string {
+    return this.message;
+  }
+}
+
+function isKiriErrorPayload(value: unknown): value is {
+  code: KiriErrorCode;
+  message: string;
+  retryable: boolean;
+  key?: string;
+  params?: Record<string, string>;
+  details?: KiriErrorDetails;
+} {
+  return (
+    typeof value === 'object' &&
+    value !== null &&
+    typeof (value as { code?: unknown }).code === 'string' &&
+    typeof (value as { message?: unknown }).message === 'string'
+  );
+}
+
+/**
+ * Whether `error` is a command error with `code`
+ */
+export function hasErrorCode(error: unknown, code: KiriErrorCode): boolean {
+  return error instanceof KiriError && error.code === code;
+}
+
+/**
+ * `value` as a {@link KiriError} when it is an error sent by a command, such as
+ * a per-item error in a command's result; anything else is returned as is
+ */
+export function toKiriError(value: unknown): unknown {
+  if (!isKiriErrorPayload(value)) {
+    return value;
+  }
+  return new KiriError(
+    value.code,
+    value.message,
+    value.retryable === true,
+    value.key,
+    value.params,
+    value.details
+  );
+}
+
+/**
+ * `invoke` from @tauri-apps/api/core that rejects with a {@link KiriError}
+ * when the command returns one
+ */
+export async function invoke<T>(...params: Parameters<typeof tauriInvoke>): Promise<T> {
+  try {
+    return await tauriInvoke<T>(...params);
+  } catch (error) {
+    throw toKiriError(error);
+  }
+}
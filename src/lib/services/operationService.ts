@@ -0,0 +1,42 @@
+import { invoke } from './invoke';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+
+/**
+ * A long-running command call that can be cancelled by id. Passed to the
+ * command as its `operation` argument; closing `windowLabel` cancels it.
+ */
+export interface Operation {
+  id: string;
+  windowLabel: string | null;
+}
+
+let counter = 0;
+
+function currentWindowLabel(): string | null {
+  try {
+    return getCurrentWindow().label;
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * Cancellation of long-running commands (search, copy, git fetch / pull / push)
+ */
+export const operationService = {
+  /**
+   * A fresh operation owned by this window
+   * @param kind Short name used in the id, e.g. `search`
+   */
+  start(kind: string): Operation {
+    counter += 1;
+    const windowLabel = currentWindowLabel();
+    return { id: `${windowLabel ?? 'app'}:${kind}-${counter}`, windowLabel };
+  },
+
+  /**
+   * Cancel an operation, or a background job by `job-<id>`.
+   * Rejects with `not_found` when it already finished.
+   */
+  cancel: (id: string): Promise<void> => invoke('cancel_operation', { id }),
+};
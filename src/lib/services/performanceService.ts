@@ -4,7 +4,7 @@
  * Provides utilities for measuring and tracking performance metrics.
  * All functionality is no-op in production builds (tree-shaken away).
  */
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from './invoke';
 
 const isDev = import.meta.env.DEV;
 
@@ -30,6 +30,22 @@ export interface CommandTiming {
   duration_ms: number;
   /** Timestamp since app start (ms) */
   timestamp_ms: number;
+  /** Serialized argument size, for commands timed by the invoke handler */
+  arg_bytes: number | null;
+}
+
+/**
+ * Per-command aggregate of command timings
+ */
+export interface CommandStats {
+  command: string;
+  count: number;
+  total_ms: number;
+  max_ms: number;
+  /** 95th percentile duration */
+  p95_ms: number;
+  /** Largest argument payload seen */
+  max_arg_bytes: number | null;
 }
 
 /**
@@ -40,6 +56,8 @@ export interface PerformanceReport {
   memory: MemoryMetrics;
   /** Command timings */
   command_timings: CommandTiming[];
+  /** Per-command aggregates, slowest total first */
+  command_stats: CommandStats[];
   /** App uptime in milliseconds */
   app_uptime_ms: number;
 }
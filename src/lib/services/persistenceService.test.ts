@@ -1,4 +1,5 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { invoke } from '@tauri-apps/api/core';
 import { Store } from '@tauri-apps/plugin-store';
 import {
   STARTUP_COMMANDS,
@@ -88,7 +89,7 @@ describe('getDefaultProjectSettings', () => {
   });
 });
 
-describe('GlobalSettings (with Store mock)', () => {
+describe('GlobalSettings (with Store and invoke mocks)', () => {
   let mockStore: {
     get: ReturnType<typeof vi.fn>;
     set: ReturnType<typeof vi.fn>;
@@ -113,50 +114,43 @@ describe('GlobalSettings (with Store mock)', () => {
   }
 
   describe('loadSettings', () => {
-    it('should return default settings when no settings are stored', async () => {
+    it('should return default settings when the backend returns nothing', async () => {
       const { loadSettings } = await importModule();
-      mockStore.get.mockResolvedValue(null);
+      vi.mocked(invoke).mockResolvedValue(null);
 
       const result = await loadSettings();
 
+      expect(invoke).toHaveBeenCalledWith('get_settings');
       expect(result.fontSize).toBe(13);
       expect(result.startupCommand).toBe('none');
     });
 
-    it('should reload store before reading', async () => {
+    it('should not read the store', async () => {
       const { loadSettings } = await importModule();
-      const callOrder: string[] = [];
-      mockStore.reload.mockImplementation(() => {
-        callOrder.push('reload');
-        return Promise.resolve();
-      });
-      mockStore.get.mockImplementation(() => {
-        callOrder.push('get');
-        return Promise.resolve(null);
-      });
+      vi.mocked(invoke).mockResolvedValue(null);
 
       await loadSettings();
 
-      expect(callOrder[0]).toBe('reload');
-      expect(callOrder[1]).toBe('get');
+      expect(mockStore.get).not.toHaveBeenCalled();
     });
 
     it('should return stored settings when available', async () => {
       const { loadSettings } = await importModule();
-      mockStore.get.mockResolvedValue({
+      vi.mocked(invoke).mockResolvedValue({
+        version: 1,
         fontSize: 16,
         startupCommand: 'claude',
+        showTrayIcon: false,
       });
 
       const result = await loadSettings();
 
-      expect(result.fontSize).toBe(16);
-      expect(result.startupCommand).toBe('claude');
+      expect(result).toEqual({ fontSize: 16, startupCommand: 'claude' });
     });
 
     it('should fill missing fields with defaults for partial settings', async () => {
       const { loadSettings } = await importModule();
-      mockStore.get.mockResolvedValue({ fontSize: 18 });
+      vi.mocked(invoke).mockResolvedValue({ fontSize: 18 });
 
       const result = await loadSettings();
 
@@ -164,20 +158,10 @@ describe('GlobalSettings (with Store mock)', () => {
       expect(result.startupCommand).toBe('none');
     });
 
-    it('should use default fontSize when fontSize is undefined', async () => {
-      const { loadSettings } = await importModule();
-      mockStore.get.mockResolvedValue({ startupCommand: 'claude', fontSize: undefined });
-
-      const result = await loadSettings();
-
-      expect(result.fontSize).toBe(13);
-      expect(result.startupCommand).toBe('claude');
-    });
-
-    it('should return default settings when store throws an error', async () => {
+    it('should return default settings when the backend throws an error', async () => {
       const { loadSettings } = await importModule();
       const errorSpy = vi.spyOn(console, 'error').mockImplementation(() => {});
-      mockStore.reload.mockRejectedValue(new Error('Store error'));
+      vi.mocked(invoke).mockRejectedValue(new Error('Backend error'));
 
       const result = await loadSettings();
 
@@ -188,22 +172,23 @@ describe('GlobalSettings (with Store mock)', () => {
   });
 
   describe('saveSettings', () => {
-    it('should save settings with the globalSettings key', async () => {
+    it('should send the settings to update_settings', async () => {
       const { saveSettings } = await importModule();
+      vi.mocked(invoke).mockResolvedValue(undefined);
       const settings = { fontSize: 16, startupCommand: 'claude' as const };
 
       await saveSettings(settings);
 
-      expect(mockStore.set).toHaveBeenCalledWith('globalSettings', settings);
-      expect(mockStore.save).toHaveBeenCalled();
+      expect(invoke).toHaveBeenCalledWith('update_settings', { patch: settings });
+      expect(mockStore.set).not.toHaveBeenCalled();
     });
 
-    it('should not throw when store save fails', async () => {
+    it('should not throw when the update is rejected', async () => {
       const { saveSettings } = await importModule();
       const errorSpy = vi.spyOn(console, 'error').mockImplementation(() => {});
-      mockStore.save.mockRejectedValue(new Error('Save error'));
+      vi.mocked(invoke).mockRejectedValue('fontSize must be between 8 and 32');
 
-      await expect(saveSettings({ fontSize: 13, startupCommand: 'none' })).resolves.not.toThrow();
+      await expect(saveSettings({ fontSize: 99, startupCommand: 'none' })).resolves.not.toThrow();
       errorSpy.mockRestore();
     });
   });
@@ -292,11 +277,11 @@ describe('GlobalSettings (with Store mock)', () => {
   describe('store caching', () => {
     it('should reuse cached store on subsequent calls', async () => {
       vi.mocked(Store.load).mockClear();
-      const { loadSettings, saveSettings } = await importModule();
+      const { loadProjectSettings, saveProjectSettings } = await importModule();
       mockStore.get.mockResolvedValue(null);
 
-      await loadSettings();
-      await saveSettings({ fontSize: 14, startupCommand: 'none' });
+      await loadProjectSettings('/path/to/project');
+      await saveProjectSettings('/path/to/project', { searchExcludePatterns: [] });
 
       // Store.load should have been called only once (cached for second call)
       expect(Store.load).toHaveBeenCalledTimes(1);
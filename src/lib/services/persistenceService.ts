@@ -1,3 +1,4 @@
+import { invoke } from './invoke';
 import { Store } from '@tauri-apps/plugin-store';
 
 const STORE_PATH = 'kiri-settings.json';
@@ -55,14 +56,12 @@ async function getStore(): Promise<Store> {
 }
 
 /**
- * Load global settings (font size, etc.)
+ * Load global settings (font size, etc.) from the backend, which fills
+ * in defaults for missing or invalid values
  */
 export async function loadSettings(): Promise<PersistedSettings> {
   try {
-    const s = await getStore();
-    await s.reload();
-
-    const settings = await s.get<PersistedSettings>('globalSettings');
+    const settings = await invoke<Partial<PersistedSettings> | null>('get_settings');
     if (!settings) {
       return { ...DEFAULT_SETTINGS };
     }
@@ -78,13 +77,14 @@ export async function loadSettings(): Promise<PersistedSettings> {
 }
 
 /**
- * Save global settings
+ * Save global settings. The backend validates them and rejects the
+ * update as a whole if a value is out of range.
  */
 export async function saveSettings(settings: PersistedSettings): Promise<void> {
   try {
-    const s = await getStore();
-    await s.set('globalSettings', settings);
-    await s.save();
+    await invoke('update_settings', {
+      patch: { fontSize: settings.fontSize, startupCommand: settings.startupCommand },
+    });
   } catch (error) {
     console.error('Failed to save settings:', error);
   }
@@ -1,4 +1,5 @@
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from './invoke';
+import type { Operation } from './operationService';
 
 /**
  * Result of a content match within a file
@@ -29,19 +30,22 @@ export const searchService = {
    * @param query Search query (minimum 2 characters)
    * @param maxResults Maximum number of files to return
    * @param excludePatterns Custom patterns to exclude (in addition to defaults)
+   * @param operation Lets the search be cancelled through operationService
    * @returns Array of files with matching content
    */
   async searchContent(
     rootPath: string,
     query: string,
     maxResults: number = 100,
-    excludePatterns: string[] = []
+    excludePatterns: string[] = [],
+    operation?: Operation
   ): Promise<ContentSearchResult[]> {
     return invoke<ContentSearchResult[]>('search_content', {
       rootPath,
       query,
       maxResults,
       excludePatterns,
+      ...(operation && { operation }),
     });
   },
 };
@@ -1,4 +1,41 @@
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from './invoke';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
+
+/**
+ * Handle for one startWatching call. Watchers are shared per path, so
+ * stopping a subscription only stops the watcher once no other view still
+ * subscribes to the same path.
+ */
+export interface WatchSubscription {
+  id: number;
+  path: string;
+}
+
+/** Why a watcher polls instead of using native file events. */
+export type PollReason =
+  | { kind: 'filesystem'; fsType: string }
+  | { kind: 'nativeFailed'; error: string };
+
+export interface WatcherStatus {
+  path: string;
+  backend: 'native' | 'poll';
+  pollReason: PollReason | null;
+  subscriptions: number;
+  batches: number;
+  events: number;
+  /** Backend errors; the changes they were about are lost. */
+  dropped: number;
+  lastError: string | null;
+  lastEventAt: number | null;
+}
+
+export interface WatcherHealth {
+  native: number;
+  polling: number;
+  subscriptions: number;
+  pollIntervalMs: number;
+  watchers: WatcherStatus[];
+}
 
 /**
  * File system watcher service
@@ -6,12 +43,20 @@ import { invoke } from '@tauri-apps/api/core';
  */
 export const watcherService = {
   /**
-   * Start watching a path for changes
+   * Start watching a path for changes. The subscription belongs to the
+   * calling window and is dropped automatically when the window closes.
+   */
+  startWatching: (path: string): Promise<WatchSubscription> =>
+    invoke('start_watching', { path, windowLabel: getCurrentWebviewWindow().label }),
+
+  /**
+   * Drop a subscription returned by startWatching
    */
-  startWatching: (path: string): Promise<void> => invoke('start_watching', { path }),
+  stopWatching: (subscriptionId: number): Promise<void> =>
+    invoke('stop_watching', { subscriptionId, windowLabel: getCurrentWebviewWindow().label }),
 
   /**
-   * Stop watching a path
+   * Backend, subscriptions and event counts of every running watcher
    */
-  stopWatching: (path: string): Promise<void> => invoke('stop_watching', { path }),
+  getWatcherHealth: (): Promise<WatcherHealth> => invoke('get_watcher_health'),
 };
@@ -35,6 +35,7 @@ function createMockCommitDiffResult(overrides: Partial<CommitDiffResult> = {}):
         diff: '@@ -1,3 +1,4 @@\n+new line',
         additions: 1,
         deletions: 0,
+        generated: null,
       },
     ],
     total_additions: 1,
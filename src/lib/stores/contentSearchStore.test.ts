@@ -11,6 +11,7 @@ import {
 } from './contentSearchStore';
 import { searchService } from '@/lib/services/searchService';
 import { saveProjectSettings } from '@/lib/services/persistenceService';
+import { operationService } from '@/lib/services/operationService';
 
 // Mock the search service
 vi.mock('@/lib/services/searchService', () => ({
@@ -19,6 +20,14 @@ vi.mock('@/lib/services/searchService', () => ({
   },
 }));
 
+// Mock the operation service
+vi.mock('@/lib/services/operationService', () => ({
+  operationService: {
+    start: vi.fn((kind: string) => ({ id: `main:${kind}`, windowLabel: 'main' })),
+    cancel: vi.fn().mockResolvedValue(undefined),
+  },
+}));
+
 // Mock the persistence service
 vi.mock('@/lib/services/persistenceService', () => ({
   loadProjectSettings: vi.fn().mockResolvedValue({
@@ -285,7 +294,13 @@ describe('contentSearchStore', () => {
       expect(state.selectedFileIndex).toBe(0);
       expect(state.selectedMatchIndex).toBe(0);
       expect(state.error).toBeNull();
-      expect(mockSearchContent).toHaveBeenCalledWith('/path/to/project', 'foo', 100, ['*.min.js']);
+      expect(mockSearchContent).toHaveBeenCalledWith(
+        '/path/to/project',
+        'foo',
+        100,
+        ['*.min.js'],
+        { id: 'main:search', windowLabel: 'main' }
+      );
     });
 
     it('should set error when searchService.searchContent throws an Error', async () => {
@@ -346,7 +361,29 @@ describe('contentSearchStore', () => {
 
       // Only the last query should be used
       expect(mockSearchContent).toHaveBeenCalledTimes(1);
-      expect(mockSearchContent).toHaveBeenCalledWith('/path/to/project', 'foob', 100, ['*.min.js']);
+      expect(mockSearchContent).toHaveBeenCalledWith(
+        '/path/to/project',
+        'foob',
+        100,
+        ['*.min.js'],
+        { id: 'main:search', windowLabel: 'main' }
+      );
+    });
+
+    it('should cancel a search still running when a new one starts', async () => {
+      vi.useFakeTimers();
+      const mockSearchContent = vi.mocked(searchService.searchContent);
+      mockSearchContent.mockReturnValueOnce(new Promise(() => {}));
+      mockSearchContent.mockResolvedValue(mockSearchResults);
+
+      await contentSearchStore.open('/path/to/project');
+      contentSearchStore.search('foo');
+      await vi.advanceTimersByTimeAsync(200);
+      contentSearchStore.search('food');
+      await vi.advanceTimersByTimeAsync(200);
+
+      expect(operationService.cancel).toHaveBeenCalledWith('main:search');
+      expect(get(contentSearchStore).results).toEqual(mockSearchResults);
     });
 
     it('should cancel pending search timeout on close', async () => {
@@ -8,6 +8,8 @@
 
 import { writable, derived } from 'svelte/store';
 import { searchService } from '@/lib/services/searchService';
+import { hasErrorCode } from '@/lib/services/invoke';
+import { operationService, type Operation } from '@/lib/services/operationService';
 import {
   loadProjectSettings,
   saveProjectSettings,
@@ -38,6 +40,7 @@ function createContentSearchStore() {
   };
 
   let searchTimeout: ReturnType<typeof setTimeout> | null = null;
+  let runningSearch: Operation | null = null;
   let lastProjectPath: string | null = null;
 
   const store = {
@@ -77,6 +80,10 @@ function createContentSearchStore() {
         clearTimeout(searchTimeout);
         searchTimeout = null;
       }
+      if (runningSearch) {
+        operationService.cancel(runningSearch.id).catch(() => {});
+        runningSearch = null;
+      }
       patch({ isOpen: false, isSearching: false, isSettingsOpen: false });
     },
 
@@ -109,12 +116,20 @@ function createContentSearchStore() {
           return;
         }
 
+        // A search still running for an earlier query is no longer wanted
+        if (runningSearch) {
+          operationService.cancel(runningSearch.id).catch(() => {});
+        }
+        const operation = operationService.start('search');
+        runningSearch = operation;
+
         try {
           const results = await searchService.searchContent(
             current.projectPath,
             query,
             100,
-            current.excludePatterns
+            current.excludePatterns,
+            operation
           );
           patch({
             results,
@@ -124,11 +139,18 @@ function createContentSearchStore() {
             error: null,
           });
         } catch (error) {
+          if (hasErrorCode(error, 'cancelled')) {
+            return;
+          }
           console.error('Content search failed:', error);
           patch({
             isSearching: false,
             error: error instanceof Error ? error.message : 'Search failed',
           });
+        } finally {
+          if (runningSearch === operation) {
+            runningSearch = null;
+          }
         }
       }, 200);
     },
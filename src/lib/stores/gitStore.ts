@@ -1,5 +1,5 @@
 import { writable, derived, get } from 'svelte/store';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '@/lib/services/invoke';
 
 export type GitFileStatus =
   | 'Modified'
@@ -15,6 +15,15 @@ export interface GitStatusEntry {
   status: GitFileStatus;
 }
 
+/** Why a file counts as generated; see `generated.rs`. */
+export type GeneratedKind =
+  | 'lockfile'
+  | 'buildOutput'
+  | 'minified'
+  | 'sourceMap'
+  | 'vendored'
+  | 'configured';
+
 export interface GitFileDiff {
   path: string;
   status: GitFileStatus;
@@ -24,6 +33,8 @@ export interface GitFileDiff {
   current_content_base64: string | null;
   /** Base64 encoded original file content from HEAD (for binary/image files) */
   original_content_base64: string | null;
+  /** Set for lockfiles, build output and the like, which start collapsed */
+  generated: GeneratedKind | null;
 }
 
 export interface GitRepoInfo {
@@ -6,7 +6,7 @@
 // because we don't want to drag those imports into the canonical class.
 
 import { writable, derived, get } from 'svelte/store';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '@/lib/services/invoke';
 import { Store } from '@tauri-apps/plugin-store';
 import { windowService } from '@/lib/services/windowService';
 import { eventService } from '@/lib/services/eventService';
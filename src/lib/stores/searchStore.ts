@@ -1,5 +1,5 @@
 import { writable, derived } from 'svelte/store';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '@/lib/services/invoke';
 
 export interface FileSearchResult {
   path: string;
@@ -29,6 +29,7 @@ vi.mock('@/lib/services/terminalService', () => ({
 vi.mock('@/lib/services/eventService', () => ({
   eventService: {
     listen: vi.fn().mockResolvedValue(() => {}),
+    listenBackend: vi.fn().mockResolvedValue(() => {}),
     emit: vi.fn().mockResolvedValue(undefined),
   },
 }));
@@ -51,7 +52,7 @@ vi.mock('@/lib/services/windowService', () => ({
 // Mock watcherService
 vi.mock('@/lib/services/watcherService', () => ({
   watcherService: {
-    startWatching: vi.fn().mockResolvedValue(undefined),
+    startWatching: vi.fn().mockResolvedValue({ id: 1, path: '' }),
     stopWatching: vi.fn().mockResolvedValue(undefined),
   },
 }));